@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Build the statically linked executable that a `FuzzHost` runs. Like the `nop` test, this
+/// requires a static libc.
+fn main() {
+    let src = PathBuf::from("../test/static-bin/nop.rs");
+    let out = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("fuzz_plugin");
+
+    let status = Command::new(std::env::var("RUSTC").unwrap())
+        .args(["-C", "target-feature=+crt-static", "-o"])
+        .arg(&out)
+        .arg(&src)
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to build {}", src.display());
+
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rustc-env=SHADOW_FUZZ_PLUGIN={}", out.display());
+}
@@ -0,0 +1,238 @@
+//! Makes arbitrary sequences of descriptor syscalls (creating pipes and eventfds, duplicating and
+//! closing descriptors, changing their status, and reading and writing them) through Shadow's
+//! syscall dispatch. The buffers and iovec arrays of reads and writes are in the process's memory.
+//! Checks that the syscalls allocate the same fds as Linux would, that `dup`, `fcntl`, and `close`
+//! fail with `EBADF` exactly when the fd isn't open, and that reads and writes never return more
+//! bytes than they were given.
+
+#![no_main]
+
+use std::collections::BTreeSet;
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use shadow_fuzz::{FuzzHost, Region, SyscallResult, SCRATCH_LEN};
+
+/// The scratch memory used for the fds returned by `pipe2`.
+const PIPE_FDS_OFFSET: usize = SCRATCH_LEN / 2 - 8;
+
+/// The scratch memory used for the iovec arrays of reads and writes. The memory before
+/// `PIPE_FDS_OFFSET` is used for their buffers.
+const IOVECS_OFFSET: usize = SCRATCH_LEN / 2;
+
+const EBADF: i64 = -(libc::EBADF as i64);
+
+#[derive(Arbitrary, Debug)]
+enum Syscall {
+    Pipe2 {
+        nonblock: bool,
+        direct: bool,
+        cloexec: bool,
+    },
+    Eventfd2 {
+        init_value: u32,
+        semaphore: bool,
+        nonblock: bool,
+    },
+    Dup {
+        fd: u16,
+    },
+    Dup3 {
+        old_fd: u16,
+        new_fd: u16,
+        cloexec: bool,
+    },
+    FcntlDupfd {
+        fd: u16,
+        min_fd: u16,
+    },
+    FcntlSetfl {
+        fd: u16,
+        nonblock: bool,
+    },
+    Close {
+        fd: u16,
+    },
+    Readv {
+        fd: u16,
+        iovs: Vec<Region>,
+    },
+    Writev {
+        fd: u16,
+        iovs: Vec<Region>,
+        data: Vec<u8>,
+    },
+}
+
+/// A thread's descriptors, which are checked against the fds that we expect to be open.
+struct Descriptors {
+    host: FuzzHost,
+    /// The fds that should be open.
+    open: BTreeSet<u32>,
+}
+
+impl Descriptors {
+    fn new() -> Self {
+        Self {
+            host: FuzzHost::new(),
+            // stdin, stdout, and stderr
+            open: BTreeSet::from([0, 1, 2]),
+        }
+    }
+
+    /// The lowest fd that isn't open and is at least `min_fd`.
+    fn lowest_available(&self, min_fd: u32) -> u32 {
+        (min_fd..).find(|fd| !self.open.contains(fd)).unwrap()
+    }
+
+    /// Check that a syscall that allocates an fd returned the lowest available fd that's at least
+    /// `min_fd`.
+    fn allocated(&mut self, fd: u32, min_fd: u32) {
+        assert_eq!(fd, self.lowest_available(min_fd));
+        self.open.insert(fd);
+    }
+
+    /// Check that a syscall on `fd` failed with `EBADF` exactly when `fd` isn't open.
+    fn check_ebadf(&self, fd: u16, rv: i64) {
+        assert_eq!(rv == EBADF, !self.open.contains(&fd.into()));
+    }
+
+    /// Write the iovec array for `regions` to the scratch memory, and return its address.
+    fn iovecs(&mut self, regions: &[Region]) -> u64 {
+        let bytes: Vec<u8> = regions
+            .iter()
+            .flat_map(|region| {
+                let base = usize::from(self.host.region_ptr(region));
+                [base.to_ne_bytes(), region.len().to_ne_bytes()]
+            })
+            .flatten()
+            .collect();
+        self.host.write(IOVECS_OFFSET, &bytes);
+        usize::from(self.host.ptr::<u8>(IOVECS_OFFSET)) as u64
+    }
+
+    /// Make the syscall, and return its result if it completed.
+    fn syscall(&mut self, number: libc::c_long, args: &[u64]) -> Option<i64> {
+        let mut all_args = [0; 6];
+        all_args[..args.len()].copy_from_slice(args);
+        match self.host.syscall(number, all_args) {
+            SyscallResult::Done(rv) => Some(rv),
+            SyscallResult::Blocked => None,
+            SyscallResult::Native => panic!("Syscall {number} wasn't emulated"),
+        }
+    }
+
+    /// Make the syscall and check its result. Returns `None` if the syscall blocked, after which
+    /// the thread can't make any more syscalls.
+    fn run(&mut self, syscall: Syscall) -> Option<()> {
+        match syscall {
+            Syscall::Pipe2 {
+                nonblock,
+                direct,
+                cloexec,
+            } => {
+                let mut flags = 0;
+                flags |= if nonblock { libc::O_NONBLOCK } else { 0 };
+                flags |= if direct { libc::O_DIRECT } else { 0 };
+                flags |= if cloexec { libc::O_CLOEXEC } else { 0 };
+
+                let fds_ptr = usize::from(self.host.ptr::<u8>(PIPE_FDS_OFFSET)) as u64;
+                if self.syscall(libc::SYS_pipe2, &[fds_ptr, flags as u64])? == 0 {
+                    let fds: [libc::c_int; 2] = self.host.read(PIPE_FDS_OFFSET);
+                    self.allocated(fds[0].try_into().unwrap(), 0);
+                    self.allocated(fds[1].try_into().unwrap(), 0);
+                }
+            }
+            Syscall::Eventfd2 {
+                init_value,
+                semaphore,
+                nonblock,
+            } => {
+                let mut flags = 0;
+                flags |= if semaphore { libc::EFD_SEMAPHORE } else { 0 };
+                flags |= if nonblock { libc::EFD_NONBLOCK } else { 0 };
+
+                let rv = self.syscall(libc::SYS_eventfd2, &[init_value.into(), flags as u64])?;
+                if rv >= 0 {
+                    self.allocated(rv.try_into().unwrap(), 0);
+                }
+            }
+            Syscall::Dup { fd } => {
+                let rv = self.syscall(libc::SYS_dup, &[fd.into()])?;
+                self.check_ebadf(fd, rv);
+                if rv >= 0 {
+                    self.allocated(rv.try_into().unwrap(), 0);
+                }
+            }
+            Syscall::Dup3 {
+                old_fd,
+                new_fd,
+                cloexec,
+            } => {
+                let flags = if cloexec { libc::O_CLOEXEC } else { 0 };
+                let rv = self.syscall(
+                    libc::SYS_dup3,
+                    &[old_fd.into(), new_fd.into(), flags as u64],
+                )?;
+                if rv >= 0 {
+                    assert_ne!(old_fd, new_fd);
+                    assert!(self.open.contains(&old_fd.into()));
+                    assert_eq!(rv, i64::from(new_fd));
+                    self.open.insert(new_fd.into());
+                }
+            }
+            Syscall::FcntlDupfd { fd, min_fd } => {
+                let rv = self.syscall(
+                    libc::SYS_fcntl,
+                    &[fd.into(), libc::F_DUPFD as u64, min_fd.into()],
+                )?;
+                self.check_ebadf(fd, rv);
+                if rv >= 0 {
+                    self.allocated(rv.try_into().unwrap(), min_fd.into());
+                }
+            }
+            Syscall::FcntlSetfl { fd, nonblock } => {
+                let flags = if nonblock { libc::O_NONBLOCK } else { 0 };
+                let rv = self.syscall(
+                    libc::SYS_fcntl,
+                    &[fd.into(), libc::F_SETFL as u64, flags as u64],
+                )?;
+                self.check_ebadf(fd, rv);
+            }
+            Syscall::Close { fd } => {
+                let rv = self.syscall(libc::SYS_close, &[fd.into()])?;
+                self.check_ebadf(fd, rv);
+                self.open.remove(&fd.into());
+            }
+            Syscall::Readv { fd, iovs } => {
+                let len: usize = iovs.iter().map(Region::len).sum();
+                let iovs_ptr = self.iovecs(&iovs);
+                let rv =
+                    self.syscall(libc::SYS_readv, &[fd.into(), iovs_ptr, iovs.len() as u64])?;
+                assert!(rv <= len as i64);
+            }
+            Syscall::Writev { fd, iovs, data } => {
+                let len: usize = iovs.iter().map(Region::len).sum();
+                self.host
+                    .write(0, &data[..std::cmp::min(data.len(), PIPE_FDS_OFFSET)]);
+                let iovs_ptr = self.iovecs(&iovs);
+                let rv =
+                    self.syscall(libc::SYS_writev, &[fd.into(), iovs_ptr, iovs.len() as u64])?;
+                assert!(rv <= len as i64);
+            }
+        }
+
+        Some(())
+    }
+}
+
+fuzz_target!(|syscalls: Vec<Syscall>| {
+    let mut descs = Descriptors::new();
+
+    for x in syscalls {
+        // errors are expected, but panics aren't
+        if descs.run(x).is_none() {
+            break;
+        }
+    }
+});
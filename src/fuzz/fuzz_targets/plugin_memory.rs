@@ -0,0 +1,104 @@
+//! Reads and writes syscall arguments (socket addresses, iovec arrays, message headers, and string
+//! arrays) in arbitrary plugin memory, using the same functions as the syscall handlers. Plugins
+//! control the contents of their memory and the pointers and lengths that they pass to syscalls,
+//! so none of these should panic.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use shadow_fuzz::{Plugin, Region};
+use shadow_rs::host::syscall::io;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// The initial contents of the plugin's memory.
+    memory: Vec<u8>,
+    calls: Vec<Call>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Call {
+    /// Read a socket address, like `bind()` and `connect()`, and write it back, like `accept()`
+    /// and `getsockname()`.
+    Sockaddr {
+        addr: Region,
+        /// Where to write the address.
+        out: Region,
+        /// Where to read and write the length of the written address.
+        out_len_offset: u16,
+    },
+    /// Read an iovec array, like `readv()` and `writev()`.
+    Iovecs { iovs: Region, count: u16 },
+    /// Read a message header and its address, like `sendmsg()` and `recvmsg()`.
+    Msghdr { msg: Region },
+    /// Read a string array, like `execve()`.
+    CstringVec { strs: Region },
+}
+
+fn call(plugin: &mut Plugin, call: Call) {
+    match call {
+        Call::Sockaddr {
+            addr,
+            out,
+            out_len_offset,
+        } => {
+            let addr_len = addr.len().try_into().unwrap();
+            let Ok(Some(addr)) =
+                io::read_sockaddr(plugin.mem(), plugin.region_ptr(&addr), addr_len)
+            else {
+                return;
+            };
+
+            // use the accessors for the address' family
+            let _ = addr.family();
+            let _ = addr.as_inet();
+            let _ = addr.as_inet6();
+            if let Some(unix) = addr.as_unix() {
+                let _ = unix.as_path();
+                let _ = unix.as_abstract();
+                let _ = unix.is_unnamed();
+            }
+            let _ = format!("{addr} {addr:?}");
+
+            let out_len: libc::socklen_t = out.len().try_into().unwrap();
+            plugin.write(out_len_offset.into(), &out_len.to_ne_bytes());
+            let out_len_ptr = plugin.ptr(out_len_offset.into());
+            let out_ptr = plugin.region_ptr(&out);
+            let _ = io::write_sockaddr_and_len(plugin.mem_mut(), Some(&addr), out_ptr, out_len_ptr);
+        }
+        Call::Iovecs { iovs, count } => {
+            if let Ok(iovs) = io::read_iovecs(
+                plugin.mem(),
+                plugin.region_ptr(&iovs).cast::<libc::iovec>(),
+                count.into(),
+            ) {
+                assert_eq!(iovs.len(), usize::from(count));
+            }
+        }
+        Call::Msghdr { msg } => {
+            let Ok(msg) =
+                io::read_msghdr(plugin.mem(), plugin.region_ptr(&msg).cast::<libc::msghdr>())
+            else {
+                return;
+            };
+            let _ = io::read_sockaddr(plugin.mem(), msg.name, msg.name_len);
+        }
+        Call::CstringVec { strs } => {
+            let _ = io::read_cstring_vec(
+                plugin.mem(),
+                plugin.region_ptr(&strs).cast::<ForeignPtr<i8>>(),
+            );
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut plugin = Plugin::new();
+    plugin.write(0, &input.memory);
+
+    for x in input.calls {
+        call(&mut plugin, x);
+    }
+});
@@ -0,0 +1,142 @@
+//! Makes arbitrary sequences of syscalls through Shadow's syscall dispatch, with arguments that are
+//! arbitrary integers, small integers (which are likely to be valid fds, flags, and lengths), or
+//! pointers into the process's memory. Checks that every syscall that completes returns either a
+//! non-negative value or a negated errno.
+//!
+//! Only syscalls that are fully emulated within the host are made. Syscalls that Shadow makes
+//! natively on the process's behalf (such as memory mappings and `prctl`), that use paths in the
+//! real filesystem, or that end or replace the process or create new ones (such as `exit`,
+//! `execve`, `clone`, and `kill`) aren't.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use shadow_fuzz::{FuzzHost, SyscallResult};
+
+const SYSCALLS: &[libc::c_long] = &[
+    // descriptors
+    libc::SYS_pipe,
+    libc::SYS_pipe2,
+    libc::SYS_eventfd,
+    libc::SYS_eventfd2,
+    libc::SYS_dup,
+    libc::SYS_dup2,
+    libc::SYS_dup3,
+    libc::SYS_close,
+    libc::SYS_fcntl,
+    libc::SYS_ioctl,
+    libc::SYS_lseek,
+    libc::SYS_fstat,
+    // i/o
+    libc::SYS_read,
+    libc::SYS_write,
+    libc::SYS_readv,
+    libc::SYS_writev,
+    libc::SYS_pread64,
+    libc::SYS_pwrite64,
+    // sockets
+    libc::SYS_socket,
+    libc::SYS_socketpair,
+    libc::SYS_bind,
+    libc::SYS_listen,
+    libc::SYS_connect,
+    libc::SYS_accept,
+    libc::SYS_accept4,
+    libc::SYS_getsockname,
+    libc::SYS_getpeername,
+    libc::SYS_getsockopt,
+    libc::SYS_setsockopt,
+    libc::SYS_shutdown,
+    libc::SYS_sendto,
+    libc::SYS_recvfrom,
+    libc::SYS_sendmsg,
+    libc::SYS_recvmsg,
+    // polling
+    libc::SYS_epoll_create,
+    libc::SYS_epoll_create1,
+    libc::SYS_epoll_ctl,
+    libc::SYS_epoll_wait,
+    libc::SYS_epoll_pwait,
+    libc::SYS_poll,
+    libc::SYS_ppoll,
+    libc::SYS_select,
+    // time
+    libc::SYS_timerfd_create,
+    libc::SYS_timerfd_settime,
+    libc::SYS_timerfd_gettime,
+    libc::SYS_clock_gettime,
+    libc::SYS_gettimeofday,
+    libc::SYS_nanosleep,
+    libc::SYS_getitimer,
+    libc::SYS_setitimer,
+    libc::SYS_alarm,
+    libc::SYS_sched_yield,
+    // signals
+    libc::SYS_rt_sigaction,
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_sigaltstack,
+    // process and system information
+    libc::SYS_uname,
+    libc::SYS_getrandom,
+    libc::SYS_getpid,
+    libc::SYS_getppid,
+    libc::SYS_gettid,
+    libc::SYS_getuid,
+    libc::SYS_getgid,
+    libc::SYS_geteuid,
+    libc::SYS_getegid,
+    // synchronization
+    libc::SYS_futex,
+];
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// The initial contents of the process's scratch memory.
+    memory: Vec<u8>,
+    syscalls: Vec<Syscall>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Syscall {
+    /// An index into `SYSCALLS`, which wraps around.
+    index: u8,
+    args: [Arg; 6],
+}
+
+#[derive(Arbitrary, Debug, Copy, Clone)]
+enum Arg {
+    Int(u64),
+    Small(i8),
+    /// A pointer to this offset in the scratch memory.
+    Scratch(u16),
+}
+
+impl Arg {
+    fn value(self, host: &FuzzHost) -> u64 {
+        match self {
+            Arg::Int(x) => x,
+            Arg::Small(x) => i64::from(x) as u64,
+            Arg::Scratch(offset) => usize::from(host.ptr::<u8>(offset.into())) as u64,
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut host = FuzzHost::new();
+    host.write(0, &input.memory);
+
+    for syscall in input.syscalls {
+        let number = SYSCALLS[usize::from(syscall.index) % SYSCALLS.len()];
+        let args = syscall.args.map(|x| x.value(&host));
+
+        match host.syscall(number, args) {
+            SyscallResult::Done(rv) => {
+                assert!(rv >= -4095, "Syscall {number} returned {rv}");
+            }
+            // the thread can't make any more syscalls
+            SyscallResult::Blocked => break,
+            SyscallResult::Native => {}
+        }
+    }
+});
@@ -0,0 +1,524 @@
+//! Helpers for Shadow's fuzz targets.
+//!
+//! [`FuzzHost`] makes syscalls the same way that a simulation does: each syscall is passed to the
+//! syscall handler's C entry point, which dispatches it to the C or Rust handler for that syscall.
+//! The host is built from a small configuration by the same code that builds the manager's hosts,
+//! and its process is a statically linked executable that stays stopped at its first syscall, so
+//! the handlers read and write the memory of a real process that never runs.
+//!
+//! [`Plugin`] is a lighter-weight stand-in for a plugin process, for targets that only call the
+//! functions that read and write syscall arguments in plugin memory. Plugin memory is accessed
+//! through a [`MemoryManager`] just as it is during a simulation.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CString, OsString};
+use std::fmt::Debug;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use clap::Parser;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use nix::unistd::{ForkResult, Pid};
+use shadow_pod::Pod;
+use shadow_rs::core::hooks::Hooks;
+use shadow_rs::core::manager;
+use shadow_rs::core::scheduler::runahead::Runahead;
+use shadow_rs::core::sim_config::{HostInfo, SimConfig};
+use shadow_rs::core::support::configuration::{CliOptions, ConfigFileOptions, ConfigOptions};
+use shadow_rs::core::worker::{Worker, WorkerShared, WorkerThreadID, WORKER_SHARED};
+use shadow_rs::cshadow as c;
+use shadow_rs::host::host::Host;
+use shadow_rs::host::memory_manager::MemoryManager;
+use shadow_rs::host::process::{Process, ProcessId};
+use shadow_rs::host::syscall_condition::SysCallCondition;
+use shadow_rs::host::syscall_types::{ForeignArrayPtr, SyscallReturn};
+use shadow_rs::host::thread::ThreadId;
+use shadow_rs::utility;
+use shadow_rs::utility::childpid_watcher::ChildPidWatcher;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
+use shadow_shim_helper_rs::shim_shmem::ManagerShmem;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+use shadow_shim_helper_rs::syscall_types::{ForeignPtr, SysCallArgs, SysCallReg};
+use shadow_shim_helper_rs::util::SyncSendPointer;
+use shadow_shim_helper_rs::HostId;
+use shadow_shmem::allocator::ShMemBlock;
+
+/// The configuration of the simulation that a [`FuzzHost`] belongs to. `{plugin}` is replaced with
+/// the path of the host's executable.
+const CONFIG: &str = r#"
+general:
+  stop_time: 3600
+  # a completed syscall would otherwise block so that other hosts can run
+  model_unblocked_syscall_latency: false
+network:
+  graph:
+    type: 1_gbit_switch
+hosts:
+  fuzzhost:
+    network_node_id: 0
+    processes:
+    - path: {plugin}
+"#;
+
+/// The CPU frequency of the host. The manager uses the machine's frequency, but inputs should
+/// behave the same on every machine.
+const CPU_FREQUENCY_HZ: u64 = 2_500_000_000;
+
+/// The size of the memory in a [`FuzzHost`]'s process that the fuzz targets may use.
+pub const SCRATCH_LEN: usize = 64 * 1024;
+
+/// The state of the simulation that every [`FuzzHost`] belongs to, which is created the first time
+/// that it's needed. The fuzzer runs inputs on a single thread, which is the simulation's only
+/// worker thread.
+struct Sim {
+    config: ConfigOptions,
+    host_info: HostInfo,
+    hosts_path: PathBuf,
+    shmem: ShMemBlock<'static, ManagerShmem>,
+    end_time: EmulatedTime,
+}
+
+impl Sim {
+    fn get() -> &'static Self {
+        static SIM: OnceLock<Sim> = OnceLock::new();
+        SIM.get_or_init(Self::new)
+    }
+
+    fn new() -> Self {
+        // the handlers' log messages would slow down the fuzzer, and their errors are reported
+        // through the syscalls' return values
+        unsafe {
+            logger::logger_setLevel(
+                logger::logger_getDefault(),
+                logger::_LogLevel_LOGLEVEL_ERROR,
+            )
+        };
+
+        let config_file = CONFIG.replace("{plugin}", env!("SHADOW_FUZZ_PLUGIN"));
+        let config_file: ConfigFileOptions = serde_yaml::from_str(&config_file).unwrap();
+        let cli = CliOptions::try_parse_from(["shadow", "-"]).unwrap();
+        let config = ConfigOptions::new(config_file, cli);
+        unsafe { c::runConfigHandlers(&config as *const ConfigOptions) };
+
+        let mut sim_config = SimConfig::new(&config, &HashSet::new()).unwrap();
+        let host_info = sim_config.hosts.pop().unwrap();
+
+        let end_time: Duration = config.general.stop_time.unwrap().into();
+        let end_time = EmulatedTime::SIMULATION_START + SimulationTime::try_from(end_time).unwrap();
+        let smallest_latency =
+            SimulationTime::from_nanos(sim_config.routing_info.get_smallest_latency_ns().unwrap());
+
+        // the host's events are never run, so no packets are sent between hosts and there are no
+        // event queues to push them to
+        WORKER_SHARED.borrow_mut().replace(WorkerShared {
+            ip_assignment: sim_config.ip_assignment,
+            routing_info: sim_config.routing_info,
+            host_bandwidths: sim_config.host_bandwidths,
+            broadcast_domains: sim_config.broadcast_domains,
+            // safe since the DNS type has an internal mutex
+            dns: unsafe { SyncSendPointer::new(c::dns_new()) },
+            num_plugin_errors: AtomicU32::new(0),
+            status_logger_state: None,
+            runahead: Runahead::new(
+                config.experimental.use_dynamic_runahead.unwrap(),
+                smallest_latency,
+                None,
+            ),
+            child_pid_watcher: ChildPidWatcher::new(),
+            event_queues: HashMap::new(),
+            bootstrap_end_time: EmulatedTime::SIMULATION_START,
+            sim_end_time: end_time,
+            flow_volumes: None,
+            observers: None,
+            censors: None,
+            event_trace: None,
+            group_stats: None,
+            probe_results: None,
+            breakpoints: None,
+            invariants: None,
+            hooks: Hooks::registered(),
+        });
+        Worker::new_for_this_thread(WorkerThreadID(0));
+        Worker::set_round_end_time(end_time);
+
+        let hosts_path = std::env::temp_dir().join(format!("shadow-fuzz-{}", std::process::id()));
+
+        let shmem = shadow_shmem::allocator::shmalloc(ManagerShmem {
+            log_start_time_micros: unsafe { c::logger_get_global_start_time_micros() },
+        });
+
+        Self {
+            config,
+            host_info,
+            hosts_path,
+            shmem,
+            end_time,
+        }
+    }
+}
+
+/// The result of a syscall made by a [`FuzzHost`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SyscallResult {
+    /// The syscall completed with this return value.
+    Done(i64),
+    /// The syscall blocked. The thread can't make another syscall until the blocked syscall
+    /// completes, which it never does since the host's events aren't run.
+    Blocked,
+    /// The syscall would be made natively by the process.
+    Native,
+}
+
+/// A new host with a single process, whose main thread makes the syscalls that it's given. Only
+/// one may exist at a time, since the host is the worker's active host.
+pub struct FuzzHost {
+    pid: ProcessId,
+    tid: ThreadId,
+    /// The start of the process's memory that the fuzz targets may use.
+    scratch: usize,
+    blocked: bool,
+}
+
+impl FuzzHost {
+    pub fn new() -> Self {
+        let sim = Sim::get();
+        assert!(
+            Worker::with_active_host(|_| ()).is_none(),
+            "Only one host may exist at a time"
+        );
+
+        let params = manager::host_parameters(
+            &sim.config,
+            HostId::from(0),
+            &sim.host_info,
+            CPU_FREQUENCY_HZ,
+            CPU_FREQUENCY_HZ,
+            sim.end_time,
+        );
+        let host = unsafe {
+            Host::new(
+                params,
+                &sim.hosts_path,
+                CPU_FREQUENCY_HZ,
+                Worker::with_dns(|dns| dns as *const c::DNS).cast_mut(),
+                &sim.shmem,
+                Arc::new(Vec::new()),
+            )
+        }
+        .unwrap();
+        Worker::set_active_host(Box::new(host));
+
+        Worker::with_active_host(|host| {
+            Worker::set_current_time(EmulatedTime::SIMULATION_START);
+            // the shared memory stays locked until the host is shut down, like it is while a
+            // host's events are run
+            host.lock_shmem();
+            host.boot().unwrap();
+
+            let proc = &sim.host_info.processes[0];
+            let argv: Vec<CString> = proc
+                .args
+                .iter()
+                .map(|x| CString::new(x.as_bytes()).unwrap())
+                .collect();
+            let envv: Vec<CString> = proc
+                .env
+                .clone()
+                .into_iter()
+                .map(|(x, y)| {
+                    let mut x: OsString = String::from(x).into();
+                    x.push("=");
+                    x.push(y);
+                    CString::new(x.as_bytes()).unwrap()
+                })
+                .collect();
+
+            let process = Process::spawn(
+                host,
+                CString::new(proc.plugin.file_name().unwrap().as_bytes()).unwrap(),
+                &utility::pathbuf_to_nul_term_cstring(proc.plugin.clone()),
+                None,
+                argv,
+                envv,
+                false,
+                None,
+                proc.expected_final_state,
+                proc.quota.clone(),
+                proc.credentials.clone(),
+                None,
+                &[],
+                0,
+            )
+            .unwrap();
+
+            let (pid, tid, native_pid) = {
+                let process = process.borrow(host.root());
+                (
+                    process.id(),
+                    process.thread_group_leader_id(),
+                    process.native_pid(),
+                )
+            };
+
+            // the process's resume is never run, since the fuzz targets make its syscalls
+            host.add_and_schedule_forked_process(host, process);
+
+            Self {
+                pid,
+                tid,
+                scratch: stack_start(native_pid),
+                blocked: false,
+            }
+        })
+        .unwrap()
+    }
+
+    /// A pointer to `offset` bytes into the scratch memory. Offsets past the end of the memory
+    /// wrap around.
+    pub fn ptr<T>(&self, offset: usize) -> ForeignPtr<T> {
+        ForeignPtr::from(self.scratch + offset % SCRATCH_LEN).cast::<T>()
+    }
+
+    /// The pointer to a region of memory. [`Region::Plugin`] regions are in the scratch memory.
+    pub fn region_ptr(&self, region: &Region) -> ForeignPtr<u8> {
+        match *region {
+            Region::Plugin { offset, .. } => self.ptr(offset.into()),
+            Region::Wild { addr, .. } => ForeignPtr::from(addr).cast::<u8>(),
+        }
+    }
+
+    /// Write `bytes` to the scratch memory at `offset`. The bytes are truncated at the end of the
+    /// memory.
+    pub fn write(&mut self, offset: usize, bytes: &[u8]) {
+        let offset = offset % SCRATCH_LEN;
+        let len = std::cmp::min(bytes.len(), SCRATCH_LEN - offset);
+        let ptr = ForeignArrayPtr::new(self.ptr(offset), len);
+        self.with_process(|process| {
+            process
+                .memory_borrow_mut()
+                .copy_to_ptr(ptr, &bytes[..len])
+                .unwrap()
+        });
+    }
+
+    /// Read a `T` from the scratch memory at `offset`.
+    pub fn read<T: Pod + Debug>(&self, offset: usize) -> T {
+        let ptr = self.ptr(offset);
+        self.with_process(|process| process.memory_borrow().read(ptr).unwrap())
+    }
+
+    /// Make a syscall from the process's main thread, through the same entry point as the
+    /// syscalls of a simulated thread.
+    pub fn syscall(&mut self, number: libc::c_long, args: [u64; 6]) -> SyscallResult {
+        if self.blocked {
+            return SyscallResult::Blocked;
+        }
+
+        let args = SysCallArgs {
+            number,
+            args: args.map(SysCallReg::from),
+        };
+
+        let tid = self.tid;
+        let rv = self.with_process(|process| {
+            Worker::with_active_host(|host| {
+                let threadrc = process.thread_borrow(tid).unwrap().clone(host.root());
+                Worker::set_active_thread(&threadrc);
+
+                let rv = {
+                    let thread = threadrc.borrow(host.root());
+                    let scr =
+                        unsafe { c::syscallhandler_make_syscall(thread.csyscallhandler(), &args) };
+                    thread.cleanup_syscall_condition();
+                    process.free_unsafe_borrows_flush().unwrap();
+
+                    match scr {
+                        SyscallReturn::Done(d) => SyscallResult::Done(d.retval.into()),
+                        SyscallReturn::Block(b) => {
+                            drop(unsafe { SysCallCondition::consume_from_c(b.cond) });
+                            SyscallResult::Blocked
+                        }
+                        SyscallReturn::Native => SyscallResult::Native,
+                    }
+                };
+
+                Worker::clear_active_thread();
+                threadrc.explicit_drop_recursive(host.root(), host);
+                rv
+            })
+            .unwrap()
+        });
+
+        self.blocked = rv == SyscallResult::Blocked;
+        rv
+    }
+
+    /// Run `f` with the host's process, which is the worker's active process while `f` runs.
+    fn with_process<R>(&self, f: impl FnOnce(&Process) -> R) -> R {
+        Worker::with_active_host(|host| {
+            let processrc = host.process_borrow(self.pid).unwrap().clone(host.root());
+            Worker::set_active_process(&processrc);
+            let rv = f(&processrc.borrow(host.root()));
+            Worker::clear_active_process();
+            processrc.explicit_drop(host.root());
+            rv
+        })
+        .unwrap()
+    }
+}
+
+impl Default for FuzzHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FuzzHost {
+    fn drop(&mut self) {
+        Worker::with_active_host(|host| {
+            host.unlock_shmem();
+            host.free_all_applications();
+            host.shutdown();
+        })
+        .unwrap();
+        Worker::clear_current_time();
+        drop(Worker::take_active_host());
+    }
+}
+
+/// The start of the stack mapping of the process with native pid `pid`. The stack grows down from
+/// the end of the mapping, but the process never runs again, so the fuzz targets may use the
+/// whole mapping.
+fn stack_start(pid: Pid) -> usize {
+    let maps = std::fs::read_to_string(format!("/proc/{pid}/maps")).unwrap();
+    let range = maps
+        .lines()
+        .find(|line| line.ends_with("[stack]"))
+        .and_then(|line| line.split(' ').next())
+        .unwrap();
+    let (start, end) = range.split_once('-').unwrap();
+    let start = usize::from_str_radix(start, 16).unwrap();
+    let end = usize::from_str_radix(end, 16).unwrap();
+    assert!(
+        end - start >= SCRATCH_LEN,
+        "The stack mapping {range} is too small"
+    );
+    start
+}
+
+/// The size of the plugin's memory that the fuzz targets may use.
+pub const PLUGIN_MEMORY_LEN: usize = 64 * 1024;
+
+/// The memory that the fuzz targets use in the plugin. The fuzzer itself never accesses this; it
+/// only uses its address, which is the same in the plugin since the plugin is forked from the
+/// fuzzer.
+static mut PLUGIN_MEMORY: [u8; PLUGIN_MEMORY_LEN] = [0; PLUGIN_MEMORY_LEN];
+
+/// Whether a [`Plugin`] exists.
+static PLUGIN_IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// The pid of the plugin process, which is forked the first time that it's needed and sleeps
+/// until the fuzzer exits.
+fn plugin_pid() -> Pid {
+    static PID: OnceLock<Pid> = OnceLock::new();
+    *PID.get_or_init(|| match unsafe { nix::unistd::fork() }.unwrap() {
+        ForkResult::Parent { child } => child,
+        ForkResult::Child => {
+            // only async-signal-safe functions may be called after forking
+            unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) };
+            loop {
+                unsafe { libc::pause() };
+            }
+        }
+    })
+}
+
+/// A stand-in for a plugin process whose memory the fuzz targets pass to Shadow's syscall code.
+/// Only one may exist at a time.
+pub struct Plugin {
+    mem: MemoryManager,
+}
+
+impl Plugin {
+    pub fn new() -> Self {
+        assert!(
+            !PLUGIN_IN_USE.swap(true, Ordering::SeqCst),
+            "Only one plugin may exist at a time"
+        );
+
+        // SAFETY: the plugin's memory is only accessed through this memory manager, since the
+        // plugin doesn't run any code that touches it and only one `Plugin` exists at a time
+        let mem = unsafe { MemoryManager::new(plugin_pid()) };
+        Self { mem }
+    }
+
+    pub fn mem(&self) -> &MemoryManager {
+        &self.mem
+    }
+
+    pub fn mem_mut(&mut self) -> &mut MemoryManager {
+        &mut self.mem
+    }
+
+    /// A pointer to `offset` bytes into the plugin's memory. Offsets past the end of the memory
+    /// wrap around.
+    pub fn ptr<T>(&self, offset: usize) -> ForeignPtr<T> {
+        let base = unsafe { std::ptr::addr_of!(PLUGIN_MEMORY) } as usize;
+        ForeignPtr::from(base + offset % PLUGIN_MEMORY_LEN).cast::<T>()
+    }
+
+    /// The pointer to a region of memory.
+    pub fn region_ptr(&self, region: &Region) -> ForeignPtr<u8> {
+        match *region {
+            Region::Plugin { offset, .. } => self.ptr(offset.into()),
+            Region::Wild { addr, .. } => ForeignPtr::from(addr).cast::<u8>(),
+        }
+    }
+
+    /// Write `bytes` to the plugin's memory at `offset`. The bytes are truncated at the end of the
+    /// memory.
+    pub fn write(&mut self, offset: usize, bytes: &[u8]) {
+        let offset = offset % PLUGIN_MEMORY_LEN;
+        let len = std::cmp::min(bytes.len(), PLUGIN_MEMORY_LEN - offset);
+        let ptr = ForeignArrayPtr::new(self.ptr(offset), len);
+        self.mem.copy_to_ptr(ptr, &bytes[..len]).unwrap();
+    }
+}
+
+impl Default for Plugin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        PLUGIN_IN_USE.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A region of memory that a syscall argument points to.
+#[derive(Arbitrary, Debug, Copy, Clone)]
+pub enum Region {
+    /// A region of a [`FuzzHost`]'s scratch memory or a [`Plugin`]'s memory. The region may extend
+    /// past the end of the memory.
+    Plugin { offset: u16, len: u16 },
+    /// A region at an arbitrary address, which is usually not mapped in the plugin.
+    Wild { addr: usize, len: u16 },
+}
+
+impl Region {
+    pub fn len(&self) -> usize {
+        match *self {
+            Region::Plugin { len, .. } | Region::Wild { len, .. } => len.into(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
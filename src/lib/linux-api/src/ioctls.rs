@@ -72,6 +72,12 @@ pub enum IoctlRequest {
     TIOCSERSETMULTI = bindings::LINUX_TIOCSERSETMULTI,
     TIOCMIWAIT = bindings::LINUX_TIOCMIWAIT,
     TIOCGICOUNT = bindings::LINUX_TIOCGICOUNT,
+    // The pseudoterminal requests are defined with the `_IOR`, `_IOW`, and `_IO`
+    // macros, which bindgen can't evaluate.
+    TIOCGPTN = 0x80045430,
+    TIOCSPTLCK = 0x40045431,
+    TIOCGPTLCK = 0x80045439,
+    TIOCGPTPEER = 0x5441,
     FIOQSIZE = bindings::LINUX_FIOQSIZE,
     FIOSETOWN = bindings::LINUX_FIOSETOWN,
     SIOCSPGRP = bindings::LINUX_SIOCSPGRP,
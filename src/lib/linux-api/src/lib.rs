@@ -60,12 +60,15 @@ pub mod ioctls;
 pub mod ldt;
 pub mod limits;
 pub mod mman;
+pub mod netdevice;
+pub mod netlink;
 pub mod posix_types;
 pub mod resource;
 pub mod rseq;
 pub mod sched;
 pub mod signal;
 pub mod sysinfo;
+pub mod termios;
 pub mod time;
 pub mod ucontext;
 pub mod wait;
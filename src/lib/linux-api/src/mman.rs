@@ -68,3 +68,14 @@ bitflags::bitflags! {
         const MREMAP_DONTUNMAP = const_conversions::u64_from_u32(bindings::LINUX_MREMAP_DONTUNMAP);
     }
 }
+
+bitflags::bitflags! {
+    /// Flags used with `msync`. i32 to match the x86-64 `msync` syscall parameter:
+    /// <https://github.com/torvalds/linux/tree/v6.3/mm/msync.c#L32>
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+    pub struct MsyncFlags: i32 {
+        const MS_ASYNC = const_conversions::i32_from_u32(bindings::LINUX_MS_ASYNC);
+        const MS_INVALIDATE = const_conversions::i32_from_u32(bindings::LINUX_MS_INVALIDATE);
+        const MS_SYNC = const_conversions::i32_from_u32(bindings::LINUX_MS_SYNC);
+    }
+}
@@ -0,0 +1,146 @@
+//! Types used with the network device ioctls, such as `SIOCGIFCONF`. See netdevice(7).
+
+/// The length of an interface name, including its nul terminator.
+pub const IFNAMSIZ: usize = 16;
+
+/// The hardware type of an ethernet interface, from `linux/if_arp.h`.
+pub const ARPHRD_ETHER: u16 = 1;
+/// The hardware type of a loopback interface, from `linux/if_arp.h`.
+pub const ARPHRD_LOOPBACK: u16 = 772;
+
+bitflags::bitflags! {
+    /// Interface flags, as used with `SIOCGIFFLAGS`. These are i16 to match the `ifr_flags` field
+    /// of `struct ifreq`.
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+    pub struct InterfaceFlags: i16 {
+        const IFF_UP = 1 << 0;
+        const IFF_BROADCAST = 1 << 1;
+        const IFF_DEBUG = 1 << 2;
+        const IFF_LOOPBACK = 1 << 3;
+        const IFF_POINTOPOINT = 1 << 4;
+        const IFF_NOTRAILERS = 1 << 5;
+        const IFF_RUNNING = 1 << 6;
+        const IFF_NOARP = 1 << 7;
+        const IFF_PROMISC = 1 << 8;
+        const IFF_ALLMULTI = 1 << 9;
+        const IFF_MASTER = 1 << 10;
+        const IFF_SLAVE = 1 << 11;
+        const IFF_MULTICAST = 1 << 12;
+        const IFF_PORTSEL = 1 << 13;
+        const IFF_AUTOMEDIA = 1 << 14;
+        const IFF_DYNAMIC = 1 << 15;
+    }
+}
+
+// Manually translated from linux/if.h.
+//
+// The kernel's `ifr_ifru` is a union of a `struct sockaddr`, integers, a `struct ifmap`, an
+// interface name, and a pointer. It's represented here as its bytes, so that the type has no
+// invalid bit patterns. The kernel's type is 8-byte aligned, but has the same size.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct linux_ifreq {
+    /// The name of the interface, such as "eth0"
+    pub ifr_name: [u8; IFNAMSIZ],
+    /// The request's argument or result, which depends on the request
+    pub ifr_ifru: [u8; 24],
+}
+
+#[allow(non_camel_case_types)]
+pub type ifreq = linux_ifreq;
+unsafe impl shadow_pod::Pod for ifreq {}
+
+static_assertions::assert_eq_size!(ifreq, [u8; 40]);
+
+impl ifreq {
+    /// The interface name, without its nul terminator.
+    pub fn name(&self) -> &[u8] {
+        let len = self
+            .ifr_name
+            .iter()
+            .position(|x| *x == 0)
+            .unwrap_or(IFNAMSIZ);
+        &self.ifr_name[..len]
+    }
+
+    /// Set the interface name. Panics if the name doesn't fit with its nul terminator.
+    pub fn set_name(&mut self, name: &[u8]) {
+        assert!(name.len() < IFNAMSIZ);
+        self.ifr_name = [0; IFNAMSIZ];
+        self.ifr_name[..name.len()].copy_from_slice(name);
+    }
+
+    /// The `ifr_ifindex` (or `ifr_mtu`, etc) member of the union.
+    pub fn ifru_int(&self) -> i32 {
+        i32::from_ne_bytes(self.ifr_ifru[..4].try_into().unwrap())
+    }
+
+    /// Set the `ifr_ifindex` (or `ifr_mtu`, etc) member of the union.
+    pub fn set_ifru_int(&mut self, val: i32) {
+        self.ifr_ifru = [0; 24];
+        self.ifr_ifru[..4].copy_from_slice(&val.to_ne_bytes());
+    }
+
+    /// Set the `ifr_flags` member of the union.
+    pub fn set_ifru_flags(&mut self, flags: InterfaceFlags) {
+        self.ifr_ifru = [0; 24];
+        self.ifr_ifru[..2].copy_from_slice(&flags.bits().to_ne_bytes());
+    }
+
+    /// Set the `ifr_addr` (or `ifr_netmask`, `ifr_hwaddr`, etc) member of the union to a `struct
+    /// sockaddr` with the family `family` and the data `data`.
+    pub fn set_ifru_sockaddr(&mut self, family: u16, data: &[u8]) {
+        assert!(data.len() <= 14);
+        self.ifr_ifru = [0; 24];
+        self.ifr_ifru[..2].copy_from_slice(&family.to_ne_bytes());
+        self.ifr_ifru[2..][..data.len()].copy_from_slice(data);
+    }
+}
+
+// Manually translated from linux/if.h.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct linux_ifconf {
+    /// The length of the buffer in bytes
+    pub ifc_len: i32,
+    /// Explicit padding
+    pub pad: i32,
+    /// The buffer of `struct ifreq` entries (the `ifc_buf` and `ifc_req` members of the union)
+    pub ifc_buf: *mut u8,
+}
+
+#[allow(non_camel_case_types)]
+pub type ifconf = linux_ifconf;
+unsafe impl shadow_pod::Pod for ifconf {}
+
+static_assertions::assert_eq_size!(ifconf, [u8; 16]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ifreq_name() {
+        let mut req = ifreq {
+            ifr_name: [1; IFNAMSIZ],
+            ifr_ifru: [0; 24],
+        };
+        assert_eq!(req.name().len(), IFNAMSIZ);
+        req.set_name(b"eth0");
+        assert_eq!(req.name(), b"eth0");
+    }
+
+    #[test]
+    fn test_ifreq_sockaddr() {
+        let mut req = ifreq {
+            ifr_name: [0; IFNAMSIZ],
+            ifr_ifru: [0xff; 24],
+        };
+        req.set_ifru_sockaddr(ARPHRD_ETHER, &[2, 0, 11, 0, 0, 1]);
+        assert_eq!(req.ifr_ifru[..2], ARPHRD_ETHER.to_ne_bytes());
+        assert_eq!(req.ifr_ifru[2..8], [2, 0, 11, 0, 0, 1]);
+        assert!(req.ifr_ifru[8..].iter().all(|x| *x == 0));
+    }
+}
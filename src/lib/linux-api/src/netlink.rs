@@ -0,0 +1,105 @@
+//! Constants used with `NETLINK_ROUTE` sockets. See netlink(7) and rtnetlink(7).
+
+/// The netlink protocol for routing and interface messages, from `linux/netlink.h`.
+pub const NETLINK_ROUTE: i32 = 0;
+
+/// The socket option level of netlink sockets, from `linux/socket.h`.
+pub const SOL_NETLINK: i32 = 270;
+
+// Netlink socket options, from `linux/netlink.h`.
+pub const NETLINK_ADD_MEMBERSHIP: i32 = 1;
+pub const NETLINK_DROP_MEMBERSHIP: i32 = 2;
+pub const NETLINK_PKTINFO: i32 = 3;
+pub const NETLINK_BROADCAST_ERROR: i32 = 4;
+pub const NETLINK_NO_ENOBUFS: i32 = 5;
+pub const NETLINK_LISTEN_ALL_NSID: i32 = 8;
+pub const NETLINK_CAP_ACK: i32 = 10;
+pub const NETLINK_EXT_ACK: i32 = 11;
+pub const NETLINK_GET_STRICT_CHK: i32 = 12;
+
+/// The length of a `struct nlmsghdr`.
+pub const NLMSG_HDRLEN: usize = 16;
+/// Netlink messages and attributes are aligned to 4 bytes.
+pub const NLMSG_ALIGNTO: usize = 4;
+
+// Netlink control message types, from `linux/netlink.h`.
+pub const NLMSG_NOOP: u16 = 0x1;
+pub const NLMSG_ERROR: u16 = 0x2;
+pub const NLMSG_DONE: u16 = 0x3;
+pub const NLMSG_OVERRUN: u16 = 0x4;
+/// Message types below this value are reserved for control messages.
+pub const NLMSG_MIN_TYPE: u16 = 0x10;
+
+// Netlink message flags, from `linux/netlink.h`.
+pub const NLM_F_REQUEST: u16 = 0x01;
+pub const NLM_F_MULTI: u16 = 0x02;
+pub const NLM_F_ACK: u16 = 0x04;
+pub const NLM_F_ECHO: u16 = 0x08;
+pub const NLM_F_ROOT: u16 = 0x100;
+pub const NLM_F_MATCH: u16 = 0x200;
+pub const NLM_F_DUMP: u16 = NLM_F_ROOT | NLM_F_MATCH;
+/// Set on an error message that has no request payload (see `NETLINK_CAP_ACK`).
+pub const NLM_F_CAPPED: u16 = 0x100;
+
+// rtnetlink message types, from `linux/rtnetlink.h`. The message types come in groups of four
+// (new, delete, get, and set), starting at `RTM_BASE`.
+pub const RTM_BASE: u16 = 16;
+pub const RTM_NEWLINK: u16 = 16;
+pub const RTM_DELLINK: u16 = 17;
+pub const RTM_GETLINK: u16 = 18;
+pub const RTM_SETLINK: u16 = 19;
+pub const RTM_NEWADDR: u16 = 20;
+pub const RTM_DELADDR: u16 = 21;
+pub const RTM_GETADDR: u16 = 22;
+pub const RTM_NEWROUTE: u16 = 24;
+pub const RTM_DELROUTE: u16 = 25;
+pub const RTM_GETROUTE: u16 = 26;
+
+// Link attributes, from `linux/if_link.h`.
+pub const IFLA_ADDRESS: u16 = 1;
+pub const IFLA_BROADCAST: u16 = 2;
+pub const IFLA_IFNAME: u16 = 3;
+pub const IFLA_MTU: u16 = 4;
+pub const IFLA_TXQLEN: u16 = 13;
+pub const IFLA_OPERSTATE: u16 = 16;
+
+// Operational states of a link, from `linux/if.h`.
+pub const IF_OPER_UNKNOWN: u8 = 0;
+pub const IF_OPER_UP: u8 = 6;
+
+/// The interface flag that the link is up, which doesn't fit in the 16-bit `ifr_flags` of
+/// [`crate::netdevice::InterfaceFlags`], from `linux/if.h`.
+pub const IFF_LOWER_UP: u32 = 1 << 16;
+
+// Address attributes, from `linux/if_addr.h`.
+pub const IFA_ADDRESS: u16 = 1;
+pub const IFA_LOCAL: u16 = 2;
+pub const IFA_LABEL: u16 = 3;
+pub const IFA_BROADCAST: u16 = 4;
+
+/// The flag of an address that doesn't expire, from `linux/if_addr.h`.
+pub const IFA_F_PERMANENT: u8 = 0x80;
+
+// Route attributes, from `linux/rtnetlink.h`.
+pub const RTA_DST: u16 = 1;
+pub const RTA_OIF: u16 = 4;
+pub const RTA_PREFSRC: u16 = 7;
+pub const RTA_TABLE: u16 = 15;
+
+// Routing tables, from `linux/rtnetlink.h`.
+pub const RT_TABLE_MAIN: u8 = 254;
+pub const RT_TABLE_LOCAL: u8 = 255;
+
+// Route origins, from `linux/rtnetlink.h`.
+pub const RTPROT_UNSPEC: u8 = 0;
+pub const RTPROT_KERNEL: u8 = 2;
+pub const RTPROT_BOOT: u8 = 3;
+
+// Route scopes, from `linux/rtnetlink.h`.
+pub const RT_SCOPE_UNIVERSE: u8 = 0;
+pub const RT_SCOPE_LINK: u8 = 253;
+pub const RT_SCOPE_HOST: u8 = 254;
+
+// Route types, from `linux/rtnetlink.h`.
+pub const RTN_UNICAST: u8 = 1;
+pub const RTN_LOCAL: u8 = 2;
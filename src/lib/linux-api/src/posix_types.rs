@@ -18,6 +18,10 @@ pub use bindings::linux___kernel_off_t;
 #[allow(non_camel_case_types)]
 pub type kernel_off_t = linux___kernel_off_t;
 
+pub use bindings::linux___kernel_loff_t;
+#[allow(non_camel_case_types)]
+pub type kernel_loff_t = linux___kernel_loff_t;
+
 pub use bindings::linux___kernel_size_t;
 #[allow(non_camel_case_types)]
 pub type kernel_size_t = linux___kernel_size_t;
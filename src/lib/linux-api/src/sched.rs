@@ -48,6 +48,11 @@ pub type clone_args = linux_clone_args;
 
 unsafe impl shadow_pod::Pod for clone_args {}
 
+/// The size of the first published version of [`clone_args`], which is the smallest size that
+/// `clone3` accepts.
+pub const CLONE_ARGS_SIZE_VER0: usize =
+    const_conversions::usize_from_u32(bindings::LINUX_CLONE_ARGS_SIZE_VER0);
+
 pub fn sched_yield() -> Result<(), Errno> {
     unsafe { linux_syscall::syscall!(linux_syscall::SYS_sched_yield) }
         .check()
@@ -758,6 +758,7 @@ impl siginfo_t {
         exit_signal: Signal,
         child_pid: i32,
         child_uid: u32,
+        stop_signal: Signal,
         child_utime: i64,
         child_stime: i64,
     ) -> Self {
@@ -766,7 +767,7 @@ impl siginfo_t {
             SigInfoCodeCld::CLD_STOPPED,
             child_pid,
             child_uid,
-            Signal::SIGSTOP,
+            stop_signal,
             child_utime,
             child_stime,
         )
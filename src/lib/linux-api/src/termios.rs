@@ -0,0 +1,45 @@
+// Manually translated from asm-generic/termbits.h and asm-generic/termios.h.
+// We don't generate bindings for these headers since their `termios` definition
+// conflicts with the C library's (which has a different layout).
+
+/// Number of control characters in [`termios::c_cc`](linux_termios::c_cc).
+pub const NCCS: usize = 19;
+
+/// Terminal attributes, as read and written by the `TCGETS` and `TCSETS` ioctls.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct linux_termios {
+    /// Input mode flags
+    pub c_iflag: u32,
+    /// Output mode flags
+    pub c_oflag: u32,
+    /// Control mode flags
+    pub c_cflag: u32,
+    /// Local mode flags
+    pub c_lflag: u32,
+    /// Line discipline
+    pub c_line: u8,
+    /// Control characters
+    pub c_cc: [u8; NCCS],
+}
+
+#[allow(non_camel_case_types)]
+pub type termios = linux_termios;
+unsafe impl shadow_pod::Pod for termios {}
+
+/// Terminal window size, as read and written by the `TIOCGWINSZ` and
+/// `TIOCSWINSZ` ioctls.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct linux_winsize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
+
+#[allow(non_camel_case_types)]
+pub type winsize = linux_winsize;
+unsafe impl shadow_pod::Pod for winsize {}
@@ -7,11 +7,15 @@ use vasi::VirtualAddressSpaceIndependent;
 
 pub mod emulated_time;
 pub mod explicit_drop;
+// The shared memory types aren't built under loom, since the loom versions of the vasi-sync
+// primitives aren't `VirtualAddressSpaceIndependent`.
+#[cfg(not(loom))]
 pub mod ipc;
 pub mod notnull;
 pub mod option;
 pub mod rootedcell;
 pub mod shim_event;
+#[cfg(not(loom))]
 pub mod shim_shmem;
 pub mod simulation_time;
 pub mod syscall_types;
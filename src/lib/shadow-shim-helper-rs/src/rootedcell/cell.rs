@@ -1,6 +1,5 @@
-use std::cell::UnsafeCell;
-
 use vasi::VirtualAddressSpaceIndependent;
+use vasi_sync::sync::UnsafeCell;
 
 use super::{Root, Tag};
 
@@ -11,7 +10,8 @@ use super::{Root, Tag};
 /// Unlike [std::cell::Cell], this type is [Send] and [Sync] if `T` is
 /// [Send]. This is safe because the owner is required to prove access to the
 /// associated [Root], which is `![Sync]`, to access.
-#[derive(Debug, VirtualAddressSpaceIndependent)]
+#[derive(Debug)]
+#[cfg_attr(not(loom), derive(VirtualAddressSpaceIndependent))]
 #[repr(C)]
 pub struct RootedCell<T> {
     tag: Tag,
@@ -31,7 +31,7 @@ impl<T> RootedCell<T> {
     #[inline]
     pub fn get_mut(&mut self) -> &mut T {
         // Since we have the only reference to `self`, we don't need to check the root.
-        unsafe { &mut *self.val.get() }
+        self.val.get_mut().with(|p| unsafe { &mut *p })
     }
 
     #[inline]
@@ -49,7 +49,7 @@ impl<T> RootedCell<T> {
             self.tag, root.tag
         );
 
-        unsafe { self.val.get().replace(val) }
+        self.val.get_mut().with(|p| unsafe { p.replace(val) })
     }
 
     #[inline]
@@ -68,7 +68,7 @@ impl<T: Copy> RootedCell<T> {
             self.tag, root.tag
         );
 
-        unsafe { *self.val.get() }
+        self.val.get().with(|p| unsafe { *p })
     }
 }
 
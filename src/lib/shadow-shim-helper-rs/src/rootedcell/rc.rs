@@ -1,7 +1,6 @@
-use std::{
-    cell::{Cell, UnsafeCell},
-    ptr::NonNull,
-};
+use std::ptr::NonNull;
+
+use vasi_sync::sync::{Cell, UnsafeCell};
 
 use crate::explicit_drop::ExplicitDrop;
 
@@ -92,7 +91,7 @@ impl<T> RootedRcCommon<T> {
         let val: Option<T> = if strong_count == 0 {
             // SAFETY: Since no strong references remain, nothing else can be
             // referencing the internal value.
-            unsafe { internal.val.get().as_mut().unwrap().take() }
+            internal.val.get_mut().with(|p| unsafe { (*p).take() })
         } else {
             None
         };
@@ -254,7 +253,9 @@ impl<T> std::ops::Deref for RootedRc<T> {
         // that there are no mutable references to it. (The only time we create
         // a mutable reference is to drop the T value when the strong ref count
         // reaches zero)
-        let val = unsafe { &*internal.val.get() };
+        //
+        // Under loom, this only checks the access at the time of the deref.
+        let val = internal.val.get().with(|p| unsafe { &*p });
         val.as_ref().unwrap()
     }
 }
@@ -1,6 +1,5 @@
-use std::cell::{Cell, UnsafeCell};
-
 use vasi::VirtualAddressSpaceIndependent;
+use vasi_sync::sync::{Cell, UnsafeCell};
 
 use crate::explicit_drop::ExplicitDrop;
 
@@ -13,7 +12,8 @@ use super::{Root, Tag};
 /// Unlike [std::cell::RefCell], this type is [Send] and [Sync] if `T` is
 /// [Send]. This is safe because the owner is required to prove access to the
 /// associated [Root], which is `![Sync]`, to borrow.
-#[derive(Debug, VirtualAddressSpaceIndependent)]
+#[derive(Debug)]
+#[cfg_attr(not(loom), derive(VirtualAddressSpaceIndependent))]
 #[repr(C)]
 pub struct RootedRefCell<T> {
     tag: Tag,
@@ -101,7 +101,8 @@ impl<'a, T> std::ops::Deref for RootedRefCellRef<'a, T> {
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { self.guard.val.get().as_ref().unwrap() }
+        // Under loom, this only checks the access at the time of the deref.
+        self.guard.val.get().with(|p| unsafe { &*p })
     }
 }
 
@@ -123,14 +124,16 @@ impl<'a, T> std::ops::Deref for RootedRefCellRefMut<'a, T> {
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { self.guard.val.get().as_ref().unwrap() }
+        // Under loom, this only checks the access at the time of the deref.
+        self.guard.val.get().with(|p| unsafe { &*p })
     }
 }
 
 impl<'a, T> std::ops::DerefMut for RootedRefCellRefMut<'a, T> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { self.guard.val.get().as_mut().unwrap() }
+        // Under loom, this only checks the access at the time of the deref.
+        self.guard.val.get_mut().with(|p| unsafe { &mut *p })
     }
 }
 
@@ -92,6 +92,9 @@ pub struct HostShmem {
 
     pub shim_log_level: logger::LogLevel,
 
+    // Whether `getaddrinfo()` lists a name's IPv6 addresses before its IPv4 addresses.
+    pub resolver_prefers_ipv6: bool,
+
     pub manager_shmem: ShMemBlockSerialized,
 }
 assert_shmem_safe!(HostShmem, _hostshmem_test_fn);
@@ -107,6 +110,7 @@ impl HostShmem {
         shadow_pid: libc::pid_t,
         tsc_hz: u64,
         shim_log_level: ::logger::LogLevel,
+        resolver_prefers_ipv6: bool,
         manager_shmem: &ShMemBlock<ManagerShmem>,
     ) -> Self {
         Self {
@@ -125,6 +129,7 @@ impl HostShmem {
             tsc_hz,
             sim_time: AtomicEmulatedTime::new(EmulatedTime::MIN),
             shim_log_level,
+            resolver_prefers_ipv6,
             manager_shmem: manager_shmem.serialize(),
         }
     }
@@ -292,6 +297,8 @@ impl ThreadShmem {
                     pending_standard_siginfos: [siginfo_t::default();
                         Signal::STANDARD_MAX.as_i32() as usize],
                     blocked_signals: sigset_t::EMPTY,
+                    saved_blocked_signals: sigset_t::EMPTY,
+                    has_saved_blocked_signals: false,
                     sigaltstack: StackWrapper(stack_t {
                         ss_sp: std::ptr::null_mut(),
                         ss_flags: libc::SS_DISABLE,
@@ -332,6 +339,12 @@ pub struct ThreadShmemProtected {
     // actually supported by the kernel.
     pub blocked_signals: sigset_t,
 
+    // Signal mask to restore once pending signals have been handled, when a
+    // syscall such as `ppoll` temporarily replaced `blocked_signals`. Only
+    // valid if `has_saved_blocked_signals` is set.
+    saved_blocked_signals: sigset_t,
+    has_saved_blocked_signals: bool,
+
     // Configured alternate signal stack for this thread.
     sigaltstack: StackWrapper,
 }
@@ -350,6 +363,26 @@ impl ThreadShmemProtected {
         self.pending_standard_siginfos[signal_idx(signal)] = *info;
     }
 
+    /// Temporarily replace the signal mask, as `ppoll` and `pselect6` do while
+    /// they wait. The original mask is kept until `restore_blocked_signals` is
+    /// called, even if the mask is replaced again in the meantime.
+    pub fn set_temporary_blocked_signals(&mut self, mask: sigset_t) {
+        if !self.has_saved_blocked_signals {
+            self.saved_blocked_signals = self.blocked_signals;
+            self.has_saved_blocked_signals = true;
+        }
+        self.blocked_signals = mask;
+    }
+
+    /// Restore the signal mask replaced by `set_temporary_blocked_signals`, if
+    /// any.
+    pub fn restore_blocked_signals(&mut self) {
+        if self.has_saved_blocked_signals {
+            self.blocked_signals = self.saved_blocked_signals;
+            self.has_saved_blocked_signals = false;
+        }
+    }
+
     /// # Safety
     ///
     /// `stack_t::ss_sp` must not be dereferenced except from corresponding
@@ -676,6 +709,41 @@ pub mod export {
         protected.blocked_signals = sigset_t::wrap(s);
     }
 
+    /// Temporarily set the thread's blocked signal set. See
+    /// `ThreadShmemProtected::set_temporary_blocked_signals`.
+    ///
+    /// # Safety
+    ///
+    /// Pointer args must be safely dereferenceable.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shimshmem_setTemporaryBlockedSignals(
+        lock: *const ShimShmemHostLock,
+        thread: *const ShimShmemThread,
+        s: linux_sigset_t,
+    ) {
+        let thread_mem = unsafe { thread.as_ref().unwrap() };
+        let lock = unsafe { lock.as_ref().unwrap() };
+        let mut protected = thread_mem.protected.borrow_mut(&lock.root);
+        protected.set_temporary_blocked_signals(sigset_t::wrap(s));
+    }
+
+    /// Restore the thread's blocked signal set after it was set by
+    /// `shimshmem_setTemporaryBlockedSignals`.
+    ///
+    /// # Safety
+    ///
+    /// Pointer args must be safely dereferenceable.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shimshmem_restoreBlockedSignals(
+        lock: *const ShimShmemHostLock,
+        thread: *const ShimShmemThread,
+    ) {
+        let thread_mem = unsafe { thread.as_ref().unwrap() };
+        let lock = unsafe { lock.as_ref().unwrap() };
+        let mut protected = thread_mem.protected.borrow_mut(&lock.root);
+        protected.restore_blocked_signals();
+    }
+
     /// Get the signal stack as set by `sigaltstack(2)`.
     ///
     /// # Safety
@@ -756,6 +824,20 @@ pub mod export {
         host.model_unblocked_syscall_latency
     }
 
+    /// Whether `getaddrinfo()` should list a name's IPv6 addresses before its
+    /// IPv4 addresses.
+    ///
+    /// # Safety
+    ///
+    /// Pointer args must be safely dereferenceable.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shimshmem_getResolverPrefersIpv6(
+        host: *const ShimShmemHost,
+    ) -> bool {
+        let host = unsafe { host.as_ref().unwrap() };
+        host.resolver_prefers_ipv6
+    }
+
     /// Get the configured maximum unblocked syscall latency to accumulate before
     /// yielding.
     ///
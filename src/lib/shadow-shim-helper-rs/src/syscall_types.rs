@@ -474,6 +474,12 @@ impl From<SysCallReg> for linux_api::mman::MRemapFlags {
     }
 }
 
+impl From<SysCallReg> for linux_api::mman::MsyncFlags {
+    fn from(reg: SysCallReg) -> Self {
+        Self::from_bits_retain(reg.into())
+    }
+}
+
 impl TryFrom<SysCallReg> for linux_api::time::ClockId {
     type Error = ();
     fn try_from(reg: SysCallReg) -> Result<Self, Self::Error> {
@@ -0,0 +1,158 @@
+//! Tests of the [rootedcell](shadow_shim_helper_rs::rootedcell) types that are
+//! intended to be run using [loom]. A [`Root`] is typically shared between
+//! threads by protecting it with a mutex, so these tests check that the
+//! objects associated with it are synchronized by that mutex.
+//!
+//! From the shadow source directory:
+//!
+//! ```text
+//! LOOM_MAX_PREEMPTIONS=3 \
+//! RUSTFLAGS="--cfg loom" \
+//! cargo test \
+//! --manifest-path=src/Cargo.toml \
+//! -p shadow-shim-helper-rs \
+//! --test rootedcell-tests \
+//! --target-dir=loomtarget \
+//! -- --nocapture
+//! ```
+//!
+//! [loom]: <https://docs.rs/loom/latest/loom/>
+use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
+use shadow_shim_helper_rs::rootedcell::cell::RootedCell;
+use shadow_shim_helper_rs::rootedcell::rc::RootedRc;
+use shadow_shim_helper_rs::rootedcell::refcell::RootedRefCell;
+use shadow_shim_helper_rs::rootedcell::Root;
+
+#[cfg(not(loom))]
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[cfg(loom)]
+use loom::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+fn model<F>(f: F)
+where
+    F: Fn() + Sync + Send + 'static,
+{
+    #[cfg(loom)]
+    loom::model(move || {
+        f();
+        vasi_sync::sync::loom_reset();
+    });
+    #[cfg(not(loom))]
+    f()
+}
+
+#[test]
+fn test_rc_clone_and_drop() {
+    model(|| {
+        let root = Arc::new(Mutex::new(Root::new()));
+        let rc = {
+            let root = root.lock().unwrap();
+            RootedRc::new(&root, RootedCell::new(&root, 0u32))
+        };
+
+        let threads: Vec<_> = (1..=2)
+            .map(|i| {
+                let root = Arc::clone(&root);
+                let rc = {
+                    let root = root.lock().unwrap();
+                    rc.clone(&root)
+                };
+                thread::spawn(move || {
+                    let root = root.lock().unwrap();
+                    let clone = rc.clone(&root);
+                    clone.set(&root, i);
+                    clone.explicit_drop(&root);
+                    rc.explicit_drop(&root);
+                })
+            })
+            .collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let root = root.lock().unwrap();
+        let val = rc.get(&root);
+        assert!(val == 1 || val == 2);
+        let cell = RootedRc::into_inner(rc, &root).unwrap();
+        assert_eq!(cell.into_inner(), val);
+    })
+}
+
+#[test]
+fn test_refcell_borrow_mut() {
+    model(|| {
+        let root = Arc::new(Mutex::new(Root::new()));
+        let rc = {
+            let root = root.lock().unwrap();
+            RootedRc::new(&root, RootedRefCell::new(&root, Vec::new()))
+        };
+
+        let threads: Vec<_> = (1..=2)
+            .map(|i| {
+                let root = Arc::clone(&root);
+                let rc = {
+                    let root = root.lock().unwrap();
+                    rc.clone(&root)
+                };
+                thread::spawn(move || {
+                    let root = root.lock().unwrap();
+                    rc.borrow_mut(&root).push(i);
+                    assert!(rc.borrow(&root).contains(&i));
+                    rc.explicit_drop(&root);
+                })
+            })
+            .collect();
+
+        for t in threads {
+            t.join().unwrap();
+        }
+
+        let root = root.lock().unwrap();
+        let mut vals = RootedRc::into_inner(rc, &root).unwrap().into_inner();
+        vals.sort();
+        assert_eq!(vals, vec![1, 2]);
+    })
+}
+
+#[test]
+fn test_weak_upgrade() {
+    model(|| {
+        let root = Arc::new(Mutex::new(Root::new()));
+        let (rc, weak) = {
+            let root = root.lock().unwrap();
+            let rc = RootedRc::new(&root, RootedCell::new(&root, 0u32));
+            let weak = RootedRc::downgrade(&rc, &root);
+            (rc, weak)
+        };
+
+        let thread = {
+            let root = Arc::clone(&root);
+            thread::spawn(move || {
+                let root = root.lock().unwrap();
+                if let Some(rc) = weak.upgrade(&root) {
+                    rc.set(&root, 1);
+                    rc.explicit_drop(&root);
+                }
+                weak.explicit_drop(&root);
+            })
+        };
+
+        {
+            let root = root.lock().unwrap();
+            // Whether the other thread upgrades the weak reference depends on
+            // whether it locks the root before or after this.
+            let cell = RootedRc::into_inner(rc, &root).unwrap();
+            assert!(cell.into_inner() <= 1);
+        }
+
+        thread.join().unwrap();
+    })
+}
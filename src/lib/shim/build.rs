@@ -121,5 +121,8 @@ fn main() {
             "shim_syscall.c",
             "shim_tls.c",
         ])
+        // Start functions with `endbr64`, so that they're valid targets of the indirect jumps
+        // that we patch into the vdso when Indirect Branch Tracking (IBT) is enabled.
+        .flag_if_supported("-fcf-protection=branch")
         .compile("shim_c");
 }
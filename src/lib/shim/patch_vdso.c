@@ -161,8 +161,11 @@ static int _replacement_getcpu(void* arg1, void* arg2, void* arg3) {
 // Inject a trampoline that uses a relative jump. Only needs 5 bytes, but requires
 // that the offset fits in an i32.
 //
+// The trampoline is written to `dst`, and will run at address `start`.
+//
 // Returns injected trampoline size on success, or 0 if the trampoline couldn't be injected.
-static size_t _inject_trampoline_relative(void* start, size_t symbolSize, void* replacementFn) {
+static size_t _inject_trampoline_relative(void* start, uint8_t* dst, size_t symbolSize,
+                                          void* replacementFn) {
     intptr_t jmp_offset = (intptr_t)replacementFn - ((intptr_t)start + 5);
 
     if (jmp_offset > INT32_MAX || jmp_offset < INT32_MIN) {
@@ -176,23 +179,26 @@ static size_t _inject_trampoline_relative(void* start, size_t symbolSize, void*
         return 0;
     }
 
-    uint8_t* current = start;
+    uint8_t* current = dst;
     // opcode for jmp
     *(current++) = 0xe9;
     // jmp offset
     *(int32_t*)current = (int32_t)jmp_offset;
     current += sizeof(int32_t);
 
-    size_t actualTrampolineSize = (size_t)current - (size_t)start;
+    size_t actualTrampolineSize = (size_t)current - (size_t)dst;
     assert(actualTrampolineSize == trampolineSize);
     return actualTrampolineSize;
 }
 
 // Inject a trampoline using an absolute jump. Less efficient at runtime and
-// needs 13 bytes, but can jump to any target address.
+// needs 13 bytes, but can jump to any target address. The jump is indirect, so
+// with IBT the replacement function must start with `endbr64`.
+//
+// The trampoline is written to `dst`.
 //
 // Returns injected trampoline size on success, or 0 if the trampoline couldn't be injected.
-static size_t _inject_trampoline_absolute(void* start, size_t symbolSize, void* replacementFn) {
+static size_t _inject_trampoline_absolute(uint8_t* dst, size_t symbolSize, void* replacementFn) {
     const size_t trampolineSize = 13;
 
     if (symbolSize < trampolineSize) {
@@ -200,7 +206,7 @@ static size_t _inject_trampoline_absolute(void* start, size_t symbolSize, void*
         return 0;
     }
 
-    uint8_t* current = start;
+    uint8_t* current = dst;
 
     // movabs $...,%r10
     *(current++) = 0x49;
@@ -213,12 +219,17 @@ static size_t _inject_trampoline_absolute(void* start, size_t symbolSize, void*
     *(current++) = 0xff;
     *(current++) = 0xe2;
 
-    size_t actualTrampolineSize = (size_t)current - (size_t)start;
+    size_t actualTrampolineSize = (size_t)current - (size_t)dst;
     assert(actualTrampolineSize == trampolineSize);
     return actualTrampolineSize;
 }
 
-static void _inject_trampoline(struct ParsedElf* parsedElf, const char* vdsoFnName,
+// The `endbr64` instruction, which marks the valid targets of indirect branches
+// when Indirect Branch Tracking (IBT) is enabled.
+static const uint8_t ENDBR64[] = {0xf3, 0x0f, 0x1e, 0xfa};
+
+// Inject a trampoline into `copy`, a copy of the vdso mapping.
+static void _inject_trampoline(struct ParsedElf* parsedElf, uint8_t* copy, const char* vdsoFnName,
                                void* replacementFn) {
     const Elf64_Sym* symbol = _findSymbol(parsedElf, vdsoFnName);
     if (symbol == NULL) {
@@ -228,11 +239,22 @@ static void _inject_trampoline(struct ParsedElf* parsedElf, const char* vdsoFnNa
     }
 
     uint8_t* start = (void*)parsedElf->hdr + symbol->st_value;
+    uint8_t* dst = copy + ((size_t)start - (size_t)parsedElf->mapStart);
+    size_t symbolSize = symbol->st_size;
+
+    // Callers reach the symbol through an indirect call, so if it starts with
+    // `endbr64` we keep it and inject the trampoline after it. Otherwise the
+    // call would fault when IBT is enabled.
+    if (symbolSize >= sizeof(ENDBR64) && memcmp(start, ENDBR64, sizeof(ENDBR64)) == 0) {
+        start += sizeof(ENDBR64);
+        dst += sizeof(ENDBR64);
+        symbolSize -= sizeof(ENDBR64);
+    }
 
     size_t actualTrampolineSize =
-        _inject_trampoline_relative(start, symbol->st_size, replacementFn);
+        _inject_trampoline_relative(start, dst, symbolSize, replacementFn);
     if (actualTrampolineSize == 0) {
-        actualTrampolineSize = _inject_trampoline_absolute(start, symbol->st_size, replacementFn);
+        actualTrampolineSize = _inject_trampoline_absolute(dst, symbolSize, replacementFn);
     }
     // TODO: Some other trampoline strategies if neither of the above work:
     //
@@ -250,26 +272,88 @@ static void _inject_trampoline(struct ParsedElf* parsedElf, const char* vdsoFnNa
     }
 
     // Validate that we didn't actually clobber another symbol.
-    if (symbol->st_size < actualTrampolineSize) {
+    if (symbolSize < actualTrampolineSize) {
         panic("Accidentally wrote %zd byte trampoline into %zd byte symbol %s",
-              actualTrampolineSize, symbol->st_size, vdsoFnName);
+              actualTrampolineSize, symbolSize, vdsoFnName);
+    }
+}
+
+// Overwrite the vdso with the patched `copy`. The vdso is writable but not
+// executable while it's being overwritten, so that it's never both. Returns
+// false if the vdso's protections can't be changed, for example under a W^X
+// policy that doesn't allow executable memory to become writable.
+//
+// Nothing may use the vdso while it's not executable, so this doesn't log.
+static bool _replace_in_place(void* start, const uint8_t* copy, size_t size) {
+    if (mprotect(start, size, PROT_READ | PROT_WRITE)) {
+        return false;
+    }
+    memcpy(start, copy, size);
+    if (mprotect(start, size, PROT_READ | PROT_EXEC)) {
+        return false;
     }
+    return true;
+}
+
+// Replace the vdso mapping with a mapping of a memfd that contains the patched
+// `copy`. The memory is never writable, so this works under W^X policies that
+// don't allow writable memory to become executable.
+static bool _replace_with_memfd(void* start, const uint8_t* copy, size_t size) {
+    int fd = memfd_create("shadow-vdso", MFD_CLOEXEC);
+    if (fd < 0) {
+        trace("memfd_create: %s", strerror(errno));
+        return false;
+    }
+
+    size_t written = 0;
+    while (written < size) {
+        ssize_t rv = write(fd, copy + written, size - written);
+        if (rv < 0 && errno == EINTR) {
+            continue;
+        }
+        if (rv <= 0) {
+            trace("write: %s", strerror(errno));
+            close(fd);
+            return false;
+        }
+        written += rv;
+    }
+
+    void* rv = mmap(start, size, PROT_READ | PROT_EXEC, MAP_PRIVATE | MAP_FIXED, fd, 0);
+    close(fd);
+    if (rv == MAP_FAILED) {
+        trace("mmap: %s", strerror(errno));
+        return false;
+    }
+    return true;
 }
 
 void patch_vdso(void* vdsoBase) {
     struct ParsedElf parsedElf = _parseElf(vdsoBase);
     size_t regionSize = (size_t)parsedElf.mapEnd - (size_t)parsedElf.mapStart;
 
-    if (mprotect((void*)parsedElf.mapStart, regionSize, PROT_READ | PROT_WRITE | PROT_EXEC)) {
-        panic("mprotect: %s", strerror(errno));
+    // Patch a copy of the vdso rather than making the vdso itself writable and
+    // executable, which hardened systems may not allow.
+    uint8_t* copy =
+        mmap(NULL, regionSize, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    if (copy == MAP_FAILED) {
+        panic("mmap: %s", strerror(errno));
     }
+    memcpy(copy, parsedElf.mapStart, regionSize);
 
-    _inject_trampoline(&parsedElf, "__vdso_gettimeofday", _replacement_gettimeofday);
-    _inject_trampoline(&parsedElf, "__vdso_time", _replacement_time);
-    _inject_trampoline(&parsedElf, "__vdso_clock_gettime", _replacement_clock_gettime);
-    _inject_trampoline(&parsedElf, "__vdso_getcpu", _replacement_getcpu);
+    _inject_trampoline(&parsedElf, copy, "__vdso_gettimeofday", _replacement_gettimeofday);
+    _inject_trampoline(&parsedElf, copy, "__vdso_time", _replacement_time);
+    _inject_trampoline(&parsedElf, copy, "__vdso_clock_gettime", _replacement_clock_gettime);
+    _inject_trampoline(&parsedElf, copy, "__vdso_getcpu", _replacement_getcpu);
 
-    if (mprotect((void*)parsedElf.mapStart, regionSize, PROT_READ | PROT_EXEC)) {
-        panic("mprotect: %s", strerror(errno));
+    if (!_replace_in_place((void*)parsedElf.mapStart, copy, regionSize)) {
+        if (!_replace_with_memfd((void*)parsedElf.mapStart, copy, regionSize)) {
+            panic("Couldn't replace the vdso with the patched vdso");
+        }
+        trace("Replaced the vdso with a memfd mapping");
     }
-}
\ No newline at end of file
+
+    if (munmap(copy, regionSize)) {
+        panic("munmap: %s", strerror(errno));
+    }
+}
@@ -24,7 +24,7 @@
 #include "lib/logger/logger.h"
 #include "lib/shim/shim.h"
 #include "lib/shim/shim_syscall.h"
-#include "main/host/syscall_numbers.h" // For SYS_shadow_hostname_to_addr_ipv4
+#include "main/host/syscall_numbers.h" // For SYS_shadow_hostname_to_addr_ipv{4,6}
 
 // Sets `port` to the port specified by `service`, according to the criteria in
 // getaddrinfo(3). Returns 0 on success or the appropriate getaddrinfo error on
@@ -91,6 +91,7 @@ static int _getaddrinfo_service(in_port_t* port, const char* service,
 // `*head` and `*tail` to NULL.
 static void _getaddrinfo_append(struct addrinfo** head, struct addrinfo** tail, int socktype,
                                 struct sockaddr* addr, socklen_t addrlen) {
+    int family = addr->sa_family;
     int protocol = 0;
     if (socktype == SOCK_DGRAM) {
         protocol = IPPROTO_UDP;
@@ -103,7 +104,7 @@ static void _getaddrinfo_append(struct addrinfo** head, struct addrinfo** tail,
     }
     struct addrinfo* new_tail = malloc(sizeof(*new_tail));
     *new_tail = (struct addrinfo){.ai_flags = 0,
-                                  .ai_family = AF_INET,
+                                  .ai_family = family,
                                   .ai_socktype = socktype,
                                   .ai_protocol = protocol,
                                   .ai_addrlen = addrlen,
@@ -140,6 +141,28 @@ static void _getaddrinfo_appendv4(struct addrinfo** head, struct addrinfo** tail
     }
 }
 
+// IPv6 wrapper for _getaddrinfo_append. Appends an entry for the address and
+// port for each requested socket type.
+static void _getaddrinfo_appendv6(struct addrinfo** head, struct addrinfo** tail, bool add_tcp,
+                                  bool add_udp, bool add_raw, const struct in6_addr* addr,
+                                  in_port_t port) {
+    if (add_tcp) {
+        struct sockaddr_in6* sai = malloc(sizeof(*sai));
+        *sai = (struct sockaddr_in6){.sin6_family = AF_INET6, .sin6_port = port, .sin6_addr = *addr};
+        _getaddrinfo_append(head, tail, SOCK_STREAM, (struct sockaddr*)sai, sizeof(*sai));
+    }
+    if (add_udp) {
+        struct sockaddr_in6* sai = malloc(sizeof(*sai));
+        *sai = (struct sockaddr_in6){.sin6_family = AF_INET6, .sin6_port = port, .sin6_addr = *addr};
+        _getaddrinfo_append(head, tail, SOCK_DGRAM, (struct sockaddr*)sai, sizeof(*sai));
+    }
+    if (add_raw) {
+        struct sockaddr_in6* sai = malloc(sizeof(*sai));
+        *sai = (struct sockaddr_in6){.sin6_family = AF_INET6, .sin6_port = port, .sin6_addr = *addr};
+        _getaddrinfo_append(head, tail, SOCK_RAW, (struct sockaddr*)sai, sizeof(*sai));
+    }
+}
+
 // Looks for matching IPv4 addresses in /etc/hosts and them to the list
 // specified by `head` and `tail`.
 static void _getaddrinfo_add_matching_hosts_ipv4(struct addrinfo** head, struct addrinfo** tail,
@@ -220,10 +243,10 @@ out:
 }
 
 // Ask shadow to provide an ipv4 addr for a node using a custom syscall.
-// Returns true if we got a valid address from shadow, false otherwise.
-static bool _shim_api_hostname_to_addr_ipv4(const char* node, uint32_t* addr) {
+// Returns 0 if we got a valid address from shadow, or a negative errno otherwise.
+static int _shim_api_hostname_to_addr_ipv4(const char* node, uint32_t* addr) {
     if (!node || !addr) {
-        return false;
+        return -EINVAL;
     }
 
     // Skip the Shadow syscall for localhost lookups.
@@ -231,7 +254,7 @@ static bool _shim_api_hostname_to_addr_ipv4(const char* node, uint32_t* addr) {
         // Loopback address in network order.
         *addr = htonl(INADDR_LOOPBACK);
         trace("handled localhost getaddrinfo() lookup locally");
-        return true;
+        return 0;
     }
 
     // Resolve the hostname (find the ipv4 `addr` associated with hostname `name`) using a custom
@@ -252,11 +275,88 @@ static bool _shim_api_hostname_to_addr_ipv4(const char* node, uint32_t* addr) {
             trace("SYS_shadow_hostname_to_addr_ipv4 succeeded for name %s", node);
         }
 #endif
-        return true;
     } else {
         trace("SYS_shadow_hostname_to_addr_ipv4 failed for name %s", node);
-        return false;
     }
+    return rv;
+}
+
+// Ask shadow to provide an ipv6 addr for a node using a custom syscall.
+// Returns 0 if we got a valid address from shadow, or a negative errno otherwise.
+static int _shim_api_hostname_to_addr_ipv6(const char* node, struct in6_addr* addr) {
+    if (!node || !addr) {
+        return -EINVAL;
+    }
+
+    // Skip the Shadow syscall for localhost lookups.
+    if (strcasecmp(node, "localhost") == 0) {
+        *addr = in6addr_loopback;
+        trace("handled localhost getaddrinfo() lookup locally");
+        return 0;
+    }
+
+    trace("Performing custom shadow syscall SYS_shadow_hostname_to_addr_ipv6 for name %s", node);
+    int rv = shim_syscall(
+        NULL, SYS_shadow_hostname_to_addr_ipv6, node, strlen(node), addr, sizeof(*addr));
+
+    if (rv == 0) {
+#ifdef DEBUG
+        char addr_str_buf[INET6_ADDRSTRLEN] = {0};
+        if (inet_ntop(AF_INET6, addr, addr_str_buf, INET6_ADDRSTRLEN)) {
+            trace("SYS_shadow_hostname_to_addr_ipv6 returned addr %s for name %s", addr_str_buf,
+                  node);
+        } else {
+            trace("SYS_shadow_hostname_to_addr_ipv6 succeeded for name %s", node);
+        }
+#endif
+    } else {
+        trace("SYS_shadow_hostname_to_addr_ipv6 failed for name %s", node);
+    }
+    return rv;
+}
+
+// Looks up the IPv4 address of `node` and adds it to the list specified by
+// `head` and `tail`. Returns a negative errno if the host's resolver is
+// configured to fail the lookup, or 0 otherwise.
+static int _getaddrinfo_lookup_ipv4(struct addrinfo** head, struct addrinfo** tail,
+                                    const char* node, bool add_tcp, bool add_udp, bool add_raw,
+                                    in_port_t port) {
+    // Try first to avoid scanning the /etc/hosts file.
+    uint32_t addr;
+    int rv = _shim_api_hostname_to_addr_ipv4(node, &addr);
+    if (rv == 0) {
+        // We got the address we needed.
+        _getaddrinfo_appendv4(head, tail, add_tcp, add_udp, add_raw, addr, port);
+    } else if (rv == -ENOENT || rv == -ETIMEDOUT) {
+        // The host's resolver is configured to fail this lookup, so don't
+        // fall back to /etc/hosts.
+        return rv;
+    } else {
+        // Fall back to scanning /etc/hosts.
+        warning("shadow_hostname_to_addr_ipv4 syscall failed for name %s, falling back to less "
+                "efficient scan of '/etc/hosts' file.",
+                node);
+        _getaddrinfo_add_matching_hosts_ipv4(head, tail, node, add_tcp, add_udp, add_raw, port);
+    }
+    return 0;
+}
+
+// Looks up the IPv6 address of `node` and adds it to the list specified by
+// `head` and `tail`. Returns a negative errno if the host's resolver is
+// configured to fail the lookup, or 0 otherwise.
+static int _getaddrinfo_lookup_ipv6(struct addrinfo** head, struct addrinfo** tail,
+                                    const char* node, bool add_tcp, bool add_udp, bool add_raw,
+                                    in_port_t port) {
+    struct in6_addr addr;
+    int rv = _shim_api_hostname_to_addr_ipv6(node, &addr);
+    if (rv == 0) {
+        _getaddrinfo_appendv6(head, tail, add_tcp, add_udp, add_raw, &addr, port);
+    } else if (rv == -ENOENT || rv == -ETIMEDOUT) {
+        return rv;
+    }
+    // Names that Shadow doesn't know only have the IPv4 addresses in
+    // /etc/hosts.
+    return 0;
 }
 
 int shimc_api_getaddrinfo(const char* node, const char* service, const struct addrinfo* hints,
@@ -310,10 +410,9 @@ int shimc_api_getaddrinfo(const char* node, const char* service, const struct ad
     // returned only if the local system has at least one IPv6 address
     // configured."
     //
-    // Determining what kind of addresses the local system has configured is
-    // unimplemented. For now we assume it has IPv4 and not IPv6.
+    // Every host in Shadow has both an IPv4 and an IPv6 address.
     const bool system_has_an_ipv4_address = true;
-    const bool system_has_an_ipv6_address = false;
+    const bool system_has_an_ipv6_address = true;
 
     // "There are several reasons why the linked list may have more than one
     // addrinfo structure, including: the network host is ... accessible  over
@@ -353,7 +452,7 @@ int shimc_api_getaddrinfo(const char* node, const char* service, const struct ad
                     res, &tail, add_tcp, add_udp, add_raw, ntohl(INADDR_ANY), port);
             }
             if (add_ipv6) {
-                // TODO: IPv6
+                _getaddrinfo_appendv6(res, &tail, add_tcp, add_udp, add_raw, &in6addr_any, port);
             }
         } else {
             // "If the AI_PASSIVE flag is not set in hints.ai_flags, then the
@@ -367,7 +466,8 @@ int shimc_api_getaddrinfo(const char* node, const char* service, const struct ad
                     res, &tail, add_tcp, add_udp, add_raw, ntohl(INADDR_LOOPBACK), port);
             }
             if (add_ipv6) {
-                // TODO: IPv6
+                _getaddrinfo_appendv6(
+                    res, &tail, add_tcp, add_udp, add_raw, &in6addr_loopback, port);
             }
         }
         // We've finished adding all relevant addresses.
@@ -379,15 +479,18 @@ int shimc_api_getaddrinfo(const char* node, const char* service, const struct ad
     }
 
     // "`node` specifies either a numerical network address..."
-    if (add_ipv6) {
-        // TODO: try parsing as IPv6
-    }
     if (add_ipv4) {
         uint32_t addr;
         if (inet_pton(AF_INET, node, &addr) == 1) {
             _getaddrinfo_appendv4(res, &tail, add_tcp, add_udp, add_raw, addr, port);
         }
     }
+    if (add_ipv6) {
+        struct in6_addr addr;
+        if (inet_pton(AF_INET6, node, &addr) == 1) {
+            _getaddrinfo_appendv6(res, &tail, add_tcp, add_udp, add_raw, &addr, port);
+        }
+    }
     // If we successfully parsed as a numeric address, there's no need to
     // continue on to doing name-based lookups.
     if (*res != NULL) {
@@ -413,27 +516,32 @@ int shimc_api_getaddrinfo(const char* node, const char* service, const struct ad
     // order in which to try lookups.  We just hard-code trying `files` first
     // (and for now, only). For hosts lookups, the corresponding file is
     // /etc/hosts. See NSSWITCH.CONF(5).
-    if (add_ipv6) {
-        // TODO: look for IPv6 addresses in /etc/hosts.
+    //
+    // The IPv4 and IPv6 addresses are looked up one after the other. The IPv4
+    // addresses are listed first, like glibc does by default for unique local
+    // IPv6 addresses (RFC 6724), unless the host's resolver is configured to
+    // list the IPv6 addresses first.
+    const bool ipv6_first = shimshmem_getResolverPrefersIpv6(shim_hostSharedMem());
+    int ipv4_rv = 0;
+    int ipv6_rv = 0;
+    if (add_ipv6 && ipv6_first) {
+        ipv6_rv = _getaddrinfo_lookup_ipv6(res, &tail, node, add_tcp, add_udp, add_raw, port);
     }
     if (add_ipv4) {
-        // Try first to avoid scanning the /etc/hosts file.
-        uint32_t addr;
-        if (_shim_api_hostname_to_addr_ipv4(node, &addr)) {
-            // We got the address we needed.
-            _getaddrinfo_appendv4(res, &tail, add_tcp, add_udp, add_raw, addr, port);
-        } else {
-            // Fall back to scanning /etc/hosts.
-            warning("shadow_hostname_to_addr_ipv4 syscall failed for name %s, falling back to less "
-                    "efficient scan of '/etc/hosts' file.",
-                    node);
-            _getaddrinfo_add_matching_hosts_ipv4(res, &tail, node, add_tcp, add_udp, add_raw, port);
-        }
+        ipv4_rv = _getaddrinfo_lookup_ipv4(res, &tail, node, add_tcp, add_udp, add_raw, port);
+    }
+    if (add_ipv6 && !ipv6_first) {
+        ipv6_rv = _getaddrinfo_lookup_ipv6(res, &tail, node, add_tcp, add_udp, add_raw, port);
     }
 
     // TODO: maybe do DNS lookup, if we end up supporting that in Shadow.
 
     if (*res == NULL) {
+        if (ipv4_rv == -ETIMEDOUT || ipv6_rv == -ETIMEDOUT) {
+            // "EAI_AGAIN: The name server returned a temporary failure
+            // indication. Try again later."
+            return EAI_AGAIN;
+        }
         // "EAI_NONAME: The node or service is not known"
         return EAI_NONAME;
     }
@@ -7,6 +7,7 @@
 #include <assert.h>
 #include <errno.h>
 #include <inttypes.h>
+#include <linux/audit.h>
 #include <linux/filter.h>
 #include <linux/seccomp.h>
 #include <signal.h>
@@ -51,6 +52,17 @@ static void _shim_seccomp_handle_sigsys(int sig, siginfo_t* info, void* voidUcon
     // bytes long.
     const void* syscall_insn_addr = (void*)regs[REG_RIP] - SIZEOF_SYSCALL_INSN;
     trace("Trapped syscall %lld at %p", regs[REG_N], syscall_insn_addr);
+
+    // Syscalls made with the 32-bit ABI (e.g. with `int $0x80`) have different
+    // syscall numbers and struct layouts, which we don't support. Fail them
+    // rather than handling them as the 64-bit syscalls with the same numbers.
+    if (info->si_arch != AUDIT_ARCH_X86_64) {
+        warning("Unsupported syscall %lld with architecture %x at %p; returning ENOSYS",
+                regs[REG_N], info->si_arch, syscall_insn_addr);
+        regs[REG_RAX] = -ENOSYS;
+        return;
+    }
+
     if (syscall_insn_addr >= TEXT_START && syscall_insn_addr < TEXT_END) {
         panic("seccomp filter blocked syscall from %p, which is within %p-%p", syscall_insn_addr,
               TEXT_START, TEXT_END);
@@ -185,6 +197,12 @@ void shim_seccomp_init() {
      * https://www.kernel.org/doc./html/latest/admin-guide/syscall-user-dispatch.html
      */
     struct sock_filter filter[] = {
+        /* Trap syscalls that don't use the x86-64 ABI, since their syscall
+         * numbers mean different syscalls. */
+        BPF_STMT(BPF_LD + BPF_W + BPF_ABS, offsetof(struct seccomp_data, arch)),
+        BPF_JUMP(BPF_JMP + BPF_JEQ + BPF_K, AUDIT_ARCH_X86_64, /*true-skip=*/1, /*false-skip=*/0),
+        BPF_STMT(BPF_RET + BPF_K, SECCOMP_RET_TRAP),
+
         /* accumulator := syscall number */
         BPF_STMT(BPF_LD + BPF_W + BPF_ABS, offsetof(struct seccomp_data, nr)),
 
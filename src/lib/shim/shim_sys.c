@@ -75,7 +75,9 @@ bool shim_sys_handle_syscall_locally(long syscall_num, long* rv, va_list args) {
             clockid_t clk_id = va_arg(args, clockid_t);
             struct timespec* tp = va_arg(args, struct timespec*);
 
-            if (clk_id < LINUX_CLOCK_REALTIME || clk_id > LINUX_CLOCK_TAI) {
+            // Linux doesn't implement CLOCK_SGI_CYCLE.
+            if (clk_id < LINUX_CLOCK_REALTIME || clk_id > LINUX_CLOCK_TAI ||
+                clk_id == LINUX_CLOCK_SGI_CYCLE) {
                 trace("found invalid clock id %ld", (long)clk_id);
                 *rv = -EINVAL;
             } else if (tp) {
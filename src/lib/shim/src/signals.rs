@@ -131,8 +131,11 @@ pub unsafe fn process_signals(mut ucontext: Option<&mut ucontext>) -> bool {
                     drop(host_lock);
                     die_with_fatal_signal(sig);
                 }
-                linux_api::signal::LinuxDefaultAction::STOP => unimplemented!(),
-                linux_api::signal::LinuxDefaultAction::CONT => unimplemented!(),
+                // Shadow stops and continues the process when these signals
+                // are generated, so there's nothing left to do when they're
+                // delivered.
+                linux_api::signal::LinuxDefaultAction::STOP
+                | linux_api::signal::LinuxDefaultAction::CONT => continue,
             }
         }
 
@@ -315,6 +318,17 @@ pub unsafe fn process_signals(mut ucontext: Option<&mut ucontext>) -> bool {
             }
         });
     }
+
+    // A syscall such as `ppoll` that was interrupted leaves its temporary
+    // signal mask in place so that the handlers above run with it. Now that
+    // they've run, restore the original mask.
+    tls_thread_shmem::with(|thread| {
+        thread
+            .protected
+            .borrow_mut(&host_lock.root)
+            .restore_blocked_signals()
+    });
+
     restartable
 }
 
@@ -35,24 +35,39 @@ unsafe fn native_syscall(args: &SysCallArgs) -> SysCallReg {
         // TODO: make shim fully no_std and install a panic handler that aborts.
         // https://doc.rust-lang.org/nomicon/panic-handler.html
         unsafe { crate::release_and_exit_current_thread(exit_status) };
+    } else if args.number == libc::SYS_arch_prctl
+        && i64::from(args.args[0]) == i64::from(libc::ARCH_SET_FS)
+    {
+        // Changing the thread pointer changes where we find this thread's
+        // thread local storage; arrange for it to be migrated.
+        // SAFETY: We don't hold any references to thread local storage here.
+        unsafe { crate::SHIM_TLS.with_thread_pointer_change(|| raw_syscall(args)) }
     } else {
-        let rv: i64;
         // SAFETY: Caller is responsible for ensuring this syscall is safe to make.
-        unsafe {
-            core::arch::asm!(
-                "syscall",
-                inout("rax") args.number => rv,
-                in("rdi") u64::from(args.args[0]),
-                in("rsi") u64::from(args.args[1]),
-                in("rdx") u64::from(args.args[2]),
-                in("r10") u64::from(args.args[3]),
-                in("r8") u64::from(args.args[4]),
-                in("r9") u64::from(args.args[5]))
-        };
-        return rv.into();
+        unsafe { raw_syscall(args) }
     }
 }
 
+/// # Safety
+///
+/// The specified syscall must be safe to make.
+unsafe fn raw_syscall(args: &SysCallArgs) -> SysCallReg {
+    let rv: i64;
+    // SAFETY: Caller is responsible for ensuring this syscall is safe to make.
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            inout("rax") args.number => rv,
+            in("rdi") u64::from(args.args[0]),
+            in("rsi") u64::from(args.args[1]),
+            in("rdx") u64::from(args.args[2]),
+            in("r10") u64::from(args.args[3]),
+            in("r8") u64::from(args.args[4]),
+            in("r9") u64::from(args.args[5]))
+    };
+    rv.into()
+}
+
 /// # Safety
 ///
 /// `ctx` must be valid if provided.
@@ -156,6 +156,15 @@ impl TlsOneThreadStorage {
             bytes: UnsafeCell::new([MaybeUninit::new(0); BYTES_PER_THREAD]),
         }
     }
+
+    /// Copies the contents of `src` into `dst`.
+    ///
+    /// # Safety
+    ///
+    /// There must be no outstanding references into either storage.
+    unsafe fn copy(src: &Self, dst: &Self) {
+        unsafe { core::ptr::copy_nonoverlapping(src.bytes.get(), dst.bytes.get(), 1) };
+    }
 }
 
 /// This is a "proxy" type to `TlsOneThreadStorage` with the same size and alignment.
@@ -450,6 +459,87 @@ impl ThreadLocalStorage {
             }
         }
     }
+
+    /// Runs `f`, which may change the current thread's ELF thread pointer (e.g.
+    /// via `arch_prctl(ARCH_SET_FS)`), and then migrates the current thread's
+    /// storage so that it is found again via the new thread pointer.
+    ///
+    /// # Safety
+    ///
+    /// There must be no outstanding references to the current thread's
+    /// [`ShimTlsVar`]s, and `f` must not access this [`ThreadLocalStorage`].
+    ///
+    /// If the new thread pointer uses native thread local storage, it must be
+    /// set up such that `shim_native_tls` can be safely called.
+    pub unsafe fn with_thread_pointer_change<T>(&self, f: impl FnOnce() -> T) -> T {
+        let prev_key = self.current_key();
+        let prev_native = match prev_key {
+            Some(_) => None,
+            None => Some(unsafe { crate::bindings::shim_native_tls() }),
+        };
+
+        let rv = f();
+
+        let curr_key = self.current_key();
+        match (prev_key, curr_key) {
+            (Some(prev_key), Some(curr_key)) => {
+                if prev_key != curr_key {
+                    // Any storage already under the new key belonged to a
+                    // thread that no longer exists.
+                    // SAFETY: Both keys refer to the current thread.
+                    unsafe { self.storages.remove(curr_key.0.to_nonzero_usize()) };
+                    if let Some(storage) =
+                        unsafe { self.storages.remove(prev_key.0.to_nonzero_usize()) }
+                    {
+                        unsafe {
+                            self.storages
+                                .get_or_insert_with(curr_key.0.to_nonzero_usize(), move || storage)
+                        };
+                    }
+                }
+            }
+            (None, None) => {
+                let prev_native = prev_native.unwrap();
+                let curr_native = unsafe { crate::bindings::shim_native_tls() };
+                if prev_native != curr_native {
+                    // SAFETY: Both are valid, distinct, per-thread allocations.
+                    unsafe { core::ptr::copy_nonoverlapping(prev_native, curr_native, 1) };
+                }
+            }
+            (Some(prev_key), None) => {
+                // table -> native.
+                // SAFETY: `prev_key` referred to the current thread.
+                if let Some(storage) =
+                    unsafe { self.storages.remove(prev_key.0.to_nonzero_usize()) }
+                {
+                    let curr_native = unsafe {
+                        TlsOneThreadStorage::from_static_lifetime_zeroed_allocation(
+                            crate::bindings::shim_native_tls(),
+                        )
+                    };
+                    unsafe { TlsOneThreadStorage::copy(&storage, curr_native) };
+                }
+            }
+            (None, Some(curr_key)) => {
+                // native -> table.
+                let prev_native = unsafe {
+                    TlsOneThreadStorage::from_static_lifetime_zeroed_allocation(
+                        prev_native.unwrap(),
+                    )
+                };
+                // SAFETY: `curr_key` refers to the current thread.
+                unsafe { self.storages.remove(curr_key.0.to_nonzero_usize()) };
+                let storage = MmapBox::new(TlsOneThreadStorage::new());
+                unsafe { TlsOneThreadStorage::copy(prev_native, &storage) };
+                unsafe {
+                    self.storages
+                        .get_or_insert_with(curr_key.0.to_nonzero_usize(), move || storage)
+                };
+            }
+        }
+
+        rv
+    }
 }
 
 enum TlsOneThreadBackingStoreRef<'tls> {
@@ -46,6 +46,11 @@ where
     SHMALLOC.lock().free(block);
 }
 
+/// Returns the current usage of the global shared memory allocator.
+pub fn shmalloc_usage() -> ShMemUsage {
+    SHMALLOC.lock().usage()
+}
+
 /// This function takes a serialized block and converts it back into a BlockAlias that can be
 /// dereferenced.
 ///
@@ -177,7 +182,9 @@ where
     fn drop(&mut self) {
         if !self.block.is_null() {
             // Guard here to prevent deadlock on free.
-            SHMALLOC.lock().internal.dealloc(self.block);
+            SHMALLOC
+                .lock()
+                .dealloc(self.block, core::mem::size_of::<T>());
             self.block = core::ptr::null_mut();
         }
     }
@@ -271,12 +278,26 @@ impl core::str::FromStr for ShMemBlockSerialized {
     }
 }
 
+/// Usage statistics of a shared memory allocator.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ShMemUsage {
+    /// The number of blocks that are currently allocated.
+    pub nallocs: usize,
+    /// The number of bytes in the blocks that are currently allocated, not including block
+    /// metadata.
+    pub allocated_nbytes: usize,
+    /// The number of bytes of shared memory that the allocator has mapped, including memory that
+    /// isn't in use.
+    pub reserved_nbytes: usize,
+}
+
 /// Safe wrapper around our low-level, unsafe, nostd shared memory allocator. This allocator type
 /// is not meant to be used directly, but can be accessed indirectly via calls made to `shmalloc()`
 /// and `shfree()`.
 pub struct SharedMemAllocator<'alloc> {
     internal: crate::shmalloc_impl::FreelistAllocator,
     nallocs: isize,
+    allocated_nbytes: usize,
     phantom: core::marker::PhantomData<&'alloc ()>,
 }
 
@@ -288,6 +309,7 @@ impl<'alloc> SharedMemAllocator<'alloc> {
         Self {
             internal,
             nallocs: 0,
+            allocated_nbytes: 0,
             phantom: Default::default(),
         }
     }
@@ -303,6 +325,7 @@ impl<'alloc> SharedMemAllocator<'alloc> {
         }
 
         self.nallocs += 1;
+        self.allocated_nbytes += t_nbytes;
         ShMemBlock::<'alloc, T> {
             block,
             phantom: Default::default(),
@@ -310,9 +333,23 @@ impl<'alloc> SharedMemAllocator<'alloc> {
     }
 
     fn free<T: Sync + VirtualAddressSpaceIndependent>(&mut self, mut block: ShMemBlock<'alloc, T>) {
+        // null the block's pointer so that the block's destructor doesn't try to free it again
+        let block_p = core::mem::replace(&mut block.block, core::ptr::null_mut());
+        self.dealloc(block_p, core::mem::size_of::<T>());
+    }
+
+    fn dealloc(&mut self, block: *mut crate::shmalloc_impl::Block, t_nbytes: usize) {
         self.nallocs -= 1;
-        block.block = core::ptr::null_mut();
-        self.internal.dealloc(block.block);
+        self.allocated_nbytes -= t_nbytes;
+        self.internal.dealloc(block);
+    }
+
+    fn usage(&self) -> ShMemUsage {
+        ShMemUsage {
+            nallocs: self.nallocs.try_into().unwrap_or(0),
+            allocated_nbytes: self.allocated_nbytes,
+            reserved_nbytes: self.internal.reserved_nbytes(),
+        }
     }
 
     fn destruct(&mut self) {
@@ -537,4 +574,36 @@ mod tests {
 
         shfree(alloced_block);
     }
+
+    #[test]
+    // Uses FFI
+    #[cfg_attr(miri, ignore)]
+    fn usage() {
+        // use our own allocator so that allocations from other tests don't affect the usage
+        let mut alloc = SharedMemAllocator::new();
+        let reserved_nbytes = alloc.usage().reserved_nbytes;
+        assert!(reserved_nbytes > 0);
+
+        let a = alloc.alloc(0u64);
+        let b = alloc.alloc(0u32);
+        assert_eq!(
+            alloc.usage(),
+            ShMemUsage {
+                nallocs: 2,
+                allocated_nbytes: 12,
+                reserved_nbytes,
+            }
+        );
+
+        alloc.free(a);
+        assert_eq!(alloc.usage().nallocs, 1);
+        assert_eq!(alloc.usage().allocated_nbytes, 4);
+
+        alloc.free(b);
+        assert_eq!(alloc.usage().nallocs, 0);
+        assert_eq!(alloc.usage().allocated_nbytes, 0);
+        assert_eq!(alloc.usage().reserved_nbytes, reserved_nbytes);
+
+        alloc.destruct();
+    }
 }
@@ -393,6 +393,7 @@ pub(crate) struct FreelistAllocator {
     first_chunk: *mut Chunk,
     next_free_block: *mut Block,
     chunk_nbytes: usize,
+    nchunks: usize,
 }
 
 impl FreelistAllocator {
@@ -401,9 +402,15 @@ impl FreelistAllocator {
             first_chunk: core::ptr::null_mut(),
             next_free_block: core::ptr::null_mut(),
             chunk_nbytes: CHUNK_NBYTES_DEFAULT,
+            nchunks: 0,
         }
     }
 
+    /// The number of bytes of shared memory that have been mapped for this allocator's chunks.
+    pub fn reserved_nbytes(&self) -> usize {
+        self.nchunks * self.chunk_nbytes
+    }
+
     pub fn init(&mut self) -> Result<(), i32> {
         self.add_chunk()
     }
@@ -419,6 +426,7 @@ impl FreelistAllocator {
         }
 
         self.first_chunk = new_chunk;
+        self.nchunks += 1;
 
         Ok(())
     }
@@ -646,6 +654,7 @@ impl FreelistAllocator {
             }
 
             self.first_chunk = core::ptr::null_mut();
+            self.nchunks = 0;
         }
     }
 }
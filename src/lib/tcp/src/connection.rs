@@ -4,7 +4,8 @@ use std::net::SocketAddrV4;
 
 use crate::buffer::{RecvQueue, Segment};
 use crate::seq::{Seq, SeqRange};
-use crate::util::time::Instant;
+use crate::timestamps::Timestamps;
+use crate::util::time::{Duration, Instant};
 use crate::window_scaling::WindowScaling;
 use crate::{
     Ipv4Header, Payload, PopPacketError, PushPacketError, RecvError, SendError, TcpConfig,
@@ -20,8 +21,23 @@ pub(crate) struct Connection<I: Instant> {
     pub(crate) send: ConnectionSend<I>,
     pub(crate) recv: Option<ConnectionRecv>,
     pub(crate) need_to_ack: bool,
+    /// We received payload data and are delaying its acknowledgement.
+    pub(crate) ack_is_delayed: bool,
+    pub(crate) delayed_ack_timer_pending: bool,
+    /// The number of delayed acknowledgement timers that have been started.
+    pub(crate) num_delayed_acks: u32,
+    /// The number of payload bytes received since we last sent an acknowledgement.
+    pub(crate) unacked_bytes: u64,
     pub(crate) last_advertised_window: Option<u32>,
     pub(crate) window_scaling: WindowScaling,
+    pub(crate) timestamps: Timestamps,
+    /// The time of the first packet we sent, which is when our timestamp clock started.
+    pub(crate) timestamps_start: Option<I>,
+    /// The end of the last segment we sent with less than a full segment of payload.
+    pub(crate) last_partial_end: Option<Seq>,
+    pub(crate) cork_timer_pending: bool,
+    /// The cork timer expired while a partial segment was held, so it should be sent.
+    pub(crate) cork_expired: bool,
     pub(crate) send_rst_if_recv_payload: bool,
     pub(crate) is_reset: bool,
     pub(crate) need_to_send_rst: bool,
@@ -33,6 +49,10 @@ impl<I: Instant> Connection<I> {
     const SEND_BUF_MAX: usize = 100_000;
     const RECV_BUF_MAX: u32 = 100_000;
 
+    /// The max number of payload bytes in a packet. This roughly represents the MSS.
+    // TODO: handle the MSS properly
+    const MAX_BYTES_PER_PACKET: u32 = 1500;
+
     pub fn new(
         local_addr: SocketAddrV4,
         remote_addr: SocketAddrV4,
@@ -46,8 +66,17 @@ impl<I: Instant> Connection<I> {
             send: ConnectionSend::new(send_initial_seq),
             recv: None,
             need_to_ack: true,
+            ack_is_delayed: false,
+            delayed_ack_timer_pending: false,
+            num_delayed_acks: 0,
+            unacked_bytes: 0,
             last_advertised_window: None,
             window_scaling: WindowScaling::new(),
+            timestamps: Timestamps::new(),
+            timestamps_start: None,
+            last_partial_end: None,
+            cork_timer_pending: false,
+            cork_expired: false,
             send_rst_if_recv_payload: false,
             is_reset: false,
             need_to_send_rst: false,
@@ -58,6 +87,11 @@ impl<I: Instant> Connection<I> {
             rv.window_scaling.disable();
         }
 
+        // disable timestamps if they're disabled in the config
+        if !rv.config.timestamps_enabled {
+            rv.timestamps.disable();
+        }
+
         rv
     }
 
@@ -190,6 +224,7 @@ impl<I: Instant> Connection<I> {
             self.recv = Some(ConnectionRecv::new(seq));
 
             self.window_scaling.received_syn(header.window_scale);
+            self.timestamps.received_syn(header.timestamp);
         }
 
         // We need to keep track of if the original packet had the SYN flag set, even if we trim a
@@ -214,6 +249,9 @@ impl<I: Instant> Connection<I> {
             return Ok(());
         };
 
+        // the segment is within the receive window, so we can use its timestamp
+        self.timestamps.received(header.timestamp);
+
         let Some(recv) = self.recv.as_mut() else {
             // we received a non-SYN packet before the first SYN packet
             self.send_rst();
@@ -291,7 +329,22 @@ impl<I: Instant> Connection<I> {
         // we've added to the receive buffer (payload, syn, or fin), so we need to send an
         // acknowledgement
         if recv.buffer.next_seq() != initial_seq {
-            self.need_to_ack = true;
+            let only_payload = !header.flags.intersects(TcpFlags::SYN | TcpFlags::FIN);
+            let payload_len = u64::from(recv.buffer.next_seq() - initial_seq);
+            self.unacked_bytes += payload_len;
+
+            // like linux, don't leave too much received data unacknowledged
+            let too_many_unacked_bytes = self
+                .config
+                .delayed_ack
+                .max_unacked_bytes
+                .is_some_and(|max| self.unacked_bytes >= max);
+
+            if only_payload && !self.config.quickack && !too_many_unacked_bytes {
+                self.ack_is_delayed = true;
+            } else {
+                self.need_to_ack = true;
+            }
         }
 
         // update the send window, applying the window scale shift only if it wasn't a SYN packet
@@ -340,8 +393,13 @@ impl<I: Instant> Connection<I> {
             Seq::new(0)
         };
 
+        // like linux, our timestamp clock ticks every millisecond (and wraps)
+        let timestamps_start = *self.timestamps_start.get_or_insert(now);
+        let timestamp = now.saturating_duration_since(timestamps_start).as_millis() as u32;
+
         let header_window_size;
         let header_window_scale;
+        let header_timestamps;
 
         if flags.contains(TcpFlags::SYN) {
             if self.window_scaling.can_send_window_scale() {
@@ -369,6 +427,23 @@ impl<I: Instant> Connection<I> {
             debug_assert!(header_window_size <= u16::MAX as u32);
 
             self.window_scaling.sent_syn(header_window_scale);
+
+            if self.timestamps.can_send_in_syn() {
+                // RFC 7323 3.2.:
+                // > The TSecr field is valid if the ACK bit is set in the TCP header. If the ACK
+                // > bit is not set in the outgoing TCP header, the sender of that segment SHOULD
+                // > set the TSecr field to zero.
+                let echo = if flags.contains(TcpFlags::ACK) {
+                    self.timestamps.echo()
+                } else {
+                    0
+                };
+                header_timestamps = Some((timestamp, echo));
+            } else {
+                header_timestamps = None;
+            }
+
+            self.timestamps.sent_syn(header_timestamps.is_some());
         } else {
             // don't send a window scale
             //
@@ -382,6 +457,11 @@ impl<I: Instant> Connection<I> {
 
             // this is the value the peer will see (precision is intentionally lost due to bit-shift)
             self.last_advertised_window = Some(header_window_size << shift);
+
+            header_timestamps = self
+                .timestamps
+                .is_enabled()
+                .then(|| (timestamp, self.timestamps.echo()));
         }
 
         let header = TcpHeader {
@@ -397,12 +477,20 @@ impl<I: Instant> Connection<I> {
             window_size: header_window_size.try_into().unwrap(),
             selective_acks: None,
             window_scale: header_window_scale,
-            timestamp: None,
-            timestamp_echo: None,
+            timestamp: header_timestamps.map(|x| x.0),
+            timestamp_echo: header_timestamps.map(|x| x.1),
         };
 
         // we're sending the most up-to-date acknowledgement
         self.need_to_ack = false;
+        self.ack_is_delayed = false;
+        self.unacked_bytes = 0;
+
+        let payload_len = payload.len();
+        if payload_len > 0 && payload_len < Self::MAX_BYTES_PER_PACKET {
+            self.last_partial_end = Some(seq_range.end);
+            self.cork_expired = false;
+        }
 
         // inform the buffer that we transmitted this segment
         self.send.buffer.mark_as_transmitted(seq_range.end, now);
@@ -471,8 +559,13 @@ impl<I: Instant> Connection<I> {
                 send_empty_packet = true;
             }
 
-            // do we need to send a window update?
-            if let Some(window) = self.recv_window().map(|x| x.len()) {
+            // do we need to send a window update? if we're delaying an acknowledgement, the
+            // window will be updated when the acknowledgement is sent
+            if let Some(window) = self
+                .recv_window()
+                .map(|x| x.len())
+                .filter(|_| !self.ack_is_delayed)
+            {
                 let window_scale = self.window_scaling.recv_window_scale_shift();
 
                 let apparent_window = window >> window_scale << window_scale;
@@ -521,10 +614,6 @@ impl<I: Instant> Connection<I> {
         let mut seq_len = 0;
         let mut payload_bytes_len = 0;
 
-        // roughly represents the MSS
-        // TODO: handle the MSS properly
-        const MAX_BYTES_PER_PACKET: u32 = 1500;
-
         // do we have syn/fin/payload data to send?
         while let Some((seq, segment)) = self.send.buffer.next_not_transmitted(seq_len) {
             // if no bytes of this segment fit within the send window
@@ -533,7 +622,7 @@ impl<I: Instant> Connection<I> {
             }
 
             // if we can't send any more payload bytes
-            if payload_bytes_len == MAX_BYTES_PER_PACKET {
+            if payload_bytes_len == Self::MAX_BYTES_PER_PACKET {
                 break;
             }
 
@@ -553,7 +642,7 @@ impl<I: Instant> Connection<I> {
                 }
                 Segment::Data(mut chunk) => {
                     let allowed_payload_len =
-                        MAX_BYTES_PER_PACKET.saturating_sub(payload_bytes_len);
+                        Self::MAX_BYTES_PER_PACKET.saturating_sub(payload_bytes_len);
                     let allowed_seq_len = send_window.end - seq;
                     let allowed_len = std::cmp::min(allowed_payload_len, allowed_seq_len);
 
@@ -568,7 +657,15 @@ impl<I: Instant> Connection<I> {
             };
 
             // we shouldn't be sending more than allowed
-            debug_assert!(payload_bytes_len <= MAX_BYTES_PER_PACKET);
+            debug_assert!(payload_bytes_len <= Self::MAX_BYTES_PER_PACKET);
+        }
+
+        // hold back a partial segment so that later writes can be sent in the same segment
+        if syn_fin_flags.is_empty()
+            && payload_bytes_len < Self::MAX_BYTES_PER_PACKET
+            && self.should_hold_partial_segment()
+        {
+            return None;
         }
 
         if !chunks.is_empty() || !syn_fin_flags.is_empty() {
@@ -580,6 +677,73 @@ impl<I: Instant> Connection<I> {
         None
     }
 
+    /// Returns true if a segment with less than a full segment of payload should not be sent yet.
+    /// This is similar to `tcp_nagle_check()` in Linux's "net/ipv4/tcp_output.c".
+    fn should_hold_partial_segment(&self) -> bool {
+        if self.config.cork {
+            return !self.cork_expired;
+        }
+
+        // Nagle's algorithm with Minshall's variant: wait while our last partial segment is unacked
+        let last_partial_unacked = self
+            .last_partial_end
+            .is_some_and(|end| self.send.buffer.contains(end - 1));
+
+        !self.config.nodelay && last_partial_unacked
+    }
+
+    /// Returns true if the connection is corked and has data that isn't being sent, and there
+    /// isn't already a cork timer pending.
+    pub fn needs_cork_timer(&self) -> bool {
+        self.config.cork
+            && !self.cork_timer_pending
+            && self.send.buffer.next_not_transmitted(0).is_some()
+    }
+
+    /// A cork timer was started.
+    pub fn cork_timer_started(&mut self) {
+        self.cork_timer_pending = true;
+    }
+
+    /// The cork timer expired, so any held partial segment should be sent.
+    pub fn cork_timer_expired(&mut self) {
+        self.cork_timer_pending = false;
+        if self.config.cork && self.send.buffer.next_not_transmitted(0).is_some() {
+            self.cork_expired = true;
+        }
+    }
+
+    /// Returns true if we're delaying an acknowledgement, and there isn't already a delayed
+    /// acknowledgement timer pending.
+    pub fn needs_delayed_ack_timer(&self) -> bool {
+        self.ack_is_delayed && !self.delayed_ack_timer_pending
+    }
+
+    /// A delayed acknowledgement timer was started. Returns the time until it should expire.
+    pub fn delayed_ack_timer_started(&mut self) -> std::time::Duration {
+        self.delayed_ack_timer_pending = true;
+
+        // "quick acknowledgements" happen at the beginning of a connection, so that the peer gets
+        // the acknowledgements sooner and we don't throttle its sending rate
+        let timeout = self.config.delayed_ack.timeout(self.num_delayed_acks);
+        self.num_delayed_acks = self.num_delayed_acks.saturating_add(1);
+
+        timeout
+    }
+
+    /// The delayed acknowledgement timer expired, so any delayed acknowledgement should be sent.
+    pub fn delayed_ack_timer_expired(&mut self) {
+        self.delayed_ack_timer_pending = false;
+        self.send_delayed_ack();
+    }
+
+    /// Send any delayed acknowledgement now.
+    pub fn send_delayed_ack(&mut self) {
+        if self.ack_is_delayed {
+            self.need_to_ack = true;
+        }
+    }
+
     /// Returns true if we received a RST packet, or if we want to send a RST packet.
     pub fn is_reset(&self) -> bool {
         self.is_reset
@@ -90,6 +90,7 @@ mod buffer;
 mod connection;
 mod seq;
 mod states;
+mod timestamps;
 mod window_scaling;
 
 #[cfg(test)]
@@ -329,6 +330,81 @@ impl<X: Dependencies> TcpState<X> {
     pub fn local_remote_addrs(&self) -> Option<(SocketAddrV4, SocketAddrV4)> {
         self.0.as_ref().unwrap().local_remote_addrs()
     }
+
+    /// Change the [`TcpConfig::nodelay`] option. This has no effect if the socket is closed. A
+    /// listening socket's children that haven't been accepted yet aren't changed.
+    pub fn set_nodelay(&mut self, enable: bool) {
+        if let Some(config) = self.0.as_mut().unwrap().config_mut() {
+            config.nodelay(enable);
+        }
+    }
+
+    /// Change the [`TcpConfig::cork`] option. This has no effect if the socket is closed. A
+    /// listening socket's children that haven't been accepted yet aren't changed.
+    pub fn set_cork(&mut self, enable: bool) {
+        let state = self.0.as_mut().unwrap();
+
+        if let Some(config) = state.config_mut() {
+            config.cork(enable);
+        }
+
+        // data may already be waiting to be sent
+        state.start_cork_timer();
+    }
+
+    /// Change the [`TcpConfig::quickack`] option. If enabled, a delayed acknowledgement is sent
+    /// now. This has no effect if the socket is closed. A listening socket's children that haven't
+    /// been accepted yet aren't changed.
+    pub fn set_quickack(&mut self, enable: bool) {
+        let state = self.0.as_mut().unwrap();
+
+        if let Some(config) = state.config_mut() {
+            config.quickack(enable);
+        }
+
+        if enable {
+            state.send_delayed_ack();
+        }
+    }
+
+    /// The kind of state that the TCP is in. This is meant for reporting the state (for example in
+    /// diagnostics), and shouldn't be used to decide how to use the [`TcpState`].
+    pub fn kind(&self) -> TcpStateKind {
+        match self.0.as_ref().unwrap() {
+            TcpStateEnum::Init(_) => TcpStateKind::Init,
+            TcpStateEnum::Listen(_) => TcpStateKind::Listen,
+            TcpStateEnum::SynSent(_) => TcpStateKind::SynSent,
+            TcpStateEnum::SynReceived(_) => TcpStateKind::SynReceived,
+            TcpStateEnum::Established(_) => TcpStateKind::Established,
+            TcpStateEnum::FinWaitOne(_) => TcpStateKind::FinWaitOne,
+            TcpStateEnum::FinWaitTwo(_) => TcpStateKind::FinWaitTwo,
+            TcpStateEnum::Closing(_) => TcpStateKind::Closing,
+            TcpStateEnum::TimeWait(_) => TcpStateKind::TimeWait,
+            TcpStateEnum::CloseWait(_) => TcpStateKind::CloseWait,
+            TcpStateEnum::LastAck(_) => TcpStateKind::LastAck,
+            TcpStateEnum::Rst(_) => TcpStateKind::Rst,
+            TcpStateEnum::Closed(_) => TcpStateKind::Closed,
+        }
+    }
+}
+
+/// The kind of state that a [`TcpState`] is in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TcpStateKind {
+    Init,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWaitOne,
+    FinWaitTwo,
+    Closing,
+    TimeWait,
+    CloseWait,
+    LastAck,
+    /// Sending a RST packet before closing.
+    Rst,
+    Closed,
 }
 
 /// A macro that forwards an argument-less method to the inner type.
@@ -635,18 +711,103 @@ bitflags::bitflags! {
 #[derive(Copy, Clone, Debug)]
 pub struct TcpConfig {
     pub(crate) window_scaling_enabled: bool,
+    pub(crate) timestamps_enabled: bool,
+    pub(crate) time_wait_timeout: std::time::Duration,
+    pub(crate) nodelay: bool,
+    pub(crate) cork: bool,
+    pub(crate) quickack: bool,
+    pub(crate) delayed_ack: DelayedAckConfig,
 }
 
 impl TcpConfig {
     pub fn window_scaling(&mut self, enable: bool) {
         self.window_scaling_enabled = enable;
     }
+
+    /// Negotiate the timestamps option. Timestamps are echoed back to the peer, but aren't used to
+    /// measure the round-trip time or to reject old duplicate segments.
+    pub fn timestamps(&mut self, enable: bool) {
+        self.timestamps_enabled = enable;
+    }
+
+    /// The time that a connection stays in the "time-wait" state before closing.
+    pub fn time_wait_timeout(&mut self, timeout: std::time::Duration) {
+        self.time_wait_timeout = timeout;
+    }
+
+    /// Send partial segments without waiting for earlier partial segments to be acknowledged (like
+    /// `TCP_NODELAY`). If disabled, Nagle's algorithm is used.
+    pub fn nodelay(&mut self, enable: bool) {
+        self.nodelay = enable;
+    }
+
+    /// Hold partial segments for up to 200 ms (like `TCP_CORK`).
+    pub fn cork(&mut self, enable: bool) {
+        self.cork = enable;
+    }
+
+    /// Acknowledge received data without delay (like `TCP_QUICKACK`). Unlike Linux, this stays
+    /// enabled after data is sent.
+    pub fn quickack(&mut self, enable: bool) {
+        self.quickack = enable;
+    }
+
+    /// When acknowledgements of received data are delayed.
+    pub fn delayed_ack(&mut self, config: DelayedAckConfig) {
+        self.delayed_ack = config;
+    }
 }
 
 impl Default for TcpConfig {
     fn default() -> Self {
         Self {
             window_scaling_enabled: true,
+            timestamps_enabled: true,
+            // taken from /proc/sys/net/ipv4/tcp_fin_timeout
+            time_wait_timeout: std::time::Duration::from_secs(60),
+            // like linux, nagle's algorithm is used by default
+            nodelay: false,
+            cork: false,
+            quickack: false,
+            delayed_ack: DelayedAckConfig::default(),
+        }
+    }
+}
+
+/// When acknowledgements of received data are delayed. An acknowledgement is delayed in case it
+/// can acknowledge more data or be sent with data of our own.
+#[derive(Copy, Clone, Debug)]
+pub struct DelayedAckConfig {
+    /// The time that an acknowledgement is delayed once `quick_acks` acknowledgements have been
+    /// delayed.
+    pub timeout: std::time::Duration,
+    /// The time that each of the first `quick_acks` acknowledgements is delayed.
+    pub quick_timeout: std::time::Duration,
+    pub quick_acks: u32,
+    /// The number of received payload bytes that can be unacknowledged before an acknowledgement
+    /// is sent without delay, or unlimited if `None`.
+    pub max_unacked_bytes: Option<u64>,
+}
+
+impl DelayedAckConfig {
+    /// The time that an acknowledgement is delayed, given the number of acknowledgements that the
+    /// connection has already delayed.
+    pub(crate) fn timeout(&self, num_delayed: u32) -> std::time::Duration {
+        if num_delayed < self.quick_acks {
+            self.quick_timeout
+        } else {
+            self.timeout
+        }
+    }
+}
+
+impl Default for DelayedAckConfig {
+    fn default() -> Self {
+        Self {
+            timeout: std::time::Duration::from_millis(5),
+            quick_timeout: std::time::Duration::from_millis(1),
+            quick_acks: 1000,
+            max_unacked_bytes: None,
         }
     }
 }
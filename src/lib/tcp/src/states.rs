@@ -988,6 +988,8 @@ impl<X: Dependencies> TcpStateTrait<X> for SynReceivedState<X> {
             return (self.into(), Err(e));
         }
 
+        start_delayed_ack_timer(&self.common, &mut self.connection);
+
         // if the connection was reset
         if self.connection.is_reset() {
             if header.flags.contains(TcpFlags::RST) {
@@ -1099,6 +1101,7 @@ impl<X: Dependencies> TcpStateTrait<X> for EstablishedState<X> {
         len: usize,
     ) -> (TcpStateEnum<X>, Result<usize, SendError>) {
         let rv = self.connection.send(reader, len);
+        start_cork_timer(&self.common, &mut self.connection);
         (self.into(), rv)
     }
 
@@ -1126,6 +1129,8 @@ impl<X: Dependencies> TcpStateTrait<X> for EstablishedState<X> {
             return (self.into(), Err(e));
         }
 
+        start_delayed_ack_timer(&self.common, &mut self.connection);
+
         // if the connection was reset
         if self.connection.is_reset() {
             if header.flags.contains(TcpFlags::RST) {
@@ -1256,6 +1261,8 @@ impl<X: Dependencies> TcpStateTrait<X> for FinWaitOneState<X> {
             return (self.into(), Err(e));
         }
 
+        start_delayed_ack_timer(&self.common, &mut self.connection);
+
         // if the connection was reset
         if self.connection.is_reset() {
             if header.flags.contains(TcpFlags::RST) {
@@ -1398,6 +1405,8 @@ impl<X: Dependencies> TcpStateTrait<X> for FinWaitTwoState<X> {
             return (self.into(), Err(e));
         }
 
+        start_delayed_ack_timer(&self.common, &mut self.connection);
+
         // if the connection was reset
         if self.connection.is_reset() {
             if header.flags.contains(TcpFlags::RST) {
@@ -1603,8 +1612,8 @@ impl<X: Dependencies> TimeWaitState<X> {
     fn new(common: Common<X>, connection: Connection<X::Instant>) -> Self {
         let state = TimeWaitState { common, connection };
 
-        // taken from /proc/sys/net/ipv4/tcp_fin_timeout
-        let timeout = X::Duration::from_secs(60);
+        let timeout = state.connection.config.time_wait_timeout;
+        let timeout = X::Duration::from_nanos(timeout.as_nanos().try_into().unwrap());
 
         // if still in the "time-wait" state after the timeout, close it
         let timeout = state.common.current_time() + timeout;
@@ -1807,6 +1816,7 @@ impl<X: Dependencies> TcpStateTrait<X> for CloseWaitState<X> {
         len: usize,
     ) -> (TcpStateEnum<X>, Result<usize, SendError>) {
         let rv = self.connection.send(reader, len);
+        start_cork_timer(&self.common, &mut self.connection);
         (self.into(), rv)
     }
 
@@ -2281,6 +2291,95 @@ impl<X: Dependencies> TcpStateTrait<X> for ClosedState<X> {
     }
 }
 
+impl<X: Dependencies> TcpStateEnum<X> {
+    /// The config of this state, or `None` if the state is closed or closing.
+    pub(crate) fn config_mut(&mut self) -> Option<&mut TcpConfig> {
+        match self {
+            Self::Init(x) => Some(&mut x.config),
+            Self::Listen(x) => Some(&mut x.config),
+            Self::SynSent(x) => Some(&mut x.connection.config),
+            Self::SynReceived(x) => Some(&mut x.connection.config),
+            Self::Established(x) => Some(&mut x.connection.config),
+            Self::FinWaitOne(x) => Some(&mut x.connection.config),
+            Self::FinWaitTwo(x) => Some(&mut x.connection.config),
+            Self::Closing(x) => Some(&mut x.connection.config),
+            Self::TimeWait(x) => Some(&mut x.connection.config),
+            Self::CloseWait(x) => Some(&mut x.connection.config),
+            Self::LastAck(x) => Some(&mut x.connection.config),
+            Self::Rst(_) | Self::Closed(_) => None,
+        }
+    }
+
+    /// The common state and connection of this state, or `None` if the state has no connection.
+    fn common_and_connection_mut(&mut self) -> Option<(&Common<X>, &mut Connection<X::Instant>)> {
+        match self {
+            Self::SynSent(x) => Some((&x.common, &mut x.connection)),
+            Self::SynReceived(x) => Some((&x.common, &mut x.connection)),
+            Self::Established(x) => Some((&x.common, &mut x.connection)),
+            Self::FinWaitOne(x) => Some((&x.common, &mut x.connection)),
+            Self::FinWaitTwo(x) => Some((&x.common, &mut x.connection)),
+            Self::Closing(x) => Some((&x.common, &mut x.connection)),
+            Self::TimeWait(x) => Some((&x.common, &mut x.connection)),
+            Self::CloseWait(x) => Some((&x.common, &mut x.connection)),
+            Self::LastAck(x) => Some((&x.common, &mut x.connection)),
+            Self::Init(_) | Self::Listen(_) | Self::Rst(_) | Self::Closed(_) => None,
+        }
+    }
+
+    /// Start a cork timer if the connection is corked and is holding data.
+    pub(crate) fn start_cork_timer(&mut self) {
+        if let Some((common, connection)) = self.common_and_connection_mut() {
+            start_cork_timer(common, connection);
+        }
+    }
+
+    /// Send any delayed acknowledgement now.
+    pub(crate) fn send_delayed_ack(&mut self) {
+        if let Some((_common, connection)) = self.common_and_connection_mut() {
+            connection.send_delayed_ack();
+        }
+    }
+}
+
+/// If the connection is delaying an acknowledgement, start a timer so that the acknowledgement is
+/// sent later.
+fn start_delayed_ack_timer<X: Dependencies>(
+    common: &Common<X>,
+    connection: &mut Connection<X::Instant>,
+) {
+    if !connection.needs_delayed_ack_timer() {
+        return;
+    }
+
+    let timeout = connection.delayed_ack_timer_started();
+    let timeout = X::Duration::from_nanos(timeout.as_nanos().try_into().unwrap());
+
+    common.register_timer(common.current_time() + timeout, |mut state| {
+        if let Some((_common, connection)) = state.common_and_connection_mut() {
+            connection.delayed_ack_timer_expired();
+        }
+        state
+    });
+}
+
+/// If the connection is corked and is holding data, start a timer so that the data isn't held for
+/// more than 200 ms (see `TCP_CORK` in tcp(7)).
+fn start_cork_timer<X: Dependencies>(common: &Common<X>, connection: &mut Connection<X::Instant>) {
+    if !connection.needs_cork_timer() {
+        return;
+    }
+
+    connection.cork_timer_started();
+
+    let timeout = common.current_time() + X::Duration::from_millis(200);
+    common.register_timer(timeout, |mut state| {
+        if let Some((_common, connection)) = state.common_and_connection_mut() {
+            connection.cork_timer_expired();
+        }
+        state
+    });
+}
+
 /// Reset the connection, get the resulting RST packet, and return a new `RstState` that will send
 /// this RST packet.
 fn reset_connection<X: Dependencies>(
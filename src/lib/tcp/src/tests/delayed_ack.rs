@@ -0,0 +1,147 @@
+//! Test when acknowledgements of received data are delayed.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use crate::tests::{establish_helper, establish_helper_with_config, Host, Scheduler, TcpSocket};
+use crate::{DelayedAckConfig, Ipv4Header, TcpConfig, TcpFlags, TcpHeader};
+
+/// Send `data` to the socket, where `seq` is the sequence number of its first byte (the peer's SYN
+/// has sequence number 0).
+fn send_data(tcp: &Rc<RefCell<TcpSocket>>, host: &Host, seq: u32, data: &'static [u8]) {
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: host.ip_addr,
+        },
+        flags: TcpFlags::ACK,
+        src_port: 20,
+        dst_port: 10,
+        seq,
+        ack: 1,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    tcp.borrow_mut()
+        .push_in_packet(&header, Bytes::from(data).into());
+}
+
+#[test]
+fn test_delayed_ack() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let tcp = establish_helper(&scheduler, &mut host);
+
+    // the acknowledgement isn't sent right away
+    send_data(&tcp, &host, 1, b"hello");
+    assert!(scheduler.pop_packet().is_none());
+
+    // data received before the timer expires is acknowledged by the same acknowledgement
+    send_data(&tcp, &host, 6, b"world");
+    assert!(scheduler.pop_packet().is_none());
+
+    scheduler.advance(Duration::from_millis(1));
+    let (header, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.flags, TcpFlags::ACK);
+    assert_eq!(header.ack, 11);
+    assert!(payload.is_empty());
+    assert!(scheduler.pop_packet().is_none());
+}
+
+#[test]
+fn test_delayed_ack_with_data() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let tcp = establish_helper(&scheduler, &mut host);
+
+    send_data(&tcp, &host, 1, b"hello");
+    assert!(scheduler.pop_packet().is_none());
+
+    // the acknowledgement is sent with our data
+    TcpSocket::sendmsg(&tcp, &b"a"[..], 1).unwrap();
+    let (header, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.ack, 6);
+    assert_eq!(payload.concat()[..], b"a"[..]);
+
+    // so there's nothing left to acknowledge when the timer expires
+    scheduler.advance(Duration::from_millis(1));
+    assert!(scheduler.pop_packet().is_none());
+}
+
+#[test]
+fn test_delayed_ack_timeouts() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let mut config = TcpConfig::default();
+    config.delayed_ack(DelayedAckConfig {
+        timeout: Duration::from_millis(5),
+        quick_timeout: Duration::from_millis(1),
+        quick_acks: 1,
+        max_unacked_bytes: None,
+    });
+    let tcp = establish_helper_with_config(&scheduler, &mut host, config);
+
+    // the first acknowledgement is a quick acknowledgement
+    send_data(&tcp, &host, 1, b"hello");
+    scheduler.advance(Duration::from_millis(1));
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.ack, 6);
+
+    // and later acknowledgements are delayed longer
+    send_data(&tcp, &host, 6, b"world");
+    scheduler.advance(Duration::from_millis(4));
+    assert!(scheduler.pop_packet().is_none());
+    scheduler.advance(Duration::from_millis(1));
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.ack, 11);
+}
+
+#[test]
+fn test_delayed_ack_max_unacked_bytes() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let mut config = TcpConfig::default();
+    config.delayed_ack(DelayedAckConfig {
+        max_unacked_bytes: Some(8),
+        ..Default::default()
+    });
+    let tcp = establish_helper_with_config(&scheduler, &mut host, config);
+
+    send_data(&tcp, &host, 1, b"hello");
+    assert!(scheduler.pop_packet().is_none());
+
+    // too much data is unacknowledged, so it's acknowledged right away
+    send_data(&tcp, &host, 6, b"world");
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.ack, 11);
+}
+
+#[test]
+fn test_quickack() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let tcp = establish_helper(&scheduler, &mut host);
+
+    // enabling `TCP_QUICKACK` sends a delayed acknowledgement
+    send_data(&tcp, &host, 1, b"hello");
+    assert!(scheduler.pop_packet().is_none());
+    tcp.borrow_mut().set_quickack(true);
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.ack, 6);
+
+    // and later data is acknowledged right away
+    send_data(&tcp, &host, 6, b"world");
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.ack, 11);
+}
@@ -5,7 +5,10 @@
 // TODO: ideally remove this
 #![allow(dead_code)]
 
+mod delayed_ack;
+mod nagle;
 mod send_recv;
+mod timestamps;
 mod transitions;
 mod window_scale;
 
@@ -451,6 +454,18 @@ impl TcpSocket {
             .unwrap();
     }
 
+    pub fn set_nodelay(&mut self, enable: bool) {
+        self.with_tcp_state(|s| s.set_nodelay(enable))
+    }
+
+    pub fn set_cork(&mut self, enable: bool) {
+        self.with_tcp_state(|s| s.set_cork(enable))
+    }
+
+    pub fn set_quickack(&mut self, enable: bool) {
+        self.with_tcp_state(|s| s.set_quickack(enable))
+    }
+
     pub fn close(&mut self) -> Result<(), Errno> {
         // we don't expect close() to ever have an error
         self.with_tcp_state(|state| state.close()).unwrap();
@@ -832,12 +847,21 @@ fn verify_send_sync() {
 /// Returns an established socket that is bound to the host's IP at port 10 and connected to
 /// 5.6.7.8:20.
 fn establish_helper(scheduler: &Scheduler, host: &mut Host) -> Rc<RefCell<TcpSocket>> {
+    establish_helper_with_config(scheduler, host, TcpConfig::default())
+}
+
+/// Like [`establish_helper`], but the socket uses the given config.
+fn establish_helper_with_config(
+    scheduler: &Scheduler,
+    host: &mut Host,
+    config: TcpConfig,
+) -> Rc<RefCell<TcpSocket>> {
     /// Helper to get the state from a socket.
     fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
         Ref::map(tcp.borrow(), |x| x.tcp_state())
     }
 
-    let tcp = TcpSocket::new(scheduler, TcpConfig::default());
+    let tcp = TcpSocket::new(scheduler, config);
     assert!(s(&tcp).as_init().is_some());
 
     TcpSocket::bind(&tcp, SocketAddrV4::new(host.ip_addr, 10), host).unwrap();
@@ -0,0 +1,146 @@
+//! Test when partial segments are held by Nagle's algorithm and by corking.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::tests::{establish_helper_with_config, Host, Scheduler, TcpSocket};
+use crate::{Ipv4Header, Payload, TcpConfig, TcpFlags, TcpHeader};
+
+/// Acknowledge the payload bytes up to `ack` (the SYN has sequence number 0).
+fn ack(tcp: &Rc<RefCell<TcpSocket>>, host: &Host, ack: u32) {
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: host.ip_addr,
+        },
+        flags: TcpFlags::ACK,
+        src_port: 20,
+        dst_port: 10,
+        seq: 1,
+        ack,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    tcp.borrow_mut().push_in_packet(&header, Payload::default());
+}
+
+#[test]
+fn test_nodelay() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let mut config = TcpConfig::default();
+    config.nodelay(true);
+    let tcp = establish_helper_with_config(&scheduler, &mut host, config);
+
+    TcpSocket::sendmsg(&tcp, &b"a"[..], 1).unwrap();
+    TcpSocket::sendmsg(&tcp, &b"b"[..], 1).unwrap();
+
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"a"[..]);
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"b"[..]);
+}
+
+#[test]
+fn test_nagle() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    // like linux, nagle's algorithm is used by default
+    let tcp = establish_helper_with_config(&scheduler, &mut host, TcpConfig::default());
+
+    // the second and third writes are held until the first is acknowledged
+    TcpSocket::sendmsg(&tcp, &b"a"[..], 1).unwrap();
+    TcpSocket::sendmsg(&tcp, &b"b"[..], 1).unwrap();
+    TcpSocket::sendmsg(&tcp, &b"c"[..], 1).unwrap();
+
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"a"[..]);
+    assert!(scheduler.pop_packet().is_none());
+
+    ack(&tcp, &host, 2);
+    let (header, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(header.seq, 2);
+    assert_eq!(payload.concat()[..], b"bc"[..]);
+    assert!(scheduler.pop_packet().is_none());
+
+    // setting `TCP_NODELAY` sends held data
+    TcpSocket::sendmsg(&tcp, &b"d"[..], 1).unwrap();
+    assert!(scheduler.pop_packet().is_none());
+    tcp.borrow_mut().set_nodelay(true);
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"d"[..]);
+}
+
+#[test]
+fn test_nagle_full_segment() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let tcp = establish_helper_with_config(&scheduler, &mut host, TcpConfig::default());
+
+    TcpSocket::sendmsg(&tcp, &b"a"[..], 1).unwrap();
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"a"[..]);
+
+    // full segments aren't held, but the partial segment after them is
+    let data = vec![0u8; 2000];
+    TcpSocket::sendmsg(&tcp, &data[..], data.len()).unwrap();
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.len(), 1500);
+    assert!(scheduler.pop_packet().is_none());
+
+    ack(&tcp, &host, 2);
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.len(), 500);
+}
+
+#[test]
+fn test_cork() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let tcp = establish_helper_with_config(&scheduler, &mut host, TcpConfig::default());
+    tcp.borrow_mut().set_cork(true);
+
+    TcpSocket::sendmsg(&tcp, &b"a"[..], 1).unwrap();
+    TcpSocket::sendmsg(&tcp, &b"b"[..], 1).unwrap();
+    scheduler.advance(Duration::from_millis(100));
+    assert!(scheduler.pop_packet().is_none());
+
+    // both writes are sent in a single segment once the socket is uncorked
+    tcp.borrow_mut().set_cork(false);
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"ab"[..]);
+}
+
+#[test]
+fn test_cork_max_delay() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    let mut config = TcpConfig::default();
+    config.cork(true);
+    let tcp = establish_helper_with_config(&scheduler, &mut host, config);
+
+    TcpSocket::sendmsg(&tcp, &b"a"[..], 1).unwrap();
+    scheduler.advance(Duration::from_millis(199));
+    assert!(scheduler.pop_packet().is_none());
+
+    // the held data is sent after 200 ms
+    scheduler.advance(Duration::from_millis(1));
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"a"[..]);
+
+    // and the next write is held again
+    TcpSocket::sendmsg(&tcp, &b"b"[..], 1).unwrap();
+    assert!(scheduler.pop_packet().is_none());
+    scheduler.advance(Duration::from_millis(200));
+    let (_, payload) = scheduler.pop_packet().unwrap();
+    assert_eq!(payload.concat()[..], b"b"[..]);
+}
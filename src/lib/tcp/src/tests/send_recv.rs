@@ -2,11 +2,14 @@
 
 use std::cell::{Ref, RefCell};
 use std::rc::Rc;
+use std::time::Duration;
 
 use bytes::Bytes;
 
-use crate::tests::{establish_helper, Host, Scheduler, TcpSocket, TestEnvState};
-use crate::{Ipv4Header, Payload, Shutdown, TcpFlags, TcpHeader, TcpState};
+use crate::tests::{
+    establish_helper, establish_helper_with_config, Host, Scheduler, TcpSocket, TestEnvState,
+};
+use crate::{Ipv4Header, Payload, Shutdown, TcpConfig, TcpFlags, TcpHeader, TcpState};
 
 #[test]
 fn test_send_recv() {
@@ -118,6 +121,9 @@ fn test_ack_with_empty_usable_send_window() {
     tcp.borrow_mut()
         .push_in_packet(&header, Bytes::from(&b"world"[..]).into());
 
+    // the acknowledgement is delayed
+    scheduler.advance(Duration::from_millis(1));
+
     // check the packet sent by the socket
     let (header, payload) = scheduler.pop_packet().unwrap();
 
@@ -141,8 +147,10 @@ fn test_coalesce_send() {
         Ref::map(tcp.borrow(), |x| x.tcp_state())
     }
 
-    // get an established tcp socket
-    let tcp = establish_helper(&scheduler, &mut host);
+    // get an established tcp socket that doesn't use nagle's algorithm
+    let mut config = TcpConfig::default();
+    config.nodelay(true);
+    let tcp = establish_helper_with_config(&scheduler, &mut host, config);
 
     // PART 1: We test with `collect_packets == true`, which will immediately pop a packet as soon
     // as its ready. We expect two `sendmsg` calls to result in two packets.
@@ -264,7 +272,8 @@ fn test_close_with_non_empty_recv_buffer() {
     tcp.borrow_mut()
         .push_in_packet(&header, Bytes::from(&b"hello"[..]).into());
 
-    // check that our payload packet was acknowledged
+    // check that our payload packet was acknowledged (the acknowledgement is delayed)
+    scheduler.advance(Duration::from_millis(1));
     let (header, _) = scheduler.pop_packet().unwrap();
     assert!(header.flags.contains(TcpFlags::ACK));
 
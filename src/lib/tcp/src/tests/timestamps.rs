@@ -0,0 +1,139 @@
+//! Test different timestamps configurations.
+
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+
+use crate::tests::{Host, Scheduler, TcpSocket, TestEnvState};
+use crate::{Ipv4Header, Payload, TcpConfig, TcpFlags, TcpHeader, TcpState};
+
+/// Helper to get the state from a socket.
+fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
+    Ref::map(tcp.borrow(), |x| x.tcp_state())
+}
+
+/// Connect a socket with the given config, and reply to its SYN with a SYN+ACK that has the given
+/// timestamp. Returns the SYN and the socket.
+fn connect(
+    scheduler: &Scheduler,
+    config: TcpConfig,
+    peer_timestamp: Option<u32>,
+) -> (TcpHeader, Rc<RefCell<TcpSocket>>) {
+    let mut host = Host::new();
+
+    let tcp = TcpSocket::new(scheduler, config);
+    TcpSocket::connect(&tcp, "5.6.7.8:10".parse().unwrap(), &mut host).unwrap();
+    assert!(s(&tcp).as_syn_sent().is_some());
+
+    let (syn_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(syn_header.flags, TcpFlags::SYN);
+
+    // get the autobind address of the socket
+    let tcp_bind_addr = syn_header.src();
+
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: *tcp_bind_addr.ip(),
+        },
+        flags: TcpFlags::SYN | TcpFlags::ACK,
+        src_port: 10,
+        dst_port: tcp_bind_addr.port(),
+        seq: 0,
+        ack: 1,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: peer_timestamp,
+        timestamp_echo: peer_timestamp.and(syn_header.timestamp),
+    };
+    tcp.borrow_mut().push_in_packet(&header, Payload::default());
+    assert!(s(&tcp).as_established().is_some());
+
+    (syn_header, tcp)
+}
+
+#[test]
+fn test_timestamps() {
+    let scheduler = Scheduler::new();
+
+    let mut config = TcpConfig::default();
+    config.timestamps(true);
+    // acknowledge data right away
+    config.quickack(true);
+
+    // the SYN has a timestamp, but nothing to echo
+    let (syn_header, tcp) = connect(&scheduler, config, Some(100));
+    assert_eq!(syn_header.timestamp, Some(0));
+    assert_eq!(syn_header.timestamp_echo, Some(0));
+
+    // the ACK echoes the peer's timestamp
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::ACK);
+    assert_eq!(response_header.timestamp, Some(0));
+    assert_eq!(response_header.timestamp_echo, Some(100));
+
+    // the timestamp clock ticks every millisecond
+    scheduler.advance(std::time::Duration::from_millis(5));
+
+    // send data with a newer timestamp
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: response_header.ip.dst,
+            dst: response_header.ip.src,
+        },
+        flags: TcpFlags::ACK,
+        src_port: response_header.dst_port,
+        dst_port: response_header.src_port,
+        seq: 1,
+        ack: 1,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: Some(107),
+        timestamp_echo: Some(0),
+    };
+    tcp.borrow_mut()
+        .push_in_packet(&header, Payload(vec![b"hello"[..].into()]));
+
+    // the ACK has a new timestamp and echoes the newer timestamp
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::ACK);
+    assert_eq!(response_header.ack, 6);
+    assert_eq!(response_header.timestamp, Some(5));
+    assert_eq!(response_header.timestamp_echo, Some(107));
+}
+
+#[test]
+fn test_peer_no_timestamps() {
+    let scheduler = Scheduler::new();
+
+    let mut config = TcpConfig::default();
+    config.timestamps(true);
+
+    let (syn_header, _tcp) = connect(&scheduler, config, None);
+    assert!(syn_header.timestamp.is_some());
+
+    // the peer didn't send the option, so it isn't used
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::ACK);
+    assert_eq!(response_header.timestamp, None);
+    assert_eq!(response_header.timestamp_echo, None);
+}
+
+#[test]
+fn test_local_no_timestamps() {
+    let scheduler = Scheduler::new();
+
+    let mut config = TcpConfig::default();
+    config.timestamps(false);
+
+    let (syn_header, _tcp) = connect(&scheduler, config, Some(100));
+    assert_eq!(syn_header.timestamp, None);
+    assert_eq!(syn_header.timestamp_echo, None);
+
+    // the peer sent the option anyway, but it isn't used
+    let (response_header, _) = scheduler.pop_packet().unwrap();
+    assert_eq!(response_header.flags, TcpFlags::ACK);
+    assert_eq!(response_header.timestamp, None);
+    assert_eq!(response_header.timestamp_echo, None);
+}
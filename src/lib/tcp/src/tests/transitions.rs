@@ -4,8 +4,10 @@ use std::cell::{Ref, RefCell};
 use std::rc::Rc;
 
 use crate::tests::util::time::Duration;
-use crate::tests::{establish_helper, Errno, Host, Scheduler, TcpSocket, TestEnvState};
-use crate::{Ipv4Header, Payload, TcpConfig, TcpFlags, TcpHeader, TcpState};
+use crate::tests::{
+    establish_helper, establish_helper_with_config, Errno, Host, Scheduler, TcpSocket, TestEnvState,
+};
+use crate::{Ipv4Header, Payload, TcpConfig, TcpFlags, TcpHeader, TcpState, TcpStateKind};
 
 #[test]
 fn test_close() {
@@ -14,9 +16,11 @@ fn test_close() {
     let tcp = TcpSocket::new(&scheduler, TcpConfig::default());
     let mut tcp_ref = tcp.borrow_mut();
     assert!(tcp_ref.tcp_state().as_init().is_some());
+    assert_eq!(tcp_ref.tcp_state().kind(), TcpStateKind::Init);
 
     tcp_ref.close().unwrap();
     assert!(tcp_ref.tcp_state().as_closed().is_some());
+    assert_eq!(tcp_ref.tcp_state().kind(), TcpStateKind::Closed);
 }
 
 #[test]
@@ -32,6 +36,7 @@ fn test_listen() {
         tcp.borrow().tcp_state().as_listen().unwrap().max_backlog,
         11
     );
+    assert_eq!(tcp.borrow().tcp_state().kind(), TcpStateKind::Listen);
 
     // we can update the backlog while already in the "listen" state
     TcpSocket::listen(&tcp, &mut host, 2).unwrap();
@@ -466,6 +471,77 @@ fn test_active_close_1() {
     assert!(s(&tcp).as_closed().is_some());
 }
 
+#[test]
+fn test_active_close_time_wait_timeout() {
+    let scheduler = Scheduler::new();
+    let mut host = Host::new();
+
+    /// Helper to get the state from a socket.
+    fn s(tcp: &Rc<RefCell<TcpSocket>>) -> Ref<TcpState<TestEnvState>> {
+        Ref::map(tcp.borrow(), |x| x.tcp_state())
+    }
+
+    let mut config = TcpConfig::default();
+    config.time_wait_timeout(std::time::Duration::from_secs(5));
+
+    // get an established tcp socket
+    let tcp = establish_helper_with_config(&scheduler, &mut host, config);
+
+    // close the socket (move tcp to the "fin-wait-one" state)
+    tcp.borrow_mut().close().unwrap();
+    assert!(s(&tcp).as_fin_wait_one().is_some());
+    let (header, _) = scheduler.pop_packet().unwrap();
+    assert!(header.flags.contains(TcpFlags::FIN));
+
+    // send an ACK (move tcp to the "fin-wait-two" state)
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: "1.2.3.4".parse().unwrap(),
+        },
+        flags: TcpFlags::ACK,
+        src_port: 20,
+        dst_port: 10,
+        seq: 1,
+        ack: 2,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    tcp.borrow_mut().push_in_packet(&header, Payload::default());
+    assert!(s(&tcp).as_fin_wait_two().is_some());
+
+    // send a FIN (move tcp to the "time-wait" state)
+    let header = TcpHeader {
+        ip: Ipv4Header {
+            src: "5.6.7.8".parse().unwrap(),
+            dst: "1.2.3.4".parse().unwrap(),
+        },
+        flags: TcpFlags::FIN,
+        src_port: 20,
+        dst_port: 10,
+        seq: 1,
+        ack: 2,
+        window_size: 10000,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    };
+    tcp.borrow_mut().push_in_packet(&header, Payload::default());
+    assert!(s(&tcp).as_time_wait().is_some());
+
+    // at 4 seconds, the socket is still in time-wait
+    scheduler.advance(Duration::from_secs(4));
+    assert!(s(&tcp).as_time_wait().is_some());
+
+    // at 6 seconds, the configured timeout has passed and the socket is now closed
+    scheduler.advance(Duration::from_secs(2));
+    assert!(s(&tcp).as_closed().is_some());
+}
+
 #[test]
 fn test_active_close_2() {
     let scheduler = Scheduler::new();
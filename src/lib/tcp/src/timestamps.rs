@@ -0,0 +1,139 @@
+/// An object to encode the timestamps option logic. It asserts that no operations have been taken
+/// out-of-order.
+///
+/// Timestamps are negotiated and echoed back to the peer as described in RFC 7323, but they aren't
+/// used to measure the round-trip time or to reject old duplicate segments ("PAWS").
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Timestamps {
+    /// Whether we sent a timestamps option in our SYN packet, if we've sent it.
+    sent_syn: Option<bool>,
+    /// Whether the peer sent a timestamps option in its SYN packet, if we've received it.
+    received_syn: Option<bool>,
+    /// The most recent timestamp received from the peer ("TS.Recent" in RFC 7323).
+    recent: u32,
+    disabled: bool,
+}
+
+impl Timestamps {
+    pub fn new() -> Self {
+        Self {
+            sent_syn: None,
+            received_syn: None,
+            recent: 0,
+            disabled: false,
+        }
+    }
+
+    /// Disable timestamps. This will ensure that a timestamps option is not sent in a SYN packet.
+    pub fn disable(&mut self) {
+        // tried to disable timestamps after sending the SYN
+        assert!(self.sent_syn.is_none());
+        self.disabled = true;
+    }
+
+    /// Checks if it's valid for an outward SYN packet to contain a timestamps option.
+    pub fn can_send_in_syn(&self) -> bool {
+        // RFC 7323 3.2.:
+        // > A TCP MAY send the TSopt in an initial <SYN> segment (i.e., segment containing a SYN
+        // > bit and no ACK bit), and MAY send a TSopt in <SYN,ACK> only if it received a TSopt in
+        // > the initial <SYN> segment for the connection.
+        !self.disabled && self.received_syn != Some(false)
+    }
+
+    /// A SYN packet was sent, with or without a timestamps option.
+    pub fn sent_syn(&mut self, sent_option: bool) {
+        // why did we send the SYN twice?
+        assert!(self.sent_syn.is_none());
+
+        if sent_option {
+            assert!(self.can_send_in_syn());
+        }
+
+        self.sent_syn = Some(sent_option);
+    }
+
+    /// A SYN packet was received with the given timestamp.
+    pub fn received_syn(&mut self, timestamp: Option<u32>) {
+        // why did we receive the SYN twice?
+        assert!(self.received_syn.is_none());
+
+        self.received_syn = Some(timestamp.is_some());
+        if let Some(timestamp) = timestamp {
+            self.recent = timestamp;
+        }
+    }
+
+    /// A non-SYN packet was received with the given timestamp.
+    pub fn received(&mut self, timestamp: Option<u32>) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        // RFC 7323 4.3. only updates "TS.Recent" for segments that aren't older than the most
+        // recent segment; timestamps wrap, so compare them like sequence numbers
+        if let Some(timestamp) = timestamp {
+            if timestamp.wrapping_sub(self.recent) as i32 >= 0 {
+                self.recent = timestamp;
+            }
+        }
+    }
+
+    /// Are timestamps sent in non-SYN packets? This is only true if both SYN packets contained a
+    /// timestamps option.
+    pub fn is_enabled(&self) -> bool {
+        self.sent_syn == Some(true) && self.received_syn == Some(true)
+    }
+
+    /// The timestamp to echo back to the peer. This is 0 if we haven't received a timestamp.
+    pub fn echo(&self) -> u32 {
+        self.recent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiation() {
+        // both SYN packets contained the option
+        let mut ts = Timestamps::new();
+        assert!(ts.can_send_in_syn());
+        ts.sent_syn(true);
+        assert!(!ts.is_enabled());
+        ts.received_syn(Some(5));
+        assert!(ts.is_enabled());
+        assert_eq!(ts.echo(), 5);
+
+        // the peer's SYN didn't contain the option, so we can't send it in the SYN+ACK
+        let mut ts = Timestamps::new();
+        ts.received_syn(None);
+        assert!(!ts.can_send_in_syn());
+        ts.sent_syn(false);
+        assert!(!ts.is_enabled());
+
+        // disabled
+        let mut ts = Timestamps::new();
+        ts.disable();
+        ts.received_syn(Some(5));
+        assert!(!ts.can_send_in_syn());
+        ts.sent_syn(false);
+        assert!(!ts.is_enabled());
+    }
+
+    #[test]
+    fn test_received() {
+        let mut ts = Timestamps::new();
+        ts.sent_syn(true);
+        ts.received_syn(Some(u32::MAX - 1));
+
+        // the timestamp wraps
+        ts.received(Some(2));
+        assert_eq!(ts.echo(), 2);
+
+        // older timestamps and packets without a timestamp are ignored
+        ts.received(Some(u32::MAX));
+        ts.received(None);
+        assert_eq!(ts.echo(), 2);
+    }
+}
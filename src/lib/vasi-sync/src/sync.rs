@@ -284,6 +284,11 @@ impl<T> UnsafeCell<T> {
     pub fn untracked_get(&self) -> *mut T {
         self.0.get()
     }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
 }
 #[cfg(loom)]
 #[derive(Debug)]
@@ -302,6 +307,10 @@ impl<T> UnsafeCell<T> {
     pub fn get(&self) -> ConstPtr<T> {
         self.0.get()
     }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
 }
 
 /// Lets us clear global state in between loom iterations, in loom tests.
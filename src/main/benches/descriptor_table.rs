@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use shadow_rs::host::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
+use shadow_rs::host::descriptor::eventfd::EventFd;
+use shadow_rs::host::descriptor::{CompatFile, Descriptor, File, FileStatus, OpenFile};
+
+// Roughly the number of concurrent connections that a simulated load balancer might have open.
+const NUM_DESCRIPTORS: u32 = 200_000;
+
+fn new_descriptor() -> Descriptor {
+    let eventfd = EventFd::new(0, false, FileStatus::empty());
+    let file = File::EventFd(Arc::new(AtomicRefCell::new(eventfd)));
+    Descriptor::new(CompatFile::New(OpenFile::new(file)))
+}
+
+fn filled_table(desc: &Descriptor) -> DescriptorTable {
+    let mut table = DescriptorTable::new();
+    for _ in 0..NUM_DESCRIPTORS {
+        table.register_descriptor(desc.clone()).unwrap();
+    }
+    table
+}
+
+fn handle(fd: u32) -> DescriptorHandle {
+    DescriptorHandle::new(fd).unwrap()
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let desc = new_descriptor();
+
+    // Open many descriptors in a new table.
+    c.bench_function("descriptor_table_register", |b| {
+        b.iter_batched(
+            DescriptorTable::new,
+            |mut table| {
+                for _ in 0..NUM_DESCRIPTORS {
+                    table.register_descriptor(desc.clone()).unwrap();
+                }
+                table
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    // Look up descriptors in a full table, in a scattered order.
+    let table = filled_table(&desc);
+    c.bench_function("descriptor_table_get", |b| {
+        b.iter(|| {
+            let mut fd: u32 = 0;
+            for _ in 0..NUM_DESCRIPTORS {
+                fd = (fd + 7919) % NUM_DESCRIPTORS;
+                assert!(table.get(handle(fd)).is_some());
+            }
+        })
+    });
+
+    // Close and reopen descriptors in a full table, so that every new descriptor needs to find
+    // the lowest available fd.
+    let mut table = filled_table(&desc);
+    c.bench_function("descriptor_table_churn", |b| {
+        b.iter(|| {
+            let mut fd: u32 = 0;
+            for _ in 0..1000 {
+                fd = (fd + 7919) % NUM_DESCRIPTORS;
+                let old = table.deregister_descriptor(handle(fd)).unwrap();
+                assert_eq!(table.register_descriptor(old).unwrap(), handle(fd));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -14,7 +14,6 @@ fn run_cbindgen(build_common: &ShadowBuildCommon) {
             "EmulatedTime".into(),
             "SimulationTime".into(),
             "StatusListener".into(),
-            "NetworkInterface".into(),
             "Tsc".into(),
             // We define manually with varargs
             "thread_nativeSyscall".into(),
@@ -56,7 +55,6 @@ fn run_cbindgen(build_common: &ShadowBuildCommon) {
             "main/host/descriptor/descriptor_types.h".into(),
             "main/host/descriptor/tcp.h".into(),
             "main/host/futex_table.h".into(),
-            "main/host/network/network_interface.h".into(),
             "main/host/protocol.h".into(),
             "main/host/status_listener.h".into(),
             "main/host/syscall_handler.h".into(),
@@ -113,7 +111,6 @@ fn run_cbindgen(build_common: &ShadowBuildCommon) {
             Some(v)
         };
         config.export = cbindgen::ExportConfig {
-            include: vec!["QDiscMode".into()],
             // Export everything except function definitions, since those are already
             // exported in the other header file, and need the C header files.
             item_types: base_config
@@ -187,7 +184,6 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .allowlist_function("legacyfile_.*")
         .allowlist_function("legacysocket_.*")
         .blocklist_function("legacysocket_init")
-        .allowlist_function("networkinterface_.*")
         .allowlist_function("hostc_.*")
         // used by shadow's main function
         .allowlist_function("main_.*")
@@ -231,6 +227,7 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .allowlist_type("LogInfoFlags")
         .allowlist_type("SimulationTime")
         .allowlist_type("ProtocolTCPFlags")
+        .allowlist_type("ProtocolICMPType")
         .allowlist_type("PacketDeliveryStatusFlags")
         .allowlist_var("AFFINITY_UNINIT")
         .allowlist_var("CONFIG_HEADER_SIZE_TCP")
@@ -265,7 +262,6 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .allowlist_type("WorkerPool")
         .opaque_type("WorkerPool")
         .blocklist_type("HashSet_String")
-        .blocklist_type("QDiscMode")
         // Imported from libc crate below
         .blocklist_type("siginfo_t")
         .blocklist_type("SysCallReg")
@@ -279,7 +275,6 @@ fn run_bindgen(build_common: &ShadowBuildCommon) {
         .raw_line("")
         .raw_line("use crate::core::main::ShadowBuildInfo;")
         .raw_line("use crate::core::support::configuration::ConfigOptions;")
-        .raw_line("use crate::core::support::configuration::QDiscMode;")
         .raw_line("use crate::host::descriptor::File;")
         .raw_line("use crate::host::descriptor::OpenFile;")
         .raw_line("use crate::host::descriptor::socket::inet::{InetSocket, InetSocketWeak};")
@@ -338,6 +333,7 @@ fn build_shadow_c(build_common: &ShadowBuildCommon) {
 
     build.files(&[
         "core/affinity.c",
+        "core/api.c",
         "core/support/config_handlers.c",
         "core/main.c",
         "host/descriptor/descriptor.c",
@@ -369,8 +365,6 @@ fn build_shadow_c(build_common: &ShadowBuildCommon) {
         "host/syscall/unistd.c",
         "host/syscall/uio.c",
         "host/syscall_condition.c",
-        "host/network/network_interface.c",
-        "host/network/network_queuing_disciplines.c",
         "host/tracker.c",
         "routing/payload.c",
         "routing/packet.c",
@@ -0,0 +1,16 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// The stable API is implemented in Rust (see api.rs), so we include both its
+// header and the generated bindings here to check that the header's
+// declarations match the implementation.
+
+#include "main/core/shadow_api.h"
+#include "main/bindings/c/bindings.h"
+
+_Static_assert(SHADOW_API_VERSION == SHADOW_API_MAKE_VERSION(SHADOW_API_VERSION_MAJOR,
+                                                             SHADOW_API_VERSION_MINOR,
+                                                             SHADOW_API_VERSION_PATCH),
+               "The encoded API version is inconsistent");
@@ -0,0 +1,115 @@
+//! The implementation of the stable C API declared in `shadow_api.h`, which is versioned
+//! separately from Shadow. Changes to the functions here must follow the versioning rules in that
+//! header, and the version constants here must match the header's.
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+use crate::core::worker::Worker;
+
+const VERSION_MAJOR: u32 = 1;
+const VERSION_MINOR: u32 = 0;
+const VERSION_PATCH: u32 = 0;
+
+/// The version of the API, encoded like `SHADOW_API_MAKE_VERSION`.
+const fn version() -> u32 {
+    (VERSION_MAJOR << 16) | (VERSION_MINOR << 8) | VERSION_PATCH
+}
+
+mod export {
+    use std::ffi::{c_char, c_void};
+
+    use rand::RngCore;
+
+    use super::*;
+
+    #[no_mangle]
+    pub extern "C-unwind" fn shadow_api_version() -> u32 {
+        version()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shadow_api_host_id(id: *mut u32) -> bool {
+        let id = unsafe { id.as_mut() }.unwrap();
+        Worker::with_active_host(|host| *id = host.id().into()).is_some()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shadow_api_host_name(buf: *mut c_char, len: usize) -> usize {
+        Worker::with_active_host(|host| {
+            let name = host.name().as_bytes();
+            if len > 0 {
+                let buf = unsafe { std::slice::from_raw_parts_mut(buf as *mut u8, len) };
+                let copy_len = std::cmp::min(name.len(), len - 1);
+                buf[..copy_len].copy_from_slice(&name[..copy_len]);
+                buf[copy_len] = 0;
+            }
+            name.len()
+        })
+        .unwrap_or(0)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shadow_api_host_ipv4(addr: *mut u32) -> bool {
+        let addr = unsafe { addr.as_mut() }.unwrap();
+        Worker::with_active_host(|host| *addr = u32::from(host.default_ip()).to_be()).is_some()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shadow_api_now_ns(ns: *mut u64) -> bool {
+        let ns = unsafe { ns.as_mut() }.unwrap();
+        let Some(now) = Worker::current_time() else {
+            return false;
+        };
+        *ns = now
+            .duration_since(&EmulatedTime::UNIX_EPOCH)
+            .as_nanos()
+            .try_into()
+            .unwrap();
+        true
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn shadow_api_random_bytes(buf: *mut c_void, len: usize) -> bool {
+        Worker::with_active_host(|host| {
+            if len > 0 {
+                let buf = unsafe { std::slice::from_raw_parts_mut(buf as *mut u8, len) };
+                host.random_mut().fill_bytes(buf);
+            }
+        })
+        .is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value of the `#define` named `name` in the header.
+    fn header_define(name: &str) -> u32 {
+        let prefix = format!("#define {name} ");
+        include_str!("shadow_api.h")
+            .lines()
+            .find_map(|line| line.strip_prefix(&prefix))
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_version_matches_header() {
+        assert_eq!(header_define("SHADOW_API_VERSION_MAJOR"), VERSION_MAJOR);
+        assert_eq!(header_define("SHADOW_API_VERSION_MINOR"), VERSION_MINOR);
+        assert_eq!(header_define("SHADOW_API_VERSION_PATCH"), VERSION_PATCH);
+    }
+
+    #[test]
+    fn test_no_active_host() {
+        let mut id = 0;
+        assert!(!unsafe { export::shadow_api_host_id(&mut id) });
+        assert_eq!(
+            unsafe { export::shadow_api_host_name(std::ptr::null_mut(), 0) },
+            0
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Breakpoints that pause the simulation when a condition is met, such as when a host receives a
+//! packet on a given port or when a process exits with a non-zero status.
+//!
+//! Breakpoints are evaluated by the worker threads while they run hosts, so the simulation pauses
+//! at the end of the scheduling round in which the breakpoint was hit. The paused simulation can
+//! then be stepped or resumed using the control socket.
+
+use std::collections::HashMap;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::HostId;
+
+use crate::core::control::PauseHandle;
+use crate::core::support::configuration::BreakpointOptions;
+use crate::core::worker::Worker;
+use crate::host::host::Host;
+use crate::host::process::ExitStatus;
+
+#[derive(Debug)]
+pub struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    pause_handle: PauseHandle,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Breakpoint {
+    PacketReceived {
+        host: Option<HostId>,
+        port: Option<u16>,
+    },
+    ProcessExited {
+        host: Option<HostId>,
+        process: Option<String>,
+        nonzero: bool,
+    },
+}
+
+impl Breakpoints {
+    /// Create the breakpoints from the configuration. `host_ids` maps each host name to its ID.
+    pub fn new(
+        options: &[BreakpointOptions],
+        host_ids: &HashMap<&str, HostId>,
+        pause_handle: PauseHandle,
+    ) -> anyhow::Result<Self> {
+        let breakpoints = options
+            .iter()
+            .map(|x| Breakpoint::new(x, host_ids))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            breakpoints,
+            pause_handle,
+        })
+    }
+
+    /// Called when `host` receives a packet with the destination port `port`.
+    pub fn packet_received(&self, host: &Host, port: u16) {
+        for breakpoint in &self.breakpoints {
+            if breakpoint.matches_packet(host.id(), port) {
+                self.hit(&format!(
+                    "host '{}' received a packet on port {port}",
+                    host.name()
+                ));
+                return;
+            }
+        }
+    }
+
+    /// Called when a process named `process` on `host` exits.
+    pub fn process_exited(&self, host: &Host, process: &str, exit_status: ExitStatus) {
+        for breakpoint in &self.breakpoints {
+            if breakpoint.matches_exit(host.id(), process, exit_status) {
+                self.hit(&format!(
+                    "process '{process}' on host '{}' exited with status {exit_status:?}",
+                    host.name()
+                ));
+                return;
+            }
+        }
+    }
+
+    fn hit(&self, msg: &str) {
+        let now = Worker::current_time().unwrap();
+        log::info!(
+            "Breakpoint hit at {} ns: {msg}",
+            (now - EmulatedTime::SIMULATION_START).as_nanos()
+        );
+        self.pause_handle.pause();
+    }
+}
+
+impl Breakpoint {
+    fn new(options: &BreakpointOptions, host_ids: &HashMap<&str, HostId>) -> anyhow::Result<Self> {
+        let host_id = |name: &Option<String>| {
+            name.as_deref()
+                .map(|name| {
+                    host_ids
+                        .get(name)
+                        .copied()
+                        .ok_or_else(|| anyhow::anyhow!("Breakpoint has an unknown host '{name}'"))
+                })
+                .transpose()
+        };
+
+        Ok(match options {
+            BreakpointOptions::PacketReceived { host, port } => Self::PacketReceived {
+                host: host_id(host)?,
+                port: *port,
+            },
+            BreakpointOptions::ProcessExited {
+                host,
+                process,
+                nonzero,
+            } => Self::ProcessExited {
+                host: host_id(host)?,
+                process: process.clone(),
+                nonzero: *nonzero,
+            },
+        })
+    }
+
+    fn matches_packet(&self, host_id: HostId, packet_port: u16) -> bool {
+        let Self::PacketReceived { host, port } = self else {
+            return false;
+        };
+        host.map_or(true, |x| x == host_id) && port.map_or(true, |x| x == packet_port)
+    }
+
+    fn matches_exit(&self, host_id: HostId, process_name: &str, exit_status: ExitStatus) -> bool {
+        let Self::ProcessExited {
+            host,
+            process,
+            nonzero,
+        } = self
+        else {
+            return false;
+        };
+
+        // processes that are still running when the simulation ends never count as failures
+        let failed = !matches!(
+            exit_status,
+            ExitStatus::Normal(0) | ExitStatus::StoppedByShadow
+        );
+
+        host.map_or(true, |x| x == host_id)
+            && process.as_deref().map_or(true, |x| x == process_name)
+            && (!nonzero || failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let server = HostId::from(0);
+        let client = HostId::from(1);
+        let host_ids = HashMap::from([("server", server), ("client", client)]);
+
+        let options: Vec<BreakpointOptions> = serde_yaml::from_str(
+            "
+            - type: packet-received
+              host: server
+              port: 443
+            - type: process-exited
+              process: curl
+              nonzero: true
+            ",
+        )
+        .unwrap();
+        let breakpoints: Vec<_> = options
+            .iter()
+            .map(|x| Breakpoint::new(x, &host_ids).unwrap())
+            .collect();
+
+        assert!(breakpoints[0].matches_packet(server, 443));
+        assert!(!breakpoints[0].matches_packet(server, 80));
+        assert!(!breakpoints[0].matches_packet(client, 443));
+        assert!(!breakpoints[0].matches_exit(server, "curl", ExitStatus::Normal(1)));
+
+        assert!(breakpoints[1].matches_exit(client, "curl", ExitStatus::Normal(1)));
+        assert!(breakpoints[1].matches_exit(server, "curl", ExitStatus::Normal(1)));
+        assert!(!breakpoints[1].matches_exit(client, "curl", ExitStatus::Normal(0)));
+        assert!(!breakpoints[1].matches_exit(client, "curl", ExitStatus::StoppedByShadow));
+        assert!(!breakpoints[1].matches_exit(client, "tgen", ExitStatus::Normal(1)));
+        assert!(!breakpoints[1].matches_packet(client, 443));
+    }
+
+    #[test]
+    fn test_unknown_host() {
+        let options = BreakpointOptions::PacketReceived {
+            host: Some("missing".into()),
+            port: None,
+        };
+        assert!(Breakpoint::new(&options, &HashMap::new()).is_err());
+    }
+}
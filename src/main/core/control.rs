@@ -0,0 +1,503 @@
+//! An optional control socket that allows an external program to pause, single-step, and resume
+//! the simulation, like a debugger for the whole simulated network.
+//!
+//! The control socket is a Unix stream socket. Clients send one command per line, and Shadow
+//! replies to each command with one line:
+//!
+//! - `pause`: Pause the simulation at the next scheduling round barrier. Replies `paused <time>`
+//!   once the simulation has paused.
+//! - `step <ms>`: Run a paused simulation for exactly `<ms>` simulated milliseconds and pause it
+//!   again. Replies `paused <time>` once the simulation has paused.
+//! - `resume`: Resume running a paused simulation. Replies `running <time>`.
+//! - `status`: Replies `paused <time>` or `running <time>`.
+//! - `wait`: Wait until the simulation pauses, for example at a breakpoint. Replies
+//!   `paused <time>` once the simulation has paused.
+//! - `firewall <host> <ingress|egress> <rule>`: Add a rule after a host's existing firewall rules,
+//!   where the rule is an action followed by `key=value` matches, for example
+//!   `firewall server ingress deny protocol=tcp dst_port=80`. Replies `paused <time>`.
+//! - `firewall <host> <ingress|egress> clear`: Remove all of a host's ingress or egress firewall
+//!   rules. Replies `paused <time>`.
+//!
+//! Times are in nanoseconds of simulated time since the start of the simulation. While paused,
+//! all events before this time have run and no events at or after this time have run. If the
+//! simulation finishes while a command is waiting, or if a client connects after the simulation
+//! finished, Shadow replies `finished <time>`. Invalid commands are replied to with
+//! `error <message>`. Firewall commands are only accepted while the simulation is paused, so that
+//! the rules change at a deterministic time.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use anyhow::Context;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::host::network::firewall::{Firewall, FirewallDirection};
+
+/// Listens on the control socket and accepts commands from clients on a background thread.
+pub struct ControlSocket {
+    path: PathBuf,
+    control: Arc<Control>,
+}
+
+impl ControlSocket {
+    /// Create the control socket at `path` and start accepting clients.
+    pub fn bind(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to bind control socket '{}'", path.display()))?;
+
+        let control = Arc::new(Control::new());
+
+        {
+            let control = Arc::clone(&control);
+            std::thread::Builder::new()
+                .name("control-socket".into())
+                .spawn(move || accept_loop(&listener, &control))
+                .context("Failed to start the control socket thread")?;
+        }
+
+        log::info!("Listening for control commands on '{}'", path.display());
+
+        Ok(Self { path, control })
+    }
+
+    /// Called at each scheduling round barrier, where all events before `time` have run and the
+    /// next event is at `next_event_time`. Blocks while the simulation is paused. Returns a time
+    /// that the next round must not run past, if any.
+    pub fn barrier(
+        &self,
+        time: EmulatedTime,
+        next_event_time: EmulatedTime,
+    ) -> Option<EmulatedTime> {
+        self.control.barrier(time, next_event_time)
+    }
+
+    /// A handle that can be used to pause the simulation from other threads.
+    pub fn pause_handle(&self) -> PauseHandle {
+        PauseHandle(Arc::clone(&self.control))
+    }
+
+    /// A handle that can be used to make the hosts' firewalls available to clients.
+    pub fn firewall_registry(&self) -> FirewallRegistry {
+        FirewallRegistry(Arc::clone(&self.control))
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.control.finish();
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!(
+                "Failed to remove control socket '{}': {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Pauses the simulation at the next scheduling round barrier.
+#[derive(Debug, Clone)]
+pub struct PauseHandle(Arc<Control>);
+
+impl PauseHandle {
+    /// Pause the simulation at the end of the current round. This interrupts any step in progress.
+    pub fn pause(&self) {
+        self.0.request_pause();
+    }
+}
+
+/// Makes the hosts' firewalls available to the control socket's `firewall` command.
+#[derive(Debug, Clone)]
+pub struct FirewallRegistry(Arc<Control>);
+
+impl FirewallRegistry {
+    /// Allow clients to change the firewall rules of host `hostname`.
+    pub fn register(&self, hostname: &str, firewall: Arc<Mutex<Firewall>>) {
+        self.0
+            .firewalls
+            .lock()
+            .unwrap()
+            .insert(hostname.to_string(), firewall);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Running,
+    /// A client asked to pause at the next barrier.
+    PauseRequested,
+    Paused,
+    /// Run until the given time, then pause.
+    Stepping(EmulatedTime),
+}
+
+#[derive(Debug)]
+struct State {
+    mode: Mode,
+    /// The time of the most recent barrier, or the time that the simulation is paused at.
+    time: EmulatedTime,
+    /// Incremented each time the simulation pauses, so that waiting clients can tell that their
+    /// command has completed even if another client has resumed the simulation since.
+    num_pauses: u64,
+    finished: bool,
+}
+
+/// The state shared between the simulation and the control socket's clients.
+#[derive(Debug)]
+struct Control {
+    state: Mutex<State>,
+    cond: Condvar,
+    /// The firewall of each host, indexed by hostname.
+    firewalls: Mutex<HashMap<String, Arc<Mutex<Firewall>>>>,
+}
+
+impl Control {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                mode: Mode::Running,
+                time: EmulatedTime::SIMULATION_START,
+                num_pauses: 0,
+                finished: false,
+            }),
+            cond: Condvar::new(),
+            firewalls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn barrier(&self, time: EmulatedTime, next_event_time: EmulatedTime) -> Option<EmulatedTime> {
+        let mut state = self.state.lock().unwrap();
+        state.time = time;
+
+        loop {
+            match state.mode {
+                Mode::Running => return None,
+                Mode::Stepping(until) if next_event_time < until => return Some(until),
+                Mode::Stepping(until) => {
+                    // there are no events before `until`, so we can pause there
+                    state.time = until;
+                    self.pause(&mut state);
+                }
+                Mode::PauseRequested => self.pause(&mut state),
+                Mode::Paused => state = self.cond.wait(state).unwrap(),
+            }
+        }
+    }
+
+    fn pause(&self, state: &mut State) {
+        state.mode = Mode::Paused;
+        state.num_pauses += 1;
+        log::info!("Paused the simulation at {}", nanos_since_start(state.time));
+        self.cond.notify_all();
+    }
+
+    fn request_pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.mode != Mode::Paused {
+            state.mode = Mode::PauseRequested;
+        }
+    }
+
+    /// Wake any clients that are waiting for the simulation to pause.
+    fn finish(&self) {
+        self.state.lock().unwrap().finished = true;
+        self.cond.notify_all();
+    }
+
+    /// Wait until the simulation has paused more than `num_pauses` times in total, or has
+    /// finished.
+    fn wait_for_pause<'a>(
+        &self,
+        mut state: MutexGuard<'a, State>,
+        num_pauses: u64,
+    ) -> MutexGuard<'a, State> {
+        while state.num_pauses == num_pauses && !state.finished {
+            state = self.cond.wait(state).unwrap();
+        }
+        state
+    }
+
+    /// Add a rule to a host's firewall, or remove all rules if `rule` is "clear".
+    fn update_firewall(&self, hostname: &str, direction: &str, rule: &str) -> anyhow::Result<()> {
+        let direction: FirewallDirection = direction.parse()?;
+        let firewall = self
+            .firewalls
+            .lock()
+            .unwrap()
+            .get(hostname)
+            .cloned()
+            .with_context(|| format!("Unknown host '{hostname}'"))?;
+        let mut firewall = firewall.lock().unwrap();
+
+        if rule == "clear" {
+            firewall.clear(direction);
+        } else {
+            firewall.add_rule(direction, rule.parse()?);
+        }
+
+        log::info!("Updated the {direction:?} firewall rules of host '{hostname}': {rule}");
+        Ok(())
+    }
+
+    /// Run a command and return the reply.
+    fn handle_command(&self, command: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+
+        if state.finished {
+            return format!("finished {}", nanos_since_start(state.time));
+        }
+
+        let mut args = command.split_whitespace();
+        let name = args.next().unwrap_or("");
+        let args: Vec<&str> = args.collect();
+
+        match (name, args.as_slice()) {
+            ("pause", []) => {
+                if state.mode != Mode::Paused {
+                    state.mode = Mode::PauseRequested;
+                    let num_pauses = state.num_pauses;
+                    state = self.wait_for_pause(state, num_pauses);
+                }
+            }
+            ("step", [ms]) => {
+                let Ok(ms) = ms.parse() else {
+                    return format!("error invalid number of milliseconds '{ms}'");
+                };
+                if state.mode != Mode::Paused {
+                    return "error the simulation is not paused".into();
+                }
+                let until = SimulationTime::try_from_millis(ms)
+                    .and_then(|step| state.time.checked_add(step));
+                let Some(until) = until else {
+                    return "error the step is too large".into();
+                };
+
+                log::info!("Stepping the simulation to {}", nanos_since_start(until));
+                state.mode = Mode::Stepping(until);
+                self.cond.notify_all();
+
+                let num_pauses = state.num_pauses;
+                state = self.wait_for_pause(state, num_pauses);
+            }
+            ("resume", []) => {
+                if state.mode == Mode::Paused {
+                    log::info!("Resuming the simulation");
+                }
+                state.mode = Mode::Running;
+                self.cond.notify_all();
+            }
+            ("wait", []) => {
+                if state.mode != Mode::Paused {
+                    let num_pauses = state.num_pauses;
+                    state = self.wait_for_pause(state, num_pauses);
+                }
+            }
+            ("status", []) => {}
+            ("firewall", [hostname, direction, rule @ ..]) if !rule.is_empty() => {
+                if state.mode != Mode::Paused {
+                    return "error the simulation is not paused".into();
+                }
+                if let Err(e) = self.update_firewall(hostname, direction, &rule.join(" ")) {
+                    return format!("error {e:#}");
+                }
+            }
+            ("pause" | "step" | "resume" | "wait" | "status" | "firewall", _) => {
+                return format!("error wrong number of arguments for '{name}'");
+            }
+            _ => return format!("error unknown command '{}'", command.trim()),
+        }
+
+        let status = if state.finished {
+            "finished"
+        } else if state.mode == Mode::Paused {
+            "paused"
+        } else {
+            "running"
+        };
+        format!("{status} {}", nanos_since_start(state.time))
+    }
+}
+
+fn nanos_since_start(time: EmulatedTime) -> u128 {
+    (time - EmulatedTime::SIMULATION_START).as_nanos()
+}
+
+fn accept_loop(listener: &UnixListener, control: &Arc<Control>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(x) => x,
+            Err(e) => {
+                log::warn!("Failed to accept a control socket client: {e}");
+                continue;
+            }
+        };
+
+        // a client may block while waiting for the simulation to pause, so each client gets its
+        // own thread
+        let control = Arc::clone(control);
+        let rv = std::thread::Builder::new()
+            .name("control-client".into())
+            .spawn(move || {
+                if let Err(e) = handle_client(stream, &control) {
+                    log::warn!("Control socket client error: {e}");
+                }
+            });
+        if let Err(e) = rv {
+            log::warn!("Failed to start a control socket client thread: {e}");
+        }
+    }
+}
+
+fn handle_client(stream: UnixStream, control: &Control) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", control.handle_command(&line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    /// Run rounds of at most 1 ms until `stop` is set, with an event at every barrier.
+    fn run_rounds(control: &Control, stop: &AtomicBool) {
+        let mut time = EmulatedTime::SIMULATION_START;
+        while !stop.load(Ordering::Relaxed) {
+            let limit = control.barrier(time, time).unwrap_or(EmulatedTime::MAX);
+            time = std::cmp::min(time + SimulationTime::from_millis(1), limit);
+        }
+    }
+
+    #[test]
+    fn test_pause_step_resume() {
+        let control = Arc::new(Control::new());
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let sim = {
+            let control = Arc::clone(&control);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || run_rounds(&control, &stop))
+        };
+
+        assert_eq!(
+            control.handle_command("step 5"),
+            "error the simulation is not paused"
+        );
+
+        let reply = control.handle_command("pause");
+        let paused_at: u128 = reply.strip_prefix("paused ").unwrap().parse().unwrap();
+        assert_eq!(control.handle_command("status"), reply);
+
+        // the simulation advances by exactly the step
+        assert_eq!(
+            control.handle_command("step 5"),
+            format!("paused {}", paused_at + 5_000_000)
+        );
+        assert_eq!(
+            control.handle_command("resume"),
+            format!("running {}", paused_at + 5_000_000)
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        sim.join().unwrap();
+        control.finish();
+
+        assert!(control.handle_command("pause").starts_with("finished "));
+    }
+
+    #[test]
+    fn test_pause_handle() {
+        let control = Arc::new(Control::new());
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let sim = {
+            let control = Arc::clone(&control);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || run_rounds(&control, &stop))
+        };
+
+        let client = {
+            let control = Arc::clone(&control);
+            std::thread::spawn(move || control.handle_command("wait"))
+        };
+
+        PauseHandle(Arc::clone(&control)).pause();
+        let reply = client.join().unwrap();
+        assert!(reply.starts_with("paused "));
+        assert_eq!(control.handle_command("status"), reply);
+
+        control.handle_command("resume");
+        stop.store(true, Ordering::Relaxed);
+        sim.join().unwrap();
+    }
+
+    #[test]
+    fn test_firewall() {
+        let control = Arc::new(Control::new());
+        let firewall = Arc::new(Mutex::new(Firewall::default()));
+        FirewallRegistry(Arc::clone(&control)).register("server", Arc::clone(&firewall));
+
+        assert_eq!(
+            control.handle_command("firewall server ingress deny dst_port=80"),
+            "error the simulation is not paused"
+        );
+
+        control.state.lock().unwrap().mode = Mode::Paused;
+        assert_eq!(
+            control.handle_command("firewall server ingress deny dst_port=80"),
+            "paused 0"
+        );
+        assert!(control
+            .handle_command("firewall client ingress deny")
+            .starts_with("error Unknown host"));
+        assert!(control
+            .handle_command("firewall server sideways deny")
+            .starts_with("error Invalid firewall direction"));
+        assert_eq!(
+            control.handle_command("firewall server ingress"),
+            "error wrong number of arguments for 'firewall'"
+        );
+
+        let mut expected = Firewall::default();
+        expected.add_rule(
+            FirewallDirection::Ingress,
+            "deny dst_port=80".parse().unwrap(),
+        );
+        assert_eq!(*firewall.lock().unwrap(), expected);
+
+        assert_eq!(
+            control.handle_command("firewall server ingress clear"),
+            "paused 0"
+        );
+        assert_eq!(*firewall.lock().unwrap(), Firewall::default());
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        let control = Control::new();
+        assert_eq!(
+            control.handle_command("jump"),
+            "error unknown command 'jump'"
+        );
+        assert_eq!(
+            control.handle_command("step"),
+            "error wrong number of arguments for 'step'"
+        );
+        assert_eq!(
+            control.handle_command("step x"),
+            "error invalid number of milliseconds 'x'"
+        );
+        assert_eq!(control.handle_command("status"), "running 0");
+    }
+}
@@ -1,5 +1,5 @@
 use std::io::IsTerminal;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::Context;
@@ -9,9 +9,11 @@ use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::util::time::TimeParts;
 
+use crate::core::control::ControlSocket;
 use crate::core::manager::{Manager, ManagerConfig};
+use crate::core::pacing::RealTimePacer;
 use crate::core::sim_config::SimConfig;
-use crate::core::support::configuration::ConfigOptions;
+use crate::core::support::configuration::{ConfigOptions, Flatten};
 use crate::core::worker;
 use crate::utility::status_bar::{self, StatusBar, StatusPrinter};
 
@@ -22,6 +24,12 @@ pub struct Controller<'a> {
 
     // the simulator should attempt to end immediately after this time
     end_time: EmulatedTime,
+
+    // accepts commands to pause, step, and resume the simulation, if enabled
+    control_socket: Option<ControlSocket>,
+
+    // paces the simulation to real time, if enabled
+    pacer: Option<Mutex<RealTimePacer>>,
 }
 
 impl<'a> Controller<'a> {
@@ -34,6 +42,8 @@ impl<'a> Controller<'a> {
             config,
             sim_config: Some(sim_config),
             end_time,
+            control_socket: None,
+            pacer: None,
         }
     }
 
@@ -51,12 +61,28 @@ impl<'a> Controller<'a> {
             }
         });
 
+        if let Some(path) = self.config.experimental.control_socket.flatten_ref() {
+            self.control_socket = Some(ControlSocket::bind(path)?);
+        }
+
+        if let Some(ratio) = self.config.experimental.real_time_pacing.flatten() {
+            let pacer =
+                RealTimePacer::new(ratio).context("Failed to configure real-time pacing")?;
+            self.pacer = Some(Mutex::new(pacer));
+        }
+
         let manager_config = ManagerConfig {
             random: Xoshiro256PlusPlus::from_rng(&mut sim_config.random).unwrap(),
             ip_assignment: sim_config.ip_assignment,
             routing_info: sim_config.routing_info,
             host_bandwidths: sim_config.host_bandwidths,
+            broadcast_domains: sim_config.broadcast_domains,
             hosts: sim_config.hosts,
+            topology: sim_config.topology,
+            observers: sim_config.observers,
+            censors: sim_config.censors,
+            pause_handle: self.control_socket.as_ref().map(|x| x.pause_handle()),
+            firewall_registry: self.control_socket.as_ref().map(|x| x.firewall_registry()),
         };
 
         let manager = Manager::new(manager_config, &self, self.config, self.end_time)
@@ -66,6 +92,13 @@ impl<'a> Controller<'a> {
         let num_plugin_errors = manager.run(status_logger.as_ref().map(|x| x.status()))?;
         log::info!("Finished simulation");
 
+        if let Some(pacer) = &self.pacer {
+            let lag = pacer.lock().unwrap().total_lag();
+            if !lag.is_zero() {
+                log::info!("The simulation fell behind real time by {lag:?} in total");
+            }
+        }
+
         if num_plugin_errors > 0 {
             return Err(anyhow::anyhow!(
                 "{num_plugin_errors} managed processes in unexpected final state"
@@ -80,6 +113,7 @@ impl<'a> Controller<'a> {
 pub trait SimController {
     fn manager_finished_current_round(
         &self,
+        round_end_time: EmulatedTime,
         min_next_event_time: EmulatedTime,
     ) -> Option<(EmulatedTime, EmulatedTime)>;
 }
@@ -87,6 +121,7 @@ pub trait SimController {
 impl SimController for Controller<'_> {
     fn manager_finished_current_round(
         &self,
+        round_end_time: EmulatedTime,
         min_next_event_time: EmulatedTime,
     ) -> Option<(EmulatedTime, EmulatedTime)> {
         // TODO: once we get multiple managers, we have to block them here until they have all
@@ -107,8 +142,23 @@ impl SimController for Controller<'_> {
         let new_end = new_start.checked_add(runahead).unwrap_or(EmulatedTime::MAX);
         let new_end = std::cmp::min(new_end, self.end_time);
 
-        let continue_running = new_start < new_end;
-        continue_running.then_some((new_start, new_end))
+        if new_start >= new_end {
+            return None;
+        }
+
+        // wait while the simulation is paused, and don't run past the end of a single step
+        let step_end = self
+            .control_socket
+            .as_ref()
+            .and_then(|x| x.barrier(round_end_time, new_start));
+        let new_end = step_end.map_or(new_end, |x| std::cmp::min(new_end, x));
+
+        // don't start the next round before its real time
+        if let Some(pacer) = &self.pacer {
+            pacer.lock().unwrap().wait_until(new_start);
+        }
+
+        Some((new_start, new_end))
     }
 }
 
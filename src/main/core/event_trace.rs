@@ -0,0 +1,365 @@
+//! An optional trace of significant simulation events in the Chrome trace event format, which can
+//! be viewed in a timeline UI such as [Perfetto](https://ui.perfetto.dev) or `chrome://tracing`.
+//!
+//! Two traces are written, since they use different clocks:
+//!
+//! - The simulation trace uses simulated time. Each host is a trace process, each simulated
+//!   process is a trace thread showing when it ran, and network events are shown on a separate
+//!   "network" thread of the host.
+//! - The scheduler trace uses real time since the start of the simulation. It shows each
+//!   scheduling round and the periods when each worker thread was busy running hosts.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::Context;
+use serde::Serialize;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::HostId;
+
+use crate::host::process::ProcessId;
+
+pub const SIM_TRACE_FILE_NAME: &str = "sim-trace.json";
+pub const SCHEDULER_TRACE_FILE_NAME: &str = "scheduler-trace.json";
+
+/// The trace thread ID of a host's network events. Simulated process IDs are never 0.
+const NETWORK_TID: u32 = 0;
+
+/// The trace thread ID of the scheduling rounds. Worker threads are numbered from 1.
+const ROUNDS_TID: u32 = 0;
+
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    /// Timestamp in microseconds.
+    ts: f64,
+    /// Duration in microseconds, for complete events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    tid: u32,
+    /// Scope of instant events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    args: BTreeMap<&'static str, serde_json::Value>,
+}
+
+impl TraceEvent {
+    fn metadata(name: &str, pid: u32, tid: u32, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            cat: "",
+            ph: "M",
+            ts: 0.0,
+            dur: None,
+            pid,
+            tid,
+            s: None,
+            args: BTreeMap::from([("name", value.into())]),
+        }
+    }
+
+    fn complete(name: String, cat: &'static str, pid: u32, tid: u32, ts: f64, dur: f64) -> Self {
+        Self {
+            name,
+            cat,
+            ph: "X",
+            ts,
+            dur: Some(dur),
+            pid,
+            tid,
+            s: None,
+            args: BTreeMap::new(),
+        }
+    }
+
+    fn instant(name: String, cat: &'static str, pid: u32, tid: u32, ts: f64) -> Self {
+        Self {
+            name,
+            cat,
+            ph: "i",
+            ts,
+            dur: None,
+            pid,
+            tid,
+            s: Some("t"),
+            args: BTreeMap::new(),
+        }
+    }
+
+    fn with_arg(mut self, key: &'static str, value: impl Into<serde_json::Value>) -> Self {
+        self.args.insert(key, value.into());
+        self
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceFile<'a> {
+    trace_events: &'a [TraceEvent],
+    display_time_unit: &'static str,
+}
+
+/// Microseconds of simulated time since the start of the simulation.
+fn sim_micros(t: EmulatedTime) -> f64 {
+    (t - EmulatedTime::SIMULATION_START).as_nanos_f64() / 1000.0
+}
+
+#[derive(Debug)]
+pub struct EventTrace {
+    real_start: Instant,
+    sim_events: Mutex<Vec<TraceEvent>>,
+    scheduler_events: Mutex<Vec<TraceEvent>>,
+}
+
+impl EventTrace {
+    pub fn new<'a>(hosts: impl IntoIterator<Item = (HostId, &'a str)>, num_threads: usize) -> Self {
+        let mut sim_events = Vec::new();
+        for (host_id, name) in hosts {
+            let pid = u32::from(host_id);
+            sim_events.push(TraceEvent::metadata("process_name", pid, 0, name));
+            sim_events.push(TraceEvent::metadata(
+                "thread_name",
+                pid,
+                NETWORK_TID,
+                "network",
+            ));
+        }
+
+        let mut scheduler_events = vec![
+            TraceEvent::metadata("process_name", 0, 0, "scheduler"),
+            TraceEvent::metadata("thread_name", 0, ROUNDS_TID, "rounds"),
+        ];
+        for thread in 0..num_threads {
+            scheduler_events.push(TraceEvent::metadata(
+                "thread_name",
+                0,
+                thread as u32 + 1,
+                &format!("worker {thread}"),
+            ));
+        }
+
+        Self {
+            real_start: Instant::now(),
+            sim_events: Mutex::new(sim_events),
+            scheduler_events: Mutex::new(scheduler_events),
+        }
+    }
+
+    fn real_micros(&self, t: Instant) -> f64 {
+        t.saturating_duration_since(self.real_start).as_nanos() as f64 / 1000.0
+    }
+
+    /// Record that a process ran from `start` until it exited at `end`.
+    pub fn process_exited(
+        &self,
+        host_id: HostId,
+        process_id: ProcessId,
+        name: &str,
+        start: EmulatedTime,
+        end: EmulatedTime,
+        exit_status: &str,
+    ) {
+        let (pid, tid) = (u32::from(host_id), u32::from(process_id));
+        let start = sim_micros(start);
+        let end = sim_micros(end);
+
+        let mut events = self.sim_events.lock().unwrap();
+        events.push(TraceEvent::metadata("thread_name", pid, tid, name));
+        events.push(
+            TraceEvent::complete(name.to_string(), "process", pid, tid, start, end - start)
+                .with_arg("exit_status", exit_status),
+        );
+    }
+
+    /// Record a problem with a process, such as it exiting in an unexpected state.
+    pub fn fault(&self, host_id: HostId, process_id: ProcessId, time: EmulatedTime, msg: &str) {
+        let event = TraceEvent::instant(
+            "fault".to_string(),
+            "fault",
+            u32::from(host_id),
+            u32::from(process_id),
+            sim_micros(time),
+        )
+        .with_arg("message", msg);
+        self.sim_events.lock().unwrap().push(event);
+    }
+
+    /// Record that a connection between `local` and `peer` was established.
+    pub fn connection_established(
+        &self,
+        host_id: HostId,
+        time: EmulatedTime,
+        local: &str,
+        peer: &str,
+    ) {
+        let event = TraceEvent::instant(
+            format!("connected to {peer}"),
+            "network",
+            u32::from(host_id),
+            NETWORK_TID,
+            sim_micros(time),
+        )
+        .with_arg("local", local)
+        .with_arg("peer", peer);
+        self.sim_events.lock().unwrap().push(event);
+    }
+
+    /// Record a scheduling round that ran the simulation window `window`.
+    pub fn round(&self, start: Instant, end: Instant, window: (EmulatedTime, EmulatedTime)) {
+        let (start, end) = (self.real_micros(start), self.real_micros(end));
+        let window_start = (window.0 - EmulatedTime::SIMULATION_START).as_nanos() as u64;
+        let window_end = (window.1 - EmulatedTime::SIMULATION_START).as_nanos() as u64;
+
+        let event = TraceEvent::complete(
+            "round".to_string(),
+            "scheduler",
+            0,
+            ROUNDS_TID,
+            start,
+            end - start,
+        )
+        .with_arg("window_start_ns", window_start)
+        .with_arg("window_end_ns", window_end);
+        self.scheduler_events.lock().unwrap().push(event);
+    }
+
+    /// Record a period when worker thread `thread` was busy running hosts.
+    pub fn worker_busy(&self, thread: usize, start: Instant, end: Instant) {
+        let (start, end) = (self.real_micros(start), self.real_micros(end));
+        let event = TraceEvent::complete(
+            "busy".to_string(),
+            "scheduler",
+            0,
+            thread as u32 + 1,
+            start,
+            end - start,
+        );
+        self.scheduler_events.lock().unwrap().push(event);
+    }
+
+    /// Write the simulation and scheduler traces to directory `dir`.
+    pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+        let mut sim_events = self.sim_events.lock().unwrap();
+        // events are recorded by worker threads in a nondeterministic order
+        sim_events.sort_by(|a, b| {
+            a.ts.total_cmp(&b.ts)
+                .then(a.pid.cmp(&b.pid))
+                .then(a.tid.cmp(&b.tid))
+                .then(a.ph.cmp(&b.ph))
+                .then(a.name.cmp(&b.name))
+        });
+        write_trace(&dir.join(SIM_TRACE_FILE_NAME), &sim_events)?;
+
+        let scheduler_events = self.scheduler_events.lock().unwrap();
+        write_trace(&dir.join(SCHEDULER_TRACE_FILE_NAME), &scheduler_events)?;
+
+        Ok(())
+    }
+}
+
+fn write_trace(path: &Path, events: &[TraceEvent]) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create trace file '{}'", path.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    let trace = TraceFile {
+        trace_events: events,
+        display_time_unit: "ns",
+    };
+    serde_json::to_writer(&mut writer, &trace)
+        .map_err(std::io::Error::from)
+        .and_then(|_| writer.flush())
+        .with_context(|| format!("Failed to write trace file '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+    use super::*;
+
+    #[test]
+    fn test_sim_trace() {
+        let host_id = HostId::from(3);
+        let process_id = ProcessId::try_from(1000).unwrap();
+        let trace = EventTrace::new([(host_id, "server")], 1);
+
+        let t = |ms| EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms);
+        trace.process_exited(
+            host_id,
+            process_id,
+            "server.app.1000",
+            t(2000),
+            t(3500),
+            "Normal(0)",
+        );
+        trace.connection_established(host_id, t(2500), "11.0.0.1:80", "11.0.0.2:5000");
+        trace.fault(host_id, process_id, t(3500), "unexpected state");
+
+        let dir = tempfile::tempdir().unwrap();
+        trace.write(dir.path()).unwrap();
+
+        let sim_trace = std::fs::read_to_string(dir.path().join(SIM_TRACE_FILE_NAME)).unwrap();
+        let sim_trace: serde_json::Value = serde_json::from_str(&sim_trace).unwrap();
+        let events = sim_trace["traceEvents"].as_array().unwrap();
+
+        // two host metadata events, the process' metadata event, and three other events
+        assert_eq!(events.len(), 6);
+
+        let process = events.iter().find(|x| x["ph"] == "X").unwrap();
+        assert_eq!(process["pid"], 3);
+        assert_eq!(process["tid"], 1000);
+        assert_eq!(process["ts"], 2_000_000.0);
+        assert_eq!(process["dur"], 1_500_000.0);
+        assert_eq!(process["args"]["exit_status"], "Normal(0)");
+
+        let connection = events.iter().find(|x| x["cat"] == "network").unwrap();
+        assert_eq!(connection["tid"], NETWORK_TID);
+        assert_eq!(connection["args"]["peer"], "11.0.0.2:5000");
+
+        // non-metadata events are sorted by time
+        let times: Vec<_> = events
+            .iter()
+            .filter(|x| x["ph"] != "M")
+            .map(|x| x["ts"].as_f64().unwrap())
+            .collect();
+        assert_eq!(times, [2_000_000.0, 2_500_000.0, 3_500_000.0]);
+    }
+
+    #[test]
+    fn test_scheduler_trace() {
+        let trace = EventTrace::new(Vec::<(HostId, &str)>::new(), 2);
+
+        let start = Instant::now();
+        let end = start + std::time::Duration::from_millis(5);
+        let window = (
+            EmulatedTime::SIMULATION_START,
+            EmulatedTime::SIMULATION_START + SimulationTime::from_millis(10),
+        );
+        trace.round(start, end, window);
+        trace.worker_busy(1, start, end);
+
+        let dir = tempfile::tempdir().unwrap();
+        trace.write(dir.path()).unwrap();
+
+        let sched_trace =
+            std::fs::read_to_string(dir.path().join(SCHEDULER_TRACE_FILE_NAME)).unwrap();
+        let sched_trace: serde_json::Value = serde_json::from_str(&sched_trace).unwrap();
+        let events = sched_trace["traceEvents"].as_array().unwrap();
+
+        let round = events.iter().find(|x| x["name"] == "round").unwrap();
+        assert_eq!(round["tid"], ROUNDS_TID);
+        assert_eq!(round["dur"], 5000.0);
+        assert_eq!(round["args"]["window_end_ns"], 10_000_000);
+
+        let busy = events.iter().find(|x| x["name"] == "busy").unwrap();
+        assert_eq!(busy["tid"], 2);
+    }
+}
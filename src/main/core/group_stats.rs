@@ -0,0 +1,196 @@
+//! Aggregate heartbeat statistics of groups of hosts, where each group is the set of hosts with a
+//! given tag. At each heartbeat, a host adds its statistics for the heartbeat interval to the group
+//! of each of its tags. When the simulation ends, the sum, mean, and percentiles of each statistic
+//! over the hosts of a group are written for each heartbeat time.
+//!
+//! Hosts with different heartbeat intervals have their heartbeats at different times, so the
+//! groups should only contain hosts with the same heartbeat interval.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+use crate::utility;
+
+pub const GROUP_STATS_FILE_NAME: &str = "host-group-stats.csv";
+
+/// A host's statistics for a heartbeat interval.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct HostStats {
+    /// Bytes received from other hosts.
+    pub recv_bytes: u64,
+    /// Bytes sent to other hosts.
+    pub send_bytes: u64,
+    /// Total time that events were delayed because the host's CPU was busy.
+    pub cpu_delay_ns: u64,
+    /// Sockets connected to a peer at the end of the interval.
+    pub connections: u64,
+}
+
+/// The names of the metrics in the same order as [`HostStats::metrics`].
+const METRICS: [&str; 4] = ["recv-bytes", "send-bytes", "cpu-delay-ns", "connections"];
+
+impl HostStats {
+    fn metrics(&self) -> [u64; 4] {
+        [
+            self.recv_bytes,
+            self.send_bytes,
+            self.cpu_delay_ns,
+            self.connections,
+        ]
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GroupStats {
+    /// The statistics of the hosts of each group, keyed by the heartbeat time and the tag.
+    samples: Mutex<BTreeMap<(EmulatedTime, String), Vec<HostStats>>>,
+}
+
+/// A statistic summarized over the hosts of a group.
+#[derive(Debug, PartialEq)]
+struct Summary {
+    sum: u64,
+    mean: f64,
+    p50: u64,
+    p90: u64,
+    p99: u64,
+}
+
+impl Summary {
+    fn new(values: &mut [u64]) -> Self {
+        assert!(!values.is_empty());
+        values.sort_unstable();
+
+        let sum: u64 = values.iter().sum();
+
+        Self {
+            sum,
+            mean: sum as f64 / values.len() as f64,
+            p50: utility::percentile(values, 50),
+            p90: utility::percentile(values, 90),
+            p99: utility::percentile(values, 99),
+        }
+    }
+}
+
+impl GroupStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a host's statistics for the heartbeat at time `time` to the groups of its `tags`.
+    pub fn add(&self, tags: &[String], time: EmulatedTime, stats: HostStats) {
+        let mut samples = self.samples.lock().unwrap();
+        for tag in tags {
+            samples.entry((time, tag.clone())).or_default().push(stats);
+        }
+    }
+
+    /// Write the statistics of each group to a CSV file in directory `dir`.
+    pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+        let path = dir.join(GROUP_STATS_FILE_NAME);
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create file '{}'", path.display()))?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        self.write_csv(&mut writer)
+            .and_then(|_| writer.flush())
+            .with_context(|| format!("Failed to write file '{}'", path.display()))
+    }
+
+    fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(writer, "time_ns,tag,metric,hosts,sum,mean,p50,p90,p99")?;
+
+        for ((time, tag), stats) in self.samples.lock().unwrap().iter() {
+            let time_ns = (*time - EmulatedTime::SIMULATION_START).as_nanos();
+            for (i, metric) in METRICS.iter().enumerate() {
+                let mut values: Vec<u64> = stats.iter().map(|x| x.metrics()[i]).collect();
+                let summary = Summary::new(&mut values);
+                writeln!(
+                    writer,
+                    "{time_ns},{tag},{metric},{},{},{:.3},{},{},{}",
+                    stats.len(),
+                    summary.sum,
+                    summary.mean,
+                    summary.p50,
+                    summary.p90,
+                    summary.p99,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut values: Vec<u64> = (1..=100).rev().collect();
+        assert_eq!(
+            Summary::new(&mut values),
+            Summary {
+                sum: 5050,
+                mean: 50.5,
+                p50: 50,
+                p90: 90,
+                p99: 99,
+            }
+        );
+
+        assert_eq!(
+            Summary::new(&mut [7]),
+            Summary {
+                sum: 7,
+                mean: 7.0,
+                p50: 7,
+                p90: 7,
+                p99: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let stats = GroupStats::new();
+        let time = EmulatedTime::SIMULATION_START + SimulationTime::from_secs(1);
+        let tags = ["relay".to_string(), "exit".to_string()];
+
+        let host = |recv_bytes| HostStats {
+            recv_bytes,
+            send_bytes: 10,
+            cpu_delay_ns: 0,
+            connections: 2,
+        };
+        stats.add(&tags, time, host(100));
+        stats.add(&tags[..1], time, host(300));
+
+        let mut csv = Vec::new();
+        stats.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "time_ns,tag,metric,hosts,sum,mean,p50,p90,p99");
+        assert_eq!(lines.len(), 1 + 2 * 4);
+        // groups are ordered by tag
+        assert_eq!(
+            lines[1],
+            "1000000000,exit,recv-bytes,1,100,100.000,100,100,100"
+        );
+        assert_eq!(
+            lines[5],
+            "1000000000,relay,recv-bytes,2,400,200.000,100,300,300"
+        );
+        assert_eq!(lines[8], "1000000000,relay,connections,2,4,2.000,2,2,2");
+    }
+}
@@ -0,0 +1,236 @@
+//! Callbacks for significant simulation events, which allow analyzers in other crates to observe a
+//! simulation without modifying Shadow.
+//!
+//! To use them, implement [`SimulationHooks`] and [`register`] it before calling
+//! [`run_shadow`](crate::core::main::run_shadow). The hooks are called from Shadow's worker
+//! threads while the simulation runs, so the events of different hosts may be reported in parallel
+//! and in any order, but the events of each host are reported in the order that they happened.
+//! The hooks run while the host is running, so they should be quick, and they must not call back
+//! into the simulation.
+
+use std::net::SocketAddrV4;
+use std::sync::{Arc, Mutex};
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::HostId;
+
+pub use tcp::TcpStateKind;
+
+use crate::cshadow as c;
+use crate::host::process::{ExitStatus, ProcessId};
+use crate::host::thread::ThreadId;
+use crate::network::packet::PacketRc;
+
+/// The hooks registered with [`register`], which are used by the next simulation.
+static REGISTERED: Mutex<Vec<Arc<dyn SimulationHooks>>> = Mutex::new(Vec::new());
+
+/// Register `hooks` to be called for the events of the simulations that start after this call.
+pub fn register(hooks: Arc<dyn SimulationHooks>) {
+    REGISTERED.lock().unwrap().push(hooks);
+}
+
+/// Callbacks for simulation events. All methods do nothing by default, so implementations only
+/// need to implement the events that they're interested in.
+pub trait SimulationHooks: Send + Sync {
+    /// A packet arrived at a host's inbound queue from the simulated network.
+    fn packet_enqueued(&self, _host: HostId, _time: EmulatedTime, _packet: &PacketInfo) {}
+
+    /// A host took a packet from its inbound queue to pass it to its network interface.
+    fn packet_dequeued(&self, _host: HostId, _time: EmulatedTime, _packet: &PacketInfo) {}
+
+    /// A TCP socket moved from the `old` state to the `new` state. The addresses are unspecified
+    /// if the socket doesn't have them yet. The legacy TCP stack never reports the
+    /// [`TcpStateKind::Init`] and [`TcpStateKind::Rst`] states.
+    fn tcp_state_changed(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _local: SocketAddrV4,
+        _peer: SocketAddrV4,
+        _old: TcpStateKind,
+        _new: TcpStateKind,
+    ) {
+    }
+
+    /// A managed process started running.
+    fn process_started(&self, _host: HostId, _time: EmulatedTime, _pid: ProcessId, _name: &str) {}
+
+    /// A managed process exited.
+    fn process_exited(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _pid: ProcessId,
+        _status: &ExitStatus,
+    ) {
+    }
+
+    /// A thread made a syscall. A syscall that blocks is reported again each time that it's
+    /// resumed, but only reports [`SimulationHooks::syscall_exit`] once it completes.
+    fn syscall_enter(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _pid: ProcessId,
+        _tid: ThreadId,
+        _number: i64,
+    ) {
+    }
+
+    /// A thread's syscall completed with the result `retval`. The result is `None` if Shadow let
+    /// the syscall run natively, in which case Shadow doesn't see its result.
+    fn syscall_exit(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _pid: ProcessId,
+        _tid: ThreadId,
+        _number: i64,
+        _retval: Option<i64>,
+    ) {
+    }
+}
+
+/// The protocol of a packet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PacketProtocol {
+    Tcp,
+    Udp,
+    /// An ICMP echo request or reply. The ports of its addresses are the echo identifier.
+    Icmp,
+}
+
+/// A summary of a packet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PacketInfo {
+    pub protocol: PacketProtocol,
+    pub src: SocketAddrV4,
+    pub dst: SocketAddrV4,
+    /// The size of the packet's headers and payload.
+    pub size: usize,
+    /// The size of the packet's payload.
+    pub payload_size: usize,
+}
+
+impl From<&PacketRc> for PacketInfo {
+    fn from(packet: &PacketRc) -> Self {
+        let protocol = match packet.protocol() {
+            c::_ProtocolType_PTCP => PacketProtocol::Tcp,
+            c::_ProtocolType_PUDP => PacketProtocol::Udp,
+            c::_ProtocolType_PICMP => PacketProtocol::Icmp,
+            x => panic!("Unexpected packet protocol {x}"),
+        };
+        Self {
+            protocol,
+            src: packet.src_address(),
+            dst: packet.dst_address(),
+            size: packet.total_size(),
+            payload_size: packet.payload_size(),
+        }
+    }
+}
+
+/// The hooks used by a simulation.
+pub struct Hooks {
+    hooks: Vec<Arc<dyn SimulationHooks>>,
+}
+
+impl Hooks {
+    /// The hooks that have been registered, or `None` if no hooks have been registered.
+    pub fn registered() -> Option<Self> {
+        let hooks = REGISTERED.lock().unwrap().clone();
+        (!hooks.is_empty()).then_some(Self { hooks })
+    }
+
+    /// Call `f` for each of the hooks.
+    pub fn call(&self, mut f: impl FnMut(&dyn SimulationHooks)) {
+        for hooks in &self.hooks {
+            f(&**hooks);
+        }
+    }
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("len", &self.hooks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Converts the name of one of the legacy TCP stack's states, such as "TCPS_ESTABLISHED", to a
+/// [`TcpStateKind`].
+pub fn legacy_tcp_state_kind(name: &str) -> Option<TcpStateKind> {
+    Some(match name {
+        "TCPS_CLOSED" => TcpStateKind::Closed,
+        "TCPS_LISTEN" => TcpStateKind::Listen,
+        "TCPS_SYNSENT" => TcpStateKind::SynSent,
+        "TCPS_SYNRECEIVED" => TcpStateKind::SynReceived,
+        "TCPS_ESTABLISHED" => TcpStateKind::Established,
+        "TCPS_FINWAIT1" => TcpStateKind::FinWaitOne,
+        "TCPS_FINWAIT2" => TcpStateKind::FinWaitTwo,
+        "TCPS_CLOSING" => TcpStateKind::Closing,
+        "TCPS_TIMEWAIT" => TcpStateKind::TimeWait,
+        "TCPS_CLOSEWAIT" => TcpStateKind::CloseWait,
+        "TCPS_LASTACK" => TcpStateKind::LastAck,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountStarts(AtomicU32);
+
+    impl SimulationHooks for CountStarts {
+        fn process_started(&self, _host: HostId, _time: EmulatedTime, _pid: ProcessId, _: &str) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        let a = Arc::new(CountStarts::default());
+        let b = Arc::new(CountStarts::default());
+        let hooks = Hooks {
+            hooks: vec![a.clone(), b.clone()],
+        };
+
+        let pid = ProcessId::try_from(1000u32).unwrap();
+        hooks.call(|x| x.process_started(HostId::from(0), EmulatedTime::SIMULATION_START, pid, ""));
+        // hooks that don't implement an event ignore it
+        hooks.call(|x| {
+            x.packet_dequeued(
+                HostId::from(0),
+                EmulatedTime::SIMULATION_START,
+                &PacketInfo {
+                    protocol: PacketProtocol::Udp,
+                    src: "1.2.3.4:5".parse().unwrap(),
+                    dst: "5.6.7.8:9".parse().unwrap(),
+                    size: 28,
+                    payload_size: 0,
+                },
+            )
+        });
+
+        assert_eq!(a.0.load(Ordering::Relaxed), 1);
+        assert_eq!(b.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_legacy_tcp_state_kind() {
+        assert_eq!(
+            legacy_tcp_state_kind("TCPS_ESTABLISHED"),
+            Some(TcpStateKind::Established)
+        );
+        assert_eq!(
+            legacy_tcp_state_kind("TCPS_FINWAIT2"),
+            Some(TcpStateKind::FinWaitTwo)
+        );
+        assert_eq!(legacy_tcp_state_kind("ESTABLISHED"), None);
+    }
+}
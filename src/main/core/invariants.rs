@@ -0,0 +1,331 @@
+//! Invariants that an experiment declares about the simulation, such as "host A never sends to
+//! host B". Shadow checks them while the simulation runs and writes a pass/fail result for each
+//! invariant to the data directory, which allows Shadow to be used as a test oracle for
+//! distributed systems.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Serialize;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+use shadow_shim_helper_rs::HostId;
+
+use crate::core::support::configuration::InvariantOptions;
+
+pub const RESULTS_FILE_NAME: &str = "invariants.json";
+
+#[derive(Debug)]
+pub struct Invariants {
+    invariants: Vec<Invariant>,
+}
+
+#[derive(Debug)]
+struct Invariant {
+    description: String,
+    check: Check,
+    status: Mutex<Status>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Check {
+    NeverSends {
+        from: HostId,
+        to: HostId,
+    },
+    MaxQueueLength {
+        host: HostId,
+        max: usize,
+    },
+    ConnectsWithin {
+        host: HostId,
+        deadline: EmulatedTime,
+    },
+}
+
+#[derive(Debug, Default)]
+struct Status {
+    violations: u64,
+    /// The earliest violation. Worker threads may report violations in any order, so we keep the
+    /// earliest rather than the first reported to keep the results deterministic.
+    first_violation: Option<Violation>,
+    first_connection: Option<EmulatedTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct Violation {
+    /// Nanoseconds of simulated time since the start of the simulation.
+    time_ns: u64,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ResultsFile<'a> {
+    passed: usize,
+    failed: usize,
+    invariants: Vec<InvariantResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct InvariantResult<'a> {
+    invariant: &'a str,
+    passed: bool,
+    violations: u64,
+    first_violation: Option<Violation>,
+}
+
+impl Invariants {
+    /// Create the invariants from the configuration. `host_ids` maps each host name to its ID.
+    pub fn new(
+        options: &[InvariantOptions],
+        host_ids: &HashMap<&str, HostId>,
+    ) -> anyhow::Result<Self> {
+        let invariants = options
+            .iter()
+            .map(|x| Invariant::new(x, host_ids))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { invariants })
+    }
+
+    /// Called when host `src` sends a packet to host `dst`.
+    pub fn packet_sent(&self, src: HostId, dst: HostId, time: EmulatedTime) {
+        for invariant in &self.invariants {
+            if let Check::NeverSends { from, to } = invariant.check {
+                if (from, to) == (src, dst) {
+                    invariant.violated(time, "sent a packet".into());
+                }
+            }
+        }
+    }
+
+    /// Called when a packet is added to the inbound packet queue of `host`, which now holds `len`
+    /// packets.
+    pub fn queue_length(&self, host: HostId, len: usize, time: EmulatedTime) {
+        for invariant in &self.invariants {
+            if let Check::MaxQueueLength { host: x, max } = invariant.check {
+                if x == host && len > max {
+                    invariant.violated(time, format!("queue held {len} packets"));
+                }
+            }
+        }
+    }
+
+    /// Called when `host` establishes a connection.
+    pub fn connection_established(&self, host: HostId, time: EmulatedTime) {
+        for invariant in &self.invariants {
+            if let Check::ConnectsWithin { host: x, .. } = invariant.check {
+                if x == host {
+                    let mut status = invariant.status.lock().unwrap();
+                    let first = status.first_connection.get_or_insert(time);
+                    *first = std::cmp::min(*first, time);
+                }
+            }
+        }
+    }
+
+    /// Check the invariants that can only be checked once the simulation has finished, and write
+    /// the results to the directory `dir`. Returns the number of invariants that were violated.
+    pub fn finish(&self, dir: &Path) -> anyhow::Result<usize> {
+        for invariant in &self.invariants {
+            if let Check::ConnectsWithin { deadline, .. } = invariant.check {
+                let first_connection = invariant.status.lock().unwrap().first_connection;
+                match first_connection {
+                    Some(time) if time <= deadline => {}
+                    Some(time) => invariant.violated(
+                        deadline,
+                        format!("first connection was at {} ns", nanos_since_start(time)),
+                    ),
+                    None => invariant.violated(deadline, "no connection was established".into()),
+                }
+            }
+        }
+
+        let results: Vec<_> = self
+            .invariants
+            .iter()
+            .map(|x| {
+                let status = x.status.lock().unwrap();
+                InvariantResult {
+                    invariant: &x.description,
+                    passed: status.violations == 0,
+                    violations: status.violations,
+                    first_violation: status.first_violation.clone(),
+                }
+            })
+            .collect();
+
+        for result in results.iter().filter(|x| !x.passed) {
+            let first = result.first_violation.as_ref().unwrap();
+            log::error!(
+                "Invariant '{}' was violated {} times, first at {} ns: {}",
+                result.invariant,
+                result.violations,
+                first.time_ns,
+                first.message,
+            );
+        }
+
+        let failed = results.iter().filter(|x| !x.passed).count();
+        let file = ResultsFile {
+            passed: results.len() - failed,
+            failed,
+            invariants: results,
+        };
+
+        let path = dir.join(RESULTS_FILE_NAME);
+        let json = serde_json::to_string_pretty(&file)?;
+        std::fs::write(&path, json + "\n")
+            .with_context(|| format!("Failed to write file '{}'", path.display()))?;
+
+        Ok(failed)
+    }
+}
+
+impl Invariant {
+    fn new(options: &InvariantOptions, host_ids: &HashMap<&str, HostId>) -> anyhow::Result<Self> {
+        let host_id = |name: &str| {
+            host_ids
+                .get(name)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Invariant has an unknown host '{name}'"))
+        };
+
+        let (description, check) = match options {
+            InvariantOptions::NeverSends { from, to } => (
+                format!("host '{from}' never sends to host '{to}'"),
+                Check::NeverSends {
+                    from: host_id(from)?,
+                    to: host_id(to)?,
+                },
+            ),
+            InvariantOptions::MaxQueueLength { host, max } => (
+                format!("host '{host}' never queues more than {max} inbound packets"),
+                Check::MaxQueueLength {
+                    host: host_id(host)?,
+                    max: *max,
+                },
+            ),
+            InvariantOptions::ConnectsWithin { host, within } => {
+                let description = format!("host '{host}' connects within {within}");
+                let within: Duration = (*within).into();
+                let within = SimulationTime::try_from(within)
+                    .ok()
+                    .context("Invariant has an out of range time")?;
+                (
+                    description,
+                    Check::ConnectsWithin {
+                        host: host_id(host)?,
+                        deadline: EmulatedTime::SIMULATION_START + within,
+                    },
+                )
+            }
+        };
+
+        Ok(Self {
+            description,
+            check,
+            status: Mutex::new(Status::default()),
+        })
+    }
+
+    fn violated(&self, time: EmulatedTime, message: String) {
+        let violation = Violation {
+            time_ns: nanos_since_start(time),
+            message,
+        };
+
+        let mut status = self.status.lock().unwrap();
+        status.violations += 1;
+        if status
+            .first_violation
+            .as_ref()
+            .map_or(true, |x| violation < *x)
+        {
+            status.first_violation = Some(violation);
+        }
+    }
+}
+
+fn nanos_since_start(time: EmulatedTime) -> u64 {
+    (time - EmulatedTime::SIMULATION_START)
+        .as_nanos()
+        .try_into()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time_ms(ms: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_results() {
+        let a = HostId::from(0);
+        let b = HostId::from(1);
+        let host_ids = HashMap::from([("a", a), ("b", b)]);
+
+        let options: Vec<InvariantOptions> = serde_yaml::from_str(
+            "
+            - type: never-sends
+              from: a
+              to: b
+            - type: max-queue-length
+              host: b
+              max: 10
+            - type: connects-within
+              host: a
+              within: 30 s
+            - type: connects-within
+              host: b
+              within: 1 s
+            ",
+        )
+        .unwrap();
+        let invariants = Invariants::new(&options, &host_ids).unwrap();
+
+        invariants.packet_sent(b, a, time_ms(1));
+        invariants.packet_sent(a, b, time_ms(3));
+        invariants.packet_sent(a, b, time_ms(2));
+        invariants.queue_length(b, 10, time_ms(4));
+        invariants.connection_established(a, time_ms(20_000));
+        invariants.connection_established(b, time_ms(2_000));
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(invariants.finish(dir.path()).unwrap(), 2);
+
+        let results = std::fs::read_to_string(dir.path().join(RESULTS_FILE_NAME)).unwrap();
+        let results: serde_json::Value = serde_json::from_str(&results).unwrap();
+        assert_eq!(results["passed"], 2);
+        assert_eq!(results["failed"], 2);
+
+        let never_sends = &results["invariants"][0];
+        assert_eq!(never_sends["passed"], false);
+        assert_eq!(never_sends["violations"], 2);
+        assert_eq!(never_sends["first_violation"]["time_ns"], 2_000_000);
+
+        assert_eq!(results["invariants"][1]["passed"], true);
+        assert_eq!(results["invariants"][2]["passed"], true);
+
+        let connects = &results["invariants"][3];
+        assert_eq!(connects["passed"], false);
+        assert_eq!(
+            connects["first_violation"]["message"],
+            "first connection was at 2000000000 ns"
+        );
+    }
+
+    #[test]
+    fn test_unknown_host() {
+        let options = InvariantOptions::MaxQueueLength {
+            host: "missing".into(),
+            max: 1,
+        };
+        assert!(Invariant::new(&options, &HashMap::new()).is_err());
+    }
+}
@@ -13,8 +13,10 @@ use crate::core::controller::Controller;
 use crate::core::logger::shadow_logger;
 use crate::core::sim_config::SimConfig;
 use crate::core::support::configuration::{CliOptions, ConfigFileOptions, ConfigOptions};
+use crate::core::trials;
 use crate::core::worker;
 use crate::cshadow as c;
+use crate::host::host::HostSetupError;
 use crate::utility::shm_cleanup;
 
 fn verify_supported_system() -> anyhow::Result<()> {
@@ -122,9 +124,17 @@ pub fn run_shadow(build_info: &ShadowBuildInfo, args: Vec<&OsStr>) -> anyhow::Re
     .into();
 
     // load the configuration yaml
-    let config_file = load_config_file(&config_filename, true)
+    let mut config_file = load_config_file(&config_filename, true)
         .with_context(|| format!("Failed to load configuration file {}", config_filename))?;
 
+    // apply the tags and instrumentation profiles before the host option defaults are merged
+    config_file
+        .apply_tags()
+        .context("Failed to apply the host tags")?;
+    config_file
+        .apply_instrumentation_profiles()
+        .context("Failed to apply the instrumentation profiles")?;
+
     // generate the final shadow configuration from the config file and cli options
     let shadow_config = ConfigOptions::new(config_file, options.clone());
 
@@ -231,6 +241,18 @@ pub fn run_shadow(build_info: &ShadowBuildInfo, args: Vec<&OsStr>) -> anyhow::Re
 
     log::debug!("Startup checks passed, we are ready to start the simulation");
 
+    // run each trial as a separate shadow process
+    if let Some(num_trials) = options.trials {
+        if config_filename == "/dev/stdin" {
+            anyhow::bail!(
+                "The '--trials' option cannot be used when reading the config from stdin"
+            );
+        }
+
+        return trials::run_trials(&shadow_config, &args, num_trials, options.trial_jobs)
+            .context("Failed to run the simulation trials");
+    }
+
     // allow gdb to attach before starting the simulation
     if options.gdb {
         pause_for_gdb_attach().context("Could not pause shadow to allow gdb to attach")?;
@@ -397,6 +419,22 @@ mod export {
         log::logger().flush();
 
         if let Err(e) = result {
+            // host setup errors are usually configuration mistakes, so show a short report
+            // without the backtrace
+            if let Some(e) = e.chain().find_map(|x| x.downcast_ref::<HostSetupError>()) {
+                let report = e.report();
+                for line in report.split('\n') {
+                    log::error!("{}", line);
+                }
+                log::logger().flush();
+
+                eprintln!("** Shadow did not complete successfully");
+                for line in report.split('\n') {
+                    eprintln!("**   {}", line);
+                }
+                return 1;
+            }
+
             // log the full error, its context, and its backtrace if enabled
             if log::log_enabled!(log::Level::Error) {
                 for line in format!("{:?}", e).split('\n') {
@@ -1,9 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{CStr, CString, OsStr, OsString};
 use std::os::unix::ffi::OsStrExt;
-use std::path::PathBuf;
-use std::sync::atomic::AtomicU32;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::{self, Context};
@@ -18,21 +18,38 @@ use shadow_shim_helper_rs::util::SyncSendPointer;
 use shadow_shim_helper_rs::HostId;
 use shadow_shmem::allocator::ShMemBlock;
 
+use crate::core::breakpoints::Breakpoints;
+use crate::core::control::{FirewallRegistry, PauseHandle};
 use crate::core::controller::{Controller, ShadowStatusBarState, SimController};
 use crate::core::cpu;
+use crate::core::event_trace::EventTrace;
+use crate::core::group_stats::GroupStats;
+use crate::core::hooks::Hooks;
+use crate::core::invariants::{self, Invariants};
 use crate::core::resource_usage;
 use crate::core::scheduler::runahead::Runahead;
 use crate::core::scheduler::{HostIter, Scheduler, ThreadPerCoreSched, ThreadPerHostSched};
-use crate::core::sim_config::{Bandwidth, HostInfo};
+#[cfg(feature = "tor_helpers")]
+use crate::core::sim_config::TorHost;
+use crate::core::sim_config::{Bandwidth, HostInfo, PortRemap};
 use crate::core::sim_stats;
 use crate::core::support::configuration::{self, ConfigOptions, Flatten};
 use crate::core::worker;
 use crate::cshadow as c;
-use crate::host::host::{Host, HostParameters};
+use crate::host::host::{Host, HostParameters, HostSetupError};
+use crate::host::probe::ProbeResults;
+use crate::host::replay::TraceReplay;
+use crate::network::graph::censors::Censors;
+use crate::network::graph::export::{self as topology_export, FlowVolumes, Topology};
+use crate::network::graph::observers::Observers;
 use crate::network::graph::{IpAssignment, RoutingInfo};
 use crate::utility;
 use crate::utility::childpid_watcher::ChildPidWatcher;
 use crate::utility::status_bar::Status;
+use crate::utility::tls_certs::CertAuthority;
+
+/// The file in the data directory that lists the hosts and their metadata.
+pub const HOSTS_MANIFEST_FILE_NAME: &str = "hosts.json";
 
 pub struct Manager<'a> {
     manager_config: Option<ManagerConfig>,
@@ -48,10 +65,18 @@ pub struct Manager<'a> {
 
     preload_paths: Arc<Vec<PathBuf>>,
 
+    // issues each host's TLS certificate, if enabled
+    tls_ca: Option<CertAuthority>,
+
     check_fd_usage: bool,
     check_mem_usage: bool,
 
+    // whether we've already warned that we're close to or over the memory budget
+    warned_near_memory_budget: bool,
+    warned_over_memory_budget: bool,
+
     meminfo_file: std::fs::File,
+    process_status_file: std::fs::File,
     shmem: ShMemBlock<'static, ManagerShmem>,
 }
 
@@ -189,8 +214,33 @@ impl<'a> Manager<'a> {
             )
         })?;
 
+        // save the hosts' addresses and metadata for analysis tools
+        let manifest_filename = data_path.join(HOSTS_MANIFEST_FILE_NAME);
+        write_hosts_manifest(&manifest_filename, &manager_config.hosts).with_context(|| {
+            format!(
+                "Failed to write the hosts manifest to file '{}'",
+                manifest_filename.display()
+            )
+        })?;
+
+        let tls_ca = if config.experimental.generate_tls_certs.unwrap() {
+            let tls_path = data_path.join("tls");
+            log::info!(
+                "Generating a TLS certificate authority in '{}'",
+                tls_path.display()
+            );
+            Some(
+                CertAuthority::generate(&tls_path, config.general.seed.unwrap().into())
+                    .context("Failed to generate the TLS certificate authority")?,
+            )
+        } else {
+            None
+        };
+
         let meminfo_file =
             std::fs::File::open("/proc/meminfo").context("Failed to open '/proc/meminfo'")?;
+        let process_status_file = std::fs::File::open("/proc/self/status")
+            .context("Failed to open '/proc/self/status'")?;
 
         let shmem = shadow_shmem::allocator::shmalloc(ManagerShmem {
             log_start_time_micros: unsafe { c::logger_get_global_start_time_micros() },
@@ -206,9 +256,13 @@ impl<'a> Manager<'a> {
             data_path,
             hosts_path,
             preload_paths: Arc::new(preload_paths),
+            tls_ca,
             check_fd_usage: true,
             check_mem_usage: true,
+            warned_near_memory_budget: false,
+            warned_over_memory_budget: false,
             meminfo_file,
+            process_status_file,
             shmem,
         })
     }
@@ -251,6 +305,42 @@ impl<'a> Manager<'a> {
             x => x.try_into().unwrap(),
         };
 
+        // the ID that each host will be built with
+        let host_ids: HashMap<&str, HostId> = manager_config
+            .hosts
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (x.name.as_str(), HostId::from(u32::try_from(i).unwrap())))
+            .collect();
+
+        let breakpoints = self
+            .config
+            .experimental
+            .breakpoints
+            .as_deref()
+            .unwrap_or_default();
+        let breakpoints = if breakpoints.is_empty() {
+            None
+        } else {
+            let pause_handle = manager_config
+                .pause_handle
+                .take()
+                .context("The 'breakpoints' option requires the 'control_socket' option")?;
+            Some(Breakpoints::new(breakpoints, &host_ids, pause_handle)?)
+        };
+
+        let invariants = self
+            .config
+            .experimental
+            .invariants
+            .as_deref()
+            .unwrap_or_default();
+        let invariants = if invariants.is_empty() {
+            None
+        } else {
+            Some(Invariants::new(invariants, &host_ids)?)
+        };
+
         // note: there are several return points before we add these hosts to the scheduler and we
         // would leak memory if we return before then, but not worrying about that since the issues
         // will go away when we move the hosts to rust, and if we don't add them to the scheduler
@@ -265,6 +355,14 @@ impl<'a> Manager<'a> {
             })
             .collect::<anyhow::Result<_>>()?;
 
+        if let Some(registry) = &manager_config.firewall_registry {
+            for host in &hosts {
+                if let Some(firewall) = host.firewall() {
+                    registry.register(host.name(), Arc::clone(firewall));
+                }
+            }
+        }
+
         // shuffle the list of hosts to make sure that they are randomly assigned by the scheduler
         hosts.shuffle(&mut manager_config.random);
 
@@ -294,6 +392,42 @@ impl<'a> Manager<'a> {
         }
         assert_eq!(cpus.len(), parallelism);
 
+        // only record the traffic between hosts if it will be exported
+        let topology = manager_config.topology.take();
+        let flow_volumes = topology.as_ref().map(|_| {
+            let interval = self
+                .config
+                .network
+                .topology_export
+                .as_ref()
+                .and_then(|x| x.flow_interval)
+                .map(|x| {
+                    let x: Duration = x.into();
+                    SimulationTime::try_from(x).unwrap()
+                });
+            FlowVolumes::new(interval)
+        });
+
+        let observers = manager_config.observers.take();
+        let censors = manager_config.censors.take();
+
+        let use_event_trace = self.config.experimental.use_event_trace.unwrap();
+        let event_trace = use_event_trace
+            .then(|| EventTrace::new(hosts.iter().map(|x| (x.id(), x.name())), parallelism));
+
+        let group_stats = self
+            .config
+            .experimental
+            .host_heartbeat_group_stats
+            .unwrap()
+            .then(GroupStats::new);
+
+        let probe_results = manager_config
+            .hosts
+            .iter()
+            .any(|x| !x.probes.is_empty())
+            .then(ProbeResults::new);
+
         // set the simulation's global state
         worker::WORKER_SHARED
             .borrow_mut()
@@ -301,6 +435,7 @@ impl<'a> Manager<'a> {
                 ip_assignment: manager_config.ip_assignment,
                 routing_info: manager_config.routing_info,
                 host_bandwidths: manager_config.host_bandwidths,
+                broadcast_domains: manager_config.broadcast_domains,
                 // safe since the DNS type has an internal mutex
                 dns: unsafe { SyncSendPointer::new(dns) },
                 num_plugin_errors: AtomicU32::new(0),
@@ -318,8 +453,20 @@ impl<'a> Manager<'a> {
                     .collect(),
                 bootstrap_end_time,
                 sim_end_time: self.end_time,
+                flow_volumes,
+                observers,
+                censors,
+                event_trace,
+                group_stats,
+                probe_results,
+                breakpoints,
+                invariants,
+                hooks: Hooks::registered(),
             });
 
+        // set if the simulation was stopped before the configured stop time
+        let mut stopped_early = false;
+
         // scope used so that the scheduler is dropped before we log the global counters below
         {
             let mut scheduler = match self.config.experimental.scheduler.unwrap() {
@@ -342,21 +489,31 @@ impl<'a> Manager<'a> {
                 });
             });
 
+            // the first error from booting a host, if any
+            let boot_error: Mutex<Option<HostSetupError>> = Mutex::new(None);
+
             // boot each host
             scheduler.scope(|s| {
-                s.run_with_hosts(move |_, hosts| {
+                s.run_with_hosts(|_, hosts| {
                     for_each_host(hosts, |host| {
                         worker::Worker::set_current_time(EmulatedTime::SIMULATION_START);
                         host.lock_shmem();
-                        host.boot();
+                        let rv = host.boot();
                         host.unlock_shmem();
                         worker::Worker::clear_current_time();
+
+                        if let Err(e) = rv {
+                            boot_error.lock().unwrap().get_or_insert(e);
+                        }
                     });
                 });
             });
 
-            // the current simulation interval
-            let mut window = Some((
+            let boot_error = boot_error.into_inner().unwrap();
+
+            // the current simulation interval; if a host couldn't boot, we skip straight to
+            // shutting down the hosts
+            let mut window = boot_error.is_none().then_some((
                 EmulatedTime::SIMULATION_START,
                 EmulatedTime::SIMULATION_START + SimulationTime::NANOSECOND,
             ));
@@ -374,8 +531,25 @@ impl<'a> Manager<'a> {
                 .flatten()
                 .map(|x| Duration::from(x).try_into().unwrap());
 
+            // the number of runnable hosts for each active worker thread, if autoscaling the
+            // number of active worker threads
+            let worker_autoscale_hosts = self
+                .config
+                .experimental
+                .worker_autoscale_hosts
+                .flatten()
+                .map(|x| usize::try_from(x.get()).unwrap());
+
+            // the number of hosts that had events during the current round
+            let num_runnable_hosts = AtomicUsize::new(0);
+
+            let memory_budget = self.config.memory_budget();
+            let memory_budget_action = self.config.experimental.memory_budget_action.unwrap();
+
             let mut last_heartbeat = EmulatedTime::SIMULATION_START;
             let mut time_of_last_usage_check = std::time::Instant::now();
+            let mut time_of_last_budget_check: Option<std::time::Instant> = None;
+            let mut over_memory_budget = false;
 
             // the scheduling loop
             while let Some((window_start, window_end)) = window {
@@ -389,6 +563,9 @@ impl<'a> Manager<'a> {
                         state.current = display_time;
                     });
 
+                let round_start = use_event_trace.then(std::time::Instant::now);
+                let num_runnable_hosts = &num_runnable_hosts;
+
                 // run the events
                 scheduler.scope(|s| {
                     // run the closure on each of the scheduler's threads
@@ -396,15 +573,21 @@ impl<'a> Manager<'a> {
                         &thread_next_event_times,
                         // each call of the closure is given an abstract thread-specific host
                         // iterator, and an element of 'thread_next_event_times'
-                        move |_, hosts, next_event_time| {
+                        move |thread_idx, hosts, next_event_time| {
+                            let busy_start = use_event_trace.then(std::time::Instant::now);
                             let mut next_event_time = next_event_time.borrow_mut();
 
                             worker::Worker::reset_next_event_time();
                             worker::Worker::set_round_end_time(window_end);
 
+                            let mut num_runnable = 0;
+
                             for_each_host(hosts, |host| {
                                 let host_next_event_time = {
                                     host.lock_shmem();
+                                    if host.next_event_time().is_some_and(|x| x < window_end) {
+                                        num_runnable += 1;
+                                    }
                                     host.execute(window_end);
                                     let host_next_event_time = host.next_event_time();
                                     host.unlock_shmem();
@@ -416,12 +599,21 @@ impl<'a> Manager<'a> {
                                     .reduce(std::cmp::min);
                             });
 
+                            num_runnable_hosts.fetch_add(num_runnable, Ordering::Relaxed);
+
                             let packet_next_event_time = worker::Worker::get_next_event_time();
 
                             *next_event_time = [*next_event_time, packet_next_event_time]
                                 .into_iter()
                                 .flatten() // filter out None
                                 .reduce(std::cmp::min);
+
+                            if let Some(busy_start) = busy_start {
+                                worker::Worker::with_event_trace(|event_trace| {
+                                    let now = std::time::Instant::now();
+                                    event_trace.worker_busy(thread_idx, busy_start, now);
+                                });
+                            }
                         },
                     );
 
@@ -441,8 +633,26 @@ impl<'a> Manager<'a> {
                         time_of_last_usage_check = current_time;
                         self.check_resource_usage();
                     }
+
+                    // check the memory budget in the first round and then every real second
+                    if let Some(memory_budget) = memory_budget {
+                        let check_due = time_of_last_budget_check.map_or(true, |t| {
+                            current_time.duration_since(t) > Duration::from_secs(1)
+                        });
+                        if check_due {
+                            time_of_last_budget_check = Some(current_time);
+                            over_memory_budget = self.check_memory_budget(memory_budget);
+                        }
+                    }
                 });
 
+                if let Some(round_start) = round_start {
+                    let shared = worker::WORKER_SHARED.borrow();
+                    let event_trace = shared.as_ref().unwrap().event_trace.as_ref().unwrap();
+                    let now = std::time::Instant::now();
+                    event_trace.round(round_start, now, (window_start, window_end));
+                }
+
                 // get the minimum next event time for all threads (also resets the next event times
                 // to None while we have them borrowed)
                 let min_next_event_time = thread_next_event_times
@@ -459,13 +669,37 @@ impl<'a> Manager<'a> {
                     (min_next_event_time - EmulatedTime::SIMULATION_START).as_nanos(),
                 );
 
+                // use fewer worker threads during rounds with few runnable hosts, which doesn't
+                // affect the simulation since any thread may run any host
+                let num_runnable = num_runnable_hosts.swap(0, Ordering::Relaxed);
+                if let Some(worker_autoscale_hosts) = worker_autoscale_hosts {
+                    scheduler.set_active_threads(num_runnable.div_ceil(worker_autoscale_hosts));
+                }
+
                 // notify controller that we finished this round, and the time of our next event in
                 // order to fast-forward our execute window if possible
                 window = self
                     .controller
-                    .manager_finished_current_round(min_next_event_time);
+                    .manager_finished_current_round(window_end, min_next_event_time);
+
+                if over_memory_budget
+                    && memory_budget_action == configuration::MemoryBudgetAction::Stop
+                    && window.is_some()
+                {
+                    log::error!(
+                        "Stopping the simulation early at simtime {} ns since Shadow exceeded its \
+                        memory budget",
+                        (window_end - EmulatedTime::SIMULATION_START).as_nanos(),
+                    );
+                    self.end_time = window_end;
+                    stopped_early = true;
+                    window = None;
+                }
             }
 
+            // shut down the hosts on every thread
+            scheduler.set_active_threads(scheduler.parallelism());
+
             scheduler.scope(|s| {
                 s.run_with_hosts(move |_, hosts| {
                     for_each_host(hosts, |host| {
@@ -485,6 +719,10 @@ impl<'a> Manager<'a> {
             });
 
             scheduler.join();
+
+            if let Some(e) = boot_error {
+                return Err(e.into());
+            }
         }
 
         // simulation is finished, so update the status logger
@@ -502,6 +740,66 @@ impl<'a> Manager<'a> {
             .unwrap()
             .plugin_error_count();
 
+        if let Some(topology) = &topology {
+            let shared = worker::WORKER_SHARED.borrow();
+            let flow_volumes = shared.as_ref().unwrap().flow_volumes.as_ref().unwrap();
+            let ns3_stop_time = self
+                .config
+                .network
+                .topology_export
+                .as_ref()
+                .is_some_and(|x| x.ns3)
+                .then(|| {
+                    self.end_time
+                        .duration_since(&EmulatedTime::SIMULATION_START)
+                });
+            topology_export::export(&self.data_path, topology, flow_volumes, ns3_stop_time)
+                .context("Failed to export the network topology")?;
+        }
+
+        if let Some(observers) = &worker::WORKER_SHARED.borrow().as_ref().unwrap().observers {
+            observers
+                .write(
+                    &self.data_path,
+                    self.config.network.observer_compression.unwrap(),
+                )
+                .context("Failed to write the network observers' records")?;
+        }
+
+        if let Some(event_trace) = &worker::WORKER_SHARED.borrow().as_ref().unwrap().event_trace {
+            event_trace
+                .write(&self.data_path)
+                .context("Failed to write the event trace")?;
+        }
+
+        if let Some(group_stats) = &worker::WORKER_SHARED.borrow().as_ref().unwrap().group_stats {
+            group_stats
+                .write(&self.data_path)
+                .context("Failed to write the host group statistics")?;
+        }
+
+        if let Some(probe_results) = &worker::WORKER_SHARED
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .probe_results
+        {
+            probe_results.log_summary();
+            probe_results
+                .write(&self.data_path)
+                .context("Failed to write the probe results")?;
+        }
+
+        let num_failed_invariants = if let Some(invariants) =
+            &worker::WORKER_SHARED.borrow().as_ref().unwrap().invariants
+        {
+            invariants
+                .finish(&self.data_path)
+                .context("Failed to write the invariant results")?
+        } else {
+            0
+        };
+
         // drop the simulation's global state
         // must drop before the allocation counters have been checked
         worker::WORKER_SHARED.borrow_mut().take();
@@ -534,6 +832,21 @@ impl<'a> Manager<'a> {
             sim_stats::write_stats_to_file(&stats_filename, stats)
         })?;
 
+        if num_failed_invariants > 0 {
+            return Err(anyhow::anyhow!(
+                "{num_failed_invariants} invariants were violated; see '{}'",
+                self.data_path.join(invariants::RESULTS_FILE_NAME).display()
+            ));
+        }
+
+        if stopped_early {
+            return Err(anyhow::anyhow!(
+                "The simulation was stopped early at simtime {} ns since Shadow exceeded its \
+                memory budget; the results are incomplete",
+                (self.end_time - EmulatedTime::SIMULATION_START).as_nanos(),
+            ));
+        }
+
         Ok(num_plugin_errors)
     }
 
@@ -543,61 +856,16 @@ impl<'a> Manager<'a> {
         host_info: &HostInfo,
         dns: *mut c::DNS,
     ) -> anyhow::Result<Box<Host>> {
-        let hostname = CString::new(&*host_info.name).unwrap();
-
         // scope used to enforce drop order for pointers
         let host = {
-            let params = HostParameters {
-                // the manager sets this ID
-                id: host_id,
-                // the manager sets this CPU frequency
-                cpu_frequency: self.raw_frequency,
-                node_seed: host_info.seed,
-                hostname,
-                node_id: host_info.network_node_id,
-                ip_addr: match host_info.ip_addr.unwrap() {
-                    std::net::IpAddr::V4(ip) => u32::to_be(ip.into()),
-                    // the config only allows ipv4 addresses, so this shouldn't happen
-                    std::net::IpAddr::V6(_) => unreachable!("IPv6 not supported"),
-                },
-                sim_end_time: self.end_time,
-                requested_bw_down_bits: host_info.bandwidth_down_bits.unwrap(),
-                requested_bw_up_bits: host_info.bandwidth_up_bits.unwrap(),
-                cpu_threshold: host_info.cpu_threshold,
-                cpu_precision: host_info.cpu_precision,
-                heartbeat_interval: host_info.heartbeat_interval,
-                heartbeat_log_level: host_info
-                    .heartbeat_log_level
-                    .map(|x| x.to_c_loglevel())
-                    .unwrap_or(c::_LogLevel_LOGLEVEL_UNSET),
-                heartbeat_log_info: host_info
-                    .heartbeat_log_info
-                    .iter()
-                    .map(|x| x.to_c_loginfoflag())
-                    .reduce(|x, y| x | y)
-                    .unwrap_or(c::_LogInfoFlags_LOG_INFO_FLAGS_NONE),
-                log_level: host_info
-                    .log_level
-                    .map(|x| x.to_c_loglevel())
-                    .unwrap_or(c::_LogLevel_LOGLEVEL_UNSET),
-                pcap_config: host_info.pcap_config,
-                qdisc: host_info.qdisc,
-                init_sock_recv_buf_size: host_info.recv_buf_size,
-                autotune_recv_buf: host_info.autotune_recv_buf,
-                init_sock_send_buf_size: host_info.send_buf_size,
-                autotune_send_buf: host_info.autotune_send_buf,
-                native_tsc_frequency: self.native_tsc_frequency,
-                model_unblocked_syscall_latency: self.config.model_unblocked_syscall_latency(),
-                max_unapplied_cpu_latency: self.config.max_unapplied_cpu_latency(),
-                unblocked_syscall_latency: self.config.unblocked_syscall_latency(),
-                unblocked_vdso_latency: self.config.unblocked_vdso_latency(),
-                strace_logging_options: self.config.strace_logging_mode(),
-                shim_log_level: host_info
-                    .log_level
-                    .unwrap_or_else(|| self.config.general.log_level.unwrap())
-                    .to_c_loglevel(),
-                use_new_tcp: self.config.experimental.use_new_tcp.unwrap(),
-            };
+            let params = host_parameters(
+                &self.config,
+                host_id,
+                host_info,
+                self.raw_frequency,
+                self.native_tsc_frequency,
+                self.end_time,
+            );
 
             Box::new(unsafe {
                 Host::new(
@@ -608,12 +876,92 @@ impl<'a> Manager<'a> {
                     self.shmem(),
                     self.preload_paths.clone(),
                 )
-            })
+            }?)
         };
 
+        // populate the host's data directory before any processes start
+        if let Some(template) = &host_info.data_template {
+            let ip_addr = host_info.ip_addr.unwrap().to_string();
+            let vars = [
+                ("SHADOW_HOSTNAME", host_info.name.as_str()),
+                ("SHADOW_IP_ADDR", ip_addr.as_str()),
+            ];
+
+            utility::data_template::apply_data_template(
+                &template.path,
+                host.data_dir_path(),
+                template.mode,
+                &vars,
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to apply data template '{}' to host '{}'",
+                    template.path.display(),
+                    host_info.name,
+                )
+            })?;
+        }
+
+        if let Some(ca) = &self.tls_ca {
+            ca.issue(
+                host.data_dir_path().join("tls"),
+                &host_info.name,
+                host_info.ip_addr.unwrap(),
+                host_info.seed,
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to issue a TLS certificate for host '{}'",
+                    host_info.name
+                )
+            })?;
+        }
+
+        #[cfg(feature = "tor_helpers")]
+        if let Some(TorHost {
+            nickname,
+            torrc_template: Some(template),
+            consensus_time_skew,
+        }) = &host_info.tor
+        {
+            let nickname = nickname
+                .clone()
+                .unwrap_or_else(|| utility::tor::default_nickname(&host_info.name));
+            let ip_addr = host_info.ip_addr.unwrap().to_string();
+            let bandwidth_rate = (host_info.bandwidth_up_bits.unwrap() / 8).to_string();
+            let time_skew = consensus_time_skew.map(|x| x.to_string());
+
+            let mut vars = vec![
+                ("SHADOW_HOSTNAME", host_info.name.as_str()),
+                ("SHADOW_IP_ADDR", ip_addr.as_str()),
+                ("TOR_NICKNAME", nickname.as_str()),
+                ("TOR_BANDWIDTH_RATE", bandwidth_rate.as_str()),
+            ];
+            if let Some(time_skew) = &time_skew {
+                vars.push(("TOR_CONSENSUS_TIME_SKEW", time_skew.as_str()));
+            }
+
+            utility::tor::write_torrc(template, &host.data_dir_path().join("torrc"), &vars)
+                .with_context(|| {
+                    format!("Failed to write the torrc for host '{}'", host_info.name)
+                })?;
+        }
+
+        if let Some(replay) = &host_info.replay {
+            TraceReplay::new(replay)
+                .with_context(|| {
+                    format!(
+                        "Failed to open the replay trace '{}' for host '{}'",
+                        replay.path.display(),
+                        host_info.name,
+                    )
+                })?
+                .start(&host);
+        }
+
         host.lock_shmem();
 
-        for proc in &host_info.processes {
+        for (id_slot, proc) in host_info.processes.iter().enumerate() {
             let plugin_path =
                 CString::new(proc.plugin.clone().into_os_string().as_bytes()).unwrap();
             let plugin_name = CString::new(proc.plugin.file_name().unwrap().as_bytes()).unwrap();
@@ -645,10 +993,18 @@ impl<'a> Manager<'a> {
                 proc.shutdown_signal,
                 plugin_name,
                 plugin_path,
+                proc.interpreter
+                    .clone()
+                    .map(utility::pathbuf_to_nul_term_cstring),
                 argv,
                 envv,
                 pause_for_debugging,
                 proc.expected_final_state,
+                proc.quota.clone(),
+                proc.credentials.clone(),
+                proc.root.clone().map(utility::pathbuf_to_nul_term_cstring),
+                proc.listen_sockets.clone(),
+                id_slot,
             );
 
             host.stop_execution_timer();
@@ -737,6 +1093,56 @@ impl<'a> Manager<'a> {
         }
     }
 
+    /// Checks Shadow's memory usage against the memory budget and warns with a breakdown of the
+    /// usage if it's close to or over the budget. Returns true if the usage is over the budget.
+    fn check_memory_budget(&mut self, budget: u64) -> bool {
+        let mem = match resource_usage::process_meminfo(&mut self.process_status_file) {
+            Ok(x) => x,
+            Err(e) => {
+                log::warn!("Unable to check memory usage against the memory budget: {e}");
+                return false;
+            }
+        };
+        let shmem = shadow_shmem::allocator::shmalloc_usage();
+
+        let usage = mem.vm_rss.unwrap_or(0) + u64::try_from(shmem.reserved_nbytes).unwrap();
+        let over_budget = usage > budget;
+
+        let should_warn = if over_budget {
+            !std::mem::replace(&mut self.warned_over_memory_budget, true)
+        } else if usage > budget / 10 * 9 {
+            !std::mem::replace(&mut self.warned_near_memory_budget, true)
+        } else {
+            false
+        };
+
+        if should_warn {
+            let mib = |x: Option<u64>| match x {
+                Some(x) => format!("{} MiB", x / 1024 / 1024),
+                None => "unknown".to_string(),
+            };
+
+            log::warn!(
+                "Shadow's memory usage of {} is {} the memory budget of {}: \
+                heap and other anonymous memory: {}, \
+                file mappings: {}, \
+                shared memory: {}, \
+                shared memory allocator: {} in {} blocks ({} reserved)",
+                mib(Some(usage)),
+                if over_budget { "over" } else { "close to" },
+                mib(Some(budget)),
+                mib(mem.rss_anon),
+                mib(mem.rss_file),
+                mib(mem.rss_shmem),
+                mib(Some(shmem.allocated_nbytes.try_into().unwrap())),
+                shmem.nallocs,
+                mib(Some(shmem.reserved_nbytes.try_into().unwrap())),
+            );
+        }
+
+        over_budget
+    }
+
     /// Returns a tuple of (usage, limit).
     fn fd_usage(&mut self) -> anyhow::Result<(u64, u64)> {
         let dir = std::fs::read_dir("/proc/self/fd").context("Failed to open '/proc/self/fd'")?;
@@ -793,8 +1199,132 @@ pub struct ManagerConfig {
     // bandwidths of hosts at ip addresses
     pub host_bandwidths: HashMap<std::net::IpAddr, Bandwidth>,
 
+    // ip addresses of the hosts attached to each switch node, which share a broadcast domain
+    pub broadcast_domains: HashMap<u32, Vec<std::net::IpAddr>>,
+
     // a list of hosts and their processes
     pub hosts: Vec<HostInfo>,
+
+    // a snapshot of the network topology, if it should be exported
+    pub topology: Option<Topology>,
+
+    // passive observers of the traffic between hosts, if any were configured
+    pub observers: Option<Observers>,
+
+    // on-path censors of the traffic between hosts, if any were configured
+    pub censors: Option<Censors>,
+
+    // pauses the simulation, if the control socket is enabled
+    pub pause_handle: Option<PauseHandle>,
+
+    // makes the hosts' firewalls available to the control socket, if it's enabled
+    pub firewall_registry: Option<FirewallRegistry>,
+}
+
+/// The parameters of the host described by `host_info`.
+pub fn host_parameters(
+    config: &ConfigOptions,
+    host_id: HostId,
+    host_info: &HostInfo,
+    cpu_frequency: u64,
+    native_tsc_frequency: u64,
+    end_time: EmulatedTime,
+) -> HostParameters {
+    HostParameters {
+        // the manager sets this ID
+        id: host_id,
+        // the manager sets this CPU frequency
+        cpu_frequency: cpu_frequency,
+        node_seed: host_info.seed,
+        hostname: CString::new(&*host_info.name).unwrap(),
+        node_id: host_info.network_node_id,
+        ip_addr: match host_info.ip_addr.unwrap() {
+            std::net::IpAddr::V4(ip) => u32::to_be(ip.into()),
+            // the config only allows ipv4 addresses, so this shouldn't happen
+            std::net::IpAddr::V6(_) => unreachable!("IPv6 not supported"),
+        },
+        sim_end_time: end_time,
+        requested_bw_down_bits: host_info.bandwidth_down_bits.unwrap(),
+        requested_bw_up_bits: host_info.bandwidth_up_bits.unwrap(),
+        cpu_threshold: host_info.cpu_threshold,
+        cpu_precision: host_info.cpu_precision,
+        heartbeat_interval: host_info.heartbeat_interval,
+        heartbeat_sample_interval: host_info.heartbeat_sample_interval,
+        heartbeat_top_talkers: host_info.heartbeat_top_talkers,
+        app_metrics_port: host_info.app_metrics_port,
+        tags: host_info.tags.clone(),
+        metadata: host_info.metadata.clone(),
+        heartbeat_log_level: host_info
+            .heartbeat_log_level
+            .map(|x| x.to_c_loglevel())
+            .unwrap_or(c::_LogLevel_LOGLEVEL_UNSET),
+        heartbeat_log_info: host_info
+            .heartbeat_log_info
+            .iter()
+            .map(|x| x.to_c_loginfoflag())
+            .reduce(|x, y| x | y)
+            .unwrap_or(c::_LogInfoFlags_LOG_INFO_FLAGS_NONE),
+        log_level: host_info
+            .log_level
+            .map(|x| x.to_c_loglevel())
+            .unwrap_or(c::_LogLevel_LOGLEVEL_UNSET),
+        pcap_config: host_info.pcap_config,
+        qdisc: host_info.qdisc,
+        router_qdisc: host_info.router_qdisc,
+        init_sock_recv_buf_size: host_info.recv_buf_size,
+        autotune_recv_buf: host_info.autotune_recv_buf,
+        init_sock_send_buf_size: host_info.send_buf_size,
+        autotune_send_buf: host_info.autotune_send_buf,
+        native_tsc_frequency: native_tsc_frequency,
+        model_unblocked_syscall_latency: config.model_unblocked_syscall_latency(),
+        max_unapplied_cpu_latency: config.max_unapplied_cpu_latency(),
+        unblocked_syscall_latency: config.unblocked_syscall_latency(),
+        unblocked_vdso_latency: config.unblocked_vdso_latency(),
+        timer_slack: config.timer_slack(),
+        syscall_latencies: config.syscall_latencies(),
+        strace_logging_options: host_info.strace_logging_options,
+        shim_log_level: host_info
+            .log_level
+            .unwrap_or_else(|| config.general.log_level.unwrap())
+            .to_c_loglevel(),
+        use_new_tcp: config.experimental.use_new_tcp.unwrap(),
+        syslog_sink: host_info.syslog_sink,
+        shm_size: host_info.shm_size,
+        seed_node: host_info.seed_node.clone(),
+        probes: host_info.probes.clone(),
+        probe_ports: host_info.probe_ports.clone(),
+        uname: host_info.uname.clone(),
+        persistent_state: host_info.persistent_state.clone(),
+        resolver: host_info.resolver.clone(),
+        tcp_delayed_ack: host_info.tcp_delayed_ack.clone(),
+        ip_local_port_range: host_info.ip_local_port_range.clone(),
+        tcp_time_wait: host_info.tcp_time_wait.clone(),
+        arp_cache_timeout: host_info.arp_cache_timeout,
+        load_balancer: host_info.load_balancer.clone(),
+        load_balancer_vips: host_info.load_balancer_vips.clone(),
+        // rules can be added to any host's firewall through the control socket
+        firewall: (host_info.firewall.is_some()
+            || self
+                .config
+                .experimental
+                .control_socket
+                .flatten_ref()
+                .is_some())
+        .then(|| Arc::new(Mutex::new(host_info.firewall.clone().unwrap_or_default()))),
+        throttles: host_info.throttles.clone(),
+        bridge: host_info.bridge.clone(),
+        native_apps: host_info.native_apps.clone(),
+        tcp_abort_on_overflow: host_info.tcp_abort_on_overflow,
+        tcp_max_syn_backlog: host_info.tcp_max_syn_backlog,
+        tcp_syncookies: host_info.tcp_syncookies,
+        tcp_timestamps: host_info.tcp_timestamps,
+        tcp_window_scaling: host_info.tcp_window_scaling,
+        max_event_queue_len: host_info.max_event_queue_len,
+        event_queue_limit_action: host_info.event_queue_limit_action,
+        packet_payload: host_info.packet_payload,
+        output_compression: host_info.output_compression,
+        packet_fingerprints: config.experimental.packet_fingerprints.unwrap(),
+    }
 }
 
 /// Helper function to initialize the global [`Host`] before running the closure.
@@ -816,6 +1346,36 @@ fn get_raw_cpu_frequency_hz() -> anyhow::Result<u64> {
     Ok(khz * 1000)
 }
 
+/// Write the name, address, network graph node, metadata, and remapped ports of each host to a
+/// JSON file.
+fn write_hosts_manifest(path: &Path, hosts: &[HostInfo]) -> anyhow::Result<()> {
+    #[derive(serde::Serialize)]
+    struct ManifestHost<'a> {
+        name: &'a str,
+        ip_addr: std::net::IpAddr,
+        network_node_id: u32,
+        metadata: &'a BTreeMap<String, String>,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        port_remaps: &'a [PortRemap],
+    }
+
+    let mut hosts: Vec<_> = hosts
+        .iter()
+        .map(|x| ManifestHost {
+            name: &x.name,
+            ip_addr: x.ip_addr.unwrap(),
+            network_node_id: x.network_node_id,
+            metadata: &x.metadata,
+            port_remaps: &x.port_remaps,
+        })
+        .collect();
+    hosts.sort_by_key(|x| x.name);
+
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(file, &hosts)?;
+    Ok(())
+}
+
 fn get_required_preload_path(libname: &str) -> anyhow::Result<PathBuf> {
     let libname_c = CString::new(libname).unwrap();
     let libpath_c = unsafe { c::scanRpathForLib(libname_c.as_ptr()) };
@@ -1,12 +1,21 @@
+pub mod api;
+pub mod breakpoints;
+pub mod control;
 pub mod controller;
 pub mod cpu;
+pub mod event_trace;
+pub mod group_stats;
+pub mod hooks;
+pub mod invariants;
 pub mod logger;
 pub mod main;
 pub mod manager;
+pub mod pacing;
 pub mod resource_usage;
 pub mod scheduler;
 pub mod sim_config;
 pub mod sim_stats;
 pub mod support;
+pub mod trials;
 pub mod work;
 pub mod worker;
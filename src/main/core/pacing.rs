@@ -0,0 +1,123 @@
+//! Pacing of the simulation to real time, so that the simulation can interact with components
+//! outside of the simulation that run in real time. Before each scheduling round, the controller
+//! waits until the real time at which the round's simulated start time should begin.
+//!
+//! The pacing is only approximate: the events in a round all run at the start of the round, and a
+//! simulation that runs slower than the pace falls behind. The simulation catches up after falling
+//! behind by a small amount, but continues from the current real time after falling behind by more
+//! than [`MAX_LAG`] (for example while it was paused).
+
+use std::time::{Duration, Instant};
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+/// The most that the simulation can fall behind real time before it stops trying to catch up.
+pub const MAX_LAG: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct RealTimePacer {
+    /// Simulated time per real time.
+    ratio: f64,
+    /// The real time that corresponds to the start of the simulation.
+    origin: Option<Instant>,
+    /// The total real time that the simulation didn't catch up on.
+    total_lag: Duration,
+}
+
+impl RealTimePacer {
+    /// A pacer that runs `ratio` seconds of simulated time for each second of real time.
+    pub fn new(ratio: f64) -> anyhow::Result<Self> {
+        if !(ratio.is_finite() && ratio > 0.0) {
+            anyhow::bail!("The real-time pacing ratio must be a positive number, not {ratio}");
+        }
+
+        Ok(Self {
+            ratio,
+            origin: None,
+            total_lag: Duration::ZERO,
+        })
+    }
+
+    /// Wait until the real time at which simulated time `time` should begin. The first call
+    /// starts the pacing.
+    pub fn wait_until(&mut self, time: EmulatedTime) {
+        let now = Instant::now();
+        if let Some(deadline) = self.deadline(time, now) {
+            std::thread::sleep(deadline.saturating_duration_since(now));
+        }
+    }
+
+    /// The total real time that the simulation fell behind and didn't catch up on.
+    pub fn total_lag(&self) -> Duration {
+        self.total_lag
+    }
+
+    /// The real time at which simulated time `time` should begin, or `None` if it should begin
+    /// immediately.
+    fn deadline(&mut self, time: EmulatedTime, now: Instant) -> Option<Instant> {
+        let origin = *self.origin.get_or_insert(now);
+
+        let offset = time.duration_since(&EmulatedTime::SIMULATION_START);
+        let offset = (offset.as_nanos() as f64 / self.ratio).round();
+        // the end of the simulation may be too far in the future to represent
+        if offset >= u64::MAX as f64 {
+            return None;
+        }
+        let deadline = origin.checked_add(Duration::from_nanos(offset as u64))?;
+
+        let lag = now.saturating_duration_since(deadline);
+        if lag > MAX_LAG {
+            log::warn!(
+                "The simulation fell {lag:?} behind real time, and will continue from the current \
+                real time"
+            );
+            self.origin = Some(origin + lag);
+            self.total_lag += lag;
+        }
+
+        (deadline > now).then_some(deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+    use super::*;
+
+    fn time(ms: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_deadline() {
+        let start = Instant::now();
+        let ms = |x| start + Duration::from_millis(x);
+
+        // twice as fast as real time
+        let mut pacer = RealTimePacer::new(2.0).unwrap();
+        assert_eq!(pacer.deadline(time(0), start), None);
+        assert_eq!(pacer.deadline(time(100), ms(10)), Some(ms(50)));
+
+        // catches up after falling a little behind
+        assert_eq!(pacer.deadline(time(100), ms(500)), None);
+        assert_eq!(pacer.deadline(time(1100), ms(500)), Some(ms(550)));
+        assert_eq!(pacer.total_lag(), Duration::ZERO);
+
+        // continues from the current time after falling far behind
+        assert_eq!(pacer.deadline(time(1200), ms(2600)), None);
+        assert_eq!(pacer.total_lag(), Duration::from_millis(2000));
+        assert_eq!(pacer.deadline(time(1300), ms(2600)), Some(ms(2650)));
+
+        // the end of the simulation
+        assert_eq!(pacer.deadline(EmulatedTime::MAX, ms(2600)), None);
+    }
+
+    #[test]
+    fn test_invalid_ratio() {
+        assert!(RealTimePacer::new(0.0).is_err());
+        assert!(RealTimePacer::new(-1.0).is_err());
+        assert!(RealTimePacer::new(f64::NAN).is_err());
+        assert!(RealTimePacer::new(0.5).is_ok());
+    }
+}
@@ -19,44 +19,59 @@ pub struct MemInfo {
 /// Collects some of the fields from '/proc/meminfo'. This function will seek to the start of the
 /// file before reading.
 pub fn meminfo(file: &mut File) -> std::io::Result<MemInfo> {
-    let mut buffer = String::new();
-    file.rewind()?;
-    file.read_to_string(&mut buffer)?;
+    let mut mem = MemInfo::default();
 
-    let lines = buffer.lines().filter_map(|line| {
-        let Some((name, val)) = line.split_once(':') else {
-            // don't know how to parse this line
-            return None;
+    for (name, val, unit) in read_fields(file)?.iter() {
+        let Some(val) = val.parse().ok() else {
+            // expected an integer
+            continue;
         };
 
-        let name = name.trim();
-        let val = val.trim();
+        match *name {
+            "MemTotal" => mem.mem_total = as_base_unit(val, *unit),
+            "MemFree" => mem.mem_free = as_base_unit(val, *unit),
+            "SwapTotal" => mem.swap_total = as_base_unit(val, *unit),
+            "SwapFree" => mem.swap_free = as_base_unit(val, *unit),
+            "Buffers" => mem.buffers = as_base_unit(val, *unit),
+            "Cached" => mem.cached = as_base_unit(val, *unit),
+            "SReclaimable" => mem.s_reclaimable = as_base_unit(val, *unit),
+            "Shmem" => mem.shmem = as_base_unit(val, *unit),
+            _ => {}
+        }
+    }
 
-        let (val, unit) = val
-            .rsplit_once(' ')
-            .map(|(x, y)| (x, Some(y)))
-            .unwrap_or((val, None));
+    Ok(mem)
+}
 
-        Some((name, val, unit))
-    });
+/// Memory usage of a process parsed from '/proc/[pid]/status'. All units are converted to bytes.
+#[derive(Copy, Clone, Debug, Default, Serialize)]
+pub struct ProcessMemInfo {
+    /// Total resident set size.
+    pub vm_rss: Option<u64>,
+    /// Resident anonymous memory, such as the heap.
+    pub rss_anon: Option<u64>,
+    /// Resident file mappings, such as shared libraries.
+    pub rss_file: Option<u64>,
+    /// Resident shared memory, such as memory shared with managed processes.
+    pub rss_shmem: Option<u64>,
+}
 
-    let mut mem = MemInfo::default();
+/// Collects the memory usage fields from '/proc/[pid]/status'. This function will seek to the
+/// start of the file before reading.
+pub fn process_meminfo(file: &mut File) -> std::io::Result<ProcessMemInfo> {
+    let mut mem = ProcessMemInfo::default();
 
-    for (name, val, unit) in lines {
+    for (name, val, unit) in read_fields(file)?.iter() {
         let Some(val) = val.parse().ok() else {
             // expected an integer
             continue;
         };
 
-        match name {
-            "MemTotal" => mem.mem_total = as_base_unit(val, unit),
-            "MemFree" => mem.mem_free = as_base_unit(val, unit),
-            "SwapTotal" => mem.swap_total = as_base_unit(val, unit),
-            "SwapFree" => mem.swap_free = as_base_unit(val, unit),
-            "Buffers" => mem.buffers = as_base_unit(val, unit),
-            "Cached" => mem.cached = as_base_unit(val, unit),
-            "SReclaimable" => mem.s_reclaimable = as_base_unit(val, unit),
-            "Shmem" => mem.shmem = as_base_unit(val, unit),
+        match *name {
+            "VmRSS" => mem.vm_rss = as_base_unit(val, *unit),
+            "RssAnon" => mem.rss_anon = as_base_unit(val, *unit),
+            "RssFile" => mem.rss_file = as_base_unit(val, *unit),
+            "RssShmem" => mem.rss_shmem = as_base_unit(val, *unit),
             _ => {}
         }
     }
@@ -64,6 +79,38 @@ pub fn meminfo(file: &mut File) -> std::io::Result<MemInfo> {
     Ok(mem)
 }
 
+/// The lines of a file in the "name: value unit" format used by files like '/proc/meminfo'.
+struct Fields(String);
+
+impl Fields {
+    /// Returns a `(name, value, unit)` tuple for each line that could be parsed.
+    fn iter(&self) -> impl Iterator<Item = (&str, &str, Option<&str>)> {
+        self.0.lines().filter_map(|line| {
+            let Some((name, val)) = line.split_once(':') else {
+                // don't know how to parse this line
+                return None;
+            };
+
+            let name = name.trim();
+            let val = val.trim();
+
+            let (val, unit) = val
+                .rsplit_once(' ')
+                .map(|(x, y)| (x.trim(), Some(y)))
+                .unwrap_or((val, None));
+
+            Some((name, val, unit))
+        })
+    }
+}
+
+fn read_fields(file: &mut File) -> std::io::Result<Fields> {
+    let mut buffer = String::new();
+    file.rewind()?;
+    file.read_to_string(&mut buffer)?;
+    Ok(Fields(buffer))
+}
+
 /// Returns `None` if either the `unit` wasn't known, or the base unit is too large.
 fn as_base_unit(val: u64, unit: Option<&str>) -> Option<u64> {
     let mul = match unit {
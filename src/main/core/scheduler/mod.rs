@@ -42,6 +42,17 @@ impl Scheduler {
         }
     }
 
+    /// Limit the number of threads that run hosts in later calls of
+    /// [`SchedulerScope::run_with_hosts`] and [`SchedulerScope::run_with_data`], between 1 and
+    /// [`Scheduler::parallelism`]. The other threads return immediately without running any hosts.
+    /// The thread-per-host scheduler always runs every thread.
+    pub fn set_active_threads(&mut self, num_threads: usize) {
+        match self {
+            Self::ThreadPerHost(_) => {}
+            Self::ThreadPerCore(sched) => sched.set_active_threads(num_threads),
+        }
+    }
+
     /// A scope for any task run on the scheduler. The current thread will block at the end of the
     /// scope until the task has completed.
     pub fn scope<'scope>(
@@ -12,6 +12,8 @@ impl<T> Host for T where T: Debug + Send {}
 pub struct ThreadPerCoreSched<HostType: Host> {
     pool: UnboundedThreadPool,
     num_threads: usize,
+    /// The number of threads that run hosts.
+    active_threads: usize,
     thread_hosts: Vec<ArrayQueue<HostType>>,
     thread_hosts_processed: Vec<ArrayQueue<HostType>>,
     hosts_need_swap: bool,
@@ -63,6 +65,7 @@ impl<HostType: Host> ThreadPerCoreSched<HostType> {
         Self {
             pool,
             num_threads,
+            active_threads: num_threads,
             thread_hosts,
             thread_hosts_processed: thread_hosts_2,
             hosts_need_swap: false,
@@ -74,6 +77,11 @@ impl<HostType: Host> ThreadPerCoreSched<HostType> {
         self.num_threads
     }
 
+    /// See [`crate::core::scheduler::Scheduler::set_active_threads`].
+    pub fn set_active_threads(&mut self, num_threads: usize) {
+        self.active_threads = num_threads.clamp(1, self.num_threads);
+    }
+
     /// See [`crate::core::scheduler::Scheduler::scope`].
     pub fn scope<'scope>(
         &'scope mut self,
@@ -92,6 +100,7 @@ impl<HostType: Host> ThreadPerCoreSched<HostType> {
         let thread_hosts = &self.thread_hosts;
         let thread_hosts_processed = &self.thread_hosts_processed;
         let hosts_need_swap = &mut self.hosts_need_swap;
+        let active_threads = self.active_threads;
 
         // we cannot access `self` after calling `pool.scope()` since `SchedulerScope` has a
         // lifetime of `'scope` (which at minimum spans the entire current function)
@@ -101,6 +110,7 @@ impl<HostType: Host> ThreadPerCoreSched<HostType> {
                 thread_hosts,
                 thread_hosts_processed,
                 hosts_need_swap,
+                active_threads,
                 runner: s,
             };
 
@@ -122,6 +132,7 @@ where
     thread_hosts: &'sched Vec<ArrayQueue<HostType>>,
     thread_hosts_processed: &'sched Vec<ArrayQueue<HostType>>,
     hosts_need_swap: &'sched mut bool,
+    active_threads: usize,
     runner: TaskRunner<'pool, 'scope>,
 }
 
@@ -137,6 +148,11 @@ impl<'sched, 'pool, 'scope, HostType: Host> SchedulerScope<'sched, 'pool, 'scope
         f: impl Fn(usize, &mut HostIter<'_, HostType>) + Send + Sync + 'scope,
     ) {
         self.runner.run(move |i| {
+            // the active threads will take the hosts of the inactive threads
+            if i >= self.active_threads {
+                return;
+            }
+
             let mut host_iter = HostIter {
                 thread_hosts_from: self.thread_hosts,
                 thread_hosts_to: &self.thread_hosts_processed[i],
@@ -158,6 +174,10 @@ impl<'sched, 'pool, 'scope, HostType: Host> SchedulerScope<'sched, 'pool, 'scope
         T: Sync,
     {
         self.runner.run(move |i| {
+            if i >= self.active_threads {
+                return;
+            }
+
             let this_elem = &data[i];
 
             let mut host_iter = HostIter {
@@ -294,6 +314,38 @@ mod tests {
         sched.join();
     }
 
+    #[test]
+    fn test_active_threads() {
+        let hosts = [(); 5].map(|_| TestHost {});
+        let mut sched: ThreadPerCoreSched<TestHost> =
+            ThreadPerCoreSched::new(&[None, None, None], hosts, false);
+
+        let counters = [(); 3].map(|_| AtomicU32::new(0));
+        let count = |i: usize| counters[i].load(Ordering::SeqCst);
+
+        // the number of active threads is at least 1
+        for active_threads in [1, 0, 3] {
+            sched.set_active_threads(active_threads);
+            sched.scope(|s| {
+                s.run_with_hosts(|i, hosts| {
+                    hosts.for_each(|host| {
+                        counters[i].fetch_add(1, Ordering::SeqCst);
+                        host
+                    });
+                });
+            });
+
+            if active_threads <= 1 {
+                // the first thread runs every host when it's the only active thread
+                assert_eq!(count(1) + count(2), 0);
+            }
+        }
+
+        assert_eq!(count(0) + count(1) + count(2), 5 * 3);
+
+        sched.join();
+    }
+
     #[test]
     fn test_run_with_data() {
         let hosts = [(); 5].map(|_| TestHost {});
@@ -0,0 +1,90 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+/*
+ * The stable C API for components that run inside of Shadow.
+ *
+ * Unlike the generated `main/bindings/c/bindings.h`, whose functions may change
+ * in any release, this API follows semantic versioning: functions are only
+ * added in minor releases of the API, and are only removed or changed in major
+ * releases. Functions are marked with `SHADOW_API_DEPRECATED` at least one
+ * minor release before they're removed.
+ *
+ * The functions act on the host that is currently running on the calling
+ * thread, and fail if no host is running (for example if they're called from
+ * a thread that isn't one of Shadow's worker threads).
+ */
+
+#ifndef SHADOW_API_H_
+#define SHADOW_API_H_
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#define SHADOW_API_VERSION_MAJOR 1
+#define SHADOW_API_VERSION_MINOR 0
+#define SHADOW_API_VERSION_PATCH 0
+
+/* Encodes a version as a single integer that can be compared with
+ * `SHADOW_API_VERSION` and the result of `shadow_api_version()`. */
+#define SHADOW_API_MAKE_VERSION(major, minor, patch)                                               \
+    (((uint32_t)(major) << 16) | ((uint32_t)(minor) << 8) | (uint32_t)(patch))
+
+/* The version of the API that this header declares. */
+#define SHADOW_API_VERSION                                                                         \
+    SHADOW_API_MAKE_VERSION(                                                                       \
+        SHADOW_API_VERSION_MAJOR, SHADOW_API_VERSION_MINOR, SHADOW_API_VERSION_PATCH)
+
+/* True if this header declares at least the given version of the API. */
+#define SHADOW_API_VERSION_AT_LEAST(major, minor)                                                  \
+    (SHADOW_API_VERSION >= SHADOW_API_MAKE_VERSION(major, minor, 0))
+
+/* Marks a function that will be removed in the next major version of the API.
+ * Define `SHADOW_API_NO_DEPRECATION_WARNINGS` to silence the warnings. */
+#if defined(SHADOW_API_NO_DEPRECATION_WARNINGS)
+#define SHADOW_API_DEPRECATED(since, msg)
+#elif defined(__GNUC__) || defined(__clang__)
+#define SHADOW_API_DEPRECATED(since, msg) __attribute__((deprecated("since " since ": " msg)))
+#else
+#define SHADOW_API_DEPRECATED(since, msg)
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Since 1.0. Returns the version of the API that the running Shadow
+ * implements, encoded like `SHADOW_API_MAKE_VERSION`. Components should check
+ * that its major version is the same as `SHADOW_API_VERSION_MAJOR`, and that
+ * it's at least `SHADOW_API_VERSION`. */
+uint32_t shadow_api_version(void);
+
+/* Since 1.0. Stores the ID of the current host in `id`. */
+bool shadow_api_host_id(uint32_t* id);
+
+/* Since 1.0. Copies the name of the current host, including its nul
+ * terminator, to `buf`, truncating it if it doesn't fit in `len` bytes. Returns
+ * the length of the name without its nul terminator, or 0 if there's no
+ * current host. */
+size_t shadow_api_host_name(char* buf, size_t len);
+
+/* Since 1.0. Stores the default IPv4 address of the current host, in network
+ * byte order, in `addr`. */
+bool shadow_api_host_ipv4(uint32_t* addr);
+
+/* Since 1.0. Stores the current simulated time, in nanoseconds since the Unix
+ * epoch, in `ns`. */
+bool shadow_api_now_ns(uint64_t* ns);
+
+/* Since 1.0. Fills `buf` with `len` bytes from the current host's
+ * deterministic random number generator. */
+bool shadow_api_random_bytes(void* buf, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SHADOW_API_H_ */
@@ -2,7 +2,8 @@ use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 use std::time::Duration;
 
@@ -14,12 +15,27 @@ use shadow_shim_helper_rs::simulation_time::SimulationTime;
 
 use crate::core::support::configuration::Flatten;
 use crate::core::support::configuration::{
-    parse_string_as_args, ConfigOptions, EnvName, HostOptions, LogInfoFlag, LogLevel, ProcessArgs,
-    ProcessOptions, QDiscMode,
+    parse_string_as_args, ConfigOptions, DataTemplateMode, EnvName, EventQueueLimitAction,
+    HostOptions, ListenSocketProtocol, LoadBalancerAlgorithm, LogInfoFlag, LogLevel,
+    NativeAppOptions, OutputCompression, PacketPayload, PcapErrorAction, PcapPayload, ProbeKind,
+    ProcessArgs, ProcessOptions, QDiscMode, ReplayFormat, RouterQDiscMode,
 };
 use crate::core::support::units::{self, Unit};
+use crate::host::credentials::{Credentials, NGROUPS_MAX};
+use crate::host::native_app;
+use crate::host::network::firewall::Firewall;
+use crate::host::persistent_state::{PersistentState, StateValue};
+use crate::host::probe::MAX_FETCH_SIZE;
+use crate::host::quota::{QuotaLimits, SocketRateLimit};
+use crate::host::resolver::Resolver;
+use crate::host::seed_node::MAX_RESPONSE_PEERS;
+use crate::host::syscall::formatter::FmtOptions;
+use crate::network::graph::censors::Censors;
+use crate::network::graph::export::{Topology, TopologyHost};
+use crate::network::graph::observers::Observers;
 use crate::network::graph::{load_network_graph, IpAssignment, NetworkGraph, RoutingInfo};
-use crate::utility::{tilde_expansion, verify_plugin_path};
+use crate::network::relay::throttle::ThrottleRule;
+use crate::utility::{tilde_expansion, verify_executable_path, verify_plugin_path};
 
 use super::support::configuration::ProcessFinalState;
 
@@ -37,8 +53,20 @@ pub struct SimConfig {
     // bandwidths of hosts at ip addresses
     pub host_bandwidths: HashMap<std::net::IpAddr, Bandwidth>,
 
+    // ip addresses of the hosts attached to each switch node, which share a broadcast domain
+    pub broadcast_domains: HashMap<u32, Vec<std::net::IpAddr>>,
+
     // a list of hosts and their processes
     pub hosts: Vec<HostInfo>,
+
+    // a snapshot of the network topology, if it should be exported
+    pub topology: Option<Topology>,
+
+    // passive observers of the traffic between hosts, if any were configured
+    pub observers: Option<Observers>,
+
+    // on-path censors of the traffic between hosts, if any were configured
+    pub censors: Option<Censors>,
 }
 
 impl SimConfig {
@@ -71,7 +99,7 @@ impl SimConfig {
         }
 
         // load and parse the network graph
-        let graph: String = load_network_graph(config.network.graph.as_ref().unwrap())
+        let graph: String = load_network_graph(config.network.graph.as_ref().unwrap(), seed.into())
             .map_err(|e| anyhow::anyhow!(e))
             .context("Failed to load the network graph")?;
         let graph = NetworkGraph::parse(&graph)
@@ -89,6 +117,14 @@ impl SimConfig {
             }
         }
 
+        // hosts that are tor relays may get their bandwidth from the consensus
+        apply_tor_consensus(config, &mut hosts)?;
+
+        let mut num_hosts_per_node: HashMap<u32, u64> = HashMap::new();
+        for host in &hosts {
+            *num_hosts_per_node.entry(host.network_node_id).or_default() += 1;
+        }
+
         // assign a bandwidth to every host
         for host in &mut hosts {
             let node_index = graph.node_id_to_index(host.network_node_id).unwrap();
@@ -117,6 +153,24 @@ impl SimConfig {
                     host.name
                 ));
             }
+
+            // a host attached to a switch is limited by the switch's port bandwidth, and hosts that
+            // contend for the switch's shared bandwidth each get an equal share of it
+            if let Some(switch) = &node.switch {
+                let bits = |x: units::BitsPerSec<units::SiPrefixUpper>| {
+                    x.convert(units::SiPrefixUpper::Base).unwrap().value()
+                };
+                let num_hosts = num_hosts_per_node[&host.network_node_id];
+                let limits = [
+                    switch.port_bandwidth.map(bits),
+                    switch.shared_bandwidth.map(|x| bits(x) / num_hosts),
+                ];
+
+                for limit in limits.into_iter().flatten() {
+                    host.bandwidth_down_bits = host.bandwidth_down_bits.map(|x| x.min(limit));
+                    host.bandwidth_up_bits = host.bandwidth_up_bits.map(|x| x.min(limit));
+                }
+            }
         }
 
         // check if any hosts in 'hosts_to_debug' don't exist
@@ -131,6 +185,28 @@ impl SimConfig {
         // assign IP addresses to hosts and graph nodes
         let ip_assignment = assign_ips(&mut hosts)?;
 
+        // resolve hostnames that hosts use to refer to other hosts
+        resolve_host_references(&mut hosts)?;
+
+        // the probe ports are only known once host references have been resolved
+        for host in &mut hosts {
+            check_port_conflicts(host, config.experimental.remap_conflicting_ports.unwrap())
+                .with_context(|| format!("Port conflict on host '{}'", host.name))?;
+        }
+
+        // hosts attached to the same switch receive each other's broadcast packets
+        let mut broadcast_domains: HashMap<u32, Vec<std::net::IpAddr>> = HashMap::new();
+        for host in &hosts {
+            let node_index = graph.node_id_to_index(host.network_node_id).unwrap();
+            let node = graph.graph().node_weight(*node_index).unwrap();
+            if node.switch.is_some() {
+                broadcast_domains
+                    .entry(host.network_node_id)
+                    .or_default()
+                    .push(host.ip_addr.unwrap());
+            }
+        }
+
         // generate routing info between every pair of in-use nodes
         let routing_info = generate_routing_info(
             &graph,
@@ -152,12 +228,73 @@ impl SimConfig {
             })
             .collect();
 
+        let flow_interval = config
+            .network
+            .topology_export
+            .as_ref()
+            .and_then(|x| x.flow_interval);
+        if flow_interval.is_some_and(|x| x.value() == 0) {
+            return Err(anyhow::anyhow!(
+                "The topology export's flow interval must be greater than zero"
+            ));
+        }
+
+        // take a snapshot of the topology before the graph is dropped
+        let topology = config.network.topology_export.as_ref().map(|_| {
+            let hosts = hosts
+                .iter()
+                .map(|host| TopologyHost {
+                    name: host.name.clone(),
+                    ip_addr: host.ip_addr.unwrap(),
+                    node_id: host.network_node_id,
+                    bandwidth_down_bits: host.bandwidth_down_bits.unwrap(),
+                    bandwidth_up_bits: host.bandwidth_up_bits.unwrap(),
+                    metadata: host.metadata.clone(),
+                })
+                .collect();
+            Topology::new(&graph, hosts)
+        });
+
+        let observers = config.network.observers.as_deref().unwrap_or(&[]);
+        let observers = if observers.is_empty() {
+            None
+        } else {
+            Some(
+                Observers::new(
+                    observers,
+                    &graph,
+                    &ip_assignment.get_nodes(),
+                    config.network.use_shortest_path.unwrap(),
+                )
+                .context("Failed to set up the network observers")?,
+            )
+        };
+
+        let censors = config.network.censors.as_deref().unwrap_or(&[]);
+        let censors = if censors.is_empty() {
+            None
+        } else {
+            Some(
+                Censors::new(
+                    censors,
+                    &graph,
+                    &ip_assignment.get_nodes(),
+                    config.network.use_shortest_path.unwrap(),
+                )
+                .context("Failed to set up the network censors")?,
+            )
+        };
+
         Ok(Self {
             random,
             ip_assignment,
             routing_info,
             host_bandwidths,
+            broadcast_domains,
             hosts,
+            topology,
+            observers,
+            censors,
         })
     }
 }
@@ -165,6 +302,8 @@ impl SimConfig {
 #[derive(Clone)]
 pub struct HostInfo {
     pub name: String,
+    pub tags: Vec<String>,
+    pub metadata: BTreeMap<String, String>,
     pub processes: Vec<ProcessInfo>,
     pub seed: u64,
     pub network_node_id: u32,
@@ -179,11 +318,54 @@ pub struct HostInfo {
     pub heartbeat_log_level: Option<LogLevel>,
     pub heartbeat_log_info: HashSet<LogInfoFlag>,
     pub heartbeat_interval: Option<SimulationTime>,
+    pub heartbeat_sample_interval: Option<SimulationTime>,
+    pub heartbeat_top_talkers: u32,
+    pub app_metrics_port: Option<u16>,
+    pub strace_logging_options: Option<FmtOptions>,
     pub send_buf_size: u64,
     pub recv_buf_size: u64,
     pub autotune_send_buf: bool,
     pub autotune_recv_buf: bool,
     pub qdisc: QDiscMode,
+    pub router_qdisc: RouterQDiscMode,
+    pub data_template: Option<DataTemplate>,
+    pub syslog_sink: bool,
+    /// The size limit of the host's `/dev/shm` tmpfs, if it's emulated.
+    pub shm_size: Option<u64>,
+    pub replay: Option<Replay>,
+    pub tor: Option<TorHost>,
+    pub seed_node: Option<SeedNode>,
+    pub probes: Vec<Probe>,
+    /// The ports that the host answers other hosts' probes on, which are only known once all hosts'
+    /// probes have been resolved.
+    pub probe_ports: Vec<ProbePort>,
+    pub uname: Uname,
+    pub persistent_state: Option<PersistentState>,
+    pub resolver: Option<Resolver>,
+    pub tcp_delayed_ack: TcpDelayedAck,
+    pub ip_local_port_range: RangeInclusive<u16>,
+    pub tcp_time_wait: TcpTimeWait,
+    pub arp_cache_timeout: Option<SimulationTime>,
+    pub load_balancer: Option<LoadBalancer>,
+    /// The addresses of the load balancers in DSR mode that the host is a backend of. The host's
+    /// packets from a load balancer's port are sent from the load balancer's address.
+    pub load_balancer_vips: Vec<std::net::SocketAddrV4>,
+    pub firewall: Option<Firewall>,
+    pub throttles: Vec<ThrottleRule>,
+    pub bridge: Option<Bridge>,
+    pub native_apps: Vec<NativeApp>,
+    /// The process listen sockets that were moved to a different port since their port was
+    /// already bound.
+    pub port_remaps: Vec<PortRemap>,
+    pub tcp_abort_on_overflow: bool,
+    pub tcp_max_syn_backlog: u32,
+    pub tcp_syncookies: bool,
+    pub tcp_timestamps: bool,
+    pub tcp_window_scaling: bool,
+    pub max_event_queue_len: Option<u64>,
+    pub event_queue_limit_action: EventQueueLimitAction,
+    pub packet_payload: PacketPayload,
+    pub output_compression: OutputCompression,
 }
 
 #[derive(Clone)]
@@ -195,6 +377,29 @@ pub struct ProcessInfo {
     pub args: Vec<OsString>,
     pub env: BTreeMap<EnvName, String>,
     pub expected_final_state: ProcessFinalState,
+    pub quota: Option<QuotaLimits>,
+    pub credentials: Credentials,
+    pub root: Option<PathBuf>,
+    pub listen_sockets: Vec<ListenSocket>,
+    /// The dynamic linker that runs the executable, if not the executable's own.
+    pub interpreter: Option<PathBuf>,
+}
+
+/// A socket that's passed to a process using the systemd socket activation protocol.
+#[derive(Debug, Clone)]
+pub struct ListenSocket {
+    pub protocol: ListenSocketProtocol,
+    pub port: u16,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PortRemap {
+    /// The index of the process in the host's processes.
+    pub process: usize,
+    pub protocol: ListenSocketProtocol,
+    pub from: u16,
+    pub to: u16,
 }
 
 #[derive(Debug, Clone)]
@@ -206,6 +411,144 @@ pub struct Bandwidth {
 #[derive(Debug, Clone, Copy)]
 pub struct PcapConfig {
     pub capture_size: u64,
+    pub payload: PcapPayload,
+    pub error_action: PcapErrorAction,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataTemplate {
+    pub path: PathBuf,
+    pub mode: DataTemplateMode,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub path: PathBuf,
+    pub format: ReplayFormat,
+    pub destination_hostnames: Vec<String>,
+    /// The IP addresses of the destination hosts, which are only known once IP addresses have been
+    /// assigned to all hosts.
+    pub destinations: Vec<std::net::Ipv4Addr>,
+    pub start_time: SimulationTime,
+    pub time_scale: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeedNode {
+    pub port: u16,
+    pub peer_port: u16,
+    pub peer_hostnames: Option<Vec<String>>,
+    /// The IP addresses of the peer hosts, which are only known once IP addresses have been
+    /// assigned to all hosts.
+    pub peers: Vec<std::net::Ipv4Addr>,
+    pub max_peers: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub target_hostname: String,
+    /// The IP address of the target host, which is only known once IP addresses have been
+    /// assigned to all hosts.
+    pub target: Option<std::net::Ipv4Addr>,
+    pub kind: ProbeKind,
+    pub interval: SimulationTime,
+    pub port: u16,
+    pub fetch_size: u32,
+    pub summary_interval: SimulationTime,
+}
+
+/// A port that a host answers other hosts' probes on.
+#[derive(Debug, Clone)]
+pub struct ProbePort {
+    pub port: u16,
+    /// The name of the host with the first probe that uses this port.
+    pub prober: String,
+    /// The index of that probe in the prober's `probes`.
+    pub index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bridge {
+    /// The name of the TUN device.
+    pub tun: String,
+    pub poll_interval: SimulationTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct NativeApp {
+    pub name: String,
+    pub args: Vec<String>,
+    pub start_time: SimulationTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadBalancer {
+    pub port: u16,
+    pub backend_hostnames: Vec<String>,
+    /// The addresses of the backends, which are only known once IP addresses have been assigned
+    /// to all hosts.
+    pub backends: Vec<std::net::SocketAddrV4>,
+    pub backend_port: u16,
+    pub algorithm: LoadBalancerAlgorithm,
+    pub dsr: bool,
+}
+
+/// The system identification fields that Linux stores in a `struct new_utsname`, other than the
+/// hostname.
+#[derive(Debug, Clone)]
+pub struct Uname {
+    pub sysname: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+    pub domainname: String,
+}
+
+/// When a host's TCP sockets send delayed ACKs.
+#[derive(Debug, Clone)]
+pub struct TcpDelayedAck {
+    pub timeout: SimulationTime,
+    pub quick_timeout: SimulationTime,
+    pub quick_acks: u32,
+    pub max_unacked_bytes: Option<u64>,
+}
+
+impl TcpDelayedAck {
+    /// The time that an ACK is delayed, given the number of ACKs that the connection has already
+    /// delayed.
+    pub fn timeout(&self, num_delayed: u32) -> SimulationTime {
+        if num_delayed < self.quick_acks {
+            self.quick_timeout
+        } else {
+            self.timeout
+        }
+    }
+}
+
+impl From<&TcpDelayedAck> for tcp::DelayedAckConfig {
+    fn from(x: &TcpDelayedAck) -> Self {
+        Self {
+            timeout: x.timeout.into(),
+            quick_timeout: x.quick_timeout.into(),
+            quick_acks: x.quick_acks,
+            max_unacked_bytes: x.max_unacked_bytes,
+        }
+    }
+}
+
+/// How a host's TCP connections behave in the TIME_WAIT state.
+#[derive(Debug, Clone)]
+pub struct TcpTimeWait {
+    pub timeout: SimulationTime,
+    pub reuse: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TorHost {
+    pub nickname: Option<String>,
+    pub torrc_template: Option<PathBuf>,
+    /// Seconds from the start of the simulation until the consensus becomes valid.
+    pub consensus_time_skew: Option<i64>,
 }
 
 /// For a host entry in the configuration options, build `HostInfo` object.
@@ -227,7 +570,11 @@ fn build_host(
 
     let pause_for_debugging = hosts_to_debug.contains(&hostname);
 
-    let processes: Vec<_> = host
+    let profile = config.instrumentation_profile(host);
+
+    let seed = randomness_for_seed_calc ^ hostname_hash;
+
+    let mut processes: Vec<_> = host
         .processes
         .iter()
         .map(|proc| {
@@ -236,11 +583,352 @@ fn build_host(
         })
         .collect::<anyhow::Result<_>>()?;
 
+    let start_jitter: SimulationTime =
+        Duration::from(host.host_options.process_start_jitter.unwrap())
+            .try_into()
+            .unwrap();
+    if start_jitter > SimulationTime::ZERO {
+        let stop_time =
+            SimulationTime::try_from(Duration::from(config.general.stop_time.unwrap())).unwrap();
+        stagger_start_times(&mut processes, start_jitter, stop_time, seed);
+    }
+
+    let data_template = host
+        .data_template
+        .as_ref()
+        .map(|template| {
+            let expanded_path = expand_config_path(&template.path)?;
+            let path = expanded_path.canonicalize().with_context(|| {
+                format!("Failed to resolve data template path '{expanded_path:?}'")
+            })?;
+            if !path.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "Data template path '{path:?}' is not a directory"
+                ));
+            }
+            Ok(DataTemplate {
+                path,
+                mode: template.mode,
+            })
+        })
+        .transpose()?;
+
+    let replay = host
+        .replay
+        .as_ref()
+        .map(|replay| {
+            let expanded_path = expand_config_path(&replay.path)?;
+            let path = expanded_path.canonicalize().with_context(|| {
+                format!("Failed to resolve replay trace path '{expanded_path:?}'")
+            })?;
+            if replay.destinations.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Replay hosts require at least one destination"
+                ));
+            }
+            if !(replay.time_scale.is_finite() && replay.time_scale > 0.0) {
+                return Err(anyhow::anyhow!(
+                    "The replay time scale must be a positive number, not {}",
+                    replay.time_scale
+                ));
+            }
+            Ok(Replay {
+                path,
+                format: replay.format,
+                destination_hostnames: replay.destinations.clone(),
+                destinations: Vec::new(),
+                start_time: Duration::from(replay.start_time).try_into().unwrap(),
+                time_scale: replay.time_scale,
+            })
+        })
+        .transpose()?;
+
+    let tor = host
+        .tor
+        .as_ref()
+        .map(|tor| {
+            if !cfg!(feature = "tor_helpers") {
+                return Err(anyhow::anyhow!(
+                    "The 'tor' host option requires Shadow to be built with the 'tor_helpers' feature"
+                ));
+            }
+            let torrc_template = tor
+                .torrc_template
+                .as_ref()
+                .map(|path| {
+                    let expanded_path = expand_config_path(path)?;
+                    expanded_path.canonicalize().with_context(|| {
+                        format!("Failed to resolve torrc template path '{expanded_path:?}'")
+                    })
+                })
+                .transpose()?;
+            Ok(TorHost {
+                nickname: tor.nickname.clone(),
+                torrc_template,
+                consensus_time_skew: None,
+            })
+        })
+        .transpose()?;
+
+    let seed_node = host
+        .seed_node
+        .as_ref()
+        .map(|seed_node| {
+            if !(1..=MAX_RESPONSE_PEERS).contains(&seed_node.max_peers) {
+                return Err(anyhow::anyhow!(
+                    "The seed node's 'max_peers' must be between 1 and {MAX_RESPONSE_PEERS}"
+                ));
+            }
+            Ok(SeedNode {
+                port: seed_node.port,
+                peer_port: seed_node.peer_port,
+                peer_hostnames: seed_node.peers.clone(),
+                peers: Vec::new(),
+                max_peers: seed_node.max_peers.try_into().unwrap(),
+            })
+        })
+        .transpose()?;
+
+    let probes = host
+        .probes
+        .iter()
+        .map(|probe| {
+            if probe.target == hostname {
+                return Err(anyhow::anyhow!("A host can't probe itself"));
+            }
+            let time = |x: units::Time<units::TimePrefix>, name: &str| {
+                let x: Duration = x.into();
+                let x = SimulationTime::try_from(x).unwrap();
+                if x == SimulationTime::ZERO {
+                    return Err(anyhow::anyhow!(
+                        "The probe's '{name}' must be greater than 0"
+                    ));
+                }
+                Ok(x)
+            };
+            let fetch_size = probe
+                .fetch_size
+                .convert(units::SiPrefixUpper::Base)
+                .unwrap()
+                .value();
+            if fetch_size > u64::from(MAX_FETCH_SIZE) {
+                return Err(anyhow::anyhow!(
+                    "The probe's 'fetch_size' must be at most {MAX_FETCH_SIZE} bytes"
+                ));
+            }
+            Ok(Probe {
+                target_hostname: probe.target.clone(),
+                target: None,
+                kind: probe.kind,
+                interval: time(probe.interval, "interval")?,
+                port: probe.port,
+                fetch_size: fetch_size.try_into().unwrap(),
+                summary_interval: time(probe.summary_interval, "summary_interval")?,
+            })
+        })
+        .collect::<anyhow::Result<_>>()
+        .context("Failed to configure the host's probes")?;
+
+    let uname = host.uname.clone().unwrap_or_default();
+    for (name, value) in [
+        ("sysname", &uname.sysname),
+        ("release", &uname.release),
+        ("version", &uname.version),
+        ("machine", &uname.machine),
+        ("domainname", &uname.domainname),
+    ] {
+        // each field of a `struct utsname` has room for 64 bytes and a nul terminator
+        if value.len() > 64 || value.contains('\0') {
+            return Err(anyhow::anyhow!(
+                "The uname '{name}' must be at most 64 bytes and must not contain nul bytes"
+            ));
+        }
+    }
+    let uname = Uname {
+        sysname: uname.sysname,
+        release: uname.release,
+        version: uname.version,
+        machine: uname.machine,
+        domainname: uname.domainname,
+    };
+
+    let persistent_state = host
+        .persistent_state
+        .as_ref()
+        .map(|options| {
+            let mut state = match &options.path {
+                Some(path) => {
+                    let expanded_path = expand_config_path(path)?;
+                    PersistentState::load(expanded_path)?
+                }
+                None => PersistentState::new(),
+            };
+            for (key, value) in &options.values {
+                state.insert(key.clone(), StateValue::Text(value.clone()))?;
+            }
+            Ok::<_, anyhow::Error>(state)
+        })
+        .transpose()
+        .context("Failed to load the host's persistent state")?;
+
+    let resolver = host
+        .resolver
+        .as_ref()
+        .map(Resolver::new)
+        .transpose()
+        .context("Failed to configure the host's resolver")?;
+
+    let tcp_delayed_ack = host.tcp_delayed_ack.clone().unwrap_or_default();
+    let to_sim_time = |x: units::Time<units::TimePrefix>| {
+        SimulationTime::try_from(Duration::from(x))
+            .ok()
+            .context("The TCP delayed ACK timeout is out of range")
+    };
+    if tcp_delayed_ack
+        .max_unacked_bytes
+        .is_some_and(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value() == 0)
+    {
+        return Err(anyhow::anyhow!(
+            "The TCP delayed ACK 'max_unacked_bytes' must be greater than 0"
+        ));
+    }
+    let tcp_delayed_ack = TcpDelayedAck {
+        timeout: to_sim_time(tcp_delayed_ack.timeout)?,
+        quick_timeout: to_sim_time(tcp_delayed_ack.quick_timeout)?,
+        quick_acks: tcp_delayed_ack.quick_acks,
+        max_unacked_bytes: tcp_delayed_ack
+            .max_unacked_bytes
+            .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value()),
+    };
+
+    let ip_local_port_range = host.ip_local_port_range.clone().unwrap_or_default();
+    if ip_local_port_range.min == 0 || ip_local_port_range.min > ip_local_port_range.max {
+        return Err(anyhow::anyhow!(
+            "The 'ip_local_port_range' must have a non-zero 'min' that is not larger than its 'max'"
+        ));
+    }
+    let ip_local_port_range = ip_local_port_range.min..=ip_local_port_range.max;
+
+    let tcp_time_wait = host.tcp_time_wait.clone().unwrap_or_default();
+    let tcp_time_wait = TcpTimeWait {
+        timeout: SimulationTime::try_from(Duration::from(tcp_time_wait.timeout))
+            .ok()
+            .context("The TCP TIME_WAIT timeout is out of range")?,
+        reuse: tcp_time_wait.reuse,
+    };
+
+    let arp_cache_timeout = host
+        .arp
+        .as_ref()
+        .map(|x| {
+            SimulationTime::try_from(Duration::from(x.cache_timeout))
+                .ok()
+                .context("The ARP cache timeout is out of range")
+        })
+        .transpose()?;
+
+    let load_balancer = host
+        .load_balancer
+        .as_ref()
+        .map(|lb| {
+            if lb.backends.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Load balancers require at least one backend"
+                ));
+            }
+            let backend_port = lb.backend_port.unwrap_or(lb.port);
+            if lb.dsr && backend_port != lb.port {
+                return Err(anyhow::anyhow!(
+                    "The 'backend_port' of a load balancer in DSR mode must be its 'port'"
+                ));
+            }
+            Ok(LoadBalancer {
+                port: lb.port,
+                backend_hostnames: lb.backends.clone(),
+                backends: Vec::new(),
+                backend_port,
+                algorithm: lb.algorithm,
+                dsr: lb.dsr,
+            })
+        })
+        .transpose()?;
+
+    let firewall = host
+        .firewall
+        .as_ref()
+        .map(Firewall::from_options)
+        .transpose()
+        .context("Failed to configure the host's firewall")?;
+
+    let throttles = host
+        .throttles
+        .iter()
+        .map(ThrottleRule::from_options)
+        .collect::<anyhow::Result<_>>()
+        .context("Failed to configure the host's throttles")?;
+
+    let bridge = host
+        .bridge
+        .as_ref()
+        .map(|bridge| {
+            // the packets that the host receives are written to the device rather than its sockets
+            if !processes.is_empty() || !host.native_apps.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "A bridged host can't run processes or native apps"
+                ));
+            }
+            // interface names have room for 15 bytes and a nul terminator
+            if bridge.tun.is_empty() || bridge.tun.len() > 15 || bridge.tun.contains('\0') {
+                return Err(anyhow::anyhow!(
+                    "The TUN device name must be between 1 and 15 bytes and must not contain nul \
+                    bytes"
+                ));
+            }
+            let poll_interval = Duration::from(bridge.poll_interval);
+            let poll_interval = SimulationTime::try_from(poll_interval).unwrap();
+            if poll_interval == SimulationTime::ZERO {
+                return Err(anyhow::anyhow!(
+                    "The bridge's 'poll_interval' must be greater than 0"
+                ));
+            }
+            log::warn!(
+                "Host '{hostname}' is bridged to TUN device '{}', so the simulation will not be \
+                deterministic",
+                bridge.tun
+            );
+            Ok(Bridge {
+                tun: bridge.tun.clone(),
+                poll_interval,
+            })
+        })
+        .transpose()
+        .context("Failed to configure the host's bridge")?;
+
+    let native_apps = host
+        .native_apps
+        .iter()
+        .map(|app| build_native_app(app, config))
+        .collect::<anyhow::Result<_>>()
+        .context("Failed to configure the host's native apps")?;
+
+    let heartbeat_sample_interval: Option<SimulationTime> = config
+        .experimental
+        .host_heartbeat_sample_interval
+        .flatten()
+        .map(|x| Duration::from(x).try_into().unwrap());
+    if heartbeat_sample_interval == Some(SimulationTime::ZERO) {
+        return Err(anyhow::anyhow!(
+            "The 'host_heartbeat_sample_interval' must be greater than 0"
+        ));
+    }
+
     Ok(HostInfo {
         name: hostname,
+        tags: host.tags.clone(),
+        metadata: host.metadata.clone(),
         processes,
 
-        seed: randomness_for_seed_calc ^ hostname_hash,
+        seed,
         network_node_id: host.network_node_id,
         pause_for_debugging,
 
@@ -268,20 +956,27 @@ fn build_host(
                     .convert(units::SiPrefixUpper::Base)
                     .unwrap()
                     .value(),
+                payload: host.host_options.pcap_payload.unwrap(),
+                error_action: host.host_options.pcap_error_action.unwrap(),
             }),
 
-        // some options come from the config options and not the host options
+        // some options come from the config options and not the host options, but may be
+        // overridden by the host's instrumentation profile
         heartbeat_log_level: config.experimental.host_heartbeat_log_level,
-        heartbeat_log_info: config
-            .experimental
-            .host_heartbeat_log_info
-            .clone()
+        heartbeat_log_info: profile
+            .and_then(|x| x.host_heartbeat_log_info.as_ref())
+            .or(config.experimental.host_heartbeat_log_info.as_ref())
+            .cloned()
             .unwrap_or_default(),
-        heartbeat_interval: config
-            .experimental
-            .host_heartbeat_interval
+        heartbeat_interval: profile
+            .and_then(|x| x.host_heartbeat_interval)
+            .or(config.experimental.host_heartbeat_interval)
             .flatten()
             .map(|x| Duration::from(x).try_into().unwrap()),
+        heartbeat_sample_interval,
+        heartbeat_top_talkers: config.experimental.host_heartbeat_top_talkers.unwrap(),
+        app_metrics_port: config.experimental.app_metrics_port.flatten(),
+        strace_logging_options: config.strace_logging_mode(host),
         send_buf_size: config
             .experimental
             .socket_send_buffer
@@ -299,6 +994,102 @@ fn build_host(
         autotune_send_buf: config.experimental.socket_send_autotune.unwrap(),
         autotune_recv_buf: config.experimental.socket_recv_autotune.unwrap(),
         qdisc: config.experimental.interface_qdisc.unwrap(),
+        router_qdisc: config.experimental.router_qdisc.unwrap(),
+        data_template,
+        syslog_sink: host.host_options.syslog_sink.unwrap(),
+        shm_size: host
+            .host_options
+            .shm_size
+            .flatten()
+            .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value()),
+        replay,
+        tor,
+        seed_node,
+        probes,
+        probe_ports: Vec::new(),
+        uname,
+        persistent_state,
+        resolver,
+        tcp_delayed_ack,
+        ip_local_port_range,
+        tcp_time_wait,
+        arp_cache_timeout,
+        load_balancer,
+        load_balancer_vips: Vec::new(),
+        firewall,
+        throttles,
+        bridge,
+        native_apps,
+        port_remaps: Vec::new(),
+        tcp_abort_on_overflow: host.host_options.tcp_abort_on_overflow.unwrap(),
+        tcp_max_syn_backlog: host.host_options.tcp_max_syn_backlog.unwrap(),
+        tcp_syncookies: host.host_options.tcp_syncookies.unwrap(),
+        tcp_timestamps: host.host_options.tcp_timestamps.unwrap(),
+        tcp_window_scaling: host.host_options.tcp_window_scaling.unwrap(),
+        max_event_queue_len: host.host_options.max_event_queue_len.flatten(),
+        event_queue_limit_action: host.host_options.event_queue_limit_action.unwrap(),
+        packet_payload: host.host_options.packet_payload.unwrap(),
+        output_compression: host.host_options.output_compression.unwrap(),
+    })
+}
+
+/// Delay the start time of each process by a random time less than `jitter`, while keeping it
+/// earlier than the process' shutdown time and the simulation stop time. The delays are chosen from
+/// `seed`, so they're the same in each run of a simulation.
+fn stagger_start_times(
+    processes: &mut [ProcessInfo],
+    jitter: SimulationTime,
+    stop_time: SimulationTime,
+    seed: u64,
+) {
+    // a separate stream from the host's own random number generator, which uses the same seed
+    let mut random = Xoshiro256PlusPlus::seed_from_u64(seed);
+    random.jump();
+
+    for proc in processes {
+        let end = proc.shutdown_time.map_or(stop_time, |x| x.min(stop_time));
+        // `build_process` checked that the process starts before `end`
+        let max_delay = u64::try_from(jitter.min(end - proc.start_time).as_nanos()).unwrap();
+        proc.start_time =
+            proc.start_time + SimulationTime::from_nanos(random.gen_range(0..max_delay));
+    }
+}
+
+/// For a native app entry in the configuration options, build a `NativeApp` object.
+fn build_native_app(app: &NativeAppOptions, config: &ConfigOptions) -> anyhow::Result<NativeApp> {
+    let start_time = Duration::from(app.start_time).try_into().unwrap();
+    let sim_stop_time =
+        SimulationTime::try_from(Duration::from(config.general.stop_time.unwrap())).unwrap();
+
+    if start_time >= sim_stop_time {
+        return Err(anyhow::anyhow!(
+            "Native app start time '{}' must be earlier than the simulation stop time '{}'",
+            app.start_time,
+            config.general.stop_time.unwrap(),
+        ));
+    }
+
+    let args = match &app.args {
+        ProcessArgs::List(x) => x.clone(),
+        ProcessArgs::Str(x) => parse_string_as_args(OsStr::new(&x.trim()))
+            .map_err(|e| anyhow::anyhow!(e))
+            .with_context(|| format!("Failed to parse arguments: {x}"))?
+            .into_iter()
+            .map(|x| {
+                x.into_string()
+                    .map_err(|x| anyhow::anyhow!("Argument {x:?} is not valid UTF-8"))
+            })
+            .collect::<anyhow::Result<_>>()?,
+    };
+
+    // check the app's name and arguments now rather than when the host starts it
+    native_app::create(&app.name, &args)
+        .with_context(|| format!("Invalid native app '{}'", app.name))?;
+
+    Ok(NativeApp {
+        name: app.name.clone(),
+        args,
+        start_time,
     })
 }
 
@@ -382,17 +1173,353 @@ fn build_process(proc: &ProcessOptions, config: &ConfigOptions) -> anyhow::Resul
     // set argv[0] as the user-provided expanded string, not the canonicalized version
     args.insert(0, expanded_path.into());
 
+    let quota = build_quota_limits(proc)?;
+
+    let mut credentials = Credentials::native();
+    if let Some(uid) = proc.uid {
+        if uid == u32::MAX {
+            return Err(anyhow::anyhow!("The process uid must not be {uid}"));
+        }
+        credentials = Credentials {
+            ruid: uid,
+            euid: uid,
+            suid: uid,
+            fsuid: uid,
+            ..credentials
+        };
+    }
+    if let Some(gid) = proc.gid {
+        if gid == u32::MAX {
+            return Err(anyhow::anyhow!("The process gid must not be {gid}"));
+        }
+        credentials = Credentials {
+            rgid: gid,
+            egid: gid,
+            sgid: gid,
+            fsgid: gid,
+            ..credentials
+        };
+    }
+    if let Some(groups) = &proc.groups {
+        if groups.len() > NGROUPS_MAX {
+            return Err(anyhow::anyhow!(
+                "A process can have at most {NGROUPS_MAX} supplementary groups"
+            ));
+        }
+        credentials.groups = groups.clone();
+    }
+
+    let root = proc
+        .root
+        .as_ref()
+        .map(|root| {
+            let root = expand_config_path(root)?;
+            let root = std::fs::canonicalize(&root)
+                .with_context(|| format!("Failed to resolve root directory '{root:?}'"))?;
+            if !root.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "The root directory '{root:?}' is not a directory"
+                ));
+            }
+            Ok(root)
+        })
+        .transpose()?;
+
+    let listen_sockets = build_listen_sockets(proc)?;
+
+    let interpreter = proc
+        .interpreter
+        .as_ref()
+        .map(|path| {
+            let path = resolve_config_path(path, "interpreter")?;
+            verify_executable_path(&path)
+                .with_context(|| format!("Failed to verify interpreter path '{path:?}'"))?;
+            Ok::<_, anyhow::Error>(path)
+        })
+        .transpose()?;
+
+    let env = build_library_env(proc)?;
+
     Ok(ProcessInfo {
         plugin: canonical_path,
         start_time,
         shutdown_time,
         shutdown_signal,
         args,
-        env: proc.environment.clone(),
+        env,
         expected_final_state: proc.expected_final_state,
+        quota,
+        credentials,
+        root,
+        listen_sockets,
+        interpreter,
     })
 }
 
+/// Expand a leading `~` in a path from the configuration. Paths that aren't valid UTF-8 are
+/// rejected, since the tilde expansion works on strings.
+fn expand_config_path(path: &Path) -> anyhow::Result<PathBuf> {
+    let path_str = path
+        .to_str()
+        .with_context(|| format!("The path '{path:?}' is not valid UTF-8"))?;
+    Ok(tilde_expansion(path_str))
+}
+
+/// Resolve a path from a process' options, which may start with `~/` or be relative to the
+/// current working directory. `what` describes the path in the error.
+fn resolve_config_path(path: &Path, what: &str) -> anyhow::Result<PathBuf> {
+    let path = expand_config_path(path)?;
+    std::fs::canonicalize(&path).with_context(|| format!("Failed to resolve {what} '{path:?}'"))
+}
+
+/// The process' environment, with its `library_path` and `preload` options added to
+/// `LD_LIBRARY_PATH` and `LD_PRELOAD`. They're placed before any paths that the `environment`
+/// option gives for these variables, so they take precedence.
+fn build_library_env(proc: &ProcessOptions) -> anyhow::Result<BTreeMap<EnvName, String>> {
+    let mut env = proc.environment.clone();
+
+    let vars = [
+        ("LD_LIBRARY_PATH", &proc.library_path, "library path"),
+        ("LD_PRELOAD", &proc.preload, "preload"),
+    ];
+
+    for (var, paths, what) in vars {
+        if paths.is_empty() {
+            continue;
+        }
+
+        let mut resolved = Vec::new();
+        for path in paths {
+            let path = resolve_config_path(path, what)?;
+            let path = path
+                .to_str()
+                .with_context(|| format!("The {what} '{path:?}' isn't valid UTF-8"))?;
+            // ld.so(8): the items of both lists are separated by colons, and LD_PRELOAD also
+            // allows spaces
+            if path.contains([':', ' ']) {
+                return Err(anyhow::anyhow!(
+                    "The {what} '{path}' must not contain ':' or ' '"
+                ));
+            }
+            resolved.push(path.to_string());
+        }
+
+        let var = EnvName::new(var).unwrap();
+        if let Some(existing) = env.get(&var).filter(|x| !x.is_empty()) {
+            resolved.push(existing.clone());
+        }
+        env.insert(var, resolved.join(":"));
+    }
+
+    Ok(env)
+}
+
+/// Build the sockets that are passed to the process for socket activation.
+fn build_listen_sockets(proc: &ProcessOptions) -> anyhow::Result<Vec<ListenSocket>> {
+    if proc.listen_sockets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    for var in ["LISTEN_FDS", "LISTEN_PID", "LISTEN_FDNAMES"] {
+        if proc.environment.contains_key(&EnvName::new(var).unwrap()) {
+            return Err(anyhow::anyhow!(
+                "The environment variable '{var}' can't be set for a process with listen sockets"
+            ));
+        }
+    }
+
+    let mut sockets: Vec<ListenSocket> = Vec::new();
+    for socket in &proc.listen_sockets {
+        if socket.port == 0 {
+            return Err(anyhow::anyhow!("A listen socket's port must not be 0"));
+        }
+        if sockets
+            .iter()
+            .any(|x| x.protocol == socket.protocol && x.port == socket.port)
+        {
+            return Err(anyhow::anyhow!(
+                "The process has more than one {:?} listen socket on port {}",
+                socket.protocol,
+                socket.port,
+            ));
+        }
+
+        // the same restrictions as systemd's 'FileDescriptorName'
+        let name = socket.name.clone().unwrap_or_else(|| "unknown".to_string());
+        if name.is_empty()
+            || name.len() > 255
+            || !name.bytes().all(|x| x.is_ascii_graphic() && x != b':')
+        {
+            return Err(anyhow::anyhow!(
+                "The listen socket name '{name}' must be 1 to 255 printable ASCII characters, \
+                 excluding spaces and ':'"
+            ));
+        }
+
+        sockets.push(ListenSocket {
+            protocol: socket.protocol,
+            port: socket.port,
+            name,
+        });
+    }
+
+    Ok(sockets)
+}
+
+/// Check that the host's process listen sockets don't bind a port that's already bound by one of
+/// the host's services or by another process that runs at the same time. If `remap` is true, the
+/// conflicting sockets are instead moved to free ports, which are recorded in the host's
+/// `port_remaps`.
+fn check_port_conflicts(host: &mut HostInfo, remap: bool) -> anyhow::Result<()> {
+    struct Binding {
+        protocol: ListenSocketProtocol,
+        port: u16,
+        owner: String,
+        lifetime: std::ops::Range<SimulationTime>,
+    }
+
+    let forever = SimulationTime::ZERO..SimulationTime::MAX;
+    let mut bindings = Vec::new();
+    if let Some(seed_node) = &host.seed_node {
+        bindings.push(Binding {
+            protocol: ListenSocketProtocol::Udp,
+            port: seed_node.port,
+            owner: "the seed node".to_string(),
+            lifetime: forever.clone(),
+        });
+    }
+    for port in &host.probe_ports {
+        bindings.push(Binding {
+            protocol: ListenSocketProtocol::Udp,
+            port: port.port,
+            owner: "the probe responder".to_string(),
+            lifetime: forever.clone(),
+        });
+    }
+
+    // ports that are never used as the new port of a conflicting socket
+    let mut declared: HashSet<(ListenSocketProtocol, u16)> =
+        bindings.iter().map(|x| (x.protocol, x.port)).collect();
+    for proc in &host.processes {
+        declared.extend(proc.listen_sockets.iter().map(|x| (x.protocol, x.port)));
+    }
+
+    for (index, proc) in host.processes.iter_mut().enumerate() {
+        let owner = format!(
+            "process {index} ('{}')",
+            proc.plugin.file_name().unwrap().to_string_lossy()
+        );
+        // the process may not exit when it's sent its shutdown signal, but a later process can
+        // only bind the same port if it has
+        let lifetime = proc.start_time..proc.shutdown_time.unwrap_or(SimulationTime::MAX);
+
+        for socket in &mut proc.listen_sockets {
+            let conflict = bindings.iter().find(|x| {
+                x.protocol == socket.protocol
+                    && x.port == socket.port
+                    && x.lifetime.start < lifetime.end
+                    && lifetime.start < x.lifetime.end
+            });
+
+            if let Some(conflict) = conflict {
+                if !remap {
+                    return Err(anyhow::anyhow!(
+                        "Both {owner} and {} bind {:?} port {}. Change one of the ports, or set \
+                         'experimental.remap_conflicting_ports' to move the process's socket to a \
+                         free port.",
+                        conflict.owner,
+                        socket.protocol,
+                        socket.port,
+                    ));
+                }
+
+                // ephemeral ports may already be bound when the process starts
+                let ephemeral = &host.ip_local_port_range;
+                let port = (socket.port.saturating_add(1)..=u16::MAX)
+                    .chain(1024..socket.port)
+                    .find(|x| !ephemeral.contains(x) && !declared.contains(&(socket.protocol, *x)))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("There's no free port to move {owner}'s socket to")
+                    })?;
+
+                log::info!(
+                    "Moved {owner}'s {:?} listen socket on host '{}' from port {} to port {port}, \
+                     since the port is already bound by {}",
+                    socket.protocol,
+                    host.name,
+                    socket.port,
+                    conflict.owner,
+                );
+                host.port_remaps.push(PortRemap {
+                    process: index,
+                    protocol: socket.protocol,
+                    from: socket.port,
+                    to: port,
+                });
+                declared.insert((socket.protocol, port));
+                socket.port = port;
+            }
+
+            bindings.push(Binding {
+                protocol: socket.protocol,
+                port: socket.port,
+                owner: owner.clone(),
+                lifetime: lifetime.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Build the process's quota limits, or `None` if the process has no limits.
+fn build_quota_limits(proc: &ProcessOptions) -> anyhow::Result<Option<QuotaLimits>> {
+    if proc.quota.is_none() && proc.socket_rate_limits.is_empty() {
+        return Ok(None);
+    }
+
+    let bytes_per_sec = |bw: Option<units::BitsPerSec<units::SiPrefixUpper>>| {
+        let bw = bw.map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value() / 8);
+        if bw == Some(0) {
+            return Err(anyhow::anyhow!(
+                "Process bandwidth limits must be at least 1 byte per second"
+            ));
+        }
+        Ok(bw)
+    };
+
+    let mut limits = QuotaLimits {
+        cpu_share: None,
+        bandwidth_up: None,
+        bandwidth_down: None,
+        socket_limits: Vec::new(),
+    };
+
+    if let Some(quota) = &proc.quota {
+        if let Some(cpu) = quota.cpu {
+            if !(cpu > 0.0 && cpu <= 1.0) {
+                return Err(anyhow::anyhow!(
+                    "Process CPU quota '{cpu}' must be greater than 0 and at most 1"
+                ));
+            }
+        }
+
+        limits.cpu_share = quota.cpu;
+        limits.bandwidth_up = bytes_per_sec(quota.bandwidth_up)?;
+        limits.bandwidth_down = bytes_per_sec(quota.bandwidth_down)?;
+    }
+
+    for socket_limit in &proc.socket_rate_limits {
+        limits.socket_limits.push(SocketRateLimit {
+            port: socket_limit.port,
+            bandwidth_up: bytes_per_sec(socket_limit.bandwidth_up)?,
+            bandwidth_down: bytes_per_sec(socket_limit.bandwidth_down)?,
+        });
+    }
+
+    Ok(Some(limits))
+}
+
 /// Generate an IP assignment map using hosts' configured IP addresses and graph node IDs. For hosts
 /// without IP addresses, they will be assigned an arbitrary IP address.
 fn assign_ips(hosts: &mut [HostInfo]) -> anyhow::Result<IpAssignment<u32>> {
@@ -418,6 +1545,183 @@ fn assign_ips(hosts: &mut [HostInfo]) -> anyhow::Result<IpAssignment<u32>> {
     Ok(ip_assignment)
 }
 
+/// Look up the IP addresses of hosts that are referred to by other hosts' options (the
+/// destination hosts of replay hosts, the peers of seed nodes, the backends of load balancers, and
+/// the targets of probes). Must be called after IP addresses have been assigned to all hosts.
+fn resolve_host_references(hosts: &mut [HostInfo]) -> anyhow::Result<()> {
+    let host_ips: Vec<(String, std::net::IpAddr)> = hosts
+        .iter()
+        .map(|x| (x.name.clone(), x.ip_addr.unwrap()))
+        .collect();
+    let host_ips_map: HashMap<&str, std::net::IpAddr> =
+        host_ips.iter().map(|(x, y)| (x.as_str(), *y)).collect();
+
+    let lookup = |name: &str| -> anyhow::Result<std::net::Ipv4Addr> {
+        match host_ips_map.get(name) {
+            Some(std::net::IpAddr::V4(ip)) => Ok(*ip),
+            Some(std::net::IpAddr::V6(_)) => Err(anyhow::anyhow!(
+                "Host '{name}' does not have an IPv4 address"
+            )),
+            None => Err(anyhow::anyhow!("Host '{name}' doesn't exist")),
+        }
+    };
+
+    for host in hosts.iter_mut() {
+        if let Some(replay) = &mut host.replay {
+            replay.destinations = replay
+                .destination_hostnames
+                .iter()
+                .map(|name| lookup(name))
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| format!("Invalid replay destination for host '{}'", host.name))?;
+        }
+
+        if let Some(seed_node) = &mut host.seed_node {
+            seed_node.peers = match &seed_node.peer_hostnames {
+                Some(names) => names
+                    .iter()
+                    .map(|name| lookup(name))
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("Invalid seed node peer for host '{}'", host.name))?,
+                // all hosts other than the seed node
+                None => host_ips
+                    .iter()
+                    .filter(|(name, _)| *name != host.name)
+                    .map(|(name, _)| lookup(name))
+                    .collect::<anyhow::Result<_>>()?,
+            };
+        }
+
+        for probe in &mut host.probes {
+            probe.target = Some(
+                lookup(&probe.target_hostname)
+                    .with_context(|| format!("Invalid probe target for host '{}'", host.name))?,
+            );
+        }
+
+        if let Some(lb) = &mut host.load_balancer {
+            lb.backends = lb
+                .backend_hostnames
+                .iter()
+                .map(|name| Ok(std::net::SocketAddrV4::new(lookup(name)?, lb.backend_port)))
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| {
+                    format!("Invalid load balancer backend for host '{}'", host.name)
+                })?;
+        }
+    }
+
+    // the targets of probes answer them on the probes' ports
+    let mut probe_ports: HashMap<String, Vec<ProbePort>> = HashMap::new();
+    for host in hosts.iter() {
+        for (index, probe) in host.probes.iter().enumerate() {
+            let ports = probe_ports
+                .entry(probe.target_hostname.clone())
+                .or_default();
+            if !ports.iter().any(|x| x.port == probe.port) {
+                ports.push(ProbePort {
+                    port: probe.port,
+                    prober: host.name.clone(),
+                    index,
+                });
+            }
+        }
+    }
+    for host in hosts.iter_mut() {
+        if let Some(ports) = probe_ports.remove(&host.name) {
+            host.probe_ports = ports;
+        }
+    }
+
+    // the backends of load balancers in DSR mode reply to clients from the load balancer's address
+    let mut vips: HashMap<String, Vec<std::net::SocketAddrV4>> = HashMap::new();
+    for host in hosts.iter() {
+        if let Some(lb) = host.load_balancer.as_ref().filter(|x| x.dsr) {
+            let vip = std::net::SocketAddrV4::new(lookup(&host.name)?, lb.port);
+            for name in &lb.backend_hostnames {
+                let host_vips = vips.entry(name.clone()).or_default();
+                if !host_vips.contains(&vip) {
+                    host_vips.push(vip);
+                }
+            }
+        }
+    }
+    for host in hosts.iter_mut() {
+        let Some(host_vips) = vips.remove(&host.name) else {
+            continue;
+        };
+        for (i, vip) in host_vips.iter().enumerate() {
+            if host_vips[..i].iter().any(|x| x.port() == vip.port()) {
+                return Err(anyhow::anyhow!(
+                    "Host '{}' is a backend of multiple load balancers in DSR mode with port {}",
+                    host.name,
+                    vip.port()
+                ));
+            }
+        }
+        host.load_balancer_vips = host_vips;
+    }
+
+    Ok(())
+}
+
+/// Import the bandwidths of hosts that are tor relays from the consensus, if the host doesn't have a
+/// bandwidth configured.
+#[cfg(feature = "tor_helpers")]
+fn apply_tor_consensus(config: &ConfigOptions, hosts: &mut [HostInfo]) -> anyhow::Result<()> {
+    use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+    use crate::utility::tor::Consensus;
+
+    let Some(path) = config.experimental.tor_consensus.flatten_ref() else {
+        return Ok(());
+    };
+
+    let consensus = Consensus::from_file(tilde_expansion(path))?;
+    let sim_start = EmulatedTime::SIMULATION_START.duration_since(&EmulatedTime::UNIX_EPOCH);
+    let time_skew = consensus.time_skew(sim_start.as_secs());
+    log::info!(
+        "The tor consensus becomes valid {time_skew} seconds after the start of the simulation"
+    );
+
+    for host in hosts.iter_mut() {
+        let Some(tor) = &mut host.tor else {
+            continue;
+        };
+
+        tor.consensus_time_skew = Some(time_skew);
+
+        let Some(nickname) = &tor.nickname else {
+            continue;
+        };
+
+        let relay = consensus.relay(nickname).with_context(|| {
+            format!(
+                "The relay '{nickname}' for host '{}' is not in the consensus",
+                host.name
+            )
+        })?;
+
+        if let Some(bandwidth) = relay.bandwidth {
+            let bandwidth_bits = bandwidth * 8;
+            host.bandwidth_down_bits = host.bandwidth_down_bits.or(Some(bandwidth_bits));
+            host.bandwidth_up_bits = host.bandwidth_up_bits.or(Some(bandwidth_bits));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "tor_helpers"))]
+fn apply_tor_consensus(config: &ConfigOptions, _hosts: &mut [HostInfo]) -> anyhow::Result<()> {
+    if config.experimental.tor_consensus.flatten_ref().is_some() {
+        return Err(anyhow::anyhow!(
+            "The 'tor_consensus' option requires Shadow to be built with the 'tor_helpers' feature"
+        ));
+    }
+    Ok(())
+}
+
 /// Generate a map containing routing information (latency, packet loss, etc) for each pair of
 /// nodes.
 fn generate_routing_info(
@@ -438,7 +1742,20 @@ fn generate_routing_info(
         ((src, dst), path)
     };
 
-    let paths = if use_shortest_paths {
+    let paths = if graph.has_autonomous_systems() {
+        if !use_shortest_paths {
+            return Err(anyhow::anyhow!(
+                "Routing between autonomous systems requires the 'use_shortest_path' option"
+            ));
+        }
+        graph
+            .compute_as_paths(&nodes[..])
+            .map_err(|e| anyhow::anyhow!(e))
+            .context("Failed to compute paths between autonomous systems")?
+            .into_iter()
+            .map(to_ids)
+            .collect()
+    } else if use_shortest_paths {
         graph
             .compute_shortest_paths(&nodes[..])
             .map_err(|e| anyhow::anyhow!(e))
@@ -58,6 +58,17 @@ pub struct CliOptions {
     #[clap(long)]
     pub show_config: bool,
 
+    /// Run the simulation N times, each with a different seed derived from the configured seed.
+    /// Each trial's output is written to a 'trial-<i>' directory within the data directory
+    #[clap(long, value_name = "N")]
+    #[clap(value_parser = clap::value_parser!(u32).range(1..))]
+    pub trials: Option<u32>,
+
+    /// The maximum number of trials to run at the same time
+    #[clap(long, value_name = "N", requires("trials"), default_value_t = 1)]
+    #[clap(value_parser = clap::value_parser!(u32).range(1..))]
+    pub trial_jobs: u32,
+
     #[clap(flatten)]
     pub general: GeneralOptions,
 
@@ -85,12 +96,119 @@ pub struct ConfigFileOptions {
     #[serde(default)]
     pub experimental: ExperimentalOptions,
 
+    /// Host options and processes for the hosts with a given tag
+    #[serde(default)]
+    pub tags: BTreeMap<String, TagOptions>,
+
+    /// Named bundles of instrumentation settings (pcap, heartbeat, strace, logging, etc) that can be
+    /// used by hosts
+    #[serde(default)]
+    pub instrumentation_profiles: BTreeMap<String, InstrumentationProfileOptions>,
+
     // we use a BTreeMap so that the hosts are sorted by their hostname (useful for determinism)
     // since shadow parses to a serde_yaml::Value initially, we don't need to worry about duplicate
     // hostnames here
     pub hosts: BTreeMap<HostName, HostOptions>,
 }
 
+impl ConfigFileOptions {
+    /// Merge the options of each host's tags into the host's options. The tags' processes start
+    /// before the host's own processes, and the host's own `host_options` and
+    /// `instrumentation_profile` take precedence over the tags'. If a host has more than one tag,
+    /// the options of the earlier tags take precedence over the later tags'.
+    pub fn apply_tags(&mut self) -> anyhow::Result<()> {
+        for (hostname, host) in &mut self.hosts {
+            let mut processes = Vec::new();
+            let mut tag_profile: Option<(&String, &String)> = None;
+
+            for tag in &host.tags {
+                // a tag doesn't need to be defined if it's only used in host selectors
+                let Some(tag_options) = self.tags.get(tag) else {
+                    continue;
+                };
+
+                processes.extend(tag_options.processes.iter().cloned());
+                host.host_options = host
+                    .host_options
+                    .clone()
+                    .with_defaults(tag_options.host_options.clone());
+
+                if let Some(profile) = &tag_options.instrumentation_profile {
+                    match tag_profile {
+                        Some((other_tag, other_profile)) if other_profile != profile => {
+                            anyhow::bail!(
+                                "Host '{hostname}' has tags '{other_tag}' and '{tag}' with \
+                                different instrumentation profiles"
+                            );
+                        }
+                        Some(_) => {}
+                        None => tag_profile = Some((tag, profile)),
+                    }
+                }
+            }
+
+            if host.instrumentation_profile.is_none() {
+                host.instrumentation_profile = tag_profile.map(|(_, profile)| profile.clone());
+            }
+
+            processes.append(&mut host.processes);
+            host.processes = processes;
+        }
+
+        Ok(())
+    }
+
+    /// Merge each host's instrumentation profile into the host's options, and set the host's
+    /// `instrumentation_profile` to the name of the profile. A host uses the profile named by its
+    /// `instrumentation_profile` option, or otherwise the profile whose `hosts` patterns match the
+    /// host's name or tags, if any. The host's own `host_options` take precedence over the
+    /// profile's.
+    pub fn apply_instrumentation_profiles(&mut self) -> anyhow::Result<()> {
+        let selectors: Vec<(&String, HostSelector)> = self
+            .instrumentation_profiles
+            .iter()
+            .map(|(name, profile)| (name, HostSelector::new(&profile.hosts)))
+            .collect();
+
+        for (hostname, host) in &mut self.hosts {
+            let name = match &host.instrumentation_profile {
+                Some(name) => name,
+                None => {
+                    let matching: Vec<&String> = selectors
+                        .iter()
+                        .filter(|(_, selector)| selector.is_match(hostname, &host.tags))
+                        .map(|(name, _)| *name)
+                        .collect();
+
+                    match matching[..] {
+                        [] => continue,
+                        [name] => name,
+                        _ => anyhow::bail!(
+                            "Host '{hostname}' matches the host patterns of more than one \
+                            instrumentation profile: {matching:?}"
+                        ),
+                    }
+                }
+            };
+
+            let Some(profile) = self.instrumentation_profiles.get(name) else {
+                anyhow::bail!(
+                    "Host '{hostname}' uses the instrumentation profile '{name}', which doesn't \
+                    exist"
+                );
+            };
+
+            host.host_options = host
+                .host_options
+                .clone()
+                .with_defaults(profile.host_options.clone());
+            host.instrumentation_profile = Some(name.clone());
+        }
+
+        Ok(())
+    }
+}
+
 /// Shadow configuration options after processing command-line and configuration file options.
 #[derive(Debug, Clone, Serialize)]
 pub struct ConfigOptions {
@@ -100,6 +218,8 @@ pub struct ConfigOptions {
 
     pub experimental: ExperimentalOptions,
 
+    pub instrumentation_profiles: BTreeMap<String, InstrumentationProfileOptions>,
+
     // we use a BTreeMap so that the hosts are sorted by their hostname (useful for determinism)
     pub hosts: BTreeMap<HostName, HostOptions>,
 }
@@ -132,6 +252,7 @@ impl ConfigOptions {
             general: config_file.general,
             network: config_file.network,
             experimental: config_file.experimental,
+            instrumentation_profiles: config_file.instrumentation_profiles,
             hosts: config_file.hosts,
         }
     }
@@ -158,13 +279,65 @@ impl ConfigOptions {
         SimulationTime::from_nanos(nanos)
     }
 
-    pub fn strace_logging_mode(&self) -> Option<FmtOptions> {
-        match self.experimental.strace_logging_mode.as_ref().unwrap() {
+    pub fn timer_slack(&self) -> SimulationTime {
+        let nanos = self.experimental.timer_slack.unwrap();
+        let nanos = nanos.convert(units::TimePrefix::Nano).unwrap().value();
+        SimulationTime::from_nanos(nanos)
+    }
+
+    /// The configured syscall latencies, indexed by syscall number.
+    pub fn syscall_latencies(&self) -> std::collections::HashMap<libc::c_long, SimulationTime> {
+        let options = self.experimental.syscall_latencies.as_ref().unwrap();
+        let latencies = [
+            (libc::SYS_socket, options.socket),
+            (libc::SYS_socketpair, options.socket),
+            (libc::SYS_connect, options.connect),
+            (libc::SYS_accept, options.accept),
+            (libc::SYS_accept4, options.accept),
+            (libc::SYS_close, options.close),
+        ];
+
+        latencies
+            .into_iter()
+            .filter_map(|(num, latency)| {
+                let nanos = latency?.convert(units::TimePrefix::Nano).unwrap().value();
+                Some((num, SimulationTime::from_nanos(nanos)))
+            })
+            .collect()
+    }
+
+    /// The instrumentation profile used by `host`, if any. The profiles must have been applied
+    /// with [`ConfigFileOptions::apply_instrumentation_profiles`].
+    pub fn instrumentation_profile(
+        &self,
+        host: &HostOptions,
+    ) -> Option<&InstrumentationProfileOptions> {
+        let name = host.instrumentation_profile.as_ref()?;
+        Some(&self.instrumentation_profiles[name])
+    }
+
+    /// The strace logging mode of `host`'s processes, which may be overridden by the host's
+    /// instrumentation profile.
+    pub fn strace_logging_mode(&self, host: &HostOptions) -> Option<FmtOptions> {
+        let mode = self
+            .instrumentation_profile(host)
+            .and_then(|x| x.strace_logging_mode.as_ref())
+            .or(self.experimental.strace_logging_mode.as_ref());
+
+        match mode.unwrap() {
             StraceLoggingMode::Standard => Some(FmtOptions::Standard),
             StraceLoggingMode::Deterministic => Some(FmtOptions::Deterministic),
             StraceLoggingMode::Off => None,
         }
     }
+
+    /// The memory budget in bytes, if there is one.
+    pub fn memory_budget(&self) -> Option<u64> {
+        self.experimental
+            .memory_budget
+            .flatten()
+            .map(|x| x.convert(units::SiPrefixUpper::Base).unwrap().value())
+    }
 }
 
 /// Help messages used by Clap for command line arguments, combining the doc string with
@@ -274,6 +447,28 @@ pub struct NetworkOptions {
     #[clap(long, value_name = "bool")]
     #[clap(help = NETWORK_HELP.get("use_shortest_path").unwrap().as_str())]
     pub use_shortest_path: Option<bool>,
+
+    /// Export the network topology and the traffic between hosts to the data directory
+    #[clap(skip)]
+    #[serde(default)]
+    pub topology_export: Option<TopologyExportOptions>,
+
+    /// Passive observers that record the metadata of packets crossing an edge of the network graph
+    /// or an autonomous system
+    #[clap(skip)]
+    #[serde(default)]
+    pub observers: Option<Vec<ObserverOptions>>,
+
+    /// On-path censors that block the connections whose packets contain a keyword
+    #[clap(skip)]
+    #[serde(default)]
+    pub censors: Option<Vec<CensorOptions>>,
+
+    /// Compress the network observers' records
+    #[serde(default = "default_some_output_compression_none")]
+    #[clap(long, value_name = "format")]
+    #[clap(help = NETWORK_HELP.get("observer_compression").unwrap().as_str())]
+    pub observer_compression: Option<OutputCompression>,
 }
 
 impl NetworkOptions {
@@ -284,6 +479,129 @@ impl NetworkOptions {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TopologyExportOptions {
+    /// Also record the number of bytes sent between hosts in each interval of this length
+    #[serde(default)]
+    pub flow_interval: Option<units::Time<units::TimePrefix>>,
+
+    /// Also export the topology and traffic as an ns-3 scenario
+    #[serde(default)]
+    pub ns3: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ObserverOptions {
+    /// Observes packets whose path crosses the edge from graph node `source` to graph node
+    /// `target` (in either direction if the graph is undirected).
+    Edge {
+        name: String,
+        source: u32,
+        target: u32,
+    },
+    /// Observes packets whose path passes through a node of the autonomous system `asn`.
+    AutonomousSystem { name: String, asn: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CensorOptions {
+    /// The censor's name in log messages
+    pub name: String,
+    /// Where the censor is in the network graph
+    pub location: CensorLocationOptions,
+    /// Blocks the connections whose TCP or UDP payloads contain one of these strings
+    pub keywords: Vec<String>,
+    /// What the censor does with the packets that it blocks
+    #[serde(default)]
+    pub action: CensorAction,
+    /// Also block the packets between the connection's client and server address and port for
+    /// this long after a keyword is matched
+    #[serde(default)]
+    pub residual_blocking: Option<units::Time<units::TimePrefix>>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum CensorLocationOptions {
+    /// Inspects packets whose path crosses the edge from graph node `source` to graph node
+    /// `target` (in either direction if the graph is undirected).
+    Edge { source: u32, target: u32 },
+    /// Inspects packets whose path passes through a node of the autonomous system `asn`.
+    AutonomousSystem { asn: u32 },
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CensorAction {
+    /// Drop the packets and send a TCP RST to both ends of the connection.
+    #[default]
+    Reset,
+    /// Silently drop the packets.
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum BreakpointOptions {
+    /// A host receives a packet.
+    PacketReceived {
+        /// Only packets received by this host. If not set, any host.
+        #[serde(default)]
+        host: Option<String>,
+        /// Only packets with this destination port. If not set, any port.
+        #[serde(default)]
+        port: Option<u16>,
+    },
+    /// A process exits.
+    ProcessExited {
+        /// Only processes on this host. If not set, any host.
+        #[serde(default)]
+        host: Option<String>,
+        /// Only processes with this executable name. If not set, any process.
+        #[serde(default)]
+        process: Option<String>,
+        /// Only processes that exit with a non-zero status or are killed by a signal.
+        #[serde(default)]
+        nonzero: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum InvariantOptions {
+    /// Host `from` never sends a packet to host `to`.
+    NeverSends { from: String, to: String },
+    /// The host's queue of inbound packets never holds more than `max` packets.
+    MaxQueueLength { host: String, max: usize },
+    /// The host establishes a TCP connection within `within` of the start of the simulation.
+    ConnectsWithin {
+        host: String,
+        within: units::Time<units::TimePrefix>,
+    },
+}
+
+/// Simulated latencies of individual syscalls. A syscall's latency is added each time the syscall
+/// completes (even if `model_unblocked_syscall_latency` is disabled), but not while it's blocked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SyscallLatencyOptions {
+    /// Latency of `socket` and `socketpair`.
+    #[serde(default)]
+    pub socket: Option<units::Time<units::TimePrefix>>,
+    /// Latency of `connect`.
+    #[serde(default)]
+    pub connect: Option<units::Time<units::TimePrefix>>,
+    /// Latency of `accept` and `accept4`.
+    #[serde(default)]
+    pub accept: Option<units::Time<units::TimePrefix>>,
+    /// Latency of `close`.
+    #[serde(default)]
+    pub close: Option<units::Time<units::TimePrefix>>,
+}
+
 /// Help messages used by Clap for command line arguments, combining the doc string with
 /// the Serde default.
 static EXP_HELP: Lazy<std::collections::HashMap<String, String>> =
@@ -314,6 +632,13 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("use_object_counters").unwrap().as_str())]
     pub use_object_counters: Option<bool>,
 
+    /// Write a trace of significant simulation events to the data directory in the Chrome trace
+    /// event format, which can be viewed using Perfetto
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("use_event_trace").unwrap().as_str())]
+    pub use_event_trace: Option<bool>,
+
     /// Preload our libc library for all managed processes for fast syscall interposition when possible.
     #[clap(hide_short_help = true)]
     #[clap(long, value_name = "bool")]
@@ -334,6 +659,20 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("use_preload_openssl_crypto").unwrap().as_str())]
     pub use_preload_openssl_crypto: Option<bool>,
 
+    /// Write a fingerprint of the packets that each host sent and received to the host's data
+    /// directory, to check that simulations are deterministic
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("packet_fingerprints").unwrap().as_str())]
+    pub packet_fingerprints: Option<bool>,
+
+    /// Move the process listen sockets that bind a port that's already bound on their host to a
+    /// free port, instead of failing to start the simulation
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("remap_conflicting_ports").unwrap().as_str())]
+    pub remap_conflicting_ports: Option<bool>,
+
     /// Use the MemoryManager in memory-mapping mode. This can improve
     /// performance, but disables support for dynamically spawning processes
     /// inside the simulation (e.g. the `fork` syscall).
@@ -355,6 +694,14 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("use_worker_spinning").unwrap().as_str())]
     pub use_worker_spinning: Option<bool>,
 
+    /// Run hosts on one worker thread for each N hosts that had events in the previous scheduling
+    /// round, up to the number of worker threads, or on every worker thread if null. This is
+    /// ignored if not using the thread-per-core scheduler.
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "N")]
+    #[clap(help = EXP_HELP.get("worker_autoscale_hosts").unwrap().as_str())]
+    pub worker_autoscale_hosts: Option<NullableOption<std::num::NonZeroU32>>,
+
     /// If set, overrides the automatically calculated minimum time workers may run ahead when sending events between nodes
     #[clap(hide_short_help = true)]
     #[clap(long, value_name = "seconds")]
@@ -416,6 +763,33 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("host_heartbeat_interval").unwrap().as_str())]
     pub host_heartbeat_interval: Option<NullableOption<units::Time<units::TimePrefix>>>,
 
+    /// Amount of time between samples of the host's socket buffer occupancy and interface queue
+    /// depths
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "seconds")]
+    #[clap(help = EXP_HELP.get("host_heartbeat_sample_interval").unwrap().as_str())]
+    pub host_heartbeat_sample_interval: Option<NullableOption<units::Time<units::TimePrefix>>>,
+
+    /// Number of flows and peer hosts with the most traffic to log with each heartbeat message,
+    /// or 0 to not log them
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "N")]
+    #[clap(help = EXP_HELP.get("host_heartbeat_top_talkers").unwrap().as_str())]
+    pub host_heartbeat_top_talkers: Option<u32>,
+
+    /// Loopback UDP port that managed processes can send StatsD metrics to, which are logged with
+    /// the host heartbeat messages, or null to disable
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "port")]
+    #[clap(help = EXP_HELP.get("app_metrics_port").unwrap().as_str())]
+    pub app_metrics_port: Option<NullableOption<u16>>,
+
+    /// Write aggregate heartbeat statistics of the hosts with each tag
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("host_heartbeat_group_stats").unwrap().as_str())]
+    pub host_heartbeat_group_stats: Option<bool>,
+
     /// Log the syscalls for each process to individual "strace" files
     #[clap(hide_short_help = true)]
     #[clap(long, value_name = "mode")]
@@ -448,6 +822,19 @@ pub struct ExperimentalOptions {
     #[clap(help = EXP_HELP.get("unblocked_vdso_latency").unwrap().as_str())]
     pub unblocked_vdso_latency: Option<units::Time<units::TimePrefix>>,
 
+    /// Simulated latencies of the syscalls used to open and close connections, which are used
+    /// instead of `unblocked_syscall_latency` for those syscalls
+    #[clap(skip)]
+    pub syscall_latencies: Option<SyscallLatencyOptions>,
+
+    /// Initial timer slack of each process, which the kernel uses to delay timer expirations
+    /// such as sleeps and syscall timeouts. Processes can change their own with
+    /// `prctl(PR_SET_TIMERSLACK)`. A value of 0 gives exact wakeups.
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "seconds")]
+    #[clap(help = EXP_HELP.get("timer_slack").unwrap().as_str())]
+    pub timer_slack: Option<units::Time<units::TimePrefix>>,
+
     /// The host scheduler implementation, which decides how to assign hosts to threads and threads
     /// to CPU cores
     #[clap(hide_short_help = true)]
@@ -467,6 +854,63 @@ pub struct ExperimentalOptions {
     #[clap(long, value_name = "bool")]
     #[clap(help = EXP_HELP.get("use_new_tcp").unwrap().as_str())]
     pub use_new_tcp: Option<bool>,
+
+    /// Generate a certificate authority and a TLS certificate for each host during setup
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bool")]
+    #[clap(help = EXP_HELP.get("generate_tls_certs").unwrap().as_str())]
+    pub generate_tls_certs: Option<bool>,
+
+    /// Tor consensus to import relay bandwidths from (requires the `tor_helpers` feature)
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "path")]
+    #[clap(help = EXP_HELP.get("tor_consensus").unwrap().as_str())]
+    pub tor_consensus: Option<NullableOption<String>>,
+
+    /// Path of a Unix socket to listen on for commands that pause, step, and resume the simulation
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "path")]
+    #[clap(help = EXP_HELP.get("control_socket").unwrap().as_str())]
+    pub control_socket: Option<NullableOption<String>>,
+
+    /// Pace the simulation to real time, running this many seconds of simulated time for each
+    /// second of real time, or don't pace the simulation if null
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "ratio")]
+    #[clap(help = EXP_HELP.get("real_time_pacing").unwrap().as_str())]
+    pub real_time_pacing: Option<NullableOption<f64>>,
+
+    /// Conditions that pause the simulation when they're met. Requires the control socket.
+    #[clap(skip)]
+    pub breakpoints: Option<Vec<BreakpointOptions>>,
+
+    /// Conditions that must hold throughout the simulation. The results are written to the data
+    /// directory, and the simulation fails if any are violated.
+    #[clap(skip)]
+    pub invariants: Option<Vec<InvariantOptions>>,
+
+    /// Maximum amount of memory that Shadow itself may use, which is Shadow's resident set size
+    /// plus the shared memory reserved by its shared memory allocator. A warning with a breakdown
+    /// of the usage is logged when the usage exceeds 90% of the budget, and again when it exceeds
+    /// the budget. This doesn't include the memory of the managed processes.
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "bytes")]
+    #[clap(help = EXP_HELP.get("memory_budget").unwrap().as_str())]
+    pub memory_budget: Option<NullableOption<units::Bytes<units::SiPrefixUpper>>>,
+
+    /// What to do when Shadow's memory usage exceeds `memory_budget`
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "action")]
+    #[clap(help = EXP_HELP.get("memory_budget_action").unwrap().as_str())]
+    pub memory_budget_action: Option<MemoryBudgetAction>,
+
+    /// The queueing discipline to use at each host's upstream router, which queues packets that
+    /// arrive from the simulated network until the host's downstream bandwidth allows the host to
+    /// receive them
+    #[clap(hide_short_help = true)]
+    #[clap(long, value_name = "mode")]
+    #[clap(help = EXP_HELP.get("router_qdisc").unwrap().as_str())]
+    pub router_qdisc: Option<RouterQDiscMode>,
 }
 
 impl ExperimentalOptions {
@@ -483,9 +927,12 @@ impl Default for ExperimentalOptions {
             use_sched_fifo: Some(false),
             use_syscall_counters: Some(true),
             use_object_counters: Some(true),
+            use_event_trace: Some(false),
             use_preload_libc: Some(true),
             use_preload_openssl_rng: Some(true),
             use_preload_openssl_crypto: Some(false),
+            packet_fingerprints: Some(false),
+            remap_conflicting_ports: Some(false),
             max_unapplied_cpu_latency: Some(units::Time::new(1, units::TimePrefix::Micro)),
             // 1-2 microseconds is a ballpark estimate of the minimal latency for
             // context switching to the kernel and back on modern machines.
@@ -494,9 +941,13 @@ impl Default for ExperimentalOptions {
             // Actual latencies vary from ~40 to ~400 CPU cycles. https://stackoverflow.com/a/13096917
             // Default to the lower end to minimize effect in simualations without busy loops.
             unblocked_vdso_latency: Some(units::Time::new(10, units::TimePrefix::Nano)),
+            syscall_latencies: Some(SyscallLatencyOptions::default()),
+            // Linux's default for non-realtime processes.
+            timer_slack: Some(units::Time::new(50, units::TimePrefix::Micro)),
             use_memory_manager: Some(false),
             use_cpu_pinning: Some(true),
             use_worker_spinning: Some(true),
+            worker_autoscale_hosts: Some(NullableOption::Null),
             runahead: Some(NullableOption::Value(units::Time::new(
                 1,
                 units::TimePrefix::Milli,
@@ -513,10 +964,23 @@ impl Default for ExperimentalOptions {
                 1,
                 units::TimePrefix::Sec,
             ))),
+            host_heartbeat_sample_interval: Some(NullableOption::Null),
+            host_heartbeat_top_talkers: Some(0),
+            app_metrics_port: Some(NullableOption::Null),
+            host_heartbeat_group_stats: Some(false),
             strace_logging_mode: Some(StraceLoggingMode::Off),
             scheduler: Some(Scheduler::ThreadPerCore),
             log_errors_to_tty: Some(true),
             use_new_tcp: Some(false),
+            generate_tls_certs: Some(false),
+            tor_consensus: Some(NullableOption::Null),
+            control_socket: Some(NullableOption::Null),
+            real_time_pacing: Some(NullableOption::Null),
+            breakpoints: Some(Vec::new()),
+            invariants: Some(Vec::new()),
+            memory_budget: Some(NullableOption::Null),
+            memory_budget_action: Some(MemoryBudgetAction::Warn),
+            router_qdisc: Some(RouterQDiscMode::Codel),
         }
     }
 }
@@ -533,12 +997,33 @@ static HOST_HELP: Lazy<std::collections::HashMap<String, String>> =
 // serde will default all fields to `None`, but in the cli help we want the actual defaults
 #[schemars(default = "HostDefaultOptions::new_with_defaults")]
 pub struct HostDefaultOptions {
+    /// What to do when the host's event queue is longer than `max_event_queue_len`
+    #[clap(long, value_name = "action")]
+    #[clap(help = HOST_HELP.get("event_queue_limit_action").unwrap().as_str())]
+    pub event_queue_limit_action: Option<EventQueueLimitAction>,
+
     /// Log level at which to print node messages
     #[clap(long = "host-log-level", name = "host-log-level")]
     #[clap(value_name = "level")]
     #[clap(help = HOST_HELP.get("log_level").unwrap().as_str())]
     pub log_level: Option<NullableOption<LogLevel>>,
 
+    /// Maximum number of pending events in the host's event queue, or null for no limit
+    #[clap(long, value_name = "N")]
+    #[clap(help = HOST_HELP.get("max_event_queue_len").unwrap().as_str())]
+    pub max_event_queue_len: Option<NullableOption<u64>>,
+
+    /// Compress the host's pcap files and the stdout and stderr files of its processes while
+    /// they're being written
+    #[clap(long, value_name = "format")]
+    #[clap(help = HOST_HELP.get("output_compression").unwrap().as_str())]
+    pub output_compression: Option<OutputCompression>,
+
+    /// How the payloads of packets sent by the host are stored
+    #[clap(long, value_name = "mode")]
+    #[clap(help = HOST_HELP.get("packet_payload").unwrap().as_str())]
+    pub packet_payload: Option<PacketPayload>,
+
     /// Should shadow generate pcap files?
     #[clap(long, value_name = "bool")]
     #[clap(help = HOST_HELP.get("pcap_enabled").unwrap().as_str())]
@@ -548,17 +1033,91 @@ pub struct HostDefaultOptions {
     #[clap(long, value_name = "bytes")]
     #[clap(help = HOST_HELP.get("pcap_capture_size").unwrap().as_str())]
     pub pcap_capture_size: Option<units::Bytes<units::SiPrefixUpper>>,
+
+    /// What to do when a pcap file can't be created or written to, for example because the disk
+    /// is full
+    #[clap(long, value_name = "action")]
+    #[clap(help = HOST_HELP.get("pcap_error_action").unwrap().as_str())]
+    pub pcap_error_action: Option<PcapErrorAction>,
+
+    /// Replace application payloads in pcap files with zeroes ("zero") or leave them out
+    /// ("truncate") so that captures can be shared without revealing them. Packet headers, sizes,
+    /// and timing are kept
+    #[clap(long, value_name = "mode")]
+    #[clap(help = HOST_HELP.get("pcap_payload").unwrap().as_str())]
+    pub pcap_payload: Option<PcapPayload>,
+
+    /// Delay the start of each of the host's processes by a random time less than this, to spread
+    /// out processes that are configured to start at the same time
+    #[clap(long, value_name = "seconds")]
+    #[clap(help = HOST_HELP.get("process_start_jitter").unwrap().as_str())]
+    pub process_start_jitter: Option<units::Time<units::TimePrefix>>,
+
+    /// Size limit of the host's emulated `/dev/shm` tmpfs, or null to use the real `/dev/shm`
+    #[clap(long, value_name = "bytes")]
+    #[clap(help = HOST_HELP.get("shm_size").unwrap().as_str())]
+    pub shm_size: Option<NullableOption<units::Bytes<units::SiPrefixUpper>>>,
+
+    /// Accept syslog messages on the host's `/dev/log` socket and write them to a file in the
+    /// host's data directory
+    #[clap(long, value_name = "bool")]
+    #[clap(help = HOST_HELP.get("syslog_sink").unwrap().as_str())]
+    pub syslog_sink: Option<bool>,
+
+    /// Reset connections that complete their handshake while the listening socket's accept queue
+    /// is full, instead of ignoring the handshake's final ACK
+    #[clap(long, value_name = "bool")]
+    #[clap(help = HOST_HELP.get("tcp_abort_on_overflow").unwrap().as_str())]
+    pub tcp_abort_on_overflow: Option<bool>,
+
+    /// Maximum number of half-open connections in a listening TCP socket's SYN queue
+    #[clap(long, value_name = "N")]
+    #[clap(help = HOST_HELP.get("tcp_max_syn_backlog").unwrap().as_str())]
+    pub tcp_max_syn_backlog: Option<u32>,
+
+    /// Respond with SYN cookies when a listening TCP socket's SYN queue is full, instead of
+    /// dropping the SYN
+    #[clap(long, value_name = "bool")]
+    #[clap(help = HOST_HELP.get("tcp_syncookies").unwrap().as_str())]
+    pub tcp_syncookies: Option<bool>,
+
+    /// Negotiate the TCP timestamps option, and use timestamps to measure round-trip times and to
+    /// reject old duplicate segments
+    #[clap(long, value_name = "bool")]
+    #[clap(help = HOST_HELP.get("tcp_timestamps").unwrap().as_str())]
+    pub tcp_timestamps: Option<bool>,
+
+    /// Negotiate the TCP window scale option
+    #[clap(long, value_name = "bool")]
+    #[clap(help = HOST_HELP.get("tcp_window_scaling").unwrap().as_str())]
+    pub tcp_window_scaling: Option<bool>,
 }
 
 impl HostDefaultOptions {
     pub fn new_with_defaults() -> Self {
         Self {
+            event_queue_limit_action: Some(EventQueueLimitAction::Abort),
             log_level: None,
+            max_event_queue_len: Some(NullableOption::Null),
+            output_compression: Some(OutputCompression::None),
+            packet_payload: Some(PacketPayload::Keep),
             pcap_enabled: Some(false),
             // From pcap(3): "A value of 65535 should be sufficient, on most if not all networks, to
             // capture all the data available from the packet". The maximum length of an IP packet
             // (including the header) is 65535 bytes.
             pcap_capture_size: Some(units::Bytes::new(65535, units::SiPrefixUpper::Base)),
+            pcap_error_action: Some(PcapErrorAction::Disable),
+            pcap_payload: Some(PcapPayload::Keep),
+            process_start_jitter: Some(units::Time::new(0, units::TimePrefix::Sec)),
+            shm_size: Some(NullableOption::Null),
+            syslog_sink: Some(false),
+            // the linux defaults
+            tcp_abort_on_overflow: Some(false),
+            tcp_max_syn_backlog: Some(4096),
+            tcp_syncookies: Some(true),
+            // both are enabled by default in linux
+            tcp_timestamps: Some(true),
+            tcp_window_scaling: Some(true),
         }
     }
 
@@ -578,9 +1137,23 @@ impl Default for HostDefaultOptions {
         // `host_options`. So instead we use defaults of `None` here and set the real defaults with
         // `Self::new_with_defaults` in `ConfigOptions::new`.
         Self {
+            event_queue_limit_action: None,
             log_level: None,
+            max_event_queue_len: None,
+            output_compression: None,
+            packet_payload: None,
             pcap_enabled: None,
             pcap_capture_size: None,
+            pcap_error_action: None,
+            pcap_payload: None,
+            process_start_jitter: None,
+            shm_size: None,
+            syslog_sink: None,
+            tcp_abort_on_overflow: None,
+            tcp_max_syn_backlog: None,
+            tcp_syncookies: None,
+            tcp_timestamps: None,
+            tcp_window_scaling: None,
         }
     }
 }
@@ -601,82 +1174,852 @@ pub enum ProcessFinalState {
     Running(RunningVal),
 }
 
-impl Default for ProcessFinalState {
-    fn default() -> Self {
-        Self::Exited { exited: 0 }
-    }
+impl Default for ProcessFinalState {
+    fn default() -> Self {
+        Self::Exited { exited: 0 }
+    }
+}
+
+impl std::fmt::Display for ProcessFinalState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // We use the yaml serialization here so that when reporting that an
+        // expected state didn't match the actual state, it's clear how to set
+        // the expected state in the config file to match the actual state if
+        // desired.
+        //
+        // The current enum works OK for this since there are no internal
+        // newlines in the serialization; if there are some later we might wand
+        // to serialize to json instead, which can always be put on a single
+        // line and should also be valid yaml.
+        let s = serde_yaml::to_string(self).or(Err(std::fmt::Error))?;
+        write!(f, "{}", s.trim())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessOptions {
+    pub path: std::path::PathBuf,
+
+    /// Process arguments
+    #[serde(default = "default_args_empty")]
+    pub args: ProcessArgs,
+
+    /// Environment variables passed when executing this process. Multiple variables can be
+    /// specified by using a semicolon separator (ex: `ENV_A=1;ENV_B=2`)
+    #[serde(default)]
+    pub environment: BTreeMap<EnvName, String>,
+
+    /// The simulated time at which to execute the process
+    #[serde(default)]
+    pub start_time: units::Time<units::TimePrefix>,
+
+    /// The simulated time at which to send a `shutdown_signal` signal to the process
+    #[serde(default)]
+    pub shutdown_time: Option<units::Time<units::TimePrefix>>,
+
+    /// The signal that will be sent to the process at `shutdown_time`
+    #[serde(default = "default_shutdown_signal")]
+    pub shutdown_signal: Signal,
+
+    /// The expected final state of the process. Shadow will report an error
+    /// if the actual state doesn't match.
+    #[serde(default)]
+    pub expected_final_state: ProcessFinalState,
+
+    /// Limits on the process's share of the host's CPU and network bandwidth
+    #[serde(default)]
+    pub quota: Option<ProcessQuotaOptions>,
+
+    /// Rate limits applied individually to each of the process's network sockets with a matching
+    /// local or peer port
+    #[serde(default)]
+    pub socket_rate_limits: Vec<SocketRateLimitOptions>,
+
+    /// The user ID that the process starts with, or Shadow's user ID if null
+    #[serde(default)]
+    pub uid: Option<u32>,
+
+    /// The group ID that the process starts with, or Shadow's group ID if null
+    #[serde(default)]
+    pub gid: Option<u32>,
+
+    /// The supplementary group IDs that the process starts with, or Shadow's supplementary group
+    /// IDs if null
+    #[serde(default)]
+    pub groups: Option<Vec<u32>>,
+
+    /// Directory that the process' absolute paths are resolved in, similar to chroot(2)
+    #[serde(default)]
+    pub root: Option<std::path::PathBuf>,
+
+    /// Sockets that are bound before the process starts and passed to it using the systemd
+    /// socket activation protocol
+    #[serde(default)]
+    pub listen_sockets: Vec<ListenSocketOptions>,
+
+    /// The dynamic linker that runs the executable, instead of the one named in the executable
+    #[serde(default)]
+    pub interpreter: Option<std::path::PathBuf>,
+
+    /// Directories that are searched for shared libraries before the directories in
+    /// `LD_LIBRARY_PATH`
+    #[serde(default)]
+    pub library_path: Vec<std::path::PathBuf>,
+
+    /// Shared libraries that are preloaded into the process after Shadow's own libraries
+    #[serde(default)]
+    pub preload: Vec<std::path::PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListenSocketOptions {
+    /// The port that the socket is bound to on all of the host's addresses
+    pub port: u16,
+
+    /// The protocol of the socket
+    #[serde(default)]
+    pub protocol: ListenSocketProtocol,
+
+    /// The name of the socket in `LISTEN_FDNAMES`
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListenSocketProtocol {
+    /// A listening TCP socket
+    #[default]
+    Tcp,
+    /// A bound UDP socket
+    Udp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessQuotaOptions {
+    /// Fraction of the host's CPU time that the process may use, in the range (0, 1]
+    #[serde(default)]
+    pub cpu: Option<f64>,
+
+    /// Rate at which the process may send data on network sockets
+    #[serde(default)]
+    pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// Rate at which the process may receive data on network sockets
+    #[serde(default)]
+    pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SocketRateLimitOptions {
+    /// The local or peer port of the sockets that the limit applies to
+    pub port: u16,
+
+    /// Rate at which data may be sent on each matching socket
+    #[serde(default)]
+    pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// Rate at which data may be received on each matching socket
+    #[serde(default)]
+    pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HostOptions {
+    /// Network graph node ID to assign the host to
+    pub network_node_id: u32,
+
+    #[serde(default)]
+    pub processes: Vec<ProcessOptions>,
+
+    /// IP address to assign to the host
+    #[serde(default)]
+    pub ip_addr: Option<std::net::Ipv4Addr>,
+
+    /// Downstream bandwidth capacity of the host
+    #[serde(default)]
+    pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// Upstream bandwidth capacity of the host
+    #[serde(default)]
+    pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+
+    /// Directory tree to add to the host's data directory before the host boots
+    #[serde(default)]
+    pub data_template: Option<DataTemplateOptions>,
+
+    /// Recorded traffic that the host sends to other hosts
+    #[serde(default)]
+    pub replay: Option<ReplayOptions>,
+
+    /// Tor relay settings for the host (requires the `tor_helpers` feature)
+    #[serde(default)]
+    pub tor: Option<TorHostOptions>,
+
+    /// Peer discovery service that the host runs for peer-to-peer applications
+    #[serde(default)]
+    pub seed_node: Option<SeedNodeOptions>,
+
+    /// Probes that periodically measure the network between the host and other hosts
+    #[serde(default)]
+    pub probes: Vec<ProbeOptions>,
+
+    /// System identification returned by `uname` and `/proc/sys/kernel/*`
+    #[serde(default)]
+    pub uname: Option<UnameOptions>,
+
+    /// Key-value state that the host's processes can persist across simulations
+    #[serde(default)]
+    pub persistent_state: Option<PersistentStateOptions>,
+
+    /// Latency and injected failures of the host's name lookups
+    #[serde(default)]
+    pub resolver: Option<ResolverOptions>,
+
+    /// When the host's TCP sockets send delayed ACKs
+    #[serde(default)]
+    pub tcp_delayed_ack: Option<TcpDelayedAckOptions>,
+
+    /// Range of ports that the host's sockets choose ephemeral ports from
+    #[serde(default)]
+    pub ip_local_port_range: Option<IpLocalPortRangeOptions>,
+
+    /// How long the host's TCP connections stay in the TIME_WAIT state, and whether new
+    /// connections can reuse their ports
+    #[serde(default)]
+    pub tcp_time_wait: Option<TcpTimeWaitOptions>,
+
+    /// Resolve the hardware address of each packet's next hop with ARP before sending it
+    #[serde(default)]
+    pub arp: Option<ArpOptions>,
+
+    /// Layer 4 load balancer that forwards connections to the host's port to backend hosts
+    #[serde(default)]
+    pub load_balancer: Option<LoadBalancerOptions>,
+
+    /// Rules that filter the packets sent and received by the host
+    #[serde(default)]
+    pub firewall: Option<FirewallOptions>,
+
+    /// Rules that limit the bandwidth of the packets sent and received by the host that match a
+    /// rule, optionally only during a period of the simulation
+    #[serde(default)]
+    pub throttles: Vec<ThrottleOptions>,
+
+    /// TUN device that the host exchanges its packets with, so that the host represents a system
+    /// outside of the simulation
+    #[serde(default)]
+    pub bridge: Option<BridgeOptions>,
+
+    /// Applications that are compiled into Shadow and run in its process, instead of as managed
+    /// processes
+    #[serde(default)]
+    pub native_apps: Vec<NativeAppOptions>,
+
+    #[serde(default)]
+    pub host_options: HostDefaultOptions,
+
+    /// Name of the instrumentation profile that the host uses, instead of a profile whose `hosts`
+    /// patterns match the host's name
+    #[serde(default)]
+    pub instrumentation_profile: Option<String>,
+
+    /// Tags of the host, which can be used to share options and processes between hosts
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Metadata of the host such as its country, autonomous system, or city, which is added to
+    /// the simulation's outputs
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct InstrumentationProfileOptions {
+    /// Patterns of the names of the hosts that use this profile, where `*` matches any sequence of
+    /// characters and `?` matches any single character, or `tag:<tag>` to match the hosts with a
+    /// tag
+    #[serde(default)]
+    pub hosts: Vec<String>,
+
+    /// Host options used by the hosts, which take precedence over `host_option_defaults` but not
+    /// over the host's own `host_options`
+    #[serde(default)]
+    pub host_options: HostDefaultOptions,
+
+    /// Amount of time between heartbeat messages for the hosts, instead of
+    /// `experimental.host_heartbeat_interval`
+    #[serde(default, deserialize_with = "deserialize_yaml_null_as_null")]
+    pub host_heartbeat_interval: Option<NullableOption<units::Time<units::TimePrefix>>>,
+
+    /// List of information to show in the hosts' heartbeat messages, instead of
+    /// `experimental.host_heartbeat_log_info`
+    #[serde(default)]
+    pub host_heartbeat_log_info: Option<HashSet<LogInfoFlag>>,
+
+    /// Log the syscalls of the hosts' processes, instead of `experimental.strace_logging_mode`
+    #[serde(default)]
+    pub strace_logging_mode: Option<StraceLoggingMode>,
+}
+
+/// Host options and processes shared by all hosts with a given tag.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TagOptions {
+    /// Processes to run on the hosts, which start before the host's own processes
+    #[serde(default)]
+    pub processes: Vec<ProcessOptions>,
+
+    /// Host options used by the hosts, which take precedence over the instrumentation profile's
+    /// and `host_option_defaults`, but not over the host's own `host_options`
+    #[serde(default)]
+    pub host_options: HostDefaultOptions,
+
+    /// Name of the instrumentation profile that the hosts use, unless the host sets its own
+    #[serde(default)]
+    pub instrumentation_profile: Option<String>,
+}
+
+/// A set of hosts given by host name patterns and tags. A `*` in a host name pattern matches any
+/// sequence of characters and a `?` matches any single character, and a pattern `tag:<tag>`
+/// matches the hosts with tag `<tag>`.
+#[derive(Debug)]
+struct HostSelector {
+    names: Option<regex::Regex>,
+    tags: HashSet<String>,
+}
+
+impl HostSelector {
+    fn new(patterns: &[String]) -> Self {
+        let mut names = Vec::new();
+        let mut tags = HashSet::new();
+
+        for pattern in patterns {
+            if let Some(tag) = pattern.strip_prefix("tag:") {
+                tags.insert(tag.to_string());
+                continue;
+            }
+
+            let regex: String = pattern
+                .chars()
+                .map(|c| match c {
+                    '*' => ".*".to_string(),
+                    '?' => ".".to_string(),
+                    c => regex::escape(&c.to_string()),
+                })
+                .collect();
+            names.push(regex);
+        }
+
+        let names = (!names.is_empty())
+            .then(|| regex::Regex::new(&format!("^(?:{})$", names.join("|"))).unwrap());
+
+        Self { names, tags }
+    }
+
+    fn is_match(&self, hostname: &str, tags: &[String]) -> bool {
+        self.names.as_ref().is_some_and(|x| x.is_match(hostname))
+            || tags.iter().any(|x| self.tags.contains(x))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DataTemplateOptions {
+    /// Path to the template directory
+    pub path: std::path::PathBuf,
+
+    /// Whether template files are copied (with variable substitution) or symlinked
+    #[serde(default)]
+    pub mode: DataTemplateMode,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataTemplateMode {
+    #[default]
+    Copy,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayOptions {
+    /// Path to the trace file
+    pub path: std::path::PathBuf,
+
+    /// Format of the trace file
+    #[serde(default)]
+    pub format: ReplayFormat,
+
+    /// Hosts that the replayed packets are sent to
+    pub destinations: Vec<String>,
+
+    /// The simulated time at which to send the first packet of the trace
+    #[serde(default)]
+    pub start_time: units::Time<units::TimePrefix>,
+
+    /// Factor that the time between packets in the trace is multiplied by
+    #[serde(default = "default_replay_time_scale")]
+    pub time_scale: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SeedNodeOptions {
+    /// The UDP port that the service listens on
+    pub port: u16,
+
+    /// The port to include with each peer address in responses
+    pub peer_port: u16,
+
+    /// Hosts to choose peers from, or all other hosts if null
+    #[serde(default)]
+    pub peers: Option<Vec<String>>,
+
+    /// The maximum number of peers in each response
+    #[serde(default = "default_seed_node_max_peers")]
+    pub max_peers: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeOptions {
+    /// The host to probe
+    pub target: String,
+
+    /// The kind of probe
+    #[serde(default)]
+    pub kind: ProbeKind,
+
+    /// Time between probes
+    #[serde(default = "default_probe_interval")]
+    pub interval: units::Time<units::TimePrefix>,
+
+    /// The UDP port that the target host answers probes on
+    #[serde(default = "default_probe_port")]
+    pub port: u16,
+
+    /// Size of the response to each fetch probe
+    #[serde(default = "default_probe_fetch_size")]
+    pub fetch_size: units::Bytes<units::SiPrefixUpper>,
+
+    /// Length of the periods that the probe's results are summarized over
+    #[serde(default = "default_probe_summary_interval")]
+    pub summary_interval: units::Time<units::TimePrefix>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeOptions {
+    /// Name of an existing TUN device
+    pub tun: String,
+
+    /// Time between reads of the packets sent to the device
+    #[serde(default = "default_bridge_poll_interval")]
+    pub poll_interval: units::Time<units::TimePrefix>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NativeAppOptions {
+    /// Name of the app
+    pub name: String,
+
+    /// App arguments
+    #[serde(default = "default_args_empty")]
+    pub args: ProcessArgs,
+
+    /// The simulated time at which to start the app
+    #[serde(default)]
+    pub start_time: units::Time<units::TimePrefix>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProbeKind {
+    /// Measure the round-trip time of a small request
+    #[default]
+    Ping,
+    /// Measure the time to fetch a response of `fetch_size` bytes
+    Fetch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PersistentStateOptions {
+    /// State file to load the initial state from, such as one saved by a previous simulation
+    #[serde(default)]
+    pub path: Option<std::path::PathBuf>,
+
+    /// Initial values, which replace any values of the same keys from the state file
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ResolverOptions {
+    /// The time taken to look up a name
+    #[serde(default)]
+    pub latency: Option<ResolverLatencyOptions>,
+
+    /// The time taken by a lookup that times out
+    #[serde(default = "default_resolver_timeout")]
+    pub timeout: units::Time<units::TimePrefix>,
+
+    /// Names that fail to resolve during a window of time
+    #[serde(default)]
+    pub failures: Vec<ResolverFailureOptions>,
+
+    /// Whether a name's IPv4 or IPv6 addresses are listed first
+    #[serde(default)]
+    pub address_order: ResolverAddressOrder,
+
+    /// The time taken to look up a name's IPv6 addresses, or the same as `latency` if null
+    #[serde(default)]
+    pub ipv6_latency: Option<ResolverLatencyOptions>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum ResolverLatencyOptions {
+    /// Every lookup takes `latency`.
+    Fixed {
+        latency: units::Time<units::TimePrefix>,
+    },
+    /// Each lookup takes a time chosen uniformly at random between `min` and `max`.
+    Uniform {
+        min: units::Time<units::TimePrefix>,
+        max: units::Time<units::TimePrefix>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ResolverFailureOptions {
+    /// The name that fails to resolve
+    pub name: String,
+
+    /// How the lookup fails
+    pub error: ResolverError,
+
+    /// The simulated time at which lookups of the name start to fail
+    #[serde(default)]
+    pub start: units::Time<units::TimePrefix>,
+
+    /// The simulated time at which lookups of the name stop failing, or never if null
+    #[serde(default)]
+    pub end: Option<units::Time<units::TimePrefix>>,
+
+    /// The address family whose lookups fail, or both if null
+    #[serde(default)]
+    pub family: Option<ResolverFamily>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResolverError {
+    /// The name doesn't exist.
+    Nxdomain,
+    /// The lookup times out.
+    Timeout,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResolverFamily {
+    /// IPv4 addresses (A records)
+    Ipv4,
+    /// IPv6 addresses (AAAA records)
+    Ipv6,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResolverAddressOrder {
+    /// IPv4 addresses are listed before IPv6 addresses.
+    #[default]
+    Ipv4First,
+    /// IPv6 addresses are listed before IPv4 addresses.
+    Ipv6First,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TcpDelayedAckOptions {
+    /// The time that an ACK is delayed, once the connection's quick ACKs have been sent
+    #[serde(default = "default_tcp_delayed_ack_timeout")]
+    pub timeout: units::Time<units::TimePrefix>,
+
+    /// The time that each of the connection's quick ACKs is delayed
+    #[serde(default = "default_tcp_delayed_ack_quick_timeout")]
+    pub quick_timeout: units::Time<units::TimePrefix>,
+
+    /// The number of delayed ACKs at the start of a connection that use `quick_timeout`
+    #[serde(default = "default_tcp_delayed_ack_quick_acks")]
+    pub quick_acks: u32,
+
+    /// The number of received bytes that can be unacknowledged before an ACK is sent without
+    /// delay, or unlimited if null
+    #[serde(default)]
+    pub max_unacked_bytes: Option<units::Bytes<units::SiPrefixUpper>>,
+}
+
+impl Default for TcpDelayedAckOptions {
+    fn default() -> Self {
+        Self {
+            timeout: default_tcp_delayed_ack_timeout(),
+            quick_timeout: default_tcp_delayed_ack_quick_timeout(),
+            quick_acks: default_tcp_delayed_ack_quick_acks(),
+            max_unacked_bytes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IpLocalPortRangeOptions {
+    /// The lowest ephemeral port
+    #[serde(default = "default_ip_local_port_range_min")]
+    pub min: u16,
+
+    /// The highest ephemeral port
+    #[serde(default = "default_ip_local_port_range_max")]
+    pub max: u16,
+}
+
+impl Default for IpLocalPortRangeOptions {
+    fn default() -> Self {
+        Self {
+            min: default_ip_local_port_range_min(),
+            max: default_ip_local_port_range_max(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TcpTimeWaitOptions {
+    /// The time that a connection stays in the TIME_WAIT state
+    #[serde(default = "default_tcp_time_wait_timeout")]
+    pub timeout: units::Time<units::TimePrefix>,
+
+    /// Whether a new connection can take the port of a connection to the same peer that is in the
+    /// TIME_WAIT state, when there are no other ephemeral ports available
+    #[serde(default)]
+    pub reuse: bool,
+}
+
+impl Default for TcpTimeWaitOptions {
+    fn default() -> Self {
+        Self {
+            timeout: default_tcp_time_wait_timeout(),
+            reuse: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ArpOptions {
+    /// The time that a resolved hardware address stays in the host's ARP cache
+    #[serde(default = "default_arp_cache_timeout")]
+    pub cache_timeout: units::Time<units::TimePrefix>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LoadBalancerOptions {
+    /// The TCP or UDP port that clients send packets to
+    pub port: u16,
+
+    /// Hosts that the load balancer forwards connections to
+    pub backends: Vec<String>,
+
+    /// The port of the backends, or the load balancer's `port` if null
+    #[serde(default)]
+    pub backend_port: Option<u16>,
+
+    /// How a backend is chosen for each new connection
+    #[serde(default)]
+    pub algorithm: LoadBalancerAlgorithm,
+
+    /// Forward packets without rewriting their addresses, and let the backends reply to clients
+    /// directly (direct server return)
+    #[serde(default)]
+    pub dsr: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadBalancerAlgorithm {
+    #[default]
+    RoundRobin,
+    LeastConnections,
+    ConsistentHash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallOptions {
+    /// Rules for the packets that the host receives, in the order that they're checked
+    #[serde(default)]
+    pub ingress: Vec<FirewallRuleOptions>,
+
+    /// Rules for the packets that the host sends, in the order that they're checked
+    #[serde(default)]
+    pub egress: Vec<FirewallRuleOptions>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallRuleOptions {
+    /// What happens to packets that match the rule
+    pub action: FirewallAction,
+
+    /// The protocol of matching packets, or any protocol if null
+    #[serde(default)]
+    pub protocol: Option<FirewallProtocol>,
+
+    /// The source address or address prefix (such as "11.0.0.0/8") of matching packets
+    #[serde(default)]
+    pub src: Option<String>,
+
+    /// The destination address or address prefix of matching packets
+    #[serde(default)]
+    pub dst: Option<String>,
+
+    /// The source port of matching packets
+    #[serde(default)]
+    pub src_port: Option<u16>,
+
+    /// The destination port of matching packets
+    #[serde(default)]
+    pub dst_port: Option<u16>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum FirewallAction {
+    Allow,
+    Deny,
+    Reject,
 }
 
-impl std::fmt::Display for ProcessFinalState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // We use the yaml serialization here so that when reporting that an
-        // expected state didn't match the actual state, it's clear how to set
-        // the expected state in the config file to match the actual state if
-        // desired.
-        //
-        // The current enum works OK for this since there are no internal
-        // newlines in the serialization; if there are some later we might wand
-        // to serialize to json instead, which can always be put on a single
-        // line and should also be valid yaml.
-        let s = serde_yaml::to_string(self).or(Err(std::fmt::Error))?;
-        write!(f, "{}", s.trim())
-    }
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum FirewallProtocol {
+    Tcp,
+    Udp,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
-pub struct ProcessOptions {
-    pub path: std::path::PathBuf,
+pub struct ThrottleOptions {
+    /// Bandwidth of the packets that match the rule
+    pub rate: units::BitsPerSec<units::SiPrefixUpper>,
 
-    /// Process arguments
-    #[serde(default = "default_args_empty")]
-    pub args: ProcessArgs,
+    /// Whether the rule limits the packets that the host receives or sends, or both if null
+    #[serde(default)]
+    pub direction: Option<ThrottleDirection>,
 
-    /// Environment variables passed when executing this process. Multiple variables can be
-    /// specified by using a semicolon separator (ex: `ENV_A=1;ENV_B=2`)
+    /// The time that the rule starts limiting packets
     #[serde(default)]
-    pub environment: BTreeMap<EnvName, String>,
+    pub start: Option<units::Time<units::TimePrefix>>,
 
-    /// The simulated time at which to execute the process
+    /// The time that the rule stops limiting packets, or never if null
     #[serde(default)]
-    pub start_time: units::Time<units::TimePrefix>,
+    pub end: Option<units::Time<units::TimePrefix>>,
 
-    /// The simulated time at which to send a `shutdown_signal` signal to the process
+    /// The protocol of matching packets, or any protocol if null
     #[serde(default)]
-    pub shutdown_time: Option<units::Time<units::TimePrefix>>,
+    pub protocol: Option<FirewallProtocol>,
 
-    /// The signal that will be sent to the process at `shutdown_time`
-    #[serde(default = "default_shutdown_signal")]
-    pub shutdown_signal: Signal,
+    /// The source address or address prefix (such as "11.0.0.0/8") of matching packets
+    #[serde(default)]
+    pub src: Option<String>,
 
-    /// The expected final state of the process. Shadow will report an error
-    /// if the actual state doesn't match.
+    /// The destination address or address prefix of matching packets
     #[serde(default)]
-    pub expected_final_state: ProcessFinalState,
+    pub dst: Option<String>,
+
+    /// The source port of matching packets
+    #[serde(default)]
+    pub src_port: Option<u16>,
+
+    /// The destination port of matching packets
+    #[serde(default)]
+    pub dst_port: Option<u16>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThrottleDirection {
+    Ingress,
+    Egress,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
-pub struct HostOptions {
-    /// Network graph node ID to assign the host to
-    pub network_node_id: u32,
+pub struct UnameOptions {
+    /// The operating system name
+    #[serde(default = "default_uname_sysname")]
+    pub sysname: String,
 
-    pub processes: Vec<ProcessOptions>,
+    /// The kernel release
+    #[serde(default = "default_uname_release")]
+    pub release: String,
 
-    /// IP address to assign to the host
-    #[serde(default)]
-    pub ip_addr: Option<std::net::Ipv4Addr>,
+    /// The kernel version
+    #[serde(default = "default_uname_version")]
+    pub version: String,
 
-    /// Downstream bandwidth capacity of the host
-    #[serde(default)]
-    pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+    /// The hardware identifier
+    #[serde(default = "default_uname_machine")]
+    pub machine: String,
 
-    /// Upstream bandwidth capacity of the host
+    /// The NIS domain name
+    #[serde(default = "default_uname_domainname")]
+    pub domainname: String,
+}
+
+impl Default for UnameOptions {
+    fn default() -> Self {
+        Self {
+            sysname: default_uname_sysname(),
+            release: default_uname_release(),
+            version: default_uname_version(),
+            machine: default_uname_machine(),
+            domainname: default_uname_domainname(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TorHostOptions {
+    /// The nickname of the relay in the consensus
     #[serde(default)]
-    pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+    pub nickname: Option<String>,
 
+    /// Path to a torrc template to write to the host's data directory
     #[serde(default)]
-    pub host_options: HostDefaultOptions,
+    pub torrc_template: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplayFormat {
+    #[default]
+    Pcap,
+    Flows,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, JsonSchema)]
@@ -956,6 +2299,142 @@ impl FromStr for QDiscMode {
     }
 }
 
+/// The queueing discipline used by a host's upstream router.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RouterQDiscMode {
+    /// Drop packets that wait in the queue for too long, using the CoDel active queue management
+    /// algorithm.
+    Codel,
+    /// Never drop packets.
+    Fifo,
+}
+
+impl FromStr for RouterQDiscMode {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// How application payloads are written to pcap files.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[repr(C)]
+pub enum PcapPayload {
+    /// Write payloads unchanged.
+    Keep,
+    /// Replace each payload byte with zero.
+    Zero,
+    /// Leave out payloads, capturing only the headers.
+    Truncate,
+}
+
+impl FromStr for PcapPayload {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// What a network interface does when it can't create or write to its pcap file.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PcapErrorAction {
+    /// Stop capturing packets on the interface.
+    Disable,
+    /// Close the file and periodically try to continue the capture in a new file.
+    Rotate,
+    /// Stop the simulation.
+    Abort,
+}
+
+impl FromStr for PcapErrorAction {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// How an output file is compressed.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputCompression {
+    /// Don't compress the file.
+    None,
+    /// Compress the file with gzip. A ".gz" extension is added to the file name.
+    Gzip,
+    /// Compress the file with zstd. A ".zst" extension is added to the file name.
+    Zstd,
+}
+
+impl FromStr for OutputCompression {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// How a host stores the payloads of the packets that it sends.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PacketPayload {
+    /// Store a copy of each payload.
+    Keep,
+    /// Store only the length of each payload. The payload is received as zero bytes.
+    Zero,
+}
+
+impl FromStr for PacketPayload {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// What a host does when its event queue is longer than its maximum length.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventQueueLimitAction {
+    /// Log the most common sources of events and stop the simulation.
+    Abort,
+    /// Log the most common sources of events and pause the host's processes until the queue has
+    /// drained to half of its maximum length.
+    Backpressure,
+}
+
+impl FromStr for EventQueueLimitAction {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// What Shadow does when its memory usage exceeds the memory budget.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum MemoryBudgetAction {
+    /// Log a warning and continue the simulation.
+    Warn,
+    /// Stop the simulation at the end of the current scheduling round, shut down the hosts and
+    /// write the results collected so far, and exit with an error.
+    Stop,
+}
+
+impl FromStr for MemoryBudgetAction {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum Compression {
@@ -986,6 +2465,43 @@ pub enum GraphOptions {
     Gml(GraphSource),
     #[serde(rename = "1_gbit_switch")]
     OneGbitSwitch,
+    Generated(GeneratedGraphOptions),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratedGraphOptions {
+    /// The model used to generate the graph
+    pub model: GraphModel,
+    /// The latency of each link
+    pub latency: units::Time<units::TimePrefix>,
+    /// The upstream and downstream bandwidth of hosts attached to each node
+    pub bandwidth: units::BitsPerSec<units::SiPrefixUpper>,
+    /// The packet loss of each link
+    #[serde(default)]
+    pub packet_loss: f32,
+    /// Seed for generating the graph. If not set, `general.seed` is used.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum GraphModel {
+    /// Each pair of nodes is linked with probability `edge_probability`.
+    ErdosRenyi { nodes: u32, edge_probability: f64 },
+    /// Nodes are added one at a time, each linking to `edges_per_node` existing nodes chosen with
+    /// probability proportional to their degree.
+    BarabasiAlbert { nodes: u32, edges_per_node: u32 },
+    /// A ring where each node is linked to its `neighbors` nearest nodes, and each link is rewired
+    /// to a random node with probability `rewire_probability`.
+    WattsStrogatz {
+        nodes: u32,
+        neighbors: u32,
+        rewire_probability: f64,
+    },
+    /// A `k`-ary fat-tree datacenter network of edge, aggregation, and core switches.
+    FatTree { k: u32 },
 }
 
 #[derive(Debug, Clone, Serialize, JsonSchema)]
@@ -1292,11 +2808,126 @@ impl<T> Flatten<T> for Option<NullableOption<T>> {
     }
 }
 
+/// Helper function for serde fields where a yaml "null" should be `Some(NullableOption::Null)`
+/// rather than `None`, so that a null value can be distinguished from a value that isn't set.
+fn deserialize_yaml_null_as_null<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<NullableOption<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    let value = Option::<T>::deserialize(deserializer)?;
+    Ok(Some(
+        value.map_or(NullableOption::Null, NullableOption::Value),
+    ))
+}
+
 /// Helper function for serde default `ProcessArgs::Str("")` values.
 fn default_args_empty() -> ProcessArgs {
     ProcessArgs::Str("".to_string())
 }
 
+/// Helper function for serde default `max_peers`.
+fn default_seed_node_max_peers() -> u32 {
+    25
+}
+
+/// Helper function for serde default `interval`.
+fn default_probe_interval() -> units::Time<units::TimePrefix> {
+    units::Time::new(1, units::TimePrefix::Sec)
+}
+
+/// Helper function for serde default `poll_interval`.
+fn default_bridge_poll_interval() -> units::Time<units::TimePrefix> {
+    units::Time::new(1, units::TimePrefix::Milli)
+}
+
+/// Helper function for serde default `port`.
+fn default_probe_port() -> u16 {
+    7
+}
+
+/// Helper function for serde default `fetch_size`.
+fn default_probe_fetch_size() -> units::Bytes<units::SiPrefixUpper> {
+    units::Bytes::new(16, units::SiPrefixUpper::Kilo)
+}
+
+/// Helper function for serde default `summary_interval`.
+fn default_probe_summary_interval() -> units::Time<units::TimePrefix> {
+    units::Time::new(60, units::TimePrefix::Sec)
+}
+
+/// Helper function for serde default `time_scale`.
+fn default_replay_time_scale() -> f64 {
+    1.0
+}
+
+/// Helper function for serde default `timeout`.
+fn default_resolver_timeout() -> units::Time<units::TimePrefix> {
+    units::Time::new(5, units::TimePrefix::Sec)
+}
+
+/// Helper function for serde default `timeout`.
+fn default_tcp_delayed_ack_timeout() -> units::Time<units::TimePrefix> {
+    units::Time::new(5, units::TimePrefix::Milli)
+}
+
+/// Helper function for serde default `quick_timeout`.
+fn default_tcp_delayed_ack_quick_timeout() -> units::Time<units::TimePrefix> {
+    units::Time::new(1, units::TimePrefix::Milli)
+}
+
+/// Helper function for serde default `quick_acks`.
+fn default_tcp_delayed_ack_quick_acks() -> u32 {
+    1000
+}
+
+/// Helper function for serde default `min`.
+fn default_ip_local_port_range_min() -> u16 {
+    10000
+}
+
+/// Helper function for serde default `max`.
+fn default_ip_local_port_range_max() -> u16 {
+    u16::MAX
+}
+
+/// Helper function for serde default `timeout`.
+fn default_tcp_time_wait_timeout() -> units::Time<units::TimePrefix> {
+    units::Time::new(60, units::TimePrefix::Sec)
+}
+
+/// Helper function for serde default `cache_timeout`.
+fn default_arp_cache_timeout() -> units::Time<units::TimePrefix> {
+    units::Time::new(30, units::TimePrefix::Sec)
+}
+
+/// Helper function for serde default `sysname`.
+fn default_uname_sysname() -> String {
+    "shadowsys".to_string()
+}
+
+/// Helper function for serde default `release`.
+fn default_uname_release() -> String {
+    "shadowrelease".to_string()
+}
+
+/// Helper function for serde default `version`.
+fn default_uname_version() -> String {
+    "shadowversion".to_string()
+}
+
+/// Helper function for serde default `machine`.
+fn default_uname_machine() -> String {
+    "shadowmachine".to_string()
+}
+
+/// Helper function for serde default `domainname`.
+fn default_uname_domainname() -> String {
+    "(none)".to_string()
+}
+
 /// Helper function for serde default `shutdown_signal`.
 fn default_shutdown_signal() -> Signal {
     Signal(nix::sys::signal::Signal::SIGTERM)
@@ -1338,6 +2969,11 @@ fn default_some_info() -> Option<LogLevel> {
     Some(LogLevel::Info)
 }
 
+/// Helper function for serde default `Some(OutputCompression::None)` values.
+fn default_some_output_compression_none() -> Option<OutputCompression> {
+    Some(OutputCompression::None)
+}
+
 // when updating this graph, make sure to also update the copy in docs/shadow_config_spec.md
 pub const ONE_GBIT_SWITCH_GRAPH: &str = r#"graph [
   directed 0
@@ -1596,6 +3232,194 @@ mod tests {
             Some(NullableOption::Null)
         );
     }
+
+    #[test]
+    // can't call foreign function: process_parseArgStr
+    #[cfg_attr(miri, ignore)]
+    fn test_instrumentation_profiles() {
+        // format the yaml with hosts given in the yaml flow style
+        let yaml_fmt_fn = |hosts: &[&str]| {
+            format!(
+                r#"
+                general:
+                  stop_time: 1 min
+                network:
+                  graph:
+                    type: 1_gbit_switch
+                instrumentation_profiles:
+                  full-capture:
+                    hosts: ["server", "relay?"]
+                    host_options:
+                      pcap_enabled: true
+                      log_level: debug
+                    strace_logging_mode: standard
+                  silent:
+                    hosts: ["client*"]
+                    host_options:
+                      log_level: error
+                    host_heartbeat_interval: null
+                hosts: {{{}}}
+                "#,
+                hosts.join(", ")
+            )
+        };
+
+        let yaml = yaml_fmt_fn(&[
+            "server: {network_node_id: 0, processes: []}",
+            "relay1: {network_node_id: 0, processes: [], host_options: {log_level: info}}",
+            "client1: {network_node_id: 0, processes: []}",
+            "client2: {network_node_id: 0, processes: [], instrumentation_profile: full-capture}",
+            "other: {network_node_id: 0, processes: []}",
+        ]);
+        let mut config_file: ConfigFileOptions = serde_yaml::from_str(&yaml).unwrap();
+        config_file.apply_instrumentation_profiles().unwrap();
+        let cli: CliOptions = CliOptions::try_parse_from(["shadow", "-"]).unwrap();
+        let config = ConfigOptions::new(config_file, cli);
+
+        let host = |name: &str| {
+            config
+                .hosts
+                .iter()
+                .find(|(x, _)| x.as_str() == name)
+                .unwrap()
+                .1
+        };
+        let profile = |name: &str| host(name).instrumentation_profile.as_deref();
+        let log_level = |name: &str| host(name).host_options.log_level.flatten();
+
+        assert_eq!(profile("server"), Some("full-capture"));
+        assert_eq!(profile("relay1"), Some("full-capture"));
+        assert_eq!(profile("client1"), Some("silent"));
+        assert_eq!(profile("client2"), Some("full-capture"));
+        assert_eq!(profile("other"), None);
+
+        // the host's own options take precedence over the profile's
+        assert!(matches!(log_level("server"), Some(LogLevel::Debug)));
+        assert!(matches!(log_level("relay1"), Some(LogLevel::Info)));
+        assert!(matches!(log_level("client1"), Some(LogLevel::Error)));
+        assert!(log_level("other").is_none());
+
+        assert!(host("server").host_options.pcap_enabled.unwrap());
+        assert!(!host("client1").host_options.pcap_enabled.unwrap());
+
+        assert_eq!(
+            config.strace_logging_mode(host("relay1")),
+            Some(FmtOptions::Standard)
+        );
+        assert_eq!(config.strace_logging_mode(host("other")), None);
+        assert_eq!(
+            config
+                .instrumentation_profile(host("client1"))
+                .unwrap()
+                .host_heartbeat_interval,
+            Some(NullableOption::Null)
+        );
+
+        // a host can't match more than one profile
+        let yaml = yaml_fmt_fn(&["client-server: {network_node_id: 0, processes: []}"]);
+        let yaml = yaml.replace("\"server\"", "\"*server\"");
+        let mut config_file: ConfigFileOptions = serde_yaml::from_str(&yaml).unwrap();
+        assert!(config_file.apply_instrumentation_profiles().is_err());
+
+        // the profile must exist
+        let yaml = yaml_fmt_fn(&[
+            "server: {network_node_id: 0, processes: [], instrumentation_profile: verbose}",
+        ]);
+        let mut config_file: ConfigFileOptions = serde_yaml::from_str(&yaml).unwrap();
+        assert!(config_file.apply_instrumentation_profiles().is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        // format the yaml with hosts given in the yaml flow style
+        let yaml_fmt_fn = |hosts: &[&str]| {
+            format!(
+                r#"
+                general:
+                  stop_time: 1 min
+                network:
+                  graph:
+                    type: 1_gbit_switch
+                tags:
+                  relay:
+                    host_options:
+                      pcap_enabled: true
+                      log_level: info
+                    processes:
+                    - path: /bin/relay
+                  exit:
+                    host_options:
+                      log_level: debug
+                    processes:
+                    - path: /bin/exit
+                    instrumentation_profile: stats-only
+                  guard:
+                    instrumentation_profile: full-capture
+                instrumentation_profiles:
+                  stats-only:
+                    hosts: ["tag:monitored"]
+                  full-capture: {{}}
+                hosts: {{{}}}
+                "#,
+                hosts.join(", ")
+            )
+        };
+
+        let yaml = yaml_fmt_fn(&[
+            "relay1: {network_node_id: 0, tags: [relay, exit], processes: [{path: /bin/true}]}",
+            "relay2: {network_node_id: 0, tags: [exit, relay], host_options: {pcap_enabled: false}}",
+            "client: {network_node_id: 0, tags: [monitored]}",
+        ]);
+        let mut config_file: ConfigFileOptions = serde_yaml::from_str(&yaml).unwrap();
+        config_file.apply_tags().unwrap();
+        config_file.apply_instrumentation_profiles().unwrap();
+
+        let host = |name: &str| {
+            config_file
+                .hosts
+                .iter()
+                .find(|(x, _)| x.as_str() == name)
+                .unwrap()
+                .1
+        };
+        let paths = |name: &str| -> Vec<_> {
+            host(name)
+                .processes
+                .iter()
+                .map(|x| x.path.to_str().unwrap())
+                .collect()
+        };
+
+        // the tags' processes come first, in the order of the host's tags
+        assert_eq!(paths("relay1"), ["/bin/relay", "/bin/exit", "/bin/true"]);
+        assert_eq!(paths("relay2"), ["/bin/exit", "/bin/relay"]);
+        assert!(paths("client").is_empty());
+
+        // earlier tags take precedence, and the host's own options take precedence over the tags'
+        let relay1 = &host("relay1").host_options;
+        let relay2 = &host("relay2").host_options;
+        assert!(matches!(relay1.log_level.flatten(), Some(LogLevel::Info)));
+        assert!(matches!(relay2.log_level.flatten(), Some(LogLevel::Debug)));
+        assert_eq!(relay1.pcap_enabled, Some(true));
+        assert_eq!(relay2.pcap_enabled, Some(false));
+
+        // tags can set a profile, or be used to select hosts in a profile
+        let profile = |name: &str| host(name).instrumentation_profile.as_deref();
+        assert_eq!(profile("relay1"), Some("stats-only"));
+        assert_eq!(profile("client"), Some("stats-only"));
+
+        // a host can't have tags with different profiles
+        let yaml = yaml_fmt_fn(&["relay1: {network_node_id: 0, tags: [exit, guard]}"]);
+        let mut config_file: ConfigFileOptions = serde_yaml::from_str(&yaml).unwrap();
+        assert!(config_file.apply_tags().is_err());
+
+        // unless the host sets its own profile
+        let yaml = yaml_fmt_fn(&[
+            "relay1: {network_node_id: 0, tags: [exit, guard], instrumentation_profile: full-capture}",
+        ]);
+        let mut config_file: ConfigFileOptions = serde_yaml::from_str(&yaml).unwrap();
+        config_file.apply_tags().unwrap();
+    }
 }
 
 mod export {
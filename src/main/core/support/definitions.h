@@ -106,6 +106,11 @@ typedef struct Host Host;
  */
 #define CONFIG_HEADER_SIZE_TCP 20
 
+/**
+ * ICMP echo header size in bytes.
+ */
+#define CONFIG_HEADER_SIZE_ICMP 8
+
 /**
  * Header size in bytes of a routable packet with UDP encapsulation; includes
  * the IP and UDP headers but excludes the ethernet header and packet payload.
@@ -118,6 +123,12 @@ typedef struct Host Host;
  */
 #define CONFIG_HEADER_SIZE_TCPIP (CONFIG_HEADER_SIZE_TCP + CONFIG_HEADER_SIZE_IP)
 
+/**
+ * Header size in bytes of a routable ICMP echo packet; includes the IP and ICMP headers but
+ * excludes the ethernet header and packet payload.
+ */
+#define CONFIG_HEADER_SIZE_ICMPIP (CONFIG_HEADER_SIZE_ICMP + CONFIG_HEADER_SIZE_IP)
+
 /**
  * Maximum size of an IP packet without fragmenting over Ethernetv2
  */
@@ -128,14 +139,30 @@ typedef struct Host Host;
  */
 #define CONFIG_TCP_MAX_SEGMENT_SIZE (CONFIG_MTU - CONFIG_HEADER_SIZE_TCPIP)
 
+/**
+ * Size of the TCP timestamps option (RFC 7323), including the two NOP options that align it
+ */
+#define CONFIG_HEADER_SIZE_TCP_TIMESTAMPS 12
+
+/**
+ * Maximum shift of the TCP window scale option (RFC 7323)
+ */
+#define CONFIG_TCP_MAX_WINDOW_SCALE 14
+
 /**
  * Maximum size of a datagram we are allowed to send out over the network
  */
 #define CONFIG_DATAGRAM_MAX_SIZE 65507
 
 /**
- * Delay in nanoseconds for a TCP close timer.
+ * Maximum time in nanoseconds that TCP_CORK holds back a partial segment, from tcp(7).
+ */
+#define CONFIG_TCP_CORK_MAX_DELAY (200 * SIMTIME_ONE_MILLISECOND)
+
+/**
+ * How long a listening TCP socket accepts the ACKs of SYN cookies after it last sent one. Linux
+ * accepts cookies for up to 2 minutes (TCP_SYNCOOKIE_VALID).
  */
-#define CONFIG_TCPCLOSETIMER_DELAY (60 * SIMTIME_ONE_SECOND)
+#define CONFIG_TCP_SYNCOOKIE_LIFETIME (120 * SIMTIME_ONE_SECOND)
 
 #endif /* SHD_DEFINITIONS_H_ */
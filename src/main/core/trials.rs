@@ -0,0 +1,316 @@
+//! Support for running the same simulation several times with different seeds.
+//!
+//! Each trial is run as a separate shadow process since much of shadow's state (the worker and
+//! simulation statistics globals, the shared memory allocator, the logger, etc) is process-wide
+//! and can only be initialized once.
+
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use anyhow::Context;
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::Serialize;
+
+use crate::core::support::configuration::ConfigOptions;
+
+/// Command line options that are either handled by the trial runner itself, or that the trial
+/// runner overrides for each trial. The second tuple value is the option's short name, if any.
+const RUNNER_OPTIONS: &[(&str, Option<char>)] = &[
+    ("--trials", None),
+    ("--trial-jobs", None),
+    ("--seed", None),
+    ("--data-directory", Some('d')),
+];
+
+/// The name of the summary file written to the top-level data directory.
+const SUMMARY_FILENAME: &str = "trials-summary.json";
+
+#[derive(Debug, Clone, Serialize)]
+struct TrialResult {
+    trial: u32,
+    seed: u32,
+    data_directory: PathBuf,
+    log_file: PathBuf,
+    /// The exit code of the trial's shadow process, or `None` if it was killed by a signal.
+    exit_code: Option<i32>,
+    success: bool,
+    wall_time_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct TrialsSummary {
+    base_seed: u32,
+    num_trials: u32,
+    num_succeeded: u32,
+    num_failed: u32,
+    trials: Vec<TrialResult>,
+}
+
+/// Run `num_trials` copies of the simulation described by `args`, with at most `jobs` copies
+/// running at the same time. Each trial gets its own seed (derived from the configured seed) and
+/// its own data directory within the configured data directory. An aggregate summary is written
+/// to the configured data directory once all trials have finished.
+pub fn run_trials(
+    config: &ConfigOptions,
+    args: &[&OsStr],
+    num_trials: u32,
+    jobs: u32,
+) -> anyhow::Result<()> {
+    let base_seed = config.general.seed.unwrap();
+    let data_path = PathBuf::from(config.general.data_directory.as_ref().unwrap());
+
+    let shadow_bin = std::env::current_exe().context("Failed to get the path to shadow")?;
+
+    // the first argument is the program name
+    let child_args = strip_runner_args(args.get(1..).unwrap_or_default());
+
+    std::fs::create_dir(&data_path)
+        .with_context(|| format!("Failed to create data directory '{}'", data_path.display()))?;
+
+    if jobs > 1 && config.experimental.use_cpu_pinning.unwrap() {
+        log::warn!(
+            "Running {jobs} trials in parallel with CPU pinning enabled. The trials may be pinned \
+             to the same CPUs; consider disabling CPU pinning."
+        );
+    }
+
+    let seeds = derive_trial_seeds(base_seed, num_trials);
+    let results: Mutex<Vec<Option<TrialResult>>> = Mutex::new(vec![None; seeds.len()]);
+    let next_trial = AtomicU32::new(0);
+
+    std::thread::scope(|scope| {
+        let mut handles = Vec::new();
+
+        for _ in 0..std::cmp::min(jobs, num_trials) {
+            handles.push(scope.spawn(|| -> anyhow::Result<()> {
+                loop {
+                    let trial = next_trial.fetch_add(1, Ordering::Relaxed);
+                    let Some(seed) = seeds.get(trial as usize).copied() else {
+                        return Ok(());
+                    };
+
+                    let result = run_trial(&shadow_bin, &child_args, &data_path, trial, seed)?;
+                    results.lock().unwrap()[trial as usize] = Some(result);
+                }
+            }));
+        }
+
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        anyhow::Ok(())
+    })?;
+
+    let trials: Vec<_> = results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect();
+    let num_succeeded = trials.iter().filter(|x| x.success).count() as u32;
+
+    let summary = TrialsSummary {
+        base_seed,
+        num_trials,
+        num_succeeded,
+        num_failed: num_trials - num_succeeded,
+        trials,
+    };
+
+    let summary_path = data_path.join(SUMMARY_FILENAME);
+    let summary_file = std::fs::File::create(&summary_path)
+        .with_context(|| format!("Failed to create file '{}'", summary_path.display()))?;
+    serde_json::to_writer_pretty(summary_file, &summary).with_context(|| {
+        format!(
+            "Failed to write trials summary to file '{}'",
+            summary_path.display()
+        )
+    })?;
+
+    log::info!(
+        "Finished {} trials ({} succeeded, {} failed); summary written to '{}'",
+        summary.num_trials,
+        summary.num_succeeded,
+        summary.num_failed,
+        summary_path.display(),
+    );
+
+    if summary.num_failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} trials failed",
+            summary.num_failed,
+            summary.num_trials
+        ));
+    }
+
+    Ok(())
+}
+
+/// Run a single trial to completion, writing its stdout and stderr to a log file.
+fn run_trial(
+    shadow_bin: &Path,
+    args: &[OsString],
+    data_path: &Path,
+    trial: u32,
+    seed: u32,
+) -> anyhow::Result<TrialResult> {
+    let trial_data_path = data_path.join(format!("trial-{trial}"));
+    let log_path = data_path.join(format!("trial-{trial}.shadow.log"));
+
+    let log_file = std::fs::File::create(&log_path)
+        .with_context(|| format!("Failed to create file '{}'", log_path.display()))?;
+    let log_file_stderr = log_file
+        .try_clone()
+        .with_context(|| format!("Failed to clone file '{}'", log_path.display()))?;
+
+    log::info!(
+        "Starting trial {trial} with seed {seed} in '{}'",
+        trial_data_path.display()
+    );
+
+    let start = std::time::Instant::now();
+
+    let status = std::process::Command::new(shadow_bin)
+        .arg("--seed")
+        .arg(seed.to_string())
+        .arg("--data-directory")
+        .arg(&trial_data_path)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(log_file)
+        .stderr(log_file_stderr)
+        .status()
+        .with_context(|| format!("Failed to run trial {trial}"))?;
+
+    let wall_time = start.elapsed();
+
+    if status.success() {
+        log::info!("Trial {trial} finished successfully");
+    } else {
+        log::warn!(
+            "Trial {trial} failed ({status}); see '{}' for details",
+            log_path.display()
+        );
+    }
+
+    Ok(TrialResult {
+        trial,
+        seed,
+        data_directory: trial_data_path,
+        log_file: log_path,
+        exit_code: status.code(),
+        success: status.success(),
+        wall_time_secs: wall_time.as_secs_f64(),
+    })
+}
+
+/// Derive a seed for each trial from the base seed. The same base seed always produces the same
+/// trial seeds.
+fn derive_trial_seeds(base_seed: u32, num_trials: u32) -> Vec<u32> {
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(base_seed.into());
+    (0..num_trials).map(|_| rng.next_u32()).collect()
+}
+
+/// Remove any options that are handled by the trial runner (and their values) from the argument
+/// list.
+fn strip_runner_args(args: &[&OsStr]) -> Vec<OsString> {
+    let mut stripped = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let bytes = arg.as_bytes();
+
+        // everything after "--" is positional
+        if bytes == b"--" {
+            stripped.push(arg.to_os_string());
+            stripped.extend(args.map(|x| x.to_os_string()));
+            break;
+        }
+
+        let mut is_runner_option = false;
+        let mut takes_next_arg = false;
+
+        for (long, short) in RUNNER_OPTIONS {
+            let long = long.as_bytes();
+            if bytes == long {
+                (is_runner_option, takes_next_arg) = (true, true);
+            } else if bytes.starts_with(long) && bytes.get(long.len()) == Some(&b'=') {
+                is_runner_option = true;
+            } else if let Some(short) = short {
+                let short = [b'-', *short as u8];
+                if bytes == short {
+                    (is_runner_option, takes_next_arg) = (true, true);
+                } else if bytes.starts_with(&short) {
+                    is_runner_option = true;
+                }
+            }
+
+            if is_runner_option {
+                break;
+            }
+        }
+
+        if !is_runner_option {
+            stripped.push(arg.to_os_string());
+        } else if takes_next_arg {
+            // skip the option's value
+            args.next();
+        }
+    }
+
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(args: &[&str]) -> Vec<String> {
+        let args: Vec<&OsStr> = args.iter().map(OsStr::new).collect();
+        strip_runner_args(&args)
+            .into_iter()
+            .map(|x| x.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_derive_trial_seeds() {
+        let seeds = derive_trial_seeds(1, 10);
+        assert_eq!(seeds.len(), 10);
+        assert_eq!(seeds, derive_trial_seeds(1, 10));
+        assert_eq!(seeds[..5], derive_trial_seeds(1, 5));
+        assert_ne!(seeds, derive_trial_seeds(2, 10));
+    }
+
+    #[test]
+    fn test_strip_runner_args() {
+        assert_eq!(
+            strip(&["--trials", "5", "--progress", "true", "shadow.yaml"]),
+            ["--progress", "true", "shadow.yaml"]
+        );
+        assert_eq!(
+            strip(&["--trials=5", "--trial-jobs=2", "--seed=3", "shadow.yaml"]),
+            ["shadow.yaml"]
+        );
+        assert_eq!(
+            strip(&[
+                "-d",
+                "foo",
+                "-dbar",
+                "--data-directory",
+                "baz",
+                "shadow.yaml"
+            ]),
+            ["shadow.yaml"]
+        );
+        assert_eq!(
+            strip(&["--seedling", "shadow.yaml", "--", "--seed"]),
+            ["--seedling", "shadow.yaml", "--", "--seed"]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::HostId;
 
-use super::task::TaskRef;
+use super::task::{TaskRef, TaskSource};
 use crate::host::host::Host;
 use crate::network::packet::PacketRc;
 use crate::utility::{Magic, ObjectCounter};
@@ -59,6 +59,33 @@ impl Event {
         self.magic.debug_check();
         self.data
     }
+
+    /// What created the event.
+    pub fn source(&self) -> EventSource {
+        self.magic.debug_check();
+        match &self.data {
+            EventData::Packet(data) => EventSource::Packet(data.src_host_id),
+            EventData::Local(data) => EventSource::Task(data.task.source()),
+        }
+    }
+}
+
+/// What created an event. Used to describe what is filling a host's event queue.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EventSource {
+    /// A packet sent by the given host.
+    Packet(HostId),
+    /// A task created locally.
+    Task(TaskSource),
+}
+
+impl std::fmt::Display for EventSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Packet(host_id) => write!(f, "packets from host {}", u32::from(*host_id)),
+            Self::Task(source) => write!(f, "task created at {source}"),
+        }
+    }
 }
 
 impl PartialEq for Event {
@@ -1,9 +1,10 @@
 use std::cmp::Reverse;
 use std::collections::binary_heap::BinaryHeap;
+use std::collections::HashMap;
 
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 
-use super::event::Event;
+use super::event::{Event, EventSource};
 
 /// A queue of [`Event`]s ordered by their times.
 #[derive(Debug)]
@@ -46,6 +47,31 @@ impl EventQueue {
     pub fn next_event_time(&self) -> Option<EmulatedTime> {
         self.queue.peek().map(|x| x.0.time())
     }
+
+    /// The number of [`Event`]s in the queue.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if the queue has no [`Event`]s.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// The `n` most common sources of [`Event`]s in the queue and how many events each has, from
+    /// most to least common.
+    pub fn top_sources(&self, n: usize) -> Vec<(EventSource, usize)> {
+        let mut counts = HashMap::<EventSource, usize>::new();
+        for event in &self.queue {
+            *counts.entry(event.0.source()).or_default() += 1;
+        }
+
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        // sort by the count, then by the source's description so that the order is deterministic
+        counts.sort_by_cached_key(|(source, count)| (Reverse(*count), source.to_string()));
+        counts.truncate(n);
+        counts
+    }
 }
 
 impl Default for EventQueue {
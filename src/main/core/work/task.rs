@@ -1,3 +1,4 @@
+use std::panic::Location;
 use std::sync::Arc;
 
 use crate::{
@@ -13,17 +14,25 @@ pub struct TaskRef {
     magic: Magic<Self>,
     _counter: ObjectCounter,
     inner: Arc<dyn Fn(&Host) + Send + Sync>,
+    source: TaskSource,
 }
 
 impl TaskRef {
+    #[track_caller]
     pub fn new<T: 'static + Fn(&Host) + Send + Sync>(f: T) -> Self {
         Self {
             inner: Arc::new(f),
             magic: Magic::new(),
             _counter: ObjectCounter::new("TaskRef"),
+            source: TaskSource::Rust(Location::caller()),
         }
     }
 
+    /// Where the task was created.
+    pub fn source(&self) -> TaskSource {
+        self.source
+    }
+
     /// Executes the task.
     ///
     /// If the task was created from C, will panic if the task's host lock isn't held.
@@ -36,6 +45,35 @@ impl TaskRef {
 impl IsSend for TaskRef {}
 impl IsSync for TaskRef {}
 
+/// Where a task was created. Used to describe what is filling a host's event queue.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TaskSource {
+    /// A task created in Rust code at the given location.
+    Rust(&'static Location<'static>),
+    /// A task created in C code with the callback function at the given address.
+    C(usize),
+}
+
+impl std::fmt::Display for TaskSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rust(location) => write!(f, "{location}"),
+            Self::C(callback) => {
+                let mut name = None;
+                backtrace::resolve(*callback as *mut std::ffi::c_void, |symbol| {
+                    if name.is_none() {
+                        name = symbol.name().map(|x| x.to_string());
+                    }
+                });
+                match name {
+                    Some(name) => write!(f, "{name}"),
+                    None => write!(f, "{callback:#x}"),
+                }
+            }
+        }
+    }
+}
+
 impl std::fmt::Debug for TaskRef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.magic.debug_check();
@@ -213,7 +251,8 @@ pub mod export {
                 argument_free,
             )
         };
-        let task = TaskRef::new(move |host: &Host| objs.execute(host));
+        let mut task = TaskRef::new(move |host: &Host| objs.execute(host));
+        task.source = TaskSource::C(callback as usize);
         // It'd be nice if we could use Arc::into_raw here, avoiding a level of
         // pointer indirection. Unfortunately that doesn't work because of the
         // internal dynamic Trait object, making the resulting pointer non-ABI
@@ -252,7 +291,8 @@ pub mod export {
                 argument_free,
             )
         };
-        let task = TaskRef::new(move |host: &Host| objs.execute(host));
+        let mut task = TaskRef::new(move |host: &Host| objs.execute(host));
+        task.source = TaskSource::C(callback as usize);
         // It'd be nice if we could use Arc::into_raw here, avoiding a level of
         // pointer indirection. Unfortunately that doesn't work because of the
         // internal dynamic Trait object, making the resulting pointer non-ABI
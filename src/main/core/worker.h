@@ -19,4 +19,12 @@
 // same name, otherwise we print a warning that a memory leak was detected.
 #define worker_count_deallocation(type) worker_increment_object_dealloc_counter(#type)
 
+// Allocate a zeroed object of the given type, reusing a freed object of the same size from the
+// worker's pool if possible. The object must be freed with worker_free_pooled.
+#define worker_new0_pooled(type) ((type*)worker_alloc_pooled_object(sizeof(type)))
+
+// Free an object allocated with worker_new0_pooled, keeping it in the worker's pool for reuse.
+// Objects can be freed on a different worker than the one that allocated them.
+#define worker_free_pooled(type, ptr) worker_free_pooled_object((ptr), sizeof(type))
+
 #endif /* SHD_WORKER_H_ */
@@ -1,5 +1,8 @@
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::ffi::CStr;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
 use std::sync::atomic::{AtomicBool, AtomicU32};
 use std::sync::{Arc, Mutex};
 
@@ -15,23 +18,42 @@ use shadow_shim_helper_rs::util::SyncSendPointer;
 use shadow_shim_helper_rs::HostId;
 
 use super::work::event_queue::EventQueue;
+use crate::core::breakpoints::Breakpoints;
 use crate::core::controller::ShadowStatusBarState;
+use crate::core::event_trace::EventTrace;
+use crate::core::group_stats::GroupStats;
+use crate::core::hooks::{Hooks, SimulationHooks};
+use crate::core::invariants::Invariants;
 use crate::core::scheduler::runahead::Runahead;
 use crate::core::sim_config::Bandwidth;
 use crate::core::sim_stats::{LocalSimStats, SharedSimStats};
+use crate::core::support::configuration::CensorAction;
 use crate::core::work::event::Event;
 use crate::cshadow;
 use crate::host::host::Host;
+use crate::host::network::arp::ArpNeighbor;
+use crate::host::network::firewall;
+use crate::host::probe::ProbeResults;
 use crate::host::process::{Process, ProcessId};
 use crate::host::thread::{Thread, ThreadId};
+use crate::network::graph::censors::{self, CensoredPacket, Censors, Verdict as CensorVerdict};
+use crate::network::graph::export::FlowVolumes;
+use crate::network::graph::observers::{Observers, Record as ObserverRecord};
 use crate::network::graph::{IpAssignment, RoutingInfo};
 use crate::network::packet::PacketRc;
 use crate::utility::childpid_watcher::ChildPidWatcher;
 use crate::utility::counter::Counter;
+use crate::utility::object_pool::ObjectPool;
 use crate::utility::status_bar;
 
 static USE_OBJECT_COUNTERS: AtomicBool = AtomicBool::new(false);
 
+/// The maximum number of freed objects of each size that a worker keeps for reuse.
+const OBJECT_POOL_MAX_BLOCKS: usize = 4096;
+
+/// The name of the host random stream used to sample packet duplication and reordering.
+const PACKET_FAULTS_RANDOM_STREAM: &str = "packet-faults";
+
 // global counters to be used when there is no worker active
 static SIM_STATS: Lazy<SharedSimStats> = Lazy::new(SharedSimStats::new);
 
@@ -80,6 +102,9 @@ pub struct Worker {
     sim_stats: LocalSimStats,
 
     next_event_time: Cell<Option<EmulatedTime>>,
+
+    // Freed C objects (such as packets) that can be reused for new objects.
+    object_pool: RefCell<ObjectPool>,
 }
 
 impl Worker {
@@ -99,6 +124,7 @@ impl Worker {
                 min_latency_cache: Cell::new(None),
                 sim_stats: LocalSimStats::new(),
                 next_event_time: Cell::new(None),
+                object_pool: RefCell::new(ObjectPool::new(OBJECT_POOL_MAX_BLOCKS)),
             }));
             assert!(res.is_ok(), "Worker already initialized");
         });
@@ -184,6 +210,26 @@ impl Worker {
         Worker::with(|w| f(w.shared.dns())).unwrap()
     }
 
+    /// Resolve the name of a host to its IP address using the global DNS, or return `None` if
+    /// there's no host with that name.
+    pub fn resolve_name_to_ip(name: &CStr) -> Option<Ipv4Addr> {
+        Worker::with(|w| {
+            let dns = w.shared.dns.ptr();
+            let addr = unsafe { cshadow::dns_resolveNameToAddress(dns, name.as_ptr()) };
+            if addr.is_null() {
+                return None;
+            }
+            let ip = unsafe { cshadow::address_toNetworkIP(addr) };
+            Some(Ipv4Addr::from(u32::from_be(ip)))
+        })
+        .flatten()
+    }
+
+    /// The id of the host that `ip` is assigned to, or `None` if there's no such host.
+    pub fn resolve_ip_to_host_id(ip: Ipv4Addr) -> Option<HostId> {
+        Worker::with(|w| w.shared.resolve_ip_to_host_id(ip)).flatten()
+    }
+
     /// Set the currently-active Host.
     pub fn set_active_host(host: Box<Host>) {
         let old = Worker::with(|w| w.active_host.borrow_mut().replace(host)).unwrap();
@@ -331,24 +377,75 @@ impl Worker {
         assert!(!packet.is_null());
 
         let current_time = Worker::current_time().unwrap();
-        let round_end_time = Worker::round_end_time().unwrap();
 
         let is_completed = current_time >= Worker::with(|w| w.shared.sim_end_time).unwrap();
-        let is_bootstrapping =
-            current_time < Worker::with(|w| w.shared.bootstrap_end_time).unwrap();
 
         if is_completed {
             // the simulation is over, don't bother
             return;
         }
 
+        // a backend of a load balancer in DSR mode replies to clients from the load balancer's
+        // address, so a copy of the packet is sent with that source address (the socket may still
+        // need the original packet, for example to retransmit it)
+        let src = unsafe {
+            std::net::SocketAddrV4::new(
+                u32::from_be(cshadow::packet_getSourceIP(packet)).into(),
+                u16::from_be(cshadow::packet_getSourcePort(packet)),
+            )
+        };
+        if let Some(vip) = src_host.load_balancer_vip(src) {
+            let mut copy = PacketRc::from_raw(unsafe { cshadow::packet_copy(packet) });
+            let dst = copy.dst_address();
+            copy.set_addresses(vip, dst);
+            let cpacket = copy.into_inner();
+            unsafe { Self::send_packet(src_host, cpacket) };
+            unsafe { cshadow::packet_unref(cpacket) };
+            return;
+        }
+
         let src_ip = unsafe { cshadow::packet_getSourceIP(packet) };
         let dst_ip = unsafe { cshadow::packet_getDestinationIP(packet) };
-        let payload_size = unsafe { cshadow::packet_getPayloadSize(packet) };
 
         let src_ip: std::net::Ipv4Addr = u32::from_be(src_ip).into();
         let dst_ip: std::net::Ipv4Addr = u32::from_be(dst_ip).into();
 
+        // broadcast and multicast packets are flooded to the other hosts attached to the sender's
+        // switch, and go nowhere if the sender isn't attached to a switch
+        if dst_ip.is_broadcast() || dst_ip.is_multicast() {
+            let src_ip = std::net::IpAddr::V4(src_ip);
+            let neighbors = Worker::with(|w| w.shared.broadcast_domain(src_ip).to_vec()).unwrap();
+
+            if neighbors.len() <= 1 {
+                unsafe {
+                    cshadow::packet_addDeliveryStatus(
+                        packet,
+                        cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                    )
+                };
+                return;
+            }
+
+            for neighbor in neighbors.into_iter().filter(|x| *x != src_ip) {
+                let std::net::IpAddr::V4(neighbor_v4) = neighbor else {
+                    unreachable!();
+                };
+                let dst_host_id = Worker::with(|w| w.shared.resolve_ip_to_host_id(neighbor_v4))
+                    .unwrap()
+                    .unwrap();
+                unsafe {
+                    Self::send_packet_to_host(
+                        src_host,
+                        packet,
+                        dst_host_id,
+                        neighbor,
+                        SimulationTime::ZERO,
+                    )
+                };
+            }
+            return;
+        }
+
         let dst_host_id = Worker::with(|w| {
             w.shared
                 .resolve_ip_to_host_id(dst_ip)
@@ -356,9 +453,83 @@ impl Worker {
         })
         .unwrap();
 
-        let src_ip = std::net::IpAddr::V4(src_ip);
+        // the packet's path starts at the sending host, whose address isn't the packet's source
+        // address when a load balancer forwards the packet in DSR mode
+        let src_ip = std::net::IpAddr::V4(src_host.default_ip());
         let dst_ip = std::net::IpAddr::V4(dst_ip);
 
+        // the packet waits for the host to resolve the hardware address of its next hop, which is
+        // the destination host if it's in the same network graph node, or the gateway otherwise
+        let arp_delay = if src_host.params.arp_cache_timeout.is_some() {
+            let (next_hop, round_trip) = Worker::with(|w| {
+                let src_node = w.shared.ip_assignment.get_node(src_ip).unwrap();
+                let dst_node = w.shared.ip_assignment.get_node(dst_ip).unwrap();
+                let next_hop = if src_node == dst_node {
+                    ArpNeighbor::Host(dst_ip)
+                } else {
+                    ArpNeighbor::Gateway
+                };
+                // the request and reply each cross the source node's LAN
+                let lan_latency = w.shared.latency(src_ip, src_ip).unwrap();
+                (next_hop, lan_latency + lan_latency)
+            })
+            .unwrap();
+            src_host.arp_delay(next_hop, round_trip)
+        } else {
+            SimulationTime::ZERO
+        };
+
+        unsafe { Self::send_packet_to_host(src_host, packet, dst_host_id, dst_ip, arp_delay) };
+    }
+
+    /// Send a copy of `packet` to the host `dst_host_id`, whose address is `dst_ip`. The packet is
+    /// delayed by `extra_delay` in addition to the path latency.
+    ///
+    /// # Safety
+    ///
+    /// `packet` must be valid and not accessed by another thread while this function is
+    /// running.
+    unsafe fn send_packet_to_host(
+        src_host: &Host,
+        packet: *mut cshadow::Packet,
+        dst_host_id: HostId,
+        dst_ip: std::net::IpAddr,
+        extra_delay: SimulationTime,
+    ) {
+        let current_time = Worker::current_time().unwrap();
+        let round_end_time = Worker::round_end_time().unwrap();
+
+        let is_bootstrapping =
+            current_time < Worker::with(|w| w.shared.bootstrap_end_time).unwrap();
+
+        let src_ip = std::net::IpAddr::V4(src_host.default_ip());
+        let payload_size = unsafe { cshadow::packet_getPayloadSize(packet) };
+
+        Worker::with_invariants(|invariants| {
+            invariants.packet_sent(src_host.id(), dst_host_id, current_time)
+        });
+
+        // an on-path censor may block the packet
+        let censor_verdict = Worker::with(|w| {
+            // the packet reference is temporary and owned by the caller
+            let packet = std::mem::ManuallyDrop::new(PacketRc::from_raw(packet));
+            w.shared
+                .censor_packet(src_ip, dst_ip, &packet, current_time, round_end_time)
+        })
+        .unwrap();
+        if let Some(verdict) = censor_verdict {
+            unsafe {
+                cshadow::packet_addDeliveryStatus(
+                    packet,
+                    cshadow::_PacketDeliveryStatusFlags_PDS_INET_DROPPED,
+                )
+            };
+            if verdict.action == CensorAction::Reset {
+                unsafe { Self::inject_resets(src_host, packet, verdict.offset) };
+            }
+            return;
+        }
+
         // check if network reliability forces us to 'drop' the packet
         let reliability: f64 = Worker::with(|w| w.shared.reliability(src_ip, dst_ip).unwrap())
             .unwrap()
@@ -381,8 +552,56 @@ impl Worker {
 
         let delay = Worker::with(|w| w.shared.latency(src_ip, dst_ip).unwrap()).unwrap();
 
+        // the packet faults are sampled from their own random stream so that configuring them
+        // doesn't change any other random values used by the host
+        let (is_duplicated, is_reordered) = if is_bootstrapping {
+            (false, false)
+        } else {
+            let (duplication, reordering) =
+                Worker::with(|w| w.shared.packet_faults(src_ip, dst_ip).unwrap()).unwrap();
+            let mut random = src_host.random_stream_mut(PACKET_FAULTS_RANDOM_STREAM);
+            (
+                duplication > 0.0 && random.gen_bool(duplication.into()),
+                reordering > 0.0 && random.gen_bool(reordering.into()),
+            )
+        };
+
         Worker::update_lowest_used_latency(delay);
         Worker::with(|w| w.shared.increment_packet_count(src_ip, dst_ip)).unwrap();
+        Worker::with(|w| {
+            w.shared
+                .record_flow(src_ip, dst_ip, current_time, payload_size)
+        })
+        .unwrap();
+        Worker::with(|w| {
+            w.shared
+                .observe_packet(src_ip, dst_ip, current_time, |time_ns| {
+                    let address = |ip: u32, port: u16| {
+                        std::net::SocketAddrV4::new(u32::from_be(ip).into(), u16::from_be(port))
+                    };
+                    unsafe {
+                        ObserverRecord {
+                            time_ns,
+                            protocol: match cshadow::packet_getProtocol(packet) {
+                                cshadow::_ProtocolType_PTCP => "tcp",
+                                cshadow::_ProtocolType_PUDP => "udp",
+                                cshadow::_ProtocolType_PICMP => "icmp",
+                                _ => "other",
+                            },
+                            src: address(
+                                cshadow::packet_getSourceIP(packet),
+                                cshadow::packet_getSourcePort(packet),
+                            ),
+                            dst: address(
+                                cshadow::packet_getDestinationIP(packet),
+                                cshadow::packet_getDestinationPort(packet),
+                            ),
+                            size: cshadow::packet_getTotalSize(packet) as usize,
+                        }
+                    }
+                })
+        })
+        .unwrap();
 
         // TODO: this should change for sending to remote manager (on a different machine); this is
         // the only place where tasks are sent between separate host
@@ -394,11 +613,19 @@ impl Worker {
             )
         };
 
+        // a duplicated packet is delivered a second time along with the original
+        let duplicate =
+            is_duplicated.then(|| PacketRc::from_raw(unsafe { cshadow::packet_copy(packet) }));
+
         // copy the packet
         let packet = PacketRc::from_raw(unsafe { cshadow::packet_copy(packet) });
 
+        // a reordered packet is held back for another path latency, so that the packets sent after
+        // it during that time arrive first
+        let delay = if is_reordered { delay + delay } else { delay };
+
         // delay the packet until the next round
-        let mut deliver_time = current_time + delay;
+        let mut deliver_time = current_time + extra_delay + delay;
         if deliver_time < round_end_time {
             deliver_time = round_end_time;
         }
@@ -409,7 +636,54 @@ impl Worker {
 
         Worker::with(|w| {
             w.shared
-                .push_packet_to_host(packet, dst_host_id, deliver_time, src_host)
+                .push_packet_to_host(packet, dst_host_id, deliver_time, src_host);
+            if let Some(duplicate) = duplicate {
+                w.shared
+                    .push_packet_to_host(duplicate, dst_host_id, deliver_time, src_host);
+            }
+        })
+        .unwrap();
+    }
+
+    /// Inject a TCP RST to each end of the connection of `packet`, which was blocked by a censor
+    /// that is `offset` along the packet's path. Does nothing if `packet` isn't a TCP packet.
+    ///
+    /// # Safety
+    ///
+    /// `packet` must be valid and not accessed by another thread while this function is
+    /// running.
+    unsafe fn inject_resets(src_host: &Host, packet: *mut cshadow::Packet, offset: SimulationTime) {
+        // the packet reference is temporary and owned by the caller
+        let packet = std::mem::ManuallyDrop::new(PacketRc::from_raw(packet));
+        let Some(to_src) = firewall::reset_reply(&packet) else {
+            return;
+        };
+        let header = packet.get_tcp().unwrap();
+
+        // the RST to the destination continues along the packet's path
+        let mut to_dst = PacketRc::new();
+        to_dst.set_tcp(&tcp::TcpHeader {
+            flags: tcp::TcpFlags::RST,
+            ack: 0,
+            window_size: 0,
+            selective_acks: None,
+            window_scale: None,
+            timestamp: None,
+            timestamp_echo: None,
+            ..header
+        });
+        let cpacket = to_dst.into_inner();
+        unsafe { Self::send_packet(src_host, cpacket) };
+        unsafe { cshadow::packet_unref(cpacket) };
+
+        // the RST to the source returns from the censor
+        let current_time = Worker::current_time().unwrap();
+        let round_end_time = Worker::round_end_time().unwrap();
+        let deliver_time = std::cmp::max(current_time + offset + offset, round_end_time);
+        Worker::update_next_event_time(deliver_time);
+        Worker::with(|w| {
+            w.shared
+                .push_packet_to_host(to_src, src_host.id(), deliver_time, src_host)
         })
         .unwrap();
     }
@@ -494,6 +768,90 @@ impl Worker {
     pub fn is_bootstrapping() -> bool {
         Worker::with(|w| w.clock.borrow().now.unwrap() < w.shared.bootstrap_end_time).unwrap()
     }
+
+    /// Run `f` with the simulation's event trace, if event tracing is enabled.
+    pub fn with_event_trace(f: impl FnOnce(&EventTrace)) {
+        Worker::with(|w| {
+            if let Some(event_trace) = &w.shared.event_trace {
+                f(event_trace)
+            }
+        })
+        .unwrap()
+    }
+
+    /// Run `f` with the hosts' aggregate heartbeat statistics, if they're enabled.
+    pub fn with_group_stats(f: impl FnOnce(&GroupStats)) {
+        Worker::with(|w| {
+            if let Some(group_stats) = &w.shared.group_stats {
+                f(group_stats)
+            }
+        })
+        .unwrap()
+    }
+
+    /// Run `f` with the measurements of the hosts' probes, if any hosts have probes.
+    pub fn with_probe_results(f: impl FnOnce(&ProbeResults)) {
+        Worker::with(|w| {
+            if let Some(probe_results) = &w.shared.probe_results {
+                f(probe_results)
+            }
+        })
+        .unwrap()
+    }
+
+    /// Run `f` with the simulation's breakpoints, if any were configured.
+    pub fn with_breakpoints(f: impl FnOnce(&Breakpoints)) {
+        Worker::with(|w| {
+            if let Some(breakpoints) = &w.shared.breakpoints {
+                f(breakpoints)
+            }
+        })
+        .unwrap()
+    }
+
+    /// Run `f` with the simulation's invariants, if any were configured.
+    pub fn with_invariants(f: impl FnOnce(&Invariants)) {
+        Worker::with(|w| {
+            if let Some(invariants) = &w.shared.invariants {
+                f(invariants)
+            }
+        })
+        .unwrap()
+    }
+
+    /// Record that the active host established a connection, if event tracing is enabled or any
+    /// invariants were configured.
+    pub fn trace_connection_established(local: &str, peer: &str) {
+        let host_id = Worker::with_active_host(|host| host.id());
+        let now = Worker::current_time();
+        let (Some(host_id), Some(now)) = (host_id, now) else {
+            return;
+        };
+
+        Worker::with_event_trace(|event_trace| {
+            event_trace.connection_established(host_id, now, local, peer);
+        });
+        Worker::with_invariants(|invariants| {
+            invariants.connection_established(host_id, now);
+        });
+    }
+
+    /// Call `f` for each of the registered simulation hooks with the active host's id and the
+    /// current time. Does nothing if no hooks were registered or there's no active host.
+    pub fn call_hooks(mut f: impl FnMut(&dyn SimulationHooks, HostId, EmulatedTime)) {
+        // this isn't called with a worker in unit tests
+        let _ = Worker::with(|w| {
+            let Some(hooks) = &w.shared.hooks else {
+                return;
+            };
+            let host_id = w.active_host.borrow().as_ref().map(|host| host.id());
+            let now = w.clock.borrow().now;
+            let (Some(host_id), Some(now)) = (host_id, now) else {
+                return;
+            };
+            hooks.call(|x| f(x, host_id, now));
+        });
+    }
 }
 
 #[derive(Debug)]
@@ -501,6 +859,8 @@ pub struct WorkerShared {
     pub ip_assignment: IpAssignment<u32>,
     pub routing_info: RoutingInfo<u32>,
     pub host_bandwidths: HashMap<std::net::IpAddr, Bandwidth>,
+    /// Addresses of the hosts attached to each switch node.
+    pub broadcast_domains: HashMap<u32, Vec<std::net::IpAddr>>,
     pub dns: SyncSendPointer<cshadow::DNS>,
     // allows for easy updating of the status bar's state
     pub status_logger_state: Option<Arc<status_bar::Status<ShadowStatusBarState>>>,
@@ -513,6 +873,24 @@ pub struct WorkerShared {
     pub event_queues: HashMap<HostId, Arc<Mutex<EventQueue>>>,
     pub bootstrap_end_time: EmulatedTime,
     pub sim_end_time: EmulatedTime,
+    /// Traffic between hosts, recorded only if the topology will be exported.
+    pub flow_volumes: Option<FlowVolumes>,
+    /// Passive observers of the traffic between hosts, if any were configured.
+    pub observers: Option<Observers>,
+    /// On-path censors of the traffic between hosts, if any were configured.
+    pub censors: Option<Censors>,
+    /// Significant simulation events, recorded only if event tracing is enabled.
+    pub event_trace: Option<EventTrace>,
+    /// Aggregate heartbeat statistics of the hosts with each tag, recorded only if enabled.
+    pub group_stats: Option<GroupStats>,
+    /// Measurements of the hosts' probes, if any hosts have probes.
+    pub probe_results: Option<ProbeResults>,
+    /// Conditions that pause the simulation, if any were configured.
+    pub breakpoints: Option<Breakpoints>,
+    /// Conditions that must hold throughout the simulation, if any were configured.
+    pub invariants: Option<Invariants>,
+    /// Callbacks for simulation events, if any were registered.
+    pub hooks: Option<Hooks>,
 }
 
 impl WorkerShared {
@@ -544,6 +922,19 @@ impl WorkerShared {
         Some(1.0 - self.routing_info.path(src, dst)?.packet_loss)
     }
 
+    /// The fraction of packets from `src` to `dst` that are duplicated and reordered.
+    pub fn packet_faults(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+    ) -> Option<(f32, f32)> {
+        let src = self.ip_assignment.get_node(src)?;
+        let dst = self.ip_assignment.get_node(dst)?;
+        let path = self.routing_info.path(src, dst)?;
+
+        Some((path.packet_duplication, path.packet_reordering))
+    }
+
     pub fn bandwidth(&self, ip: std::net::IpAddr) -> Option<&Bandwidth> {
         self.host_bandwidths.get(&ip)
     }
@@ -555,6 +946,75 @@ impl WorkerShared {
         self.routing_info.increment_packet_count(src, dst)
     }
 
+    /// Record a packet with `bytes` payload bytes sent from `src` to `dst` at time `time`, if the
+    /// traffic between hosts is being recorded.
+    pub fn record_flow(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+        time: EmulatedTime,
+        bytes: u64,
+    ) {
+        if let Some(flow_volumes) = &self.flow_volumes {
+            flow_volumes.add(src, dst, time - EmulatedTime::SIMULATION_START, bytes);
+        }
+    }
+
+    /// Record a packet sent from `src` to `dst` at time `time` with the observers that its path
+    /// crosses, if any observers were configured. `record` returns the packet's record for a given
+    /// observation time.
+    pub fn observe_packet(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+        time: EmulatedTime,
+        record: impl Fn(u64) -> ObserverRecord,
+    ) {
+        if let Some(observers) = &self.observers {
+            let src = self.ip_assignment.get_node(src).unwrap();
+            let dst = self.ip_assignment.get_node(dst).unwrap();
+            observers.packet_sent(src, dst, time, record);
+        }
+    }
+
+    /// Check a packet sent from `src` to `dst` at time `time` with the censors that its path
+    /// crosses, if any censors were configured. Returns what happens to the packet if a censor
+    /// blocks it.
+    pub fn censor_packet(
+        &self,
+        src: std::net::IpAddr,
+        dst: std::net::IpAddr,
+        packet: &PacketRc,
+        time: EmulatedTime,
+        round_end_time: EmulatedTime,
+    ) -> Option<CensorVerdict> {
+        let censors = self.censors.as_ref()?;
+
+        let protocol = match packet.protocol() {
+            cshadow::_ProtocolType_PTCP => censors::Protocol::Tcp,
+            cshadow::_ProtocolType_PUDP => censors::Protocol::Udp,
+            _ => return None,
+        };
+        let is_reset = packet
+            .get_tcp()
+            .is_some_and(|x| x.flags.contains(tcp::TcpFlags::RST));
+        let censored = CensoredPacket {
+            protocol,
+            src: packet.src_address(),
+            dst: packet.dst_address(),
+            is_reset,
+        };
+        let payload = || {
+            let mut payload = vec![0; packet.payload_size()];
+            packet.get_payload(&mut payload);
+            payload
+        };
+
+        let src = self.ip_assignment.get_node(src).unwrap();
+        let dst = self.ip_assignment.get_node(dst).unwrap();
+        censors.packet_sent(src, dst, censored, payload, time, round_end_time)
+    }
+
     pub fn is_routable(&self, src: std::net::IpAddr, dst: std::net::IpAddr) -> bool {
         if self.ip_assignment.get_node(src).is_none() {
             return false;
@@ -568,6 +1028,16 @@ impl WorkerShared {
         true
     }
 
+    /// The addresses of the hosts that receive the broadcast packets sent by the host at `ip`,
+    /// including itself. This is empty if the host isn't attached to a switch.
+    pub fn broadcast_domain(&self, ip: std::net::IpAddr) -> &[std::net::IpAddr] {
+        let node = self.ip_assignment.get_node(ip).unwrap();
+        self.broadcast_domains
+            .get(&node)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
     pub fn resolve_ip_to_host_id(&self, ip: std::net::Ipv4Addr) -> Option<HostId> {
         let dns = self.dns.ptr();
         let ip = u32::from(ip).to_be();
@@ -726,6 +1196,30 @@ mod export {
         Worker::increment_object_dealloc_counter(s);
     }
 
+    /// Implementation for allocating pooled objects. Do not use this function directly. Use
+    /// worker_new0_pooled instead from the call site.
+    #[no_mangle]
+    pub extern "C-unwind" fn worker_alloc_pooled_object(size: usize) -> *mut libc::c_void {
+        Worker::with(|w| w.object_pool.borrow_mut().alloc_zeroed(size))
+            .unwrap_or_else(|| ObjectPool::calloc(size))
+            .as_ptr()
+    }
+
+    /// Implementation for freeing pooled objects. Do not use this function directly. Use
+    /// worker_free_pooled instead from the call site.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn worker_free_pooled_object(ptr: *mut libc::c_void, size: usize) {
+        let Some(ptr) = NonNull::new(ptr) else {
+            return;
+        };
+
+        let pooled = Worker::with(|w| unsafe { w.object_pool.borrow_mut().free(ptr, size) });
+        if pooled.is_none() {
+            // there's no worker on this thread
+            unsafe { libc::free(ptr.as_ptr()) };
+        }
+    }
+
     /// Aggregate the given syscall counts in a worker syscall counter.
     #[no_mangle]
     pub extern "C-unwind" fn worker_add_syscall_counts(syscall_counts: *const Counter) {
@@ -772,6 +1266,54 @@ mod export {
         .unwrap()
     }
 
+    /// Record that the active host established a connection between `local` and `peer`, if event
+    /// tracing is enabled. Either string may be NULL if the address is unknown.
+    #[no_mangle]
+    pub extern "C-unwind" fn worker_traceConnectionEstablished(
+        local: *const libc::c_char,
+        peer: *const libc::c_char,
+    ) {
+        let to_string = |s: *const libc::c_char| {
+            if s.is_null() {
+                return "unknown".to_string();
+            }
+            unsafe { std::ffi::CStr::from_ptr(s) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        Worker::trace_connection_established(&to_string(local), &to_string(peer));
+    }
+
+    /// Call the simulation hooks for a legacy TCP socket that moved from the state named `old` to
+    /// the state named `new`. The addresses and ports are in network byte order.
+    #[no_mangle]
+    pub extern "C-unwind" fn worker_traceTcpStateChanged(
+        local_ip: libc::in_addr_t,
+        local_port: libc::in_port_t,
+        peer_ip: libc::in_addr_t,
+        peer_port: libc::in_port_t,
+        old: *const libc::c_char,
+        new: *const libc::c_char,
+    ) {
+        let to_kind = |s: *const libc::c_char| {
+            assert!(!s.is_null());
+            let name = unsafe { std::ffi::CStr::from_ptr(s) }.to_str().unwrap();
+            crate::core::hooks::legacy_tcp_state_kind(name).unwrap()
+        };
+        let to_addr = |ip: libc::in_addr_t, port: libc::in_port_t| {
+            std::net::SocketAddrV4::new(Ipv4Addr::from(u32::from_be(ip)), u16::from_be(port))
+        };
+
+        let (old, new) = (to_kind(old), to_kind(new));
+        let local = to_addr(local_ip, local_port);
+        let peer = to_addr(peer_ip, peer_port);
+
+        Worker::call_hooks(|hooks, host_id, now| {
+            hooks.tcp_state_changed(host_id, now, local, peer, old, new)
+        });
+    }
+
     /// Returns a pointer to the current running host. The returned pointer is
     /// invalidated the next time the worker switches hosts.
     #[no_mangle]
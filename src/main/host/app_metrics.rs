@@ -0,0 +1,143 @@
+//! Metrics that managed applications report to Shadow, which are logged with the host's heartbeat
+//! messages at the simulated time that they were reported. This puts the application-level and
+//! simulator-level metrics in one output stream.
+//!
+//! Applications report metrics by sending UDP datagrams to the loopback address at the port given
+//! by the `app_metrics_port` option. The datagrams use the plain-text StatsD format, so existing
+//! StatsD clients can be used. Each line of a datagram is a metric `<name>:<value>|<type>`, where
+//! the type is `c` (counter), `g` (gauge), or `ms` (timer). Any sample rate or tags following the
+//! type are ignored.
+
+/// The type of an application metric.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Timer,
+}
+
+impl MetricKind {
+    /// The name used for the metric type in the log.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Timer => "timer",
+        }
+    }
+}
+
+/// A metric reported by an application.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AppMetric<'a> {
+    pub name: &'a str,
+    pub kind: MetricKind,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    NotUtf8,
+    Malformed,
+    InvalidName,
+    InvalidValue,
+    UnsupportedType(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotUtf8 => write!(f, "the metric isn't valid UTF-8"),
+            Self::Malformed => write!(f, "expected a metric of the form '<name>:<value>|<type>'"),
+            Self::InvalidName => write!(f, "the metric name is empty or has reserved characters"),
+            Self::InvalidValue => write!(f, "the metric value isn't a finite number"),
+            Self::UnsupportedType(x) => write!(f, "unsupported metric type '{x}'"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse the metrics in a datagram, one per non-empty line.
+pub fn parse(datagram: &[u8]) -> impl Iterator<Item = Result<AppMetric<'_>, ParseError>> {
+    datagram
+        .split(|x| *x == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_line(std::str::from_utf8(line).map_err(|_| ParseError::NotUtf8)?))
+}
+
+fn parse_line(line: &str) -> Result<AppMetric<'_>, ParseError> {
+    let line = line.trim_end_matches('\r');
+    let (name, rest) = line.split_once(':').ok_or(ParseError::Malformed)?;
+
+    let mut fields = rest.split('|');
+    let value = fields.next().unwrap();
+    let kind = fields.next().ok_or(ParseError::Malformed)?;
+
+    // the metric names are logged in comma-separated lists
+    let is_valid_char = |c: char| c.is_ascii_graphic() && !matches!(c, ',' | ';' | '|' | ':');
+    if name.is_empty() || !name.chars().all(is_valid_char) {
+        return Err(ParseError::InvalidName);
+    }
+
+    let value: f64 = value.parse().map_err(|_| ParseError::InvalidValue)?;
+    if !value.is_finite() {
+        return Err(ParseError::InvalidValue);
+    }
+
+    let kind = match kind {
+        "c" => MetricKind::Counter,
+        "g" => MetricKind::Gauge,
+        "ms" => MetricKind::Timer,
+        x => return Err(ParseError::UnsupportedType(x.to_string())),
+    };
+
+    Ok(AppMetric { name, kind, value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let metrics: Vec<_> =
+            parse(b"requests:1|c\nqueue.len:-3.5|g\r\n\nlatency:12|ms|@0.5|#a:b\n").collect();
+        assert_eq!(
+            metrics,
+            [
+                Ok(AppMetric {
+                    name: "requests",
+                    kind: MetricKind::Counter,
+                    value: 1.0,
+                }),
+                Ok(AppMetric {
+                    name: "queue.len",
+                    kind: MetricKind::Gauge,
+                    value: -3.5,
+                }),
+                Ok(AppMetric {
+                    name: "latency",
+                    kind: MetricKind::Timer,
+                    value: 12.0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse_one = |x: &[u8]| parse(x).next().unwrap();
+        assert_eq!(parse_one(b"requests"), Err(ParseError::Malformed));
+        assert_eq!(parse_one(b"requests:1"), Err(ParseError::Malformed));
+        assert_eq!(parse_one(b":1|c"), Err(ParseError::InvalidName));
+        assert_eq!(parse_one(b"a,b:1|c"), Err(ParseError::InvalidName));
+        assert_eq!(parse_one(b"requests:x|c"), Err(ParseError::InvalidValue));
+        assert_eq!(parse_one(b"requests:inf|c"), Err(ParseError::InvalidValue));
+        assert_eq!(
+            parse_one(b"users:1|s"),
+            Err(ParseError::UnsupportedType("s".to_string()))
+        );
+        assert_eq!(parse_one(b"\xff:1|c"), Err(ParseError::NotUtf8));
+    }
+}
@@ -47,6 +47,12 @@ impl Cpu {
 
     /// Account for `native_delay` spent natively executing code.
     pub fn add_delay(&mut self, native_delay: Duration) {
+        self.time_cpu_available += self.simulated_delay(native_delay);
+    }
+
+    /// Convert `native_delay` spent natively executing code to the corresponding time spent on
+    /// the simulated CPU.
+    pub fn simulated_delay(&self, native_delay: Duration) -> SimulationTime {
         // first normalize the physical CPU to the virtual CPU. We use u128 here
         // to guarantee no overflow when multiplying two u64's.
         let cycles = native_delay
@@ -74,7 +80,7 @@ impl Cpu {
             }
         }
 
-        self.time_cpu_available += adjusted_delay;
+        adjusted_delay
     }
 
     /// Calculate the simulated delay until this CPU is ready to run again.
@@ -0,0 +1,529 @@
+//! Emulated user and group IDs of a process, as described in credentials(7).
+//!
+//! Shadow itself usually runs unprivileged, so the IDs are emulated rather than set on the native
+//! processes. A process is considered privileged (having `CAP_SETUID`, `CAP_SETGID`, `CAP_CHOWN`,
+//! and `CAP_SETPCAP`) if and only if its effective user ID is 0.
+
+use std::sync::OnceLock;
+
+use linux_api::errno::Errno;
+
+/// The maximum number of supplementary group IDs.
+pub const NGROUPS_MAX: usize = 65536;
+
+/// The highest capability number known to Linux (`CAP_CHECKPOINT_RESTORE`).
+pub const CAP_LAST_CAP: u64 = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub ruid: libc::uid_t,
+    pub euid: libc::uid_t,
+    pub suid: libc::uid_t,
+    pub fsuid: libc::uid_t,
+    pub rgid: libc::gid_t,
+    pub egid: libc::gid_t,
+    pub sgid: libc::gid_t,
+    pub fsgid: libc::gid_t,
+    pub groups: Vec<libc::gid_t>,
+    /// The capability bounding set, with bit `n` set if capability `n` is in the set. See
+    /// capabilities(7).
+    pub cap_bounding_set: u64,
+}
+
+impl Credentials {
+    /// Credentials where the real, effective, saved, and filesystem IDs are all `uid` and `gid`.
+    pub fn new(uid: libc::uid_t, gid: libc::gid_t, groups: Vec<libc::gid_t>) -> Self {
+        Self {
+            ruid: uid,
+            euid: uid,
+            suid: uid,
+            fsuid: uid,
+            rgid: gid,
+            egid: gid,
+            sgid: gid,
+            fsgid: gid,
+            groups,
+            cap_bounding_set: (1 << (CAP_LAST_CAP + 1)) - 1,
+        }
+    }
+
+    /// The credentials of the Shadow process.
+    pub fn native() -> Self {
+        let groups = nix::unistd::getgroups()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.as_raw())
+            .collect();
+        Self::new(
+            nix::unistd::getuid().as_raw(),
+            nix::unistd::getgid().as_raw(),
+            groups,
+        )
+    }
+
+    pub fn is_privileged(&self) -> bool {
+        self.euid == 0
+    }
+
+    /// See setuid(2).
+    pub fn setuid(&mut self, uid: libc::uid_t) -> Result<(), Errno> {
+        if self.is_privileged() {
+            self.ruid = uid;
+            self.suid = uid;
+        } else if uid != self.ruid && uid != self.suid {
+            return Err(Errno::EPERM);
+        }
+        self.euid = uid;
+        self.fsuid = uid;
+        Ok(())
+    }
+
+    /// See setgid(2).
+    pub fn setgid(&mut self, gid: libc::gid_t) -> Result<(), Errno> {
+        if self.is_privileged() {
+            self.rgid = gid;
+            self.sgid = gid;
+        } else if gid != self.rgid && gid != self.sgid {
+            return Err(Errno::EPERM);
+        }
+        self.egid = gid;
+        self.fsgid = gid;
+        Ok(())
+    }
+
+    /// See setreuid(2). An ID of `None` is left unchanged.
+    pub fn setreuid(
+        &mut self,
+        ruid: Option<libc::uid_t>,
+        euid: Option<libc::uid_t>,
+    ) -> Result<(), Errno> {
+        let (old_ruid, old_euid, old_suid) = (self.ruid, self.euid, self.suid);
+        if !self.is_privileged() {
+            if ruid.is_some_and(|x| x != old_ruid && x != old_euid) {
+                return Err(Errno::EPERM);
+            }
+            if euid.is_some_and(|x| x != old_ruid && x != old_euid && x != old_suid) {
+                return Err(Errno::EPERM);
+            }
+        }
+
+        let new_ruid = ruid.unwrap_or(old_ruid);
+        let new_euid = euid.unwrap_or(old_euid);
+
+        // the saved ID is set to the new effective ID if the real ID is set or the effective ID is
+        // set to a value not equal to the previous real ID
+        if ruid.is_some() || euid.is_some_and(|x| x != old_ruid) {
+            self.suid = new_euid;
+        }
+        self.ruid = new_ruid;
+        self.euid = new_euid;
+        self.fsuid = new_euid;
+        Ok(())
+    }
+
+    /// See setregid(2). An ID of `None` is left unchanged.
+    pub fn setregid(
+        &mut self,
+        rgid: Option<libc::gid_t>,
+        egid: Option<libc::gid_t>,
+    ) -> Result<(), Errno> {
+        let (old_rgid, old_egid, old_sgid) = (self.rgid, self.egid, self.sgid);
+        if !self.is_privileged() {
+            if rgid.is_some_and(|x| x != old_rgid && x != old_egid) {
+                return Err(Errno::EPERM);
+            }
+            if egid.is_some_and(|x| x != old_rgid && x != old_egid && x != old_sgid) {
+                return Err(Errno::EPERM);
+            }
+        }
+
+        let new_rgid = rgid.unwrap_or(old_rgid);
+        let new_egid = egid.unwrap_or(old_egid);
+
+        if rgid.is_some() || egid.is_some_and(|x| x != old_rgid) {
+            self.sgid = new_egid;
+        }
+        self.rgid = new_rgid;
+        self.egid = new_egid;
+        self.fsgid = new_egid;
+        Ok(())
+    }
+
+    /// See setresuid(2). An ID of `None` is left unchanged.
+    pub fn setresuid(
+        &mut self,
+        ruid: Option<libc::uid_t>,
+        euid: Option<libc::uid_t>,
+        suid: Option<libc::uid_t>,
+    ) -> Result<(), Errno> {
+        let current = [self.ruid, self.euid, self.suid];
+        if !self.is_privileged()
+            && [ruid, euid, suid]
+                .into_iter()
+                .flatten()
+                .any(|x| !current.contains(&x))
+        {
+            return Err(Errno::EPERM);
+        }
+
+        self.ruid = ruid.unwrap_or(self.ruid);
+        self.euid = euid.unwrap_or(self.euid);
+        self.suid = suid.unwrap_or(self.suid);
+        self.fsuid = self.euid;
+        Ok(())
+    }
+
+    /// See setresgid(2). An ID of `None` is left unchanged.
+    pub fn setresgid(
+        &mut self,
+        rgid: Option<libc::gid_t>,
+        egid: Option<libc::gid_t>,
+        sgid: Option<libc::gid_t>,
+    ) -> Result<(), Errno> {
+        let current = [self.rgid, self.egid, self.sgid];
+        if !self.is_privileged()
+            && [rgid, egid, sgid]
+                .into_iter()
+                .flatten()
+                .any(|x| !current.contains(&x))
+        {
+            return Err(Errno::EPERM);
+        }
+
+        self.rgid = rgid.unwrap_or(self.rgid);
+        self.egid = egid.unwrap_or(self.egid);
+        self.sgid = sgid.unwrap_or(self.sgid);
+        self.fsgid = self.egid;
+        Ok(())
+    }
+
+    /// See setfsuid(2). Returns the previous filesystem user ID, even if the ID wasn't changed.
+    pub fn setfsuid(&mut self, fsuid: libc::uid_t) -> libc::uid_t {
+        let old_fsuid = self.fsuid;
+        if self.is_privileged() || [self.ruid, self.euid, self.suid, self.fsuid].contains(&fsuid) {
+            self.fsuid = fsuid;
+        }
+        old_fsuid
+    }
+
+    /// See setfsgid(2). Returns the previous filesystem group ID, even if the ID wasn't changed.
+    pub fn setfsgid(&mut self, fsgid: libc::gid_t) -> libc::gid_t {
+        let old_fsgid = self.fsgid;
+        if self.is_privileged() || [self.rgid, self.egid, self.sgid, self.fsgid].contains(&fsgid) {
+            self.fsgid = fsgid;
+        }
+        old_fsgid
+    }
+
+    /// See setgroups(2).
+    pub fn setgroups(&mut self, groups: Vec<libc::gid_t>) -> Result<(), Errno> {
+        if groups.len() > NGROUPS_MAX {
+            return Err(Errno::EINVAL);
+        }
+        if !self.is_privileged() {
+            return Err(Errno::EPERM);
+        }
+        self.groups = groups;
+        Ok(())
+    }
+
+    /// Checks whether the ownership of a file owned by `file_uid` and `file_gid` can be changed
+    /// to `uid` and `gid`, where `None` leaves the ID unchanged. See chown(2).
+    ///
+    /// `CAP_CHOWN` is cleared when the filesystem user ID is nonzero, so a privileged process
+    /// that has changed only its filesystem user ID is subject to the same checks as an
+    /// unprivileged process.
+    pub fn chown(
+        &self,
+        file_uid: libc::uid_t,
+        file_gid: libc::gid_t,
+        uid: Option<libc::uid_t>,
+        gid: Option<libc::gid_t>,
+    ) -> Result<(), Errno> {
+        if self.is_privileged() && self.fsuid == 0 {
+            return Ok(());
+        }
+
+        // only the owner can change the ownership, and can't give the file away
+        if uid.is_some_and(|uid| self.fsuid != file_uid || uid != file_uid) {
+            return Err(Errno::EPERM);
+        }
+
+        // the owner can change the group to any group it's a member of
+        let in_group = |gid| gid == file_gid || gid == self.fsgid || self.groups.contains(&gid);
+        if gid.is_some_and(|gid| self.fsuid != file_uid || !in_group(gid)) {
+            return Err(Errno::EPERM);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the IDs used in permission checks are the same as those of the Shadow process, in
+    /// which case the native permission checks are the same as the emulated checks.
+    pub fn is_native(&self) -> bool {
+        static NATIVE: OnceLock<Credentials> = OnceLock::new();
+        let native = NATIVE.get_or_init(Self::native);
+        let ids = |x: &Self| (x.ruid, x.rgid, x.fsuid, x.fsgid);
+        ids(self) == ids(native) && self.groups == native.groups
+    }
+
+    /// The credentials that access(2) uses, where the filesystem IDs are the real IDs.
+    pub fn with_real_ids(&self) -> Self {
+        Self {
+            fsuid: self.ruid,
+            fsgid: self.rgid,
+            ..self.clone()
+        }
+    }
+
+    /// Checks whether the access in `mask` (a combination of `R_OK`, `W_OK`, and `X_OK`) is
+    /// allowed to a file owned by `file_uid` and `file_gid` with mode `file_mode`. See
+    /// path_resolution(7).
+    ///
+    /// `CAP_DAC_OVERRIDE` is cleared when the filesystem user ID is nonzero, so only a process
+    /// with a filesystem user ID of 0 bypasses the permission bits.
+    pub fn check_access(
+        &self,
+        file_uid: libc::uid_t,
+        file_gid: libc::gid_t,
+        file_mode: libc::mode_t,
+        mask: libc::c_int,
+    ) -> Result<(), Errno> {
+        let mask = (mask & (libc::R_OK | libc::W_OK | libc::X_OK)) as libc::mode_t;
+
+        if self.fsuid == 0 {
+            // execute permission is only granted if some execute bit is set
+            let is_dir = file_mode & libc::S_IFMT == libc::S_IFDIR;
+            if mask & libc::X_OK as libc::mode_t != 0 && !is_dir && file_mode & 0o111 == 0 {
+                return Err(Errno::EACCES);
+            }
+            return Ok(());
+        }
+
+        let bits = if self.fsuid == file_uid {
+            file_mode >> 6
+        } else if self.fsgid == file_gid || self.groups.contains(&file_gid) {
+            file_mode >> 3
+        } else {
+            file_mode
+        };
+
+        if bits & mask != mask {
+            return Err(Errno::EACCES);
+        }
+        Ok(())
+    }
+
+    /// Checks whether a file owned by `file_uid` can be removed from or renamed in a directory
+    /// owned by `dir_uid` and `dir_gid` with mode `dir_mode`. In a directory with the sticky bit
+    /// set, only the owners of the file and the directory can do so. See unlink(2).
+    pub fn check_delete(
+        &self,
+        dir_uid: libc::uid_t,
+        dir_gid: libc::gid_t,
+        dir_mode: libc::mode_t,
+        file_uid: libc::uid_t,
+    ) -> Result<(), Errno> {
+        self.check_access(dir_uid, dir_gid, dir_mode, libc::W_OK | libc::X_OK)?;
+
+        let is_sticky = dir_mode & libc::S_ISVTX != 0;
+        if is_sticky && self.fsuid != 0 && self.fsuid != file_uid && self.fsuid != dir_uid {
+            return Err(Errno::EPERM);
+        }
+        Ok(())
+    }
+
+    /// See `PR_CAPBSET_READ` in prctl(2).
+    pub fn capbset_read(&self, cap: u64) -> Result<bool, Errno> {
+        if cap > CAP_LAST_CAP {
+            return Err(Errno::EINVAL);
+        }
+        Ok(self.cap_bounding_set & (1 << cap) != 0)
+    }
+
+    /// See `PR_CAPBSET_DROP` in prctl(2).
+    pub fn capbset_drop(&mut self, cap: u64) -> Result<(), Errno> {
+        if !self.is_privileged() {
+            return Err(Errno::EPERM);
+        }
+        if cap > CAP_LAST_CAP {
+            return Err(Errno::EINVAL);
+        }
+        self.cap_bounding_set &= !(1 << cap);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_privileges() {
+        let mut creds = Credentials::new(0, 0, vec![0, 1]);
+
+        // the usual order for a server dropping privileges
+        creds.setgroups(vec![33]).unwrap();
+        creds.setgid(33).unwrap();
+        creds.setuid(33).unwrap();
+        assert_eq!(creds, Credentials::new(33, 33, vec![33]));
+
+        // privileges can't be regained
+        assert_eq!(creds.setuid(0), Err(Errno::EPERM));
+        assert_eq!(creds.setgroups(vec![]), Err(Errno::EPERM));
+        assert_eq!(creds.setresgid(Some(0), None, None), Err(Errno::EPERM));
+        assert_eq!(creds.setfsuid(0), 33);
+        assert_eq!(creds.fsuid, 33);
+    }
+
+    #[test]
+    fn test_temporarily_drop_privileges() {
+        let mut creds = Credentials::new(0, 0, vec![]);
+
+        // only change the effective ID; the saved ID remains 0
+        creds.setresuid(None, Some(1000), None).unwrap();
+        assert_eq!((creds.ruid, creds.euid, creds.suid), (0, 1000, 0));
+        assert!(!creds.is_privileged());
+
+        // regain privileges from the saved ID
+        creds.setuid(0).unwrap();
+        assert_eq!((creds.ruid, creds.euid, creds.suid), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_setreuid() {
+        let mut creds = Credentials::new(1000, 1000, vec![]);
+        creds.suid = 2000;
+
+        // an unprivileged process can set the effective ID to the saved ID
+        creds.setreuid(None, Some(2000)).unwrap();
+        assert_eq!((creds.ruid, creds.euid, creds.suid), (1000, 2000, 2000));
+
+        // and can swap the real and effective IDs
+        creds.setreuid(Some(2000), Some(1000)).unwrap();
+        assert_eq!((creds.ruid, creds.euid, creds.suid), (2000, 1000, 1000));
+
+        assert_eq!(creds.setreuid(Some(3000), None), Err(Errno::EPERM));
+    }
+
+    #[test]
+    fn test_chown() {
+        let creds = Credentials::new(1000, 1000, vec![1000, 2000]);
+
+        // the owner can change the group to one of its groups
+        assert_eq!(creds.chown(1000, 1000, None, Some(2000)), Ok(()));
+        assert_eq!(creds.chown(1000, 1000, Some(1000), Some(1000)), Ok(()));
+        assert_eq!(creds.chown(1000, 1000, None, Some(3000)), Err(Errno::EPERM));
+
+        // but can't give the file away
+        assert_eq!(creds.chown(1000, 1000, Some(0), None), Err(Errno::EPERM));
+
+        // and can't change files it doesn't own
+        assert_eq!(creds.chown(0, 1000, None, Some(1000)), Err(Errno::EPERM));
+        assert_eq!(creds.chown(0, 0, None, None), Ok(()));
+
+        // a privileged process can change the ownership of any file, unless it changed its
+        // filesystem user ID
+        let mut creds = Credentials::new(0, 0, vec![]);
+        assert_eq!(creds.chown(1000, 1000, Some(33), Some(33)), Ok(()));
+        creds.setfsuid(1000);
+        assert_eq!(creds.chown(1000, 1000, Some(33), None), Err(Errno::EPERM));
+    }
+
+    #[test]
+    fn test_check_access() {
+        let creds = Credentials::new(1000, 1000, vec![1000, 2000]);
+        let (r, w, x) = (libc::R_OK, libc::W_OK, libc::X_OK);
+        let file = libc::S_IFREG;
+
+        // the owner's bits apply to the owner, even if the group or other bits allow more
+        assert_eq!(creds.check_access(1000, 0, file | 0o640, r | w), Ok(()));
+        assert_eq!(
+            creds.check_access(1000, 0, file | 0o477, w),
+            Err(Errno::EACCES)
+        );
+
+        // then the group's bits apply to the members of the group
+        assert_eq!(creds.check_access(0, 2000, file | 0o640, r), Ok(()));
+        assert_eq!(
+            creds.check_access(0, 2000, file | 0o640, w),
+            Err(Errno::EACCES)
+        );
+
+        // and the other bits to everyone else
+        assert_eq!(creds.check_access(0, 0, file | 0o604, r), Ok(()));
+        assert_eq!(
+            creds.check_access(0, 0, file | 0o640, r),
+            Err(Errno::EACCES)
+        );
+
+        // a process with a filesystem user ID of 0 can read and write any file, but can only
+        // execute files with some execute bit set
+        let mut creds = Credentials::new(0, 0, vec![]);
+        assert_eq!(creds.check_access(1000, 1000, file, r | w), Ok(()));
+        assert_eq!(
+            creds.check_access(1000, 1000, file | 0o600, x),
+            Err(Errno::EACCES)
+        );
+        assert_eq!(creds.check_access(1000, 1000, file | 0o001, x), Ok(()));
+        assert_eq!(creds.check_access(1000, 1000, libc::S_IFDIR, x), Ok(()));
+
+        // after dropping privileges, the permission bits apply
+        creds.setuid(33).unwrap();
+        assert_eq!(
+            creds.check_access(1000, 1000, file | 0o600, r),
+            Err(Errno::EACCES)
+        );
+
+        // access(2) checks the real IDs
+        let mut creds = Credentials::new(1000, 1000, vec![]);
+        creds.euid = 0;
+        creds.fsuid = 0;
+        assert_eq!(creds.check_access(0, 0, file | 0o600, r), Ok(()));
+        assert_eq!(
+            creds.with_real_ids().check_access(0, 0, file | 0o600, r),
+            Err(Errno::EACCES)
+        );
+    }
+
+    #[test]
+    fn test_check_delete() {
+        let creds = Credentials::new(1000, 1000, vec![]);
+        let dir = libc::S_IFDIR;
+
+        // removing a file requires write and search permission on the directory
+        assert_eq!(creds.check_delete(1000, 1000, dir | 0o700, 0), Ok(()));
+        assert_eq!(
+            creds.check_delete(0, 0, dir | 0o755, 1000),
+            Err(Errno::EACCES)
+        );
+
+        // in a sticky directory, only the owners of the file and the directory can remove it
+        let tmp = dir | libc::S_ISVTX | 0o777;
+        assert_eq!(creds.check_delete(0, 0, tmp, 1000), Ok(()));
+        assert_eq!(creds.check_delete(0, 0, tmp, 2000), Err(Errno::EPERM));
+        assert_eq!(creds.check_delete(1000, 1000, tmp, 2000), Ok(()));
+        assert_eq!(
+            Credentials::new(0, 0, vec![]).check_delete(1000, 1000, tmp, 2000),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_capbset() {
+        const CAP_SETGID: u64 = 6;
+        const CAP_SETUID: u64 = 7;
+
+        let mut creds = Credentials::new(0, 0, vec![]);
+        assert_eq!(creds.capbset_read(CAP_SETUID), Ok(true));
+        assert_eq!(creds.capbset_read(CAP_LAST_CAP + 1), Err(Errno::EINVAL));
+
+        creds.capbset_drop(CAP_SETUID).unwrap();
+        assert_eq!(creds.capbset_read(CAP_SETUID), Ok(false));
+        assert_eq!(creds.capbset_read(CAP_SETGID), Ok(true));
+
+        // an unprivileged process can't drop capabilities
+        creds.setuid(1000).unwrap();
+        assert_eq!(creds.capbset_drop(CAP_SETGID), Err(Errno::EPERM));
+    }
+}
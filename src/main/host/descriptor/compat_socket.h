@@ -6,13 +6,14 @@
 #ifndef SRC_MAIN_HOST_DESCRIPTOR_COMPAT_SOCKET_H_
 #define SRC_MAIN_HOST_DESCRIPTOR_COMPAT_SOCKET_H_
 
+#include <stdint.h>
+
 typedef enum _CompatSocketTypes CompatSocketTypes;
 typedef union _CompatSocketObject CompatSocketObject;
 typedef struct _CompatSocket CompatSocket;
 
 #include "main/bindings/c/bindings-opaque.h"
 #include "main/host/descriptor/socket.h"
-#include "main/utility/tagged_ptr.h"
 
 enum _CompatSocketTypes {
     CST_NONE,
@@ -33,22 +34,7 @@ struct _CompatSocket {
 CompatSocket compatsocket_fromLegacySocket(LegacySocket* socket);
 CompatSocket compatsocket_fromInetSocket(const InetSocket* socket);
 
-/* reference counting */
-CompatSocket compatsocket_refAs(const CompatSocket* socket);
-void compatsocket_unref(const CompatSocket* socket);
-
 /* handle to the socket object */
 uintptr_t compatsocket_getCanonicalHandle(const CompatSocket* socket);
 
-/* converting between a CompatSocket and a tagged pointer */
-uintptr_t compatsocket_toTagged(const CompatSocket* socket);
-CompatSocket compatsocket_fromTagged(uintptr_t ptr);
-
-/* compatability wrappers */
-int compatsocket_peekNextPacketPriority(const CompatSocket* socket, uint64_t* priorityOut);
-bool compatsocket_hasDataToSend(const CompatSocket* socket);
-void compatsocket_pushInPacket(const CompatSocket* socket, const Host* host, Packet* packet,
-                               CEmulatedTime recvTime);
-Packet* compatsocket_pullOutPacket(const CompatSocket* socket, const Host* host);
-
 #endif /* SRC_MAIN_HOST_DESCRIPTOR_COMPAT_SOCKET_H_ */
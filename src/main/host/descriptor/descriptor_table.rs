@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::HashMap;
 
 use log::*;
 use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
@@ -11,17 +11,30 @@ use crate::utility::ObjectCounter;
 /// POSIX requires fds to be assigned as `libc::c_int`, so we can't allow any fds larger than this.
 pub const FD_MAX: u32 = i32::MAX as u32;
 
+/// The initial number of slots in the dense part of the table. Must be a multiple of 64.
+const MIN_DENSE_LEN: usize = 64;
+
 /// Map of file handles to file descriptors. Typically owned by a Process.
+///
+/// Like Linux's fdtable, descriptors are stored in a vector indexed by fd along with a bitmap of
+/// the occupied slots, so that lookups are a single index and the lowest available fd can be found
+/// without scanning every slot. A second-level bitmap tracks which bitmap words are full, so
+/// finding a free fd only needs to look at one word per 4096 fds. Fds far past the end of the
+/// vector (for example from `dup2(fd, 1000000)`) are stored in a separate map so that a single
+/// large fd doesn't force the vector to grow.
 #[derive(Clone)]
 pub struct DescriptorTable {
-    descriptors: HashMap<DescriptorHandle, Descriptor>,
+    // Descriptors with fds less than `dense.len()`. The length is always a multiple of 64.
+    dense: Vec<Option<Descriptor>>,
+
+    // Bit `i % 64` of `used[i / 64]` is set if `dense[i]` is occupied.
+    used: Vec<u64>,
 
-    // Indices less than `next_index` known to be available.
-    available_indices: BTreeSet<u32>,
+    // Bit `i % 64` of `full[i / 64]` is set if `used[i]` has all of its bits set.
+    full: Vec<u64>,
 
-    // Lowest index not in `available_indices` that *might* be available. We still need to verify
-    // availability in `descriptors`, though.
-    next_index: u32,
+    // Descriptors with fds greater than or equal to `dense.len()`.
+    sparse: HashMap<DescriptorHandle, Descriptor>,
 
     _counter: ObjectCounter,
 }
@@ -29,9 +42,10 @@ pub struct DescriptorTable {
 impl DescriptorTable {
     pub fn new() -> Self {
         DescriptorTable {
-            descriptors: HashMap::new(),
-            available_indices: BTreeSet::new(),
-            next_index: 0,
+            dense: Vec::new(),
+            used: Vec::new(),
+            full: Vec::new(),
+            sparse: HashMap::new(),
             _counter: ObjectCounter::new("DescriptorTable"),
         }
     }
@@ -43,103 +57,170 @@ impl DescriptorTable {
         descriptor: Descriptor,
         min_index: DescriptorHandle,
     ) -> Result<DescriptorHandle, Descriptor> {
-        let idx = if let Some(idx) = self.available_indices.range(min_index.val()..).next() {
-            // Un-borrow from `available_indices`.
-            let idx = *idx;
-            // Take from `available_indices`
+        let idx = if let Some(idx) = self.lowest_free_dense(min_index.val() as usize) {
             trace!("Reusing available index {}", idx);
-            self.available_indices.remove(&idx);
-            idx
+            // `dense.len()` is never larger than `FD_MAX + 1`.
+            idx as u32
         } else {
-            // Start our search at either the next likely available index or the minimum index,
-            // whichever is larger.
-            let mut idx = std::cmp::max(self.next_index, min_index.val());
-
-            // Check if this index out of range.
-            if idx > FD_MAX {
-                return Err(descriptor);
-            }
+            // There are no free slots in the dense part of the table, so start our search at
+            // either the end of the dense part or the minimum index, whichever is larger.
+            let mut idx = std::cmp::max(self.dense.len() as u32, min_index.val());
+
+            // Skip past any indexes that are in use. This can happen after calling `set` with a
+            // value past the end of the dense part.
+            loop {
+                // Check if this index out of range.
+                let Some(handle) = DescriptorHandle::new(idx) else {
+                    return Err(descriptor);
+                };
 
-            // Only update next_index if we started at it, otherwise there may be other
-            // available indexes lower than idx.
-            let should_update_next_index = idx == self.next_index;
+                if !self.sparse.contains_key(&handle) {
+                    break;
+                }
 
-            // Skip past any indexes that are in use. This can happen after
-            // calling `set` with a value greater than `next_index`.
-            while self
-                .descriptors
-                .contains_key(&DescriptorHandle::new(idx).unwrap())
-            {
                 trace!("Skipping past in-use index {}", idx);
 
-                // Check if the next index is out of range.
-                if idx >= FD_MAX {
-                    return Err(descriptor);
-                }
-
-                // Won't overflow because of the check above.
+                // Won't overflow since `idx <= FD_MAX`.
                 idx += 1;
             }
 
-            if should_update_next_index {
-                self.next_index = idx + 1;
-            }
-
-            // Take the next index.
             trace!("Using index {}", idx);
             idx
         };
 
         let idx = DescriptorHandle::new(idx).unwrap();
 
-        let prev = self.descriptors.insert(idx, descriptor);
+        let prev = self.set(idx, descriptor);
         assert!(prev.is_none(), "Already a descriptor at {}", idx);
 
         Ok(idx)
     }
 
-    // Call after inserting to `available_indices`, to free any that are contiguous
-    // with `next_index`.
-    fn trim_tail(&mut self) {
-        while let Some(last_in_available) = self.available_indices.iter().next_back().copied() {
-            if (last_in_available + 1) == self.next_index {
-                // Last entry in available_indices is adjacent to next_index.
-                // We can merge them, freeing an entry in `available_indices`.
-                self.next_index -= 1;
-                self.available_indices.remove(&last_in_available);
-            } else {
-                break;
+    /// Returns the lowest unoccupied index in the dense part of the table that is at least `min`,
+    /// if any.
+    fn lowest_free_dense(&self, min: usize) -> Option<usize> {
+        let word = min / 64;
+        if word >= self.used.len() {
+            return None;
+        }
+
+        // Treat the bits below `min` as occupied.
+        let bits = self.used[word] | ((1 << (min % 64)) - 1);
+        if bits != u64::MAX {
+            return Some(word * 64 + bits.trailing_ones() as usize);
+        }
+
+        // Use the second-level bitmap to skip past the full words after `word`.
+        let word = word + 1;
+        let mut summary_idx = word / 64;
+        let mut mask: u64 = (1 << (word % 64)) - 1;
+        while let Some(summary) = self.full.get(summary_idx) {
+            let summary = *summary | mask;
+            if summary != u64::MAX {
+                let word = summary_idx * 64 + summary.trailing_ones() as usize;
+                // The bits past the end of `used` are never set.
+                let bits = self.used.get(word)?;
+                return Some(word * 64 + bits.trailing_ones() as usize);
             }
+            summary_idx += 1;
+            mask = 0;
         }
+
+        None
+    }
+
+    /// The largest index that the dense part of the table will grow to hold. Anything past this
+    /// is stored in the sparse map instead.
+    fn dense_limit(&self) -> usize {
+        std::cmp::max(2 * self.dense.len(), MIN_DENSE_LEN).min(FD_MAX as usize + 1)
+    }
+
+    /// Grow the dense part of the table so that it holds `idx`, which must be less than
+    /// [`Self::dense_limit`].
+    fn grow_dense(&mut self, idx: usize) {
+        let new_len = self.dense_limit();
+        assert!(idx < new_len);
+        debug_assert_eq!(new_len % 64, 0);
+
+        self.dense.resize_with(new_len, || None);
+        self.used.resize(new_len / 64, 0);
+        self.full.resize(self.used.len().div_ceil(64), 0);
+
+        // Move any descriptors that are now covered by the dense part out of the sparse map.
+        if !self.sparse.is_empty() {
+            let covered: Vec<DescriptorHandle> = self
+                .sparse
+                .keys()
+                .filter(|fd| (fd.val() as usize) < new_len)
+                .copied()
+                .collect();
+            for fd in covered {
+                let descriptor = self.sparse.remove(&fd).unwrap();
+                let idx = fd.val() as usize;
+                self.dense[idx] = Some(descriptor);
+                self.mark_used(idx);
+            }
+        }
+    }
+
+    fn mark_used(&mut self, idx: usize) {
+        let word = idx / 64;
+        self.used[word] |= 1 << (idx % 64);
+        if self.used[word] == u64::MAX {
+            self.full[word / 64] |= 1 << (word % 64);
+        }
+    }
+
+    fn mark_free(&mut self, idx: usize) {
+        let word = idx / 64;
+        self.used[word] &= !(1 << (idx % 64));
+        self.full[word / 64] &= !(1 << (word % 64));
     }
 
     /// Get the descriptor at `idx`, if any.
     pub fn get(&self, idx: DescriptorHandle) -> Option<&Descriptor> {
-        self.descriptors.get(&idx)
+        match self.dense.get(idx.val() as usize) {
+            Some(slot) => slot.as_ref(),
+            None => self.sparse.get(&idx),
+        }
     }
 
     /// Get the descriptor at `idx`, if any.
     pub fn get_mut(&mut self, idx: DescriptorHandle) -> Option<&mut Descriptor> {
-        self.descriptors.get_mut(&idx)
+        match self.dense.get_mut(idx.val() as usize) {
+            Some(slot) => slot.as_mut(),
+            None => self.sparse.get_mut(&idx),
+        }
     }
 
     /// Insert a descriptor at `index`. If a descriptor is already present at that index, it is
     /// unregistered from that index and returned.
     #[must_use]
     fn set(&mut self, index: DescriptorHandle, descriptor: Descriptor) -> Option<Descriptor> {
-        // We ensure the index is no longer in `self.available_indices`. We *don't* ensure
-        // `self.next_index` is > `index`, since that'd require adding the indices in between to
-        // `self.available_indices`. It uses less memory and is no more expensive to iterate when
-        // *using* `self.available_indices` instead.
-        self.available_indices.remove(&index.val());
+        let idx = index.val() as usize;
+
+        if idx >= self.dense.len() && idx < self.dense_limit() {
+            self.grow_dense(idx);
+        }
 
-        if let Some(prev) = self.descriptors.insert(index, descriptor) {
+        let prev = match self.dense.get_mut(idx) {
+            Some(slot) => {
+                let prev = slot.replace(descriptor);
+                if prev.is_none() {
+                    self.mark_used(idx);
+                }
+                prev
+            }
+            None => self.sparse.insert(index, descriptor),
+        };
+
+        if prev.is_some() {
             trace!("Overwriting index {}", index);
-            Some(prev)
         } else {
             trace!("Setting to unused index {}", index);
-            None
         }
+
+        prev
     }
 
     /// Register a descriptor and return its fd handle. Equivalent to
@@ -179,10 +260,17 @@ impl DescriptorTable {
     /// Deregister the descriptor with the given fd handle and return it.
     #[must_use]
     pub fn deregister_descriptor(&mut self, fd: DescriptorHandle) -> Option<Descriptor> {
-        let maybe_descriptor = self.descriptors.remove(&fd);
-        self.available_indices.insert(fd.val());
-        self.trim_tail();
-        maybe_descriptor
+        let idx = fd.val() as usize;
+        match self.dense.get_mut(idx) {
+            Some(slot) => {
+                let maybe_descriptor = slot.take();
+                if maybe_descriptor.is_some() {
+                    self.mark_free(idx);
+                }
+                maybe_descriptor
+            }
+            None => self.sparse.remove(&fd),
+        }
     }
 
     /// Remove and return all descriptors.
@@ -190,11 +278,19 @@ impl DescriptorTable {
         // reset the descriptor table
         let old_self = std::mem::replace(self, Self::new());
         // return the old descriptors
-        old_self.descriptors.into_values()
+        old_self
+            .dense
+            .into_iter()
+            .flatten()
+            .chain(old_self.sparse.into_values())
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&DescriptorHandle, &Descriptor)> {
-        self.descriptors.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (DescriptorHandle, &Descriptor)> {
+        let dense = self.dense.iter().enumerate().filter_map(|(idx, slot)| {
+            let handle = DescriptorHandle::new(idx.try_into().unwrap()).unwrap();
+            Some((handle, slot.as_ref()?))
+        });
+        dense.chain(self.sparse.iter().map(|(handle, desc)| (*handle, desc)))
     }
 }
 
@@ -313,3 +409,120 @@ impl std::fmt::Display for DescriptorHandleError {
 }
 
 impl std::error::Error for DescriptorHandleError {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use atomic_refcell::AtomicRefCell;
+
+    use super::*;
+    use crate::host::descriptor::eventfd::EventFd;
+    use crate::host::descriptor::{CompatFile, File, FileStatus, OpenFile};
+
+    fn new_descriptor() -> Descriptor {
+        let eventfd = EventFd::new(0, false, FileStatus::empty());
+        let file = File::EventFd(Arc::new(AtomicRefCell::new(eventfd)));
+        Descriptor::new(CompatFile::New(OpenFile::new(file)))
+    }
+
+    fn handle(fd: u32) -> DescriptorHandle {
+        DescriptorHandle::new(fd).unwrap()
+    }
+
+    #[test]
+    fn test_lowest_available() {
+        let desc = new_descriptor();
+        let mut table = DescriptorTable::new();
+
+        for fd in 0..200 {
+            assert_eq!(table.register_descriptor(desc.clone()).unwrap(), handle(fd));
+        }
+
+        // the freed fds are reused in increasing order, across bitmap word boundaries
+        for fd in [130, 5, 64, 63] {
+            assert!(table.deregister_descriptor(handle(fd)).is_some());
+        }
+        for fd in [5, 63, 64, 130, 200] {
+            assert_eq!(table.register_descriptor(desc.clone()).unwrap(), handle(fd));
+        }
+
+        // deregistering an fd that isn't registered does nothing
+        assert!(table.deregister_descriptor(handle(1000)).is_none());
+        assert!(table.deregister_descriptor(handle(201)).is_none());
+        assert_eq!(
+            table.register_descriptor(desc.clone()).unwrap(),
+            handle(201)
+        );
+
+        assert_eq!(table.iter().count(), 202);
+    }
+
+    #[test]
+    fn test_min_fd() {
+        let desc = new_descriptor();
+        let mut table = DescriptorTable::new();
+
+        for fd in 0..10 {
+            assert_eq!(table.register_descriptor(desc.clone()).unwrap(), handle(fd));
+        }
+        assert!(table.deregister_descriptor(handle(3)).is_some());
+
+        let fd = table.register_descriptor_with_min_fd(desc.clone(), handle(4));
+        assert_eq!(fd.unwrap(), handle(10));
+        let fd = table.register_descriptor_with_min_fd(desc.clone(), handle(100));
+        assert_eq!(fd.unwrap(), handle(100));
+        let fd = table.register_descriptor_with_min_fd(desc.clone(), handle(100));
+        assert_eq!(fd.unwrap(), handle(101));
+        let fd = table.register_descriptor_with_min_fd(desc.clone(), handle(0));
+        assert_eq!(fd.unwrap(), handle(3));
+    }
+
+    #[test]
+    fn test_sparse() {
+        let desc = new_descriptor();
+        let mut table = DescriptorTable::new();
+
+        // far past the end of the dense part of the table
+        assert!(table
+            .register_descriptor_with_fd(desc.clone(), handle(1000))
+            .is_none());
+        assert!(table
+            .register_descriptor_with_fd(desc.clone(), handle(FD_MAX))
+            .is_none());
+        assert!(table.get(handle(1000)).is_some());
+        assert!(table.get(handle(999)).is_none());
+
+        // the table grows past the sparse descriptor, which is skipped
+        for fd in (0..1000).chain(1001..2000) {
+            assert_eq!(table.register_descriptor(desc.clone()).unwrap(), handle(fd));
+        }
+        assert!(table.get(handle(1000)).is_some());
+
+        // replacing a descriptor returns the old one
+        assert!(table
+            .register_descriptor_with_fd(desc.clone(), handle(1000))
+            .is_some());
+        assert!(table.deregister_descriptor(handle(1000)).is_some());
+        assert!(table.get(handle(1000)).is_none());
+        assert_eq!(
+            table.register_descriptor(desc.clone()).unwrap(),
+            handle(1000)
+        );
+
+        // there are no fds available past `FD_MAX`
+        assert!(table
+            .register_descriptor_with_min_fd(desc.clone(), handle(FD_MAX))
+            .is_err());
+        assert!(table.deregister_descriptor(handle(FD_MAX)).is_some());
+        assert_eq!(
+            table
+                .register_descriptor_with_min_fd(desc.clone(), handle(FD_MAX))
+                .unwrap(),
+            handle(FD_MAX)
+        );
+
+        assert_eq!(table.remove_all().count(), 2001);
+        assert_eq!(table.iter().count(), 0);
+    }
+}
@@ -18,7 +18,9 @@ use crate::utility::{HostTreePointer, IsSend, IsSync, ObjectCounter};
 pub mod descriptor_table;
 pub mod epoll;
 pub mod eventfd;
+pub mod pidfd;
 pub mod pipe;
+pub mod pty;
 pub mod shared_buf;
 pub mod socket;
 pub mod timerfd;
@@ -304,6 +306,8 @@ pub enum File {
     Socket(Socket),
     TimerFd(Arc<AtomicRefCell<timerfd::TimerFd>>),
     Epoll(Arc<AtomicRefCell<epoll::Epoll>>),
+    PidFd(Arc<AtomicRefCell<pidfd::PidFd>>),
+    Pty(Arc<AtomicRefCell<pty::Pty>>),
 }
 
 // will not compile if `File` is not Send + Sync
@@ -318,6 +322,8 @@ impl File {
             Self::Socket(ref f) => FileRef::Socket(f.borrow()),
             Self::TimerFd(ref f) => FileRef::TimerFd(f.borrow()),
             Self::Epoll(ref f) => FileRef::Epoll(f.borrow()),
+            Self::PidFd(ref f) => FileRef::PidFd(f.borrow()),
+            Self::Pty(ref f) => FileRef::Pty(f.borrow()),
         }
     }
 
@@ -328,6 +334,8 @@ impl File {
             Self::Socket(ref f) => FileRef::Socket(f.try_borrow()?),
             Self::TimerFd(ref f) => FileRef::TimerFd(f.try_borrow()?),
             Self::Epoll(ref f) => FileRef::Epoll(f.try_borrow()?),
+            Self::PidFd(ref f) => FileRef::PidFd(f.try_borrow()?),
+            Self::Pty(ref f) => FileRef::Pty(f.try_borrow()?),
         })
     }
 
@@ -338,6 +346,8 @@ impl File {
             Self::Socket(ref f) => FileRefMut::Socket(f.borrow_mut()),
             Self::TimerFd(ref f) => FileRefMut::TimerFd(f.borrow_mut()),
             Self::Epoll(ref f) => FileRefMut::Epoll(f.borrow_mut()),
+            Self::PidFd(ref f) => FileRefMut::PidFd(f.borrow_mut()),
+            Self::Pty(ref f) => FileRefMut::Pty(f.borrow_mut()),
         }
     }
 
@@ -348,6 +358,8 @@ impl File {
             Self::Socket(ref f) => FileRefMut::Socket(f.try_borrow_mut()?),
             Self::TimerFd(ref f) => FileRefMut::TimerFd(f.try_borrow_mut()?),
             Self::Epoll(ref f) => FileRefMut::Epoll(f.try_borrow_mut()?),
+            Self::PidFd(ref f) => FileRefMut::PidFd(f.try_borrow_mut()?),
+            Self::Pty(ref f) => FileRefMut::Pty(f.try_borrow_mut()?),
         })
     }
 
@@ -358,6 +370,8 @@ impl File {
             Self::Socket(ref f) => f.canonical_handle(),
             Self::TimerFd(f) => Arc::as_ptr(f) as usize,
             Self::Epoll(f) => Arc::as_ptr(f) as usize,
+            Self::PidFd(f) => Arc::as_ptr(f) as usize,
+            Self::Pty(f) => Arc::as_ptr(f) as usize,
         }
     }
 }
@@ -370,6 +384,8 @@ impl std::fmt::Debug for File {
             Self::Socket(_) => write!(f, "Socket")?,
             Self::TimerFd(_) => write!(f, "TimerFd")?,
             Self::Epoll(_) => write!(f, "Epoll")?,
+            Self::PidFd(_) => write!(f, "PidFd")?,
+            Self::Pty(_) => write!(f, "Pty")?,
         }
 
         if let Ok(file) = self.try_borrow() {
@@ -391,6 +407,8 @@ pub enum FileRef<'a> {
     Socket(SocketRef<'a>),
     TimerFd(atomic_refcell::AtomicRef<'a, timerfd::TimerFd>),
     Epoll(atomic_refcell::AtomicRef<'a, epoll::Epoll>),
+    PidFd(atomic_refcell::AtomicRef<'a, pidfd::PidFd>),
+    Pty(atomic_refcell::AtomicRef<'a, pty::Pty>),
 }
 
 pub enum FileRefMut<'a> {
@@ -399,55 +417,57 @@ pub enum FileRefMut<'a> {
     Socket(SocketRefMut<'a>),
     TimerFd(atomic_refcell::AtomicRefMut<'a, timerfd::TimerFd>),
     Epoll(atomic_refcell::AtomicRefMut<'a, epoll::Epoll>),
+    PidFd(atomic_refcell::AtomicRefMut<'a, pidfd::PidFd>),
+    Pty(atomic_refcell::AtomicRefMut<'a, pty::Pty>),
 }
 
 impl FileRef<'_> {
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn supports_sa_restart(&self) -> bool
     );
 }
 
 impl FileRefMut<'_> {
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn supports_sa_restart(&self) -> bool
     );
-    enum_passthrough!(self, (val), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (val), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn set_has_open_file(&mut self, val: bool)
     );
-    enum_passthrough!(self, (cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
-    enum_passthrough!(self, (status), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (status), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn set_status(&mut self, status: FileStatus)
     );
-    enum_passthrough!(self, (request, arg_ptr, memory_manager), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (request, arg_ptr, memory_manager), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn ioctl(&mut self, request: IoctlRequest, arg_ptr: ForeignPtr<()>, memory_manager: &mut MemoryManager) -> SyscallResult
     );
-    enum_passthrough!(self, (monitoring, filter, notify_fn), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (monitoring, filter, notify_fn), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn add_listener(
             &mut self,
             monitoring: FileState,
@@ -455,17 +475,17 @@ impl FileRefMut<'_> {
             notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
         ) -> Handle<(FileState, FileState)>
     );
-    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>)
     );
-    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (ptr), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener)
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn readv(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                      mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Pipe, EventFd, Socket, TimerFd, Epoll, PidFd, Pty;
         pub fn writev(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                       mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
@@ -479,6 +499,8 @@ impl std::fmt::Debug for FileRef<'_> {
             Self::Socket(_) => write!(f, "Socket")?,
             Self::TimerFd(_) => write!(f, "TimerFd")?,
             Self::Epoll(_) => write!(f, "Epoll")?,
+            Self::PidFd(_) => write!(f, "PidFd")?,
+            Self::Pty(_) => write!(f, "Pty")?,
         }
 
         write!(
@@ -498,6 +520,8 @@ impl std::fmt::Debug for FileRefMut<'_> {
             Self::Socket(_) => write!(f, "Socket")?,
             Self::TimerFd(_) => write!(f, "TimerFd")?,
             Self::Epoll(_) => write!(f, "Epoll")?,
+            Self::PidFd(_) => write!(f, "PidFd")?,
+            Self::Pty(_) => write!(f, "Pty")?,
         }
 
         write!(
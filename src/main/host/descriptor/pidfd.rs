@@ -0,0 +1,171 @@
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use linux_api::posix_types::kernel_off_t;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::cshadow as c;
+use crate::host::descriptor::{
+    FileMode, FileState, FileStatus, StateEventSource, StateListenerFilter,
+};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::process::ProcessId;
+use crate::host::syscall::io::IoVec;
+use crate::host::syscall_types::{SyscallError, SyscallResult};
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::HostTreePointer;
+
+/// A file descriptor that refers to a process, as created by pidfd_open(2) or by clone(2) with
+/// `CLONE_PIDFD`. The file becomes readable once the process has exited.
+pub struct PidFd {
+    pid: ProcessId,
+    event_source: StateEventSource,
+    status: FileStatus,
+    state: FileState,
+    // Should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file,
+    has_open_file: bool,
+}
+
+impl PidFd {
+    /// Creates a new [`PidFd`] for the process `pid`. `exited` should be true if the process has
+    /// already exited, otherwise [`PidFd::process_exited`] should be called when it exits.
+    pub fn new(pid: ProcessId, exited: bool, status: FileStatus) -> Self {
+        let mut state = FileState::ACTIVE;
+        state.set(FileState::READABLE, exited);
+        Self {
+            pid,
+            event_source: StateEventSource::new(),
+            status,
+            state,
+            has_open_file: false,
+        }
+    }
+
+    /// The ID of the process that this file refers to.
+    pub fn pid(&self) -> ProcessId {
+        self.pid
+    }
+
+    /// Should be called when the process that this file refers to exits.
+    pub fn process_exited(&mut self, cb_queue: &mut CallbackQueue) {
+        if self.state.contains(FileState::CLOSED) {
+            return;
+        }
+        self.copy_state(FileState::READABLE, FileState::READABLE, cb_queue);
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ | FileMode::WRITE
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        false
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<kernel_off_t>,
+        _flags: std::ffi::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<isize, SyscallError> {
+        // PidFds don't support reading.
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<kernel_off_t>,
+        _flags: std::ffi::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<isize, SyscallError> {
+        // PidFds don't support writing.
+        Err(Errno::EINVAL.into())
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // Set the closed flag and remove the active and readable flags.
+        self.copy_state(
+            FileState::CLOSED | FileState::ACTIVE | FileState::READABLE,
+            FileState::CLOSED,
+            cb_queue,
+        );
+
+        Ok(())
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        _arg_ptr: ForeignPtr<()>,
+        _memory_manager: &mut MemoryManager,
+    ) -> SyscallResult {
+        warn_once_then_debug!(
+            "(LOG_ONCE) We do not yet handle ioctl request {request:?} on PidFds"
+        );
+        Err(Errno::ENOTTY.into())
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring: FileState,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
+    ) -> Handle<(FileState, FileState)> {
+        self.event_source
+            .add_listener(monitoring, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn copy_state(&mut self, mask: FileState, state: FileState, cb_queue: &mut CallbackQueue) {
+        let old_state = self.state;
+
+        // Remove the mask, then copy the masked flags.
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, cb_queue);
+    }
+
+    fn handle_state_change(&mut self, old_state: FileState, cb_queue: &mut CallbackQueue) {
+        let states_changed = self.state ^ old_state;
+
+        // Just return if nothing changed.
+        if states_changed.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, cb_queue);
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use atomic_refcell::AtomicRefCell;
+use atomic_refcell::{AtomicRefCell, AtomicRefMut};
 use linux_api::errno::Errno;
 use linux_api::ioctls::IoctlRequest;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
@@ -18,6 +18,10 @@ use crate::host::syscall_types::{SyscallError, SyscallResult};
 use crate::utility::callback_queue::{CallbackQueue, Handle};
 use crate::utility::HostTreePointer;
 
+/// The default value of `/proc/sys/fs/pipe-max-size`: the largest capacity that an unprivileged
+/// process can set for a pipe with `F_SETPIPE_SZ`.
+pub const PIPE_MAX_SIZE: usize = 1024 * 1024;
+
 pub struct Pipe {
     buffer: Option<Arc<AtomicRefCell<SharedBuf>>>,
     event_source: StateEventSource,
@@ -79,6 +83,91 @@ impl Pipe {
         self.buffer.as_ref().unwrap().borrow().max_len()
     }
 
+    /// Change the capacity of the pipe's buffer, which is shared with the other end of the pipe.
+    /// Returns `EBUSY` if the buffer currently holds more than `size` bytes.
+    pub fn set_max_size(&mut self, size: usize, cb_queue: &mut CallbackQueue) -> Result<(), Errno> {
+        self.buffer
+            .as_ref()
+            .unwrap()
+            .borrow_mut()
+            .set_max_len(size, cb_queue)
+    }
+
+    /// Move up to `len` bytes from this pipe to the pipe `dst`, as splice(2) does. Returns
+    /// `EWOULDBLOCK` if this pipe has no data or if `dst` is full.
+    pub fn splice_to(
+        &mut self,
+        dst: &mut Pipe,
+        len: usize,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<usize, SyscallError> {
+        let (mut src_buf, mut dst_buf) = self.transfer_buffers(dst)?;
+
+        if len == 0 || !Self::can_transfer(&src_buf, &dst_buf)? {
+            return Ok(0);
+        }
+
+        Ok(src_buf.splice_into(&mut dst_buf, len, cb_queue))
+    }
+
+    /// Copy up to `len` bytes from this pipe to the pipe `dst` without consuming them, as tee(2)
+    /// does. Returns `EWOULDBLOCK` if this pipe has no data or if `dst` is full.
+    pub fn tee_to(
+        &self,
+        dst: &mut Pipe,
+        len: usize,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<usize, SyscallError> {
+        let (src_buf, mut dst_buf) = self.transfer_buffers(dst)?;
+
+        if len == 0 || !Self::can_transfer(&src_buf, &dst_buf)? {
+            return Ok(0);
+        }
+
+        Ok(src_buf.copy_into(&mut dst_buf, len, cb_queue))
+    }
+
+    /// Borrow the buffers for moving data from this pipe to `dst`.
+    fn transfer_buffers<'a>(
+        &'a self,
+        dst: &'a Pipe,
+    ) -> Result<(AtomicRefMut<'a, SharedBuf>, AtomicRefMut<'a, SharedBuf>), SyscallError> {
+        if !self.mode.contains(FileMode::READ) || !dst.mode.contains(FileMode::WRITE) {
+            return Err(Errno::EBADF.into());
+        }
+
+        let src_buf = self.buffer.as_ref().unwrap();
+        let dst_buf = dst.buffer.as_ref().unwrap();
+
+        // both ends of the same pipe
+        if Arc::ptr_eq(src_buf, dst_buf) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        Ok((src_buf.borrow_mut(), dst_buf.borrow_mut()))
+    }
+
+    /// Returns false if there's nothing to transfer since `src_buf` is empty and has no writers,
+    /// or an error if the transfer can't happen yet.
+    fn can_transfer(src_buf: &SharedBuf, dst_buf: &SharedBuf) -> Result<bool, SyscallError> {
+        if !src_buf.has_data() {
+            if src_buf.num_writers() == 0 {
+                return Ok(false);
+            }
+            return Err(Errno::EWOULDBLOCK.into());
+        }
+
+        if dst_buf.num_readers() == 0 {
+            return Err(Errno::EPIPE.into());
+        }
+
+        if !dst_buf.state().contains(BufferState::WRITABLE) {
+            return Err(Errno::EWOULDBLOCK.into());
+        }
+
+        Ok(true)
+    }
+
     pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
         if self.state.contains(FileState::CLOSED) {
             log::warn!("Attempting to close an already-closed pipe");
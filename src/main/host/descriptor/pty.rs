@@ -0,0 +1,577 @@
+//! Pseudoterminals, as created by opening `/dev/ptmx`.
+//!
+//! We don't emulate a line discipline. Bytes written to one end of a pseudoterminal are passed
+//! through unmodified to the other end, as if the terminal were always in raw mode. The terminal
+//! attributes can be read and changed, but don't affect the data.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak};
+
+use atomic_refcell::AtomicRefCell;
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use linux_api::posix_types::kernel_off_t;
+use linux_api::termios::{termios, winsize};
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::cshadow as c;
+use crate::host::descriptor::shared_buf::{
+    BufferHandle, BufferState, ReaderHandle, SharedBuf, WriterHandle,
+};
+use crate::host::descriptor::{
+    FileMode, FileState, FileStatus, StateEventSource, StateListenerFilter,
+};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::process::ProcessId;
+use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter};
+use crate::host::syscall_types::{SyscallError, SyscallResult};
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::HostTreePointer;
+
+/// The maximum number of bytes buffered in each direction. Linux buffers up to 64 KiB.
+const PTY_BUFFER_SIZE: usize = 65536;
+
+/// The attributes of a newly created pseudoterminal. These are the kernel's defaults for a
+/// pseudoterminal slave.
+const DEFAULT_TERMIOS: termios = termios {
+    c_iflag: libc::ICRNL | libc::IXON,
+    c_oflag: libc::OPOST | libc::ONLCR,
+    c_cflag: libc::B38400 | libc::CS8 | libc::CREAD,
+    c_lflag: libc::ISIG
+        | libc::ICANON
+        | libc::ECHO
+        | libc::ECHOE
+        | libc::ECHOK
+        | libc::ECHOCTL
+        | libc::ECHOKE
+        | libc::IEXTEN,
+    c_line: 0,
+    // ^C, ^\, DEL, ^U, ^D, VTIME 0, VMIN 1, VSWTC 0, ^Q, ^S, ^Z, VEOL 0, ^R, ^O, ^W, ^V, VEOL2 0
+    c_cc: *b"\x03\x1c\x7f\x15\x04\x00\x01\x00\x11\x13\x1a\x00\x12\x0f\x17\x16\x00\x00\x00",
+};
+
+/// Which end of a pseudoterminal a [`Pty`] is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PtyEnd {
+    Master,
+    Slave,
+}
+
+/// The state shared by both ends of a pseudoterminal. Like Linux, requests for the terminal
+/// attributes and window size apply to the pair rather than to a specific end.
+pub struct PtyPair {
+    index: u32,
+    locked: bool,
+    master_open: bool,
+    termios: termios,
+    winsize: winsize,
+    /// The session that this is the controlling terminal of.
+    session: Option<ProcessId>,
+    /// The foreground process group, if this is a controlling terminal.
+    foreground_group: Option<ProcessId>,
+    to_master: Arc<AtomicRefCell<SharedBuf>>,
+    to_slave: Arc<AtomicRefCell<SharedBuf>>,
+}
+
+impl PtyPair {
+    /// The number of the pseudoterminal, as in `/dev/pts/<index>`.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Whether the slave can't be opened yet. See unlockpt(3).
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether the master is still open. Once it's closed, the slave can't be opened again.
+    pub fn is_master_open(&self) -> bool {
+        self.master_open
+    }
+
+    pub fn session(&self) -> Option<ProcessId> {
+        self.session
+    }
+
+    pub fn foreground_group(&self) -> Option<ProcessId> {
+        self.foreground_group
+    }
+
+    /// Make this the controlling terminal of `session`, with `group` in the foreground.
+    pub fn set_controlling_session(&mut self, session: ProcessId, group: ProcessId) {
+        self.session = Some(session);
+        self.foreground_group = Some(group);
+    }
+
+    /// Stop this being a controlling terminal.
+    pub fn clear_controlling_session(&mut self) {
+        self.session = None;
+        self.foreground_group = None;
+    }
+
+    pub fn set_foreground_group(&mut self, group: ProcessId) {
+        self.foreground_group = Some(group);
+    }
+
+    pub fn winsize(&self) -> winsize {
+        self.winsize
+    }
+
+    /// Set the window size, returning true if it changed.
+    pub fn set_winsize(&mut self, winsize: winsize) -> bool {
+        let changed = self.winsize != winsize;
+        self.winsize = winsize;
+        changed
+    }
+}
+
+/// One end of a pseudoterminal.
+pub struct Pty {
+    pair: Arc<AtomicRefCell<PtyPair>>,
+    end: PtyEnd,
+    input: Arc<AtomicRefCell<SharedBuf>>,
+    output: Arc<AtomicRefCell<SharedBuf>>,
+    event_source: StateEventSource,
+    state: FileState,
+    mode: FileMode,
+    status: FileStatus,
+    input_event_handle: Option<BufferHandle>,
+    output_event_handle: Option<BufferHandle>,
+    reader_handle: Option<ReaderHandle>,
+    writer_handle: Option<WriterHandle>,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+}
+
+impl Pty {
+    /// Create the master end of a new pseudoterminal numbered `index`. The slave is locked until
+    /// it's unlocked with the `TIOCSPTLCK` ioctl.
+    pub fn new_master(
+        index: u32,
+        mode: FileMode,
+        status: FileStatus,
+        cb_queue: &mut CallbackQueue,
+    ) -> Arc<AtomicRefCell<Self>> {
+        let pair = PtyPair {
+            index,
+            locked: true,
+            master_open: true,
+            termios: DEFAULT_TERMIOS,
+            winsize: winsize::default(),
+            session: None,
+            foreground_group: None,
+            to_master: Arc::new(AtomicRefCell::new(SharedBuf::new(PTY_BUFFER_SIZE))),
+            to_slave: Arc::new(AtomicRefCell::new(SharedBuf::new(PTY_BUFFER_SIZE))),
+        };
+
+        Self::new(
+            Arc::new(AtomicRefCell::new(pair)),
+            PtyEnd::Master,
+            mode,
+            status,
+            cb_queue,
+        )
+    }
+
+    /// Open the slave end of the pseudoterminal `pair`. The caller is responsible for checking
+    /// that the pair is unlocked and that its master is still open.
+    pub fn new_slave(
+        pair: Arc<AtomicRefCell<PtyPair>>,
+        mode: FileMode,
+        status: FileStatus,
+        cb_queue: &mut CallbackQueue,
+    ) -> Arc<AtomicRefCell<Self>> {
+        Self::new(pair, PtyEnd::Slave, mode, status, cb_queue)
+    }
+
+    fn new(
+        pair: Arc<AtomicRefCell<PtyPair>>,
+        end: PtyEnd,
+        mode: FileMode,
+        status: FileStatus,
+        cb_queue: &mut CallbackQueue,
+    ) -> Arc<AtomicRefCell<Self>> {
+        let (input, output) = {
+            let pair = pair.borrow();
+            match end {
+                PtyEnd::Master => (Arc::clone(&pair.to_master), Arc::clone(&pair.to_slave)),
+                PtyEnd::Slave => (Arc::clone(&pair.to_slave), Arc::clone(&pair.to_master)),
+            }
+        };
+
+        // Each end is always both a reader and a writer, regardless of its access mode, so that
+        // the other end sees it as connected.
+        let reader_handle = input.borrow_mut().add_reader(cb_queue);
+        let writer_handle = output.borrow_mut().add_writer(cb_queue);
+
+        let pty = Arc::new(AtomicRefCell::new(Self {
+            pair,
+            end,
+            input: Arc::clone(&input),
+            output: Arc::clone(&output),
+            event_source: StateEventSource::new(),
+            state: FileState::ACTIVE,
+            mode,
+            status,
+            input_event_handle: None,
+            output_event_handle: None,
+            reader_handle: Some(reader_handle),
+            writer_handle: Some(writer_handle),
+            has_open_file: false,
+        }));
+
+        let weak = Arc::downgrade(&pty);
+        let input_event_handle = input.borrow_mut().add_listener(
+            BufferState::READABLE | BufferState::NO_WRITERS,
+            move |buffer_state, cb_queue| {
+                if let Some(pty) = weak.upgrade() {
+                    pty.borrow_mut()
+                        .align_state_to_input(buffer_state, cb_queue);
+                }
+            },
+        );
+
+        let weak = Arc::downgrade(&pty);
+        let output_event_handle = output.borrow_mut().add_listener(
+            BufferState::WRITABLE | BufferState::NO_READERS,
+            move |buffer_state, cb_queue| {
+                if let Some(pty) = weak.upgrade() {
+                    pty.borrow_mut()
+                        .align_state_to_output(buffer_state, cb_queue);
+                }
+            },
+        );
+
+        {
+            let mut pty_ref = pty.borrow_mut();
+            pty_ref.input_event_handle = Some(input_event_handle);
+            pty_ref.output_event_handle = Some(output_event_handle);
+
+            // update the initial state to align with the buffers' current states
+            let input_state = input.borrow().state();
+            let output_state = output.borrow().state();
+            pty_ref.align_state_to_input(input_state, cb_queue);
+            pty_ref.align_state_to_output(output_state, cb_queue);
+        }
+
+        pty
+    }
+
+    /// The state shared with the other end of the pseudoterminal.
+    pub fn pair(&self) -> &Arc<AtomicRefCell<PtyPair>> {
+        &self.pair
+    }
+
+    pub fn end(&self) -> PtyEnd {
+        self.end
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        self.mode
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        true
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        if self.state.contains(FileState::CLOSED) {
+            log::warn!("Attempting to close an already-closed pty");
+        }
+
+        // drop the event listener handles so that we stop receiving new events
+        if let Some(h) = self.input_event_handle.take() {
+            h.stop_listening()
+        }
+        if let Some(h) = self.output_event_handle.take() {
+            h.stop_listening()
+        }
+
+        if let Some(reader_handle) = self.reader_handle.take() {
+            self.input
+                .borrow_mut()
+                .remove_reader(reader_handle, cb_queue);
+        }
+        if let Some(writer_handle) = self.writer_handle.take() {
+            self.output
+                .borrow_mut()
+                .remove_writer(writer_handle, cb_queue);
+        }
+
+        if self.end == PtyEnd::Master {
+            // the pseudoterminal's number can now be reused
+            let mut pair = self.pair.borrow_mut();
+            pair.master_open = false;
+            pair.clear_controlling_session();
+        }
+
+        // set the closed flag and remove the active, readable, and writable flags
+        self.copy_state(
+            FileState::CLOSED | FileState::ACTIVE | FileState::READABLE | FileState::WRITABLE,
+            FileState::CLOSED,
+            cb_queue,
+        );
+
+        Ok(())
+    }
+
+    pub fn readv(
+        &mut self,
+        iovs: &[IoVec],
+        offset: Option<kernel_off_t>,
+        _flags: std::ffi::c_int,
+        mem: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<isize, SyscallError> {
+        // ptys don't support seeking
+        if offset.is_some() {
+            return Err(Errno::ESPIPE.into());
+        }
+
+        if !self.mode.contains(FileMode::READ) {
+            return Err(Errno::EBADF.into());
+        }
+
+        let num_bytes_to_read: libc::size_t = iovs.iter().map(|x| x.len).sum();
+
+        let mut writer = IoVecWriter::new(iovs, mem);
+        let (num_copied, _num_removed_from_buf) =
+            self.input.borrow_mut().read(&mut writer, cb_queue)?;
+
+        if num_copied == 0 && num_bytes_to_read != 0 {
+            if self.input.borrow().num_writers() > 0 {
+                return Err(Errno::EWOULDBLOCK.into());
+            }
+
+            // The other end has been closed. Linux returns EIO when reading from a master with no
+            // open slaves, and end-of-file when reading from a slave whose master was closed.
+            return match self.end {
+                PtyEnd::Master => Err(Errno::EIO.into()),
+                PtyEnd::Slave => Ok(0),
+            };
+        }
+
+        Ok(num_copied.try_into().unwrap())
+    }
+
+    pub fn writev(
+        &mut self,
+        iovs: &[IoVec],
+        offset: Option<kernel_off_t>,
+        _flags: std::ffi::c_int,
+        mem: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<isize, SyscallError> {
+        // ptys don't support seeking
+        if offset.is_some() {
+            return Err(Errno::ESPIPE.into());
+        }
+
+        if !self.mode.contains(FileMode::WRITE) {
+            return Err(Errno::EBADF.into());
+        }
+
+        let mut output = self.output.borrow_mut();
+
+        // the other end has been closed
+        if output.num_readers() == 0 {
+            return Err(Errno::EIO.into());
+        }
+
+        let len: libc::size_t = iovs.iter().map(|x| x.len).sum();
+        let reader = IoVecReader::new(iovs, mem);
+        let num_copied = output.write_stream(reader, len, cb_queue)?;
+
+        Ok(num_copied.try_into().unwrap())
+    }
+
+    /// Handles the ioctl requests that only involve the pseudoterminal itself. Requests involving
+    /// sessions, process groups, or new descriptors are handled by the ioctl syscall handler.
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+        memory_manager: &mut MemoryManager,
+    ) -> SyscallResult {
+        match request {
+            IoctlRequest::TCGETS => {
+                let termios = self.pair.borrow().termios;
+                memory_manager.write(arg_ptr.cast::<termios>(), &termios)?;
+                Ok(0.into())
+            }
+            // Since there's no line discipline, there's no queued input or output to wait for or
+            // flush.
+            IoctlRequest::TCSETS | IoctlRequest::TCSETSW | IoctlRequest::TCSETSF => {
+                let termios = memory_manager.read(arg_ptr.cast::<termios>())?;
+                self.pair.borrow_mut().termios = termios;
+                Ok(0.into())
+            }
+            IoctlRequest::TIOCGWINSZ => {
+                let winsize = self.pair.borrow().winsize;
+                memory_manager.write(arg_ptr.cast::<winsize>(), &winsize)?;
+                Ok(0.into())
+            }
+            IoctlRequest::FIONREAD => {
+                let input = self.input.borrow();
+                let num_bytes = input.max_len() - input.space_available();
+                let num_bytes = std::ffi::c_int::try_from(num_bytes).unwrap();
+                memory_manager.write(arg_ptr.cast::<std::ffi::c_int>(), &num_bytes)?;
+                Ok(0.into())
+            }
+            IoctlRequest::TIOCGPTN if self.end == PtyEnd::Master => {
+                let index = self.pair.borrow().index;
+                memory_manager.write(arg_ptr.cast::<u32>(), &index)?;
+                Ok(0.into())
+            }
+            IoctlRequest::TIOCSPTLCK if self.end == PtyEnd::Master => {
+                let lock = memory_manager.read(arg_ptr.cast::<std::ffi::c_int>())?;
+                self.pair.borrow_mut().locked = lock != 0;
+                Ok(0.into())
+            }
+            IoctlRequest::TIOCGPTLCK if self.end == PtyEnd::Master => {
+                let lock = std::ffi::c_int::from(self.pair.borrow().locked);
+                memory_manager.write(arg_ptr.cast::<std::ffi::c_int>(), &lock)?;
+                Ok(0.into())
+            }
+            _ => {
+                warn_once_then_debug!(
+                    "(LOG_ONCE) We do not yet handle ioctl request {request:?} on ptys"
+                );
+                Err(Errno::ENOTTY.into())
+            }
+        }
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring: FileState,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
+    ) -> Handle<(FileState, FileState)> {
+        self.event_source
+            .add_listener(monitoring, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    /// Align the readable state to the state of the buffer that this end reads from.
+    fn align_state_to_input(&mut self, buffer_state: BufferState, cb_queue: &mut CallbackQueue) {
+        // if the pty is already closed, do nothing
+        if self.state.contains(FileState::CLOSED) {
+            return;
+        }
+
+        // readable if there's data or the other end was closed
+        let mut file_state = FileState::empty();
+        if buffer_state.intersects(BufferState::READABLE | BufferState::NO_WRITERS) {
+            file_state.insert(FileState::READABLE);
+        }
+
+        self.copy_state(FileState::READABLE, file_state, cb_queue);
+    }
+
+    /// Align the writable state to the state of the buffer that this end writes to.
+    fn align_state_to_output(&mut self, buffer_state: BufferState, cb_queue: &mut CallbackQueue) {
+        // if the pty is already closed, do nothing
+        if self.state.contains(FileState::CLOSED) {
+            return;
+        }
+
+        // writable if there's space or the other end was closed
+        let mut file_state = FileState::empty();
+        if buffer_state.intersects(BufferState::WRITABLE | BufferState::NO_READERS) {
+            file_state.insert(FileState::WRITABLE);
+        }
+
+        self.copy_state(FileState::WRITABLE, file_state, cb_queue);
+    }
+
+    fn copy_state(&mut self, mask: FileState, state: FileState, cb_queue: &mut CallbackQueue) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, cb_queue);
+    }
+
+    fn handle_state_change(&mut self, old_state: FileState, cb_queue: &mut CallbackQueue) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, cb_queue);
+    }
+}
+
+/// A host's pseudoterminals, indexed by their number in `/dev/pts`.
+#[derive(Default)]
+pub struct PtyTable {
+    ptys: BTreeMap<u32, Weak<AtomicRefCell<PtyPair>>>,
+}
+
+impl PtyTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The lowest number that isn't used by a pseudoterminal whose master is still open.
+    pub fn next_index(&self) -> u32 {
+        (0..).find(|index| self.get(*index).is_none()).unwrap()
+    }
+
+    /// Add the new pseudoterminal `pair`, replacing any closed pseudoterminal with the same
+    /// number.
+    pub fn insert(&mut self, pair: &Arc<AtomicRefCell<PtyPair>>) {
+        let index = pair.borrow().index;
+        self.ptys.insert(index, Arc::downgrade(pair));
+    }
+
+    /// The pseudoterminal numbered `index`, if its master is still open.
+    pub fn get(&self, index: u32) -> Option<Arc<AtomicRefCell<PtyPair>>> {
+        self.ptys
+            .get(&index)
+            .and_then(Weak::upgrade)
+            .filter(|pair| pair.borrow().master_open)
+    }
+
+    /// The controlling terminal of `session`, if it has one.
+    pub fn controlling_terminal(&self, session: ProcessId) -> Option<Arc<AtomicRefCell<PtyPair>>> {
+        self.ptys
+            .values()
+            .filter_map(Weak::upgrade)
+            .find(|pair| pair.borrow().session == Some(session))
+    }
+}
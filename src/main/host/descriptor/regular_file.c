@@ -7,6 +7,8 @@
 
 #include <errno.h>
 #include <fcntl.h>
+#include <linux/falloc.h>
+#include <linux/magic.h>
 #include <poll.h>
 #include <stdbool.h>
 #include <stdio.h>
@@ -15,6 +17,7 @@
 #include <sys/file.h>
 #include <sys/ioctl.h>
 #include <sys/stat.h>
+#include <sys/statfs.h>
 #include <sys/syscall.h>
 #include <sys/time.h>
 #include <sys/types.h>
@@ -32,6 +35,9 @@
 
 #define OSFILE_INVALID -1
 
+/* Where the host's tmpfs is mounted, if it has one. */
+#define TMPFS_MOUNT_POINT "/dev/shm"
+
 const int SHADOW_FLAG_MASK = O_CLOEXEC;
 
 typedef enum _FileType FileType;
@@ -61,6 +67,9 @@ struct _RegularFile {
             mode_t modeAtOpen;
             /* The path of the file when it was opened. */
             char* absPathAtOpen;
+            /* Whether the file is a regular file in the host's tmpfs, whose size counts against
+             * the tmpfs's size limit. */
+            bool inTmpfs;
         } osfile;
         struct {
             off_t cursor;
@@ -122,11 +131,108 @@ static inline bool _fd_isValid(int fd) { return fd >= 0; }
 
 int regularfile_getOSBackedFD(RegularFile* file) { return _regularfile_getOSBackedFD(file); }
 
+/* Returns true if the OS path is in the host's tmpfs directory. */
+static bool _regularfile_isTmpfsPath(const char* ospath) {
+    const Host* host = worker_getCurrentHost();
+    const char* tmpfsDir = host ? host_getTmpfsDir(host) : NULL;
+    if (!tmpfsDir) {
+        return false;
+    }
+
+    size_t len = strlen(tmpfsDir);
+    return !strncmp(ospath, tmpfsDir, len) && (ospath[len] == '\0' || ospath[len] == '/');
+}
+
+/* Start tracking the size of a newly opened file if it's in the host's tmpfs. */
+static void _regularfile_tmpfsOpened(RegularFile* file) {
+    file->osfile.inTmpfs = false;
+    if (!_regularfile_isTmpfsPath(file->osfile.absPathAtOpen)) {
+        return;
+    }
+
+    struct stat st;
+    if (fstat(file->osfile.fd, &st) < 0 || !S_ISREG(st.st_mode)) {
+        return;
+    }
+
+    file->osfile.inTmpfs = true;
+    host_tmpfsFileOpened(worker_getCurrentHost(), st.st_dev, st.st_ino, st.st_size);
+}
+
+/* Returns -ENOSPC if the tmpfs file can't grow to `size` bytes without exceeding the tmpfs's size
+ * limit. Unlike Linux, which writes as much as fits, a write that would exceed the limit fails
+ * without writing anything. */
+static int _regularfile_tmpfsCheckSize(RegularFile* file, const struct stat* st, off_t size) {
+    if (size <= st->st_size) {
+        return 0;
+    }
+
+    if (!host_tmpfsCanResize(worker_getCurrentHost(), st->st_dev, st->st_ino, size)) {
+        trace("RegularFile %p can't grow to %ld bytes, the host's tmpfs is full", file, size);
+        return -ENOSPC;
+    }
+    return 0;
+}
+
+/* If the file is in the host's tmpfs, returns -ENOSPC if writing `len` bytes at `offset` would grow
+ * the tmpfs past its size limit. A negative `offset` means the file's current offset. */
+static int _regularfile_tmpfsCheckWrite(RegularFile* file, off_t offset, size_t len) {
+    if (!file->osfile.inTmpfs || len == 0) {
+        return 0;
+    }
+
+    int fd = file->osfile.fd;
+    struct stat st;
+    if (fstat(fd, &st) < 0) {
+        return -errno;
+    }
+
+    if (offset < 0) {
+        if (fcntl(fd, F_GETFL) & O_APPEND) {
+            offset = st.st_size;
+        } else if ((offset = lseek(fd, 0, SEEK_CUR)) < 0) {
+            return -errno;
+        }
+    }
+
+    return _regularfile_tmpfsCheckSize(file, &st, offset + (off_t)len);
+}
+
+/* Update the tracked size of a tmpfs file after it may have been resized. */
+static void _regularfile_tmpfsResized(RegularFile* file) {
+    struct stat st;
+    if (!file->osfile.inTmpfs || fstat(file->osfile.fd, &st) < 0) {
+        return;
+    }
+    host_tmpfsFileResized(worker_getCurrentHost(), st.st_dev, st.st_ino, st.st_size);
+}
+
+static void _regularfile_tmpfsClosed(RegularFile* file) {
+    const Host* host = worker_getCurrentHost();
+    struct stat st;
+    if (!file->osfile.inTmpfs || !host || fstat(file->osfile.fd, &st) < 0) {
+        return;
+    }
+    host_tmpfsFileClosed(host, st.st_dev, st.st_ino, st.st_nlink);
+}
+
+/* Get the file at the pathname before it's unlinked or replaced, so that the host's tmpfs can
+ * free its memory. Returns false if the host doesn't have a tmpfs or there's no regular file at
+ * the path. */
+static bool _regularfile_tmpfsStatBeforeUnlink(int osFd, const char* pathname, struct stat* st) {
+    const Host* host = worker_getCurrentHost();
+    if (!host || !host_getTmpfsDir(host)) {
+        return false;
+    }
+    return fstatat(osFd, pathname, st, AT_SYMLINK_NOFOLLOW) == 0 && S_ISREG(st->st_mode);
+}
+
 static void _regularfile_closeHelper(RegularFile* file) {
     if(file && file->type != FILE_TYPE_IN_MEMORY) {
         if (file && _fd_isValid(file->osfile.fd)) {
             trace("On file %p, closing os-backed file %i", file, _regularfile_getOSBackedFD(file));
 
+            _regularfile_tmpfsClosed(file);
             close(file->osfile.fd);
             file->osfile.fd = OSFILE_INVALID;
 
@@ -181,6 +287,7 @@ RegularFile* regularfile_new() {
     legacyfile_init(&(file->super), DT_FILE, &_fileFunctions);
     MAGIC_INIT(file);
     file->osfile.fd = OSFILE_INVALID; // negative means uninitialized (0 is a valid fd)
+    file->osfile.inTmpfs = false;
 
     worker_count_allocation(RegularFile);
     return file;
@@ -217,6 +324,197 @@ static char* _regularfile_getAbsolutePath(RegularFile* dir, const char* pathname
     return abspath;
 }
 
+/* Move an absolute path in /dev/shm into the host's tmpfs directory, if it has one, or otherwise
+ * into the current process's root directory, if it has one. Takes ownership of `abspath`. */
+static char* _regularfile_getRootedPath(char* abspath) {
+    utility_debugAssert(abspath && abspath[0] == '/');
+
+    /* Like a mount, the host's tmpfs also replaces /dev/shm within the process's root directory. */
+    const Host* host = worker_getCurrentHost();
+    const char* tmpfsDir = host ? host_getTmpfsDir(host) : NULL;
+    size_t mountLen = strlen(TMPFS_MOUNT_POINT);
+    if (tmpfsDir && !strncmp(abspath, TMPFS_MOUNT_POINT, mountLen) &&
+        (abspath[mountLen] == '\0' || abspath[mountLen] == '/')) {
+        char* path = NULL;
+        if (asprintf(&path, "%s%s", tmpfsDir, abspath + mountLen) < 0) {
+            utility_panic(
+                "asprintf could not allocate a buffer, error %i: %s", errno, strerror(errno));
+            abort();
+        }
+        free(abspath);
+        return path;
+    }
+
+    const Process* proc = worker_getCurrentProcess();
+    const char* root = proc ? process_getRootDir(proc) : NULL;
+    if (!root) {
+        return abspath;
+    }
+
+    char* path = _regularfile_getConcatStr(root, '/', abspath + 1);
+    free(abspath);
+    return path;
+}
+
+/* Get the path to give to the OS for a pathname used with a directory fd. Absolute pathnames and
+ * pathnames relative to the working directory are converted to absolute paths, and absolute
+ * pathnames are moved into the process's root directory. Returns `pathname` if it can be used
+ * unchanged with the directory fd. */
+static const char* _regularfile_getOSPath(int* osFd, const char* pathname,
+                                          const char* workingDir) {
+    if (*osFd != AT_FDCWD && pathname[0] != '/') {
+        return pathname;
+    }
+
+    *osFd = -1;
+    char* abspath = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
+    if (pathname[0] == '/') {
+        abspath = _regularfile_getRootedPath(abspath);
+    }
+    return abspath;
+}
+
+/* Get the current process if its emulated credentials must be checked, or NULL if the native
+ * permission checks, which use Shadow's own credentials, are the same. */
+static const Process* _regularfile_getCredentialsProcess() {
+    const Process* proc = worker_getCurrentProcess();
+    return (proc && !process_hasNativeCredentials(proc)) ? proc : NULL;
+}
+
+/* Get the directory that contains the last component of a pathname, which is relative to the same
+ * directory as the pathname. The caller must free the returned string. */
+static char* _regularfile_getParentPath(const char* pathname) {
+    char* parent = strdup(pathname);
+
+    /* ignore trailing slashes, as in "dir/" */
+    size_t len = strlen(parent);
+    while (len > 1 && parent[len - 1] == '/') {
+        parent[--len] = '\0';
+    }
+
+    char* slash = strrchr(parent, '/');
+    if (!slash) {
+        free(parent);
+        return strdup(".");
+    }
+
+    if (slash == parent) {
+        slash[1] = '\0';
+    } else {
+        slash[0] = '\0';
+    }
+    return parent;
+}
+
+/* The following functions apply the permission checks of the current process's emulated
+ * credentials before a native syscall, which then applies the checks of Shadow's own credentials.
+ * Errors other than permission errors are left for the native syscall to report. Each returns 0
+ * or a negative errno. */
+
+/* Check the permission to create a directory entry at a pathname. */
+static int _regularfile_checkCreateAt(int osFd, const char* pathname) {
+    const Process* proc = _regularfile_getCredentialsProcess();
+    if (!proc) {
+        return 0;
+    }
+
+    /* the native syscall fails with EEXIST */
+    struct stat st;
+    if (fstatat(osFd, pathname, &st, AT_SYMLINK_NOFOLLOW) == 0) {
+        return 0;
+    }
+
+    char* parent = _regularfile_getParentPath(pathname);
+    struct stat dirSt;
+    int rv = fstatat(osFd, parent, &dirSt, 0);
+    free(parent);
+    if (rv < 0) {
+        return 0;
+    }
+
+    return process_checkFileAccess(
+        proc, dirSt.st_uid, dirSt.st_gid, dirSt.st_mode, W_OK | X_OK, false);
+}
+
+/* Check the permission to remove the directory entry at a pathname. */
+static int _regularfile_checkDeleteAt(int osFd, const char* pathname) {
+    const Process* proc = _regularfile_getCredentialsProcess();
+    if (!proc) {
+        return 0;
+    }
+
+    struct stat st;
+    if (fstatat(osFd, pathname, &st, AT_SYMLINK_NOFOLLOW) < 0) {
+        return 0;
+    }
+
+    char* parent = _regularfile_getParentPath(pathname);
+    struct stat dirSt;
+    int rv = fstatat(osFd, parent, &dirSt, 0);
+    free(parent);
+    if (rv < 0) {
+        return 0;
+    }
+
+    return process_checkFileDelete(proc, dirSt.st_uid, dirSt.st_gid, dirSt.st_mode, st.st_uid);
+}
+
+/* Check the access(2) mode for a pathname, using the real IDs unless `flags` has AT_EACCESS. */
+static int _regularfile_checkAccessAt(int osFd, const char* pathname, int mode, int flags) {
+    const Process* proc = _regularfile_getCredentialsProcess();
+    if (!proc || mode == F_OK) {
+        return 0;
+    }
+
+    struct stat st;
+    if (fstatat(osFd, pathname, &st, flags & AT_SYMLINK_NOFOLLOW) < 0) {
+        return 0;
+    }
+
+    return process_checkFileAccess(
+        proc, st.st_uid, st.st_gid, st.st_mode, mode, !(flags & AT_EACCESS));
+}
+
+/* Check the permission to open an absolute path with the open(2) flags. */
+static int _regularfile_checkOpen(const char* abspath, int flags) {
+    const Process* proc = _regularfile_getCredentialsProcess();
+    if (!proc || (flags & O_PATH)) {
+        return 0;
+    }
+
+    struct stat st;
+    int rv = (flags & O_NOFOLLOW) ? lstat(abspath, &st) : stat(abspath, &st);
+    if (rv < 0) {
+        /* creating a file requires permission to create it in its directory */
+        if (errno == ENOENT && (flags & O_CREAT)) {
+            return _regularfile_checkCreateAt(AT_FDCWD, abspath);
+        }
+        return 0;
+    }
+
+    /* the native open fails with ELOOP or EEXIST */
+    if (S_ISLNK(st.st_mode) || ((flags & O_CREAT) && (flags & O_EXCL))) {
+        return 0;
+    }
+
+    int mask = 0;
+    if ((flags & O_TMPFILE) == O_TMPFILE) {
+        /* an unnamed file is created in the directory */
+        mask = W_OK | X_OK;
+    } else if ((flags & O_ACCMODE) == O_RDONLY) {
+        mask = R_OK;
+    } else if ((flags & O_ACCMODE) == O_WRONLY) {
+        mask = W_OK;
+    } else {
+        mask = R_OK | W_OK;
+    }
+    if (flags & O_TRUNC) {
+        mask |= W_OK;
+    }
+
+    return process_checkFileAccess(proc, st.st_uid, st.st_gid, st.st_mode, mask, false);
+}
+
 #ifdef DEBUG
 #define CHECK_FLAG(flag)                                                                           \
     if (flags & flag) {                                                                            \
@@ -295,6 +593,8 @@ int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname
     /* The default case is a regular file. We do this first so that we have
      * an absolute path to compare for special files. */
     char* abspath = _regularfile_getAbsolutePath(dir, pathname, workingDir);
+    const char* procSysKernelContent = NULL;
+    char* procNetContent = NULL;
 
     /* Handle special files. */
     if (utility_isRandomPath(abspath)) {
@@ -321,8 +621,29 @@ int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname
         char content[] = "0\n";
         // size - 1 to strip the \0;
         return _regularfile_initRoInMemoryFile(file, flags, mode, sizeof(content) - 1, content);
+    } else if ((procSysKernelContent =
+                    host_getProcSysKernelFile(worker_getCurrentHost(), abspath)) != NULL) {
+        // system identification files, which must agree with uname()
+        if (abspath) {
+            free(abspath);
+        }
+        return _regularfile_initRoInMemoryFile(
+            file, flags, mode, strlen(procSysKernelContent), procSysKernelContent);
+    } else if ((procNetContent = host_allocProcNetFile(worker_getCurrentHost(), abspath)) !=
+               NULL) {
+        // network statistics, which reflect the host's current state
+        if (abspath) {
+            free(abspath);
+        }
+        int rv = _regularfile_initRoInMemoryFile(
+            file, flags, mode, strlen(procNetContent), procNetContent);
+        host_freeProcNetFile(procNetContent);
+        return rv;
     } else {
         file->type = FILE_TYPE_REGULAR;
+        if (pathname[0] == '/') {
+            abspath = _regularfile_getRootedPath(abspath);
+        }
     }
 
     int originalFlags = flags;
@@ -334,11 +655,20 @@ int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname
     // we should always use O_CLOEXEC for files opened in shadow
     flags |= O_CLOEXEC;
 
+    int errcode = _regularfile_checkOpen(abspath, flags);
+    if (errcode < 0) {
+        trace("RegularFile %p opening path '%s' is not permitted: %s", file, abspath,
+              strerror(-errcode));
+        free(abspath);
+        file->type = FILE_TYPE_NOTSET;
+        return errcode;
+    }
+
     // TODO: we should open the os-backed file in non-blocking mode even if a
     // non-block is not requested, and then properly handle the io by, e.g.,
     // epolling on all such files with a shadow support thread.
     int osfd = open(abspath, flags, mode);
-    int errcode = errno;
+    errcode = errno;
 
     if (osfd < 0) {
         trace("RegularFile %p opening path '%s' returned %i: %s", file, abspath, osfd,
@@ -355,6 +685,7 @@ int regularfile_openat(RegularFile* file, RegularFile* dir, const char* pathname
     file->osfile.absPathAtOpen = abspath;
     file->osfile.flagsAtOpen = flags;
     file->osfile.modeAtOpen = mode;
+    _regularfile_tmpfsOpened(file);
 
     trace("RegularFile %p opened os-backed file %i at absolute path %s", file,
           _regularfile_getOSBackedFD(file), file->osfile.absPathAtOpen);
@@ -530,6 +861,14 @@ ssize_t regularfile_preadv2(RegularFile* file, const Host* host, const struct io
 }
 #endif
 
+static size_t _regularfile_iovLen(const struct iovec* iov, int iovcnt) {
+    size_t len = 0;
+    for (int i = 0; i < iovcnt; i++) {
+        len += iov[i].iov_len;
+    }
+    return len;
+}
+
 ssize_t regularfile_write(RegularFile* file, const void* buf, size_t bufSize) {
     MAGIC_ASSERT(file);
 
@@ -544,10 +883,20 @@ ssize_t regularfile_write(RegularFile* file, const void* buf, size_t bufSize) {
     trace("RegularFile %p will write %zu bytes to os-backed file %i at path '%s'", file, bufSize,
           _regularfile_getOSBackedFD(file), file->osfile.absPathAtOpen);
 
+    int rv = _regularfile_tmpfsCheckWrite(file, -1, bufSize);
+    if (rv < 0) {
+        return rv;
+    }
+
     /* TODO: this may block the shadow thread until we properly handle
      * os-backed files in non-blocking mode. */
     ssize_t result = write(_regularfile_getOSBackedFD(file), buf, bufSize);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    _regularfile_tmpfsResized(file);
+    return result;
 }
 
 ssize_t regularfile_pwrite(RegularFile* file, const void* buf, size_t bufSize, off_t offset) {
@@ -564,10 +913,20 @@ ssize_t regularfile_pwrite(RegularFile* file, const void* buf, size_t bufSize, o
     trace("RegularFile %p will pwrite %zu bytes to os-backed file %i offset %ld at path '%s'", file,
           bufSize, _regularfile_getOSBackedFD(file), offset, file->osfile.absPathAtOpen);
 
+    int rv = _regularfile_tmpfsCheckWrite(file, offset, bufSize);
+    if (rv < 0) {
+        return rv;
+    }
+
     /* TODO: this may block the shadow thread until we properly handle
      * os-backed files in non-blocking mode. */
     ssize_t result = pwrite(_regularfile_getOSBackedFD(file), buf, bufSize, offset);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    _regularfile_tmpfsResized(file);
+    return result;
 }
 
 ssize_t regularfile_pwritev(RegularFile* file, const struct iovec* iov, int iovcnt, off_t offset) {
@@ -584,10 +943,20 @@ ssize_t regularfile_pwritev(RegularFile* file, const struct iovec* iov, int iovc
     trace("RegularFile %p will pwritev %d vector items from os-backed file %i at path '%s'", file,
           iovcnt, _regularfile_getOSBackedFD(file), file->osfile.absPathAtOpen);
 
+    int rv = _regularfile_tmpfsCheckWrite(file, offset, _regularfile_iovLen(iov, iovcnt));
+    if (rv < 0) {
+        return rv;
+    }
+
     /* TODO: this may block the shadow thread until we properly handle
      * os-backed files in non-blocking mode. */
     ssize_t result = pwritev(_regularfile_getOSBackedFD(file), iov, iovcnt, offset);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    _regularfile_tmpfsResized(file);
+    return result;
 }
 
 #ifdef SYS_pwritev2
@@ -606,11 +975,21 @@ ssize_t regularfile_pwritev2(RegularFile* file, const struct iovec* iov, int iov
     trace("RegularFile %p will pwritev2 %d vector items from os-backed file %i at path '%s'", file,
           iovcnt, _regularfile_getOSBackedFD(file), file->osfile.absPathAtOpen);
 
+    int rv = _regularfile_tmpfsCheckWrite(file, offset, _regularfile_iovLen(iov, iovcnt));
+    if (rv < 0) {
+        return rv;
+    }
+
     /* TODO: this may block the shadow thread until we properly handle
      * os-backed files in non-blocking mode. */
     ssize_t result =
         pwritev2(_regularfile_getOSBackedFD(file), iov, iovcnt, offset, flags);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    _regularfile_tmpfsResized(file);
+    return result;
 }
 #endif
 
@@ -637,7 +1016,25 @@ int regularfile_fstatfs(RegularFile* file, struct statfs* statbuf) {
     trace("RegularFile %p fstatfs os-backed file %i", file, _regularfile_getOSBackedFD(file));
 
     int result = fstatfs(_regularfile_getOSBackedFD(file), statbuf);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    /* Describe the host's tmpfs rather than the file system that stores its files. */
+    if (file->osfile.inTmpfs) {
+        const Host* host = worker_getCurrentHost();
+        uint64_t sizeLimit = host_getTmpfsSizeLimit(host);
+        uint64_t usage = host_getTmpfsUsage(host);
+        uint64_t available = (usage < sizeLimit) ? sizeLimit - usage : 0;
+        statbuf->f_type = TMPFS_MAGIC;
+        statbuf->f_bsize = 4096;
+        statbuf->f_frsize = 4096;
+        statbuf->f_blocks = sizeLimit / 4096;
+        statbuf->f_bfree = available / 4096;
+        statbuf->f_bavail = available / 4096;
+    }
+
+    return result;
 }
 
 int regularfile_fsync(RegularFile* file) {
@@ -688,8 +1085,24 @@ int regularfile_ftruncate(RegularFile* file, off_t length) {
 
     trace("RegularFile %p ftruncate os-backed file %i", file, _regularfile_getOSBackedFD(file));
 
+    if (file->osfile.inTmpfs) {
+        struct stat st;
+        if (fstat(file->osfile.fd, &st) < 0) {
+            return -errno;
+        }
+        int rv = _regularfile_tmpfsCheckSize(file, &st, length);
+        if (rv < 0) {
+            return rv;
+        }
+    }
+
     int result = ftruncate(_regularfile_getOSBackedFD(file), length);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    _regularfile_tmpfsResized(file);
+    return result;
 }
 
 int regularfile_fallocate(RegularFile* file, int mode, off_t offset, off_t length) {
@@ -701,8 +1114,24 @@ int regularfile_fallocate(RegularFile* file, int mode, off_t offset, off_t lengt
 
     trace("RegularFile %p fallocate os-backed file %i", file, _regularfile_getOSBackedFD(file));
 
+    if (file->osfile.inTmpfs && !(mode & FALLOC_FL_KEEP_SIZE)) {
+        struct stat st;
+        if (fstat(file->osfile.fd, &st) < 0) {
+            return -errno;
+        }
+        int rv = _regularfile_tmpfsCheckSize(file, &st, offset + length);
+        if (rv < 0) {
+            return rv;
+        }
+    }
+
     int result = fallocate(_regularfile_getOSBackedFD(file), mode, offset, length);
-    return (result < 0) ? -errno : result;
+    if (result < 0) {
+        return -errno;
+    }
+
+    _regularfile_tmpfsResized(file);
+    return result;
 }
 
 int regularfile_fadvise(RegularFile* file, off_t offset, off_t len, int advice) {
@@ -942,10 +1371,7 @@ int regularfile_fstatat(RegularFile* dir, const char* pathname, struct stat* sta
 
     trace("RegularFile %p fstatat os-backed file %i, flags %d", dir, osFd, flags);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     int result = fstatat(osFd, pathnameTmp, statbuf, flags);
 
@@ -963,10 +1389,7 @@ int regularfile_fchownat(RegularFile* dir, const char* pathname, uid_t owner, gi
 
     trace("RegularFile %p fchownat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     int result = fchownat(osFd, pathnameTmp, owner, group, flags);
 
@@ -984,10 +1407,7 @@ int regularfile_fchmodat(RegularFile* dir, const char* pathname, mode_t mode, in
 
     trace("RegularFile %p fchmodat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     int result = fchmodat(osFd, pathnameTmp, mode, flags);
 
@@ -1005,10 +1425,7 @@ int regularfile_futimesat(RegularFile* dir, const char* pathname, const struct t
 
     trace("RegularFile %p futimesat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     int result = futimesat(osFd, pathnameTmp, times);
 
@@ -1026,10 +1443,7 @@ int regularfile_utimensat(RegularFile* dir, const char* pathname, const struct t
 
     trace("RegularFile %p utimesat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     int result = utimensat(osFd, pathnameTmp, times, flags);
 
@@ -1047,18 +1461,19 @@ int regularfile_faccessat(RegularFile* dir, const char* pathname, int mode, int
 
     trace("RegularFile %p faccessat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
-    int result = faccessat(osFd, pathnameTmp, mode, flags);
+    int result = _regularfile_checkAccessAt(osFd, pathnameTmp, mode, flags);
+    if (result == 0) {
+        result = faccessat(osFd, pathnameTmp, mode, flags);
+        result = (result < 0) ? -errno : result;
+    }
 
     if (pathnameTmp != pathname) {
         free((char*)pathnameTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 int regularfile_mkdirat(RegularFile* dir, const char* pathname, mode_t mode,
@@ -1068,18 +1483,19 @@ int regularfile_mkdirat(RegularFile* dir, const char* pathname, mode_t mode,
 
     trace("RegularFile %p mkdirat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
-    int result = mkdirat(osFd, pathnameTmp, mode);
+    int result = _regularfile_checkCreateAt(osFd, pathnameTmp);
+    if (result == 0) {
+        result = mkdirat(osFd, pathnameTmp, mode);
+        result = (result < 0) ? -errno : result;
+    }
 
     if (pathnameTmp != pathname) {
         free((char*)pathnameTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 int regularfile_mknodat(RegularFile* dir, const char* pathname, mode_t mode, dev_t dev,
@@ -1089,18 +1505,19 @@ int regularfile_mknodat(RegularFile* dir, const char* pathname, mode_t mode, dev
 
     trace("RegularFile %p mknodat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
-    int result = mknodat(osFd, pathnameTmp, mode, dev);
+    int result = _regularfile_checkCreateAt(osFd, pathnameTmp);
+    if (result == 0) {
+        result = mknodat(osFd, pathnameTmp, mode, dev);
+        result = (result < 0) ? -errno : result;
+    }
 
     if (pathnameTmp != pathname) {
         free((char*)pathnameTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 int regularfile_linkat(RegularFile* oldDir, const char* oldPath, RegularFile* newDir,
@@ -1112,16 +1529,14 @@ int regularfile_linkat(RegularFile* oldDir, const char* oldPath, RegularFile* ne
 
     trace("RegularFiles %p, %p linkat os-backed files %i, %i", oldDir, newDir, oldOsFd, newOsFd);
 
-    if (oldOsFd == AT_FDCWD) {
-        oldOsFd = -1;
-        oldPathTmp = _regularfile_getAbsolutePath(NULL, oldPath, workingDir);
-    }
-    if (newOsFd == AT_FDCWD) {
-        newOsFd = -1;
-        newPathTmp = _regularfile_getAbsolutePath(NULL, newPath, workingDir);
-    }
+    oldPathTmp = _regularfile_getOSPath(&oldOsFd, oldPath, workingDir);
+    newPathTmp = _regularfile_getOSPath(&newOsFd, newPath, workingDir);
 
-    int result = linkat(oldOsFd, oldPathTmp, newOsFd, newPathTmp, flags);
+    int result = _regularfile_checkCreateAt(newOsFd, newPathTmp);
+    if (result == 0) {
+        result = linkat(oldOsFd, oldPathTmp, newOsFd, newPathTmp, flags);
+        result = (result < 0) ? -errno : result;
+    }
 
     if (oldPathTmp != oldPath) {
         free((char*)oldPathTmp);
@@ -1130,7 +1545,7 @@ int regularfile_linkat(RegularFile* oldDir, const char* oldPath, RegularFile* ne
         free((char*)newPathTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 int regularfile_unlinkat(RegularFile* dir, const char* pathname, int flags,
@@ -1140,18 +1555,27 @@ int regularfile_unlinkat(RegularFile* dir, const char* pathname, int flags,
 
     trace("RegularFile %p unlinkat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
+
+    int result = _regularfile_checkDeleteAt(osFd, pathnameTmp);
+    if (result == 0) {
+        struct stat st;
+        bool isFile = _regularfile_tmpfsStatBeforeUnlink(osFd, pathnameTmp, &st);
+
+        result = unlinkat(osFd, pathnameTmp, flags);
+        result = (result < 0) ? -errno : result;
 
-    int result = unlinkat(osFd, pathnameTmp, flags);
+        if (result == 0 && isFile) {
+            host_tmpfsFileUnlinked(
+                worker_getCurrentHost(), st.st_dev, st.st_ino, st.st_nlink - 1);
+        }
+    }
 
     if (pathnameTmp != pathname) {
         free((char*)pathnameTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 int regularfile_symlinkat(RegularFile* dir, const char* linkpath, const char* target,
@@ -1161,18 +1585,19 @@ int regularfile_symlinkat(RegularFile* dir, const char* linkpath, const char* ta
 
     trace("RegularFile %p symlinkat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        linkpathTmp = _regularfile_getAbsolutePath(NULL, linkpath, workingDir);
-    }
+    linkpathTmp = _regularfile_getOSPath(&osFd, linkpath, workingDir);
 
-    int result = symlinkat(target, osFd, linkpathTmp);
+    int result = _regularfile_checkCreateAt(osFd, linkpathTmp);
+    if (result == 0) {
+        result = symlinkat(target, osFd, linkpathTmp);
+        result = (result < 0) ? -errno : result;
+    }
 
     if (linkpathTmp != linkpath) {
         free((char*)linkpathTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 ssize_t regularfile_readlinkat(RegularFile* dir, const char* pathname, char* buf, size_t bufsize,
@@ -1182,10 +1607,7 @@ ssize_t regularfile_readlinkat(RegularFile* dir, const char* pathname, char* buf
 
     trace("RegularFile %p readlinkat os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     ssize_t result = readlinkat(osFd, pathnameTmp, buf, bufsize);
 
@@ -1205,17 +1627,32 @@ int regularfile_renameat2(RegularFile* oldDir, const char* oldPath, RegularFile*
 
     trace("RegularFiles %p, %p renameat2 os-backed files %i, %i", oldDir, newDir, oldOsFd, newOsFd);
 
-    if (oldOsFd == AT_FDCWD) {
-        oldOsFd = -1;
-        oldPathTmp = _regularfile_getAbsolutePath(NULL, oldPath, workingDir);
-    }
+    oldPathTmp = _regularfile_getOSPath(&oldOsFd, oldPath, workingDir);
+    newPathTmp = _regularfile_getOSPath(&newOsFd, newPath, workingDir);
 
-    if (newOsFd == AT_FDCWD) {
-        newOsFd = -1;
-        newPathTmp = _regularfile_getAbsolutePath(NULL, newPath, workingDir);
+    /* The old entry is removed, and the new entry is either created or replaced. */
+    int result = _regularfile_checkDeleteAt(oldOsFd, oldPathTmp);
+    if (result == 0) {
+        struct stat st;
+        bool newExists = fstatat(newOsFd, newPathTmp, &st, AT_SYMLINK_NOFOLLOW) == 0;
+        result = newExists ? _regularfile_checkDeleteAt(newOsFd, newPathTmp)
+                           : _regularfile_checkCreateAt(newOsFd, newPathTmp);
     }
 
-    int result = (int)syscall(SYS_renameat2, oldOsFd, oldPathTmp, newOsFd, newPathTmp, flags);
+    if (result == 0) {
+        /* A file that's replaced by the rename loses a link. */
+        struct stat st;
+        bool isFile = !(flags & RENAME_EXCHANGE) &&
+                      _regularfile_tmpfsStatBeforeUnlink(newOsFd, newPathTmp, &st);
+
+        result = (int)syscall(SYS_renameat2, oldOsFd, oldPathTmp, newOsFd, newPathTmp, flags);
+        result = (result < 0) ? -errno : result;
+
+        if (result == 0 && isFile) {
+            host_tmpfsFileUnlinked(
+                worker_getCurrentHost(), st.st_dev, st.st_ino, st.st_nlink - 1);
+        }
+    }
 
     if (oldPathTmp != oldPath) {
         free((char*)oldPathTmp);
@@ -1224,7 +1661,7 @@ int regularfile_renameat2(RegularFile* oldDir, const char* oldPath, RegularFile*
         free((char*)newPathTmp);
     }
 
-    return (result < 0) ? -errno : result;
+    return result;
 }
 
 #ifdef SYS_statx
@@ -1235,10 +1672,7 @@ int regularfile_statx(RegularFile* dir, const char* pathname, int flags, unsigne
 
     trace("RegularFile %p statx os-backed file %i", dir, osFd);
 
-    if (osFd == AT_FDCWD) {
-        osFd = -1;
-        pathnameTmp = _regularfile_getAbsolutePath(NULL, pathname, workingDir);
-    }
+    pathnameTmp = _regularfile_getOSPath(&osFd, pathname, workingDir);
 
     int result = syscall(SYS_statx, osFd, pathnameTmp, flags, mask, statxbuf);
 
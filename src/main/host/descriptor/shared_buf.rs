@@ -1,11 +1,12 @@
 use linux_api::errno::Errno;
 
-use crate::utility::byte_queue::ByteQueue;
+use crate::utility::byte_queue::{ByteQueue, ChunkType};
 use crate::utility::callback_queue::{CallbackQueue, EventSource, Handle};
 
 pub struct SharedBuf {
     queue: ByteQueue,
     max_len: usize,
+    atomic_write_len: usize,
     state: BufferState,
     num_readers: u16,
     num_writers: u16,
@@ -18,6 +19,7 @@ impl SharedBuf {
         Self {
             queue: ByteQueue::new(4096),
             max_len,
+            atomic_write_len: 1,
             state: BufferState::WRITABLE | BufferState::NO_READERS | BufferState::NO_WRITERS,
             num_readers: 0,
             num_writers: 0,
@@ -25,6 +27,17 @@ impl SharedBuf {
         }
     }
 
+    /// Writes of up to `len` bytes will either be written entirely or not at all, and the buffer
+    /// will only be writable when there is at least `len` bytes of space available. Linux pipes
+    /// treat writes of up to `PIPE_BUF` bytes this way.
+    pub fn with_atomic_write_len(mut self, len: usize) -> Self {
+        assert!(len > 0 && len <= self.max_len);
+        self.atomic_write_len = len;
+        self.state
+            .set(BufferState::WRITABLE, self.space_available() >= len);
+        self
+    }
+
     pub fn has_data(&self) -> bool {
         self.queue.has_chunks()
     }
@@ -37,6 +50,25 @@ impl SharedBuf {
         self.max_len - self.queue.num_bytes()
     }
 
+    /// Change the capacity of the buffer. Returns `EBUSY` if the buffer has more than `max_len`
+    /// bytes.
+    pub fn set_max_len(
+        &mut self,
+        max_len: usize,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), Errno> {
+        assert!(max_len >= self.atomic_write_len);
+
+        if max_len < self.queue.num_bytes() {
+            return Err(Errno::EBUSY);
+        }
+
+        self.max_len = max_len;
+        self.refresh_state(cb_queue);
+
+        Ok(())
+    }
+
     /// Register as a reader. The [`ReaderHandle`] must be returned to the buffer later with
     /// [`remove_reader()`](Self::remove_reader).
     pub fn add_reader(&mut self, cb_queue: &mut CallbackQueue) -> ReaderHandle {
@@ -105,6 +137,11 @@ impl SharedBuf {
             return Err(Errno::EAGAIN.into());
         }
 
+        // small writes must be written all at once
+        if len <= self.atomic_write_len && len > self.space_available() {
+            return Err(Errno::EAGAIN.into());
+        }
+
         let written = self
             .queue
             .push_stream(bytes.take(self.space_available().try_into().unwrap()))?;
@@ -136,6 +173,66 @@ impl SharedBuf {
         Ok(())
     }
 
+    /// Move up to `len` bytes to the buffer `dst` without copying them, limited by the space
+    /// available in `dst`. Packets that don't fit are split. Returns the number of bytes moved.
+    pub fn splice_into(
+        &mut self,
+        dst: &mut SharedBuf,
+        len: usize,
+        cb_queue: &mut CallbackQueue,
+    ) -> usize {
+        let mut num_moved = 0;
+
+        loop {
+            let max_len = std::cmp::min(len - num_moved, dst.space_available());
+            if max_len == 0 {
+                break;
+            }
+
+            let Some((bytes, chunk_type)) = self.queue.pop_chunk_split(max_len) else {
+                break;
+            };
+            num_moved += dst.queue.push_chunk(bytes, chunk_type);
+        }
+
+        self.refresh_state(cb_queue);
+        dst.refresh_state(cb_queue);
+
+        num_moved
+    }
+
+    /// Copy up to `len` bytes to the buffer `dst` without removing them from this buffer, limited
+    /// by the space available in `dst`. Packets that don't fit are truncated. Returns the number
+    /// of bytes copied.
+    pub fn copy_into(
+        &self,
+        dst: &mut SharedBuf,
+        len: usize,
+        cb_queue: &mut CallbackQueue,
+    ) -> usize {
+        let mut num_copied = 0;
+
+        for (bytes, chunk_type) in self.queue.chunks() {
+            let max_len = std::cmp::min(len - num_copied, dst.space_available());
+            if max_len == 0 {
+                break;
+            }
+
+            let bytes = &bytes[..std::cmp::min(bytes.len(), max_len)];
+            match chunk_type {
+                ChunkType::Stream => dst.queue.push_stream(bytes).unwrap(),
+                ChunkType::Packet => dst
+                    .queue
+                    .push_chunk(bytes::Bytes::copy_from_slice(bytes), ChunkType::Packet),
+            };
+            num_copied += bytes.len();
+        }
+
+        dst.refresh_state(cb_queue);
+
+        num_copied
+    }
+
     pub fn add_listener(
         &mut self,
         monitoring: BufferState,
@@ -167,7 +264,10 @@ impl SharedBuf {
         let mut new_state = BufferState::empty();
 
         new_state.set(BufferState::READABLE, self.has_data());
-        new_state.set(BufferState::WRITABLE, self.space_available() > 0);
+        new_state.set(
+            BufferState::WRITABLE,
+            self.space_available() >= self.atomic_write_len,
+        );
         new_state.set(BufferState::NO_READERS, self.num_readers() == 0);
         new_state.set(BufferState::NO_WRITERS, self.num_writers() == 0);
 
@@ -418,18 +418,19 @@ gsize _legacysocket_getOutputBufferSpaceIncludingTCP(LegacySocket* socket) {
     return space;
 }
 
-gboolean legacysocket_addToOutputBuffer(LegacySocket* socket, CompatSocket compatSocket,
+gboolean legacysocket_addToOutputBuffer(LegacySocket* socket, const InetSocket* inetSocket,
                                         const Host* host, Packet* packet) {
     MAGIC_ASSERT(socket);
 
     /* check if the packet fits */
     gsize length = packet_getPayloadSize(packet);
     if(length > legacysocket_getOutputBufferSpace(socket)) {
+        inetsocket_drop(inetSocket);
         return FALSE;
     }
 
     /* add to our queue */
-    if(packet_getPriority(packet) == 0) {
+    if (packet_getPriority(packet) == PACKET_PRIORITY_CONTROL) {
         /* control packets get sent first */
         g_queue_push_tail(socket->outputControlBuffer, packet);
     } else {
@@ -454,7 +455,7 @@ gboolean legacysocket_addToOutputBuffer(LegacySocket* socket, CompatSocket compa
 
     /* tell the interface to include us when sending out to the network */
     in_addr_t ip = packet_getSourceIP(packet);
-    socket_wants_to_send_with_global_cb_queue(host, compatSocket, ip);
+    socket_wants_to_send_with_global_cb_queue(host, inetSocket, ip);
 
     return TRUE;
 }
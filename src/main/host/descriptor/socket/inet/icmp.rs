@@ -0,0 +1,1028 @@
+//! Ping sockets, which applications create with `socket(AF_INET, SOCK_DGRAM, IPPROTO_ICMP)` to send
+//! ICMP echo requests and receive the echo replies without needing a raw socket (see icmp(7)).
+//!
+//! Like Linux, the socket's "port" is the identifier of its echo messages. The identifier is
+//! chosen when the socket is bound (implicitly when sending if it wasn't bound), and it replaces
+//! the identifier of every echo request that the application sends. Echo requests are answered by
+//! the destination host's network interface, not by a socket.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use bytes::BytesMut;
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use nix::sys::socket::{AddressFamily, MsgFlags, Shutdown, SockaddrIn};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::core::worker::Worker;
+use crate::cshadow as c;
+use crate::host::descriptor::socket::inet::udp::MessageBuffer;
+use crate::host::descriptor::socket::inet::{self, InetSocket, Ipv6Options};
+use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags};
+use crate::host::descriptor::{
+    File, FileMode, FileState, FileStatus, OpenFile, Socket, StateEventSource, StateListenerFilter,
+    SyscallResult,
+};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::network::interface::FifoPacketPriority;
+use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
+use crate::host::network::proc_net::{ProcNetEntry, SocketState};
+use crate::host::syscall::io::{write_partial, IoVec, IoVecReader, IoVecWriter};
+use crate::host::syscall_types::SyscallError;
+use crate::network::packet::{IcmpEchoHeader, IcmpEchoKind, PacketRc, PacketStatus};
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrStorage;
+use crate::utility::{HostTreePointer, ObjectCounter};
+
+/// The length of the ICMP echo header that the application writes and reads.
+const ECHO_HEADER_LEN: usize = 8;
+
+/// Maximum size of an ICMP message (header and data) that we are allowed to send out over the
+/// network.
+// 65,535 (2^16 - 1) - 20 (ip header)
+const CONFIG_ICMP_MESSAGE_MAX_SIZE: usize = 65515;
+
+/// The ICMP type of an echo request.
+const ICMP_ECHO: u8 = 8;
+/// The ICMP type of an echo reply.
+const ICMP_ECHOREPLY: u8 = 0;
+
+pub struct IcmpSocket {
+    event_source: StateEventSource,
+    status: FileStatus,
+    state: FileState,
+    shutdown_status: ShutdownFlags,
+    send_buffer: MessageBuffer<MessageSendHeader>,
+    recv_buffer: MessageBuffer<MessageRecvHeader>,
+    peer_addr: Option<Ipv4Addr>,
+    /// The bound address, whose port is the identifier of the socket's echo messages.
+    bound_addr: Option<SocketAddrV4>,
+    association: Option<AssociationHandle>,
+    /// The receive time of the last packet returned to the managed process during a call to
+    /// `recvmsg()`. Used for `SIOCGSTAMP`.
+    recv_time_of_last_read_packet: Option<EmulatedTime>,
+    /// The number of received packets that were dropped because the receive buffer was full.
+    drops: u32,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+    _counter: ObjectCounter,
+}
+
+impl IcmpSocket {
+    pub fn new(
+        status: FileStatus,
+        send_buf_size: usize,
+        recv_buf_size: usize,
+    ) -> Arc<AtomicRefCell<Self>> {
+        let mut socket = Self {
+            event_source: StateEventSource::new(),
+            status,
+            state: FileState::ACTIVE,
+            shutdown_status: ShutdownFlags::empty(),
+            send_buffer: MessageBuffer::new(send_buf_size),
+            recv_buffer: MessageBuffer::new(recv_buf_size),
+            peer_addr: None,
+            bound_addr: None,
+            association: None,
+            recv_time_of_last_read_packet: None,
+            drops: 0,
+            has_open_file: false,
+            _counter: ObjectCounter::new("IcmpSocket"),
+        };
+
+        CallbackQueue::queue_and_run(|cb_queue| socket.refresh_readable_writable(cb_queue));
+
+        Arc::new(AtomicRefCell::new(socket))
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ | FileMode::WRITE
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        true
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    pub fn push_in_packet(
+        &mut self,
+        mut packet: PacketRc,
+        cb_queue: &mut CallbackQueue,
+        recv_time: EmulatedTime,
+    ) {
+        packet.add_status(PacketStatus::RcvSocketProcessed);
+
+        // ping sockets only receive echo replies
+        let Some(icmp) = packet.get_icmp().filter(|x| x.kind == IcmpEchoKind::Reply) else {
+            packet.add_status(PacketStatus::RcvSocketDropped);
+            return;
+        };
+
+        // a connected socket only receives replies from its peer
+        if self.peer_addr.is_some_and(|peer| peer != icmp.src) {
+            packet.add_status(PacketStatus::RcvSocketDropped);
+            return;
+        }
+
+        // don't bother copying the bytes if we know the push will fail
+        if !self.recv_buffer.has_space() {
+            log::trace!("Dropping a packet since the ICMP socket's recv buffer is full");
+            packet.add_status(PacketStatus::RcvSocketDropped);
+            self.drops = self.drops.wrapping_add(1);
+            return;
+        }
+
+        let mut data = BytesMut::zeroed(packet.payload_size());
+        let num_bytes_copied = packet.get_payload(&mut data);
+        assert_eq!(num_bytes_copied, packet.payload_size());
+
+        let header = MessageRecvHeader {
+            src: icmp.src,
+            identifier: icmp.identifier,
+            sequence: icmp.sequence,
+            recv_time,
+        };
+
+        // push the message to the receive buffer (shouldn't fail since we checked for available
+        // space above)
+        self.recv_buffer
+            .push_message(data.freeze(), header)
+            .unwrap();
+
+        log::trace!("Added a packet to the ICMP socket's recv buffer");
+        packet.add_status(PacketStatus::RcvSocketBuffered);
+
+        self.refresh_readable_writable(cb_queue);
+    }
+
+    pub fn pull_out_packet(&mut self, cb_queue: &mut CallbackQueue) -> Option<PacketRc> {
+        let Some((data, header)) = self.send_buffer.pop_message() else {
+            log::debug!(
+                "Attempted to remove a message from the ICMP socket's send buffer, but none \
+                available"
+            );
+            return None;
+        };
+
+        // the socket is always bound before a message is added to the send buffer
+        let identifier = self.bound_addr.unwrap().port();
+
+        let mut packet = PacketRc::new();
+        packet.set_icmp(&IcmpEchoHeader {
+            kind: IcmpEchoKind::Request,
+            src: header.src,
+            dst: header.dst,
+            identifier,
+            sequence: header.sequence,
+        });
+        packet.set_payload(&data, header.packet_priority);
+        packet.add_status(PacketStatus::SndCreated);
+
+        self.refresh_readable_writable(cb_queue);
+
+        Some(packet)
+    }
+
+    pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority> {
+        self.send_buffer
+            .peek_message()
+            .map(|(_, header)| header.packet_priority)
+    }
+
+    pub fn has_data_to_send(&self) -> bool {
+        !self.send_buffer.is_empty()
+    }
+
+    pub fn getsockname(&self) -> Result<Option<SockaddrIn>, SyscallError> {
+        let mut addr = self
+            .bound_addr
+            .unwrap_or(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
+
+        // if we are bound to INADDR_ANY, we should instead return the IP used to communicate with
+        // the connected peer (if we have one)
+        if *addr.ip() == Ipv4Addr::UNSPECIFIED {
+            if let Some(peer_addr) = self.peer_addr {
+                addr.set_ip(peer_addr);
+            }
+        }
+
+        Ok(Some(addr.into()))
+    }
+
+    pub fn getpeername(&self) -> Result<Option<SockaddrIn>, SyscallError> {
+        let peer_addr = self.peer_addr.ok_or(Errno::ENOTCONN)?;
+        Ok(Some(SocketAddrV4::new(peer_addr, 0).into()))
+    }
+
+    pub fn address_family(&self) -> AddressFamily {
+        AddressFamily::Inet
+    }
+
+    /// Always `None`, since ping sockets are only supported for `AF_INET`.
+    pub fn ipv6_options(&self) -> Option<Ipv6Options> {
+        None
+    }
+
+    pub fn set_ipv6_options(&mut self, options: Option<Ipv6Options>) {
+        assert!(options.is_none(), "ICMP sockets can't be AF_INET6 sockets");
+    }
+
+    /// The socket's line in `/proc/net/icmp`.
+    pub fn proc_net_entry(&self) -> ProcNetEntry {
+        let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+        // like linux, the queue lengths include the per-message overhead
+        let tx_queue = self.send_buffer.alloc_bytes();
+        let rx_queue = self.recv_buffer.alloc_bytes();
+
+        ProcNetEntry {
+            local: self.bound_addr.unwrap_or(unspecified),
+            peer: SocketAddrV4::new(self.peer_addr.unwrap_or(Ipv4Addr::UNSPECIFIED), 0),
+            state: match self.peer_addr {
+                Some(_) => SocketState::Established,
+                None => SocketState::Close,
+            },
+            tx_queue: tx_queue.try_into().unwrap_or(u32::MAX),
+            rx_queue: rx_queue.try_into().unwrap_or(u32::MAX),
+            drops: self.drops,
+        }
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // drop the existing association handle to disassociate the socket
+        self.association = None;
+
+        self.copy_state(
+            /* mask= */ FileState::all(),
+            FileState::CLOSED,
+            cb_queue,
+        );
+        Ok(())
+    }
+
+    pub fn bind(
+        socket: &Arc<AtomicRefCell<Self>>,
+        addr: Option<&SockaddrStorage>,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+    ) -> SyscallResult {
+        // if the address pointer was NULL
+        let Some(addr) = addr else {
+            return Err(Errno::EFAULT.into());
+        };
+
+        // if not an inet socket address
+        let Some(addr) = addr.as_inet() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        let addr: SocketAddrV4 = (*addr).into();
+
+        // if the socket is already bound
+        if socket.borrow().bound_addr.is_some() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // a port of 0 chooses a free identifier
+        Self::associate(socket, addr, Errno::EADDRINUSE, net_ns, rng)?;
+
+        Ok(0.into())
+    }
+
+    /// Associate the unbound socket with the local address `addr`, whose port is the identifier
+    /// for the socket's echo messages. The socket receives the replies from every peer, and
+    /// `push_in_packet` drops the replies that aren't from a connected socket's peer.
+    fn associate(
+        socket: &Arc<AtomicRefCell<Self>>,
+        addr: SocketAddrV4,
+        no_ports_errno: Errno,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+    ) -> Result<(), SyscallError> {
+        assert!(socket.borrow().association.is_none());
+
+        let unspecified_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+        let (addr, handle) = inet::associate_socket(
+            InetSocket::Icmp(Arc::clone(socket)),
+            addr,
+            unspecified_addr,
+            /* check_generic_peer= */ true,
+            no_ports_errno,
+            net_ns,
+            rng,
+        )?;
+
+        let mut socket = socket.borrow_mut();
+        socket.bound_addr = Some(addr);
+        socket.association = Some(handle);
+
+        Ok(())
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the readv() syscall handler should have called IcmpSocket::recvmsg() instead
+        panic!("Called IcmpSocket::readv() on an ICMP socket");
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the writev() syscall handler should have called IcmpSocket::sendmsg() instead
+        panic!("Called IcmpSocket::writev() on an ICMP socket");
+    }
+
+    pub fn sendmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: SendmsgArgs,
+        mem: &mut MemoryManager,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // if the file's writing has been shut down, return EPIPE
+        if socket
+            .borrow()
+            .shutdown_status
+            .contains(ShutdownFlags::WRITE)
+        {
+            return Err(Errno::EPIPE.into());
+        }
+
+        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
+            log::debug!("Unrecognized send flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        };
+
+        // the port of the destination address is ignored
+        let dst_ip = match args.addr {
+            Some(addr) => match addr.as_inet() {
+                // an inet socket address
+                Some(x) => *SocketAddrV4::from(*x).ip(),
+                // not an inet socket address
+                None => return Err(Errno::EAFNOSUPPORT.into()),
+            },
+            // no destination address provided
+            None => match socket.borrow().peer_addr {
+                Some(x) => x,
+                None => return Err(Errno::EDESTADDRREQ.into()),
+            },
+        };
+
+        // we don't support `SO_BROADCAST` on ping sockets
+        if dst_ip.is_broadcast() {
+            return Err(Errno::EACCES.into());
+        }
+
+        check_routable(net_ns, dst_ip)?;
+
+        if socket.borrow().status().contains(FileStatus::NONBLOCK) {
+            flags.insert(MsgFlags::MSG_DONTWAIT);
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        // the message must have room for the echo header
+        if len < ECHO_HEADER_LEN {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // TODO: should use IP fragmentation to make sure packets fit within the MTU
+        if len > CONFIG_ICMP_MESSAGE_MAX_SIZE {
+            return Err(Errno::EMSGSIZE.into());
+        }
+
+        // make sure that we're bound
+        if socket.borrow().bound_addr.is_none() {
+            // implicit bind (use default interface unless the remote peer is on loopback)
+            let local_ip = if dst_ip == Ipv4Addr::LOCALHOST {
+                Ipv4Addr::LOCALHOST
+            } else {
+                net_ns.default_ip
+            };
+
+            Self::associate(
+                socket,
+                SocketAddrV4::new(local_ip, 0),
+                Errno::EAGAIN,
+                net_ns,
+                rng,
+            )?;
+        }
+
+        let mut socket_ref = socket.borrow_mut();
+
+        // run in a closure so that an early return doesn't skip checking if we should block
+        let result = (|| {
+            // don't bother copying the bytes if we know the push will fail
+            if !socket_ref.send_buffer.has_space() {
+                return Err(Errno::EWOULDBLOCK);
+            }
+
+            // read the echo header and data from the iovs
+            let mut reader = IoVecReader::new(args.iovs, mem);
+            let mut message = BytesMut::zeroed(len);
+            reader
+                .read_exact(&mut message[..])
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            // icmp(7): "When a ping socket is used, only echo requests with a code of 0 can be
+            // sent"; the checksum is computed and the identifier is replaced by the kernel
+            if message[0] != ICMP_ECHO || message[1] != 0 {
+                return Err(Errno::EINVAL);
+            }
+            let sequence = u16::from_be_bytes([message[6], message[7]]);
+            let data = message.split_off(ECHO_HEADER_LEN);
+
+            // get the priority that we'll assign to the eventual packet
+            let packet_priority =
+                Worker::with_active_host(|host| host.get_next_packet_priority()).unwrap();
+
+            let bound_ip = *socket_ref.bound_addr.unwrap().ip();
+            let src_ip = if bound_ip.is_unspecified() {
+                // depending on the destination address, choose either localhost or the public IP
+                // address
+                if dst_ip == Ipv4Addr::LOCALHOST {
+                    Ipv4Addr::LOCALHOST
+                } else {
+                    net_ns.default_ip
+                }
+            } else {
+                bound_ip
+            };
+
+            let header = MessageSendHeader {
+                src: src_ip,
+                dst: dst_ip,
+                sequence,
+                packet_priority,
+            };
+
+            // push the message to the send buffer (shouldn't fail since we checked for available
+            // space above)
+            socket_ref
+                .send_buffer
+                .push_message(data.freeze(), header)
+                .unwrap();
+
+            // notify the host that this socket has packets to send
+            Self::notify_has_packets(socket, src_ip, cb_queue);
+
+            Ok(len)
+        })();
+
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        // if the syscall would block and we don't have the MSG_DONTWAIT flag
+        if result == Err(Errno::EWOULDBLOCK) && !flags.contains(MsgFlags::MSG_DONTWAIT) {
+            return Err(SyscallError::new_blocked_on_file(
+                File::Socket(Socket::Inet(InetSocket::Icmp(socket.clone()))),
+                FileState::WRITABLE,
+                socket_ref.supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?.try_into().unwrap())
+    }
+
+    /// Notify the host that the socket has packets to send on the interface with address
+    /// `interface_ip`.
+    fn notify_has_packets(
+        socket: &Arc<AtomicRefCell<Self>>,
+        interface_ip: Ipv4Addr,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let socket = Arc::clone(socket);
+        cb_queue.add(move |_cb_queue| {
+            Worker::with_active_host(|host| {
+                let inet_socket = InetSocket::Icmp(socket);
+                host.notify_socket_has_packets(interface_ip, &inet_socket);
+            })
+            .unwrap();
+        });
+    }
+
+    pub fn recvmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: RecvmsgArgs,
+        mem: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<RecvmsgReturn, SyscallError> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
+            log::debug!("Unrecognized recv flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        };
+
+        if socket_ref.status().contains(FileStatus::NONBLOCK) {
+            flags.insert(MsgFlags::MSG_DONTWAIT);
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        // run in a closure so that an early return doesn't skip checking if we should block
+        let result = (|| {
+            let (message, header) = if !flags.contains(MsgFlags::MSG_PEEK) {
+                socket_ref
+                    .recv_buffer
+                    .pop_message()
+                    .ok_or(Errno::EWOULDBLOCK)?
+            } else {
+                let (message, header) = socket_ref
+                    .recv_buffer
+                    .peek_message()
+                    .ok_or(Errno::EWOULDBLOCK)?;
+                (message.clone(), *header)
+            };
+
+            let message = echo_reply_bytes(&header, &message);
+
+            // truncate the message if it's larger than the user-provided buffers
+            let truncated_message = &message[..std::cmp::min(len, message.len())];
+
+            // write the truncated message to the iovs
+            let mut writer = IoVecWriter::new(args.iovs, mem);
+            writer
+                .write_all(truncated_message)
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            let return_val = if flags.contains(MsgFlags::MSG_TRUNC) {
+                message.len()
+            } else {
+                // the number of bytes written
+                truncated_message.len()
+            };
+
+            let mut return_flags = MsgFlags::empty();
+            return_flags.set(MsgFlags::MSG_TRUNC, truncated_message.len() < message.len());
+
+            // update the cache of the last recv time
+            socket_ref.recv_time_of_last_read_packet = Some(header.recv_time);
+
+            Ok(RecvmsgReturn {
+                return_val: return_val.try_into().unwrap(),
+                // like linux, the port of the source address is 0
+                addr: Some(SocketAddrV4::new(header.src, 0).into()),
+                msg_flags: return_flags.bits(),
+                control_len: 0,
+            })
+        })();
+
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        // if the syscall would block and we don't have the MSG_DONTWAIT flag
+        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
+            // if the syscall would block but the file's reading has been shut down, return EOF
+            if socket_ref.shutdown_status.contains(ShutdownFlags::READ) {
+                return Ok(RecvmsgReturn {
+                    return_val: 0,
+                    addr: None,
+                    msg_flags: 0,
+                    control_len: 0,
+                });
+            }
+
+            return Err(SyscallError::new_blocked_on_file(
+                File::Socket(Socket::Inet(InetSocket::Icmp(socket.clone()))),
+                FileState::READABLE,
+                socket_ref.supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?)
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+        mem: &mut MemoryManager,
+    ) -> SyscallResult {
+        match request {
+            // equivalent to SIOCINQ
+            IoctlRequest::FIONREAD => {
+                let len = self
+                    .recv_buffer
+                    .peek_message()
+                    .map(|m| ECHO_HEADER_LEN + m.0.len())
+                    .unwrap_or(0)
+                    .try_into()
+                    .unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            // equivalent to SIOCOUTQ
+            IoctlRequest::TIOCOUTQ => {
+                let len = self.send_buffer.len_bytes().try_into().unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            IoctlRequest::SIOCGSTAMP => {
+                let Some(last_recv_time) = self.recv_time_of_last_read_packet else {
+                    return Err(Errno::ENOENT.into());
+                };
+
+                let last_recv_time = (last_recv_time - EmulatedTime::UNIX_EPOCH)
+                    .try_into()
+                    .unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::timeval>();
+                mem.write(arg_ptr, &last_recv_time)?;
+
+                Ok(0.into())
+            }
+            IoctlRequest::FIONBIO => {
+                panic!("This should have been handled by the ioctl syscall handler");
+            }
+            IoctlRequest::TCGETS
+            | IoctlRequest::TCSETS
+            | IoctlRequest::TCSETSW
+            | IoctlRequest::TCSETSF
+            | IoctlRequest::TCGETA
+            | IoctlRequest::TCSETA
+            | IoctlRequest::TCSETAW
+            | IoctlRequest::TCSETAF
+            | IoctlRequest::TIOCGWINSZ
+            | IoctlRequest::TIOCSWINSZ => {
+                // not a terminal
+                Err(Errno::ENOTTY.into())
+            }
+            request => {
+                warn_once_then_debug!(
+                    "(LOG_ONCE) We do not yet handle ioctl request {request:?} on icmp sockets"
+                );
+                Err(Errno::EINVAL.into())
+            }
+        }
+    }
+
+    pub fn listen(
+        _socket: &Arc<AtomicRefCell<Self>>,
+        _backlog: i32,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn connect(
+        socket: &Arc<AtomicRefCell<Self>>,
+        peer_addr: &SockaddrStorage,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        // if not an inet socket address
+        let Some(peer_addr) = peer_addr.as_inet() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // the port of the peer address is ignored
+        let mut peer_ip = *SocketAddrV4::from(*peer_addr).ip();
+
+        if peer_ip.is_unspecified() {
+            peer_ip = Ipv4Addr::LOCALHOST;
+        }
+
+        if peer_ip.is_broadcast() {
+            return Err(Errno::EACCES.into());
+        }
+
+        check_routable(net_ns, peer_ip)?;
+
+        let bound_addr = socket.borrow().bound_addr;
+
+        match bound_addr {
+            // make sure the new peer address is connectable from the bound interface
+            Some(bound_addr) if !bound_addr.ip().is_unspecified() => {
+                if (*bound_addr.ip() == Ipv4Addr::LOCALHOST) != (peer_ip == Ipv4Addr::LOCALHOST) {
+                    return Err(Errno::EINVAL.into());
+                }
+            }
+            Some(_) => {}
+            None => {
+                // implicit bind (use default interface unless the remote peer is on loopback)
+                let local_ip = if peer_ip == Ipv4Addr::LOCALHOST {
+                    Ipv4Addr::LOCALHOST
+                } else {
+                    net_ns.default_ip
+                };
+
+                Self::associate(
+                    socket,
+                    SocketAddrV4::new(local_ip, 0),
+                    Errno::EAGAIN,
+                    net_ns,
+                    rng,
+                )?;
+            }
+        }
+
+        socket.borrow_mut().peer_addr = Some(peer_ip);
+
+        Ok(())
+    }
+
+    pub fn accept(
+        &mut self,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<OpenFile, SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn shutdown(
+        &mut self,
+        how: Shutdown,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        if self.peer_addr.is_none() {
+            return Err(Errno::ENOTCONN.into());
+        }
+
+        if how == Shutdown::Write || how == Shutdown::Both {
+            self.shutdown_status.insert(ShutdownFlags::WRITE)
+        }
+
+        if how == Shutdown::Read || how == Shutdown::Both {
+            self.shutdown_status.insert(ShutdownFlags::READ)
+        }
+
+        Ok(())
+    }
+
+    pub fn getsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::socklen_t, SyscallError> {
+        let val: libc::c_int = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
+                self.send_buffer.soft_limit_bytes().try_into().unwrap()
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                self.recv_buffer.soft_limit_bytes().try_into().unwrap()
+            }
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => libc::AF_INET,
+            (libc::SOL_SOCKET, libc::SO_TYPE) => libc::SOCK_DGRAM,
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => libc::IPPROTO_ICMP,
+            (libc::SOL_SOCKET, libc::SO_ACCEPTCONN) => 0,
+            (libc::SOL_SOCKET, _) => {
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+            _ => {
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::EOPNOTSUPP.into());
+            }
+        };
+
+        let optval_ptr = optval_ptr.cast::<libc::c_int>();
+        let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+        Ok(bytes_written as libc::socklen_t)
+    }
+
+    pub fn setsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
+    ) -> Result<(), SyscallError> {
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF | libc::SO_RCVBUF) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val: u64 = mem.read(optval_ptr)?.try_into().or(Err(Errno::EINVAL))?;
+
+                // linux kernel doubles this value upon setting, and we use the same limits as
+                // for UDP sockets
+                if optname == libc::SO_SNDBUF {
+                    let val = (val * 2).clamp(4096, 268435456); // 2^28 = 256 MiB
+                    self.send_buffer
+                        .set_soft_limit_bytes(val.try_into().unwrap());
+                } else {
+                    let val = (val * 2).clamp(2048, 268435456);
+                    self.recv_buffer
+                        .set_soft_limit_bytes(val.try_into().unwrap());
+                }
+            }
+            _ => {
+                log::debug!("setsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring: FileState,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
+    ) -> Handle<(FileState, FileState)> {
+        self.event_source
+            .add_listener(monitoring, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn refresh_readable_writable(&mut self, cb_queue: &mut CallbackQueue) {
+        let readable = !self.recv_buffer.is_empty();
+        let writable = self.send_buffer.has_space();
+
+        let readable = readable.then_some(FileState::READABLE).unwrap_or_default();
+        let writable = writable.then_some(FileState::WRITABLE).unwrap_or_default();
+
+        self.copy_state(
+            /* mask= */ FileState::READABLE | FileState::WRITABLE,
+            readable | writable,
+            cb_queue,
+        );
+    }
+
+    fn copy_state(&mut self, mask: FileState, state: FileState, cb_queue: &mut CallbackQueue) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, cb_queue);
+    }
+
+    fn handle_state_change(&mut self, old_state: FileState, cb_queue: &mut CallbackQueue) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, cb_queue);
+    }
+}
+
+/// Returns `ENETUNREACH` if there's no host with the address `ip`, since we'd have nowhere to send
+/// the echo requests.
+fn check_routable(net_ns: &NetworkNamespace, ip: Ipv4Addr) -> Result<(), Errno> {
+    if ip == Ipv4Addr::LOCALHOST || Worker::is_routable(net_ns.default_ip.into(), ip.into()) {
+        return Ok(());
+    }
+
+    log::debug!("Attempting to ping address '{ip}' for which no host exists");
+    Err(Errno::ENETUNREACH)
+}
+
+/// The ICMP echo reply message (header and data) that the application reads, including a valid
+/// checksum.
+fn echo_reply_bytes(header: &MessageRecvHeader, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ECHO_HEADER_LEN + data.len());
+    message.extend_from_slice(&[ICMP_ECHOREPLY, 0, 0, 0]);
+    message.extend_from_slice(&header.identifier.to_be_bytes());
+    message.extend_from_slice(&header.sequence.to_be_bytes());
+    message.extend_from_slice(data);
+
+    let checksum = internet_checksum(&message);
+    message[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+    message
+}
+
+/// The internet checksum (RFC 1071) of `data`.
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|x| u32::from(u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Non-payload data for a message in the send buffer.
+#[derive(Debug)]
+struct MessageSendHeader {
+    /// The source address (typically the bind address).
+    src: Ipv4Addr,
+    /// The destination address (for example the peer).
+    dst: Ipv4Addr,
+    /// The sequence number that the application chose.
+    sequence: u16,
+    /// The priority for the packet that we'll create in the future, given to us by the host.
+    packet_priority: FifoPacketPriority,
+}
+
+/// Non-payload data for a message in the receive buffer.
+#[derive(Debug, Copy, Clone)]
+struct MessageRecvHeader {
+    /// The source address (for example the peer).
+    src: Ipv4Addr,
+    identifier: u16,
+    sequence: u16,
+    /// The time when the network interface received the message.
+    recv_time: EmulatedTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internet_checksum() {
+        // the example from RFC 1071, section 3
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(internet_checksum(&data), !0xddf2);
+
+        // an odd number of bytes is padded with a zero byte
+        assert_eq!(internet_checksum(&[0x01]), !0x0100);
+        assert_eq!(internet_checksum(&[]), 0xffff);
+    }
+
+    #[test]
+    fn test_echo_reply_bytes() {
+        let header = MessageRecvHeader {
+            src: Ipv4Addr::new(11, 0, 0, 1),
+            identifier: 0x1234,
+            sequence: 7,
+            recv_time: EmulatedTime::SIMULATION_START,
+        };
+        let message = echo_reply_bytes(&header, b"ping");
+
+        assert_eq!(message.len(), ECHO_HEADER_LEN + 4);
+        assert_eq!(message[..2], [ICMP_ECHOREPLY, 0]);
+        assert_eq!(message[4..8], [0x12, 0x34, 0x00, 0x07]);
+        assert_eq!(&message[8..], b"ping");
+
+        // the checksum of a message with a valid checksum is zero
+        assert_eq!(internet_checksum(&message), 0);
+    }
+}
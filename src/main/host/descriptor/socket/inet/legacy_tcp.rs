@@ -11,8 +11,10 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::core::worker::Worker;
 use crate::cshadow as c;
-use crate::host::descriptor::socket::inet::{self, InetSocket};
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::inet::{self, InetSocket, Ipv6Options};
+use crate::host::descriptor::socket::{
+    RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket, SocketFlagOptions,
+};
 use crate::host::descriptor::{
     CompatFile, File, FileMode, FileState, FileStatus, OpenFile, StateListenerFilter, SyscallResult,
 };
@@ -20,6 +22,8 @@ use crate::host::host::Host;
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::interface::FifoPacketPriority;
 use crate::host::network::namespace::NetworkNamespace;
+use crate::host::network::proc_net::{ProcNetEntry, SocketState};
+use crate::host::process::ProcessId;
 use crate::host::syscall::io::{write_partial, IoVec};
 use crate::host::syscall_types::{ForeignArrayPtr, SyscallError};
 use crate::host::thread::ThreadId;
@@ -35,6 +39,9 @@ pub struct LegacyTcpSocket {
     has_open_file: bool,
     /// Did the last connect() call block, and if so what thread?
     thread_of_blocked_connect: Option<ThreadId>,
+    flag_options: SocketFlagOptions,
+    /// `Some` if this is an `AF_INET6` socket.
+    ipv6_options: Option<Ipv6Options>,
     _counter: ObjectCounter,
 }
 
@@ -63,6 +70,8 @@ impl LegacyTcpSocket {
             socket: HostTreePointer::new(legacy_tcp),
             has_open_file: false,
             thread_of_blocked_connect: None,
+            flag_options: SocketFlagOptions::default(),
+            ipv6_options: None,
             _counter: ObjectCounter::new("LegacyTcpSocket"),
         };
 
@@ -136,13 +145,13 @@ impl LegacyTcpSocket {
         _cb_queue: &mut CallbackQueue,
         _recv_time: EmulatedTime,
     ) {
+        let packet = packet.into_inner();
         Worker::with_active_host(|host| {
-            // the C code should ref the inner `Packet`, so it's fine to drop the `PacketRc`
-            unsafe {
-                c::legacysocket_pushInPacket(self.as_legacy_socket(), host, packet.borrow_inner())
-            };
+            unsafe { c::legacysocket_pushInPacket(self.as_legacy_socket(), host, packet) };
         })
         .unwrap();
+        // the C code refs the packet if it needs to keep it
+        unsafe { c::packet_unref(packet) };
     }
 
     pub fn pull_out_packet(&mut self, _cb_queue: &mut CallbackQueue) -> Option<PacketRc> {
@@ -170,9 +179,9 @@ impl LegacyTcpSocket {
             return None;
         }
 
-        let packet = PacketRc::from_raw(packet);
-        unsafe { c::packet_ref(packet.borrow_inner()) }
-        Some(packet)
+        // the socket still owns its reference, so we need our own
+        unsafe { c::packet_ref(packet) };
+        Some(PacketRc::from_raw(packet))
     }
 
     pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority> {
@@ -220,7 +229,55 @@ impl LegacyTcpSocket {
     }
 
     pub fn address_family(&self) -> nix::sys::socket::AddressFamily {
-        nix::sys::socket::AddressFamily::Inet
+        match self.ipv6_options {
+            Some(_) => nix::sys::socket::AddressFamily::Inet6,
+            None => nix::sys::socket::AddressFamily::Inet,
+        }
+    }
+
+    pub fn ipv6_options(&self) -> Option<Ipv6Options> {
+        self.ipv6_options
+    }
+
+    pub fn set_ipv6_options(&mut self, options: Option<Ipv6Options>) {
+        self.ipv6_options = options;
+    }
+
+    /// The socket's line in `/proc/net/tcp`.
+    pub fn proc_net_entry(&self) -> ProcNetEntry {
+        let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+        let local = self.getsockname().ok().flatten().map(SocketAddrV4::from);
+        let peer = self.getpeername().ok().flatten().map(SocketAddrV4::from);
+
+        let tcp = self.as_legacy_tcp();
+        let mut info: c::tcp_info = shadow_pod::zeroed();
+        unsafe { c::tcp_getInfo(tcp, &mut info) };
+        let state = SocketState::from_tcpi_state(info.tcpi_state).unwrap_or(SocketState::Close);
+
+        let (tx_queue, rx_queue) = if state == SocketState::Listen {
+            // like linux, `tcp_getInfo` reports the accept queue's limit and length in these fields
+            (info.tcpi_sacked, info.tcpi_unacked)
+        } else {
+            let (tx, rx) = unsafe {
+                (
+                    c::tcp_getOutputBufferLength(tcp),
+                    c::tcp_getInputBufferLength(tcp),
+                )
+            };
+            (
+                tx.try_into().unwrap_or(u32::MAX),
+                rx.try_into().unwrap_or(u32::MAX),
+            )
+        };
+
+        ProcNetEntry {
+            local: local.unwrap_or(unspecified),
+            peer: peer.unwrap_or(unspecified),
+            state,
+            tx_queue,
+            rx_queue,
+            drops: 0,
+        }
     }
 
     pub fn close(&mut self, _cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
@@ -278,6 +335,8 @@ impl LegacyTcpSocket {
             addr,
             peer_addr,
             /* check_generic_peer= */ true,
+            /* reuse_addr= */ false,
+            Errno::EADDRINUSE,
             net_ns,
             rng,
         )?;
@@ -626,6 +685,20 @@ impl LegacyTcpSocket {
         net_ns: &NetworkNamespace,
         rng: impl rand::Rng,
         _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        let process_id = Worker::active_process_id().unwrap();
+        Self::listen_for_process(socket, backlog, net_ns, rng, process_id)
+    }
+
+    /// Like [`Self::listen`], but the accepted connections are added to the process `process_id`
+    /// rather than the active process. This is used for sockets that are opened for a process
+    /// before it exists.
+    pub fn listen_for_process(
+        socket: &Arc<AtomicRefCell<Self>>,
+        backlog: i32,
+        net_ns: &NetworkNamespace,
+        rng: impl rand::Rng,
+        process_id: ProcessId,
     ) -> Result<(), SyscallError> {
         let socket_ref = socket.borrow();
 
@@ -662,6 +735,8 @@ impl LegacyTcpSocket {
                 local_addr,
                 peer_addr,
                 /* check_generic_peer= */ true,
+                /* reuse_addr= */ false,
+                Errno::EADDRINUSE,
                 net_ns,
                 rng,
             )?;
@@ -682,12 +757,7 @@ impl LegacyTcpSocket {
         // we are allowed to listen but not already listening; start now
         Worker::with_active_host(|host| {
             unsafe {
-                c::tcp_enterServerMode(
-                    socket_ref.as_legacy_tcp(),
-                    host,
-                    Worker::active_process_id().unwrap().into(),
-                    backlog,
-                )
+                c::tcp_enterServerMode(socket_ref.as_legacy_tcp(), host, process_id.into(), backlog)
             };
         })
         .unwrap();
@@ -766,6 +836,8 @@ impl LegacyTcpSocket {
                 local_addr,
                 peer_addr,
                 /* check_generic_peer= */ true,
+                /* reuse_addr= */ false,
+                Errno::EADDRNOTAVAIL,
                 net_ns,
                 rng,
             )?;
@@ -920,7 +992,11 @@ impl LegacyTcpSocket {
                 panic!("Expected this to be a LegacyTcpSocket");
             };
 
-            let new_socket = new_socket.borrow();
+            let mut new_socket = new_socket.borrow_mut();
+
+            // like linux, the socket inherits the listening socket's options
+            new_socket.flag_options = self.flag_options;
+            new_socket.ipv6_options = self.ipv6_options;
 
             let mut ip: libc::in_addr_t = 0;
             let mut port: libc::in_port_t = 0;
@@ -983,10 +1059,14 @@ impl LegacyTcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
-            (libc::SOL_TCP, libc::TCP_NODELAY) => {
-                // shadow doesn't support nagle's algorithm, so shadow always behaves as if
-                // TCP_NODELAY is enabled
-                let val = 1;
+            (libc::SOL_TCP, libc::TCP_NODELAY | libc::TCP_CORK | libc::TCP_QUICKACK) => {
+                let tcp = self.as_legacy_tcp();
+                let val: libc::c_int = match optname {
+                    libc::TCP_NODELAY => unsafe { c::tcp_getNoDelay(tcp) },
+                    libc::TCP_CORK => unsafe { c::tcp_getCork(tcp) },
+                    libc::TCP_QUICKACK => unsafe { c::tcp_getQuickACK(tcp) },
+                    _ => unreachable!(),
+                };
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written =
@@ -1047,15 +1127,8 @@ impl LegacyTcpSocket {
                 Ok(bytes_written as libc::socklen_t)
             }
             (libc::SOL_SOCKET, libc::SO_ERROR) => {
-                // return error for failed connect() attempts
-                let conn_err = unsafe { c::tcp_getConnectionError(self.as_legacy_tcp()) };
-
-                let error = if conn_err == -libc::ECONNRESET || conn_err == -libc::ECONNREFUSED {
-                    // result is a positive errcode
-                    -conn_err
-                } else {
-                    0
-                };
+                // like linux, the pending error is cleared once it's been returned
+                let error = unsafe { c::tcp_takeSocketError(self.as_legacy_tcp()) };
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written =
@@ -1064,7 +1137,7 @@ impl LegacyTcpSocket {
                 Ok(bytes_written as libc::socklen_t)
             }
             (libc::SOL_SOCKET, libc::SO_DOMAIN) => {
-                let domain = libc::AF_INET;
+                let domain = self.address_family() as libc::c_int;
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written =
@@ -1099,6 +1172,18 @@ impl LegacyTcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                let val = libc::c_int::from(self.flag_options.get(optname));
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(memory_manager, &val, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             _ => {
                 log::warn!("getsockopt called with unsupported level {level} and opt {optname}");
                 Err(Errno::ENOPROTOOPT.into())
@@ -1115,12 +1200,7 @@ impl LegacyTcpSocket {
         memory_manager: &MemoryManager,
     ) -> Result<(), SyscallError> {
         match (level, optname) {
-            (libc::SOL_TCP, libc::TCP_NODELAY) => {
-                // Shadow doesn't support nagle's algorithm, so Shadow always behaves as if
-                // TCP_NODELAY is enabled. Some programs will fail if `setsockopt(fd, SOL_TCP,
-                // TCP_NODELAY, &1, sizeof(int))` returns an error, so we treat this as a no-op for
-                // compatibility.
-
+            (libc::SOL_TCP, libc::TCP_NODELAY | libc::TCP_CORK | libc::TCP_QUICKACK) => {
                 type OptType = libc::c_int;
 
                 if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
@@ -1129,15 +1209,16 @@ impl LegacyTcpSocket {
 
                 let optval_ptr = optval_ptr.cast::<OptType>();
                 let enable = memory_manager.read(optval_ptr)?;
-
-                if enable != 0 {
-                    // wants to enable TCP_NODELAY
-                    log::debug!("Ignoring TCP_NODELAY");
-                } else {
-                    // wants to disable TCP_NODELAY
-                    log::warn!("Cannot disable TCP_NODELAY since shadow does not implement Nagle's algorithm.");
-                    return Err(Errno::ENOPROTOOPT.into());
-                }
+                let enable = i32::from(enable != 0);
+
+                let tcp = self.as_legacy_tcp();
+                Worker::with_active_host(|host| match optname {
+                    libc::TCP_NODELAY => unsafe { c::tcp_setNoDelay(tcp, host, enable) },
+                    libc::TCP_CORK => unsafe { c::tcp_setCork(tcp, host, enable) },
+                    libc::TCP_QUICKACK => unsafe { c::tcp_setQuickACK(tcp, host, enable) },
+                    _ => unreachable!(),
+                })
+                .unwrap();
             }
             (libc::SOL_TCP, libc::TCP_CONGESTION) => {
                 // the value of TCP_CA_NAME_MAX in linux
@@ -1227,22 +1308,26 @@ impl LegacyTcpSocket {
                 unsafe { c::legacysocket_setInputBufferSize(self.as_legacy_socket(), val) };
                 unsafe { c::tcp_disableReceiveBufferAutotuning(self.as_legacy_tcp()) };
             }
-            (libc::SOL_SOCKET, libc::SO_REUSEADDR) => {
-                // TODO: implement this, tor and tgen use it
-                log::trace!("setsockopt SO_REUSEADDR not yet implemented");
-            }
-            (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
-                // TODO: implement this, tgen uses it
-                log::trace!("setsockopt SO_REUSEPORT not yet implemented");
-            }
-            (libc::SOL_SOCKET, libc::SO_KEEPALIVE) => {
-                // TODO: implement this, libevent uses it in
-                // evconnlistener_new_bind()
-                log::trace!("setsockopt SO_KEEPALIVE not yet implemented");
-            }
-            (libc::SOL_SOCKET, libc::SO_BROADCAST) => {
-                // TODO: implement this, pkg.go.dev/net uses it
-                log::trace!("setsockopt SO_BROADCAST not yet implemented");
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                // TODO: implement these; tor and tgen use SO_REUSEADDR, tgen uses SO_REUSEPORT,
+                // libevent uses SO_KEEPALIVE in evconnlistener_new_bind(), and pkg.go.dev/net uses
+                // SO_BROADCAST
+                log::trace!("setsockopt option {optname} not yet implemented");
+
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = memory_manager.read(optval_ptr)?;
+
+                // the value is still returned by getsockopt()
+                self.flag_options.set(optname, val != 0);
             }
             _ => {
                 log::warn!("setsockopt called with unsupported level {level} and opt {optname}");
@@ -1,10 +1,10 @@
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{Ipv4Addr, SocketAddrV4, SocketAddrV6};
 use std::sync::{Arc, Weak};
 
 use atomic_refcell::AtomicRefCell;
 use linux_api::errno::Errno;
 use linux_api::ioctls::IoctlRequest;
-use nix::sys::socket::Shutdown;
+use nix::sys::socket::{AddressFamily, Shutdown};
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
@@ -15,19 +15,25 @@ use crate::host::descriptor::{
 };
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::interface::FifoPacketPriority;
+use crate::host::network::ipv6;
 use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
-use crate::host::syscall::io::IoVec;
+use crate::host::network::proc_net::ProcNetEntry;
+use crate::host::syscall::io::{write_partial, IoVec};
 use crate::host::syscall_types::SyscallError;
 use crate::network::packet::PacketRc;
 use crate::utility::callback_queue::CallbackQueue;
 use crate::utility::sockaddr::SockaddrStorage;
 use crate::utility::HostTreePointer;
 
+use self::icmp::IcmpSocket;
 use self::legacy_tcp::LegacyTcpSocket;
+use self::raw::RawSocket;
 use self::tcp::TcpSocket;
 use self::udp::UdpSocket;
 
+pub mod icmp;
 pub mod legacy_tcp;
+pub mod raw;
 pub mod tcp;
 pub mod udp;
 
@@ -36,6 +42,8 @@ pub enum InetSocket {
     LegacyTcp(Arc<AtomicRefCell<LegacyTcpSocket>>),
     Tcp(Arc<AtomicRefCell<TcpSocket>>),
     Udp(Arc<AtomicRefCell<UdpSocket>>),
+    Icmp(Arc<AtomicRefCell<IcmpSocket>>),
+    Raw(Arc<AtomicRefCell<RawSocket>>),
 }
 
 impl InetSocket {
@@ -44,6 +52,8 @@ impl InetSocket {
             Self::LegacyTcp(ref f) => InetSocketRef::LegacyTcp(f.borrow()),
             Self::Tcp(ref f) => InetSocketRef::Tcp(f.borrow()),
             Self::Udp(ref f) => InetSocketRef::Udp(f.borrow()),
+            Self::Icmp(ref f) => InetSocketRef::Icmp(f.borrow()),
+            Self::Raw(ref f) => InetSocketRef::Raw(f.borrow()),
         }
     }
 
@@ -52,6 +62,8 @@ impl InetSocket {
             Self::LegacyTcp(ref f) => InetSocketRef::LegacyTcp(f.try_borrow()?),
             Self::Tcp(ref f) => InetSocketRef::Tcp(f.try_borrow()?),
             Self::Udp(ref f) => InetSocketRef::Udp(f.try_borrow()?),
+            Self::Icmp(ref f) => InetSocketRef::Icmp(f.try_borrow()?),
+            Self::Raw(ref f) => InetSocketRef::Raw(f.try_borrow()?),
         })
     }
 
@@ -60,6 +72,8 @@ impl InetSocket {
             Self::LegacyTcp(ref f) => InetSocketRefMut::LegacyTcp(f.borrow_mut()),
             Self::Tcp(ref f) => InetSocketRefMut::Tcp(f.borrow_mut()),
             Self::Udp(ref f) => InetSocketRefMut::Udp(f.borrow_mut()),
+            Self::Icmp(ref f) => InetSocketRefMut::Icmp(f.borrow_mut()),
+            Self::Raw(ref f) => InetSocketRefMut::Raw(f.borrow_mut()),
         }
     }
 
@@ -68,6 +82,8 @@ impl InetSocket {
             Self::LegacyTcp(ref f) => InetSocketRefMut::LegacyTcp(f.try_borrow_mut()?),
             Self::Tcp(ref f) => InetSocketRefMut::Tcp(f.try_borrow_mut()?),
             Self::Udp(ref f) => InetSocketRefMut::Udp(f.try_borrow_mut()?),
+            Self::Icmp(ref f) => InetSocketRefMut::Icmp(f.try_borrow_mut()?),
+            Self::Raw(ref f) => InetSocketRefMut::Raw(f.try_borrow_mut()?),
         })
     }
 
@@ -76,6 +92,8 @@ impl InetSocket {
             Self::LegacyTcp(x) => InetSocketWeak::LegacyTcp(Arc::downgrade(x)),
             Self::Tcp(x) => InetSocketWeak::Tcp(Arc::downgrade(x)),
             Self::Udp(x) => InetSocketWeak::Udp(Arc::downgrade(x)),
+            Self::Icmp(x) => InetSocketWeak::Icmp(Arc::downgrade(x)),
+            Self::Raw(x) => InetSocketWeak::Raw(Arc::downgrade(x)),
         }
     }
 
@@ -86,6 +104,8 @@ impl InetSocket {
             Self::LegacyTcp(f) => f.borrow().canonical_handle(),
             Self::Tcp(f) => Arc::as_ptr(f) as usize,
             Self::Udp(f) => Arc::as_ptr(f) as usize,
+            Self::Icmp(f) => Arc::as_ptr(f) as usize,
+            Self::Raw(f) => Arc::as_ptr(f) as usize,
         }
     }
 
@@ -95,10 +115,21 @@ impl InetSocket {
         net_ns: &NetworkNamespace,
         rng: impl rand::Rng,
     ) -> SyscallResult {
+        let translated_addr;
+        let addr = match (self.ipv6_options(), addr) {
+            (Some(options), Some(addr)) => {
+                translated_addr = inet6_to_inet(addr, options, Ipv6AddrUse::Bind)?;
+                Some(&translated_addr)
+            }
+            _ => addr,
+        };
+
         match self {
             Self::LegacyTcp(socket) => LegacyTcpSocket::bind(socket, addr, net_ns, rng),
             Self::Tcp(socket) => TcpSocket::bind(socket, addr, net_ns, rng),
             Self::Udp(socket) => UdpSocket::bind(socket, addr, net_ns, rng),
+            Self::Icmp(socket) => IcmpSocket::bind(socket, addr, net_ns, rng),
+            Self::Raw(socket) => RawSocket::bind(socket, addr, net_ns, rng),
         }
     }
 
@@ -115,6 +146,8 @@ impl InetSocket {
             }
             Self::Tcp(socket) => TcpSocket::listen(socket, backlog, net_ns, rng, cb_queue),
             Self::Udp(socket) => UdpSocket::listen(socket, backlog, net_ns, rng, cb_queue),
+            Self::Icmp(socket) => IcmpSocket::listen(socket, backlog, net_ns, rng, cb_queue),
+            Self::Raw(socket) => RawSocket::listen(socket, backlog, net_ns, rng, cb_queue),
         }
     }
 
@@ -125,23 +158,47 @@ impl InetSocket {
         rng: impl rand::Rng,
         cb_queue: &mut CallbackQueue,
     ) -> Result<(), SyscallError> {
+        let translated_addr;
+        let addr = match self.ipv6_options() {
+            Some(options) => {
+                // like linux, only UDP sockets can connect to an IPv4 address or disconnect
+                let addr_use = match self {
+                    Self::Udp(_) => Ipv6AddrUse::UdpPeer,
+                    Self::LegacyTcp(_) | Self::Tcp(_) => Ipv6AddrUse::TcpPeer,
+                };
+                translated_addr = inet6_to_inet(addr, options, addr_use)?;
+                &translated_addr
+            }
+            None => addr,
+        };
+
         match self {
             Self::LegacyTcp(socket) => {
                 LegacyTcpSocket::connect(socket, addr, net_ns, rng, cb_queue)
             }
             Self::Tcp(socket) => TcpSocket::connect(socket, addr, net_ns, rng, cb_queue),
             Self::Udp(socket) => UdpSocket::connect(socket, addr, net_ns, rng, cb_queue),
+            Self::Icmp(socket) => IcmpSocket::connect(socket, addr, net_ns, rng, cb_queue),
+            Self::Raw(socket) => RawSocket::connect(socket, addr, net_ns, rng, cb_queue),
         }
     }
 
     pub fn sendmsg(
         &self,
-        args: SendmsgArgs,
+        mut args: SendmsgArgs,
         memory_manager: &mut MemoryManager,
         net_ns: &NetworkNamespace,
         rng: impl rand::Rng,
         cb_queue: &mut CallbackQueue,
     ) -> Result<libc::ssize_t, SyscallError> {
+        if let (Some(options), Some(addr)) = (self.ipv6_options(), args.addr) {
+            let addr_use = match self {
+                Self::Udp(_) => Ipv6AddrUse::UdpPeer,
+                Self::LegacyTcp(_) | Self::Tcp(_) => Ipv6AddrUse::TcpPeer,
+            };
+            args.addr = Some(inet6_to_inet(&addr, options, addr_use)?);
+        }
+
         match self {
             Self::LegacyTcp(socket) => {
                 LegacyTcpSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
@@ -152,6 +209,12 @@ impl InetSocket {
             Self::Udp(socket) => {
                 UdpSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
             }
+            Self::Icmp(socket) => {
+                IcmpSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
+            }
+            Self::Raw(socket) => {
+                RawSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
+            }
         }
     }
 
@@ -161,13 +224,28 @@ impl InetSocket {
         memory_manager: &mut MemoryManager,
         cb_queue: &mut CallbackQueue,
     ) -> Result<RecvmsgReturn, SyscallError> {
-        match self {
+        let is_ipv6 = self.ipv6_options().is_some();
+
+        let mut rv = match self {
             Self::LegacyTcp(socket) => {
                 LegacyTcpSocket::recvmsg(socket, args, memory_manager, cb_queue)
             }
             Self::Tcp(socket) => TcpSocket::recvmsg(socket, args, memory_manager, cb_queue),
             Self::Udp(socket) => UdpSocket::recvmsg(socket, args, memory_manager, cb_queue),
+            Self::Icmp(socket) => IcmpSocket::recvmsg(socket, args, memory_manager, cb_queue),
+            Self::Raw(socket) => RawSocket::recvmsg(socket, args, memory_manager, cb_queue),
+        }?;
+
+        if is_ipv6 {
+            rv.addr = rv.addr.map(|addr| inet_to_inet6(&addr));
         }
+
+        Ok(rv)
+    }
+
+    /// The socket's IPv6 options if it's an `AF_INET6` socket.
+    fn ipv6_options(&self) -> Option<Ipv6Options> {
+        self.borrow().ipv6_options()
     }
 }
 
@@ -177,6 +255,8 @@ impl std::fmt::Debug for InetSocket {
             Self::LegacyTcp(_) => write!(f, "LegacyTcp")?,
             Self::Tcp(_) => write!(f, "Tcp")?,
             Self::Udp(_) => write!(f, "Udp")?,
+            Self::Icmp(_) => write!(f, "Icmp")?,
+            Self::Raw(_) => write!(f, "Raw")?,
         }
 
         if let Ok(file) = self.try_borrow() {
@@ -196,29 +276,33 @@ pub enum InetSocketRef<'a> {
     LegacyTcp(atomic_refcell::AtomicRef<'a, LegacyTcpSocket>),
     Tcp(atomic_refcell::AtomicRef<'a, TcpSocket>),
     Udp(atomic_refcell::AtomicRef<'a, UdpSocket>),
+    Icmp(atomic_refcell::AtomicRef<'a, IcmpSocket>),
+    Raw(atomic_refcell::AtomicRef<'a, RawSocket>),
 }
 
 pub enum InetSocketRefMut<'a> {
     LegacyTcp(atomic_refcell::AtomicRefMut<'a, LegacyTcpSocket>),
     Tcp(atomic_refcell::AtomicRefMut<'a, TcpSocket>),
     Udp(atomic_refcell::AtomicRefMut<'a, UdpSocket>),
+    Icmp(atomic_refcell::AtomicRefMut<'a, IcmpSocket>),
+    Raw(atomic_refcell::AtomicRefMut<'a, RawSocket>),
 }
 
 // file functions
 impl InetSocketRef<'_> {
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn supports_sa_restart(&self) -> bool
     );
 }
@@ -226,66 +310,86 @@ impl InetSocketRef<'_> {
 // socket-specific functions
 impl InetSocketRef<'_> {
     pub fn getpeername(&self) -> Result<Option<SockaddrStorage>, SyscallError> {
-        match self {
+        let addr: Option<SockaddrStorage> = match self {
             Self::LegacyTcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
-        }
+            Self::Icmp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+            Self::Raw(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+        }?;
+
+        Ok(match self.ipv6_options() {
+            Some(_) => addr.map(|addr| inet_to_inet6(&addr)),
+            None => addr,
+        })
     }
 
     pub fn getsockname(&self) -> Result<Option<SockaddrStorage>, SyscallError> {
-        match self {
+        let addr: Option<SockaddrStorage> = match self {
             Self::LegacyTcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
-        }
+            Self::Icmp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+            Self::Raw(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+        }?;
+
+        Ok(match self.ipv6_options() {
+            Some(_) => addr.map(|addr| inet_to_inet6(&addr)),
+            None => addr,
+        })
     }
 
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn address_family(&self) -> nix::sys::socket::AddressFamily
     );
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
+        pub fn ipv6_options(&self) -> Option<Ipv6Options>
+    );
 }
 
 // inet socket-specific functions
 impl InetSocketRef<'_> {
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn has_data_to_send(&self) -> bool
     );
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
+        pub fn proc_net_entry(&self) -> ProcNetEntry
+    );
 }
 
 // file functions
 impl InetSocketRefMut<'_> {
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (val), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (val), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn set_has_open_file(&mut self, val: bool)
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn supports_sa_restart(&self) -> bool
     );
-    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
-    enum_passthrough!(self, (status), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (status), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn set_status(&mut self, status: FileStatus)
     );
-    enum_passthrough!(self, (request, arg_ptr, memory_manager), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (request, arg_ptr, memory_manager), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn ioctl(&mut self, request: IoctlRequest, arg_ptr: ForeignPtr<()>, memory_manager: &mut MemoryManager) -> SyscallResult
     );
-    enum_passthrough!(self, (monitoring, filter, notify_fn), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (monitoring, filter, notify_fn), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn add_listener(
             &mut self,
             monitoring: FileState,
@@ -293,17 +397,17 @@ impl InetSocketRefMut<'_> {
             notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
         ) -> Handle<(FileState, FileState)>
     );
-    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>)
     );
-    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (ptr), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener)
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn readv(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                      mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn writev(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                       mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
@@ -312,37 +416,111 @@ impl InetSocketRefMut<'_> {
 // socket-specific functions
 impl InetSocketRefMut<'_> {
     pub fn getpeername(&self) -> Result<Option<SockaddrStorage>, SyscallError> {
-        match self {
+        let addr: Option<SockaddrStorage> = match self {
             Self::LegacyTcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
-        }
+            Self::Icmp(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+            Self::Raw(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+        }?;
+
+        Ok(match self.ipv6_options() {
+            Some(_) => addr.map(|addr| inet_to_inet6(&addr)),
+            None => addr,
+        })
     }
 
     pub fn getsockname(&self) -> Result<Option<SockaddrStorage>, SyscallError> {
-        match self {
+        let addr: Option<SockaddrStorage> = match self {
             Self::LegacyTcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Tcp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Udp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
-        }
+            Self::Icmp(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+            Self::Raw(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+        }?;
+
+        Ok(match self.ipv6_options() {
+            Some(_) => addr.map(|addr| inet_to_inet6(&addr)),
+            None => addr,
+        })
     }
 
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn address_family(&self) -> nix::sys::socket::AddressFamily
     );
-
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), LegacyTcp, Tcp, Udp;
-        pub fn getsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
-                          optlen: libc::socklen_t, memory_manager: &mut MemoryManager, cb_queue: &mut CallbackQueue)
-        -> Result<libc::socklen_t, SyscallError>
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
+        pub fn ipv6_options(&self) -> Option<Ipv6Options>
     );
 
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager), LegacyTcp, Tcp, Udp;
-        pub fn setsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
-                          optlen: libc::socklen_t, memory_manager: &MemoryManager)
-        -> Result<(), SyscallError>
+    enum_passthrough!(self, (options), LegacyTcp, Tcp, Udp, Icmp, Raw;
+        pub fn set_ipv6_options(&mut self, options: Option<Ipv6Options>)
     );
 
+    pub fn getsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        memory_manager: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::socklen_t, SyscallError> {
+        if let (libc::IPPROTO_IPV6, Some(options)) = (level, self.ipv6_options()) {
+            return options.getsockopt(optname, optval_ptr, optlen, memory_manager);
+        }
+
+        match self {
+            Self::LegacyTcp(socket) => {
+                socket.getsockopt(level, optname, optval_ptr, optlen, memory_manager, cb_queue)
+            }
+            Self::Tcp(socket) => {
+                socket.getsockopt(level, optname, optval_ptr, optlen, memory_manager, cb_queue)
+            }
+            Self::Udp(socket) => {
+                socket.getsockopt(level, optname, optval_ptr, optlen, memory_manager, cb_queue)
+            }
+            Self::Icmp(socket) => {
+                socket.getsockopt(level, optname, optval_ptr, optlen, memory_manager, cb_queue)
+            }
+            Self::Raw(socket) => {
+                socket.getsockopt(level, optname, optval_ptr, optlen, memory_manager, cb_queue)
+            }
+        }
+    }
+
+    pub fn setsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        memory_manager: &MemoryManager,
+    ) -> Result<(), SyscallError> {
+        if let (libc::IPPROTO_IPV6, Some(mut options)) = (level, self.ipv6_options()) {
+            options.setsockopt(optname, optval_ptr, optlen, memory_manager)?;
+            self.set_ipv6_options(Some(options));
+            return Ok(());
+        }
+
+        match self {
+            Self::LegacyTcp(socket) => {
+                socket.setsockopt(level, optname, optval_ptr, optlen, memory_manager)
+            }
+            Self::Tcp(socket) => {
+                socket.setsockopt(level, optname, optval_ptr, optlen, memory_manager)
+            }
+            Self::Udp(socket) => {
+                socket.setsockopt(level, optname, optval_ptr, optlen, memory_manager)
+            }
+            Self::Icmp(socket) => {
+                socket.setsockopt(level, optname, optval_ptr, optlen, memory_manager)
+            }
+            Self::Raw(socket) => {
+                socket.setsockopt(level, optname, optval_ptr, optlen, memory_manager)
+            }
+        }
+    }
+
     pub fn accept(
         &mut self,
         net_ns: &NetworkNamespace,
@@ -353,26 +531,28 @@ impl InetSocketRefMut<'_> {
             Self::LegacyTcp(socket) => socket.accept(net_ns, rng, cb_queue),
             Self::Tcp(socket) => socket.accept(net_ns, rng, cb_queue),
             Self::Udp(socket) => socket.accept(net_ns, rng, cb_queue),
+            Self::Icmp(socket) => socket.accept(net_ns, rng, cb_queue),
+            Self::Raw(socket) => socket.accept(net_ns, rng, cb_queue),
         }
     }
 
-    enum_passthrough!(self, (how, cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (how, cb_queue), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn shutdown(&mut self, how: Shutdown, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
 }
 
 // inet socket-specific functions
 impl InetSocketRefMut<'_> {
-    enum_passthrough!(self, (packet, cb_queue, recv_time), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (packet, cb_queue, recv_time), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn push_in_packet(&mut self, packet: PacketRc, cb_queue: &mut CallbackQueue, recv_time: EmulatedTime)
     );
-    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (cb_queue), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn pull_out_packet(&mut self, cb_queue: &mut CallbackQueue) -> Option<PacketRc>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority>
     );
-    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp;
+    enum_passthrough!(self, (), LegacyTcp, Tcp, Udp, Icmp, Raw;
         pub fn has_data_to_send(&self) -> bool
     );
 }
@@ -383,6 +563,8 @@ impl std::fmt::Debug for InetSocketRef<'_> {
             Self::LegacyTcp(_) => write!(f, "LegacyTcp")?,
             Self::Tcp(_) => write!(f, "Tcp")?,
             Self::Udp(_) => write!(f, "Udp")?,
+            Self::Icmp(_) => write!(f, "Icmp")?,
+            Self::Raw(_) => write!(f, "Raw")?,
         }
 
         write!(
@@ -400,6 +582,8 @@ impl std::fmt::Debug for InetSocketRefMut<'_> {
             Self::LegacyTcp(_) => write!(f, "LegacyTcp")?,
             Self::Tcp(_) => write!(f, "Tcp")?,
             Self::Udp(_) => write!(f, "Udp")?,
+            Self::Icmp(_) => write!(f, "Icmp")?,
+            Self::Raw(_) => write!(f, "Raw")?,
         }
 
         write!(
@@ -416,6 +600,8 @@ pub enum InetSocketWeak {
     LegacyTcp(Weak<AtomicRefCell<LegacyTcpSocket>>),
     Tcp(Weak<AtomicRefCell<TcpSocket>>),
     Udp(Weak<AtomicRefCell<UdpSocket>>),
+    Icmp(Weak<AtomicRefCell<IcmpSocket>>),
+    Raw(Weak<AtomicRefCell<RawSocket>>),
 }
 
 impl InetSocketWeak {
@@ -424,22 +610,166 @@ impl InetSocketWeak {
             Self::LegacyTcp(x) => x.upgrade().map(InetSocket::LegacyTcp),
             Self::Tcp(x) => x.upgrade().map(InetSocket::Tcp),
             Self::Udp(x) => x.upgrade().map(InetSocket::Udp),
+            Self::Icmp(x) => x.upgrade().map(InetSocket::Icmp),
+            Self::Raw(x) => x.upgrade().map(InetSocket::Raw),
         }
     }
 }
 
+/// The options of an `AF_INET6` socket. Shadow's network only carries IPv4 packets, so an
+/// `AF_INET6` socket is an IPv4 socket whose addresses are translated to and from IPv6 addresses
+/// (see [`ipv6`]).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ipv6Options {
+    /// Is `IPV6_V6ONLY` enabled? If so, the socket can't use IPv4-mapped addresses. Since all
+    /// hosts have an IPv6 address, this doesn't stop the socket from receiving from IPv4 sockets.
+    v6only: bool,
+}
+
+impl Ipv6Options {
+    fn getsockopt(
+        &self,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &mut MemoryManager,
+    ) -> Result<libc::socklen_t, SyscallError> {
+        match optname {
+            libc::IPV6_V6ONLY => {
+                let val = libc::c_int::from(self.v6only);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            _ => {
+                log::debug!(
+                    "getsockopt called with unsupported level {} and opt {optname}",
+                    libc::IPPROTO_IPV6
+                );
+                Err(Errno::ENOPROTOOPT.into())
+            }
+        }
+    }
+
+    fn setsockopt(
+        &mut self,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
+    ) -> Result<(), SyscallError> {
+        match optname {
+            libc::IPV6_V6ONLY => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                self.v6only = val != 0;
+
+                Ok(())
+            }
+            _ => {
+                log::debug!(
+                    "setsockopt called with unsupported level {} and opt {optname}",
+                    libc::IPPROTO_IPV6
+                );
+                Err(Errno::ENOPROTOOPT.into())
+            }
+        }
+    }
+}
+
+/// How a socket address passed to an `AF_INET6` socket will be used, which determines which
+/// addresses are valid and the errors that are returned for invalid addresses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Ipv6AddrUse {
+    /// The local address for `bind()`.
+    Bind,
+    /// The peer address for a TCP socket's `connect()` or `sendto()`.
+    TcpPeer,
+    /// The peer address for a UDP socket's `connect()` or `sendto()`. Like Linux, these may also
+    /// be `AF_INET` addresses, or an `AF_UNSPEC` address to disconnect.
+    UdpPeer,
+}
+
+/// Translate a socket address given to an `AF_INET6` socket to the IPv4 socket address used by the
+/// underlying IPv4 socket.
+fn inet6_to_inet(
+    addr: &SockaddrStorage,
+    options: Ipv6Options,
+    addr_use: Ipv6AddrUse,
+) -> Result<SockaddrStorage, SyscallError> {
+    let Some(addr_v6) = addr.as_inet6() else {
+        if addr_use == Ipv6AddrUse::UdpPeer
+            && matches!(
+                addr.family(),
+                Some(AddressFamily::Inet | AddressFamily::Unspec)
+            )
+        {
+            return Ok(*addr);
+        }
+        return Err(Errno::EAFNOSUPPORT.into());
+    };
+
+    let addr_v6 = SocketAddrV6::from(*addr_v6);
+
+    let errno = match addr_use {
+        Ipv6AddrUse::Bind => Errno::EADDRNOTAVAIL,
+        Ipv6AddrUse::TcpPeer | Ipv6AddrUse::UdpPeer => Errno::ENETUNREACH,
+    };
+
+    if options.v6only && addr_v6.ip().to_ipv4_mapped().is_some() {
+        log::debug!("Can't use the IPv4-mapped address {addr_v6} with IPV6_V6ONLY");
+        return Err(match addr_use {
+            Ipv6AddrUse::Bind => Errno::EINVAL,
+            _ => errno,
+        }
+        .into());
+    }
+
+    let Some(addr_v4) = ipv6::to_socket_addr_v4(addr_v6) else {
+        log::debug!("The IPv6 address {addr_v6} doesn't belong to any host");
+        return Err(errno.into());
+    };
+
+    Ok(addr_v4.into())
+}
+
+/// Translate a socket address from the underlying IPv4 socket to the IPv6 socket address returned
+/// to the application. Addresses that aren't `AF_INET` addresses are returned unchanged.
+fn inet_to_inet6(addr: &SockaddrStorage) -> SockaddrStorage {
+    match addr.as_inet() {
+        Some(addr_v4) => ipv6::to_socket_addr_v6(SocketAddrV4::from(*addr_v4)).into(),
+        None => *addr,
+    }
+}
+
 /// Associate the socket with a network interface. If the local address is unspecified, the socket
 /// will be associated with every available interface. If the local address has a port of 0, a
 /// non-zero port will be chosen. The final local address will be returned. If the peer address is
 /// unspecified and has a port of 0, the socket will receive packets from every peer address. The
 /// socket will be automatically disassociated when the returned [`AssociationHandle`] is dropped.
 /// If `check_generic_peer` is true, the association will also fail if there is already a socket
-/// associated with the local address `local_addr` and peer address 0.0.0.0:0.
+/// associated with the local address `local_addr` and peer address 0.0.0.0:0. If a port needs to be
+/// chosen but there are no free ports in the host's ephemeral port range, `no_ports_errno` is
+/// returned (Linux returns a different error depending on the syscall). If `reuse_addr` is true (the
+/// socket's `SO_REUSEADDR` option), the socket may share the local address with other sockets that
+/// allow it (see [`NetworkNamespace::is_addr_shareable`]).
+#[allow(clippy::too_many_arguments)]
 fn associate_socket(
     socket: InetSocket,
     local_addr: SocketAddrV4,
     peer_addr: SocketAddrV4,
     check_generic_peer: bool,
+    reuse_addr: bool,
+    no_ports_errno: Errno,
     net_ns: &NetworkNamespace,
     rng: impl rand::Rng,
 ) -> Result<(SocketAddrV4, AssociationHandle), SyscallError> {
@@ -457,6 +787,9 @@ fn associate_socket(
         InetSocket::LegacyTcp(_) => c::_ProtocolType_PTCP,
         InetSocket::Tcp(_) => c::_ProtocolType_PTCP,
         InetSocket::Udp(_) => c::_ProtocolType_PUDP,
+        InetSocket::Icmp(_) => c::_ProtocolType_PICMP,
+        // raw sockets aren't associated with a port (see `NetworkNamespace::associate_raw`)
+        InetSocket::Raw(_) => panic!("Raw sockets can't be associated with a port"),
     };
 
     // get a free ephemeral port if they didn't specify one
@@ -467,7 +800,7 @@ fn associate_socket(
             net_ns.get_random_free_port(protocol, *local_addr.ip(), peer_addr, rng)
         else {
             log::debug!("Association required an ephemeral port but none are available");
-            return Err(Errno::EADDRINUSE.into());
+            return Err(no_ports_errno.into());
         };
 
         log::debug!("Associating with generated ephemeral port {new_port}");
@@ -478,6 +811,9 @@ fn associate_socket(
 
     // make sure the port is available at this address for this protocol
     match net_ns.is_addr_in_use(protocol, local_addr, peer_addr) {
+        Ok(true) if reuse_addr && net_ns.is_addr_shareable(protocol, local_addr, peer_addr) => {
+            log::debug!("Sharing the addresses (local={local_addr}, peer={peer_addr})");
+        }
         Ok(true) => {
             log::debug!(
                 "The provided addresses (local={local_addr}, peer={peer_addr}) are not available"
@@ -489,15 +825,14 @@ fn associate_socket(
     }
 
     if check_generic_peer {
-        match net_ns.is_addr_in_use(
-            protocol,
-            local_addr,
-            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
-        ) {
+        let generic_peer = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+        match net_ns.is_addr_in_use(protocol, local_addr, generic_peer) {
+            Ok(true)
+                if reuse_addr && net_ns.is_addr_shareable(protocol, local_addr, generic_peer) => {}
             Ok(true) => {
                 log::debug!(
-                    "The generic addresses (local={local_addr}, peer={}) are not available",
-                    SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)
+                    "The generic addresses (local={local_addr}, peer={generic_peer}) are not \
+                     available"
                 );
                 return Err(Errno::EADDRINUSE.into());
             }
@@ -506,10 +841,8 @@ fn associate_socket(
         }
     }
 
-    let socket = unsafe { c::compatsocket_fromInetSocket(&socket) };
-
     // associate the interfaces corresponding to addr with socket
-    let handle = unsafe { net_ns.associate_interface(&socket, protocol, local_addr, peer_addr) };
+    let handle = net_ns.associate_interface(&socket, protocol, local_addr, peer_addr);
 
     Ok((local_addr, handle))
 }
@@ -517,8 +850,6 @@ fn associate_socket(
 mod export {
     use super::*;
 
-    use shadow_shim_helper_rs::emulated_time::CEmulatedTime;
-
     /// Decrement the ref count of the `InetSocket` object. The pointer must not be used after
     /// calling this function.
     #[no_mangle]
@@ -551,64 +882,6 @@ mod export {
         socket.canonical_handle()
     }
 
-    #[no_mangle]
-    pub extern "C-unwind" fn inetsocket_pushInPacket(
-        socket: *const InetSocket,
-        packet: *mut c::Packet,
-        recv_time: CEmulatedTime,
-    ) {
-        let socket = unsafe { socket.as_ref() }.unwrap();
-        let recv_time = EmulatedTime::from_c_emutime(recv_time).unwrap();
-
-        // we don't own the reference to the packet, so we need our own reference
-        unsafe { c::packet_ref(packet) };
-        let packet = PacketRc::from_raw(packet);
-
-        crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
-            CallbackQueue::queue_and_run(|cb_queue| {
-                socket
-                    .borrow_mut()
-                    .push_in_packet(packet, cb_queue, recv_time);
-            });
-        });
-    }
-
-    #[no_mangle]
-    pub extern "C-unwind" fn inetsocket_pullOutPacket(socket: *const InetSocket) -> *mut c::Packet {
-        let socket = unsafe { socket.as_ref() }.unwrap();
-
-        crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
-            CallbackQueue::queue_and_run(|cb_queue| {
-                socket
-                    .borrow_mut()
-                    .pull_out_packet(cb_queue)
-                    .map(|p| p.into_inner())
-                    .unwrap_or(std::ptr::null_mut())
-            })
-        })
-    }
-
-    /// Will return non-zero if socket doesn't have data to send (there is no priority).
-    #[no_mangle]
-    pub extern "C-unwind" fn inetsocket_peekNextPacketPriority(
-        socket: *const InetSocket,
-        priority_out: *mut u64,
-    ) -> libc::c_int {
-        let socket = unsafe { socket.as_ref() }.unwrap();
-        let priority_out = unsafe { priority_out.as_mut() }.unwrap();
-        if let Some(priority) = socket.borrow().peek_next_packet_priority() {
-            *priority_out = priority;
-            return 0;
-        }
-        -1
-    }
-
-    #[no_mangle]
-    pub extern "C-unwind" fn inetsocket_hasDataToSend(socket: *const InetSocket) -> bool {
-        let socket = unsafe { socket.as_ref() }.unwrap();
-        socket.borrow().has_data_to_send()
-    }
-
     /// Get a legacy C [`TCP`](c::TCP) pointer for the socket. Will panic if `socket` is not a
     /// legacy TCP socket or if `socket` is already mutably borrowed. Will never return `NULL`.
     #[no_mangle]
@@ -0,0 +1,1019 @@
+//! Raw sockets, which applications create with `socket(AF_INET, SOCK_RAW, protocol)` to send and
+//! receive IP packets with their headers (see raw(7)).
+//!
+//! Unlike other inet sockets, raw sockets aren't associated with a port. A raw socket receives a
+//! copy of every packet with its IP protocol that the host receives, including packets that are
+//! also received by another socket or answered by the network stack, and it reads the packets
+//! starting at their IP header. Packets that are sent are converted to Shadow's packets, so only
+//! the protocols that Shadow supports can be sent: TCP, UDP, and ICMP echo messages.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use bytes::Bytes;
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use nix::sys::socket::{AddressFamily, MsgFlags, Shutdown, SockaddrIn};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::core::worker::Worker;
+use crate::cshadow as c;
+use crate::host::descriptor::socket::inet::udp::MessageBuffer;
+use crate::host::descriptor::socket::inet::{InetSocket, Ipv6Options};
+use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags};
+use crate::host::descriptor::{
+    File, FileMode, FileState, FileStatus, OpenFile, Socket, StateEventSource, StateListenerFilter,
+    SyscallResult,
+};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::network::interface::FifoPacketPriority;
+use crate::host::network::namespace::{NetworkNamespace, RawAssociationHandle};
+use crate::host::network::proc_net::{ProcNetEntry, SocketState};
+use crate::host::syscall::io::{write_partial, IoVec, IoVecReader, IoVecWriter};
+use crate::host::syscall_types::SyscallError;
+use crate::network::ipv4::{self, ParsedPacket, TransportHeader};
+use crate::network::packet::{PacketRc, PacketStatus};
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrStorage;
+use crate::utility::{HostTreePointer, ObjectCounter};
+
+/// The largest IPv4 packet.
+const MAX_PACKET_LEN: usize = u16::MAX as usize;
+
+pub struct RawSocket {
+    event_source: StateEventSource,
+    status: FileStatus,
+    state: FileState,
+    shutdown_status: ShutdownFlags,
+    /// The IP protocol of the packets that the socket sends and receives.
+    protocol: u8,
+    /// Whether the application writes the IP headers of the packets that it sends (`IP_HDRINCL`).
+    header_included: bool,
+    send_buffer: MessageBuffer<MessageSendHeader>,
+    /// The received packets, starting at their IP headers.
+    recv_buffer: MessageBuffer<MessageRecvHeader>,
+    /// If bound, the socket only receives packets sent to this address.
+    bound_ip: Option<Ipv4Addr>,
+    /// If connected, the socket only receives packets from this address.
+    peer_ip: Option<Ipv4Addr>,
+    association: Option<RawAssociationHandle>,
+    /// The receive time of the last packet returned to the managed process during a call to
+    /// `recvmsg()`. Used for `SIOCGSTAMP`.
+    recv_time_of_last_read_packet: Option<EmulatedTime>,
+    /// The number of received packets that were dropped because the receive buffer was full.
+    drops: u32,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+    _counter: ObjectCounter,
+}
+
+impl RawSocket {
+    /// A new raw socket for the IP protocol `protocol`. Like Linux, an `IPPROTO_RAW` socket can
+    /// only send packets and always includes the IP header. Sockets for other protocols start
+    /// receiving packets immediately.
+    pub fn new(
+        status: FileStatus,
+        protocol: u8,
+        send_buf_size: usize,
+        recv_buf_size: usize,
+        net_ns: &NetworkNamespace,
+    ) -> Arc<AtomicRefCell<Self>> {
+        let is_send_only = protocol == libc::IPPROTO_RAW as u8;
+
+        let mut socket = Self {
+            event_source: StateEventSource::new(),
+            status,
+            state: FileState::ACTIVE,
+            shutdown_status: ShutdownFlags::empty(),
+            protocol,
+            header_included: is_send_only,
+            send_buffer: MessageBuffer::new(send_buf_size),
+            recv_buffer: MessageBuffer::new(recv_buf_size),
+            bound_ip: None,
+            peer_ip: None,
+            association: None,
+            recv_time_of_last_read_packet: None,
+            drops: 0,
+            has_open_file: false,
+            _counter: ObjectCounter::new("RawSocket"),
+        };
+
+        CallbackQueue::queue_and_run(|cb_queue| socket.refresh_readable_writable(cb_queue));
+
+        let socket = Arc::new(AtomicRefCell::new(socket));
+
+        if !is_send_only {
+            let handle = net_ns.associate_raw(&socket, protocol);
+            socket.borrow_mut().association = Some(handle);
+        }
+
+        socket
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ | FileMode::WRITE
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        true
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    pub fn push_in_packet(
+        &mut self,
+        packet: PacketRc,
+        cb_queue: &mut CallbackQueue,
+        recv_time: EmulatedTime,
+    ) {
+        // raw sockets aren't associated with a port, so the interface gives them the packet's
+        // bytes instead
+        self.push_in_bytes(&ipv4::to_bytes(&packet), cb_queue, recv_time);
+    }
+
+    /// Receive a copy of the IPv4 packet `bytes`. The packet is ignored if it's not for the
+    /// socket's bound address or from its connected peer.
+    pub fn push_in_bytes(
+        &mut self,
+        bytes: &[u8],
+        cb_queue: &mut CallbackQueue,
+        recv_time: EmulatedTime,
+    ) {
+        let src = Ipv4Addr::from(<[u8; 4]>::try_from(&bytes[12..16]).unwrap());
+        let dst = Ipv4Addr::from(<[u8; 4]>::try_from(&bytes[16..20]).unwrap());
+
+        if self
+            .bound_ip
+            .is_some_and(|x| !x.is_unspecified() && x != dst)
+        {
+            return;
+        }
+
+        if self.peer_ip.is_some_and(|x| x != src) {
+            return;
+        }
+
+        if self.shutdown_status.contains(ShutdownFlags::READ) {
+            return;
+        }
+
+        let header = MessageRecvHeader { src, recv_time };
+
+        if self
+            .recv_buffer
+            .push_message(Bytes::copy_from_slice(bytes), header)
+            .is_err()
+        {
+            log::trace!("Dropping a packet since the raw socket's recv buffer is full");
+            self.drops = self.drops.wrapping_add(1);
+            return;
+        }
+
+        log::trace!("Added a packet to the raw socket's recv buffer");
+
+        self.refresh_readable_writable(cb_queue);
+    }
+
+    pub fn pull_out_packet(&mut self, cb_queue: &mut CallbackQueue) -> Option<PacketRc> {
+        let Some((data, header)) = self.send_buffer.pop_message() else {
+            log::debug!(
+                "Attempted to remove a message from the raw socket's send buffer, but none \
+                available"
+            );
+            return None;
+        };
+
+        let parsed = ParsedPacket {
+            src: header.src,
+            dst: header.dst,
+            header: header.header,
+            payload: data.to_vec(),
+        };
+
+        let mut packet = parsed.to_packet(header.packet_priority);
+        packet.add_status(PacketStatus::SndCreated);
+
+        self.refresh_readable_writable(cb_queue);
+
+        Some(packet)
+    }
+
+    pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority> {
+        self.send_buffer
+            .peek_message()
+            .map(|(_, header)| header.packet_priority)
+    }
+
+    pub fn has_data_to_send(&self) -> bool {
+        !self.send_buffer.is_empty()
+    }
+
+    pub fn getsockname(&self) -> Result<Option<SockaddrIn>, SyscallError> {
+        let ip = self.bound_ip.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        Ok(Some(SocketAddrV4::new(ip, 0).into()))
+    }
+
+    pub fn getpeername(&self) -> Result<Option<SockaddrIn>, SyscallError> {
+        let peer_ip = self.peer_ip.ok_or(Errno::ENOTCONN)?;
+        Ok(Some(SocketAddrV4::new(peer_ip, 0).into()))
+    }
+
+    pub fn address_family(&self) -> AddressFamily {
+        AddressFamily::Inet
+    }
+
+    /// Always `None`, since raw sockets are only supported for `AF_INET`.
+    pub fn ipv6_options(&self) -> Option<Ipv6Options> {
+        None
+    }
+
+    pub fn set_ipv6_options(&mut self, options: Option<Ipv6Options>) {
+        assert!(options.is_none(), "Raw sockets can't be AF_INET6 sockets");
+    }
+
+    /// The socket's line in `/proc/net/raw`. Like Linux, the local port is the socket's protocol.
+    pub fn proc_net_entry(&self) -> ProcNetEntry {
+        // like linux, the queue lengths include the per-message overhead
+        let tx_queue = self.send_buffer.alloc_bytes();
+        let rx_queue = self.recv_buffer.alloc_bytes();
+
+        ProcNetEntry {
+            local: SocketAddrV4::new(
+                self.bound_ip.unwrap_or(Ipv4Addr::UNSPECIFIED),
+                self.protocol.into(),
+            ),
+            peer: SocketAddrV4::new(self.peer_ip.unwrap_or(Ipv4Addr::UNSPECIFIED), 0),
+            state: match self.peer_ip {
+                Some(_) => SocketState::Established,
+                None => SocketState::Close,
+            },
+            tx_queue: tx_queue.try_into().unwrap_or(u32::MAX),
+            rx_queue: rx_queue.try_into().unwrap_or(u32::MAX),
+            drops: self.drops,
+        }
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // drop the existing association handle to disassociate the socket
+        self.association = None;
+
+        self.copy_state(
+            /* mask= */ FileState::all(),
+            FileState::CLOSED,
+            cb_queue,
+        );
+        Ok(())
+    }
+
+    pub fn bind(
+        socket: &Arc<AtomicRefCell<Self>>,
+        addr: Option<&SockaddrStorage>,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+    ) -> SyscallResult {
+        // if the address pointer was NULL
+        let Some(addr) = addr else {
+            return Err(Errno::EFAULT.into());
+        };
+
+        // if not an inet socket address
+        let Some(addr) = addr.as_inet() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // the port is ignored
+        let ip = *SocketAddrV4::from(*addr).ip();
+
+        if !ip.is_unspecified() && net_ns.interface_borrow(ip).is_none() {
+            return Err(Errno::EADDRNOTAVAIL.into());
+        }
+
+        socket.borrow_mut().bound_ip = Some(ip);
+
+        Ok(0.into())
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the readv() syscall handler should have called RawSocket::recvmsg() instead
+        panic!("Called RawSocket::readv() on a raw socket");
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the writev() syscall handler should have called RawSocket::sendmsg() instead
+        panic!("Called RawSocket::writev() on a raw socket");
+    }
+
+    pub fn sendmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: SendmsgArgs,
+        mem: &mut MemoryManager,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // if the file's writing has been shut down, return EPIPE
+        if socket
+            .borrow()
+            .shutdown_status
+            .contains(ShutdownFlags::WRITE)
+        {
+            return Err(Errno::EPIPE.into());
+        }
+
+        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
+            log::debug!("Unrecognized send flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        };
+
+        // the port of the destination address is ignored
+        let dst_ip = match args.addr {
+            Some(addr) => match addr.as_inet() {
+                // an inet socket address
+                Some(x) => *SocketAddrV4::from(*x).ip(),
+                // not an inet socket address
+                None => return Err(Errno::EAFNOSUPPORT.into()),
+            },
+            // no destination address provided
+            None => match socket.borrow().peer_ip {
+                Some(x) => x,
+                None => return Err(Errno::EDESTADDRREQ.into()),
+            },
+        };
+
+        if socket.borrow().status().contains(FileStatus::NONBLOCK) {
+            flags.insert(MsgFlags::MSG_DONTWAIT);
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        let mut socket_ref = socket.borrow_mut();
+
+        let max_len = if socket_ref.header_included {
+            MAX_PACKET_LEN
+        } else {
+            MAX_PACKET_LEN - ipv4::HEADER_LEN
+        };
+
+        // TODO: should use IP fragmentation to make sure packets fit within the MTU
+        if len > max_len {
+            return Err(Errno::EMSGSIZE.into());
+        }
+
+        // run in a closure so that an early return doesn't skip checking if we should block
+        let result = (|| {
+            // don't bother copying the bytes if we know the push will fail
+            if !socket_ref.send_buffer.has_space() {
+                return Err(Errno::EWOULDBLOCK);
+            }
+
+            let mut message = vec![0; len];
+            IoVecReader::new(args.iovs, mem)
+                .read_exact(&mut message)
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            // depending on the destination address, choose either localhost or the public IP
+            // address, unless the socket is bound
+            let src_ip = match socket_ref.bound_ip {
+                Some(ip) if !ip.is_unspecified() => ip,
+                _ if dst_ip.is_loopback() => Ipv4Addr::LOCALHOST,
+                _ => net_ns.default_ip,
+            };
+
+            let bytes = if socket_ref.header_included {
+                // like linux, a source address of 0 is filled in
+                if message.len() >= ipv4::HEADER_LEN && message[12..16] == [0; 4] {
+                    message[12..16].copy_from_slice(&src_ip.octets());
+                }
+                message
+            } else {
+                ipv4::with_header(src_ip, dst_ip, socket_ref.protocol, &message)
+            };
+
+            let parsed = match ipv4::parse(&bytes) {
+                Ok(x) => x,
+                Err(e) => {
+                    warn_once_then_debug!(
+                        "(LOG_ONCE) Raw sockets can only send TCP, UDP, and ICMP echo packets; \
+                        returning EINVAL"
+                    );
+                    log::debug!("Can't send packet from raw socket: {e}");
+                    return Err(Errno::EINVAL);
+                }
+            };
+
+            let src_ip = *parsed.src.ip();
+            let dst_ip = *parsed.dst.ip();
+
+            // the packet must be sent from one of the host's interfaces, since we route packets
+            // back to the host that owns their source address
+            if net_ns.interface_borrow(src_ip).is_none() {
+                log::debug!("Can't send packet from raw socket with source address {src_ip}");
+                return Err(Errno::EINVAL);
+            }
+            if src_ip.is_loopback() != dst_ip.is_loopback() {
+                return Err(Errno::EINVAL);
+            }
+
+            check_routable(net_ns, dst_ip)?;
+
+            // get the priority that we'll assign to the eventual packet
+            let packet_priority =
+                Worker::with_active_host(|host| host.get_next_packet_priority()).unwrap();
+
+            let header = MessageSendHeader {
+                src: parsed.src,
+                dst: parsed.dst,
+                header: parsed.header,
+                packet_priority,
+            };
+
+            // push the message to the send buffer (shouldn't fail since we checked for available
+            // space above)
+            socket_ref
+                .send_buffer
+                .push_message(parsed.payload.into(), header)
+                .unwrap();
+
+            // notify the host that this socket has packets to send
+            Self::notify_has_packets(socket, src_ip, cb_queue);
+
+            Ok(len)
+        })();
+
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        // if the syscall would block and we don't have the MSG_DONTWAIT flag
+        if result == Err(Errno::EWOULDBLOCK) && !flags.contains(MsgFlags::MSG_DONTWAIT) {
+            return Err(SyscallError::new_blocked_on_file(
+                File::Socket(Socket::Inet(InetSocket::Raw(socket.clone()))),
+                FileState::WRITABLE,
+                socket_ref.supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?.try_into().unwrap())
+    }
+
+    /// Notify the host that the socket has packets to send on the interface with address
+    /// `interface_ip`.
+    fn notify_has_packets(
+        socket: &Arc<AtomicRefCell<Self>>,
+        interface_ip: Ipv4Addr,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let socket = Arc::clone(socket);
+        cb_queue.add(move |_cb_queue| {
+            Worker::with_active_host(|host| {
+                let inet_socket = InetSocket::Raw(socket);
+                host.notify_socket_has_packets(interface_ip, &inet_socket);
+            })
+            .unwrap();
+        });
+    }
+
+    pub fn recvmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: RecvmsgArgs,
+        mem: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<RecvmsgReturn, SyscallError> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
+            log::debug!("Unrecognized recv flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        };
+
+        if socket_ref.status().contains(FileStatus::NONBLOCK) {
+            flags.insert(MsgFlags::MSG_DONTWAIT);
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        // run in a closure so that an early return doesn't skip checking if we should block
+        let result = (|| {
+            let (message, header) = if !flags.contains(MsgFlags::MSG_PEEK) {
+                socket_ref
+                    .recv_buffer
+                    .pop_message()
+                    .ok_or(Errno::EWOULDBLOCK)?
+            } else {
+                let (message, header) = socket_ref
+                    .recv_buffer
+                    .peek_message()
+                    .ok_or(Errno::EWOULDBLOCK)?;
+                (message.clone(), *header)
+            };
+
+            // truncate the message if it's larger than the user-provided buffers
+            let truncated_message = &message[..std::cmp::min(len, message.len())];
+
+            // write the truncated message to the iovs
+            let mut writer = IoVecWriter::new(args.iovs, mem);
+            writer
+                .write_all(truncated_message)
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            let return_val = if flags.contains(MsgFlags::MSG_TRUNC) {
+                message.len()
+            } else {
+                // the number of bytes written
+                truncated_message.len()
+            };
+
+            let mut return_flags = MsgFlags::empty();
+            return_flags.set(MsgFlags::MSG_TRUNC, truncated_message.len() < message.len());
+
+            // update the cache of the last recv time
+            socket_ref.recv_time_of_last_read_packet = Some(header.recv_time);
+
+            Ok(RecvmsgReturn {
+                return_val: return_val.try_into().unwrap(),
+                // like linux, the port of the source address is 0
+                addr: Some(SocketAddrV4::new(header.src, 0).into()),
+                msg_flags: return_flags.bits(),
+                control_len: 0,
+            })
+        })();
+
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        // if the syscall would block and we don't have the MSG_DONTWAIT flag
+        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
+            // if the syscall would block but the file's reading has been shut down, return EOF
+            if socket_ref.shutdown_status.contains(ShutdownFlags::READ) {
+                return Ok(RecvmsgReturn {
+                    return_val: 0,
+                    addr: None,
+                    msg_flags: 0,
+                    control_len: 0,
+                });
+            }
+
+            return Err(SyscallError::new_blocked_on_file(
+                File::Socket(Socket::Inet(InetSocket::Raw(socket.clone()))),
+                FileState::READABLE,
+                socket_ref.supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?)
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+        mem: &mut MemoryManager,
+    ) -> SyscallResult {
+        match request {
+            // equivalent to SIOCINQ
+            IoctlRequest::FIONREAD => {
+                let len = self
+                    .recv_buffer
+                    .peek_message()
+                    .map(|m| m.0.len())
+                    .unwrap_or(0)
+                    .try_into()
+                    .unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            // equivalent to SIOCOUTQ
+            IoctlRequest::TIOCOUTQ => {
+                let len = self.send_buffer.len_bytes().try_into().unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            IoctlRequest::SIOCGSTAMP => {
+                let Some(last_recv_time) = self.recv_time_of_last_read_packet else {
+                    return Err(Errno::ENOENT.into());
+                };
+
+                let last_recv_time = (last_recv_time - EmulatedTime::UNIX_EPOCH)
+                    .try_into()
+                    .unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::timeval>();
+                mem.write(arg_ptr, &last_recv_time)?;
+
+                Ok(0.into())
+            }
+            IoctlRequest::FIONBIO => {
+                panic!("This should have been handled by the ioctl syscall handler");
+            }
+            IoctlRequest::TCGETS
+            | IoctlRequest::TCSETS
+            | IoctlRequest::TCSETSW
+            | IoctlRequest::TCSETSF
+            | IoctlRequest::TCGETA
+            | IoctlRequest::TCSETA
+            | IoctlRequest::TCSETAW
+            | IoctlRequest::TCSETAF
+            | IoctlRequest::TIOCGWINSZ
+            | IoctlRequest::TIOCSWINSZ => {
+                // not a terminal
+                Err(Errno::ENOTTY.into())
+            }
+            request => {
+                warn_once_then_debug!(
+                    "(LOG_ONCE) We do not yet handle ioctl request {request:?} on raw sockets"
+                );
+                Err(Errno::EINVAL.into())
+            }
+        }
+    }
+
+    pub fn listen(
+        _socket: &Arc<AtomicRefCell<Self>>,
+        _backlog: i32,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn connect(
+        socket: &Arc<AtomicRefCell<Self>>,
+        peer_addr: &SockaddrStorage,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        // if not an inet socket address
+        let Some(peer_addr) = peer_addr.as_inet() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // the port of the peer address is ignored
+        let mut peer_ip = *SocketAddrV4::from(*peer_addr).ip();
+
+        if peer_ip.is_unspecified() {
+            peer_ip = Ipv4Addr::LOCALHOST;
+        }
+
+        check_routable(net_ns, peer_ip)?;
+
+        socket.borrow_mut().peer_ip = Some(peer_ip);
+
+        Ok(())
+    }
+
+    pub fn accept(
+        &mut self,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<OpenFile, SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn shutdown(
+        &mut self,
+        how: Shutdown,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        if self.peer_ip.is_none() {
+            return Err(Errno::ENOTCONN.into());
+        }
+
+        if how == Shutdown::Write || how == Shutdown::Both {
+            self.shutdown_status.insert(ShutdownFlags::WRITE)
+        }
+
+        if how == Shutdown::Read || how == Shutdown::Both {
+            self.shutdown_status.insert(ShutdownFlags::READ)
+        }
+
+        Ok(())
+    }
+
+    pub fn getsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::socklen_t, SyscallError> {
+        let val: libc::c_int = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
+                self.send_buffer.soft_limit_bytes().try_into().unwrap()
+            }
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                self.recv_buffer.soft_limit_bytes().try_into().unwrap()
+            }
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => libc::AF_INET,
+            (libc::SOL_SOCKET, libc::SO_TYPE) => libc::SOCK_RAW,
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => self.protocol.into(),
+            (libc::SOL_SOCKET, libc::SO_ACCEPTCONN) => 0,
+            (libc::IPPROTO_IP, libc::IP_HDRINCL) => self.header_included.into(),
+            (libc::SOL_SOCKET, _) => {
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+            _ => {
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::EOPNOTSUPP.into());
+            }
+        };
+
+        let optval_ptr = optval_ptr.cast::<libc::c_int>();
+        let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+        Ok(bytes_written as libc::socklen_t)
+    }
+
+    pub fn setsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
+    ) -> Result<(), SyscallError> {
+        type OptType = libc::c_int;
+
+        let read_val = || -> Result<OptType, SyscallError> {
+            if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                return Err(Errno::EINVAL.into());
+            }
+            Ok(mem.read(optval_ptr.cast::<OptType>())?)
+        };
+
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF | libc::SO_RCVBUF) => {
+                let val: u64 = read_val()?.try_into().or(Err(Errno::EINVAL))?;
+
+                // linux kernel doubles this value upon setting, and we use the same limits as
+                // for UDP sockets
+                if optname == libc::SO_SNDBUF {
+                    let val = (val * 2).clamp(4096, 268435456); // 2^28 = 256 MiB
+                    self.send_buffer
+                        .set_soft_limit_bytes(val.try_into().unwrap());
+                } else {
+                    let val = (val * 2).clamp(2048, 268435456);
+                    self.recv_buffer
+                        .set_soft_limit_bytes(val.try_into().unwrap());
+                }
+            }
+            (libc::IPPROTO_IP, libc::IP_HDRINCL) => {
+                self.header_included = read_val()? != 0;
+            }
+            _ => {
+                log::debug!("setsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring: FileState,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
+    ) -> Handle<(FileState, FileState)> {
+        self.event_source
+            .add_listener(monitoring, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn refresh_readable_writable(&mut self, cb_queue: &mut CallbackQueue) {
+        let readable = !self.recv_buffer.is_empty();
+        let writable = self.send_buffer.has_space();
+
+        let readable = readable.then_some(FileState::READABLE).unwrap_or_default();
+        let writable = writable.then_some(FileState::WRITABLE).unwrap_or_default();
+
+        self.copy_state(
+            /* mask= */ FileState::READABLE | FileState::WRITABLE,
+            readable | writable,
+            cb_queue,
+        );
+    }
+
+    fn copy_state(&mut self, mask: FileState, state: FileState, cb_queue: &mut CallbackQueue) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, cb_queue);
+    }
+
+    fn handle_state_change(&mut self, old_state: FileState, cb_queue: &mut CallbackQueue) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, cb_queue);
+    }
+}
+
+/// Returns `ENETUNREACH` if there's no host with the address `ip`, since we'd have nowhere to send
+/// the packets.
+fn check_routable(net_ns: &NetworkNamespace, ip: Ipv4Addr) -> Result<(), Errno> {
+    if ip.is_loopback() || Worker::is_routable(net_ns.default_ip.into(), ip.into()) {
+        return Ok(());
+    }
+
+    log::debug!("Attempting to send to address '{ip}' for which no host exists");
+    Err(Errno::ENETUNREACH)
+}
+
+/// Non-payload data for a message in the send buffer.
+#[derive(Debug)]
+struct MessageSendHeader {
+    src: SocketAddrV4,
+    dst: SocketAddrV4,
+    header: TransportHeader,
+    /// The priority for the packet that we'll create in the future, given to us by the host.
+    packet_priority: FifoPacketPriority,
+}
+
+/// Non-payload data for a message in the receive buffer.
+#[derive(Debug, Copy, Clone)]
+struct MessageRecvHeader {
+    /// The source address of the packet.
+    src: Ipv4Addr,
+    /// The time when the network interface received the packet.
+    recv_time: EmulatedTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 1);
+    const B: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 2);
+    const C: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 3);
+
+    /// A raw ICMP socket that isn't associated with a network namespace, so it only receives the
+    /// packets given to it directly.
+    fn socket(recv_buf_size: usize) -> RawSocket {
+        RawSocket {
+            event_source: StateEventSource::new(),
+            status: FileStatus::empty(),
+            state: FileState::ACTIVE,
+            shutdown_status: ShutdownFlags::empty(),
+            protocol: libc::IPPROTO_ICMP as u8,
+            header_included: false,
+            send_buffer: MessageBuffer::new(65536),
+            recv_buffer: MessageBuffer::new(recv_buf_size),
+            bound_ip: None,
+            peer_ip: None,
+            association: None,
+            recv_time_of_last_read_packet: None,
+            drops: 0,
+            has_open_file: false,
+            _counter: ObjectCounter::new("RawSocket"),
+        }
+    }
+
+    /// An IPv4 header from `src` to `dst`, followed by an 8 byte payload.
+    fn packet(src: Ipv4Addr, dst: Ipv4Addr) -> Vec<u8> {
+        let mut bytes = vec![0; 28];
+        bytes[0] = 0x45;
+        bytes[2..4].copy_from_slice(&28u16.to_be_bytes());
+        bytes[9] = libc::IPPROTO_ICMP as u8;
+        bytes[12..16].copy_from_slice(&src.octets());
+        bytes[16..20].copy_from_slice(&dst.octets());
+        bytes
+    }
+
+    /// Give the packet from `src` to `dst` to the socket, and return the source address of the
+    /// packet that the socket received, if any.
+    fn push(socket: &mut RawSocket, src: Ipv4Addr, dst: Ipv4Addr) -> Option<Ipv4Addr> {
+        let bytes = packet(src, dst);
+        CallbackQueue::queue_and_run(|cb_queue| {
+            socket.push_in_bytes(&bytes, cb_queue, EmulatedTime::SIMULATION_START)
+        });
+
+        let (data, header) = socket.recv_buffer.pop_message()?;
+        assert_eq!(data, bytes);
+        Some(header.src)
+    }
+
+    #[test]
+    fn test_push_in_bytes() {
+        let mut socket = socket(65536);
+        assert!(!socket.state().contains(FileState::READABLE));
+
+        let bytes = packet(A, B);
+        CallbackQueue::queue_and_run(|cb_queue| {
+            socket.push_in_bytes(&bytes, cb_queue, EmulatedTime::SIMULATION_START)
+        });
+        assert!(socket.state().contains(FileState::READABLE));
+
+        // the packet is read starting at its IP header
+        let (data, header) = socket.recv_buffer.pop_message().unwrap();
+        assert_eq!(data, bytes);
+        assert_eq!(header.src, A);
+        assert_eq!(header.recv_time, EmulatedTime::SIMULATION_START);
+    }
+
+    #[test]
+    fn test_push_in_bytes_filters() {
+        // an unbound and unconnected socket receives every packet
+        let mut socket = socket(65536);
+        assert_eq!(push(&mut socket, A, B), Some(A));
+        assert_eq!(push(&mut socket, C, A), Some(C));
+
+        // a bound socket only receives packets to its address
+        socket.bound_ip = Some(B);
+        assert_eq!(push(&mut socket, A, B), Some(A));
+        assert_eq!(push(&mut socket, A, C), None);
+
+        // a socket bound to the unspecified address receives packets to any address
+        socket.bound_ip = Some(Ipv4Addr::UNSPECIFIED);
+        assert_eq!(push(&mut socket, A, C), Some(A));
+
+        // a connected socket only receives packets from its peer
+        socket.peer_ip = Some(A);
+        assert_eq!(push(&mut socket, A, B), Some(A));
+        assert_eq!(push(&mut socket, C, B), None);
+
+        // a socket that was shut down for reading doesn't receive packets
+        socket.shutdown_status.insert(ShutdownFlags::READ);
+        assert_eq!(push(&mut socket, A, B), None);
+    }
+
+    #[test]
+    fn test_push_in_bytes_full() {
+        // like linux, a packet is accepted as long as the buffer isn't already full
+        let mut socket = socket(1);
+
+        for _ in 0..3 {
+            let bytes = packet(A, B);
+            CallbackQueue::queue_and_run(|cb_queue| {
+                socket.push_in_bytes(&bytes, cb_queue, EmulatedTime::SIMULATION_START)
+            });
+        }
+
+        // the later packets were dropped and counted
+        assert!(socket.recv_buffer.pop_message().is_some());
+        assert!(socket.recv_buffer.pop_message().is_none());
+        assert_eq!(socket.drops, 2);
+    }
+}
@@ -13,15 +13,19 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use crate::core::work::task::TaskRef;
 use crate::core::worker::Worker;
 use crate::cshadow as c;
-use crate::host::descriptor::socket::inet;
-use crate::host::descriptor::socket::{InetSocket, RecvmsgArgs, RecvmsgReturn, SendmsgArgs};
+use crate::host::descriptor::socket::inet::{self, Ipv6Options};
+use crate::host::descriptor::socket::{
+    InetSocket, RecvmsgArgs, RecvmsgReturn, SendmsgArgs, SocketFlagOptions,
+};
 use crate::host::descriptor::{File, Socket};
 use crate::host::descriptor::{
     FileMode, FileState, FileStatus, OpenFile, StateEventSource, StateListenerFilter, SyscallResult,
 };
+use crate::host::host::HostParameters;
 use crate::host::memory_manager::MemoryManager;
-use crate::host::network::interface::FifoPacketPriority;
+use crate::host::network::interface::{FifoPacketPriority, FIFO_CONTROL_PRIORITY};
 use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
+use crate::host::network::proc_net::{ProcNetEntry, SocketState};
 use crate::host::syscall::io::{write_partial, IoVec, IoVecReader, IoVecWriter};
 use crate::host::syscall_types::SyscallError;
 use crate::network::packet::{PacketRc, PacketStatus};
@@ -38,6 +42,11 @@ pub struct TcpSocket {
     association: Option<AssociationHandle>,
     connect_result_is_pending: bool,
     shutdown_status: Option<Shutdown>,
+    flag_options: SocketFlagOptions,
+    /// `Some` if this is an `AF_INET6` socket.
+    ipv6_options: Option<Ipv6Options>,
+    /// The `TCP_NODELAY`, `TCP_CORK`, and `TCP_QUICKACK` options.
+    tcp_options: TcpFlagOptions,
     // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
     // this file
     has_open_file: bool,
@@ -45,7 +54,17 @@ pub struct TcpSocket {
 }
 
 impl TcpSocket {
-    pub fn new(status: FileStatus) -> Arc<AtomicRefCell<Self>> {
+    /// The configuration of a new socket on a host with the parameters `params`.
+    pub fn config_for_host(params: &HostParameters) -> tcp::TcpConfig {
+        let mut config = tcp::TcpConfig::default();
+        config.window_scaling(params.tcp_window_scaling);
+        config.timestamps(params.tcp_timestamps);
+        config.time_wait_timeout(params.tcp_time_wait.timeout.into());
+        config.delayed_ack((&params.tcp_delayed_ack).into());
+        config
+    }
+
+    pub fn new(status: FileStatus, config: tcp::TcpConfig) -> Arc<AtomicRefCell<Self>> {
         let rv = Arc::new_cyclic(|weak: &Weak<AtomicRefCell<Self>>| {
             let tcp_dependencies = TcpDeps {
                 timer_state: Arc::new(AtomicRefCell::new(TcpDepsTimerState {
@@ -55,7 +74,7 @@ impl TcpSocket {
             };
 
             AtomicRefCell::new(Self {
-                tcp_state: tcp::TcpState::new(tcp_dependencies, tcp::TcpConfig::default()),
+                tcp_state: tcp::TcpState::new(tcp_dependencies, config),
                 socket_weak: weak.clone(),
                 event_source: StateEventSource::new(),
                 status,
@@ -65,6 +84,9 @@ impl TcpSocket {
                 association: None,
                 connect_result_is_pending: false,
                 shutdown_status: None,
+                flag_options: SocketFlagOptions::default(),
+                ipv6_options: None,
+                tcp_options: TcpFlagOptions::default(),
                 has_open_file: false,
                 _counter: ObjectCounter::new("TcpSocket"),
             })
@@ -109,8 +131,34 @@ impl TcpSocket {
         cb_queue: &mut CallbackQueue,
         f: impl FnOnce(&mut tcp::TcpState<TcpDeps>) -> T,
     ) -> T {
+        let was_connecting = self.tcp_state.poll().contains(tcp::PollState::CONNECTING);
+        let old_kind = self.tcp_state.kind();
+
         let rv = f(&mut self.tcp_state);
 
+        let new_kind = self.tcp_state.kind();
+        if new_kind != old_kind {
+            let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+            let (local, peer) = self
+                .tcp_state
+                .local_remote_addrs()
+                .unwrap_or((unspecified, unspecified));
+            Worker::call_hooks(|hooks, host_id, now| {
+                hooks.tcp_state_changed(host_id, now, local, peer, old_kind, new_kind)
+            });
+        }
+
+        // a connection attempt that finished without an error was established
+        let poll_state = self.tcp_state.poll();
+        if was_connecting
+            && poll_state.contains(tcp::PollState::CONNECTED)
+            && !poll_state.contains(tcp::PollState::ERROR)
+        {
+            if let Some((local, remote)) = self.tcp_state.local_remote_addrs() {
+                Worker::trace_connection_established(&local.to_string(), &remote.to_string());
+            }
+        }
+
         // we may have mutated the tcp state, so update the socket's file state and notify listeners
 
         // if there are packets to send, notify the host
@@ -134,8 +182,7 @@ impl TcpSocket {
             cb_queue.add(move |_cb_queue| {
                 Worker::with_active_host(|host| {
                     let inet_socket = InetSocket::Tcp(socket);
-                    let compat_socket = unsafe { c::compatsocket_fromInetSocket(&inet_socket) };
-                    host.notify_socket_has_packets(interface_ip, &compat_socket);
+                    host.notify_socket_has_packets(interface_ip, &inet_socket);
                 })
                 .unwrap();
             });
@@ -256,7 +303,7 @@ impl TcpSocket {
 
         packet.set_tcp(&header);
         // TODO: set packet priority?
-        packet.set_payload(&payload, /* priority= */ 0);
+        packet.set_payload(&payload, FIFO_CONTROL_PRIORITY);
         packet.add_status(PacketStatus::SndCreated);
 
         Some(packet)
@@ -264,7 +311,7 @@ impl TcpSocket {
 
     pub fn peek_next_packet_priority(&self) -> Option<FifoPacketPriority> {
         // TODO: support packet priorities?
-        self.has_data_to_send().then_some(0)
+        self.has_data_to_send().then_some(FIFO_CONTROL_PRIORITY)
     }
 
     pub fn has_data_to_send(&self) -> bool {
@@ -303,7 +350,54 @@ impl TcpSocket {
     }
 
     pub fn address_family(&self) -> AddressFamily {
-        AddressFamily::Inet
+        match self.ipv6_options {
+            Some(_) => AddressFamily::Inet6,
+            None => AddressFamily::Inet,
+        }
+    }
+
+    pub fn ipv6_options(&self) -> Option<Ipv6Options> {
+        self.ipv6_options
+    }
+
+    pub fn set_ipv6_options(&mut self, options: Option<Ipv6Options>) {
+        self.ipv6_options = options;
+    }
+
+    /// The socket's line in `/proc/net/tcp`. The queue lengths aren't available from the TCP
+    /// state, so are always 0.
+    pub fn proc_net_entry(&self) -> ProcNetEntry {
+        let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+        let (local, peer) = self.tcp_state.local_remote_addrs().unwrap_or_else(|| {
+            let local = self.association.as_ref().map(|x| x.local_addr());
+            (local.unwrap_or(unspecified), unspecified)
+        });
+
+        let state = match self.tcp_state.kind() {
+            tcp::TcpStateKind::Init | tcp::TcpStateKind::Rst | tcp::TcpStateKind::Closed => {
+                SocketState::Close
+            }
+            tcp::TcpStateKind::Listen => SocketState::Listen,
+            tcp::TcpStateKind::SynSent => SocketState::SynSent,
+            tcp::TcpStateKind::SynReceived => SocketState::SynRecv,
+            tcp::TcpStateKind::Established => SocketState::Established,
+            tcp::TcpStateKind::FinWaitOne => SocketState::FinWait1,
+            tcp::TcpStateKind::FinWaitTwo => SocketState::FinWait2,
+            tcp::TcpStateKind::Closing => SocketState::Closing,
+            tcp::TcpStateKind::TimeWait => SocketState::TimeWait,
+            tcp::TcpStateKind::CloseWait => SocketState::CloseWait,
+            tcp::TcpStateKind::LastAck => SocketState::LastAck,
+        };
+
+        ProcNetEntry {
+            local,
+            peer,
+            state,
+            tx_queue: 0,
+            rx_queue: 0,
+            drops: 0,
+        }
     }
 
     pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
@@ -351,6 +445,8 @@ impl TcpSocket {
             addr,
             peer_addr,
             /* check_generic_peer= */ true,
+            /* reuse_addr= */ false,
+            Errno::EADDRINUSE,
             net_ns,
             rng,
         )?;
@@ -557,6 +653,8 @@ impl TcpSocket {
                     local_addr,
                     peer_addr,
                     /* check_generic_peer= */ true,
+                    /* reuse_addr= */ false,
+                    Errno::EADDRINUSE,
                     net_ns,
                     rng,
                 )?;
@@ -666,6 +764,8 @@ impl TcpSocket {
                     local_addr,
                     peer_addr,
                     /* check_generic_peer= */ true,
+                    /* reuse_addr= */ false,
+                    Errno::EADDRNOTAVAIL,
                     net_ns,
                     rng,
                 )?;
@@ -742,6 +842,10 @@ impl TcpSocket {
         let local_addr = accepted_state.local_addr();
         let remote_addr = accepted_state.remote_addr();
 
+        // the child connection doesn't have a socket until it's accepted, so it's traced as being
+        // established when it's accepted
+        Worker::trace_connection_established(&local_addr.to_string(), &remote_addr.to_string());
+
         // convert the accepted tcp state to a full tcp socket
         let new_socket = Arc::new_cyclic(|weak: &Weak<AtomicRefCell<Self>>| {
             let accepted_state = accepted_state.finalize(|deps| {
@@ -763,6 +867,10 @@ impl TcpSocket {
                 association: None,
                 connect_result_is_pending: false,
                 shutdown_status: None,
+                // like linux, the socket inherits the listening socket's options
+                flag_options: self.flag_options,
+                ipv6_options: self.ipv6_options,
+                tcp_options: self.tcp_options,
                 has_open_file: false,
                 _counter: ObjectCounter::new("TcpSocket"),
             })
@@ -782,6 +890,8 @@ impl TcpSocket {
             local_addr,
             remote_addr,
             /* check_generic_peer= */ false,
+            /* reuse_addr= */ false,
+            Errno::EADDRINUSE,
             net_ns,
             rng,
         )?;
@@ -871,7 +981,7 @@ impl TcpSocket {
                 Ok(bytes_written as libc::socklen_t)
             }
             (libc::SOL_SOCKET, libc::SO_DOMAIN) => {
-                let domain = libc::AF_INET;
+                let domain = self.address_family() as libc::c_int;
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written = write_partial(mem, &domain, optval_ptr, optlen as usize)?;
@@ -903,6 +1013,25 @@ impl TcpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                let val = libc::c_int::from(self.flag_options.get(optname));
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::SOL_TCP, libc::TCP_NODELAY | libc::TCP_CORK | libc::TCP_QUICKACK) => {
+                let val = libc::c_int::from(self.tcp_options.get(optname));
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             _ => {
                 log::warn!("getsockopt called with unsupported level {level} and opt {optname}");
                 Err(Errno::ENOPROTOOPT.into())
@@ -914,26 +1043,53 @@ impl TcpSocket {
         &mut self,
         level: libc::c_int,
         optname: libc::c_int,
-        _optval_ptr: ForeignPtr<()>,
-        _optlen: libc::socklen_t,
-        _mem: &MemoryManager,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
     ) -> Result<(), SyscallError> {
         match (level, optname) {
-            (libc::SOL_SOCKET, libc::SO_REUSEADDR) => {
-                // TODO: implement this, tor and tgen use it
-                log::trace!("setsockopt SO_REUSEADDR not yet implemented");
-            }
-            (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
-                // TODO: implement this, tgen uses it
-                log::trace!("setsockopt SO_REUSEPORT not yet implemented");
-            }
-            (libc::SOL_SOCKET, libc::SO_KEEPALIVE) => {
-                // TODO: implement this, libevent uses it in evconnlistener_new_bind()
-                log::trace!("setsockopt SO_KEEPALIVE not yet implemented");
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                // TODO: implement these; tor and tgen use SO_REUSEADDR, tgen uses SO_REUSEPORT,
+                // libevent uses SO_KEEPALIVE in evconnlistener_new_bind(), and pkg.go.dev/net uses
+                // SO_BROADCAST
+                log::trace!("setsockopt option {optname} not yet implemented");
+
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                // the value is still returned by getsockopt()
+                self.flag_options.set(optname, val != 0);
             }
-            (libc::SOL_SOCKET, libc::SO_BROADCAST) => {
-                // TODO: implement this, pkg.go.dev/net uses it
-                log::trace!("setsockopt SO_BROADCAST not yet implemented");
+            (libc::SOL_TCP, libc::TCP_NODELAY | libc::TCP_CORK | libc::TCP_QUICKACK) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let enable = mem.read(optval_ptr)? != 0;
+
+                self.tcp_options.set(optname, enable);
+
+                // this may release data that is being held, so the socket may want to send
+                CallbackQueue::queue_and_run(|cb_queue| {
+                    self.with_tcp_state(cb_queue, |state| match optname {
+                        libc::TCP_NODELAY => state.set_nodelay(enable),
+                        libc::TCP_CORK => state.set_cork(enable),
+                        libc::TCP_QUICKACK => state.set_quickack(enable),
+                        _ => unreachable!(),
+                    })
+                });
             }
             _ => {
                 log::warn!("setsockopt called with unsupported level {level} and opt {optname}");
@@ -999,6 +1155,50 @@ fn tcp_error_to_errno(error: tcp::TcpError) -> Errno {
     }
 }
 
+/// The values of the `SOL_TCP` options that can be enabled or disabled.
+#[derive(Copy, Clone, Debug)]
+struct TcpFlagOptions {
+    nodelay: bool,
+    cork: bool,
+    quickack: bool,
+}
+
+impl TcpFlagOptions {
+    /// Get the value of the `TCP_NODELAY`, `TCP_CORK`, or `TCP_QUICKACK` option. Panics for any
+    /// other option.
+    fn get(&self, optname: libc::c_int) -> bool {
+        let mut options = *self;
+        *options.option_mut(optname)
+    }
+
+    /// Set the value of the `TCP_NODELAY`, `TCP_CORK`, or `TCP_QUICKACK` option. Panics for any
+    /// other option.
+    fn set(&mut self, optname: libc::c_int, val: bool) {
+        *self.option_mut(optname) = val;
+    }
+
+    fn option_mut(&mut self, optname: libc::c_int) -> &mut bool {
+        match optname {
+            libc::TCP_NODELAY => &mut self.nodelay,
+            libc::TCP_CORK => &mut self.cork,
+            libc::TCP_QUICKACK => &mut self.quickack,
+            _ => panic!("Socket option {optname} is not a stored tcp flag option"),
+        }
+    }
+}
+
+impl Default for TcpFlagOptions {
+    fn default() -> Self {
+        Self {
+            // like linux, nagle's algorithm is used by default
+            nodelay: false,
+            cork: false,
+            // like shadow's legacy tcp stack, acknowledgements are delayed by default
+            quickack: false,
+        }
+    }
+}
+
 /// Shared state stored in timers. This allows us to update existing timers when a child `TcpState`
 /// is accept()ed and becomes owned by a new `TcpSocket` object.
 #[derive(Debug)]
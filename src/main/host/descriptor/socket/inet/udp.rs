@@ -13,17 +13,20 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 
 use crate::core::worker::Worker;
 use crate::cshadow as c;
-use crate::host::descriptor::socket::inet::{self, InetSocket};
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags};
+use crate::host::descriptor::socket::inet::{self, InetSocket, Ipv6Options};
+use crate::host::descriptor::socket::{
+    RecvmsgArgs, RecvmsgReturn, SendmsgArgs, ShutdownFlags, SocketFlagOptions,
+};
 use crate::host::descriptor::{
     File, FileMode, FileState, FileStatus, OpenFile, Socket, StateEventSource, StateListenerFilter,
     SyscallResult,
 };
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::interface::FifoPacketPriority;
-use crate::host::network::namespace::{AssociationHandle, NetworkNamespace};
+use crate::host::network::namespace::{AssociationHandle, MulticastMembership, NetworkNamespace};
+use crate::host::network::proc_net::{ProcNetEntry, SocketState};
 use crate::host::syscall::io::{write_partial, IoVec, IoVecReader, IoVecWriter};
-use crate::host::syscall_types::SyscallError;
+use crate::host::syscall_types::{ForeignArrayPtr, SyscallError};
 use crate::network::packet::{PacketRc, PacketStatus};
 use crate::utility::callback_queue::{CallbackQueue, Handle};
 use crate::utility::sockaddr::SockaddrStorage;
@@ -33,6 +36,15 @@ use crate::utility::{HostTreePointer, ObjectCounter};
 // 65,535 (2^16 - 1) - 20 (ip header) - 8 (udp header)
 const CONFIG_DATAGRAM_MAX_SIZE: usize = 65507;
 
+/// The number of bytes that each message is charged against the socket buffer size in addition to
+/// its payload. This approximates the overhead of the `sk_buff` and `skb_shared_info` structs that
+/// Linux includes in an skb's `truesize`.
+const MESSAGE_OVERHEAD_BYTES: usize = 576;
+
+/// The maximum number of multicast groups that a socket can join, which is the default value of
+/// Linux's `net.ipv4.igmp_max_memberships` sysctl.
+const MAX_MULTICAST_MEMBERSHIPS: usize = 20;
+
 pub struct UdpSocket {
     event_source: StateEventSource,
     status: FileStatus,
@@ -46,6 +58,19 @@ pub struct UdpSocket {
     /// The receive time of the last packet returned to the managed process during a call to
     /// `recvmsg()`. Used for `SIOCGSTAMP`.
     recv_time_of_last_read_packet: Option<EmulatedTime>,
+    /// The number of received packets that were dropped because the receive buffer was full.
+    drops: u32,
+    /// Is `SO_RXQ_OVFL` enabled?
+    rxq_ovfl: bool,
+    flag_options: SocketFlagOptions,
+    /// `Some` if this is an `AF_INET6` socket.
+    ipv6_options: Option<Ipv6Options>,
+    /// The multicast groups joined using `IP_ADD_MEMBERSHIP`.
+    multicast_memberships: Vec<MulticastMembership>,
+    /// The options set using `IP_MULTICAST_TTL`, `IP_MULTICAST_LOOP`, and `IP_MULTICAST_IF`.
+    multicast_ttl: u8,
+    multicast_loop: bool,
+    multicast_interface: Ipv4Addr,
     // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
     // this file
     has_open_file: bool,
@@ -69,6 +94,15 @@ impl UdpSocket {
             bound_addr: None,
             association: None,
             recv_time_of_last_read_packet: None,
+            drops: 0,
+            rxq_ovfl: false,
+            flag_options: SocketFlagOptions::default(),
+            ipv6_options: None,
+            multicast_memberships: Vec::new(),
+            // like Linux, multicast packets don't leave the local network by default
+            multicast_ttl: 1,
+            multicast_loop: true,
+            multicast_interface: Ipv4Addr::UNSPECIFIED,
             has_open_file: false,
             _counter: ObjectCounter::new("UdpSocket"),
         };
@@ -135,7 +169,18 @@ impl UdpSocket {
 
         // don't bother copying the bytes if we know the push will fail
         if !self.recv_buffer.has_space() {
+            log::trace!("Dropping a packet since the UDP socket's recv buffer is full");
             packet.add_status(PacketStatus::RcvSocketDropped);
+
+            self.drops = self.drops.wrapping_add(1);
+            Worker::with_active_host(|host| {
+                host.network_namespace_borrow().update_udp_stats(|stats| {
+                    stats.in_errors += 1;
+                    stats.rcvbuf_errors += 1;
+                })
+            })
+            .unwrap();
+
             return;
         }
 
@@ -150,6 +195,9 @@ impl UdpSocket {
             src: packet.src_address(),
             dst: packet.dst_address(),
             recv_time,
+            // like Linux, only record the drop count if `SO_RXQ_OVFL` was enabled when the packet
+            // was received
+            drops: if self.rxq_ovfl { self.drops } else { 0 },
         };
 
         // push the message to the receive buffer (shouldn't fail since we checked for available
@@ -195,6 +243,12 @@ impl UdpSocket {
         self.send_buffer.buffer.front().map(|x| x.1.packet_priority)
     }
 
+    /// Whether the socket's `SO_REUSEADDR` option is set, which allows it to bind to an address
+    /// that other sockets with the option are bound to.
+    pub fn reuse_addr(&self) -> bool {
+        self.flag_options.get(libc::SO_REUSEADDR)
+    }
+
     pub fn has_data_to_send(&self) -> bool {
         !self.send_buffer.is_empty()
     }
@@ -220,13 +274,48 @@ impl UdpSocket {
     }
 
     pub fn address_family(&self) -> AddressFamily {
-        AddressFamily::Inet
+        match self.ipv6_options {
+            Some(_) => AddressFamily::Inet6,
+            None => AddressFamily::Inet,
+        }
+    }
+
+    pub fn ipv6_options(&self) -> Option<Ipv6Options> {
+        self.ipv6_options
+    }
+
+    pub fn set_ipv6_options(&mut self, options: Option<Ipv6Options>) {
+        self.ipv6_options = options;
+    }
+
+    /// The socket's line in `/proc/net/udp`.
+    pub fn proc_net_entry(&self) -> ProcNetEntry {
+        let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+        // like linux, the queue lengths include the per-message overhead
+        let tx_queue = self.send_buffer.alloc_bytes();
+        let rx_queue = self.recv_buffer.alloc_bytes();
+
+        ProcNetEntry {
+            local: self.bound_addr.unwrap_or(unspecified),
+            peer: self.peer_addr.unwrap_or(unspecified),
+            state: match self.peer_addr {
+                Some(_) => SocketState::Established,
+                None => SocketState::Close,
+            },
+            tx_queue: tx_queue.try_into().unwrap_or(u32::MAX),
+            rx_queue: rx_queue.try_into().unwrap_or(u32::MAX),
+            drops: self.drops,
+        }
     }
 
     pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
         // drop the existing association handle to disassociate the socket
         self.association = None;
 
+        // drop the memberships to leave the multicast groups
+        self.multicast_memberships.clear();
+
         self.copy_state(
             /* mask= */ FileState::all(),
             FileState::CLOSED,
@@ -253,7 +342,7 @@ impl UdpSocket {
 
         let addr: SocketAddrV4 = (*addr).into();
 
-        {
+        let reuse_addr = {
             let socket = socket.borrow();
 
             // if the socket is already bound
@@ -267,7 +356,9 @@ impl UdpSocket {
 
             // must not have been associated with the network interface
             assert!(socket.association.is_none());
-        }
+
+            socket.reuse_addr()
+        };
 
         // this will allow us to receive packets from any peer
         let unspecified_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
@@ -278,6 +369,8 @@ impl UdpSocket {
             addr,
             unspecified_addr,
             /* check_generic_peer= */ true,
+            reuse_addr,
+            Errno::EADDRINUSE,
             net_ns,
             rng,
         )?;
@@ -354,6 +447,11 @@ impl UdpSocket {
             },
         };
 
+        // like linux, a socket can only send to the broadcast address if `SO_BROADCAST` is set
+        if dst_addr.ip().is_broadcast() && !socket_ref.flag_options.get(libc::SO_BROADCAST) {
+            return Err(Errno::EACCES.into());
+        }
+
         if socket_ref.status().contains(FileStatus::NONBLOCK) {
             flags.insert(MsgFlags::MSG_DONTWAIT);
         }
@@ -365,6 +463,20 @@ impl UdpSocket {
             return Err(linux_api::errno::Errno::EMSGSIZE.into());
         }
 
+        // datagrams sent to the metrics port are consumed by shadow rather than sent
+        let metrics_port = Worker::with_active_host(|host| host.app_metrics_port()).unwrap();
+        if dst_addr.ip().is_loopback() && Some(dst_addr.port()) == metrics_port {
+            let mut reader = IoVecReader::new(args.iovs, mem);
+            let mut message = vec![0; len];
+            reader
+                .read_exact(&mut message[..])
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            let pid = Worker::active_process_id().unwrap();
+            Worker::with_active_host(|host| host.record_app_metrics(pid, &message)).unwrap();
+            return Ok(len.try_into().unwrap());
+        }
+
         // make sure that we're bound
         if socket_ref.bound_addr.is_some() {
             // we must have an association since we're bound
@@ -390,6 +502,8 @@ impl UdpSocket {
                 local_addr,
                 unspecified_addr,
                 /* check_generic_peer= */ true,
+                /* reuse_addr= */ false,
+                Errno::EAGAIN,
                 net_ns,
                 rng,
             )?;
@@ -443,16 +557,9 @@ impl UdpSocket {
                 .unwrap();
 
             // notify the host that this socket has packets to send
-            let socket = Arc::clone(socket);
-            let interface_ip = *socket_ref.bound_addr.unwrap().ip();
-            cb_queue.add(move |_cb_queue| {
-                Worker::with_active_host(|host| {
-                    let inet_socket = InetSocket::Udp(socket);
-                    let compat_socket = unsafe { c::compatsocket_fromInetSocket(&inet_socket) };
-                    host.notify_socket_has_packets(interface_ip, &compat_socket);
-                })
-                .unwrap();
-            });
+            Self::notify_has_packets(socket, *socket_ref.bound_addr.unwrap().ip(), cb_queue);
+
+            net_ns.update_udp_stats(|stats| stats.out_datagrams += 1);
 
             Ok(len)
         })();
@@ -471,6 +578,72 @@ impl UdpSocket {
         Ok(result?.try_into().unwrap())
     }
 
+    /// Add a message to the send buffer of a bound socket. This is used by services that run
+    /// within Shadow rather than in a managed process. Returns `EWOULDBLOCK` if the send buffer is
+    /// full.
+    pub fn send_internal(
+        socket: &Arc<AtomicRefCell<Self>>,
+        dst_addr: SocketAddrV4,
+        message: Bytes,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), Errno> {
+        let mut socket_ref = socket.borrow_mut();
+
+        let src_addr = socket_ref
+            .bound_addr
+            .expect("Internal UDP sockets must be bound before sending");
+        assert!(!src_addr.ip().is_unspecified());
+
+        if !socket_ref.send_buffer.has_space() {
+            return Err(Errno::EWOULDBLOCK);
+        }
+
+        let packet_priority =
+            Worker::with_active_host(|host| host.get_next_packet_priority()).unwrap();
+
+        let header = MessageSendHeader {
+            src: src_addr,
+            dst: dst_addr,
+            packet_priority,
+        };
+
+        socket_ref
+            .send_buffer
+            .push_message(message, header)
+            .unwrap();
+
+        Self::notify_has_packets(socket, *src_addr.ip(), cb_queue);
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        Ok(())
+    }
+
+    /// Remove the next message from the receive buffer, returning the message and the address it
+    /// was sent from. This is used by services that run within Shadow rather than in a managed
+    /// process.
+    pub fn recv_internal(&mut self, cb_queue: &mut CallbackQueue) -> Option<(Bytes, SocketAddrV4)> {
+        let (message, header) = self.recv_buffer.pop_message()?;
+        self.refresh_readable_writable(cb_queue);
+        Some((message, header.src))
+    }
+
+    /// Notify the host that the socket has packets to send on the interface with address
+    /// `interface_ip`.
+    fn notify_has_packets(
+        socket: &Arc<AtomicRefCell<Self>>,
+        interface_ip: Ipv4Addr,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let socket = Arc::clone(socket);
+        cb_queue.add(move |_cb_queue| {
+            Worker::with_active_host(|host| {
+                let inet_socket = InetSocket::Udp(socket);
+                host.notify_socket_has_packets(interface_ip, &inet_socket);
+            })
+            .unwrap();
+        });
+    }
+
     pub fn recvmsg(
         socket: &Arc<AtomicRefCell<Self>>,
         args: RecvmsgArgs,
@@ -531,14 +704,39 @@ impl UdpSocket {
             let mut return_flags = MsgFlags::empty();
             return_flags.set(MsgFlags::MSG_TRUNC, truncated_message.len() < message.len());
 
+            // socket(7): "Indicates that an unsigned 32-bit value ancillary message (cmsg) should
+            // be attached to received skbs indicating the number of packets dropped by the socket
+            // since its creation."
+            let control_len = if socket_ref.rxq_ovfl && header.drops != 0 {
+                let written = write_u32_cmsg(
+                    mem,
+                    args.control_ptr,
+                    libc::SOL_SOCKET,
+                    libc::SO_RXQ_OVFL,
+                    header.drops,
+                )?;
+                return_flags.set(MsgFlags::MSG_CTRUNC, written.is_none());
+                written.unwrap_or(0)
+            } else {
+                0
+            };
+
             // update the cache of the last recv time
             socket_ref.recv_time_of_last_read_packet = Some(header.recv_time);
 
+            if !flags.contains(MsgFlags::MSG_PEEK) {
+                Worker::with_active_host(|host| {
+                    host.network_namespace_borrow()
+                        .update_udp_stats(|stats| stats.in_datagrams += 1)
+                })
+                .unwrap();
+            }
+
             Ok(RecvmsgReturn {
                 return_val: return_val.try_into().unwrap(),
                 addr: Some(header.src.into()),
                 msg_flags: return_flags.bits(),
-                control_len: 0,
+                control_len,
             })
         })();
 
@@ -678,9 +876,17 @@ impl UdpSocket {
         // to `Ipv4Addr::LOCALHOST`, but the rest of Shadow probably can't handle other loopback
         // addresses (ex: 127.0.0.2) and it's probably best not to change this behaviour
 
-        // make sure we will be able to route this later
+        if peer_addr.ip().is_broadcast() && !socket.borrow().flag_options.get(libc::SO_BROADCAST) {
+            return Err(Errno::EACCES.into());
+        }
+
+        // make sure we will be able to route this later (broadcast and multicast packets are
+        // routed to the hosts on the same switch, if any)
         // TODO: UDP sockets probably shouldn't return `ECONNREFUSED`
-        if peer_addr.ip() != &std::net::Ipv4Addr::LOCALHOST {
+        if peer_addr.ip() != &std::net::Ipv4Addr::LOCALHOST
+            && !peer_addr.ip().is_broadcast()
+            && !peer_addr.ip().is_multicast()
+        {
             let is_routable =
                 Worker::is_routable(net_ns.default_ip.into(), (*peer_addr.ip()).into());
 
@@ -738,6 +944,8 @@ impl UdpSocket {
                     local_addr,
                     unspecified_addr,
                     /* check_generic_peer= */ true,
+                    /* reuse_addr= */ false,
+                    Errno::EAGAIN,
                     net_ns,
                     rng,
                 )?;
@@ -819,7 +1027,7 @@ impl UdpSocket {
                 Ok(bytes_written as libc::socklen_t)
             }
             (libc::SOL_SOCKET, libc::SO_DOMAIN) => {
-                let domain = libc::AF_INET;
+                let domain = self.address_family() as libc::c_int;
 
                 let optval_ptr = optval_ptr.cast::<libc::c_int>();
                 let bytes_written = write_partial(mem, &domain, optval_ptr, optlen as usize)?;
@@ -848,10 +1056,59 @@ impl UdpSocket {
 
                 Ok(bytes_written as libc::socklen_t)
             }
+            (libc::SOL_SOCKET, libc::SO_RXQ_OVFL) => {
+                let rxq_ovfl = libc::c_int::from(self.rxq_ovfl);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &rxq_ovfl, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                let val = libc::c_int::from(self.flag_options.get(optname));
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
             (libc::SOL_SOCKET, _) => {
                 log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
                 Err(Errno::ENOPROTOOPT.into())
             }
+            (libc::IPPROTO_IP, libc::IP_MULTICAST_TTL) => {
+                let ttl = libc::c_int::from(self.multicast_ttl);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written = write_partial(mem, &ttl, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::IPPROTO_IP, libc::IP_MULTICAST_LOOP) => {
+                let multicast_loop = libc::c_int::from(self.multicast_loop);
+
+                let optval_ptr = optval_ptr.cast::<libc::c_int>();
+                let bytes_written =
+                    write_partial(mem, &multicast_loop, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::IPPROTO_IP, libc::IP_MULTICAST_IF) => {
+                // an `in_addr` in network byte order
+                let addr = u32::from(self.multicast_interface).to_be();
+
+                let optval_ptr = optval_ptr.cast::<u32>();
+                let bytes_written = write_partial(mem, &addr, optval_ptr, optlen as usize)?;
+
+                Ok(bytes_written as libc::socklen_t)
+            }
+            (libc::IPPROTO_IP, _) => {
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                Err(Errno::ENOPROTOOPT.into())
+            }
             _ => {
                 log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
                 Err(Errno::EOPNOTSUPP.into())
@@ -920,30 +1177,116 @@ impl UdpSocket {
                 self.recv_buffer
                     .set_soft_limit_bytes(val.try_into().unwrap());
             }
-            (libc::SOL_SOCKET, libc::SO_REUSEADDR) => {
-                // TODO: implement this
-                warn_once_then_debug!(
-                    "(LOG_ONCE) setsockopt SO_REUSEADDR not yet implemented for udp"
-                );
-                return Err(Errno::ENOPROTOOPT.into());
+            (libc::SOL_SOCKET, libc::SO_RXQ_OVFL) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                self.rxq_ovfl = val != 0;
             }
-            (libc::SOL_SOCKET, libc::SO_REUSEPORT) => {
-                // TODO: implement this
-                warn_once_then_debug!(
-                    "(LOG_ONCE) setsockopt SO_REUSEPORT not yet implemented for udp"
-                );
-                return Err(Errno::ENOPROTOOPT.into());
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                // only SO_BROADCAST changes how the socket is simulated (it allows sending to the
+                // broadcast address), but the others are still returned by getsockopt()
+                self.flag_options.set(optname, val != 0);
             }
-            (libc::SOL_SOCKET, libc::SO_KEEPALIVE) => {
-                // TODO: implement this
-                warn_once_then_debug!(
-                    "(LOG_ONCE) setsockopt SO_KEEPALIVE not yet implemented for udp"
-                );
-                return Err(Errno::ENOPROTOOPT.into());
+            (libc::IPPROTO_IP, libc::IP_ADD_MEMBERSHIP | libc::IP_DROP_MEMBERSHIP) => {
+                let (group, interface_addr, ifindex) =
+                    read_ip_mreqn(optval_ptr, optlen, /* allow_in_addr= */ false, mem)?;
+
+                if !group.is_multicast() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let interface_ip = Worker::with_active_host(|host| {
+                    host.network_namespace_borrow()
+                        .multicast_interface(interface_addr, ifindex)
+                })
+                .unwrap();
+
+                let Some(interface_ip) = interface_ip else {
+                    return Err(Errno::ENODEV.into());
+                };
+
+                let existing = self
+                    .multicast_memberships
+                    .iter()
+                    .position(|x| x.group() == group && x.interface_ip() == interface_ip);
+
+                if optname == libc::IP_ADD_MEMBERSHIP {
+                    if existing.is_some() {
+                        return Err(Errno::EADDRINUSE.into());
+                    }
+
+                    if self.multicast_memberships.len() >= MAX_MULTICAST_MEMBERSHIPS {
+                        return Err(Errno::ENOBUFS.into());
+                    }
+
+                    let membership = Worker::with_active_host(|host| {
+                        host.network_namespace_borrow()
+                            .join_multicast_group(group, interface_ip)
+                    })
+                    .unwrap();
+
+                    self.multicast_memberships.push(membership);
+                } else {
+                    let Some(existing) = existing else {
+                        return Err(Errno::EADDRNOTAVAIL.into());
+                    };
+
+                    // dropping the membership leaves the group
+                    self.multicast_memberships.remove(existing);
+                }
             }
-            (libc::SOL_SOCKET, libc::SO_BROADCAST) => {
-                // TODO: implement this, pkg.go.dev/net uses it
-                warn_once_then_debug!("(LOG_ONCE) setsockopt SO_BROADCAST not yet implemented for udp; ignoring and returning 0");
+            (libc::IPPROTO_IP, libc::IP_MULTICAST_TTL) => {
+                let val = read_int_or_byte(optval_ptr, optlen, mem)?;
+
+                // -1 selects the default
+                let val = if val == -1 { 1 } else { val };
+
+                self.multicast_ttl = val.try_into().or(Err(Errno::EINVAL))?;
+            }
+            (libc::IPPROTO_IP, libc::IP_MULTICAST_LOOP) => {
+                let val = read_int_or_byte(optval_ptr, optlen, mem)?;
+
+                // TODO: shadow never loops multicast packets back to the sending host
+                self.multicast_loop = val != 0;
+            }
+            (libc::IPPROTO_IP, libc::IP_MULTICAST_IF) => {
+                let (_group, interface_addr, ifindex) =
+                    read_ip_mreqn(optval_ptr, optlen, /* allow_in_addr= */ true, mem)?;
+
+                // an unspecified interface resets the option
+                if interface_addr.is_unspecified() && ifindex == 0 {
+                    self.multicast_interface = Ipv4Addr::UNSPECIFIED;
+                    return Ok(());
+                }
+
+                let interface_ip = Worker::with_active_host(|host| {
+                    host.network_namespace_borrow()
+                        .multicast_interface(interface_addr, ifindex)
+                })
+                .unwrap();
+
+                // TODO: multicast packets are always sent on the interface chosen by the socket's
+                // bound address, regardless of this option
+                self.multicast_interface = interface_ip.ok_or(Errno::EADDRNOTAVAIL)?;
             }
             _ => {
                 log::debug!("setsockopt called with unsupported level {level} and opt {optname}");
@@ -1036,18 +1379,75 @@ struct MessageRecvHeader {
     dst: SocketAddrV4,
     /// The time when the network interface received the message.
     recv_time: EmulatedTime,
+    /// The socket's drop count when the message was received, or 0 if `SO_RXQ_OVFL` wasn't
+    /// enabled.
+    drops: u32,
 }
 
-/// A buffer of UDP messages and message headers.
+/// Read an `ip_mreqn` or `ip_mreq` socket option value, depending on `optlen`. If `allow_in_addr`
+/// is true, a value that's too short for an `ip_mreq` may instead be an `in_addr` with the
+/// interface address. Returns the group address, interface address, and interface index (which is
+/// 0 if not given).
+fn read_ip_mreqn(
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    allow_in_addr: bool,
+    mem: &MemoryManager,
+) -> Result<(Ipv4Addr, Ipv4Addr, i32), SyscallError> {
+    let optlen = usize::try_from(optlen).unwrap();
+
+    // all of the addresses are in network byte order
+    let (group, interface_addr, ifindex) = if optlen >= std::mem::size_of::<libc::ip_mreqn>() {
+        let [group, interface_addr, ifindex] = mem.read(optval_ptr.cast::<[u32; 3]>())?;
+        (group, interface_addr, ifindex as i32)
+    } else if optlen >= std::mem::size_of::<libc::ip_mreq>() {
+        let [group, interface_addr] = mem.read(optval_ptr.cast::<[u32; 2]>())?;
+        (group, interface_addr, 0)
+    } else if allow_in_addr && optlen >= std::mem::size_of::<libc::in_addr>() {
+        let interface_addr = mem.read(optval_ptr.cast::<u32>())?;
+        (0, interface_addr, 0)
+    } else {
+        return Err(Errno::EINVAL.into());
+    };
+
+    Ok((
+        Ipv4Addr::from(u32::from_be(group)),
+        Ipv4Addr::from(u32::from_be(interface_addr)),
+        ifindex,
+    ))
+}
+
+/// Read a socket option value that Linux accepts as either an int or a single byte, depending on
+/// `optlen`.
+fn read_int_or_byte(
+    optval_ptr: ForeignPtr<()>,
+    optlen: libc::socklen_t,
+    mem: &MemoryManager,
+) -> Result<libc::c_int, SyscallError> {
+    let optlen = usize::try_from(optlen).unwrap();
+
+    if optlen >= std::mem::size_of::<libc::c_int>() {
+        Ok(mem.read(optval_ptr.cast::<libc::c_int>())?)
+    } else if optlen >= 1 {
+        Ok(mem.read(optval_ptr.cast::<u8>())?.into())
+    } else {
+        Err(Errno::EINVAL.into())
+    }
+}
+
+/// A buffer of UDP messages and message headers. Also used for the messages of ICMP and raw
+/// sockets.
 #[derive(Debug)]
-struct MessageBuffer<Hdr> {
+pub(super) struct MessageBuffer<Hdr> {
     /// The message payloads and headers.
     // use a `LinkedList` so that socket buffers can shrink when they're empty (as opposed to
     // `VecDeque`)
     buffer: LinkedList<(Bytes, Hdr)>,
     /// The number of payload bytes in this socket.
     len_bytes: usize,
-    /// A soft limit for the maximum number of payload bytes this buffer can hold.
+    /// The number of bytes charged against the soft limit, which includes a per-message overhead.
+    alloc_bytes: usize,
+    /// A soft limit for the maximum number of bytes this buffer can hold.
     soft_limit_bytes: usize,
 }
 
@@ -1056,6 +1456,7 @@ impl<Hdr> MessageBuffer<Hdr> {
         Self {
             buffer: std::collections::LinkedList::new(),
             len_bytes: 0,
+            alloc_bytes: 0,
             soft_limit_bytes,
         }
     }
@@ -1063,15 +1464,15 @@ impl<Hdr> MessageBuffer<Hdr> {
     /// Push a message to the buffer. Returns the message and header as an `Err` if there wasn't
     /// enough space.
     pub fn push_message(&mut self, message: Bytes, header: Hdr) -> Result<(), (Bytes, Hdr)> {
-        // TODO: i think udp allows at most one packet to exceed the buffer capacity; should confirm
-        // this
+        // like linux, we allow the last message to exceed the buffer capacity
         if !self.has_space() {
             return Err((message, header));
         }
 
-        // TODO: on linux the socket buffer length also takes into account any header and struct
+        // on linux the socket buffer length also takes into account any header and struct
         // overhead, otherwise the buffer would take an infinite amount of 0-len packets
         self.len_bytes += message.len();
+        self.alloc_bytes += message.len() + MESSAGE_OVERHEAD_BYTES;
         self.buffer.push_back((message, header));
 
         Ok(())
@@ -1082,6 +1483,7 @@ impl<Hdr> MessageBuffer<Hdr> {
     pub fn pop_message(&mut self) -> Option<(Bytes, Hdr)> {
         let (message, header) = self.buffer.pop_front()?;
         self.len_bytes -= message.len();
+        self.alloc_bytes -= message.len() + MESSAGE_OVERHEAD_BYTES;
 
         Some((message, header))
     }
@@ -1097,9 +1499,14 @@ impl<Hdr> MessageBuffer<Hdr> {
         self.len_bytes
     }
 
+    /// The number of bytes charged against the soft limit.
+    pub fn alloc_bytes(&self) -> usize {
+        self.alloc_bytes
+    }
+
     /// Is there space for at least one more packet?
     pub fn has_space(&self) -> bool {
-        self.len_bytes < self.soft_limit_bytes
+        self.alloc_bytes < self.soft_limit_bytes
     }
 
     /// Is the buffer empty (does it have 0 packets)?
@@ -1117,3 +1524,33 @@ impl<Hdr> MessageBuffer<Hdr> {
         self.soft_limit_bytes = soft_limit_bytes;
     }
 }
+
+/// Write a control message containing a `u32` to the start of the control buffer `control_ptr`.
+/// Returns the number of bytes written, or `None` if the control buffer is too small.
+fn write_u32_cmsg(
+    mem: &mut MemoryManager,
+    control_ptr: ForeignArrayPtr<u8>,
+    level: libc::c_int,
+    cmsg_type: libc::c_int,
+    val: u32,
+) -> Result<Option<usize>, Errno> {
+    let data_len = std::mem::size_of::<u32>() as u32;
+    let cmsg_len = unsafe { libc::CMSG_LEN(data_len) } as usize;
+    let cmsg_space = unsafe { libc::CMSG_SPACE(data_len) } as usize;
+    let data_offset = unsafe { libc::CMSG_LEN(0) } as usize;
+
+    if control_ptr.is_null() || control_ptr.len() < cmsg_space {
+        return Ok(None);
+    }
+
+    let header = libc::cmsghdr {
+        cmsg_len,
+        cmsg_level: level,
+        cmsg_type,
+    };
+
+    mem.write(control_ptr.ptr().cast::<libc::cmsghdr>(), &header)?;
+    mem.write(control_ptr.slice(data_offset..).ptr().cast::<u32>(), &val)?;
+
+    Ok(Some(cmsg_space))
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use atomic_refcell::AtomicRefCell;
 use inet::{InetSocket, InetSocketRef, InetSocketRefMut};
 use linux_api::ioctls::IoctlRequest;
+use netlink::NetlinkSocket;
 use nix::sys::socket::Shutdown;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use unix::UnixSocket;
@@ -21,6 +22,7 @@ use crate::utility::HostTreePointer;
 
 pub mod abstract_unix_ns;
 pub mod inet;
+pub mod netlink;
 pub mod unix;
 
 bitflags::bitflags! {
@@ -37,6 +39,7 @@ bitflags::bitflags! {
 pub enum Socket {
     Unix(Arc<AtomicRefCell<UnixSocket>>),
     Inet(InetSocket),
+    Netlink(Arc<AtomicRefCell<NetlinkSocket>>),
 }
 
 impl Socket {
@@ -44,6 +47,7 @@ impl Socket {
         match self {
             Self::Unix(ref f) => SocketRef::Unix(f.borrow()),
             Self::Inet(ref f) => SocketRef::Inet(f.borrow()),
+            Self::Netlink(ref f) => SocketRef::Netlink(f.borrow()),
         }
     }
 
@@ -51,6 +55,7 @@ impl Socket {
         Ok(match self {
             Self::Unix(ref f) => SocketRef::Unix(f.try_borrow()?),
             Self::Inet(ref f) => SocketRef::Inet(f.try_borrow()?),
+            Self::Netlink(ref f) => SocketRef::Netlink(f.try_borrow()?),
         })
     }
 
@@ -58,6 +63,7 @@ impl Socket {
         match self {
             Self::Unix(ref f) => SocketRefMut::Unix(f.borrow_mut()),
             Self::Inet(ref f) => SocketRefMut::Inet(f.borrow_mut()),
+            Self::Netlink(ref f) => SocketRefMut::Netlink(f.borrow_mut()),
         }
     }
 
@@ -65,6 +71,7 @@ impl Socket {
         Ok(match self {
             Self::Unix(ref f) => SocketRefMut::Unix(f.try_borrow_mut()?),
             Self::Inet(ref f) => SocketRefMut::Inet(f.try_borrow_mut()?),
+            Self::Netlink(ref f) => SocketRefMut::Netlink(f.try_borrow_mut()?),
         })
     }
 
@@ -72,6 +79,7 @@ impl Socket {
         match self {
             Self::Unix(f) => Arc::as_ptr(f) as usize,
             Self::Inet(ref f) => f.canonical_handle(),
+            Self::Netlink(f) => Arc::as_ptr(f) as usize,
         }
     }
 
@@ -84,6 +92,7 @@ impl Socket {
         match self {
             Self::Unix(socket) => UnixSocket::bind(socket, addr, net_ns, rng),
             Self::Inet(socket) => InetSocket::bind(socket, addr, net_ns, rng),
+            Self::Netlink(socket) => NetlinkSocket::bind(socket, addr, net_ns, rng),
         }
     }
 
@@ -97,6 +106,7 @@ impl Socket {
         match self {
             Self::Unix(socket) => UnixSocket::listen(socket, backlog, net_ns, rng, cb_queue),
             Self::Inet(socket) => InetSocket::listen(socket, backlog, net_ns, rng, cb_queue),
+            Self::Netlink(socket) => NetlinkSocket::listen(socket, backlog, net_ns, rng, cb_queue),
         }
     }
 
@@ -110,6 +120,7 @@ impl Socket {
         match self {
             Self::Unix(socket) => UnixSocket::connect(socket, addr, net_ns, rng, cb_queue),
             Self::Inet(socket) => InetSocket::connect(socket, addr, net_ns, rng, cb_queue),
+            Self::Netlink(socket) => NetlinkSocket::connect(socket, addr, net_ns, rng, cb_queue),
         }
     }
 
@@ -128,6 +139,9 @@ impl Socket {
             Self::Inet(socket) => {
                 InetSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
             }
+            Self::Netlink(socket) => {
+                NetlinkSocket::sendmsg(socket, args, memory_manager, net_ns, rng, cb_queue)
+            }
         }
     }
 
@@ -140,6 +154,7 @@ impl Socket {
         match self {
             Self::Unix(socket) => UnixSocket::recvmsg(socket, args, memory_manager, cb_queue),
             Self::Inet(socket) => InetSocket::recvmsg(socket, args, memory_manager, cb_queue),
+            Self::Netlink(socket) => NetlinkSocket::recvmsg(socket, args, memory_manager, cb_queue),
         }
     }
 }
@@ -149,6 +164,7 @@ impl std::fmt::Debug for Socket {
         match self {
             Self::Unix(_) => write!(f, "Unix")?,
             Self::Inet(_) => write!(f, "Inet")?,
+            Self::Netlink(_) => write!(f, "Netlink")?,
         }
 
         if let Ok(file) = self.try_borrow() {
@@ -167,28 +183,30 @@ impl std::fmt::Debug for Socket {
 pub enum SocketRef<'a> {
     Unix(atomic_refcell::AtomicRef<'a, UnixSocket>),
     Inet(InetSocketRef<'a>),
+    Netlink(atomic_refcell::AtomicRef<'a, NetlinkSocket>),
 }
 
 pub enum SocketRefMut<'a> {
     Unix(atomic_refcell::AtomicRefMut<'a, UnixSocket>),
     Inet(InetSocketRefMut<'a>),
+    Netlink(atomic_refcell::AtomicRefMut<'a, NetlinkSocket>),
 }
 
 // file functions
 impl SocketRef<'_> {
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn supports_sa_restart(&self) -> bool
     );
 }
@@ -199,6 +217,7 @@ impl SocketRef<'_> {
         match self {
             Self::Unix(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Inet(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+            Self::Netlink(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
         }
     }
 
@@ -206,44 +225,45 @@ impl SocketRef<'_> {
         match self {
             Self::Unix(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Inet(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+            Self::Netlink(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
         }
     }
 
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn address_family(&self) -> nix::sys::socket::AddressFamily
     );
 }
 
 // file functions
 impl SocketRefMut<'_> {
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn state(&self) -> FileState
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn mode(&self) -> FileMode
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn status(&self) -> FileStatus
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn has_open_file(&self) -> bool
     );
-    enum_passthrough!(self, (val), Unix, Inet;
+    enum_passthrough!(self, (val), Unix, Inet, Netlink;
         pub fn set_has_open_file(&mut self, val: bool)
     );
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn supports_sa_restart(&self) -> bool
     );
-    enum_passthrough!(self, (cb_queue), Unix, Inet;
+    enum_passthrough!(self, (cb_queue), Unix, Inet, Netlink;
         pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
-    enum_passthrough!(self, (status), Unix, Inet;
+    enum_passthrough!(self, (status), Unix, Inet, Netlink;
         pub fn set_status(&mut self, status: FileStatus)
     );
-    enum_passthrough!(self, (request, arg_ptr, memory_manager), Unix, Inet;
+    enum_passthrough!(self, (request, arg_ptr, memory_manager), Unix, Inet, Netlink;
         pub fn ioctl(&mut self, request: IoctlRequest, arg_ptr: ForeignPtr<()>, memory_manager: &mut MemoryManager) -> SyscallResult
     );
-    enum_passthrough!(self, (monitoring, filter, notify_fn), Unix, Inet;
+    enum_passthrough!(self, (monitoring, filter, notify_fn), Unix, Inet, Netlink;
         pub fn add_listener(
             &mut self,
             monitoring: FileState,
@@ -251,17 +271,17 @@ impl SocketRefMut<'_> {
             notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
         ) -> Handle<(FileState, FileState)>
     );
-    enum_passthrough!(self, (ptr), Unix, Inet;
+    enum_passthrough!(self, (ptr), Unix, Inet, Netlink;
         pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>)
     );
-    enum_passthrough!(self, (ptr), Unix, Inet;
+    enum_passthrough!(self, (ptr), Unix, Inet, Netlink;
         pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener)
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Unix, Inet;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Unix, Inet, Netlink;
         pub fn readv(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                      mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
-    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Unix, Inet;
+    enum_passthrough!(self, (iovs, offset, flags, mem, cb_queue), Unix, Inet, Netlink;
         pub fn writev(&mut self, iovs: &[IoVec], offset: Option<libc::off_t>, flags: libc::c_int,
                       mem: &mut MemoryManager, cb_queue: &mut CallbackQueue) -> Result<libc::ssize_t, SyscallError>
     );
@@ -273,6 +293,7 @@ impl SocketRefMut<'_> {
         match self {
             Self::Unix(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
             Self::Inet(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
+            Self::Netlink(socket) => socket.getpeername().map(|opt| opt.map(Into::into)),
         }
     }
 
@@ -280,20 +301,21 @@ impl SocketRefMut<'_> {
         match self {
             Self::Unix(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
             Self::Inet(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
+            Self::Netlink(socket) => socket.getsockname().map(|opt| opt.map(Into::into)),
         }
     }
 
-    enum_passthrough!(self, (), Unix, Inet;
+    enum_passthrough!(self, (), Unix, Inet, Netlink;
         pub fn address_family(&self) -> nix::sys::socket::AddressFamily
     );
 
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), Unix, Inet;
+    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager, cb_queue), Unix, Inet, Netlink;
         pub fn getsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
                           optlen: libc::socklen_t, memory_manager: &mut MemoryManager, cb_queue: &mut CallbackQueue)
         -> Result<libc::socklen_t, SyscallError>
     );
 
-    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager), Unix, Inet;
+    enum_passthrough!(self, (level, optname, optval_ptr, optlen, memory_manager), Unix, Inet, Netlink;
         pub fn setsockopt(&mut self, level: libc::c_int, optname: libc::c_int, optval_ptr: ForeignPtr<()>,
                           optlen: libc::socklen_t, memory_manager: &MemoryManager)
         -> Result<(), SyscallError>
@@ -308,10 +330,11 @@ impl SocketRefMut<'_> {
         match self {
             Self::Unix(socket) => socket.accept(net_ns, rng, cb_queue),
             Self::Inet(socket) => socket.accept(net_ns, rng, cb_queue),
+            Self::Netlink(socket) => socket.accept(net_ns, rng, cb_queue),
         }
     }
 
-    enum_passthrough!(self, (how, cb_queue), Unix, Inet;
+    enum_passthrough!(self, (how, cb_queue), Unix, Inet, Netlink;
         pub fn shutdown(&mut self, how: Shutdown, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError>
     );
 }
@@ -321,6 +344,7 @@ impl std::fmt::Debug for SocketRef<'_> {
         match self {
             Self::Unix(_) => write!(f, "Unix")?,
             Self::Inet(_) => write!(f, "Inet")?,
+            Self::Netlink(_) => write!(f, "Netlink")?,
         }
 
         write!(
@@ -337,6 +361,7 @@ impl std::fmt::Debug for SocketRefMut<'_> {
         match self {
             Self::Unix(_) => write!(f, "Unix")?,
             Self::Inet(_) => write!(f, "Inet")?,
+            Self::Netlink(_) => write!(f, "Netlink")?,
         }
 
         write!(
@@ -382,3 +407,39 @@ pub struct RecvmsgReturn {
     /// The number of control data bytes read.
     pub control_len: libc::size_t,
 }
+
+/// Boolean `SOL_SOCKET` options that a socket stores and reports back from `getsockopt()`. Apart
+/// from `SO_BROADCAST` for UDP sockets, they don't otherwise change how Shadow simulates the
+/// socket.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SocketFlagOptions {
+    reuse_addr: bool,
+    reuse_port: bool,
+    keep_alive: bool,
+    broadcast: bool,
+}
+
+impl SocketFlagOptions {
+    /// Get the value of the `SO_REUSEADDR`, `SO_REUSEPORT`, `SO_KEEPALIVE`, or `SO_BROADCAST`
+    /// option. Panics for any other option.
+    pub fn get(&self, optname: libc::c_int) -> bool {
+        let mut options = *self;
+        *options.option_mut(optname)
+    }
+
+    /// Set the value of the `SO_REUSEADDR`, `SO_REUSEPORT`, `SO_KEEPALIVE`, or `SO_BROADCAST`
+    /// option. Panics for any other option.
+    pub fn set(&mut self, optname: libc::c_int, val: bool) {
+        *self.option_mut(optname) = val;
+    }
+
+    fn option_mut(&mut self, optname: libc::c_int) -> &mut bool {
+        match optname {
+            libc::SO_REUSEADDR => &mut self.reuse_addr,
+            libc::SO_REUSEPORT => &mut self.reuse_port,
+            libc::SO_KEEPALIVE => &mut self.keep_alive,
+            libc::SO_BROADCAST => &mut self.broadcast,
+            _ => panic!("Socket option {optname} is not a stored flag option"),
+        }
+    }
+}
@@ -0,0 +1,744 @@
+//! Netlink sockets, which applications create with `socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE)` to
+//! query the network configuration (see netlink(7)).
+//!
+//! Only the `NETLINK_ROUTE` protocol is supported, and only messages to the kernel. The kernel's
+//! side is the responder in [`rtnetlink`](crate::host::network::rtnetlink), which answers each
+//! request as soon as it's sent, so the responses are immediately available to `recvmsg()` and
+//! sending never blocks.
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use linux_api::netlink::{
+    NETLINK_ADD_MEMBERSHIP, NETLINK_CAP_ACK, NETLINK_DROP_MEMBERSHIP, NETLINK_EXT_ACK,
+    NETLINK_GET_STRICT_CHK, NETLINK_NO_ENOBUFS, NETLINK_ROUTE, SOL_NETLINK,
+};
+use nix::sys::socket::{AddressFamily, MsgFlags, NetlinkAddr, Shutdown};
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::core::worker::Worker;
+use crate::cshadow as c;
+use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs};
+use crate::host::descriptor::{
+    File, FileMode, FileState, FileStatus, OpenFile, Socket, StateEventSource, StateListenerFilter,
+    SyscallResult,
+};
+use crate::host::memory_manager::MemoryManager;
+use crate::host::network::namespace::{NetlinkPortHandle, NetworkNamespace};
+use crate::host::network::rtnetlink::{self, ResponseOptions};
+use crate::host::syscall::io::{write_partial, IoVec, IoVecReader, IoVecWriter};
+use crate::host::syscall_types::SyscallError;
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrStorage;
+use crate::utility::{HostTreePointer, ObjectCounter};
+
+pub struct NetlinkSocket {
+    event_source: StateEventSource,
+    status: FileStatus,
+    state: FileState,
+    /// Either `SOCK_RAW` or `SOCK_DGRAM`, which behave the same.
+    socket_type: libc::c_int,
+    /// The socket's binding to its port id, which identifies the socket to the kernel.
+    port: Option<NetlinkPortHandle>,
+    /// The bitmask of the multicast groups that the socket joined. The network configuration never
+    /// changes, so the groups never receive any messages.
+    groups: u32,
+    /// The response datagrams that haven't been read yet.
+    recv_buffer: VecDeque<Vec<u8>>,
+    /// The total length of the datagrams in the receive buffer.
+    recv_buffer_len: usize,
+    recv_buf_size: usize,
+    send_buf_size: usize,
+    /// A response was dropped because the receive buffer was full, so the next `recvmsg()` returns
+    /// `ENOBUFS`.
+    overrun: bool,
+    options: NetlinkOptions,
+    // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
+    // this file
+    has_open_file: bool,
+    _counter: ObjectCounter,
+}
+
+/// The boolean `SOL_NETLINK` socket options.
+#[derive(Debug, Default, Copy, Clone)]
+struct NetlinkOptions {
+    /// `NETLINK_CAP_ACK`: error messages don't include the request's payload.
+    cap_ack: bool,
+    /// `NETLINK_NO_ENOBUFS`: don't report responses that were dropped.
+    no_enobufs: bool,
+    /// `NETLINK_EXT_ACK` is stored, but no extended acknowledgements are sent.
+    ext_ack: bool,
+    /// `NETLINK_GET_STRICT_CHK` is stored, but the requests aren't checked more strictly.
+    strict_check: bool,
+}
+
+impl NetlinkSocket {
+    pub fn new(
+        status: FileStatus,
+        socket_type: libc::c_int,
+        send_buf_size: usize,
+        recv_buf_size: usize,
+    ) -> Arc<AtomicRefCell<Self>> {
+        let mut socket = Self {
+            event_source: StateEventSource::new(),
+            status,
+            state: FileState::ACTIVE,
+            socket_type,
+            port: None,
+            groups: 0,
+            recv_buffer: VecDeque::new(),
+            recv_buffer_len: 0,
+            recv_buf_size,
+            send_buf_size,
+            overrun: false,
+            options: NetlinkOptions::default(),
+            has_open_file: false,
+            _counter: ObjectCounter::new("NetlinkSocket"),
+        };
+
+        CallbackQueue::queue_and_run(|cb_queue| socket.refresh_readable_writable(cb_queue));
+
+        Arc::new(AtomicRefCell::new(socket))
+    }
+
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: FileStatus) {
+        self.status = status;
+    }
+
+    pub fn mode(&self) -> FileMode {
+        FileMode::READ | FileMode::WRITE
+    }
+
+    pub fn has_open_file(&self) -> bool {
+        self.has_open_file
+    }
+
+    pub fn supports_sa_restart(&self) -> bool {
+        true
+    }
+
+    pub fn set_has_open_file(&mut self, val: bool) {
+        self.has_open_file = val;
+    }
+
+    fn portid(&self) -> u32 {
+        self.port.as_ref().map(|x| x.portid()).unwrap_or(0)
+    }
+
+    pub fn getsockname(&self) -> Result<Option<NetlinkAddr>, SyscallError> {
+        Ok(Some(NetlinkAddr::new(self.portid(), self.groups)))
+    }
+
+    pub fn getpeername(&self) -> Result<Option<NetlinkAddr>, SyscallError> {
+        // the peer is always the kernel
+        Ok(Some(NetlinkAddr::new(0, 0)))
+    }
+
+    pub fn address_family(&self) -> AddressFamily {
+        AddressFamily::Netlink
+    }
+
+    pub fn close(&mut self, cb_queue: &mut CallbackQueue) -> Result<(), SyscallError> {
+        // drop the port handle to release the port id
+        self.port = None;
+
+        self.copy_state(
+            /* mask= */ FileState::all(),
+            FileState::CLOSED,
+            cb_queue,
+        );
+        Ok(())
+    }
+
+    pub fn bind(
+        socket: &Arc<AtomicRefCell<Self>>,
+        addr: Option<&SockaddrStorage>,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+    ) -> SyscallResult {
+        // if the address pointer was NULL
+        let Some(addr) = addr else {
+            return Err(Errno::EFAULT.into());
+        };
+
+        // if not a netlink socket address
+        let Some(addr) = addr.as_netlink() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        let socket = &mut *socket.borrow_mut();
+
+        match &socket.port {
+            // a bound socket can't be bound to a different port id
+            Some(port) if addr.pid() != port.portid() => return Err(Errno::EINVAL.into()),
+            Some(_) => {}
+            None if addr.pid() == 0 => socket.autobind(net_ns)?,
+            None => {
+                let port = net_ns
+                    .bind_netlink_port(addr.pid())
+                    .ok_or(Errno::EADDRINUSE)?;
+                socket.port = Some(port);
+            }
+        }
+
+        socket.groups = addr.groups();
+
+        Ok(0.into())
+    }
+
+    /// Bind the socket to a free port id if it isn't bound.
+    fn autobind(&mut self, net_ns: &NetworkNamespace) -> Result<(), Errno> {
+        if self.port.is_some() {
+            return Ok(());
+        }
+
+        let pid = Worker::active_process_id().map(u32::from).unwrap_or(0);
+        let portid = net_ns.get_free_netlink_port(pid).ok_or(Errno::EAGAIN)?;
+        self.port = Some(net_ns.bind_netlink_port(portid).unwrap());
+
+        Ok(())
+    }
+
+    pub fn readv(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the readv() syscall handler should have called NetlinkSocket::recvmsg() instead
+        panic!("Called NetlinkSocket::readv() on a netlink socket");
+    }
+
+    pub fn writev(
+        &mut self,
+        _iovs: &[IoVec],
+        _offset: Option<libc::off_t>,
+        _flags: libc::c_int,
+        _mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        // the writev() syscall handler should have called NetlinkSocket::sendmsg() instead
+        panic!("Called NetlinkSocket::writev() on a netlink socket");
+    }
+
+    pub fn sendmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: SendmsgArgs,
+        mem: &mut MemoryManager,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::ssize_t, SyscallError> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        if MsgFlags::from_bits(args.flags).is_none() {
+            log::debug!("Unrecognized send flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        }
+
+        if let Some(addr) = args.addr {
+            // if not a netlink socket address
+            let Some(addr) = addr.as_netlink() else {
+                return Err(Errno::EINVAL.into());
+            };
+
+            // sending to multicast groups requires `CAP_NET_ADMIN`
+            if addr.groups() != 0 {
+                return Err(Errno::EPERM.into());
+            }
+
+            // we only support messages to the kernel, not to other netlink sockets
+            if addr.pid() != 0 {
+                log::debug!("Netlink messages can only be sent to the kernel");
+                return Err(Errno::ECONNREFUSED.into());
+            }
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        if len > socket_ref.send_buf_size {
+            return Err(Errno::EMSGSIZE.into());
+        }
+
+        socket_ref.autobind(net_ns)?;
+
+        let mut reader = IoVecReader::new(args.iovs, mem);
+        let mut data = vec![0; len];
+        reader
+            .read_exact(&mut data)
+            .map_err(|e| Errno::try_from(e).unwrap())?;
+
+        let options = ResponseOptions {
+            portid: socket_ref.portid(),
+            cap_ack: socket_ref.options.cap_ack,
+        };
+        let links = rtnetlink::links(net_ns);
+
+        for response in rtnetlink::handle_requests(&links, &data, &options) {
+            socket_ref.push_response(response);
+        }
+
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        Ok(len.try_into().unwrap())
+    }
+
+    /// Add a response datagram to the receive buffer, or drop it if the buffer is full.
+    fn push_response(&mut self, response: Vec<u8>) {
+        // like linux, a datagram is accepted as long as the buffer isn't already full
+        if self.recv_buffer_len >= self.recv_buf_size {
+            log::trace!("Dropping a response since the netlink socket's recv buffer is full");
+            if !self.options.no_enobufs {
+                self.overrun = true;
+            }
+            return;
+        }
+
+        self.recv_buffer_len += response.len();
+        self.recv_buffer.push_back(response);
+    }
+
+    pub fn recvmsg(
+        socket: &Arc<AtomicRefCell<Self>>,
+        args: RecvmsgArgs,
+        mem: &mut MemoryManager,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<RecvmsgReturn, SyscallError> {
+        let socket_ref = &mut *socket.borrow_mut();
+
+        let Some(mut flags) = MsgFlags::from_bits(args.flags) else {
+            log::debug!("Unrecognized recv flags: {:#b}", args.flags);
+            return Err(Errno::EINVAL.into());
+        };
+
+        if socket_ref.status().contains(FileStatus::NONBLOCK) {
+            flags.insert(MsgFlags::MSG_DONTWAIT);
+        }
+
+        // report that responses were dropped, once
+        if std::mem::take(&mut socket_ref.overrun) {
+            socket_ref.refresh_readable_writable(cb_queue);
+            return Err(Errno::ENOBUFS.into());
+        }
+
+        let len: libc::size_t = args.iovs.iter().map(|x| x.len).sum();
+
+        // run in a closure so that an early return doesn't skip checking if we should block
+        let result = (|| {
+            let message = if !flags.contains(MsgFlags::MSG_PEEK) {
+                let message = socket_ref
+                    .recv_buffer
+                    .pop_front()
+                    .ok_or(Errno::EWOULDBLOCK)?;
+                socket_ref.recv_buffer_len -= message.len();
+                message
+            } else {
+                socket_ref
+                    .recv_buffer
+                    .front()
+                    .ok_or(Errno::EWOULDBLOCK)?
+                    .clone()
+            };
+
+            // truncate the message if it's larger than the user-provided buffers
+            let truncated_message = &message[..std::cmp::min(len, message.len())];
+
+            // write the truncated message to the iovs
+            let mut writer = IoVecWriter::new(args.iovs, mem);
+            writer
+                .write_all(truncated_message)
+                .map_err(|e| Errno::try_from(e).unwrap())?;
+
+            let return_val = if flags.contains(MsgFlags::MSG_TRUNC) {
+                message.len()
+            } else {
+                // the number of bytes written
+                truncated_message.len()
+            };
+
+            let mut return_flags = MsgFlags::empty();
+            return_flags.set(MsgFlags::MSG_TRUNC, truncated_message.len() < message.len());
+
+            Ok(RecvmsgReturn {
+                return_val: return_val.try_into().unwrap(),
+                // the responses are from the kernel
+                addr: Some(NetlinkAddr::new(0, 0).into()),
+                msg_flags: return_flags.bits(),
+                control_len: 0,
+            })
+        })();
+
+        socket_ref.refresh_readable_writable(cb_queue);
+
+        // if the syscall would block and we don't have the MSG_DONTWAIT flag
+        if result.as_ref().err() == Some(&Errno::EWOULDBLOCK)
+            && !flags.contains(MsgFlags::MSG_DONTWAIT)
+        {
+            return Err(SyscallError::new_blocked_on_file(
+                File::Socket(Socket::Netlink(socket.clone())),
+                FileState::READABLE,
+                socket_ref.supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?)
+    }
+
+    pub fn ioctl(
+        &mut self,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+        mem: &mut MemoryManager,
+    ) -> SyscallResult {
+        match request {
+            // equivalent to SIOCINQ
+            IoctlRequest::FIONREAD => {
+                let len = self
+                    .recv_buffer
+                    .front()
+                    .map(|x| x.len())
+                    .unwrap_or(0)
+                    .try_into()
+                    .unwrap();
+
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &len)?;
+
+                Ok(0.into())
+            }
+            // equivalent to SIOCOUTQ; requests are handled immediately
+            IoctlRequest::TIOCOUTQ => {
+                let arg_ptr = arg_ptr.cast::<libc::c_int>();
+                mem.write(arg_ptr, &0)?;
+
+                Ok(0.into())
+            }
+            IoctlRequest::FIONBIO => {
+                panic!("This should have been handled by the ioctl syscall handler");
+            }
+            IoctlRequest::TCGETS
+            | IoctlRequest::TCSETS
+            | IoctlRequest::TCSETSW
+            | IoctlRequest::TCSETSF
+            | IoctlRequest::TCGETA
+            | IoctlRequest::TCSETA
+            | IoctlRequest::TCSETAW
+            | IoctlRequest::TCSETAF
+            | IoctlRequest::TIOCGWINSZ
+            | IoctlRequest::TIOCSWINSZ => {
+                // not a terminal
+                Err(Errno::ENOTTY.into())
+            }
+            request => {
+                warn_once_then_debug!(
+                    "(LOG_ONCE) We do not yet handle ioctl request {request:?} on netlink sockets"
+                );
+                Err(Errno::EINVAL.into())
+            }
+        }
+    }
+
+    pub fn listen(
+        _socket: &Arc<AtomicRefCell<Self>>,
+        _backlog: i32,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn connect(
+        socket: &Arc<AtomicRefCell<Self>>,
+        peer_addr: &SockaddrStorage,
+        net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        // if not a netlink socket address
+        let Some(peer_addr) = peer_addr.as_netlink() else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        // we only support messages to the kernel, not to other netlink sockets
+        if peer_addr.pid() != 0 || peer_addr.groups() != 0 {
+            log::debug!("Netlink sockets can only be connected to the kernel");
+            return Err(Errno::ECONNREFUSED.into());
+        }
+
+        socket.borrow_mut().autobind(net_ns)?;
+
+        Ok(())
+    }
+
+    pub fn accept(
+        &mut self,
+        _net_ns: &NetworkNamespace,
+        _rng: impl rand::Rng,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<OpenFile, SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn shutdown(
+        &mut self,
+        _how: Shutdown,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        Err(Errno::EOPNOTSUPP.into())
+    }
+
+    pub fn getsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &mut MemoryManager,
+        _cb_queue: &mut CallbackQueue,
+    ) -> Result<libc::socklen_t, SyscallError> {
+        let val: libc::c_int = match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => self.send_buf_size.try_into().unwrap(),
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => self.recv_buf_size.try_into().unwrap(),
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => libc::AF_NETLINK,
+            (libc::SOL_SOCKET, libc::SO_TYPE) => self.socket_type,
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => NETLINK_ROUTE,
+            (libc::SOL_SOCKET, libc::SO_ACCEPTCONN) => 0,
+            (SOL_NETLINK, NETLINK_CAP_ACK) => self.options.cap_ack.into(),
+            (SOL_NETLINK, NETLINK_NO_ENOBUFS) => self.options.no_enobufs.into(),
+            (SOL_NETLINK, NETLINK_EXT_ACK) => self.options.ext_ack.into(),
+            (SOL_NETLINK, NETLINK_GET_STRICT_CHK) => self.options.strict_check.into(),
+            _ => {
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+        };
+
+        let optval_ptr = optval_ptr.cast::<libc::c_int>();
+        let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+        Ok(bytes_written as libc::socklen_t)
+    }
+
+    pub fn setsockopt(
+        &mut self,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
+    ) -> Result<(), SyscallError> {
+        type OptType = libc::c_int;
+
+        let is_supported = matches!(
+            (level, optname),
+            (libc::SOL_SOCKET, libc::SO_SNDBUF | libc::SO_RCVBUF)
+                | (
+                    SOL_NETLINK,
+                    NETLINK_ADD_MEMBERSHIP
+                        | NETLINK_DROP_MEMBERSHIP
+                        | NETLINK_CAP_ACK
+                        | NETLINK_NO_ENOBUFS
+                        | NETLINK_EXT_ACK
+                        | NETLINK_GET_STRICT_CHK
+                )
+        );
+
+        if !is_supported {
+            log::debug!("setsockopt called with unsupported level {level} and opt {optname}");
+            return Err(Errno::ENOPROTOOPT.into());
+        }
+
+        if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let optval_ptr = optval_ptr.cast::<OptType>();
+        let val = mem.read(optval_ptr)?;
+
+        match (level, optname) {
+            (libc::SOL_SOCKET, libc::SO_SNDBUF | libc::SO_RCVBUF) => {
+                let val: u64 = val.try_into().or(Err(Errno::EINVAL))?;
+
+                // linux kernel doubles this value upon setting, and we use the same limits as
+                // for UDP sockets
+                if optname == libc::SO_SNDBUF {
+                    let val = (val * 2).clamp(4096, 268435456); // 2^28 = 256 MiB
+                    self.send_buf_size = val.try_into().unwrap();
+                } else {
+                    let val = (val * 2).clamp(2048, 268435456);
+                    self.recv_buf_size = val.try_into().unwrap();
+                }
+            }
+            (SOL_NETLINK, NETLINK_ADD_MEMBERSHIP | NETLINK_DROP_MEMBERSHIP) => {
+                let group: u32 = val.try_into().or(Err(Errno::EINVAL))?;
+                if group == 0 {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                // only the first 32 groups are reported by getsockname(), so we don't store the
+                // others
+                if let Some(bit) = 1u32.checked_shl(group - 1) {
+                    if optname == NETLINK_ADD_MEMBERSHIP {
+                        self.groups |= bit;
+                    } else {
+                        self.groups &= !bit;
+                    }
+                }
+            }
+            (SOL_NETLINK, _) => {
+                let val = val != 0;
+                match optname {
+                    NETLINK_CAP_ACK => self.options.cap_ack = val,
+                    NETLINK_NO_ENOBUFS => self.options.no_enobufs = val,
+                    NETLINK_EXT_ACK => self.options.ext_ack = val,
+                    NETLINK_GET_STRICT_CHK => self.options.strict_check = val,
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    pub fn add_listener(
+        &mut self,
+        monitoring: FileState,
+        filter: StateListenerFilter,
+        notify_fn: impl Fn(FileState, FileState, &mut CallbackQueue) + Send + Sync + 'static,
+    ) -> Handle<(FileState, FileState)> {
+        self.event_source
+            .add_listener(monitoring, filter, notify_fn)
+    }
+
+    pub fn add_legacy_listener(&mut self, ptr: HostTreePointer<c::StatusListener>) {
+        self.event_source.add_legacy_listener(ptr);
+    }
+
+    pub fn remove_legacy_listener(&mut self, ptr: *mut c::StatusListener) {
+        self.event_source.remove_legacy_listener(ptr);
+    }
+
+    pub fn state(&self) -> FileState {
+        self.state
+    }
+
+    fn refresh_readable_writable(&mut self, cb_queue: &mut CallbackQueue) {
+        // a pending error is reported by the next read
+        let readable = !self.recv_buffer.is_empty() || self.overrun;
+
+        let readable = readable.then_some(FileState::READABLE).unwrap_or_default();
+
+        // sending never blocks
+        self.copy_state(
+            /* mask= */ FileState::READABLE | FileState::WRITABLE,
+            readable | FileState::WRITABLE,
+            cb_queue,
+        );
+    }
+
+    fn copy_state(&mut self, mask: FileState, state: FileState, cb_queue: &mut CallbackQueue) {
+        let old_state = self.state;
+
+        // remove the masked flags, then copy the masked flags
+        self.state.remove(mask);
+        self.state.insert(state & mask);
+
+        self.handle_state_change(old_state, cb_queue);
+    }
+
+    fn handle_state_change(&mut self, old_state: FileState, cb_queue: &mut CallbackQueue) {
+        let states_changed = self.state ^ old_state;
+
+        // if nothing changed
+        if states_changed.is_empty() {
+            return;
+        }
+
+        self.event_source
+            .notify_listeners(self.state, states_changed, cb_queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_response() {
+        let socket = NetlinkSocket::new(FileStatus::empty(), libc::SOCK_RAW, 1024, 100);
+        let mut socket = socket.borrow_mut();
+
+        // sending never blocks, and there's nothing to read yet
+        assert!(socket.state().contains(FileState::WRITABLE));
+        assert!(!socket.state().contains(FileState::READABLE));
+
+        // like linux, a datagram is accepted as long as the buffer isn't already full
+        socket.push_response(vec![0; 60]);
+        socket.push_response(vec![0; 60]);
+        assert_eq!(socket.recv_buffer.len(), 2);
+        assert_eq!(socket.recv_buffer_len, 120);
+        assert!(!socket.overrun);
+
+        // the buffer is full, so the response is dropped and the next read reports it
+        socket.push_response(vec![0; 10]);
+        assert_eq!(socket.recv_buffer.len(), 2);
+        assert!(socket.overrun);
+
+        CallbackQueue::queue_and_run(|cb_queue| socket.refresh_readable_writable(cb_queue));
+        assert!(socket.state().contains(FileState::READABLE));
+    }
+
+    #[test]
+    fn test_push_response_no_enobufs() {
+        let socket = NetlinkSocket::new(FileStatus::empty(), libc::SOCK_RAW, 1024, 100);
+        let mut socket = socket.borrow_mut();
+        socket.options.no_enobufs = true;
+
+        socket.push_response(vec![0; 100]);
+        socket.push_response(vec![0; 10]);
+
+        // the response is dropped without being reported
+        assert_eq!(socket.recv_buffer.len(), 1);
+        assert!(!socket.overrun);
+    }
+
+    #[test]
+    fn test_overrun_readable() {
+        let socket = NetlinkSocket::new(FileStatus::empty(), libc::SOCK_RAW, 1024, 0);
+        let mut socket = socket.borrow_mut();
+
+        // a socket with a pending error is readable even if its buffer is empty
+        socket.push_response(vec![0; 10]);
+        assert!(socket.recv_buffer.is_empty());
+        CallbackQueue::queue_and_run(|cb_queue| socket.refresh_readable_writable(cb_queue));
+        assert!(socket.state().contains(FileState::READABLE));
+
+        socket.overrun = false;
+        CallbackQueue::queue_and_run(|cb_queue| socket.refresh_readable_writable(cb_queue));
+        assert!(!socket.state().contains(FileState::READABLE));
+    }
+
+    #[test]
+    fn test_unbound_portid() {
+        // the socket doesn't have a port id until it's bound, or until it sends its first message
+        let socket = NetlinkSocket::new(FileStatus::empty(), libc::SOCK_DGRAM, 1024, 1024);
+        assert_eq!(socket.borrow().portid(), 0);
+        assert_eq!(
+            socket.borrow().getsockname().unwrap(),
+            Some(NetlinkAddr::new(0, 0))
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{LinkedList, VecDeque};
+use std::ffi::CStr;
 use std::io::Read;
 use std::ops::DerefMut;
 use std::sync::{Arc, Weak};
@@ -14,15 +15,18 @@ use crate::host::descriptor::shared_buf::{
     BufferHandle, BufferState, ReaderHandle, SharedBuf, WriterHandle,
 };
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
-use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
+use crate::host::descriptor::socket::{
+    RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket, SocketFlagOptions,
+};
 use crate::host::descriptor::{
     File, FileMode, FileState, FileStatus, OpenFile, StateEventSource, StateListenerFilter,
     SyscallResult,
 };
 use crate::host::memory_manager::MemoryManager;
 use crate::host::network::namespace::NetworkNamespace;
-use crate::host::syscall::io::{IoVec, IoVecReader, IoVecWriter};
+use crate::host::syscall::io::{write_partial, IoVec, IoVecReader, IoVecWriter};
 use crate::host::syscall_types::SyscallError;
+use crate::host::syslog::{SYSLOG_ABSTRACT_NAME, SYSLOG_PATH};
 use crate::utility::callback_queue::{CallbackQueue, Handle};
 use crate::utility::sockaddr::{SockaddrStorage, SockaddrUnix};
 use crate::utility::HostTreePointer;
@@ -59,6 +63,7 @@ impl UnixSocket {
                 status,
                 socket_type,
                 namespace: Arc::clone(namespace),
+                flag_options: SocketFlagOptions::default(),
                 has_open_file: false,
             };
 
@@ -259,27 +264,90 @@ impl UnixSocket {
 
     pub fn getsockopt(
         &mut self,
-        _level: libc::c_int,
-        _optname: libc::c_int,
-        _optval_ptr: ForeignPtr<()>,
-        _optlen: libc::socklen_t,
-        _memory_manager: &mut MemoryManager,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &mut MemoryManager,
         _cb_queue: &mut CallbackQueue,
     ) -> Result<libc::socklen_t, SyscallError> {
-        log::warn!("getsockopt() syscall not yet supported for unix sockets; Returning ENOSYS");
-        Err(Errno::ENOSYS.into())
+        let val: libc::c_int = match (level, optname) {
+            // unix sockets never have a pending error
+            (libc::SOL_SOCKET, libc::SO_ERROR) => 0,
+            (libc::SOL_SOCKET, libc::SO_DOMAIN) => libc::AF_UNIX,
+            (libc::SOL_SOCKET, libc::SO_TYPE) => match self.common.socket_type {
+                UnixSocketType::Stream => libc::SOCK_STREAM,
+                UnixSocketType::Dgram => libc::SOCK_DGRAM,
+                UnixSocketType::SeqPacket => libc::SOCK_SEQPACKET,
+            },
+            (libc::SOL_SOCKET, libc::SO_PROTOCOL) => 0,
+            (libc::SOL_SOCKET, libc::SO_ACCEPTCONN) => libc::c_int::from(matches!(
+                self.protocol_state,
+                ProtocolState::ConnOrientedListening(_)
+            )),
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => libc::c_int::from(self.common.flag_options.get(optname)),
+            (libc::SOL_SOCKET, _) => {
+                log::warn!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::ENOPROTOOPT.into());
+            }
+            _ => {
+                // linux unix sockets don't have any options at other levels
+                log::debug!("getsockopt called with unsupported level {level} and opt {optname}");
+                return Err(Errno::EOPNOTSUPP.into());
+            }
+        };
+
+        let optval_ptr = optval_ptr.cast::<libc::c_int>();
+        let bytes_written = write_partial(mem, &val, optval_ptr, optlen as usize)?;
+
+        Ok(bytes_written as libc::socklen_t)
     }
 
     pub fn setsockopt(
         &mut self,
-        _level: libc::c_int,
-        _optname: libc::c_int,
-        _optval_ptr: ForeignPtr<()>,
-        _optlen: libc::socklen_t,
-        _memory_manager: &MemoryManager,
+        level: libc::c_int,
+        optname: libc::c_int,
+        optval_ptr: ForeignPtr<()>,
+        optlen: libc::socklen_t,
+        mem: &MemoryManager,
     ) -> Result<(), SyscallError> {
-        log::warn!("setsockopt() syscall not yet supported for unix sockets; Returning ENOSYS");
-        Err(Errno::ENOSYS.into())
+        match (level, optname) {
+            (
+                libc::SOL_SOCKET,
+                libc::SO_REUSEADDR | libc::SO_REUSEPORT | libc::SO_KEEPALIVE | libc::SO_BROADCAST,
+            ) => {
+                type OptType = libc::c_int;
+
+                if usize::try_from(optlen).unwrap() < std::mem::size_of::<OptType>() {
+                    return Err(Errno::EINVAL.into());
+                }
+
+                let optval_ptr = optval_ptr.cast::<OptType>();
+                let val = mem.read(optval_ptr)?;
+
+                // linux only supports SO_REUSEPORT for inet sockets
+                if optname == libc::SO_REUSEPORT && val != 0 {
+                    return Err(Errno::EOPNOTSUPP.into());
+                }
+
+                // these options have no effect on unix sockets, but are still returned by
+                // getsockopt()
+                self.common.flag_options.set(optname, val != 0);
+
+                Ok(())
+            }
+            (libc::SOL_SOCKET, _) => {
+                log::warn!("setsockopt called with unsupported level {level} and opt {optname}");
+                Err(Errno::ENOPROTOOPT.into())
+            }
+            _ => {
+                log::debug!("setsockopt called with unsupported level {level} and opt {optname}");
+                Err(Errno::EOPNOTSUPP.into())
+            }
+        }
     }
 
     pub fn pair(
@@ -342,6 +410,38 @@ impl UnixSocket {
     pub fn state(&self) -> FileState {
         self.common.state
     }
+
+    /// Read the next message from a connectionless socket on behalf of shadow itself rather than a
+    /// managed process. Returns `None` if there are no messages to read.
+    pub fn recv_internal(&mut self, cb_queue: &mut CallbackQueue) -> Option<Vec<u8>> {
+        let ProtocolState::ConnLessInitial(Some(state)) = &mut self.protocol_state else {
+            return None;
+        };
+
+        let mut bytes = Vec::new();
+        {
+            let mut recv_buffer = self.common.recv_buffer.borrow_mut();
+            if !recv_buffer.has_data() {
+                return None;
+            }
+            recv_buffer.read(&mut bytes, cb_queue).unwrap();
+        }
+
+        let byte_data = state.recv_data.pop_front().unwrap();
+        assert_eq!(byte_data.num_bytes, u64::try_from(bytes.len()).unwrap());
+
+        // defer informing the sender until we're done processing the current socket
+        cb_queue.add(move |cb_queue| {
+            byte_data
+                .from_socket
+                .borrow_mut()
+                .inform_bytes_read(byte_data.num_bytes, cb_queue);
+        });
+
+        state.refresh_file_state(&mut self.common, cb_queue);
+
+        Some(bytes)
+    }
 }
 
 struct ConnOrientedInitial {
@@ -1852,6 +1952,7 @@ struct UnixSocketCommon {
     status: FileStatus,
     socket_type: UnixSocketType,
     namespace: Arc<AtomicRefCell<AbstractUnixNamespace>>,
+    flag_options: SocketFlagOptions,
     // should only be used by `OpenFile` to make sure there is only ever one `OpenFile` instance for
     // this file
     has_open_file: bool,
@@ -2218,6 +2319,11 @@ fn lookup_address(
         namespace
             .lookup(socket_type, name)
             .ok_or(linux_api::errno::Errno::ECONNREFUSED)
+    } else if addr.as_path().map(CStr::to_bytes) == Some(SYSLOG_PATH) {
+        // the host's syslog socket (if enabled) is bound to an abstract address
+        namespace
+            .lookup(socket_type, SYSLOG_ABSTRACT_NAME)
+            .ok_or(linux_api::errno::Errno::ENOENT)
     } else {
         log::warn!("Unix sockets with pathname addresses are not yet supported");
         Err(linux_api::errno::Errno::ENOENT)
@@ -77,6 +77,9 @@ enum TCPChildState {
     TCPCS_NONE, TCPCS_INCOMPLETE, TCPCS_PENDING, TCPCS_ACCEPTED
 };
 
+/* we don't randomize initial sequence numbers, and 0 is saved for representing control packets */
+#define TCP_INITIAL_SEQUENCE_NUMBER 1
+
 typedef enum TCPReceiveState TCPReceiveState;
 enum TCPReceiveState {
     TCPRS_OPEN = 0,
@@ -105,6 +108,11 @@ struct _TCPServer {
     /* maximum number of pending connections (capped at SHADOW_SOMAXCONN) */
     guint pendingMax;
     guint pendingCount;
+    /* number of children that haven't finished their handshake (the SYN queue) */
+    guint synCount;
+    /* when we last responded to a SYN with a SYN cookie */
+    gboolean hasSentSyncookie;
+    CSimulationTime lastSyncookieTime;
     /* IP and port of the last peer trying to connect to us; both in network byte order */
     in_addr_t lastPeerIP;
     in_port_t lastPeerPort;
@@ -126,6 +134,8 @@ struct _TCP {
     enum TCPState stateLast;
     enum TCPFlags flags;
     enum TCPError error;
+    /* when the connection entered the TIME_WAIT state */
+    CSimulationTime timeWaitStart;
 
     /* sequence numbers we track for incoming packets */
     struct {
@@ -169,14 +179,45 @@ struct _TCP {
         guint32 highestSequence;
         /* total number of packets sent */
         guint32 packetsSent;
-        /* total number of quick acknowledgments sent */
-        guint32 numQuickACKsSent;
+        /* total number of delayed acknowledgments scheduled */
+        guint32 numDelayedACKs;
         gboolean delayedACKIsScheduled;
         guint32 delayedACKCounter;
+        /* bytes received since we last sent an ACK */
+        gsize delayedACKBytes;
+        /* TCP_QUICKACK, send ACKs now instead of delaying them */
+        gboolean quickACK;
         /* list of selective ACKs, packets received after a missing packet */
         GList* selectiveACKs;
     } send;
 
+    /* small writes are held back by nagle's algorithm (unless TCP_NODELAY is set) or by TCP_CORK
+     * so that later writes can be sent in the same packet */
+    struct {
+        gboolean noDelay;
+        gboolean cork;
+        /* user data that isn't in a packet yet, always less than a full segment */
+        GByteArray* data;
+        /* when the oldest byte in data was written */
+        CSimulationTime heldSince;
+        /* sequence number of the last packet we sent with less than a full segment */
+        guint32 lastPartialSequence;
+        gboolean corkTimerIsScheduled;
+    } nagle;
+
+    /* the TCP options from RFC 7323. before the handshake completes, the flags are the options we
+     * offer, and after it they are the options that both sides agreed to use */
+    struct {
+        gboolean windowScaling;
+        /* the shift of the windows that we advertise */
+        unsigned char receiveWindowScale;
+        /* the shift of the windows that they advertise */
+        unsigned char sendWindowScale;
+        gboolean timestamps;
+        /* the timestamp to echo, from the segment that started the data we last acknowledged */
+        CSimulationTime recentTimestamp;
+    } options;
+
     struct {
         /* TCP provides reliable transport, keep track of packets until they are acked */
         GHashTable* queue;
@@ -386,6 +427,28 @@ static bool _tcpserver_acceptQueueFull(TCPServer* server) {
     return server->pendingCount >= server->pendingMax;
 }
 
+static bool _tcpserver_synQueueFull(TCPServer* server, const Host* host) {
+    MAGIC_ASSERT(server);
+    /* like linux, the SYN queue is limited by both the listen backlog and tcp_max_syn_backlog */
+    return server->synCount >= MIN(server->pendingMax, host_getTCPMaxSynBacklog(host));
+}
+
+/* Returns true if the packet acknowledges a SYN-ACK that we sent as a SYN cookie. */
+static bool _tcpserver_isSyncookieACK(TCPServer* server, PacketTCPHeader* header) {
+    MAGIC_ASSERT(server);
+
+    /* like linux, we only check for cookies if we recently sent one */
+    CSimulationTime now = worker_getCurrentSimulationTime();
+    if (!server->hasSentSyncookie ||
+        now - server->lastSyncookieTime > CONFIG_TCP_SYNCOOKIE_LIFETIME) {
+        return false;
+    }
+
+    /* the cookie is the sequence number of the SYN-ACK */
+    return (header->flags & PTCP_ACK) && !(header->flags & (PTCP_SYN | PTCP_FIN)) &&
+           header->acknowledgment == TCP_INITIAL_SEQUENCE_NUMBER + 1;
+}
+
 struct TCPCong_ *tcp_cong(TCP *tcp) {
     return &tcp->cong;
 }
@@ -663,9 +726,29 @@ static void _tcp_setState(TCP* tcp, const Host* host, enum TCPState state) {
     tcp->stateLast = tcp->state;
     tcp->state = state;
 
+    /* children are in their server's SYN queue until their handshake completes or fails */
+    if (tcp->child && tcp->stateLast != tcp->state) {
+        TCPServer* server = tcp->child->parent->server;
+        MAGIC_ASSERT(server);
+        if (tcp->state == TCPS_SYNRECEIVED) {
+            server->synCount++;
+        } else if (tcp->stateLast == TCPS_SYNRECEIVED) {
+            server->synCount--;
+        }
+    }
+
     trace("%s <-> %s: moved from TCP state '%s' to '%s'", tcp->super.boundString, tcp->super.peerString,
             _tcp_stateToAscii(tcp->stateLast), _tcp_stateToAscii(tcp->state));
 
+    if (tcp->stateLast != tcp->state) {
+        in_addr_t localIP = 0, peerIP = 0;
+        in_port_t localPort = 0, peerPort = 0;
+        legacysocket_getSocketName(&tcp->super, &localIP, &localPort);
+        legacysocket_getPeerName(&tcp->super, &peerIP, &peerPort);
+        worker_traceTcpStateChanged(localIP, localPort, peerIP, peerPort,
+                                    _tcp_stateToAscii(tcp->stateLast), _tcp_stateToAscii(tcp->state));
+    }
+
     /* some state transitions require us to update the descriptor status */
     switch (state) {
         case TCPS_LISTEN: {
@@ -680,6 +763,7 @@ static void _tcp_setState(TCP* tcp, const Host* host, enum TCPState state) {
         }
         case TCPS_ESTABLISHED: {
             tcp->flags |= TCPF_WAS_ESTABLISHED;
+            worker_traceConnectionEstablished(tcp->super.boundString, tcp->super.peerString);
             legacyfile_adjustStatus(
                 (LegacyFile*)tcp, STATUS_FILE_ACTIVE | STATUS_FILE_WRITABLE, TRUE);
             break;
@@ -752,11 +836,12 @@ static void _tcp_setState(TCP* tcp, const Host* host, enum TCPState state) {
             TaskRef* closeTask =
                 taskref_new_bound(host_getID(host), _tcp_runCloseTimerExpiredTask,
                                   (void*)inetSocket, NULL, inetsocket_dropVoid, NULL);
-            CSimulationTime delay = CONFIG_TCPCLOSETIMER_DELAY;
+            tcp->timeWaitStart = worker_getCurrentSimulationTime();
+            CSimulationTime delay = host_getTCPTimeWaitTimeout(host);
 
             /* if a child of a server initiated the close, close more quickly */
             if(tcp->child && tcp->child->parent) {
-                delay = SIMTIME_ONE_SECOND;
+                delay = MIN(delay, SIMTIME_ONE_SECOND);
             }
 
             host_scheduleTaskWithDelay(host, closeTask, delay);
@@ -775,7 +860,36 @@ static void _tcp_runCloseTimerExpiredTask(const Host* host, gpointer voidInetSoc
     TCP* tcp = inetsocket_asLegacyTcp(inetSocket);
     MAGIC_ASSERT(tcp);
 
+    /* the connection may have already closed so that a new connection could reuse its port */
+    if (tcp->state != TCPS_TIMEWAIT) {
+        return;
+    }
+
+    _tcp_setState(tcp, host, TCPS_CLOSED);
+}
+
+gboolean tcp_reuseTimeWait(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+
+    /* the user must have closed the socket */
+    if (tcp->state != TCPS_TIMEWAIT ||
+        !(legacyfile_getStatus((LegacyFile*)tcp) & STATUS_FILE_CLOSED)) {
+        return FALSE;
+    }
+
+    /* like linux, the connection must use timestamps (so that the peer can reject any of its old
+     * segments) and must have been in TIME_WAIT for at least a second */
+    if (!tcp->options.timestamps ||
+        worker_getCurrentSimulationTime() < tcp->timeWaitStart + SIMTIME_ONE_SECOND) {
+        return FALSE;
+    }
+
+    trace("%s <-> %s: closing TIME_WAIT connection so that its port can be reused",
+          tcp->super.boundString, tcp->super.peerString);
+
+    /* this will disassociate the socket from the network interface */
     _tcp_setState(tcp, host, TCPS_CLOSED);
+    return TRUE;
 }
 
 /* returns the total amount of buffered data in this TCP socket, including TCP-specific buffers */
@@ -783,7 +897,7 @@ gsize tcp_getOutputBufferLength(TCP* tcp) {
     MAGIC_ASSERT(tcp);
     /* this does not include the socket output buffer to avoid double counting, since the
      * data in the socket output buffer is already counted as part of the tcp retransmit queue */
-    return tcp->throttledOutputLength + tcp->retransmit.queueLength;
+    return tcp->nagle.data->len + tcp->throttledOutputLength + tcp->retransmit.queueLength;
 }
 
 /* returns the total amount of buffered data in this TCP socket, including TCP-specific buffers */
@@ -795,7 +909,7 @@ gsize tcp_getInputBufferLength(TCP* tcp) {
 /* returns the total number of bytes that we have not yet sent out into the network */
 gsize tcp_getNotSentBytes(TCP* tcp) {
     MAGIC_ASSERT(tcp);
-    return tcp->throttledOutputLength;
+    return tcp->nagle.data->len + tcp->throttledOutputLength;
 }
 
 static gsize _tcp_getBufferSpaceOut(TCP* tcp) {
@@ -852,6 +966,16 @@ static void _tcp_bufferPacketIn(TCP* tcp, Packet* packet) {
     }
 }
 
+/* the maximum payload size of our packets, which is smaller if they carry timestamps */
+static gsize _tcp_getMSS(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+
+    if (tcp->options.timestamps) {
+        return CONFIG_TCP_MAX_SEGMENT_SIZE - CONFIG_HEADER_SIZE_TCP_TIMESTAMPS;
+    }
+    return CONFIG_TCP_MAX_SEGMENT_SIZE;
+}
+
 static void _tcp_updateReceiveWindow(TCP* tcp) {
     MAGIC_ASSERT(tcp);
 
@@ -859,7 +983,7 @@ static void _tcp_updateReceiveWindow(TCP* tcp) {
      * unordered input packets should count against buffer space, so use the _tcp version. */
     //gsize space = _tcp_getBufferSpaceIn(tcp); // causes throughput problems
     gsize space = legacysocket_getInputBufferSpace(&(tcp->super));
-    gsize nPackets = space / CONFIG_TCP_MAX_SEGMENT_SIZE;
+    gsize nPackets = space / _tcp_getMSS(tcp);
     tcp->receive.window = nPackets;
 
     /* handle window updates */
@@ -960,7 +1084,7 @@ static void _tcp_sendControlPacket(TCP* tcp, const Host* host, enum ProtocolTCPF
     Packet* control = _tcp_createControlPacket(tcp, host, flags);
 
     /* make sure it gets sent before whatever else is in the queue */
-    packet_setPriority(control, 0);
+    packet_setPriority(control, PACKET_PRIORITY_CONTROL);
 
     /* push it in the buffer and to the socket */
     _tcp_bufferPacketOut(tcp, control);
@@ -1234,25 +1358,163 @@ static void _tcp_sendShutdownFin(TCP* tcp, const Host* host) {
     }
 }
 
+/* returns TRUE if a packet with less than a full segment of data should be held back so that later
+ * writes can be added to it, see tcp_nagle_check() in net/ipv4/tcp_output.c */
+static gboolean _tcp_shouldHoldPartialSegment(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+
+    /* nothing more will be written */
+    if (tcp->flags & TCPF_LOCAL_CLOSED_WR) {
+        return FALSE;
+    }
+
+    if (tcp->nagle.cork) {
+        return TRUE;
+    }
+
+    /* nagle's algorithm with minshall's variant: wait while our last partial segment is unacked */
+    return !tcp->nagle.noDelay && tcp->nagle.lastPartialSequence != 0 &&
+           tcp->nagle.lastPartialSequence >= tcp->send.unacked;
+}
+
+/* add user data to the held data, returning FALSE if the data could not be read */
+static gboolean _tcp_holdUserData(TCP* tcp, UntypedForeignPtr buffer, gsize nBytes,
+                                  const MemoryManager* mem) {
+    MAGIC_ASSERT(tcp);
+
+    guint heldLength = tcp->nagle.data->len;
+    utility_debugAssert(heldLength + nBytes <= _tcp_getMSS(tcp));
+
+    g_byte_array_set_size(tcp->nagle.data, heldLength + nBytes);
+    if (memorymanager_readPtr(mem, tcp->nagle.data->data + heldLength, buffer, nBytes) != 0) {
+        g_byte_array_set_size(tcp->nagle.data, heldLength);
+        return FALSE;
+    }
+
+    if (heldLength == 0) {
+        tcp->nagle.heldSince = worker_getCurrentSimulationTime();
+    }
+
+    return TRUE;
+}
+
+/* put the held data in a packet and buffer it for sending */
+static void _tcp_releaseHeldData(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+
+    gsize length = tcp->nagle.data->len;
+    if (length == 0) {
+        return;
+    }
+
+    trace("%s <-> %s: releasing %" G_GSIZE_FORMAT " held user bytes", tcp->super.boundString,
+          tcp->super.peerString, length);
+
+    Packet* packet = _tcp_createPacketWithoutPayload(tcp, host, PTCP_ACK, /*isEmpty=*/false);
+    packet_setPayloadFromShadow(
+        packet, tcp->nagle.data->data, length, host_getNextPacketPriority(host));
+    g_byte_array_set_size(tcp->nagle.data, 0);
+
+    if (length < _tcp_getMSS(tcp)) {
+        tcp->nagle.lastPartialSequence = packet_getTCPHeader(packet)->sequence;
+    }
+
+    /* we are sending more user data */
+    tcp->send.end++;
+
+    _tcp_bufferPacketOut(tcp, packet);
+
+    /* the output buffer holds the packet ref now */
+    packet_unref(packet);
+}
+
+static void _tcp_scheduleCorkTimer(TCP* tcp, const Host* host, CSimulationTime delay);
+
+static void _tcp_runCorkTimerExpiredTask(const Host* host, gpointer voidInetSocket,
+                                         gpointer userData) {
+    const InetSocket* inetSocket = voidInetSocket;
+    utility_alwaysAssert(inetSocket != NULL);
+    TCP* tcp = inetsocket_asLegacyTcp(inetSocket);
+    MAGIC_ASSERT(tcp);
+
+    tcp->nagle.corkTimerIsScheduled = FALSE;
+
+    if (!tcp->nagle.cork || tcp->nagle.data->len == 0) {
+        return;
+    }
+
+    CSimulationTime now = worker_getCurrentSimulationTime();
+    CSimulationTime expiration = tcp->nagle.heldSince + CONFIG_TCP_CORK_MAX_DELAY;
+
+    if (now >= expiration) {
+        /* data is only corked for a limited time */
+        trace("%s <-> %s: cork timer expired", tcp->super.boundString, tcp->super.peerString);
+        _tcp_releaseHeldData(tcp, host);
+        _tcp_flush(tcp, host);
+    } else {
+        /* the data that was held when the timer was scheduled has since been sent */
+        _tcp_scheduleCorkTimer(tcp, host, expiration - now);
+    }
+}
+
+static void _tcp_scheduleCorkTimer(TCP* tcp, const Host* host, CSimulationTime delay) {
+    MAGIC_ASSERT(tcp);
+
+    utility_alwaysAssert(tcp->rustSocket != NULL);
+    const InetSocket* inetSocket = inetsocketweak_upgrade(tcp->rustSocket);
+    utility_alwaysAssert(inetSocket != NULL);
+    TaskRef* corkTask = taskref_new_bound(host_getID(host), _tcp_runCorkTimerExpiredTask,
+                                          (void*)inetSocket, NULL, inetsocket_dropVoid, NULL);
+
+    host_scheduleTaskWithDelay(host, corkTask, delay);
+    taskref_drop(corkTask);
+
+    tcp->nagle.corkTimerIsScheduled = TRUE;
+}
+
+static void _tcp_scheduleCorkTimerIfNeeded(TCP* tcp, const Host* host) {
+    MAGIC_ASSERT(tcp);
+
+    if (tcp->nagle.cork && tcp->nagle.data->len > 0 && !tcp->nagle.corkTimerIsScheduled) {
+        _tcp_scheduleCorkTimer(tcp, host, CONFIG_TCP_CORK_MAX_DELAY);
+    }
+}
+
 void tcp_networkInterfaceIsAboutToSendPacket(TCP* tcp, const Host* host, Packet* packet) {
     MAGIC_ASSERT(tcp);
 
+    if (tcp->server) {
+        /* the only packets that servers send themselves are SYN cookies, which are already
+         * complete and are never retransmitted */
+        return;
+    }
+
     CSimulationTime now = worker_getCurrentSimulationTime();
 
+    PacketTCPHeader* header = packet_getTCPHeader(packet);
+
+    /* the window scale option is only sent in SYN packets */
+    gboolean windowScaleSet = (header->flags & PTCP_SYN) && tcp->options.windowScaling;
+
+    /* with the timestamps option we echo the timestamp as in RFC 7323, otherwise we only use the
+     * timestamps internally to measure the RTT */
+    CSimulationTime timestampEcho =
+        tcp->options.timestamps ? tcp->options.recentTimestamp : tcp->receive.lastTimestamp;
+
     /* update TCP header to our current advertised window and acknowledgment and timestamps */
-    packet_updateTCP(packet, tcp->receive.next, tcp->send.selectiveACKs, tcp->receive.window, 0,
-                     false, now, tcp->receive.lastTimestamp);
+    packet_updateTCP(packet, tcp->receive.next, tcp->send.selectiveACKs, tcp->receive.window,
+                     tcp->options.receiveWindowScale, windowScaleSet, tcp->options.timestamps, now,
+                     timestampEcho);
 
     /* keep track of the last things we sent them */
     tcp->send.lastAcknowledgment = tcp->receive.next;
     tcp->send.lastWindow = tcp->receive.window;
     tcp->info.lastAckSent = now;
 
-    PacketTCPHeader* header = packet_getTCPHeader(packet);
-
     if(header->flags & PTCP_ACK) {
         /* we are sending an ACK already, so we may not need any delayed ACK */
         tcp->send.delayedACKCounter = 0;
+        tcp->send.delayedACKBytes = 0;
     }
 
     if(header->sequence > 0) {
@@ -1273,6 +1535,11 @@ static void _tcp_flush(TCP* tcp, const Host* host) {
     _tcp_updateReceiveWindow(tcp);
     _tcp_updateSendWindow(tcp);
 
+    /* send any held data that no longer needs to be held */
+    if (tcp->nagle.data->len > 0 && !_tcp_shouldHoldPartialSegment(tcp)) {
+        _tcp_releaseHeldData(tcp, host);
+    }
+
     CSimulationTime now = worker_getCurrentSimulationTime();
     double dtime = (double)(now) / (1.0E9);
 
@@ -1350,10 +1617,8 @@ static void _tcp_flush(TCP* tcp, const Host* host) {
         const InetSocket* inetSocket = inetsocketweak_upgrade(tcp->rustSocket);
         utility_alwaysAssert(inetSocket != NULL);
 
-        CompatSocket compatSocket = compatsocket_fromInetSocket(inetSocket);
-
         gboolean success =
-            legacysocket_addToOutputBuffer(&(tcp->super), compatSocket, host, packet);
+            legacysocket_addToOutputBuffer(&(tcp->super), inetSocket, host, packet);
 
         tcp->send.packetsSent++;
         tcp->send.highestSequence = (guint32)MAX(tcp->send.highestSequence, (guint)header->sequence);
@@ -1583,6 +1848,19 @@ gint tcp_getConnectionError(TCP* tcp) {
     }
 }
 
+gint tcp_takeSocketError(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+
+    /* A reset is only reported once, and not at all if it was already reported by another call
+     * like connect(). */
+    if ((tcp->error & TCPE_CONNECTION_RESET) && !(tcp->flags & TCPF_RESET_SIGNALED)) {
+        tcp->flags |= TCPF_RESET_SIGNALED;
+        return (tcp->flags & TCPF_WAS_ESTABLISHED) ? ECONNRESET : ECONNREFUSED;
+    }
+
+    return 0;
+}
+
 static guint8 _tcp_getTCPInfoState(TCP* tcp) {
     switch(tcp->state) {
         case TCPS_ESTABLISHED: return (guint8) TCP_ESTABLISHED;
@@ -1610,16 +1888,30 @@ void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo) {
 //  tcpinfo->tcpi_retransmits;
 //  tcpinfo->tcpi_probes;
 //  tcpinfo->tcpi_backoff;
-//  tcpinfo->tcpi_options;
-//  tcpinfo->tcpi_snd_wscale;
-//  tcpinfo->tcpi_rcv_wscale;
+    if (tcp->state != TCPS_CLOSED && tcp->state != TCPS_LISTEN && tcp->state != TCPS_SYNSENT) {
+        /* the options were negotiated in the handshake */
+        if (tcp->options.timestamps) {
+            tcpinfo->tcpi_options |= TCPI_OPT_TIMESTAMPS;
+        }
+        if (tcp->options.windowScaling) {
+            tcpinfo->tcpi_options |= TCPI_OPT_WSCALE;
+            tcpinfo->tcpi_snd_wscale = tcp->options.sendWindowScale;
+            tcpinfo->tcpi_rcv_wscale = tcp->options.receiveWindowScale;
+        }
+    }
 
 //  tcpinfo->tcpi_rto;
 //  tcpinfo->tcpi_ato;
-    tcpinfo->tcpi_snd_mss = (u_int32_t)CONFIG_TCP_MAX_SEGMENT_SIZE;
-    tcpinfo->tcpi_rcv_mss = (u_int32_t)CONFIG_TCP_MAX_SEGMENT_SIZE;
+    tcpinfo->tcpi_snd_mss = (u_int32_t)_tcp_getMSS(tcp);
+    tcpinfo->tcpi_rcv_mss = (u_int32_t)_tcp_getMSS(tcp);
 
-    tcpinfo->tcpi_unacked = (u_int32_t)(tcp->send.next - tcp->send.unacked);
+    if (tcp->state == TCPS_LISTEN) {
+        /* like linux, listeners report the length and limit of their accept queue */
+        tcpinfo->tcpi_unacked = (u_int32_t)tcp->server->pendingCount;
+        tcpinfo->tcpi_sacked = (u_int32_t)(tcp->server->pendingMax - 1);
+    } else {
+        tcpinfo->tcpi_unacked = (u_int32_t)(tcp->send.next - tcp->send.unacked);
+    }
 //  tcpinfo->tcpi_sacked;
 //  tcpinfo->tcpi_lost;
     tcpinfo->tcpi_retrans = (u_int32_t) tcp->info.retransmitCount;
@@ -2011,6 +2303,132 @@ static void _tcp_sendACKTaskCallback(const Host* host, gpointer voidInetSocket,
     }
 }
 
+/* use the RFC 7323 options only if both sides offered them in their SYNs */
+static void _tcp_negotiateOptions(TCP* tcp, PacketTCPHeader* header) {
+    MAGIC_ASSERT(tcp);
+
+    tcp->options.windowScaling = tcp->options.windowScaling && header->windowScaleSet;
+    if (tcp->options.windowScaling) {
+        tcp->options.sendWindowScale = MIN(header->windowScale, CONFIG_TCP_MAX_WINDOW_SCALE);
+    } else {
+        tcp->options.receiveWindowScale = 0;
+        tcp->options.sendWindowScale = 0;
+    }
+
+    tcp->options.timestamps = tcp->options.timestamps && header->timestampSet;
+    if (tcp->options.timestamps) {
+        tcp->options.recentTimestamp = header->timestampValue;
+    }
+
+    trace("negotiated window scaling=%d (send shift %u, receive shift %u) and timestamps=%d",
+          tcp->options.windowScaling, tcp->options.sendWindowScale,
+          tcp->options.receiveWindowScale, tcp->options.timestamps);
+}
+
+/* Create a child of the server for the peer that sent the packet. Returns NULL if the child
+ * couldn't be created. */
+static TCP* _tcp_newChild(TCP* tcp, const Host* host, PacketTCPHeader* header,
+                          guint32 peerInitialSequence) {
+    MAGIC_ASSERT(tcp);
+    MAGIC_ASSERT(tcp->server);
+
+    guint64 recvBufSize = host_getConfiguredRecvBufSize(host);
+    guint64 sendBufSize = host_getConfiguredSendBufSize(host);
+
+    /* We will register the child socket with whichever process called listen() on the
+     * parent socket. This is incorrect and we should register the child socket with
+     * whichever process eventually calls accept() on the parent socket, but this is
+     * difficult to fix and isn't an issue until we support fork().
+     * See: https://github.com/shadow/shadow/issues/1780 */
+    const Process* registerInProcess = host_getProcess(host, tcp->server->processForChildren);
+    if (!registerInProcess) {
+        debug("Listening process no longer exists");
+        return NULL;
+    }
+    /* The descriptor table is stored in the  thread; typically all threads
+     * within a Process share the same one, so using an arbitrary thread
+     * should work. This should be fixed as part of fixing
+     * https://github.com/shadow/shadow/issues/1780.
+     */
+    const Thread* registerInThread = process_firstLiveThread(registerInProcess);
+    utility_alwaysAssert(registerInThread != NULL);
+
+    TCP* multiplexed = tcp_new(host, recvBufSize, sendBufSize);
+    /* like linux, the child inherits the listener's socket options */
+    multiplexed->nagle.noDelay = tcp->nagle.noDelay;
+    multiplexed->nagle.cork = tcp->nagle.cork;
+    _tcp_negotiateOptions(multiplexed, header);
+    Descriptor* desc = descriptor_fromLegacyTcp(multiplexed, /* flags= */ 0);
+    int handle = thread_registerDescriptor(registerInThread, desc);
+
+    multiplexed->child =
+        _tcpchild_new(multiplexed, tcp, handle, header->sourceIP, header->sourcePort);
+    utility_debugAssert(
+        g_hash_table_lookup(tcp->server->children, &(multiplexed->child->key)) == NULL);
+
+    /* multiplexed TCP was initialized with a ref of 1, which the host table consumes.
+     * so we need another ref for the children table */
+    legacyfile_refWeak(multiplexed);
+    g_hash_table_replace(tcp->server->children, &(multiplexed->child->key), multiplexed);
+
+    multiplexed->receive.start = peerInitialSequence;
+    multiplexed->receive.next = multiplexed->receive.start + 1;
+
+    trace("%s <-> %s: server multiplexed child socket %s <-> %s", tcp->super.boundString,
+          tcp->super.peerString, multiplexed->super.boundString, multiplexed->super.peerString);
+
+    return multiplexed;
+}
+
+/* Add a child that finished its handshake to its server's accept queue. */
+static void _tcpchild_addToAcceptQueue(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    MAGIC_ASSERT(tcp->child);
+
+    TCP* parent = tcp->child->parent;
+    MAGIC_ASSERT(parent->server);
+
+    tcp->child->state = TCPCS_PENDING;
+    g_queue_push_tail(parent->server->pending, tcp);
+    parent->server->pendingCount += 1;
+
+    /* user should accept new child from parent */
+    legacyfile_adjustStatus(&(parent->super.super), STATUS_FILE_READABLE, TRUE);
+}
+
+/* Respond to a SYN with a SYN-ACK without creating a child, like a linux SYN cookie. We don't
+ * remember anything about the connection, so the SYN-ACK is never retransmitted and doesn't
+ * offer any TCP options. */
+static void _tcp_sendSyncookie(TCP* tcp, const Host* host, PacketTCPHeader* header) {
+    MAGIC_ASSERT(tcp);
+    MAGIC_ASSERT(tcp->server);
+
+    trace("%s <-> %s: sending SYN cookie now", tcp->super.boundString, tcp->super.peerString);
+
+    /* reply from the address that the SYN was sent to */
+    Packet* packet = packet_new(host);
+    packet_setTCP(packet, PTCP_SYN | PTCP_ACK, header->destinationIP, header->destinationPort,
+                  header->sourceIP, header->sourcePort, TCP_INITIAL_SEQUENCE_NUMBER);
+    packet_addDeliveryStatus(packet, PDS_SND_CREATED);
+
+    _tcp_updateReceiveWindow(tcp);
+    packet_updateTCP(packet, header->sequence + 1, NULL, tcp->receive.window, 0, false, false, 0,
+                     0);
+
+    /* make sure it gets sent before whatever else is in the queue */
+    packet_setPriority(packet, PACKET_PRIORITY_CONTROL);
+
+    /* push it in the buffer and to the socket */
+    _tcp_bufferPacketOut(tcp, packet);
+    _tcp_flush(tcp, host);
+
+    /* the output buffer holds the packet ref now */
+    packet_unref(packet);
+
+    tcp->server->hasSentSyncookie = TRUE;
+    tcp->server->lastSyncookieTime = worker_getCurrentSimulationTime();
+}
+
 /* return TRUE if the packet should be retransmitted */
 static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* packet) {
     TCP* tcp = _tcp_fromLegacyFile((LegacyFile*)socket);
@@ -2053,6 +2471,26 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
         tcp->server->lastIP = header->destinationIP;
     }
 
+    gboolean isSynchronized = tcp->state != TCPS_LISTEN && tcp->state != TCPS_SYNSENT;
+    if (isSynchronized && tcp->options.timestamps && header->timestampSet) {
+        /* PAWS (RFC 7323): a segment with a timestamp older than the most recent one is an old
+         * duplicate, which we drop but still acknowledge */
+        if (header->timestampValue < tcp->options.recentTimestamp) {
+            trace("dropping old duplicate packet with timestamp %" G_GUINT64_FORMAT
+                  " older than %" G_GUINT64_FORMAT,
+                  header->timestampValue, tcp->options.recentTimestamp);
+            packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+            _tcp_sendControlPacket(tcp, host, PTCP_ACK);
+            return;
+        }
+
+        /* echo the timestamp of the earliest segment that we haven't acknowledged yet, so that
+         * the other side's RTT measurements include our ACK delay */
+        if (header->sequence <= tcp->send.lastAcknowledgment) {
+            tcp->options.recentTimestamp = header->timestampValue;
+        }
+    }
+
     /* go through the state machine, tracking processing and response */
     TCPProcessFlags flags = TCP_PF_NONE;
     enum ProtocolTCPFlags responseFlags = PTCP_NONE;
@@ -2074,60 +2512,66 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                     return;
                 }
 
-                flags |= TCP_PF_PROCESSED;
+                if (_tcpserver_synQueueFull(tcp->server, host)) {
+                    if (host_tcpSyncookies(host)) {
+                        /* respond without remembering the connection */
+                        debug("Server socket SYN queue is full; sending a SYN cookie");
+                        flags |= TCP_PF_PROCESSED;
+                        _tcp_sendSyncookie(tcp, host, header);
+                        break;
+                    }
 
-                guint64 recvBufSize = host_getConfiguredRecvBufSize(host);
-                guint64 sendBufSize = host_getConfiguredSendBufSize(host);
-
-                /* We will register the child socket with whichever process called listen() on the
-                 * parent socket. This is incorrect and we should register the child socket with
-                 * whichever process eventually calls accept() on the parent socket, but this is
-                 * difficult to fix and isn't an issue until we support fork().
-                 * See: https://github.com/shadow/shadow/issues/1780 */
-                const Process* registerInProcess =
-                    host_getProcess(host, tcp->server->processForChildren);
-                if (!registerInProcess) {
-                    debug("Listening process no longer exists");
+                    debug("Server socket SYN queue is full; dropping SYN packet");
                     packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
                     return;
                 }
-                /* The descriptor table is stored in the  thread; typically all threads
-                 * within a Process share the same one, so using an arbitrary thread
-                 * should work. This should be fixed as part of fixing
-                 * https://github.com/shadow/shadow/issues/1780.
-                 */
-                const Thread* registerInThread = process_firstLiveThread(registerInProcess);
-                utility_alwaysAssert(registerInThread != NULL);
 
                 /* we need to multiplex a new child */
-                TCP* multiplexed = tcp_new(host, recvBufSize, sendBufSize);
-                Descriptor* desc = descriptor_fromLegacyTcp(multiplexed, /* flags= */ 0);
-                int handle = thread_registerDescriptor(registerInThread, desc);
+                TCP* multiplexed = _tcp_newChild(tcp, host, header, header->sequence);
+                if (!multiplexed) {
+                    packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+                    return;
+                }
 
-                multiplexed->child =
-                    _tcpchild_new(multiplexed, tcp, handle, header->sourceIP, header->sourcePort);
-                utility_debugAssert(
-                    g_hash_table_lookup(tcp->server->children, &(multiplexed->child->key)) == NULL);
+                flags |= TCP_PF_PROCESSED;
+                _tcp_setState(multiplexed, host, TCPS_SYNRECEIVED);
 
-                /* multiplexed TCP was initialized with a ref of 1, which the host table consumes.
-                 * so we need another ref for the children table */
-                legacyfile_refWeak(multiplexed);
-                g_hash_table_replace(tcp->server->children, &(multiplexed->child->key), multiplexed);
+                /* child will send response */
+                tcp = multiplexed;
+                responseFlags = PTCP_SYN|PTCP_ACK;
 
-                tcp->server->pendingCount += 1;
+                trace("new child state %s", _tcp_stateToAscii(tcp->state));
+            }
+            /* receive ACK of a SYN cookie, move to ESTABLISHED */
+            else if (_tcpserver_isSyncookieACK(tcp->server, header)) {
+                if (_tcpserver_acceptQueueFull(tcp->server)) {
+                    /* like linux, drop it whether or not tcp_abort_on_overflow is set */
+                    debug("Server socket accept queue is full; dropping SYN cookie ACK packet");
+                    packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+                    return;
+                }
 
-                multiplexed->receive.start = header->sequence;
-                multiplexed->receive.next = multiplexed->receive.start + 1;
+                /* the ACK doesn't tell us their initial sequence number, but it's the same as
+                 * ours */
+                TCP* multiplexed =
+                    _tcp_newChild(tcp, host, header, TCP_INITIAL_SEQUENCE_NUMBER);
+                if (!multiplexed) {
+                    packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+                    return;
+                }
 
-                trace("%s <-> %s: server multiplexed child socket %s <-> %s",
-                        tcp->super.boundString, tcp->super.peerString,
-                        multiplexed->super.boundString, multiplexed->super.peerString);
+                flags |= TCP_PF_PROCESSED;
 
-                _tcp_setState(multiplexed, host, TCPS_SYNRECEIVED);
+                /* the server already sent the SYN-ACK, and this ACK acknowledges it */
+                multiplexed->send.unacked = TCP_INITIAL_SEQUENCE_NUMBER + 1;
+                multiplexed->send.next = TCP_INITIAL_SEQUENCE_NUMBER + 1;
+                multiplexed->send.highestSequence = TCP_INITIAL_SEQUENCE_NUMBER;
 
-                /* child will send response */
+                _tcp_setState(multiplexed, host, TCPS_ESTABLISHED);
+                _tcpchild_addToAcceptQueue(multiplexed);
+
+                /* child will process any data in the packet */
                 tcp = multiplexed;
-                responseFlags = PTCP_SYN|PTCP_ACK;
 
                 trace("new child state %s", _tcp_stateToAscii(tcp->state));
             }
@@ -2140,6 +2584,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 flags |= TCP_PF_PROCESSED;
                 tcp->receive.start = header->sequence;
                 tcp->receive.next = tcp->receive.start + 1;
+                _tcp_negotiateOptions(tcp, header);
 
                 responseFlags |= PTCP_ACK;
                 _tcp_setState(tcp, host, TCPS_ESTABLISHED);
@@ -2149,6 +2594,7 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                 flags |= TCP_PF_PROCESSED;
                 tcp->receive.start = header->sequence;
                 tcp->receive.next = tcp->receive.start + 1;
+                _tcp_negotiateOptions(tcp, header);
 
                 responseFlags |= PTCP_ACK;
                 _tcp_setState(tcp, host, TCPS_SYNRECEIVED);
@@ -2160,24 +2606,39 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
         case TCPS_SYNRECEIVED: {
             /* receive ACK, move to ESTABLISHED */
             if(header->flags & PTCP_ACK) {
+                if (tcp->child && _tcpserver_acceptQueueFull(tcp->child->parent->server)) {
+                    /* like linux, stay in the SYN queue and wait for another ACK (for example when
+                     * they receive our retransmitted SYN-ACK), or reset the connection */
+                    /* https://blog.cloudflare.com/syn-packet-handling-in-the-wild/#slowapplication
+                     */
+                    debug("Server socket accept queue is full; dropping ACK packet");
+                    packet_addDeliveryStatus(packet, PDS_RCV_SOCKET_DROPPED);
+                    if (host_tcpAbortOnOverflow(host)) {
+                        _tcp_sendControlPacket(tcp, host, PTCP_RST);
+                        _tcp_setState(tcp, host, TCPS_CLOSED);
+                    }
+                    return;
+                }
+
                 flags |= TCP_PF_PROCESSED;
                 _tcp_setState(tcp, host, TCPS_ESTABLISHED);
 
                 /* if this is a child, mark it accordingly */
                 if(tcp->child) {
-                    tcp->child->state = TCPCS_PENDING;
-                    g_queue_push_tail(tcp->child->parent->server->pending, tcp);
-                    /* user should accept new child from parent */
-                    legacyfile_adjustStatus(
-                        &(tcp->child->parent->super.super), STATUS_FILE_READABLE, TRUE);
+                    _tcpchild_addToAcceptQueue(tcp);
                 }
             }
             break;
         }
 
         case TCPS_ESTABLISHED: {
+            /* receive retransmitted SYNACK, our ACK may have been lost or dropped so send it again */
+            if ((header->flags & PTCP_SYN) && (header->flags & PTCP_ACK)) {
+                flags |= TCP_PF_PROCESSED;
+                responseFlags |= PTCP_ACK;
+            }
             /* receive FIN, send FINACK, move to CLOSEWAIT */
-            if(header->flags & PTCP_FIN) {
+            else if(header->flags & PTCP_FIN) {
                 flags |= TCP_PF_PROCESSED;
 
                 /* other side of connection closed */
@@ -2304,7 +2765,11 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
 
     /* update the last time stamp value (RFC 1323) */
     tcp->receive.lastTimestamp = header->timestampValue;
-    if(header->timestampEcho && tcp->retransmit.backoffCount == 0) {
+
+    /* with the timestamps option, the echo may be from an earlier segment than the one being
+     * acknowledged, so only ACKs of new data give valid RTT measurements (RFC 7323, section 4) */
+    gboolean isValidEcho = !tcp->options.timestamps || (flags & TCP_PF_DATA_ACKED);
+    if (header->timestampEcho && isValidEcho && tcp->retransmit.backoffCount == 0) {
         _tcp_updateRTTEstimate(tcp, host, header->timestampEcho);
     }
 
@@ -2341,7 +2806,17 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
         _rswlog(tcp, "Sending control packet on %d\n",
                 header->sequence);
 
-        if(responseFlags != PTCP_ACK) { // includes DUPACKs
+        if (responseFlags == PTCP_ACK) {
+            tcp->send.delayedACKBytes += packetLength;
+        }
+
+        /* like linux, don't leave too much received data unacknowledged */
+        guint64 maxUnackedBytes = host_getTCPDelayedACKMaxBytes(host);
+        gboolean tooManyUnackedBytes =
+            maxUnackedBytes > 0 && tcp->send.delayedACKBytes >= maxUnackedBytes;
+
+        if (responseFlags != PTCP_ACK || tcp->send.quickACK ||
+            tooManyUnackedBytes) { // includes DUPACKs
             /* just send the response now */
             trace("sending ACK control packet now");
             _tcp_sendControlPacket(tcp, host, responseFlags);
@@ -2357,15 +2832,12 @@ static void _tcp_processPacket(LegacySocket* socket, const Host* host, Packet* p
                     taskref_new_bound(host_getID(host), _tcp_sendACKTaskCallback, (void*)inetSocket,
                                       NULL, inetsocket_dropVoid, NULL);
 
-                /* figure out what we should use as delay */
-                CSimulationTime delay = 0;
-                /* "quick acknowledgments" happen at the beginning of a connection */
-                if(tcp->send.numQuickACKsSent < 1000) {
-                    /* we want the other side to get the ACKs sooner so we don't throttle its sending rate */
-                    delay = 1*SIMTIME_ONE_MILLISECOND;
-                    tcp->send.numQuickACKsSent++;
-                } else {
-                    delay = 5*SIMTIME_ONE_MILLISECOND;
+                /* "quick acknowledgments" happen at the beginning of a connection, so that the
+                 * other side gets the ACKs sooner and we don't throttle its sending rate */
+                CSimulationTime delay =
+                    host_getTCPDelayedACKTimeout(host, tcp->send.numDelayedACKs);
+                if (tcp->send.numDelayedACKs < G_MAXUINT32) {
+                    tcp->send.numDelayedACKs++;
                 }
 
                 host_scheduleTaskWithDelay(host, sendACKTask, delay);
@@ -2436,7 +2908,7 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
     gsize remaining = MIN(acceptable, space);
 
     /* break data into segments and send each in a packet */
-    gsize maxPacketLength = CONFIG_TCP_MAX_SEGMENT_SIZE;
+    gsize maxPacketLength = _tcp_getMSS(tcp);
     gsize bytesCopied = 0;
 
     /* Need non-NULL buffer. */
@@ -2450,20 +2922,55 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
         return -EFAULT;
     }
 
+    /* the data first fills up any partial segment we're holding */
+    if (tcp->nagle.data->len > 0 && remaining > 0) {
+        gsize copyLength = MIN(maxPacketLength - tcp->nagle.data->len, remaining);
+
+        if (!_tcp_holdUserData(tcp, buffer, copyLength, mem)) {
+            return -EFAULT;
+        }
+
+        remaining -= copyLength;
+        bytesCopied += copyLength;
+
+        if (tcp->nagle.data->len == maxPacketLength) {
+            _tcp_releaseHeldData(tcp, host);
+        }
+    }
+
+    gboolean holdPartialSegment = _tcp_shouldHoldPartialSegment(tcp);
+
     /* create as many packets as needed */
     while(remaining > 0) {
         gsize copyLength = MIN(maxPacketLength, remaining);
+        UntypedForeignPtr copyBuffer = (UntypedForeignPtr){.val = buffer.val + bytesCopied};
+
+        if (copyLength < maxPacketLength && holdPartialSegment) {
+            /* hold the partial segment so that later writes can be added to it */
+            if (!_tcp_holdUserData(tcp, copyBuffer, copyLength, mem)) {
+                if (bytesCopied == 0) {
+                    return -EFAULT;
+                }
+                break;
+            }
+
+            remaining -= copyLength;
+            bytesCopied += copyLength;
+            continue;
+        }
 
         /* use helper to create the packet */
-        Packet* packet = _tcp_createDataPacket(tcp, host, PTCP_ACK,
-                                               (UntypedForeignPtr){.val = buffer.val + bytesCopied},
-                                               copyLength, mem);
+        Packet* packet = _tcp_createDataPacket(tcp, host, PTCP_ACK, copyBuffer, copyLength, mem);
 
         if(copyLength > 0) {
             /* we are sending more user data */
             tcp->send.end++;
         }
 
+        if (copyLength < maxPacketLength) {
+            tcp->nagle.lastPartialSequence = packet_getTCPHeader(packet)->sequence;
+        }
+
         /* buffer the outgoing packet in TCP */
         _tcp_bufferPacketOut(tcp, packet);
 
@@ -2476,6 +2983,13 @@ gssize tcp_sendUserData(TCP* tcp, const Host* host, UntypedForeignPtr buffer, gs
 
     trace("%s <-> %s: sending %"G_GSIZE_FORMAT" user bytes", tcp->super.boundString, tcp->super.peerString, bytesCopied);
 
+    if (bytesCopied > 0) {
+        /* like linux, sending data takes us out of quick ACK mode */
+        tcp->send.quickACK = FALSE;
+    }
+
+    _tcp_scheduleCorkTimerIfNeeded(tcp, host);
+
     /* now flush as much as possible out to socket */
     _tcp_flush(tcp, host);
 
@@ -2691,6 +3205,7 @@ static void _tcp_free(LegacyFile* descriptor) {
     priorityqueue_free(tcp->throttledOutput);
     priorityqueue_free(tcp->unorderedInput);
     g_hash_table_destroy(tcp->retransmit.queue);
+    g_byte_array_unref(tcp->nagle.data);
     priorityqueue_free(tcp->retransmit.scheduledTimerExpirations);
 
     if (tcp->partialUserDataPacket != NULL) {
@@ -2747,6 +3262,11 @@ static void _tcp_close(LegacyFile* descriptor, const Host* host) {
         case TCPS_SYNRECEIVED:
         case TCPS_ESTABLISHED:
         case TCPS_CLOSEWAIT: {
+            if (tcp->nagle.data->len > 0) {
+                /* held data is no longer held since nothing more will be written */
+                _tcp_flush(tcp, host);
+            }
+
             if(tcp_getOutputBufferLength(tcp) == 0) {
                 _tcp_sendShutdownFin(tcp, host);
             } else {
@@ -2793,6 +3313,11 @@ gint tcp_shutdown(TCP* tcp, const Host* host, gint how) {
         tcp->flags |= TCPF_LOCAL_CLOSED_WR;
         tcp->error |= TCPE_SEND_EOF;
 
+        if (tcp->nagle.data->len > 0) {
+            /* held data is no longer held since nothing more will be written */
+            _tcp_flush(tcp, host);
+        }
+
         if(tcp_getOutputBufferLength(tcp) == 0) {
             _tcp_sendShutdownFin(tcp, host);
         } else {
@@ -2803,6 +3328,63 @@ gint tcp_shutdown(TCP* tcp, const Host* host, gint how) {
     return 0;
 }
 
+gboolean tcp_getNoDelay(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->nagle.noDelay;
+}
+
+void tcp_setNoDelay(TCP* tcp, const Host* host, gboolean noDelay) {
+    MAGIC_ASSERT(tcp);
+
+    tcp->nagle.noDelay = noDelay;
+
+    if (noDelay && tcp->nagle.data->len > 0) {
+        /* like linux, this sends any held data even if we're corked */
+        _tcp_releaseHeldData(tcp, host);
+        _tcp_flush(tcp, host);
+    }
+}
+
+gboolean tcp_getCork(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->nagle.cork;
+}
+
+void tcp_setCork(TCP* tcp, const Host* host, gboolean cork) {
+    MAGIC_ASSERT(tcp);
+
+    tcp->nagle.cork = cork;
+
+    if (cork) {
+        _tcp_scheduleCorkTimerIfNeeded(tcp, host);
+    } else if (tcp->nagle.data->len > 0) {
+        /* like linux, with TCP_NODELAY any held data is sent now, otherwise it's still subject to
+         * nagle's algorithm */
+        if (tcp->nagle.noDelay) {
+            _tcp_releaseHeldData(tcp, host);
+        }
+        _tcp_flush(tcp, host);
+    }
+}
+
+gboolean tcp_getQuickACK(TCP* tcp) {
+    MAGIC_ASSERT(tcp);
+    return tcp->send.quickACK;
+}
+
+void tcp_setQuickACK(TCP* tcp, const Host* host, gboolean quickACK) {
+    MAGIC_ASSERT(tcp);
+
+    tcp->send.quickACK = quickACK;
+
+    if (quickACK && tcp->send.delayedACKCounter > 0) {
+        /* send the pending delayed ACK now */
+        trace("sending a delayed ACK now since quick ACKs were enabled");
+        _tcp_sendControlPacket(tcp, host, PTCP_ACK);
+        tcp->send.delayedACKCounter = 0;
+    }
+}
+
 static gssize _sendUserDataPanic(LegacySocket* socket, const Thread* thread,
                                  UntypedForeignPtr buffer, gsize nBytes, in_addr_t ip,
                                  in_port_t port) {
@@ -2856,8 +3438,7 @@ TCP* tcp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize) {
     tcp->receive.window = initial_window;
     tcp->receive.lastWindow = initial_window;
 
-    /* 0 is saved for representing control packets */
-    guint32 initialSequenceNumber = 1;
+    guint32 initialSequenceNumber = TCP_INITIAL_SEQUENCE_NUMBER;
 
     /* the first packet (the SYN packet) has a sequence number of 'initialSequenceNumber' */
     tcp->send.unacked = initialSequenceNumber;
@@ -2871,6 +3452,22 @@ TCP* tcp_new(const Host* host, guint receiveBufferSize, guint sendBufferSize) {
 
     tcp->autotune.isEnabled = TRUE;
 
+    /* like linux, nagle's algorithm is enabled by default */
+    tcp->nagle.data = g_byte_array_new();
+
+    /* the options that we offer in our SYN */
+    tcp->options.windowScaling = host_tcpWindowScaling(host);
+    tcp->options.timestamps = host_tcpTimestamps(host);
+    if (tcp->options.windowScaling) {
+        /* like linux, use a shift that allows us to advertise the largest receive buffer */
+        gsize space = MAX(receiveBufferSize, CONFIG_TCP_RMEM_MAX);
+        while (space > G_MAXUINT16 &&
+               tcp->options.receiveWindowScale < CONFIG_TCP_MAX_WINDOW_SCALE) {
+            space >>= 1;
+            tcp->options.receiveWindowScale++;
+        }
+    }
+
     tcp->throttledOutput =
             priorityqueue_new((GCompareDataFunc)packet_compareTCPSequence, NULL, (GDestroyNotify)packet_unref);
     tcp->unorderedInput =
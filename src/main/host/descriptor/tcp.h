@@ -64,6 +64,10 @@ void tcp_setRustSocket(TCP* tcp, InetSocketWeak* rustSocket);
 gint tcp_getConnectionError(TCP* tcp);
 // clang-format on
 
+/* Returns the socket's pending error as a positive errno value for SO_ERROR, or 0 if there is
+ * none, and clears it. */
+gint tcp_takeSocketError(TCP* tcp);
+
 void tcp_getInfo(TCP* tcp, struct tcp_info *tcpinfo);
 void tcp_enterServerMode(TCP* tcp, const Host* host, pid_t process, gint backlog);
 void tcp_updateServerBacklog(TCP* tcp, gint backlog);
@@ -82,6 +86,18 @@ gsize tcp_getNotSentBytes(TCP* tcp);
 void tcp_disableSendBufferAutotuning(TCP* tcp);
 void tcp_disableReceiveBufferAutotuning(TCP* tcp);
 
+/* the TCP_NODELAY, TCP_CORK, and TCP_QUICKACK socket options */
+gboolean tcp_getNoDelay(TCP* tcp);
+void tcp_setNoDelay(TCP* tcp, const Host* host, gboolean noDelay);
+gboolean tcp_getCork(TCP* tcp);
+void tcp_setCork(TCP* tcp, const Host* host, gboolean cork);
+gboolean tcp_getQuickACK(TCP* tcp);
+void tcp_setQuickACK(TCP* tcp, const Host* host, gboolean quickACK);
+
+/* If the connection is in the TIME_WAIT state and its port can be reused by a new connection,
+ * closes it and returns TRUE. */
+gboolean tcp_reuseTimeWait(TCP* tcp, const Host* host);
+
 gboolean tcp_isValidListener(TCP* tcp);
 gboolean tcp_isListeningAllowed(TCP* tcp);
 
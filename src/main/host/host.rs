@@ -1,19 +1,20 @@
 use std::cell::{Cell, Ref, RefCell, RefMut, UnsafeCell};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{CStr, CString, OsString};
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4};
 use std::num::NonZeroU8;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, RangeInclusive};
 use std::os::unix::prelude::OsStringExt;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use atomic_refcell::AtomicRefCell;
+use linux_api::errno::Errno;
 use linux_api::signal::{siginfo_t, Signal};
-use log::{debug, trace};
+use log::{debug, error, info, trace, warn};
 use logger::LogLevel;
 use once_cell::unsync::OnceCell;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256PlusPlus;
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
@@ -29,25 +30,61 @@ use shadow_shmem::allocator::ShMemBlock;
 use shadow_tsc::Tsc;
 use vasi_sync::scmutex::SelfContainedMutexGuard;
 
-use crate::core::sim_config::PcapConfig;
-use crate::core::support::configuration::{ProcessFinalState, QDiscMode};
+use crate::core::group_stats::HostStats;
+use crate::core::sim_config::{
+    Bridge as BridgeConfig, ListenSocket, LoadBalancer as LoadBalancerConfig,
+    NativeApp as NativeAppConfig, PcapConfig, Probe, ProbePort, SeedNode, TcpDelayedAck,
+    TcpTimeWait, Uname,
+};
+use crate::core::support::configuration::{
+    EventQueueLimitAction, OutputCompression, PacketPayload, ProcessFinalState, QDiscMode,
+    ResolverFamily, RouterQDiscMode, ThrottleDirection,
+};
 use crate::core::work::event::{Event, EventData};
 use crate::core::work::event_queue::EventQueue;
 use crate::core::work::task::TaskRef;
 use crate::core::worker::Worker;
 use crate::cshadow;
+use crate::host::app_metrics;
+use crate::host::credentials::Credentials;
+use crate::host::descriptor::pty::PtyTable;
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
-use crate::host::network::interface::{FifoPacketPriority, NetworkInterface, PcapOptions};
+use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::load_balancer::{Action, LoadBalancer, Protocol};
+use crate::host::native_app::NativeApp;
+use crate::host::network::arp::{ArpCache, ArpNeighbor};
+use crate::host::network::bridge::Bridge;
+use crate::host::network::firewall::Firewall;
+use crate::host::network::interface::{
+    FifoPacketPriority, NetworkInterface, PcapOptions, FIFO_FIRST_DATA_PRIORITY,
+};
 use crate::host::network::namespace::NetworkNamespace;
+use crate::host::network::proc_net;
+use crate::host::persistent_state::{self, PersistentState};
+use crate::host::probe::{ProbeResponder, Prober};
 use crate::host::process::Process;
-use crate::host::thread::ThreadId;
+use crate::host::quota::QuotaLimits;
+use crate::host::resolver::{Lookup, LookupStatus, Resolver};
+use crate::host::seed_node::SeedNodeService;
+use crate::host::syslog::SyslogSink;
+use crate::host::thread::{Thread, ThreadId};
+use crate::host::thread_id_allocator::ThreadIdAllocator;
+use crate::host::tmpfs::Tmpfs;
+use crate::network::fingerprint::{self, PacketDirection, PacketFingerprint};
+use crate::network::packet::{PacketRc, PacketStatus};
+use crate::network::relay::throttle::{Throttle, ThrottleRule};
 use crate::network::relay::{RateLimit, Relay};
 use crate::network::router::Router;
 use crate::network::PacketDevice;
 use crate::utility;
+use crate::utility::compression::PipeCompressor;
+use crate::utility::deferred_queue::DeferredQueue;
 #[cfg(feature = "perf_timers")]
 use crate::utility::perf_timer::PerfTimer;
 
+/// The random stream that the resolver's latencies are drawn from.
+const RESOLVER_RANDOM_STREAM: &str = "resolver";
+
 pub struct HostParameters {
     pub id: HostId,
     pub node_seed: u64,
@@ -63,11 +100,22 @@ pub struct HostParameters {
     pub cpu_threshold: Option<SimulationTime>,
     pub cpu_precision: Option<SimulationTime>,
     pub heartbeat_interval: Option<SimulationTime>,
+    /// How often to sample the host's socket buffers and interface queues, if at all.
+    pub heartbeat_sample_interval: Option<SimulationTime>,
+    /// How many of the flows and peers with the most traffic to log at each heartbeat.
+    pub heartbeat_top_talkers: u32,
+    /// The loopback UDP port that managed processes send their metrics to, if any.
+    pub app_metrics_port: Option<u16>,
     pub heartbeat_log_level: LogLevel,
     pub heartbeat_log_info: cshadow::LogInfoFlags,
+    /// The host's tags, which are the groups of the host's aggregate heartbeat statistics.
+    pub tags: Vec<String>,
+    /// The host's metadata from the config, which is logged with its heartbeat messages.
+    pub metadata: BTreeMap<String, String>,
     pub log_level: LogLevel,
     pub pcap_config: Option<PcapConfig>,
     pub qdisc: QDiscMode,
+    pub router_qdisc: RouterQDiscMode,
     pub init_sock_recv_buf_size: u64,
     pub autotune_recv_buf: bool,
     pub init_sock_send_buf_size: u64,
@@ -77,9 +125,52 @@ pub struct HostParameters {
     pub max_unapplied_cpu_latency: SimulationTime,
     pub unblocked_syscall_latency: SimulationTime,
     pub unblocked_vdso_latency: SimulationTime,
+    /// Initial timer slack of each process.
+    pub timer_slack: SimulationTime,
+    /// Latencies of individual syscalls, indexed by syscall number.
+    pub syscall_latencies: HashMap<libc::c_long, SimulationTime>,
     pub strace_logging_options: Option<FmtOptions>,
     pub shim_log_level: LogLevel,
     pub use_new_tcp: bool,
+    pub syslog_sink: bool,
+    /// The size limit of the host's `/dev/shm` tmpfs, if it's emulated.
+    pub shm_size: Option<u64>,
+    pub seed_node: Option<SeedNode>,
+    pub probes: Vec<Probe>,
+    /// The ports that the host answers other hosts' probes on.
+    pub probe_ports: Vec<ProbePort>,
+    pub uname: Uname,
+    pub persistent_state: Option<PersistentState>,
+    pub resolver: Option<Resolver>,
+    pub tcp_delayed_ack: TcpDelayedAck,
+    pub ip_local_port_range: RangeInclusive<u16>,
+    pub tcp_time_wait: TcpTimeWait,
+    /// The time that the host's ARP cache entries are valid, if the host models ARP.
+    pub arp_cache_timeout: Option<SimulationTime>,
+    pub load_balancer: Option<LoadBalancerConfig>,
+    /// The addresses of the load balancers in DSR mode that the host is a backend of.
+    pub load_balancer_vips: Vec<SocketAddrV4>,
+    /// The host's firewall rules, which are shared with the control socket.
+    pub firewall: Option<Arc<Mutex<Firewall>>>,
+    /// Rules that limit the bandwidth of the host's matching packets.
+    pub throttles: Vec<ThrottleRule>,
+    /// The TUN device that the host exchanges its packets with, if any.
+    pub bridge: Option<BridgeConfig>,
+    /// Applications that run in Shadow's process instead of as managed processes.
+    pub native_apps: Vec<NativeAppConfig>,
+    pub tcp_abort_on_overflow: bool,
+    pub tcp_max_syn_backlog: u32,
+    pub tcp_syncookies: bool,
+    pub tcp_timestamps: bool,
+    pub tcp_window_scaling: bool,
+    /// The maximum length of the host's event queue, if any.
+    pub max_event_queue_len: Option<u64>,
+    pub event_queue_limit_action: EventQueueLimitAction,
+    pub packet_payload: PacketPayload,
+    /// How the host's pcap files and its processes' stdout and stderr files are compressed.
+    pub output_compression: OutputCompression,
+    /// Whether to record a fingerprint of the packets that the host sends and receives.
+    pub packet_fingerprints: bool,
 }
 
 use super::cpu::Cpu;
@@ -95,6 +186,221 @@ pub struct HostInfo {
     pub log_level: Option<log::LevelFilter>,
 }
 
+/// An error that prevented a host from being set up. These are usually caused by the host's
+/// configuration or by the environment that Shadow is running in, so they describe the offending
+/// path or option and how the user might fix it.
+#[derive(Debug)]
+pub enum HostSetupError {
+    /// The host's data directory could not be created.
+    CreateDataDir {
+        host: String,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The pcap capture size is larger than the pcap file format supports.
+    PcapCaptureSize { host: String, capture_size: u64 },
+    /// A `uname` field contains a nul byte, so it can't be returned to the application.
+    InvalidUname { host: String, field: &'static str },
+    /// The syslog sink could not be started.
+    SyslogSink {
+        host: String,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The seed node service could not be started.
+    SeedNode {
+        host: String,
+        port: u16,
+        source: std::io::Error,
+    },
+    /// The host's persistent state could not be written to its data directory.
+    PersistentState {
+        host: String,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The responder for other hosts' probes could not be started.
+    ProbeResponder {
+        host: String,
+        port: ProbePort,
+        source: std::io::Error,
+    },
+    /// One of the host's probes could not be started.
+    Prober {
+        host: String,
+        index: usize,
+        source: std::io::Error,
+    },
+    /// The TUN device for a bridged host could not be opened.
+    Bridge {
+        host: String,
+        tun: String,
+        source: std::io::Error,
+    },
+    /// One of a process's socket activation sockets could not be bound or could not listen.
+    ListenSocket {
+        host: String,
+        process: usize,
+        index: usize,
+        port: u16,
+        source: Errno,
+    },
+}
+
+impl HostSetupError {
+    /// The name of the host that couldn't be set up.
+    pub fn host_name(&self) -> &str {
+        match self {
+            Self::CreateDataDir { host, .. } => host,
+            Self::PcapCaptureSize { host, .. } => host,
+            Self::InvalidUname { host, .. } => host,
+            Self::SyslogSink { host, .. } => host,
+            Self::SeedNode { host, .. } => host,
+            Self::PersistentState { host, .. } => host,
+            Self::ProbeResponder { host, .. } => host,
+            Self::Prober { host, .. } => host,
+            Self::Bridge { host, .. } => host,
+            Self::ListenSocket { host, .. } => host,
+        }
+    }
+
+    /// The path or configuration option that caused the error.
+    pub fn parameter(&self) -> String {
+        match self {
+            Self::CreateDataDir { path, .. } => path.display().to_string(),
+            Self::PcapCaptureSize { host, .. } => {
+                format!("hosts.{host}.host_options.pcap_capture_size")
+            }
+            Self::InvalidUname { host, field, .. } => format!("hosts.{host}.uname.{field}"),
+            Self::SyslogSink { path, .. } => path.display().to_string(),
+            Self::SeedNode { host, .. } => format!("hosts.{host}.seed_node.port"),
+            Self::PersistentState { path, .. } => path.display().to_string(),
+            Self::ProbeResponder { port, .. } => {
+                format!("hosts.{}.probes[{}].port", port.prober, port.index)
+            }
+            Self::Prober { host, index, .. } => format!("hosts.{host}.probes[{index}]"),
+            Self::Bridge { host, .. } => format!("hosts.{host}.bridge.tun"),
+            Self::ListenSocket {
+                host,
+                process,
+                index,
+                ..
+            } => format!("hosts.{host}.processes[{process}].listen_sockets[{index}].port"),
+        }
+    }
+
+    /// A suggestion for how the user might fix the error.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::CreateDataDir { .. } => {
+                "Check that the data directory (general.data_directory) is writable and that the \
+                filesystem has free space."
+            }
+            Self::PcapCaptureSize { .. } => {
+                "Use a capture size smaller than 4 GiB; 65535 bytes is enough to capture full \
+                packets."
+            }
+            Self::InvalidUname { .. } => "Remove the nul byte from the uname value.",
+            Self::SyslogSink { .. } | Self::PersistentState { .. } => {
+                "Check that the data directory (general.data_directory) is writable and that the \
+                filesystem has free space."
+            }
+            Self::SeedNode { .. } => {
+                "Use a seed node port that isn't also probed by other hosts or used by another of \
+                the host's services."
+            }
+            Self::ProbeResponder { .. } => {
+                "Probe a port that isn't used by the target host's seed node or other services."
+            }
+            Self::Prober { .. } => "Check that the host has a free ephemeral port for the probe.",
+            Self::Bridge { .. } => {
+                "Check that the TUN device exists and that Shadow has permission to open it, for \
+                example by creating it with 'ip tuntap add dev <tun> mode tun user $USER'."
+            }
+            Self::ListenSocket { .. } => {
+                "Use a listen socket port that isn't bound by another socket on the host when the \
+                process starts."
+            }
+        }
+    }
+
+    /// A multi-line report of the error for the user, including the offending path or option, the
+    /// underlying cause (if any), and a hint for fixing it.
+    pub fn report(&self) -> String {
+        let mut report = format!("{self}\n  at: {}", self.parameter());
+        if let Some(source) = std::error::Error::source(self) {
+            report.push_str(&format!("\n  caused by: {source}"));
+        }
+        report.push_str(&format!("\n  hint: {}", self.hint()));
+        report
+    }
+}
+
+impl std::error::Error for HostSetupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CreateDataDir { source, .. } => Some(source),
+            Self::PcapCaptureSize { .. } => None,
+            Self::InvalidUname { .. } => None,
+            Self::SyslogSink { source, .. } => Some(source),
+            Self::SeedNode { source, .. } => Some(source),
+            Self::PersistentState { source, .. } => Some(source),
+            Self::ProbeResponder { source, .. } => Some(source),
+            Self::Prober { source, .. } => Some(source),
+            Self::Bridge { source, .. } => Some(source),
+            Self::ListenSocket { source, .. } => Some(source),
+        }
+    }
+}
+
+impl std::fmt::Display for HostSetupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let host = self.host_name();
+        match self {
+            Self::CreateDataDir { path, .. } => write!(
+                f,
+                "Could not create the data directory '{}' for host '{host}'",
+                path.display()
+            ),
+            Self::PcapCaptureSize { capture_size, .. } => write!(
+                f,
+                "The pcap capture size of {capture_size} bytes for host '{host}' is too large"
+            ),
+            Self::InvalidUname { field, .. } => {
+                write!(f, "The uname {field} of host '{host}' contains a nul byte")
+            }
+            Self::SyslogSink { .. } => {
+                write!(f, "Could not start the syslog sink for host '{host}'")
+            }
+            Self::SeedNode { port, .. } => write!(
+                f,
+                "Could not start the seed node service on port {port} for host '{host}'"
+            ),
+            Self::PersistentState { path, .. } => write!(
+                f,
+                "Could not write the persistent state to '{}' for host '{host}'",
+                path.display()
+            ),
+            Self::ProbeResponder { port, .. } => write!(
+                f,
+                "Could not start the probe responder on port {} for host '{host}'",
+                port.port
+            ),
+            Self::Prober { index, .. } => {
+                write!(f, "Could not start probe {index} of host '{host}'")
+            }
+            Self::Bridge { tun, .. } => {
+                write!(f, "Could not open the TUN device '{tun}' for host '{host}'")
+            }
+            Self::ListenSocket { port, process, .. } => write!(
+                f,
+                "Could not open the listen socket on port {port} for process {process} of host \
+                '{host}'"
+            ),
+        }
+    }
+}
+
 /// A simulated Host.
 pub struct Host {
     // Store immutable info in an Arc, that we can safely clone into the
@@ -114,6 +420,17 @@ pub struct Host {
     event_queue: Arc<Mutex<EventQueue>>,
 
     random: RefCell<Xoshiro256PlusPlus>,
+    /// Random number generators that are independent of `random`, indexed by name.
+    random_streams: RefCell<HashMap<&'static str, Xoshiro256PlusPlus>>,
+
+    /// Name lookups that haven't returned yet, indexed by the thread that started them.
+    pending_lookups: RefCell<HashMap<ThreadId, Lookup>>,
+
+    /// Neighbors whose hardware addresses have been resolved, if the host models ARP.
+    arp_cache: RefCell<Option<ArpCache>>,
+
+    /// The connections forwarded by the host's load balancer, if enabled.
+    load_balancer: RefCell<Option<LoadBalancer>>,
 
     // The upstream router that will queue packets until we can receive them.
     // This only applies to the internet interface; the localhost interface
@@ -142,6 +459,28 @@ pub struct Host {
 
     net_ns: NetworkNamespace,
 
+    // receives messages sent to the host's `/dev/log` socket, if enabled
+    syslog_sink: RefCell<Option<SyslogSink>>,
+
+    // backs the host's `/dev/shm` directory, if enabled
+    tmpfs: RefCell<Option<Tmpfs>>,
+
+    // answers peer discovery requests, if enabled
+    seed_node: RefCell<Option<SeedNodeService>>,
+
+    // answers other hosts' probes and sends the host's own probes, if any
+    probe_responders: RefCell<Vec<ProbeResponder>>,
+    probers: RefCell<Vec<Prober>>,
+
+    // applications that run in shadow's process, once they've started
+    native_apps: RefCell<Vec<NativeApp>>,
+
+    // a fingerprint of the packets that the host sent and received, if enabled
+    packet_fingerprint: RefCell<Option<PacketFingerprint>>,
+
+    // pseudoterminals, as found in the host's `/dev/pts`
+    pty_table: RefCell<PtyTable>,
+
     // Store as a CString so that we can return a borrowed pointer to C code
     // instead of having to allocate a new string.
     //
@@ -150,8 +489,12 @@ pub struct Host {
     data_dir_path: PathBuf,
     data_dir_path_cstring: CString,
 
-    // virtual process and event id counter
-    thread_id_counter: Cell<libc::pid_t>,
+    // contents of the emulated `/proc/sys/kernel/*` files, stored as CStrings so that we can return
+    // borrowed pointers to C code
+    proc_sys_kernel_files: Vec<(&'static str, CString)>,
+
+    // virtual thread/process ids and event id counter
+    thread_id_allocator: RefCell<ThreadIdAllocator>,
     event_id_counter: Cell<u64>,
     packet_id_counter: Cell<u64>,
 
@@ -192,6 +535,21 @@ pub struct Host {
 
     in_notify_socket_has_packets: RootedCell<bool>,
 
+    // Whether the event queue has grown longer than `params.max_event_queue_len` and hasn't yet
+    // drained to half of that length. Threads aren't resumed while this is set if the limit action
+    // is backpressure.
+    event_queue_over_limit: RootedCell<bool>,
+
+    // Threads that would have been resumed while the event queue was over its limit. These are
+    // kept out of the event queue so that the queue can drain while they're paused.
+    paused_threads: RefCell<Vec<(ProcessId, ThreadId)>>,
+
+    // Work to run after the current event's handler returns. See `Host::defer`.
+    deferred: DeferredQueue<Host>,
+
+    // Threads compressing the stdout and stderr files of the host's processes.
+    output_compressors: RefCell<Vec<PipeCompressor>>,
+
     /// Paths to be added to LD_PRELOAD of managed processes.
     preload_paths: Arc<Vec<PathBuf>>,
 }
@@ -219,19 +577,59 @@ impl Host {
         dns: *mut cshadow::DNS,
         manager_shmem: &ShMemBlock<ManagerShmem>,
         preload_paths: Arc<Vec<PathBuf>>,
-    ) -> Self {
+    ) -> Result<Self, HostSetupError> {
+        // check everything that can fail before allocating any resources
+        let host_name = params.hostname.to_string_lossy().into_owned();
+        let data_dir_path = Self::make_data_dir_path(&params.hostname, host_root_path);
+        std::fs::create_dir_all(&data_dir_path).map_err(|source| {
+            HostSetupError::CreateDataDir {
+                host: host_name.clone(),
+                path: data_dir_path.clone(),
+                source,
+            }
+        })?;
+        let tmpfs = match params.shm_size {
+            Some(size_limit) => {
+                let path = data_dir_path.join("shm");
+                std::fs::create_dir_all(&path).map_err(|source| HostSetupError::CreateDataDir {
+                    host: host_name.clone(),
+                    path: path.clone(),
+                    source,
+                })?;
+                Some(Tmpfs::new(path, size_limit))
+            }
+            None => None,
+        };
+        let proc_sys_kernel_files = Self::make_proc_sys_kernel_files(&params)?;
+        let pcap_options = match &params.pcap_config {
+            Some(x) => Some(PcapOptions {
+                path: data_dir_path.clone(),
+                capture_size_bytes: x.capture_size.try_into().map_err(|_| {
+                    HostSetupError::PcapCaptureSize {
+                        host: host_name.clone(),
+                        capture_size: x.capture_size,
+                    }
+                })?,
+                payload: x.payload,
+                compression: params.output_compression,
+                error_action: x.error_action,
+            }),
+            None => None,
+        };
+
         #[cfg(feature = "perf_timers")]
         let execution_timer = RefCell::new(PerfTimer::new());
 
         let root = Root::new();
         let random = RefCell::new(Xoshiro256PlusPlus::seed_from_u64(params.node_seed));
+        let arp_cache = params.arp_cache_timeout.map(ArpCache::new);
+        let load_balancer = params.load_balancer.as_ref().map(LoadBalancer::new);
         let cpu = RefCell::new(Cpu::new(
             params.cpu_frequency,
             raw_cpu_freq_khz,
             params.cpu_threshold,
             params.cpu_precision,
         ));
-        let data_dir_path = Self::make_data_dir_path(&params.hostname, host_root_path);
         let data_dir_path_cstring = utility::pathbuf_to_nul_term_cstring(data_dir_path.clone());
 
         let host_shmem = HostShmem::new(
@@ -243,21 +641,22 @@ impl Host {
             nix::unistd::getpid().as_raw(),
             params.native_tsc_frequency,
             params.shim_log_level,
+            params
+                .resolver
+                .as_ref()
+                .is_some_and(|resolver| resolver.prefers_ipv6()),
             manager_shmem,
         );
         let shim_shmem = UnsafeCell::new(shadow_shmem::allocator::shmalloc(host_shmem));
 
-        // Process IDs start at 1000
-        let thread_id_counter = Cell::new(1000);
+        let thread_id_allocator = RefCell::new(ThreadIdAllocator::new());
         let event_id_counter = Cell::new(0);
         let packet_id_counter = Cell::new(0);
         let determinism_sequence_counter = Cell::new(0);
-        // Packet priorities start at 1. "0" is used for control packets.
-        let packet_priority_counter = Cell::new(1);
+        // "0" is reserved for control packets.
+        let packet_priority_counter = Cell::new(FIFO_FIRST_DATA_PRIORITY);
         let tsc = Tsc::new(params.native_tsc_frequency);
 
-        std::fs::create_dir_all(&data_dir_path).unwrap();
-
         // Register using the param hints.
         // We already checked that the addresses are available, so fail if they are not.
 
@@ -270,11 +669,6 @@ impl Host {
             .map(|x| (*x).try_into().unwrap())
             .collect();
 
-        let pcap_options = params.pcap_config.as_ref().map(|x| PcapOptions {
-            path: data_dir_path.clone(),
-            capture_size_bytes: x.capture_size.try_into().unwrap(),
-        });
-
         let net_ns = unsafe {
             NetworkNamespace::new(
                 params.id,
@@ -282,6 +676,8 @@ impl Host {
                 public_ip,
                 pcap_options,
                 params.qdisc,
+                params.ip_local_port_range.clone(),
+                params.tcp_time_wait.reuse,
                 dns,
             )
         };
@@ -289,21 +685,35 @@ impl Host {
         // Packets that are not for localhost or our public ip go to the router.
         // Use `Ipv4Addr::UNSPECIFIED` for the router to encode this for our
         // routing table logic inside of `Host::get_packet_device()`.
-        let router = Router::new(Ipv4Addr::UNSPECIFIED);
+        let router = Router::new(Ipv4Addr::UNSPECIFIED, params.router_qdisc);
+        let packet_fingerprint = params.packet_fingerprints.then(PacketFingerprint::new);
+        let throttles = |direction: ThrottleDirection| {
+            params
+                .throttles
+                .iter()
+                .filter(|x| x.applies_to(direction))
+                .cloned()
+                .map(Throttle::new)
+                .collect()
+        };
         let relay_inet_out = Relay::new(
             RateLimit::BytesPerSecond(params.requested_bw_up_bits / 8),
             net_ns.internet.borrow().get_address(),
+            throttles(ThrottleDirection::Egress),
         );
         let relay_inet_in = Relay::new(
             RateLimit::BytesPerSecond(params.requested_bw_down_bits / 8),
             router.get_address(),
+            throttles(ThrottleDirection::Ingress),
         );
         let relay_loopback = Relay::new(
             RateLimit::Unlimited,
             net_ns.localhost.borrow().get_address(),
+            Vec::new(),
         );
 
         let in_notify_socket_has_packets = RootedCell::new(&root, false);
+        let event_queue_over_limit = RootedCell::new(&root, false);
 
         let res = Self {
             info: OnceCell::new(),
@@ -317,13 +727,26 @@ impl Host {
             tracker: RefCell::new(None),
             futex_table: RefCell::new(unsafe { SyncSendPointer::new(cshadow::futextable_new()) }),
             random,
+            random_streams: RefCell::new(HashMap::new()),
+            pending_lookups: RefCell::new(HashMap::new()),
+            arp_cache: RefCell::new(arp_cache),
+            load_balancer: RefCell::new(load_balancer),
             shim_shmem,
             shim_shmem_lock: RefCell::new(None),
             cpu,
             net_ns,
+            syslog_sink: RefCell::new(None),
+            tmpfs: RefCell::new(tmpfs),
+            seed_node: RefCell::new(None),
+            probe_responders: RefCell::new(Vec::new()),
+            probers: RefCell::new(Vec::new()),
+            native_apps: RefCell::new(Vec::new()),
+            packet_fingerprint: RefCell::new(packet_fingerprint),
+            pty_table: RefCell::new(PtyTable::new()),
             data_dir_path,
             data_dir_path_cstring,
-            thread_id_counter,
+            proc_sys_kernel_files,
+            thread_id_allocator,
             event_id_counter,
             packet_id_counter,
             packet_priority_counter,
@@ -333,6 +756,10 @@ impl Host {
             #[cfg(feature = "perf_timers")]
             execution_timer,
             in_notify_socket_has_packets,
+            event_queue_over_limit,
+            paused_threads: RefCell::new(Vec::new()),
+            deferred: DeferredQueue::new(),
+            output_compressors: RefCell::new(Vec::new()),
             preload_paths,
         };
 
@@ -363,13 +790,61 @@ impl Host {
             cpu_precision = res.params.cpu_precision,
         );
 
-        res
+        Ok(res)
     }
 
     pub fn root(&self) -> &Root {
         &self.root
     }
 
+    /// The `/proc/sys/kernel/*` files that describe the host's system identification, which must
+    /// agree with the values returned by `uname`.
+    fn make_proc_sys_kernel_files(
+        params: &HostParameters,
+    ) -> Result<Vec<(&'static str, CString)>, HostSetupError> {
+        let uname = &params.uname;
+        let hostname = params.hostname.to_string_lossy();
+        [
+            ("/proc/sys/kernel/hostname", "nodename", &*hostname),
+            (
+                "/proc/sys/kernel/domainname",
+                "domainname",
+                uname.domainname.as_str(),
+            ),
+            ("/proc/sys/kernel/ostype", "sysname", uname.sysname.as_str()),
+            (
+                "/proc/sys/kernel/osrelease",
+                "release",
+                uname.release.as_str(),
+            ),
+            (
+                "/proc/sys/kernel/version",
+                "version",
+                uname.version.as_str(),
+            ),
+        ]
+        .into_iter()
+        .map(|(path, field, value)| {
+            let value =
+                CString::new(format!("{value}\n")).map_err(|_| HostSetupError::InvalidUname {
+                    host: hostname.clone().into_owned(),
+                    field,
+                })?;
+            Ok((path, value))
+        })
+        .collect()
+    }
+
+    /// The contents of the emulated `/proc/net/*` file at `path`, or `None` if the file isn't
+    /// emulated. Unlike the `/proc/sys/kernel/*` files, these describe the host's current state so
+    /// are generated each time they're opened.
+    fn proc_net_file(&self, path: &[u8]) -> Option<String> {
+        let name = path
+            .strip_prefix(b"/proc/net/")
+            .or_else(|| path.strip_prefix(b"/proc/self/net/"))?;
+        proc_net::file_contents(&self.net_ns, name)
+    }
+
     fn make_data_dir_path(hostname: &CStr, host_root_path: &Path) -> PathBuf {
         let hostname: OsString = { OsString::from_vec(hostname.to_bytes().to_vec()) };
 
@@ -390,10 +865,16 @@ impl Host {
         shutdown_signal: nix::sys::signal::Signal,
         plugin_name: CString,
         plugin_path: CString,
+        interpreter: Option<CString>,
         argv: Vec<CString>,
         envv: Vec<CString>,
         pause_for_debugging: bool,
         expected_final_state: ProcessFinalState,
+        quota: Option<QuotaLimits>,
+        credentials: Credentials,
+        root: Option<CString>,
+        listen_sockets: Vec<ListenSocket>,
+        id_slot: usize,
     ) {
         debug_assert!(shutdown_time.is_none() || shutdown_time.unwrap() > start_time);
 
@@ -405,18 +886,39 @@ impl Host {
             let envv = envv.clone();
             let argv = argv.clone();
             let plugin_name = plugin_name.clone();
+            let quota = quota.clone();
+            let credentials = credentials.clone();
+            let root = root.clone();
 
             let process = Process::spawn(
                 host,
-                plugin_name,
+                plugin_name.clone(),
                 &plugin_path,
+                interpreter.as_deref(),
                 argv,
                 envv,
                 pause_for_debugging,
                 host.params.strace_logging_options,
                 expected_final_state,
-            )
-            .expect("Failed to initialize application {plugin_name:?}");
+                quota,
+                credentials,
+                root,
+                &listen_sockets,
+                id_slot,
+            );
+            let process = match process {
+                Ok(process) => process,
+                Err(e) => {
+                    // like a process that exits in an unexpected state, this fails the simulation
+                    // but doesn't stop it
+                    error!(
+                        "Failed to start process {plugin_name:?} on host '{}': {e}",
+                        host.name()
+                    );
+                    Worker::increment_plugin_error_count();
+                    return;
+                }
+            };
             let (process_id, thread_id) = {
                 let process = process.borrow(host.root());
                 (process.id(), process.thread_group_leader_id())
@@ -466,6 +968,15 @@ impl Host {
     }
 
     pub fn resume(&self, pid: ProcessId, tid: ThreadId) {
+        if self.event_queue_over_limit.get(&self.root)
+            && self.params.event_queue_limit_action == EventQueueLimitAction::Backpressure
+        {
+            // Don't let the thread schedule any more events until the queue has drained.
+            trace!("Event queue is over its limit; pausing {pid:?} {tid:?}");
+            self.paused_threads.borrow_mut().push((pid, tid));
+            return;
+        }
+
         let Some(processrc) = self
             .process_borrow(pid)
             .map(|p| RootedRc::clone(&p, &self.root))
@@ -495,7 +1006,9 @@ impl Host {
             return;
         }
 
-        // Reparent children, and collect IDs of children that are dead.
+        // Reparent children, and collect IDs of children that are dead, and of
+        // children that asked to be signalled when their parent died.
+        let mut parent_death_signals: Vec<(ProcessId, Signal)> = Vec::new();
         let mut orphaned_zombie_pids: Vec<ProcessId> = self
             .processes
             .borrow()
@@ -507,6 +1020,9 @@ impl Host {
                     return None;
                 }
                 process.set_parent_id(ProcessId::INIT);
+                if let Some(signal) = process.parent_death_signal() {
+                    parent_death_signals.push((*other_pid, signal));
+                }
                 let Some(z) = process.borrow_as_zombie() else {
                     // Not a zombie
                     return None;
@@ -521,6 +1037,19 @@ impl Host {
             })
             .collect();
 
+        // Send the requested parent-death signals. Linux actually sends the
+        // signal when the thread that created the child exits, but we only
+        // track the parent process, and so send it when the whole parent
+        // process exits.
+        for (child_pid, signal) in parent_death_signals {
+            let Some(child) = self.process_borrow(child_pid) else {
+                continue;
+            };
+            let child = child.borrow(&self.root);
+            let siginfo_t = siginfo_t::new_for_kill(signal, pid.into(), 0);
+            child.signal(self, None, &siginfo_t);
+        }
+
         // Process we ran is a zombie; is it also an orphan?
         debug_assert!(died);
         if is_orphan {
@@ -596,26 +1125,73 @@ impl Host {
         &self.info().name
     }
 
+    /// The host's system identification, as returned by `uname`.
+    pub fn uname(&self) -> libc::utsname {
+        fn copy_field(dst: &mut [libc::c_char], src: &[u8]) {
+            // leave room for the nul terminator
+            let len = std::cmp::min(src.len(), dst.len() - 1);
+            for (d, s) in dst.iter_mut().zip(&src[..len]) {
+                *d = *s as libc::c_char;
+            }
+        }
+
+        let uname = &self.params.uname;
+        let mut buf = shadow_pod::zeroed::<libc::utsname>();
+        copy_field(&mut buf.sysname, uname.sysname.as_bytes());
+        copy_field(&mut buf.nodename, self.params.hostname.as_bytes());
+        copy_field(&mut buf.release, uname.release.as_bytes());
+        copy_field(&mut buf.version, uname.version.as_bytes());
+        copy_field(&mut buf.machine, uname.machine.as_bytes());
+        copy_field(&mut buf.domainname, uname.domainname.as_bytes());
+        buf
+    }
+
     pub fn default_ip(&self) -> Ipv4Addr {
         let addr = self.net_ns.default_address.ptr();
         let addr = unsafe { cshadow::address_toNetworkIP(addr) };
         u32::from_be(addr).into()
     }
 
+    /// The host's IPv6 address, which corresponds to its IPv4 address [`Self::default_ip`].
+    pub fn default_ipv6(&self) -> Ipv6Addr {
+        self.net_ns.default_ipv6()
+    }
+
     pub fn abstract_unix_namespace(
         &self,
     ) -> impl Deref<Target = Arc<AtomicRefCell<AbstractUnixNamespace>>> + '_ {
         &self.net_ns.unix
     }
 
+    #[track_caller]
+    pub fn pty_table_borrow(&self) -> impl Deref<Target = PtyTable> + '_ {
+        self.pty_table.borrow()
+    }
+
+    #[track_caller]
+    pub fn pty_table_borrow_mut(&self) -> impl Deref<Target = PtyTable> + DerefMut + '_ {
+        self.pty_table.borrow_mut()
+    }
+
+    /// The tmpfs that backs the host's `/dev/shm` directory, if it's emulated.
+    #[track_caller]
+    pub fn tmpfs_borrow(&self) -> impl Deref<Target = Option<Tmpfs>> + '_ {
+        self.tmpfs.borrow()
+    }
+
+    #[track_caller]
+    pub fn tmpfs_borrow_mut(&self) -> impl Deref<Target = Option<Tmpfs>> + DerefMut + '_ {
+        self.tmpfs.borrow_mut()
+    }
+
     pub fn log_level(&self) -> Option<log::LevelFilter> {
         let level = self.params.log_level;
         log_c2rust::c_to_rust_log_level(level).map(|l| l.to_level_filter())
     }
 
     #[track_caller]
-    pub fn upstream_router_borrow_mut(&self) -> impl Deref<Target = Router> + DerefMut + '_ {
-        self.router.borrow_mut()
+    pub fn upstream_router_borrow(&self) -> impl Deref<Target = Router> + '_ {
+        self.router.borrow()
     }
 
     #[track_caller]
@@ -637,6 +1213,47 @@ impl Host {
         }
     }
 
+    /// The loopback UDP port that managed processes can send metrics to (see [`app_metrics`]).
+    pub fn app_metrics_port(&self) -> Option<u16> {
+        self.params.app_metrics_port
+    }
+
+    /// Log the metrics in a datagram that the process `pid` sent to the metrics port. Invalid
+    /// metrics are skipped. The metrics are logged with the heartbeat messages, so they're dropped
+    /// if the host doesn't log heartbeats.
+    pub fn record_app_metrics(&self, pid: ProcessId, datagram: &[u8]) {
+        for metric in app_metrics::parse(datagram) {
+            let metric = match metric {
+                Ok(x) => x,
+                Err(e) => {
+                    debug!("Ignoring an invalid metric from process {pid}: {e}");
+                    continue;
+                }
+            };
+
+            let Some(mut tracker) = self.tracker_borrow_mut() else {
+                trace!(
+                    "Dropping metric '{}' since heartbeats are disabled",
+                    metric.name
+                );
+                continue;
+            };
+
+            let name = CString::new(metric.name).unwrap();
+            let kind = CString::new(metric.kind.as_str()).unwrap();
+            let value = CString::new(metric.value.to_string()).unwrap();
+            unsafe {
+                cshadow::tracker_logMetric(
+                    &mut *tracker,
+                    pid.into(),
+                    name.as_ptr(),
+                    kind.as_ptr(),
+                    value.as_ptr(),
+                )
+            };
+        }
+    }
+
     #[track_caller]
     pub fn futextable_borrow_mut(
         &self,
@@ -660,7 +1277,7 @@ impl Host {
     /// Panics if we have shut down.
     pub fn interface_borrow_mut(
         &self,
-        addr: Ipv4Addr,
+        addr: impl Into<IpAddr>,
     ) -> Option<impl Deref<Target = NetworkInterface> + DerefMut + '_> {
         self.net_ns.interface_borrow_mut(addr)
     }
@@ -670,7 +1287,7 @@ impl Host {
     /// Panics if we have shut down.
     pub fn interface_borrow(
         &self,
-        addr: Ipv4Addr,
+        addr: impl Into<IpAddr>,
     ) -> Option<impl Deref<Target = NetworkInterface> + '_> {
         self.net_ns.interface_borrow(addr)
     }
@@ -680,16 +1297,135 @@ impl Host {
         self.random.borrow_mut()
     }
 
+    /// The random number generator for the stream `name`. Drawing from a stream doesn't change the
+    /// numbers drawn from the host's main generator or any other stream.
+    #[track_caller]
+    pub fn random_stream_mut(
+        &self,
+        name: &'static str,
+    ) -> impl Deref<Target = Xoshiro256PlusPlus> + DerefMut + '_ {
+        RefMut::map(self.random_streams.borrow_mut(), |streams| {
+            streams
+                .entry(name)
+                .or_insert_with(|| utility::rng_stream::new(self.params.node_seed, name))
+        })
+    }
+
+    /// Apply the host's resolver options to a lookup of the `family` addresses of `name` by thread
+    /// `tid`. A thread that looks up the same name and family again while its earlier lookup is
+    /// pending (for example after being interrupted by a signal) continues the earlier lookup.
+    pub fn resolver_lookup(
+        &self,
+        tid: ThreadId,
+        name: &str,
+        family: ResolverFamily,
+    ) -> LookupStatus {
+        let Some(resolver) = &self.params.resolver else {
+            return LookupStatus::Resolve;
+        };
+
+        let now = Worker::current_time().unwrap();
+        let mut pending_lookups = self.pending_lookups.borrow_mut();
+
+        if !pending_lookups
+            .get(&tid)
+            .is_some_and(|x| x.name() == name && x.family() == family)
+        {
+            let mut random = self.random_stream_mut(RESOLVER_RANDOM_STREAM);
+            let lookup = resolver.start_lookup(name, family, now, &mut *random);
+            pending_lookups.insert(tid, lookup);
+        }
+
+        let status = pending_lookups[&tid].status(now);
+        if !matches!(status, LookupStatus::Pending(_)) {
+            pending_lookups.remove(&tid);
+        }
+        status
+    }
+
+    /// The time that a packet sent to `next_hop` waits for the host to resolve the next hop's
+    /// hardware address, where an ARP request and its reply take `round_trip`. Packets are sent
+    /// immediately if the host doesn't model ARP.
+    pub fn arp_delay(&self, next_hop: ArpNeighbor, round_trip: SimulationTime) -> SimulationTime {
+        let mut arp_cache = self.arp_cache.borrow_mut();
+        let Some(arp_cache) = arp_cache.as_mut() else {
+            return SimulationTime::ZERO;
+        };
+
+        arp_cache.resolve(next_hop, Worker::current_time().unwrap(), round_trip)
+    }
+
+    /// Forward `packet` if it was sent to the host's load balancer, or to a connection that the
+    /// load balancer translated. Returns the packet if it should be received by the host as usual.
+    fn load_balance(&self, mut packet: PacketRc) -> Option<PacketRc> {
+        if self.params.load_balancer.is_none() {
+            return Some(packet);
+        }
+
+        let (protocol, tcp_flags) = match packet.protocol() {
+            cshadow::_ProtocolType_PTCP => (Protocol::Tcp, packet.get_tcp().map(|x| x.flags)),
+            cshadow::_ProtocolType_PUDP => (Protocol::Udp, None),
+            _ => return Some(packet),
+        };
+
+        let action = match self.load_balancer.borrow_mut().as_mut() {
+            Some(load_balancer) => load_balancer.handle_packet(
+                self.default_ip(),
+                protocol,
+                packet.src_address(),
+                packet.dst_address(),
+                tcp_flags,
+            ),
+            None => Action::Receive,
+        };
+
+        match action {
+            Action::Receive => Some(packet),
+            Action::Forward { src, dst } => {
+                packet.set_addresses(src, dst);
+                packet.add_status(PacketStatus::RelayForwarded);
+                let cpacket = packet.into_inner();
+                unsafe { Worker::send_packet(self, cpacket) };
+                unsafe { cshadow::packet_unref(cpacket) };
+                None
+            }
+            Action::Drop => {
+                packet.add_status(PacketStatus::RcvInterfaceDropped);
+                None
+            }
+        }
+    }
+
+    /// The host's firewall rules, if the host has a firewall.
+    pub fn firewall(&self) -> Option<&Arc<Mutex<Firewall>>> {
+        self.params.firewall.as_ref()
+    }
+
+    /// The address that the host's packets from `src` are sent from. This is the address of a
+    /// load balancer in DSR mode that the host is a backend of, if `src` is the host's address and
+    /// the load balancer's port.
+    pub fn load_balancer_vip(&self, src: SocketAddrV4) -> Option<SocketAddrV4> {
+        if *src.ip() != self.default_ip() {
+            return None;
+        }
+        self.params
+            .load_balancer_vips
+            .iter()
+            .find(|x| x.port() == src.port())
+            .copied()
+    }
+
     pub fn get_new_event_id(&self) -> u64 {
         let res = self.event_id_counter.get();
         self.event_id_counter.set(res + 1);
         res
     }
 
-    pub fn get_new_thread_id(&self) -> ThreadId {
-        let res = self.thread_id_counter.get();
-        self.thread_id_counter.set(res + 1);
-        res.try_into().unwrap()
+    /// Get a new thread ID for a thread of a process that descends from the configured process in
+    /// slot `id_slot` (see [`ThreadIdAllocator`]). Returns `None` if the host has run out of
+    /// thread IDs.
+    pub fn get_new_thread_id(&self, id_slot: usize) -> Option<ThreadId> {
+        self.thread_id_allocator.borrow_mut().allocate(id_slot)
     }
 
     pub fn get_new_packet_id(&self) -> u64 {
@@ -704,6 +1440,9 @@ impl Host {
         res
     }
 
+    /// Returns a new priority for a data packet. Each call returns a larger value than the previous
+    /// call, so packets are sent in the order that their priorities were assigned. See
+    /// [`FifoPacketPriority`].
     pub fn get_next_packet_priority(&self) -> FifoPacketPriority {
         let res = self.packet_priority_counter.get();
         self.packet_priority_counter
@@ -742,23 +1481,135 @@ impl Host {
         true
     }
 
-    pub fn boot(&self) {
+    /// Start the host's services and schedule the start of its processes. This runs outside of
+    /// an event, so work deferred using [`Host::defer`] while booting only runs after the host's
+    /// first event. Returns an error if one of the host's services couldn't be started, in which
+    /// case the host should still be shut down.
+    pub fn boot(&self) -> Result<(), HostSetupError> {
         // must be done after the default IP exists so tracker_heartbeat works
         if let Some(heartbeat_interval) = self.params.heartbeat_interval {
             let heartbeat_interval = SimulationTime::to_c_simtime(Some(heartbeat_interval));
+            let sample_interval =
+                SimulationTime::to_c_simtime(self.params.heartbeat_sample_interval);
             let tracker = unsafe {
                 cshadow::tracker_new(
                     self,
                     heartbeat_interval,
+                    sample_interval,
+                    self.params.heartbeat_top_talkers,
                     self.params.heartbeat_log_level,
                     self.params.heartbeat_log_info,
                 )
             };
+            if !self.params.metadata.is_empty() {
+                let metadata = serde_json::to_string(&self.params.metadata).unwrap();
+                let metadata = CString::new(metadata).unwrap();
+                unsafe { cshadow::tracker_logMetadata(tracker, metadata.as_ptr()) };
+            }
             // SAFETY: we synchronize access to the Host's tracker using a RefCell.
             self.tracker
                 .borrow_mut()
                 .replace(unsafe { SyncSendPointer::new(tracker) });
         }
+
+        if self.params.syslog_sink {
+            let path = self.data_dir_path.join("syslog");
+            let sink = SyslogSink::new(&self.net_ns, &path, &mut *self.random_mut()).map_err(
+                |source| HostSetupError::SyslogSink {
+                    host: self.name().to_string(),
+                    path: path.clone(),
+                    source,
+                },
+            )?;
+            self.syslog_sink.borrow_mut().replace(sink);
+        }
+
+        if let Some(config) = &self.params.seed_node {
+            let buf_sizes = (
+                self.params.init_sock_send_buf_size.try_into().unwrap(),
+                self.params.init_sock_recv_buf_size.try_into().unwrap(),
+            );
+            let service =
+                SeedNodeService::new(&self.net_ns, config, buf_sizes, &mut *self.random_mut())
+                    .map_err(|source| HostSetupError::SeedNode {
+                        host: self.name().to_string(),
+                        port: config.port,
+                        source,
+                    })?;
+            self.seed_node.borrow_mut().replace(service);
+        }
+
+        let buf_sizes = (
+            self.params.init_sock_send_buf_size.try_into().unwrap(),
+            self.params.init_sock_recv_buf_size.try_into().unwrap(),
+        );
+        for port in &self.params.probe_ports {
+            let responder =
+                ProbeResponder::new(&self.net_ns, port.port, buf_sizes, &mut *self.random_mut())
+                    .map_err(|source| HostSetupError::ProbeResponder {
+                        host: self.name().to_string(),
+                        port: port.clone(),
+                        source,
+                    })?;
+            self.probe_responders.borrow_mut().push(responder);
+        }
+        for (index, probe) in self.params.probes.iter().enumerate() {
+            let prober = Prober::new(
+                &self.net_ns,
+                index,
+                probe,
+                buf_sizes,
+                &mut *self.random_mut(),
+            )
+            .map_err(|source| HostSetupError::Prober {
+                host: self.name().to_string(),
+                index,
+                source,
+            })?;
+            // spread out the probes of different hosts within their first interval
+            let interval: u64 = probe.interval.as_nanos().try_into().unwrap();
+            let offset = self.random_mut().gen_range(0..interval);
+            let start = EmulatedTime::SIMULATION_START + SimulationTime::from_nanos(offset);
+            prober.start(self, start);
+            self.probers.borrow_mut().push(prober);
+        }
+
+        if let Some(config) = &self.params.bridge {
+            let bridge = Bridge::open(&config.tun).map_err(|source| HostSetupError::Bridge {
+                host: self.name().to_string(),
+                tun: config.tun.clone(),
+                source,
+            })?;
+            self.interface_borrow(self.default_ip())
+                .unwrap()
+                .set_bridge(bridge);
+            let interval = config.poll_interval;
+            let task = TaskRef::new(move |host| host.read_bridge(interval));
+            self.schedule_task_with_delay(task, interval);
+        }
+
+        for config in &self.params.native_apps {
+            let start = EmulatedTime::SIMULATION_START + config.start_time;
+            let config = config.clone();
+            let task = TaskRef::new(move |host| {
+                let app = NativeApp::start(host, &config);
+                host.native_apps.borrow_mut().push(app);
+            });
+            self.schedule_task_at_emulated_time(task, start);
+        }
+
+        if let Some(state) = &self.params.persistent_state {
+            let path = self.data_dir_path.join(persistent_state::STATE_DIR_NAME);
+            state
+                .write_dir(&path)
+                .map_err(|source| HostSetupError::PersistentState {
+                    host: self.name().to_string(),
+                    path: path.clone(),
+                    source,
+                })?;
+        }
+
+        Ok(())
     }
 
     /// Shut down the host. This should be called while `Worker` has the active host set.
@@ -767,11 +1618,67 @@ impl Host {
 
         debug!("shutting down host {}", self.name());
 
+        // run any work that was deferred outside of an event
+        self.run_deferred();
+
+        // the syslog, seed node, probe, and native app sockets must be closed before the network
+        // namespace is cleaned up
+        if let Some(syslog_sink) = self.syslog_sink.borrow_mut().take() {
+            syslog_sink.close();
+        }
+        if let Some(seed_node) = self.seed_node.borrow_mut().take() {
+            seed_node.close();
+        }
+        for responder in self.probe_responders.take() {
+            responder.close();
+        }
+        for prober in self.probers.take() {
+            prober.close();
+            Worker::with_probe_results(|results| prober.add_results(self.name(), results));
+        }
+        for app in self.native_apps.take() {
+            app.stop();
+        }
+
         // the network namespace object needs to be cleaned up before it's dropped
         Worker::with_dns(|dns| self.net_ns.cleanup(dns));
 
+        if let Some(packet_fingerprint) = self.packet_fingerprint.borrow_mut().take() {
+            let path = self.data_dir_path.join(fingerprint::FINGERPRINT_FILE_NAME);
+            if let Err(e) = packet_fingerprint.write(&path) {
+                warn!(
+                    "Could not write the packet fingerprint file '{}': {e}",
+                    path.display()
+                );
+            }
+        }
+
         assert!(self.processes.borrow().is_empty());
 
+        // the processes have been freed and have closed their stdout and stderr files, so the
+        // compressed files can be finished
+        for compressor in self.output_compressors.take() {
+            let path = compressor.path().to_owned();
+            if let Err(e) = compressor.join() {
+                warn!(
+                    "Could not write compressed output file '{}': {e}",
+                    path.display()
+                );
+            }
+        }
+
+        // save any changes that the processes made to the persistent state
+        if self.params.persistent_state.is_some() {
+            let dir = self.data_dir_path.join(persistent_state::STATE_DIR_NAME);
+            let path = self.data_dir_path.join(persistent_state::STATE_FILE_NAME);
+            if let Err(e) = PersistentState::read_dir(dir).and_then(|state| state.save(path)) {
+                error!(
+                    "Could not save the persistent state for host '{}': {e:?}",
+                    self.name()
+                );
+            }
+        }
+
         self.stop_execution_timer();
         #[cfg(feature = "perf_timers")]
         debug!(
@@ -846,17 +1753,118 @@ impl Host {
             self.continue_execution_timer();
             match event.data() {
                 EventData::Packet(data) => {
-                    self.upstream_router_borrow_mut()
-                        .route_incoming_packet(data.into());
-                    self.notify_router_has_packets();
+                    // packets forwarded by the host's load balancer aren't received by the host
+                    if let Some(packet) = self.load_balance(data.into()) {
+                        self.upstream_router_borrow().route_incoming_packet(packet);
+                        Worker::with_invariants(|invariants| {
+                            let len = self.upstream_router_borrow().inbound_queue_len();
+                            invariants.queue_length(
+                                self.id(),
+                                len,
+                                Worker::current_time().unwrap(),
+                            );
+                        });
+                        self.notify_router_has_packets();
+                    }
                 }
                 EventData::Local(data) => TaskRef::from(data).execute(self),
             }
+            self.run_deferred();
+            self.check_event_queue_len();
             self.stop_execution_timer();
             Worker::clear_current_time();
         }
     }
 
+    /// Run `f` after the code handling the current event has returned, but before the host moves
+    /// on to the next event. This is useful for work that needs to borrow objects (such as a
+    /// process or a socket) that are borrowed by the caller. Deferred closures run in the order
+    /// they were deferred, at the same simulated time as the current event, and may defer more
+    /// work. If there is no current event, `f` runs after the next event, or when the host shuts
+    /// down. In particular, work deferred while the host boots in [`Host::boot`] doesn't run
+    /// until after the host's first event.
+    pub fn defer(&self, f: impl FnOnce(&Host) + Send + 'static) {
+        self.deferred.defer(f);
+    }
+
+    /// Keep a compressor of a process's output until the host shuts down, at which point we wait
+    /// for it to finish the compressed file.
+    pub fn add_output_compressor(&self, compressor: PipeCompressor) {
+        self.output_compressors.borrow_mut().push(compressor);
+    }
+
+    /// Run all deferred closures, including any that are deferred while running them.
+    fn run_deferred(&self) {
+        self.deferred.run(self);
+    }
+
+    /// Check the event queue's length against `max_event_queue_len`, and apply the configured
+    /// action if it's too long.
+    fn check_event_queue_len(&self) {
+        let Some(limit) = self.params.max_event_queue_len else {
+            return;
+        };
+
+        let event_queue = self.event_queue.lock().unwrap();
+        let len = u64::try_from(event_queue.len()).unwrap();
+        let was_over_limit = self.event_queue_over_limit.get(&self.root);
+
+        if was_over_limit {
+            // Wait until the queue is well under the limit before resuming the paused threads, so
+            // that we don't pause and resume them after every event.
+            if len > limit / 2 {
+                return;
+            }
+            drop(event_queue);
+
+            self.event_queue_over_limit.set(&self.root, false);
+            let paused_threads = self.paused_threads.take();
+            info!(
+                "Event queue of host '{}' has drained to {len} events; resuming {} paused threads",
+                self.name(),
+                paused_threads.len(),
+            );
+            for (pid, tid) in paused_threads {
+                let task = TaskRef::new(move |host| host.resume(pid, tid));
+                self.schedule_task_with_delay(task, SimulationTime::ZERO);
+            }
+            return;
+        }
+
+        if len <= limit {
+            return;
+        }
+        self.event_queue_over_limit.set(&self.root, true);
+
+        const NUM_SOURCES: usize = 10;
+        let sources: Vec<String> = event_queue
+            .top_sources(NUM_SOURCES)
+            .into_iter()
+            .map(|(source, count)| format!("{count} events: {source}"))
+            .collect();
+        drop(event_queue);
+
+        let msg = format!(
+            "Event queue of host '{}' has {len} events, more than its limit of {limit}. \
+            The most common sources of events are:\n  {}",
+            self.name(),
+            sources.join("\n  "),
+        );
+
+        match self.params.event_queue_limit_action {
+            EventQueueLimitAction::Abort => {
+                error!("{msg}");
+                panic!(
+                    "Host '{}' exceeded its maximum event queue length of {limit}",
+                    self.name(),
+                );
+            }
+            EventQueueLimitAction::Backpressure => {
+                warn!("{msg}\nPausing the host's processes until the queue has drained.");
+            }
+        }
+    }
+
     pub fn next_event_time(&self) -> Option<EmulatedTime> {
         self.event_queue.lock().unwrap().next_event_time()
     }
@@ -965,39 +1973,66 @@ impl Host {
         }
     }
 
+    /// Add a packet that the host sent or received to the host's packet fingerprint, if enabled.
+    pub fn add_to_packet_fingerprint(&self, packet: &PacketRc, direction: PacketDirection) {
+        if let Some(packet_fingerprint) = self.packet_fingerprint.borrow_mut().as_mut() {
+            packet_fingerprint.add(Worker::current_time().unwrap(), direction, packet);
+        }
+    }
+
     /// Call to trigger the forwarding of packets from the router to the network
     /// interface.
     pub fn notify_router_has_packets(&self) {
         self.relay_inet_in.notify(self);
     }
 
+    /// Send the packets that were written to the host's bridge, and read it again after
+    /// `interval`.
+    fn read_bridge(&self, interval: SimulationTime) {
+        let queued = self
+            .interface_borrow(self.default_ip())
+            .is_some_and(|iface| iface.read_bridge(self));
+        if queued {
+            self.relay_inet_out.notify(self);
+        }
+
+        let task = TaskRef::new(move |host| host.read_bridge(interval));
+        self.schedule_task_with_delay(task, interval);
+    }
+
     /// Call to trigger the forwarding of packets from the network interface to
     /// the next hop (either back to the network interface for loopback, or up to
     /// the router for internet-bound packets).
     ///
-    /// WARNING: This is not reentrant. Do not allow this to be called recursively. Nothing in
-    /// `add_data_source()` or `notify()` can call back into this method. This includes any socket
-    /// code called in any indirect way from here.
-    pub fn notify_socket_has_packets(
-        &self,
-        addr: Ipv4Addr,
-        socket_ptr: *const cshadow::CompatSocket,
-    ) {
+    /// This is not reentrant. If it's called recursively (for example by socket code that runs
+    /// while the packets are being forwarded), the recursive call is deferred using
+    /// [`Host::defer`] until the current event's handler has returned.
+    pub fn notify_socket_has_packets(&self, addr: Ipv4Addr, socket: &InetSocket) {
         if self.in_notify_socket_has_packets.replace(&self.root, true) {
-            panic!("Recursively calling host.notify_socket_has_packets()");
+            let socket = socket.clone();
+            self.defer(move |host| host.notify_socket_has_packets(addr, &socket));
+            return;
         }
 
         if let Some(iface) = self.interface_borrow(addr) {
-            iface.add_data_source(socket_ptr);
-            match addr {
-                Ipv4Addr::LOCALHOST => self.relay_loopback.notify(self),
-                _ => self.relay_inet_out.notify(self),
-            };
+            iface.add_data_source(socket);
+            self.notify_interface_has_packets(addr);
         }
 
         self.in_notify_socket_has_packets.set(&self.root, false);
     }
 
+    /// Trigger the forwarding of packets from the network interface with address `addr`, for
+    /// packets that the interface queued itself rather than taking them from a socket (for
+    /// example replies to ICMP echo requests). This is safe to call while the interface is
+    /// borrowed.
+    pub fn notify_interface_has_packets(&self, addr: Ipv4Addr) {
+        match addr {
+            Ipv4Addr::LOCALHOST => self.relay_loopback.notify(self),
+            _ => self.relay_inet_out.notify(self),
+        };
+    }
+
     /// Returns the Session ID for the given process group ID, if it exists.
     pub fn process_session_id_of_group_id(&self, group_id: ProcessId) -> Option<ProcessId> {
         let processes = self.processes.borrow();
@@ -1010,6 +2045,33 @@ impl Host {
         None
     }
 
+    /// Sends `siginfo` to every process in the given process group. Returns false if there is no
+    /// such process group.
+    pub fn signal_process_group(
+        &self,
+        current_thread: Option<&Thread>,
+        group_id: ProcessId,
+        siginfo: &siginfo_t,
+    ) -> bool {
+        let pids: Vec<ProcessId> = self
+            .processes
+            .borrow()
+            .iter()
+            .filter(|(_, process)| process.borrow(&self.root).group_id() == group_id)
+            .map(|(pid, _)| *pid)
+            .collect();
+
+        for pid in &pids {
+            let Some(process) = self.process_borrow(*pid) else {
+                continue;
+            };
+            let process = process.borrow(&self.root);
+            process.signal(self, current_thread, siginfo);
+        }
+
+        !pids.is_empty()
+    }
+
     /// Paths of libraries that should be preloaded into managed processes.
     pub fn preload_paths(&self) -> &[PathBuf] {
         &self.preload_paths
@@ -1049,7 +2111,6 @@ mod export {
     use crate::{
         cshadow::{CEmulatedTime, CSimulationTime},
         host::{process::Process, thread::Thread},
-        network::router::Router,
     };
 
     #[no_mangle]
@@ -1097,6 +2158,136 @@ mod export {
         hostrc.params.hostname.as_ptr()
     }
 
+    /// Returns the contents of the emulated `/proc/sys/kernel/*` file at `path`, or NULL if the
+    /// file isn't emulated. The returned pointer belongs to Host.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getProcSysKernelFile(
+        hostrc: *const Host,
+        path: *const c_char,
+    ) -> *const c_char {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let path = unsafe { CStr::from_ptr(path) };
+        hostrc
+            .proc_sys_kernel_files
+            .iter()
+            .find(|(x, _)| x.as_bytes() == path.to_bytes())
+            .map(|(_, contents)| contents.as_ptr())
+            .unwrap_or(std::ptr::null())
+    }
+
+    /// Returns the contents of the emulated `/proc/net/*` file at `path`, or NULL if the file isn't
+    /// emulated. The returned string must be freed with `host_freeProcNetFile`.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_allocProcNetFile(
+        hostrc: *const Host,
+        path: *const c_char,
+    ) -> *mut c_char {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let path = unsafe { CStr::from_ptr(path) };
+        match hostrc.proc_net_file(path.to_bytes()) {
+            Some(contents) => CString::new(contents).unwrap().into_raw(),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    /// Frees a string previously returned from `host_allocProcNetFile`.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_freeProcNetFile(contents: *mut c_char) {
+        assert!(!contents.is_null());
+        drop(unsafe { CString::from_raw(contents) });
+    }
+
+    /// Returns the directory that stores the files of the host's `/dev/shm` tmpfs, or NULL if the
+    /// host uses the real `/dev/shm`. The returned pointer belongs to Host.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTmpfsDir(hostrc: *const Host) -> *const c_char {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        match &*hostrc.tmpfs_borrow() {
+            Some(tmpfs) => tmpfs.dir_cstring().as_ptr(),
+            None => std::ptr::null(),
+        }
+    }
+
+    /// Returns the size limit of the host's tmpfs in bytes.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTmpfsSizeLimit(hostrc: *const Host) -> u64 {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.tmpfs_borrow().as_ref().unwrap().size_limit()
+    }
+
+    /// Returns the total size of the files in the host's tmpfs in bytes.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTmpfsUsage(hostrc: *const Host) -> u64 {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.tmpfs_borrow().as_ref().unwrap().usage()
+    }
+
+    /// Returns true if the tmpfs file can grow to `size` bytes without exceeding the size limit.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tmpfsCanResize(
+        hostrc: *const Host,
+        dev: u64,
+        ino: u64,
+        size: u64,
+    ) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc
+            .tmpfs_borrow()
+            .as_ref()
+            .unwrap()
+            .can_resize((dev, ino), size)
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tmpfsFileOpened(
+        hostrc: *const Host,
+        dev: u64,
+        ino: u64,
+        size: u64,
+    ) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let mut tmpfs = hostrc.tmpfs_borrow_mut();
+        tmpfs.as_mut().unwrap().file_opened((dev, ino), size);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tmpfsFileResized(
+        hostrc: *const Host,
+        dev: u64,
+        ino: u64,
+        size: u64,
+    ) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let mut tmpfs = hostrc.tmpfs_borrow_mut();
+        tmpfs.as_mut().unwrap().file_resized((dev, ino), size);
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tmpfsFileClosed(
+        hostrc: *const Host,
+        dev: u64,
+        ino: u64,
+        nlink: u64,
+    ) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let mut tmpfs = hostrc.tmpfs_borrow_mut();
+        tmpfs.as_mut().unwrap().file_closed((dev, ino), nlink);
+    }
+
+    /// Called when a hard link was removed from any file while the host has a tmpfs. Files that
+    /// aren't in the tmpfs are ignored.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tmpfsFileUnlinked(
+        hostrc: *const Host,
+        dev: u64,
+        ino: u64,
+        nlink: u64,
+    ) {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        let mut tmpfs = hostrc.tmpfs_borrow_mut();
+        tmpfs.as_mut().unwrap().file_unlinked((dev, ino), nlink);
+    }
+
     /// SAFETY: Returned pointer belongs to Host, and is only safe to access
     /// while no other threads are accessing Host.
     #[no_mangle]
@@ -1128,6 +2319,13 @@ mod export {
         hostrc.params.autotune_recv_buf
     }
 
+    /// Returns true if the payloads of packets sent by the host should only store their length.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_zeroPacketPayloads(hostrc: *const Host) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.packet_payload == PacketPayload::Zero
+    }
+
     #[no_mangle]
     pub unsafe extern "C-unwind" fn host_autotuneSendBuffer(hostrc: *const Host) -> bool {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
@@ -1147,9 +2345,61 @@ mod export {
     }
 
     #[no_mangle]
-    pub unsafe extern "C-unwind" fn host_getUpstreamRouter(hostrc: *const Host) -> *mut Router {
+    pub unsafe extern "C-unwind" fn host_tcpAbortOnOverflow(hostrc: *const Host) -> bool {
         let hostrc = unsafe { hostrc.as_ref().unwrap() };
-        &mut *hostrc.upstream_router_borrow_mut()
+        hostrc.params.tcp_abort_on_overflow
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTCPMaxSynBacklog(hostrc: *const Host) -> u32 {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.tcp_max_syn_backlog
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tcpSyncookies(hostrc: *const Host) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.tcp_syncookies
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tcpTimestamps(hostrc: *const Host) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.tcp_timestamps
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_tcpWindowScaling(hostrc: *const Host) -> bool {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.tcp_window_scaling
+    }
+
+    /// The time that a TCP connection delays an ACK, given the number of ACKs that it has already
+    /// delayed.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTCPDelayedACKTimeout(
+        hostrc: *const Host,
+        num_delayed: u32,
+    ) -> CSimulationTime {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        SimulationTime::to_c_simtime(Some(hostrc.params.tcp_delayed_ack.timeout(num_delayed)))
+    }
+
+    /// The number of received bytes that a TCP connection can leave unacknowledged before it sends
+    /// an ACK without delay, or 0 if unlimited.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTCPDelayedACKMaxBytes(hostrc: *const Host) -> u64 {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        hostrc.params.tcp_delayed_ack.max_unacked_bytes.unwrap_or(0)
+    }
+
+    /// The time that a TCP connection stays in the TIME_WAIT state.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_getTCPTimeWaitTimeout(
+        hostrc: *const Host,
+    ) -> CSimulationTime {
+        let hostrc = unsafe { hostrc.as_ref().unwrap() };
+        SimulationTime::to_c_simtime(Some(hostrc.params.tcp_time_wait.timeout))
     }
 
     #[no_mangle]
@@ -1210,7 +2460,7 @@ mod export {
         // associate the interfaces corresponding to bind_addr with socket
         hostrc
             .net_ns
-            .disassociate_interface(protocol, bind_addr, peer_addr);
+            .disassociate_interface(protocol, bind_addr, peer_addr, None);
     }
 
     #[no_mangle]
@@ -1267,6 +2517,30 @@ mod export {
             .unwrap_or(std::ptr::null_mut())
     }
 
+    /// Sends the signal in `siginfo` to every process in the process group
+    /// `group_id`, or if `siginfo` is NULL only checks that the process group
+    /// exists. Returns false if there is no such process group.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_signalProcessGroup(
+        host: *const Host,
+        current_running_thread: *const Thread,
+        group_id: libc::pid_t,
+        siginfo: *const linux_api::signal::linux_siginfo_t,
+    ) -> bool {
+        let host = unsafe { host.as_ref().unwrap() };
+        let current_running_thread = unsafe { current_running_thread.as_ref() };
+        let Ok(group_id) = ProcessId::try_from(group_id) else {
+            return false;
+        };
+        match unsafe { siginfo.as_ref() } {
+            Some(siginfo) => {
+                let siginfo = unsafe { siginfo_t::wrap_ref_assume_initd(siginfo) };
+                host.signal_process_group(current_running_thread, group_id, siginfo)
+            }
+            None => host.process_session_id_of_group_id(group_id).is_some(),
+        }
+    }
+
     /// Returns the specified thread, or NULL if it doesn't exist.
     /// If you already have the thread's Process*, `process_getThread` may be more
     /// efficient.
@@ -1416,12 +2690,118 @@ mod export {
         host.cpu.borrow_mut().add_delay(delay);
     }
 
+    /// Add the host's heartbeat statistics for the interval that just ended to the aggregate
+    /// statistics of its tags, if they're enabled.
+    #[no_mangle]
+    pub extern "C-unwind" fn host_recordHeartbeatStats(
+        host: *const Host,
+        recv_bytes: u64,
+        send_bytes: u64,
+        cpu_delay: CSimulationTime,
+    ) {
+        let host = unsafe { host.as_ref().unwrap() };
+        if host.params.tags.is_empty() {
+            return;
+        }
+
+        Worker::with_group_stats(|group_stats| {
+            let connections = host
+                .network_namespace_borrow()
+                .internet
+                .borrow()
+                .num_connections();
+            let stats = HostStats {
+                recv_bytes,
+                send_bytes,
+                cpu_delay_ns: SimulationTime::from_c_simtime(cpu_delay)
+                    .unwrap()
+                    .as_nanos()
+                    .try_into()
+                    .unwrap(),
+                connections: connections.try_into().unwrap(),
+            };
+            let now = Worker::current_time().unwrap();
+            group_stats.add(&host.params.tags, now, stats);
+        });
+    }
+
+    /// The number of packets waiting in the host's upstream router queue.
+    #[no_mangle]
+    pub extern "C-unwind" fn host_getRouterQueueLength(host: *const Host) -> u64 {
+        let host = unsafe { host.as_ref().unwrap() };
+        let len = host.upstream_router_borrow().inbound_queue_len();
+        len.try_into().unwrap()
+    }
+
+    /// The number of sockets waiting to send packets on the host's loopback interface if
+    /// `loopback` is true, or on its internet-facing interface otherwise.
+    #[no_mangle]
+    pub extern "C-unwind" fn host_getInterfaceQueueLength(
+        host: *const Host,
+        loopback: bool,
+    ) -> u64 {
+        let host = unsafe { host.as_ref().unwrap() };
+        let net_ns = host.network_namespace_borrow();
+        let interface = if loopback {
+            &net_ns.localhost
+        } else {
+            &net_ns.internet
+        };
+        let len = interface.borrow().send_queue_len();
+        len.try_into().unwrap()
+    }
+
     #[no_mangle]
     pub extern "C-unwind" fn host_paramsHeartbeatInterval(host: *const Host) -> CSimulationTime {
         let host = unsafe { host.as_ref().unwrap() };
         SimulationTime::to_c_simtime(host.params.heartbeat_interval)
     }
 
+    /// The configured latency of the syscall `number`, or `SIMTIME_INVALID` if it doesn't have a
+    /// latency of its own.
+    #[no_mangle]
+    pub extern "C-unwind" fn host_paramsSyscallLatency(
+        host: *const Host,
+        number: libc::c_long,
+    ) -> CSimulationTime {
+        let host = unsafe { host.as_ref().unwrap() };
+        SimulationTime::to_c_simtime(host.params.syscall_latencies.get(&number).copied())
+    }
+
+    /// Apply the host's resolver options to a lookup of the IPv4 (or IPv6 if `ipv6` is true)
+    /// addresses of `name` by thread `tid`. If the lookup hasn't finished, sets `block_until` to the
+    /// time it finishes and returns 0. Otherwise sets `block_until` to `EMUTIME_INVALID` and
+    /// returns 0 if the name should be resolved as usual, or a positive errno if the lookup fails.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn host_resolverLookup(
+        host: *const Host,
+        tid: libc::pid_t,
+        name: *const c_char,
+        ipv6: bool,
+        block_until: *mut CEmulatedTime,
+    ) -> i32 {
+        let host = unsafe { host.as_ref().unwrap() };
+        let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+        let block_until = unsafe { block_until.as_mut().unwrap() };
+
+        let family = if ipv6 {
+            ResolverFamily::Ipv6
+        } else {
+            ResolverFamily::Ipv4
+        };
+        let status = host.resolver_lookup(tid.try_into().unwrap(), &name, family);
+
+        *block_until = match status {
+            LookupStatus::Pending(time) => EmulatedTime::to_c_emutime(Some(time)),
+            _ => EmulatedTime::to_c_emutime(None),
+        };
+
+        match status {
+            LookupStatus::Fail(e) => e.into(),
+            LookupStatus::Pending(_) | LookupStatus::Resolve => 0,
+        }
+    }
+
     #[no_mangle]
     pub extern "C-unwind" fn host_paramsHeartbeatLogLevel(host: *const Host) -> LogLevel {
         let host = unsafe { host.as_ref().unwrap() };
@@ -1436,17 +2816,6 @@ mod export {
         host.params.heartbeat_log_info
     }
 
-    #[no_mangle]
-    pub unsafe extern "C-unwind" fn host_socketWantsToSend(
-        hostrc: *const Host,
-        socket: *const cshadow::CompatSocket,
-        addr: in_addr_t,
-    ) {
-        let host = unsafe { hostrc.as_ref().unwrap() };
-        let addr = u32::from_be(addr).into();
-        host.notify_socket_has_packets(addr, socket);
-    }
-
     #[no_mangle]
     pub unsafe extern "C-unwind" fn host_continue(
         host: *const Host,
@@ -0,0 +1,399 @@
+//! A layer 4 load balancer that a host can run in place of a real load balancer implementation.
+//! Packets sent to the load balancer's port are forwarded to one of its backends, which is chosen
+//! when the first packet of a connection (a TCP SYN, or any UDP datagram from a new client address)
+//! arrives.
+//!
+//! By default the load balancer translates addresses in both directions: packets to a backend are
+//! sent from the load balancer's address and a port that identifies the connection, and the
+//! backend's replies are sent back to the client from the load balancer's port. In direct server
+//! return (DSR) mode, only packets from clients pass through the load balancer, and the backends
+//! reply to clients directly from the load balancer's address.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use crate::core::sim_config::LoadBalancer as LoadBalancerConfig;
+use crate::core::support::configuration::LoadBalancerAlgorithm;
+
+/// The number of connections that the load balancer tracks at once. A new connection replaces the
+/// oldest connection. When translating addresses, each connection is identified by a port starting
+/// at [`FIRST_NAT_PORT`].
+const MAX_FLOWS: usize = 64512;
+
+/// The first port used to identify connections when translating addresses.
+const FIRST_NAT_PORT: u16 = 1024;
+
+/// The number of points on the consistent hash ring for each backend.
+const RING_POINTS_PER_BACKEND: u32 = 100;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// What the host does with a packet that it received.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// The packet isn't handled by the load balancer, and is received by the host as usual.
+    Receive,
+    /// The packet is forwarded with new source and destination addresses.
+    Forward {
+        src: SocketAddrV4,
+        dst: SocketAddrV4,
+    },
+    /// The packet doesn't belong to a known connection and is dropped.
+    Drop,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct FlowKey {
+    protocol: Protocol,
+    client: SocketAddrV4,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Flow {
+    backend: usize,
+    slot: usize,
+    /// Whether the connection is still open (a TCP connection is closed once the load balancer
+    /// sees a FIN or RST).
+    open: bool,
+}
+
+/// The connection table and backend selection state of a host's load balancer.
+#[derive(Debug)]
+pub struct LoadBalancer {
+    port: u16,
+    backends: Vec<SocketAddrV4>,
+    algorithm: LoadBalancerAlgorithm,
+    dsr: bool,
+    /// Points on the consistent hash ring, sorted by their hash.
+    ring: Vec<(u32, usize)>,
+    /// The next backend chosen by the round-robin algorithm.
+    next_backend: usize,
+    /// The number of open connections of each backend.
+    open_flows: Vec<usize>,
+    flows: HashMap<FlowKey, Flow>,
+    /// The connection using each slot, which is also the connection's port when translating
+    /// addresses.
+    slots: Vec<Option<FlowKey>>,
+    next_slot: usize,
+}
+
+impl LoadBalancer {
+    pub fn new(config: &LoadBalancerConfig) -> Self {
+        assert!(!config.backends.is_empty());
+
+        let mut ring: Vec<(u32, usize)> = (0..config.backends.len())
+            .flat_map(|backend| {
+                let addr = config.backends[backend];
+                (0..RING_POINTS_PER_BACKEND).map(move |point| {
+                    let mut bytes = addr.ip().octets().to_vec();
+                    bytes.extend(addr.port().to_be_bytes());
+                    bytes.extend(point.to_be_bytes());
+                    (fnv1a(&bytes), backend)
+                })
+            })
+            .collect();
+        ring.sort_unstable();
+
+        Self {
+            port: config.port,
+            backends: config.backends.clone(),
+            algorithm: config.algorithm,
+            dsr: config.dsr,
+            ring,
+            next_backend: 0,
+            open_flows: vec![0; config.backends.len()],
+            flows: HashMap::new(),
+            slots: vec![None; MAX_FLOWS],
+            next_slot: 0,
+        }
+    }
+
+    /// Decide what to do with a packet received by the host, whose address is `local_ip`. For TCP
+    /// packets, `tcp_flags` are the packet's flags.
+    pub fn handle_packet(
+        &mut self,
+        local_ip: Ipv4Addr,
+        protocol: Protocol,
+        src: SocketAddrV4,
+        dst: SocketAddrV4,
+        tcp_flags: Option<tcp::TcpFlags>,
+    ) -> Action {
+        let is_closing =
+            tcp_flags.is_some_and(|x| x.intersects(tcp::TcpFlags::FIN | tcp::TcpFlags::RST));
+
+        // a backend's reply to a client
+        if let Some(key) = self.reply_flow(protocol, src, dst) {
+            if is_closing {
+                self.close(key);
+            }
+            return Action::Forward {
+                src: SocketAddrV4::new(local_ip, self.port),
+                dst: key.client,
+            };
+        }
+
+        if dst.port() != self.port {
+            return Action::Receive;
+        }
+
+        // a client's packet
+        let key = FlowKey {
+            protocol,
+            client: src,
+        };
+        let is_new_connection = match tcp_flags {
+            Some(flags) => {
+                flags.contains(tcp::TcpFlags::SYN) && !flags.contains(tcp::TcpFlags::ACK)
+            }
+            None => true,
+        };
+
+        let flow = match self.flows.get(&key).copied() {
+            // a new TCP connection that reuses a closed connection's client address
+            Some(flow) if !flow.open && is_new_connection && protocol == Protocol::Tcp => {
+                self.remove(key);
+                self.open(key)
+            }
+            Some(flow) => flow,
+            None if is_new_connection => self.open(key),
+            None => return Action::Drop,
+        };
+
+        if is_closing {
+            self.close(key);
+        }
+
+        let backend = self.backends[flow.backend];
+        if self.dsr {
+            Action::Forward { src, dst: backend }
+        } else {
+            let nat_port = FIRST_NAT_PORT + u16::try_from(flow.slot).unwrap();
+            Action::Forward {
+                src: SocketAddrV4::new(local_ip, nat_port),
+                dst: backend,
+            }
+        }
+    }
+
+    /// The connection of a packet that a backend sent to the translated address `dst`.
+    fn reply_flow(
+        &self,
+        protocol: Protocol,
+        src: SocketAddrV4,
+        dst: SocketAddrV4,
+    ) -> Option<FlowKey> {
+        if self.dsr || dst.port() < FIRST_NAT_PORT {
+            return None;
+        }
+        let slot = usize::from(dst.port() - FIRST_NAT_PORT);
+        let key = self.slots.get(slot).copied().flatten()?;
+        (key.protocol == protocol && self.backends[self.flows[&key].backend] == src).then_some(key)
+    }
+
+    /// Start tracking a new connection, replacing the oldest connection if the table is full.
+    fn open(&mut self, key: FlowKey) -> Flow {
+        let slot = self.next_slot;
+        self.next_slot = (self.next_slot + 1) % MAX_FLOWS;
+        if let Some(old_key) = self.slots[slot] {
+            self.remove(old_key);
+        }
+
+        let backend = self.choose_backend(key.client);
+        let flow = Flow {
+            backend,
+            slot,
+            open: true,
+        };
+        self.slots[slot] = Some(key);
+        self.flows.insert(key, flow);
+        self.open_flows[backend] += 1;
+
+        log::trace!(
+            "Load balancing connection from {} to backend {}",
+            key.client,
+            self.backends[backend]
+        );
+
+        flow
+    }
+
+    fn close(&mut self, key: FlowKey) {
+        let flow = self.flows.get_mut(&key).unwrap();
+        if flow.open {
+            flow.open = false;
+            self.open_flows[flow.backend] -= 1;
+        }
+    }
+
+    fn remove(&mut self, key: FlowKey) {
+        self.close(key);
+        let flow = self.flows.remove(&key).unwrap();
+        self.slots[flow.slot] = None;
+    }
+
+    fn choose_backend(&mut self, client: SocketAddrV4) -> usize {
+        match self.algorithm {
+            LoadBalancerAlgorithm::RoundRobin => {
+                let backend = self.next_backend;
+                self.next_backend = (self.next_backend + 1) % self.backends.len();
+                backend
+            }
+            LoadBalancerAlgorithm::LeastConnections => {
+                // the first backend with the fewest open connections
+                (0..self.backends.len())
+                    .min_by_key(|x| self.open_flows[*x])
+                    .unwrap()
+            }
+            LoadBalancerAlgorithm::ConsistentHash => {
+                // the first point on the ring at or after the client address's hash
+                let hash = fnv1a(&client.ip().octets());
+                let index = self.ring.partition_point(|(x, _)| *x < hash);
+                self.ring[index % self.ring.len()].1
+            }
+        }
+    }
+}
+
+/// The 32-bit FNV-1a hash of `bytes`, which unlike the standard library's hasher is stable across
+/// Rust versions.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x01000193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tcp::TcpFlags;
+
+    use super::*;
+
+    const LB_IP: Ipv4Addr = Ipv4Addr::new(11, 0, 0, 1);
+
+    fn config(algorithm: LoadBalancerAlgorithm, dsr: bool) -> LoadBalancerConfig {
+        LoadBalancerConfig {
+            port: 80,
+            backend_hostnames: Vec::new(),
+            backends: (2..5)
+                .map(|x| SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, x), 8080))
+                .collect(),
+            backend_port: 8080,
+            algorithm,
+            dsr,
+        }
+    }
+
+    fn client(port: u16) -> SocketAddrV4 {
+        SocketAddrV4::new(Ipv4Addr::new(11, 0, 1, 1), port)
+    }
+
+    fn vip() -> SocketAddrV4 {
+        SocketAddrV4::new(LB_IP, 80)
+    }
+
+    /// Send a TCP packet from a client and return the backend it was forwarded to.
+    fn send(lb: &mut LoadBalancer, client: SocketAddrV4, flags: TcpFlags) -> Option<SocketAddrV4> {
+        match lb.handle_packet(LB_IP, Protocol::Tcp, client, vip(), Some(flags)) {
+            Action::Forward { dst, .. } => Some(dst),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut lb = LoadBalancer::new(&config(LoadBalancerAlgorithm::RoundRobin, false));
+        let backends: Vec<_> = (1..=4)
+            .map(|x| send(&mut lb, client(x), TcpFlags::SYN).unwrap())
+            .collect();
+        assert_eq!(backends[0], lb.backends[0]);
+        assert_eq!(backends[1], lb.backends[1]);
+        assert_eq!(backends[2], lb.backends[2]);
+        assert_eq!(backends[3], lb.backends[0]);
+
+        // later packets of a connection go to the same backend
+        assert_eq!(send(&mut lb, client(2), TcpFlags::ACK), Some(backends[1]));
+
+        // packets of unknown connections are dropped
+        assert_eq!(send(&mut lb, client(5), TcpFlags::ACK), None);
+    }
+
+    #[test]
+    fn test_least_connections() {
+        let mut lb = LoadBalancer::new(&config(LoadBalancerAlgorithm::LeastConnections, false));
+        for x in 1..=3 {
+            send(&mut lb, client(x), TcpFlags::SYN).unwrap();
+        }
+
+        // close the second connection, so its backend has the fewest connections
+        send(&mut lb, client(2), TcpFlags::FIN | TcpFlags::ACK).unwrap();
+        assert_eq!(
+            send(&mut lb, client(4), TcpFlags::SYN),
+            Some(lb.backends[1])
+        );
+        assert_eq!(
+            send(&mut lb, client(5), TcpFlags::SYN),
+            Some(lb.backends[0])
+        );
+    }
+
+    #[test]
+    fn test_consistent_hash() {
+        let mut lb = LoadBalancer::new(&config(LoadBalancerAlgorithm::ConsistentHash, false));
+        let backend = send(&mut lb, client(1), TcpFlags::SYN).unwrap();
+
+        // connections from the same client address go to the same backend
+        for x in 2..10 {
+            assert_eq!(send(&mut lb, client(x), TcpFlags::SYN), Some(backend));
+        }
+    }
+
+    #[test]
+    fn test_address_translation() {
+        let mut lb = LoadBalancer::new(&config(LoadBalancerAlgorithm::RoundRobin, false));
+
+        let Action::Forward { src, dst } =
+            lb.handle_packet(LB_IP, Protocol::Udp, client(1), vip(), None)
+        else {
+            panic!();
+        };
+        assert_eq!(*src.ip(), LB_IP);
+        assert_eq!(dst, lb.backends[0]);
+
+        // the backend's reply is sent to the client from the load balancer's port
+        assert_eq!(
+            lb.handle_packet(LB_IP, Protocol::Udp, dst, src, None),
+            Action::Forward {
+                src: vip(),
+                dst: client(1),
+            }
+        );
+
+        // other packets are received by the host
+        assert_eq!(
+            lb.handle_packet(
+                LB_IP,
+                Protocol::Udp,
+                client(1),
+                SocketAddrV4::new(LB_IP, 53),
+                None
+            ),
+            Action::Receive
+        );
+    }
+
+    #[test]
+    fn test_dsr() {
+        let mut lb = LoadBalancer::new(&config(LoadBalancerAlgorithm::RoundRobin, true));
+        assert_eq!(
+            lb.handle_packet(LB_IP, Protocol::Udp, client(1), vip(), None),
+            Action::Forward {
+                src: client(1),
+                dst: lb.backends[0],
+            }
+        );
+    }
+}
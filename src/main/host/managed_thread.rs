@@ -1,31 +1,39 @@
 use std::cell::{Cell, RefCell};
 use std::ffi::{CStr, CString};
 use std::io::Write;
-use std::os::fd::AsRawFd;
+use std::os::fd::{AsRawFd, RawFd};
 use std::os::unix::prelude::OsStrExt;
 use std::path::PathBuf;
 use std::sync::{atomic, Arc};
 
 use linux_api::sched::CloneFlags;
-use log::{debug, error, log_enabled, trace, Level};
+use linux_api::signal::{
+    defaultaction, sigaction, siginfo_t, sigset_t, LinuxDefaultAction, SigActionFlags, Signal,
+    SignalHandler,
+};
+use log::{debug, error, log_enabled, trace, warn, Level};
 use nix::errno::Errno;
 use shadow_shim_helper_rs::ipc::IPCData;
 use shadow_shim_helper_rs::shim_event::{
     ShimEventAddThreadReq, ShimEventAddThreadRes, ShimEventSyscall, ShimEventSyscallComplete,
     ShimEventToShadow, ShimEventToShim,
 };
+use shadow_shim_helper_rs::shim_shmem;
 use shadow_shim_helper_rs::syscall_types::{ForeignPtr, SysCallArgs, SysCallReg};
 use shadow_shmem::allocator::ShMemBlock;
 use vasi_sync::scchannel::SelfContainedChannelError;
 
 use super::context::ThreadContext;
-use super::host::Host;
 use super::syscall_condition::SysCallCondition;
 use crate::core::scheduler;
 use crate::core::worker::{Worker, WORKER_SHARED};
 use crate::cshadow;
 use crate::host::syscall_types::{ForeignArrayPtr, SyscallReturn};
-use crate::utility::{inject_preloads, syscall, verify_plugin_path, VerifyPluginPathError};
+use crate::utility::seccomp_notify::{Notification, SeccompNotifier};
+use crate::utility::{
+    inject_cet_tunables, inject_preloads, syscall, verify_executable_path, verify_plugin_path,
+    PluginLinkage, VerifyPluginPathError,
+};
 
 /// The ManagedThread's state after having been allowed to execute some code.
 #[derive(Debug)]
@@ -39,8 +47,21 @@ pub enum ResumeResult {
     ExitedProcess,
 }
 
+/// How Shadow intercepts the syscalls of the native thread.
+enum Interposer {
+    /// The shim, which is preloaded into the process, sends the syscalls over an IPC channel.
+    Shim(Arc<ShMemBlock<'static, IPCData>>),
+    /// The kernel sends the syscalls to a seccomp listener, for statically linked executables
+    /// that can't load the shim.
+    SeccompNotify {
+        notifier: Arc<SeccompNotifier>,
+        /// The syscall that the native thread is waiting on, if it hasn't been answered yet.
+        notification: Cell<Option<Notification>>,
+    },
+}
+
 pub struct ManagedThread {
-    ipc_shmem: Arc<ShMemBlock<'static, IPCData>>,
+    interposer: Interposer,
     is_running: Cell<bool>,
     return_code: Cell<Option<i32>>,
 
@@ -66,18 +87,30 @@ impl ManagedThread {
         self.native_tid
     }
 
+    /// Whether the native thread loaded the shim, rather than having its syscalls sent to a
+    /// seccomp listener.
+    pub fn has_shim(&self) -> bool {
+        matches!(self.interposer, Interposer::Shim(_))
+    }
+
     /// Make the specified syscall on the native thread.
     ///
     /// Panics if the native thread is dead or dies during the syscall,
     /// including if the syscall itself is SYS_exit or SYS_exit_group.
+    /// Also panics if the thread doesn't have the shim (see [`Self::has_shim`]).
     pub fn native_syscall(&self, ctx: &ThreadContext, n: i64, args: &[SysCallReg]) -> SysCallReg {
+        assert!(
+            self.has_shim(),
+            "Can't make syscall {n} in thread {} without the shim",
+            self.native_tid
+        );
         let mut syscall_args = SysCallArgs {
             number: n,
             args: [SysCallReg::from(0u64); 6],
         };
         syscall_args.args[..args.len()].copy_from_slice(args);
         match self.continue_plugin(
-            ctx.host,
+            ctx,
             &ShimEventToShim::Syscall(ShimEventSyscall { syscall_args }),
         ) {
             ShimEventToShadow::SyscallComplete(res) => res.retval,
@@ -85,24 +118,57 @@ impl ManagedThread {
         }
     }
 
+    /// Spawn a managed thread running the executable at `plugin_path`. If `interpreter` is given,
+    /// the executable is run by that dynamic linker rather than the one named in the executable.
+    /// `working_dir` is the initial working directory of the process.
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         plugin_path: &CStr,
+        interpreter: Option<&CStr>,
         argv: Vec<CString>,
         envv: Vec<CString>,
+        working_dir: &CStr,
         strace_file: Option<&std::fs::File>,
         log_file: &std::fs::File,
         injected_preloads: &[PathBuf],
     ) -> nix::Result<Self> {
         debug!("spawning new mthread '{plugin_path:?}' with environment '{envv:?}', arguments '{argv:?}'");
 
+        // Preemptively check for likely reasons that execve might fail, and whether the
+        // executable can load the shim. Launching a statically linked executable as if it could
+        // would deadlock the whole simulation waiting for the shim to initialize.
+        //
+        // This is also helpful since we can't retrieve specific `execve` errors
+        // through `posix_spawn`.
+        let linkage = verify_plugin_path(std::ffi::OsStr::from_bytes(plugin_path.to_bytes()))
+            .map_err(|e| {
+                debug!("Failed to verify path {plugin_path:?}");
+                verify_error_to_errno(e)
+            })?;
+
         let envv = inject_preloads(envv, injected_preloads);
+        let envv = inject_cet_tunables(envv);
 
-        debug!("env after preload injection: {envv:?}");
+        debug!("env after preload and tunable injection: {envv:?}");
+
+        if linkage == PluginLinkage::Static {
+            if interpreter.is_some() {
+                warn!("Ignoring the interpreter for statically linked executable {plugin_path:?}");
+            }
+            return Self::spawn_notified(plugin_path, argv, envv, working_dir, log_file);
+        }
 
         let ipc_shmem = Arc::new(shadow_shmem::allocator::shmalloc(IPCData::new()));
 
-        let child_pid =
-            Self::spawn_native(plugin_path, argv, envv, strace_file, log_file, &ipc_shmem)?;
+        let child_pid = Self::spawn_native(
+            plugin_path,
+            interpreter,
+            argv,
+            envv,
+            strace_file,
+            log_file,
+            &ipc_shmem,
+        )?;
 
         // In Linux, the PID is equal to the TID of its first thread.
         let native_pid = child_pid;
@@ -167,7 +233,7 @@ impl ManagedThread {
         };
 
         Ok(Self {
-            ipc_shmem,
+            interposer: Interposer::Shim(ipc_shmem),
             is_running: Cell::new(true),
             return_code: Cell::new(None),
             current_event: RefCell::new(start_req),
@@ -177,6 +243,43 @@ impl ManagedThread {
         })
     }
 
+    /// Spawn a managed thread running the statically linked executable at `plugin_path`, whose
+    /// syscalls are sent to a seccomp listener.
+    fn spawn_notified(
+        plugin_path: &CStr,
+        argv: Vec<CString>,
+        envv: Vec<CString>,
+        working_dir: &CStr,
+        log_file: &std::fs::File,
+    ) -> nix::Result<Self> {
+        let (notifier, first) =
+            SeccompNotifier::spawn(plugin_path, &argv, &envv, working_dir, log_file)?;
+
+        // In Linux, the PID is equal to the TID of its first thread.
+        let native_pid = first.tid;
+        debug!("started statically linked process {plugin_path:?} with native pid {native_pid}");
+
+        WORKER_SHARED
+            .borrow()
+            .as_ref()
+            .unwrap()
+            .child_pid_watcher()
+            .register_pid(native_pid);
+
+        Ok(Self {
+            interposer: Interposer::SeccompNotify {
+                notifier: Arc::new(notifier),
+                notification: Cell::new(Some(first)),
+            },
+            is_running: Cell::new(true),
+            return_code: Cell::new(None),
+            current_event: RefCell::new(syscall_event(&first)),
+            native_pid,
+            native_tid: native_pid,
+            affinity: Cell::new(cshadow::AFFINITY_UNINIT),
+        })
+    }
+
     pub fn resume(&self, ctx: &ThreadContext) -> ResumeResult {
         debug_assert!(self.is_running());
 
@@ -228,7 +331,7 @@ impl ManagedThread {
 
                     // send the message to the shim to call main().
                     trace!("sending start event code to shim");
-                    self.continue_plugin(ctx.host, &ShimEventToShim::StartRes)
+                    self.continue_plugin(ctx, &ShimEventToShim::StartRes)
                 }
                 ShimEventToShadow::ProcessDeath => {
                     // The native threads are all dead or zombies. Nothing to do but
@@ -255,13 +358,28 @@ impl ManagedThread {
                         // since that'd release the ShimSharedMemHostLock, and we
                         // aren't going to get a message back to know when it'd be
                         // safe to take it again.
-                        self.ipc_shmem
-                            .to_plugin()
-                            .send(ShimEventToShim::SyscallDoNative);
+                        match &self.interposer {
+                            Interposer::Shim(ipc_shmem) => {
+                                ipc_shmem.to_plugin().send(ShimEventToShim::SyscallDoNative)
+                            }
+                            Interposer::SeccompNotify {
+                                notifier,
+                                notification,
+                            } => notifier.respond_native(notification.take().unwrap().id),
+                        }
                         self.cleanup_after_exit_initiated();
+                        if let Interposer::SeccompNotify { notifier, .. } = &self.interposer {
+                            notifier.forget_thread(self.native_tid);
+                        }
                         return ResumeResult::ExitedThread(return_code);
                     }
 
+                    let number = syscall.syscall_args.number;
+                    let (pid, tid) = (ctx.process.id(), ctx.thread.id());
+                    Worker::call_hooks(|hooks, host_id, now| {
+                        hooks.syscall_enter(host_id, now, pid, tid, number)
+                    });
+
                     let scr = unsafe {
                         cshadow::syscallhandler_make_syscall(
                             ctx.thread.csyscallhandler(),
@@ -278,6 +396,18 @@ impl ManagedThread {
                     // made.
                     ctx.process.free_unsafe_borrows_flush().unwrap();
 
+                    // syscalls that block will be made again when they're resumed
+                    let retval = match &scr {
+                        SyscallReturn::Block(_) => None,
+                        SyscallReturn::Done(d) => Some(Some(i64::from(d.retval))),
+                        SyscallReturn::Native => Some(None),
+                    };
+                    if let Some(retval) = retval {
+                        Worker::call_hooks(|hooks, host_id, now| {
+                            hooks.syscall_exit(host_id, now, pid, tid, number, retval)
+                        });
+                    }
+
                     match scr {
                         SyscallReturn::Block(b) => {
                             return ResumeResult::Blocked(unsafe {
@@ -285,14 +415,14 @@ impl ManagedThread {
                             })
                         }
                         SyscallReturn::Done(d) => self.continue_plugin(
-                            ctx.host,
+                            ctx,
                             &ShimEventToShim::SyscallComplete(ShimEventSyscallComplete {
                                 retval: d.retval,
                                 restartable: d.restartable,
                             }),
                         ),
                         SyscallReturn::Native => {
-                            self.continue_plugin(ctx.host, &ShimEventToShim::SyscallDoNative)
+                            self.continue_plugin(ctx, &ShimEventToShim::SyscallDoNative)
                         }
                     }
                 }
@@ -304,7 +434,7 @@ impl ManagedThread {
 
                     // Complete the virtualized clone syscall.
                     self.continue_plugin(
-                        ctx.host,
+                        ctx,
                         &ShimEventToShim::SyscallComplete(ShimEventSyscallComplete {
                             retval: 0.into(),
                             restartable: false,
@@ -351,11 +481,19 @@ impl ManagedThread {
         ctid: ForeignPtr<libc::pid_t>,
         newtls: libc::c_ulong,
     ) -> Result<ManagedThread, linux_api::errno::Errno> {
+        if let Interposer::SeccompNotify {
+            notifier,
+            notification,
+        } = &self.interposer
+        {
+            return self.native_clone_notified(flags, notifier, notification);
+        }
+
         let child_ipc_shmem = Arc::new(shadow_shmem::allocator::shmalloc(IPCData::new()));
 
         // Send the IPC block for the new mthread to use.
         let clone_res: i64 = match self.continue_plugin(
-            ctx.host,
+            ctx,
             &ShimEventToShim::AddThreadReq(ShimEventAddThreadReq {
                 ipc_block: child_ipc_shmem.serialize(),
                 flags: flags.bits(),
@@ -412,7 +550,7 @@ impl ManagedThread {
         };
 
         Ok(Self {
-            ipc_shmem: child_ipc_shmem,
+            interposer: Interposer::Shim(child_ipc_shmem),
             is_running: Cell::new(true),
             return_code: Cell::new(None),
             current_event: RefCell::new(start_req),
@@ -423,8 +561,68 @@ impl ManagedThread {
         })
     }
 
+    /// `native_clone` for a thread without the shim, which can only create threads. Instead of
+    /// making a new `clone` syscall, the thread makes the one it's waiting on natively. The
+    /// caller then overwrites the native thread IDs that the kernel wrote.
+    fn native_clone_notified(
+        &self,
+        flags: CloneFlags,
+        notifier: &Arc<SeccompNotifier>,
+        notification: &Cell<Option<Notification>>,
+    ) -> Result<ManagedThread, linux_api::errno::Errno> {
+        // The clone handler rejects new processes, since their syscalls would be sent to the
+        // same listener.
+        assert!(flags.contains(CloneFlags::CLONE_THREAD));
+
+        notifier.respond_native(notification.take().unwrap().id);
+        let Some(first) = notifier.recv_new_thread(self.native_tid) else {
+            // The thread has already been given the native error.
+            debug!("native clone in thread {} failed", self.native_tid);
+            return Err(linux_api::errno::Errno::EAGAIN);
+        };
+        trace!("native clone created tid {}", first.tid);
+
+        Ok(Self {
+            interposer: Interposer::SeccompNotify {
+                notifier: Arc::clone(notifier),
+                notification: Cell::new(Some(first)),
+            },
+            is_running: Cell::new(true),
+            return_code: Cell::new(None),
+            current_event: RefCell::new(syscall_event(&first)),
+            native_pid: self.native_pid,
+            native_tid: first.tid,
+            // TODO: can we assume it's inherited from the current thread affinity?
+            affinity: Cell::new(cshadow::AFFINITY_UNINIT),
+        })
+    }
+
+    /// Copy Shadow's descriptor `fd` into the native process at descriptor number `target_fd`,
+    /// while the native thread waits for its current syscall. Only for threads without the shim,
+    /// which can't open files on Shadow's behalf.
+    pub fn install_native_fd(&self, fd: RawFd, target_fd: RawFd) -> nix::Result<()> {
+        let Interposer::SeccompNotify {
+            notifier,
+            notification,
+        } = &self.interposer
+        else {
+            panic!("Thread {} has the shim", self.native_tid);
+        };
+        let id = notification.get().unwrap().id;
+        notifier.install_fd(id, fd, target_fd)
+    }
+
     #[must_use]
-    fn continue_plugin(&self, host: &Host, event: &ShimEventToShim) -> ShimEventToShadow {
+    fn continue_plugin(&self, ctx: &ThreadContext, event: &ShimEventToShim) -> ShimEventToShadow {
+        let ipc_shmem = match &self.interposer {
+            Interposer::Shim(ipc_shmem) => ipc_shmem,
+            Interposer::SeccompNotify {
+                notifier,
+                notification,
+            } => return self.continue_notified(ctx, notifier, notification, event),
+        };
+        let host = ctx.host;
+
         // Update shared state before transferring control.
         host.shim_shmem_lock_borrow_mut().unwrap().max_runahead_time =
             Worker::max_event_runahead_time(host);
@@ -435,9 +633,9 @@ impl ManagedThread {
         // Release lock so that plugin can take it. Reacquired in `wait_for_next_event`.
         host.unlock_shmem();
 
-        self.ipc_shmem.to_plugin().send(*event);
+        ipc_shmem.to_plugin().send(*event);
 
-        let event = match self.ipc_shmem.from_plugin().receive() {
+        let event = match ipc_shmem.from_plugin().receive() {
             Ok(e) => e,
             Err(SelfContainedChannelError::WriterIsClosed) => ShimEventToShadow::ProcessDeath,
         };
@@ -462,6 +660,169 @@ impl ManagedThread {
         event
     }
 
+    /// `continue_plugin` for a thread without the shim, whose syscall waits on a seccomp
+    /// notification. Since there's no shim to handle emulated signals, they're delivered
+    /// natively here.
+    fn continue_notified(
+        &self,
+        ctx: &ThreadContext,
+        notifier: &SeccompNotifier,
+        notification: &Cell<Option<Notification>>,
+        event: &ShimEventToShim,
+    ) -> ShimEventToShadow {
+        // `native_clone` has already let the thread make its syscall.
+        if let Some(n) = notification.take() {
+            match event {
+                ShimEventToShim::SyscallComplete(res) => {
+                    if self.complete_notified(ctx, notifier, &n, res) == Completion::Rerun {
+                        notification.set(Some(n));
+                        return syscall_event(&n);
+                    }
+                }
+                ShimEventToShim::SyscallDoNative => notifier.respond_native(n.id),
+                e => panic!("Unexpected event for a thread without the shim: {e:?}"),
+            }
+        }
+
+        match notifier.recv(self.native_tid) {
+            Some(n) => {
+                notification.set(Some(n));
+                syscall_event(&n)
+            }
+            None => ShimEventToShadow::ProcessDeath,
+        }
+    }
+
+    /// Answer the syscall of notification `n` with the result of its emulation, and deliver the
+    /// thread's pending signals, as the shim would have.
+    fn complete_notified(
+        &self,
+        ctx: &ThreadContext,
+        notifier: &SeccompNotifier,
+        n: &Notification,
+        res: &ShimEventSyscallComplete,
+    ) -> Completion {
+        let (signals, handler_runs) = self.take_native_signals(ctx);
+        let retval = i64::from(res.retval);
+        let interrupted = retval == linux_api::errno::Errno::EINTR.to_negated_i64();
+
+        if interrupted && res.restartable && !handler_runs {
+            // Like the shim, restart a syscall that wasn't interrupted by a handler.
+            self.send_native_signals(&signals);
+            return Completion::Rerun;
+        }
+
+        let wait_killable = notifier.signals_wait_for_response();
+        let native = (retval == 0 && MIRRORED_SIGNAL_SYSCALLS.contains(&n.number))
+            // The native syscall unblocks the signal for the handler, and then fails with `EINTR`.
+            || (interrupted && handler_runs && n.number == libc::SYS_rt_sigsuspend);
+        let respond = || {
+            if native {
+                notifier.respond_native(n.id);
+            } else if interrupted && handler_runs && res.restartable && wait_killable {
+                // The kernel restarts the syscall after the handler if it has `SA_RESTART`, and
+                // otherwise fails it with `EINTR`.
+                notifier.respond_interrupted(n.id);
+            } else {
+                notifier.respond(n.id, retval);
+            }
+        };
+
+        // A thread that isn't waiting killably would have its syscall interrupted by the signal,
+        // losing the result, so the signals are sent after it returns.
+        if wait_killable {
+            self.send_native_signals(&signals);
+            respond();
+        } else {
+            respond();
+            self.send_native_signals(&signals);
+        }
+
+        Completion::Answered
+    }
+
+    /// Take the thread's pending emulated signals that it doesn't block, and that should be
+    /// delivered natively. Also returns whether any of them will run a handler, rather than
+    /// kill the process.
+    ///
+    /// The native signal actions and mask mirror the emulated ones, so the kernel then does what
+    /// `shim::signals::process_signals` does.
+    fn take_native_signals(&self, ctx: &ThreadContext) -> (Vec<(Signal, siginfo_t)>, bool) {
+        let host_lock = ctx.host.shim_shmem_lock_borrow().unwrap();
+        let process_shmem = ctx.process.shmem();
+        let thread_shmem = ctx.thread.shmem();
+
+        let mut signals = Vec::new();
+        let mut handler_runs = false;
+        while let Some((signal, siginfo)) =
+            shim_shmem::take_pending_unblocked_signal(&host_lock, &process_shmem, thread_shmem)
+        {
+            let mut process_protected = process_shmem.protected.borrow_mut(&host_lock.root);
+            // SAFETY: We don't call the handler.
+            let action = unsafe { process_protected.signal_action_mut(signal) };
+            match unsafe { action.handler() } {
+                SignalHandler::SigIgn => continue,
+                SignalHandler::SigDfl => match defaultaction(signal) {
+                    LinuxDefaultAction::IGN
+                    | LinuxDefaultAction::STOP
+                    | LinuxDefaultAction::CONT => continue,
+                    LinuxDefaultAction::CORE | LinuxDefaultAction::TERM => (),
+                },
+                SignalHandler::Handler(_) | SignalHandler::Action(_) => {
+                    handler_runs = true;
+                    // The kernel resets the native action when it delivers the signal.
+                    if action.flags_retain().contains(SigActionFlags::SA_RESETHAND) {
+                        *action = sigaction::new_raw(
+                            SignalHandler::SigDfl,
+                            SigActionFlags::empty(),
+                            sigset_t::EMPTY,
+                            None,
+                        );
+                    }
+                }
+            }
+            signals.push((signal, siginfo));
+        }
+
+        // A syscall such as `ppoll` that was interrupted leaves its temporary signal mask in
+        // place so that the signals above are taken with it.
+        thread_shmem
+            .protected
+            .borrow_mut(&host_lock.root)
+            .restore_blocked_signals();
+
+        (signals, handler_runs)
+    }
+
+    /// Send `signals` to the native thread.
+    fn send_native_signals(&self, signals: &[(Signal, siginfo_t)]) {
+        let (pid, tid) = (self.native_pid.as_raw(), self.native_tid.as_raw());
+        for (signal, siginfo) in signals {
+            trace!("Delivering emulated signal {signal:?} natively to {pid}.{tid}");
+            let res = Errno::result(unsafe {
+                libc::syscall(
+                    libc::SYS_rt_tgsigqueueinfo,
+                    pid,
+                    tid,
+                    signal.as_i32(),
+                    siginfo as *const siginfo_t,
+                )
+            })
+            .or_else(|e| {
+                // We may only send our own `siginfo_t` if it claims to be from user space, rather
+                // than e.g. from `kill` or the kernel.
+                if e != Errno::EPERM {
+                    return Err(e);
+                }
+                Errno::result(unsafe { libc::syscall(libc::SYS_tgkill, pid, tid, signal.as_i32()) })
+            });
+            if let Err(e) = res {
+                // The thread may have been killed by a previous signal.
+                debug!("Couldn't send signal {signal:?} to {pid}.{tid}: {e}");
+            }
+        }
+    }
+
     /// To be called after we expect the native thread to have exited, or to
     /// exit imminently.
     fn cleanup_after_exit_initiated(&self) {
@@ -486,7 +847,11 @@ impl ManagedThread {
         // though. In practice this shouldn't have to loop significantly.
         trace!("Waiting for native thread {native_pid}.{native_tid} to exit");
         loop {
-            if self.ipc_shmem.from_plugin().writer_is_closed() {
+            let process_exited = match &self.interposer {
+                Interposer::Shim(ipc_shmem) => ipc_shmem.from_plugin().writer_is_closed(),
+                Interposer::SeccompNotify { notifier, .. } => notifier.has_exited(),
+            };
+            if process_exited {
                 // This indicates that the whole process has stopped executing;
                 // no need to poll the individual thread.
                 break;
@@ -541,42 +906,31 @@ impl ManagedThread {
 
     fn spawn_native(
         plugin_path: &CStr,
+        interpreter: Option<&CStr>,
         argv: Vec<CString>,
         envv: Vec<CString>,
         strace_file: Option<&std::fs::File>,
         shimlog_file: &std::fs::File,
         shmem_block: &ShMemBlock<IPCData>,
     ) -> nix::Result<nix::unistd::Pid> {
-        // Preemptively check for likely reasons that execve might fail.
-        // In particular we want to ensure that we  don't launch a statically
-        // linked executable, since we'd then deadlock the whole simulation
-        // waiting for the plugin to initialize.
-        //
-        // This is also helpful since we can't retrieve specific `execve` errors
-        // through `posix_spawn`.
-        verify_plugin_path(std::ffi::OsStr::from_bytes(plugin_path.to_bytes())).map_err(|e| {
-            debug!("Failed to verify path {plugin_path:?}");
-            match e {
-                // execve(2): ENOENT The file pathname [...] does not exist.
-                VerifyPluginPathError::NotFound => Errno::ENOENT,
-                // execve(2): EACCES The file or a script interpreter is not a regular file.
-                VerifyPluginPathError::NotFile => Errno::EACCES,
-                // execve(2): EACCES Execute permission is denied for the file or a script or ELF interpreter.
-                VerifyPluginPathError::NotExecutable => Errno::EACCES,
-                // execve(2): ENOEXEC An executable is not in a recognized
-                // format, is for the wrong architecture, or has some other
-                // format error that means it cannot be executed.
-                VerifyPluginPathError::NotDynamicallyLinkedElf => Errno::ENOEXEC,
-                // execve(2): EACCES Search permission is denied on a component
-                // of the path prefix of pathname or the name of a script
-                // interpreter.
-                VerifyPluginPathError::PathPermissionDenied => Errno::EACCES,
-                VerifyPluginPathError::UnhandledIoError(_) => {
-                    // Arbitrary error that should be handled by callers.
-                    Errno::ENOEXEC
-                }
+        // The interpreter is run with the executable's path as its first argument, and passes the
+        // remaining arguments to the executable. The executable's `argv[0]` is the path that the
+        // interpreter was given.
+        let (exec_path, argv) = match interpreter {
+            Some(interpreter) => {
+                verify_executable_path(std::ffi::OsStr::from_bytes(interpreter.to_bytes()))
+                    .map_err(|e| {
+                        debug!("Failed to verify interpreter path {interpreter:?}");
+                        verify_error_to_errno(e)
+                    })?;
+                let argv: Vec<CString> = [interpreter.to_owned(), plugin_path.to_owned()]
+                    .into_iter()
+                    .chain(argv.into_iter().skip(1))
+                    .collect();
+                (interpreter, argv)
             }
-        })?;
+            None => (plugin_path, argv),
+        };
 
         // posix_spawn is documented as taking pointers to *mutable* char for argv and
         // envv. It *probably* doesn't actually mutate them, but we
@@ -682,7 +1036,7 @@ impl ManagedThread {
             Errno::result(unsafe {
                 libc::posix_spawn(
                     &mut child_pid,
-                    plugin_path.as_ptr(),
+                    exec_path.as_ptr(),
                     &file_actions,
                     &spawn_attr,
                     argv_ptrs.as_ptr(),
@@ -772,6 +1126,34 @@ impl Drop for ManagedThread {
     }
 }
 
+/// How the syscall of a thread without the shim was completed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Completion {
+    /// The thread was given a result.
+    Answered,
+    /// The syscall should be emulated again, since it was interrupted without running a signal
+    /// handler.
+    Rerun,
+}
+
+/// Syscalls that change the signal actions or mask. In threads without the shim, the kernel
+/// delivers signals, so it also makes these syscalls after they've been emulated successfully.
+const MIRRORED_SIGNAL_SYSCALLS: [i64; 3] = [
+    libc::SYS_rt_sigaction,
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_sigaltstack,
+];
+
+/// The event for the syscall of a thread without the shim.
+fn syscall_event(n: &Notification) -> ShimEventToShadow {
+    ShimEventToShadow::Syscall(ShimEventSyscall {
+        syscall_args: SysCallArgs {
+            number: n.number,
+            args: n.args.map(SysCallReg::from),
+        },
+    })
+}
+
 fn tgkill(
     pid: nix::unistd::Pid,
     tid: nix::unistd::Pid,
@@ -788,3 +1170,28 @@ fn tgkill(
         assert_eq!(i, 0);
     })
 }
+
+/// The error that `execve` would return for a path that failed verification.
+fn verify_error_to_errno(e: VerifyPluginPathError) -> Errno {
+    match e {
+        // execve(2): ENOENT The file pathname [...] does not exist.
+        VerifyPluginPathError::NotFound => Errno::ENOENT,
+        // execve(2): EACCES The file or a script interpreter is not a regular file.
+        VerifyPluginPathError::NotFile => Errno::EACCES,
+        // execve(2): EACCES Execute permission is denied for the file or a script or ELF interpreter.
+        VerifyPluginPathError::NotExecutable => Errno::EACCES,
+        // execve(2): ENOEXEC An executable is not in a recognized
+        // format, is for the wrong architecture, or has some other
+        // format error that means it cannot be executed.
+        VerifyPluginPathError::NotDynamicallyLinkedElf => Errno::ENOEXEC,
+        VerifyPluginPathError::Elf32 => Errno::ENOEXEC,
+        // execve(2): EACCES Search permission is denied on a component
+        // of the path prefix of pathname or the name of a script
+        // interpreter.
+        VerifyPluginPathError::PathPermissionDenied => Errno::EACCES,
+        VerifyPluginPathError::UnhandledIoError(_) => {
+            // Arbitrary error that should be handled by callers.
+            Errno::ENOEXEC
+        }
+    }
+}
@@ -190,6 +190,13 @@ impl ShmFile {
     }
 }
 
+/// Round `len` up to a multiple of the page size, as the kernel does with the lengths passed to the
+/// mman syscalls. Returns `None` if the result would overflow, which the kernel rejects, so this
+/// can only fail for lengths that haven't already been accepted by a native syscall.
+fn page_align(len: usize) -> Option<usize> {
+    Some(len.checked_add(page_size() - 1)? & !(page_size() - 1))
+}
+
 /// Get the current mapped regions of the process.
 fn get_regions(pid: Pid) -> IntervalMap<Region> {
     let mut regions = IntervalMap::new();
@@ -549,7 +556,7 @@ impl MemoryMapper {
             return;
         }
         let addr = usize::from(ptr.ptr());
-        let interval = addr..(addr + ptr.len());
+        let interval = addr..(addr + page_align(ptr.len()).unwrap());
         let is_anonymous = flags & libc::MAP_ANONYMOUS != 0;
         let sharing = if flags & libc::MAP_PRIVATE != 0 {
             Sharing::Private
@@ -587,9 +594,9 @@ impl MemoryMapper {
             self.shm_file.mmap_into_plugin(ctx, &interval, prot);
         }
 
-        // TODO: We *could* handle file mappings and some shared mappings as well. Doesn't make
-        // sense to add that complexity until if/when we see a lot of misses in such regions,
-        // though.
+        // File mappings and shared mappings must stay native mappings in the plugin. Copying them
+        // into the shared memory file would make them stale with respect to the file's page cache,
+        // which Shadow's `read` and `write` syscalls and the mappings in other processes use.
 
         {
             // There shouldn't be any mutations here; we already cleared a hole above.
@@ -610,7 +617,7 @@ impl MemoryMapper {
 
         // Clear out metadata and mappings for anything unmapped.
         let start = usize::from(addr);
-        let end = start + length;
+        let end = start + page_align(length).unwrap();
         let mutations = self.regions.clear(start..end);
         self.unmap_mutations(mutations);
     }
@@ -633,6 +640,9 @@ impl MemoryMapper {
             let (ctx, thread) = ctx.split_thread();
             thread.native_mremap(&ctx, old_address, old_size, new_size, flags, new_address)?
         };
+        // The kernel rounds the sizes up to a multiple of the page size.
+        let old_size = page_align(old_size).unwrap();
+        let new_size = page_align(new_size).unwrap();
         let old_interval = usize::from(old_address)..(usize::from(old_address) + old_size);
         let new_interval = usize::from(new_address)..(usize::from(new_address) + new_size);
 
@@ -655,22 +665,25 @@ impl MemoryMapper {
             return Ok(new_address);
         }
 
-        // Clear and retrieve the old mapping.
-        // For the remap to have succeeded, it should have corresponded exactly to an old mapping.
-        let mut region = {
-            let mut mutations = self.regions.clear(old_interval.clone());
-            assert_eq!(mutations.len(), 1);
-            if let Some(Mutation::Removed(removed_interval, region)) = mutations.pop() {
-                assert_eq!(removed_interval, old_interval);
-                region
-            } else {
-                panic!("Unexpected mutation {:?}", mutations[0])
+        // The old range may be only part of a region, or may span adjacent regions that the kernel
+        // merged into a single mapping. Either way the mapping has the properties of the region at
+        // its start.
+        let mut region = self.regions.get(old_interval.start).unwrap().1.clone();
+
+        if new_interval.start == old_interval.start {
+            if new_size < old_size {
+                // Shrunk in place, which is the same as unmapping the end.
+                let mutations = self.regions.clear(new_interval.end..old_interval.end);
+                self.unmap_mutations(mutations);
+            } else if new_size > old_size {
+                self.handle_mremap_grow_in_place(&old_interval, &new_interval);
             }
-        };
+            return Ok(new_address);
+        }
 
-        // Clear any mappings that are about to be overwritten by the new mapping. We have to do
-        // this *before* potentially mapping the new region into Shadow, so that we don't end up
-        // freeing space for that new mapping.
+        // Clear any mappings that were overwritten by the new mapping. We have to do this *before*
+        // potentially mapping the new region into Shadow, so that we don't end up freeing space for
+        // that new mapping. Note that mremap(2) should have failed if the ranges overlap.
         {
             let mutations = self.regions.clear(new_interval.clone());
             self.unmap_mutations(mutations);
@@ -681,64 +694,44 @@ impl MemoryMapper {
             // implementing mremap for stack or heap regions for now; that'd be pretty weird.
             assert_eq!(region.original_path, None);
 
-            if new_interval.start != old_interval.start {
-                // region has moved
-
-                // Note that mremap(2) should have failed if the regions overlap.
-                assert!(!new_interval.contains(&old_interval.start));
-                assert!(!old_interval.contains(&new_interval.start));
-
-                // Ensure there's space allocated at the new location in the memory file.
-                self.shm_file.alloc(&new_interval);
-
-                // Remap the region in the child to the new position in the mem file.
-                self.shm_file
-                    .mmap_into_plugin(ctx, &new_interval, region.prot);
-
-                // Map the new location into Shadow.
-                let new_shadow_base = self.shm_file.mmap_into_shadow(&new_interval, region.prot);
-
-                // Copy the data.
-                unsafe {
-                    libc::memcpy(
-                        new_shadow_base,
-                        region.shadow_base,
-                        std::cmp::min(old_size, new_size),
-                    )
-                };
+            // Ensure there's space allocated at the new location in the memory file.
+            self.shm_file.alloc(&new_interval);
+
+            // Map the new location into Shadow.
+            let new_shadow_base = self.shm_file.mmap_into_shadow(&new_interval, region.prot);
+
+            // The plugin's moved mapping still refers to the old location in the memory file, so
+            // copy the data from there.
+            let old_shadow_base = self
+                .shm_file
+                .mmap_into_shadow(&old_interval, libc::PROT_READ);
+            unsafe {
+                libc::memcpy(
+                    new_shadow_base,
+                    old_shadow_base,
+                    std::cmp::min(old_size, new_size),
+                )
+            };
+            unsafe { sys::mman::munmap(old_shadow_base, old_size) }
+                .unwrap_or_else(|e| warn!("munmap: {}", e));
 
-                // Unmap the old location from Shadow.
-                unsafe { sys::mman::munmap(region.shadow_base, old_size) }
-                    .unwrap_or_else(|e| warn!("munmap: {}", e));
+            // Remap the region in the child to the new position in the mem file.
+            self.shm_file
+                .mmap_into_plugin(ctx, &new_interval, region.prot);
 
-                // Update the region metadata.
-                region.shadow_base = new_shadow_base;
+            region.shadow_base = new_shadow_base;
+        }
 
-                // Deallocate the old location.
+        if flags & libc::MREMAP_DONTUNMAP != 0 {
+            // The old range stays mapped, but its anonymous pages are now empty.
+            if !region.shadow_base.is_null() {
                 self.shm_file.dealloc(&old_interval);
-            } else if new_size < old_size {
-                // Deallocate the part no longer in use.
-                self.shm_file.dealloc(&(new_interval.end..old_interval.end));
-
-                // Shrink Shadow's mapping.
-                // TODO: use nix wrapper once it exists. https://github.com/nix-rust/nix/issues/1295
-                assert_ne!(
-                    unsafe { libc::mremap(region.shadow_base, old_size, new_size, 0) },
-                    libc::MAP_FAILED
-                );
-            } else if new_size > old_size {
-                // Allocate space in the file.
-                self.shm_file.alloc(&new_interval);
-
-                // Grow Shadow's mapping into the memory file, allowing the mapping to move if
-                // needed.
-                // TODO: use nix wrapper once it exists. https://github.com/nix-rust/nix/issues/1295
-                region.shadow_base = unsafe {
-                    libc::mremap(region.shadow_base, old_size, new_size, libc::MREMAP_MAYMOVE)
-                };
-                assert_ne!(region.shadow_base, libc::MAP_FAILED);
             }
+        } else {
+            let mutations = self.regions.clear(old_interval);
+            self.unmap_mutations(mutations);
         }
+
         // Insert the new mapping. There shouldn't be any mutations since we already cleared
         // this interval, above.
         let mutations = self.regions.insert(new_interval, region);
@@ -747,6 +740,43 @@ impl MemoryMapper {
         Ok(new_address)
     }
 
+    /// Update our mappings after the plugin's mapping at `old_interval` was grown in place to
+    /// `new_interval`, into space that was previously unmapped.
+    fn handle_mremap_grow_in_place(&mut self, old_interval: &Interval, new_interval: &Interval) {
+        let added = old_interval.end..new_interval.end;
+        {
+            let mutations = self.regions.clear(added.clone());
+            self.unmap_mutations(mutations);
+        }
+
+        // The mapping grows the last region in the old range.
+        let (last_interval, last_region) = self.regions.get(old_interval.end - 1).unwrap();
+        let mut region = last_region.clone();
+
+        if !region.shadow_base.is_null() {
+            // Allocate space in the file. The plugin's mapping already refers to it, since it's
+            // at the same offset as the added addresses.
+            self.shm_file.alloc(&added);
+
+            // Grow Shadow's mapping into the memory file, allowing the mapping to move if
+            // needed.
+            // TODO: use nix wrapper once it exists. https://github.com/nix-rust/nix/issues/1295
+            region.shadow_base = unsafe {
+                libc::mremap(
+                    region.shadow_base,
+                    last_interval.len(),
+                    last_interval.len() + added.len(),
+                    libc::MREMAP_MAYMOVE,
+                )
+            };
+            assert_ne!(region.shadow_base, libc::MAP_FAILED);
+        }
+
+        // This replaces the last region, whose mapping into Shadow (if any) we already moved.
+        let mutations = self.regions.insert(last_interval.start..added.end, region);
+        assert_eq!(mutations.len(), 1);
+    }
+
     /// Execute the requested `brk` and update our mappings accordingly. May invalidate outstanding
     /// pointers. (Rust won't allow mutable methods such as this one to be called with outstanding
     /// borrowed references).
@@ -893,7 +923,7 @@ impl MemoryMapper {
         // regions.
         let mutations = self
             .regions
-            .clear(usize::from(addr)..(usize::from(addr) + size));
+            .clear(usize::from(addr)..(usize::from(addr) + page_align(size).unwrap()));
         for mutation in mutations {
             match mutation {
                 Mutation::ModifiedBegin(interval, new_start) => {
@@ -1003,6 +1033,92 @@ impl MemoryMapper {
         Ok(0)
     }
 
+    /// Shadow should delegate a plugin's call to madvise to this method.
+    ///
+    /// Executes the actual madvise operation in the plugin. Memory that we've remapped into the
+    /// shared memory file is a shared mapping as far as the kernel is concerned, but the plugin
+    /// expects the advice to behave as it would for a private anonymous mapping. In particular,
+    /// `MADV_DONTNEED` wouldn't zero the memory, and `MADV_FREE` isn't allowed at all.
+    pub fn handle_madvise(
+        &mut self,
+        ctx: &ThreadContext,
+        addr: ForeignPtr<u8>,
+        length: usize,
+        advice: i32,
+    ) -> Result<(), Errno> {
+        let (ctx, thread) = ctx.split_thread();
+        trace!("madvise({:?}, {}, {})", addr, length, advice);
+
+        let start = usize::from(addr);
+        if start % page_size() != 0 {
+            return Err(Errno::EINVAL);
+        }
+        let end = page_align(length)
+            .and_then(|length| start.checked_add(length))
+            .ok_or(Errno::EINVAL)?;
+        let remapped = self.remapped_intervals(&(start..end));
+        let all_remapped =
+            !remapped.is_empty() && remapped.iter().map(|x| x.len()).sum::<usize>() == end - start;
+
+        match advice {
+            libc::MADV_FREE if !remapped.is_empty() => {
+                // The kernel doesn't allow `MADV_FREE` for the shared memory file. The pages of a
+                // private anonymous mapping may be freed immediately with `MADV_FREE`, so we use
+                // `MADV_DONTNEED` for the remapped parts of the range and `MADV_FREE` for the
+                // rest.
+                let mut pos = start;
+                for interval in remapped.iter().chain(std::iter::once(&(end..end))) {
+                    if pos < interval.start {
+                        let ptr = ForeignPtr::from(pos).cast::<u8>();
+                        thread.native_madvise(&ctx, ptr, interval.start - pos, libc::MADV_FREE)?;
+                    }
+                    if !interval.is_empty() {
+                        let ptr = ForeignPtr::from(interval.start).cast::<u8>();
+                        thread.native_madvise(&ctx, ptr, interval.len(), libc::MADV_DONTNEED)?;
+                    }
+                    pos = interval.end;
+                }
+
+                // Free the pages in the file, so that the memory reads as zeros afterwards.
+                for interval in &remapped {
+                    self.shm_file.dealloc(interval);
+                }
+            }
+            libc::MADV_DONTNEED => {
+                thread.native_madvise(&ctx, addr, length, advice)?;
+
+                // Free the pages in the file, so that the memory reads as zeros afterwards.
+                for interval in &remapped {
+                    self.shm_file.dealloc(interval);
+                }
+            }
+            libc::MADV_HUGEPAGE | libc::MADV_NOHUGEPAGE => {
+                match thread.native_madvise(&ctx, addr, length, advice) {
+                    // The kernel may not support huge pages for the shared memory file, even if
+                    // it does for private anonymous mappings. The advice is only a hint, so
+                    // accept it anyway.
+                    Err(Errno::EINVAL) if all_remapped => {}
+                    rv => rv?,
+                }
+            }
+            _ => thread.native_madvise(&ctx, addr, length, advice)?,
+        }
+
+        Ok(())
+    }
+
+    /// The parts of `interval` that have been remapped into Shadow.
+    fn remapped_intervals(&self, interval: &Interval) -> Vec<Interval> {
+        self.regions
+            .iter_from(interval.start)
+            .take_while(|(x, _)| x.start < interval.end)
+            .filter(|(_, region)| !region.shadow_base.is_null())
+            .map(|(x, _)| {
+                std::cmp::max(x.start, interval.start)..std::cmp::min(x.end, interval.end)
+            })
+            .collect()
+    }
+
     // Get a raw pointer to the plugin's memory, if it's been remapped into Shadow.
     // Panics if called with zero-length `src`.
     fn get_mapped_ptr<T: Pod + Debug>(&self, src: ForeignArrayPtr<T>) -> Option<*mut T> {
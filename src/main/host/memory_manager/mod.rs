@@ -270,7 +270,7 @@ where
     }
 }
 
-fn page_size() -> usize {
+pub(crate) fn page_size() -> usize {
     nix::unistd::sysconf(nix::unistd::SysconfVar::PAGE_SIZE)
         .unwrap()
         .unwrap()
@@ -705,6 +705,19 @@ impl MemoryManager {
             None => Err(SyscallError::Native),
         }
     }
+
+    pub fn handle_madvise(
+        &mut self,
+        ctx: &ThreadContext,
+        addr: ForeignPtr<u8>,
+        length: usize,
+        advice: i32,
+    ) -> Result<(), SyscallError> {
+        match &mut self.memory_mapper {
+            Some(mm) => Ok(mm.handle_madvise(ctx, addr, length, advice)?),
+            None => Err(SyscallError::Native),
+        }
+    }
 }
 
 /// Memory allocated by Shadow, in a remote address space.
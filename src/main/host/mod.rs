@@ -1,15 +1,28 @@
+pub mod app_metrics;
 pub mod context;
 pub mod cpu;
+pub mod credentials;
 pub mod descriptor;
 #[allow(clippy::module_inception)]
 pub mod host;
+pub mod load_balancer;
 pub mod managed_thread;
 pub mod memory_manager;
+pub mod native_app;
 pub mod network;
+pub mod persistent_state;
+pub mod probe;
 pub mod process;
+pub mod quota;
+pub mod replay;
+pub mod resolver;
+pub mod seed_node;
 pub mod status_listener;
 pub mod syscall;
 pub mod syscall_condition;
 pub mod syscall_types;
+pub mod syslog;
 pub mod thread;
+pub mod thread_id_allocator;
 pub mod timer;
+pub mod tmpfs;
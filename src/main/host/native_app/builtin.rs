@@ -0,0 +1,173 @@
+//! Native apps that are included with Shadow. These are small, but are useful for generating
+//! traffic and as examples of the [`ShadowApp`] interface.
+
+use std::net::SocketAddrV4;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use super::{AppContext, ShadowApp, SocketId};
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, name: &str) -> anyhow::Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    let arg = args
+        .get(index)
+        .ok_or_else(|| anyhow::anyhow!("Missing the '{name}' argument"))?;
+    arg.parse()
+        .map_err(|e| anyhow::anyhow!("Invalid '{name}' argument '{arg}': {e}"))
+}
+
+/// Sends every datagram that it receives on a port back to its sender.
+///
+/// Arguments: `<port>`
+pub struct UdpEcho {
+    port: u16,
+}
+
+impl UdpEcho {
+    pub fn create(args: &[String]) -> anyhow::Result<Box<dyn ShadowApp>> {
+        if args.len() != 1 {
+            return Err(anyhow::anyhow!("Expected the arguments '<port>'"));
+        }
+        Ok(Box::new(Self {
+            port: parse_arg(args, 0, "port")?,
+        }))
+    }
+}
+
+impl ShadowApp for UdpEcho {
+    fn start(&mut self, ctx: &mut AppContext) {
+        // the socket's datagrams are passed to `on_datagram`, so we don't need to keep its id
+        if let Err(e) = ctx.bind_udp(self.port) {
+            log::warn!("udp-echo could not start: {e}");
+        }
+    }
+
+    fn on_datagram(
+        &mut self,
+        ctx: &mut AppContext,
+        socket: SocketId,
+        src: SocketAddrV4,
+        payload: &[u8],
+    ) {
+        if let Err(e) = ctx.send_to(socket, src, payload) {
+            log::debug!("udp-echo dropped a reply to {src}: {e}");
+        }
+    }
+}
+
+/// The length of a ping: its sequence number and the time that it was sent.
+const PING_LEN: usize = 16;
+
+/// Sends a number of pings to a host that runs [`UdpEcho`] and logs the round-trip time of each
+/// reply.
+///
+/// Arguments: `<host> <port> <count> <interval in milliseconds>`
+pub struct UdpPing {
+    target: String,
+    port: u16,
+    count: u64,
+    interval: SimulationTime,
+    dst: Option<SocketAddrV4>,
+    socket: Option<SocketId>,
+    sent: u64,
+    received: u64,
+}
+
+impl UdpPing {
+    pub fn create(args: &[String]) -> anyhow::Result<Box<dyn ShadowApp>> {
+        if args.len() != 4 {
+            return Err(anyhow::anyhow!(
+                "Expected the arguments '<host> <port> <count> <interval in milliseconds>'"
+            ));
+        }
+        let interval: u64 = parse_arg(args, 3, "interval")?;
+        if interval == 0 {
+            return Err(anyhow::anyhow!(
+                "The 'interval' argument must be at least 1"
+            ));
+        }
+        Ok(Box::new(Self {
+            target: args[0].clone(),
+            port: parse_arg(args, 1, "port")?,
+            count: parse_arg(args, 2, "count")?,
+            interval: SimulationTime::from_millis(interval),
+            dst: None,
+            socket: None,
+            sent: 0,
+            received: 0,
+        }))
+    }
+
+    fn send_ping(&mut self, ctx: &mut AppContext) {
+        let (Some(socket), Some(dst)) = (self.socket, self.dst) else {
+            return;
+        };
+
+        let sent_at = ctx.now().duration_since(&EmulatedTime::SIMULATION_START);
+        let mut ping = [0; PING_LEN];
+        ping[..8].copy_from_slice(&self.sent.to_be_bytes());
+        ping[8..].copy_from_slice(&u64::try_from(sent_at.as_nanos()).unwrap().to_be_bytes());
+        if let Err(e) = ctx.send_to(socket, dst, &ping) {
+            log::debug!("udp-ping dropped ping {} to {dst}: {e}", self.sent);
+        }
+        self.sent += 1;
+
+        if self.sent < self.count {
+            ctx.set_timer(self.interval, 0);
+        }
+    }
+}
+
+impl ShadowApp for UdpPing {
+    fn start(&mut self, ctx: &mut AppContext) {
+        let Some(ip) = ctx.resolve(&self.target) else {
+            log::warn!("udp-ping could not resolve host '{}'", self.target);
+            return;
+        };
+        self.dst = Some(SocketAddrV4::new(ip, self.port));
+        match ctx.bind_udp(0) {
+            Ok(socket) => self.socket = Some(socket),
+            Err(e) => {
+                log::warn!("udp-ping could not start: {e}");
+                return;
+            }
+        }
+        if self.count > 0 {
+            self.send_ping(ctx);
+        }
+    }
+
+    fn on_timer(&mut self, ctx: &mut AppContext, _token: u64) {
+        self.send_ping(ctx);
+    }
+
+    fn on_datagram(
+        &mut self,
+        ctx: &mut AppContext,
+        _socket: SocketId,
+        src: SocketAddrV4,
+        payload: &[u8],
+    ) {
+        let Ok(ping) = <[u8; PING_LEN]>::try_from(payload) else {
+            return;
+        };
+        let seq = u64::from_be_bytes(ping[..8].try_into().unwrap());
+        let sent_at = u64::from_be_bytes(ping[8..].try_into().unwrap());
+        let sent_at = EmulatedTime::SIMULATION_START + SimulationTime::from_nanos(sent_at);
+
+        self.received += 1;
+        let rtt = ctx.now().saturating_duration_since(&sent_at);
+        log::info!("udp-ping reply {seq} from {src}: rtt {rtt:?}");
+    }
+
+    fn stop(&mut self) {
+        log::info!(
+            "udp-ping sent {} pings and received {} replies",
+            self.sent,
+            self.received
+        );
+    }
+}
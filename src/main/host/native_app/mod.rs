@@ -0,0 +1,279 @@
+//! Applications that are compiled into Shadow and run in its process, driven directly by the
+//! events of their host. Native apps skip the machinery that managed processes need (the shim,
+//! seccomp, and ptrace), so they're much cheaper to run, but they can only interact with the
+//! simulation through [`AppContext`] rather than through Linux syscalls. They can currently only
+//! use UDP sockets.
+//!
+//! To add an app, implement [`ShadowApp`] for it and add its factory to [`APPS`]. Hosts start apps
+//! by name with their `native_apps` option. Apps can't be loaded from shared libraries, since Rust
+//! trait objects don't have a stable ABI.
+
+use std::ffi::CString;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+
+use atomic_refcell::AtomicRefCell;
+use bytes::Bytes;
+use linux_api::errno::Errno;
+use rand::RngCore;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::sim_config::NativeApp as NativeAppConfig;
+use crate::core::work::task::TaskRef;
+use crate::core::worker::Worker;
+use crate::host::descriptor::socket::inet::udp::UdpSocket;
+use crate::host::descriptor::{FileState, FileStatus, StateListenerFilter};
+use crate::host::host::Host;
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrStorage;
+
+pub mod builtin;
+
+/// Creates an app from its arguments, returning an error if the arguments are invalid.
+pub type AppFactory = fn(args: &[String]) -> anyhow::Result<Box<dyn ShadowApp>>;
+
+/// The apps that hosts can run, by name.
+pub const APPS: &[(&str, AppFactory)] = &[
+    ("udp-echo", builtin::UdpEcho::create),
+    ("udp-ping", builtin::UdpPing::create),
+];
+
+/// Create the app named `name` with the arguments `args`.
+pub fn create(name: &str, args: &[String]) -> anyhow::Result<Box<dyn ShadowApp>> {
+    let Some((_, factory)) = APPS.iter().find(|(x, _)| *x == name) else {
+        let names: Vec<_> = APPS.iter().map(|(x, _)| *x).collect();
+        return Err(anyhow::anyhow!(
+            "There is no native app named '{name}'. The available apps are: {}",
+            names.join(", ")
+        ));
+    };
+    factory(args)
+}
+
+/// An application that runs in Shadow's process. The methods are called when the app's host
+/// processes the corresponding events, at the simulated time of those events.
+pub trait ShadowApp: Send {
+    /// Called once, at the app's start time.
+    fn start(&mut self, ctx: &mut AppContext);
+
+    /// Called for each datagram received by one of the app's sockets.
+    fn on_datagram(
+        &mut self,
+        _ctx: &mut AppContext,
+        _socket: SocketId,
+        _src: SocketAddrV4,
+        _payload: &[u8],
+    ) {
+    }
+
+    /// Called when a timer set with [`AppContext::set_timer`] expires.
+    fn on_timer(&mut self, _ctx: &mut AppContext, _token: u64) {}
+
+    /// Called when the host shuts down, after the app's sockets have been closed.
+    fn stop(&mut self) {}
+}
+
+/// Identifies one of an app's sockets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SocketId(usize);
+
+/// The interface that an app uses to interact with its host.
+pub struct AppContext<'a> {
+    host: &'a Host,
+    inner: &'a Arc<Inner>,
+}
+
+impl AppContext<'_> {
+    /// The current simulated time.
+    pub fn now(&self) -> EmulatedTime {
+        Worker::current_time().unwrap()
+    }
+
+    pub fn host_name(&self) -> &str {
+        self.host.name()
+    }
+
+    /// The host's default IP address.
+    pub fn ip(&self) -> Ipv4Addr {
+        self.host.default_ip()
+    }
+
+    /// Resolve the name of a host to its IP address.
+    pub fn resolve(&self, name: &str) -> Option<Ipv4Addr> {
+        Worker::resolve_name_to_ip(&CString::new(name).ok()?)
+    }
+
+    /// Fill `buf` with bytes from the host's deterministic random number generator.
+    pub fn fill_random(&self, buf: &mut [u8]) {
+        self.host.random_mut().fill_bytes(buf);
+    }
+
+    /// Open a UDP socket bound to `port` of the host's default address, or to an ephemeral port if
+    /// `port` is 0.
+    pub fn bind_udp(&mut self, port: u16) -> std::io::Result<SocketId> {
+        let socket = UdpSocket::new(
+            FileStatus::empty(),
+            self.host.params.init_sock_send_buf_size.try_into().unwrap(),
+            self.host.params.init_sock_recv_buf_size.try_into().unwrap(),
+        );
+
+        let addr = SocketAddrV4::new(self.ip(), port);
+        UdpSocket::bind(
+            &socket,
+            Some(&SockaddrStorage::from(addr)),
+            &self.host.network_namespace_borrow(),
+            &mut *self.host.random_mut(),
+        )
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("Could not bind the socket to {addr}: {e:?}"),
+            )
+        })?;
+
+        let mut sockets = self.inner.sockets.lock().unwrap();
+        let id = SocketId(sockets.len());
+        let handle = {
+            let weak_inner = Arc::downgrade(self.inner);
+            socket.borrow_mut().add_listener(
+                FileState::READABLE,
+                StateListenerFilter::OffToOn,
+                move |_state, _changed, _cb_queue| {
+                    let Some(inner) = weak_inner.upgrade() else {
+                        return;
+                    };
+                    // the socket is borrowed while its listeners run, so the datagrams are
+                    // received after the current event's handler returns
+                    Worker::with_active_host(|host| {
+                        host.defer(move |host| inner.receive_datagrams(host, id))
+                    })
+                    .unwrap();
+                },
+            )
+        };
+        sockets.push(AppSocket {
+            socket,
+            _handle: handle,
+        });
+
+        Ok(id)
+    }
+
+    /// Send a datagram from `socket` to `dst`. Returns `EWOULDBLOCK` if the socket's send buffer is
+    /// full.
+    pub fn send_to(
+        &mut self,
+        socket: SocketId,
+        dst: SocketAddrV4,
+        payload: &[u8],
+    ) -> Result<(), Errno> {
+        let socket = Arc::clone(&self.inner.sockets.lock().unwrap()[socket.0].socket);
+        CallbackQueue::queue_and_run(|cb_queue| {
+            UdpSocket::send_internal(&socket, dst, Bytes::copy_from_slice(payload), cb_queue)
+        })
+    }
+
+    /// Call the app's [`ShadowApp::on_timer`] with `token` after `delay`.
+    pub fn set_timer(&mut self, delay: SimulationTime, token: u64) {
+        let inner = Arc::clone(self.inner);
+        let task = TaskRef::new(move |host| inner.run(host, |app, ctx| app.on_timer(ctx, token)));
+        self.host.schedule_task_with_delay(task, delay);
+    }
+}
+
+struct AppSocket {
+    socket: Arc<AtomicRefCell<UdpSocket>>,
+    _handle: Handle<(FileState, FileState)>,
+}
+
+/// The parts of an app that its scheduled tasks use.
+struct Inner {
+    name: String,
+    app: Mutex<Box<dyn ShadowApp>>,
+    sockets: Mutex<Vec<AppSocket>>,
+}
+
+impl Inner {
+    fn run(self: &Arc<Self>, host: &Host, f: impl FnOnce(&mut dyn ShadowApp, &mut AppContext)) {
+        let mut app = self.app.lock().unwrap();
+        let mut ctx = AppContext { host, inner: self };
+        f(&mut **app, &mut ctx);
+    }
+
+    /// Pass all datagrams that have been received by `id` to the app.
+    fn receive_datagrams(self: &Arc<Self>, host: &Host, id: SocketId) {
+        // the socket was closed if the app was stopped
+        let Some(socket) = self
+            .sockets
+            .lock()
+            .unwrap()
+            .get(id.0)
+            .map(|x| Arc::clone(&x.socket))
+        else {
+            return;
+        };
+        loop {
+            let datagram = CallbackQueue::queue_and_run(|cb_queue| {
+                socket.borrow_mut().recv_internal(cb_queue)
+            });
+            let Some((payload, src)) = datagram else {
+                break;
+            };
+            self.run(host, |app, ctx| app.on_datagram(ctx, id, src, &payload));
+        }
+    }
+}
+
+/// A native app that a host runs.
+pub struct NativeApp {
+    inner: Arc<Inner>,
+}
+
+impl NativeApp {
+    /// Create and start the app described by `config`.
+    pub fn start(host: &Host, config: &NativeAppConfig) -> Self {
+        let app = create(&config.name, &config.args).unwrap_or_else(|e| {
+            panic!(
+                "Could not create native app '{}' on host '{}': {e:?}",
+                config.name,
+                host.name()
+            )
+        });
+
+        let inner = Arc::new(Inner {
+            name: config.name.clone(),
+            app: Mutex::new(app),
+            sockets: Mutex::new(Vec::new()),
+        });
+        log::debug!("Starting native app '{}'", inner.name);
+        inner.run(host, |app, ctx| app.start(ctx));
+
+        Self { inner }
+    }
+
+    /// Close the app's sockets, removing them from the network namespace, and stop the app.
+    pub fn stop(&self) {
+        log::debug!("Stopping native app '{}'", self.inner.name);
+        for socket in std::mem::take(&mut *self.inner.sockets.lock().unwrap()) {
+            CallbackQueue::queue_and_run(|cb_queue| {
+                if let Err(e) = socket.socket.borrow_mut().close(cb_queue) {
+                    log::warn!("Unable to close the native app's socket: {e:?}");
+                }
+            });
+        }
+        self.inner.app.lock().unwrap().stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create() {
+        assert!(create("udp-echo", &["7".to_string()]).is_ok());
+        assert!(create("udp-echo", &["not-a-port".to_string()]).is_err());
+        assert!(create("no-such-app", &[]).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! A model of ARP neighbor resolution. Before a host can send a packet, it must know the hardware
+//! address of the packet's next hop, which is either the destination host if it's on the same LAN,
+//! or the LAN's gateway otherwise. The first packet to a next hop waits for an ARP request and
+//! reply, and the result is cached for a while. Shadow doesn't model hardware addresses, so without
+//! this every packet is sent immediately.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+/// The next hop of a packet, whose hardware address must be resolved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ArpNeighbor {
+    /// A host on the same LAN.
+    Host(IpAddr),
+    /// The gateway to the hosts on other LANs.
+    Gateway,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ArpEntry {
+    /// The time that the neighbor's hardware address was (or will be) resolved.
+    resolved: EmulatedTime,
+    /// The time that the entry must be resolved again.
+    expires: EmulatedTime,
+}
+
+/// A host's cache of resolved neighbors.
+#[derive(Debug)]
+pub struct ArpCache {
+    timeout: SimulationTime,
+    entries: HashMap<ArpNeighbor, ArpEntry>,
+}
+
+impl ArpCache {
+    /// A cache whose entries are valid for `timeout` after being resolved.
+    pub fn new(timeout: SimulationTime) -> Self {
+        Self {
+            timeout,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The time that a packet sent to `neighbor` at time `now` waits for the neighbor's hardware
+    /// address to be resolved. If the neighbor isn't in the cache, an ARP request is sent and its
+    /// reply arrives after `round_trip`. Packets sent while a request is outstanding wait for the
+    /// same reply.
+    pub fn resolve(
+        &mut self,
+        neighbor: ArpNeighbor,
+        now: EmulatedTime,
+        round_trip: SimulationTime,
+    ) -> SimulationTime {
+        if let Some(entry) = self.entries.get(&neighbor) {
+            if now < entry.expires {
+                return entry.resolved.saturating_duration_since(&now);
+            }
+        }
+
+        let resolved = now.saturating_add(round_trip);
+        let expires = resolved.saturating_add(self.timeout);
+        self.entries
+            .insert(neighbor, ArpEntry { resolved, expires });
+
+        log::trace!("Sent an ARP request for {neighbor:?}");
+
+        round_trip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn time(ms: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut cache = ArpCache::new(SimulationTime::from_secs(60));
+        let host = ArpNeighbor::Host(IpAddr::V4(Ipv4Addr::new(11, 0, 0, 1)));
+        let rtt = SimulationTime::from_millis(2);
+
+        // the first packet waits for the reply
+        assert_eq!(cache.resolve(host, time(0), rtt), rtt);
+        // a packet sent while the request is outstanding waits for the same reply
+        assert_eq!(
+            cache.resolve(host, time(1), rtt),
+            SimulationTime::from_millis(1)
+        );
+        // later packets are sent immediately
+        assert_eq!(cache.resolve(host, time(10), rtt), SimulationTime::ZERO);
+        // other neighbors are resolved separately
+        assert_eq!(cache.resolve(ArpNeighbor::Gateway, time(10), rtt), rtt);
+
+        // the entry expires after the timeout
+        assert_eq!(cache.resolve(host, time(60_001), rtt), SimulationTime::ZERO);
+        assert_eq!(cache.resolve(host, time(60_002), rtt), rtt);
+    }
+}
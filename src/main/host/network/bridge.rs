@@ -0,0 +1,105 @@
+//! Bridges between simulated hosts and TUN devices on the machine running Shadow, which allow
+//! systems outside of the simulation to exchange packets with the simulated hosts. A bridged host
+//! represents an external system: the packets that the host receives are written to the TUN
+//! device, and the IPv4 packets read from the TUN device are sent from the host.
+//!
+//! The TUN device must already exist, for example created by `ip tuntap add`, and may be in any
+//! network namespace. Packets are read from the device in real time, so a simulation with a bridged
+//! host isn't deterministic.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::Ipv4Addr;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+
+use crate::network::ipv4::{self, ParsedPacket};
+use crate::network::packet::PacketRc;
+
+/// `_IOW('T', 202, int)` from "linux/if_tun.h".
+const TUNSETIFF: libc::c_ulong = 0x4004_54ca;
+const IFF_TUN: libc::c_short = 0x0001;
+const IFF_NO_PI: libc::c_short = 0x1000;
+
+/// The largest IPv4 packet.
+const MAX_PACKET_LEN: usize = u16::MAX as usize;
+
+/// The `ifreq` struct used by `TUNSETIFF`.
+#[repr(C)]
+struct IfReq {
+    name: [u8; libc::IFNAMSIZ],
+    flags: libc::c_short,
+    _padding: [u8; 22],
+}
+
+pub struct Bridge {
+    tun: File,
+    name: String,
+    buf: Vec<u8>,
+}
+
+impl Bridge {
+    /// Attach to the existing TUN device `name`.
+    pub fn open(name: &str) -> std::io::Result<Self> {
+        if name.is_empty() || name.len() >= libc::IFNAMSIZ {
+            return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        let tun = File::options()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/net/tun")?;
+
+        let mut req = IfReq {
+            name: [0; libc::IFNAMSIZ],
+            flags: IFF_TUN | IFF_NO_PI,
+            _padding: [0; 22],
+        };
+        req.name[..name.len()].copy_from_slice(name.as_bytes());
+
+        // SAFETY: `req` is a valid `ifreq` struct
+        if unsafe { libc::ioctl(tun.as_raw_fd(), TUNSETIFF as _, &mut req) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            tun,
+            name: name.to_string(),
+            buf: vec![0; MAX_PACKET_LEN],
+        })
+    }
+
+    /// Write a packet received by the host to the TUN device. Packets that can't be written are
+    /// dropped, like packets sent to a device that's down.
+    pub fn write(&mut self, packet: &PacketRc) {
+        if let Err(e) = self.tun.write(&ipv4::to_bytes(packet)) {
+            log::debug!("Dropping packet for TUN device '{}': {e}", self.name);
+        }
+    }
+
+    /// Read the next packet that's waiting on the TUN device, skipping packets that can't be sent
+    /// from `addr`. Returns `None` if there are no more packets waiting.
+    pub fn read(&mut self, addr: Ipv4Addr) -> Option<ParsedPacket> {
+        loop {
+            let len = match self.tun.read(&mut self.buf) {
+                Ok(len) => len,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return None,
+                Err(e) => {
+                    log::warn!("Failed to read from TUN device '{}': {e}", self.name);
+                    return None;
+                }
+            };
+
+            match ipv4::parse(&self.buf[..len]) {
+                Ok(packet) if *packet.src.ip() == addr => return Some(packet),
+                Ok(packet) => log::debug!(
+                    "Ignoring packet from TUN device '{}' with source address {} instead of {addr}",
+                    self.name,
+                    packet.src.ip(),
+                ),
+                Err(e) => log::debug!("Ignoring packet from TUN device '{}': {e}", self.name),
+            }
+        }
+    }
+}
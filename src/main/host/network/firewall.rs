@@ -0,0 +1,318 @@
+//! Per-host packet filtering rules. Each packet that a host's internet interface sends or receives
+//! is checked against the host's egress or ingress rules in order, and the first rule that matches
+//! the packet decides what happens to it. Packets that don't match any rule are allowed.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::str::FromStr;
+
+use anyhow::Context;
+
+use crate::core::support::configuration::{
+    FirewallAction, FirewallOptions, FirewallProtocol, FirewallRuleOptions,
+};
+use crate::cshadow as c;
+use crate::network::packet::PacketRc;
+
+/// Whether a packet is sent or received by the host.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FirewallDirection {
+    Ingress,
+    Egress,
+}
+
+impl FromStr for FirewallDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ingress" => Ok(Self::Ingress),
+            "egress" => Ok(Self::Egress),
+            _ => Err(anyhow::anyhow!(
+                "Invalid firewall direction '{s}' (expected 'ingress' or 'egress')"
+            )),
+        }
+    }
+}
+
+/// An IPv4 address prefix such as "11.0.0.0/8". An address without a prefix length matches only
+/// that address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AddressPrefix {
+    addr: Ipv4Addr,
+    len: u8,
+}
+
+impl AddressPrefix {
+    pub fn contains(&self, addr: Ipv4Addr) -> bool {
+        let mask = u32::MAX.checked_shl(32 - u32::from(self.len)).unwrap_or(0);
+        u32::from(addr) & mask == u32::from(self.addr) & mask
+    }
+}
+
+impl FromStr for AddressPrefix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr = addr
+            .parse()
+            .with_context(|| format!("Invalid IPv4 address '{addr}'"))?;
+        let len = match len {
+            Some(len) => len
+                .parse()
+                .ok()
+                .filter(|x| *x <= 32)
+                .with_context(|| format!("Invalid prefix length '{len}'"))?,
+            None => 32,
+        };
+        Ok(Self { addr, len })
+    }
+}
+
+/// A rule that matches packets by their protocol and addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirewallRule {
+    action: FirewallAction,
+    protocol: Option<FirewallProtocol>,
+    src: Option<AddressPrefix>,
+    dst: Option<AddressPrefix>,
+    src_port: Option<u16>,
+    dst_port: Option<u16>,
+}
+
+impl FirewallRule {
+    pub fn from_options(options: &FirewallRuleOptions) -> anyhow::Result<Self> {
+        Ok(Self {
+            action: options.action,
+            protocol: options.protocol,
+            src: options.src.as_deref().map(str::parse).transpose()?,
+            dst: options.dst.as_deref().map(str::parse).transpose()?,
+            src_port: options.src_port,
+            dst_port: options.dst_port,
+        })
+    }
+
+    fn matches(&self, protocol: FirewallProtocol, src: SocketAddrV4, dst: SocketAddrV4) -> bool {
+        self.protocol.map_or(true, |x| x == protocol)
+            && self.src.map_or(true, |x| x.contains(*src.ip()))
+            && self.dst.map_or(true, |x| x.contains(*dst.ip()))
+            && self.src_port.map_or(true, |x| x == src.port())
+            && self.dst_port.map_or(true, |x| x == dst.port())
+    }
+}
+
+/// Parses a rule written as an action followed by `key=value` matches, for example
+/// "deny protocol=tcp dst=11.0.0.0/8 dst_port=443".
+impl FromStr for FirewallRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+
+        let action = words.next().context("Missing firewall action")?;
+        let action = match action {
+            "allow" => FirewallAction::Allow,
+            "deny" => FirewallAction::Deny,
+            "reject" => FirewallAction::Reject,
+            _ => return Err(anyhow::anyhow!("Invalid firewall action '{action}'")),
+        };
+
+        let mut rule = Self {
+            action,
+            protocol: None,
+            src: None,
+            dst: None,
+            src_port: None,
+            dst_port: None,
+        };
+
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .with_context(|| format!("Expected 'key=value', not '{word}'"))?;
+            let port = || {
+                value
+                    .parse::<u16>()
+                    .with_context(|| format!("Invalid port '{value}'"))
+            };
+            match key {
+                "protocol" => {
+                    rule.protocol = Some(match value {
+                        "tcp" => FirewallProtocol::Tcp,
+                        "udp" => FirewallProtocol::Udp,
+                        _ => return Err(anyhow::anyhow!("Invalid protocol '{value}'")),
+                    })
+                }
+                "src" => rule.src = Some(value.parse()?),
+                "dst" => rule.dst = Some(value.parse()?),
+                "src_port" => rule.src_port = Some(port()?),
+                "dst_port" => rule.dst_port = Some(port()?),
+                _ => return Err(anyhow::anyhow!("Invalid firewall rule key '{key}'")),
+            }
+        }
+
+        Ok(rule)
+    }
+}
+
+/// A host's ingress and egress rules.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Firewall {
+    ingress: Vec<FirewallRule>,
+    egress: Vec<FirewallRule>,
+}
+
+impl Firewall {
+    pub fn from_options(options: &FirewallOptions) -> anyhow::Result<Self> {
+        let rules = |rules: &[FirewallRuleOptions]| {
+            rules
+                .iter()
+                .map(FirewallRule::from_options)
+                .collect::<anyhow::Result<_>>()
+        };
+        Ok(Self {
+            ingress: rules(&options.ingress).context("Invalid ingress rule")?,
+            egress: rules(&options.egress).context("Invalid egress rule")?,
+        })
+    }
+
+    fn rules_mut(&mut self, direction: FirewallDirection) -> &mut Vec<FirewallRule> {
+        match direction {
+            FirewallDirection::Ingress => &mut self.ingress,
+            FirewallDirection::Egress => &mut self.egress,
+        }
+    }
+
+    /// Add a rule after the existing rules.
+    pub fn add_rule(&mut self, direction: FirewallDirection, rule: FirewallRule) {
+        self.rules_mut(direction).push(rule);
+    }
+
+    /// Remove all of the rules for the direction.
+    pub fn clear(&mut self, direction: FirewallDirection) {
+        self.rules_mut(direction).clear();
+    }
+
+    /// The action of the first rule that matches a packet, or [`FirewallAction::Allow`] if no rule
+    /// matches it. Packets other than TCP and UDP packets are always allowed.
+    pub fn check(&self, direction: FirewallDirection, packet: &PacketRc) -> FirewallAction {
+        let protocol = match packet.protocol() {
+            c::_ProtocolType_PTCP => FirewallProtocol::Tcp,
+            c::_ProtocolType_PUDP => FirewallProtocol::Udp,
+            _ => return FirewallAction::Allow,
+        };
+        let rules = match direction {
+            FirewallDirection::Ingress => &self.ingress,
+            FirewallDirection::Egress => &self.egress,
+        };
+        let (src, dst) = (packet.src_address(), packet.dst_address());
+
+        rules
+            .iter()
+            .find(|rule| rule.matches(protocol, src, dst))
+            .map_or(FirewallAction::Allow, |rule| rule.action)
+    }
+}
+
+/// The TCP RST that rejects `packet`, or `None` if `packet` isn't a TCP packet or is itself a RST.
+pub fn reset_reply(packet: &PacketRc) -> Option<PacketRc> {
+    let header = packet.get_tcp()?;
+    if header.flags.contains(tcp::TcpFlags::RST) {
+        return None;
+    }
+
+    // like a RST sent for a segment that doesn't belong to a connection (RFC 793, section 3.4)
+    let (flags, seq, ack) = if header.flags.contains(tcp::TcpFlags::ACK) {
+        (tcp::TcpFlags::RST, header.ack, 0)
+    } else {
+        let mut len = u32::try_from(packet.payload_size()).unwrap();
+        len += u32::from(header.flags.contains(tcp::TcpFlags::SYN));
+        len += u32::from(header.flags.contains(tcp::TcpFlags::FIN));
+        (
+            tcp::TcpFlags::RST | tcp::TcpFlags::ACK,
+            0,
+            header.seq.wrapping_add(len),
+        )
+    };
+
+    let mut reply = PacketRc::new();
+    reply.set_tcp(&tcp::TcpHeader {
+        ip: tcp::Ipv4Header {
+            src: header.ip.dst,
+            dst: header.ip.src,
+        },
+        flags,
+        src_port: header.dst_port,
+        dst_port: header.src_port,
+        seq,
+        ack,
+        window_size: 0,
+        selective_acks: None,
+        window_scale: None,
+        timestamp: None,
+        timestamp_echo: None,
+    });
+    Some(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddrV4 {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule: FirewallRule = "reject protocol=tcp dst=11.0.0.0/8 dst_port=443"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule,
+            FirewallRule {
+                action: FirewallAction::Reject,
+                protocol: Some(FirewallProtocol::Tcp),
+                src: None,
+                dst: Some(AddressPrefix {
+                    addr: Ipv4Addr::new(11, 0, 0, 0),
+                    len: 8,
+                }),
+                src_port: None,
+                dst_port: Some(443),
+            }
+        );
+
+        assert!("".parse::<FirewallRule>().is_err());
+        assert!("block".parse::<FirewallRule>().is_err());
+        assert!("deny dst=11.0.0.0/33".parse::<FirewallRule>().is_err());
+        assert!("deny port=80".parse::<FirewallRule>().is_err());
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let rule: FirewallRule = "deny protocol=udp src=11.0.0.0/24 dst_port=53"
+            .parse()
+            .unwrap();
+        let udp = FirewallProtocol::Udp;
+        assert!(rule.matches(udp, addr("11.0.0.5:1000"), addr("11.0.1.1:53")));
+        assert!(!rule.matches(udp, addr("11.0.1.5:1000"), addr("11.0.1.1:53")));
+        assert!(!rule.matches(udp, addr("11.0.0.5:1000"), addr("11.0.1.1:54")));
+        assert!(!rule.matches(
+            FirewallProtocol::Tcp,
+            addr("11.0.0.5:1000"),
+            addr("11.0.1.1:53")
+        ));
+
+        // a rule without matches matches every packet
+        let rule: FirewallRule = "allow".parse().unwrap();
+        assert!(rule.matches(udp, addr("1.2.3.4:1"), addr("5.6.7.8:2")));
+
+        // a prefix of length 0 matches every address
+        let rule: FirewallRule = "allow src=0.0.0.0/0".parse().unwrap();
+        assert!(rule.matches(udp, addr("1.2.3.4:1"), addr("5.6.7.8:2")));
+    }
+}
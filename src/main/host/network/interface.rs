@@ -1,145 +1,784 @@
-use std::ffi::{CString, OsStr};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
 use std::net::{Ipv4Addr, SocketAddrV4};
-use std::os::unix::ffi::OsStrExt;
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use atomic_refcell::AtomicRefCell;
+use linux_api::netdevice::InterfaceFlags;
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::HostId;
 
-use crate::core::support::configuration::QDiscMode;
+use crate::core::support::configuration::{
+    FirewallAction, OutputCompression, PcapErrorAction, PcapPayload, QDiscMode,
+};
 use crate::core::worker::Worker;
 use crate::cshadow as c;
-use crate::network::packet::PacketRc;
+use crate::host::descriptor::socket::inet::raw::RawSocket;
+use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::host::Host;
+use crate::host::network::bridge::Bridge;
+use crate::host::network::firewall::{self, FirewallDirection};
+use crate::host::network::pcap_capture::PcapCapture;
+use crate::host::network::queuing::SendQueue;
+use crate::network::fingerprint::PacketDirection;
+use crate::network::ipv4;
+use crate::network::packet::{IcmpEchoHeader, IcmpEchoKind, PacketRc, PacketStatus};
 use crate::network::PacketDevice;
-use crate::utility::{self, HostTreePointer};
+use crate::utility::callback_queue::CallbackQueue;
+use crate::utility::legacy_callback_queue::with_global_cb_queue;
+use crate::utility::pcap_writer::{EthernetFrame, ETHERNET_HEADER_LEN};
+use crate::utility::ObjectCounter;
 
 /// The priority used by the fifo qdisc to choose the next socket to send a packet from.
+///
+/// Packets with smaller values are sent first. Each host hands out data packet priorities from a
+/// counter (see `Host::get_next_packet_priority`) that starts at [`FIFO_FIRST_DATA_PRIORITY`] and
+/// increases by one for every packet, so data packets are sent in the order that the application
+/// wrote them, even across sockets. The value [`FIFO_CONTROL_PRIORITY`] is reserved for control
+/// packets (for example TCP acks), which are sent before any data packets. The counter panics
+/// rather than wrapping around, so the order is never silently violated.
+///
+/// The C code uses the same values through `PACKET_PRIORITY_CONTROL` in "main/routing/packet.h".
 pub type FifoPacketPriority = u64;
 
+/// The priority of control packets, which are sent before all data packets.
+pub const FIFO_CONTROL_PRIORITY: FifoPacketPriority = 0;
+
+/// The priority of the first data packet sent by a host.
+pub const FIFO_FIRST_DATA_PRIORITY: FifoPacketPriority = FIFO_CONTROL_PRIORITY + 1;
+
+/// The hardware address of the host with id `host_id`. It's a locally administered address that
+/// contains the host id, so it's unique within the simulation and the same in every run.
+pub fn host_hw_addr(host_id: HostId) -> [u8; 6] {
+    let [a, b, c, d] = u32::from(host_id).to_be_bytes();
+    [0x02, 0x00, a, b, c, d]
+}
+
 #[derive(Debug, Clone)]
 pub struct PcapOptions {
     pub path: PathBuf,
     pub capture_size_bytes: u32,
+    pub payload: PcapPayload,
+    pub compression: OutputCompression,
+    pub error_action: PcapErrorAction,
 }
 
-/// Represents a network device that can send and receive packets. All accesses
-/// to the internal C implementation should be done through this module.
+/// Represents a network device that can send and receive packets. Received packets are passed to
+/// the socket associated with the packet's addresses, and sent packets are taken from the sockets
+/// that have data to send, in an order chosen by the interface's queuing discipline.
+///
+/// Sockets may call back into the interface while it's passing packets to or from them (for
+/// example to disassociate themselves or to ask to send more packets), so the interface never holds
+/// a borrow of its internal state while calling into a socket.
 pub struct NetworkInterface {
-    c_ptr: HostTreePointer<c::NetworkInterface>,
     addr: Ipv4Addr,
+    name: String,
+    hw_addr: [u8; 6],
+    stats: Cell<InterfaceStats>,
+    /// Sockets that will receive packets, keyed by the local port and peer address, in the order
+    /// that they were associated. Only UDP sockets with `SO_REUSEADDR` can share a key.
+    associations: RefCell<HashMap<AssociationKey, Vec<InetSocket>>>,
+    /// Raw sockets, which receive a copy of every packet of their IP protocol, in the order that
+    /// they were associated.
+    raw_associations: RefCell<Vec<RawAssociation>>,
+    /// Sockets that have packets to send.
+    send_queue: RefCell<SendQueue<InetSocket>>,
+    pcap: RefCell<Option<PcapCapture>>,
+    /// The TUN device that the interface exchanges packets with instead of its sockets, if any.
+    bridge: RefCell<Option<Bridge>>,
+    /// Packets read from the bridge that are waiting to be sent.
+    bridge_packets: RefCell<VecDeque<PacketRc>>,
+    /// Packets that weren't sent by a socket (such as replies to received ICMP echo requests and
+    /// replayed packets) that are waiting to be sent.
+    socketless_packets: RefCell<VecDeque<PacketRc>>,
+    _counter: ObjectCounter,
+}
+
+/// Counters for the packets that passed through a network interface, like the counters in Linux's
+/// `/proc/net/dev`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct InterfaceStats {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+}
+
+/// A raw socket and the IP protocol of the packets that it receives.
+struct RawAssociation {
+    protocol: u8,
+    socket: Arc<AtomicRefCell<RawSocket>>,
+}
+
+/// Identifies a socket association on an interface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct AssociationKey {
+    protocol: c::ProtocolType,
+    port: u16,
+    peer: SocketAddrV4,
 }
 
 impl NetworkInterface {
-    /// Create a new network interface for `host_id` with the assigned `addr`.
-    ///
-    /// # Safety
-    ///
-    /// This function will trigger undefined behavior if `addr` is
-    /// invalid. The reference count of `addr` will be increased by one using
-    /// `address_ref()`, so the caller should call `address_unref()` on it to
-    /// drop their reference when they no longer need it.
-    pub unsafe fn new(
-        host_id: HostId,
-        addr: *mut c::Address,
+    /// Create a new network interface named `name` (for example "eth0") with the assigned `addr`,
+    /// for the host with id `host_id`.
+    pub fn new(
         name: &OsStr,
+        host_id: HostId,
+        addr: Ipv4Addr,
         pcap_options: Option<PcapOptions>,
         qdisc: QDiscMode,
     ) -> NetworkInterface {
-        let maybe_pcap_dir = pcap_options
-            .as_ref()
-            .map(|x| utility::pathbuf_to_nul_term_cstring(x.path.clone()));
-        let pcap_dir_cptr = maybe_pcap_dir
-            .as_ref()
-            .map_or(std::ptr::null(), |p| p.as_ptr());
+        let name = name.to_string_lossy().into_owned();
 
-        let pcap_capture_size = pcap_options
+        // interfaces are created before the simulation starts
+        let pcap = pcap_options
             .as_ref()
-            .map(|x| x.capture_size_bytes)
-            .unwrap_or(0);
+            .map(|options| PcapCapture::new(&name, options, EmulatedTime::SIMULATION_START));
 
-        let mut name = name.as_bytes().to_vec();
-        name.push(0);
-        let name = CString::from_vec_with_nul(name).unwrap();
+        log::debug!(
+            "Bringing up network interface '{name}' at '{addr}' using queuing discipline {qdisc:?}"
+        );
 
-        let c_ptr = unsafe {
-            c::networkinterface_new(addr, name.as_ptr(), pcap_dir_cptr, pcap_capture_size, qdisc)
-        };
-
-        let ipv4_addr: Ipv4Addr = {
-            let addr = unsafe { c::address_toNetworkIP(addr) };
-            u32::from_be(addr).into()
+        // loopback interfaces don't have a hardware address
+        let hw_addr = if addr.is_loopback() {
+            [0; 6]
+        } else {
+            host_hw_addr(host_id)
         };
 
         NetworkInterface {
-            c_ptr: HostTreePointer::new_for_host(host_id, c_ptr),
-            addr: ipv4_addr,
+            addr,
+            name,
+            hw_addr,
+            stats: Cell::new(InterfaceStats::default()),
+            associations: RefCell::new(HashMap::new()),
+            raw_associations: RefCell::new(Vec::new()),
+            send_queue: RefCell::new(SendQueue::new(qdisc)),
+            pcap: RefCell::new(pcap),
+            bridge: RefCell::new(None),
+            bridge_packets: RefCell::new(VecDeque::new()),
+            socketless_packets: RefCell::new(VecDeque::new()),
+            _counter: ObjectCounter::new("NetworkInterface"),
+        }
+    }
+
+    /// The name of the interface, for example "eth0".
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The interface's IPv4 address.
+    pub fn addr(&self) -> Ipv4Addr {
+        self.addr
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.addr.is_loopback()
+    }
+
+    /// The interface's netmask. Shadow doesn't model subnets, so other interfaces are given a /24
+    /// netmask, the same as in the emulated `getifaddrs()`.
+    pub fn netmask(&self) -> Ipv4Addr {
+        if self.is_loopback() {
+            Ipv4Addr::new(255, 0, 0, 0)
+        } else {
+            Ipv4Addr::new(255, 255, 255, 0)
+        }
+    }
+
+    /// The interface's MTU, which is Linux's default for the interface type.
+    pub fn mtu(&self) -> u32 {
+        if self.is_loopback() {
+            65536
+        } else {
+            c::CONFIG_MTU
+        }
+    }
+
+    /// The interface's hardware address (see [`host_hw_addr`]). Loopback interfaces don't have
+    /// one, so it's all zeros.
+    pub fn hw_addr(&self) -> [u8; 6] {
+        self.hw_addr
+    }
+
+    /// The interface's broadcast address, or `None` for the loopback interface.
+    pub fn broadcast(&self) -> Option<Ipv4Addr> {
+        if self.is_loopback() {
+            return None;
+        }
+        Some(Ipv4Addr::from(
+            u32::from(self.addr) | !u32::from(self.netmask()),
+        ))
+    }
+
+    /// The interface's flags. Interfaces are always up.
+    pub fn flags(&self) -> InterfaceFlags {
+        let flags = InterfaceFlags::IFF_UP | InterfaceFlags::IFF_RUNNING;
+        if self.is_loopback() {
+            flags | InterfaceFlags::IFF_LOOPBACK
+        } else {
+            flags | InterfaceFlags::IFF_BROADCAST | InterfaceFlags::IFF_MULTICAST
+        }
+    }
+
+    /// The interface's transmit queue length, which is Linux's default.
+    pub fn tx_queue_len(&self) -> u32 {
+        1000
+    }
+
+    /// The hardware address that frames to or from `ip` use on this interface. This is the address
+    /// of the host that `ip` is assigned to, or the broadcast address.
+    fn peer_hw_addr(&self, ip: Ipv4Addr) -> [u8; 6] {
+        if self.is_loopback() {
+            return [0; 6];
+        }
+        if ip == Ipv4Addr::BROADCAST || Some(ip) == self.broadcast() {
+            return [0xff; 6];
         }
+        // the peer is outside of the simulation (for example a bridged network) if it has no host
+        Worker::resolve_ip_to_host_id(ip)
+            .map(host_hw_addr)
+            .unwrap_or([0; 6])
+    }
+
+    /// The packet counters for the interface.
+    pub fn stats(&self) -> InterfaceStats {
+        self.stats.get()
+    }
+
+    /// The number of sockets waiting to send packets on the interface.
+    pub fn send_queue_len(&self) -> usize {
+        self.send_queue.borrow().len()
+    }
+
+    fn update_stats(&self, f: impl FnOnce(&mut InterfaceStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
     }
 
     pub fn associate(
         &self,
-        socket_ptr: *const c::CompatSocket,
-        protocol_type: c::ProtocolType,
+        socket: &InetSocket,
+        protocol: c::ProtocolType,
         port: u16,
-        peer_addr: SocketAddrV4,
+        peer: SocketAddrV4,
     ) {
-        let port = port.to_be();
-        let peer_ip = u32::from(*peer_addr.ip()).to_be();
-        let peer_port = peer_addr.port().to_be();
-
-        unsafe {
-            c::networkinterface_associate(
-                self.c_ptr.ptr(),
-                socket_ptr,
-                protocol_type,
-                port,
-                peer_ip,
-                peer_port,
-            )
+        let key = AssociationKey {
+            protocol,
+            port,
+            peer,
         };
+
+        let mut associations = self.associations.borrow_mut();
+        let sockets = associations.entry(key).or_default();
+        // make sure there was no collision
+        debug_assert!(sockets.is_empty() || protocol == c::_ProtocolType_PUDP);
+        sockets.push(socket.clone());
+
+        log::trace!("Associated socket key {key:?}");
     }
 
-    pub fn disassociate(&self, protocol_type: c::ProtocolType, port: u16, peer_addr: SocketAddrV4) {
-        let port = port.to_be();
-        let peer_ip = u32::from(*peer_addr.ip()).to_be();
-        let peer_port = peer_addr.port().to_be();
+    /// Disassociate the socket with the canonical handle `socket` from the port and peer address.
+    /// If `socket` is `None`, all sockets associated with the port and peer address are
+    /// disassociated.
+    pub fn disassociate(
+        &self,
+        protocol: c::ProtocolType,
+        port: u16,
+        peer: SocketAddrV4,
+        socket: Option<usize>,
+    ) {
+        let key = AssociationKey {
+            protocol,
+            port,
+            peer,
+        };
 
-        unsafe {
-            c::networkinterface_disassociate(
-                self.c_ptr.ptr(),
-                protocol_type,
-                port,
-                peer_ip,
-                peer_port,
-            )
+        // TODO: Return an error if the disassociation fails. Generally the calling code should only
+        // try to disassociate a socket if it thinks that the socket is actually associated with
+        // this interface, and if it's not, then it's probably an error. But TCP sockets will
+        // disassociate all sockets (including ones that have never been associated) and will try
+        // to disassociate the same socket multiple times, so we can't just add an assert here.
+        let sockets = {
+            let mut associations = self.associations.borrow_mut();
+            match (socket, associations.get_mut(&key)) {
+                (Some(handle), Some(sockets)) if sockets.len() > 1 => {
+                    let index = sockets.iter().position(|x| x.canonical_handle() == handle);
+                    index.map(|index| vec![sockets.remove(index)])
+                }
+                _ => associations.remove(&key),
+            }
         };
+        // dropping the sockets may call back into the interface, so must not hold the borrow
+        drop(sockets);
+
+        log::trace!("Disassociated socket key {key:?}");
     }
 
     pub fn is_addr_in_use(&self, protocol: c::ProtocolType, port: u16, peer: SocketAddrV4) -> bool {
-        let port = port.to_be();
-        let peer_ip = u32::from(*peer.ip()).to_be();
-        let peer_port = peer.port().to_be();
+        let key = AssociationKey {
+            protocol,
+            port,
+            peer,
+        };
+        self.associations.borrow().contains_key(&key)
+    }
+
+    /// Associate the raw socket with the interface, so that it receives a copy of every packet with
+    /// the IP protocol `protocol` that the interface receives.
+    pub fn associate_raw(&self, socket: &Arc<AtomicRefCell<RawSocket>>, protocol: u8) {
+        self.raw_associations.borrow_mut().push(RawAssociation {
+            protocol,
+            socket: Arc::clone(socket),
+        });
 
-        (unsafe {
-            c::networkinterface_isAssociated(self.c_ptr.ptr(), protocol, port, peer_ip, peer_port)
-        }) != 0
+        log::trace!("Associated raw socket for IP protocol {protocol}");
+    }
+
+    /// Disassociate the raw socket with the canonical handle `handle`.
+    pub fn disassociate_raw(&self, handle: usize) {
+        let association = {
+            let mut associations = self.raw_associations.borrow_mut();
+            associations
+                .iter()
+                .position(|x| Arc::as_ptr(&x.socket) as usize == handle)
+                .map(|index| associations.remove(index))
+        };
+        // dropping the socket may call back into the interface, so must not hold the borrow
+        drop(association);
+
+        log::trace!("Disassociated raw socket {handle:#x}");
+    }
+
+    /// The inet socket associated with the port and peer address, if any. If several sockets share
+    /// the port and peer address, this is the most recently associated socket, which is the socket
+    /// that receives unicast packets.
+    pub fn associated_inet_socket(
+        &self,
+        protocol: c::ProtocolType,
+        port: u16,
+        peer: SocketAddrV4,
+    ) -> Option<InetSocket> {
+        let key = AssociationKey {
+            protocol,
+            port,
+            peer,
+        };
+        self.associations
+            .borrow()
+            .get(&key)
+            .and_then(|x| x.last())
+            .cloned()
+    }
+
+    /// All inet sockets associated with the port and peer address, in the order that they were
+    /// associated.
+    pub fn associated_inet_sockets(
+        &self,
+        protocol: c::ProtocolType,
+        port: u16,
+        peer: SocketAddrV4,
+    ) -> Vec<InetSocket> {
+        let key = AssociationKey {
+            protocol,
+            port,
+            peer,
+        };
+        self.associations
+            .borrow()
+            .get(&key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// All inet sockets that are associated with this interface. A socket is returned more than
+    /// once if it has more than one association.
+    pub fn inet_sockets(&self) -> Vec<InetSocket> {
+        let mut sockets: Vec<_> = self
+            .associations
+            .borrow()
+            .values()
+            .flatten()
+            .cloned()
+            .collect();
+        sockets.extend(
+            self.raw_associations
+                .borrow()
+                .iter()
+                .map(|x| InetSocket::Raw(Arc::clone(&x.socket))),
+        );
+        sockets
+    }
+
+    /// The number of associations with a specific peer, such as those of connected TCP sockets.
+    pub fn num_connections(&self) -> usize {
+        self.associations
+            .borrow()
+            .keys()
+            .filter(|x| !x.peer.ip().is_unspecified())
+            .count()
+    }
+
+    /// Add the socket to the sockets that have packets ready for us to send out to the network.
+    pub fn add_data_source(&self, socket: &InetSocket) {
+        if !socket.borrow().has_data_to_send() {
+            log::warn!("Socket wants send, but no packets available");
+            return;
+        }
+
+        // track the socket for sending if not already tracking
+        self.queue_socket(socket);
+    }
+
+    /// Add the socket to the send queue if it isn't already queued, or otherwise update its
+    /// priority since the socket may have queued a packet with a higher priority.
+    fn queue_socket(&self, socket: &InetSocket) {
+        let handle = socket.canonical_handle();
+        // don't borrow the socket while the send queue is borrowed
+        let priority = Self::socket_priority(socket);
+        let mut send_queue = self.send_queue.borrow_mut();
+
+        if send_queue.contains(handle) {
+            send_queue.update_priority(handle, priority);
+        } else {
+            send_queue.push(handle, priority, socket.clone());
+        }
     }
 
-    pub fn add_data_source(&self, socket_ptr: *const c::CompatSocket) {
-        unsafe { c::networkinterface_wantsSend(self.c_ptr.ptr(), socket_ptr) };
+    /// The priority of the socket's next packet, used by the fifo queuing discipline.
+    fn socket_priority(socket: &InetSocket) -> FifoPacketPriority {
+        // a socket without a next packet priority is sent from first, which lets the interface
+        // remove it from the queue as early as possible
+        socket
+            .borrow()
+            .peek_next_packet_priority()
+            .unwrap_or(FIFO_CONTROL_PRIORITY)
     }
 
     /// Disassociate all bound sockets and remove sockets from the sending queue. This should be
     /// called as part of the host's cleanup procedure.
     pub fn remove_all_sockets(&self) {
-        unsafe { c::networkinterface_removeAllSockets(self.c_ptr.ptr()) };
+        // dropping the sockets may call back into the interface, so must not hold the borrows
+        let associations = std::mem::take(&mut *self.associations.borrow_mut());
+        drop(associations);
+        let raw_associations = std::mem::take(&mut *self.raw_associations.borrow_mut());
+        drop(raw_associations);
+
+        let mut sockets = Vec::new();
+        while let Some(socket) = self.send_queue.borrow_mut().pop() {
+            sockets.push(socket);
+        }
+        drop(sockets);
     }
-}
 
-impl Drop for NetworkInterface {
-    fn drop(&mut self) {
-        // don't check the active host since we're in the middle of dropping the host
-        unsafe { c::networkinterface_free(self.c_ptr.ptr_unchecked()) };
+    /// Exchange the interface's packets with a TUN device instead of its sockets. Received packets
+    /// are written to the device, and packets read from the device by [`Self::read_bridge`] are
+    /// sent.
+    pub fn set_bridge(&self, bridge: Bridge) {
+        self.bridge.borrow_mut().replace(bridge);
+    }
+
+    /// Queue the packets waiting on the interface's bridge to be sent. Returns true if any packets
+    /// were queued.
+    pub fn read_bridge(&self, host: &Host) -> bool {
+        let mut bridge = self.bridge.borrow_mut();
+        let Some(bridge) = bridge.as_mut() else {
+            return false;
+        };
+
+        let mut queued = false;
+        while let Some(parsed) = bridge.read(self.addr) {
+            let packet = parsed.to_packet(host.get_next_packet_priority());
+            self.bridge_packets.borrow_mut().push_back(packet);
+            queued = true;
+        }
+        queued
+    }
+
+    /// Pass a received packet to the socket associated with the packet's addresses. A multicast
+    /// packet is passed to every socket associated with the packet's addresses.
+    fn receive_packet(&self, host: &Host, mut packet: PacketRc, recv_time: EmulatedTime) {
+        packet.add_status(PacketStatus::RcvInterfaceReceived);
+
+        let sockets = self.receiving_sockets(&packet);
+
+        // record the packet before we process it, otherwise we may send more packets before we
+        // record this one and the order will be incorrect
+        self.capture_packet(host, &packet, PacketDirection::Received);
+        host.add_to_packet_fingerprint(&packet, PacketDirection::Received);
+
+        // like a real firewall, this happens after the packet was captured
+        if !self.firewall_allows(host, FirewallDirection::Ingress, &mut packet) {
+            return;
+        }
+
+        if let Some(bridge) = self.bridge.borrow_mut().as_mut() {
+            bridge.write(&packet);
+            return;
+        }
+
+        // like linux, raw sockets receive a copy of the packet even if it's also received by
+        // another socket or by the network stack
+        self.deliver_to_raw_sockets(&packet, recv_time);
+
+        // like linux, echo requests are answered by the network stack rather than by a socket
+        if let Some(request) = packet.get_icmp() {
+            if request.kind == IcmpEchoKind::Request {
+                self.queue_echo_reply(host, &mut packet, &request);
+                return;
+            }
+        }
+
+        if sockets.is_empty() {
+            // the socket closed, so just drop the packet
+            packet.add_status(PacketStatus::RcvInterfaceDropped);
+            if packet.protocol() == c::_ProtocolType_PUDP {
+                host.network_namespace_borrow()
+                    .update_udp_stats(|stats| stats.no_ports += 1);
+            }
+            return;
+        }
+
+        // the sockets copy the packet's payload, so a multicast packet can be shared between them
+        for socket in &sockets {
+            with_global_cb_queue(|| {
+                CallbackQueue::queue_and_run(|cb_queue| {
+                    socket
+                        .borrow_mut()
+                        .push_in_packet(packet.clone(), cb_queue, recv_time);
+                });
+            });
+
+            // count our bandwidth usage by interface, and by socket
+            if let Some(mut tracker) = host.tracker_borrow_mut() {
+                let compat_socket = unsafe { c::compatsocket_fromInetSocket(socket) };
+                unsafe { c::tracker_addInputBytes(&mut *tracker, packet.as_ptr(), &compat_socket) };
+            }
+        }
+    }
+
+    /// The sockets that should receive the packet. A socket associated with the packet's source
+    /// address is chosen before a socket with a wildcard association. Like Linux, a UDP multicast
+    /// packet is received by every socket associated with its addresses, since the host has
+    /// already checked that the interface joined the packet's group.
+    fn receiving_sockets(&self, packet: &PacketRc) -> Vec<InetSocket> {
+        let protocol = packet.protocol();
+        let port = packet.dst_address().port();
+
+        let specific_key = AssociationKey {
+            protocol,
+            port,
+            peer: packet.src_address(),
+        };
+        let general_key = AssociationKey {
+            protocol,
+            port,
+            peer: SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+        };
+
+        let associations = self.associations.borrow();
+        let specific = associations.get(&specific_key).into_iter().flatten();
+        let general = associations.get(&general_key).into_iter().flatten();
+
+        if protocol == c::_ProtocolType_PUDP && packet.dst_address().ip().is_multicast() {
+            return specific.chain(general).cloned().collect();
+        }
+
+        // first check for a socket with the specific association, then check for a socket with a
+        // wildcard association
+        log::trace!("Looking for socket associated with specific key {specific_key:?}");
+        if let Some(socket) = specific.last() {
+            return vec![socket.clone()];
+        }
+        log::trace!("Looking for socket associated with general key {general_key:?}");
+        general.last().cloned().into_iter().collect()
+    }
+
+    /// Pass a copy of a received packet to the raw sockets for the packet's IP protocol.
+    fn deliver_to_raw_sockets(&self, packet: &PacketRc, recv_time: EmulatedTime) {
+        // don't hold the borrow while calling into the sockets
+        let sockets: Vec<_> = {
+            let associations = self.raw_associations.borrow();
+            if associations.is_empty() {
+                return;
+            }
+            let protocol = ipv4::protocol(packet);
+            associations
+                .iter()
+                .filter(|x| x.protocol == protocol)
+                .map(|x| Arc::clone(&x.socket))
+                .collect()
+        };
+
+        if sockets.is_empty() {
+            return;
+        }
+
+        let bytes = ipv4::to_bytes(packet);
+
+        with_global_cb_queue(|| {
+            CallbackQueue::queue_and_run(|cb_queue| {
+                for socket in sockets {
+                    socket
+                        .borrow_mut()
+                        .push_in_bytes(&bytes, cb_queue, recv_time);
+                }
+            });
+        });
+    }
+
+    /// Queue a reply to the received echo request `packet` with the same identifier, sequence
+    /// number, and data. Requests sent to a broadcast or multicast address are ignored, like
+    /// Linux's default `icmp_echo_ignore_broadcasts` setting.
+    fn queue_echo_reply(&self, host: &Host, packet: &mut PacketRc, request: &IcmpEchoHeader) {
+        if request.dst.is_broadcast() || request.dst.is_multicast() {
+            packet.add_status(PacketStatus::RcvInterfaceDropped);
+            return;
+        }
+
+        let mut data = vec![0; packet.payload_size()];
+        packet.get_payload(&mut data);
+
+        let mut reply = PacketRc::new();
+        reply.set_icmp(&IcmpEchoHeader {
+            kind: IcmpEchoKind::Reply,
+            src: request.dst,
+            dst: request.src,
+            ..*request
+        });
+        reply.set_payload(&data, host.get_next_packet_priority());
+        reply.add_status(PacketStatus::SndCreated);
+
+        packet.add_status(PacketStatus::RcvSocketProcessed);
+
+        self.send_socketless_packet(host, reply);
+    }
+
+    /// Send a packet that wasn't sent by a socket. Like packets from sockets, it's subject to the
+    /// host's bandwidth limits and firewall, and is captured if the interface is capturing packets.
+    pub fn send_socketless_packet(&self, host: &Host, packet: PacketRc) {
+        self.socketless_packets.borrow_mut().push_back(packet);
+        host.notify_interface_has_packets(self.addr);
+    }
+
+    /// Take the next packet to send from the sockets that have data to send. Returns the packet and
+    /// the socket that it was taken from.
+    fn pull_next_packet(&self) -> Option<(PacketRc, InetSocket)> {
+        loop {
+            // don't hold the borrow while calling into the socket
+            let socket = self.send_queue.borrow_mut().pop()?;
+
+            let packet = with_global_cb_queue(|| {
+                CallbackQueue::queue_and_run(|cb_queue| {
+                    socket.borrow_mut().pull_out_packet(cb_queue)
+                })
+            });
+
+            // the socket had no packet, so it's removed from the queue
+            let Some(packet) = packet else {
+                continue;
+            };
+
+            // if the socket has more packets, keep it in the queue
+            if socket.borrow().has_data_to_send() {
+                self.queue_socket(&socket);
+            }
+
+            return Some((packet, socket));
+        }
+    }
+
+    /// Apply the host's firewall rules to a packet that the interface is sending or receiving.
+    /// Returns whether the packet is allowed. The loopback interface doesn't have a firewall.
+    fn firewall_allows(
+        &self,
+        host: &Host,
+        direction: FirewallDirection,
+        packet: &mut PacketRc,
+    ) -> bool {
+        if self.addr.is_loopback() {
+            return true;
+        }
+        let Some(firewall) = host.firewall() else {
+            return true;
+        };
+
+        let action = firewall.lock().unwrap().check(direction, packet);
+
+        match action {
+            FirewallAction::Allow => return true,
+            FirewallAction::Deny => {}
+            FirewallAction::Reject => {
+                if let Some(reply) = firewall::reset_reply(packet) {
+                    match direction {
+                        // the RST is sent back to the packet's sender
+                        FirewallDirection::Ingress => {
+                            let reply = reply.into_inner();
+                            unsafe { Worker::send_packet(host, reply) };
+                            unsafe { c::packet_unref(reply) };
+                        }
+                        // the RST is received by the socket that sent the packet, once the
+                        // interface has finished sending packets
+                        FirewallDirection::Egress => {
+                            let addr = self.addr;
+                            host.defer(move |host| {
+                                if let Some(iface) = host.interface_borrow(addr) {
+                                    iface.push(reply);
+                                }
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        log::trace!(
+            "Firewall dropped {direction:?} packet from {} to {}",
+            packet.src_address(),
+            packet.dst_address()
+        );
+        packet.add_status(match direction {
+            FirewallDirection::Ingress => PacketStatus::RcvInterfaceDropped,
+            FirewallDirection::Egress => PacketStatus::InetDropped,
+        });
+        false
+    }
+
+    fn capture_packet(&self, host: &Host, packet: &PacketRc, direction: PacketDirection) {
+        let mut pcap = self.pcap.borrow_mut();
+        let Some(pcap) = pcap.as_mut() else {
+            return;
+        };
+
+        // the current time that the packet is being sent/received
+        let now = Worker::current_time().unwrap();
+        let packet_len: u32 = packet.total_size().try_into().unwrap();
+
+        let frame = match direction {
+            PacketDirection::Sent => EthernetFrame {
+                src: self.hw_addr,
+                dst: self.peer_hw_addr(*packet.dst_address().ip()),
+                packet,
+            },
+            PacketDirection::Received => EthernetFrame {
+                src: self.peer_hw_addr(*packet.src_address().ip()),
+                dst: self.hw_addr,
+                packet,
+            },
+        };
+
+        // the error has already been logged, so we only need to record it
+        if pcap
+            .capture(&frame, ETHERNET_HEADER_LEN + packet_len, now)
+            .is_err()
+        {
+            if let Some(mut tracker) = host.tracker_borrow_mut() {
+                unsafe { c::tracker_addPcapError(&mut *tracker) };
+            }
+        }
     }
 }
 
@@ -149,23 +788,67 @@ impl PacketDevice for NetworkInterface {
     }
 
     fn pop(&self) -> Option<PacketRc> {
-        let packet_ptr = unsafe { c::networkinterface_pop(self.c_ptr.ptr()) };
-        match packet_ptr.is_null() {
-            true => None,
-            false => Some(PacketRc::from_raw(packet_ptr)),
-        }
+        let (mut packet, socket) = loop {
+            // packets from the bridge, echo replies, and replayed packets don't have a socket
+            let socketless_packet = self.bridge_packets.borrow_mut().pop_front();
+            let socketless_packet =
+                socketless_packet.or_else(|| self.socketless_packets.borrow_mut().pop_front());
+            let (mut packet, socket) = match socketless_packet {
+                Some(packet) => (packet, None),
+                None => {
+                    let (packet, socket) = self.pull_next_packet()?;
+                    (packet, Some(socket))
+                }
+            };
+
+            // packets dropped by the firewall never leave the host
+            let is_allowed = Worker::with_active_host(|host| {
+                self.firewall_allows(host, FirewallDirection::Egress, &mut packet)
+            })
+            .unwrap();
+
+            if is_allowed {
+                break (packet, socket);
+            }
+        };
+
+        packet.add_status(PacketStatus::SndInterfaceSent);
+
+        Worker::with_active_host(|host| {
+            // record the packet early before we do anything else
+            self.capture_packet(host, &packet, PacketDirection::Sent);
+            host.add_to_packet_fingerprint(&packet, PacketDirection::Sent);
+
+            if let (Some(mut tracker), Some(socket)) = (host.tracker_borrow_mut(), &socket) {
+                let compat_socket = unsafe { c::compatsocket_fromInetSocket(socket) };
+                unsafe {
+                    c::tracker_addOutputBytes(&mut *tracker, packet.as_ptr(), &compat_socket)
+                };
+            }
+        })
+        .unwrap();
+
+        self.update_stats(|stats| {
+            stats.tx_bytes += packet.total_size() as u64;
+            stats.tx_packets += 1;
+        });
+
+        Some(packet)
     }
 
     fn push(&self, packet: PacketRc) {
-        let packet_ptr = packet.into_inner();
-        let current_time = Worker::current_time().unwrap();
-        unsafe {
-            c::networkinterface_push(
-                self.c_ptr.ptr(),
-                packet_ptr,
-                EmulatedTime::to_c_emutime(Some(current_time)),
-            )
-        };
-        unsafe { c::packet_unref(packet_ptr) };
+        Worker::with_breakpoints(|breakpoints| {
+            Worker::with_active_host(|host| {
+                breakpoints.packet_received(host, packet.dst_address().port())
+            });
+        });
+
+        self.update_stats(|stats| {
+            stats.rx_bytes += packet.total_size() as u64;
+            stats.rx_packets += 1;
+        });
+
+        let recv_time = Worker::current_time().unwrap();
+        Worker::with_active_host(|host| self.receive_packet(host, packet, recv_time)).unwrap();
     }
 }
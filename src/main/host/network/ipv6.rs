@@ -0,0 +1,126 @@
+//! IPv6 addresses for the simulated hosts. Shadow's network only carries IPv4 packets, so an
+//! `AF_INET6` socket is an IPv4 socket whose addresses are translated to and from IPv6 addresses
+//! when they're passed between the socket and the application. Each IPv4 address has one
+//! corresponding IPv6 address:
+//!
+//! - `127.0.0.1` is `::1`,
+//! - `0.0.0.0` is `::`,
+//! - and a host's address `a.b.c.d` is [`HOST_PREFIX`] followed by `a.b.c.d` (for example
+//!   `fd00::b00:1` for `11.0.0.1`).
+//!
+//! IPv4-mapped addresses (`::ffff:a.b.c.d`) are also accepted from the application, but are always
+//! reported back to it as the host's IPv6 address.
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+
+/// The prefix of the hosts' IPv6 addresses, which is followed by the host's IPv4 address in the last
+/// 32 bits. This is a unique local address prefix, which isn't routed on the internet. The shim's
+/// `getaddrinfo()` and the DNS hosts file use the same prefix.
+pub const HOST_PREFIX: Ipv6Addr = Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 0);
+
+/// The IPv6 address that corresponds to the IPv4 address.
+pub fn to_ipv6(addr: Ipv4Addr) -> Ipv6Addr {
+    if addr.is_loopback() {
+        return Ipv6Addr::LOCALHOST;
+    }
+    if addr.is_unspecified() {
+        return Ipv6Addr::UNSPECIFIED;
+    }
+
+    let mut octets = HOST_PREFIX.octets();
+    octets[12..].copy_from_slice(&addr.octets());
+    Ipv6Addr::from(octets)
+}
+
+/// The IPv4 address that corresponds to the IPv6 address, or `None` if the IPv6 address isn't one
+/// that Shadow can route.
+pub fn to_ipv4(addr: Ipv6Addr) -> Option<Ipv4Addr> {
+    if addr == Ipv6Addr::LOCALHOST {
+        return Some(Ipv4Addr::LOCALHOST);
+    }
+    if addr == Ipv6Addr::UNSPECIFIED {
+        return Some(Ipv4Addr::UNSPECIFIED);
+    }
+    if let Some(addr) = addr.to_ipv4_mapped() {
+        return Some(addr);
+    }
+
+    let octets = addr.octets();
+    if octets[..12] != HOST_PREFIX.octets()[..12] {
+        return None;
+    }
+
+    let addr = Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]);
+
+    // these have their own IPv6 addresses
+    if addr.is_loopback() || addr.is_unspecified() {
+        return None;
+    }
+
+    Some(addr)
+}
+
+/// The IPv6 socket address that corresponds to the IPv4 socket address.
+pub fn to_socket_addr_v6(addr: SocketAddrV4) -> SocketAddrV6 {
+    SocketAddrV6::new(to_ipv6(*addr.ip()), addr.port(), 0, 0)
+}
+
+/// The IPv4 socket address that corresponds to the IPv6 socket address, or `None` if the IPv6
+/// address isn't one that Shadow can route. The flow label and scope ID are ignored.
+pub fn to_socket_addr_v4(addr: SocketAddrV6) -> Option<SocketAddrV4> {
+    Some(SocketAddrV4::new(to_ipv4(*addr.ip())?, addr.port()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_special_addresses() {
+        assert_eq!(to_ipv6(Ipv4Addr::LOCALHOST), Ipv6Addr::LOCALHOST);
+        assert_eq!(to_ipv6(Ipv4Addr::UNSPECIFIED), Ipv6Addr::UNSPECIFIED);
+        assert_eq!(to_ipv4(Ipv6Addr::LOCALHOST), Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(to_ipv4(Ipv6Addr::UNSPECIFIED), Some(Ipv4Addr::UNSPECIFIED));
+    }
+
+    #[test]
+    fn test_host_addresses() {
+        let v4 = Ipv4Addr::new(11, 0, 0, 1);
+        let v6: Ipv6Addr = "fd00::b00:1".parse().unwrap();
+
+        assert_eq!(to_ipv6(v4), v6);
+        assert_eq!(to_ipv4(v6), Some(v4));
+
+        // the prefix can't hide a loopback or unspecified address
+        assert_eq!(to_ipv4("fd00::7f00:1".parse().unwrap()), None);
+        assert_eq!(to_ipv4(HOST_PREFIX), None);
+    }
+
+    #[test]
+    fn test_mapped_addresses() {
+        let v4 = Ipv4Addr::new(11, 0, 0, 1);
+        assert_eq!(to_ipv4(v4.to_ipv6_mapped()), Some(v4));
+        assert_eq!(
+            to_ipv4(Ipv4Addr::LOCALHOST.to_ipv6_mapped()),
+            Some(Ipv4Addr::LOCALHOST)
+        );
+    }
+
+    #[test]
+    fn test_other_addresses() {
+        assert_eq!(to_ipv4("2001:db8::1".parse().unwrap()), None);
+        assert_eq!(to_ipv4("fd00:1::b00:1".parse().unwrap()), None);
+        assert_eq!(to_ipv4("fe80::1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn test_socket_addresses() {
+        let v4 = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 1), 80);
+        let v6 = to_socket_addr_v6(v4);
+        assert_eq!(v6, "[fd00::b00:1]:80".parse().unwrap());
+        assert_eq!(to_socket_addr_v4(v6), Some(v4));
+
+        let scoped = SocketAddrV6::new(*v6.ip(), 80, 7, 2);
+        assert_eq!(to_socket_addr_v4(scoped), Some(v4));
+    }
+}
@@ -1,2 +1,10 @@
+pub mod arp;
+pub mod bridge;
+pub mod firewall;
 pub mod interface;
+pub mod ipv6;
 pub mod namespace;
+pub mod pcap_capture;
+pub mod proc_net;
+pub mod queuing;
+pub mod rtnetlink;
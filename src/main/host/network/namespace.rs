@@ -1,8 +1,9 @@
 use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::{CString, OsStr};
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4};
 use std::num::NonZeroU8;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, RangeInclusive};
 use std::sync::Arc;
 
 use atomic_refcell::AtomicRefCell;
@@ -13,11 +14,13 @@ use crate::core::support::configuration::QDiscMode;
 use crate::core::worker::Worker;
 use crate::cshadow;
 use crate::host::descriptor::socket::abstract_unix_ns::AbstractUnixNamespace;
+use crate::host::descriptor::socket::inet::raw::RawSocket;
+use crate::host::descriptor::socket::inet::InetSocket;
 use crate::host::network::interface::{NetworkInterface, PcapOptions};
+use crate::host::network::ipv6;
 
-// The start of our random port range in host order, used if application doesn't
-// specify the port it wants to bind to, and for client connections.
-const MIN_RANDOM_PORT: u16 = 10000;
+/// The all-hosts multicast group, which every interface is a member of (see RFC 1112).
+const ALL_HOSTS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 1);
 
 /// Represents a network namespace. Can be thought of as roughly equivalent to a Linux `struct net`.
 /// Shadow doesn't support multiple network namespaces, but this `NetworkNamespace` allows us to
@@ -34,10 +37,42 @@ pub struct NetworkNamespace {
     pub default_address: SyncSendPointer<cshadow::Address>,
     pub default_ip: Ipv4Addr,
 
+    // the range of ports in host order, used if the application doesn't specify the port it wants
+    // to bind to, and for client connections
+    local_port_range: RangeInclusive<u16>,
+    // whether TCP connections can take the port of a connection in the TIME_WAIT state
+    tcp_tw_reuse: bool,
+
+    // UDP counters for the whole host, like the "Udp:" lines of Linux's `/proc/net/snmp`
+    udp_stats: Cell<UdpStats>,
+
+    // the multicast groups that the interfaces have joined, mapped from the group and interface
+    // addresses to the number of sockets that joined the group on the interface
+    multicast_groups: RefCell<BTreeMap<(Ipv4Addr, Ipv4Addr), u32>>,
+
+    // the port ids of the bound netlink sockets
+    netlink_ports: RefCell<BTreeSet<u32>>,
+
     // used for debugging to make sure we've cleaned up before being dropped
     has_run_cleanup: Cell<bool>,
 }
 
+/// A host's UDP statistics. These have the same meaning as the counters of the same name in Linux's
+/// `/proc/net/snmp`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct UdpStats {
+    /// Datagrams read by applications.
+    pub in_datagrams: u64,
+    /// Datagrams received for a port with no socket.
+    pub no_ports: u64,
+    /// Datagrams that were dropped by the receiving socket.
+    pub in_errors: u64,
+    /// Datagrams sent by applications.
+    pub out_datagrams: u64,
+    /// Datagrams that were dropped because the receiving socket's buffer was full.
+    pub rcvbuf_errors: u64,
+}
+
 impl NetworkNamespace {
     /// # Safety
     ///
@@ -48,6 +83,8 @@ impl NetworkNamespace {
         public_ip: Ipv4Addr,
         pcap: Option<PcapOptions>,
         qdisc: QDiscMode,
+        local_port_range: RangeInclusive<u16>,
+        tcp_tw_reuse: bool,
         dns: *mut cshadow::DNS,
     ) -> Self {
         let (localhost, local_addr) = unsafe {
@@ -86,6 +123,11 @@ impl NetworkNamespace {
             internet: RefCell::new(internet),
             default_address: unsafe { SyncSendPointer::new(public_addr) },
             default_ip: public_ip,
+            local_port_range,
+            tcp_tw_reuse,
+            udp_stats: Cell::new(UdpStats::default()),
+            multicast_groups: RefCell::new(BTreeMap::new()),
+            netlink_ports: RefCell::new(BTreeSet::new()),
             has_run_cleanup: Cell::new(false),
         }
     }
@@ -106,15 +148,13 @@ impl NetworkNamespace {
         let addr = unsafe { cshadow::dns_register(dns, options.host_id, hostname, ip) };
         assert!(!addr.is_null());
 
-        let interface = unsafe {
-            NetworkInterface::new(
-                options.host_id,
-                addr,
-                name,
-                options.pcap.clone(),
-                options.qdisc,
-            )
-        };
+        let interface = NetworkInterface::new(
+            name,
+            options.host_id,
+            options.ip,
+            options.pcap.clone(),
+            options.qdisc,
+        );
 
         (interface, addr)
     }
@@ -139,12 +179,53 @@ impl NetworkNamespace {
         self.has_run_cleanup.set(true);
     }
 
-    /// Returns `None` if there is no such interface.
+    /// The IPv6 address of the host's `internet` interface.
+    pub fn default_ipv6(&self) -> Ipv6Addr {
+        ipv6::to_ipv6(self.default_ip)
+    }
+
+    /// Update the host's UDP statistics.
+    pub fn update_udp_stats(&self, f: impl FnOnce(&mut UdpStats)) {
+        let mut stats = self.udp_stats.get();
+        f(&mut stats);
+        self.udp_stats.set(stats);
+    }
+
+    /// The host's UDP statistics.
+    pub fn udp_stats(&self) -> UdpStats {
+        self.udp_stats.get()
+    }
+
+    /// The network interfaces, in the order of their interface indexes (which start at 1).
+    pub fn interfaces(&self) -> [&RefCell<NetworkInterface>; 2] {
+        [&self.localhost, &self.internet]
+    }
+
+    /// All inet sockets that are associated with one of the host's interfaces.
+    pub fn inet_sockets(&self) -> Vec<InetSocket> {
+        let mut sockets = self.localhost.borrow().inet_sockets();
+        sockets.extend(self.internet.borrow().inet_sockets());
+
+        // a socket may have associations with both interfaces, or more than one association with
+        // the same interface
+        let mut handles = std::collections::HashSet::new();
+        sockets.retain(|x| handles.insert(x.canonical_handle()));
+
+        sockets
+    }
+
+    /// Returns `None` if there is no such interface. IPv6 addresses are translated to the IPv4
+    /// address of the interface (see [`ipv6`]).
     #[track_caller]
     pub fn interface_borrow(
         &self,
-        addr: Ipv4Addr,
+        addr: impl Into<IpAddr>,
     ) -> Option<impl Deref<Target = NetworkInterface> + '_> {
+        let addr = match addr.into() {
+            IpAddr::V4(addr) => addr,
+            IpAddr::V6(addr) => ipv6::to_ipv4(addr)?,
+        };
+
         // Notes:
         // - The `is_loopback` matches all loopback addresses, but shadow will only work correctly
         //   with 127.0.0.1. Using any other loopback address will lead to problems.
@@ -161,12 +242,18 @@ impl NetworkNamespace {
         }
     }
 
-    /// Returns `None` if there is no such interface.
+    /// Returns `None` if there is no such interface. IPv6 addresses are translated to the IPv4
+    /// address of the interface (see [`ipv6`]).
     #[track_caller]
     pub fn interface_borrow_mut(
         &self,
-        addr: Ipv4Addr,
+        addr: impl Into<IpAddr>,
     ) -> Option<impl Deref<Target = NetworkInterface> + DerefMut + '_> {
+        let addr = match addr.into() {
+            IpAddr::V4(addr) => addr,
+            IpAddr::V6(addr) => ipv6::to_ipv4(addr)?,
+        };
+
         // Notes:
         // - The `is_loopback` matches all loopback addresses, but shadow will only work correctly
         //   with 127.0.0.1. Using any other loopback address will lead to problems.
@@ -222,7 +309,7 @@ impl NetworkNamespace {
         // if choosing randomly doesn't succeed within 10 tries, then we have already
         // allocated a lot of ports (>90% on average). then we fall back to linear search.
         for _ in 0..10 {
-            let random_port = rng.gen_range(MIN_RANDOM_PORT..=u16::MAX);
+            let random_port = rng.gen_range(self.local_port_range.clone());
 
             // `is_addr_in_use` will check all interfaces in the case of INADDR_ANY
             let specific_in_use = self
@@ -247,8 +334,9 @@ impl NetworkNamespace {
         // now if we tried too many times and still don't have a port, fall back
         // to a linear search to make sure we get a free port if we have one.
         // but start from a random port instead of the min.
-        let start = rng.gen_range(MIN_RANDOM_PORT..=u16::MAX);
-        for port in (start..=u16::MAX).chain(MIN_RANDOM_PORT..start) {
+        let (min, max) = (*self.local_port_range.start(), *self.local_port_range.end());
+        let start = rng.gen_range(min..=max);
+        for port in (start..=max).chain(min..start) {
             let specific_in_use = self
                 .is_addr_in_use(protocol_type, SocketAddrV4::new(interface_ip, port), peer)
                 .unwrap_or(true);
@@ -264,19 +352,56 @@ impl NetworkNamespace {
             }
         }
 
-        log::warn!("unable to find free ephemeral port for {protocol_type} peer {peer}");
+        // like linux with `tcp_tw_reuse`, a new connection can take the port of an old connection
+        // to the same peer that's in the TIME_WAIT state
+        if self.tcp_tw_reuse
+            && protocol_type == cshadow::_ProtocolType_PTCP
+            && !interface_ip.is_unspecified()
+            && !peer.ip().is_unspecified()
+        {
+            for port in (start..=max).chain(min..start) {
+                let local = SocketAddrV4::new(interface_ip, port);
+                let generic_in_use = self
+                    .is_addr_in_use(
+                        protocol_type,
+                        local,
+                        SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+                    )
+                    .unwrap_or(true);
+                if !generic_in_use && self.reuse_time_wait(local, peer) {
+                    log::debug!("Reusing the port of TIME_WAIT connection {local} <-> {peer}");
+                    return Some(port);
+                }
+            }
+        }
+
+        log::debug!("unable to find free ephemeral port for {protocol_type} peer {peer}");
         None
     }
 
+    /// If the TCP connection with the given addresses is in the TIME_WAIT state and its port can be
+    /// reused, closes the connection and returns true.
+    fn reuse_time_wait(&self, local: SocketAddrV4, peer: SocketAddrV4) -> bool {
+        let socket = self.interface_borrow(*local.ip()).and_then(|iface| {
+            iface.associated_inet_socket(cshadow::_ProtocolType_PTCP, local.port(), peer)
+        });
+
+        // the new tcp stack doesn't support reusing connections in the TIME_WAIT state
+        let Some(InetSocket::LegacyTcp(socket)) = socket else {
+            return false;
+        };
+
+        // closing the connection will disassociate it from the interface
+        let tcp = socket.borrow().as_legacy_tcp();
+        Worker::with_active_host(|host| unsafe { cshadow::tcp_reuseTimeWait(tcp, host) } != 0)
+            .unwrap()
+    }
+
     /// Associate the socket with any applicable network interfaces. The socket will be
     /// automatically disassociated when the returned handle is dropped.
-    ///
-    /// # Safety
-    ///
-    /// Pointer args must be safely dereferenceable.
-    pub unsafe fn associate_interface(
+    pub fn associate_interface(
         &self,
-        socket: *const cshadow::CompatSocket,
+        socket: &InetSocket,
         protocol: cshadow::ProtocolType,
         bind_addr: SocketAddrV4,
         peer_addr: SocketAddrV4,
@@ -300,11 +425,13 @@ impl NetworkNamespace {
             protocol,
             local_addr: bind_addr,
             remote_addr: peer_addr,
+            socket: socket.canonical_handle(),
         }
     }
 
     /// Disassociate the socket associated using the local and remote addresses from all network
-    /// interfaces.
+    /// interfaces. If the addresses are shared by several sockets, only the socket with the
+    /// canonical handle `socket` is disassociated, or all of them if `socket` is `None`.
     ///
     /// Is only public so that it can be called from `host_disassociateInterface`. Normally this
     /// should only be called from the [`AssociationHandle`].
@@ -313,23 +440,180 @@ impl NetworkNamespace {
         protocol: cshadow::ProtocolType,
         bind_addr: SocketAddrV4,
         peer_addr: SocketAddrV4,
+        socket: Option<usize>,
     ) {
         if bind_addr.ip().is_unspecified() {
             // need to disassociate all interfaces
             self.localhost
                 .borrow()
-                .disassociate(protocol, bind_addr.port(), peer_addr);
+                .disassociate(protocol, bind_addr.port(), peer_addr, socket);
 
             self.internet
                 .borrow()
-                .disassociate(protocol, bind_addr.port(), peer_addr);
+                .disassociate(protocol, bind_addr.port(), peer_addr, socket);
         } else {
             // TODO: return error if interface does not exist
             if let Some(iface) = self.interface_borrow(*bind_addr.ip()) {
-                iface.disassociate(protocol, bind_addr.port(), peer_addr);
+                iface.disassociate(protocol, bind_addr.port(), peer_addr, socket);
             }
         }
     }
+
+    /// Whether an address that's in use can be shared with a new socket. Like Linux, a UDP socket
+    /// can bind to the same address as other UDP sockets if they all set `SO_REUSEADDR` before
+    /// binding. The caller must check the new socket's option.
+    pub fn is_addr_shareable(
+        &self,
+        protocol_type: cshadow::ProtocolType,
+        src: SocketAddrV4,
+        dst: SocketAddrV4,
+    ) -> bool {
+        if protocol_type != cshadow::_ProtocolType_PUDP {
+            return false;
+        }
+
+        let sockets = if src.ip().is_unspecified() {
+            let mut sockets =
+                self.localhost
+                    .borrow()
+                    .associated_inet_sockets(protocol_type, src.port(), dst);
+            sockets.extend(self.internet.borrow().associated_inet_sockets(
+                protocol_type,
+                src.port(),
+                dst,
+            ));
+            sockets
+        } else {
+            match self.interface_borrow(*src.ip()) {
+                Some(i) => i.associated_inet_sockets(protocol_type, src.port(), dst),
+                None => return false,
+            }
+        };
+
+        sockets.iter().all(|x| match x {
+            InetSocket::Udp(x) => x.borrow().reuse_addr(),
+            _ => false,
+        })
+    }
+
+    /// Associate the raw socket with all network interfaces, so that it receives a copy of every
+    /// packet with the IP protocol `protocol` that the host receives. The socket will be
+    /// automatically disassociated when the returned [`RawAssociationHandle`] is dropped.
+    pub fn associate_raw(
+        &self,
+        socket: &Arc<AtomicRefCell<RawSocket>>,
+        protocol: u8,
+    ) -> RawAssociationHandle {
+        for iface in self.interfaces() {
+            iface.borrow().associate_raw(socket, protocol);
+        }
+
+        RawAssociationHandle {
+            handle: Arc::as_ptr(socket) as usize,
+        }
+    }
+
+    /// Disassociate the raw socket with the canonical handle `handle` from all network interfaces.
+    /// Should only be called from the [`RawAssociationHandle`].
+    fn disassociate_raw(&self, handle: usize) {
+        for iface in self.interfaces() {
+            iface.borrow().disassociate_raw(handle);
+        }
+    }
+
+    /// The address of the interface that a socket option such as `IP_ADD_MEMBERSHIP` refers to.
+    /// Like Linux, the interface is chosen by its index `ifindex` if it's non-zero, and otherwise
+    /// by its address `addr`. If both are unspecified, the internet interface is chosen since
+    /// that's where the default route would send multicast packets. Returns `None` if there is no
+    /// such interface.
+    pub fn multicast_interface(&self, addr: Ipv4Addr, ifindex: i32) -> Option<Ipv4Addr> {
+        // the interface indexes are the same as in `Self::interfaces`
+        match ifindex {
+            0 => {}
+            1 => return Some(Ipv4Addr::LOCALHOST),
+            2 => return Some(self.default_ip),
+            _ => return None,
+        }
+
+        if addr.is_loopback() {
+            Some(Ipv4Addr::LOCALHOST)
+        } else if addr == self.default_ip || addr.is_unspecified() {
+            Some(self.default_ip)
+        } else {
+            None
+        }
+    }
+
+    /// Join the multicast group `group` on the interface with address `interface_ip`, so that the
+    /// host receives packets sent to the group. The group will be left when the returned
+    /// [`MulticastMembership`] is dropped, unless other memberships of the same group on the same
+    /// interface remain.
+    pub fn join_multicast_group(
+        &self,
+        group: Ipv4Addr,
+        interface_ip: Ipv4Addr,
+    ) -> MulticastMembership {
+        assert!(group.is_multicast());
+        *self
+            .multicast_groups
+            .borrow_mut()
+            .entry((group, interface_ip))
+            .or_insert(0) += 1;
+
+        MulticastMembership {
+            group,
+            interface_ip,
+        }
+    }
+
+    /// Should only be called from the [`MulticastMembership`].
+    fn leave_multicast_group(&self, group: Ipv4Addr, interface_ip: Ipv4Addr) {
+        let mut groups = self.multicast_groups.borrow_mut();
+        let count = groups.get_mut(&(group, interface_ip)).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            groups.remove(&(group, interface_ip));
+        }
+    }
+
+    /// Whether the interface with address `interface_ip` is a member of the multicast group
+    /// `group`.
+    pub fn is_multicast_member(&self, group: Ipv4Addr, interface_ip: Ipv4Addr) -> bool {
+        group == ALL_HOSTS_GROUP
+            || self
+                .multicast_groups
+                .borrow()
+                .contains_key(&(group, interface_ip))
+    }
+
+    /// Bind a netlink socket to the port id `portid`. The port id will be released when the
+    /// returned [`NetlinkPortHandle`] is dropped. Returns `None` if the port id is already in use.
+    pub fn bind_netlink_port(&self, portid: u32) -> Option<NetlinkPortHandle> {
+        if !self.netlink_ports.borrow_mut().insert(portid) {
+            return None;
+        }
+        Some(NetlinkPortHandle { portid })
+    }
+
+    /// A free port id for a netlink socket that's bound without choosing a port id. Like Linux,
+    /// this is the process id `pid` if it's free, and otherwise a negative port id counting down
+    /// from -4096.
+    pub fn get_free_netlink_port(&self, pid: u32) -> Option<u32> {
+        let ports = self.netlink_ports.borrow();
+        if pid != 0 && !ports.contains(&pid) {
+            return Some(pid);
+        }
+        (i32::MIN..=-4096)
+            .rev()
+            .map(|x| x as u32)
+            .find(|x| !ports.contains(x))
+    }
+
+    /// Should only be called from the [`NetlinkPortHandle`].
+    fn unbind_netlink_port(&self, portid: u32) {
+        let removed = self.netlink_ports.borrow_mut().remove(&portid);
+        debug_assert!(removed);
+    }
 }
 
 impl std::ops::Drop for NetworkNamespace {
@@ -369,6 +653,8 @@ pub struct AssociationHandle {
     protocol: cshadow::ProtocolType,
     local_addr: SocketAddrV4,
     remote_addr: SocketAddrV4,
+    /// The canonical handle of the associated socket.
+    socket: usize,
 }
 
 impl AssociationHandle {
@@ -388,8 +674,78 @@ impl std::ops::Drop for AssociationHandle {
                 self.protocol,
                 self.local_addr,
                 self.remote_addr,
+                Some(self.socket),
             );
         })
         .unwrap();
     }
 }
+
+/// A handle for a raw socket's association with the network interfaces. The association will be
+/// dissolved when this handle is dropped.
+#[derive(Debug)]
+pub struct RawAssociationHandle {
+    /// The socket's canonical handle.
+    handle: usize,
+}
+
+impl std::ops::Drop for RawAssociationHandle {
+    fn drop(&mut self) {
+        Worker::with_active_host(|host| {
+            host.network_namespace_borrow()
+                .disassociate_raw(self.handle);
+        })
+        .unwrap();
+    }
+}
+
+/// A membership of a multicast group on a network interface. The interface will leave the group
+/// when this handle is dropped, unless it has other memberships of the same group.
+#[derive(Debug)]
+pub struct MulticastMembership {
+    group: Ipv4Addr,
+    interface_ip: Ipv4Addr,
+}
+
+impl MulticastMembership {
+    pub fn group(&self) -> Ipv4Addr {
+        self.group
+    }
+
+    pub fn interface_ip(&self) -> Ipv4Addr {
+        self.interface_ip
+    }
+}
+
+impl std::ops::Drop for MulticastMembership {
+    fn drop(&mut self) {
+        Worker::with_active_host(|host| {
+            host.network_namespace_borrow()
+                .leave_multicast_group(self.group, self.interface_ip);
+        })
+        .unwrap();
+    }
+}
+
+/// A netlink socket's binding to a port id. The port id will be released when this handle is
+/// dropped.
+#[derive(Debug)]
+pub struct NetlinkPortHandle {
+    portid: u32,
+}
+
+impl NetlinkPortHandle {
+    pub fn portid(&self) -> u32 {
+        self.portid
+    }
+}
+
+impl std::ops::Drop for NetlinkPortHandle {
+    fn drop(&mut self) {
+        Worker::with_active_host(|host| {
+            host.network_namespace_borrow()
+                .unbind_netlink_port(self.portid);
+        })
+        .unwrap();
+    }
+}
@@ -0,0 +1,412 @@
+//! Packet captures for network interfaces that keep the simulation running when the pcap file
+//! can't be written, for example because the disk is full.
+
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::support::configuration::{OutputCompression, PcapErrorAction, PcapPayload};
+use crate::host::network::interface::PcapOptions;
+use crate::utility::compression::{self, CompressedWriter};
+use crate::utility::pcap_writer::{PacketDisplay, PcapWriter, LINKTYPE_ETHERNET};
+
+/// How long to wait after an error before trying to continue a capture in a new file, when using
+/// [`PcapErrorAction::Rotate`]. This prevents us from trying to create a new file for every
+/// packet while the disk is full.
+const RETRY_INTERVAL: SimulationTime = SimulationTime::SECOND;
+
+/// Captures packets to a pcap file named after the interface. If the file can't be created or
+/// written to, the error is handled according to the configured [`PcapErrorAction`].
+pub struct PcapCapture {
+    dir: PathBuf,
+    interface_name: String,
+    capture_size_bytes: u32,
+    payload: PcapPayload,
+    compression: OutputCompression,
+    error_action: PcapErrorAction,
+    state: State,
+    /// The number of files that we've tried to create, used to name rotated files.
+    num_files: u32,
+}
+
+type Writer = PcapWriter<RecordBuffer<CompressedWriter<BufWriter<File>>>>;
+
+enum State {
+    Open(Writer),
+    /// The previous file was closed after an error, and we'll try to open a new file at this
+    /// time.
+    Paused {
+        retry_at: EmulatedTime,
+    },
+    Disabled,
+}
+
+impl PcapCapture {
+    /// Start capturing ethernet frames to "<interface_name>.pcap" in the directory given by
+    /// `options`. If the file can't be created, the error is handled as if `now` is the current
+    /// time.
+    pub fn new(interface_name: &str, options: &PcapOptions, now: EmulatedTime) -> Self {
+        let mut capture = Self {
+            dir: options.path.clone(),
+            interface_name: interface_name.to_string(),
+            capture_size_bytes: options.capture_size_bytes,
+            payload: options.payload,
+            compression: options.compression,
+            error_action: options.error_action,
+            state: State::Disabled,
+            num_files: 0,
+        };
+
+        match capture.open() {
+            Ok(writer) => capture.state = State::Open(writer),
+            Err(e) => capture.handle_error(&e, now),
+        }
+
+        capture
+    }
+
+    /// The path of the next file to open. The first file is "<interface_name>.pcap", and rotated
+    /// files are "<interface_name>.1.pcap", "<interface_name>.2.pcap", etc. The compression
+    /// format's extension is added if the file is compressed.
+    fn next_path(&self) -> PathBuf {
+        let name = &self.interface_name;
+        let path = match self.num_files {
+            0 => self.dir.join(format!("{name}.pcap")),
+            n => self.dir.join(format!("{name}.{n}.pcap")),
+        };
+        compression::compressed_path(&path, self.compression)
+    }
+
+    fn open(&mut self) -> std::io::Result<Writer> {
+        let path = self.next_path();
+        self.num_files += 1;
+
+        log::debug!(
+            "Capturing packets on interface '{}' to '{}'",
+            self.interface_name,
+            path.display()
+        );
+
+        let file = File::create(&path)?;
+        let writer = CompressedWriter::new(BufWriter::new(file), self.compression)?;
+        let mut writer = PcapWriter::new(
+            RecordBuffer::new(writer),
+            self.capture_size_bytes,
+            LINKTYPE_ETHERNET,
+        )?;
+        writer.get_mut().commit()?;
+        Ok(writer)
+    }
+
+    /// Write an ethernet frame of `packet_len` bytes to the capture. If the frame couldn't be
+    /// written, the error has already been handled (and logged) according to the error action, and
+    /// is returned so that the caller can record it. Frames are silently skipped while the capture
+    /// is paused or disabled.
+    pub fn capture(
+        &mut self,
+        packet: &impl PacketDisplay,
+        packet_len: u32,
+        now: EmulatedTime,
+    ) -> std::io::Result<()> {
+        if let State::Paused { retry_at } = self.state {
+            if now < retry_at {
+                return Ok(());
+            }
+            match self.open() {
+                Ok(writer) => self.state = State::Open(writer),
+                Err(e) => {
+                    self.handle_error(&e, now);
+                    return Err(e);
+                }
+            }
+        }
+
+        let State::Open(writer) = &mut self.state else {
+            return Ok(());
+        };
+
+        let ts = now.to_abs_simtime();
+        let ts_sec: u32 = ts.as_secs().try_into().unwrap_or(u32::MAX);
+        let ts_usec: u32 = (ts.as_micros() % 1_000_000).try_into().unwrap();
+
+        let rv = writer
+            .write_packet_fmt(ts_sec, ts_usec, packet_len, |writer| {
+                packet.display_bytes(writer, self.payload)
+            })
+            .and_then(|()| writer.get_mut().commit());
+
+        if let Err(e) = &rv {
+            self.handle_error(e, now);
+        }
+
+        rv
+    }
+
+    fn handle_error(&mut self, e: &std::io::Error, now: EmulatedTime) {
+        let name = &self.interface_name;
+
+        // dropping the writer will close the file, which may be corrupt after the error
+        match self.error_action {
+            PcapErrorAction::Disable => {
+                log::warn!(
+                    "Pcap logging error on interface '{name}': {e}; stopping pcap logging for \
+                    this interface"
+                );
+                self.state = State::Disabled;
+            }
+            PcapErrorAction::Rotate => {
+                log::warn!(
+                    "Pcap logging error on interface '{name}': {e}; will try to continue pcap \
+                    logging in '{}' after {RETRY_INTERVAL:?}",
+                    self.next_path().display(),
+                );
+                self.state = State::Paused {
+                    retry_at: now + RETRY_INTERVAL,
+                };
+            }
+            PcapErrorAction::Abort => {
+                panic!("Pcap logging error on interface '{name}': {e}");
+            }
+        }
+    }
+}
+
+/// Buffers a pcap record in memory until it's committed. This allows [`PcapWriter`] to seek back
+/// and update the record's captured length after writing the packet, which isn't possible when
+/// writing to a compressed stream.
+struct RecordBuffer<W: Write> {
+    inner: W,
+    buf: Cursor<Vec<u8>>,
+    /// The number of bytes that have been committed to `inner`.
+    committed: u64,
+}
+
+impl<W: Write> RecordBuffer<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buf: Cursor::new(Vec::new()),
+            committed: 0,
+        }
+    }
+
+    /// Write the buffered bytes to the inner writer. We can no longer seek to these bytes.
+    fn commit(&mut self) -> std::io::Result<()> {
+        let buf = self.buf.get_mut();
+        self.inner.write_all(buf)?;
+        self.committed += u64::try_from(buf.len()).unwrap();
+        buf.clear();
+        self.buf.set_position(0);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for RecordBuffer<W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.write(data)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.commit()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Seek for RecordBuffer<W> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let buf_len = u64::try_from(self.buf.get_ref().len()).unwrap();
+
+        // the position relative to the start of the buffer
+        let buf_pos = match pos {
+            SeekFrom::Start(x) => x.checked_sub(self.committed),
+            SeekFrom::Current(x) => self.buf.position().checked_add_signed(x),
+            SeekFrom::End(x) => buf_len.checked_add_signed(x),
+        };
+
+        // we can't seek to committed bytes
+        let Some(buf_pos) = buf_pos else {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        };
+
+        self.buf.set_position(buf_pos);
+        Ok(self.committed + buf_pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    use super::*;
+    use crate::cshadow;
+    use crate::network::packet::PacketRc;
+    use crate::utility::pcap_writer::{EthernetFrame, ETHERNET_HEADER_LEN};
+
+    struct MockPacket;
+
+    impl PacketDisplay for MockPacket {
+        fn display_bytes(
+            &self,
+            mut writer: impl Write,
+            _payload: PcapPayload,
+        ) -> std::io::Result<()> {
+            writer.write_all(&[1, 2, 3, 4])
+        }
+    }
+
+    fn time(secs: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_secs(secs)
+    }
+
+    fn options(path: PathBuf, error_action: PcapErrorAction) -> PcapOptions {
+        PcapOptions {
+            path,
+            capture_size_bytes: 65535,
+            payload: PcapPayload::Keep,
+            compression: OutputCompression::None,
+            error_action,
+        }
+    }
+
+    #[test]
+    fn disable() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+
+        let mut capture = PcapCapture::new(
+            "eth0",
+            &options(missing.clone(), PcapErrorAction::Disable),
+            time(0),
+        );
+        std::fs::create_dir(&missing).unwrap();
+
+        // the capture stays disabled even after the directory exists
+        assert!(capture.capture(&MockPacket, 4, time(10)).is_ok());
+        assert!(!missing.join("eth0.pcap").exists());
+        assert!(!missing.join("eth0.1.pcap").exists());
+    }
+
+    #[test]
+    fn rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+
+        let mut capture = PcapCapture::new(
+            "eth0",
+            &options(missing.clone(), PcapErrorAction::Rotate),
+            time(0),
+        );
+
+        // paused until the retry interval has passed
+        assert!(capture.capture(&MockPacket, 4, time(0)).is_ok());
+
+        // the directory still doesn't exist
+        assert!(capture.capture(&MockPacket, 4, time(1)).is_err());
+
+        // continue in a new file once the directory exists
+        std::fs::create_dir(&missing).unwrap();
+        assert!(capture.capture(&MockPacket, 4, time(2)).is_ok());
+        assert!(capture.capture(&MockPacket, 4, time(3)).is_ok());
+        drop(capture);
+
+        let header_len = 24;
+        let record_len = 16 + 4;
+        let len = std::fs::metadata(missing.join("eth0.2.pcap"))
+            .unwrap()
+            .len();
+        assert_eq!(len, header_len + 2 * record_len);
+    }
+
+    #[test]
+    fn compressed() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut options = options(dir.path().to_owned(), PcapErrorAction::Disable);
+        options.compression = OutputCompression::Zstd;
+
+        let mut capture = PcapCapture::new("eth0", &options, time(0));
+        assert!(capture.capture(&MockPacket, 4, time(1)).is_ok());
+        assert!(capture.capture(&MockPacket, 4, time(2)).is_ok());
+        drop(capture);
+
+        let bytes = std::fs::read(dir.path().join("eth0.pcap.zst")).unwrap();
+        let bytes = zstd::decode_all(&bytes[..]).unwrap();
+
+        let header_len = 24;
+        let record_len = 16 + 4;
+        assert_eq!(bytes.len(), header_len + 2 * record_len);
+
+        // the captured length was updated after the packet was written
+        let captured_len = u32::from_ne_bytes(bytes[header_len + 8..][..4].try_into().unwrap());
+        assert_eq!(captured_len, 4);
+    }
+
+    /// Capture a UDP packet with a 5 byte payload using the given payload mode. Returns the
+    /// captured length, original length, and data of the pcap record.
+    fn capture_udp_packet(payload: PcapPayload) -> (u32, u32, Vec<u8>) {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut options = options(dir.path().to_owned(), PcapErrorAction::Disable);
+        options.payload = payload;
+
+        let mut packet = PacketRc::from_raw(unsafe { cshadow::packet_new_inner(1, 1) });
+        let src = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 1), 1000);
+        let dst = SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 2000);
+        packet.set_udp(src, dst);
+        packet.set_payload(b"hello", 1);
+
+        let frame = EthernetFrame {
+            src: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            dst: [0x02, 0x00, 0x00, 0x00, 0x00, 0x02],
+            packet: &packet,
+        };
+        let packet_len = ETHERNET_HEADER_LEN + u32::try_from(packet.total_size()).unwrap();
+
+        let mut capture = PcapCapture::new("eth0", &options, time(0));
+        capture.capture(&frame, packet_len, time(1)).unwrap();
+        drop(capture);
+
+        let bytes = std::fs::read(dir.path().join("eth0.pcap")).unwrap();
+
+        // skip the pcap header
+        let record = &bytes[24..];
+        let captured_len = u32::from_ne_bytes(record[8..][..4].try_into().unwrap());
+        let original_len = u32::from_ne_bytes(record[12..][..4].try_into().unwrap());
+        let data = record[16..].to_vec();
+        assert_eq!(data.len(), usize::try_from(captured_len).unwrap());
+
+        (captured_len, original_len, data)
+    }
+
+    #[test]
+    fn payload() {
+        // the ethernet, IPv4, and UDP headers
+        let headers_len = ETHERNET_HEADER_LEN + 20 + 8;
+        let packet_len = headers_len + 5;
+        let payload_start = usize::try_from(headers_len).unwrap();
+
+        let (captured_len, original_len, data) = capture_udp_packet(PcapPayload::Keep);
+        assert_eq!((captured_len, original_len), (packet_len, packet_len));
+        assert_eq!(&data[payload_start..], b"hello");
+
+        // the payload is still captured, but is all zeroes
+        let (captured_len, original_len, data) = capture_udp_packet(PcapPayload::Zero);
+        assert_eq!((captured_len, original_len), (packet_len, packet_len));
+        assert_eq!(&data[payload_start..], [0; 5]);
+
+        // only the headers are captured, but the original length still includes the payload
+        let (captured_len, original_len, _data) = capture_udp_packet(PcapPayload::Truncate);
+        assert_eq!((captured_len, original_len), (headers_len, packet_len));
+    }
+
+    #[test]
+    #[should_panic]
+    fn abort() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        PcapCapture::new("eth0", &options(missing, PcapErrorAction::Abort), time(0));
+    }
+}
@@ -0,0 +1,250 @@
+//! The emulated `/proc/net/*` files. These describe a host's simulated sockets and network
+//! interfaces in the same format as Linux, so that tools like `netstat` and applications that
+//! monitor themselves read the simulated state rather than the state of the machine running Shadow.
+
+use std::fmt::Write;
+use std::net::SocketAddrV4;
+
+use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::network::namespace::NetworkNamespace;
+
+/// The socket states that Linux uses in `/proc/net/tcp` and `/proc/net/udp`, from
+/// `include/net/tcp_states.h`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SocketState {
+    Established = 1,
+    SynSent = 2,
+    SynRecv = 3,
+    FinWait1 = 4,
+    FinWait2 = 5,
+    TimeWait = 6,
+    Close = 7,
+    CloseWait = 8,
+    LastAck = 9,
+    Listen = 10,
+    Closing = 11,
+}
+
+impl SocketState {
+    /// Convert from a `tcp_info.tcpi_state` value.
+    pub fn from_tcpi_state(state: u8) -> Option<Self> {
+        Some(match state {
+            1 => Self::Established,
+            2 => Self::SynSent,
+            3 => Self::SynRecv,
+            4 => Self::FinWait1,
+            5 => Self::FinWait2,
+            6 => Self::TimeWait,
+            7 => Self::Close,
+            8 => Self::CloseWait,
+            9 => Self::LastAck,
+            10 => Self::Listen,
+            11 => Self::Closing,
+            _ => return None,
+        })
+    }
+}
+
+/// A socket's line in `/proc/net/tcp`, `/proc/net/udp`, `/proc/net/icmp`, or `/proc/net/raw`.
+#[derive(Debug, Clone)]
+pub struct ProcNetEntry {
+    pub local: SocketAddrV4,
+    pub peer: SocketAddrV4,
+    pub state: SocketState,
+    /// For a listening socket this is the maximum length of the accept queue, otherwise it's the
+    /// number of bytes in the send buffer.
+    pub tx_queue: u32,
+    /// For a listening socket this is the length of the accept queue, otherwise it's the number of
+    /// bytes in the receive buffer.
+    pub rx_queue: u32,
+    /// The number of packets that the socket dropped.
+    pub drops: u32,
+}
+
+/// The contents of the host's `/proc/net/<name>` file, or `None` if the file isn't emulated.
+pub fn file_contents(net_ns: &NetworkNamespace, name: &[u8]) -> Option<String> {
+    match name {
+        b"dev" => Some(dev(net_ns)),
+        b"snmp" => Some(snmp(net_ns)),
+        b"tcp" => Some(tcp(net_ns)),
+        b"udp" => Some(datagram(net_ns, |x| matches!(x, InetSocket::Udp(_)))),
+        b"icmp" => Some(datagram(net_ns, |x| matches!(x, InetSocket::Icmp(_)))),
+        b"raw" => Some(datagram(net_ns, |x| matches!(x, InetSocket::Raw(_)))),
+        _ => None,
+    }
+}
+
+fn dev(net_ns: &NetworkNamespace) -> String {
+    let mut s = String::new();
+    s.push_str(concat!(
+        "Inter-|   Receive                                                |  Transmit\n",
+        " face |bytes    packets errs drop fifo frame compressed multicast|",
+        "bytes    packets errs drop fifo colls carrier compressed\n",
+    ));
+
+    for interface in net_ns.interfaces() {
+        let interface = interface.borrow();
+        let stats = interface.stats();
+        writeln!(
+            s,
+            "{:>6}:{:>8} {:>7} {:>4} {:>4} {:>4} {:>5} {:>10} {:>9} {:>8} {:>7} {:>4} {:>4} {:>4} {:>5} {:>7} {:>10}",
+            interface.name(),
+            stats.rx_bytes,
+            stats.rx_packets,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            stats.tx_bytes,
+            stats.tx_packets,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        )
+        .unwrap();
+    }
+
+    s
+}
+
+fn snmp(net_ns: &NetworkNamespace) -> String {
+    // only the UDP counters are emulated
+    let stats = net_ns.udp_stats();
+    format!(
+        concat!(
+            "Udp: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors",
+            " InCsumErrors IgnoredMulti MemErrors\n",
+            "Udp: {} {} {} {} {} 0 0 0 0\n",
+        ),
+        stats.in_datagrams,
+        stats.no_ports,
+        stats.in_errors,
+        stats.out_datagrams,
+        stats.rcvbuf_errors,
+    )
+}
+
+fn tcp(net_ns: &NetworkNamespace) -> String {
+    // linux pads each line to this width
+    const WIDTH: usize = 149;
+
+    let mut s = String::new();
+    push_padded(
+        &mut s,
+        "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode",
+        WIDTH,
+    );
+
+    let entries = net_ns
+        .inet_sockets()
+        .iter()
+        .filter(|x| matches!(x, InetSocket::LegacyTcp(_) | InetSocket::Tcp(_)))
+        .map(|x| x.borrow().proc_net_entry())
+        .collect::<Vec<_>>();
+
+    for (i, entry) in sorted(entries).iter().enumerate() {
+        let line = format!(
+            "{i:>4}: {} {} {:02X} {:08X}:{:08X} 00:00000000 00000000 {:>5} {:>8} {} 1 0000000000000000 0 0 0 0 -1",
+            format_addr(entry.local),
+            format_addr(entry.peer),
+            entry.state as u8,
+            entry.tx_queue,
+            entry.rx_queue,
+            0,
+            0,
+            0,
+        );
+        push_padded(&mut s, &line, WIDTH);
+    }
+
+    s
+}
+
+/// The `/proc/net/udp`, `/proc/net/icmp`, or `/proc/net/raw` file, which have the same format,
+/// listing the sockets for which `filter` returns true.
+fn datagram(net_ns: &NetworkNamespace, filter: impl Fn(&InetSocket) -> bool) -> String {
+    // linux pads each line to this width
+    const WIDTH: usize = 127;
+
+    let mut s = String::new();
+    push_padded(
+        &mut s,
+        "   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops",
+        WIDTH,
+    );
+
+    let entries = net_ns
+        .inet_sockets()
+        .iter()
+        .filter(|x| filter(x))
+        .map(|x| x.borrow().proc_net_entry())
+        .collect::<Vec<_>>();
+
+    for (i, entry) in sorted(entries).iter().enumerate() {
+        let line = format!(
+            "{i:>5}: {} {} {:02X} {:08X}:{:08X} 00:00000000 00000000 {:>5} {:>8} {} 2 0000000000000000 {}",
+            format_addr(entry.local),
+            format_addr(entry.peer),
+            entry.state as u8,
+            entry.tx_queue,
+            entry.rx_queue,
+            0,
+            0,
+            0,
+            entry.drops,
+        );
+        push_padded(&mut s, &line, WIDTH);
+    }
+
+    s
+}
+
+/// Sort the entries so that the file contents don't depend on the order of the interfaces' hash
+/// tables.
+fn sorted(mut entries: Vec<ProcNetEntry>) -> Vec<ProcNetEntry> {
+    entries.sort_by_key(|x| (x.local.port(), *x.local.ip(), x.peer.port(), *x.peer.ip()));
+    entries
+}
+
+/// Format an address like linux, which prints the IP address's bytes in network order as a
+/// little-endian integer.
+fn format_addr(addr: SocketAddrV4) -> String {
+    let ip = u32::from_le_bytes(addr.ip().octets());
+    format!("{ip:08X}:{:04X}", addr.port())
+}
+
+fn push_padded(s: &mut String, line: &str, width: usize) {
+    writeln!(s, "{line:<width$}").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_format_addr() {
+        assert_eq!(
+            format_addr(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8080)),
+            "0100007F:1F90"
+        );
+        assert_eq!(
+            format_addr(SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, 2), 53)),
+            "0200000B:0035"
+        );
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut s = String::new();
+        push_padded(&mut s, "abc", 5);
+        assert_eq!(s, "abc  \n");
+    }
+}
@@ -0,0 +1,225 @@
+//! Queuing disciplines used by a network interface to choose which socket to send the next packet
+//! from.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use crate::core::support::configuration::QDiscMode;
+use crate::host::network::interface::FifoPacketPriority;
+
+/// A queue of items (usually sockets) that have packets to send. Each item is identified by a
+/// handle, and an item can only be in the queue once.
+pub struct SendQueue<T> {
+    queue: Queue,
+    /// The items in the queue, so that we can check if an item is already queued without searching
+    /// the queue.
+    items: HashMap<usize, QueuedItem<T>>,
+}
+
+enum Queue {
+    RoundRobin(VecDeque<usize>),
+    /// The handles ordered by their priority, and then by a sequence number so that items with the
+    /// same priority are returned in the order that they were pushed. When an item's priority
+    /// changes a new entry is pushed, and the item's old entry is skipped when it's popped.
+    Fifo {
+        heap: BinaryHeap<Reverse<(FifoPacketPriority, u64, usize)>>,
+        next_seq: u64,
+    },
+}
+
+struct QueuedItem<T> {
+    item: T,
+    priority: FifoPacketPriority,
+    /// The sequence number of the item's current heap entry.
+    seq: u64,
+}
+
+impl<T> SendQueue<T> {
+    pub fn new(mode: QDiscMode) -> Self {
+        let queue = match mode {
+            QDiscMode::RoundRobin => Queue::RoundRobin(VecDeque::new()),
+            QDiscMode::Fifo => Queue::Fifo {
+                heap: BinaryHeap::new(),
+                next_seq: 0,
+            },
+        };
+
+        Self {
+            queue,
+            items: HashMap::new(),
+        }
+    }
+
+    /// Add an item to the queue. With the round-robin discipline items are returned in the order
+    /// they were pushed, and with the fifo discipline items are returned in order of their
+    /// `priority` (the priority of the item's next packet), smallest first. Panics if an item
+    /// with the same handle is already in the queue.
+    pub fn push(&mut self, handle: usize, priority: FifoPacketPriority, item: T) {
+        assert!(
+            !self.items.contains_key(&handle),
+            "An item should not be queued more than once"
+        );
+
+        let seq = self.queue.push(handle, priority);
+        self.items.insert(
+            handle,
+            QueuedItem {
+                item,
+                priority,
+                seq,
+            },
+        );
+    }
+
+    /// Change the priority of an item that's already in the queue, for example if a socket queued
+    /// a control packet that should be sent before its other packets. Does nothing if the item
+    /// isn't in the queue, or with the round-robin discipline.
+    pub fn update_priority(&mut self, handle: usize, priority: FifoPacketPriority) {
+        let Queue::Fifo { .. } = self.queue else {
+            return;
+        };
+        let Some(queued) = self.items.get_mut(&handle) else {
+            return;
+        };
+        if queued.priority == priority {
+            return;
+        }
+
+        queued.priority = priority;
+        queued.seq = self.queue.push(handle, priority);
+    }
+
+    /// Remove and return the next item.
+    pub fn pop(&mut self) -> Option<T> {
+        let handle = match &mut self.queue {
+            Queue::RoundRobin(queue) => queue.pop_front()?,
+            Queue::Fifo { heap, .. } => loop {
+                let Reverse((_priority, seq, handle)) = heap.pop()?;
+                // skip entries that were replaced when the item's priority changed, or whose item
+                // was already popped
+                if self.items.get(&handle).is_some_and(|x| x.seq == seq) {
+                    break handle;
+                }
+            },
+        };
+
+        let queued = self.items.remove(&handle);
+        debug_assert!(queued.is_some());
+
+        queued.map(|x| x.item)
+    }
+
+    pub fn contains(&self, handle: usize) -> bool {
+        self.items.contains_key(&handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Queue {
+    /// Add an entry for the handle, and return its sequence number.
+    fn push(&mut self, handle: usize, priority: FifoPacketPriority) -> u64 {
+        match self {
+            Self::RoundRobin(queue) => {
+                queue.push_back(handle);
+                0
+            }
+            Self::Fifo { heap, next_seq } => {
+                let seq = *next_seq;
+                *next_seq += 1;
+                heap.push(Reverse((priority, seq, handle)));
+                seq
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let mut queue = SendQueue::new(QDiscMode::RoundRobin);
+
+        // the priorities are ignored
+        queue.push(10, 3, "a");
+        queue.push(20, 2, "b");
+        queue.push(30, 1, "c");
+        queue.update_priority(30, 0);
+        assert_eq!(queue.len(), 3);
+        assert!(queue.contains(20));
+
+        assert_eq!(queue.pop(), Some("a"));
+        assert_eq!(queue.pop(), Some("b"));
+        assert!(!queue.contains(20));
+        queue.push(20, 2, "b");
+        assert_eq!(queue.pop(), Some("c"));
+        assert_eq!(queue.pop(), Some("b"));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn fifo() {
+        let mut queue = SendQueue::new(QDiscMode::Fifo);
+
+        queue.push(10, 5, "a");
+        queue.push(20, 2, "b");
+        queue.push(30, 0, "c");
+        // the same priority as "c", so should be returned after "c"
+        queue.push(40, 0, "d");
+
+        assert_eq!(queue.pop(), Some("c"));
+        assert_eq!(queue.pop(), Some("d"));
+        assert_eq!(queue.pop(), Some("b"));
+        assert_eq!(queue.pop(), Some("a"));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn fifo_priority_changes() {
+        let mut queue = SendQueue::new(QDiscMode::Fifo);
+
+        queue.push(10, 5, "a");
+        queue.push(20, 2, "b");
+        queue.push(30, 3, "c");
+
+        // "a" now has a packet that should be sent before the others
+        queue.update_priority(10, 1);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop(), Some("a"));
+
+        // "b" is pushed again with a newer packet than the packet of "c"
+        assert_eq!(queue.pop(), Some("b"));
+        queue.push(20, 4, "b");
+        assert_eq!(queue.pop(), Some("c"));
+
+        // "a" is pushed again with its old priority, and its stale entry must not be used
+        queue.push(10, 5, "a");
+        queue.update_priority(20, 6);
+        assert_eq!(queue.pop(), Some("a"));
+        assert_eq!(queue.pop(), Some("b"));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+
+        // updating an item that isn't queued does nothing
+        queue.update_priority(10, 1);
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate() {
+        let mut queue = SendQueue::new(QDiscMode::Fifo);
+        queue.push(10, 1, "a");
+        queue.push(10, 2, "a");
+    }
+}
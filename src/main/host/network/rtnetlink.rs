@@ -0,0 +1,787 @@
+//! A minimal `NETLINK_ROUTE` responder (see rtnetlink(7)). It answers an application's requests for
+//! the host's network interfaces, addresses, and routes with the simulated configuration of the
+//! host's network namespace, so that tools like `ip` and functions like glibc's `getifaddrs()`
+//! describe the simulated network rather than the network of the machine running Shadow.
+//!
+//! The configuration never changes, so requests that would modify it aren't supported, and no
+//! notifications are ever sent to the rtnetlink multicast groups.
+
+use std::net::Ipv4Addr;
+
+use linux_api::errno::Errno;
+use linux_api::netdevice::{InterfaceFlags, ARPHRD_ETHER, ARPHRD_LOOPBACK};
+use linux_api::netlink::{
+    IFA_ADDRESS, IFA_BROADCAST, IFA_F_PERMANENT, IFA_LABEL, IFA_LOCAL, IFF_LOWER_UP, IFLA_ADDRESS,
+    IFLA_BROADCAST, IFLA_IFNAME, IFLA_MTU, IFLA_OPERSTATE, IFLA_TXQLEN, IF_OPER_UNKNOWN,
+    IF_OPER_UP, NLMSG_ALIGNTO, NLMSG_DONE, NLMSG_ERROR, NLMSG_HDRLEN, NLMSG_MIN_TYPE, NLM_F_ACK,
+    NLM_F_CAPPED, NLM_F_DUMP, NLM_F_MULTI, NLM_F_REQUEST, RTA_DST, RTA_OIF, RTA_PREFSRC, RTA_TABLE,
+    RTM_GETADDR, RTM_GETLINK, RTM_GETROUTE, RTM_NEWADDR, RTM_NEWLINK, RTM_NEWROUTE, RTN_LOCAL,
+    RTN_UNICAST, RTPROT_BOOT, RTPROT_KERNEL, RTPROT_UNSPEC, RT_SCOPE_HOST, RT_SCOPE_LINK,
+    RT_SCOPE_UNIVERSE, RT_TABLE_LOCAL, RT_TABLE_MAIN,
+};
+
+use crate::host::network::namespace::NetworkNamespace;
+
+/// The length of a `struct ifinfomsg`.
+const IFINFOMSG_LEN: usize = 16;
+/// The length of a `struct rtmsg`.
+const RTMSG_LEN: usize = 12;
+/// The length of a `struct nlattr`.
+const NLA_HDRLEN: usize = 4;
+
+/// A snapshot of a network interface's configuration.
+#[derive(Debug, Clone)]
+pub struct Link {
+    /// The interface index, which starts at 1.
+    pub index: i32,
+    pub name: String,
+    pub addr: Ipv4Addr,
+    pub prefix_len: u8,
+    pub broadcast: Option<Ipv4Addr>,
+    pub hw_addr: [u8; 6],
+    pub mtu: u32,
+    pub tx_queue_len: u32,
+    pub flags: InterfaceFlags,
+}
+
+impl Link {
+    fn is_loopback(&self) -> bool {
+        self.flags.contains(InterfaceFlags::IFF_LOOPBACK)
+    }
+}
+
+/// The host's network interfaces, ordered by their indexes.
+pub fn links(net_ns: &NetworkNamespace) -> Vec<Link> {
+    net_ns
+        .interfaces()
+        .iter()
+        .enumerate()
+        .map(|(i, interface)| {
+            let interface = interface.borrow();
+            Link {
+                index: i32::try_from(i + 1).unwrap(),
+                name: interface.name().to_string(),
+                addr: interface.addr(),
+                prefix_len: u32::from(interface.netmask())
+                    .count_ones()
+                    .try_into()
+                    .unwrap(),
+                broadcast: interface.broadcast(),
+                hw_addr: interface.hw_addr(),
+                mtu: interface.mtu(),
+                tx_queue_len: interface.tx_queue_len(),
+                flags: interface.flags(),
+            }
+        })
+        .collect()
+}
+
+/// The socket options that change the responses.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ResponseOptions {
+    /// The port id of the socket, which is the `nlmsg_pid` of every response.
+    pub portid: u32,
+    /// Whether error messages exclude the payload of the request (`NETLINK_CAP_ACK`).
+    pub cap_ack: bool,
+}
+
+/// Handle the netlink messages in a datagram that an application sent to the kernel, and return
+/// the response datagrams. Like Linux, the messages after a malformed message are ignored.
+pub fn handle_requests(links: &[Link], data: &[u8], options: &ResponseOptions) -> Vec<Vec<u8>> {
+    let mut responses = Vec::new();
+    let mut data = data;
+
+    while let Some((header, message, rest)) = split_message(data) {
+        data = rest;
+        let payload = &message[NLMSG_HDRLEN..];
+        let mut writer = MessageWriter::new(header.seq, options.portid);
+
+        let result = if header.flags & NLM_F_REQUEST == 0 || header.msg_type < NLMSG_MIN_TYPE {
+            // control messages and messages that aren't requests are only acknowledged
+            Ok(Reply::Single)
+        } else {
+            handle_request(links, &header, payload, &mut writer)
+        };
+
+        match result {
+            // like linux, dumps are never acknowledged
+            Ok(Reply::Dump) => {}
+            Ok(Reply::Single) => {
+                if header.flags & NLM_F_ACK != 0 {
+                    writer.error(0, message, options.cap_ack);
+                }
+            }
+            Err(e) => writer.error(e.to_negated_i32(), message, options.cap_ack),
+        }
+
+        if !writer.buf.is_empty() {
+            responses.push(writer.buf);
+        }
+    }
+
+    responses
+}
+
+/// How a successful request was answered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Reply {
+    /// The request was a dump, which is answered by a multipart message.
+    Dump,
+    /// The request was answered by a single message, or didn't need an answer.
+    Single,
+}
+
+fn handle_request(
+    links: &[Link],
+    header: &Header,
+    payload: &[u8],
+    writer: &mut MessageWriter,
+) -> Result<Reply, Errno> {
+    let is_dump = header.flags & NLM_F_DUMP == NLM_F_DUMP;
+
+    // most requests start with a struct whose first field is the address family
+    let family = payload
+        .first()
+        .copied()
+        .map(i32::from)
+        .unwrap_or(libc::AF_UNSPEC);
+    // we only have IPv4 addresses and routes
+    let is_inet = family == libc::AF_UNSPEC || family == libc::AF_INET;
+
+    match (header.msg_type, is_dump) {
+        (RTM_GETLINK, true) => {
+            for link in links {
+                writer.link(link, NLM_F_MULTI);
+            }
+        }
+        (RTM_GETLINK, false) => {
+            let link = find_link(links, payload)?;
+            writer.link(link, 0);
+        }
+        (RTM_GETADDR, true) => {
+            if is_inet {
+                for link in links {
+                    writer.addr(link, NLM_F_MULTI);
+                }
+            }
+        }
+        (RTM_GETROUTE, true) => {
+            if is_inet {
+                for route in routes(links) {
+                    writer.route(&route, NLM_F_MULTI);
+                }
+            }
+        }
+        (RTM_GETROUTE, false) => {
+            if payload.len() < RTMSG_LEN {
+                return Err(Errno::EINVAL);
+            }
+            if family != libc::AF_INET {
+                return Err(Errno::EAFNOSUPPORT);
+            }
+            let dst = match find_attr(&payload[RTMSG_LEN..], RTA_DST) {
+                Some(dst) => Ipv4Addr::from(<[u8; 4]>::try_from(dst).or(Err(Errno::EINVAL))?),
+                None => Ipv4Addr::UNSPECIFIED,
+            };
+            writer.route(&route_to(links, dst), 0);
+        }
+        // we have nothing else to report, such as neighbours or routing rules
+        (msg_type, true) if is_get_request(msg_type) => {}
+        (msg_type, _) => {
+            log::debug!("Unsupported rtnetlink request type {msg_type}");
+            return Err(Errno::EOPNOTSUPP);
+        }
+    }
+
+    if is_dump {
+        writer.done();
+        return Ok(Reply::Dump);
+    }
+
+    Ok(Reply::Single)
+}
+
+/// rtnetlink message types come in groups of four (new, delete, get, and set).
+fn is_get_request(msg_type: u16) -> bool {
+    msg_type & 0x3 == 0x2
+}
+
+/// The link that a non-dump `RTM_GETLINK` request refers to, by its index or its `IFLA_IFNAME`
+/// attribute.
+fn find_link<'a>(links: &'a [Link], payload: &[u8]) -> Result<&'a Link, Errno> {
+    if payload.len() < IFINFOMSG_LEN {
+        return Err(Errno::EINVAL);
+    }
+
+    let index = i32::from_ne_bytes(payload[4..8].try_into().unwrap());
+    if index > 0 {
+        return links.iter().find(|x| x.index == index).ok_or(Errno::ENODEV);
+    }
+
+    let Some(name) = find_attr(&payload[IFINFOMSG_LEN..], IFLA_IFNAME) else {
+        return Err(Errno::EINVAL);
+    };
+    // the name may or may not be nul-terminated
+    let name = name.split(|x| *x == 0).next().unwrap();
+
+    links
+        .iter()
+        .find(|x| x.name.as_bytes() == name)
+        .ok_or(Errno::ENODEV)
+}
+
+/// A route in one of the routing tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Route {
+    table: u8,
+    dst: Ipv4Addr,
+    dst_len: u8,
+    protocol: u8,
+    scope: u8,
+    route_type: u8,
+    prefsrc: Option<Ipv4Addr>,
+    oif: i32,
+}
+
+/// The routes of the main and local tables, in the order that Linux dumps them. Other hosts are
+/// reachable through the default route of the first non-loopback interface.
+fn routes(links: &[Link]) -> Vec<Route> {
+    let mut main = Vec::new();
+    let mut local = Vec::new();
+
+    for link in links {
+        let subnet_mask = u32::MAX
+            .checked_shl(32 - u32::from(link.prefix_len))
+            .unwrap_or(0);
+        let subnet = Ipv4Addr::from(u32::from(link.addr) & subnet_mask);
+
+        let route = |table, dst, dst_len, route_type| Route {
+            table,
+            dst,
+            dst_len,
+            protocol: RTPROT_KERNEL,
+            scope: if route_type == RTN_LOCAL {
+                RT_SCOPE_HOST
+            } else {
+                RT_SCOPE_LINK
+            },
+            route_type,
+            prefsrc: Some(link.addr),
+            oif: link.index,
+        };
+
+        if !link.is_loopback() {
+            if main.is_empty() {
+                main.push(Route {
+                    protocol: RTPROT_BOOT,
+                    prefsrc: None,
+                    ..route(RT_TABLE_MAIN, Ipv4Addr::UNSPECIFIED, 0, RTN_UNICAST)
+                });
+            }
+            main.push(route(RT_TABLE_MAIN, subnet, link.prefix_len, RTN_UNICAST));
+        } else {
+            local.push(route(RT_TABLE_LOCAL, subnet, link.prefix_len, RTN_LOCAL));
+        }
+        local.push(route(RT_TABLE_LOCAL, link.addr, 32, RTN_LOCAL));
+    }
+
+    main.extend(local);
+    main
+}
+
+/// The route that a packet to `dst` would take, as returned by `RTM_GETROUTE` for a single address.
+/// Like Linux, the host's own addresses are reached through the loopback interface.
+fn route_to(links: &[Link], dst: Ipv4Addr) -> Route {
+    let is_local = dst.is_loopback() || dst.is_unspecified() || links.iter().any(|x| x.addr == dst);
+
+    let link = links
+        .iter()
+        .find(|x| x.is_loopback() == is_local)
+        .unwrap_or(&links[0]);
+
+    let (table, scope, route_type, prefsrc) = if is_local {
+        let prefsrc = if dst.is_loopback() || dst.is_unspecified() {
+            link.addr
+        } else {
+            dst
+        };
+        (RT_TABLE_LOCAL, RT_SCOPE_HOST, RTN_LOCAL, prefsrc)
+    } else {
+        (RT_TABLE_MAIN, RT_SCOPE_UNIVERSE, RTN_UNICAST, link.addr)
+    };
+
+    Route {
+        table,
+        dst,
+        dst_len: 32,
+        protocol: RTPROT_UNSPEC,
+        scope,
+        route_type,
+        prefsrc: Some(prefsrc),
+        oif: link.index,
+    }
+}
+
+/// A `struct nlmsghdr`.
+#[derive(Debug, Copy, Clone)]
+struct Header {
+    len: u32,
+    msg_type: u16,
+    flags: u16,
+    seq: u32,
+    pid: u32,
+}
+
+impl Header {
+    fn from_bytes(bytes: &[u8; NLMSG_HDRLEN]) -> Self {
+        Self {
+            len: u32::from_ne_bytes(bytes[0..4].try_into().unwrap()),
+            msg_type: u16::from_ne_bytes(bytes[4..6].try_into().unwrap()),
+            flags: u16::from_ne_bytes(bytes[6..8].try_into().unwrap()),
+            seq: u32::from_ne_bytes(bytes[8..12].try_into().unwrap()),
+            pid: u32::from_ne_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; NLMSG_HDRLEN] {
+        let mut bytes = [0; NLMSG_HDRLEN];
+        bytes[0..4].copy_from_slice(&self.len.to_ne_bytes());
+        bytes[4..6].copy_from_slice(&self.msg_type.to_ne_bytes());
+        bytes[6..8].copy_from_slice(&self.flags.to_ne_bytes());
+        bytes[8..12].copy_from_slice(&self.seq.to_ne_bytes());
+        bytes[12..16].copy_from_slice(&self.pid.to_ne_bytes());
+        bytes
+    }
+}
+
+/// Split the first message (including its header) from `data`. Returns `None` if there are no
+/// more messages, or if the message is malformed.
+fn split_message(data: &[u8]) -> Option<(Header, &[u8], &[u8])> {
+    let header = Header::from_bytes(data.get(..NLMSG_HDRLEN)?.try_into().unwrap());
+
+    let len = usize::try_from(header.len).unwrap();
+    if len < NLMSG_HDRLEN || len > data.len() {
+        return None;
+    }
+
+    let rest = data.get(align(len)..).unwrap_or(&[]);
+    Some((header, &data[..len], rest))
+}
+
+/// The data of the first attribute of type `attr_type` in `attrs`.
+fn find_attr(mut attrs: &[u8], attr_type: u16) -> Option<&[u8]> {
+    while attrs.len() >= NLA_HDRLEN {
+        let len = usize::from(u16::from_ne_bytes(attrs[0..2].try_into().unwrap()));
+        let this_type = u16::from_ne_bytes(attrs[2..4].try_into().unwrap());
+        if len < NLA_HDRLEN || len > attrs.len() {
+            return None;
+        }
+        if this_type == attr_type {
+            return Some(&attrs[NLA_HDRLEN..len]);
+        }
+        attrs = attrs.get(align(len)..).unwrap_or(&[]);
+    }
+    None
+}
+
+fn align(len: usize) -> usize {
+    (len + NLMSG_ALIGNTO - 1) & !(NLMSG_ALIGNTO - 1)
+}
+
+/// Builds a datagram of response messages.
+struct MessageWriter {
+    buf: Vec<u8>,
+    seq: u32,
+    pid: u32,
+}
+
+impl MessageWriter {
+    fn new(seq: u32, pid: u32) -> Self {
+        Self {
+            buf: Vec::new(),
+            seq,
+            pid,
+        }
+    }
+
+    /// Write a message whose payload is written by `f`.
+    fn message(&mut self, msg_type: u16, flags: u16, f: impl FnOnce(&mut Self)) {
+        let start = self.buf.len();
+        self.buf.extend_from_slice(&[0; NLMSG_HDRLEN]);
+        f(self);
+
+        let header = Header {
+            len: (self.buf.len() - start).try_into().unwrap(),
+            msg_type,
+            flags,
+            seq: self.seq,
+            pid: self.pid,
+        };
+        self.buf[start..][..NLMSG_HDRLEN].copy_from_slice(&header.to_bytes());
+    }
+
+    /// Write `bytes`, padded to the netlink alignment.
+    fn put(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(align(self.buf.len()), 0);
+    }
+
+    fn put_attr(&mut self, attr_type: u16, data: &[u8]) {
+        let len = u16::try_from(NLA_HDRLEN + data.len()).unwrap();
+        self.buf.extend_from_slice(&len.to_ne_bytes());
+        self.buf.extend_from_slice(&attr_type.to_ne_bytes());
+        self.put(data);
+    }
+
+    fn put_attr_str(&mut self, attr_type: u16, s: &str) {
+        let mut data = s.as_bytes().to_vec();
+        data.push(0);
+        self.put_attr(attr_type, &data);
+    }
+
+    /// An `RTM_NEWLINK` message that describes `link`.
+    fn link(&mut self, link: &Link, flags: u16) {
+        self.message(RTM_NEWLINK, flags, |w| {
+            let (hw_type, oper_state, hw_broadcast) = if link.is_loopback() {
+                (ARPHRD_LOOPBACK, IF_OPER_UNKNOWN, [0; 6])
+            } else {
+                (ARPHRD_ETHER, IF_OPER_UP, [0xff; 6])
+            };
+            // the flags are an i16 in `struct ifreq`, but a u32 here
+            let link_flags = u32::from(link.flags.bits() as u16) | IFF_LOWER_UP;
+
+            // struct ifinfomsg
+            let mut ifinfo = Vec::with_capacity(IFINFOMSG_LEN);
+            ifinfo.extend_from_slice(&[libc::AF_UNSPEC as u8, 0]);
+            ifinfo.extend_from_slice(&hw_type.to_ne_bytes());
+            ifinfo.extend_from_slice(&link.index.to_ne_bytes());
+            ifinfo.extend_from_slice(&link_flags.to_ne_bytes());
+            ifinfo.extend_from_slice(&0u32.to_ne_bytes());
+            w.put(&ifinfo);
+
+            w.put_attr_str(IFLA_IFNAME, &link.name);
+            w.put_attr(IFLA_TXQLEN, &link.tx_queue_len.to_ne_bytes());
+            w.put_attr(IFLA_OPERSTATE, &[oper_state]);
+            w.put_attr(IFLA_MTU, &link.mtu.to_ne_bytes());
+            w.put_attr(IFLA_ADDRESS, &link.hw_addr);
+            w.put_attr(IFLA_BROADCAST, &hw_broadcast);
+        });
+    }
+
+    /// An `RTM_NEWADDR` message that describes the address of `link`.
+    fn addr(&mut self, link: &Link, flags: u16) {
+        self.message(RTM_NEWADDR, flags, |w| {
+            let scope = if link.is_loopback() {
+                RT_SCOPE_HOST
+            } else {
+                RT_SCOPE_UNIVERSE
+            };
+
+            // struct ifaddrmsg
+            let mut ifaddr = vec![libc::AF_INET as u8, link.prefix_len, IFA_F_PERMANENT, scope];
+            ifaddr.extend_from_slice(&u32::try_from(link.index).unwrap().to_ne_bytes());
+            w.put(&ifaddr);
+
+            w.put_attr(IFA_ADDRESS, &link.addr.octets());
+            w.put_attr(IFA_LOCAL, &link.addr.octets());
+            if let Some(broadcast) = link.broadcast {
+                w.put_attr(IFA_BROADCAST, &broadcast.octets());
+            }
+            w.put_attr_str(IFA_LABEL, &link.name);
+        });
+    }
+
+    /// An `RTM_NEWROUTE` message that describes `route`.
+    fn route(&mut self, route: &Route, flags: u16) {
+        self.message(RTM_NEWROUTE, flags, |w| {
+            // struct rtmsg
+            let mut rtmsg = vec![
+                libc::AF_INET as u8,
+                route.dst_len,
+                0,
+                0,
+                route.table,
+                route.protocol,
+                route.scope,
+                route.route_type,
+            ];
+            rtmsg.extend_from_slice(&0u32.to_ne_bytes());
+            w.put(&rtmsg);
+
+            w.put_attr(RTA_TABLE, &u32::from(route.table).to_ne_bytes());
+            if route.dst_len > 0 {
+                w.put_attr(RTA_DST, &route.dst.octets());
+            }
+            if let Some(prefsrc) = route.prefsrc {
+                w.put_attr(RTA_PREFSRC, &prefsrc.octets());
+            }
+            w.put_attr(RTA_OIF, &route.oif.to_ne_bytes());
+        });
+    }
+
+    /// The `NLMSG_DONE` message that ends a dump.
+    fn done(&mut self) {
+        self.message(NLMSG_DONE, NLM_F_MULTI, |w| w.put(&0i32.to_ne_bytes()));
+    }
+
+    /// An `NLMSG_ERROR` message for the request `request`, which is an acknowledgement if `error`
+    /// is 0. Like Linux, the request's payload is only included for errors, and only if the socket
+    /// didn't set `NETLINK_CAP_ACK`.
+    fn error(&mut self, error: i32, request: &[u8], cap_ack: bool) {
+        let capped = error == 0 || cap_ack;
+        let flags = if capped { NLM_F_CAPPED } else { 0 };
+        self.message(NLMSG_ERROR, flags, |w| {
+            w.put(&error.to_ne_bytes());
+            if capped {
+                w.put(&request[..NLMSG_HDRLEN]);
+            } else {
+                w.put(request);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linux_api::netlink::NLM_F_ROOT;
+
+    use super::*;
+
+    fn test_links() -> Vec<Link> {
+        vec![
+            Link {
+                index: 1,
+                name: "lo".to_string(),
+                addr: Ipv4Addr::LOCALHOST,
+                prefix_len: 8,
+                broadcast: None,
+                hw_addr: [0; 6],
+                mtu: 65536,
+                tx_queue_len: 1000,
+                flags: InterfaceFlags::IFF_UP
+                    | InterfaceFlags::IFF_RUNNING
+                    | InterfaceFlags::IFF_LOOPBACK,
+            },
+            Link {
+                index: 2,
+                name: "eth0".to_string(),
+                addr: Ipv4Addr::new(11, 0, 0, 5),
+                prefix_len: 24,
+                broadcast: Some(Ipv4Addr::new(11, 0, 0, 255)),
+                hw_addr: [2, 0, 0, 0, 0, 5],
+                mtu: 1500,
+                tx_queue_len: 1000,
+                flags: InterfaceFlags::IFF_UP
+                    | InterfaceFlags::IFF_RUNNING
+                    | InterfaceFlags::IFF_BROADCAST
+                    | InterfaceFlags::IFF_MULTICAST,
+            },
+        ]
+    }
+
+    fn request(msg_type: u16, flags: u16, payload: &[u8]) -> Vec<u8> {
+        let header = Header {
+            len: (NLMSG_HDRLEN + payload.len()).try_into().unwrap(),
+            msg_type,
+            flags,
+            seq: 42,
+            pid: 0,
+        };
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// Split a response datagram into its messages.
+    fn messages(mut data: &[u8]) -> Vec<(Header, &[u8])> {
+        let mut messages = Vec::new();
+        while let Some((header, message, rest)) = split_message(data) {
+            messages.push((header, &message[NLMSG_HDRLEN..]));
+            data = rest;
+        }
+        assert!(data.is_empty());
+        messages
+    }
+
+    fn options() -> ResponseOptions {
+        ResponseOptions {
+            portid: 100,
+            cap_ack: false,
+        }
+    }
+
+    #[test]
+    fn test_link_dump() {
+        let req = request(
+            RTM_GETLINK,
+            NLM_F_REQUEST | NLM_F_DUMP,
+            &[libc::AF_PACKET as u8],
+        );
+        let responses = handle_requests(&test_links(), &req, &options());
+        assert_eq!(responses.len(), 1);
+
+        let messages = messages(&responses[0]);
+        assert_eq!(messages.len(), 3);
+
+        for (header, _) in &messages {
+            assert_eq!(header.flags, NLM_F_MULTI);
+            assert_eq!(header.seq, 42);
+            assert_eq!(header.pid, 100);
+        }
+
+        let (header, payload) = messages[1];
+        assert_eq!(header.msg_type, RTM_NEWLINK);
+        assert_eq!(
+            u16::from_ne_bytes(payload[2..4].try_into().unwrap()),
+            ARPHRD_ETHER
+        );
+        assert_eq!(i32::from_ne_bytes(payload[4..8].try_into().unwrap()), 2);
+        let attrs = &payload[IFINFOMSG_LEN..];
+        assert_eq!(find_attr(attrs, IFLA_IFNAME), Some(&b"eth0\0"[..]));
+        assert_eq!(find_attr(attrs, IFLA_MTU), Some(&1500u32.to_ne_bytes()[..]));
+        assert_eq!(
+            find_attr(attrs, IFLA_ADDRESS),
+            Some(&[2, 0, 0, 0, 0, 5][..])
+        );
+
+        let (header, payload) = messages[2];
+        assert_eq!(header.msg_type, NLMSG_DONE);
+        assert_eq!(payload, 0i32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_link_by_name() {
+        let mut payload = vec![0; IFINFOMSG_LEN];
+        payload.extend_from_slice(&8u16.to_ne_bytes());
+        payload.extend_from_slice(&IFLA_IFNAME.to_ne_bytes());
+        payload.extend_from_slice(b"lo\0\0");
+
+        let req = request(RTM_GETLINK, NLM_F_REQUEST | NLM_F_ACK, &payload);
+        let responses = handle_requests(&test_links(), &req, &options());
+        let messages = messages(&responses[0]);
+        assert_eq!(messages.len(), 2);
+
+        let (header, payload) = messages[0];
+        assert_eq!(header.msg_type, RTM_NEWLINK);
+        assert_eq!(header.flags, 0);
+        assert_eq!(i32::from_ne_bytes(payload[4..8].try_into().unwrap()), 1);
+
+        // the ack only includes the request's header
+        let (header, payload) = messages[1];
+        assert_eq!(header.msg_type, NLMSG_ERROR);
+        assert_eq!(header.flags, NLM_F_CAPPED);
+        assert_eq!(payload[..4], 0i32.to_ne_bytes());
+        assert_eq!(payload[4..], req[..NLMSG_HDRLEN]);
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let mut payload = vec![0; IFINFOMSG_LEN];
+        payload[4..8].copy_from_slice(&7i32.to_ne_bytes());
+
+        let req = request(RTM_GETLINK, NLM_F_REQUEST, &payload);
+        let responses = handle_requests(&test_links(), &req, &options());
+        let messages = messages(&responses[0]);
+        assert_eq!(messages.len(), 1);
+
+        // errors include the whole request
+        let (header, payload) = messages[0];
+        assert_eq!(header.msg_type, NLMSG_ERROR);
+        assert_eq!(header.flags, 0);
+        assert_eq!(payload[..4], Errno::ENODEV.to_negated_i32().to_ne_bytes());
+        assert_eq!(payload[4..], req);
+
+        // changing the configuration isn't supported
+        let req = request(RTM_NEWLINK, NLM_F_REQUEST, &[0; IFINFOMSG_LEN]);
+        let options = ResponseOptions {
+            cap_ack: true,
+            ..options()
+        };
+        let responses = handle_requests(&test_links(), &req, &options);
+        let (header, payload) = messages(&responses[0])[0];
+        assert_eq!(header.flags, NLM_F_CAPPED);
+        assert_eq!(
+            payload[..4],
+            Errno::EOPNOTSUPP.to_negated_i32().to_ne_bytes()
+        );
+        assert_eq!(payload.len(), 4 + NLMSG_HDRLEN);
+    }
+
+    #[test]
+    fn test_addr_dump() {
+        let req = request(RTM_GETADDR, NLM_F_REQUEST | NLM_F_DUMP, &[0; 8]);
+        let responses = handle_requests(&test_links(), &req, &options());
+        let messages = messages(&responses[0]);
+        assert_eq!(messages.len(), 3);
+
+        let (header, payload) = messages[1];
+        assert_eq!(header.msg_type, RTM_NEWADDR);
+        assert_eq!(payload[..4], [libc::AF_INET as u8, 24, IFA_F_PERMANENT, 0]);
+        let attrs = &payload[8..];
+        assert_eq!(find_attr(attrs, IFA_LOCAL), Some(&[11, 0, 0, 5][..]));
+        assert_eq!(find_attr(attrs, IFA_BROADCAST), Some(&[11, 0, 0, 255][..]));
+        assert_eq!(find_attr(attrs, IFA_LABEL), Some(&b"eth0\0"[..]));
+
+        // there are no IPv6 addresses
+        let req = request(
+            RTM_GETADDR,
+            NLM_F_REQUEST | NLM_F_DUMP,
+            &[libc::AF_INET6 as u8],
+        );
+        let responses = handle_requests(&test_links(), &req, &options());
+        let messages = messages(&responses[0]);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0.msg_type, NLMSG_DONE);
+    }
+
+    #[test]
+    fn test_routes() {
+        let routes = routes(&test_links());
+        let summary: Vec<_> = routes
+            .iter()
+            .map(|x| (x.table, x.dst, x.dst_len, x.route_type, x.oif))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (RT_TABLE_MAIN, Ipv4Addr::UNSPECIFIED, 0, RTN_UNICAST, 2),
+                (
+                    RT_TABLE_MAIN,
+                    Ipv4Addr::new(11, 0, 0, 0),
+                    24,
+                    RTN_UNICAST,
+                    2
+                ),
+                (RT_TABLE_LOCAL, Ipv4Addr::new(127, 0, 0, 0), 8, RTN_LOCAL, 1),
+                (RT_TABLE_LOCAL, Ipv4Addr::LOCALHOST, 32, RTN_LOCAL, 1),
+                (RT_TABLE_LOCAL, Ipv4Addr::new(11, 0, 0, 5), 32, RTN_LOCAL, 2),
+            ]
+        );
+
+        let route = route_to(&test_links(), Ipv4Addr::new(11, 0, 0, 9));
+        assert_eq!(route.oif, 2);
+        assert_eq!(route.prefsrc, Some(Ipv4Addr::new(11, 0, 0, 5)));
+
+        // the host's own address is reached through the loopback interface
+        let route = route_to(&test_links(), Ipv4Addr::new(11, 0, 0, 5));
+        assert_eq!(route.oif, 1);
+        assert_eq!(route.route_type, RTN_LOCAL);
+    }
+
+    #[test]
+    fn test_multiple_requests() {
+        // a non-request message is only acknowledged, and a malformed message ends the datagram
+        let mut data = request(RTM_GETLINK, NLM_F_ACK, &[]);
+        data.extend(request(RTM_GETROUTE, NLM_F_REQUEST | NLM_F_ROOT, &[]));
+        data.extend(request(RTM_GETADDR, NLM_F_REQUEST | NLM_F_DUMP, &[]));
+        data.extend_from_slice(&[0xff; 8]);
+
+        let responses = handle_requests(&test_links(), &data, &options());
+        assert_eq!(responses.len(), 3);
+        assert_eq!(messages(&responses[0])[0].0.msg_type, NLMSG_ERROR);
+        // too short for a `struct rtmsg`
+        let (_, payload) = messages(&responses[1])[0];
+        assert_eq!(payload[..4], Errno::EINVAL.to_negated_i32().to_ne_bytes());
+        assert_eq!(messages(&responses[2]).len(), 3);
+    }
+}
@@ -0,0 +1,245 @@
+//! Small per-host state blobs that persist across simulations, such as warm caches, Tor guard
+//! state, or DHT routing tables that should carry over between the phases of an experiment.
+//!
+//! While the simulation runs, the state is a directory in the host's data directory with one file
+//! per key, which the host's processes can read and write like any other files. When the host
+//! shuts down, the directory is saved as a state file, which can be loaded by the host in a later
+//! simulation.
+//!
+//! The state file is a JSON object with a format version and the entries. Values that are valid
+//! UTF-8 are stored as strings, and other values are stored as arrays of bytes:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "entries": {
+//!     "guards": "relay1\nrelay2\n",
+//!     "routing-table": [0, 159, 255]
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// The name of the directory in the host's data directory that holds the state while the
+/// simulation runs.
+pub const STATE_DIR_NAME: &str = "state";
+
+/// The name of the file in the host's data directory that the state is saved to when the host
+/// shuts down.
+pub const STATE_FILE_NAME: &str = "state.json";
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StateValue {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl StateValue {
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Self::Text(text),
+            Err(e) => Self::Bytes(e.into_bytes()),
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PersistentState {
+    entries: BTreeMap<String, StateValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateFile {
+    version: u32,
+    entries: BTreeMap<String, StateValue>,
+}
+
+impl PersistentState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the state from a state file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Failed to open state file '{}'", path.display()))?;
+        let file: StateFile = serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("Failed to parse state file '{}'", path.display()))?;
+
+        if file.version != FORMAT_VERSION {
+            return Err(anyhow::anyhow!(
+                "State file '{}' has unsupported version {} (expected {FORMAT_VERSION})",
+                path.display(),
+                file.version,
+            ));
+        }
+
+        let mut state = Self::new();
+        for (key, value) in file.entries {
+            state.insert(key, value)?;
+        }
+        Ok(state)
+    }
+
+    /// Save the state to a state file.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let file = StateFile {
+            version: FORMAT_VERSION,
+            entries: self.entries.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write state file '{}'", path.display()))
+    }
+
+    /// Set the value of `key`, replacing any previous value. Keys are used as file names, so they
+    /// must be non-empty, must not be `.` or `..`, and must not contain `/` or NUL characters.
+    pub fn insert(&mut self, key: String, value: StateValue) -> anyhow::Result<()> {
+        if key.is_empty() || key == "." || key == ".." || key.contains(['/', '\0']) {
+            return Err(anyhow::anyhow!("Invalid state key '{key}'"));
+        }
+        self.entries.insert(key, value);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&StateValue> {
+        self.entries.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Create the directory `dir` with one file for each entry.
+    pub fn write_dir(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for (key, value) in &self.entries {
+            std::fs::write(dir.join(key), value.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Read the state from the files in the directory `dir`. Anything in the directory other than
+    /// regular files is ignored.
+    pub fn read_dir(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let mut state = Self::new();
+        let read_dir = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read state directory '{}'", dir.display()))?;
+
+        for entry in read_dir {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                log::warn!(
+                    "Ignoring '{}', which isn't a regular file",
+                    entry.path().display()
+                );
+                continue;
+            }
+
+            let Ok(key) = entry.file_name().into_string() else {
+                log::warn!(
+                    "Ignoring '{}', which doesn't have a UTF-8 name",
+                    entry.path().display()
+                );
+                continue;
+            };
+
+            let value = std::fs::read(entry.path())
+                .with_context(|| format!("Failed to read '{}'", entry.path().display()))?;
+            state.insert(key, StateValue::from_bytes(value))?;
+        }
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_invalid_keys() {
+        let mut state = PersistentState::new();
+        for key in ["", ".", "..", "a/b", "a\0b"] {
+            assert!(state
+                .insert(key.to_string(), StateValue::Text("x".into()))
+                .is_err());
+        }
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn test_dir_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = dir.path().join(STATE_DIR_NAME);
+
+        let mut state = PersistentState::new();
+        state
+            .insert("text".into(), StateValue::Text("hello\n".into()))
+            .unwrap();
+        state
+            .insert("bytes".into(), StateValue::Bytes(vec![0, 159, 255]))
+            .unwrap();
+        state.write_dir(&state_dir).unwrap();
+
+        assert_eq!(
+            std::fs::read(state_dir.join("bytes")).unwrap(),
+            [0, 159, 255]
+        );
+
+        // a process adds a key
+        std::fs::write(state_dir.join("new"), "value").unwrap();
+        let read_state = PersistentState::read_dir(&state_dir).unwrap();
+        assert_eq!(read_state.len(), 3);
+        assert_eq!(read_state.get("text"), state.get("text"));
+        assert_eq!(read_state.get("bytes"), state.get("bytes"));
+        assert_eq!(
+            read_state.get("new"),
+            Some(&StateValue::Text("value".into()))
+        );
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(STATE_FILE_NAME);
+
+        let mut state = PersistentState::new();
+        state
+            .insert("text".into(), StateValue::Text("hello".into()))
+            .unwrap();
+        state
+            .insert("bytes".into(), StateValue::Bytes(vec![0, 159, 255]))
+            .unwrap();
+        state.save(&path).unwrap();
+
+        assert_eq!(PersistentState::load(&path).unwrap(), state);
+
+        std::fs::write(&path, r#"{"version": 2, "entries": {}}"#).unwrap();
+        assert!(PersistentState::load(&path).is_err());
+    }
+}
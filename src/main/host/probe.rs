@@ -0,0 +1,681 @@
+//! Built-in probes that periodically measure the network between pairs of hosts, giving a quick
+//! view of the health of the simulated network without running any applications.
+//!
+//! A probe sends a small UDP request from its host to a target host, which answers it from a
+//! responder that Shadow runs on the target. A ping probe measures the time until a small reply is
+//! received, and a fetch probe measures the time until a response of a configured size is received
+//! in full. Requests that aren't answered in full within [`REPLY_TIMEOUT`] are counted as lost.
+//!
+//! When the simulation ends, each probe's results are summarized over periods of the simulation by
+//! the 50th, 90th, and 99th percentiles of the measured times.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use atomic_refcell::AtomicRefCell;
+use bytes::Bytes;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::sim_config::Probe;
+use crate::core::support::configuration::ProbeKind;
+use crate::core::work::task::TaskRef;
+use crate::core::worker::Worker;
+use crate::host::descriptor::socket::inet::udp::UdpSocket;
+use crate::host::descriptor::{FileState, FileStatus, StateListenerFilter};
+use crate::host::host::Host;
+use crate::host::network::namespace::NetworkNamespace;
+use crate::utility;
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrStorage;
+
+pub const PROBES_FILE_NAME: &str = "probes.csv";
+
+/// The largest response of a fetch probe.
+pub const MAX_FETCH_SIZE: u32 = 1_000_000;
+
+/// The largest payload of each datagram of a fetch response, after the message header.
+const CHUNK_SIZE: u32 = 1400;
+
+/// Requests that haven't been answered in full after this long are counted as lost.
+const REPLY_TIMEOUT: SimulationTime =
+    SimulationTime::from_duration(std::time::Duration::from_secs(60));
+
+const HEADER_LEN: usize = 13;
+
+/// The kinds of probe messages.
+const PING_REQUEST: u8 = 0;
+const FETCH_REQUEST: u8 = 1;
+const REPLY: u8 = 2;
+
+/// A probe message: its kind and sequence number, followed by the fetch size for a request, or by
+/// the datagram's index and the number of datagrams in the response for a reply. A reply is padded
+/// to the size of its part of the response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Header {
+    kind: u8,
+    seq: u32,
+    a: u32,
+    b: u32,
+}
+
+impl Header {
+    fn encode(&self, padding: u32) -> Bytes {
+        let mut msg = Vec::with_capacity(HEADER_LEN + padding as usize);
+        msg.push(self.kind);
+        msg.extend_from_slice(&self.seq.to_be_bytes());
+        msg.extend_from_slice(&self.a.to_be_bytes());
+        msg.extend_from_slice(&self.b.to_be_bytes());
+        msg.resize(HEADER_LEN + padding as usize, 0);
+        Bytes::from(msg)
+    }
+
+    fn decode(msg: &[u8]) -> Option<Self> {
+        let msg = msg.get(..HEADER_LEN)?;
+        let word = |i: usize| u32::from_be_bytes(msg[i..i + 4].try_into().unwrap());
+        Some(Self {
+            kind: msg[0],
+            seq: word(1),
+            a: word(5),
+            b: word(9),
+        })
+    }
+}
+
+/// The reply datagrams to a request.
+fn replies(request: &Header) -> Vec<Bytes> {
+    let sizes = match request.kind {
+        PING_REQUEST => vec![0],
+        FETCH_REQUEST => {
+            let size = request.a.min(MAX_FETCH_SIZE);
+            let count = size.div_ceil(CHUNK_SIZE).max(1);
+            (0..count)
+                .map(|i| (size - i * CHUNK_SIZE).min(CHUNK_SIZE))
+                .collect()
+        }
+        _ => return Vec::new(),
+    };
+
+    let count = sizes.len() as u32;
+    sizes
+        .into_iter()
+        .enumerate()
+        .map(|(index, size)| {
+            Header {
+                kind: REPLY,
+                seq: request.seq,
+                a: index as u32,
+                b: count,
+            }
+            .encode(size)
+        })
+        .collect()
+}
+
+fn bind_socket(
+    net_ns: &NetworkNamespace,
+    port: u16,
+    buf_sizes: (usize, usize),
+    rng: impl rand::Rng,
+) -> std::io::Result<Arc<AtomicRefCell<UdpSocket>>> {
+    let (send_buf_size, recv_buf_size) = buf_sizes;
+    let socket = UdpSocket::new(FileStatus::empty(), send_buf_size, recv_buf_size);
+
+    let addr = SocketAddrV4::new(net_ns.default_ip, port);
+    UdpSocket::bind(&socket, Some(&SockaddrStorage::from(addr)), net_ns, rng).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            format!("Could not bind the probe socket to {addr}: {e:?}"),
+        )
+    })?;
+
+    Ok(socket)
+}
+
+fn close_socket(socket: &Arc<AtomicRefCell<UdpSocket>>) {
+    CallbackQueue::queue_and_run(|cb_queue| {
+        if let Err(e) = socket.borrow_mut().close(cb_queue) {
+            log::warn!("Unable to close the probe socket: {e:?}");
+        }
+    });
+}
+
+/// Answers the probes sent to a port of the host.
+pub struct ProbeResponder {
+    socket: Arc<AtomicRefCell<UdpSocket>>,
+    _handle: Handle<(FileState, FileState)>,
+}
+
+impl ProbeResponder {
+    /// Start answering probes on a new UDP socket bound to `port` of the host's default address.
+    pub fn new(
+        net_ns: &NetworkNamespace,
+        port: u16,
+        buf_sizes: (usize, usize),
+        rng: impl rand::Rng,
+    ) -> std::io::Result<Self> {
+        // leave room for a few of the largest fetch responses
+        let buf_sizes = (buf_sizes.0.max(4 * MAX_FETCH_SIZE as usize), buf_sizes.1);
+        let socket = bind_socket(net_ns, port, buf_sizes, rng)?;
+
+        let handle = {
+            let weak_socket = Arc::downgrade(&socket);
+            socket.borrow_mut().add_listener(
+                FileState::READABLE,
+                StateListenerFilter::OffToOn,
+                move |_state, _changed, cb_queue| {
+                    if let Some(socket) = weak_socket.upgrade() {
+                        Self::answer_requests(&socket, cb_queue);
+                    }
+                },
+            )
+        };
+
+        Ok(Self {
+            socket,
+            _handle: handle,
+        })
+    }
+
+    /// Answer all requests that have been received.
+    fn answer_requests(socket: &Arc<AtomicRefCell<UdpSocket>>, cb_queue: &mut CallbackQueue) {
+        loop {
+            let Some((request, src)) = socket.borrow_mut().recv_internal(cb_queue) else {
+                break;
+            };
+            let Some(request) = Header::decode(&request) else {
+                continue;
+            };
+
+            for reply in replies(&request) {
+                if let Err(e) = UdpSocket::send_internal(socket, src, reply, cb_queue) {
+                    // the prober will count the request as lost
+                    log::debug!("Dropping probe reply to {src}: {e}");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Close the socket, removing it from the network namespace.
+    pub fn close(&self) {
+        close_socket(&self.socket);
+    }
+}
+
+/// A request that hasn't been answered in full.
+#[derive(Debug)]
+struct Pending {
+    sent: EmulatedTime,
+    /// Whether each datagram of the reply has been received, once the first one is received.
+    received: Vec<bool>,
+}
+
+/// The measurements of a probe.
+#[derive(Debug, Default)]
+struct Measurements {
+    /// The times that requests were sent.
+    sent: Vec<EmulatedTime>,
+    /// The times that answered requests were sent, and the times until they were answered.
+    answered: Vec<(EmulatedTime, SimulationTime)>,
+}
+
+#[derive(Debug, Default)]
+struct ProberState {
+    next_seq: u32,
+    pending: BTreeMap<u32, Pending>,
+    measurements: Measurements,
+}
+
+impl ProberState {
+    fn request_sent(&mut self, now: EmulatedTime) -> u32 {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+
+        self.pending
+            .retain(|_, x| now.saturating_duration_since(&x.sent) < REPLY_TIMEOUT);
+        self.pending.insert(
+            seq,
+            Pending {
+                sent: now,
+                received: Vec::new(),
+            },
+        );
+        self.measurements.sent.push(now);
+
+        seq
+    }
+
+    fn reply_received(&mut self, reply: &Header, now: EmulatedTime) {
+        let (index, count) = (reply.a as usize, reply.b as usize);
+        let Some(pending) = self.pending.get_mut(&reply.seq) else {
+            // a duplicate or late reply
+            return;
+        };
+        if now.saturating_duration_since(&pending.sent) >= REPLY_TIMEOUT {
+            return;
+        }
+
+        if pending.received.is_empty() {
+            pending.received = vec![false; count];
+        }
+        if let Some(x) = pending.received.get_mut(index) {
+            *x = true;
+        }
+
+        if pending.received.iter().all(|x| *x) {
+            let pending = self.pending.remove(&reply.seq).unwrap();
+            self.measurements
+                .answered
+                .push((pending.sent, now - pending.sent));
+        }
+    }
+}
+
+/// Periodically sends the requests of one of the host's probes.
+pub struct Prober {
+    index: usize,
+    inner: Arc<ProberInner>,
+    _handle: Handle<(FileState, FileState)>,
+}
+
+/// The parts of a prober that its scheduled requests use.
+struct ProberInner {
+    probe: Probe,
+    socket: Arc<AtomicRefCell<UdpSocket>>,
+    state: Mutex<ProberState>,
+}
+
+impl Prober {
+    /// Create the probe with index `index` of the host's probes, on a new UDP socket bound to an
+    /// ephemeral port.
+    pub fn new(
+        net_ns: &NetworkNamespace,
+        index: usize,
+        probe: &Probe,
+        buf_sizes: (usize, usize),
+        rng: impl rand::Rng,
+    ) -> std::io::Result<Self> {
+        // leave room for the largest fetch response
+        let buf_sizes = (buf_sizes.0, buf_sizes.1.max(2 * MAX_FETCH_SIZE as usize));
+        let socket = bind_socket(net_ns, 0, buf_sizes, rng)?;
+
+        let inner = Arc::new(ProberInner {
+            probe: probe.clone(),
+            socket: Arc::clone(&socket),
+            state: Mutex::new(ProberState::default()),
+        });
+
+        let handle = {
+            let weak_inner = Arc::downgrade(&inner);
+            socket.borrow_mut().add_listener(
+                FileState::READABLE,
+                StateListenerFilter::OffToOn,
+                move |_state, _changed, cb_queue| {
+                    if let Some(inner) = weak_inner.upgrade() {
+                        inner.receive_replies(cb_queue);
+                    }
+                },
+            )
+        };
+
+        Ok(Self {
+            index,
+            inner,
+            _handle: handle,
+        })
+    }
+
+    /// Send the probe's first request at `start`, and a request every interval after it.
+    pub fn start(&self, host: &Host, start: EmulatedTime) {
+        host.schedule_task_at_emulated_time(Self::task(Arc::clone(&self.inner)), start);
+    }
+
+    fn task(inner: Arc<ProberInner>) -> TaskRef {
+        TaskRef::new(move |host| {
+            inner.send_request();
+            host.schedule_task_with_delay(Self::task(Arc::clone(&inner)), inner.probe.interval);
+        })
+    }
+
+    /// Close the socket, removing it from the network namespace.
+    pub fn close(&self) {
+        close_socket(&self.inner.socket);
+    }
+
+    /// Add the probe's measurements to `results`.
+    pub fn add_results(&self, host_name: &str, results: &ProbeResults) {
+        let measurements = std::mem::take(&mut self.inner.state.lock().unwrap().measurements);
+        results.add(host_name, self.index, &self.inner.probe, measurements);
+    }
+}
+
+impl ProberInner {
+    fn send_request(&self) {
+        let now = Worker::current_time().unwrap();
+        let seq = self.state.lock().unwrap().request_sent(now);
+
+        let (kind, fetch_size) = match self.probe.kind {
+            ProbeKind::Ping => (PING_REQUEST, 0),
+            ProbeKind::Fetch => (FETCH_REQUEST, self.probe.fetch_size),
+        };
+        let request = Header {
+            kind,
+            seq,
+            a: fetch_size,
+            b: 0,
+        };
+
+        let dst = SocketAddrV4::new(self.probe.target.unwrap(), self.probe.port);
+        CallbackQueue::queue_and_run(|cb_queue| {
+            if let Err(e) = UdpSocket::send_internal(&self.socket, dst, request.encode(0), cb_queue)
+            {
+                log::debug!("Dropping probe request to {dst}: {e}");
+            }
+        });
+    }
+
+    fn receive_replies(&self, cb_queue: &mut CallbackQueue) {
+        let now = Worker::current_time().unwrap();
+        let mut state = self.state.lock().unwrap();
+        while let Some((reply, _)) = self.socket.borrow_mut().recv_internal(cb_queue) {
+            if let Some(reply) = Header::decode(&reply).filter(|x| x.kind == REPLY) {
+                state.reply_received(&reply, now);
+            }
+        }
+    }
+}
+
+/// A probe's measurements, summarized when the simulation ends.
+#[derive(Debug)]
+struct ProbeRecord {
+    target: String,
+    kind: ProbeKind,
+    summary_interval: SimulationTime,
+    measurements: Measurements,
+}
+
+/// A summary of the requests sent during a period of the simulation.
+#[derive(Debug, PartialEq, Eq)]
+struct PeriodSummary {
+    /// The start of the period.
+    start: SimulationTime,
+    sent: usize,
+    answered: usize,
+    /// The 50th, 90th, and 99th percentiles of the times until the requests were answered.
+    percentiles: Option<[SimulationTime; 3]>,
+}
+
+impl ProbeRecord {
+    /// Summarize the requests sent during each period, or during the whole simulation if
+    /// `interval` is `None`. Periods without requests are skipped.
+    fn summarize(&self, interval: Option<SimulationTime>) -> Vec<PeriodSummary> {
+        let period = |time: &EmulatedTime| {
+            let time = *time - EmulatedTime::SIMULATION_START;
+            interval.map_or(0, |x| time.as_nanos() / x.as_nanos())
+        };
+
+        let mut periods: BTreeMap<u128, (usize, Vec<u64>)> = BTreeMap::new();
+        for time in &self.measurements.sent {
+            periods.entry(period(time)).or_default().0 += 1;
+        }
+        for (time, latency) in &self.measurements.answered {
+            let latency = u64::try_from(latency.as_nanos()).unwrap();
+            periods.entry(period(time)).or_default().1.push(latency);
+        }
+
+        periods
+            .into_iter()
+            .map(|(period, (sent, mut latencies))| {
+                latencies.sort_unstable();
+                let percentiles = (!latencies.is_empty()).then(|| {
+                    [50, 90, 99]
+                        .map(|p| SimulationTime::from_nanos(utility::percentile(&latencies, p)))
+                });
+                let start = interval.map_or(SimulationTime::ZERO, |x| {
+                    SimulationTime::from_nanos(u64::try_from(period * x.as_nanos()).unwrap())
+                });
+                PeriodSummary {
+                    start,
+                    sent,
+                    answered: latencies.len(),
+                    percentiles,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The measurements of the probes of all hosts.
+#[derive(Debug, Default)]
+pub struct ProbeResults {
+    /// The records of the probes, keyed by the name of the probe's host and the probe's index.
+    records: Mutex<BTreeMap<(String, usize), ProbeRecord>>,
+}
+
+impl ProbeResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&self, host_name: &str, index: usize, probe: &Probe, measurements: Measurements) {
+        let record = ProbeRecord {
+            target: probe.target_hostname.clone(),
+            kind: probe.kind,
+            summary_interval: probe.summary_interval,
+            measurements,
+        };
+        self.records
+            .lock()
+            .unwrap()
+            .insert((host_name.to_string(), index), record);
+    }
+
+    /// Log a summary of each probe's measurements.
+    pub fn log_summary(&self) {
+        let ms = |x: SimulationTime| x.as_nanos_f64() / 1_000_000.0;
+
+        for ((host_name, _), record) in self.records.lock().unwrap().iter() {
+            let kind = kind_name(record.kind);
+            let Some(summary) = record.summarize(None).pop() else {
+                continue;
+            };
+            match summary.percentiles {
+                Some([p50, p90, p99]) => log::info!(
+                    "Probe from '{host_name}' to '{}' ({kind}): {} of {} requests answered, \
+                    p50/p90/p99 times {:.3}/{:.3}/{:.3} ms",
+                    record.target,
+                    summary.answered,
+                    summary.sent,
+                    ms(p50),
+                    ms(p90),
+                    ms(p99),
+                ),
+                None => log::info!(
+                    "Probe from '{host_name}' to '{}' ({kind}): 0 of {} requests answered",
+                    record.target,
+                    summary.sent,
+                ),
+            }
+        }
+    }
+
+    /// Write a summary of each probe's measurements over each of its summary periods to a CSV file
+    /// in directory `dir`.
+    pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+        let path = dir.join(PROBES_FILE_NAME);
+        let file = std::fs::File::create(&path)
+            .with_context(|| format!("Failed to create file '{}'", path.display()))?;
+        let mut writer = std::io::BufWriter::new(file);
+
+        self.write_csv(&mut writer)
+            .and_then(|_| writer.flush())
+            .with_context(|| format!("Failed to write file '{}'", path.display()))
+    }
+
+    fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "time_ns,source,target,kind,sent,answered,p50_ns,p90_ns,p99_ns"
+        )?;
+
+        for ((host_name, _), record) in self.records.lock().unwrap().iter() {
+            for summary in record.summarize(Some(record.summary_interval)) {
+                let percentiles = match summary.percentiles {
+                    Some(x) => x.map(|x| x.as_nanos().to_string()).join(","),
+                    None => ",,".to_string(),
+                };
+                writeln!(
+                    writer,
+                    "{},{host_name},{},{},{},{},{percentiles}",
+                    summary.start.as_nanos(),
+                    record.target,
+                    kind_name(record.kind),
+                    summary.sent,
+                    summary.answered,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn kind_name(kind: ProbeKind) -> &'static str {
+    match kind {
+        ProbeKind::Ping => "ping",
+        ProbeKind::Fetch => "fetch",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(ms: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_replies() {
+        let request = Header {
+            kind: FETCH_REQUEST,
+            seq: 7,
+            a: 3000,
+            b: 0,
+        };
+        assert_eq!(Header::decode(&request.encode(0)), Some(request));
+
+        let replies = replies(&request);
+        let sizes: Vec<usize> = replies.iter().map(|x| x.len() - HEADER_LEN).collect();
+        assert_eq!(sizes, [1400, 1400, 200]);
+        assert_eq!(
+            Header::decode(&replies[2]),
+            Some(Header {
+                kind: REPLY,
+                seq: 7,
+                a: 2,
+                b: 3,
+            })
+        );
+
+        let ping = Header {
+            kind: PING_REQUEST,
+            seq: 1,
+            a: 0,
+            b: 0,
+        };
+        assert_eq!(replies(&ping).len(), 1);
+        assert!(Header::decode(&[0; HEADER_LEN - 1]).is_none());
+    }
+
+    #[test]
+    fn test_prober_state() {
+        let mut state = ProberState::default();
+
+        let seq = state.request_sent(time(0));
+        let reply = |index, count| Header {
+            kind: REPLY,
+            seq,
+            a: index,
+            b: count,
+        };
+        state.reply_received(&reply(1, 2), time(30));
+        // a duplicate of the same datagram doesn't complete the response
+        state.reply_received(&reply(1, 2), time(31));
+        assert!(state.measurements.answered.is_empty());
+        state.reply_received(&reply(0, 2), time(40));
+        assert_eq!(
+            state.measurements.answered,
+            [(time(0), SimulationTime::from_millis(40))]
+        );
+
+        // replies after the timeout are ignored
+        let seq = state.request_sent(time(1000));
+        state.reply_received(
+            &Header {
+                kind: REPLY,
+                seq,
+                a: 0,
+                b: 1,
+            },
+            time(61_000),
+        );
+        assert_eq!(state.measurements.answered.len(), 1);
+        assert_eq!(state.measurements.sent, [time(0), time(1000)]);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let record = ProbeRecord {
+            target: "server".to_string(),
+            kind: ProbeKind::Ping,
+            summary_interval: SimulationTime::from_secs(10),
+            measurements: Measurements {
+                sent: vec![time(1000), time(2000), time(12_000)],
+                answered: vec![
+                    (time(1000), SimulationTime::from_millis(50)),
+                    (time(2000), SimulationTime::from_millis(70)),
+                ],
+            },
+        };
+
+        let ms = SimulationTime::from_millis;
+        assert_eq!(
+            record.summarize(Some(record.summary_interval)),
+            [
+                PeriodSummary {
+                    start: SimulationTime::ZERO,
+                    sent: 2,
+                    answered: 2,
+                    percentiles: Some([ms(50), ms(70), ms(70)]),
+                },
+                PeriodSummary {
+                    start: SimulationTime::from_secs(10),
+                    sent: 1,
+                    answered: 0,
+                    percentiles: None,
+                },
+            ]
+        );
+
+        let results = ProbeResults::new();
+        results
+            .records
+            .lock()
+            .unwrap()
+            .insert(("client".to_string(), 0), record);
+        let mut csv = Vec::new();
+        results.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time_ns,source,target,kind,sent,answered,p50_ns,p90_ns,p99_ns\n\
+            0,client,server,ping,2,2,50000000,70000000,70000000\n\
+            10000000000,client,server,ping,1,0,,,\n"
+        );
+    }
+}
@@ -1,20 +1,22 @@
 use std::cell::{Cell, Ref, RefCell, RefMut};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::{c_char, c_void, CStr, CString};
 use std::fmt::Write;
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::num::TryFromIntError;
 use std::ops::{Deref, DerefMut};
 use std::os::fd::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
-use std::sync::Arc;
-#[cfg(feature = "perf_timers")]
+use std::sync::{Arc, Weak};
 use std::time::Duration;
 
+use atomic_refcell::AtomicRefCell;
 use linux_api::errno::Errno;
+use linux_api::resource::rusage;
 use linux_api::sched::CloneFlags;
 use linux_api::signal::{
-    defaultaction, siginfo_t, sigset_t, LinuxDefaultAction, SigActionFlags, Signal,
+    defaultaction, sigaction, siginfo_t, sigset_t, LinuxDefaultAction, SigActionFlags, Signal,
     SignalFromI32Error,
 };
 use log::{debug, trace, warn};
@@ -22,6 +24,7 @@ use nix::fcntl::OFlag;
 use nix::sys::signal as nixsignal;
 use nix::sys::stat::Mode;
 use nix::unistd::Pid;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 use shadow_shim_helper_rs::rootedcell::rc::RootedRc;
 use shadow_shim_helper_rs::rootedcell::refcell::RootedRefCell;
 use shadow_shim_helper_rs::rootedcell::Root;
@@ -32,24 +35,38 @@ use shadow_shim_helper_rs::HostId;
 use shadow_shmem::allocator::ShMemBlock;
 
 use super::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
-use super::descriptor::{FileState, StateEventSource};
-use super::host::Host;
+use super::descriptor::pidfd::PidFd;
+use super::descriptor::{FileState, FileStatus, StateEventSource};
+use super::host::{Host, HostSetupError};
 use super::memory_manager::{MemoryManager, ProcessMemoryRef, ProcessMemoryRefMut};
 use super::syscall::formatter::StraceFmtMode;
-use super::syscall_types::ForeignArrayPtr;
+use super::syscall_types::{ForeignArrayPtr, SyscallError};
 use super::thread::{Thread, ThreadId};
 use super::timer::Timer;
-use crate::core::support::configuration::{ProcessFinalState, RunningVal};
+use crate::core::sim_config::ListenSocket;
+use crate::core::support::configuration::{
+    ListenSocketProtocol, OutputCompression, ProcessFinalState, RunningVal,
+};
 use crate::core::work::task::TaskRef;
 use crate::core::worker::Worker;
 use crate::cshadow;
 use crate::host::context::ProcessContext;
-use crate::host::descriptor::Descriptor;
+use crate::host::credentials::Credentials;
+use crate::host::descriptor::socket::inet::legacy_tcp::LegacyTcpSocket;
+use crate::host::descriptor::socket::inet::tcp::TcpSocket;
+use crate::host::descriptor::socket::inet::udp::UdpSocket;
+use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::descriptor::socket::Socket;
+use crate::host::descriptor::{CompatFile, Descriptor, File, OpenFile};
 use crate::host::managed_thread::ManagedThread;
+use crate::host::network::namespace::NetworkNamespace;
+use crate::host::quota::{ProcessQuota, QuotaLimits};
 use crate::host::syscall::formatter::FmtOptions;
 use crate::utility::callback_queue::CallbackQueue;
+use crate::utility::compression::PipeCompressor;
 #[cfg(feature = "perf_timers")]
 use crate::utility::perf_timer::PerfTimer;
+use crate::utility::sockaddr::SockaddrStorage;
 use crate::utility::{self, debug_assert_cloexec};
 
 /// Virtual pid of a shadow process
@@ -112,6 +129,46 @@ impl From<ThreadId> for ProcessId {
     }
 }
 
+/// An error from [`Process::spawn`].
+#[derive(Debug)]
+pub enum SpawnError {
+    /// The process or its main thread could not be created, for example because the process's
+    /// range of thread IDs is used up (`EAGAIN`).
+    Errno(nix::errno::Errno),
+    /// The host's configuration of the process could not be applied.
+    Setup(HostSetupError),
+}
+
+impl From<nix::errno::Errno> for SpawnError {
+    fn from(e: nix::errno::Errno) -> Self {
+        Self::Errno(e)
+    }
+}
+
+impl From<HostSetupError> for SpawnError {
+    fn from(e: HostSetupError) -> Self {
+        Self::Setup(e)
+    }
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Errno(e) => write!(f, "{e}"),
+            Self::Setup(e) => write!(f, "{}", e.report()),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Errno(e) => Some(e),
+            Self::Setup(e) => Some(e),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ExitStatus {
     Normal(i32),
@@ -162,6 +219,23 @@ struct Common {
     // by disallowing `chdir`.
     // See https://github.com/shadow/shadow/issues/2960
     working_dir: CString,
+
+    // absolute path to the directory that the process's absolute paths are resolved in, if any
+    root: Option<CString>,
+
+    // the position in the host's configuration of the process that this process descends from,
+    // which determines the thread IDs that it's assigned
+    id_slot: usize,
+
+    // the simulation time when the process was created
+    start_time: EmulatedTime,
+
+    // Time the process's threads have spent on the simulated CPU.
+    cpu_time: Cell<SimulationTime>,
+
+    // Time spent on the simulated CPU by the process's children that have been
+    // reaped by `wait`, including their own reaped children.
+    children_cpu_time: Cell<SimulationTime>,
 }
 
 impl Common {
@@ -211,10 +285,49 @@ impl Common {
         self.name.to_str().unwrap()
     }
 
+    /// The process's CPU time in clock ticks, as reported in the `si_utime`
+    /// field of a `SIGCHLD` siginfo.
+    fn cpu_time_clock_ticks(&self) -> i64 {
+        // Linux reports clock ticks to userspace in units of `USER_HZ`, which
+        // is 100 on all architectures.
+        (self.cpu_time.get().as_millis() / 10).try_into().unwrap()
+    }
+
     pub fn thread_group_leader_id(&self) -> ThreadId {
         // tid of the thread group leader is equal to the pid.
         ThreadId::from(self.id())
     }
+
+    /// Construct a siginfo describing a stop or continue of the process, as
+    /// sent to the parent process and reported by `waitid`.
+    fn job_control_siginfo(&self, event: JobControlEvent) -> siginfo_t {
+        match event {
+            JobControlEvent::Stopped(stop_signal) => siginfo_t::new_for_sigchld_stopped(
+                Signal::SIGCHLD,
+                self.id.into(),
+                0,
+                stop_signal,
+                self.cpu_time_clock_ticks(),
+                0,
+            ),
+            JobControlEvent::Continued => siginfo_t::new_for_sigchld_continued(
+                Signal::SIGCHLD,
+                self.id.into(),
+                0,
+                self.cpu_time_clock_ticks(),
+                0,
+            ),
+        }
+    }
+}
+
+/// A change in whether a process is stopped, which its parent can wait for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JobControlEvent {
+    /// The process was stopped by the given signal.
+    Stopped(Signal),
+    /// The process was continued by `SIGCONT`.
+    Continued,
 }
 
 /// A process that is currently runnable.
@@ -243,10 +356,23 @@ pub struct RunnableProcess {
     // Shared with forked Processes
     shimlog_file: Arc<std::fs::File>,
 
+    // Limits on the process's CPU and network usage, if any.
+    //
+    // Shared with forked Processes
+    quota: Option<Arc<AtomicRefCell<ProcessQuota>>>,
+
+    // Emulated user and group IDs. Linux stores these per-thread, but libc keeps them in sync
+    // across all threads of a process.
+    credentials: RefCell<Credentials>,
+
     // "dumpable" state, as manipulated via the prctl operations PR_SET_DUMPABLE
     // and PR_GET_DUMPABLE.
     dumpable: Cell<u32>,
 
+    // Signal to send to this process when its parent exits, as manipulated via
+    // the prctl operations PR_SET_PDEATHSIG and PR_GET_PDEATHSIG.
+    parent_death_signal: Cell<Option<Signal>>,
+
     native_pid: Pid,
 
     // timer that tracks the amount of CPU time we spend on plugin execution and processing
@@ -283,6 +409,25 @@ pub struct RunnableProcess {
     // Listeners for child-events.
     // e.g. these listeners are notified when a child of this process exits.
     child_process_event_listeners: RefCell<StateEventSource>,
+
+    // Pidfds referring to this process, which are notified when it exits.
+    pidfds: RefCell<Vec<Weak<AtomicRefCell<PidFd>>>>,
+
+    // The signal that stopped the process, if it's currently stopped. The
+    // threads of a stopped process aren't resumed until it's continued.
+    stop_signal: Cell<Option<Signal>>,
+
+    // Threads that would have been resumed while the process was stopped.
+    // They're resumed when the process is continued.
+    stopped_threads: RefCell<BTreeSet<ThreadId>>,
+
+    // Threads that would have been resumed while the process was over its CPU
+    // quota, and that have a resume scheduled for when it may run again.
+    throttled_threads: RefCell<BTreeSet<ThreadId>>,
+
+    // The most recent stop or continue of the process that hasn't yet been
+    // reported to its parent by `wait`.
+    job_control_event: Cell<Option<JobControlEvent>>,
 }
 
 impl RunnableProcess {
@@ -301,8 +446,10 @@ impl RunnableProcess {
     ) -> nix::Result<ManagedThread> {
         ManagedThread::spawn(
             plugin_path,
+            None,
             argv,
             envv,
+            &self.common.working_dir,
             self.strace_logging
                 .as_ref()
                 .map(|s| s.file.borrow(host.root()))
@@ -390,6 +537,11 @@ impl RunnableProcess {
         self.strace_logging.as_ref().map(|x| x.options)
     }
 
+    /// The process's CPU and network quota, if it has one.
+    pub fn quota(&self) -> Option<&Arc<AtomicRefCell<ProcessQuota>>> {
+        self.quota.as_ref()
+    }
+
     /// If strace logging is disabled, this function will do nothing and return `None`.
     pub fn with_strace_file<T>(&self, f: impl FnOnce(&mut std::fs::File) -> T) -> Option<T> {
         // TODO: get Host from caller. Would need t update syscall-logger.
@@ -481,6 +633,16 @@ impl RunnableProcess {
             };
             host.cpu_borrow_mut().add_delay(delta);
         }
+
+        let simulated_delay = host.cpu_borrow().simulated_delay(delta);
+        let cpu_time = &self.common.cpu_time;
+        cpu_time.set(cpu_time.get() + simulated_delay);
+
+        if let Some(quota) = &self.quota {
+            let now = Worker::current_time().unwrap();
+            quota.borrow_mut().charge_cpu(simulated_delay, now);
+        }
+
         delta
     }
 
@@ -511,6 +673,156 @@ impl RunnableProcess {
         }
     }
 
+    /// The process's action for `signal`.
+    fn signal_action(&self, host: &Host, signal: Signal) -> sigaction {
+        let host_shmem = host.shim_shmem_lock_borrow().unwrap();
+        let process_shmem_protected = self
+            .shim_shared_mem_block
+            .protected
+            .borrow(&host_shmem.root);
+        // SAFETY: We don't call any of the function pointers.
+        unsafe { *process_shmem_protected.signal_action(signal) }
+    }
+
+    /// Discards any pending instances of `signals`, whether they're directed at
+    /// the process or at one of its threads.
+    fn discard_pending_signals(&self, host: &Host, signals: sigset_t) {
+        let host_shmem = host.shim_shmem_lock_borrow().unwrap();
+        let mut process_shmem_protected = self
+            .shim_shared_mem_block
+            .protected
+            .borrow_mut(&host_shmem.root);
+        process_shmem_protected.pending_signals =
+            process_shmem_protected.pending_signals & !signals;
+
+        for thread in self.threads.borrow().values() {
+            let thread = thread.borrow(host.root());
+            let threadmem = thread.shmem();
+            let mut threadprotmem = threadmem.protected.borrow_mut(&host_shmem.root);
+            threadprotmem.pending_signals = threadprotmem.pending_signals & !signals;
+        }
+    }
+
+    /// Applies the job-control effects of generating `signal` for the process,
+    /// which Linux applies when the signal is generated rather than when it's
+    /// delivered. Returns false if the signal stopped the process, in which
+    /// case it shouldn't also be made pending.
+    ///
+    /// See "Process state changes" in `signal(7)`.
+    fn job_control_signal(
+        &self,
+        host: &Host,
+        current_thread: Option<&Thread>,
+        signal: Signal,
+    ) -> bool {
+        let stop_signals = sigset_t::from(Signal::SIGSTOP)
+            | sigset_t::from(Signal::SIGTSTP)
+            | sigset_t::from(Signal::SIGTTIN)
+            | sigset_t::from(Signal::SIGTTOU);
+
+        if signal == Signal::SIGCONT {
+            // A stopped process is continued even if SIGCONT is blocked or
+            // ignored.
+            self.discard_pending_signals(host, stop_signals);
+            if self.stop_signal.take().is_some() {
+                self.job_control_event.set(Some(JobControlEvent::Continued));
+                self.notify_parent_of_job_control_event(
+                    host,
+                    current_thread,
+                    JobControlEvent::Continued,
+                );
+                self.resume_stopped_threads(host);
+            }
+            return true;
+        }
+
+        if signal == Signal::SIGKILL {
+            // The process's threads need to run to be killed, but the parent
+            // isn't notified that it continued.
+            if self.stop_signal.take().is_some() {
+                self.resume_stopped_threads(host);
+            }
+            return true;
+        }
+
+        if !stop_signals.has(signal) {
+            return true;
+        }
+
+        self.discard_pending_signals(host, Signal::SIGCONT.into());
+
+        // SIGSTOP can't be caught or ignored, but the other stop signals only
+        // stop the process if they have their default action.
+        if signal != Signal::SIGSTOP && !self.signal_action(host, signal).is_default() {
+            return true;
+        }
+
+        if self.stop_signal.get().is_none() {
+            debug!("Process {} stopped by {signal:?}", self.common.id());
+            self.stop_signal.set(Some(signal));
+            self.job_control_event
+                .set(Some(JobControlEvent::Stopped(signal)));
+            self.notify_parent_of_job_control_event(
+                host,
+                current_thread,
+                JobControlEvent::Stopped(signal),
+            );
+        }
+
+        false
+    }
+
+    /// Notifies the parent process that this process stopped or continued.
+    /// The parent is sent `SIGCHLD` unless it set `SA_NOCLDSTOP`, and threads
+    /// blocked in `wait` are woken either way.
+    fn notify_parent_of_job_control_event(
+        &self,
+        host: &Host,
+        current_thread: Option<&Thread>,
+        event: JobControlEvent,
+    ) {
+        let parent_pid = self.common.parent_pid.get();
+        if parent_pid == ProcessId::INIT {
+            trace!("Not notifying parent of {event:?}: parent is 'init'");
+            return;
+        }
+        let Some(parent_rc) = host.process_borrow(parent_pid) else {
+            trace!("Not notifying parent of {event:?}: parent {parent_pid:?} not found");
+            return;
+        };
+        let parent = parent_rc.borrow(host.root());
+        let Some(parent_runnable) = parent.as_runnable() else {
+            trace!("Not notifying parent of {event:?}: {parent_pid:?} not running");
+            return;
+        };
+
+        let action = parent_runnable.signal_action(host, Signal::SIGCHLD);
+        if !action.flags_retain().contains(SigActionFlags::SA_NOCLDSTOP) {
+            let siginfo = self.common.job_control_siginfo(event);
+            parent_runnable.signal(host, current_thread, &siginfo);
+        }
+
+        CallbackQueue::queue_and_run(|q| {
+            let mut parent_child_listeners =
+                parent_runnable.child_process_event_listeners.borrow_mut();
+            parent_child_listeners.notify_listeners(
+                FileState::CHILD_EVENT,
+                FileState::CHILD_EVENT,
+                q,
+            );
+        });
+    }
+
+    /// Schedules the threads that weren't resumed while the process was stopped.
+    fn resume_stopped_threads(&self, host: &Host) {
+        let pid = self.common.id();
+        let tids = std::mem::take(&mut *self.stopped_threads.borrow_mut());
+        for tid in tids {
+            let task = TaskRef::new(move |host| host.resume(pid, tid));
+            host.schedule_task_with_delay(task, SimulationTime::ZERO);
+        }
+    }
+
     /// Send the signal described in `siginfo` to `process`. `current_thread`
     /// should be set if there is one (e.g. if this is being called from a syscall
     /// handler), and `None` otherwise (e.g. when called from a timer expiration event).
@@ -526,6 +838,11 @@ impl RunnableProcess {
             Err(SignalFromI32Error(n)) => panic!("Bad signo {n}"),
         };
 
+        if !self.job_control_signal(host, current_thread, signal) {
+            // The signal stopped the process, and isn't delivered.
+            return;
+        }
+
         // Scope for `process_shmem_protected`
         {
             let host_shmem = host.shim_shmem_lock_borrow().unwrap();
@@ -540,7 +857,12 @@ impl RunnableProcess {
                 linux_api::signal::SignalHandler::Action(_) => (),
                 linux_api::signal::SignalHandler::SigIgn => return,
                 linux_api::signal::SignalHandler::SigDfl => {
-                    if defaultaction(signal) == LinuxDefaultAction::IGN {
+                    // The default action of SIGCONT, continuing the process,
+                    // was already applied above.
+                    if matches!(
+                        defaultaction(signal),
+                        LinuxDefaultAction::IGN | LinuxDefaultAction::CONT
+                    ) {
                         return;
                     }
                 }
@@ -630,10 +952,15 @@ impl RunnableProcess {
             name,
             plugin_name,
             working_dir: self.common.working_dir.clone(),
+            root: self.common.root.clone(),
             parent_pid: Cell::new(parent_pid),
             group_id: Cell::new(process_group_id),
             session_id: Cell::new(session_id),
             exit_signal,
+            id_slot: self.common.id_slot,
+            start_time: Worker::current_time().unwrap(),
+            cpu_time: Cell::new(SimulationTime::ZERO),
+            children_cpu_time: Cell::new(SimulationTime::ZERO),
         };
 
         // The child will log to the same strace log file. Entries contain thread IDs,
@@ -657,12 +984,19 @@ impl RunnableProcess {
         );
         let shim_shared_mem_block = shadow_shmem::allocator::shmalloc(shim_shared_mem);
 
+        Worker::call_hooks(|hooks, host_id, now| {
+            hooks.process_started(host_id, now, common.id, common.name())
+        });
+
         let runnable_process = RunnableProcess {
             common,
             expected_final_state: None,
             shim_shared_mem_block,
             strace_logging,
+            credentials: self.credentials.clone(),
             dumpable: self.dumpable.clone(),
+            // `prctl(2)`: "The parent-death signal setting is cleared for the child of a fork(2)."
+            parent_death_signal: Cell::new(None),
             native_pid,
             itimer_real,
             threads,
@@ -670,7 +1004,21 @@ impl RunnableProcess {
             unsafe_borrows: RefCell::new(Vec::new()),
             memory_manager: Box::new(RefCell::new(unsafe { MemoryManager::new(native_pid) })),
             child_process_event_listeners: Default::default(),
+            pidfds: Default::default(),
+            stop_signal: Cell::new(None),
+            stopped_threads: Default::default(),
+            throttled_threads: Default::default(),
+            job_control_event: Cell::new(None),
+            #[cfg(feature = "perf_timers")]
+            cpu_delay_timer: {
+                let mut t = PerfTimer::new();
+                t.stop();
+                RefCell::new(t)
+            },
+            #[cfg(feature = "perf_timers")]
+            total_run_time: Cell::new(Duration::ZERO),
             shimlog_file: self.shimlog_file.clone(),
+            quota: self.quota.clone(),
         };
         let child_process = Process {
             state: RefCell::new(Some(ProcessState::Runnable(runnable_process))),
@@ -707,8 +1055,8 @@ impl ZombieProcess {
         }
         let parentrc = host.process_borrow(parent_pid)?;
 
-        // If the parent has *explicitly* ignored the exit signal, then it
-        // doesn't reap.
+        // If the parent has *explicitly* ignored SIGCHLD, or set
+        // SA_NOCLDWAIT, then it doesn't reap.
         //
         // `waitpid(2)`:
         // > POSIX.1-2001 specifies that if the disposition of SIGCHLD is set to SIG_IGN or the SA_NOCLDWAIT flag is set for SIGCHLD  (see
@@ -717,16 +1065,17 @@ impl ZombieProcess {
         // > SIGCHLD to SIG_IGN unspecified.  Note that even though the default disposition of SIGCHLD is "ignore", explicitly setting the
         // > disposition to SIG_IGN results in different treatment of zombie process children.)
         //
-        // TODO: validate that this applies to whatever signal is configured as the exit
-        // signal, even if it's not SIGCHLD.
-        if let Some(exit_signal) = self.common.exit_signal {
+        // Like Linux, this only applies to children whose exit signal is
+        // SIGCHLD; children created with a different exit signal always become
+        // zombies.
+        if self.common.exit_signal == Some(Signal::SIGCHLD) {
             let parent = parentrc.borrow(host.root());
             let parent_shmem = parent.shmem();
             let host_shmem_lock = host.shim_shmem_lock_borrow().unwrap();
             let parent_shmem_protected = parent_shmem.protected.borrow(&host_shmem_lock.root);
             // SAFETY: We don't dereference function pointers.
-            let action = unsafe { parent_shmem_protected.signal_action(exit_signal) };
-            if action.is_ignore() {
+            let action = unsafe { parent_shmem_protected.signal_action(Signal::SIGCHLD) };
+            if action.is_ignore() || action.flags_retain().contains(SigActionFlags::SA_NOCLDWAIT) {
                 return None;
             }
         }
@@ -783,7 +1132,7 @@ impl ZombieProcess {
                 self.common.id.into(),
                 0,
                 exit_code,
-                0,
+                self.common.cpu_time_clock_ticks(),
                 0,
             ),
             ExitStatus::Signaled(fatal_signal) => {
@@ -796,7 +1145,7 @@ impl ZombieProcess {
                     self.common.id.into(),
                     0,
                     fatal_signal,
-                    0,
+                    self.common.cpu_time_clock_ticks(),
                     0,
                 )
             }
@@ -911,17 +1260,29 @@ impl Process {
         host: &Host,
         plugin_name: CString,
         plugin_path: &CStr,
+        interpreter: Option<&CStr>,
         argv: Vec<CString>,
-        envv: Vec<CString>,
+        mut envv: Vec<CString>,
         pause_for_debugging: bool,
         strace_logging_options: Option<FmtOptions>,
         expected_final_state: ProcessFinalState,
-    ) -> nix::Result<RootedRc<RootedRefCell<Process>>> {
+        quota: Option<QuotaLimits>,
+        credentials: Credentials,
+        root: Option<CString>,
+        listen_sockets: &[ListenSocket],
+        id_slot: usize,
+    ) -> Result<RootedRc<RootedRefCell<Process>>, SpawnError> {
         debug!("starting process '{:?}'", plugin_name);
 
-        let main_thread_id = host.get_new_thread_id();
+        let Some(main_thread_id) = host.get_new_thread_id(id_slot) else {
+            warn!("Ran out of thread IDs");
+            return Err(nix::errno::Errno::EAGAIN.into());
+        };
         let process_id = ProcessId::from(main_thread_id);
 
+        // open the sockets before creating anything that must be cleaned up if they fail
+        let listen_fds = Self::open_listen_sockets(host, process_id, id_slot, listen_sockets)?;
+
         let desc_table = RootedRc::new(
             host.root(),
             RootedRefCell::new(host.root(), DescriptorTable::new()),
@@ -986,20 +1347,33 @@ impl Process {
             );
 
             let name = Self::static_output_file_name(&file_basename, "stdout");
-            Self::open_stdio_file_helper(
+            Self::open_stdio_output_helper(
+                host,
                 &mut descriptor_table,
                 libc::STDOUT_FILENO.try_into().unwrap(),
                 name,
-                OFlag::O_WRONLY,
             );
 
             let name = Self::static_output_file_name(&file_basename, "stderr");
-            Self::open_stdio_file_helper(
+            Self::open_stdio_output_helper(
+                host,
                 &mut descriptor_table,
                 libc::STDERR_FILENO.try_into().unwrap(),
                 name,
-                OFlag::O_WRONLY,
             );
+
+            for (fd, socket) in listen_fds {
+                let desc = Descriptor::new(CompatFile::New(OpenFile::new(File::Socket(socket))));
+                let prev = descriptor_table.register_descriptor_with_fd(desc, fd);
+                assert!(prev.is_none());
+            }
+        }
+
+        if !listen_sockets.is_empty() {
+            let names: Vec<_> = listen_sockets.iter().map(|x| x.name.as_str()).collect();
+            envv.push(CString::new(format!("LISTEN_FDS={}", listen_sockets.len())).unwrap());
+            envv.push(CString::new(format!("LISTEN_PID={process_id}")).unwrap());
+            envv.push(CString::new(format!("LISTEN_FDNAMES={}", names.join(":"))).unwrap());
         }
 
         let shimlog_file = Arc::new(
@@ -1010,8 +1384,10 @@ impl Process {
 
         let mthread = ManagedThread::spawn(
             plugin_path,
+            interpreter,
             argv,
             envv,
+            &working_dir,
             strace_logging
                 .as_ref()
                 .map(|s| s.file.borrow(host.root()))
@@ -1020,8 +1396,15 @@ impl Process {
             host.preload_paths(),
         )?;
         let native_pid = mthread.native_pid();
-        let main_thread =
-            Thread::wrap_mthread(host, mthread, desc_table, process_id, main_thread_id).unwrap();
+        let main_thread = Thread::wrap_mthread(
+            host,
+            mthread,
+            desc_table,
+            process_id,
+            main_thread_id,
+            host.params.timer_slack,
+        )
+        .unwrap();
 
         debug!("process '{:?}' started", plugin_name);
 
@@ -1060,6 +1443,7 @@ impl Process {
             id: process_id,
             host_id: host.id(),
             working_dir,
+            root,
             name,
             plugin_name,
             parent_pid: Cell::new(ProcessId::INIT),
@@ -1068,8 +1452,17 @@ impl Process {
             // Exit signal is moot; since parent is INIT there will never
             // be a valid target for it.
             exit_signal: None,
+            id_slot,
+            start_time: Worker::current_time().unwrap(),
+            cpu_time: Cell::new(SimulationTime::ZERO),
+            children_cpu_time: Cell::new(SimulationTime::ZERO),
         };
-        Ok(RootedRc::new(
+
+        Worker::call_hooks(|hooks, host_id, now| {
+            hooks.process_started(host_id, now, common.id, common.name())
+        });
+
+        let process = RootedRc::new(
             host.root(),
             RootedRefCell::new(
                 host.root(),
@@ -1081,7 +1474,9 @@ impl Process {
                         memory_manager: Box::new(RefCell::new(memory_manager)),
                         itimer_real,
                         strace_logging,
+                        credentials: RefCell::new(credentials),
                         dumpable: Cell::new(cshadow::SUID_DUMP_USER),
+                        parent_death_signal: Cell::new(None),
                         native_pid,
                         unsafe_borrow_mut: RefCell::new(None),
                         unsafe_borrows: RefCell::new(Vec::new()),
@@ -1091,17 +1486,30 @@ impl Process {
                         #[cfg(feature = "perf_timers")]
                         total_run_time: Cell::new(Duration::ZERO),
                         child_process_event_listeners: Default::default(),
+                        pidfds: Default::default(),
+                        stop_signal: Cell::new(None),
+                        stopped_threads: Default::default(),
+                        throttled_threads: Default::default(),
+                        job_control_event: Cell::new(None),
                         shimlog_file,
+                        quota: quota.map(|x| Arc::new(AtomicRefCell::new(ProcessQuota::new(&x)))),
                     }))),
                 },
             ),
-        ))
+        );
+
+        Ok(process)
     }
 
     pub fn id(&self) -> ProcessId {
         self.common().id
     }
 
+    /// The slot used to assign thread IDs to the process's threads and child processes.
+    pub fn id_slot(&self) -> usize {
+        self.common().id_slot
+    }
+
     pub fn parent_id(&self) -> ProcessId {
         self.common().parent_pid.get()
     }
@@ -1146,11 +1554,56 @@ impl Process {
         self.common().thread_group_leader_id()
     }
 
+    /// Stop waiting for the CPU quota to resume `tid`. Returns whether it was waiting.
+    fn take_throttled_thread(&self, tid: ThreadId) -> bool {
+        self.as_runnable()
+            .is_some_and(|runnable| runnable.throttled_threads.borrow_mut().remove(&tid))
+    }
+
     /// Resume execution of `tid` (if it exists).
     /// Should only be called from `Host::resume`.
     pub fn resume(&self, host: &Host, tid: ThreadId) {
         trace!("Continuing thread {} in process {}", tid, self.id());
 
+        // The threads of a stopped process don't run until it's continued.
+        if let Some(runnable) = self.as_runnable() {
+            if runnable.stop_signal.get().is_some() {
+                trace!("Process {} is stopped; deferring thread {tid}", self.id());
+                runnable.stopped_threads.borrow_mut().insert(tid);
+                return;
+            }
+        }
+
+        // if the process has used up its CPU quota, wait until it's allowed to run again
+        let now = Worker::current_time().unwrap();
+        if let Some(ready_time) = self
+            .quota()
+            .and_then(|quota| quota.borrow().cpu_ready_time(now))
+        {
+            trace!("Process {} is throttled until {:?}", self.id(), ready_time);
+            if let Some(runnable) = self.as_runnable() {
+                if !runnable.throttled_threads.borrow_mut().insert(tid) {
+                    trace!("Thread {tid} already has a resume scheduled");
+                    return;
+                }
+            }
+            let pid = self.id();
+            let task = TaskRef::new(move |host| {
+                // skip the resume if the thread has already been resumed some other way
+                let throttled = host
+                    .process_borrow(pid)
+                    .is_some_and(|process| process.borrow(host.root()).take_throttled_thread(tid));
+                if throttled {
+                    host.resume(pid, tid);
+                }
+            });
+            host.schedule_task_at_emulated_time(task, ready_time);
+            return;
+        }
+
+        // this resume replaces any that was scheduled while the process was throttled
+        self.take_throttled_thread(tid);
+
         let threadrc = {
             let Some(runnable) = self.as_runnable() else {
                 debug!("Process {} is no longer running", &*self.name());
@@ -1191,6 +1644,10 @@ impl Process {
         {
             let delay = self.stop_cpu_delay_timer(host);
             debug!("process '{}' ran for {:?}", &*self.name(), delay);
+            if let Some(thread) = self.thread_borrow(tid) {
+                let simulated_delay = host.cpu_borrow().simulated_delay(delay);
+                thread.borrow(host.root()).add_cpu_time(simulated_delay);
+            }
         }
         #[cfg(not(feature = "perf_timers"))]
         debug!("process '{}' done continuing", &*self.name());
@@ -1276,6 +1733,23 @@ impl Process {
         }
     }
 
+    /// See `RunnableProcess::job_control_signal`. Intended for signals that are
+    /// sent directly to one of the process's threads, and so don't go through
+    /// [`Process::signal`].
+    ///
+    /// No-op if the `self` is a `ZombieProcess`.
+    pub fn job_control_signal(
+        &self,
+        host: &Host,
+        current_thread: Option<&Thread>,
+        signal: Signal,
+    ) -> bool {
+        match self.as_runnable() {
+            Some(runnable) => runnable.job_control_signal(host, current_thread, signal),
+            None => true,
+        }
+    }
+
     fn open_stdio_file_helper(
         descriptor_table: &mut DescriptorTable,
         fd: DescriptorHandle,
@@ -1317,6 +1791,142 @@ impl Process {
         );
     }
 
+    /// Open the process's socket activation sockets for the process `process_id` at position
+    /// `process_index` in the host's processes. The sockets are bound, and the TCP sockets are
+    /// listening. Returns each socket with its fd, starting at fd 3 (`SD_LISTEN_FDS_START`).
+    fn open_listen_sockets(
+        host: &Host,
+        process_id: ProcessId,
+        process_index: usize,
+        listen_sockets: &[ListenSocket],
+    ) -> Result<Vec<(DescriptorHandle, Socket)>, HostSetupError> {
+        const SD_LISTEN_FDS_START: u32 = 3;
+
+        let mut sockets = Vec::new();
+
+        for (index, (listen_socket, fd)) in
+            listen_sockets.iter().zip(SD_LISTEN_FDS_START..).enumerate()
+        {
+            let socket = match listen_socket.protocol {
+                ListenSocketProtocol::Tcp if host.params.use_new_tcp => {
+                    InetSocket::Tcp(TcpSocket::new(
+                        FileStatus::empty(),
+                        TcpSocket::config_for_host(&host.params),
+                    ))
+                }
+                ListenSocketProtocol::Tcp => {
+                    InetSocket::LegacyTcp(LegacyTcpSocket::new(FileStatus::empty(), host))
+                }
+                ListenSocketProtocol::Udp => InetSocket::Udp(UdpSocket::new(
+                    FileStatus::empty(),
+                    host.params.init_sock_send_buf_size.try_into().unwrap(),
+                    host.params.init_sock_recv_buf_size.try_into().unwrap(),
+                )),
+            };
+
+            let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, listen_socket.port);
+            let net_ns = host.network_namespace_borrow();
+            let rv = crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+                CallbackQueue::queue_and_run(|cb_queue| {
+                    Self::bind_listen_socket(&socket, addr, process_id, &net_ns, host, cb_queue)
+                })
+            });
+
+            if let Err(e) = rv {
+                let SyscallError::Failed(failed) = e else {
+                    panic!("Opening a listen socket should not block: {e:?}");
+                };
+
+                // don't leave the sockets that were already opened bound to their ports
+                drop(net_ns);
+                let opened = sockets.into_iter().map(|(_, socket)| socket);
+                for mut socket in opened.chain([Socket::Inet(socket)]) {
+                    crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+                        CallbackQueue::queue_and_run(|cb_queue| socket.close(cb_queue))
+                    })
+                    .unwrap();
+                }
+
+                return Err(HostSetupError::ListenSocket {
+                    host: host.name().to_string(),
+                    process: process_index,
+                    index,
+                    port: listen_socket.port,
+                    source: failed.errno,
+                });
+            }
+
+            trace!("Successfully bound listen socket fd {fd} to {addr}");
+            sockets.push((fd.try_into().unwrap(), Socket::Inet(socket)));
+        }
+
+        Ok(sockets)
+    }
+
+    /// Bind the socket activation socket to `addr`, and start listening if it's a TCP socket.
+    fn bind_listen_socket(
+        socket: &InetSocket,
+        addr: SocketAddrV4,
+        process_id: ProcessId,
+        net_ns: &NetworkNamespace,
+        host: &Host,
+        cb_queue: &mut CallbackQueue,
+    ) -> Result<(), SyscallError> {
+        let addr = SockaddrStorage::from(addr);
+        let mut rng = host.random_mut();
+
+        match socket {
+            InetSocket::LegacyTcp(socket) => {
+                LegacyTcpSocket::bind(socket, Some(&addr), net_ns, &mut *rng)?;
+                // the legacy tcp socket adds the connections that it accepts to the process, which
+                // doesn't exist yet
+                LegacyTcpSocket::listen_for_process(
+                    socket,
+                    libc::SOMAXCONN,
+                    net_ns,
+                    &mut *rng,
+                    process_id,
+                )
+            }
+            InetSocket::Tcp(socket) => {
+                TcpSocket::bind(socket, Some(&addr), net_ns, &mut *rng)?;
+                TcpSocket::listen(socket, libc::SOMAXCONN, net_ns, &mut *rng, cb_queue)
+            }
+            InetSocket::Udp(socket) => {
+                UdpSocket::bind(socket, Some(&addr), net_ns, &mut *rng)?;
+                Ok(())
+            }
+            InetSocket::Icmp(_) | InetSocket::Raw(_) => unreachable!(),
+        }
+    }
+
+    /// Open the stdout or stderr file at `path`. If the host compresses its output, the process
+    /// instead writes to a pipe, and the data is compressed by another thread.
+    fn open_stdio_output_helper(
+        host: &Host,
+        descriptor_table: &mut DescriptorTable,
+        fd: DescriptorHandle,
+        path: PathBuf,
+    ) {
+        let compression = host.params.output_compression;
+        if compression == OutputCompression::None {
+            Self::open_stdio_file_helper(descriptor_table, fd, path, OFlag::O_WRONLY);
+            return;
+        }
+
+        let path = utility::compression::compressed_path(&path, compression);
+        let (compressor, pipe) = PipeCompressor::spawn(path.clone(), compression)
+            .unwrap_or_else(|e| panic!("Opening {}: {e}", path.display()));
+
+        // the legacy file opens its own copy of the pipe's write end, which is closed once the
+        // process (and any children that inherited the descriptor) have exited
+        let pipe_path = format!("/proc/self/fd/{}", pipe.as_raw_fd());
+        Self::open_stdio_file_helper(descriptor_table, fd, pipe_path.into(), OFlag::O_WRONLY);
+        drop(pipe);
+
+        host.add_output_compressor(compressor);
+    }
+
     // Needed during early init, before `Self` is created.
     fn static_output_file_name(file_basename: &Path, extension: &str) -> PathBuf {
         let mut path = file_basename.to_owned().into_os_string();
@@ -1349,6 +1959,60 @@ impl Process {
         })
     }
 
+    /// The process's emulated user and group IDs.
+    pub fn credentials(&self) -> Credentials {
+        self.as_runnable().unwrap().credentials.borrow().clone()
+    }
+
+    /// Set the process's emulated user and group IDs.
+    pub fn set_credentials(&self, credentials: Credentials) {
+        *self.as_runnable().unwrap().credentials.borrow_mut() = credentials;
+    }
+
+    /// The process's "dumpable" state, as manipulated by the prctl operations
+    /// PR_SET_DUMPABLE and PR_GET_DUMPABLE.
+    pub fn dumpable(&self) -> u32 {
+        self.as_runnable().unwrap().dumpable.get()
+    }
+
+    /// Set the process's "dumpable" state, as manipulated by the prctl
+    /// operations PR_SET_DUMPABLE and PR_GET_DUMPABLE.
+    pub fn set_dumpable(&self, val: u32) {
+        assert!(val == cshadow::SUID_DUMP_DISABLE || val == cshadow::SUID_DUMP_USER);
+        self.as_runnable().unwrap().dumpable.set(val)
+    }
+
+    /// Create a new pidfd referring to this process. It becomes readable when the
+    /// process exits.
+    pub fn new_pidfd(&self, status: FileStatus) -> Arc<AtomicRefCell<PidFd>> {
+        let Some(runnable) = self.as_runnable() else {
+            return Arc::new(AtomicRefCell::new(PidFd::new(self.id(), true, status)));
+        };
+        let pidfd = Arc::new(AtomicRefCell::new(PidFd::new(self.id(), false, status)));
+        let mut pidfds = runnable.pidfds.borrow_mut();
+        pidfds.retain(|x| x.strong_count() > 0);
+        pidfds.push(Arc::downgrade(&pidfd));
+        pidfd
+    }
+
+    /// The signal to send to the process when its parent exits, as manipulated
+    /// by the prctl operations PR_SET_PDEATHSIG and PR_GET_PDEATHSIG. `None` if
+    /// the process is no longer running.
+    pub fn parent_death_signal(&self) -> Option<Signal> {
+        self.as_runnable()?.parent_death_signal.get()
+    }
+
+    /// Set the signal to send to the process when its parent exits.
+    pub fn set_parent_death_signal(&self, signal: Option<Signal>) {
+        self.as_runnable().unwrap().parent_death_signal.set(signal)
+    }
+
+    /// The process's CPU and network quota, if it has one. Returns `None` if the process is no
+    /// longer running.
+    pub fn quota(&self) -> Option<Arc<AtomicRefCell<ProcessQuota>>> {
+        self.as_runnable()?.quota().cloned()
+    }
+
     /// Deprecated wrapper for `RunnableProcess::strace_logging_options`
     pub fn strace_logging_options(&self) -> Option<FmtOptions> {
         self.as_runnable().unwrap().strace_logging_options()
@@ -1509,6 +2173,38 @@ impl Process {
         };
         log::log!(log_level, "{}", main_result_string);
 
+        Worker::with_event_trace(|event_trace| {
+            let now = Worker::current_time().unwrap();
+            let common = &runnable.common;
+            event_trace.process_exited(
+                common.host_id,
+                common.id,
+                common.name(),
+                common.start_time,
+                now,
+                &format!("{exit_status:?}"),
+            );
+            if log_level == log::Level::Error {
+                event_trace.fault(common.host_id, common.id, now, &main_result_string);
+            }
+        });
+
+        Worker::call_hooks(|hooks, host_id, now| {
+            hooks.process_exited(host_id, now, runnable.common.id, &exit_status)
+        });
+
+        Worker::with_breakpoints(|breakpoints| {
+            let plugin_name = runnable.common.plugin_name.to_str().unwrap();
+            breakpoints.process_exited(host, plugin_name, exit_status);
+        });
+
+        let pidfds = std::mem::take(&mut *runnable.pidfds.borrow_mut());
+        CallbackQueue::queue_and_run(|q| {
+            for pidfd in pidfds.iter().filter_map(Weak::upgrade) {
+                pidfd.borrow_mut().process_exited(q);
+            }
+        });
+
         let zombie = ZombieProcess {
             common: runnable.into_common(),
             exit_status,
@@ -1538,43 +2234,60 @@ impl Process {
         Ref::map(self.as_runnable().unwrap(), |r| &r.shim_shared_mem_block)
     }
 
-    /// Resource usage, as returned e.g. by the `getrusage` syscall.
-    pub fn rusage(&self) -> linux_api::resource::rusage {
-        warn_once_then_debug!(
-            "resource usage (rusage) tracking unimplemented; Returning bogus zeroed values"
-        );
-        // TODO: Actually track some of these.
-        // Assuming we want to support `RUSAGE_THREAD` in the `getrusage`
-        // syscall, we'll actually want to track at the thread level, and either
-        // increment at both thread and process level at the points where we do
-        // the tracking, or dynamically iterate over the threads here and sum
-        // the results.
-        linux_api::resource::rusage {
-            ru_utime: linux_api::time::old_timeval {
-                tv_sec: 0,
-                tv_usec: 0,
-            },
-            ru_stime: linux_api::time::old_timeval {
-                tv_sec: 0,
-                tv_usec: 0,
-            },
-            ru_maxrss: 0,
-            ru_ixrss: 0,
-            ru_idrss: 0,
-            ru_isrss: 0,
-            ru_minflt: 0,
-            ru_majflt: 0,
-            ru_nswap: 0,
-            ru_inblock: 0,
-            ru_oublock: 0,
-            ru_msgsnd: 0,
-            ru_msgrcv: 0,
-            ru_nsignals: 0,
-            ru_nvcsw: 0,
-            ru_nivcsw: 0,
+    /// Resource usage of the process, as returned by `getrusage(RUSAGE_SELF)`.
+    pub fn rusage(&self) -> rusage {
+        rusage_from_cpu_time(self.common().cpu_time.get())
+    }
+
+    /// Resource usage of the process's reaped children, as returned by
+    /// `getrusage(RUSAGE_CHILDREN)`.
+    pub fn children_rusage(&self) -> rusage {
+        rusage_from_cpu_time(self.common().children_cpu_time.get())
+    }
+
+    /// Time spent on the simulated CPU by the process and its reaped children.
+    /// This is what's reported by `wait4` when the process is waited for, and
+    /// what's added to its parent's children's CPU time when it's reaped.
+    pub fn total_cpu_time(&self) -> SimulationTime {
+        let common = self.common();
+        common.cpu_time.get() + common.children_cpu_time.get()
+    }
+
+    /// Accounts for a child process, with total CPU time `cpu_time`, being
+    /// reaped.
+    pub fn add_children_cpu_time(&self, cpu_time: SimulationTime) {
+        let common = self.common();
+        common
+            .children_cpu_time
+            .set(common.children_cpu_time.get() + cpu_time);
+    }
+
+    /// Whether the process is stopped by a stop signal.
+    pub fn is_stopped(&self) -> bool {
+        self.as_runnable()
+            .is_some_and(|runnable| runnable.stop_signal.get().is_some())
+    }
+
+    /// The most recent stop or continue of the process that hasn't yet been
+    /// reported by `wait`, if any.
+    pub fn job_control_event(&self) -> Option<JobControlEvent> {
+        self.as_runnable()
+            .and_then(|runnable| runnable.job_control_event.get())
+    }
+
+    /// Marks the process's most recent stop or continue as reported by `wait`.
+    pub fn clear_job_control_event(&self) {
+        if let Some(runnable) = self.as_runnable() {
+            runnable.job_control_event.set(None);
         }
     }
 
+    /// Construct a siginfo describing a stop or continue of the process, as
+    /// reported by `waitid`.
+    pub fn job_control_siginfo(&self, event: JobControlEvent) -> siginfo_t {
+        self.common().job_control_siginfo(event)
+    }
+
     /// Signal that will be sent to parent process on exit. Typically `Some(SIGCHLD)`.
     pub fn exit_signal(&self) -> Option<Signal> {
         self.common().exit_signal
@@ -1584,6 +2297,11 @@ impl Process {
         Ref::map(self.common(), |common| &common.working_dir)
     }
 
+    /// The directory that the process's absolute paths are resolved in, if any.
+    pub fn root_dir(&self) -> impl Deref<Target = Option<CString>> + '_ {
+        Ref::map(self.common(), |common| &common.root)
+    }
+
     /// Update `self` to complete an `exec` syscall from thread `tid`, replacing
     /// the running managed process with `mthread`.
     pub fn update_for_exec(&mut self, host: &Host, tid: ThreadId, mthread: ManagedThread) {
@@ -1878,6 +2596,19 @@ impl UnsafeBorrowMut {
 // RefCell, but this whole type is temporary scaffolding to support legacy C code.
 unsafe impl Send for UnsafeBorrowMut {}
 
+/// Resource usage for a process that has spent `cpu_time` on the simulated CPU.
+/// Shadow's CPU model doesn't distinguish user and system time, so all of it is
+/// reported as user time, and other resources aren't tracked.
+pub fn rusage_from_cpu_time(cpu_time: SimulationTime) -> rusage {
+    let cpu_time = Duration::from(cpu_time);
+    let mut usage = shadow_pod::zeroed::<rusage>();
+    usage.ru_utime = linux_api::time::old_timeval {
+        tv_sec: cpu_time.as_secs().try_into().unwrap(),
+        tv_usec: cpu_time.subsec_micros().into(),
+    };
+    usage
+}
+
 fn make_name(host: &Host, exe_name: &str, id: ProcessId) -> CString {
     CString::new(format!(
         "{host_name}.{exe_name}.{id}",
@@ -2210,6 +2941,13 @@ mod export {
         proc.id().into()
     }
 
+    /// Returns the ID of the process group that the process is in.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn process_getProcessGroupID(proc: *const Process) -> libc::pid_t {
+        let proc = unsafe { proc.as_ref().unwrap() };
+        proc.group_id().into()
+    }
+
     #[no_mangle]
     pub unsafe extern "C-unwind" fn process_getHostId(proc: *const Process) -> HostId {
         let proc = unsafe { proc.as_ref().unwrap() };
@@ -2246,29 +2984,75 @@ mod export {
         proc.common().working_dir.as_ptr()
     }
 
+    /// Get the directory that the process's absolute paths are resolved in, or NULL if the
+    /// process uses the real root directory.
     #[no_mangle]
-    pub unsafe extern "C-unwind" fn process_straceLoggingMode(
+    pub unsafe extern "C-unwind" fn process_getRootDir(proc: *const Process) -> *const c_char {
+        let proc = unsafe { proc.as_ref().unwrap() };
+        match &proc.common().root {
+            Some(root) => root.as_ptr(),
+            None => std::ptr::null(),
+        }
+    }
+
+    /// Whether the permission checks of the process's emulated credentials are the same as the
+    /// native permission checks, so that the emulated checks can be skipped.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn process_hasNativeCredentials(proc: *const Process) -> bool {
+        let proc = unsafe { proc.as_ref().unwrap() };
+        proc.credentials().is_native()
+    }
+
+    /// Check whether the process's emulated credentials allow the access in `mask` (a combination
+    /// of `R_OK`, `W_OK`, and `X_OK`) to a file with the given owner and mode. The real IDs are
+    /// checked instead of the filesystem IDs if `use_real_ids` is true, as in access(2). Returns 0
+    /// or a negative errno.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn process_checkFileAccess(
         proc: *const Process,
-    ) -> StraceFmtMode {
+        file_uid: libc::uid_t,
+        file_gid: libc::gid_t,
+        file_mode: libc::mode_t,
+        mask: libc::c_int,
+        use_real_ids: bool,
+    ) -> libc::c_int {
         let proc = unsafe { proc.as_ref().unwrap() };
-        proc.strace_logging_options().into()
+        let mut creds = proc.credentials();
+        if use_real_ids {
+            creds = creds.with_real_ids();
+        }
+        match creds.check_access(file_uid, file_gid, file_mode, mask) {
+            Ok(()) => 0,
+            Err(e) => e.to_negated_i32(),
+        }
     }
 
-    /// Get process's "dumpable" state, as manipulated by the prctl operations
-    /// PR_SET_DUMPABLE and PR_GET_DUMPABLE.
+    /// Check whether the process's emulated credentials allow a file owned by `file_uid` to be
+    /// removed from a directory with the given owner and mode. Returns 0 or a negative errno.
     #[no_mangle]
-    pub unsafe extern "C-unwind" fn process_getDumpable(proc: *const Process) -> u32 {
+    pub unsafe extern "C-unwind" fn process_checkFileDelete(
+        proc: *const Process,
+        dir_uid: libc::uid_t,
+        dir_gid: libc::gid_t,
+        dir_mode: libc::mode_t,
+        file_uid: libc::uid_t,
+    ) -> libc::c_int {
         let proc = unsafe { proc.as_ref().unwrap() };
-        proc.as_runnable().unwrap().dumpable.get()
+        match proc
+            .credentials()
+            .check_delete(dir_uid, dir_gid, dir_mode, file_uid)
+        {
+            Ok(()) => 0,
+            Err(e) => e.to_negated_i32(),
+        }
     }
 
-    /// Set process's "dumpable" state, as manipulated by the prctl operations
-    /// PR_SET_DUMPABLE and PR_GET_DUMPABLE.
     #[no_mangle]
-    pub unsafe extern "C-unwind" fn process_setDumpable(proc: *const Process, val: u32) {
-        assert!(val == cshadow::SUID_DUMP_DISABLE || val == cshadow::SUID_DUMP_USER);
+    pub unsafe extern "C-unwind" fn process_straceLoggingMode(
+        proc: *const Process,
+    ) -> StraceFmtMode {
         let proc = unsafe { proc.as_ref().unwrap() };
-        proc.as_runnable().unwrap().dumpable.set(val)
+        proc.strace_logging_options().into()
     }
 
     #[no_mangle]
@@ -2376,6 +3160,31 @@ mod export {
             .unwrap()
     }
 
+    /// Applies the job-control effects of sending `sig` to `target_proc`.
+    /// Returns false if the signal stopped the process, in which case it
+    /// shouldn't also be made pending. Not needed when using `process_signal`.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn process_jobControlSignal(
+        target_proc: *const Process,
+        current_running_thread: *const Thread,
+        sig: i32,
+    ) -> bool {
+        let target_proc = unsafe { target_proc.as_ref().unwrap() };
+        let current_running_thread = unsafe { current_running_thread.as_ref() };
+        let signal = Signal::try_from(sig).unwrap();
+        Worker::with_active_host(|host| {
+            target_proc.job_control_signal(host, current_running_thread, signal)
+        })
+        .unwrap()
+    }
+
+    /// Whether the process is stopped by a stop signal.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn process_isStopped(proc: *const Process) -> bool {
+        let proc = unsafe { proc.as_ref().unwrap() };
+        proc.is_stopped()
+    }
+
     #[no_mangle]
     pub unsafe extern "C-unwind" fn process_addChildEventListener(
         host: *const Host,
@@ -8,7 +8,7 @@
 #define SHD_PROTOCOL_H_
 
 typedef enum _ProtocolType ProtocolType;
-enum _ProtocolType { PNONE, PLOCAL, PTCP, PUDP, PMOCK };
+enum _ProtocolType { PNONE, PLOCAL, PTCP, PUDP, PMOCK, PICMP };
 
 enum ProtocolLocalFlags {
     PLOCAL_NONE = 0,
@@ -18,6 +18,12 @@ enum ProtocolUDPFlags {
     PUDP_NONE = 0,
 };
 
+// The ICMP message types that we support (see RFC 792).
+enum ProtocolICMPType {
+    PICMP_ECHO_REPLY = 0,
+    PICMP_ECHO_REQUEST = 8,
+};
+
 enum ProtocolTCPFlags {
     PTCP_NONE = 0,
     PTCP_RST =  1 << 1,
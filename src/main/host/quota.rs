@@ -0,0 +1,327 @@
+//! Per-process limits on a process's share of the host's CPU and network bandwidth, similar to
+//! Linux cgroups. A process and all of its descendants share the same quota. Rate limits can also
+//! be applied individually to each of the process's sockets.
+
+use std::collections::HashMap;
+use std::net::SocketAddrV4;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+/// The amount of usage that a quota allows above its long-term rate before the process is
+/// throttled, expressed as a duration of usage at that rate.
+const QUOTA_BURST: SimulationTime = SimulationTime::MILLISECOND;
+
+/// Don't bother removing idle per-socket rate limiters until there are at least this many.
+const MAX_IDLE_SOCKET_QUOTAS: usize = 1024;
+
+/// The configured limits of a process's quota.
+#[derive(Debug, Clone)]
+pub struct QuotaLimits {
+    /// Fraction of the host's CPU time that may be used, in the range (0, 1].
+    pub cpu_share: Option<f64>,
+    /// Bytes per second that may be sent on network sockets.
+    pub bandwidth_up: Option<u64>,
+    /// Bytes per second that may be received on network sockets.
+    pub bandwidth_down: Option<u64>,
+    /// Rate limits for individual sockets.
+    pub socket_limits: Vec<SocketRateLimit>,
+}
+
+/// A rate limit that applies individually to each socket with a matching local or peer port.
+#[derive(Debug, Clone, Copy)]
+pub struct SocketRateLimit {
+    pub port: u16,
+    /// Bytes per second that may be sent on each socket.
+    pub bandwidth_up: Option<u64>,
+    /// Bytes per second that may be received on each socket.
+    pub bandwidth_down: Option<u64>,
+}
+
+impl SocketRateLimit {
+    fn matches(&self, flow: &SocketFlow) -> bool {
+        [flow.local, flow.peer]
+            .iter()
+            .flatten()
+            .any(|addr| addr.port() == self.port)
+    }
+
+    fn rate(&self, dir: QuotaDirection) -> Option<u64> {
+        match dir {
+            QuotaDirection::Send => self.bandwidth_up,
+            QuotaDirection::Recv => self.bandwidth_down,
+        }
+    }
+}
+
+/// Identifies a network socket by its local and peer addresses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SocketFlow {
+    pub local: Option<SocketAddrV4>,
+    pub peer: Option<SocketAddrV4>,
+}
+
+/// The direction of a network transfer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum QuotaDirection {
+    Send,
+    Recv,
+}
+
+/// Tracks a process's usage of its quota.
+#[derive(Debug)]
+pub struct ProcessQuota {
+    cpu: Option<RateQuota>,
+    bandwidth_up: Option<RateQuota>,
+    bandwidth_down: Option<RateQuota>,
+    socket_limits: Vec<SocketRateLimit>,
+    socket_quotas: HashMap<(SocketFlow, QuotaDirection), RateQuota>,
+}
+
+impl ProcessQuota {
+    pub fn new(limits: &QuotaLimits) -> Self {
+        Self {
+            // the CPU quota is measured in nanoseconds of CPU time
+            cpu: limits
+                .cpu_share
+                .map(|share| RateQuota::new((share * 1_000_000_000f64) as u64)),
+            bandwidth_up: limits.bandwidth_up.map(RateQuota::new),
+            bandwidth_down: limits.bandwidth_down.map(RateQuota::new),
+            socket_limits: limits.socket_limits.clone(),
+            socket_quotas: HashMap::new(),
+        }
+    }
+
+    /// Account for `delay` of simulated CPU time used by the process at time `now`.
+    pub fn charge_cpu(&mut self, delay: SimulationTime, now: EmulatedTime) {
+        if let Some(cpu) = &mut self.cpu {
+            cpu.charge(delay.as_nanos().try_into().unwrap(), now);
+        }
+    }
+
+    /// The time at which the process is allowed to run again, or `None` if it is allowed to run
+    /// at time `now`.
+    pub fn cpu_ready_time(&self, now: EmulatedTime) -> Option<EmulatedTime> {
+        self.cpu.as_ref().and_then(|x| x.ready_time(now))
+    }
+
+    /// Account for `bytes` transferred by the process on the socket `flow` in the direction `dir`
+    /// at time `now`.
+    pub fn charge_bandwidth(
+        &mut self,
+        dir: QuotaDirection,
+        flow: &SocketFlow,
+        bytes: u64,
+        now: EmulatedTime,
+    ) {
+        let process_quota = match dir {
+            QuotaDirection::Send => self.bandwidth_up.as_mut(),
+            QuotaDirection::Recv => self.bandwidth_down.as_mut(),
+        };
+        if let Some(process_quota) = process_quota {
+            process_quota.charge(bytes, now);
+        }
+
+        let Some(rate) = self.socket_limit(dir, flow) else {
+            return;
+        };
+
+        if self.socket_quotas.len() >= MAX_IDLE_SOCKET_QUOTAS {
+            // a rate limiter with no outstanding usage is the same as a new one
+            self.socket_quotas.retain(|_, x| x.busy_until > now);
+        }
+
+        self.socket_quotas
+            .entry((*flow, dir))
+            .or_insert_with(|| RateQuota::new(rate))
+            .charge(bytes, now);
+    }
+
+    /// The time at which the process is allowed to transfer data on the socket `flow` in the
+    /// direction `dir` again, or `None` if it is allowed to transfer data at time `now`.
+    pub fn bandwidth_ready_time(
+        &self,
+        dir: QuotaDirection,
+        flow: &SocketFlow,
+        now: EmulatedTime,
+    ) -> Option<EmulatedTime> {
+        let process_quota = match dir {
+            QuotaDirection::Send => self.bandwidth_up.as_ref(),
+            QuotaDirection::Recv => self.bandwidth_down.as_ref(),
+        };
+        let socket_quota = self.socket_quotas.get(&(*flow, dir));
+
+        [process_quota, socket_quota]
+            .into_iter()
+            .flatten()
+            .filter_map(|x| x.ready_time(now))
+            .max()
+    }
+
+    /// The rate limit of the first socket limit that matches the socket, if any.
+    fn socket_limit(&self, dir: QuotaDirection, flow: &SocketFlow) -> Option<u64> {
+        self.socket_limits
+            .iter()
+            .find(|x| x.matches(flow))
+            .and_then(|x| x.rate(dir))
+    }
+}
+
+/// A token bucket whose balance is allowed to become negative. Rather than rejecting usage that
+/// exceeds the available tokens (we usually only learn how much was used after the fact), the
+/// usage is always accepted and the owner must wait until the bucket has refilled.
+///
+/// Instead of storing a token balance, we store the time at which all of the charged usage would
+/// have completed at the configured rate. The bucket has a capacity of [`QUOTA_BURST`] worth of
+/// tokens.
+#[derive(Debug)]
+struct RateQuota {
+    units_per_sec: u64,
+    busy_until: EmulatedTime,
+}
+
+impl RateQuota {
+    fn new(units_per_sec: u64) -> Self {
+        assert!(units_per_sec > 0);
+        Self {
+            units_per_sec,
+            busy_until: EmulatedTime::SIMULATION_START,
+        }
+    }
+
+    fn charge(&mut self, units: u64, now: EmulatedTime) {
+        // u128 so that the multiplication can't overflow
+        let nanos = u128::from(units) * 1_000_000_000 / u128::from(self.units_per_sec);
+        let duration = SimulationTime::from_nanos(nanos.try_into().unwrap());
+
+        // unused time in the past doesn't accumulate
+        self.busy_until = std::cmp::max(self.busy_until, now) + duration;
+    }
+
+    fn ready_time(&self, now: EmulatedTime) -> Option<EmulatedTime> {
+        let ready_time = self.busy_until.checked_sub(QUOTA_BURST)?;
+        (ready_time > now).then_some(ready_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bandwidth_quota() {
+        let limits = QuotaLimits {
+            cpu_share: None,
+            bandwidth_up: Some(1_000_000),
+            bandwidth_down: None,
+            socket_limits: Vec::new(),
+        };
+        let mut quota = ProcessQuota::new(&limits);
+        let start = EmulatedTime::SIMULATION_START;
+        let dir = QuotaDirection::Send;
+        let flow = SocketFlow {
+            local: None,
+            peer: None,
+        };
+
+        assert_eq!(quota.bandwidth_ready_time(dir, &flow, start), None);
+
+        // within the burst allowance
+        quota.charge_bandwidth(dir, &flow, 1000, start);
+        assert_eq!(quota.bandwidth_ready_time(dir, &flow, start), None);
+
+        // 10 ms worth of data
+        quota.charge_bandwidth(dir, &flow, 10_000, start);
+        assert_eq!(
+            quota.bandwidth_ready_time(dir, &flow, start),
+            Some(start + SimulationTime::from_millis(10))
+        );
+        assert_eq!(
+            quota.bandwidth_ready_time(dir, &flow, start + SimulationTime::from_millis(10)),
+            None
+        );
+
+        // no limit in the other direction
+        quota.charge_bandwidth(QuotaDirection::Recv, &flow, 1_000_000, start);
+        assert_eq!(
+            quota.bandwidth_ready_time(QuotaDirection::Recv, &flow, start),
+            None
+        );
+
+        // idle time doesn't accumulate
+        let later = start + SimulationTime::from_secs(10);
+        quota.charge_bandwidth(dir, &flow, 5000, later);
+        assert_eq!(
+            quota.bandwidth_ready_time(dir, &flow, later),
+            Some(later + SimulationTime::from_millis(4))
+        );
+    }
+
+    #[test]
+    fn test_cpu_quota() {
+        let limits = QuotaLimits {
+            cpu_share: Some(0.25),
+            bandwidth_up: None,
+            bandwidth_down: None,
+            socket_limits: Vec::new(),
+        };
+        let mut quota = ProcessQuota::new(&limits);
+        let start = EmulatedTime::SIMULATION_START;
+
+        // 1 ms of CPU time at a 25% share takes 4 ms of wall time
+        quota.charge_cpu(SimulationTime::from_millis(1), start);
+        assert_eq!(
+            quota.cpu_ready_time(start),
+            Some(start + SimulationTime::from_millis(3))
+        );
+        assert_eq!(
+            quota.cpu_ready_time(start + SimulationTime::from_millis(3)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_socket_rate_limit() {
+        let limits = QuotaLimits {
+            cpu_share: None,
+            bandwidth_up: None,
+            bandwidth_down: None,
+            socket_limits: vec![SocketRateLimit {
+                port: 443,
+                bandwidth_up: Some(1_000_000),
+                bandwidth_down: None,
+            }],
+        };
+        let mut quota = ProcessQuota::new(&limits);
+        let start = EmulatedTime::SIMULATION_START;
+        let dir = QuotaDirection::Send;
+
+        let flow_1 = SocketFlow {
+            local: Some("11.0.0.1:20000".parse().unwrap()),
+            peer: Some("11.0.0.2:443".parse().unwrap()),
+        };
+        let flow_2 = SocketFlow {
+            local: Some("11.0.0.1:20001".parse().unwrap()),
+            peer: Some("11.0.0.2:443".parse().unwrap()),
+        };
+        let other_flow = SocketFlow {
+            local: Some("11.0.0.1:20002".parse().unwrap()),
+            peer: Some("11.0.0.2:80".parse().unwrap()),
+        };
+
+        quota.charge_bandwidth(dir, &flow_1, 11_000, start);
+        quota.charge_bandwidth(dir, &other_flow, 11_000, start);
+
+        // only the matching socket is limited
+        assert_eq!(
+            quota.bandwidth_ready_time(dir, &flow_1, start),
+            Some(start + SimulationTime::from_millis(10))
+        );
+        assert_eq!(quota.bandwidth_ready_time(dir, &flow_2, start), None);
+        assert_eq!(quota.bandwidth_ready_time(dir, &other_flow, start), None);
+        assert_eq!(
+            quota.bandwidth_ready_time(QuotaDirection::Recv, &flow_1, start),
+            None
+        );
+    }
+}
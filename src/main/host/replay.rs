@@ -0,0 +1,429 @@
+//! Replay of recorded network traffic. A replay host reads UDP datagrams from a packet capture or a
+//! flow trace, and sends them to other hosts at the simulated times that they were recorded.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::sim_config::Replay;
+use crate::core::support::configuration::ReplayFormat;
+use crate::core::work::task::TaskRef;
+use crate::core::worker::Worker;
+use crate::host::host::Host;
+use crate::network::packet::{PacketRc, PacketStatus};
+use crate::utility::pcap_reader::{LinkType, PcapReader};
+
+/// The largest payload of a UDP datagram sent over IPv4.
+const MAX_UDP_PAYLOAD: usize = 65507;
+
+/// A UDP datagram read from a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TraceDatagram {
+    /// The time that the datagram was recorded, relative to an arbitrary epoch.
+    time: Duration,
+    src_port: u16,
+    /// The destination in the trace, which is mapped to one of the configured destinations.
+    dst: SocketAddrV4,
+    payload: Vec<u8>,
+}
+
+/// A source of datagrams, in the order they were recorded.
+trait TraceSource: Send {
+    /// The next datagram, or `None` if there are no more datagrams.
+    fn next_datagram(&mut self) -> anyhow::Result<Option<TraceDatagram>>;
+
+    /// The number of records that were skipped since they could not be replayed.
+    fn num_skipped(&self) -> u64;
+}
+
+/// Reads UDP/IPv4 datagrams from a pcap file. Other packets are skipped.
+struct PcapSource<R: Read> {
+    reader: PcapReader<R>,
+    skipped: u64,
+}
+
+impl<R: Read> PcapSource<R> {
+    fn new(reader: R) -> anyhow::Result<Self> {
+        let reader = PcapReader::new(reader).context("Failed to read the pcap file header")?;
+        if let LinkType::Other(x) = reader.link_type() {
+            return Err(anyhow::anyhow!("Unsupported pcap link type {x}"));
+        }
+        Ok(Self { reader, skipped: 0 })
+    }
+}
+
+impl<R: Read + Send> TraceSource for PcapSource<R> {
+    fn next_datagram(&mut self) -> anyhow::Result<Option<TraceDatagram>> {
+        while let Some(packet) = self.reader.next_packet()? {
+            match parse_udp_datagram(self.reader.link_type(), &packet.data) {
+                Some((src_port, dst, payload)) => {
+                    return Ok(Some(TraceDatagram {
+                        time: packet.timestamp,
+                        src_port,
+                        dst,
+                        payload,
+                    }))
+                }
+                None => self.skipped += 1,
+            }
+        }
+        Ok(None)
+    }
+
+    fn num_skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+/// Reads datagrams from a CSV file where each line is a single datagram in the form
+/// "time,src_port,dst_ip,dst_port,bytes", where "time" is in seconds. Empty lines and lines
+/// beginning with '#' are ignored. The datagram payloads are filled with zeros.
+struct FlowSource<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line_num: usize,
+}
+
+impl<R: BufRead> FlowSource<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_num: 0,
+        }
+    }
+}
+
+impl<R: BufRead + Send> TraceSource for FlowSource<R> {
+    fn next_datagram(&mut self) -> anyhow::Result<Option<TraceDatagram>> {
+        for line in &mut self.lines {
+            self.line_num += 1;
+            let line = line?;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let datagram = parse_flow_record(line)
+                .with_context(|| format!("Invalid flow record on line {}", self.line_num))?;
+            return Ok(Some(datagram));
+        }
+        Ok(None)
+    }
+
+    fn num_skipped(&self) -> u64 {
+        0
+    }
+}
+
+fn parse_flow_record(line: &str) -> anyhow::Result<TraceDatagram> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    let [time, src_port, dst_ip, dst_port, bytes] = fields[..] else {
+        return Err(anyhow::anyhow!(
+            "Expected 5 fields but found {}",
+            fields.len()
+        ));
+    };
+
+    let time: f64 = time.parse().context("Invalid time")?;
+    let time = Duration::try_from_secs_f64(time).context("Invalid time")?;
+    let bytes: usize = bytes.parse().context("Invalid number of bytes")?;
+    if bytes > MAX_UDP_PAYLOAD {
+        return Err(anyhow::anyhow!(
+            "Datagrams can't be larger than {MAX_UDP_PAYLOAD} bytes"
+        ));
+    }
+
+    Ok(TraceDatagram {
+        time,
+        src_port: src_port.parse().context("Invalid source port")?,
+        dst: SocketAddrV4::new(
+            dst_ip.parse().context("Invalid destination IP address")?,
+            dst_port.parse().context("Invalid destination port")?,
+        ),
+        payload: vec![0; bytes],
+    })
+}
+
+/// Parse a captured frame, returning the source port, destination address, and payload if it's an
+/// unfragmented UDP/IPv4 datagram. If the capture was truncated, the payload is padded with zeros to
+/// its original length.
+fn parse_udp_datagram(link_type: LinkType, frame: &[u8]) -> Option<(u16, SocketAddrV4, Vec<u8>)> {
+    const ETHERTYPE_IPV4: u16 = 0x0800;
+    const ETHERTYPE_VLAN: u16 = 0x8100;
+
+    let be_u16 = |bytes: &[u8], offset: usize| -> Option<u16> {
+        Some(u16::from_be_bytes(
+            bytes.get(offset..offset + 2)?.try_into().unwrap(),
+        ))
+    };
+
+    let ip = match link_type {
+        LinkType::Null => {
+            // the address family is in the byte order of the machine that captured the packet
+            let family: [u8; 4] = frame.get(0..4)?.try_into().unwrap();
+            let af_inet = libc::AF_INET as u32;
+            if u32::from_le_bytes(family) != af_inet && u32::from_be_bytes(family) != af_inet {
+                return None;
+            }
+            &frame[4..]
+        }
+        LinkType::Ethernet => {
+            let mut offset = 12;
+            let mut ethertype = be_u16(frame, offset)?;
+            if ethertype == ETHERTYPE_VLAN {
+                offset += 4;
+                ethertype = be_u16(frame, offset)?;
+            }
+            if ethertype != ETHERTYPE_IPV4 {
+                return None;
+            }
+            frame.get(offset + 2..)?
+        }
+        LinkType::LinuxSll => {
+            if be_u16(frame, 14)? != ETHERTYPE_IPV4 {
+                return None;
+            }
+            frame.get(16..)?
+        }
+        LinkType::Raw => frame,
+        LinkType::Other(_) => return None,
+    };
+
+    // IPv4 header
+    let version = ip.first()? >> 4;
+    let header_len = usize::from(ip.first()? & 0x0f) * 4;
+    let flags_and_offset = be_u16(ip, 6)?;
+    let protocol = *ip.get(9)?;
+    let more_fragments = flags_and_offset & 0x2000 != 0;
+    let fragment_offset = flags_and_offset & 0x1fff;
+    if version != 4
+        || header_len < 20
+        || protocol != libc::IPPROTO_UDP as u8
+        || more_fragments
+        || fragment_offset != 0
+    {
+        return None;
+    }
+    let dst_ip = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(16..20)?).unwrap());
+
+    // UDP header
+    let udp = ip.get(header_len..)?;
+    let src_port = be_u16(udp, 0)?;
+    let dst_port = be_u16(udp, 2)?;
+    let payload_len = usize::from(be_u16(udp, 4)?).checked_sub(8)?;
+
+    let captured = udp.get(8..)?;
+    let mut payload = captured[..std::cmp::min(captured.len(), payload_len)].to_vec();
+    payload.resize(payload_len, 0);
+
+    Some((src_port, SocketAddrV4::new(dst_ip, dst_port), payload))
+}
+
+/// Sends the datagrams of a trace from a host.
+pub struct TraceReplay {
+    source: Box<dyn TraceSource>,
+    /// The next datagram to send.
+    next: Option<TraceDatagram>,
+    /// The trace time of the first datagram.
+    first_time: Option<Duration>,
+    start_time: EmulatedTime,
+    time_scale: f64,
+    destinations: Vec<Ipv4Addr>,
+    /// The configured destination that each destination in the trace is mapped to.
+    destination_map: HashMap<Ipv4Addr, Ipv4Addr>,
+    num_sent: u64,
+}
+
+impl TraceReplay {
+    /// Open the trace.
+    pub fn new(replay: &Replay) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(&replay.path)
+            .with_context(|| format!("Failed to open '{}'", replay.path.display()))?;
+        let reader = std::io::BufReader::new(file);
+
+        let source: Box<dyn TraceSource> = match replay.format {
+            ReplayFormat::Pcap => Box::new(PcapSource::new(reader)?),
+            ReplayFormat::Flows => Box::new(FlowSource::new(reader)),
+        };
+
+        assert!(!replay.destinations.is_empty());
+
+        Ok(Self {
+            source,
+            next: None,
+            first_time: None,
+            start_time: EmulatedTime::SIMULATION_START + replay.start_time,
+            time_scale: replay.time_scale,
+            destinations: replay.destinations.clone(),
+            destination_map: HashMap::new(),
+            num_sent: 0,
+        })
+    }
+
+    /// Schedule the datagrams to be sent from the host.
+    pub fn start(self, host: &Host) {
+        let start_time = self.start_time;
+        let replay = Arc::new(Mutex::new(self));
+        host.schedule_task_at_emulated_time(Self::task(replay), start_time);
+    }
+
+    fn task(replay: Arc<Mutex<Self>>) -> TaskRef {
+        TaskRef::new(move |host| {
+            let next_time = replay.lock().unwrap().send_due(host);
+            if let Some(next_time) = next_time {
+                host.schedule_task_at_emulated_time(Self::task(Arc::clone(&replay)), next_time);
+            }
+        })
+    }
+
+    /// Send all datagrams that are due, and return the time that the next datagram is due.
+    fn send_due(&mut self, host: &Host) -> Option<EmulatedTime> {
+        let now = Worker::current_time().unwrap();
+
+        loop {
+            if self.next.is_none() {
+                self.next = match self.source.next_datagram() {
+                    Ok(Some(x)) => Some(x),
+                    Ok(None) => {
+                        log::debug!(
+                            "Finished replaying trace: sent {} datagrams and skipped {} records",
+                            self.num_sent,
+                            self.source.num_skipped(),
+                        );
+                        return None;
+                    }
+                    Err(e) => {
+                        log::warn!("Stopping replay after failing to read the trace: {e:?}");
+                        return None;
+                    }
+                };
+            }
+
+            let due = self.due_time();
+            if due > now {
+                return Some(due);
+            }
+
+            let datagram = self.next.take().unwrap();
+            self.send(host, datagram);
+        }
+    }
+
+    /// The simulated time at which the next datagram should be sent.
+    fn due_time(&mut self) -> EmulatedTime {
+        let datagram = self.next.as_ref().unwrap();
+        let first_time = *self.first_time.get_or_insert(datagram.time);
+        // traces aren't always in order, so send late datagrams immediately
+        let offset = datagram.time.saturating_sub(first_time);
+        let offset = SimulationTime::try_from(offset.mul_f64(self.time_scale)).unwrap();
+        self.start_time + offset
+    }
+
+    fn send(&mut self, host: &Host, datagram: TraceDatagram) {
+        let num_mapped = self.destination_map.len();
+        let dst_ip = *self
+            .destination_map
+            .entry(*datagram.dst.ip())
+            .or_insert_with(|| self.destinations[num_mapped % self.destinations.len()]);
+
+        let src = SocketAddrV4::new(host.default_ip(), datagram.src_port);
+        let dst = SocketAddrV4::new(dst_ip, datagram.dst.port());
+
+        let mut packet = PacketRc::new();
+        packet.set_udp(src, dst);
+        packet.set_payload(&datagram.payload, host.get_next_packet_priority());
+        packet.add_status(PacketStatus::SndCreated);
+
+        // sent like the host's other traffic, so that it's limited by the host's bandwidth and
+        // captured in its pcap file
+        host.interface_borrow(host.default_ip())
+            .unwrap()
+            .send_socketless_packet(host, packet);
+        self.num_sent += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_ipv4(dst: SocketAddrV4, src_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut ip = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, 17, 0, 0, 11, 0, 0, 1];
+        ip.extend(dst.ip().octets());
+        ip.extend(src_port.to_be_bytes());
+        ip.extend(dst.port().to_be_bytes());
+        ip.extend(u16::try_from(payload.len() + 8).unwrap().to_be_bytes());
+        ip.extend([0, 0]);
+        ip.extend(payload);
+        ip
+    }
+
+    #[test]
+    fn test_parse_udp_datagram() {
+        let dst: SocketAddrV4 = "11.0.0.2:53".parse().unwrap();
+        let ip = udp_ipv4(dst, 4000, b"hello");
+
+        assert_eq!(
+            parse_udp_datagram(LinkType::Raw, &ip),
+            Some((4000, dst, b"hello".to_vec()))
+        );
+
+        let mut ethernet = vec![0; 12];
+        ethernet.extend([0x08, 0x00]);
+        ethernet.extend(&ip);
+        assert_eq!(
+            parse_udp_datagram(LinkType::Ethernet, &ethernet),
+            Some((4000, dst, b"hello".to_vec()))
+        );
+
+        // truncated captures are padded
+        assert_eq!(
+            parse_udp_datagram(LinkType::Ethernet, &ethernet[..ethernet.len() - 2]),
+            Some((4000, dst, b"hel\0\0".to_vec()))
+        );
+
+        // ARP
+        ethernet[12..14].copy_from_slice(&[0x08, 0x06]);
+        assert_eq!(parse_udp_datagram(LinkType::Ethernet, &ethernet), None);
+
+        // TCP
+        let mut tcp = ip.clone();
+        tcp[9] = 6;
+        assert_eq!(parse_udp_datagram(LinkType::Raw, &tcp), None);
+
+        assert_eq!(parse_udp_datagram(LinkType::Raw, &ip[..10]), None);
+    }
+
+    #[test]
+    fn test_flow_source() {
+        let trace = "# time,src_port,dst_ip,dst_port,bytes\n\
+                     0.5,4000,10.0.0.1,80,100\n\
+                     \n\
+                     1.25, 4001, 10.0.0.2, 443, 0\n";
+        let mut source = FlowSource::new(trace.as_bytes());
+
+        assert_eq!(
+            source.next_datagram().unwrap(),
+            Some(TraceDatagram {
+                time: Duration::from_millis(500),
+                src_port: 4000,
+                dst: "10.0.0.1:80".parse().unwrap(),
+                payload: vec![0; 100],
+            })
+        );
+        assert_eq!(
+            source.next_datagram().unwrap().unwrap().time,
+            Duration::from_millis(1250)
+        );
+        assert_eq!(source.next_datagram().unwrap(), None);
+
+        let mut source = FlowSource::new("1.0,4000,10.0.0.1,80\n".as_bytes());
+        assert!(source.next_datagram().is_err());
+    }
+}
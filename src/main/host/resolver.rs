@@ -0,0 +1,334 @@
+//! The behaviour of a host's name lookups: how long they take, which names fail to resolve during
+//! which windows of time, and the order of a name's IPv4 and IPv6 addresses. Shadow resolves names
+//! from its own table of hosts, so without this every lookup succeeds instantly. This lets
+//! experiments test how applications cope with slow resolvers and DNS outages, and how dual-stack
+//! clients (for example with Happy Eyeballs, RFC 8305) cope with slow or broken IPv6 lookups.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use linux_api::errno::Errno;
+use rand::Rng;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::support::configuration::{
+    ResolverAddressOrder, ResolverError, ResolverFamily, ResolverLatencyOptions, ResolverOptions,
+};
+use crate::core::support::units;
+
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    latency: Latency,
+    /// The latency of IPv6 lookups, if different from `latency`.
+    ipv6_latency: Option<Latency>,
+    timeout: SimulationTime,
+    failures: Vec<Failure>,
+    address_order: ResolverAddressOrder,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Latency {
+    Fixed(SimulationTime),
+    /// The minimum and maximum latency in nanoseconds.
+    Uniform(u64, u64),
+}
+
+#[derive(Debug, Clone)]
+struct Failure {
+    name: String,
+    error: ResolverError,
+    start: EmulatedTime,
+    end: Option<EmulatedTime>,
+    /// The address family that fails, or both if `None`.
+    family: Option<ResolverFamily>,
+}
+
+/// A lookup that has been started.
+#[derive(Debug, Clone)]
+pub struct Lookup {
+    name: String,
+    family: ResolverFamily,
+    done_time: EmulatedTime,
+    error: Option<Errno>,
+}
+
+/// The status of a lookup at a given time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LookupStatus {
+    /// The lookup returns its result at the given time.
+    Pending(EmulatedTime),
+    /// The name should be resolved as usual.
+    Resolve,
+    /// The lookup fails with the given error.
+    Fail(Errno),
+}
+
+impl Resolver {
+    pub fn new(options: &ResolverOptions) -> anyhow::Result<Self> {
+        let latency = match &options.latency {
+            None => Latency::Fixed(SimulationTime::ZERO),
+            Some(latency) => Latency::new(latency)?,
+        };
+        let ipv6_latency = options
+            .ipv6_latency
+            .as_ref()
+            .map(Latency::new)
+            .transpose()?;
+
+        let failures = options
+            .failures
+            .iter()
+            .map(|x| {
+                Ok(Failure {
+                    name: x.name.clone(),
+                    error: x.error,
+                    start: EmulatedTime::SIMULATION_START + to_sim_time(&x.start)?,
+                    end: match &x.end {
+                        Some(end) => Some(EmulatedTime::SIMULATION_START + to_sim_time(end)?),
+                        None => None,
+                    },
+                    family: x.family,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            latency,
+            ipv6_latency,
+            timeout: to_sim_time(&options.timeout)?,
+            failures,
+            address_order: options.address_order,
+        })
+    }
+
+    /// Whether a name's IPv6 addresses should be listed before its IPv4 addresses.
+    pub fn prefers_ipv6(&self) -> bool {
+        self.address_order == ResolverAddressOrder::Ipv6First
+    }
+
+    /// Start a lookup of the `family` addresses of `name` at time `now`.
+    pub fn start_lookup(
+        &self,
+        name: &str,
+        family: ResolverFamily,
+        now: EmulatedTime,
+        rng: &mut impl Rng,
+    ) -> Lookup {
+        let failure = self.failures.iter().find(|x| {
+            x.name.eq_ignore_ascii_case(name)
+                && x.family.map_or(true, |f| f == family)
+                && x.start <= now
+                && x.end.map_or(true, |end| now < end)
+        });
+
+        let latency = match family {
+            ResolverFamily::Ipv4 => self.latency,
+            ResolverFamily::Ipv6 => self.ipv6_latency.unwrap_or(self.latency),
+        };
+
+        let (delay, error) = match failure.map(|x| x.error) {
+            Some(ResolverError::Timeout) => (self.timeout, Some(Errno::ETIMEDOUT)),
+            Some(ResolverError::Nxdomain) => (latency.sample(rng), Some(Errno::ENOENT)),
+            None => (latency.sample(rng), None),
+        };
+
+        Lookup {
+            name: name.to_string(),
+            family,
+            done_time: now + delay,
+            error,
+        }
+    }
+}
+
+impl Latency {
+    fn new(options: &ResolverLatencyOptions) -> anyhow::Result<Self> {
+        Ok(match options {
+            ResolverLatencyOptions::Fixed { latency } => Latency::Fixed(to_sim_time(latency)?),
+            ResolverLatencyOptions::Uniform { min, max } => {
+                let nanos =
+                    |x| -> anyhow::Result<u64> { Ok(u64::try_from(to_sim_time(x)?.as_nanos())?) };
+                let (min, max) = (nanos(min)?, nanos(max)?);
+                if min > max {
+                    anyhow::bail!("The resolver's minimum latency is larger than its maximum");
+                }
+                Latency::Uniform(min, max)
+            }
+        })
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> SimulationTime {
+        match *self {
+            Latency::Fixed(x) => x,
+            Latency::Uniform(min, max) => SimulationTime::from_nanos(rng.gen_range(min..=max)),
+        }
+    }
+}
+
+impl Lookup {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn family(&self) -> ResolverFamily {
+        self.family
+    }
+
+    /// The status of the lookup at time `now`.
+    pub fn status(&self, now: EmulatedTime) -> LookupStatus {
+        if now < self.done_time {
+            return LookupStatus::Pending(self.done_time);
+        }
+
+        match self.error {
+            Some(e) => LookupStatus::Fail(e),
+            None => LookupStatus::Resolve,
+        }
+    }
+}
+
+fn to_sim_time(time: &units::Time<units::TimePrefix>) -> anyhow::Result<SimulationTime> {
+    SimulationTime::try_from(Duration::from(*time))
+        .ok()
+        .context("The resolver has an out of range time")
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    use super::*;
+
+    fn time_ms(ms: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_lookups() {
+        let options: ResolverOptions = serde_yaml::from_str(
+            "
+            latency:
+              type: uniform
+              min: 10 ms
+              max: 20 ms
+            timeout: 3 s
+            failures:
+            - name: db.example
+              error: nxdomain
+              start: 1 s
+              end: 2 s
+            - name: api.example
+              error: timeout
+            ",
+        )
+        .unwrap();
+        let resolver = Resolver::new(&options).unwrap();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+
+        let lookup = resolver.start_lookup("server", ResolverFamily::Ipv4, time_ms(0), &mut rng);
+        let LookupStatus::Pending(done_time) = lookup.status(time_ms(0)) else {
+            panic!("The lookup should be pending");
+        };
+        assert!(done_time >= time_ms(10) && done_time <= time_ms(20));
+        assert_eq!(lookup.status(time_ms(20)), LookupStatus::Resolve);
+
+        // the failure only applies during its window
+        let lookup =
+            resolver.start_lookup("DB.example", ResolverFamily::Ipv4, time_ms(1000), &mut rng);
+        assert_eq!(
+            lookup.status(time_ms(1020)),
+            LookupStatus::Fail(Errno::ENOENT)
+        );
+        let lookup =
+            resolver.start_lookup("db.example", ResolverFamily::Ipv4, time_ms(2000), &mut rng);
+        assert_eq!(lookup.status(time_ms(2020)), LookupStatus::Resolve);
+
+        let lookup =
+            resolver.start_lookup("api.example", ResolverFamily::Ipv4, time_ms(0), &mut rng);
+        assert_eq!(
+            lookup.status(time_ms(2999)),
+            LookupStatus::Pending(time_ms(3000))
+        );
+        assert_eq!(
+            lookup.status(time_ms(3000)),
+            LookupStatus::Fail(Errno::ETIMEDOUT)
+        );
+    }
+
+    #[test]
+    fn test_ipv6_lookups() {
+        let options: ResolverOptions = serde_yaml::from_str(
+            "
+            latency:
+              type: fixed
+              latency: 10 ms
+            ipv6_latency:
+              type: fixed
+              latency: 300 ms
+            address_order: ipv6-first
+            failures:
+            - name: server
+              error: nxdomain
+              family: ipv6
+            ",
+        )
+        .unwrap();
+        let resolver = Resolver::new(&options).unwrap();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        assert!(resolver.prefers_ipv6());
+
+        let lookup = resolver.start_lookup("client", ResolverFamily::Ipv6, time_ms(0), &mut rng);
+        assert_eq!(
+            lookup.status(time_ms(0)),
+            LookupStatus::Pending(time_ms(300))
+        );
+        assert_eq!(lookup.status(time_ms(300)), LookupStatus::Resolve);
+
+        // the failure only applies to the name's IPv6 addresses
+        let lookup = resolver.start_lookup("server", ResolverFamily::Ipv4, time_ms(0), &mut rng);
+        assert_eq!(lookup.status(time_ms(10)), LookupStatus::Resolve);
+        let lookup = resolver.start_lookup("server", ResolverFamily::Ipv6, time_ms(0), &mut rng);
+        assert_eq!(
+            lookup.status(time_ms(300)),
+            LookupStatus::Fail(Errno::ENOENT)
+        );
+    }
+
+    #[test]
+    fn test_default_ipv6_options() {
+        let options: ResolverOptions = serde_yaml::from_str(
+            "
+            latency:
+              type: fixed
+              latency: 10 ms
+            ",
+        )
+        .unwrap();
+        let resolver = Resolver::new(&options).unwrap();
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        assert!(!resolver.prefers_ipv6());
+
+        // IPv6 lookups have the same latency as IPv4 lookups
+        let lookup = resolver.start_lookup("server", ResolverFamily::Ipv6, time_ms(0), &mut rng);
+        assert_eq!(
+            lookup.status(time_ms(0)),
+            LookupStatus::Pending(time_ms(10))
+        );
+    }
+
+    #[test]
+    fn test_invalid_latency() {
+        let options: ResolverOptions = serde_yaml::from_str(
+            "
+            latency:
+              type: uniform
+              min: 20 ms
+              max: 10 ms
+            ",
+        )
+        .unwrap();
+        assert!(Resolver::new(&options).is_err());
+    }
+}
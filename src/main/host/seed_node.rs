@@ -0,0 +1,172 @@
+//! A per-host peer discovery service for bootstrapping peer-to-peer overlays. Any UDP datagram
+//! received on the service's port is answered with a random subset of the configured peer
+//! addresses, one "ip:port" per line.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+
+use atomic_refcell::AtomicRefCell;
+use bytes::Bytes;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::core::sim_config::SeedNode;
+use crate::host::descriptor::socket::inet::udp::UdpSocket;
+use crate::host::descriptor::{FileState, FileStatus, StateListenerFilter};
+use crate::host::network::namespace::NetworkNamespace;
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrStorage;
+
+/// The maximum number of peers in a response, chosen so that a response always fits in a single
+/// datagram.
+pub const MAX_RESPONSE_PEERS: u32 = 1000;
+
+/// Answers peer discovery requests sent to the host.
+pub struct SeedNodeService {
+    socket: Arc<AtomicRefCell<UdpSocket>>,
+    _handle: Handle<(FileState, FileState)>,
+}
+
+impl SeedNodeService {
+    /// Start the service on a new UDP socket bound to the host's default address. Peers are chosen
+    /// using a random number generator seeded from `rng`.
+    pub fn new(
+        net_ns: &NetworkNamespace,
+        config: &SeedNode,
+        buf_sizes: (usize, usize),
+        mut rng: impl rand::Rng,
+    ) -> std::io::Result<Self> {
+        let (send_buf_size, recv_buf_size) = buf_sizes;
+        let socket = UdpSocket::new(FileStatus::empty(), send_buf_size, recv_buf_size);
+
+        let addr = SocketAddrV4::new(net_ns.default_ip, config.port);
+        UdpSocket::bind(
+            &socket,
+            Some(&SockaddrStorage::from(addr)),
+            net_ns,
+            &mut rng,
+        )
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("Could not bind the seed node socket to {addr}: {e:?}"),
+            )
+        })?;
+
+        let peers: Arc<Vec<SocketAddrV4>> = Arc::new(
+            config
+                .peers
+                .iter()
+                .map(|ip| SocketAddrV4::new(*ip, config.peer_port))
+                .collect(),
+        );
+        let max_peers = config.max_peers;
+        let peer_rng = Arc::new(Mutex::new(Xoshiro256PlusPlus::seed_from_u64(rng.gen())));
+
+        let handle = {
+            let weak_socket = Arc::downgrade(&socket);
+            socket.borrow_mut().add_listener(
+                FileState::READABLE,
+                StateListenerFilter::OffToOn,
+                move |_state, _changed, cb_queue| {
+                    if let Some(socket) = weak_socket.upgrade() {
+                        let mut rng = peer_rng.lock().unwrap();
+                        Self::answer_requests(&socket, &peers, max_peers, &mut *rng, cb_queue);
+                    }
+                },
+            )
+        };
+
+        Ok(Self {
+            socket,
+            _handle: handle,
+        })
+    }
+
+    /// Answer all requests that have been received.
+    fn answer_requests(
+        socket: &Arc<AtomicRefCell<UdpSocket>>,
+        peers: &[SocketAddrV4],
+        max_peers: usize,
+        rng: &mut impl rand::Rng,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        loop {
+            let Some((_request, src)) = socket.borrow_mut().recv_internal(cb_queue) else {
+                break;
+            };
+
+            let response = format_response(&choose_peers(peers, *src.ip(), max_peers, rng));
+
+            if let Err(e) = UdpSocket::send_internal(socket, src, Bytes::from(response), cb_queue) {
+                log::debug!("Dropping seed node response to {src}: {e}");
+            }
+        }
+    }
+
+    /// Close the socket, removing it from the network namespace.
+    pub fn close(&self) {
+        CallbackQueue::queue_and_run(|cb_queue| {
+            if let Err(e) = self.socket.borrow_mut().close(cb_queue) {
+                log::warn!("Unable to close the seed node socket: {e:?}");
+            }
+        });
+    }
+}
+
+/// Choose up to `max_peers` random peers, excluding any peers with the requester's address.
+fn choose_peers(
+    peers: &[SocketAddrV4],
+    requester: Ipv4Addr,
+    max_peers: usize,
+    rng: &mut impl rand::Rng,
+) -> Vec<SocketAddrV4> {
+    let candidates: Vec<&SocketAddrV4> = peers.iter().filter(|x| *x.ip() != requester).collect();
+    candidates
+        .choose_multiple(rng, max_peers)
+        .map(|x| **x)
+        .collect()
+}
+
+fn format_response(peers: &[SocketAddrV4]) -> String {
+    peers.iter().map(|x| format!("{x}\n")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_peers() {
+        let peers: Vec<SocketAddrV4> = (1..=10)
+            .map(|x| SocketAddrV4::new(Ipv4Addr::new(11, 0, 0, x), 8333))
+            .collect();
+        let requester = Ipv4Addr::new(11, 0, 0, 3);
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        let chosen = choose_peers(&peers, requester, 4, &mut rng);
+        assert_eq!(chosen.len(), 4);
+        assert!(chosen
+            .iter()
+            .all(|x| peers.contains(x) && *x.ip() != requester));
+
+        // the same seed gives the same peers
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(1);
+        assert_eq!(choose_peers(&peers, requester, 4, &mut rng), chosen);
+
+        // never more than the number of other peers
+        let chosen = choose_peers(&peers, requester, 100, &mut rng);
+        assert_eq!(chosen.len(), 9);
+    }
+
+    #[test]
+    fn test_format_response() {
+        let peers = [
+            "11.0.0.1:8333".parse().unwrap(),
+            "11.0.0.2:18444".parse().unwrap(),
+        ];
+        assert_eq!(format_response(&peers), "11.0.0.1:8333\n11.0.0.2:18444\n");
+        assert_eq!(format_response(&[]), "");
+    }
+}
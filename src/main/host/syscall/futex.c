@@ -117,6 +117,8 @@ static SyscallReturn _syscallhandler_futexWaitHelper(SysCallHandler* sys,
                                                 ? timeoutSimTime + worker_getCurrentEmulatedTime()
                                                 : timeoutSimTime;
         syscallcondition_setTimeout(cond, timeoutEmulatedTime);
+        syscallcondition_setTimeoutSlack(
+            cond, thread_getTimerSlack(_syscallhandler_getThread(sys)));
     }
     return syscallreturn_makeBlocked(cond, true);
 }
@@ -1,6 +1,9 @@
+use std::ffi::c_int;
+use std::mem::MaybeUninit;
+
 use linux_api::errno::Errno;
 use linux_api::posix_types::kernel_pid_t;
-use linux_api::sched::CloneFlags;
+use linux_api::sched::{clone_args, CloneFlags, CLONE_ARGS_SIZE_VER0};
 use linux_api::signal::Signal;
 use log::{debug, trace, warn};
 use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
@@ -10,8 +13,10 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use syscall_logger::log_syscall;
 
 use crate::host::descriptor::descriptor_table::DescriptorTable;
+use crate::host::descriptor::{File, FileStatus};
+use crate::host::memory_manager::page_size;
 use crate::host::process::ProcessId;
-use crate::host::syscall_types::SyscallError;
+use crate::host::syscall_types::{ForeignArrayPtr, SyscallError};
 use crate::host::thread::Thread;
 
 use super::{SyscallContext, SyscallHandler};
@@ -25,6 +30,7 @@ impl SyscallHandler {
         ptid: ForeignPtr<kernel_pid_t>,
         ctid: ForeignPtr<kernel_pid_t>,
         newtls: u64,
+        pidfd: ForeignPtr<c_int>,
     ) -> Result<kernel_pid_t, SyscallError> {
         // We use this for a consistency check to validate that we've inspected
         // and emulated all of the provided flags.
@@ -79,6 +85,11 @@ impl SyscallHandler {
                 warn!("Fork with memory mapper unimplemented");
                 return Err(Errno::ENOTSUP.into());
             }
+            if !ctx.objs.thread.mthread().has_shim() {
+                // The child's syscalls would be sent to the parent's seccomp listener.
+                warn!("Fork from a statically linked executable unimplemented");
+                return Err(Errno::ENOTSUP.into());
+            }
             // Make shadow the parent process
             native_flags.insert(CloneFlags::CLONE_PARENT);
         }
@@ -177,12 +188,30 @@ impl SyscallHandler {
             handled_flags.insert(CloneFlags::CLONE_PARENT);
         }
 
+        // Handled after native clone
+        let do_pidfd = flags.contains(CloneFlags::CLONE_PIDFD);
+        if do_pidfd && flags.contains(CloneFlags::CLONE_THREAD) {
+            // From clone(2), for CLONE_PIDFD:
+            // > It is currently not possible to use this flag together with
+            // > CLONE_THREAD.
+            debug!("CLONE_PIDFD with CLONE_THREAD");
+            return Err(Errno::EINVAL.into());
+        }
+        handled_flags.insert(CloneFlags::CLONE_PIDFD);
+
         let unhandled_flags = flags.difference(handled_flags);
         if !unhandled_flags.is_empty() {
             warn!("Unhandled clone flags: {unhandled_flags:?}");
             return Err(Errno::ENOTSUP.into());
         }
 
+        // Allocate the ID before the native clone so that we don't need to clean up the native
+        // thread if we've run out. clone(2): EAGAIN Too many processes are already running.
+        let Some(child_tid) = ctx.objs.host.get_new_thread_id(ctx.objs.process.id_slot()) else {
+            warn!("Ran out of thread IDs");
+            return Err(Errno::EAGAIN.into());
+        };
+
         let child_mthread = ctx.objs.thread.mthread().native_clone(
             ctx.objs,
             native_flags,
@@ -192,7 +221,6 @@ impl SyscallHandler {
             native_newtls,
         )?;
 
-        let child_tid = ctx.objs.host.get_new_thread_id();
         let child_pid = if flags.contains(CloneFlags::CLONE_THREAD) {
             ctx.objs.process.id()
         } else {
@@ -205,6 +233,8 @@ impl SyscallHandler {
             desc_table,
             child_pid,
             child_tid,
+            // The child inherits the parent's timer slack.
+            ctx.objs.thread.timer_slack(),
         )?;
 
         let childrc = RootedRc::new(
@@ -240,6 +270,14 @@ impl SyscallHandler {
                 .add_and_schedule_forked_process(ctx.objs.host, process);
         }
 
+        if do_pidfd {
+            // The pidfd is added to the parent's descriptor table after the
+            // child's table was copied, so the child doesn't get a copy of it.
+            let file = File::PidFd(child_process.new_pidfd(FileStatus::empty()));
+            let fd = Self::register_pidfd(ctx, file)?;
+            ctx.objs.process.memory_borrow_mut().write(pidfd, &fd)?;
+        }
+
         if do_parent_settid {
             ctx.objs
                 .process
@@ -293,6 +331,15 @@ impl SyscallHandler {
             return Err(Errno::EINVAL.into());
         };
 
+        // Unlike clone3, clone writes the pidfd to `ptid`, so CLONE_PIDFD can't
+        // be used together with CLONE_PARENT_SETTID.
+        if flags.contains(CloneFlags::CLONE_PIDFD)
+            && flags.contains(CloneFlags::CLONE_PARENT_SETTID)
+        {
+            debug!("CLONE_PIDFD with CLONE_PARENT_SETTID");
+            return Err(Errno::EINVAL.into());
+        }
+
         let exit_signal = if raw_exit_signal == 0 {
             None
         } else {
@@ -303,7 +350,16 @@ impl SyscallHandler {
             Some(exit_signal)
         };
 
-        Self::clone_internal(ctx, flags, exit_signal, child_stack, ptid, ctid, newtls)
+        Self::clone_internal(
+            ctx,
+            flags,
+            exit_signal,
+            child_stack,
+            ptid,
+            ctid,
+            newtls,
+            ptid.cast::<c_int>(),
+        )
     }
 
     #[log_syscall(
@@ -312,16 +368,15 @@ impl SyscallHandler {
         /* args_size*/usize)]
     pub fn clone3(
         ctx: &mut SyscallContext,
-        args: ForeignPtr<linux_api::sched::clone_args>,
+        args: ForeignPtr<clone_args>,
         args_size: usize,
     ) -> Result<kernel_pid_t, SyscallError> {
-        if args_size != std::mem::size_of::<linux_api::sched::clone_args>() {
-            // TODO: allow smaller size, and be careful to only read
-            // as much as the caller specified, and zero-fill the rest.
-            return Err(Errno::EINVAL.into());
-        }
-        let args = ctx.objs.process.memory_borrow().read(args)?;
+        let args = Self::read_clone_args(ctx, args, args_size)?;
         trace!("clone3 args: {args:?}");
+        if args.set_tid != 0 || args.set_tid_size != 0 || args.cgroup != 0 {
+            warn!("clone3 set_tid and cgroup are unsupported");
+            return Err(Errno::ENOTSUP.into());
+        }
         let Some(flags) = CloneFlags::from_bits(args.flags) else {
             debug!("Couldn't parse clone flags: {:x}", args.flags);
             return Err(Errno::EINVAL.into());
@@ -343,9 +398,50 @@ impl SyscallHandler {
             ForeignPtr::<kernel_pid_t>::from_raw_ptr(args.parent_tid as *mut kernel_pid_t),
             ForeignPtr::<kernel_pid_t>::from_raw_ptr(args.child_tid as *mut kernel_pid_t),
             args.tls,
+            ForeignPtr::<c_int>::from_raw_ptr(args.pidfd as *mut c_int),
         )
     }
 
+    /// Reads the `clone3` args, which may be from an older or newer version of the struct than
+    /// ours. Fields missing from an older version are zeroed, and fields from a newer version
+    /// must be zero since we don't know how to handle them.
+    fn read_clone_args(
+        ctx: &SyscallContext,
+        ptr: ForeignPtr<clone_args>,
+        size: usize,
+    ) -> Result<clone_args, Errno> {
+        if size < CLONE_ARGS_SIZE_VER0 {
+            return Err(Errno::EINVAL);
+        }
+        // The kernel won't read args larger than a page.
+        if size > page_size() {
+            return Err(Errno::E2BIG);
+        }
+
+        let mem = ctx.objs.process.memory_borrow();
+        let known_size = std::cmp::min(size, std::mem::size_of::<clone_args>());
+
+        let mut args = shadow_pod::zeroed::<clone_args>();
+        // SAFETY: We only write initialized bytes into the slice.
+        let args_bytes = unsafe { shadow_pod::as_u8_slice_mut(&mut args) };
+        mem.copy_from_ptr(
+            &mut args_bytes[..known_size],
+            ForeignArrayPtr::new(ptr.cast::<MaybeUninit<u8>>(), known_size),
+        )?;
+
+        if size > known_size {
+            let mut extra = vec![0u8; size - known_size];
+            let extra_ptr = ptr.cast::<u8>().add(known_size);
+            mem.copy_from_ptr(&mut extra, ForeignArrayPtr::new(extra_ptr, extra.len()))?;
+            if extra.iter().any(|x| *x != 0) {
+                debug!("clone3 args has unknown non-zero fields");
+                return Err(Errno::E2BIG);
+            }
+        }
+
+        Ok(args)
+    }
+
     #[log_syscall(/* rv */kernel_pid_t)]
     pub fn fork(ctx: &mut SyscallContext) -> Result<kernel_pid_t, SyscallError> {
         // This should be the correct call to `clone_internal`, but `clone_internal`
@@ -358,6 +454,7 @@ impl SyscallHandler {
             ForeignPtr::<kernel_pid_t>::null(),
             ForeignPtr::<kernel_pid_t>::null(),
             0,
+            ForeignPtr::<c_int>::null(),
         )
     }
 
@@ -373,6 +470,7 @@ impl SyscallHandler {
             ForeignPtr::<kernel_pid_t>::null(),
             ForeignPtr::<kernel_pid_t>::null(),
             0,
+            ForeignPtr::<c_int>::null(),
         )
     }
 
@@ -0,0 +1,403 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use linux_api::errno::Errno;
+use nix::unistd::FchownatFlags;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+use syscall_logger::log_syscall;
+
+use crate::cshadow as c;
+use crate::host::credentials::NGROUPS_MAX;
+use crate::host::descriptor::descriptor_table::DescriptorTable;
+use crate::host::descriptor::CompatFile;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::type_formatting::SyscallStringArg;
+use crate::host::syscall_types::{ForeignArrayPtr, SyscallError, SyscallResult};
+
+/// An ID argument of -1 leaves the ID unchanged.
+fn optional_id(id: u32) -> Option<u32> {
+    (id != u32::MAX).then_some(id)
+}
+
+impl SyscallHandler {
+    #[log_syscall(/* rv */ libc::uid_t)]
+    pub fn getuid(ctx: &mut SyscallContext) -> Result<libc::uid_t, SyscallError> {
+        Ok(ctx.objs.process.credentials().ruid)
+    }
+
+    #[log_syscall(/* rv */ libc::uid_t)]
+    pub fn geteuid(ctx: &mut SyscallContext) -> Result<libc::uid_t, SyscallError> {
+        Ok(ctx.objs.process.credentials().euid)
+    }
+
+    #[log_syscall(/* rv */ libc::gid_t)]
+    pub fn getgid(ctx: &mut SyscallContext) -> Result<libc::gid_t, SyscallError> {
+        Ok(ctx.objs.process.credentials().rgid)
+    }
+
+    #[log_syscall(/* rv */ libc::gid_t)]
+    pub fn getegid(ctx: &mut SyscallContext) -> Result<libc::gid_t, SyscallError> {
+        Ok(ctx.objs.process.credentials().egid)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* ruid */ *const libc::uid_t,
+                  /* euid */ *const libc::uid_t, /* suid */ *const libc::uid_t)]
+    pub fn getresuid(
+        ctx: &mut SyscallContext,
+        ruid_ptr: ForeignPtr<libc::uid_t>,
+        euid_ptr: ForeignPtr<libc::uid_t>,
+        suid_ptr: ForeignPtr<libc::uid_t>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let creds = ctx.objs.process.credentials();
+        let mut mem = ctx.objs.process.memory_borrow_mut();
+        mem.write(ruid_ptr, &creds.ruid)?;
+        mem.write(euid_ptr, &creds.euid)?;
+        mem.write(suid_ptr, &creds.suid)?;
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* rgid */ *const libc::gid_t,
+                  /* egid */ *const libc::gid_t, /* sgid */ *const libc::gid_t)]
+    pub fn getresgid(
+        ctx: &mut SyscallContext,
+        rgid_ptr: ForeignPtr<libc::gid_t>,
+        egid_ptr: ForeignPtr<libc::gid_t>,
+        sgid_ptr: ForeignPtr<libc::gid_t>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let creds = ctx.objs.process.credentials();
+        let mut mem = ctx.objs.process.memory_borrow_mut();
+        mem.write(rgid_ptr, &creds.rgid)?;
+        mem.write(egid_ptr, &creds.egid)?;
+        mem.write(sgid_ptr, &creds.sgid)?;
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* size */ std::ffi::c_int,
+                  /* list */ *const std::ffi::c_void)]
+    pub fn getgroups(
+        ctx: &mut SyscallContext,
+        size: std::ffi::c_int,
+        list_ptr: ForeignPtr<libc::gid_t>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let groups = ctx.objs.process.credentials().groups;
+        let num_groups = std::ffi::c_int::try_from(groups.len()).unwrap();
+
+        // only return the number of groups
+        if size == 0 {
+            return Ok(num_groups);
+        }
+
+        if size < num_groups {
+            return Err(Errno::EINVAL.into());
+        }
+
+        if !groups.is_empty() {
+            ctx.objs
+                .process
+                .memory_borrow_mut()
+                .copy_to_ptr(ForeignArrayPtr::new(list_ptr, groups.len()), &groups)?;
+        }
+        Ok(num_groups)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* size */ std::ffi::c_int,
+                  /* list */ *const std::ffi::c_void)]
+    pub fn setgroups(
+        ctx: &mut SyscallContext,
+        size: std::ffi::c_int,
+        list_ptr: ForeignPtr<libc::gid_t>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let size = usize::try_from(size).or(Err(Errno::EINVAL))?;
+        if size > NGROUPS_MAX {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // the list may be NULL if there are no groups
+        let mut groups = vec![0; size];
+        if size > 0 {
+            ctx.objs
+                .process
+                .memory_borrow()
+                .copy_from_ptr(&mut groups, ForeignArrayPtr::new(list_ptr, size))?;
+        }
+
+        let mut creds = ctx.objs.process.credentials();
+        creds.setgroups(groups)?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* uid */ libc::uid_t)]
+    pub fn setuid(
+        ctx: &mut SyscallContext,
+        uid: libc::uid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        creds.setuid(uid)?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* gid */ libc::gid_t)]
+    pub fn setgid(
+        ctx: &mut SyscallContext,
+        gid: libc::gid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        creds.setgid(gid)?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* ruid */ libc::uid_t, /* euid */ libc::uid_t)]
+    pub fn setreuid(
+        ctx: &mut SyscallContext,
+        ruid: libc::uid_t,
+        euid: libc::uid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        creds.setreuid(optional_id(ruid), optional_id(euid))?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* rgid */ libc::gid_t, /* egid */ libc::gid_t)]
+    pub fn setregid(
+        ctx: &mut SyscallContext,
+        rgid: libc::gid_t,
+        egid: libc::gid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        creds.setregid(optional_id(rgid), optional_id(egid))?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* ruid */ libc::uid_t, /* euid */ libc::uid_t,
+                  /* suid */ libc::uid_t)]
+    pub fn setresuid(
+        ctx: &mut SyscallContext,
+        ruid: libc::uid_t,
+        euid: libc::uid_t,
+        suid: libc::uid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        creds.setresuid(optional_id(ruid), optional_id(euid), optional_id(suid))?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* rgid */ libc::gid_t, /* egid */ libc::gid_t,
+                  /* sgid */ libc::gid_t)]
+    pub fn setresgid(
+        ctx: &mut SyscallContext,
+        rgid: libc::gid_t,
+        egid: libc::gid_t,
+        sgid: libc::gid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        creds.setresgid(optional_id(rgid), optional_id(egid), optional_id(sgid))?;
+        ctx.objs.process.set_credentials(creds);
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ libc::uid_t, /* fsuid */ libc::uid_t)]
+    pub fn setfsuid(
+        ctx: &mut SyscallContext,
+        fsuid: libc::uid_t,
+    ) -> Result<libc::uid_t, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        let old_fsuid = creds.setfsuid(fsuid);
+        ctx.objs.process.set_credentials(creds);
+        Ok(old_fsuid)
+    }
+
+    #[log_syscall(/* rv */ libc::gid_t, /* fsgid */ libc::gid_t)]
+    pub fn setfsgid(
+        ctx: &mut SyscallContext,
+        fsgid: libc::gid_t,
+    ) -> Result<libc::gid_t, SyscallError> {
+        let mut creds = ctx.objs.process.credentials();
+        let old_fsgid = creds.setfsgid(fsgid);
+        ctx.objs.process.set_credentials(creds);
+        Ok(old_fsgid)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* owner */ libc::uid_t, /* group */ libc::gid_t)]
+    pub fn chown(
+        ctx: &mut SyscallContext,
+        path_ptr: ForeignPtr<std::ffi::c_char>,
+        owner: libc::uid_t,
+        group: libc::gid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        Self::chown_helper(ctx, path_ptr, owner, group, FchownatFlags::FollowSymlink)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* owner */ libc::uid_t, /* group */ libc::gid_t)]
+    pub fn lchown(
+        ctx: &mut SyscallContext,
+        path_ptr: ForeignPtr<std::ffi::c_char>,
+        owner: libc::uid_t,
+        group: libc::gid_t,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        Self::chown_helper(ctx, path_ptr, owner, group, FchownatFlags::NoFollowSymlink)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* fd */ std::ffi::c_int, /* owner */ libc::uid_t,
+                  /* group */ libc::gid_t)]
+    pub fn fchown(
+        ctx: &mut SyscallContext,
+        fd: std::ffi::c_int,
+        owner: libc::uid_t,
+        group: libc::gid_t,
+    ) -> SyscallResult {
+        // invalid descriptors are reported by the legacy handler
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+        if let Some(file) = Self::legacy_regular_file(&desc_table, fd) {
+            let mut stat = unsafe { std::mem::zeroed() };
+            let rv = unsafe { c::regularfile_fstat(file, &mut stat) };
+            if rv < 0 {
+                return Err(Errno::try_from(-rv).unwrap().into());
+            }
+            Self::check_chown(ctx, stat.st_uid, stat.st_gid, owner, group)?;
+        }
+        drop(desc_table);
+
+        let rv = Self::legacy_syscall(c::syscallhandler_fchown, ctx);
+        Self::permitted_chown_result(rv)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* dirfd */ std::ffi::c_int,
+                  /* pathname */ SyscallStringArg, /* owner */ libc::uid_t,
+                  /* group */ libc::gid_t, /* flags */ std::ffi::c_int)]
+    pub fn fchownat(
+        ctx: &mut SyscallContext,
+        dirfd: std::ffi::c_int,
+        path_ptr: ForeignPtr<std::ffi::c_char>,
+        owner: libc::uid_t,
+        group: libc::gid_t,
+        flags: std::ffi::c_int,
+    ) -> SyscallResult {
+        // invalid arguments are reported by the legacy handler
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+        let dir = if dirfd == libc::AT_FDCWD {
+            Some(std::ptr::null_mut())
+        } else {
+            Self::legacy_regular_file(&desc_table, dirfd)
+        };
+        let mut path_buf = [0u8; linux_api::limits::PATH_MAX];
+        let path_buf_capacity = path_buf.len();
+        let path = ctx.objs.process.memory_borrow().copy_str_from_ptr(
+            &mut path_buf,
+            ForeignArrayPtr::new(path_ptr.cast::<u8>(), path_buf_capacity),
+        );
+        if let (Some(dir), Ok(path)) = (dir, path) {
+            let cwd = ctx.objs.process.current_working_dir();
+            let mut stat = unsafe { std::mem::zeroed() };
+            let rv = unsafe {
+                c::regularfile_fstatat(dir, path.as_ptr(), &mut stat, flags, cwd.as_ptr())
+            };
+            if rv < 0 {
+                return Err(Errno::try_from(-rv).unwrap().into());
+            }
+            Self::check_chown(ctx, stat.st_uid, stat.st_gid, owner, group)?;
+        }
+        drop(desc_table);
+
+        let rv = Self::legacy_syscall(c::syscallhandler_fchownat, ctx);
+        Self::permitted_chown_result(rv)
+    }
+
+    fn chown_helper(
+        ctx: &mut SyscallContext,
+        path_ptr: ForeignPtr<std::ffi::c_char>,
+        owner: libc::uid_t,
+        group: libc::gid_t,
+        flag: FchownatFlags,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut path_buf = [0u8; linux_api::limits::PATH_MAX];
+        let path_buf_capacity = path_buf.len();
+        let path = ctx.objs.process.memory_borrow().copy_str_from_ptr(
+            &mut path_buf,
+            ForeignArrayPtr::new(path_ptr.cast::<u8>(), path_buf_capacity),
+        )?;
+        let path = Path::new(OsStr::from_bytes(path.to_bytes()));
+
+        // relative paths are relative to the managed process's working directory, not Shadow's,
+        // and absolute paths are resolved in the managed process's root directory
+        let path: PathBuf = if path.is_relative() {
+            let cwd = ctx.objs.process.current_working_dir();
+            Path::new(OsStr::from_bytes(cwd.to_bytes())).join(path)
+        } else if let Some(root) = ctx.objs.process.root_dir().as_ref() {
+            let path = path.strip_prefix("/").unwrap();
+            Path::new(OsStr::from_bytes(root.to_bytes())).join(path)
+        } else {
+            path.to_path_buf()
+        };
+
+        let stat = match flag {
+            FchownatFlags::FollowSymlink => nix::sys::stat::stat(&path),
+            FchownatFlags::NoFollowSymlink => nix::sys::stat::lstat(&path),
+        }
+        .map_err(|e| Errno::try_from(e as i32).unwrap())?;
+        Self::check_chown(ctx, stat.st_uid, stat.st_gid, owner, group)?;
+
+        let rv = nix::unistd::fchownat(
+            None,
+            &path,
+            optional_id(owner).map(nix::unistd::Uid::from_raw),
+            optional_id(group).map(nix::unistd::Gid::from_raw),
+            flag,
+        )
+        .map(|()| 0.into())
+        .map_err(|e| Errno::try_from(e as i32).unwrap().into());
+
+        Self::permitted_chown_result(rv).map(|_| 0)
+    }
+
+    /// Applies the permission checks of chown(2) using the emulated credentials of the process.
+    /// Shadow doesn't emulate file ownership, so the owner of a file is its native owner.
+    fn check_chown(
+        ctx: &SyscallContext,
+        file_uid: libc::uid_t,
+        file_gid: libc::gid_t,
+        owner: libc::uid_t,
+        group: libc::gid_t,
+    ) -> Result<(), Errno> {
+        ctx.objs.process.credentials().chown(
+            file_uid,
+            file_gid,
+            optional_id(owner),
+            optional_id(group),
+        )
+    }
+
+    /// The legacy regular file for `fd`, or `None` if `fd` isn't a legacy regular file.
+    fn legacy_regular_file(
+        desc_table: &DescriptorTable,
+        fd: std::ffi::c_int,
+    ) -> Option<*mut c::RegularFile> {
+        let CompatFile::Legacy(file) = Self::get_descriptor(desc_table, fd).ok()?.file() else {
+            return None;
+        };
+        let file_type = unsafe { c::legacyfile_getType(file.ptr()) };
+        (file_type == c::_LegacyFileType_DT_FILE).then_some(file.ptr() as *mut c::RegularFile)
+    }
+
+    /// Shadow usually runs unprivileged, so the native system refuses ownership changes that the
+    /// emulated permission checks allow, such as those requested by privileged emulated
+    /// processes. Report these changes as successful even though they aren't applied, so that
+    /// servers that change the ownership of their files before dropping privileges can start.
+    fn permitted_chown_result(rv: SyscallResult) -> SyscallResult {
+        match rv {
+            Err(SyscallError::Failed(failed)) if failed.errno == Errno::EPERM => {
+                log::debug!("Ignoring native EPERM from a permitted chown");
+                Ok(0.into())
+            }
+            rv => rv,
+        }
+    }
+}
@@ -386,8 +386,10 @@ impl SyscallHandler {
         );
 
         // Set timeout, if provided.
-        if abs_timeout_opt.is_some() {
-            rv.blocked_condition().unwrap().set_timeout(abs_timeout_opt);
+        if let Some(timeout) = timeout {
+            let cond = rv.blocked_condition().unwrap();
+            cond.set_timeout(abs_timeout_opt);
+            cond.set_timeout_slack(ctx.objs.thread.poll_timer_slack(timeout));
         }
 
         Err(rv)
@@ -1,13 +1,20 @@
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
 use linux_api::errno::Errno;
 use linux_api::fcntl::{DescriptorFlags, FcntlCommand, OFlag};
+use linux_api::posix_types::kernel_loff_t;
 use log::debug;
-use shadow_shim_helper_rs::syscall_types::SysCallReg;
+use shadow_shim_helper_rs::syscall_types::{ForeignPtr, SysCallReg};
 use syscall_logger::log_syscall;
 
 use crate::cshadow;
-use crate::host::descriptor::{CompatFile, File, FileStatus};
+use crate::host::descriptor::pipe::{Pipe, PIPE_MAX_SIZE};
+use crate::host::descriptor::{CompatFile, File, FileState, FileStatus};
+use crate::host::memory_manager::page_size;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
-use crate::host::syscall_types::SyscallResult;
+use crate::host::syscall_types::{SyscallError, SyscallResult};
+use crate::utility::callback_queue::CallbackQueue;
 
 impl SyscallHandler {
     #[log_syscall(/* rv */ std::ffi::c_int, /* fd */ std::ffi::c_int, /* cmd */ std::ffi::c_int)]
@@ -169,8 +176,43 @@ impl SyscallHandler {
                 if let File::Pipe(pipe) = file.inner_file() {
                     SysCallReg::from(i32::try_from(pipe.borrow().max_size()).unwrap())
                 } else {
+                    return Err(Errno::EBADF.into());
+                }
+            }
+            FcntlCommand::F_SETPIPE_SZ => {
+                let file = match desc.file() {
+                    CompatFile::New(d) => d,
+                    // if it's a legacy file, use the C syscall handler instead
+                    CompatFile::Legacy(_) => {
+                        drop(desc_table);
+                        return legacy_syscall_fn(ctx);
+                    }
+                };
+
+                let File::Pipe(pipe) = file.inner_file() else {
+                    return Err(Errno::EBADF.into());
+                };
+
+                // like linux, round up to a power-of-two number of pages
+                let size = usize::try_from(arg).unwrap_or(usize::MAX);
+                if size > 1 << 31 {
                     return Err(Errno::EINVAL.into());
                 }
+                let num_pages = size.div_ceil(page_size()).max(1).next_power_of_two();
+                let size = num_pages * page_size();
+
+                // only privileged processes can grow a pipe beyond the system limit
+                if size > pipe.borrow().max_size()
+                    && size > PIPE_MAX_SIZE
+                    && !ctx.objs.process.credentials().is_privileged()
+                {
+                    return Err(Errno::EPERM.into());
+                }
+
+                CallbackQueue::queue_and_run(|cb_queue| {
+                    pipe.borrow_mut().set_max_size(size, cb_queue)
+                })?;
+                SysCallReg::from(i32::try_from(size).unwrap())
             }
             cmd => {
                 warn_once_then_debug!("(LOG_ONCE) Unhandled fcntl command: {cmd:?}");
@@ -178,4 +220,131 @@ impl SyscallHandler {
             }
         })
     }
+
+    #[log_syscall(/* rv */ isize, /* fd_in */ std::ffi::c_int, /* off_in */ *const kernel_loff_t,
+                  /* fd_out */ std::ffi::c_int, /* off_out */ *const kernel_loff_t,
+                  /* len */ usize, /* flags */ std::ffi::c_uint)]
+    pub fn splice(
+        ctx: &mut SyscallContext,
+        fd_in: std::ffi::c_int,
+        off_in: ForeignPtr<kernel_loff_t>,
+        fd_out: std::ffi::c_int,
+        off_out: ForeignPtr<kernel_loff_t>,
+        len: usize,
+        flags: std::ffi::c_uint,
+    ) -> Result<isize, SyscallError> {
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let (src, dst) = Self::get_transfer_pipes(ctx, fd_in, fd_out, flags)?;
+
+        // pipes don't have offsets
+        if !off_in.is_null() || !off_out.is_null() {
+            return Err(Errno::ESPIPE.into());
+        }
+
+        Self::pipe_transfer(&src, &dst, flags, |src, dst, cb_queue| {
+            src.splice_to(dst, len, cb_queue)
+        })
+    }
+
+    #[log_syscall(/* rv */ isize, /* fd_in */ std::ffi::c_int, /* fd_out */ std::ffi::c_int,
+                  /* len */ usize, /* flags */ std::ffi::c_uint)]
+    pub fn tee(
+        ctx: &mut SyscallContext,
+        fd_in: std::ffi::c_int,
+        fd_out: std::ffi::c_int,
+        len: usize,
+        flags: std::ffi::c_uint,
+    ) -> Result<isize, SyscallError> {
+        let (src, dst) = Self::get_transfer_pipes(ctx, fd_in, fd_out, flags)?;
+
+        if len == 0 {
+            return Ok(0);
+        }
+
+        Self::pipe_transfer(&src, &dst, flags, |src, dst, cb_queue| {
+            src.tee_to(dst, len, cb_queue)
+        })
+    }
+
+    /// Get the pipes for a splice(2) or tee(2) from `fd_in` to `fd_out`. We only support
+    /// transferring data between two pipes, so any other type of file is `EINVAL`.
+    fn get_transfer_pipes(
+        ctx: &SyscallContext,
+        fd_in: std::ffi::c_int,
+        fd_out: std::ffi::c_int,
+        flags: std::ffi::c_uint,
+    ) -> Result<(Arc<AtomicRefCell<Pipe>>, Arc<AtomicRefCell<Pipe>>), SyscallError> {
+        let valid_flags = libc::SPLICE_F_MOVE
+            | libc::SPLICE_F_NONBLOCK
+            | libc::SPLICE_F_MORE
+            | libc::SPLICE_F_GIFT;
+        if flags & !valid_flags != 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+
+        let get_pipe = |fd: std::ffi::c_int| -> Result<_, SyscallError> {
+            match Self::get_descriptor(&desc_table, fd)?.file() {
+                CompatFile::New(file) => match file.inner_file() {
+                    File::Pipe(pipe) => Ok(Some(Arc::clone(pipe))),
+                    _ => Ok(None),
+                },
+                CompatFile::Legacy(_) => Ok(None),
+            }
+        };
+
+        let (Some(src), Some(dst)) = (get_pipe(fd_in)?, get_pipe(fd_out)?) else {
+            warn_once_then_debug!("(LOG_ONCE) splice() and tee() are only supported between pipes");
+            return Err(Errno::EINVAL.into());
+        };
+
+        // the same end of a pipe can't be both read and written
+        if Arc::ptr_eq(&src, &dst) {
+            return Err(Errno::EINVAL.into());
+        }
+
+        Ok((src, dst))
+    }
+
+    /// Run `transfer` to move data between the pipes `src` and `dst`. If the transfer would block
+    /// and neither the pipes nor `flags` are non-blocking, blocks until `src` has data if it's
+    /// empty, or otherwise until `dst` has space.
+    fn pipe_transfer<F>(
+        src: &Arc<AtomicRefCell<Pipe>>,
+        dst: &Arc<AtomicRefCell<Pipe>>,
+        flags: std::ffi::c_uint,
+        transfer: F,
+    ) -> Result<isize, SyscallError>
+    where
+        F: FnOnce(&mut Pipe, &mut Pipe, &mut CallbackQueue) -> Result<usize, SyscallError>,
+    {
+        let result = crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+            CallbackQueue::queue_and_run(|cb_queue| {
+                transfer(&mut src.borrow_mut(), &mut dst.borrow_mut(), cb_queue)
+            })
+        });
+
+        let nonblocking = flags & libc::SPLICE_F_NONBLOCK != 0
+            || src.borrow().status().contains(FileStatus::NONBLOCK)
+            || dst.borrow().status().contains(FileStatus::NONBLOCK);
+
+        if result == Err(Errno::EWOULDBLOCK.into()) && !nonblocking {
+            let (pipe, state) = if src.borrow().state().contains(FileState::READABLE) {
+                (dst, FileState::WRITABLE)
+            } else {
+                (src, FileState::READABLE)
+            };
+            return Err(SyscallError::new_blocked_on_file(
+                File::Pipe(Arc::clone(pipe)),
+                state,
+                pipe.borrow().supports_sa_restart(),
+            ));
+        }
+
+        Ok(result?.try_into().unwrap())
+    }
 }
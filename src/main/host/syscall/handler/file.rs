@@ -1,21 +1,24 @@
 use linux_api::posix_types::kernel_mode_t;
-use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+use shadow_shim_helper_rs::syscall_types::{ForeignPtr, SysCallArgs, SysCallReg};
 use syscall_logger::log_syscall;
 
 use crate::cshadow;
-use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall::handler::{LegacySyscallFn, SyscallContext, SyscallHandler};
 use crate::host::syscall::type_formatting::SyscallStringArg;
-use crate::host::syscall_types::SyscallResult;
+use crate::host::syscall_types::{SyscallError, SyscallResult};
 
 impl SyscallHandler {
     #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
                   /* flags */ linux_api::fcntl::OFlag, /* mode */ nix::sys::stat::Mode)]
     pub fn open(
         ctx: &mut SyscallContext,
-        _path: ForeignPtr<()>,
-        _flags: std::ffi::c_int,
+        path: ForeignPtr<()>,
+        flags: std::ffi::c_int,
         _mode: kernel_mode_t,
     ) -> SyscallResult {
+        if let Some(fd) = Self::open_tty_device(ctx, path, flags)? {
+            return Ok(fd.into());
+        }
         Self::legacy_syscall(cshadow::syscallhandler_open, ctx)
     }
 
@@ -24,10 +27,247 @@ impl SyscallHandler {
     pub fn openat(
         ctx: &mut SyscallContext,
         _dir_fd: std::ffi::c_int,
-        _path: ForeignPtr<()>,
-        _flags: std::ffi::c_int,
+        path: ForeignPtr<()>,
+        flags: std::ffi::c_int,
         _mode: kernel_mode_t,
     ) -> SyscallResult {
+        // the terminal devices have absolute paths, so the directory doesn't matter
+        if let Some(fd) = Self::open_tty_device(ctx, path, flags)? {
+            return Ok(fd.into());
+        }
         Self::legacy_syscall(cshadow::syscallhandler_openat, ctx)
     }
+
+    // The following syscalls are run natively unless the process has a root directory or emulated
+    // credentials, in which case they're emulated using the equivalent `*at` syscall so that their
+    // pathnames are resolved in the root directory and their permissions are checked against the
+    // emulated credentials.
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg, /* mode */ std::ffi::c_int)]
+    pub fn access(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        mode: std::ffi::c_int,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_faccessat,
+            libc::SYS_faccessat,
+            &[libc::AT_FDCWD.into(), path.into(), mode.into()],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* mode */ nix::sys::stat::Mode)]
+    pub fn chmod(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        mode: kernel_mode_t,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_fchmodat,
+            libc::SYS_fchmodat,
+            &[libc::AT_FDCWD.into(), path.into(), mode.into()],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* oldpath */ SyscallStringArg,
+                  /* newpath */ SyscallStringArg)]
+    pub fn link(
+        ctx: &mut SyscallContext,
+        old_path: ForeignPtr<()>,
+        new_path: ForeignPtr<()>,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_linkat,
+            libc::SYS_linkat,
+            &[
+                libc::AT_FDCWD.into(),
+                old_path.into(),
+                libc::AT_FDCWD.into(),
+                new_path.into(),
+                0.into(),
+            ],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* statbuf */ *const std::ffi::c_void)]
+    pub fn lstat(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        statbuf: ForeignPtr<()>,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_newfstatat,
+            libc::SYS_newfstatat,
+            &[
+                libc::AT_FDCWD.into(),
+                path.into(),
+                statbuf.into(),
+                libc::AT_SYMLINK_NOFOLLOW.into(),
+            ],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* mode */ nix::sys::stat::Mode)]
+    pub fn mkdir(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        mode: kernel_mode_t,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_mkdirat,
+            libc::SYS_mkdirat,
+            &[libc::AT_FDCWD.into(), path.into(), mode.into()],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* mode */ nix::sys::stat::Mode, /* dev */ libc::dev_t)]
+    pub fn mknod(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        mode: kernel_mode_t,
+        dev: libc::dev_t,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_mknodat,
+            libc::SYS_mknodat,
+            &[libc::AT_FDCWD.into(), path.into(), mode.into(), dev.into()],
+        )
+    }
+
+    #[log_syscall(/* rv */ isize, /* pathname */ SyscallStringArg, /* buf */ *const std::ffi::c_void,
+                  /* bufsiz */ usize)]
+    pub fn readlink(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        buf: ForeignPtr<()>,
+        buf_size: usize,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_readlinkat,
+            libc::SYS_readlinkat,
+            &[
+                libc::AT_FDCWD.into(),
+                path.into(),
+                buf.into(),
+                buf_size.into(),
+            ],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* oldpath */ SyscallStringArg,
+                  /* newpath */ SyscallStringArg)]
+    pub fn rename(
+        ctx: &mut SyscallContext,
+        old_path: ForeignPtr<()>,
+        new_path: ForeignPtr<()>,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_renameat,
+            libc::SYS_renameat,
+            &[
+                libc::AT_FDCWD.into(),
+                old_path.into(),
+                libc::AT_FDCWD.into(),
+                new_path.into(),
+            ],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg)]
+    pub fn rmdir(ctx: &mut SyscallContext, path: ForeignPtr<()>) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_unlinkat,
+            libc::SYS_unlinkat,
+            &[
+                libc::AT_FDCWD.into(),
+                path.into(),
+                libc::AT_REMOVEDIR.into(),
+            ],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg,
+                  /* statbuf */ *const std::ffi::c_void)]
+    pub fn stat(
+        ctx: &mut SyscallContext,
+        path: ForeignPtr<()>,
+        statbuf: ForeignPtr<()>,
+    ) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_newfstatat,
+            libc::SYS_newfstatat,
+            &[libc::AT_FDCWD.into(), path.into(), statbuf.into(), 0.into()],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* target */ SyscallStringArg,
+                  /* linkpath */ SyscallStringArg)]
+    pub fn symlink(
+        ctx: &mut SyscallContext,
+        target: ForeignPtr<()>,
+        link_path: ForeignPtr<()>,
+    ) -> SyscallResult {
+        // the target is stored in the link unchanged
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_symlinkat,
+            libc::SYS_symlinkat,
+            &[target.into(), libc::AT_FDCWD.into(), link_path.into()],
+        )
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* pathname */ SyscallStringArg)]
+    pub fn unlink(ctx: &mut SyscallContext, path: ForeignPtr<()>) -> SyscallResult {
+        Self::rooted_path_syscall(
+            ctx,
+            cshadow::syscallhandler_unlinkat,
+            libc::SYS_unlinkat,
+            &[libc::AT_FDCWD.into(), path.into(), 0.into()],
+        )
+    }
+
+    /// Run the syscall natively if the process doesn't have a root directory or emulated
+    /// credentials, and the host doesn't have a `/dev/shm` tmpfs. Otherwise run the legacy handler
+    /// `syscall` as if the process had made syscall `number` with `args`, which moves the paths
+    /// into the root directory or tmpfs and checks the emulated credentials.
+    fn rooted_path_syscall(
+        ctx: &mut SyscallContext,
+        syscall: LegacySyscallFn,
+        number: libc::c_long,
+        args: &[SysCallReg],
+    ) -> SyscallResult {
+        if ctx.objs.process.root_dir().is_none()
+            && ctx.objs.host.tmpfs_borrow().is_none()
+            && ctx.objs.process.credentials().is_native()
+        {
+            return Err(SyscallError::Native);
+        }
+
+        let mut all_args = [SysCallReg::from(0u64); 6];
+        all_args[..args.len()].copy_from_slice(args);
+        let args = SysCallArgs {
+            number,
+            args: all_args,
+        };
+
+        let mut ctx = SyscallContext {
+            objs: ctx.objs,
+            args: &args,
+        };
+        Self::legacy_syscall(syscall, &mut ctx)
+    }
 }
@@ -6,7 +6,7 @@ use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use syscall_logger::log_syscall;
 
 use crate::cshadow as c;
-use crate::host::descriptor::{CompatFile, FileStatus};
+use crate::host::descriptor::{CompatFile, File, FileStatus};
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall_types::SyscallResult;
 
@@ -69,6 +69,20 @@ impl SyscallHandler {
             file.inner_file().clone()
         };
 
+        // pseudoterminal requests that involve the calling process
+        if let File::Pty(ref pty) = file {
+            if let Some(rv) = Self::pty_ioctl(ctx, pty, request, arg_ptr) {
+                return rv;
+            }
+        }
+
+        // requests for the host's network interfaces, which can be made on any socket
+        if let File::Socket(_) = file {
+            if let Some(rv) = Self::netdevice_ioctl(ctx, request, arg_ptr) {
+                return rv;
+            }
+        }
+
         let mut file = file.borrow_mut();
 
         // all file types that shadow implements should support non-blocking operation
@@ -3,7 +3,7 @@ use syscall_logger::log_syscall;
 
 use crate::cshadow;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
-use crate::host::syscall_types::SyscallResult;
+use crate::host::syscall_types::{SyscallError, SyscallResult};
 
 impl SyscallHandler {
     #[log_syscall(/* rv */ std::ffi::c_int, /* addr */ *const std::ffi::c_void)]
@@ -33,6 +33,25 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_mmap, ctx)
     }
 
+    // <https://github.com/torvalds/linux/tree/v6.3/mm/madvise.c#L1460>
+    // ```
+    // SYSCALL_DEFINE3(madvise, unsigned long, start, size_t, len_in, int, behavior)
+    // ```
+    #[log_syscall(/* rv */ std::ffi::c_int, /* addr */ *const std::ffi::c_void, /* length */ usize,
+                  /* advice */ std::ffi::c_int)]
+    pub fn madvise(
+        ctx: &mut SyscallContext,
+        addr: ForeignPtr<u8>,
+        len: usize,
+        advice: std::ffi::c_int,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        ctx.objs
+            .process
+            .memory_borrow_mut()
+            .handle_madvise(ctx.objs, addr, len, advice)?;
+        Ok(0)
+    }
+
     // <https://github.com/torvalds/linux/tree/v6.3/mm/mremap.c#L895>
     // ```
     // SYSCALL_DEFINE5(mremap, unsigned long, addr, unsigned long, old_len,
@@ -53,6 +72,25 @@ impl SyscallHandler {
         Self::legacy_syscall(cshadow::syscallhandler_mremap, ctx)
     }
 
+    // <https://github.com/torvalds/linux/tree/v6.3/mm/msync.c#L32>
+    // ```
+    // SYSCALL_DEFINE3(msync, unsigned long, start, size_t, len, int, flags)
+    // ```
+    #[log_syscall(/* rv */ std::ffi::c_int, /* addr */ *const std::ffi::c_void, /* length */ usize,
+                  /* flags */ linux_api::mman::MsyncFlags)]
+    pub fn msync(
+        _ctx: &mut SyscallContext,
+        _addr: std::ffi::c_ulong,
+        _len: usize,
+        _flags: std::ffi::c_int,
+    ) -> SyscallResult {
+        // A file mapping is a native mapping in the plugin of the same file that Shadow reads and
+        // writes through its descriptor, so it shares the kernel's page cache with `read` and
+        // `write` syscalls and with the mappings of other processes. The MemoryManager only remaps
+        // anonymous memory into Shadow, so the kernel can flush the plugin's mappings itself.
+        Err(SyscallError::Native)
+    }
+
     // <https://github.com/torvalds/linux/tree/v6.3/mm/mmap.c#L2786>
     // ```
     // SYSCALL_DEFINE2(munmap, unsigned long, addr, size_t, len)
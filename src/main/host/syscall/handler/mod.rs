@@ -1,22 +1,35 @@
+use std::net::SocketAddrV4;
+
 use linux_api::errno::Errno;
 use shadow_shim_helper_rs::syscall_types::SysCallArgs;
 use shadow_shim_helper_rs::syscall_types::SysCallReg;
 
+use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::context::{ThreadContext, ThreadContextObjs};
 use crate::host::descriptor::descriptor_table::{DescriptorHandle, DescriptorTable};
-use crate::host::descriptor::Descriptor;
+use crate::host::descriptor::socket::Socket;
+use crate::host::descriptor::{Descriptor, FileStatus};
+use crate::host::network::ipv6;
+use crate::host::quota::{QuotaDirection, SocketFlow};
 use crate::host::syscall_types::SyscallReturn;
 use crate::host::syscall_types::{SyscallError, SyscallResult};
+use crate::utility::sockaddr::SockaddrStorage;
 
 mod clone;
+mod credentials;
 mod epoll;
 mod eventfd;
 mod fcntl;
 mod file;
 mod ioctl;
 mod mman;
+mod netdevice;
+mod pidfd;
+mod prctl;
+mod pty;
 mod random;
+mod resource;
 mod sched;
 mod socket;
 mod sysinfo;
@@ -43,9 +56,16 @@ impl SyscallHandler {
         match ctx.args.number {
             libc::SYS_accept => SyscallHandlerFn::call(Self::accept, &mut ctx),
             libc::SYS_accept4 => SyscallHandlerFn::call(Self::accept4, &mut ctx),
+            libc::SYS_access => SyscallHandlerFn::call(Self::access, &mut ctx),
+            libc::SYS_adjtimex => SyscallHandlerFn::call(Self::adjtimex, &mut ctx),
+            libc::SYS_arch_prctl => SyscallHandlerFn::call(Self::arch_prctl, &mut ctx),
             libc::SYS_bind => SyscallHandlerFn::call(Self::bind, &mut ctx),
             libc::SYS_brk => SyscallHandlerFn::call(Self::brk, &mut ctx),
+            libc::SYS_chmod => SyscallHandlerFn::call(Self::chmod, &mut ctx),
+            libc::SYS_chown => SyscallHandlerFn::call(Self::chown, &mut ctx),
+            libc::SYS_clock_adjtime => SyscallHandlerFn::call(Self::clock_adjtime, &mut ctx),
             libc::SYS_clock_getres => SyscallHandlerFn::call(Self::clock_getres, &mut ctx),
+            libc::SYS_clock_gettime => SyscallHandlerFn::call(Self::clock_gettime, &mut ctx),
             libc::SYS_clock_nanosleep => SyscallHandlerFn::call(Self::clock_nanosleep, &mut ctx),
             libc::SYS_clone => SyscallHandlerFn::call(Self::clone, &mut ctx),
             libc::SYS_clone3 => SyscallHandlerFn::call(Self::clone3, &mut ctx),
@@ -64,29 +84,53 @@ impl SyscallHandler {
             libc::SYS_eventfd2 => SyscallHandlerFn::call(Self::eventfd2, &mut ctx),
             libc::SYS_execve => SyscallHandlerFn::call(Self::execve, &mut ctx),
             libc::SYS_execveat => SyscallHandlerFn::call(Self::execveat, &mut ctx),
+            libc::SYS_fchown => SyscallHandlerFn::call(Self::fchown, &mut ctx),
+            libc::SYS_fchownat => SyscallHandlerFn::call(Self::fchownat, &mut ctx),
             libc::SYS_fcntl => SyscallHandlerFn::call(Self::fcntl, &mut ctx),
             libc::SYS_fork => SyscallHandlerFn::call(Self::fork, &mut ctx),
+            libc::SYS_getegid => SyscallHandlerFn::call(Self::getegid, &mut ctx),
+            libc::SYS_geteuid => SyscallHandlerFn::call(Self::geteuid, &mut ctx),
+            libc::SYS_getgid => SyscallHandlerFn::call(Self::getgid, &mut ctx),
+            libc::SYS_getgroups => SyscallHandlerFn::call(Self::getgroups, &mut ctx),
             libc::SYS_getitimer => SyscallHandlerFn::call(Self::getitimer, &mut ctx),
             libc::SYS_getpeername => SyscallHandlerFn::call(Self::getpeername, &mut ctx),
             libc::SYS_getpgid => SyscallHandlerFn::call(Self::getpgid, &mut ctx),
             libc::SYS_getpgrp => SyscallHandlerFn::call(Self::getpgrp, &mut ctx),
             libc::SYS_getppid => SyscallHandlerFn::call(Self::getppid, &mut ctx),
             libc::SYS_getrandom => SyscallHandlerFn::call(Self::getrandom, &mut ctx),
+            libc::SYS_getresgid => SyscallHandlerFn::call(Self::getresgid, &mut ctx),
+            libc::SYS_getresuid => SyscallHandlerFn::call(Self::getresuid, &mut ctx),
+            libc::SYS_getrusage => SyscallHandlerFn::call(Self::getrusage, &mut ctx),
             libc::SYS_getsid => SyscallHandlerFn::call(Self::getsid, &mut ctx),
             libc::SYS_getsockname => SyscallHandlerFn::call(Self::getsockname, &mut ctx),
             libc::SYS_getsockopt => SyscallHandlerFn::call(Self::getsockopt, &mut ctx),
             libc::SYS_gettid => SyscallHandlerFn::call(Self::gettid, &mut ctx),
+            libc::SYS_gettimeofday => SyscallHandlerFn::call(Self::gettimeofday, &mut ctx),
+            libc::SYS_getuid => SyscallHandlerFn::call(Self::getuid, &mut ctx),
             libc::SYS_ioctl => SyscallHandlerFn::call(Self::ioctl, &mut ctx),
+            libc::SYS_lchown => SyscallHandlerFn::call(Self::lchown, &mut ctx),
+            libc::SYS_link => SyscallHandlerFn::call(Self::link, &mut ctx),
             libc::SYS_listen => SyscallHandlerFn::call(Self::listen, &mut ctx),
+            libc::SYS_lstat => SyscallHandlerFn::call(Self::lstat, &mut ctx),
+            libc::SYS_madvise => SyscallHandlerFn::call(Self::madvise, &mut ctx),
+            libc::SYS_mkdir => SyscallHandlerFn::call(Self::mkdir, &mut ctx),
+            libc::SYS_mknod => SyscallHandlerFn::call(Self::mknod, &mut ctx),
             libc::SYS_mmap => SyscallHandlerFn::call(Self::mmap, &mut ctx),
             libc::SYS_mprotect => SyscallHandlerFn::call(Self::mprotect, &mut ctx),
             libc::SYS_mremap => SyscallHandlerFn::call(Self::mremap, &mut ctx),
+            libc::SYS_msync => SyscallHandlerFn::call(Self::msync, &mut ctx),
             libc::SYS_munmap => SyscallHandlerFn::call(Self::munmap, &mut ctx),
             libc::SYS_nanosleep => SyscallHandlerFn::call(Self::nanosleep, &mut ctx),
             libc::SYS_open => SyscallHandlerFn::call(Self::open, &mut ctx),
             libc::SYS_openat => SyscallHandlerFn::call(Self::openat, &mut ctx),
+            libc::SYS_pidfd_getfd => SyscallHandlerFn::call(Self::pidfd_getfd, &mut ctx),
+            libc::SYS_pidfd_open => SyscallHandlerFn::call(Self::pidfd_open, &mut ctx),
+            libc::SYS_pidfd_send_signal => {
+                SyscallHandlerFn::call(Self::pidfd_send_signal, &mut ctx)
+            }
             libc::SYS_pipe => SyscallHandlerFn::call(Self::pipe, &mut ctx),
             libc::SYS_pipe2 => SyscallHandlerFn::call(Self::pipe2, &mut ctx),
+            libc::SYS_prctl => SyscallHandlerFn::call(Self::prctl, &mut ctx),
             libc::SYS_pread64 => SyscallHandlerFn::call(Self::pread64, &mut ctx),
             libc::SYS_preadv => SyscallHandlerFn::call(Self::preadv, &mut ctx),
             libc::SYS_preadv2 => SyscallHandlerFn::call(Self::preadv2, &mut ctx),
@@ -95,9 +139,12 @@ impl SyscallHandler {
             libc::SYS_pwritev2 => SyscallHandlerFn::call(Self::pwritev2, &mut ctx),
             libc::SYS_rseq => SyscallHandlerFn::call(Self::rseq, &mut ctx),
             libc::SYS_read => SyscallHandlerFn::call(Self::read, &mut ctx),
+            libc::SYS_readlink => SyscallHandlerFn::call(Self::readlink, &mut ctx),
             libc::SYS_readv => SyscallHandlerFn::call(Self::readv, &mut ctx),
             libc::SYS_recvfrom => SyscallHandlerFn::call(Self::recvfrom, &mut ctx),
             libc::SYS_recvmsg => SyscallHandlerFn::call(Self::recvmsg, &mut ctx),
+            libc::SYS_rename => SyscallHandlerFn::call(Self::rename, &mut ctx),
+            libc::SYS_rmdir => SyscallHandlerFn::call(Self::rmdir, &mut ctx),
             libc::SYS_sched_getaffinity => {
                 SyscallHandlerFn::call(Self::sched_getaffinity, &mut ctx)
             }
@@ -107,17 +154,33 @@ impl SyscallHandler {
             libc::SYS_sched_yield => SyscallHandlerFn::call(Self::sched_yield, &mut ctx),
             libc::SYS_sendmsg => SyscallHandlerFn::call(Self::sendmsg, &mut ctx),
             libc::SYS_sendto => SyscallHandlerFn::call(Self::sendto, &mut ctx),
+            libc::SYS_setfsgid => SyscallHandlerFn::call(Self::setfsgid, &mut ctx),
+            libc::SYS_setfsuid => SyscallHandlerFn::call(Self::setfsuid, &mut ctx),
+            libc::SYS_setgid => SyscallHandlerFn::call(Self::setgid, &mut ctx),
+            libc::SYS_setgroups => SyscallHandlerFn::call(Self::setgroups, &mut ctx),
             libc::SYS_setitimer => SyscallHandlerFn::call(Self::setitimer, &mut ctx),
             libc::SYS_setpgid => SyscallHandlerFn::call(Self::setpgid, &mut ctx),
+            libc::SYS_setregid => SyscallHandlerFn::call(Self::setregid, &mut ctx),
+            libc::SYS_setresgid => SyscallHandlerFn::call(Self::setresgid, &mut ctx),
+            libc::SYS_setresuid => SyscallHandlerFn::call(Self::setresuid, &mut ctx),
+            libc::SYS_setreuid => SyscallHandlerFn::call(Self::setreuid, &mut ctx),
             libc::SYS_setsid => SyscallHandlerFn::call(Self::setsid, &mut ctx),
             libc::SYS_setsockopt => SyscallHandlerFn::call(Self::setsockopt, &mut ctx),
+            libc::SYS_setuid => SyscallHandlerFn::call(Self::setuid, &mut ctx),
             libc::SYS_shutdown => SyscallHandlerFn::call(Self::shutdown, &mut ctx),
             libc::SYS_socket => SyscallHandlerFn::call(Self::socket, &mut ctx),
             libc::SYS_socketpair => SyscallHandlerFn::call(Self::socketpair, &mut ctx),
+            libc::SYS_splice => SyscallHandlerFn::call(Self::splice, &mut ctx),
+            libc::SYS_stat => SyscallHandlerFn::call(Self::stat, &mut ctx),
+            libc::SYS_symlink => SyscallHandlerFn::call(Self::symlink, &mut ctx),
             libc::SYS_sysinfo => SyscallHandlerFn::call(Self::sysinfo, &mut ctx),
+            libc::SYS_tee => SyscallHandlerFn::call(Self::tee, &mut ctx),
+            libc::SYS_time => SyscallHandlerFn::call(Self::time, &mut ctx),
             libc::SYS_timerfd_create => SyscallHandlerFn::call(Self::timerfd_create, &mut ctx),
             libc::SYS_timerfd_gettime => SyscallHandlerFn::call(Self::timerfd_gettime, &mut ctx),
             libc::SYS_timerfd_settime => SyscallHandlerFn::call(Self::timerfd_settime, &mut ctx),
+            libc::SYS_uname => SyscallHandlerFn::call(Self::uname, &mut ctx),
+            libc::SYS_unlink => SyscallHandlerFn::call(Self::unlink, &mut ctx),
             libc::SYS_vfork => SyscallHandlerFn::call(Self::vfork, &mut ctx),
             libc::SYS_waitid => SyscallHandlerFn::call(Self::waitid, &mut ctx),
             libc::SYS_wait4 => SyscallHandlerFn::call(Self::wait4, &mut ctx),
@@ -163,6 +226,88 @@ impl SyscallHandler {
         }
     }
 
+    /// Internal helper that returns an error if the process has used up its bandwidth quota in the
+    /// direction `dir`, or if the socket has exceeded its own rate limit. The error blocks the
+    /// syscall until more data is allowed to be transferred, or is `EWOULDBLOCK` if the socket is
+    /// non-blocking or `msg_flags` contains `MSG_DONTWAIT`. Only inet sockets are subject to
+    /// bandwidth quotas.
+    fn check_bandwidth_quota(
+        ctx: &SyscallContext,
+        socket: &Socket,
+        dir: QuotaDirection,
+        msg_flags: std::ffi::c_int,
+    ) -> Result<(), SyscallError> {
+        let Some(quota) = ctx.objs.process.quota() else {
+            return Ok(());
+        };
+
+        let Some(flow) = Self::socket_flow(socket) else {
+            return Ok(());
+        };
+
+        let now = Worker::current_time().unwrap();
+        let Some(ready_time) = quota.borrow().bandwidth_ready_time(dir, &flow, now) else {
+            return Ok(());
+        };
+
+        if socket.borrow().status().contains(FileStatus::NONBLOCK)
+            || msg_flags & libc::MSG_DONTWAIT != 0
+        {
+            return Err(Errno::EWOULDBLOCK.into());
+        }
+
+        Err(SyscallError::new_blocked_until(
+            ready_time, /* restartable= */ true,
+        ))
+    }
+
+    /// Internal helper that charges `bytes` transferred on the socket in the direction `dir` to
+    /// the process's bandwidth quota.
+    fn charge_bandwidth_quota(
+        ctx: &SyscallContext,
+        socket: &Socket,
+        dir: QuotaDirection,
+        bytes: libc::ssize_t,
+    ) {
+        let Some(quota) = ctx.objs.process.quota() else {
+            return;
+        };
+
+        let Some(flow) = Self::socket_flow(socket) else {
+            return;
+        };
+
+        let now = Worker::current_time().unwrap();
+        quota
+            .borrow_mut()
+            .charge_bandwidth(dir, &flow, bytes.try_into().unwrap_or(0), now);
+    }
+
+    /// Internal helper that returns the local and peer addresses of an inet socket, or `None` if
+    /// it's not an inet socket.
+    fn socket_flow(socket: &Socket) -> Option<SocketFlow> {
+        let Socket::Inet(socket) = socket else {
+            return None;
+        };
+
+        let socket = socket.borrow();
+        // the addresses of `AF_INET6` sockets are translated back to the IPv4 addresses that flows
+        // are matched against
+        let to_inet = |addr: Result<Option<SockaddrStorage>, _>| {
+            let addr = addr.ok().flatten()?;
+            match (addr.as_inet(), addr.as_inet6()) {
+                (Some(x), _) => Some(SocketAddrV4::from(*x)),
+                (None, Some(x)) => ipv6::to_socket_addr_v4((*x).into()),
+                (None, None) => None,
+            }
+        };
+
+        Some(SocketFlow {
+            local: to_inet(socket.getsockname()),
+            peer: to_inet(socket.getpeername()),
+        })
+    }
+
     /// Run a legacy C syscall handler.
     fn legacy_syscall(syscall: LegacySyscallFn, ctx: &mut SyscallContext) -> SyscallResult {
         unsafe { syscall(ctx.objs.thread.csyscallhandler(), ctx.args as *const _) }.into()
@@ -0,0 +1,146 @@
+use std::net::Ipv4Addr;
+
+use linux_api::errno::Errno;
+use linux_api::ioctls::IoctlRequest;
+use linux_api::netdevice::{ifconf, ifreq, ARPHRD_ETHER, ARPHRD_LOOPBACK};
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall_types::{ForeignArrayPtr, SyscallResult};
+
+impl SyscallHandler {
+    /// Handles the ioctl requests on a socket that get information about the host's network
+    /// interfaces (see netdevice(7)). Returns `None` for other requests.
+    pub(super) fn netdevice_ioctl(
+        ctx: &mut SyscallContext,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+    ) -> Option<SyscallResult> {
+        let rv = match request {
+            IoctlRequest::SIOCGIFCONF => Self::get_interface_list(ctx, arg_ptr.cast()),
+            IoctlRequest::SIOCGIFNAME
+            | IoctlRequest::SIOCGIFINDEX
+            | IoctlRequest::SIOCGIFFLAGS
+            | IoctlRequest::SIOCGIFADDR
+            | IoctlRequest::SIOCGIFNETMASK
+            | IoctlRequest::SIOCGIFBRDADDR
+            | IoctlRequest::SIOCGIFMTU
+            | IoctlRequest::SIOCGIFHWADDR
+            | IoctlRequest::SIOCGIFTXQLEN => Self::get_interface_info(ctx, request, arg_ptr.cast()),
+            _ => return None,
+        };
+        Some(rv)
+    }
+
+    /// `SIOCGIFCONF`: list the addresses of the interfaces.
+    fn get_interface_list(ctx: &mut SyscallContext, arg_ptr: ForeignPtr<ifconf>) -> SyscallResult {
+        let net_ns = ctx.objs.host.network_namespace_borrow();
+        let mut mem = ctx.objs.process.memory_borrow_mut();
+        let mut conf = mem.read(arg_ptr)?;
+
+        // all of the interfaces have an IPv4 address, so all of them are listed
+        let reqs: Vec<ifreq> = net_ns
+            .interfaces()
+            .iter()
+            .map(|interface| {
+                let interface = interface.borrow();
+                let mut req: ifreq = shadow_pod::zeroed();
+                req.set_name(interface.name().as_bytes());
+                req.set_ifru_sockaddr(address_family(), &sockaddr_in_data(interface.addr()));
+                req
+            })
+            .collect();
+
+        let req_size = std::mem::size_of::<ifreq>();
+        let len = if conf.ifc_buf.is_null() {
+            // netdevice(7): "If ifc_req is NULL, SIOCGIFCONF returns the necessary buffer size in
+            // bytes for receiving all available addresses in ifc_len."
+            reqs.len() * req_size
+        } else {
+            // like linux, only whole entries are written
+            let max_reqs = usize::try_from(conf.ifc_len).unwrap_or(0) / req_size;
+            let reqs = &reqs[..std::cmp::min(reqs.len(), max_reqs)];
+            let buf = ForeignPtr::from_raw_ptr(conf.ifc_buf).cast::<ifreq>();
+            mem.copy_to_ptr(ForeignArrayPtr::new(buf, reqs.len()), reqs)?;
+            reqs.len() * req_size
+        };
+
+        conf.ifc_len = len.try_into().unwrap();
+        mem.write(arg_ptr, &conf)?;
+
+        Ok(0.into())
+    }
+
+    /// Get information about the interface named in the request, or for `SIOCGIFNAME` the interface
+    /// with the request's index.
+    fn get_interface_info(
+        ctx: &mut SyscallContext,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<ifreq>,
+    ) -> SyscallResult {
+        let net_ns = ctx.objs.host.network_namespace_borrow();
+        let mut mem = ctx.objs.process.memory_borrow_mut();
+        let mut req = mem.read(arg_ptr)?;
+
+        let interfaces = net_ns.interfaces();
+        let position = if request == IoctlRequest::SIOCGIFNAME {
+            usize::try_from(req.ifru_int())
+                .ok()
+                .and_then(|x| x.checked_sub(1))
+                .filter(|x| *x < interfaces.len())
+        } else {
+            interfaces
+                .iter()
+                .position(|x| x.borrow().name().as_bytes() == req.name())
+        };
+        let Some(position) = position else {
+            return Err(Errno::ENODEV.into());
+        };
+        let index = i32::try_from(position + 1).unwrap();
+        let interface = interfaces[position].borrow();
+
+        match request {
+            IoctlRequest::SIOCGIFNAME => req.set_name(interface.name().as_bytes()),
+            IoctlRequest::SIOCGIFINDEX => req.set_ifru_int(index),
+            IoctlRequest::SIOCGIFFLAGS => req.set_ifru_flags(interface.flags()),
+            IoctlRequest::SIOCGIFADDR => {
+                req.set_ifru_sockaddr(address_family(), &sockaddr_in_data(interface.addr()))
+            }
+            IoctlRequest::SIOCGIFNETMASK => {
+                req.set_ifru_sockaddr(address_family(), &sockaddr_in_data(interface.netmask()))
+            }
+            IoctlRequest::SIOCGIFBRDADDR => {
+                // the loopback interface doesn't have a broadcast address
+                let broadcast = interface.broadcast().unwrap_or(Ipv4Addr::UNSPECIFIED);
+                req.set_ifru_sockaddr(address_family(), &sockaddr_in_data(broadcast))
+            }
+            IoctlRequest::SIOCGIFMTU => req.set_ifru_int(interface.mtu().try_into().unwrap()),
+            IoctlRequest::SIOCGIFHWADDR => {
+                let hw_type = if interface.is_loopback() {
+                    ARPHRD_LOOPBACK
+                } else {
+                    ARPHRD_ETHER
+                };
+                req.set_ifru_sockaddr(hw_type, &interface.hw_addr())
+            }
+            IoctlRequest::SIOCGIFTXQLEN => {
+                req.set_ifru_int(interface.tx_queue_len().try_into().unwrap())
+            }
+            _ => unreachable!(),
+        }
+
+        mem.write(arg_ptr, &req)?;
+
+        Ok(0.into())
+    }
+}
+
+fn address_family() -> u16 {
+    libc::AF_INET.try_into().unwrap()
+}
+
+/// The `sa_data` of a `struct sockaddr_in` with the address `addr` and port 0.
+fn sockaddr_in_data(addr: Ipv4Addr) -> [u8; 6] {
+    let [a, b, c, d] = addr.octets();
+    [0, 0, a, b, c, d]
+}
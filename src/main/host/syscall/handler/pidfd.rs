@@ -0,0 +1,215 @@
+use std::ffi::{c_int, c_uint};
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+
+use linux_api::errno::Errno;
+use linux_api::fcntl::DescriptorFlags;
+use linux_api::posix_types::kernel_pid_t;
+use linux_api::signal::{siginfo_t, SigInfoCode, SigInfoCodeSi, Signal};
+use log::{debug, warn};
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+use syscall_logger::log_syscall;
+
+use crate::core::worker::Worker;
+use crate::host::descriptor::pidfd::PidFd;
+use crate::host::descriptor::{CompatFile, Descriptor, File, FileStatus, OpenFile};
+use crate::host::process::ProcessId;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall_types::SyscallError;
+use crate::host::thread::ThreadId;
+
+impl SyscallHandler {
+    #[log_syscall(/* rv */ c_int, /* pid */ kernel_pid_t, /* flags */ c_uint)]
+    pub fn pidfd_open(
+        ctx: &mut SyscallContext,
+        pid: kernel_pid_t,
+        flags: c_uint,
+    ) -> Result<c_int, SyscallError> {
+        // PIDFD_NONBLOCK is the only supported flag, and has the same value as O_NONBLOCK.
+        let Ok(flags) = i32::try_from(flags) else {
+            return Err(Errno::EINVAL.into());
+        };
+        if flags & !libc::O_NONBLOCK != 0 {
+            debug!("Invalid pidfd_open flags: {flags:#x}");
+            return Err(Errno::EINVAL.into());
+        }
+
+        let Ok(pid) = ProcessId::try_from(pid) else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        let mut file_flags = FileStatus::empty();
+        if flags & libc::O_NONBLOCK != 0 {
+            file_flags.insert(FileStatus::NONBLOCK);
+        }
+
+        let pidfd = match ctx.objs.host.process_borrow(pid) {
+            Some(process) => process.borrow(ctx.objs.host.root()).new_pidfd(file_flags),
+            // The pid refers to a thread that isn't a thread group leader.
+            None if ctx.objs.host.has_thread(ThreadId::from(pid)) => {
+                return Err(Errno::EINVAL.into())
+            }
+            None => return Err(Errno::ESRCH.into()),
+        };
+
+        Self::register_pidfd(ctx, File::PidFd(pidfd))
+    }
+
+    #[log_syscall(/* rv */ c_int, /* pidfd */ c_int, /* sig */ c_int,
+                  /* info */ *const std::ffi::c_void, /* flags */ c_uint)]
+    pub fn pidfd_send_signal(
+        ctx: &mut SyscallContext,
+        pidfd: c_int,
+        sig: c_int,
+        info_ptr: ForeignPtr<siginfo_t>,
+        flags: c_uint,
+    ) -> Result<c_int, SyscallError> {
+        // The condition only exists after a wakeup.
+        if ctx.objs.thread.syscall_condition().is_some() {
+            // We stopped ourselves with this signal, and have now been continued.
+            return Ok(0);
+        }
+
+        if flags != 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let pid = Self::get_pidfd(ctx, pidfd)?.borrow().pid();
+
+        let Some(process) = ctx.objs.host.process_borrow(pid) else {
+            debug!("Process {pid} has already been reaped");
+            return Err(Errno::ESRCH.into());
+        };
+        let process = process.borrow(ctx.objs.host.root());
+
+        if process.borrow_as_runnable().is_none() {
+            return Err(Errno::ESRCH.into());
+        }
+
+        if sig == 0 {
+            return Ok(0);
+        }
+
+        let Ok(signal) = Signal::try_from(sig) else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        if signal.is_realtime() {
+            warn!("Unimplemented signal {sig}");
+            return Err(Errno::ENOSYS.into());
+        }
+
+        let siginfo = if info_ptr.is_null() {
+            siginfo_t::new_for_kill(signal, ctx.objs.process.id().into(), 0)
+        } else {
+            let siginfo = ctx.objs.process.memory_borrow().read(info_ptr)?;
+            if siginfo.signal().ok() != Some(signal) {
+                return Err(Errno::EINVAL.into());
+            }
+
+            // Like sigqueueinfo(2), a process can only impersonate the kernel or kill(2) when
+            // signalling itself.
+            let impersonating = match siginfo.code() {
+                Ok(SigInfoCode::Si(code)) => {
+                    i32::from(code) >= 0 || code == SigInfoCodeSi::SI_TKILL
+                }
+                _ => true,
+            };
+            if impersonating && pid != ctx.objs.process.id() {
+                return Err(Errno::EPERM.into());
+            }
+
+            siginfo
+        };
+
+        process.signal(ctx.objs.host, Some(ctx.objs.thread), &siginfo);
+
+        if ctx.objs.process.is_stopped() {
+            // The thread isn't resumed while the process is stopped, so an
+            // immediate timeout is enough.
+            let now = Worker::current_time().unwrap();
+            return Err(SyscallError::new_blocked_until(now, false));
+        }
+
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ c_int, /* pidfd */ c_int, /* targetfd */ c_int, /* flags */ c_uint)]
+    pub fn pidfd_getfd(
+        ctx: &mut SyscallContext,
+        pidfd: c_int,
+        target_fd: c_int,
+        flags: c_uint,
+    ) -> Result<c_int, SyscallError> {
+        if flags != 0 {
+            return Err(Errno::EINVAL.into());
+        }
+
+        let pid = Self::get_pidfd(ctx, pidfd)?.borrow().pid();
+
+        let desc = {
+            let Some(process) = ctx.objs.host.process_borrow(pid) else {
+                return Err(Errno::ESRCH.into());
+            };
+            let process = process.borrow(ctx.objs.host.root());
+            let Some(runnable) = process.borrow_as_runnable() else {
+                return Err(Errno::ESRCH.into());
+            };
+            let Some(thread) = runnable.first_live_thread_borrow(ctx.objs.host.root()) else {
+                return Err(Errno::ESRCH.into());
+            };
+            let thread = thread.borrow(ctx.objs.host.root());
+            let desc_table = thread.descriptor_table_borrow(ctx.objs.host);
+
+            // The new descriptor always has the close-on-exec flag set.
+            Self::get_descriptor(&desc_table, target_fd)?.dup(DescriptorFlags::FD_CLOEXEC)
+        };
+
+        let fd = ctx
+            .objs
+            .thread
+            .descriptor_table_borrow_mut(ctx.objs.host)
+            .register_descriptor(desc)
+            .or(Err(Errno::ENFILE))?;
+
+        Ok(fd.val().try_into().unwrap())
+    }
+
+    /// Returns the pidfd for the descriptor `fd`, or EBADF if it isn't a pidfd.
+    pub(super) fn get_pidfd(
+        ctx: &SyscallContext,
+        fd: c_int,
+    ) -> Result<Arc<AtomicRefCell<PidFd>>, SyscallError> {
+        let desc_table = ctx.objs.thread.descriptor_table_borrow(ctx.objs.host);
+        let desc = Self::get_descriptor(&desc_table, fd)?;
+
+        let CompatFile::New(file) = desc.file() else {
+            return Err(Errno::EBADF.into());
+        };
+        let File::PidFd(pidfd) = file.inner_file() else {
+            return Err(Errno::EBADF.into());
+        };
+
+        Ok(Arc::clone(pidfd))
+    }
+
+    /// Registers a new pidfd in the current thread's descriptor table, with the close-on-exec flag
+    /// set as pidfds always are.
+    pub(super) fn register_pidfd(
+        ctx: &mut SyscallContext,
+        file: File,
+    ) -> Result<c_int, SyscallError> {
+        let mut desc = Descriptor::new(CompatFile::New(OpenFile::new(file)));
+        desc.set_flags(DescriptorFlags::FD_CLOEXEC);
+
+        let fd = ctx
+            .objs
+            .thread
+            .descriptor_table_borrow_mut(ctx.objs.host)
+            .register_descriptor(desc)
+            .or(Err(Errno::ENFILE))?;
+
+        Ok(fd.val().try_into().unwrap())
+    }
+}
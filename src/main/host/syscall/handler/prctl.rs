@@ -0,0 +1,177 @@
+use std::ffi::{c_int, c_long, c_ulong};
+
+use linux_api::errno::Errno;
+use linux_api::signal::Signal;
+use log::{trace, warn};
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+use syscall_logger::log_syscall;
+
+use crate::cshadow;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall_types::SyscallError;
+
+impl SyscallHandler {
+    #[log_syscall(/* rv */ c_long, /* option */ c_int, /* arg2 */ c_ulong, /* arg3 */ c_ulong,
+                  /* arg4 */ c_ulong, /* arg5 */ c_ulong)]
+    pub fn prctl(
+        ctx: &mut SyscallContext,
+        option: c_int,
+        arg2: c_ulong,
+        _arg3: c_ulong,
+        _arg4: c_ulong,
+        _arg5: c_ulong,
+    ) -> Result<c_long, SyscallError> {
+        match option {
+            libc::PR_CAP_AMBIENT
+            | libc::PR_SET_CHILD_SUBREAPER
+            | libc::PR_GET_CHILD_SUBREAPER
+            | libc::PR_SET_ENDIAN
+            | libc::PR_GET_ENDIAN
+            | libc::PR_SET_FP_MODE
+            | libc::PR_GET_FP_MODE
+            | libc::PR_SET_FPEMU
+            | libc::PR_GET_FPEMU
+            | libc::PR_SET_FPEXC
+            | libc::PR_GET_FPEXC
+            | libc::PR_SET_KEEPCAPS
+            | libc::PR_GET_KEEPCAPS
+            | libc::PR_MCE_KILL
+            | libc::PR_MCE_KILL_GET
+            | libc::PR_MPX_ENABLE_MANAGEMENT
+            | libc::PR_MPX_DISABLE_MANAGEMENT
+            // The name is stored in the native thread, which is the plugin's
+            // own thread.
+            | libc::PR_SET_NAME
+            | libc::PR_GET_NAME
+            | libc::PR_SET_NO_NEW_PRIVS
+            | libc::PR_GET_NO_NEW_PRIVS
+            | libc::PR_SET_MM
+            | libc::PR_SET_PTRACER
+            | libc::PR_SET_SECUREBITS
+            | libc::PR_GET_SECUREBITS
+            | libc::PR_GET_SPECULATION_CTRL
+            | libc::PR_SET_THP_DISABLE
+            | libc::PR_TASK_PERF_EVENTS_DISABLE
+            | libc::PR_TASK_PERF_EVENTS_ENABLE
+            | libc::PR_GET_THP_DISABLE
+            | libc::PR_SET_TIMING
+            | libc::PR_GET_TIMING
+            | libc::PR_GET_TSC
+            | libc::PR_GET_UNALIGN => {
+                trace!("prctl {option} executing natively");
+                Err(SyscallError::Native)
+            }
+            libc::PR_SET_SECCOMP | libc::PR_GET_SECCOMP => {
+                warn!("Not allowing seccomp prctl {option}");
+                Err(Errno::EINVAL.into())
+            }
+            // Needs emulation to have the desired effect, but also N/A on x86_64.
+            libc::PR_SET_UNALIGN
+            // Executing natively could interfere with shadow's interception of
+            // rdtsc. Needs emulation.
+            | libc::PR_SET_TSC
+            // Wouldn't actually hurt correctness, but could significantly hurt
+            // performance.
+            | libc::PR_SET_SPECULATION_CTRL => {
+                warn!("Not allowing unimplemented prctl {option}");
+                Err(Errno::EINVAL.into())
+            }
+            libc::PR_GET_TID_ADDRESS => {
+                let tid_addr = u64::from(ctx.objs.thread.get_tid_address());
+                let out_ptr = ForeignPtr::from(usize::try_from(arg2).unwrap()).cast::<u64>();
+                ctx.objs
+                    .process
+                    .memory_borrow_mut()
+                    .write(out_ptr, &tid_addr)?;
+                Ok(0)
+            }
+            libc::PR_SET_DUMPABLE => {
+                let dumpable = u32::try_from(arg2).or(Err(Errno::EINVAL))?;
+                if dumpable != cshadow::SUID_DUMP_DISABLE && dumpable != cshadow::SUID_DUMP_USER {
+                    return Err(Errno::EINVAL.into());
+                }
+                ctx.objs.process.set_dumpable(dumpable);
+                Ok(0)
+            }
+            libc::PR_GET_DUMPABLE => Ok(ctx.objs.process.dumpable().into()),
+            libc::PR_SET_TIMERSLACK => {
+                // A slack of 0 restores the thread's default slack.
+                let slack = SimulationTime::try_from_nanos(arg2).unwrap_or(SimulationTime::MAX);
+                ctx.objs.thread.set_timer_slack(slack);
+                Ok(0)
+            }
+            libc::PR_GET_TIMERSLACK => {
+                let slack = ctx.objs.thread.timer_slack().as_nanos();
+                Ok(slack.try_into().unwrap_or(c_long::MAX))
+            }
+            libc::PR_CAPBSET_READ => {
+                let credentials = ctx.objs.process.credentials();
+                Ok(credentials.capbset_read(arg2)?.into())
+            }
+            libc::PR_CAPBSET_DROP => {
+                let mut credentials = ctx.objs.process.credentials();
+                credentials.capbset_drop(arg2)?;
+                ctx.objs.process.set_credentials(credentials);
+                Ok(0)
+            }
+            // Shadow uses the native parent-death signal to ensure managed
+            // processes die when Shadow does, so we emulate the plugin's
+            // setting rather than letting it override ours.
+            libc::PR_SET_PDEATHSIG => {
+                let signal = match i32::try_from(arg2) {
+                    Ok(0) => None,
+                    Ok(signal) => Some(Signal::try_from(signal).or(Err(Errno::EINVAL))?),
+                    Err(_) => return Err(Errno::EINVAL.into()),
+                };
+                ctx.objs.process.set_parent_death_signal(signal);
+                Ok(0)
+            }
+            libc::PR_GET_PDEATHSIG => {
+                let signal = ctx
+                    .objs
+                    .process
+                    .parent_death_signal()
+                    .map(i32::from)
+                    .unwrap_or(0);
+                let out_ptr = ForeignPtr::from(usize::try_from(arg2).unwrap()).cast::<c_int>();
+                ctx.objs
+                    .process
+                    .memory_borrow_mut()
+                    .write(out_ptr, &signal)?;
+                Ok(0)
+            }
+            _ => {
+                warn!("Unknown prctl operation {option}");
+                Err(Errno::EINVAL.into())
+            }
+        }
+    }
+
+    #[log_syscall(/* rv */ c_int, /* code */ c_int, /* addr */ c_ulong)]
+    pub fn arch_prctl(
+        _ctx: &mut SyscallContext,
+        code: c_int,
+        _addr: c_ulong,
+    ) -> Result<c_int, SyscallError> {
+        match code {
+            // Faulting on cpuid could interfere with the shim.
+            libc::ARCH_SET_CPUID
+            // Mapping a new vdso would bypass shadow's patching of the vdso
+            // time functions.
+            | libc::ARCH_MAP_VDSO_X32
+            | libc::ARCH_MAP_VDSO_32
+            | libc::ARCH_MAP_VDSO_64 => {
+                warn!("Not allowing unimplemented arch_prctl {code}");
+                Err(Errno::EINVAL.into())
+            }
+            // This includes ARCH_SET_FS and ARCH_SET_GS. The shim keeps its
+            // thread-local storage consistent when the thread pointer changes,
+            // so the fs and gs bases can be managed natively.
+            _ => {
+                trace!("arch_prctl {code} executing natively");
+                Err(SyscallError::Native)
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::ffi::c_int;
+use std::sync::Arc;
+
+use atomic_refcell::AtomicRefCell;
+use linux_api::errno::Errno;
+use linux_api::fcntl::{DescriptorFlags, OFlag};
+use linux_api::ioctls::IoctlRequest;
+use linux_api::posix_types::kernel_pid_t;
+use linux_api::signal::{siginfo_t, Signal};
+use linux_api::termios::winsize;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+
+use crate::host::descriptor::pty::{Pty, PtyEnd, PtyPair};
+use crate::host::descriptor::{CompatFile, Descriptor, File, FileMode, FileStatus, OpenFile};
+use crate::host::process::ProcessId;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall_types::{ForeignArrayPtr, SyscallError, SyscallResult};
+use crate::utility::callback_queue::CallbackQueue;
+
+/// A terminal device that's emulated rather than opened from the native filesystem.
+enum TtyDevice {
+    /// `/dev/ptmx`: the master end of a new pseudoterminal.
+    NewMaster,
+    /// `/dev/pts/<n>`: the slave end of pseudoterminal `n`.
+    Slave(u32),
+    /// `/dev/tty`: the calling process's controlling terminal.
+    ControllingTerminal,
+}
+
+impl TtyDevice {
+    fn from_path(path: &[u8]) -> Option<Self> {
+        match path {
+            b"/dev/ptmx" | b"/dev/pts/ptmx" => Some(Self::NewMaster),
+            b"/dev/tty" => Some(Self::ControllingTerminal),
+            _ => {
+                let index = path.strip_prefix(b"/dev/pts/")?;
+                // only accept the canonical names, e.g. not "/dev/pts/01"
+                if !index.iter().all(u8::is_ascii_digit) || (index.len() > 1 && index[0] == b'0') {
+                    return None;
+                }
+                let index = std::str::from_utf8(index).ok()?.parse().ok()?;
+                Some(Self::Slave(index))
+            }
+        }
+    }
+}
+
+impl SyscallHandler {
+    /// Opens `path_ptr` if it names one of the emulated terminal devices: `/dev/ptmx`,
+    /// `/dev/pts/<n>`, or `/dev/tty`. Returns `None` if it doesn't, in which case the path should
+    /// be opened as usual.
+    pub(super) fn open_tty_device(
+        ctx: &mut SyscallContext,
+        path_ptr: ForeignPtr<()>,
+        flags: c_int,
+    ) -> Result<Option<c_int>, SyscallError> {
+        let mut path_buf = [0u8; linux_api::limits::PATH_MAX];
+        let path_buf_capacity = path_buf.len();
+        let Ok(path) = ctx.objs.process.memory_borrow().copy_str_from_ptr(
+            &mut path_buf,
+            ForeignArrayPtr::new(path_ptr.cast::<u8>(), path_buf_capacity),
+        ) else {
+            // let the regular open handler report the error
+            return Ok(None);
+        };
+
+        let Some(device) = TtyDevice::from_path(path.to_bytes()) else {
+            return Ok(None);
+        };
+
+        let flags = OFlag::from_bits_truncate(flags);
+        let pair = match device {
+            TtyDevice::NewMaster => None,
+            TtyDevice::Slave(index) => {
+                let Some(pair) = ctx.objs.host.pty_table_borrow().get(index) else {
+                    return Err(Errno::ENOENT.into());
+                };
+                Some(pair)
+            }
+            TtyDevice::ControllingTerminal => {
+                let session = ctx.objs.process.session_id();
+                let Some(pair) = ctx
+                    .objs
+                    .host
+                    .pty_table_borrow()
+                    .controlling_terminal(session)
+                else {
+                    return Err(Errno::ENXIO.into());
+                };
+                Some(pair)
+            }
+        };
+
+        let fd = match pair {
+            Some(pair) => Self::open_pty_slave(ctx, pair, flags)?,
+            None => Self::open_pty_master(ctx, flags)?,
+        };
+
+        Ok(Some(fd))
+    }
+
+    /// Handles the ioctl requests on a pseudoterminal that involve the calling process or its
+    /// descriptor table. Returns `None` for requests that only involve the pseudoterminal, which
+    /// are handled by [`Pty::ioctl`].
+    pub(super) fn pty_ioctl(
+        ctx: &mut SyscallContext,
+        pty: &Arc<AtomicRefCell<Pty>>,
+        request: IoctlRequest,
+        arg_ptr: ForeignPtr<()>,
+    ) -> Option<SyscallResult> {
+        let (pair, end) = {
+            let pty = pty.borrow();
+            (Arc::clone(pty.pair()), pty.end())
+        };
+        let session = ctx.objs.process.session_id();
+        let is_controlling_terminal = pair.borrow().session() == Some(session);
+
+        let rv = match request {
+            IoctlRequest::TIOCGPTPEER if end == PtyEnd::Master => {
+                // the argument is the flags to open the slave with
+                let flags = c_int::try_from(usize::from(arg_ptr)).unwrap_or(-1);
+                let Some(flags) = OFlag::from_bits(flags) else {
+                    return Some(Err(Errno::EINVAL.into()));
+                };
+                Self::open_pty_slave(ctx, pair, flags).map(Into::into)
+            }
+            IoctlRequest::TIOCSCTTY => {
+                let steal = usize::from(arg_ptr) == 1;
+                Self::set_controlling_terminal(ctx, &pair, steal).map(|()| 0.into())
+            }
+            IoctlRequest::TIOCNOTTY => {
+                if !is_controlling_terminal {
+                    return Some(Err(Errno::ENOTTY.into()));
+                }
+                // Only the session leader can disassociate the session from the terminal.
+                if ctx.objs.process.id() == session {
+                    pair.borrow_mut().clear_controlling_session();
+                }
+                Ok(0.into())
+            }
+            IoctlRequest::TIOCGPGRP => {
+                // the master can be used to query a terminal that isn't the controlling terminal
+                if end == PtyEnd::Slave && !is_controlling_terminal {
+                    return Some(Err(Errno::ENOTTY.into()));
+                }
+                let group = pair
+                    .borrow()
+                    .foreground_group()
+                    .map(kernel_pid_t::from)
+                    .unwrap_or(0);
+                ctx.objs
+                    .process
+                    .memory_borrow_mut()
+                    .write(arg_ptr.cast::<kernel_pid_t>(), &group)
+                    .map(|()| 0.into())
+                    .map_err(Into::into)
+            }
+            IoctlRequest::TIOCSPGRP => {
+                if !is_controlling_terminal {
+                    return Some(Err(Errno::ENOTTY.into()));
+                }
+                Self::set_foreground_group(ctx, &pair, arg_ptr.cast::<kernel_pid_t>())
+                    .map(|()| 0.into())
+            }
+            IoctlRequest::TIOCGSID => {
+                if end == PtyEnd::Slave && !is_controlling_terminal {
+                    return Some(Err(Errno::ENOTTY.into()));
+                }
+                let Some(session) = pair.borrow().session() else {
+                    return Some(Err(Errno::ENOTTY.into()));
+                };
+                ctx.objs
+                    .process
+                    .memory_borrow_mut()
+                    .write(arg_ptr.cast::<kernel_pid_t>(), &session.into())
+                    .map(|()| 0.into())
+                    .map_err(Into::into)
+            }
+            IoctlRequest::TIOCSWINSZ => {
+                Self::set_window_size(ctx, &pair, arg_ptr.cast::<winsize>()).map(|()| 0.into())
+            }
+            _ => return None,
+        };
+
+        Some(rv)
+    }
+
+    fn open_pty_master(ctx: &mut SyscallContext, flags: OFlag) -> Result<c_int, SyscallError> {
+        let (mode, status, flags) = Self::pty_open_flags(flags)?;
+
+        let pty = CallbackQueue::queue_and_run(|cb_queue| {
+            let mut pty_table = ctx.objs.host.pty_table_borrow_mut();
+            let pty = Pty::new_master(pty_table.next_index(), mode, status, cb_queue);
+            pty_table.insert(pty.borrow().pair());
+            pty
+        });
+
+        Self::register_pty(ctx, pty, flags)
+    }
+
+    fn open_pty_slave(
+        ctx: &mut SyscallContext,
+        pair: Arc<AtomicRefCell<PtyPair>>,
+        flags: OFlag,
+    ) -> Result<c_int, SyscallError> {
+        let (mode, status, flags) = Self::pty_open_flags(flags)?;
+
+        if pair.borrow().is_locked() {
+            return Err(Errno::EIO.into());
+        }
+
+        // Like Linux, a session leader that opens a terminal without `O_NOCTTY` acquires it as its
+        // controlling terminal, if it doesn't have one already.
+        if !flags.contains(OFlag::O_NOCTTY)
+            && ctx.objs.process.id() == ctx.objs.process.session_id()
+        {
+            let _ = Self::set_controlling_terminal(ctx, &pair, false);
+        }
+
+        let pty =
+            CallbackQueue::queue_and_run(|cb_queue| Pty::new_slave(pair, mode, status, cb_queue));
+
+        Self::register_pty(ctx, pty, flags)
+    }
+
+    /// Returns the access mode, the file status flags, and the remaining flags of a pseudoterminal
+    /// being opened with `flags`.
+    fn pty_open_flags(flags: OFlag) -> Result<(FileMode, FileStatus, OFlag), SyscallError> {
+        let Ok((mode, flags)) = FileMode::from_o_flags(flags) else {
+            return Err(Errno::EINVAL.into());
+        };
+        if mode.is_empty() {
+            // O_PATH
+            return Err(Errno::EINVAL.into());
+        }
+        let (status, flags) = FileStatus::from_o_flags(flags);
+        Ok((mode, status, flags))
+    }
+
+    fn register_pty(
+        ctx: &mut SyscallContext,
+        pty: Arc<AtomicRefCell<Pty>>,
+        flags: OFlag,
+    ) -> Result<c_int, SyscallError> {
+        let mut desc = Descriptor::new(CompatFile::New(OpenFile::new(File::Pty(pty))));
+        if flags.contains(OFlag::O_CLOEXEC) {
+            desc.set_flags(DescriptorFlags::FD_CLOEXEC);
+        }
+
+        let fd = ctx
+            .objs
+            .thread
+            .descriptor_table_borrow_mut(ctx.objs.host)
+            .register_descriptor(desc)
+            .or(Err(Errno::ENFILE))?;
+
+        Ok(fd.val().try_into().unwrap())
+    }
+
+    /// Makes `pair` the controlling terminal of the calling process's session. If `steal` is true
+    /// and the caller is privileged, it's taken from any session that it's already the
+    /// controlling terminal of.
+    fn set_controlling_terminal(
+        ctx: &mut SyscallContext,
+        pair: &Arc<AtomicRefCell<PtyPair>>,
+        steal: bool,
+    ) -> Result<(), SyscallError> {
+        let session = ctx.objs.process.session_id();
+
+        if pair.borrow().session() == Some(session) {
+            // already our controlling terminal
+            return Ok(());
+        }
+
+        // only a session leader without a controlling terminal can acquire one
+        if ctx.objs.process.id() != session
+            || ctx
+                .objs
+                .host
+                .pty_table_borrow()
+                .controlling_terminal(session)
+                .is_some()
+        {
+            return Err(Errno::EPERM.into());
+        }
+
+        let mut pair = pair.borrow_mut();
+        if pair.session().is_some() && !(steal && ctx.objs.process.credentials().is_privileged()) {
+            // the controlling terminal of another session
+            return Err(Errno::EPERM.into());
+        }
+
+        pair.set_controlling_session(session, ctx.objs.process.group_id());
+        Ok(())
+    }
+
+    fn set_foreground_group(
+        ctx: &mut SyscallContext,
+        pair: &Arc<AtomicRefCell<PtyPair>>,
+        group_ptr: ForeignPtr<kernel_pid_t>,
+    ) -> Result<(), SyscallError> {
+        let group = ctx.objs.process.memory_borrow().read(group_ptr)?;
+        if group < 0 {
+            return Err(Errno::EINVAL.into());
+        }
+        let Ok(group) = ProcessId::try_from(group) else {
+            return Err(Errno::ESRCH.into());
+        };
+
+        match ctx.objs.host.process_session_id_of_group_id(group) {
+            None => return Err(Errno::ESRCH.into()),
+            Some(session) if session != ctx.objs.process.session_id() => {
+                return Err(Errno::EPERM.into())
+            }
+            Some(_) => {}
+        }
+
+        pair.borrow_mut().set_foreground_group(group);
+        Ok(())
+    }
+
+    /// Sets the window size, and sends `SIGWINCH` to the foreground process group if it changed.
+    fn set_window_size(
+        ctx: &mut SyscallContext,
+        pair: &Arc<AtomicRefCell<PtyPair>>,
+        winsize_ptr: ForeignPtr<winsize>,
+    ) -> Result<(), SyscallError> {
+        let winsize = ctx.objs.process.memory_borrow().read(winsize_ptr)?;
+
+        let foreground_group = {
+            let mut pair = pair.borrow_mut();
+            if !pair.set_winsize(winsize) {
+                return Ok(());
+            }
+            pair.foreground_group()
+        };
+
+        if let Some(group) = foreground_group {
+            // sent by the kernel rather than by a process
+            let siginfo = siginfo_t::new_for_kill(Signal::SIGWINCH, 0, 0);
+            ctx.objs
+                .host
+                .signal_process_group(Some(ctx.objs.thread), group, &siginfo);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use std::ffi::c_int;
+
+use linux_api::errno::Errno;
+use linux_api::resource::rusage;
+use shadow_shim_helper_rs::syscall_types::ForeignPtr;
+use syscall_logger::log_syscall;
+
+use crate::host::process::rusage_from_cpu_time;
+use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
+use crate::host::syscall_types::SyscallError;
+
+impl SyscallHandler {
+    #[log_syscall(/* rv */ c_int, /* who */ c_int, /* usage */ *const std::ffi::c_void)]
+    pub fn getrusage(
+        ctx: &mut SyscallContext,
+        who: c_int,
+        usage_ptr: ForeignPtr<rusage>,
+    ) -> Result<c_int, SyscallError> {
+        let usage = match who {
+            libc::RUSAGE_SELF => ctx.objs.process.rusage(),
+            libc::RUSAGE_CHILDREN => ctx.objs.process.children_rusage(),
+            libc::RUSAGE_THREAD => rusage_from_cpu_time(ctx.objs.thread.cpu_time()),
+            _ => return Err(Errno::EINVAL.into()),
+        };
+
+        ctx.objs
+            .process
+            .memory_borrow_mut()
+            .write(usage_ptr, &usage)?;
+        Ok(0)
+    }
+}
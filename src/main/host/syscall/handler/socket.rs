@@ -1,17 +1,22 @@
 use linux_api::errno::Errno;
 use linux_api::fcntl::DescriptorFlags;
+use linux_api::netlink::NETLINK_ROUTE;
 use log::*;
 use nix::sys::socket::{Shutdown, SockFlag};
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use syscall_logger::log_syscall;
 
+use crate::host::descriptor::socket::inet::icmp::IcmpSocket;
 use crate::host::descriptor::socket::inet::legacy_tcp::LegacyTcpSocket;
+use crate::host::descriptor::socket::inet::raw::RawSocket;
 use crate::host::descriptor::socket::inet::tcp::TcpSocket;
 use crate::host::descriptor::socket::inet::udp::UdpSocket;
-use crate::host::descriptor::socket::inet::InetSocket;
+use crate::host::descriptor::socket::inet::{InetSocket, Ipv6Options};
+use crate::host::descriptor::socket::netlink::NetlinkSocket;
 use crate::host::descriptor::socket::unix::{UnixSocket, UnixSocketType};
 use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
 use crate::host::descriptor::{CompatFile, Descriptor, File, FileState, FileStatus, OpenFile};
+use crate::host::quota::QuotaDirection;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall::io::{self, IoVec};
 use crate::host::syscall::type_formatting::{SyscallBufferArg, SyscallSockAddrArg};
@@ -69,7 +74,7 @@ impl SyscallHandler {
                     &ctx.objs.host.abstract_unix_namespace(),
                 ))
             }
-            libc::AF_INET => match socket_type {
+            libc::AF_INET | libc::AF_INET6 => match socket_type {
                 libc::SOCK_STREAM => {
                     if protocol != 0 && protocol != libc::IPPROTO_TCP {
                         log::debug!("Unsupported inet stream socket protocol {protocol}");
@@ -77,7 +82,8 @@ impl SyscallHandler {
                     }
 
                     if ctx.objs.host.params.use_new_tcp {
-                        Socket::Inet(InetSocket::Tcp(TcpSocket::new(file_flags)))
+                        let config = TcpSocket::config_for_host(&ctx.objs.host.params);
+                        Socket::Inet(InetSocket::Tcp(TcpSocket::new(file_flags, config)))
                     } else {
                         Socket::Inet(InetSocket::LegacyTcp(LegacyTcpSocket::new(
                             file_flags,
@@ -86,23 +92,103 @@ impl SyscallHandler {
                     }
                 }
                 libc::SOCK_DGRAM => {
-                    if protocol != 0 && protocol != libc::IPPROTO_UDP {
-                        log::debug!("Unsupported inet dgram socket protocol {protocol}");
+                    let send_buf_size = ctx.objs.host.params.init_sock_send_buf_size;
+                    let recv_buf_size = ctx.objs.host.params.init_sock_recv_buf_size;
+                    let send_buf_size = send_buf_size.try_into().unwrap();
+                    let recv_buf_size = recv_buf_size.try_into().unwrap();
+
+                    match protocol {
+                        0 | libc::IPPROTO_UDP => Socket::Inet(InetSocket::Udp(UdpSocket::new(
+                            file_flags,
+                            send_buf_size,
+                            recv_buf_size,
+                        ))),
+                        // a ping socket (see icmp(7)); IPv6 ping sockets use ICMPv6, which
+                        // isn't supported
+                        libc::IPPROTO_ICMP if domain == libc::AF_INET => {
+                            Socket::Inet(InetSocket::Icmp(IcmpSocket::new(
+                                file_flags,
+                                send_buf_size,
+                                recv_buf_size,
+                            )))
+                        }
+                        _ => {
+                            log::debug!("Unsupported inet dgram socket protocol {protocol}");
+                            return Err(Errno::EPROTONOSUPPORT.into());
+                        }
+                    }
+                }
+                libc::SOCK_RAW => {
+                    // linux requires `CAP_NET_RAW`, which we only give to privileged processes
+                    if !ctx.objs.process.credentials().is_privileged() {
+                        return Err(Errno::EPERM.into());
+                    }
+
+                    // IPv6 raw sockets don't include the IP header, so they can't be translated
+                    // like the other `AF_INET6` sockets
+                    if domain == libc::AF_INET6 {
+                        log::debug!("IPv6 raw sockets aren't supported");
+                        return Err(Errno::ESOCKTNOSUPPORT.into());
+                    }
+
+                    if !matches!(
+                        protocol,
+                        libc::IPPROTO_ICMP
+                            | libc::IPPROTO_TCP
+                            | libc::IPPROTO_UDP
+                            | libc::IPPROTO_RAW
+                    ) {
+                        log::debug!("Unsupported inet raw socket protocol {protocol}");
                         return Err(Errno::EPROTONOSUPPORT.into());
                     }
+
                     let send_buf_size = ctx.objs.host.params.init_sock_send_buf_size;
                     let recv_buf_size = ctx.objs.host.params.init_sock_recv_buf_size;
-                    Socket::Inet(InetSocket::Udp(UdpSocket::new(
+
+                    Socket::Inet(InetSocket::Raw(RawSocket::new(
                         file_flags,
+                        protocol.try_into().unwrap(),
                         send_buf_size.try_into().unwrap(),
                         recv_buf_size.try_into().unwrap(),
+                        &ctx.objs.host.network_namespace_borrow(),
                     )))
                 }
                 _ => return Err(Errno::ESOCKTNOSUPPORT.into()),
             },
+            libc::AF_NETLINK => {
+                if socket_type != libc::SOCK_RAW && socket_type != libc::SOCK_DGRAM {
+                    return Err(Errno::ESOCKTNOSUPPORT.into());
+                }
+
+                // we only emulate the kernel's routing and interface messages
+                if protocol != NETLINK_ROUTE {
+                    log::debug!("Unsupported netlink socket protocol {protocol}");
+                    return Err(Errno::EPROTONOSUPPORT.into());
+                }
+
+                let send_buf_size = ctx.objs.host.params.init_sock_send_buf_size;
+                let recv_buf_size = ctx.objs.host.params.init_sock_recv_buf_size;
+
+                Socket::Netlink(NetlinkSocket::new(
+                    file_flags,
+                    socket_type,
+                    send_buf_size.try_into().unwrap(),
+                    recv_buf_size.try_into().unwrap(),
+                ))
+            }
             _ => return Err(Errno::EAFNOSUPPORT.into()),
         };
 
+        // an `AF_INET6` socket is an IPv4 socket that translates its addresses
+        if domain == libc::AF_INET6 {
+            let Socket::Inet(ref socket) = socket else {
+                unreachable!();
+            };
+            socket
+                .borrow_mut()
+                .set_ipv6_options(Some(Ipv6Options::default()));
+        }
+
         let mut desc = Descriptor::new(CompatFile::New(OpenFile::new(File::Socket(socket))));
         desc.set_flags(descriptor_flags);
 
@@ -214,11 +300,14 @@ impl SyscallHandler {
         };
 
         // call the socket's sendmsg(), and run any resulting events
-        let mut result = crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
-            CallbackQueue::queue_and_run(|cb_queue| {
-                Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
-            })
-        });
+        let mut result = Self::check_bandwidth_quota(ctx, socket, QuotaDirection::Send, flags)
+            .and_then(|()| {
+                crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+                    CallbackQueue::queue_and_run(|cb_queue| {
+                        Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
+                    })
+                })
+            });
 
         // if the syscall will block, keep the file open until the syscall restarts
         if let Some(err) = result.as_mut().err() {
@@ -228,6 +317,8 @@ impl SyscallHandler {
         }
 
         let bytes_sent = result?;
+        Self::charge_bandwidth_quota(ctx, socket, QuotaDirection::Send, bytes_sent);
+
         Ok(bytes_sent)
     }
 
@@ -282,11 +373,14 @@ impl SyscallHandler {
         };
 
         // call the socket's sendmsg(), and run any resulting events
-        let mut result = crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
-            CallbackQueue::queue_and_run(|cb_queue| {
-                Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
-            })
-        });
+        let mut result = Self::check_bandwidth_quota(ctx, socket, QuotaDirection::Send, flags)
+            .and_then(|()| {
+                crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+                    CallbackQueue::queue_and_run(|cb_queue| {
+                        Socket::sendmsg(socket, args, &mut mem, &net_ns, &mut *rng, cb_queue)
+                    })
+                })
+            });
 
         // if the syscall will block, keep the file open until the syscall restarts
         if let Some(err) = result.as_mut().err() {
@@ -296,6 +390,8 @@ impl SyscallHandler {
         }
 
         let bytes_written = result?;
+        Self::charge_bandwidth_quota(ctx, socket, QuotaDirection::Send, bytes_written);
+
         Ok(bytes_written)
     }
 
@@ -354,11 +450,14 @@ impl SyscallHandler {
         };
 
         // call the socket's recvmsg(), and run any resulting events
-        let mut result = crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
-            CallbackQueue::queue_and_run(|cb_queue| {
-                Socket::recvmsg(socket, args, &mut mem, cb_queue)
-            })
-        });
+        let mut result = Self::check_bandwidth_quota(ctx, socket, QuotaDirection::Recv, flags)
+            .and_then(|()| {
+                crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+                    CallbackQueue::queue_and_run(|cb_queue| {
+                        Socket::recvmsg(socket, args, &mut mem, cb_queue)
+                    })
+                })
+            });
 
         // if the syscall will block, keep the file open until the syscall restarts
         if let Some(err) = result.as_mut().err() {
@@ -372,6 +471,7 @@ impl SyscallHandler {
             addr: from_addr,
             ..
         } = result?;
+        Self::charge_bandwidth_quota(ctx, socket, QuotaDirection::Recv, return_val);
 
         if !addr_ptr.is_null() {
             io::write_sockaddr_and_len(&mut mem, from_addr.as_ref(), addr_ptr, addr_len_ptr)?;
@@ -427,11 +527,14 @@ impl SyscallHandler {
         };
 
         // call the socket's recvmsg(), and run any resulting events
-        let mut result = crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
-            CallbackQueue::queue_and_run(|cb_queue| {
-                Socket::recvmsg(socket, args, &mut mem, cb_queue)
-            })
-        });
+        let mut result = Self::check_bandwidth_quota(ctx, socket, QuotaDirection::Recv, flags)
+            .and_then(|()| {
+                crate::utility::legacy_callback_queue::with_global_cb_queue(|| {
+                    CallbackQueue::queue_and_run(|cb_queue| {
+                        Socket::recvmsg(socket, args, &mut mem, cb_queue)
+                    })
+                })
+            });
 
         // if the syscall will block, keep the file open until the syscall restarts
         if let Some(err) = result.as_mut().err() {
@@ -441,6 +544,7 @@ impl SyscallHandler {
         }
 
         let result = result?;
+        Self::charge_bandwidth_quota(ctx, socket, QuotaDirection::Recv, result.return_val);
 
         // write the socket address to the plugin and update the length in msg
         if !msg.name.is_null() {
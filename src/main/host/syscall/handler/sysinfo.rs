@@ -30,16 +30,27 @@ impl SyscallHandler {
         info.loads[0] = 1;
         info.loads[1] = 1;
         info.loads[2] = 1;
-        info.totalram = 32;
-        info.freeram = 24;
-        info.sharedram = 4;
-        info.bufferram = 4;
+        const GIB: u64 = 1024 * 1024 * 1024;
+
+        // files in the host's `/dev/shm` tmpfs are stored in memory
+        let tmpfs_usage = ctx
+            .objs
+            .host
+            .tmpfs_borrow()
+            .as_ref()
+            .map(|x| x.usage())
+            .unwrap_or(0);
+
+        info.totalram = 32 * GIB;
+        info.freeram = (24 * GIB).saturating_sub(tmpfs_usage);
+        info.sharedram = 4 * GIB + tmpfs_usage;
+        info.bufferram = 4 * GIB;
         info.totalswap = 0;
         info.freeswap = 0;
         info.procs = 100;
-        info.totalhigh = 4;
-        info.freehigh = 3;
-        info.mem_unit = 1024 * 1024 * 1024; // GiB
+        info.totalhigh = 4 * GIB;
+        info.freehigh = 3 * GIB;
+        info.mem_unit = 1; // bytes
 
         // Write the result to plugin memory.
         ctx.objs
@@ -48,4 +59,12 @@ impl SyscallHandler {
             .write(info_ptr, &info)?;
         Ok(0.into())
     }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* buf */ *const std::ffi::c_void)]
+    pub fn uname(ctx: &mut SyscallContext, buf_ptr: ForeignPtr<libc::utsname>) -> SyscallResult {
+        let buf = ctx.objs.host.uname();
+
+        ctx.objs.process.memory_borrow_mut().write(buf_ptr, &buf)?;
+        Ok(0.into())
+    }
 }
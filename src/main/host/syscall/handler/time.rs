@@ -106,8 +106,11 @@ impl SyscallHandler {
         clock_id: linux_api::time::linux___kernel_clockid_t,
         res_ptr: ForeignPtr<linux_api::time::timespec>,
     ) -> Result<std::ffi::c_int, SyscallError> {
-        // Make sure we have a valid clock id.
-        ClockId::try_from(clock_id).map_err(|_| Errno::EINVAL)?;
+        // Make sure we have a valid clock id. Linux doesn't implement `CLOCK_SGI_CYCLE`.
+        let clock_id = ClockId::try_from(clock_id).map_err(|_| Errno::EINVAL)?;
+        if clock_id == ClockId::CLOCK_SGI_CYCLE {
+            return Err(Errno::EINVAL.into());
+        }
 
         // All clocks have nanosecond resolution.
         if !res_ptr.is_null() {
@@ -121,6 +124,70 @@ impl SyscallHandler {
         Ok(0)
     }
 
+    // The shim handles the time syscalls itself, so these are only reached by processes that
+    // don't load the shim.
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* clock_id */ linux_api::time::ClockId,
+                  /* tp */ *const std::ffi::c_void)]
+    pub fn clock_gettime(
+        ctx: &mut SyscallContext,
+        clock_id: linux_api::time::linux___kernel_clockid_t,
+        tp_ptr: ForeignPtr<linux_api::time::timespec>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        // Linux doesn't implement `CLOCK_SGI_CYCLE`.
+        let clock_id = ClockId::try_from(clock_id).map_err(|_| Errno::EINVAL)?;
+        if clock_id == ClockId::CLOCK_SGI_CYCLE {
+            return Err(Errno::EINVAL.into());
+        }
+
+        // All clocks have the emulated time.
+        let now = Worker::current_time()
+            .unwrap()
+            .duration_since(&EmulatedTime::UNIX_EPOCH);
+        let now = linux_api::time::timespec::try_from(now).unwrap();
+        ctx.objs.process.memory_borrow_mut().write(tp_ptr, &now)?;
+
+        Ok(0)
+    }
+
+    #[log_syscall(/* rv */ libc::time_t, /* tloc */ *const std::ffi::c_void)]
+    pub fn time(
+        ctx: &mut SyscallContext,
+        tloc_ptr: ForeignPtr<libc::time_t>,
+    ) -> Result<libc::time_t, SyscallError> {
+        let now = Worker::current_time()
+            .unwrap()
+            .duration_since(&EmulatedTime::UNIX_EPOCH);
+        let now = libc::time_t::try_from(now.as_secs()).unwrap();
+
+        if !tloc_ptr.is_null() {
+            ctx.objs.process.memory_borrow_mut().write(tloc_ptr, &now)?;
+        }
+
+        Ok(now)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* tv */ *const std::ffi::c_void,
+                  /* tz */ *const std::ffi::c_void)]
+    pub fn gettimeofday(
+        ctx: &mut SyscallContext,
+        tv_ptr: ForeignPtr<libc::timeval>,
+        _tz_ptr: ForeignPtr<()>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        if !tv_ptr.is_null() {
+            let now = Worker::current_time()
+                .unwrap()
+                .duration_since(&EmulatedTime::UNIX_EPOCH);
+            let now = libc::timeval {
+                tv_sec: now.as_secs().try_into().unwrap(),
+                tv_usec: now.subsec_micros().into(),
+            };
+            ctx.objs.process.memory_borrow_mut().write(tv_ptr, &now)?;
+        }
+
+        Ok(0)
+    }
+
     #[log_syscall(/* rv */ std::ffi::c_int,
         /* clock_id */ linux_api::time::ClockId,
         /* flags */ linux_api::time::ClockNanosleepFlags,
@@ -192,7 +259,11 @@ impl SyscallHandler {
         allow_unspec_bitflags: bool,
     ) -> Result<std::ffi::c_int, SyscallError> {
         let request = ctx.objs.process.memory_borrow().read(request_ptr)?;
-        let request_time = SimulationTime::try_from(request).or(Err(Errno::EINVAL))?;
+        if request.tv_sec < 0 || !(0..1_000_000_000).contains(&request.tv_nsec) {
+            return Err(Errno::EINVAL.into());
+        }
+        // A valid time that's too large to represent (e.g. from `sleep infinity`) sleeps forever.
+        let request_time = SimulationTime::try_from(request).ok();
         let flags = if allow_unspec_bitflags {
             ClockNanosleepFlags::from_bits_truncate(flags)
         } else {
@@ -201,12 +272,17 @@ impl SyscallHandler {
 
         let now = Worker::current_time().unwrap();
 
-        // The requested wakeup time may be absolute or relative.
-        let abs_wakeup_time = if flags.contains(ClockNanosleepFlags::TIMER_ABSTIME) {
-            EmulatedTime::UNIX_EPOCH + request_time
-        } else {
-            now + request_time
-        };
+        // The requested wakeup time may be absolute or relative. All clocks share the emulated
+        // time, so an absolute time is relative to the unix epoch regardless of the clock.
+        let abs_wakeup_time = request_time
+            .map(|request_time| {
+                if flags.contains(ClockNanosleepFlags::TIMER_ABSTIME) {
+                    EmulatedTime::UNIX_EPOCH.saturating_add(request_time)
+                } else {
+                    now.saturating_add(request_time)
+                }
+            })
+            .unwrap_or(EmulatedTime::MAX);
 
         // A wakeup time in the past means we return without sleeping.
         if abs_wakeup_time <= now {
@@ -215,8 +291,13 @@ impl SyscallHandler {
 
         // Condition will exist after a wakeup.
         let Some(cond) = ctx.objs.thread.syscall_condition() else {
-            // Didn't sleep yet; block the thread now.
-            return Err(SyscallError::new_blocked_until(abs_wakeup_time, false));
+            // Didn't sleep yet; block the thread now. Like Linux, we wake up
+            // late by the thread's timer slack.
+            let mut rv = SyscallError::new_blocked_until(abs_wakeup_time, false);
+            rv.blocked_condition()
+                .unwrap()
+                .set_timeout_slack(ctx.objs.thread.timer_slack());
+            return Err(rv);
         };
 
         // Woke up from sleep. We must have set a timeout to sleep.
@@ -240,4 +321,80 @@ impl SyscallHandler {
             Err(SyscallError::new_interrupted(false))
         }
     }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* buf */ *const std::ffi::c_void)]
+    pub fn adjtimex(
+        ctx: &mut SyscallContext,
+        buf_ptr: ForeignPtr<libc::timex>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        Self::adjtime_helper(ctx, buf_ptr)
+    }
+
+    #[log_syscall(/* rv */ std::ffi::c_int, /* clock_id */ linux_api::time::ClockId,
+                  /* buf */ *const std::ffi::c_void)]
+    pub fn clock_adjtime(
+        ctx: &mut SyscallContext,
+        clock_id: linux_api::time::linux___kernel_clockid_t,
+        buf_ptr: ForeignPtr<libc::timex>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let clock_id = ClockId::try_from(clock_id).map_err(|_| Errno::EINVAL)?;
+
+        // Linux only supports adjusting the realtime clock.
+        if clock_id != ClockId::CLOCK_REALTIME {
+            log::debug!("Clock id {clock_id:?} unsupported for clock_adjtime.");
+            return Err(Errno::EOPNOTSUPP.into());
+        }
+
+        Self::adjtime_helper(ctx, buf_ptr)
+    }
+
+    /// The simulated clock can't be adjusted, so this only reports the state of a perfectly
+    /// synchronized clock. Like for an unprivileged process on Linux, adjustments fail with
+    /// `EPERM`.
+    fn adjtime_helper(
+        ctx: &mut SyscallContext,
+        buf_ptr: ForeignPtr<libc::timex>,
+    ) -> Result<std::ffi::c_int, SyscallError> {
+        let mut buf = ctx.objs.process.memory_borrow().read(buf_ptr)?;
+
+        // `ADJ_OFFSET_SS_READ` is the only mode that doesn't adjust the clock.
+        if buf.modes != 0 && buf.modes != libc::ADJ_OFFSET_SS_READ {
+            warn_once_then_debug!("(LOG_ONCE) Adjusting the simulated clock is unsupported");
+            return Err(Errno::EPERM.into());
+        }
+
+        let now = Worker::current_time()
+            .unwrap()
+            .duration_since(&EmulatedTime::UNIX_EPOCH);
+
+        buf.offset = 0;
+        buf.freq = 0;
+        buf.maxerror = 0;
+        buf.esterror = 0;
+        buf.status = 0;
+        buf.constant = 0;
+        // linux's defaults
+        buf.precision = 1;
+        buf.tolerance = 500 << 16;
+        buf.tick = 1_000_000 / 100;
+        // `STA_NANO` isn't set, so this is in microseconds
+        buf.time = libc::timeval {
+            tv_sec: now.as_secs().try_into().unwrap(),
+            tv_usec: now.subsec_micros().into(),
+        };
+        buf.ppsfreq = 0;
+        buf.jitter = 0;
+        buf.shift = 0;
+        buf.stabil = 0;
+        buf.jitcnt = 0;
+        buf.calcnt = 0;
+        buf.errcnt = 0;
+        buf.stbcnt = 0;
+        // `CLOCK_TAI` has the same time as `CLOCK_REALTIME`
+        buf.tai = 0;
+
+        ctx.objs.process.memory_borrow_mut().write(buf_ptr, &buf)?;
+
+        Ok(libc::TIME_OK)
+    }
 }
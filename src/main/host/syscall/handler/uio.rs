@@ -5,6 +5,7 @@ use syscall_logger::log_syscall;
 use crate::cshadow as c;
 use crate::host::descriptor::socket::{RecvmsgArgs, RecvmsgReturn, SendmsgArgs, Socket};
 use crate::host::descriptor::{CompatFile, File, FileState, FileStatus};
+use crate::host::quota::QuotaDirection;
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall::io::{self, IoVec};
 use crate::host::syscall_types::{ForeignArrayPtr, SyscallError};
@@ -231,6 +232,8 @@ impl SyscallHandler {
                 return Ok(0);
             }
 
+            Self::check_bandwidth_quota(ctx, socket, QuotaDirection::Recv, 0)?;
+
             let args = RecvmsgArgs {
                 iovs,
                 control_ptr: ForeignArrayPtr::new(ForeignPtr::null(), 0),
@@ -245,6 +248,8 @@ impl SyscallHandler {
                     })
                 })?;
 
+            Self::charge_bandwidth_quota(ctx, socket, QuotaDirection::Recv, return_val);
+
             return Ok(return_val);
         }
 
@@ -489,6 +494,8 @@ impl SyscallHandler {
                 return Err(Errno::ESPIPE.into());
             }
 
+            Self::check_bandwidth_quota(ctx, socket, QuotaDirection::Send, 0)?;
+
             let args = SendmsgArgs {
                 addr: None,
                 iovs,
@@ -504,6 +511,8 @@ impl SyscallHandler {
                     })
                 })?;
 
+            Self::charge_bandwidth_quota(ctx, socket, QuotaDirection::Send, bytes_written);
+
             return Ok(bytes_written);
         }
 
@@ -7,12 +7,9 @@ use linux_api::fcntl::{DescriptorFlags, OFlag};
 use linux_api::posix_types::{kernel_off_t, kernel_pid_t};
 use log::*;
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
-use shadow_shim_helper_rs::rootedcell::refcell::RootedRefCell;
-use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use syscall_logger::log_syscall;
 
-use crate::core::work::task::TaskRef;
 use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::descriptor::pipe;
@@ -428,8 +425,10 @@ impl SyscallHandler {
             }
         }
 
-        // reference-counted buffer for the pipe
-        let buffer = SharedBuf::new(c::CONFIG_PIPE_BUFFER_SIZE.try_into().unwrap());
+        // reference-counted buffer for the pipe; like linux, writes of up to PIPE_BUF bytes are
+        // atomic
+        let buffer = SharedBuf::new(c::CONFIG_PIPE_BUFFER_SIZE.try_into().unwrap())
+            .with_atomic_write_len(libc::PIPE_BUF);
         let buffer = Arc::new(AtomicRefCell::new(buffer));
 
         // reference-counted file object for read end of the pipe
@@ -549,13 +548,6 @@ impl SyscallHandler {
             // the calling process.
             return Err(Errno::ESRCH.into());
         }
-        if let Some(pgid) = pgid {
-            if ctx.objs.host.process_session_id_of_group_id(pgid) != Some(process.session_id()) {
-                // An attempt was made to move a process into a process group in
-                // a different session
-                return Err(Errno::EPERM.into());
-            }
-        }
         if process.session_id() != ctx.objs.process.session_id() {
             // `setpgid(2)`: ... or to change the process  group  ID of one of
             // the children of the calling process and the child was in a
@@ -570,17 +562,21 @@ impl SyscallHandler {
         // `setpgid(2): EACCES: An attempt was made to change the process group
         // ID of one of the children of the calling process and the child had
         // already performed an execve(2).
-        if let Some(pgid) = pgid {
-            if ctx.objs.host.process_session_id_of_group_id(pgid) != Some(process.session_id()) {
-                // `setpgid(2)`: An attempt was made to move a process into a
-                // process group in a different session
-                return Err(Errno::EPERM.into());
-            }
-            process.set_group_id(pgid);
-        } else {
+        match pgid {
             // `setpgid(2)`: If pgid is zero, then the PGID of the process
             // specified by pid is made the same as its process ID.
-            process.set_group_id(process.id());
+            None => process.set_group_id(process.id()),
+            // A new process group, which doesn't need to exist yet.
+            Some(pgid) if pgid == process.id() => process.set_group_id(pgid),
+            Some(pgid) => {
+                if ctx.objs.host.process_session_id_of_group_id(pgid) != Some(process.session_id())
+                {
+                    // `setpgid(2)`: An attempt was made to move a process into a
+                    // process group in a different session
+                    return Err(Errno::EPERM.into());
+                }
+                process.set_group_id(pgid);
+            }
         }
         Ok(0)
     }
@@ -699,68 +695,31 @@ impl SyscallHandler {
         // get from here since it's already borrowed immutably.
         //
         // So, we return a "blocking" result from this syscall handler, and
-        // schedule an event to update the `Process` and resume execution.
-        //
-        // It's possible that other events may affect the `Process` before this one runs.
-        // We try to handle this gracefully; e.g. if the `Process` has exited before this
-        // event runs, we kill and drop the exec'd `ManagedThread` and carry on.
-        //
-        // TODO: There may be other interactions that aren't handled correctly.
-        // e.g. if the exec'ing thread ends up handling a signal in the meantime.
-        // * We could add a new state "`Execing`" to `Process`, and force any
-        // such events to decide how to deal with it. e.g. signal delivery
-        // events could reschedule themselves to run after the exec has
-        // completed. This seems a bit heavy-weight, though.
-        // * We could add more interior mutability s.t. we don't need mutable
-        // references to the Thread and Process in order to do the necessary
-        // updates. This is a fair bit of extra interior mutability to add
-        // though, and has a side-effect of further complicating read-accesses
-        // to items that are read-mostly.
-        // * We could arrange for syscall handlers to get or be able to get
-        // mutable references to the Thread and Process, so that we can complete
-        // the updates synchronously here. This is currently blocked by the
-        // usage of `worker_getCurrentProcess` and `worker_getCurrentThread`,
-        // which will panic with incompatible borrow errors if those are
-        // borrowed mutably.  There aren't many references left to those though,
-        // maybe we can eliminate them.
+        // defer the work to update the `Process` and resume execution until the
+        // borrows held by the current event have been released.
         {
             let pid = ctx.objs.process.id();
             let tid = ctx.objs.thread.id();
 
-            // Tasks are currently required to be `Sync` and to implement `Fn`, not just `FnOnce`.
-            // Since `mthread` isn't `Sync`, we need to wrap it in a `RootedRefCell`.
-            // Since we need to consume it, we need to also wrap it in an
-            // `Option` and fail at runtime if this actually gets executed
-            // multiple times.
-            // TODO: Split TaskRef into another type that only requires `FnOnce` and `Send`.
-            let mthread = RootedRefCell::new(ctx.objs.host.root(), Some(mthread));
-            ctx.objs.host.schedule_task_with_delay(
-                TaskRef::new(move |host| {
-                    // Take the `mthread` out of the captured wrapper.
-                    // This task shouldn't run multiple times, so this should be
-                    // infallible.
-                    let mthread = mthread.borrow_mut(host.root()).take().unwrap();
-                    // The exec'ing thread's ID is changed to match the pid, since it's
-                    // the new thread-group-leader.
-                    let new_tglid = {
-                        let Some(processrc) = host.process_borrow(pid) else {
-                            // Can happen if another event runs before this one
-                            // and causes the Process to exit (e.g. exit_group
-                            // called from anothe Thread).
-                            log::debug!("Process {pid:?} disappeared before exec could complete");
-                            mthread.kill_and_drop();
-                            return;
-                        };
-                        Worker::set_active_process(&processrc);
-                        let mut process = processrc.borrow_mut(host.root());
-                        process.update_for_exec(host, tid, mthread);
-                        Worker::clear_active_process();
-                        process.thread_group_leader_id()
+            ctx.objs.host.defer(move |host| {
+                // The exec'ing thread's ID is changed to match the pid, since it's
+                // the new thread-group-leader.
+                let new_tglid = {
+                    let Some(processrc) = host.process_borrow(pid) else {
+                        // Can happen if the rest of the current event causes the
+                        // Process to exit.
+                        log::debug!("Process {pid:?} disappeared before exec could complete");
+                        mthread.kill_and_drop();
+                        return;
                     };
-                    host.resume(pid, new_tglid);
-                }),
-                SimulationTime::ZERO,
-            );
+                    Worker::set_active_process(&processrc);
+                    let mut process = processrc.borrow_mut(host.root());
+                    process.update_for_exec(host, tid, mthread);
+                    Worker::clear_active_process();
+                    process.thread_group_leader_id()
+                };
+                host.resume(pid, new_tglid);
+            });
         }
 
         Err(SyscallError::new_blocked_until(EmulatedTime::MAX, false))
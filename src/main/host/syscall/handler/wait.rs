@@ -10,13 +10,13 @@ use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
 use shadow_shim_helper_rs::syscall_types::ForeignPtr;
 use syscall_logger::log_syscall;
 
-use crate::host::process::{ExitStatus, Process, ProcessId};
+use crate::host::descriptor::FileStatus;
+use crate::host::process::{rusage_from_cpu_time, ExitStatus, JobControlEvent, Process, ProcessId};
 use crate::host::syscall::handler::{SyscallContext, SyscallHandler};
 use crate::host::syscall_types::SyscallError;
 
 enum WaitTarget {
     Pid(ProcessId),
-    PidFd(c_int),
     Pgid(ProcessId),
     Any,
 }
@@ -25,7 +25,6 @@ impl WaitTarget {
     pub fn matches(&self, process: &Process) -> bool {
         match self {
             WaitTarget::Pid(pid) => process.id() == *pid,
-            WaitTarget::PidFd(_) => unimplemented!(),
             WaitTarget::Pgid(pgid) => process.group_id() == *pgid,
             WaitTarget::Any => true,
         }
@@ -71,11 +70,19 @@ impl WaitTarget {
                 };
                 pgid.map(WaitTarget::Pgid)
             }
-            WaitId::P_PIDFD => Some(WaitTarget::PidFd(pid)),
+            // The caller needs to look up the process from the pidfd.
+            WaitId::P_PIDFD => unreachable!(),
         }
     }
 }
 
+/// A change in a child's state that can be reported by `wait`.
+#[derive(Debug, Copy, Clone)]
+enum ChildEvent {
+    Exited,
+    JobControl(JobControlEvent),
+}
+
 impl SyscallHandler {
     fn wait_internal(
         ctx: &mut SyscallContext,
@@ -113,29 +120,22 @@ impl SyscallHandler {
             return Err(Errno::ECHILD.into());
         }
 
-        if !options.contains(WaitFlags::WEXITED) {
-            warn_once_then_debug!("Waiting only for child events that currently never happen under Shadow: {options:?}");
-            // The other events that can be waited for (WUNTRACED, WSTOPPED,
-            // WCONTINUED) currently can't happen under Shadow.
-            // TODO: If and when those things *can* happen, check for them here.
-            return if options.contains(WaitFlags::WNOHANG) {
-                Ok(0)
-            } else {
-                Err(SyscallError::new_blocked_on_child(
-                    /* restartable */ true,
-                ))
-            };
-        }
-
-        let mut matching_child_zombies = matching_children.filter(|(_pid, process)| {
+        // Find the first matching child with an event that we're waiting for.
+        let matching_child_event = matching_children.find_map(|(pid, process)| {
             let process = process.borrow(ctx.objs.host.root());
-            let zombie = process.borrow_as_zombie();
-            zombie.is_some()
+            if process.borrow_as_zombie().is_some() {
+                return options
+                    .contains(WaitFlags::WEXITED)
+                    .then_some((*pid, ChildEvent::Exited));
+            }
+            match process.job_control_event()? {
+                JobControlEvent::Stopped(_) if !options.contains(WaitFlags::WSTOPPED) => None,
+                JobControlEvent::Continued if !options.contains(WaitFlags::WCONTINUED) => None,
+                event => Some((*pid, ChildEvent::JobControl(event))),
+            }
         });
-        let Some((matching_child_zombie_pid, matching_child_zombie)) =
-            matching_child_zombies.next()
-        else {
-            // There are matching children, but none are zombies yet.
+        let Some((matching_child_pid, event)) = matching_child_event else {
+            // There are matching children, but none have events to report yet.
             return if options.contains(WaitFlags::WNOHANG) {
                 Ok(0)
             } else {
@@ -148,47 +148,67 @@ impl SyscallHandler {
             };
         };
 
-        let zombie_process = matching_child_zombie.borrow(ctx.objs.host.root());
-        let zombie = zombie_process.borrow_as_zombie().unwrap();
+        let child_process = processes
+            .get(&matching_child_pid)
+            .unwrap()
+            .borrow(ctx.objs.host.root());
         let mut memory = ctx.objs.process.memory_borrow_mut();
 
         if !status_ptr.is_null() {
-            let status = match zombie.exit_status() {
-                ExitStatus::Normal(i) => i << 8,
-                ExitStatus::Signaled(s) => {
-                    // This should be `| 0x80` if the process dumped core, but since
-                    // this depends on the system config we never set this flag.
-                    i32::from(s)
-                }
-                ExitStatus::StoppedByShadow => unreachable!(),
+            let status = match event {
+                ChildEvent::Exited => match child_process.borrow_as_zombie().unwrap().exit_status()
+                {
+                    ExitStatus::Normal(i) => i << 8,
+                    ExitStatus::Signaled(s) => {
+                        // This should be `| 0x80` if the process dumped core, but since
+                        // this depends on the system config we never set this flag.
+                        i32::from(s)
+                    }
+                    ExitStatus::StoppedByShadow => unreachable!(),
+                },
+                ChildEvent::JobControl(JobControlEvent::Stopped(s)) => (i32::from(s) << 8) | 0x7f,
+                ChildEvent::JobControl(JobControlEvent::Continued) => 0xffff,
             };
             memory.write(status_ptr, &status)?;
         }
         if !infop.is_null() {
-            let info = zombie.exit_siginfo(Signal::SIGCHLD);
+            let info = match event {
+                ChildEvent::Exited => child_process
+                    .borrow_as_zombie()
+                    .unwrap()
+                    .exit_siginfo(Signal::SIGCHLD),
+                ChildEvent::JobControl(event) => child_process.job_control_siginfo(event),
+            };
             memory.write(infop, &info)?;
         }
+        // The child's resource usage, including that of its own reaped children.
+        let child_cpu_time = child_process.total_cpu_time();
         if !usage.is_null() {
-            memory.write(usage, &ctx.objs.process.rusage())?;
+            memory.write(usage, &rusage_from_cpu_time(child_cpu_time))?;
         }
 
-        let matching_child_zombie_pid: ProcessId = *matching_child_zombie_pid;
         // Drop our borrow of the process list so that we can reap without a runtime borrow error.
         drop(memory);
-        drop(zombie);
-        drop(zombie_process);
+        drop(child_process);
         drop(processes);
 
         if !options.contains(WaitFlags::WNOWAIT) {
-            let zombie_process = ctx
-                .objs
-                .host
-                .process_remove(matching_child_zombie_pid)
-                .unwrap();
-            zombie_process.explicit_drop(ctx.objs.host.root());
+            match event {
+                ChildEvent::Exited => {
+                    let zombie_process = ctx.objs.host.process_remove(matching_child_pid).unwrap();
+                    zombie_process.explicit_drop(ctx.objs.host.root());
+                    ctx.objs.process.add_children_cpu_time(child_cpu_time);
+                }
+                ChildEvent::JobControl(_) => {
+                    let child_process = ctx.objs.host.process_borrow(matching_child_pid).unwrap();
+                    child_process
+                        .borrow(ctx.objs.host.root())
+                        .clear_job_control_event();
+                }
+            }
         }
 
-        Ok(matching_child_zombie_pid.into())
+        Ok(matching_child_pid.into())
     }
 
     #[log_syscall(
@@ -244,9 +264,44 @@ impl SyscallHandler {
         options: c_int,
         uru: ForeignPtr<rusage>,
     ) -> Result<(), SyscallError> {
-        let wait_flags = WaitFlags::from_bits_retain(options);
+        let Some(mut wait_flags) = WaitFlags::from_bits(options) else {
+            return Err(Errno::EINVAL.into());
+        };
+
+        let allowed_flags = WaitFlags::WNOHANG
+            | WaitFlags::WNOWAIT
+            | WaitFlags::WEXITED
+            | WaitFlags::WSTOPPED
+            | WaitFlags::WCONTINUED
+            | WaitFlags::__WCLONE
+            | WaitFlags::__WALL
+            | WaitFlags::__WNOTHREAD;
+        let unexpected_flags = wait_flags.difference(allowed_flags);
+        if !unexpected_flags.is_empty() {
+            log::debug!("Unexpected flags: {unexpected_flags:?}");
+            return Err(Errno::EINVAL.into());
+        }
+
+        // From waitid(2): "Applications shall specify at least one of the
+        // following flags in options: WEXITED, WSTOPPED, WCONTINUED".
+        if !wait_flags.intersects(WaitFlags::WEXITED | WaitFlags::WSTOPPED | WaitFlags::WCONTINUED)
+        {
+            return Err(Errno::EINVAL.into());
+        }
+
         let wait_id = WaitId::try_from(which).map_err(|_| Errno::EINVAL)?;
-        let Some(target) = WaitTarget::from_waitid(ctx.objs.process, wait_id, upid) else {
+
+        // Whether we're waiting on a pidfd that has `O_NONBLOCK` set.
+        let mut nonblocking_pidfd = false;
+
+        let target = if wait_id == WaitId::P_PIDFD {
+            let pidfd = Self::get_pidfd(ctx, upid)?;
+            let pidfd = pidfd.borrow();
+            nonblocking_pidfd = pidfd.status().contains(FileStatus::NONBLOCK);
+            WaitTarget::Pid(pidfd.pid())
+        } else if let Some(target) = WaitTarget::from_waitid(ctx.objs.process, wait_id, upid) {
+            target
+        } else {
             // We can get here if e.g. the ID was P_PID, but the pid was
             // negative so couldn't be converted to a ProcessId. Afaict from the man page,
             // this would simply result in no child matching the target, hence `ECHILD`.
@@ -254,6 +309,33 @@ impl SyscallHandler {
             return Err(Errno::ECHILD.into());
         };
 
-        Self::wait_internal(ctx, target, ForeignPtr::null(), infop, wait_flags, uru).map(|_| ())
+        // From waitid(2):
+        // > If this PID file descriptor is open in nonblocking mode, [...] and
+        // > the child has not yet terminated, then the call returns with the
+        // > error EAGAIN instead of blocking (unless WNOHANG was specified).
+        let pidfd_would_block = nonblocking_pidfd && !wait_flags.contains(WaitFlags::WNOHANG);
+        if pidfd_would_block {
+            wait_flags.insert(WaitFlags::WNOHANG);
+        }
+
+        let pid = Self::wait_internal(ctx, target, ForeignPtr::null(), infop, wait_flags, uru)?;
+        if pid == 0 && pidfd_would_block {
+            return Err(Errno::EAGAIN.into());
+        }
+
+        // From waitid(2):
+        // > If WNOHANG was specified in options and there were no children in a
+        // > waitable state, then waitid() returns 0 immediately and the state of
+        // > the siginfo_t structure pointed to by infop depends on the
+        // > implementation. [...] the Linux implementation zeroes out the
+        // > siginfo_t structure in this case.
+        if pid == 0 && !infop.is_null() {
+            ctx.objs
+                .process
+                .memory_borrow_mut()
+                .write(infop, &shadow_pod::zeroed::<siginfo_t>())?;
+        }
+
+        Ok(())
     }
 }
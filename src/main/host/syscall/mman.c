@@ -219,6 +219,21 @@ static SyscallReturn _syscallhandler_mmap(SysCallHandler* sys, UntypedForeignPtr
     /* Now file_desc is null for an anonymous mapping, non-null otherwise. */
     int pluginFD = -1;
 
+    const Thread* thread = _syscallhandler_getThread(sys);
+    if (!thread_hasShim(thread)) {
+        /* We can't make syscalls from within a plugin that doesn't have the shim. Instead copy
+         * our descriptor into the plugin at the number of its emulated descriptor, and let the
+         * plugin make the original mmap syscall natively. */
+        if (file_desc) {
+            int res = thread_installNativeFd(thread, regularfile_getOSBackedFD(file_desc), fd);
+            if (res < 0) {
+                warning("Installing fd %d in the plugin for mmap failed: %s", fd, strerror(-res));
+                return syscallreturn_makeDoneErrno(EACCES);
+            }
+        }
+        return syscallreturn_makeNative();
+    }
+
     if (file_desc) {
         pluginFD = _syscallhandler_openPluginFile(sys, fd, file_desc);
         if (pluginFD < 0) {
@@ -7,8 +7,10 @@
 
 #include <errno.h>
 #include <poll.h>
+#include <signal.h>
 #include <stdbool.h>
 
+#include "lib/linux-api/linux-api.h"
 #include "lib/logger/logger.h"
 #include "main/bindings/c/bindings.h"
 #include "main/host/descriptor/descriptor.h"
@@ -22,6 +24,7 @@
 
 #define NANOS_PER_MILLISEC 1000000
 #define MILLIS_PER_SEC 1000
+#define NANOS_PER_SEC 1000000000
 
 ///////////////////////////////////////////////////////////
 // Helpers
@@ -122,8 +125,91 @@ static void _syscallhandler_registerPollFDs(SysCallHandler* sys, struct pollfd*
     }
 }
 
+int _syscallhandler_checkTimeout(const struct timespec* timeout) {
+    // NULL timeout is allowed, it means block indefinitely.
+    if (timeout != NULL && (timeout->tv_sec < 0 || timeout->tv_nsec < 0 ||
+                            timeout->tv_nsec >= NANOS_PER_SEC)) {
+        trace("invalid timeout given in timespec arg, returning EINVAL");
+        return -EINVAL;
+    } else {
+        return 0;
+    }
+}
+
+CEmulatedTime _syscallhandler_pollDeadline(SysCallHandler* sys, const struct timespec* timeout) {
+    if (_syscallhandler_wasBlocked(sys)) {
+        // Keep the deadline that we computed before blocking, otherwise every
+        // wakeup that doesn't complete the syscall would restart the timeout.
+        return _syscallhandler_getTimeout(sys);
+    }
+
+    if (!timeout) {
+        return EMUTIME_INVALID;
+    }
+
+    // The timeout must have been validated already. A timeout too large to
+    // represent is treated as no timeout.
+    CSimulationTime simtime = simtime_from_timespec(*timeout);
+    if (simtime == SIMTIME_INVALID) {
+        return EMUTIME_INVALID;
+    }
+
+    return emutime_add_simtime(worker_getCurrentEmulatedTime(), simtime);
+}
+
+bool _syscallhandler_pollRemainingTime(CEmulatedTime deadline, struct timespec* remaining) {
+    if (deadline == EMUTIME_INVALID) {
+        return false;
+    }
+
+    CEmulatedTime now = worker_getCurrentEmulatedTime();
+    CSimulationTime simtime = now >= deadline ? 0 : emutime_sub_emutime(deadline, now);
+    return simtime_to_timespec(simtime, remaining);
+}
+
+int _syscallhandler_setTemporarySigmask(SysCallHandler* sys, UntypedForeignPtr sigmask_ptr,
+                                        size_t sigsetsize) {
+    if (!sigmask_ptr.val) {
+        return 0;
+    }
+
+    if (sigsetsize != sizeof(linux_sigset_t)) {
+        trace("Bad sigsetsize %zu", sigsetsize);
+        return -EINVAL;
+    }
+
+    linux_sigset_t mask;
+    if (process_readPtr(_syscallhandler_getProcess(sys), &mask, sigmask_ptr, sizeof(mask)) != 0) {
+        return -EFAULT;
+    }
+
+    // SIGKILL and SIGSTOP can't be blocked.
+    linux_sigdelset(&mask, SIGKILL);
+    linux_sigdelset(&mask, SIGSTOP);
+
+    shimshmem_setTemporaryBlockedSignals(host_getShimShmemLock(_syscallhandler_getHost(sys)),
+                                         thread_sharedMem(_syscallhandler_getThread(sys)), mask);
+    return 0;
+}
+
+void _syscallhandler_finishTemporarySigmask(SysCallHandler* sys, SyscallReturn* scr) {
+    if (scr->tag == SYSCALL_RETURN_BLOCK) {
+        // Keep the temporary mask while we wait.
+        return;
+    }
+
+    if (scr->tag == SYSCALL_RETURN_DONE && syscallreturn_done(scr)->retval.as_i64 == -EINTR) {
+        // The shim restores the original mask after running the signal
+        // handlers, which need to run with the temporary mask.
+        return;
+    }
+
+    shimshmem_restoreBlockedSignals(host_getShimShmemLock(_syscallhandler_getHost(sys)),
+                                    thread_sharedMem(_syscallhandler_getThread(sys)));
+}
+
 SyscallReturn _syscallhandler_pollHelper(SysCallHandler* sys, struct pollfd* fds, nfds_t nfds,
-                                         const struct timespec* timeout) {
+                                         CEmulatedTime deadline) {
     // Check if any of the fds have events now
     int num_ready = _syscallhandler_getPollEvents(sys, fds, nfds);
 
@@ -131,9 +217,7 @@ SyscallReturn _syscallhandler_pollHelper(SysCallHandler* sys, struct pollfd* fds
 
     // Block or not depending on the timeout values
     if (num_ready == 0) {
-        bool dont_block = timeout && timeout->tv_sec == 0 && timeout->tv_nsec == 0;
-
-        if (dont_block || _syscallhandler_didListenTimeoutExpire(sys)) {
+        if (deadline != EMUTIME_INVALID && worker_getCurrentEmulatedTime() >= deadline) {
             trace("No events are ready and poll needs to return now");
             goto done;
         } else if (thread_unblockedSignalPending(
@@ -153,10 +237,13 @@ SyscallReturn _syscallhandler_pollHelper(SysCallHandler* sys, struct pollfd* fds
                                         .object = (LegacyFile*)sys->epoll,
                                         .status = STATUS_FILE_READABLE};
             SysCallCondition* cond = syscallcondition_new(trigger);
-            if (timeout && (timeout->tv_sec > 0 || timeout->tv_nsec > 0)) {
-                syscallcondition_setTimeout(cond, worker_getCurrentEmulatedTime() +
-                                                      timeout->tv_sec * SIMTIME_ONE_SECOND +
-                                                      timeout->tv_nsec * SIMTIME_ONE_NANOSECOND);
+            if (deadline != EMUTIME_INVALID) {
+                syscallcondition_setTimeout(cond, deadline);
+                // Like Linux, the slack depends on the time remaining.
+                CSimulationTime remaining =
+                    emutime_sub_emutime(deadline, worker_getCurrentEmulatedTime());
+                syscallcondition_setTimeoutSlack(
+                    cond, thread_getPollTimerSlack(_syscallhandler_getThread(sys), remaining));
             }
 
             // We either use our timer as a timeout, or no timeout
@@ -175,7 +262,7 @@ done:
 static SyscallReturn _syscallhandler_pollHelperUntypedForeignPtr(SysCallHandler* sys,
                                                                  UntypedForeignPtr fds_ptr,
                                                                  nfds_t nfds,
-                                                                 const struct timespec* timeout) {
+                                                                 CEmulatedTime deadline) {
     // Get the pollfd struct in our memory so we can read from and write to it.
     struct pollfd* fds = NULL;
     if (nfds > 0) {
@@ -185,7 +272,7 @@ static SyscallReturn _syscallhandler_pollHelperUntypedForeignPtr(SysCallHandler*
         }
     }
 
-    return _syscallhandler_pollHelper(sys, fds, nfds, timeout);
+    return _syscallhandler_pollHelper(sys, fds, nfds, deadline);
 }
 
 static int _syscallhandler_checkPollArgs(UntypedForeignPtr fds_ptr, nfds_t nfds) {
@@ -211,20 +298,27 @@ SyscallReturn syscallhandler_poll(SysCallHandler* sys, const SysCallArgs* args)
     int result = _syscallhandler_checkPollArgs(fds_ptr, nfds);
     if (result != 0) {
         return syscallreturn_makeDoneErrno(-result);
-    } else {
-        struct timespec timeout =
-            (struct timespec){.tv_sec = timeout_millis / MILLIS_PER_SEC,
-                              .tv_nsec = (timeout_millis % MILLIS_PER_SEC) * NANOS_PER_MILLISEC};
-        return _syscallhandler_pollHelperUntypedForeignPtr(sys, fds_ptr, nfds, &timeout);
     }
+
+    // A negative timeout means an infinite timeout.
+    struct timespec timeout =
+        (struct timespec){.tv_sec = timeout_millis / MILLIS_PER_SEC,
+                          .tv_nsec = (timeout_millis % MILLIS_PER_SEC) * NANOS_PER_MILLISEC};
+    CEmulatedTime deadline =
+        _syscallhandler_pollDeadline(sys, timeout_millis >= 0 ? &timeout : NULL);
+
+    return _syscallhandler_pollHelperUntypedForeignPtr(sys, fds_ptr, nfds, deadline);
 }
 
 SyscallReturn syscallhandler_ppoll(SysCallHandler* sys, const SysCallArgs* args) {
-    UntypedForeignPtr fds_ptr = args->args[0].as_ptr; // struct pollfd*
+    UntypedForeignPtr fds_ptr = args->args[0].as_ptr;        // struct pollfd*
     nfds_t nfds = args->args[1].as_u64;
-    UntypedForeignPtr ts_timeout_ptr = args->args[2].as_ptr; // const struct timespec*
+    UntypedForeignPtr ts_timeout_ptr = args->args[2].as_ptr; // struct timespec*
+    UntypedForeignPtr sigmask_ptr = args->args[3].as_ptr;    // const sigset_t*
+    size_t sigsetsize = args->args[4].as_u64;
 
-    trace("ppoll was called with nfds=%lu and timeout_ptr=%p", nfds, (void*)ts_timeout_ptr.val);
+    trace("ppoll was called with nfds=%lu, timeout_ptr=%p, and sigmask_ptr=%p", nfds,
+          (void*)ts_timeout_ptr.val, (void*)sigmask_ptr.val);
 
     int result = _syscallhandler_checkPollArgs(fds_ptr, nfds);
     if (result != 0) {
@@ -243,13 +337,33 @@ SyscallReturn syscallhandler_ppoll(SysCallHandler* sys, const SysCallArgs* args)
             return syscallreturn_makeDoneErrno(EFAULT);
         }
 
-        // Negative time values in the struct are invalid
-        if (ts_timeout_val.tv_sec < 0 || ts_timeout_val.tv_nsec < 0) {
-            trace("negative timeout given in timespec arg, returning EINVAL");
-            return syscallreturn_makeDoneErrno(EINVAL);
+        result = _syscallhandler_checkTimeout(&ts_timeout_val);
+        if (result != 0) {
+            return syscallreturn_makeDoneErrno(-result);
+        }
+    }
+
+    result = _syscallhandler_setTemporarySigmask(sys, sigmask_ptr, sigsetsize);
+    if (result != 0) {
+        return syscallreturn_makeDoneErrno(-result);
+    }
+
+    CEmulatedTime deadline =
+        _syscallhandler_pollDeadline(sys, ts_timeout_ptr.val ? &ts_timeout_val : NULL);
+
+    SyscallReturn scr = _syscallhandler_pollHelperUntypedForeignPtr(sys, fds_ptr, nfds, deadline);
+    _syscallhandler_finishTemporarySigmask(sys, &scr);
+
+    // Like Linux, report the time that was left. A zero timeout is left as is.
+    if (scr.tag == SYSCALL_RETURN_DONE && ts_timeout_ptr.val &&
+        (ts_timeout_val.tv_sec != 0 || ts_timeout_val.tv_nsec != 0)) {
+        struct timespec remaining;
+        if (_syscallhandler_pollRemainingTime(deadline, &remaining)) {
+            // Linux ignores errors here since the timeout may be read-only.
+            process_writePtr(
+                _syscallhandler_getProcess(sys), ts_timeout_ptr, &remaining, sizeof(remaining));
         }
     }
 
-    return _syscallhandler_pollHelperUntypedForeignPtr(
-        sys, fds_ptr, nfds, ts_timeout_ptr.val ? &ts_timeout_val : NULL);
+    return scr;
 }
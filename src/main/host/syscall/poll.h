@@ -14,7 +14,30 @@ SYSCALL_HANDLER(poll);
 SYSCALL_HANDLER(ppoll);
 
 /* Protected helper to allow select() to redirect here. */
+// Returns 0 if the timeout is valid for a syscall like ppoll, or -EINVAL otherwise.
+int _syscallhandler_checkTimeout(const struct timespec* timeout);
+
+// Returns the absolute time at which a syscall like poll should time out, or
+// EMUTIME_INVALID if it should block indefinitely. After the syscall has
+// blocked, this is the time that was computed before blocking.
+CEmulatedTime _syscallhandler_pollDeadline(SysCallHandler* sys, const struct timespec* timeout);
+
+// Sets the time left until the deadline, which is zero if it has passed.
+// Returns false if there is no deadline.
+bool _syscallhandler_pollRemainingTime(CEmulatedTime deadline, struct timespec* remaining);
+
+// Replaces the thread's signal mask with the mask given to ppoll or pselect6
+// until the syscall completes. Does nothing if the sigmask pointer is NULL.
+// Returns 0 on success or a negative errno.
+int _syscallhandler_setTemporarySigmask(SysCallHandler* sys, UntypedForeignPtr sigmask_ptr,
+                                        size_t sigsetsize);
+
+// Restores the signal mask replaced by _syscallhandler_setTemporarySigmask
+// once the syscall is done. If the syscall was interrupted, the shim restores
+// the mask after running the signal handlers instead.
+void _syscallhandler_finishTemporarySigmask(SysCallHandler* sys, SyscallReturn* scr);
+
 SyscallReturn _syscallhandler_pollHelper(SysCallHandler* sys, struct pollfd* fds, nfds_t nfds,
-                                         const struct timespec* timeout);
+                                         CEmulatedTime deadline);
 
 #endif /* SRC_MAIN_HOST_SYSCALL_POLL_H_ */
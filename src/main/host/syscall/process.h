@@ -8,7 +8,6 @@
 
 #include "main/host/syscall/protected.h"
 
-SYSCALL_HANDLER(prctl);
 SYSCALL_HANDLER(prlimit);
 SYSCALL_HANDLER(prlimit64);
 
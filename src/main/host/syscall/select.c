@@ -16,6 +16,15 @@
 #include "main/host/syscall/protected.h"
 #include "main/host/syscall_condition.h"
 
+#define USECS_PER_SEC 1000000
+#define NSECS_PER_USEC 1000
+
+// The sixth argument of pselect6.
+struct pselect6_sigmask {
+    UntypedForeignPtr ss; // const sigset_t*
+    size_t ss_len;
+};
+
 ///////////////////////////////////////////////////////////
 // Helpers
 ///////////////////////////////////////////////////////////
@@ -24,7 +33,7 @@ static SyscallReturn _syscallhandler_select_helper(SysCallHandler* sys, int nfds
                                                    UntypedForeignPtr readfds_ptr,
                                                    UntypedForeignPtr writefds_ptr,
                                                    UntypedForeignPtr exceptfds_ptr,
-                                                   struct timespec* timeout) {
+                                                   CEmulatedTime deadline) {
     // TODO: we could possibly reduce the max (i.e. the search space) further by checking the max fd
     // in the descriptor table.
     int nfds_max = MAX(0, MIN(nfds, FD_SETSIZE));
@@ -77,7 +86,7 @@ static SyscallReturn _syscallhandler_select_helper(SysCallHandler* sys, int nfds
         }
     }
 
-    SyscallReturn scr = _syscallhandler_pollHelper(sys, pfds, (nfds_t)nfds_max, timeout);
+    SyscallReturn scr = _syscallhandler_pollHelper(sys, pfds, (nfds_t)nfds_max, deadline);
     if (scr.tag == SYSCALL_RETURN_BLOCK ||
         (scr.tag == SYSCALL_RETURN_DONE && syscallreturn_done(&scr)->retval.as_i64 < 0)) {
         goto done;
@@ -163,17 +172,6 @@ static int _syscallhandler_check_nfds(int nfds) {
     }
 }
 
-static int _syscallhandler_check_timeout(const struct timespec* timeout) {
-    // NULL timeout is allowed, it means block indefinitely.
-    // Negative time values in the struct are invalid.
-    if (timeout != NULL && (timeout->tv_sec < 0 || timeout->tv_nsec < 0)) {
-        trace("negative timeout given in timespec arg, returning EINVAL");
-        return -EINVAL;
-    } else {
-        return 0;
-    }
-}
-
 ///////////////////////////////////////////////////////////
 // System Calls
 ///////////////////////////////////////////////////////////
@@ -207,19 +205,38 @@ SyscallReturn syscallhandler_select(SysCallHandler* sys, const SysCallArgs* args
             return syscallreturn_makeDoneErrno(EFAULT);
         }
 
-        // Convert timeval to timespec.
-        ts_timeout_val.tv_sec = tv_timeout_val.tv_sec;
-#define NSECS_PER_USEC 1000
-        ts_timeout_val.tv_nsec = tv_timeout_val.tv_usec * NSECS_PER_USEC;
+        // Convert timeval to timespec. Like Linux, microseconds beyond a second
+        // are carried over into the seconds.
+        ts_timeout_val.tv_sec = tv_timeout_val.tv_sec + tv_timeout_val.tv_usec / USECS_PER_SEC;
+        ts_timeout_val.tv_nsec = (tv_timeout_val.tv_usec % USECS_PER_SEC) * NSECS_PER_USEC;
+
+        result = _syscallhandler_checkTimeout(&ts_timeout_val);
+        if (result != 0) {
+            return syscallreturn_makeDoneErrno(-result);
+        }
     }
 
-    result = _syscallhandler_check_timeout(timeout_ptr.val ? &ts_timeout_val : NULL);
-    if (result != 0) {
-        return syscallreturn_makeDoneErrno(-result);
+    CEmulatedTime deadline =
+        _syscallhandler_pollDeadline(sys, timeout_ptr.val ? &ts_timeout_val : NULL);
+
+    SyscallReturn scr = _syscallhandler_select_helper(
+        sys, nfds, readfds_ptr, writefds_ptr, exceptfds_ptr, deadline);
+
+    // Like Linux, report the time that was left. A zero timeout is left as is.
+    if (scr.tag == SYSCALL_RETURN_DONE && timeout_ptr.val &&
+        (ts_timeout_val.tv_sec != 0 || ts_timeout_val.tv_nsec != 0)) {
+        struct timespec remaining;
+        struct timeval tv_remaining;
+        if (_syscallhandler_pollRemainingTime(deadline, &remaining)) {
+            tv_remaining.tv_sec = remaining.tv_sec;
+            tv_remaining.tv_usec = remaining.tv_nsec / NSECS_PER_USEC;
+            // Linux ignores errors here since the timeout may be read-only.
+            process_writePtr(
+                _syscallhandler_getProcess(sys), timeout_ptr, &tv_remaining, sizeof(tv_remaining));
+        }
     }
 
-    return _syscallhandler_select_helper(sys, nfds, readfds_ptr, writefds_ptr, exceptfds_ptr,
-                                         timeout_ptr.val ? &ts_timeout_val : NULL);
+    return scr;
 }
 
 SyscallReturn syscallhandler_pselect6(SysCallHandler* sys, const SysCallArgs* args) {
@@ -227,12 +244,13 @@ SyscallReturn syscallhandler_pselect6(SysCallHandler* sys, const SysCallArgs* ar
     UntypedForeignPtr readfds_ptr = args->args[1].as_ptr;   // fd_set*
     UntypedForeignPtr writefds_ptr = args->args[2].as_ptr;  // fd_set*
     UntypedForeignPtr exceptfds_ptr = args->args[3].as_ptr; // fd_set*
-    UntypedForeignPtr timeout_ptr = args->args[4].as_ptr;   // const struct timespec*
-    // TODO how do we handle the sigmask arg and behavior
+    UntypedForeignPtr timeout_ptr = args->args[4].as_ptr;   // struct timespec*
+    UntypedForeignPtr sigmask_ptr = args->args[5].as_ptr;   // struct pselect6_sigmask*
 
-    trace("select was called with nfds=%i, readfds=%p, writefds=%p, exceptfds=%p, and timeout=%p",
+    trace("pselect6 was called with nfds=%i, readfds=%p, writefds=%p, exceptfds=%p, timeout=%p, "
+          "and sigmask=%p",
           nfds, (void*)readfds_ptr.val, (void*)writefds_ptr.val, (void*)exceptfds_ptr.val,
-          (void*)timeout_ptr.val);
+          (void*)timeout_ptr.val, (void*)sigmask_ptr.val);
 
     int result = _syscallhandler_check_nfds(nfds);
     if (result != 0) {
@@ -248,13 +266,45 @@ SyscallReturn syscallhandler_pselect6(SysCallHandler* sys, const SysCallArgs* ar
                             sizeof(ts_timeout_val)) != 0) {
             return syscallreturn_makeDoneErrno(EFAULT);
         }
+
+        result = _syscallhandler_checkTimeout(&ts_timeout_val);
+        if (result != 0) {
+            return syscallreturn_makeDoneErrno(-result);
+        }
     }
 
-    result = _syscallhandler_check_timeout(timeout_ptr.val ? &ts_timeout_val : NULL);
-    if (result != 0) {
-        return syscallreturn_makeDoneErrno(-result);
+    // Unlike ppoll, the last argument points to both the signal mask and its
+    // size, since the syscall doesn't have enough arguments for both.
+    if (sigmask_ptr.val) {
+        struct pselect6_sigmask sigmask = {0};
+        if (process_readPtr(_syscallhandler_getProcess(sys), &sigmask, sigmask_ptr,
+                            sizeof(sigmask)) != 0) {
+            return syscallreturn_makeDoneErrno(EFAULT);
+        }
+
+        result = _syscallhandler_setTemporarySigmask(sys, sigmask.ss, sigmask.ss_len);
+        if (result != 0) {
+            return syscallreturn_makeDoneErrno(-result);
+        }
     }
 
-    return _syscallhandler_select_helper(sys, nfds, readfds_ptr, writefds_ptr, exceptfds_ptr,
-                                         timeout_ptr.val ? &ts_timeout_val : NULL);
+    CEmulatedTime deadline =
+        _syscallhandler_pollDeadline(sys, timeout_ptr.val ? &ts_timeout_val : NULL);
+
+    SyscallReturn scr = _syscallhandler_select_helper(
+        sys, nfds, readfds_ptr, writefds_ptr, exceptfds_ptr, deadline);
+    _syscallhandler_finishTemporarySigmask(sys, &scr);
+
+    // Like Linux, report the time that was left. A zero timeout is left as is.
+    if (scr.tag == SYSCALL_RETURN_DONE && timeout_ptr.val &&
+        (ts_timeout_val.tv_sec != 0 || ts_timeout_val.tv_nsec != 0)) {
+        struct timespec remaining;
+        if (_syscallhandler_pollRemainingTime(deadline, &remaining)) {
+            // Linux ignores errors here since the timeout may be read-only.
+            process_writePtr(
+                _syscallhandler_getProcess(sys), timeout_ptr, &remaining, sizeof(remaining));
+        }
+    }
+
+    return scr;
 }
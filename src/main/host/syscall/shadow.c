@@ -12,26 +12,29 @@
 #include <strings.h>
 
 #include "lib/logger/logger.h"
+#include "main/bindings/c/bindings.h"
 #include "main/core/support/config_handlers.h"
 #include "main/core/worker.h"
 #include "main/host/syscall/protected.h"
+#include "main/host/syscall_condition.h"
 #include "main/host/syscall_types.h"
 #include "main/routing/address.h"
 
 static bool _useMM = true;
 ADD_CONFIG_HANDLER(config_getUseMemoryManager, _useMM)
 
-SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv4(SysCallHandler* sys,
-                                                          const SysCallArgs* args) {
+// Handles the custom syscalls that look up the IPv4 address (or IPv6 address if `ipv6` is true) of
+// a host name, which the shim uses to implement getaddrinfo().
+static SyscallReturn _syscallhandler_hostnameToAddr(SysCallHandler* sys, const SysCallArgs* args,
+                                                    bool ipv6) {
     utility_debugAssert(sys && args);
     UntypedForeignPtr name_ptr = args->args[0].as_ptr;
     size_t name_len = args->args[1].as_u64;
     UntypedForeignPtr addr_ptr = args->args[2].as_ptr;
     size_t addr_len = args->args[3].as_u64;
 
-    trace("Handling custom syscall shadow_hostname_to_addr_ipv4");
-
-    if (!name_ptr.val || !addr_ptr.val || addr_len < sizeof(uint32_t)) {
+    size_t required_len = ipv6 ? sizeof(struct in6_addr) : sizeof(uint32_t);
+    if (!name_ptr.val || !addr_ptr.val || addr_len < required_len) {
         trace("Invalid argument detected, returning EINVAL");
         return syscallreturn_makeDoneErrno(EINVAL);
     }
@@ -44,10 +47,13 @@ SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv4(SysCallHandler* sys,
     }
 
     if (strcasecmp(name, "localhost") == 0) {
-        // Loopback address in network order.
-        uint32_t* addr =
-            process_getWriteablePtr(_syscallhandler_getProcess(sys), addr_ptr, addr_len);
-        *addr = htonl(INADDR_LOOPBACK);
+        void* addr = process_getWriteablePtr(_syscallhandler_getProcess(sys), addr_ptr, addr_len);
+        if (ipv6) {
+            *(struct in6_addr*)addr = in6addr_loopback;
+        } else {
+            // Loopback address in network order.
+            *(uint32_t*)addr = htonl(INADDR_LOOPBACK);
+        }
         trace("Returning loopback address for localhost");
         return syscallreturn_makeDoneI64(0);
     }
@@ -59,6 +65,19 @@ SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv4(SysCallHandler* sys,
         trace("Using default address for my own hostname %s", name);
         address = host_getDefaultAddress(_syscallhandler_getHost(sys));
     } else {
+        // Apply the configured resolver latency and failures.
+        CEmulatedTime blockUntil = EMUTIME_INVALID;
+        int error = host_resolverLookup(_syscallhandler_getHost(sys),
+                                        thread_getID(_syscallhandler_getThread(sys)), name, ipv6,
+                                        &blockUntil);
+        if (blockUntil != EMUTIME_INVALID) {
+            trace("Lookup of name %s blocked until the resolver responds", name);
+            return syscallreturn_makeBlocked(syscallcondition_newWithAbsTimeout(blockUntil), true);
+        } else if (error != 0) {
+            trace("Lookup of name %s failed with injected error %d", name, error);
+            return syscallreturn_makeDoneErrno(error);
+        }
+
         trace("Looking up name %s", name);
         address = worker_resolveNameToAddress(name);
     }
@@ -66,20 +85,21 @@ SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv4(SysCallHandler* sys,
     if (address) {
         trace("Found address %s for name %s", address_toString(address), name);
 
-        uint32_t ip = address_toNetworkIP(address);
-
         // Release the readable pointer so that we can get a writable pointer.
         int res = process_flushPtrs(_syscallhandler_getProcess(sys));
         if (res != 0) {
             return syscallreturn_makeDoneErrno(res);
         }
 
-        uint32_t* addr =
-            process_getWriteablePtr(_syscallhandler_getProcess(sys), addr_ptr, addr_len);
+        void* addr = process_getWriteablePtr(_syscallhandler_getProcess(sys), addr_ptr, addr_len);
         if (addr == NULL) {
             return syscallreturn_makeDoneErrno(EFAULT);
         }
-        *addr = ip;
+        if (ipv6) {
+            address_toNetworkIPv6(address, addr);
+        } else {
+            *(uint32_t*)addr = address_toNetworkIP(address);
+        }
 
         return syscallreturn_makeDoneI64(0);
     } else {
@@ -89,6 +109,18 @@ SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv4(SysCallHandler* sys,
     }
 }
 
+SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv4(SysCallHandler* sys,
+                                                          const SysCallArgs* args) {
+    trace("Handling custom syscall shadow_hostname_to_addr_ipv4");
+    return _syscallhandler_hostnameToAddr(sys, args, false);
+}
+
+SyscallReturn syscallhandler_shadow_hostname_to_addr_ipv6(SysCallHandler* sys,
+                                                          const SysCallArgs* args) {
+    trace("Handling custom syscall shadow_hostname_to_addr_ipv6");
+    return _syscallhandler_hostnameToAddr(sys, args, true);
+}
+
 SyscallReturn syscallhandler_shadow_init_memory_manager(SysCallHandler* sys,
                                                         const SysCallArgs* args) {
     utility_debugAssert(sys && args);
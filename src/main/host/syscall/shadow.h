@@ -10,6 +10,7 @@
 
 // Handle the custom shadow-specific syscalls defined in syscall_numbers.h
 SYSCALL_HANDLER(shadow_hostname_to_addr_ipv4);
+SYSCALL_HANDLER(shadow_hostname_to_addr_ipv6);
 SYSCALL_HANDLER(shadow_init_memory_manager);
 SYSCALL_HANDLER(shadow_yield);
 
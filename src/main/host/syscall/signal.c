@@ -33,8 +33,26 @@ static int _shim_handled_signals[] = {SIGSYS, SIGSEGV};
 // Helpers
 ///////////////////////////////////////////////////////////
 
+// Returns the result of a syscall that sent a signal. If the calling process
+// stopped itself, the calling thread blocks until the process is continued.
+static SyscallReturn _syscallhandler_signalSent(SysCallHandler* sys) {
+    if (process_isStopped(_syscallhandler_getProcess(sys))) {
+        // The thread isn't resumed while the process is stopped, so an immediate
+        // timeout is enough. The syscall returns 0 when it's executed again.
+        CEmulatedTime now = worker_getCurrentEmulatedTime();
+        return syscallreturn_makeBlocked(syscallcondition_newWithAbsTimeout(now), false);
+    }
+
+    return syscallreturn_makeDoneI64(0);
+}
+
 static SyscallReturn _syscallhandler_signalProcess(SysCallHandler* sys, const Process* process,
                                                    int sig) {
+    if (_syscallhandler_wasBlocked(sys)) {
+        // We stopped ourselves with this signal, and have now been continued.
+        return syscallreturn_makeDoneI64(0);
+    }
+
     if (sig == 0) {
         return syscallreturn_makeDoneI64(0);
     }
@@ -52,11 +70,51 @@ static SyscallReturn _syscallhandler_signalProcess(SysCallHandler* sys, const Pr
 
     process_signal(process, _syscallhandler_getThread(sys), &siginfo);
 
-    return syscallreturn_makeDoneI64(0);
+    return _syscallhandler_signalSent(sys);
+}
+
+static SyscallReturn _syscallhandler_signalProcessGroup(SysCallHandler* sys, pid_t pgid,
+                                                        int sig) {
+    if (_syscallhandler_wasBlocked(sys)) {
+        // We stopped ourselves with this signal, and have now been continued.
+        return syscallreturn_makeDoneI64(0);
+    }
+
+    if (sig == 0) {
+        if (!host_signalProcessGroup(
+                _syscallhandler_getHost(sys), _syscallhandler_getThread(sys), pgid, NULL)) {
+            return syscallreturn_makeDoneErrno(ESRCH);
+        }
+        return syscallreturn_makeDoneI64(0);
+    }
+
+    if (!linux_signal_is_valid(sig)) {
+        return syscallreturn_makeDoneErrno(EINVAL);
+    }
+
+    if (linux_signal_is_realtime(sig)) {
+        warning("Unimplemented signal %d", sig);
+        return syscallreturn_makeDoneErrno(ENOSYS);
+    }
+
+    linux_siginfo_t siginfo = linux_siginfo_new_for_kill(sig, sys->processId, 0);
+
+    if (!host_signalProcessGroup(
+            _syscallhandler_getHost(sys), _syscallhandler_getThread(sys), pgid, &siginfo)) {
+        debug("Process group %d not found", pgid);
+        return syscallreturn_makeDoneErrno(ESRCH);
+    }
+
+    return _syscallhandler_signalSent(sys);
 }
 
 static SyscallReturn _syscallhandler_signalThread(SysCallHandler* sys, const Thread* thread,
                                                   int sig) {
+    if (_syscallhandler_wasBlocked(sys)) {
+        // We stopped ourselves with this signal, and have now been continued.
+        return syscallreturn_makeDoneI64(0);
+    }
+
     if (sig == 0) {
         return syscallreturn_makeDoneI64(0);
     }
@@ -71,11 +129,19 @@ static SyscallReturn _syscallhandler_signalThread(SysCallHandler* sys, const Thr
     }
 
     const Process* process = thread_getProcess(thread);
+    if (!process_jobControlSignal(process, _syscallhandler_getThread(sys), sig)) {
+        // The signal stopped the process, and isn't delivered.
+        return _syscallhandler_signalSent(sys);
+    }
+
     struct linux_sigaction action = shimshmem_getSignalAction(
         host_getShimShmemLock(_syscallhandler_getHost(sys)), process_getSharedMem(process), sig);
+    LinuxDefaultAction default_action = linux_defaultAction(sig);
     if (action.lsa_handler == SIG_IGN ||
-        (action.lsa_handler == SIG_DFL && linux_defaultAction(sig) == LINUX_DEFAULT_ACTION_IGN)) {
-        // Don't deliver ignored an signal.
+        (action.lsa_handler == SIG_DFL && (default_action == LINUX_DEFAULT_ACTION_IGN ||
+                                           default_action == LINUX_DEFAULT_ACTION_CONT))) {
+        // Don't deliver ignored an signal. The default action of SIGCONT,
+        // continuing the process, was already applied above.
         return syscallreturn_makeDoneI64(0);
     }
 
@@ -147,16 +213,12 @@ SyscallReturn syscallhandler_kill(SysCallHandler* sys, const SysCallArgs* args)
     } else if (pid == 0) {
         // kill(2): If pid equals 0, then sig is sent to every process in the
         // process group of the calling process.
-        //
-        // Currently every emulated process is in its own process group.
-        pid = sys->processId;
+        pid_t pgid = process_getProcessGroupID(_syscallhandler_getProcess(sys));
+        return _syscallhandler_signalProcessGroup(sys, pgid, sig);
     } else if (pid < -1) {
         // kill(2): If pid is less than -1, then sig is sent to every process in
         // the process group whose ID is -pid.
-        //
-        // Currently every emulated process is in its own process group, where
-        // pgid=pid.
-        pid = -pid;
+        return _syscallhandler_signalProcessGroup(sys, -pid, sig);
     }
 
     const Process* process = host_getProcess(_syscallhandler_getHost(sys), pid);
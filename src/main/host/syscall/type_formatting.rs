@@ -193,6 +193,7 @@ bitflags_impl!(linux_api::fcntl::OFlag);
 bitflags_impl!(linux_api::mman::ProtFlags);
 bitflags_impl!(linux_api::mman::MapFlags);
 bitflags_impl!(linux_api::mman::MRemapFlags);
+bitflags_impl!(linux_api::mman::MsyncFlags);
 bitflags_impl!(linux_api::time::ClockNanosleepFlags);
 
 fn fmt_buffer(
@@ -7,7 +7,6 @@
 
 #include <errno.h>
 #include <stdio.h>
-#include <sys/utsname.h>
 
 #include "lib/logger/logger.h"
 #include "main/bindings/c/bindings.h"
@@ -239,23 +238,3 @@ SyscallReturn syscallhandler_set_tid_address(SysCallHandler* sys, const SysCallA
     thread_setTidAddress(_syscallhandler_getThread(sys), tidptr);
     return syscallreturn_makeDoneI64(sys->threadId);
 }
-
-SyscallReturn syscallhandler_uname(SysCallHandler* sys, const SysCallArgs* args) {
-    struct utsname* buf = NULL;
-
-    buf = process_getWriteablePtr(
-        _syscallhandler_getProcess(sys), args->args[0].as_ptr, sizeof(*buf));
-    if (!buf) {
-        return syscallreturn_makeDoneErrno(EFAULT);
-    }
-
-    const gchar* hostname = host_getName(_syscallhandler_getHost(sys));
-
-    snprintf(buf->sysname, _UTSNAME_SYSNAME_LENGTH, "shadowsys");
-    snprintf(buf->nodename, _UTSNAME_NODENAME_LENGTH, "%s", hostname);
-    snprintf(buf->release, _UTSNAME_RELEASE_LENGTH, "shadowrelease");
-    snprintf(buf->version, _UTSNAME_VERSION_LENGTH, "shadowversion");
-    snprintf(buf->machine, _UTSNAME_MACHINE_LENGTH, "shadowmachine");
-
-    return syscallreturn_makeDoneI64(0);
-}
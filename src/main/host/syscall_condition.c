@@ -24,7 +24,9 @@ struct _SysCallCondition {
     Trigger trigger;
     // Time at which the condition will expire, or EMUTIME_INVALID if no timeout.
     CEmulatedTime timeoutExpiration;
-    // Timeout object waiting for timeoutExpiration.
+    // Extra delay before the timeout object wakes the thread after timeoutExpiration.
+    CSimulationTime timeoutSlack;
+    // Timeout object waiting for timeoutExpiration plus timeoutSlack.
     Timer* timeout;
     // The active file in the blocked syscall. This is state used when resuming a blocked syscall.
     OpenFile* activeFile;
@@ -51,6 +53,7 @@ SysCallCondition* syscallcondition_new(Trigger trigger) {
     SysCallCondition* cond = malloc(sizeof(*cond));
 
     *cond = (SysCallCondition){.timeoutExpiration = EMUTIME_INVALID,
+                               .timeoutSlack = 0,
                                .timeout = NULL,
                                .trigger = trigger,
                                .referenceCount = 1,
@@ -103,6 +106,12 @@ void syscallcondition_setTimeout(SysCallCondition* cond, CEmulatedTime t) {
     cond->timeoutExpiration = t;
 }
 
+void syscallcondition_setTimeoutSlack(SysCallCondition* cond, CSimulationTime slack) {
+    MAGIC_ASSERT(cond);
+
+    cond->timeoutSlack = slack;
+}
+
 void syscallcondition_setActiveFile(SysCallCondition* cond, OpenFile* file) {
     MAGIC_ASSERT(cond);
 
@@ -272,8 +281,10 @@ static void _syscallcondition_logListeningState(SysCallCondition* cond, const Pr
     }
 
     if (cond->timeoutExpiration != EMUTIME_INVALID) {
-        utility_debugAssert(cond->timeoutExpiration >= worker_getCurrentEmulatedTime());
-        CSimulationTime remainingTime = cond->timeoutExpiration - worker_getCurrentEmulatedTime();
+        // The timeout may have expired while we wait out the timer slack.
+        CEmulatedTime now = worker_getCurrentEmulatedTime();
+        CSimulationTime remainingTime =
+            cond->timeoutExpiration > now ? cond->timeoutExpiration - now : 0;
         g_string_append_printf(string, "a timeout with %llu.%09llu seconds remaining",
                                remainingTime / SIMTIME_ONE_SECOND,
                                (remainingTime % SIMTIME_ONE_SECOND) / SIMTIME_ONE_NANOSECOND);
@@ -463,7 +474,11 @@ void syscallcondition_waitNonblock(SysCallCondition* cond, const Host* host, con
             taskref_drop(task);
         }
 
-        timer_arm(cond->timeout, host, cond->timeoutExpiration, 0);
+        CEmulatedTime wakeup = emutime_add_simtime(cond->timeoutExpiration, cond->timeoutSlack);
+        if (wakeup == EMUTIME_INVALID) {
+            wakeup = EMUTIME_MAX;
+        }
+        timer_arm(cond->timeout, host, wakeup, 0);
     }
 
     /* Now set up the listeners. */
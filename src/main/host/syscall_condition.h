@@ -59,6 +59,11 @@ SysCallCondition* syscallcondition_newWithAbsTimeout(CEmulatedTime abs_trigger_t
  * `worker_getCurrentEmulatedTime`. */
 void syscallcondition_setTimeout(SysCallCondition* cond, CEmulatedTime t);
 
+/* Delay the wakeup for the timeout by `slack`, as Linux does for the thread's
+ * timer slack. The condition's timeout is still considered expired at the
+ * time set by `syscallcondition_setTimeout`. */
+void syscallcondition_setTimeoutSlack(SysCallCondition* cond, CSimulationTime slack);
+
 /* Add a file to the condition which can be used in the syscall handler once it becomes unblocked,
  * without needing to lookup the file again in the descriptor table (since it may no longer exist in
  * the descriptor table). */
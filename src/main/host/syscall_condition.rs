@@ -2,6 +2,7 @@ use std::marker::PhantomData;
 
 use linux_api::signal::Signal;
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 
 use super::host::Host;
 use crate::cshadow;
@@ -84,6 +85,13 @@ impl<'a> SysCallConditionRefMut<'a> {
         let timeout = EmulatedTime::to_c_emutime(timeout);
         unsafe { cshadow::syscallcondition_setTimeout(self.c_ptr, timeout) };
     }
+
+    /// Delay the wakeup for the timeout by `slack`. The timeout is still
+    /// considered expired at the time set by `set_timeout`.
+    pub fn set_timeout_slack(&mut self, slack: SimulationTime) {
+        let slack = SimulationTime::to_c_simtime(Some(slack));
+        unsafe { cshadow::syscallcondition_setTimeoutSlack(self.c_ptr, slack) };
+    }
 }
 
 impl<'a> std::ops::Deref for SysCallConditionRefMut<'a> {
@@ -222,11 +222,6 @@ static void _syscallhandler_post_syscall(SysCallHandler* sys, long number, const
 // Single public API function for calling Shadow syscalls
 ///////////////////////////////////////////////////////////
 
-#define SHIM_ONLY(s)                                                                               \
-    case SYS_##s:                                                                                  \
-        panic("syscall " #s " (#%ld) should have been handled in the shim", args->number);         \
-        break
-
 #define HANDLE_C(s)                                                                                \
     case SYS_##s:                                                                                  \
         _syscallhandler_pre_syscall(sys, args->number, #s);                                        \
@@ -297,10 +292,16 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
         switch (args->number) {
             HANDLE_RUST(accept);
             HANDLE_RUST(accept4);
+            HANDLE_RUST(access);
+            HANDLE_RUST(adjtimex);
+            HANDLE_RUST(arch_prctl);
             HANDLE_RUST(bind);
             HANDLE_RUST(brk);
+            HANDLE_RUST(chmod);
+            HANDLE_RUST(chown);
+            HANDLE_RUST(clock_adjtime);
             HANDLE_RUST(clock_getres);
-            SHIM_ONLY(clock_gettime);
+            HANDLE_RUST(clock_gettime);
             HANDLE_RUST(clock_nanosleep);
             HANDLE_RUST(clone);
 #ifdef SYS_clone3
@@ -328,8 +329,8 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
             HANDLE_C(fallocate);
             HANDLE_C(fchmod);
             HANDLE_C(fchmodat);
-            HANDLE_C(fchown);
-            HANDLE_C(fchownat);
+            HANDLE_RUST(fchown);
+            HANDLE_RUST(fchownat);
             HANDLE_RUST(fcntl);
 #ifdef SYS_fcntl64
             // TODO: is there a nicer way to do this? Rust libc::SYS_fcntl64 does not exist.
@@ -360,6 +361,10 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
             HANDLE_C(futimesat);
             HANDLE_C(getdents);
             HANDLE_C(getdents64);
+            HANDLE_RUST(getegid);
+            HANDLE_RUST(geteuid);
+            HANDLE_RUST(getgid);
+            HANDLE_RUST(getgroups);
             HANDLE_RUST(getitimer);
             HANDLE_RUST(getpeername);
             HANDLE_C(getpid);
@@ -369,30 +374,50 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
             HANDLE_RUST(getsid);
             HANDLE_RUST(gettid);
             HANDLE_RUST(getrandom);
+            HANDLE_RUST(getresgid);
+            HANDLE_RUST(getresuid);
+            HANDLE_RUST(getrusage);
             HANDLE_C(get_robust_list);
             HANDLE_RUST(getsockname);
             HANDLE_RUST(getsockopt);
-            SHIM_ONLY(gettimeofday);
+            HANDLE_RUST(gettimeofday);
+            HANDLE_RUST(getuid);
             HANDLE_RUST(ioctl);
             HANDLE_C(kill);
+            HANDLE_RUST(lchown);
+            HANDLE_RUST(link);
             HANDLE_C(linkat);
             HANDLE_RUST(listen);
             HANDLE_C(lseek);
+            HANDLE_RUST(lstat);
+            HANDLE_RUST(madvise);
+            HANDLE_RUST(mkdir);
             HANDLE_C(mkdirat);
+            HANDLE_RUST(mknod);
             HANDLE_C(mknodat);
             HANDLE_RUST(mmap);
             HANDLE_RUST(mprotect);
             HANDLE_RUST(mremap);
+            HANDLE_RUST(msync);
             HANDLE_RUST(munmap);
             HANDLE_RUST(nanosleep);
             HANDLE_C(newfstatat);
             HANDLE_RUST(open);
             HANDLE_RUST(openat);
+#ifdef SYS_pidfd_getfd
+            HANDLE_RUST(pidfd_getfd);
+#endif
+#ifdef SYS_pidfd_open
+            HANDLE_RUST(pidfd_open);
+#endif
+#ifdef SYS_pidfd_send_signal
+            HANDLE_RUST(pidfd_send_signal);
+#endif
             HANDLE_RUST(pipe);
             HANDLE_RUST(pipe2);
             HANDLE_C(poll);
             HANDLE_C(ppoll);
-            HANDLE_C(prctl);
+            HANDLE_RUST(prctl);
             HANDLE_RUST(pread64);
             HANDLE_RUST(preadv);
 #ifdef SYS_preadv2
@@ -412,25 +437,38 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
 #endif
             HANDLE_RUST(read);
             HANDLE_C(readahead);
+            HANDLE_RUST(readlink);
             HANDLE_C(readlinkat);
             HANDLE_RUST(readv);
             HANDLE_RUST(recvfrom);
             HANDLE_RUST(recvmsg);
+            HANDLE_RUST(rename);
             HANDLE_C(renameat);
             HANDLE_C(renameat2);
+            HANDLE_RUST(rmdir);
             HANDLE_RUST(rseq);
             HANDLE_RUST(sched_getaffinity);
             HANDLE_RUST(sched_setaffinity);
-            SHIM_ONLY(sched_yield);
+            HANDLE_RUST(sched_yield);
             HANDLE_C(shadow_hostname_to_addr_ipv4);
+            HANDLE_C(shadow_hostname_to_addr_ipv6);
             HANDLE_C(shadow_init_memory_manager);
             HANDLE_C(shadow_yield);
             HANDLE_C(select);
             HANDLE_RUST(sendmsg);
             HANDLE_RUST(sendto);
+            HANDLE_RUST(setfsgid);
+            HANDLE_RUST(setfsuid);
+            HANDLE_RUST(setgid);
+            HANDLE_RUST(setgroups);
             HANDLE_RUST(setpgid);
+            HANDLE_RUST(setregid);
+            HANDLE_RUST(setresgid);
+            HANDLE_RUST(setresuid);
+            HANDLE_RUST(setreuid);
             HANDLE_RUST(setsid);
             HANDLE_RUST(setsockopt);
+            HANDLE_RUST(setuid);
 #ifdef SYS_sigaction
             // Superseded by rt_sigaction in Linux 2.2
             UNSUPPORTED(sigaction);
@@ -452,20 +490,25 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
             HANDLE_RUST(shutdown);
             HANDLE_RUST(socket);
             HANDLE_RUST(socketpair);
+            HANDLE_RUST(splice);
+            HANDLE_RUST(stat);
 #ifdef SYS_statx
             HANDLE_C(statx);
 #endif
+            HANDLE_RUST(symlink);
             HANDLE_C(symlinkat);
             HANDLE_C(sync_file_range);
             HANDLE_C(syncfs);
             HANDLE_RUST(sysinfo);
+            HANDLE_RUST(tee);
             HANDLE_C(tgkill);
-            SHIM_ONLY(time);
+            HANDLE_RUST(time);
             HANDLE_RUST(timerfd_create);
             HANDLE_RUST(timerfd_gettime);
             HANDLE_RUST(timerfd_settime);
             HANDLE_C(tkill);
-            HANDLE_C(uname);
+            HANDLE_RUST(uname);
+            HANDLE_RUST(unlink);
             HANDLE_C(unlinkat);
             HANDLE_C(utimensat);
             HANDLE_RUST(vfork);
@@ -484,14 +527,11 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
             UNSUPPORTED(fchdir);
 
             UNSUPPORTED(io_getevents);
-            UNSUPPORTED(msync);
 
             // copying data between various types of fds
             UNSUPPORTED(copy_file_range);
             UNSUPPORTED(sendfile);
-            UNSUPPORTED(splice);
             UNSUPPORTED(vmsplice);
-            UNSUPPORTED(tee);
 
             //// additional socket io
             UNSUPPORTED(recvmmsg);
@@ -501,55 +541,24 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
             // We think we don't need to handle these
             // (because the plugin can natively):
             // ***************************************
-            NATIVE(access);
-            NATIVE(arch_prctl);
-            NATIVE(chmod);
-            NATIVE(chown);
             NATIVE(exit);
             NATIVE(getcwd);
-            NATIVE(geteuid);
-            NATIVE(getegid);
-            NATIVE(getgid);
-            NATIVE(getgroups);
-            NATIVE(getresgid);
-            NATIVE(getresuid);
             NATIVE(getrlimit);
-            NATIVE(getuid);
             NATIVE(getxattr);
-            NATIVE(lchown);
             NATIVE(lgetxattr);
-            NATIVE(link);
             NATIVE(listxattr);
             NATIVE(llistxattr);
             NATIVE(lremovexattr);
             NATIVE(lsetxattr);
-            NATIVE(lstat);
-            NATIVE(madvise);
-            NATIVE(mkdir);
-            NATIVE(mknod);
-            NATIVE(readlink);
             NATIVE(removexattr);
-            NATIVE(rename);
-            NATIVE(rmdir);
             NATIVE(rt_sigreturn);
-            NATIVE(setfsgid);
-            NATIVE(setfsuid);
-            NATIVE(setgid);
-            NATIVE(setregid);
-            NATIVE(setresgid);
-            NATIVE(setresuid);
-            NATIVE(setreuid);
             NATIVE(setrlimit);
-            NATIVE(setuid);
             NATIVE(setxattr);
-            NATIVE(stat);
 #ifdef SYS_stat64
             NATIVE(stat64);
 #endif
             NATIVE(statfs);
-            NATIVE(symlink);
             NATIVE(truncate);
-            NATIVE(unlink);
             NATIVE(utime);
             NATIVE(utimes);
 
@@ -579,9 +588,9 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
     }
 
     // If the syscall would be blocked, but there's a signal pending, fail with
-    // EINTR instead. The shim-side code will run the signal handlers and then
-    // either return the EINTR or restart the syscall (See SA_RESTART in
-    // signal(7)).
+    // EINTR instead. The shim-side code (or the kernel, for processes without
+    // the shim) will run the signal handlers and then either return the EINTR
+    // or restart the syscall (See SA_RESTART in signal(7)).
     //
     // We do this check *after* (not before) trying the syscall so that we don't
     // "interrupt" a syscall that wouldn't have blocked in the first place, or
@@ -618,18 +627,34 @@ SyscallReturn syscallhandler_make_syscall(SysCallHandler* sys, const SysCallArgs
         }
     }
 
-    if (shimshmem_getModelUnblockedSyscallLatency(host_getSharedMem(host)) &&
+    // Syscalls with a configured latency of their own always have it applied, even if we aren't
+    // modelling the latency of other unblocked syscalls.
+    CSimulationTime syscallLatency = host_paramsSyscallLatency(host, args->number);
+    if ((syscallLatency != SIMTIME_INVALID ||
+         shimshmem_getModelUnblockedSyscallLatency(host_getSharedMem(host))) &&
         process_isRunning(process) &&
         (scr.tag == SYSCALL_RETURN_DONE || scr.tag == SYSCALL_RETURN_NATIVE)) {
         CSimulationTime maxUnappliedCpuLatency =
             shimshmem_maxUnappliedCpuLatency(host_getSharedMem(host));
+        if (syscallLatency == SIMTIME_INVALID) {
+            switch (args->number) {
+                case SYS_clock_gettime:
+                case SYS_time:
+                case SYS_gettimeofday:
+                case SYS_getcpu:
+                    // Only processes without the shim make these syscalls through Shadow, in
+                    // place of the vdso calls that the shim would have handled.
+                    syscallLatency = shimshmem_unblockedVdsoLatency(host_getSharedMem(host));
+                    break;
+                default:
+                    syscallLatency = shimshmem_unblockedSyscallLatency(host_getSharedMem(host));
+            }
+        }
         // Increment unblocked syscall latency, but only for
         // non-shadow-syscalls, since the latter are part of Shadow's
         // internal plumbing; they shouldn't necessarily "consume" time.
         if (!syscall_num_is_shadow(args->number)) {
-            shimshmem_incrementUnappliedCpuLatency(
-                host_getShimShmemLock(host),
-                shimshmem_unblockedSyscallLatency(host_getSharedMem(host)));
+            shimshmem_incrementUnappliedCpuLatency(host_getShimShmemLock(host), syscallLatency);
         }
         const CSimulationTime unappliedCpuLatency =
             shimshmem_getUnappliedCpuLatency(host_getShimShmemLock(host));
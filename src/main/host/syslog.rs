@@ -0,0 +1,142 @@
+//! A per-host syslog service that accepts messages on the `/dev/log` unix datagram socket.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use atomic_refcell::AtomicRefCell;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+use crate::core::worker::Worker;
+use crate::host::descriptor::socket::unix::{UnixSocket, UnixSocketType};
+use crate::host::descriptor::{FileState, FileStatus, StateListenerFilter};
+use crate::host::network::namespace::NetworkNamespace;
+use crate::utility::callback_queue::{CallbackQueue, Handle};
+use crate::utility::sockaddr::SockaddrUnix;
+
+/// The pathname address that managed processes use to send syslog messages.
+pub const SYSLOG_PATH: &[u8] = b"/dev/log";
+
+/// Shadow doesn't support unix sockets with pathname addresses, so the syslog socket is bound to
+/// this abstract address instead, and connections to [`SYSLOG_PATH`] are redirected to it.
+pub const SYSLOG_ABSTRACT_NAME: &[u8] = b"shadow-syslog:/dev/log";
+
+/// Receives syslog messages sent to the host's `/dev/log` socket and writes them to a file, each
+/// prefixed with the simulated time at which the message was received.
+pub struct SyslogSink {
+    socket: Arc<AtomicRefCell<UnixSocket>>,
+    writer: Arc<Mutex<std::io::BufWriter<std::fs::File>>>,
+    _handle: Handle<(FileState, FileState)>,
+}
+
+impl SyslogSink {
+    /// Create a new syslog socket in the network namespace, writing messages to a new file at
+    /// `path`.
+    pub fn new(
+        net_ns: &NetworkNamespace,
+        path: &Path,
+        rng: impl rand::Rng,
+    ) -> std::io::Result<Self> {
+        let file = std::fs::File::create(path)?;
+        let writer = Arc::new(Mutex::new(std::io::BufWriter::new(file)));
+
+        let socket = UnixSocket::new(FileStatus::empty(), UnixSocketType::Dgram, &net_ns.unix);
+
+        let addr = SockaddrUnix::new_abstract(SYSLOG_ABSTRACT_NAME).unwrap();
+        UnixSocket::bind(&socket, Some(&addr.into()), net_ns, rng).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("Could not bind the syslog socket: {e:?}"),
+            )
+        })?;
+
+        let handle = {
+            let weak_socket = Arc::downgrade(&socket);
+            let writer = Arc::clone(&writer);
+            socket.borrow_mut().add_listener(
+                FileState::READABLE,
+                StateListenerFilter::OffToOn,
+                move |_state, _changed, cb_queue| {
+                    if let Some(socket) = weak_socket.upgrade() {
+                        Self::drain(&socket, &writer, cb_queue);
+                    }
+                },
+            )
+        };
+
+        Ok(Self {
+            socket,
+            writer,
+            _handle: handle,
+        })
+    }
+
+    /// Read all available messages from the socket and write them to the file.
+    fn drain(
+        socket: &Arc<AtomicRefCell<UnixSocket>>,
+        writer: &Mutex<std::io::BufWriter<std::fs::File>>,
+        cb_queue: &mut CallbackQueue,
+    ) {
+        let now = Worker::current_time().unwrap();
+        let now = now.duration_since(&EmulatedTime::UNIX_EPOCH);
+
+        let mut writer = writer.lock().unwrap();
+        let mut socket = socket.borrow_mut();
+
+        while let Some(msg) = socket.recv_internal(cb_queue) {
+            if let Err(e) = write_message(&mut *writer, now.as_secs(), now.subsec_micros(), &msg) {
+                log::warn!("Unable to write syslog message: {e}");
+            }
+        }
+    }
+
+    /// Close the socket, removing it from the network namespace, and flush the file.
+    pub fn close(&self) {
+        CallbackQueue::queue_and_run(|cb_queue| {
+            if let Err(e) = self.socket.borrow_mut().close(cb_queue) {
+                log::warn!("Unable to close the syslog socket: {e:?}");
+            }
+        });
+
+        if let Err(e) = self.writer.lock().unwrap().flush() {
+            log::warn!("Unable to flush the syslog file: {e}");
+        }
+    }
+}
+
+/// Write a single message on its own line, prefixed with the timestamp. Trailing newlines and nul
+/// bytes are removed from the message.
+fn write_message(
+    mut writer: impl Write,
+    secs: u64,
+    micros: u32,
+    msg: &[u8],
+) -> std::io::Result<()> {
+    let end = msg
+        .iter()
+        .rposition(|x| !matches!(x, b'\n' | b'\0'))
+        .map(|x| x + 1)
+        .unwrap_or(0);
+
+    write!(writer, "{secs}.{micros:06} ")?;
+    writer.write_all(&msg[..end])?;
+    writer.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_message() {
+        let mut out = Vec::new();
+        write_message(&mut out, 946684800, 1234, b"<13>app: hello\n\0").unwrap();
+        write_message(&mut out, 946684801, 0, b"").unwrap();
+        write_message(&mut out, 946684802, 999999, b"<13>app: world").unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            "946684800.001234 <13>app: hello\n946684801.000000 \n946684802.999999 <13>app: world\n"
+        );
+    }
+}
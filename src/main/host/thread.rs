@@ -9,6 +9,7 @@ use shadow_shim_helper_rs::explicit_drop::ExplicitDrop;
 use shadow_shim_helper_rs::rootedcell::rc::RootedRc;
 use shadow_shim_helper_rs::rootedcell::refcell::RootedRefCell;
 use shadow_shim_helper_rs::shim_shmem::{HostShmemProtected, ThreadShmem};
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
 use shadow_shim_helper_rs::syscall_types::{ForeignPtr, SysCallReg};
 use shadow_shim_helper_rs::util::SendPointer;
 use shadow_shim_helper_rs::HostId;
@@ -45,6 +46,13 @@ pub struct Thread {
     // If non-NULL, this address should be cleared and futex-awoken on thread exit.
     // See set_tid_address(2).
     tid_address: Cell<ForeignPtr<libc::pid_t>>,
+    // Amount by which timer expirations may be delayed. See `PR_SET_TIMERSLACK` in prctl(2).
+    timer_slack: Cell<SimulationTime>,
+    // The timer slack restored when setting a timer slack of 0. This is the
+    // parent thread's timer slack at the time this thread was created.
+    default_timer_slack: SimulationTime,
+    // Time this thread has spent on the simulated CPU, as reported by `getrusage(RUSAGE_THREAD)`.
+    cpu_time: Cell<SimulationTime>,
     shim_shared_memory: ShMemBlock<'static, ThreadShmem>,
     syscallhandler: SendPointer<c::SysCallHandler>,
     /// Descriptor table; potentially shared with other threads and processes.
@@ -129,7 +137,7 @@ impl Thread {
                 .iter()
                 .filter_map(|(handle, descriptor)| {
                     if descriptor.flags().contains(DescriptorFlags::FD_CLOEXEC) {
-                        Some(*handle)
+                        Some(handle)
                     } else {
                         None
                     }
@@ -356,6 +364,26 @@ impl Thread {
         Ok(())
     }
 
+    /// Natively execute madvise(2) on the given thread.
+    pub fn native_madvise(
+        &self,
+        ctx: &ProcessContext,
+        addr: ForeignPtr<u8>,
+        len: usize,
+        advice: i32,
+    ) -> Result<(), Errno> {
+        self.native_syscall(
+            ctx,
+            libc::SYS_madvise,
+            &[
+                SysCallReg::from(addr),
+                SysCallReg::from(len),
+                SysCallReg::from(advice),
+            ],
+        )?;
+        Ok(())
+    }
+
     /// Natively execute open(2) on the given thread.
     pub fn native_open(
         &self,
@@ -423,13 +451,15 @@ impl Thread {
     }
 
     /// Create a new `Thread`, wrapping `mthread`. Intended for use by
-    /// syscall handlers such as `clone`.
+    /// syscall handlers such as `clone`. The thread's timer slack starts as
+    /// `timer_slack`, which is also its default timer slack.
     pub fn wrap_mthread(
         host: &Host,
         mthread: ManagedThread,
         desc_table: RootedRc<RootedRefCell<DescriptorTable>>,
         pid: ProcessId,
         tid: ThreadId,
+        timer_slack: SimulationTime,
     ) -> Result<Thread, Errno> {
         let child = Self {
             mthread: RefCell::new(mthread),
@@ -441,6 +471,9 @@ impl Thread {
             host_id: host.id(),
             process_id: pid,
             tid_address: Cell::new(ForeignPtr::null()),
+            timer_slack: Cell::new(timer_slack),
+            default_timer_slack: timer_slack,
+            cpu_time: Cell::new(SimulationTime::ZERO),
             shim_shared_memory: shmalloc(ThreadShmem::new(
                 &host.shim_shmem_lock_borrow().unwrap(),
                 tid.into(),
@@ -511,6 +544,37 @@ impl Thread {
         self.tid_address.set(ptr)
     }
 
+    pub fn timer_slack(&self) -> SimulationTime {
+        self.timer_slack.get()
+    }
+
+    /// Sets the timer slack as for `prctl(PR_SET_TIMERSLACK)`. A slack of 0
+    /// restores the thread's default timer slack.
+    pub fn set_timer_slack(&self, slack: SimulationTime) {
+        if slack.is_zero() {
+            self.timer_slack.set(self.default_timer_slack);
+        } else {
+            self.timer_slack.set(slack);
+        }
+    }
+
+    /// Time this thread has spent on the simulated CPU.
+    pub fn cpu_time(&self) -> SimulationTime {
+        self.cpu_time.get()
+    }
+
+    pub fn add_cpu_time(&self, time: SimulationTime) {
+        self.cpu_time.set(self.cpu_time.get() + time);
+    }
+
+    /// The timer slack of a `poll`, `select`, or `epoll_wait` timeout. As in
+    /// Linux's `select_estimate_accuracy`, this is 0.1% of the timeout, capped
+    /// at 100 ms, but no less than the thread's timer slack.
+    pub fn poll_timer_slack(&self, timeout: SimulationTime) -> SimulationTime {
+        let slack = std::cmp::min(timeout / 1000, SimulationTime::from_millis(100));
+        std::cmp::max(slack, self.timer_slack())
+    }
+
     pub fn unblocked_signal_pending(
         &self,
         process: &Process,
@@ -589,6 +653,7 @@ impl std::fmt::Display for ThreadId {
 
 mod export {
     use shadow_shim_helper_rs::shim_shmem::export::{ShimShmemHostLock, ShimShmemThread};
+    use shadow_shim_helper_rs::simulation_time::CSimulationTime;
     use shadow_shim_helper_rs::syscall_types::UntypedForeignPtr;
 
     use super::*;
@@ -638,6 +703,31 @@ mod export {
         .unwrap()
     }
 
+    /// Whether the plugin loaded the shim. `thread_nativeSyscall` is unsupported in
+    /// threads that didn't.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn thread_hasShim(thread: *const Thread) -> bool {
+        let thread = unsafe { thread.as_ref().unwrap() };
+        thread.mthread().has_shim()
+    }
+
+    /// Copy Shadow's descriptor `fd` into a plugin that didn't load the shim, at
+    /// descriptor number `target_fd`, while the thread waits for its current syscall.
+    ///
+    /// Returns 0 on success or a negative errno.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn thread_installNativeFd(
+        thread: *const Thread,
+        fd: libc::c_int,
+        target_fd: libc::c_int,
+    ) -> libc::c_int {
+        let thread = unsafe { thread.as_ref().unwrap() };
+        match thread.mthread().install_native_fd(fd, target_fd) {
+            Ok(()) => 0,
+            Err(e) => -(e as libc::c_int),
+        }
+    }
+
     #[no_mangle]
     pub unsafe extern "C-unwind" fn thread_getID(thread: *const Thread) -> libc::pid_t {
         let thread = unsafe { thread.as_ref().unwrap() };
@@ -664,6 +754,24 @@ mod export {
         thread.get_tid_address().cast::<()>()
     }
 
+    /// Gets the timer slack, as for `prctl(PR_GET_TIMERSLACK)`.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn thread_getTimerSlack(thread: *const Thread) -> CSimulationTime {
+        let thread = unsafe { thread.as_ref().unwrap() };
+        SimulationTime::to_c_simtime(Some(thread.timer_slack()))
+    }
+
+    /// Gets the timer slack of a `poll` or `select` timeout of length `timeout`.
+    #[no_mangle]
+    pub unsafe extern "C-unwind" fn thread_getPollTimerSlack(
+        thread: *const Thread,
+        timeout: CSimulationTime,
+    ) -> CSimulationTime {
+        let thread = unsafe { thread.as_ref().unwrap() };
+        let timeout = SimulationTime::from_c_simtime(timeout).unwrap();
+        SimulationTime::to_c_simtime(Some(thread.poll_timer_slack(timeout)))
+    }
+
     /// Returns a typed pointer to memory shared with the shim (which is backed by
     /// the block returned by thread_getShMBlock).
     #[no_mangle]
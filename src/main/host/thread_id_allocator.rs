@@ -0,0 +1,121 @@
+//! Allocation of thread IDs, which are also used as process IDs.
+//!
+//! Each process in a host's configuration is assigned a "slot", and all threads and child
+//! processes that descend from that process get IDs from a range reserved for the slot. This way
+//! the IDs seen by a process don't depend on how many threads or processes were created by the
+//! host's other processes, or on the order in which processes on the host start.
+
+use crate::host::thread::ThreadId;
+
+/// The first ID of the first slot.
+const FIRST_ID: libc::pid_t = 1000;
+
+/// The number of IDs reserved for each slot.
+const IDS_PER_SLOT: libc::pid_t = 10_000;
+
+/// The number of slots with reserved IDs. Processes in later slots, and processes whose slot has
+/// run out of IDs, get IDs from a range shared by all slots.
+const NUM_SLOTS: usize = 400;
+
+/// Linux's largest possible `pid_max` on 64-bit systems. Some applications assume that IDs are
+/// smaller than this.
+const MAX_ID: libc::pid_t = 4_194_304;
+
+#[derive(Debug)]
+pub struct ThreadIdAllocator {
+    /// The next unused ID of each slot.
+    next_in_slot: Vec<libc::pid_t>,
+    /// The next unused ID of the shared range.
+    next_shared: libc::pid_t,
+}
+
+impl ThreadIdAllocator {
+    pub fn new() -> Self {
+        Self {
+            next_in_slot: (0..NUM_SLOTS).map(Self::slot_start).collect(),
+            next_shared: Self::slot_start(NUM_SLOTS),
+        }
+    }
+
+    fn slot_start(slot: usize) -> libc::pid_t {
+        FIRST_ID + libc::pid_t::try_from(slot).unwrap() * IDS_PER_SLOT
+    }
+
+    /// Get a new ID for a thread of a process that descends from the process in slot `slot`.
+    /// Returns `None` if there are no IDs left, in which case `clone` and `fork` should fail with
+    /// `EAGAIN` like they do when Linux runs out of PIDs.
+    pub fn allocate(&mut self, slot: usize) -> Option<ThreadId> {
+        if let Some(next) = self.next_in_slot.get_mut(slot) {
+            if *next < Self::slot_start(slot + 1) {
+                let id = *next;
+                *next += 1;
+                return Some(id.try_into().unwrap());
+            }
+        }
+
+        let id = self.next_shared;
+        if id >= MAX_ID {
+            return None;
+        }
+        self.next_shared += 1;
+        Some(id.try_into().unwrap())
+    }
+}
+
+impl Default for ThreadIdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(x: libc::pid_t) -> ThreadId {
+        x.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_slots_are_independent() {
+        let mut alloc = ThreadIdAllocator::new();
+
+        assert_eq!(alloc.allocate(0), Some(id(1000)));
+        assert_eq!(alloc.allocate(2), Some(id(21000)));
+        assert_eq!(alloc.allocate(0), Some(id(1001)));
+        assert_eq!(alloc.allocate(1), Some(id(11000)));
+        assert_eq!(alloc.allocate(2), Some(id(21001)));
+    }
+
+    #[test]
+    fn test_shared_range() {
+        let mut alloc = ThreadIdAllocator::new();
+        let shared_start = 1000 + 400 * IDS_PER_SLOT;
+
+        // slots without reserved IDs use the shared range
+        assert_eq!(alloc.allocate(NUM_SLOTS), Some(id(shared_start)));
+        assert_eq!(alloc.allocate(NUM_SLOTS + 7), Some(id(shared_start + 1)));
+
+        // an exhausted slot also uses the shared range
+        for i in 0..IDS_PER_SLOT {
+            assert_eq!(alloc.allocate(1), Some(id(11000 + i)));
+        }
+        assert_eq!(alloc.allocate(1), Some(id(shared_start + 2)));
+        assert_eq!(alloc.allocate(2), Some(id(21000)));
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let mut alloc = ThreadIdAllocator::new();
+        let shared_start = 1000 + 400 * IDS_PER_SLOT;
+
+        for i in shared_start..MAX_ID {
+            assert_eq!(alloc.allocate(NUM_SLOTS), Some(id(i)));
+        }
+        assert_eq!(alloc.allocate(NUM_SLOTS), None);
+        assert_eq!(alloc.allocate(NUM_SLOTS), None);
+
+        // slots with reserved IDs left can still allocate
+        assert_eq!(alloc.allocate(0), Some(id(1000)));
+    }
+}
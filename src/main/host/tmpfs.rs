@@ -0,0 +1,189 @@
+//! A per-host tmpfs that is mounted at the host's `/dev/shm` directory.
+//!
+//! The tmpfs's files are stored in a directory within the host's data directory, so each host has
+//! its own `/dev/shm` rather than sharing the real one. The tmpfs tracks the sizes of its files so
+//! that writes which would grow it past its size limit fail with `ENOSPC`, and so that its files
+//! count against the host's memory.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+
+use crate::utility;
+
+/// The directory where the tmpfs is mounted in the host's file system.
+pub const MOUNT_POINT: &str = "/dev/shm";
+
+/// Identifies a file by its device and inode numbers.
+pub type FileId = (u64, u64);
+
+#[derive(Debug)]
+struct TmpfsFile {
+    /// The apparent size of the file in bytes.
+    size: u64,
+    /// The number of open file descriptions that refer to the file.
+    open_count: u32,
+}
+
+/// Tracks the sizes of the files in a host's tmpfs.
+#[derive(Debug)]
+pub struct Tmpfs {
+    /// The directory that stores the tmpfs's files.
+    dir: PathBuf,
+    dir_cstring: CString,
+    size_limit: u64,
+    /// The files that are linked into the tmpfs or are still open.
+    files: HashMap<FileId, TmpfsFile>,
+    /// The total size of all files.
+    usage: u64,
+}
+
+impl Tmpfs {
+    pub fn new(dir: PathBuf, size_limit: u64) -> Self {
+        Self {
+            dir_cstring: utility::pathbuf_to_nul_term_cstring(dir.clone()),
+            dir,
+            size_limit,
+            files: HashMap::new(),
+            usage: 0,
+        }
+    }
+
+    /// The directory that stores the tmpfs's files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn dir_cstring(&self) -> &CString {
+        &self.dir_cstring
+    }
+
+    pub fn size_limit(&self) -> u64 {
+        self.size_limit
+    }
+
+    /// The total size of the files in the tmpfs, including files that were unlinked but are still
+    /// open.
+    pub fn usage(&self) -> u64 {
+        self.usage
+    }
+
+    /// Whether the file can be resized to `size` bytes without exceeding the size limit. Shrinking
+    /// a file is always allowed, even if the tmpfs is already over its limit.
+    pub fn can_resize(&self, id: FileId, size: u64) -> bool {
+        let old_size = self.files.get(&id).map(|x| x.size).unwrap_or(0);
+        size <= old_size || self.usage - old_size + size <= self.size_limit
+    }
+
+    /// Record that a file in the tmpfs was opened.
+    pub fn file_opened(&mut self, id: FileId, size: u64) {
+        let file = self.files.entry(id).or_insert(TmpfsFile {
+            size: 0,
+            open_count: 0,
+        });
+        file.open_count += 1;
+        self.usage = self.usage - file.size + size;
+        file.size = size;
+    }
+
+    /// Record the new size of a file in the tmpfs.
+    pub fn file_resized(&mut self, id: FileId, size: u64) {
+        if let Some(file) = self.files.get_mut(&id) {
+            self.usage = self.usage - file.size + size;
+            file.size = size;
+        }
+    }
+
+    /// Record that an open file description of a file in the tmpfs was closed, where `nlink` is
+    /// the number of remaining hard links to the file.
+    pub fn file_closed(&mut self, id: FileId, nlink: u64) {
+        let Some(file) = self.files.get_mut(&id) else {
+            return;
+        };
+        file.open_count = file.open_count.checked_sub(1).unwrap();
+        self.remove_if_unused(id, nlink);
+    }
+
+    /// Record that a hard link to a file was removed, where `nlink` is the number of remaining hard
+    /// links to the file. Files that aren't in the tmpfs are ignored.
+    pub fn file_unlinked(&mut self, id: FileId, nlink: u64) {
+        if self.files.contains_key(&id) {
+            self.remove_if_unused(id, nlink);
+        }
+    }
+
+    /// Like Linux, a file's memory is only freed once it has no hard links and isn't open.
+    fn remove_if_unused(&mut self, id: FileId, nlink: u64) {
+        if nlink == 0 && self.files[&id].open_count == 0 {
+            let file = self.files.remove(&id).unwrap();
+            self.usage -= file.size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_limit() {
+        let mut tmpfs = Tmpfs::new(PathBuf::from("/tmp/shm"), 100);
+        let (a, b) = ((1, 1), (1, 2));
+
+        tmpfs.file_opened(a, 0);
+        assert!(tmpfs.can_resize(a, 100));
+        assert!(!tmpfs.can_resize(a, 101));
+        tmpfs.file_resized(a, 60);
+
+        tmpfs.file_opened(b, 0);
+        assert!(tmpfs.can_resize(b, 40));
+        assert!(!tmpfs.can_resize(b, 41));
+        tmpfs.file_resized(b, 40);
+        assert_eq!(tmpfs.usage(), 100);
+
+        // files can always shrink
+        assert!(tmpfs.can_resize(a, 10));
+        tmpfs.file_resized(a, 10);
+        assert_eq!(tmpfs.usage(), 50);
+        assert!(tmpfs.can_resize(b, 90));
+    }
+
+    #[test]
+    fn test_unlinked_while_open() {
+        let mut tmpfs = Tmpfs::new(PathBuf::from("/tmp/shm"), 100);
+        let a = (1, 1);
+
+        // opened twice
+        tmpfs.file_opened(a, 0);
+        tmpfs.file_opened(a, 0);
+        tmpfs.file_resized(a, 70);
+
+        // the memory isn't freed until the file has no links and isn't open
+        tmpfs.file_unlinked(a, 0);
+        assert_eq!(tmpfs.usage(), 70);
+        tmpfs.file_closed(a, 0);
+        assert_eq!(tmpfs.usage(), 70);
+        tmpfs.file_closed(a, 0);
+        assert_eq!(tmpfs.usage(), 0);
+    }
+
+    #[test]
+    fn test_closed_while_linked() {
+        let mut tmpfs = Tmpfs::new(PathBuf::from("/tmp/shm"), 100);
+        let a = (1, 1);
+
+        tmpfs.file_opened(a, 30);
+        tmpfs.file_closed(a, 1);
+        assert_eq!(tmpfs.usage(), 30);
+
+        // reopening the file doesn't count it twice
+        tmpfs.file_opened(a, 30);
+        tmpfs.file_closed(a, 1);
+        assert_eq!(tmpfs.usage(), 30);
+
+        // files that aren't in the tmpfs are ignored
+        tmpfs.file_unlinked((2, 1), 0);
+        tmpfs.file_unlinked(a, 0);
+        assert_eq!(tmpfs.usage(), 0);
+    }
+}
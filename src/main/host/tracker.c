@@ -46,15 +46,50 @@ typedef struct {
     Counters outCounters;
 } IFaceCounters;
 
+/* the bytes (headers and payloads) and packets that a host exchanged with a flow or peer */
+typedef struct {
+    gsize recvBytes;
+    gsize sendBytes;
+    gsize recvPackets;
+    gsize sendPackets;
+} TalkerCounters;
+
+/* a flow is identified by its protocol and addresses, from the perspective of the local host.
+ * addresses are in network order */
+typedef struct {
+    ProtocolType type;
+    in_addr_t localIP;
+    in_port_t localPort;
+    in_addr_t peerIP;
+    in_port_t peerPort;
+} FlowKey;
+
+typedef struct {
+    FlowKey key;
+    TalkerCounters counters;
+} FlowStats;
+
+typedef struct {
+    in_addr_t peerIP;
+    TalkerCounters counters;
+} PeerStats;
+
 struct _Tracker {
     /* our personal settings as configured in the shadow xml config file */
     CSimulationTime interval;
+    CSimulationTime sampleInterval;
     LogLevel loglevel;
     LogInfoFlags loginfo;
 
     gboolean didLogNodeHeader;
     gboolean didLogRAMHeader;
     gboolean didLogSocketHeader;
+    gboolean didLogPcapHeader;
+    gboolean didLogSocketSampleHeader;
+    gboolean didLogInterfaceSampleHeader;
+    gboolean didLogTopFlowsHeader;
+    gboolean didLogTopPeersHeader;
+    gboolean didLogMetricHeader;
 
     uint64_t processingTimeTotalNanos;
     uint64_t processingTimeLastIntervalNanos;
@@ -75,6 +110,15 @@ struct _Tracker {
 
     GHashTable* socketStats;
 
+    gsize numPcapErrorsTotal;
+    gsize numPcapErrorsLastInterval;
+
+    /* the number of flows and peers to log at each heartbeat, or 0 if they aren't tracked */
+    guint numTopTalkers;
+    /* the traffic of each flow and peer during the current interval */
+    GHashTable* flowStats;
+    GHashTable* peerStats;
+
     CEmulatedTime lastHeartbeat;
 
     MAGIC_DECLARE;
@@ -125,25 +169,60 @@ static void _socketstats_free(SocketStats* ss) {
     }
 }
 
+static guint _flowkey_hash(gconstpointer ptr) {
+    const FlowKey* key = ptr;
+    guint hash = key->type;
+    hash = hash * 31 + key->localIP;
+    hash = hash * 31 + key->localPort;
+    hash = hash * 31 + key->peerIP;
+    hash = hash * 31 + key->peerPort;
+    return hash;
+}
+
+static gboolean _flowkey_equal(gconstpointer a, gconstpointer b) {
+    const FlowKey* ka = a;
+    const FlowKey* kb = b;
+    return ka->type == kb->type && ka->localIP == kb->localIP && ka->localPort == kb->localPort &&
+           ka->peerIP == kb->peerIP && ka->peerPort == kb->peerPort;
+}
+
 static guintptr _tracker_socketHandle(const CompatSocket* sock) {
     return compatsocket_getCanonicalHandle(sock);
 }
 
-Tracker* tracker_new(const Host* host, CSimulationTime interval, LogLevel loglevel,
-                     LogInfoFlags loginfo) {
+static gboolean _tracker_isSampling(Tracker* tracker) {
+    return tracker->sampleInterval != SIMTIME_INVALID;
+}
+
+/* socket stats are needed for the socket heartbeat messages and for the samples */
+static gboolean _tracker_isTrackingSockets(Tracker* tracker) {
+    return (tracker->loginfo & LOG_INFO_FLAGS_SOCKET) || _tracker_isSampling(tracker);
+}
+
+Tracker* tracker_new(const Host* host, CSimulationTime interval, CSimulationTime sampleInterval,
+                     guint numTopTalkers, LogLevel loglevel, LogInfoFlags loginfo) {
     Tracker* tracker = g_new0(Tracker, 1);
     MAGIC_INIT(tracker);
 
     tracker->interval = interval;
+    tracker->sampleInterval = sampleInterval;
+    tracker->numTopTalkers = numTopTalkers;
     tracker->loglevel = loglevel;
     tracker->loginfo = loginfo;
 
     tracker->allocatedLocations = g_hash_table_new(g_direct_hash, g_direct_equal);
     tracker->socketStats = g_hash_table_new_full(g_int_hash, g_int_equal, NULL, (GDestroyNotify)_socketstats_free);
+    tracker->flowStats = g_hash_table_new_full(_flowkey_hash, _flowkey_equal, NULL, g_free);
+    tracker->peerStats = g_hash_table_new_full(g_direct_hash, g_direct_equal, NULL, g_free);
 
     /* send an alive message, and start periodic heartbeats */
     tracker_heartbeat(tracker, host);
 
+    /* start periodic samples, if enabled */
+    if (_tracker_isSampling(tracker)) {
+        tracker_sample(tracker, host);
+    }
+
     return tracker;
 }
 
@@ -159,6 +238,8 @@ void tracker_free(Tracker* tracker) {
     g_hash_table_foreach(tracker->allocatedLocations, _tracker_freeAllocatedLocations, NULL);
     g_hash_table_destroy(tracker->allocatedLocations);
     g_hash_table_destroy(tracker->socketStats);
+    g_hash_table_destroy(tracker->flowStats);
+    g_hash_table_destroy(tracker->peerStats);
 
     MAGIC_CLEAR(tracker);
     g_free(tracker);
@@ -184,6 +265,14 @@ void tracker_addVirtualProcessingDelay(Tracker* tracker, CSimulationTime delay)
     }
 }
 
+void tracker_addPcapError(Tracker* tracker) {
+    MAGIC_ASSERT(tracker);
+
+    /* always counted, since pcap errors are rare and shouldn't go unnoticed */
+    (tracker->numPcapErrorsTotal)++;
+    (tracker->numPcapErrorsLastInterval)++;
+}
+
 static void _tracker_updateCounters(Counters* c, gsize header, gsize payload,
         PacketDeliveryStatusFlags status) {
     if(!c) {
@@ -217,10 +306,58 @@ static void _tracker_updateCounters(Counters* c, gsize header, gsize payload,
     }
 }
 
-void tracker_addInputBytes(Tracker* tracker, Packet* packet, const CompatSocket* socket) {
+static void _tracker_updateTalkerCounters(TalkerCounters* c, gsize bytes, gboolean isInbound) {
+    if(isInbound) {
+        c->recvBytes += bytes;
+        c->recvPackets++;
+    } else {
+        c->sendBytes += bytes;
+        c->sendPackets++;
+    }
+}
+
+/* count the packet towards its flow and peer for the top talkers report */
+static void _tracker_addTalkerBytes(Tracker* tracker, const Packet* packet, gboolean isInbound) {
+    FlowKey key = {.type = packet_getProtocol(packet)};
+    if(isInbound) {
+        key.localIP = packet_getDestinationIP(packet);
+        key.localPort = packet_getDestinationPort(packet);
+        key.peerIP = packet_getSourceIP(packet);
+        key.peerPort = packet_getSourcePort(packet);
+    } else {
+        key.localIP = packet_getSourceIP(packet);
+        key.localPort = packet_getSourcePort(packet);
+        key.peerIP = packet_getDestinationIP(packet);
+        key.peerPort = packet_getDestinationPort(packet);
+    }
+
+    gsize bytes = packet_getHeaderSize(packet) + packet_getPayloadSize(packet);
+
+    FlowStats* flow = g_hash_table_lookup(tracker->flowStats, &key);
+    if(!flow) {
+        flow = g_new0(FlowStats, 1);
+        flow->key = key;
+        g_hash_table_insert(tracker->flowStats, &flow->key, flow);
+    }
+    _tracker_updateTalkerCounters(&flow->counters, bytes, isInbound);
+
+    PeerStats* peer = g_hash_table_lookup(tracker->peerStats, GUINT_TO_POINTER(key.peerIP));
+    if(!peer) {
+        peer = g_new0(PeerStats, 1);
+        peer->peerIP = key.peerIP;
+        g_hash_table_insert(tracker->peerStats, GUINT_TO_POINTER(key.peerIP), peer);
+    }
+    _tracker_updateTalkerCounters(&peer->counters, bytes, isInbound);
+}
+
+void tracker_addInputBytes(Tracker* tracker, const Packet* packet, const CompatSocket* socket) {
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
+    if(tracker->numTopTalkers > 0) {
+        _tracker_addTalkerBytes(tracker, packet, TRUE);
+    }
+
     if(!(tracker->loginfo & LOG_INFO_FLAGS_NODE) && !(tracker->loginfo & LOG_INFO_FLAGS_SOCKET)) {
         return;
     }
@@ -250,10 +387,14 @@ void tracker_addInputBytes(Tracker* tracker, Packet* packet, const CompatSocket*
     }
 }
 
-void tracker_addOutputBytes(Tracker* tracker, Packet* packet, const CompatSocket* socket) {
+void tracker_addOutputBytes(Tracker* tracker, const Packet* packet, const CompatSocket* socket) {
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
+    if(tracker->numTopTalkers > 0) {
+        _tracker_addTalkerBytes(tracker, packet, FALSE);
+    }
+
     if(!(tracker->loginfo & LOG_INFO_FLAGS_NODE) && !(tracker->loginfo & LOG_INFO_FLAGS_SOCKET)) {
         return;
     }
@@ -316,7 +457,7 @@ void tracker_addSocket(Tracker* tracker, const CompatSocket* socket, ProtocolTyp
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
-    if(tracker->loginfo & LOG_INFO_FLAGS_SOCKET) {
+    if(_tracker_isTrackingSockets(tracker)) {
         SocketStats* ss = _socketstats_new(handle, type, inputBufferSize, outputBufferSize);
         g_hash_table_insert(tracker->socketStats, &(ss->socket), ss);
     }
@@ -327,7 +468,7 @@ void tracker_updateSocketPeer(Tracker* tracker, const CompatSocket* socket, in_a
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
-    if(tracker->loginfo & LOG_INFO_FLAGS_SOCKET) {
+    if(_tracker_isTrackingSockets(tracker)) {
         SocketStats* socket = g_hash_table_lookup(tracker->socketStats, &handle);
         if(socket) {
             socket->peerIP = peerIP;
@@ -360,7 +501,7 @@ void tracker_updateSocketInputBuffer(Tracker* tracker, const CompatSocket* socke
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
-    if(tracker->loginfo & LOG_INFO_FLAGS_SOCKET) {
+    if(_tracker_isTrackingSockets(tracker)) {
         SocketStats* ss = g_hash_table_lookup(tracker->socketStats, &handle);
         if(ss) {
             ss->inputBufferLength = inputBufferLength;
@@ -374,7 +515,7 @@ void tracker_updateSocketOutputBuffer(Tracker* tracker, const CompatSocket* sock
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
-    if(tracker->loginfo & LOG_INFO_FLAGS_SOCKET) {
+    if(_tracker_isTrackingSockets(tracker)) {
         SocketStats* ss = g_hash_table_lookup(tracker->socketStats, &handle);
         if(ss) {
             ss->outputBufferLength = outputBufferLength;
@@ -387,7 +528,7 @@ void tracker_removeSocket(Tracker* tracker, const CompatSocket* socket) {
     MAGIC_ASSERT(tracker);
     guintptr handle = _tracker_socketHandle(socket);
 
-    if(tracker->loginfo & LOG_INFO_FLAGS_SOCKET) {
+    if(_tracker_isTrackingSockets(tracker)) {
         SocketStats* ss = g_hash_table_lookup(tracker->socketStats, &handle);
         if(ss) {
             /* remove after we log the stats we have */
@@ -480,6 +621,16 @@ static void _tracker_logNode(Tracker* tracker, LogLevel level, CSimulationTime i
     g_string_free(buffer, TRUE);
 }
 
+static const gchar* _tracker_getProtocolString(ProtocolType type) {
+    switch (type) {
+        case PTCP: return "TCP";
+        case PUDP: return "UDP";
+        case PICMP: return "ICMP";
+        case PLOCAL: return "LOCAL";
+        default: return "UNKNOWN";
+    }
+}
+
 static void _tracker_logSocket(Tracker* tracker, LogLevel level, CSimulationTime interval) {
     if(!tracker->didLogSocketHeader) {
         tracker->didLogSocketHeader = TRUE;
@@ -530,8 +681,7 @@ static void _tracker_logSocket(Tracker* tracker, LogLevel level, CSimulationTime
                 "%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT";"
                 "%s;%s;%s;%s",
                 ss->socket, /*inet_ntoa((struct in_addr){socket->peerIP})*/
-                ss->type == PTCP ? "TCP" : ss->type == PUDP ? "UDP" :
-                    ss->type == PLOCAL ? "LOCAL" : "UNKNOWN",
+                _tracker_getProtocolString(ss->type),
                 ss->peerHostname, ss->peerPort,
                 ss->inputBufferLength, ss->inputBufferSize,
                 ss->outputBufferLength, ss->outputBufferSize,
@@ -579,6 +729,194 @@ static void _tracker_logRAM(Tracker* tracker, LogLevel level, CSimulationTime in
         tracker->allocatedBytesTotal, numptrs, tracker->numFailedFrees);
 }
 
+static void _tracker_logPcap(Tracker* tracker, LogLevel level, CSimulationTime interval) {
+    guint seconds = (guint) (interval / SIMTIME_ONE_SECOND);
+
+    if(!tracker->didLogPcapHeader) {
+        tracker->didLogPcapHeader = TRUE;
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                "[shadow-heartbeat] [pcap-header] interval-seconds,error-count,total-error-count");
+    }
+
+    logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+        "[shadow-heartbeat] [pcap] %u,%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT,
+        seconds, tracker->numPcapErrorsLastInterval, tracker->numPcapErrorsTotal);
+}
+
+/* sort talkers by their total bytes, then their total packets, in descending order */
+static gint _tracker_compareTalkerCounters(const TalkerCounters* a, const TalkerCounters* b) {
+    gsize aBytes = a->recvBytes + a->sendBytes;
+    gsize bBytes = b->recvBytes + b->sendBytes;
+    if(aBytes != bBytes) {
+        return aBytes > bBytes ? -1 : 1;
+    }
+
+    gsize aPackets = a->recvPackets + a->sendPackets;
+    gsize bPackets = b->recvPackets + b->sendPackets;
+    if(aPackets != bPackets) {
+        return aPackets > bPackets ? -1 : 1;
+    }
+
+    return 0;
+}
+
+/* ties are broken by the addresses so that the report doesn't depend on the hash table's order */
+static gint _tracker_compareFlows(gconstpointer a, gconstpointer b) {
+    const FlowStats* fa = *(const FlowStats**)a;
+    const FlowStats* fb = *(const FlowStats**)b;
+
+    gint cmp = _tracker_compareTalkerCounters(&fa->counters, &fb->counters);
+    if(cmp != 0) {
+        return cmp;
+    }
+
+    if(fa->key.type != fb->key.type) {
+        return fa->key.type < fb->key.type ? -1 : 1;
+    }
+    if(fa->key.localIP != fb->key.localIP) {
+        return ntohl(fa->key.localIP) < ntohl(fb->key.localIP) ? -1 : 1;
+    }
+    if(fa->key.localPort != fb->key.localPort) {
+        return ntohs(fa->key.localPort) < ntohs(fb->key.localPort) ? -1 : 1;
+    }
+    if(fa->key.peerIP != fb->key.peerIP) {
+        return ntohl(fa->key.peerIP) < ntohl(fb->key.peerIP) ? -1 : 1;
+    }
+    if(fa->key.peerPort != fb->key.peerPort) {
+        return ntohs(fa->key.peerPort) < ntohs(fb->key.peerPort) ? -1 : 1;
+    }
+    return 0;
+}
+
+static gint _tracker_comparePeers(gconstpointer a, gconstpointer b) {
+    const PeerStats* pa = *(const PeerStats**)a;
+    const PeerStats* pb = *(const PeerStats**)b;
+
+    gint cmp = _tracker_compareTalkerCounters(&pa->counters, &pb->counters);
+    if(cmp != 0) {
+        return cmp;
+    }
+
+    if(pa->peerIP != pb->peerIP) {
+        return ntohl(pa->peerIP) < ntohl(pb->peerIP) ? -1 : 1;
+    }
+    return 0;
+}
+
+/* the values of the hash table, sorted with the comparison function */
+static GPtrArray* _tracker_sortedValues(GHashTable* table, GCompareFunc compare) {
+    GPtrArray* values = g_ptr_array_sized_new(g_hash_table_size(table));
+
+    gpointer value = NULL;
+    GHashTableIter iter;
+    g_hash_table_iter_init(&iter, table);
+    while(g_hash_table_iter_next(&iter, NULL, &value)) {
+        g_ptr_array_add(values, value);
+    }
+
+    g_ptr_array_sort(values, compare);
+    return values;
+}
+
+static gchar* _tracker_getPeerName(in_addr_t peerIP) {
+    if(peerIP == htonl(INADDR_LOOPBACK)) {
+        return g_strdup("localhost");
+    }
+
+    const Address* address = worker_resolveIPToAddress(peerIP);
+    const gchar* hostname = address ? address_toHostName(address) : NULL;
+    return g_strdup(hostname ? hostname : "UNKNOWN");
+}
+
+static void _tracker_logTopTalkers(Tracker* tracker, LogLevel level) {
+    if(!tracker->didLogTopFlowsHeader) {
+        tracker->didLogTopFlowsHeader = TRUE;
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                "[shadow-heartbeat] [top-flows-header] protocol-string,local-ip:port,peer-ip:port;"
+                "recv-bytes,send-bytes,recv-packets,send-packets|..."); // for each flow
+    }
+    if(!tracker->didLogTopPeersHeader) {
+        tracker->didLogTopPeersHeader = TRUE;
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                "[shadow-heartbeat] [top-peers-header] hostname-peer,peer-ip;"
+                "recv-bytes,send-bytes,recv-packets,send-packets|..."); // for each peer
+    }
+
+    GPtrArray* flows = _tracker_sortedValues(tracker->flowStats, _tracker_compareFlows);
+    guint numFlows = MIN(flows->len, tracker->numTopTalkers);
+
+    if(numFlows > 0) {
+        GString* msg = g_string_new("[shadow-heartbeat] [top-flows] ");
+
+        for(guint i = 0; i < numFlows; i++) {
+            const FlowStats* flow = g_ptr_array_index(flows, i);
+            gchar* localIP = address_ipToNewString(flow->key.localIP);
+            gchar* peerIP = address_ipToNewString(flow->key.peerIP);
+
+            g_string_append_printf(msg, "%s%s,%s:%u,%s:%u;"
+                    "%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT,
+                    i > 0 ? "|" : "", _tracker_getProtocolString(flow->key.type),
+                    localIP, ntohs(flow->key.localPort), peerIP, ntohs(flow->key.peerPort),
+                    flow->counters.recvBytes, flow->counters.sendBytes,
+                    flow->counters.recvPackets, flow->counters.sendPackets);
+
+            g_free(localIP);
+            g_free(peerIP);
+        }
+
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__, "%s", msg->str);
+        g_string_free(msg, TRUE);
+    }
+    g_ptr_array_free(flows, TRUE);
+
+    GPtrArray* peers = _tracker_sortedValues(tracker->peerStats, _tracker_comparePeers);
+    guint numPeers = MIN(peers->len, tracker->numTopTalkers);
+
+    if(numPeers > 0) {
+        GString* msg = g_string_new("[shadow-heartbeat] [top-peers] ");
+
+        for(guint i = 0; i < numPeers; i++) {
+            const PeerStats* peer = g_ptr_array_index(peers, i);
+            gchar* name = _tracker_getPeerName(peer->peerIP);
+            gchar* peerIP = address_ipToNewString(peer->peerIP);
+
+            g_string_append_printf(msg, "%s%s,%s;"
+                    "%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT,
+                    i > 0 ? "|" : "", name, peerIP,
+                    peer->counters.recvBytes, peer->counters.sendBytes,
+                    peer->counters.recvPackets, peer->counters.sendPackets);
+
+            g_free(name);
+            g_free(peerIP);
+        }
+
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__, "%s", msg->str);
+        g_string_free(msg, TRUE);
+    }
+    g_ptr_array_free(peers, TRUE);
+}
+
+void tracker_logMetadata(Tracker* tracker, const char* metadata) {
+    MAGIC_ASSERT(tracker);
+
+    logger_log(logger_getDefault(), tracker->loglevel, __FILE__, __FUNCTION__, __LINE__,
+               "[shadow-heartbeat] [metadata] %s", metadata);
+}
+
+void tracker_logMetric(Tracker* tracker, pid_t pid, const char* name, const char* type,
+                       const char* value) {
+    MAGIC_ASSERT(tracker);
+
+    if(!tracker->didLogMetricHeader) {
+        tracker->didLogMetricHeader = TRUE;
+        logger_log(logger_getDefault(), tracker->loglevel, __FILE__, __FUNCTION__, __LINE__,
+                   "[shadow-heartbeat] [metric-header] process-id,metric-name,metric-type,value");
+    }
+
+    logger_log(logger_getDefault(), tracker->loglevel, __FILE__, __FUNCTION__, __LINE__,
+               "[shadow-heartbeat] [metric] %d,%s,%s,%s", pid, name, type, value);
+}
+
 void tracker_heartbeat(Tracker* tracker, const Host* host) {
     MAGIC_ASSERT(tracker);
 
@@ -597,16 +935,37 @@ void tracker_heartbeat(Tracker* tracker, const Host* host) {
         _tracker_logRAM(tracker, tracker->loglevel, tracker->interval);
     }
 
+    /* only log pcap info if there has been a pcap error */
+    if(tracker->numPcapErrorsTotal > 0) {
+        _tracker_logPcap(tracker, tracker->loglevel, tracker->interval);
+    }
+
+    /* check to see if the top talkers are being logged */
+    if(tracker->numTopTalkers > 0) {
+        _tracker_logTopTalkers(tracker, tracker->loglevel);
+    }
+
+    /* add this interval's stats to the host's group stats, except at the first heartbeat which
+     * doesn't end an interval */
+    if((tracker->loginfo & LOG_INFO_FLAGS_NODE) && tracker->lastHeartbeat != 0) {
+        host_recordHeartbeatStats(host, _tracker_sumBytes(&tracker->remote.inCounters.bytes),
+                                  _tracker_sumBytes(&tracker->remote.outCounters.bytes),
+                                  tracker->delayTimeLastInterval);
+    }
+
     /* clear interval stats */
     tracker->processingTimeLastIntervalNanos = 0;
     tracker->delayTimeLastInterval = 0;
     tracker->numDelayedLastInterval = 0;
     tracker->allocatedBytesLastInterval = 0;
     tracker->deallocatedBytesLastInterval = 0;
+    tracker->numPcapErrorsLastInterval = 0;
 
     /* clear the counters */
     memset(&tracker->local, 0, sizeof(IFaceCounters));
     memset(&tracker->remote, 0, sizeof(IFaceCounters));
+    g_hash_table_remove_all(tracker->flowStats);
+    g_hash_table_remove_all(tracker->peerStats);
 
     SocketStats* ss = NULL;
     GHashTableIter socketIterator;
@@ -625,3 +984,91 @@ void tracker_heartbeat(Tracker* tracker, const Host* host) {
     host_scheduleTaskWithDelay(host, heartbeatTask, tracker->interval);
     taskref_drop(heartbeatTask);
 }
+
+static void _tracker_sampleSockets(Tracker* tracker, LogLevel level) {
+    if(!tracker->didLogSocketSampleHeader) {
+        tracker->didLogSocketSampleHeader = TRUE;
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                "[shadow-heartbeat] [socket-sample-header] descriptor-number,protocol-string,hostname:port-peer;"
+                "inbuflen-bytes,inbufsize-bytes,outbuflen-bytes,outbufsize-bytes|..."); // for each socket
+    }
+
+    GString* msg = g_string_new("[shadow-heartbeat] [socket-sample] ");
+
+    SocketStats* ss = NULL;
+    GHashTableIter socketIterator;
+    g_hash_table_iter_init(&socketIterator, tracker->socketStats);
+
+    /* the heartbeat removes closed sockets after logging them if it logs sockets, otherwise we
+     * remove them here */
+    gboolean removeClosed = !(tracker->loginfo & LOG_INFO_FLAGS_SOCKET);
+    GQueue* socketsToRemove = g_queue_new();
+    gint socketLogCount = 0;
+
+    while(g_hash_table_iter_next(&socketIterator, NULL, (gpointer*)&ss)) {
+        if(!ss) {
+            continue;
+        }
+
+        if(removeClosed && ss->removeAfterNextLog) {
+            g_queue_push_tail(socketsToRemove, GINT_TO_POINTER(ss->socket));
+        }
+
+        /* don't log tcp sockets that don't have peer IP/port set */
+        if(ss->type == PTCP && !ss->peerIP) {
+            continue;
+        }
+
+        if(socketLogCount > 0) {
+            g_string_append_printf(msg, "|");
+        }
+
+        socketLogCount++;
+        g_string_append_printf(msg, "%ld,%s,%s:%u;"
+                "%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT",%"G_GSIZE_FORMAT,
+                ss->socket, _tracker_getProtocolString(ss->type),
+                ss->peerHostname, ss->peerPort,
+                ss->inputBufferLength, ss->inputBufferSize,
+                ss->outputBufferLength, ss->outputBufferSize);
+    }
+
+    if(socketLogCount > 0) {
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__, "%s", msg->str);
+    }
+
+    while (!g_queue_is_empty(socketsToRemove)) {
+        guintptr socket = (guintptr)g_queue_pop_head(socketsToRemove);
+        g_hash_table_remove(tracker->socketStats, &socket);
+    }
+    g_queue_free(socketsToRemove);
+
+    g_string_free(msg, TRUE);
+}
+
+static void _tracker_sampleInterfaces(Tracker* tracker, LogLevel level, const Host* host) {
+    if(!tracker->didLogInterfaceSampleHeader) {
+        tracker->didLogInterfaceSampleHeader = TRUE;
+        logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+                "[shadow-heartbeat] [interface-sample-header] "
+                "localhost-queued-sockets,eth0-queued-sockets,router-queued-packets");
+    }
+
+    logger_log(logger_getDefault(), level, __FILE__, __FUNCTION__, __LINE__,
+        "[shadow-heartbeat] [interface-sample] %"G_GUINT64_FORMAT",%"G_GUINT64_FORMAT",%"G_GUINT64_FORMAT,
+        host_getInterfaceQueueLength(host, true), host_getInterfaceQueueLength(host, false),
+        host_getRouterQueueLength(host));
+}
+
+void tracker_sample(Tracker* tracker, const Host* host) {
+    MAGIC_ASSERT(tracker);
+    utility_debugAssert(_tracker_isSampling(tracker));
+
+    _tracker_sampleSockets(tracker, tracker->loglevel);
+    _tracker_sampleInterfaces(tracker, tracker->loglevel, host);
+
+    /* schedule the next sample */
+    TaskRef* sampleTask =
+        taskref_new_bound(host_getID(host), tracker_sampleTask, tracker, NULL, NULL, NULL);
+    host_scheduleTaskWithDelay(host, sampleTask, tracker->sampleInterval);
+    taskref_drop(sampleTask);
+}
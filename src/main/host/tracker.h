@@ -9,6 +9,7 @@
 
 #include <glib.h>
 #include <netinet/in.h>
+#include <sys/types.h>
 
 #include "lib/logger/log_level.h"
 #include "main/core/support/definitions.h"
@@ -16,14 +17,15 @@
 #include "main/host/tracker_types.h"
 #include "main/routing/packet.minimal.h"
 
-Tracker* tracker_new(const Host* host, CSimulationTime interval, LogLevel loglevel,
-                     LogInfoFlags loginfo);
+Tracker* tracker_new(const Host* host, CSimulationTime interval, CSimulationTime sampleInterval,
+                     guint numTopTalkers, LogLevel loglevel, LogInfoFlags loginfo);
 void tracker_free(Tracker* tracker);
 
 void tracker_addProcessingTimeNanos(Tracker* tracker, CSimulationTime processingTime);
 void tracker_addVirtualProcessingDelay(Tracker* tracker, CSimulationTime delay);
-void tracker_addInputBytes(Tracker* tracker, Packet* packet, const CompatSocket* socket);
-void tracker_addOutputBytes(Tracker* tracker, Packet* packet, const CompatSocket* socket);
+void tracker_addInputBytes(Tracker* tracker, const Packet* packet, const CompatSocket* socket);
+void tracker_addOutputBytes(Tracker* tracker, const Packet* packet, const CompatSocket* socket);
+void tracker_addPcapError(Tracker* tracker);
 void tracker_addAllocatedBytes(Tracker* tracker, gpointer location, gsize allocatedBytes);
 void tracker_removeAllocatedBytes(Tracker* tracker, gpointer location);
 void tracker_addSocket(Tracker* tracker, const CompatSocket* socket, ProtocolType type, gsize inputBufferSize, gsize outputBufferSize);
@@ -32,8 +34,15 @@ void tracker_updateSocketInputBuffer(Tracker* tracker, const CompatSocket* socke
 void tracker_updateSocketOutputBuffer(Tracker* tracker, const CompatSocket* socket, gsize outputBufferLength, gsize outputBufferSize);
 void tracker_removeSocket(Tracker* tracker, const CompatSocket* socket);
 void tracker_heartbeat(Tracker* tracker, const Host* host);
+void tracker_logMetadata(Tracker* tracker, const char* metadata);
+void tracker_logMetric(Tracker* tracker, pid_t pid, const char* name, const char* type,
+                       const char* value);
 static inline void tracker_heartbeatTask(const Host* host, gpointer tracker, gpointer userData) {
     tracker_heartbeat(tracker, host);
 }
+void tracker_sample(Tracker* tracker, const Host* host);
+static inline void tracker_sampleTask(const Host* host, gpointer tracker, gpointer userData) {
+    tracker_sample(tracker, host);
+}
 
 #endif /* SHD_TRACKER_H_ */
@@ -0,0 +1,102 @@
+//! Fingerprints of the packets that a host sends and receives, which are used to check that
+//! simulations are deterministic. A fingerprint is a hash of the time, direction, addresses,
+//! protocol, size, and TCP header of each packet, in the order that the host sent or received them,
+//! so two runs of a simulation with the same seed should have identical fingerprints regardless of
+//! the number of worker threads.
+
+use std::io::Write;
+use std::path::Path;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+use crate::cshadow as c;
+use crate::network::packet::PacketRc;
+
+pub const FINGERPRINT_FILE_NAME: &str = "packets.fingerprint";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PacketDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PacketFingerprint {
+    /// An FNV-1a hash, which unlike the standard library's hashers is guaranteed to be stable.
+    hash: u64,
+    packets: u64,
+}
+
+impl PacketFingerprint {
+    pub fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+            packets: 0,
+        }
+    }
+
+    pub fn add(&mut self, time: EmulatedTime, direction: PacketDirection, packet: &PacketRc) {
+        let time = time.duration_since(&EmulatedTime::SIMULATION_START);
+        let src = packet.src_address();
+        let dst = packet.dst_address();
+        let tcp = packet.get_tcp();
+
+        self.hash_bytes(&time.as_nanos().to_le_bytes());
+        self.hash_bytes(&[direction as u8]);
+        self.hash_bytes(&src.ip().octets());
+        self.hash_bytes(&src.port().to_le_bytes());
+        self.hash_bytes(&dst.ip().octets());
+        self.hash_bytes(&dst.port().to_le_bytes());
+        self.hash_bytes(&[u8::from(packet.protocol() == c::_ProtocolType_PTCP)]);
+        self.hash_bytes(&packet.total_size().to_le_bytes());
+        if let Some(tcp) = tcp {
+            self.hash_bytes(&tcp.seq.to_le_bytes());
+            self.hash_bytes(&tcp.ack.to_le_bytes());
+            self.hash_bytes(&[tcp.flags.bits()]);
+            self.hash_bytes(&tcp.window_size.to_le_bytes());
+        }
+
+        self.packets += 1;
+    }
+
+    fn hash_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Write the fingerprint and the number of packets to the file at `path`.
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        writeln!(file, "{:016x} {}", self.hash, self.packets)
+    }
+}
+
+impl Default for PacketFingerprint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_bytes() {
+        // test vectors of the 64-bit FNV-1a hash
+        let mut fingerprint = PacketFingerprint::new();
+        fingerprint.hash_bytes(b"");
+        assert_eq!(fingerprint.hash, 0xcbf29ce484222325);
+        fingerprint.hash_bytes(b"a");
+        assert_eq!(fingerprint.hash, 0xaf63dc4c8601ec8c);
+
+        let mut fingerprint = PacketFingerprint::new();
+        fingerprint.hash_bytes(b"foo");
+        assert_eq!(fingerprint.hash, 0xdcb27518fed9d577);
+    }
+}
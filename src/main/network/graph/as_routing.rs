@@ -0,0 +1,416 @@
+//! Valley-free routing between autonomous systems (ASes), which approximates the paths that BGP
+//! would choose.
+//!
+//! Nodes are grouped into ASes using the node `asn` attribute, and each edge between two ASes has
+//! an `as_relationship` attribute giving the relationship of the target node's AS to the source
+//! node's AS. Following the Gao-Rexford model, an AS only carries traffic between two other ASes if
+//! one of them is its customer. So a valid path climbs zero or more customer-to-provider edges,
+//! crosses at most one peering edge, and then descends zero or more provider-to-customer edges.
+//!
+//! Among the valid paths, a path is chosen the way BGP chooses a route: paths that leave the
+//! source's AS through a customer are preferred over paths through a peer, which are preferred
+//! over paths through a provider. Ties are broken by the number of inter-AS edges (the AS path
+//! length), and then by latency. Paths are chosen from the point of view of the source node rather
+//! than hop-by-hop, so this is an approximation of BGP rather than a simulation of it.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+
+use super::petgraph_wrapper::GraphWrapper;
+use super::{AsRelationship, NetGraphError, NetworkGraph, PathProperties, ShadowEdge, ShadowNode};
+
+/// The preference of a route, from most to least preferred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum RouteClass {
+    /// The path hasn't left the source's AS.
+    Local,
+    Customer,
+    Peer,
+    Provider,
+}
+
+/// The part of the valley-free path that the path is in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Phase {
+    Up,
+    Peered,
+    Down,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct State {
+    class: RouteClass,
+    phase: Phase,
+}
+
+impl State {
+    const START: Self = Self {
+        class: RouteClass::Local,
+        phase: Phase::Up,
+    };
+
+    /// The state after crossing into an AS that has the relationship `rel` with the current AS, or
+    /// `None` if the path would no longer be valley-free.
+    fn cross(self, rel: AsRelationship) -> Option<Self> {
+        let phase = match (self.phase, rel) {
+            (Phase::Up, AsRelationship::Provider) => Phase::Up,
+            (Phase::Up, AsRelationship::Peer) => Phase::Peered,
+            (_, AsRelationship::Customer) => Phase::Down,
+            _ => return None,
+        };
+
+        let class = match (self.class, rel) {
+            (RouteClass::Local, AsRelationship::Customer) => RouteClass::Customer,
+            (RouteClass::Local, AsRelationship::Peer) => RouteClass::Peer,
+            (RouteClass::Local, AsRelationship::Provider) => RouteClass::Provider,
+            (class, _) => class,
+        };
+
+        Some(Self { class, phase })
+    }
+}
+
+/// Check that either all or none of the nodes have an AS, and that exactly the edges between
+/// different ASes have an AS relationship.
+pub fn validate(graph: &NetworkGraph) -> Result<(), NetGraphError> {
+    let (nodes, edges): (Vec<&ShadowNode>, Vec<&ShadowEdge>) = match graph.graph() {
+        GraphWrapper::Directed(g) => (
+            g.raw_nodes().iter().map(|x| &x.weight).collect(),
+            g.raw_edges().iter().map(|x| &x.weight).collect(),
+        ),
+        GraphWrapper::Undirected(g) => (
+            g.raw_nodes().iter().map(|x| &x.weight).collect(),
+            g.raw_edges().iter().map(|x| &x.weight).collect(),
+        ),
+    };
+
+    if nodes.iter().any(|x| x.asn.is_some()) {
+        if let Some(node) = nodes.iter().find(|x| x.asn.is_none()) {
+            return Err(format!("Node {} has no 'asn', but other nodes do", node.id).into());
+        }
+    }
+
+    let asn = |id: u32| {
+        let index = graph.node_id_to_index(id).unwrap();
+        graph.graph().node_weight(*index).unwrap().asn
+    };
+
+    for edge in edges {
+        let same_as = asn(edge.source) == asn(edge.target);
+        match (edge.as_relationship, same_as) {
+            (Some(_), true) => {
+                return Err(format!(
+                    "Edge from node {} to node {} has an 'as_relationship', but doesn't connect \
+                     two autonomous systems",
+                    edge.source, edge.target
+                )
+                .into());
+            }
+            (None, false) => {
+                return Err(format!(
+                    "Edge from node {} to node {} connects two autonomous systems, but has no \
+                     'as_relationship'",
+                    edge.source, edge.target
+                )
+                .into());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// The edges that leave `node`, along with the node at the other end of each edge.
+fn edges(graph: &NetworkGraph, node: NodeIndex) -> Vec<(NodeIndex, &ShadowEdge)> {
+    // edges of an undirected graph may be stored in either direction
+    let other_end = |source: NodeIndex, target: NodeIndex| {
+        if source == node {
+            target
+        } else {
+            source
+        }
+    };
+
+    match graph.graph() {
+        GraphWrapper::Directed(g) => g
+            .edges(node)
+            .map(|e| (other_end(e.source(), e.target()), e.weight()))
+            .collect(),
+        GraphWrapper::Undirected(g) => g
+            .edges(node)
+            .map(|e| (other_end(e.source(), e.target()), e.weight()))
+            .collect(),
+    }
+}
+
+/// Compute the paths from `src` to each of the other nodes in `nodes`. Returns an error if there
+/// is no valley-free path to one of the nodes.
+pub fn paths_from(
+    graph: &NetworkGraph,
+    src: NodeIndex,
+    nodes: &[NodeIndex],
+) -> Result<Vec<((NodeIndex, NodeIndex), PathProperties)>, NetGraphError> {
+    let search = Search::new(graph, src);
+    nodes
+        .iter()
+        .filter(|dst| **dst != src)
+        .map(|dst| Ok(((src, *dst), search.best(*dst)?.path)))
+        .collect()
+}
+
+/// Compute the nodes on the paths from `src` to each node in `nodes`, including `src` and the
+/// destination node. The path from `src` to itself is its self-loop.
+pub fn routes_from(
+    graph: &NetworkGraph,
+    src: NodeIndex,
+    nodes: &[NodeIndex],
+) -> Result<Vec<(NodeIndex, Vec<NodeIndex>)>, NetGraphError> {
+    let search = Search::new(graph, src);
+    nodes
+        .iter()
+        .map(|dst| {
+            if *dst == src {
+                return Ok((*dst, vec![src, src]));
+            }
+
+            let mut route = vec![*dst];
+            let mut label = search.best(*dst)?;
+            while let Some(prev) = label.prev {
+                route.push(prev.0);
+                label = search.labels[&prev];
+            }
+            route.reverse();
+
+            Ok((*dst, route))
+        })
+        .collect()
+}
+
+/// The best path found to a node in a given state.
+#[derive(Debug, Copy, Clone)]
+struct Label {
+    /// The AS path length.
+    hops: u32,
+    path: PathProperties,
+    /// The previous node on the path and its state, or `None` at the source node.
+    prev: Option<(NodeIndex, State)>,
+}
+
+/// The valley-free paths from a source node.
+struct Search<'a> {
+    graph: &'a NetworkGraph,
+    src: NodeIndex,
+    /// The best path to each node for each state. Since a path's route class and phase limit where
+    /// it can go next, paths in different states can't be compared until they reach their
+    /// destination.
+    labels: HashMap<(NodeIndex, State), Label>,
+}
+
+impl<'a> Search<'a> {
+    fn new(graph: &'a NetworkGraph, src: NodeIndex) -> Self {
+        let mut labels = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        labels.insert(
+            (src, State::START),
+            Label {
+                hops: 0,
+                path: PathProperties::default(),
+                prev: None,
+            },
+        );
+        queue.push(Reverse((0, 0, src, State::START)));
+
+        while let Some(Reverse((hops, latency_ns, node, state))) = queue.pop() {
+            let label = labels[&(node, state)];
+            if (hops, latency_ns) != (label.hops, label.path.latency_ns) {
+                // we've already found a better path to this node and state
+                continue;
+            }
+
+            let node_id = graph.node_index_to_id(node).unwrap();
+
+            for (next, edge) in edges(graph, node) {
+                if next == node {
+                    continue;
+                }
+
+                let (next_state, next_hops) = match edge.as_relationship {
+                    None => (state, hops),
+                    Some(rel) => {
+                        // the relationship is from the edge's source to its target
+                        let rel = if edge.source == node_id {
+                            rel
+                        } else {
+                            rel.reverse()
+                        };
+                        let Some(next_state) = state.cross(rel) else {
+                            continue;
+                        };
+                        (next_state, hops + 1)
+                    }
+                };
+
+                let next_label = Label {
+                    hops: next_hops,
+                    path: label.path + edge.into(),
+                    prev: Some((node, state)),
+                };
+                let cost = (next_label.hops, next_label.path.latency_ns);
+
+                let improved = labels
+                    .get(&(next, next_state))
+                    .map_or(true, |x| cost < (x.hops, x.path.latency_ns));
+                if improved {
+                    labels.insert((next, next_state), next_label);
+                    queue.push(Reverse((cost.0, cost.1, next, next_state)));
+                }
+            }
+        }
+
+        Self { graph, src, labels }
+    }
+
+    /// The preferred path to `dst`, or an error if there is no valley-free path.
+    fn best(&self, dst: NodeIndex) -> Result<Label, NetGraphError> {
+        let classes = [
+            RouteClass::Local,
+            RouteClass::Customer,
+            RouteClass::Peer,
+            RouteClass::Provider,
+        ];
+        let phases = [Phase::Up, Phase::Peered, Phase::Down];
+
+        classes
+            .iter()
+            .flat_map(|class| {
+                phases.iter().map(|phase| State {
+                    class: *class,
+                    phase: *phase,
+                })
+            })
+            .filter_map(|state| self.labels.get(&(dst, state)).map(|x| (state, x)))
+            .min_by_key(|(state, x)| (state.class, x.hops, x.path.latency_ns))
+            .map(|(_, x)| *x)
+            .ok_or_else(|| {
+                format!(
+                    "No valley-free path from node {} to node {}",
+                    self.graph.node_index_to_id(self.src).unwrap(),
+                    self.graph.node_index_to_id(dst).unwrap(),
+                )
+                .into()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph with a self-loop for each node. Nodes are given as `(id, asn)`, and edges as
+    /// `(source, target, latency_ms, as_relationship)`.
+    fn graph(nodes: &[(u32, u32)], edges: &[(u32, u32, u32, Option<&str>)]) -> String {
+        let mut gml = String::from("graph [\n");
+        for (id, asn) in nodes {
+            gml += &format!("node [ id {id} asn {asn} ]\n");
+            gml += &format!("edge [ source {id} target {id} latency \"1 ms\" ]\n");
+        }
+        for (source, target, latency, rel) in edges {
+            let rel = rel.map_or(String::new(), |x| format!("as_relationship \"{x}\""));
+            gml += &format!(
+                "edge [ source {source} target {target} latency \"{latency} ms\" {rel} ]\n"
+            );
+        }
+        gml + "]"
+    }
+
+    fn latency_ms(
+        paths: &HashMap<(NodeIndex, NodeIndex), PathProperties>,
+        src: u32,
+        dst: u32,
+    ) -> u64 {
+        let src = NodeIndex::new(src as usize);
+        let dst = NodeIndex::new(dst as usize);
+        paths[&(src, dst)].latency_ns / 1_000_000
+    }
+
+    // disabled under miri due to https://github.com/rayon-rs/rayon/issues/952
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_valley_free() {
+        // AS 1 and AS 2 are customers of AS 3 (which has two nodes), and are both providers of
+        // AS 4
+        let nodes = [(0, 1), (1, 2), (2, 3), (3, 3), (4, 4)];
+        let mut edges = vec![
+            (0, 2, 10, Some("provider")),
+            (2, 3, 5, None),
+            (3, 1, 10, Some("customer")),
+            (4, 0, 1, Some("provider")),
+            (4, 1, 1, Some("provider")),
+        ];
+
+        let graph = NetworkGraph::parse(&graph(&nodes, &edges)).unwrap();
+        let all: Vec<_> = (0..5).map(NodeIndex::new).collect();
+        let paths = graph.compute_as_paths(&all).unwrap();
+
+        // AS 4 doesn't carry traffic between its providers
+        assert_eq!(latency_ms(&paths, 0, 1), 25);
+        assert_eq!(latency_ms(&paths, 1, 0), 25);
+        // but its providers carry its traffic
+        assert_eq!(latency_ms(&paths, 4, 2), 11);
+        assert_eq!(latency_ms(&paths, 3, 4), 11);
+        assert_eq!(latency_ms(&paths, 0, 0), 1);
+
+        let routes = routes_from(&graph, all[0], &all).unwrap();
+        let ids =
+            |route: &[NodeIndex]| -> Vec<u32> { route.iter().map(|x| x.index() as u32).collect() };
+        assert_eq!(ids(&routes[0].1), [0, 0]);
+        assert_eq!(ids(&routes[1].1), [0, 2, 3, 1]);
+        assert_eq!(ids(&routes[4].1), [0, 4]);
+
+        // a slower peering link is preferred over going through a provider
+        edges.push((0, 1, 100, Some("peer")));
+        let graph = NetworkGraph::parse(&graph(&nodes, &edges)).unwrap();
+        let paths = graph.compute_as_paths(&all).unwrap();
+        assert_eq!(latency_ms(&paths, 0, 1), 100);
+        assert_eq!(latency_ms(&paths, 4, 1), 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_no_valley_free_path() {
+        // AS 1 carries traffic between its two customers
+        let nodes = [(0, 1), (1, 2), (2, 3)];
+        let edges = [(1, 0, 1, Some("provider")), (2, 0, 1, Some("provider"))];
+        let graph = NetworkGraph::parse(&graph(&nodes, &edges)).unwrap();
+        let all: Vec<_> = (0..3).map(NodeIndex::new).collect();
+        assert!(graph.compute_as_paths(&all).is_ok());
+
+        // two providers of AS 3
+        let edges = [(2, 0, 1, Some("provider")), (2, 1, 1, Some("provider"))];
+        let graph = NetworkGraph::parse(&graph(&nodes, &edges)).unwrap();
+        assert!(graph.compute_as_paths(&all).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        // inter-AS edges need a relationship
+        let gml = graph(&[(0, 1), (1, 2)], &[(0, 1, 1, None)]);
+        assert!(NetworkGraph::parse(&gml).is_err());
+
+        // intra-AS edges must not have a relationship
+        let gml = graph(&[(0, 1), (1, 1)], &[(0, 1, 1, Some("peer"))]);
+        assert!(NetworkGraph::parse(&gml).is_err());
+
+        let gml = graph(&[(0, 1), (1, 2)], &[(0, 1, 1, Some("sibling"))]);
+        assert!(NetworkGraph::parse(&gml).is_err());
+
+        // every node needs an AS if any node has one
+        let gml = "graph [ node [ id 0 asn 1 ] node [ id 1 ] ]";
+        assert!(NetworkGraph::parse(gml).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! On-path censors that inspect the payloads of packets crossing an edge of the network graph or an
+//! autonomous system, representing a keyword-blocking middlebox for censorship circumvention
+//! research.
+//!
+//! A censor blocks a TCP or UDP packet whose payload contains one of its keywords, and then blocks
+//! every later packet of the same connection. If residual blocking is configured, the censor also
+//! blocks the packets between the connection's two addresses and ports on either side for a while,
+//! including new connections between the client and server. Blocked packets are dropped, and for
+//! the "reset" action a TCP RST is also injected to each end of a TCP connection. Censors never
+//! block TCP RSTs.
+//!
+//! Like the observers, a censor is at a fixed point on the path that each packet takes, which is
+//! computed once when the simulation starts. An injected RST reaches the packet's source after the
+//! round trip between the source and the censor. To keep the simulation deterministic, the censor's
+//! decision to block a connection only affects the packets sent from the end of the current
+//! scheduling round.
+
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use super::observers::{self, Tap};
+use super::NetworkGraph;
+use crate::core::support::configuration::{CensorAction, CensorLocationOptions, CensorOptions};
+
+#[derive(Debug)]
+pub struct Censors {
+    censors: Vec<Censor>,
+}
+
+#[derive(Debug)]
+struct Censor {
+    name: String,
+    keywords: Vec<Vec<u8>>,
+    action: CensorAction,
+    residual_blocking: Option<SimulationTime>,
+    /// For each pair of source and destination graph nodes whose path crosses the censor, the
+    /// latency in nanoseconds from the source node to the censor.
+    offsets: HashMap<(u32, u32), u64>,
+    /// The periods during which the censor blocks the packets matching each block.
+    blocks: Mutex<HashMap<Block, Vec<BlockPeriod>>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// A packet seen by a censor.
+#[derive(Debug, Copy, Clone)]
+pub struct CensoredPacket {
+    pub protocol: Protocol,
+    pub src: SocketAddrV4,
+    pub dst: SocketAddrV4,
+    pub is_reset: bool,
+}
+
+/// What happens to a packet that a censor blocks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub action: CensorAction,
+    /// The latency from the packet's source to the censor.
+    pub offset: SimulationTime,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Block {
+    /// The packets of a connection in either direction, with the lower address first.
+    Connection(Protocol, SocketAddrV4, SocketAddrV4),
+    /// The packets between a client address and a server address and port in either direction.
+    Residual(Protocol, Ipv4Addr, SocketAddrV4),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct BlockPeriod {
+    start: EmulatedTime,
+    end: EmulatedTime,
+}
+
+impl Censors {
+    /// Create the censors from the configuration. `nodes` are the graph nodes that have hosts
+    /// attached, and `use_shortest_paths` must be the same as when computing the routing
+    /// information.
+    pub fn new(
+        options: &[CensorOptions],
+        graph: &NetworkGraph,
+        nodes: &HashSet<u32>,
+        use_shortest_paths: bool,
+    ) -> anyhow::Result<Self> {
+        let mut names = HashSet::new();
+        let taps = options
+            .iter()
+            .map(|x| {
+                if !names.insert(x.name.as_str()) {
+                    anyhow::bail!("Censor name '{}' is used more than once", x.name);
+                }
+                if x.keywords.iter().any(|x| x.is_empty()) {
+                    anyhow::bail!("Censor '{}' has an empty keyword", x.name);
+                }
+                let owner = format!("Censor '{}'", x.name);
+                match x.location {
+                    CensorLocationOptions::Edge { source, target } => {
+                        Tap::edge(graph, source, target, &owner)
+                    }
+                    CensorLocationOptions::AutonomousSystem { asn } => {
+                        Tap::autonomous_system(graph, asn, &owner)
+                    }
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let offsets = observers::tap_offsets(&taps, graph, nodes, use_shortest_paths)?;
+
+        let censors = options
+            .iter()
+            .zip(offsets)
+            .map(|(options, offsets)| Censor {
+                name: options.name.clone(),
+                keywords: options
+                    .keywords
+                    .iter()
+                    .map(|x| x.as_bytes().to_vec())
+                    .collect(),
+                action: options.action,
+                residual_blocking: options.residual_blocking.map(|x| {
+                    let x: Duration = x.into();
+                    SimulationTime::try_from(x).unwrap()
+                }),
+                offsets,
+                blocks: Mutex::new(HashMap::new()),
+            })
+            .collect();
+
+        Ok(Self { censors })
+    }
+
+    /// Called when `packet` is sent from graph node `src_node` to graph node `dst_node` at time
+    /// `time`. `payload` returns the packet's payload, and is only called if the packet's path
+    /// crosses a censor. Connections blocked because of this packet are blocked from time
+    /// `effective`, which must be the end of the current scheduling round. Returns what happens to
+    /// the packet if a censor blocks it.
+    pub fn packet_sent(
+        &self,
+        src_node: u32,
+        dst_node: u32,
+        packet: CensoredPacket,
+        payload: impl FnOnce() -> Vec<u8>,
+        time: EmulatedTime,
+        effective: EmulatedTime,
+    ) -> Option<Verdict> {
+        if packet.is_reset {
+            return None;
+        }
+
+        let payload_cell = OnceCell::new();
+        let mut payload = Some(payload);
+        let mut verdict: Option<Verdict> = None;
+
+        for censor in &self.censors {
+            let Some(offset) = censor.offsets.get(&(src_node, dst_node)) else {
+                continue;
+            };
+            let payload = payload_cell.get_or_init(|| payload.take().unwrap()());
+
+            if censor.check(&packet, payload, time, effective) {
+                let offset = SimulationTime::from_nanos(*offset);
+                // the packet is blocked by the first censor on its path
+                if verdict.map_or(true, |x| offset < x.offset) {
+                    verdict = Some(Verdict {
+                        action: censor.action,
+                        offset,
+                    });
+                }
+            }
+        }
+
+        verdict
+    }
+}
+
+impl Censor {
+    /// Returns true if the censor blocks the packet.
+    fn check(
+        &self,
+        packet: &CensoredPacket,
+        payload: &[u8],
+        time: EmulatedTime,
+        effective: EmulatedTime,
+    ) -> bool {
+        let (src, dst) = (packet.src, packet.dst);
+        let connection = Block::Connection(packet.protocol, src.min(dst), src.max(dst));
+        let residual = [
+            Block::Residual(packet.protocol, *src.ip(), dst),
+            Block::Residual(packet.protocol, *dst.ip(), src),
+        ];
+
+        let mut blocks = self.blocks.lock().unwrap();
+
+        let is_blocked = |block: &Block| {
+            blocks
+                .get(block)
+                .is_some_and(|x| x.iter().any(|x| x.start <= time && time < x.end))
+        };
+        if is_blocked(&connection) || residual.iter().any(is_blocked) {
+            return true;
+        }
+
+        if !self.keywords.iter().any(|x| contains(payload, x)) {
+            return false;
+        }
+
+        log::debug!(
+            "Censor '{}' matched a keyword in a packet from {src} to {dst}",
+            self.name
+        );
+
+        let mut add = |block: Block, end: EmulatedTime| {
+            let periods = blocks.entry(block).or_default();
+            // the blocks added during a round start at the same time, and are merged regardless of
+            // the order that they were added
+            match periods.iter_mut().find(|x| x.start == effective) {
+                Some(period) => period.end = period.end.max(end),
+                None => periods.push(BlockPeriod {
+                    start: effective,
+                    end,
+                }),
+            }
+        };
+
+        add(connection, EmulatedTime::MAX);
+        if let Some(residual_blocking) = self.residual_blocking {
+            for block in residual {
+                add(block, time.saturating_add(residual_blocking));
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns true if `keyword` appears in `payload`.
+fn contains(payload: &[u8], keyword: &[u8]) -> bool {
+    payload.windows(keyword.len()).any(|x| x == keyword)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPH: &str = r#"graph [
+      node [ id 0 asn 1 ]
+      node [ id 1 asn 2 ]
+      edge [ source 0 target 0 latency "1 ms" ]
+      edge [ source 1 target 1 latency "1 ms" ]
+      edge [ source 0 target 1 latency "10 ms" ]
+    ]"#;
+
+    fn time(ms: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_millis(ms)
+    }
+
+    fn packet(src: &str, dst: &str) -> CensoredPacket {
+        CensoredPacket {
+            protocol: Protocol::Tcp,
+            src: src.parse().unwrap(),
+            dst: dst.parse().unwrap(),
+            is_reset: false,
+        }
+    }
+
+    fn censors(yaml: &str) -> anyhow::Result<Censors> {
+        let graph = NetworkGraph::parse(GRAPH).unwrap();
+        let options: Vec<CensorOptions> = serde_yaml::from_str(yaml).unwrap();
+        Censors::new(&options, &graph, &HashSet::from([0, 1]), true)
+    }
+
+    // disabled under miri due to https://github.com/rayon-rs/rayon/issues/952
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_censors() {
+        let censors = censors(
+            "
+            - name: gfw
+              location: {type: edge, source: 0, target: 1}
+              keywords: [forbidden]
+              residual_blocking: 90 s
+            ",
+        )
+        .unwrap();
+
+        let client = "11.0.0.1:40000";
+        let server = "11.0.0.2:80";
+        let check = |packet: CensoredPacket, payload: &str, ms: u64| {
+            let payload = payload.as_bytes().to_vec();
+            // each round is 1 ms long
+            censors.packet_sent(0, 1, packet, || payload, time(ms), time(ms + 1))
+        };
+
+        // packets whose path doesn't cross the censor aren't blocked
+        assert_eq!(
+            censors.packet_sent(
+                0,
+                0,
+                packet(client, server),
+                || b"forbidden".to_vec(),
+                time(0),
+                time(1),
+            ),
+            None
+        );
+
+        assert_eq!(check(packet(client, server), "GET /", 0), None);
+        let verdict = Some(Verdict {
+            action: CensorAction::Reset,
+            offset: SimulationTime::ZERO,
+        });
+        assert_eq!(check(packet(client, server), "GET /forbidden", 10), verdict);
+
+        // the connection is blocked in either direction from the end of the round
+        assert_eq!(check(packet(server, client), "", 10), None);
+        assert_eq!(check(packet(server, client), "", 11), verdict);
+
+        // new connections from the client to the server are blocked for a while
+        assert_eq!(check(packet("11.0.0.1:40001", server), "", 11), verdict);
+        assert_eq!(check(packet("11.0.0.1:40001", server), "", 90_011), None);
+        // but not connections to other ports
+        assert_eq!(check(packet("11.0.0.1:40001", "11.0.0.2:81"), "", 11), None);
+
+        // the connection itself stays blocked
+        assert_eq!(check(packet(client, server), "", 90_011), verdict);
+
+        // RSTs are never blocked
+        let mut reset = packet(client, server);
+        reset.is_reset = true;
+        assert_eq!(check(reset, "", 11), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(censors(
+            "[{name: x, location: {type: edge, source: 0, target: 5}, keywords: [a]}]"
+        )
+        .is_err());
+        assert!(
+            censors("[{name: x, location: {type: autonomous-system, asn: 3}, keywords: [a]}]")
+                .is_err()
+        );
+        assert!(censors(
+            "[{name: x, location: {type: autonomous-system, asn: 1}, keywords: ['']}]"
+        )
+        .is_err());
+        assert!(censors(
+            "
+            - {name: x, location: {type: autonomous-system, asn: 1}, keywords: [a]}
+            - {name: x, location: {type: autonomous-system, asn: 2}, keywords: [a]}
+            "
+        )
+        .is_err());
+        assert!(
+            censors("[{name: x, location: {type: autonomous-system, asn: 1}, keywords: [a]}]")
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,925 @@
+//! Export the effective network topology and the traffic between hosts for visualization. The
+//! topology and flows are written as Graphviz DOT files, and combined in a GEXF file that can be
+//! opened in Gephi. If a flow interval is configured, the GEXF file also contains the number of
+//! bytes sent in each interval as a dynamic edge attribute.
+//!
+//! The topology and flows can also be exported as an ns-3 scenario, so that Shadow's network model
+//! can be compared with ns-3's. The scenario models each graph node as a router and each host as a
+//! node attached to its router, and replays each flow as a TCP bulk transfer.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use crate::core::support::{units, units::Unit};
+use crate::network::graph::petgraph_wrapper::GraphWrapper;
+use crate::network::graph::NetworkGraph;
+
+pub const TOPOLOGY_DOT_FILE_NAME: &str = "topology.dot";
+pub const FLOWS_DOT_FILE_NAME: &str = "flows.dot";
+pub const GEXF_FILE_NAME: &str = "topology.gexf";
+pub const NS3_SCRIPT_FILE_NAME: &str = "ns3-scenario.cc";
+pub const NS3_HOSTS_FILE_NAME: &str = "ns3-hosts.csv";
+
+/// The data rate of the ns-3 links between graph nodes, since Shadow doesn't limit the bandwidth
+/// of the edges of the network graph.
+const NS3_LINK_DATA_RATE_BITS: u64 = 100_000_000_000;
+/// The port of the ns-3 hosts' TCP sinks.
+const NS3_SINK_PORT: u16 = 9;
+/// The networks that the ns-3 scenario assigns the addresses of links from. Each link has its own
+/// /30 network.
+const NS3_HOST_NETWORK: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 0);
+const NS3_LINK_NETWORK: Ipv4Addr = Ipv4Addr::new(172, 16, 0, 0);
+
+/// A host attached to a node of the network graph.
+#[derive(Debug, Clone)]
+pub struct TopologyHost {
+    pub name: String,
+    pub ip_addr: IpAddr,
+    pub node_id: u32,
+    pub bandwidth_down_bits: u64,
+    pub bandwidth_up_bits: u64,
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct TopologyNode {
+    id: u32,
+    bandwidth_down_bits: Option<u64>,
+    bandwidth_up_bits: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct TopologyLink {
+    source: u32,
+    target: u32,
+    latency: units::Time<units::TimePrefix>,
+    jitter: units::Time<units::TimePrefix>,
+    packet_loss: f32,
+}
+
+/// A snapshot of the network graph and the hosts attached to it, taken after the configuration has
+/// been processed.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    directed: bool,
+    nodes: Vec<TopologyNode>,
+    links: Vec<TopologyLink>,
+    hosts: Vec<TopologyHost>,
+}
+
+impl Topology {
+    pub fn new(graph: &NetworkGraph, mut hosts: Vec<TopologyHost>) -> Self {
+        let (directed, nodes, edges): (_, Vec<_>, Vec<_>) = match graph.graph() {
+            GraphWrapper::Directed(g) => (
+                true,
+                g.raw_nodes().iter().map(|x| &x.weight).collect(),
+                g.raw_edges().iter().map(|x| &x.weight).collect(),
+            ),
+            GraphWrapper::Undirected(g) => (
+                false,
+                g.raw_nodes().iter().map(|x| &x.weight).collect(),
+                g.raw_edges().iter().map(|x| &x.weight).collect(),
+            ),
+        };
+
+        let bits = |x: units::BitsPerSec<units::SiPrefixUpper>| {
+            x.convert(units::SiPrefixUpper::Base).unwrap().value()
+        };
+
+        let mut nodes: Vec<_> = nodes
+            .into_iter()
+            .map(|x| TopologyNode {
+                id: x.id,
+                bandwidth_down_bits: x.bandwidth_down.map(bits),
+                bandwidth_up_bits: x.bandwidth_up.map(bits),
+            })
+            .collect();
+        nodes.sort_by_key(|x| x.id);
+
+        let mut links: Vec<_> = edges
+            .into_iter()
+            .map(|x| TopologyLink {
+                source: x.source,
+                target: x.target,
+                latency: x.latency,
+                jitter: x.jitter,
+                packet_loss: x.packet_loss,
+            })
+            .collect();
+        links.sort_by_key(|x| (x.source, x.target));
+
+        hosts.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            directed,
+            nodes,
+            links,
+            hosts,
+        }
+    }
+
+    fn host_name(&self, ip: IpAddr) -> String {
+        self.hosts
+            .iter()
+            .find(|x| x.ip_addr == ip)
+            .map(|x| x.name.clone())
+            .unwrap_or_else(|| ip.to_string())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct FlowVolume {
+    packets: u64,
+    bytes: u64,
+    /// Bytes sent in each interval, indexed by the interval number.
+    interval_bytes: BTreeMap<u64, u64>,
+}
+
+/// The number of packets and payload bytes sent between each pair of hosts.
+#[derive(Debug)]
+pub struct FlowVolumes {
+    interval: Option<SimulationTime>,
+    flows: Mutex<HashMap<(IpAddr, IpAddr), FlowVolume>>,
+}
+
+impl FlowVolumes {
+    /// If `interval` is set, the bytes sent in each interval are also recorded.
+    pub fn new(interval: Option<SimulationTime>) -> Self {
+        assert_ne!(interval, Some(SimulationTime::ZERO));
+        Self {
+            interval,
+            flows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a packet with `bytes` payload bytes sent from `src` to `dst` at simulation time
+    /// `time`.
+    pub fn add(&self, src: IpAddr, dst: IpAddr, time: SimulationTime, bytes: u64) {
+        let mut flows = self.flows.lock().unwrap();
+        let flow = flows.entry((src, dst)).or_default();
+        flow.packets = flow.packets.saturating_add(1);
+        flow.bytes = flow.bytes.saturating_add(bytes);
+
+        if let Some(interval) = self.interval {
+            let index = (time.as_nanos() / interval.as_nanos()) as u64;
+            let interval_bytes = flow.interval_bytes.entry(index).or_default();
+            *interval_bytes = interval_bytes.saturating_add(bytes);
+        }
+    }
+
+    /// The flows sorted by source and destination address.
+    fn sorted(&self) -> Vec<((IpAddr, IpAddr), FlowVolume)> {
+        let flows = self.flows.lock().unwrap();
+        let mut flows: Vec<_> = flows.iter().map(|(k, v)| (*k, v.clone())).collect();
+        flows.sort_by_key(|(k, _)| *k);
+        flows
+    }
+}
+
+/// Write the topology and flows to the DOT and GEXF files in directory `dir`. If `ns3_stop_time`
+/// is set, also write an ns-3 scenario that runs until that time.
+pub fn export(
+    dir: &Path,
+    topology: &Topology,
+    flows: &FlowVolumes,
+    ns3_stop_time: Option<SimulationTime>,
+) -> anyhow::Result<()> {
+    write_file(&dir.join(TOPOLOGY_DOT_FILE_NAME), |w| {
+        write_topology_dot(topology, w)
+    })?;
+    write_file(&dir.join(FLOWS_DOT_FILE_NAME), |w| {
+        write_flows_dot(topology, flows, w)
+    })?;
+    write_file(&dir.join(GEXF_FILE_NAME), |w| {
+        write_gexf(topology, flows, w)
+    })?;
+
+    if let Some(stop_time) = ns3_stop_time {
+        write_file(&dir.join(NS3_SCRIPT_FILE_NAME), |w| {
+            write_ns3_script(topology, flows, stop_time, w)
+        })?;
+        write_file(&dir.join(NS3_HOSTS_FILE_NAME), |w| {
+            write_ns3_hosts(topology, w)
+        })?;
+    }
+
+    Ok(())
+}
+
+fn write_file(
+    path: &Path,
+    f: impl FnOnce(&mut dyn Write) -> std::io::Result<()>,
+) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create file '{}'", path.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    f(&mut writer)
+        .and_then(|_| writer.flush())
+        .with_context(|| format!("Failed to write file '{}'", path.display()))
+}
+
+/// Write the network graph and the hosts attached to each node as a DOT graph.
+pub fn write_topology_dot(topology: &Topology, w: &mut dyn Write) -> std::io::Result<()> {
+    let (graph_type, edge_op) = if topology.directed {
+        ("digraph", "->")
+    } else {
+        ("graph", "--")
+    };
+
+    writeln!(w, "{graph_type} topology {{")?;
+
+    for node in &topology.nodes {
+        let mut label = format!("node {}", node.id);
+        if let Some(bw) = node.bandwidth_down_bits {
+            label.push_str(&format!("\\ndown: {bw} bit/s"));
+        }
+        if let Some(bw) = node.bandwidth_up_bits {
+            label.push_str(&format!("\\nup: {bw} bit/s"));
+        }
+        writeln!(w, "  \"n{}\" [shape=ellipse, label=\"{label}\"];", node.id)?;
+    }
+
+    for host in &topology.hosts {
+        writeln!(
+            w,
+            "  \"h:{name}\" [shape=box, label=\"{name}\\n{ip}\\ndown: {down} bit/s\\nup: {up} bit/s\"];",
+            name = dot_escape(&host.name),
+            ip = host.ip_addr,
+            down = host.bandwidth_down_bits,
+            up = host.bandwidth_up_bits,
+        )?;
+        writeln!(
+            w,
+            "  \"h:{}\" {edge_op} \"n{}\" [style=dashed, dir=none];",
+            dot_escape(&host.name),
+            host.node_id,
+        )?;
+    }
+
+    for link in &topology.links {
+        writeln!(
+            w,
+            "  \"n{}\" {edge_op} \"n{}\" [label=\"{}\", latency=\"{}\", jitter=\"{}\", packet_loss={}];",
+            link.source, link.target, link.latency, link.latency, link.jitter, link.packet_loss,
+        )?;
+    }
+
+    writeln!(w, "}}")
+}
+
+/// Write the hosts and the traffic sent between them as a DOT graph. Only hosts that sent or
+/// received traffic are included.
+pub fn write_flows_dot(
+    topology: &Topology,
+    flows: &FlowVolumes,
+    w: &mut dyn Write,
+) -> std::io::Result<()> {
+    let flows = flows.sorted();
+
+    let mut hosts: Vec<_> = flows
+        .iter()
+        .flat_map(|((src, dst), _)| [topology.host_name(*src), topology.host_name(*dst)])
+        .collect();
+    hosts.sort();
+    hosts.dedup();
+
+    writeln!(w, "digraph flows {{")?;
+
+    for host in &hosts {
+        writeln!(w, "  \"{0}\" [shape=box, label=\"{0}\"];", dot_escape(host))?;
+    }
+
+    for ((src, dst), flow) in &flows {
+        writeln!(
+            w,
+            "  \"{}\" -> \"{}\" [label=\"{} packets\\n{} bytes\", packets={}, bytes={}];",
+            dot_escape(&topology.host_name(*src)),
+            dot_escape(&topology.host_name(*dst)),
+            flow.packets,
+            flow.bytes,
+            flow.packets,
+            flow.bytes,
+        )?;
+    }
+
+    writeln!(w, "}}")
+}
+
+/// Write the network graph, hosts, and flows as a GEXF graph. Graph nodes and hosts are both GEXF
+/// nodes, and links, host attachments, and flows are GEXF edges, distinguished by their `kind`
+/// attribute.
+pub fn write_gexf(
+    topology: &Topology,
+    flows: &FlowVolumes,
+    w: &mut dyn Write,
+) -> std::io::Result<()> {
+    let default_edge_type = if topology.directed {
+        "directed"
+    } else {
+        "undirected"
+    };
+    let mode = if flows.interval.is_some() {
+        r#"mode="dynamic" timeformat="double""#
+    } else {
+        r#"mode="static""#
+    };
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">"#
+    )?;
+    writeln!(w, "  <meta>")?;
+    writeln!(w, "    <creator>Shadow</creator>")?;
+    writeln!(w, "  </meta>")?;
+    writeln!(
+        w,
+        r#"  <graph defaultedgetype="{default_edge_type}" {mode}>"#
+    )?;
+
+    writeln!(w, r#"    <attributes class="node" mode="static">"#)?;
+    gexf_attribute(w, "kind", "string")?;
+    gexf_attribute(w, "ip", "string")?;
+    gexf_attribute(w, "bandwidth_down", "long")?;
+    gexf_attribute(w, "bandwidth_up", "long")?;
+    // the hosts' metadata keys are prefixed so that they can't conflict with the other attributes
+    let metadata_keys: BTreeSet<&String> = topology
+        .hosts
+        .iter()
+        .flat_map(|x| x.metadata.keys())
+        .collect();
+    for key in &metadata_keys {
+        gexf_attribute(w, &xml_escape(&format!("metadata:{key}")), "string")?;
+    }
+    writeln!(w, "    </attributes>")?;
+
+    writeln!(w, r#"    <attributes class="edge" mode="static">"#)?;
+    gexf_attribute(w, "kind", "string")?;
+    gexf_attribute(w, "latency", "string")?;
+    gexf_attribute(w, "jitter", "string")?;
+    gexf_attribute(w, "packet_loss", "float")?;
+    gexf_attribute(w, "packets", "long")?;
+    gexf_attribute(w, "bytes", "long")?;
+    writeln!(w, "    </attributes>")?;
+
+    if flows.interval.is_some() {
+        writeln!(w, r#"    <attributes class="edge" mode="dynamic">"#)?;
+        gexf_attribute(w, "interval_bytes", "long")?;
+        writeln!(w, "    </attributes>")?;
+    }
+
+    writeln!(w, "    <nodes>")?;
+    for node in &topology.nodes {
+        writeln!(w, r#"      <node id="n{0}" label="node {0}">"#, node.id)?;
+        writeln!(w, "        <attvalues>")?;
+        gexf_attvalue(w, "kind", "node")?;
+        if let Some(bw) = node.bandwidth_down_bits {
+            gexf_attvalue(w, "bandwidth_down", bw)?;
+        }
+        if let Some(bw) = node.bandwidth_up_bits {
+            gexf_attvalue(w, "bandwidth_up", bw)?;
+        }
+        writeln!(w, "        </attvalues>")?;
+        writeln!(w, "      </node>")?;
+    }
+    for host in &topology.hosts {
+        let name = xml_escape(&host.name);
+        writeln!(w, r#"      <node id="h:{name}" label="{name}">"#)?;
+        writeln!(w, "        <attvalues>")?;
+        gexf_attvalue(w, "kind", "host")?;
+        gexf_attvalue(w, "ip", host.ip_addr)?;
+        gexf_attvalue(w, "bandwidth_down", host.bandwidth_down_bits)?;
+        gexf_attvalue(w, "bandwidth_up", host.bandwidth_up_bits)?;
+        for (key, value) in &host.metadata {
+            gexf_attvalue(
+                w,
+                &xml_escape(&format!("metadata:{key}")),
+                xml_escape(value),
+            )?;
+        }
+        writeln!(w, "        </attvalues>")?;
+        writeln!(w, "      </node>")?;
+    }
+    writeln!(w, "    </nodes>")?;
+
+    writeln!(w, "    <edges>")?;
+    for (i, link) in topology.links.iter().enumerate() {
+        writeln!(
+            w,
+            r#"      <edge id="link{i}" source="n{}" target="n{}">"#,
+            link.source, link.target,
+        )?;
+        writeln!(w, "        <attvalues>")?;
+        gexf_attvalue(w, "kind", "link")?;
+        gexf_attvalue(w, "latency", link.latency)?;
+        gexf_attvalue(w, "jitter", link.jitter)?;
+        gexf_attvalue(w, "packet_loss", link.packet_loss)?;
+        writeln!(w, "        </attvalues>")?;
+        writeln!(w, "      </edge>")?;
+    }
+    for (i, host) in topology.hosts.iter().enumerate() {
+        writeln!(
+            w,
+            r#"      <edge id="attach{i}" source="h:{}" target="n{}" type="undirected">"#,
+            xml_escape(&host.name),
+            host.node_id,
+        )?;
+        writeln!(w, "        <attvalues>")?;
+        gexf_attvalue(w, "kind", "attachment")?;
+        writeln!(w, "        </attvalues>")?;
+        writeln!(w, "      </edge>")?;
+    }
+    for (i, ((src, dst), flow)) in flows.sorted().iter().enumerate() {
+        writeln!(
+            w,
+            r#"      <edge id="flow{i}" source="h:{}" target="h:{}" type="directed" weight="{}">"#,
+            xml_escape(&topology.host_name(*src)),
+            xml_escape(&topology.host_name(*dst)),
+            flow.bytes,
+        )?;
+        writeln!(w, "        <attvalues>")?;
+        gexf_attvalue(w, "kind", "flow")?;
+        gexf_attvalue(w, "packets", flow.packets)?;
+        gexf_attvalue(w, "bytes", flow.bytes)?;
+        if let Some(interval) = flows.interval {
+            // GEXF times are in seconds
+            let interval = interval.as_nanos_f64() / 1e9;
+            for (index, bytes) in &flow.interval_bytes {
+                writeln!(
+                    w,
+                    r#"          <attvalue for="interval_bytes" value="{bytes}" start="{}" end="{}"/>"#,
+                    *index as f64 * interval,
+                    (*index + 1) as f64 * interval,
+                )?;
+            }
+        }
+        writeln!(w, "        </attvalues>")?;
+        writeln!(w, "      </edge>")?;
+    }
+    writeln!(w, "    </edges>")?;
+
+    writeln!(w, "  </graph>")?;
+    writeln!(w, "</gexf>")
+}
+
+/// The addresses of the two ends of the `index`th link in the /30 networks starting at `network`.
+fn ns3_link_addresses(network: Ipv4Addr, index: usize) -> (Ipv4Addr, Ipv4Addr) {
+    let base = u32::from(network) + u32::try_from(index).unwrap() * 4;
+    (Ipv4Addr::from(base + 1), Ipv4Addr::from(base + 2))
+}
+
+/// The ns-3 address of the `index`th host, in the order of the topology's hosts.
+fn ns3_host_address(index: usize) -> Ipv4Addr {
+    ns3_link_addresses(NS3_HOST_NETWORK, index).0
+}
+
+/// Write the topology and flows as an ns-3 C++ scenario. Each graph node is a router, and each
+/// link between two graph nodes is a point-to-point link whose delay is the link's latency and
+/// whose routing metric is proportional to it. Each host is attached to its router by a
+/// point-to-point link with the host's bandwidths and half of the latency of its graph node's
+/// self-loop. Each flow is replayed as TCP bulk transfers of its payload bytes, one per flow
+/// interval if a flow interval is configured. The scenario writes ns-3's flow monitor statistics
+/// to `ns3-flows.xml`.
+pub fn write_ns3_script(
+    topology: &Topology,
+    flows: &FlowVolumes,
+    stop_time: SimulationTime,
+    w: &mut dyn Write,
+) -> std::io::Result<()> {
+    let nanos = |x: units::Time<units::TimePrefix>| std::time::Duration::from(x).as_nanos();
+
+    let node_index: HashMap<u32, usize> = topology
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id, i))
+        .collect();
+
+    // ns-3 links are bidirectional, so the links of a directed graph in both directions between
+    // the same nodes are combined
+    let mut links: BTreeMap<(u32, u32), Vec<&TopologyLink>> = BTreeMap::new();
+    let mut self_loops: HashMap<u32, &TopologyLink> = HashMap::new();
+    for link in &topology.links {
+        if link.source == link.target {
+            self_loops.insert(link.source, link);
+        } else {
+            let key = (link.source.min(link.target), link.source.max(link.target));
+            links.entry(key).or_default().push(link);
+        }
+    }
+
+    writeln!(
+        w,
+        "// An ns-3 scenario exported by Shadow. Copy it to the 'scratch' directory of ns-3 and"
+    )?;
+    writeln!(w, "// run it with './ns3 run scratch/ns3-scenario'.")?;
+    writeln!(w)?;
+    writeln!(w, r#"#include "ns3/applications-module.h""#)?;
+    writeln!(w, r#"#include "ns3/core-module.h""#)?;
+    writeln!(w, r#"#include "ns3/flow-monitor-module.h""#)?;
+    writeln!(w, r#"#include "ns3/internet-module.h""#)?;
+    writeln!(w, r#"#include "ns3/network-module.h""#)?;
+    writeln!(w, r#"#include "ns3/point-to-point-module.h""#)?;
+    writeln!(w)?;
+    writeln!(w, "using namespace ns3;")?;
+    writeln!(w)?;
+    writeln!(w, "static const uint16_t SINK_PORT = {NS3_SINK_PORT};")?;
+    writeln!(
+        w,
+        "static const uint64_t LINK_DATA_RATE = {NS3_LINK_DATA_RATE_BITS};"
+    )?;
+    writeln!(w)?;
+    writeln!(
+        w,
+        "{}",
+        r#"static void
+SetLoss(Ptr<NetDevice> device, double rate)
+{
+    if (rate > 0)
+    {
+        Ptr<RateErrorModel> model = CreateObject<RateErrorModel>();
+        model->SetAttribute("ErrorUnit", EnumValue(RateErrorModel::ERROR_UNIT_PACKET));
+        model->SetAttribute("ErrorRate", DoubleValue(rate));
+        device->SetAttribute("ReceiveErrorModel", PointerValue(model));
+    }
+}
+
+static NetDeviceContainer
+Connect(Ptr<Node> a,
+        Ptr<Node> b,
+        uint64_t rateAB,
+        uint64_t rateBA,
+        uint64_t delayNs,
+        double lossAB,
+        double lossBA)
+{
+    PointToPointHelper p2p;
+    p2p.SetChannelAttribute("Delay", TimeValue(NanoSeconds(delayNs)));
+    NetDeviceContainer devices = p2p.Install(a, b);
+    devices.Get(0)->SetAttribute("DataRate", DataRateValue(DataRate(rateAB)));
+    devices.Get(1)->SetAttribute("DataRate", DataRateValue(DataRate(rateBA)));
+    SetLoss(devices.Get(1), lossAB);
+    SetLoss(devices.Get(0), lossBA);
+    return devices;
+}
+
+static void
+AddAddress(Ptr<NetDevice> device, const char* address, uint16_t metric)
+{
+    Ptr<Ipv4> ipv4 = device->GetNode()->GetObject<Ipv4>();
+    uint32_t interface = ipv4->AddInterface(device);
+    ipv4->AddAddress(interface,
+                     Ipv4InterfaceAddress(Ipv4Address(address), Ipv4Mask("255.255.255.252")));
+    ipv4->SetMetric(interface, metric);
+    ipv4->SetUp(interface);
+}
+
+static void
+Send(Ptr<Node> src, const char* dst, uint64_t bytes, double start)
+{
+    BulkSendHelper sender("ns3::TcpSocketFactory",
+                          InetSocketAddress(Ipv4Address(dst), SINK_PORT));
+    sender.SetAttribute("MaxBytes", UintegerValue(bytes));
+    sender.Install(src).Start(Seconds(start));
+}
+"#
+    )?;
+
+    writeln!(w, "int")?;
+    writeln!(w, "main(int argc, char* argv[])")?;
+    writeln!(w, "{{")?;
+    writeln!(
+        w,
+        "    double stopTime = {};",
+        stop_time.as_nanos_f64() / 1e9
+    )?;
+    writeln!(w, "    CommandLine cmd(__FILE__);")?;
+    writeln!(
+        w,
+        r#"    cmd.AddValue("stopTime", "Simulation stop time in seconds", stopTime);"#
+    )?;
+    writeln!(w, "    cmd.Parse(argc, argv);")?;
+    writeln!(w)?;
+    writeln!(w, "    NodeContainer routers;")?;
+    writeln!(w, "    routers.Create({});", topology.nodes.len())?;
+    writeln!(w, "    NodeContainer hosts;")?;
+    writeln!(w, "    hosts.Create({});", topology.hosts.len())?;
+    writeln!(w, "    InternetStackHelper internet;")?;
+    writeln!(w, "    internet.Install(routers);")?;
+    writeln!(w, "    internet.Install(hosts);")?;
+
+    for (i, ((a, b), directions)) in links.iter().enumerate() {
+        let delay =
+            directions.iter().map(|x| nanos(x.latency)).sum::<u128>() / directions.len() as u128;
+        // ns-3 routing metrics are 16 bits, so use units of 0.1 ms
+        let metric = (delay / 100_000).clamp(1, u16::MAX.into());
+        let loss = |source: u32| {
+            directions
+                .iter()
+                .find(|x| x.source == source || !topology.directed)
+                .map_or(0.0, |x| x.packet_loss)
+        };
+        let (addr_a, addr_b) = ns3_link_addresses(NS3_LINK_NETWORK, i);
+
+        writeln!(w)?;
+        writeln!(w, "    // graph node {a} to graph node {b}")?;
+        writeln!(w, "    {{")?;
+        writeln!(
+            w,
+            "        NetDeviceContainer d = Connect(routers.Get({}), routers.Get({}), LINK_DATA_RATE, \
+            LINK_DATA_RATE, {delay}, {}, {});",
+            node_index[a],
+            node_index[b],
+            loss(*a),
+            loss(*b),
+        )?;
+        writeln!(w, r#"        AddAddress(d.Get(0), "{addr_a}", {metric});"#)?;
+        writeln!(w, r#"        AddAddress(d.Get(1), "{addr_b}", {metric});"#)?;
+        writeln!(w, "    }}")?;
+    }
+
+    for (i, host) in topology.hosts.iter().enumerate() {
+        let delay = self_loops
+            .get(&host.node_id)
+            .map_or(0, |x| nanos(x.latency) / 2);
+        let (addr_host, addr_router) = ns3_link_addresses(NS3_HOST_NETWORK, i);
+
+        writeln!(w)?;
+        writeln!(
+            w,
+            "    // host {} ({}) on graph node {}",
+            host.name, host.ip_addr, host.node_id
+        )?;
+        writeln!(w, "    {{")?;
+        writeln!(
+            w,
+            "        NetDeviceContainer d = Connect(hosts.Get({i}), routers.Get({}), {}, {}, {delay}, \
+            0, 0);",
+            node_index[&host.node_id], host.bandwidth_up_bits, host.bandwidth_down_bits,
+        )?;
+        writeln!(w, r#"        AddAddress(d.Get(0), "{addr_host}", 1);"#)?;
+        writeln!(w, r#"        AddAddress(d.Get(1), "{addr_router}", 1);"#)?;
+        writeln!(w, "    }}")?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "    Ipv4GlobalRoutingHelper::PopulateRoutingTables();")?;
+    writeln!(w)?;
+    writeln!(
+        w,
+        r#"    PacketSinkHelper sink("ns3::TcpSocketFactory", InetSocketAddress(Ipv4Address::GetAny(), SINK_PORT));"#
+    )?;
+    writeln!(w, "    sink.Install(hosts).Start(Seconds(0));")?;
+    writeln!(w)?;
+
+    let host_index = |ip: IpAddr| topology.hosts.iter().position(|x| x.ip_addr == ip);
+    for ((src, dst), flow) in flows.sorted() {
+        // traffic from or to addresses that aren't hosts can't be replayed
+        let (Some(src), Some(dst)) = (host_index(src), host_index(dst)) else {
+            continue;
+        };
+        let dst_addr = ns3_host_address(dst);
+
+        let transfers: Vec<(f64, u64)> = match flows.interval {
+            Some(interval) => flow
+                .interval_bytes
+                .iter()
+                .map(|(index, bytes)| (*index as f64 * interval.as_nanos_f64() / 1e9, *bytes))
+                .collect(),
+            None => vec![(0.0, flow.bytes)],
+        };
+        for (start, bytes) in transfers {
+            // for example the flow of a TCP connection's acknowledgements
+            if bytes == 0 {
+                continue;
+            }
+            writeln!(
+                w,
+                r#"    Send(hosts.Get({src}), "{dst_addr}", {bytes}, {start});"#
+            )?;
+        }
+    }
+
+    writeln!(w)?;
+    writeln!(w, "    FlowMonitorHelper flowMonitor;")?;
+    writeln!(
+        w,
+        "    Ptr<FlowMonitor> monitor = flowMonitor.InstallAll();"
+    )?;
+    writeln!(w, "    Simulator::Stop(Seconds(stopTime));")?;
+    writeln!(w, "    Simulator::Run();")?;
+    writeln!(
+        w,
+        r#"    monitor->SerializeToXmlFile("ns3-flows.xml", true, false);"#
+    )?;
+    writeln!(w, "    Simulator::Destroy();")?;
+    writeln!(w, "    return 0;")?;
+    writeln!(w, "}}")
+}
+
+/// Write the Shadow and ns-3 addresses of each host in the ns-3 scenario as a CSV file.
+pub fn write_ns3_hosts(topology: &Topology, w: &mut dyn Write) -> std::io::Result<()> {
+    writeln!(w, "host,shadow_ip,ns3_ip")?;
+    for (i, host) in topology.hosts.iter().enumerate() {
+        writeln!(w, "{},{},{}", host.name, host.ip_addr, ns3_host_address(i))?;
+    }
+    Ok(())
+}
+
+fn gexf_attribute(w: &mut dyn Write, id: &str, ty: &str) -> std::io::Result<()> {
+    writeln!(
+        w,
+        r#"      <attribute id="{id}" title="{id}" type="{ty}"/>"#
+    )
+}
+
+fn gexf_attvalue(
+    w: &mut dyn Write,
+    id: &str,
+    value: impl std::fmt::Display,
+) -> std::io::Result<()> {
+    writeln!(w, r#"          <attvalue for="{id}" value="{value}"/>"#)
+}
+
+/// Escape a string for use within a double-quoted DOT identifier.
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escape a string for use within an XML attribute value.
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topology() -> Topology {
+        let graph = r#"graph [
+            directed 0
+            node [
+                id 0
+                host_bandwidth_down "1 Gbit"
+                host_bandwidth_up "1 Gbit"
+            ]
+            node [
+                id 1
+            ]
+            edge [
+                source 0
+                target 1
+                latency "10 ms"
+                packet_loss 0.0
+            ]
+        ]"#;
+        let graph = NetworkGraph::parse(graph).unwrap();
+
+        let hosts = vec![
+            TopologyHost {
+                name: "server".into(),
+                ip_addr: "11.0.0.2".parse().unwrap(),
+                node_id: 1,
+                bandwidth_down_bits: 1000,
+                bandwidth_up_bits: 2000,
+                metadata: BTreeMap::from([("country".into(), "DE".into())]),
+            },
+            TopologyHost {
+                name: "client".into(),
+                ip_addr: "11.0.0.1".parse().unwrap(),
+                node_id: 0,
+                bandwidth_down_bits: 1000,
+                bandwidth_up_bits: 1000,
+                metadata: BTreeMap::new(),
+            },
+        ];
+
+        Topology::new(&graph, hosts)
+    }
+
+    #[test]
+    fn test_flow_volumes() {
+        let flows = FlowVolumes::new(Some(SimulationTime::SECOND));
+        let src = "11.0.0.1".parse().unwrap();
+        let dst = "11.0.0.2".parse().unwrap();
+
+        flows.add(src, dst, SimulationTime::from_millis(100), 10);
+        flows.add(src, dst, SimulationTime::from_millis(900), 20);
+        flows.add(src, dst, SimulationTime::from_millis(2500), 30);
+        flows.add(dst, src, SimulationTime::from_millis(0), 0);
+
+        let sorted = flows.sorted();
+        assert_eq!(sorted.len(), 2);
+
+        let ((s, d), flow) = &sorted[0];
+        assert_eq!((*s, *d), (src, dst));
+        assert_eq!(flow.packets, 3);
+        assert_eq!(flow.bytes, 60);
+        assert_eq!(flow.interval_bytes, BTreeMap::from([(0, 30), (2, 30)]));
+
+        let (_, flow) = &sorted[1];
+        assert_eq!(flow.packets, 1);
+        assert_eq!(flow.bytes, 0);
+    }
+
+    #[test]
+    fn test_topology_dot() {
+        let mut out = Vec::new();
+        write_topology_dot(&topology(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("graph topology {\n"));
+        assert!(out.contains(r#""n0" [shape=ellipse, label="node 0\ndown: 1000000000 bit/s"#));
+        assert!(out.contains(r#""h:client" -- "n0" [style=dashed, dir=none];"#));
+        assert!(out.contains(r#""n0" -- "n1" [label="10 ms""#));
+        // hosts are sorted by name
+        assert!(out.find("h:client").unwrap() < out.find("h:server").unwrap());
+    }
+
+    #[test]
+    fn test_flows_dot_and_gexf() {
+        let topology = topology();
+        let flows = FlowVolumes::new(Some(SimulationTime::from_millis(500)));
+        flows.add(
+            "11.0.0.1".parse().unwrap(),
+            "11.0.0.2".parse().unwrap(),
+            SimulationTime::from_millis(600),
+            100,
+        );
+
+        let mut out = Vec::new();
+        write_flows_dot(&topology, &flows, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(r#""client" -> "server" [label="1 packets\n100 bytes""#));
+
+        let mut out = Vec::new();
+        write_gexf(&topology, &flows, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(r#"mode="dynamic""#));
+        assert!(out.contains(r#"<edge id="link0" source="n0" target="n1">"#));
+        assert!(out.contains(r#"source="h:client" target="h:server" type="directed""#));
+        assert!(out.contains(r#"<attvalue for="interval_bytes" value="100" start="0.5" end="1"/>"#));
+        assert!(out.contains(
+            r#"<attribute id="metadata:country" title="metadata:country" type="string"/>"#
+        ));
+        assert!(out.contains(r#"<attvalue for="metadata:country" value="DE"/>"#));
+    }
+
+    #[test]
+    fn test_ns3_script() {
+        let topology = topology();
+        let flows = FlowVolumes::new(None);
+        let client = "11.0.0.1".parse().unwrap();
+        let server = "11.0.0.2".parse().unwrap();
+        flows.add(client, server, SimulationTime::from_millis(600), 100);
+        flows.add(server, client, SimulationTime::from_millis(700), 0);
+
+        let mut out = Vec::new();
+        write_ns3_script(&topology, &flows, SimulationTime::from_secs(10), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("    double stopTime = 10;\n"));
+        assert!(out.contains("    routers.Create(2);\n"));
+        assert!(out.contains("    hosts.Create(2);\n"));
+        assert!(out.contains(
+            "Connect(routers.Get(0), routers.Get(1), LINK_DATA_RATE, LINK_DATA_RATE, 10000000, 0, 0);"
+        ));
+        assert!(out.contains(r#"AddAddress(d.Get(0), "172.16.0.1", 100);"#));
+        // hosts are sorted by name, so the client is the first host
+        assert!(out.contains("Connect(hosts.Get(0), routers.Get(0), 1000, 1000, 0, 0, 0);"));
+        assert!(out.contains(r#"AddAddress(d.Get(0), "10.0.0.5", 1);"#));
+        assert!(out.contains("    Send(hosts.Get(0), \"10.0.0.5\", 100, 0);\n"));
+        // flows without payload aren't replayed
+        assert_eq!(out.matches("    Send(").count(), 1);
+
+        let mut out = Vec::new();
+        write_ns3_hosts(&topology, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "host,shadow_ip,ns3_ip\nclient,11.0.0.1,10.0.0.1\nserver,11.0.0.2,10.0.0.5\n"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(dot_escape(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(xml_escape(r#"<a&'b">"#), "&lt;a&amp;&apos;b&quot;&gt;");
+    }
+}
@@ -0,0 +1,346 @@
+//! Generate network graphs from random graph models and datacenter topologies. The generated graph
+//! is written as GML so that it's processed in the same way as a graph read from a file.
+//!
+//! The generators are seeded from the configuration, so the same options always generate the same
+//! graph. Every node has a self-loop (which Shadow requires for hosts on the same node to
+//! communicate), and all links have the same latency and packet loss.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::core::support::configuration::{GeneratedGraphOptions, GraphModel};
+
+use super::NetGraphError;
+
+/// Generate a graph as GML text. The graph is generated using `options.seed`, or `default_seed` if
+/// not set.
+pub fn generate_gml(
+    options: &GeneratedGraphOptions,
+    default_seed: u64,
+) -> Result<String, NetGraphError> {
+    if !(0.0..=1.0).contains(&options.packet_loss) {
+        return Err("The generated graph's 'packet_loss' is not in the range [0,1]".into());
+    }
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(options.seed.unwrap_or(default_seed));
+
+    let (num_nodes, edges) = match options.model {
+        GraphModel::ErdosRenyi {
+            nodes,
+            edge_probability,
+        } => {
+            check_probability("edge_probability", edge_probability)?;
+            check_nodes(nodes, 1)?;
+            let mut edges = erdos_renyi(nodes, edge_probability, &mut rng);
+            connect_components(nodes, &mut edges);
+            (nodes, edges)
+        }
+        GraphModel::BarabasiAlbert {
+            nodes,
+            edges_per_node,
+        } => {
+            if edges_per_node == 0 {
+                return Err("The 'edges_per_node' must be at least 1".into());
+            }
+            check_nodes(nodes, edges_per_node + 1)?;
+            (nodes, barabasi_albert(nodes, edges_per_node, &mut rng))
+        }
+        GraphModel::WattsStrogatz {
+            nodes,
+            neighbors,
+            rewire_probability,
+        } => {
+            check_probability("rewire_probability", rewire_probability)?;
+            if neighbors == 0 || neighbors % 2 != 0 {
+                return Err("The 'neighbors' must be a positive even number".into());
+            }
+            check_nodes(nodes, neighbors + 1)?;
+            let mut edges = watts_strogatz(nodes, neighbors, rewire_probability, &mut rng);
+            connect_components(nodes, &mut edges);
+            (nodes, edges)
+        }
+        GraphModel::FatTree { k } => {
+            if k == 0 || k % 2 != 0 {
+                return Err("The fat-tree 'k' must be a positive even number".into());
+            }
+            fat_tree(k)
+        }
+    };
+
+    Ok(write_gml(num_nodes, &edges, options))
+}
+
+fn check_probability(name: &str, p: f64) -> Result<(), NetGraphError> {
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("The '{name}' is not in the range [0,1]").into());
+    }
+    Ok(())
+}
+
+fn check_nodes(nodes: u32, min: u32) -> Result<(), NetGraphError> {
+    if nodes < min {
+        return Err(format!("The generated graph needs at least {min} nodes").into());
+    }
+    Ok(())
+}
+
+/// An undirected edge, with the smaller node ID first.
+fn edge(a: u32, b: u32) -> (u32, u32) {
+    (std::cmp::min(a, b), std::cmp::max(a, b))
+}
+
+fn erdos_renyi(nodes: u32, p: f64, rng: &mut impl Rng) -> BTreeSet<(u32, u32)> {
+    let mut edges = BTreeSet::new();
+    for a in 0..nodes {
+        for b in (a + 1)..nodes {
+            if rng.gen_bool(p) {
+                edges.insert((a, b));
+            }
+        }
+    }
+    edges
+}
+
+fn barabasi_albert(nodes: u32, m: u32, rng: &mut impl Rng) -> BTreeSet<(u32, u32)> {
+    let mut edges = BTreeSet::new();
+
+    // each node appears once for each of its edges, so that choosing a random element chooses a
+    // node with probability proportional to its degree
+    let mut degree_list = Vec::new();
+
+    // start with a complete graph of m+1 nodes
+    for a in 0..=m {
+        for b in (a + 1)..=m {
+            edges.insert((a, b));
+            degree_list.extend([a, b]);
+        }
+    }
+
+    for new in (m + 1)..nodes {
+        let mut targets = BTreeSet::new();
+        while targets.len() < m as usize {
+            targets.insert(degree_list[rng.gen_range(0..degree_list.len())]);
+        }
+        for target in targets {
+            edges.insert(edge(new, target));
+            degree_list.extend([new, target]);
+        }
+    }
+
+    edges
+}
+
+fn watts_strogatz(nodes: u32, k: u32, beta: f64, rng: &mut impl Rng) -> BTreeSet<(u32, u32)> {
+    // a ring lattice where each node is linked to its k/2 nearest nodes on each side
+    let mut edges = BTreeSet::new();
+    for a in 0..nodes {
+        for offset in 1..=(k / 2) {
+            edges.insert(edge(a, (a + offset) % nodes));
+        }
+    }
+
+    let mut degrees = vec![k; nodes as usize];
+
+    // rewire the link to the node `offset` positions away from each node, for increasing offsets
+    for offset in 1..=(k / 2) {
+        for a in 0..nodes {
+            if !rng.gen_bool(beta) {
+                continue;
+            }
+
+            if degrees[a as usize] >= nodes - 1 {
+                // already linked to every other node
+                continue;
+            }
+
+            let old = (a + offset) % nodes;
+            let new = loop {
+                let b = rng.gen_range(0..nodes);
+                if b != a && !edges.contains(&edge(a, b)) {
+                    break b;
+                }
+            };
+            edges.remove(&edge(a, old));
+            edges.insert(edge(a, new));
+            degrees[old as usize] -= 1;
+            degrees[new as usize] += 1;
+        }
+    }
+
+    edges
+}
+
+/// A `k`-ary fat-tree. The edge switches are numbered first so that hosts can be attached to
+/// nodes `0` to `k*k/2 - 1`, followed by the aggregation switches and the core switches.
+fn fat_tree(k: u32) -> (u32, BTreeSet<(u32, u32)>) {
+    let half = k / 2;
+    let num_edge = k * half;
+    let num_agg = k * half;
+    let num_core = half * half;
+
+    let edge_switch = |pod: u32, i: u32| pod * half + i;
+    let agg_switch = |pod: u32, i: u32| num_edge + pod * half + i;
+    let core_switch = |i: u32| num_edge + num_agg + i;
+
+    let mut edges = BTreeSet::new();
+    for pod in 0..k {
+        for agg in 0..half {
+            // each aggregation switch is linked to every edge switch in its pod
+            for e in 0..half {
+                edges.insert(edge(edge_switch(pod, e), agg_switch(pod, agg)));
+            }
+            // and to its group of core switches
+            for c in 0..half {
+                edges.insert(edge(agg_switch(pod, agg), core_switch(agg * half + c)));
+            }
+        }
+    }
+
+    (num_edge + num_agg + num_core, edges)
+}
+
+/// Link the components of the graph together so that every node is reachable. The components are
+/// linked in order of their smallest node.
+fn connect_components(nodes: u32, edges: &mut BTreeSet<(u32, u32)>) {
+    fn find(parents: &mut [u32], x: u32) -> u32 {
+        let mut root = x;
+        while parents[root as usize] != root {
+            root = parents[root as usize];
+        }
+        parents[x as usize] = root;
+        root
+    }
+
+    let mut parents: Vec<u32> = (0..nodes).collect();
+    for (a, b) in edges.iter() {
+        let (a, b) = (find(&mut parents, *a), find(&mut parents, *b));
+        parents[std::cmp::max(a, b) as usize] = std::cmp::min(a, b);
+    }
+
+    // since each component's root is its smallest node, the roots are in order
+    let roots: Vec<u32> = (0..nodes)
+        .filter(|x| find(&mut parents, *x) == *x)
+        .collect();
+    for pair in roots.windows(2) {
+        edges.insert((pair[0], pair[1]));
+    }
+}
+
+fn write_gml(nodes: u32, edges: &BTreeSet<(u32, u32)>, options: &GeneratedGraphOptions) -> String {
+    let mut gml = String::new();
+    let bandwidth = &options.bandwidth;
+    let latency = &options.latency;
+    let packet_loss = options.packet_loss;
+
+    writeln!(gml, "graph [").unwrap();
+    writeln!(gml, "  directed 0").unwrap();
+    for id in 0..nodes {
+        writeln!(gml, "  node [").unwrap();
+        writeln!(gml, "    id {id}").unwrap();
+        writeln!(gml, "    host_bandwidth_up \"{bandwidth}\"").unwrap();
+        writeln!(gml, "    host_bandwidth_down \"{bandwidth}\"").unwrap();
+        writeln!(gml, "  ]").unwrap();
+    }
+    let self_loops = (0..nodes).map(|x| (x, x));
+    for (source, target) in self_loops.chain(edges.iter().copied()) {
+        writeln!(gml, "  edge [").unwrap();
+        writeln!(gml, "    source {source}").unwrap();
+        writeln!(gml, "    target {target}").unwrap();
+        writeln!(gml, "    latency \"{latency}\"").unwrap();
+        writeln!(gml, "    packet_loss {packet_loss:?}").unwrap();
+        writeln!(gml, "  ]").unwrap();
+    }
+    writeln!(gml, "]").unwrap();
+
+    gml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::support::units;
+    use crate::network::graph::NetworkGraph;
+
+    fn options(model: GraphModel) -> GeneratedGraphOptions {
+        GeneratedGraphOptions {
+            model,
+            latency: units::Time::new(10, units::TimePrefix::Milli),
+            bandwidth: units::BitsPerSec::new(1, units::SiPrefixUpper::Giga),
+            packet_loss: 0.0,
+            seed: None,
+        }
+    }
+
+    /// Check that the graph is valid, and return its number of nodes and edges (not including
+    /// self-loops).
+    fn check(gml: &str) -> (usize, usize) {
+        let graph = NetworkGraph::parse(gml).unwrap();
+        let nodes: Vec<_> = (0..)
+            .map_while(|x| graph.node_id_to_index(x).copied())
+            .collect();
+
+        // every node must be reachable from every other node
+        let paths = graph.compute_shortest_paths(&nodes).unwrap();
+        assert_eq!(paths.len(), nodes.len().pow(2));
+
+        let num_edges = gml.matches("edge [").count() - nodes.len();
+        (nodes.len(), num_edges)
+    }
+
+    #[test]
+    fn test_erdos_renyi() {
+        let options = options(GraphModel::ErdosRenyi {
+            nodes: 30,
+            edge_probability: 0.05,
+        });
+        let gml = generate_gml(&options, 1).unwrap();
+        assert_eq!(check(&gml).0, 30);
+
+        // deterministic for a given seed
+        assert_eq!(gml, generate_gml(&options, 1).unwrap());
+        assert_ne!(gml, generate_gml(&options, 2).unwrap());
+    }
+
+    #[test]
+    fn test_barabasi_albert() {
+        let options = options(GraphModel::BarabasiAlbert {
+            nodes: 50,
+            edges_per_node: 2,
+        });
+        let gml = generate_gml(&options, 1).unwrap();
+        // 3 edges in the initial complete graph, then 2 for each new node
+        assert_eq!(check(&gml), (50, 3 + 47 * 2));
+    }
+
+    #[test]
+    fn test_watts_strogatz() {
+        let ring = options(GraphModel::WattsStrogatz {
+            nodes: 40,
+            neighbors: 4,
+            rewire_probability: 0.2,
+        });
+        let gml = generate_gml(&ring, 1).unwrap();
+        let (nodes, edges) = check(&gml);
+        assert_eq!(nodes, 40);
+        // rewiring keeps the number of edges, but components may be linked with extra edges
+        assert!(edges >= 80);
+
+        let odd_neighbors = options(GraphModel::WattsStrogatz {
+            nodes: 40,
+            neighbors: 3,
+            rewire_probability: 0.2,
+        });
+        assert!(generate_gml(&odd_neighbors, 1).is_err());
+    }
+
+    #[test]
+    fn test_fat_tree() {
+        let gml = generate_gml(&options(GraphModel::FatTree { k: 4 }), 1).unwrap();
+        // 8 edge, 8 aggregation, and 4 core switches, with k^3/2 links
+        assert_eq!(check(&gml), (20, 32));
+        assert!(generate_gml(&options(GraphModel::FatTree { k: 3 }), 1).is_err());
+    }
+}
@@ -1,3 +1,8 @@
+mod as_routing;
+pub mod censors;
+pub mod export;
+mod generate;
+pub mod observers;
 mod petgraph_wrapper;
 
 use std::collections::hash_map::Entry;
@@ -8,6 +13,7 @@ use std::hash::Hash;
 use anyhow::Context;
 use log::*;
 use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::core::support::configuration::{
@@ -25,12 +31,61 @@ pub struct ShadowNode {
     pub id: u32,
     pub bandwidth_down: Option<units::BitsPerSec<units::SiPrefixUpper>>,
     pub bandwidth_up: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+    /// The autonomous system that the node belongs to.
+    pub asn: Option<u32>,
+    /// The shared LAN segment that the node's hosts are attached to, if the node is a switch.
+    pub switch: Option<SwitchProperties>,
+}
+
+/// Properties of a switch node, whose hosts share a LAN segment and broadcast domain.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SwitchProperties {
+    /// The maximum bandwidth of each of the switch's ports.
+    pub port_bandwidth: Option<units::BitsPerSec<units::SiPrefixUpper>>,
+    /// The bandwidth shared by all hosts attached to the switch, if the hosts contend for it.
+    pub shared_bandwidth: Option<units::BitsPerSec<units::SiPrefixUpper>>,
 }
 
 impl TryFrom<gml_parser::gml::Node<'_>> for ShadowNode {
     type Error = String;
 
     fn try_from(mut gml_node: gml_parser::gml::Node) -> Result<Self, Self::Error> {
+        let mut bandwidth = |name: &str| {
+            gml_node
+                .other
+                .remove(name)
+                .map(|bandwidth| {
+                    bandwidth
+                        .as_str()
+                        .ok_or(format!("Node '{name}' is not a string"))?
+                        .parse()
+                        .map_err(|e| format!("Node '{name}' is not a valid unit: {e}"))
+                })
+                .transpose()
+        };
+
+        let port_bandwidth = bandwidth("port_bandwidth")?;
+        let shared_bandwidth = bandwidth("shared_bandwidth")?;
+
+        let switch = match gml_node.other.remove("type") {
+            None => None,
+            Some(x) => match x.as_str().ok_or("Node 'type' is not a string")? {
+                "router" => None,
+                "switch" => Some(SwitchProperties {
+                    port_bandwidth,
+                    shared_bandwidth,
+                }),
+                x => return Err(format!("Node 'type' '{x}' is not 'router' or 'switch'")),
+            },
+        };
+
+        if switch.is_none() && (port_bandwidth.is_some() || shared_bandwidth.is_some()) {
+            return Err(
+                "Node 'port_bandwidth' and 'shared_bandwidth' are only allowed for switch nodes"
+                    .into(),
+            );
+        }
+
         Ok(Self {
             id: gml_node.id.ok_or("Node 'id' was not provided")?,
             bandwidth_down: gml_node
@@ -57,6 +112,16 @@ impl TryFrom<gml_parser::gml::Node<'_>> for ShadowNode {
                         .map_err(|e| format!("Node 'host_bandwidth_up' is not a valid unit: {}", e))
                 })
                 .transpose()?,
+            asn: gml_node
+                .other
+                .remove("asn")
+                .map(|asn| match asn {
+                    gml_parser::gml::Value::Int(x) => u32::try_from(x).ok(),
+                    _ => None,
+                })
+                .map(|asn| asn.ok_or("Node 'asn' is not a non-negative integer"))
+                .transpose()?,
+            switch,
         })
     }
 }
@@ -69,6 +134,11 @@ pub struct ShadowEdge {
     pub latency: units::Time<units::TimePrefix>,
     pub jitter: units::Time<units::TimePrefix>,
     pub packet_loss: f32,
+    pub packet_duplication: f32,
+    pub packet_reordering: f32,
+    /// The relationship of the target node's autonomous system to the source node's autonomous
+    /// system.
+    pub as_relationship: Option<AsRelationship>,
 }
 
 impl TryFrom<gml_parser::gml::Edge<'_>> for ShadowEdge {
@@ -98,12 +168,41 @@ impl TryFrom<gml_parser::gml::Edge<'_>> for ShadowEdge {
                 Some(x) => x.as_float().ok_or("Edge 'packet_loss' is not a float")?,
                 None => 0.0,
             },
+            packet_duplication: match gml_edge.other.remove("packet_duplication") {
+                Some(x) => x
+                    .as_float()
+                    .ok_or("Edge 'packet_duplication' is not a float")?,
+                None => 0.0,
+            },
+            packet_reordering: match gml_edge.other.remove("packet_reordering") {
+                Some(x) => x
+                    .as_float()
+                    .ok_or("Edge 'packet_reordering' is not a float")?,
+                None => 0.0,
+            },
+            as_relationship: gml_edge
+                .other
+                .remove("as_relationship")
+                .map(|x| {
+                    x.as_str()
+                        .ok_or("Edge 'as_relationship' is not a string")?
+                        .parse()
+                })
+                .transpose()?,
         };
 
         if rv.packet_loss < 0f32 || rv.packet_loss > 1f32 {
             return Err("Edge 'packet_loss' is not in the range [0,1]".into());
         }
 
+        if rv.packet_duplication < 0f32 || rv.packet_duplication > 1f32 {
+            return Err("Edge 'packet_duplication' is not in the range [0,1]".into());
+        }
+
+        if rv.packet_reordering < 0f32 || rv.packet_reordering > 1f32 {
+            return Err("Edge 'packet_reordering' is not in the range [0,1]".into());
+        }
+
         if rv.latency.value() == 0 {
             return Err("Edge 'latency' must not be 0".into());
         }
@@ -112,6 +211,40 @@ impl TryFrom<gml_parser::gml::Edge<'_>> for ShadowEdge {
     }
 }
 
+/// A business relationship between two autonomous systems.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AsRelationship {
+    Customer,
+    Peer,
+    Provider,
+}
+
+impl AsRelationship {
+    /// The relationship in the opposite direction.
+    pub fn reverse(self) -> Self {
+        match self {
+            Self::Customer => Self::Provider,
+            Self::Peer => Self::Peer,
+            Self::Provider => Self::Customer,
+        }
+    }
+}
+
+impl std::str::FromStr for AsRelationship {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "customer" => Ok(Self::Customer),
+            "peer" => Ok(Self::Peer),
+            "provider" => Ok(Self::Provider),
+            _ => Err(format!(
+                "Edge 'as_relationship' must be 'customer', 'peer', or 'provider', not '{s}'"
+            )),
+        }
+    }
+}
+
 /// A network graph containing the petgraph graph and a map from gml node ids to petgraph node
 /// indexes.
 #[derive(Debug)]
@@ -176,10 +309,57 @@ impl NetworkGraph {
             g.add_edge(source, target, x);
         }
 
-        Ok(Self {
+        let graph = Self {
             graph: g,
             node_id_to_index_map: id_map,
-        })
+        };
+        as_routing::validate(&graph)?;
+
+        Ok(graph)
+    }
+
+    /// Returns true if the graph's nodes are grouped into autonomous systems.
+    pub fn has_autonomous_systems(&self) -> bool {
+        match &self.graph {
+            GraphWrapper::Directed(graph) => {
+                graph.raw_nodes().iter().any(|x| x.weight.asn.is_some())
+            }
+            GraphWrapper::Undirected(graph) => {
+                graph.raw_nodes().iter().any(|x| x.weight.asn.is_some())
+            }
+        }
+    }
+
+    /// Compute the paths between nodes using valley-free routing between autonomous systems, which
+    /// approximates the paths chosen by BGP.
+    pub fn compute_as_paths(
+        &self,
+        nodes: &[NodeIndex],
+    ) -> Result<HashMap<(NodeIndex, NodeIndex), PathProperties>, NetGraphError> {
+        let start = std::time::Instant::now();
+
+        let mut paths = nodes
+            .into_par_iter()
+            .map(|src| as_routing::paths_from(self, *src, nodes))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect::<HashMap<(_, _), _>>();
+
+        // use the self-loop for paths from a node to itself
+        for node in nodes {
+            paths.insert((*node, *node), self.get_edge_weight(node, node)?.into());
+        }
+
+        assert_eq!(paths.len(), nodes.len().pow(2));
+
+        debug!(
+            "Finished computing AS-aware paths: {} seconds, {} entries",
+            (std::time::Instant::now() - start).as_secs(),
+            paths.len()
+        );
+
+        Ok(paths)
     }
 
     pub fn compute_shortest_paths(
@@ -229,6 +409,92 @@ impl NetworkGraph {
         Ok(paths)
     }
 
+    /// Get the nodes on the paths from `src` to each node in `nodes`, including `src` and the
+    /// destination node. These are the paths used for routing, so `use_shortest_paths` must be the
+    /// same as when computing the routing information. The path from a node to itself is its
+    /// self-loop.
+    pub fn routes_from(
+        &self,
+        src: NodeIndex,
+        nodes: &[NodeIndex],
+        use_shortest_paths: bool,
+    ) -> Result<Vec<(NodeIndex, Vec<NodeIndex>)>, NetGraphError> {
+        if self.has_autonomous_systems() {
+            return as_routing::routes_from(self, src, nodes);
+        }
+
+        if !use_shortest_paths {
+            return Ok(nodes.iter().map(|dst| (*dst, vec![src, *dst])).collect());
+        }
+
+        let distances = match &self.graph {
+            GraphWrapper::Directed(graph) => {
+                petgraph::algo::dijkstra(&graph, src, None, |e| e.weight().into())
+            }
+            GraphWrapper::Undirected(graph) => {
+                petgraph::algo::dijkstra(&graph, src, None, |e| e.weight().into())
+            }
+        };
+
+        nodes
+            .iter()
+            .map(|dst| {
+                if *dst == src {
+                    return Ok((*dst, vec![src, src]));
+                }
+
+                // walk back from the destination, choosing the lowest-index predecessor that is on
+                // a shortest path so that the route is deterministic
+                let mut route = vec![*dst];
+                let mut node = *dst;
+                while node != src {
+                    let distance = distances.get(&node).ok_or(format!(
+                        "No path to node {}",
+                        self.node_index_to_id(node).unwrap()
+                    ))?;
+                    node = self
+                        .incoming_edges(node)
+                        .into_iter()
+                        .filter(|(prev, edge)| {
+                            *prev != node
+                                && distances
+                                    .get(prev)
+                                    .is_some_and(|x| *x + PathProperties::from(*edge) == *distance)
+                        })
+                        .map(|(prev, _)| prev)
+                        .min()
+                        .unwrap();
+                    route.push(node);
+                }
+                route.reverse();
+
+                Ok((*dst, route))
+            })
+            .collect()
+    }
+
+    /// The edges that end at `node`, along with the node at the other end of each edge.
+    fn incoming_edges(&self, node: NodeIndex) -> Vec<(NodeIndex, &ShadowEdge)> {
+        match &self.graph {
+            GraphWrapper::Directed(graph) => graph
+                .edges_directed(node, petgraph::Direction::Incoming)
+                .map(|e| (e.source(), e.weight()))
+                .collect(),
+            // edges of an undirected graph may be stored in either direction
+            GraphWrapper::Undirected(graph) => graph
+                .edges(node)
+                .map(|e| {
+                    let other = if e.source() == node {
+                        e.target()
+                    } else {
+                        e.source()
+                    };
+                    (other, e.weight())
+                })
+                .collect(),
+        }
+    }
+
     pub fn get_direct_paths(
         &self,
         nodes: &[NodeIndex],
@@ -302,6 +568,10 @@ pub struct PathProperties {
     pub latency_ns: u64,
     /// Packet loss as fraction.
     pub packet_loss: f32,
+    /// Fraction of packets that are delivered twice.
+    pub packet_duplication: f32,
+    /// Fraction of packets that are delayed so that later packets overtake them.
+    pub packet_reordering: f32,
 }
 
 impl PartialOrd for PathProperties {
@@ -328,6 +598,10 @@ impl core::ops::Add for PathProperties {
         Self {
             latency_ns: self.latency_ns + other.latency_ns,
             packet_loss: 1f32 - (1f32 - self.packet_loss) * (1f32 - other.packet_loss),
+            packet_duplication: 1f32
+                - (1f32 - self.packet_duplication) * (1f32 - other.packet_duplication),
+            packet_reordering: 1f32
+                - (1f32 - self.packet_reordering) * (1f32 - other.packet_reordering),
         }
     }
 }
@@ -337,6 +611,8 @@ impl std::convert::From<&ShadowEdge> for PathProperties {
         Self {
             latency_ns: e.latency.convert(units::TimePrefix::Nano).unwrap().value(),
             packet_loss: e.packet_loss,
+            packet_duplication: e.packet_duplication,
+            packet_reordering: e.packet_reordering,
         }
     }
 }
@@ -493,8 +769,11 @@ fn read_xz<P: AsRef<std::path::Path>>(path: P) -> Result<String, NetGraphError>
     Ok(String::from_utf8(decomp)?)
 }
 
-/// Get the network graph as a string.
-pub fn load_network_graph(graph_options: &GraphOptions) -> Result<String, NetGraphError> {
+/// Get the network graph as a string. Generated graphs use `seed` unless they set their own seed.
+pub fn load_network_graph(
+    graph_options: &GraphOptions,
+    seed: u64,
+) -> Result<String, NetGraphError> {
     Ok(match graph_options {
         GraphOptions::Gml(GraphSource::File(FileSource {
             compression: None,
@@ -507,6 +786,7 @@ pub fn load_network_graph(graph_options: &GraphOptions) -> Result<String, NetGra
         })) => read_xz(tilde_expansion(f))?,
         GraphOptions::Gml(GraphSource::Inline(s)) => s.clone(),
         GraphOptions::OneGbitSwitch => configuration::ONE_GBIT_SWITCH_GRAPH.to_string(),
+        GraphOptions::Generated(options) => generate::generate_gml(options, seed)?,
     })
 }
 
@@ -519,15 +799,21 @@ mod tests {
         let p1 = PathProperties {
             latency_ns: 23,
             packet_loss: 0.35,
+            packet_duplication: 0.1,
+            packet_reordering: 0.0,
         };
         let p2 = PathProperties {
             latency_ns: 11,
             packet_loss: 0.85,
+            packet_duplication: 0.2,
+            packet_reordering: 0.5,
         };
 
         let p3 = p1 + p2;
         assert_eq!(p3.latency_ns, 34);
         assert!((p3.packet_loss - 0.9025).abs() < 0.01);
+        assert!((p3.packet_duplication - 0.28).abs() < 0.01);
+        assert!((p3.packet_reordering - 0.5).abs() < 0.01);
     }
 
     #[test]
@@ -558,6 +844,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_packet_faults() {
+        let graph = |duplication: f32, reordering: f32| {
+            format!(
+                r#"graph [
+                  node [
+                    id 0
+                  ]
+                  edge [
+                    source 0
+                    target 0
+                    latency "1 ns"
+                    packet_duplication {duplication:?}
+                    packet_reordering {reordering:?}
+                  ]
+                ]"#
+            )
+        };
+
+        let parsed = NetworkGraph::parse(&graph(0.25, 0.5)).unwrap();
+        let node_0 = *parsed.node_id_to_index(0).unwrap();
+        let edge = parsed.get_edge_weight(&node_0, &node_0).unwrap();
+        assert_eq!(edge.packet_duplication, 0.25);
+        assert_eq!(edge.packet_reordering, 0.5);
+
+        NetworkGraph::parse(&graph(1.5, 0.0)).unwrap_err();
+        NetworkGraph::parse(&graph(0.0, -0.5)).unwrap_err();
+    }
+
+    #[test]
+    fn test_switch_node() {
+        let graph = |attributes: &str| {
+            format!(
+                r#"graph [
+                  node [
+                    id 0
+                    {attributes}
+                  ]
+                  edge [
+                    source 0
+                    target 0
+                    latency "1 ns"
+                  ]
+                ]"#
+            )
+        };
+        let node_0 = |graph: &NetworkGraph| {
+            let index = graph.node_id_to_index(0).unwrap();
+            graph
+                .graph()
+                .node_weight(*index)
+                .unwrap()
+                .switch
+                .as_ref()
+                .map(|x| {
+                    (
+                        x.port_bandwidth.map(|x| x.to_string()),
+                        x.shared_bandwidth.map(|x| x.to_string()),
+                    )
+                })
+        };
+
+        let parsed = NetworkGraph::parse(&graph("")).unwrap();
+        assert_eq!(node_0(&parsed), None);
+
+        let parsed = NetworkGraph::parse(&graph(r#"type "router""#)).unwrap();
+        assert_eq!(node_0(&parsed), None);
+
+        let parsed = NetworkGraph::parse(&graph(r#"type "switch""#)).unwrap();
+        assert_eq!(node_0(&parsed), Some((None, None)));
+
+        let parsed = NetworkGraph::parse(&graph(
+            r#"type "switch" port_bandwidth "1 Gbit" shared_bandwidth "100 Mbit""#,
+        ))
+        .unwrap();
+        assert_eq!(
+            node_0(&parsed),
+            Some((Some("1 Gbit".into()), Some("100 Mbit".into())))
+        );
+
+        NetworkGraph::parse(&graph(r#"type "hub""#)).unwrap_err();
+        NetworkGraph::parse(&graph(r#"port_bandwidth "1 Gbit""#)).unwrap_err();
+    }
+
     // disabled under miri due to https://github.com/rayon-rs/rayon/issues/952
     #[test]
     #[cfg_attr(miri, ignore)]
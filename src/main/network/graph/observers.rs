@@ -0,0 +1,411 @@
+//! Passive observers that record the metadata of packets crossing an edge of the network graph or
+//! an autonomous system, representing a network-level adversary for traffic analysis research.
+//!
+//! An observer records the time that each packet reaches it along with the packet's protocol,
+//! addresses, ports, and size. Observers never see payloads and don't affect the simulation. The
+//! path that each packet takes is computed once when the simulation starts, and the time that a
+//! packet reaches an observer is the time it was sent plus the latency of the path up to the
+//! observer. Packets dropped by the path's packet loss are not recorded. The records are kept in
+//! memory and written to the data directory when the simulation ends, sorted by time, and are
+//! optionally compressed.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use petgraph::graph::NodeIndex;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+use super::petgraph_wrapper::GraphWrapper;
+use super::NetworkGraph;
+use crate::core::support::configuration::{ObserverOptions, OutputCompression};
+use crate::utility::compression::{self, CompressedWriter};
+
+pub const OBSERVERS_DIR_NAME: &str = "observers";
+
+#[derive(Debug)]
+pub struct Observers {
+    observers: Vec<Observer>,
+}
+
+#[derive(Debug)]
+struct Observer {
+    name: String,
+    /// For each pair of source and destination graph nodes whose path crosses the observer, the
+    /// latency in nanoseconds from the source node to the observer.
+    offsets: HashMap<(u32, u32), u64>,
+    records: Mutex<Vec<Record>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Record {
+    /// Nanoseconds of simulated time since the start of the simulation.
+    pub time_ns: u64,
+    pub protocol: &'static str,
+    pub src: SocketAddrV4,
+    pub dst: SocketAddrV4,
+    /// The packet size in bytes, including headers.
+    pub size: usize,
+}
+
+/// Where an observer (or a censor) is in the network graph.
+#[derive(Debug, Copy, Clone)]
+pub(super) enum Tap {
+    Edge(NodeIndex, NodeIndex),
+    AutonomousSystem(u32),
+}
+
+impl Tap {
+    /// A tap on the edge from graph node `source` to graph node `target`. `owner` describes what
+    /// the tap belongs to in error messages.
+    pub(super) fn edge(
+        graph: &NetworkGraph,
+        source: u32,
+        target: u32,
+        owner: &str,
+    ) -> anyhow::Result<Self> {
+        let index = |id: u32| {
+            graph
+                .node_id_to_index(id)
+                .copied()
+                .with_context(|| format!("{owner} has an edge with an unknown node {id}"))
+        };
+        let (source, target) = (index(source)?, index(target)?);
+        if graph.graph().find_edge(source, target).is_none() {
+            anyhow::bail!("{owner} has an edge that isn't in the network graph");
+        }
+        Ok(Tap::Edge(source, target))
+    }
+
+    /// A tap on the nodes of the autonomous system `asn`. `owner` describes what the tap belongs
+    /// to in error messages.
+    pub(super) fn autonomous_system(
+        graph: &NetworkGraph,
+        asn: u32,
+        owner: &str,
+    ) -> anyhow::Result<Self> {
+        let exists = match graph.graph() {
+            GraphWrapper::Directed(g) => g.raw_nodes().iter().any(|x| x.weight.asn == Some(asn)),
+            GraphWrapper::Undirected(g) => g.raw_nodes().iter().any(|x| x.weight.asn == Some(asn)),
+        };
+        if !exists {
+            anyhow::bail!("{owner} has an unknown autonomous system {asn}");
+        }
+        Ok(Tap::AutonomousSystem(asn))
+    }
+}
+
+impl Observers {
+    /// Create the observers from the configuration. `nodes` are the graph nodes that have hosts
+    /// attached, and `use_shortest_paths` must be the same as when computing the routing
+    /// information.
+    pub fn new(
+        options: &[ObserverOptions],
+        graph: &NetworkGraph,
+        nodes: &HashSet<u32>,
+        use_shortest_paths: bool,
+    ) -> anyhow::Result<Self> {
+        let mut names = HashSet::new();
+        let taps = options
+            .iter()
+            .map(|x| {
+                let (name, tap) = tap(x, graph)?;
+                if name.is_empty() || name.contains('/') {
+                    anyhow::bail!("Observer name '{name}' is not a valid file name");
+                }
+                if !names.insert(name) {
+                    anyhow::bail!("Observer name '{name}' is used more than once");
+                }
+                Ok((name, tap))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let tap_list: Vec<Tap> = taps.iter().map(|(_, tap)| *tap).collect();
+        let offsets = tap_offsets(&tap_list, graph, nodes, use_shortest_paths)?;
+
+        let observers = taps
+            .iter()
+            .zip(offsets)
+            .map(|((name, _), offsets)| Observer {
+                name: name.to_string(),
+                offsets,
+                records: Mutex::new(Vec::new()),
+            })
+            .collect();
+
+        Ok(Self { observers })
+    }
+
+    /// Called when a packet is sent from graph node `src_node` to graph node `dst_node` at time
+    /// `time`. `record` is the packet's record, without the time.
+    pub fn packet_sent(
+        &self,
+        src_node: u32,
+        dst_node: u32,
+        time: EmulatedTime,
+        record: impl Fn(u64) -> Record,
+    ) {
+        let time_ns = u64::try_from((time - EmulatedTime::SIMULATION_START).as_nanos()).unwrap();
+
+        for observer in &self.observers {
+            if let Some(offset) = observer.offsets.get(&(src_node, dst_node)) {
+                let record = record(time_ns.saturating_add(*offset));
+                observer.records.lock().unwrap().push(record);
+            }
+        }
+    }
+
+    /// Write each observer's records to a CSV file in the subdirectory [`OBSERVERS_DIR_NAME`] of
+    /// directory `dir`.
+    pub fn write(&self, dir: &Path, compression: OutputCompression) -> anyhow::Result<()> {
+        let dir = dir.join(OBSERVERS_DIR_NAME);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create directory '{}'", dir.display()))?;
+
+        for observer in &self.observers {
+            let mut records = observer.records.lock().unwrap();
+            // worker threads add records in any order
+            records.sort();
+
+            let path = dir.join(format!("{}.csv", observer.name));
+            let path = compression::compressed_path(&path, compression);
+            write_records(&path, &records, compression)
+                .with_context(|| format!("Failed to write file '{}'", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the observer's name and where it is in the graph.
+fn tap<'a>(options: &'a ObserverOptions, graph: &NetworkGraph) -> anyhow::Result<(&'a str, Tap)> {
+    Ok(match options {
+        ObserverOptions::Edge {
+            name,
+            source,
+            target,
+        } => {
+            let owner = format!("Observer '{name}'");
+            (name.as_str(), Tap::edge(graph, *source, *target, &owner)?)
+        }
+        ObserverOptions::AutonomousSystem { name, asn } => {
+            let owner = format!("Observer '{name}'");
+            (name.as_str(), Tap::autonomous_system(graph, *asn, &owner)?)
+        }
+    })
+}
+
+/// For each tap, the latency in nanoseconds from the source node to the tap of each pair of source
+/// and destination graph nodes whose path crosses the tap. `nodes` are the graph nodes that have
+/// hosts attached, and `use_shortest_paths` must be the same as when computing the routing
+/// information.
+pub(super) fn tap_offsets(
+    taps: &[Tap],
+    graph: &NetworkGraph,
+    nodes: &HashSet<u32>,
+    use_shortest_paths: bool,
+) -> anyhow::Result<Vec<HashMap<(u32, u32), u64>>> {
+    let nodes: Vec<NodeIndex> = nodes
+        .iter()
+        .map(|x| *graph.node_id_to_index(*x).unwrap())
+        .collect();
+    let to_id = |x: NodeIndex| graph.node_index_to_id(x).unwrap();
+
+    // the taps that each path crosses, and the latency up to each tap
+    let crossings = nodes
+        .clone()
+        .into_par_iter()
+        .map(|src| {
+            let routes = graph
+                .routes_from(src, &nodes, use_shortest_paths)
+                .map_err(|e| anyhow::anyhow!(e))
+                .context("Failed to compute the paths between graph nodes")?;
+
+            let mut crossings = Vec::new();
+            for (dst, route) in routes {
+                for (i, tap) in taps.iter().enumerate() {
+                    if let Some(offset) = crossing(graph, &route, *tap) {
+                        crossings.push((i, (to_id(src), to_id(dst)), offset));
+                    }
+                }
+            }
+            Ok(crossings)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut offsets = vec![HashMap::new(); taps.len()];
+    for (i, pair, offset) in crossings.into_iter().flatten() {
+        offsets[i].insert(pair, offset);
+    }
+
+    Ok(offsets)
+}
+
+/// If the path through the nodes `route` crosses the tap, returns the latency in nanoseconds from
+/// the start of the path to the tap.
+fn crossing(graph: &NetworkGraph, route: &[NodeIndex], tap: Tap) -> Option<u64> {
+    let directed = matches!(graph.graph(), GraphWrapper::Directed(_));
+    let asn = |x: NodeIndex| graph.graph().node_weight(x).unwrap().asn;
+
+    let mut latency_ns = 0;
+    for (i, node) in route.iter().enumerate() {
+        let next = route.get(i + 1);
+
+        let crossed = match tap {
+            Tap::Edge(source, target) => next.is_some_and(|next| {
+                (*node, *next) == (source, target)
+                    || (!directed && (*node, *next) == (target, source))
+            }),
+            Tap::AutonomousSystem(x) => asn(*node) == Some(x),
+        };
+        if crossed {
+            return Some(latency_ns);
+        }
+
+        if let Some(next) = next {
+            latency_ns += edge_latency_ns(graph, *node, *next);
+        }
+    }
+
+    None
+}
+
+/// The lowest latency of the edges from `a` to `b`.
+fn edge_latency_ns(graph: &NetworkGraph, a: NodeIndex, b: NodeIndex) -> u64 {
+    let latencies: Vec<u64> = match graph.graph() {
+        GraphWrapper::Directed(g) => g
+            .edges_connecting(a, b)
+            .map(|e| super::PathProperties::from(e.weight()).latency_ns)
+            .collect(),
+        GraphWrapper::Undirected(g) => g
+            .edges_connecting(a, b)
+            .map(|e| super::PathProperties::from(e.weight()).latency_ns)
+            .collect(),
+    };
+    latencies.into_iter().min().unwrap()
+}
+
+fn write_records(
+    path: &Path,
+    records: &[Record],
+    compression: OutputCompression,
+) -> std::io::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut writer = CompressedWriter::new(file, compression)?;
+    writeln!(
+        writer,
+        "time_ns,protocol,src_ip,src_port,dst_ip,dst_port,size"
+    )?;
+    for x in records {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            x.time_ns,
+            x.protocol,
+            x.src.ip(),
+            x.src.port(),
+            x.dst.ip(),
+            x.dst.port(),
+            x.size,
+        )?;
+    }
+    writer.finish()?.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPH: &str = r#"graph [
+      node [ id 0 asn 1 ]
+      node [ id 1 asn 2 ]
+      node [ id 2 asn 3 ]
+      edge [ source 0 target 0 latency "1 ms" ]
+      edge [ source 1 target 1 latency "1 ms" ]
+      edge [ source 2 target 2 latency "1 ms" ]
+      edge [ source 0 target 1 latency "10 ms" as_relationship "provider" ]
+      edge [ source 1 target 2 latency "20 ms" as_relationship "customer" ]
+    ]"#;
+
+    fn record(time_ns: u64) -> Record {
+        Record {
+            time_ns,
+            protocol: "udp",
+            src: "11.0.0.1:1000".parse().unwrap(),
+            dst: "11.0.0.2:2000".parse().unwrap(),
+            size: 100,
+        }
+    }
+
+    // disabled under miri due to https://github.com/rayon-rs/rayon/issues/952
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_observers() {
+        let graph = NetworkGraph::parse(GRAPH).unwrap();
+        let options: Vec<ObserverOptions> = serde_yaml::from_str(
+            "
+            - type: edge
+              name: transit
+              source: 1
+              target: 2
+            - type: autonomous-system
+              name: as2
+              asn: 2
+            ",
+        )
+        .unwrap();
+        let observers = Observers::new(&options, &graph, &HashSet::from([0, 2]), true).unwrap();
+
+        let start = EmulatedTime::SIMULATION_START;
+        // the edge is crossed in either direction, 10 ms after the packet is sent from node 0
+        observers.packet_sent(0, 2, start, record);
+        observers.packet_sent(2, 0, start, record);
+        // the path from a node to itself doesn't leave the node
+        observers.packet_sent(0, 0, start, record);
+
+        let dir = tempfile::tempdir().unwrap();
+        observers
+            .write(dir.path(), OutputCompression::None)
+            .unwrap();
+
+        let read = |name: &str| {
+            std::fs::read_to_string(dir.path().join(OBSERVERS_DIR_NAME).join(name)).unwrap()
+        };
+
+        let header = "time_ns,protocol,src_ip,src_port,dst_ip,dst_port,size\n";
+        let row = |time_ns: u64| format!("{time_ns},udp,11.0.0.1,1000,11.0.0.2,2000,100\n");
+        assert_eq!(
+            read("transit.csv"),
+            header.to_owned() + &row(0) + &row(10_000_000)
+        );
+        assert_eq!(
+            read("as2.csv"),
+            header.to_owned() + &row(10_000_000) + &row(20_000_000)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let graph = NetworkGraph::parse(GRAPH).unwrap();
+        let check = |yaml: &str| {
+            let options: Vec<ObserverOptions> = serde_yaml::from_str(yaml).unwrap();
+            Observers::new(&options, &graph, &HashSet::new(), true).is_err()
+        };
+
+        assert!(check("[{type: edge, name: x, source: 0, target: 2}]"));
+        assert!(check("[{type: edge, name: x, source: 0, target: 5}]"));
+        assert!(check("[{type: autonomous-system, name: x, asn: 4}]"));
+        assert!(check("[{type: autonomous-system, name: a/b, asn: 1}]"));
+        assert!(check(
+            "
+            - {type: autonomous-system, name: x, asn: 1}
+            - {type: autonomous-system, name: x, asn: 2}
+            "
+        ));
+        assert!(!check("[{type: autonomous-system, name: x, asn: 1}]"));
+    }
+}
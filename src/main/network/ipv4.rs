@@ -0,0 +1,401 @@
+//! Conversions between Shadow's packets and the bytes of IPv4 packets, for the packets that are
+//! exchanged with systems outside of Shadow's network stack, such as TUN devices and raw sockets.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use crate::core::support::configuration::PcapPayload;
+use crate::cshadow as c;
+use crate::host::network::interface::FifoPacketPriority;
+use crate::network::packet::{IcmpEchoHeader, IcmpEchoKind, PacketRc};
+use crate::utility::pcap_writer::PacketDisplay;
+
+pub const IPPROTO_ICMP: u8 = 1;
+pub const IPPROTO_TCP: u8 = 6;
+pub const IPPROTO_UDP: u8 = 17;
+
+/// The length of an IPv4 header without options.
+pub const HEADER_LEN: usize = 20;
+
+/// The ICMP type of an echo request.
+const ICMP_ECHO: u8 = 8;
+/// The ICMP type of an echo reply.
+const ICMP_ECHOREPLY: u8 = 0;
+
+/// The transport header of a parsed packet.
+#[derive(Debug)]
+pub enum TransportHeader {
+    Tcp(tcp::TcpHeader),
+    Udp,
+    IcmpEcho(IcmpEchoHeader),
+}
+
+/// A TCP, UDP, or ICMP echo packet parsed from its bytes.
+#[derive(Debug)]
+pub struct ParsedPacket {
+    /// The source address. For an ICMP echo packet, the port is the echo identifier.
+    pub src: SocketAddrV4,
+    /// The destination address. For an ICMP echo packet, the port is the echo identifier.
+    pub dst: SocketAddrV4,
+    pub header: TransportHeader,
+    pub payload: Vec<u8>,
+}
+
+impl ParsedPacket {
+    /// A new Shadow packet with the same headers and payload. The payload is given the priority
+    /// `priority`.
+    pub fn to_packet(&self, priority: FifoPacketPriority) -> PacketRc {
+        let mut packet = PacketRc::new();
+        match &self.header {
+            TransportHeader::Tcp(header) => packet.set_tcp(header),
+            TransportHeader::Udp => packet.set_udp(self.src, self.dst),
+            TransportHeader::IcmpEcho(header) => packet.set_icmp(header),
+        }
+        if !self.payload.is_empty() {
+            packet.set_payload(&self.payload, priority);
+        }
+        packet
+    }
+}
+
+/// The IP protocol number of a Shadow packet.
+pub fn protocol(packet: &PacketRc) -> u8 {
+    match packet.protocol() {
+        c::_ProtocolType_PICMP => IPPROTO_ICMP,
+        c::_ProtocolType_PTCP => IPPROTO_TCP,
+        c::_ProtocolType_PUDP => IPPROTO_UDP,
+        x => panic!("Unexpected packet protocol {x}"),
+    }
+}
+
+/// The bytes of a Shadow packet as an IPv4 packet with valid checksums.
+pub fn to_bytes(packet: &PacketRc) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(packet.total_size());
+    packet.display_bytes(&mut bytes, PcapPayload::Keep).unwrap();
+    fill_checksums(&mut bytes);
+    bytes
+}
+
+/// An IPv4 packet with a header (without options) from `src` to `dst` and the protocol data
+/// `data`. The header checksum is left as zero.
+pub fn with_header(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, data: &[u8]) -> Vec<u8> {
+    let total_len = u16::try_from(HEADER_LEN + data.len()).unwrap();
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + data.len());
+    // version, header length, DSCP, ECN, and total length
+    bytes.extend_from_slice(&[0x45, 0x00]);
+    bytes.extend_from_slice(&total_len.to_be_bytes());
+    // identification, the "don't fragment" flag, and fragment offset
+    bytes.extend_from_slice(&[0x00, 0x00, 0x40, 0x00]);
+    // ttl, protocol, and header checksum
+    bytes.extend_from_slice(&[64, protocol, 0x00, 0x00]);
+    bytes.extend_from_slice(&src.octets());
+    bytes.extend_from_slice(&dst.octets());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Parse an IPv4 TCP, UDP, or ICMP echo packet.
+pub fn parse(bytes: &[u8]) -> Result<ParsedPacket, &'static str> {
+    if bytes.len() < HEADER_LEN || bytes[0] >> 4 != 4 {
+        return Err("not an IPv4 packet");
+    }
+    let header_len = usize::from(bytes[0] & 0xf) * 4;
+    let total_len = usize::from(u16::from_be_bytes([bytes[2], bytes[3]]));
+    if header_len < HEADER_LEN || total_len < header_len || total_len > bytes.len() {
+        return Err("invalid IPv4 header");
+    }
+    // the "more fragments" flag and the fragment offset
+    if u16::from_be_bytes([bytes[6], bytes[7]]) & 0x3fff != 0 {
+        return Err("fragmented packets aren't supported");
+    }
+
+    let src_ip = Ipv4Addr::from([bytes[12], bytes[13], bytes[14], bytes[15]]);
+    let dst_ip = Ipv4Addr::from([bytes[16], bytes[17], bytes[18], bytes[19]]);
+    let data = &bytes[header_len..total_len];
+    let port = |offset: usize| u16::from_be_bytes([data[offset], data[offset + 1]]);
+
+    match bytes[9] {
+        IPPROTO_TCP => {
+            if data.len() < 20 {
+                return Err("invalid TCP header");
+            }
+            let data_offset = usize::from(data[12] >> 4) * 4;
+            if data_offset < 20 || data_offset > data.len() {
+                return Err("invalid TCP header");
+            }
+            let word = |offset: usize| {
+                u32::from_be_bytes([
+                    data[offset],
+                    data[offset + 1],
+                    data[offset + 2],
+                    data[offset + 3],
+                ])
+            };
+
+            let mut header = tcp::TcpHeader {
+                ip: tcp::Ipv4Header {
+                    src: src_ip,
+                    dst: dst_ip,
+                },
+                flags: tcp::TcpFlags::from_bits_truncate(data[13]),
+                src_port: port(0),
+                dst_port: port(2),
+                seq: word(4),
+                ack: word(8),
+                window_size: port(14),
+                selective_acks: None,
+                window_scale: None,
+                timestamp: None,
+                timestamp_echo: None,
+            };
+
+            // the window scale and timestamp options; other options such as the MSS and selective
+            // acks are ignored
+            let mut options = &data[20..data_offset];
+            while let Some(&kind) = options.first() {
+                match kind {
+                    0 => break,
+                    1 => {
+                        options = &options[1..];
+                        continue;
+                    }
+                    _ => {}
+                }
+                let len = usize::from(*options.get(1).ok_or("invalid TCP option")?);
+                if len < 2 || len > options.len() {
+                    return Err("invalid TCP option");
+                }
+                let value = &options[2..len];
+                match (kind, value.len()) {
+                    (3, 1) => header.window_scale = Some(value[0]),
+                    (8, 8) => {
+                        header.timestamp = Some(u32::from_be_bytes(value[..4].try_into().unwrap()));
+                        header.timestamp_echo =
+                            Some(u32::from_be_bytes(value[4..].try_into().unwrap()));
+                    }
+                    _ => {}
+                }
+                options = &options[len..];
+            }
+
+            Ok(ParsedPacket {
+                src: header.src(),
+                dst: header.dst(),
+                header: TransportHeader::Tcp(header),
+                payload: data[data_offset..].to_vec(),
+            })
+        }
+        IPPROTO_UDP => {
+            if data.len() < 8 {
+                return Err("invalid UDP header");
+            }
+            let len = usize::from(port(4));
+            if len < 8 || len > data.len() {
+                return Err("invalid UDP header");
+            }
+            Ok(ParsedPacket {
+                src: SocketAddrV4::new(src_ip, port(0)),
+                dst: SocketAddrV4::new(dst_ip, port(2)),
+                header: TransportHeader::Udp,
+                payload: data[8..len].to_vec(),
+            })
+        }
+        IPPROTO_ICMP => {
+            if data.len() < 8 {
+                return Err("invalid ICMP header");
+            }
+            let kind = match (data[0], data[1]) {
+                (ICMP_ECHO, 0) => IcmpEchoKind::Request,
+                (ICMP_ECHOREPLY, 0) => IcmpEchoKind::Reply,
+                _ => return Err("only ICMP echo messages are supported"),
+            };
+            let header = IcmpEchoHeader {
+                kind,
+                src: src_ip,
+                dst: dst_ip,
+                identifier: port(4),
+                sequence: port(6),
+            };
+            Ok(ParsedPacket {
+                src: SocketAddrV4::new(src_ip, header.identifier),
+                dst: SocketAddrV4::new(dst_ip, header.identifier),
+                header: TransportHeader::IcmpEcho(header),
+                payload: data[8..].to_vec(),
+            })
+        }
+        _ => Err("only TCP, UDP, and ICMP packets are supported"),
+    }
+}
+
+/// Fill in the IPv4 header checksum and the TCP, UDP, or ICMP checksum of an IPv4 packet, which
+/// are left as zero when Shadow writes its packets.
+pub fn fill_checksums(bytes: &mut [u8]) {
+    let header_len = usize::from(bytes[0] & 0xf) * 4;
+    let (header, data) = bytes.split_at_mut(header_len);
+
+    header[10..12].fill(0);
+    let sum = checksum(0, header);
+    header[10..12].copy_from_slice(&sum.to_be_bytes());
+
+    let protocol = header[9];
+
+    // the ICMP checksum doesn't include a pseudo-header
+    if protocol == IPPROTO_ICMP {
+        data[2..4].fill(0);
+        let sum = checksum(0, data);
+        data[2..4].copy_from_slice(&sum.to_be_bytes());
+        return;
+    }
+
+    let offset = match protocol {
+        IPPROTO_TCP => 16,
+        IPPROTO_UDP => 6,
+        _ => return,
+    };
+
+    // the pseudo-header of the source and destination addresses, protocol, and length
+    let mut pseudo_header = [0u8; 12];
+    pseudo_header[..8].copy_from_slice(&header[12..20]);
+    pseudo_header[9] = protocol;
+    pseudo_header[10..].copy_from_slice(&u16::try_from(data.len()).unwrap().to_be_bytes());
+
+    data[offset..offset + 2].fill(0);
+    let mut sum = checksum(partial_sum(0, &pseudo_header), data);
+    // a UDP checksum of zero means that there's no checksum
+    if protocol == IPPROTO_UDP && sum == 0 {
+        sum = 0xffff;
+    }
+    data[offset..offset + 2].copy_from_slice(&sum.to_be_bytes());
+}
+
+/// Add the 16-bit words of `data` to `sum`.
+fn partial_sum(mut sum: u32, data: &[u8]) -> u32 {
+    for chunk in data.chunks(2) {
+        sum += u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]));
+    }
+    sum
+}
+
+/// The internet checksum (RFC 1071) of `data`, continuing from the partial sum `sum`.
+fn checksum(sum: u32, data: &[u8]) -> u16 {
+    let mut sum = partial_sum(sum, data);
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A UDP packet from 11.0.0.1:1000 to 11.0.0.2:53 with the payload "hi" and valid checksums.
+    const UDP_PACKET: [u8; 30] = [
+        0x45, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x24, 0xcd, 0x0b, 0x00, 0x00,
+        0x01, 0x0b, 0x00, 0x00, 0x02, 0x03, 0xe8, 0x00, 0x35, 0x00, 0x0a, 0x7d, 0x51, 0x68, 0x69,
+    ];
+
+    #[test]
+    fn test_fill_checksums() {
+        let mut packet = UDP_PACKET;
+        packet[10..12].fill(0);
+        packet[26..28].fill(0);
+        fill_checksums(&mut packet);
+        assert_eq!(packet, UDP_PACKET);
+
+        // the checksum of a header with a valid checksum is zero
+        assert_eq!(checksum(0, &packet[..20]), 0);
+    }
+
+    #[test]
+    fn test_with_header() {
+        let mut packet = with_header(
+            Ipv4Addr::new(11, 0, 0, 1),
+            Ipv4Addr::new(11, 0, 0, 2),
+            IPPROTO_UDP,
+            &UDP_PACKET[20..],
+        );
+        fill_checksums(&mut packet);
+        assert_eq!(packet, UDP_PACKET);
+    }
+
+    #[test]
+    fn test_parse_udp() {
+        let parsed = parse(&UDP_PACKET).unwrap();
+        assert_eq!(parsed.src, "11.0.0.1:1000".parse().unwrap());
+        assert_eq!(parsed.dst, "11.0.0.2:53".parse().unwrap());
+        assert!(matches!(parsed.header, TransportHeader::Udp));
+        assert_eq!(parsed.payload, b"hi");
+
+        // truncated
+        assert!(parse(&UDP_PACKET[..25]).is_err());
+
+        // fragmented
+        let mut packet = UDP_PACKET;
+        packet[6] = 0x20;
+        assert!(parse(&packet).is_err());
+    }
+
+    #[test]
+    fn test_parse_tcp() {
+        #[rustfmt::skip]
+        let packet = [
+            // IPv4 header
+            0x45, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00,
+            0x0b, 0x00, 0x00, 0x01, 0x0b, 0x00, 0x00, 0x02,
+            // ports, sequence number, and acknowledgement number
+            0x9c, 0x40, 0x00, 0x50, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0x00,
+            // data offset, flags, window, checksum, and urgent pointer
+            0xa0, 0x02, 0xfa, 0xf0, 0x00, 0x00, 0x00, 0x00,
+            // MSS, NOP, window scale, NOP, NOP, and timestamp options
+            0x02, 0x04, 0x05, 0xb4, 0x01, 0x03, 0x03, 0x07, 0x01, 0x01,
+            0x08, 0x0a, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let parsed = parse(&packet).unwrap();
+        assert_eq!(parsed.src, "11.0.0.1:40000".parse().unwrap());
+        assert_eq!(parsed.dst, "11.0.0.2:80".parse().unwrap());
+        assert!(parsed.payload.is_empty());
+
+        let TransportHeader::Tcp(header) = parsed.header else {
+            panic!("Expected a TCP header: {:?}", parsed.header);
+        };
+        assert_eq!(header.flags, tcp::TcpFlags::SYN);
+        assert_eq!(header.seq, 100);
+        assert_eq!(header.window_size, 0xfaf0);
+        assert_eq!(header.window_scale, Some(7));
+        assert_eq!(header.timestamp, Some(5));
+        assert_eq!(header.timestamp_echo, Some(0));
+
+        // an option longer than the header
+        let mut packet = packet;
+        packet[41] = 30;
+        assert!(parse(&packet).is_err());
+    }
+
+    #[test]
+    fn test_parse_icmp() {
+        let src = Ipv4Addr::new(11, 0, 0, 1);
+        let dst = Ipv4Addr::new(11, 0, 0, 2);
+        // an echo request with identifier 0x1234, sequence number 7, and the data "ping"
+        let message = [
+            0x08, 0x00, 0x00, 0x00, 0x12, 0x34, 0x00, 0x07, b'p', b'i', b'n', b'g',
+        ];
+
+        let parsed = parse(&with_header(src, dst, IPPROTO_ICMP, &message)).unwrap();
+        assert_eq!(parsed.src, SocketAddrV4::new(src, 0x1234));
+        assert_eq!(parsed.dst, SocketAddrV4::new(dst, 0x1234));
+        assert_eq!(parsed.payload, b"ping");
+
+        let TransportHeader::IcmpEcho(header) = parsed.header else {
+            panic!("Expected an ICMP echo header: {:?}", parsed.header);
+        };
+        assert_eq!(header.kind, IcmpEchoKind::Request);
+        assert_eq!(header.sequence, 7);
+
+        // a "destination unreachable" message
+        let mut message = message;
+        message[0] = 3;
+        assert!(parse(&with_header(src, dst, IPPROTO_ICMP, &message)).is_err());
+    }
+}
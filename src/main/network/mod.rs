@@ -2,7 +2,9 @@ use std::net::Ipv4Addr;
 
 use crate::network::packet::PacketRc;
 
+pub mod fingerprint;
 pub mod graph;
+pub mod ipv4;
 pub mod packet;
 pub mod relay;
 pub mod router;
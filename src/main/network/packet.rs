@@ -1,6 +1,7 @@
 use std::io::Write;
 use std::net::{Ipv4Addr, SocketAddrV4};
 
+use crate::core::support::configuration::PcapPayload;
 use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::memory_manager::MemoryManager;
@@ -42,6 +43,23 @@ pub struct PacketRc {
     c_ptr: SyncSendPointer<c::Packet>,
 }
 
+/// The ICMP echo messages (see RFC 792), which are the only ICMP messages that Shadow sends.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IcmpEchoKind {
+    Request,
+    Reply,
+}
+
+/// The addresses and header of an ICMP echo request or reply.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IcmpEchoHeader {
+    pub kind: IcmpEchoKind,
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub identifier: u16,
+    pub sequence: u16,
+}
+
 impl std::fmt::Debug for PacketRc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Packet").finish_non_exhaustive()
@@ -59,7 +77,7 @@ impl Eq for PacketRc {}
 /// Clone the reference to the packet.
 impl Clone for PacketRc {
     fn clone(&self) -> Self {
-        let ptr = self.borrow_inner();
+        let ptr = self.c_ptr.ptr();
         unsafe { c::packet_ref(ptr) }
         PacketRc::from_raw(ptr)
     }
@@ -125,6 +143,7 @@ impl PacketRc {
                 header.window_size.into(),
                 header.window_scale.unwrap_or(0),
                 header.window_scale.is_some(),
+                header.timestamp.is_some(),
                 timestamp.into(),
                 timestamp_echo.into(),
             );
@@ -203,8 +222,10 @@ impl PacketRc {
             window_size: header.window.try_into().unwrap(),
             selective_acks,
             window_scale,
-            timestamp: Some(timestamp.try_into().unwrap()),
-            timestamp_echo: Some(timestamp_echo.try_into().unwrap()),
+            timestamp: header.timestampSet.then(|| timestamp.try_into().unwrap()),
+            timestamp_echo: header
+                .timestampSet
+                .then(|| timestamp_echo.try_into().unwrap()),
         })
     }
 
@@ -222,6 +243,63 @@ impl PacketRc {
         };
     }
 
+    /// Set ICMP echo headers for this packet. Will panic if the packet already has a header.
+    pub fn set_icmp(&mut self, header: &IcmpEchoHeader) {
+        let icmp_type = match header.kind {
+            IcmpEchoKind::Request => c::ProtocolICMPType_PICMP_ECHO_REQUEST,
+            IcmpEchoKind::Reply => c::ProtocolICMPType_PICMP_ECHO_REPLY,
+        };
+        unsafe {
+            c::packet_setICMP(
+                self.c_ptr.ptr(),
+                icmp_type,
+                0,
+                u32::from(header.src).to_be(),
+                u32::from(header.dst).to_be(),
+                header.identifier.to_be(),
+                header.sequence.to_be(),
+            )
+        };
+    }
+
+    /// The ICMP echo header of the packet, or `None` if it's not an echo request or reply.
+    pub fn get_icmp(&self) -> Option<IcmpEchoHeader> {
+        if self.protocol() != c::_ProtocolType_PICMP {
+            return None;
+        }
+
+        let header = unsafe { c::packet_getICMPHeader(self.c_ptr.ptr()) };
+        let header = unsafe { header.as_ref() }.unwrap();
+
+        let kind = match header.type_ {
+            c::ProtocolICMPType_PICMP_ECHO_REQUEST => IcmpEchoKind::Request,
+            c::ProtocolICMPType_PICMP_ECHO_REPLY => IcmpEchoKind::Reply,
+            _ => return None,
+        };
+
+        Some(IcmpEchoHeader {
+            kind,
+            src: Ipv4Addr::from(u32::from_be(header.sourceIP)),
+            dst: Ipv4Addr::from(u32::from_be(header.destinationIP)),
+            identifier: u16::from_be(header.identifier),
+            sequence: u16::from_be(header.sequence),
+        })
+    }
+
+    /// Replace the addresses of a TCP, UDP, or ICMP packet. The ports of an ICMP packet are
+    /// ignored.
+    pub fn set_addresses(&mut self, src: SocketAddrV4, dst: SocketAddrV4) {
+        unsafe {
+            c::packet_setAddresses(
+                self.c_ptr.ptr(),
+                u32::from(*src.ip()).to_be(),
+                src.port().to_be(),
+                u32::from(*dst.ip()).to_be(),
+                dst.port().to_be(),
+            )
+        };
+    }
+
     /// Set the packet payload. Will panic if the packet already has a payload.
     pub fn set_payload(&mut self, payload: &[u8], priority: FifoPacketPriority) {
         unsafe {
@@ -328,6 +406,10 @@ impl PacketRc {
         SocketAddrV4::new(ip, port)
     }
 
+    pub fn protocol(&self) -> c::ProtocolType {
+        unsafe { c::packet_getProtocol(self.c_ptr.ptr()) }
+    }
+
     pub fn priority(&self) -> FifoPacketPriority {
         unsafe { c::packet_getPriority(self.c_ptr.ptr()) }
     }
@@ -350,7 +432,9 @@ impl PacketRc {
         c_ptr
     }
 
-    pub fn borrow_inner(&self) -> *mut c::Packet {
+    /// A pointer to the C packet for read-only access. The pointer is only valid while this
+    /// reference is alive. Use [`into_inner`](Self::into_inner) to pass a mutable packet to C code.
+    pub fn as_ptr(&self) -> *const c::Packet {
         self.c_ptr.ptr()
     }
 }
@@ -366,13 +450,13 @@ impl Drop for PacketRc {
 }
 
 impl PacketDisplay for PacketRc {
-    fn display_bytes(&self, writer: impl Write) -> std::io::Result<()> {
-        self.borrow_inner().cast_const().display_bytes(writer)
+    fn display_bytes(&self, writer: impl Write, payload: PcapPayload) -> std::io::Result<()> {
+        self.as_ptr().display_bytes(writer, payload)
     }
 }
 
 impl PacketDisplay for *const c::Packet {
-    fn display_bytes(&self, mut writer: impl Write) -> std::io::Result<()> {
+    fn display_bytes(&self, mut writer: impl Write, payload: PcapPayload) -> std::io::Result<()> {
         assert!(!self.is_null());
 
         let header_len: u16 = unsafe { c::packet_getHeaderSize(*self) }
@@ -392,6 +476,7 @@ impl PacketDisplay for *const c::Packet {
         let flags_and_fragment: u16 = 0x4000;
         let time_to_live: u8 = 64;
         let iana_protocol: u8 = match protocol {
+            c::_ProtocolType_PICMP => 1,
             c::_ProtocolType_PTCP => 6,
             c::_ProtocolType_PUDP => 17,
             _ => panic!("Unexpected packet protocol"),
@@ -426,12 +511,13 @@ impl PacketDisplay for *const c::Packet {
         match protocol {
             c::_ProtocolType_PTCP => display_tcp_bytes(*self, &mut writer)?,
             c::_ProtocolType_PUDP => display_udp_bytes(*self, &mut writer)?,
+            c::_ProtocolType_PICMP => display_icmp_bytes(*self, &mut writer)?,
             _ => panic!("Unexpected packet protocol"),
         }
 
         // write payload data
 
-        if payload_len > 0 {
+        if payload_len > 0 && payload == PcapPayload::Keep {
             // shadow's packet payloads are guarded by a mutex, so it's easiest to make a copy of them
             let mut payload_buf = vec![0u8; payload_len.try_into().unwrap()];
             let count = unsafe {
@@ -450,8 +536,14 @@ impl PacketDisplay for *const c::Packet {
 
             // packet payload: `payload_len` bytes
             writer.write_all(&payload_buf)?;
+        } else if payload_len > 0 && payload == PcapPayload::Zero {
+            // packet payload: `payload_len` zero bytes
+            writer.write_all(&vec![0u8; payload_len.into()])?;
         }
 
+        // with `PcapPayload::Truncate` the payload isn't written, but the original packet length in
+        // the pcap record still includes it
+
         Ok(())
     }
 }
@@ -474,11 +566,35 @@ fn display_tcp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::i
     // process TCP options
 
     let window_scale = tcp_header.windowScaleSet.then_some(tcp_header.windowScale);
+    let timestamps = tcp_header.timestampSet.then(|| {
+        // like linux, the timestamps on the wire have a granularity of 1 ms
+        let to_millis = |x| {
+            let ms = SimulationTime::from_c_simtime(x).unwrap().as_millis();
+            // the timestamps wrap around
+            (ms as u32).to_be_bytes()
+        };
+        (
+            to_millis(tcp_header.timestampValue),
+            to_millis(tcp_header.timestampEcho),
+        )
+    });
 
     // options can be a max of 40 bytes
     let mut options = [0u8; 40];
     let mut options_len = 0;
 
+    if let Some((value, echo)) = timestamps {
+        // two NOP options to align the timestamps, like linux
+        options[options_len..][..2].copy_from_slice(&[1, 1]);
+        options_len += 2;
+
+        // option-kind = 8, option-len = 10, option-data = value and echo reply
+        options[options_len..][..2].copy_from_slice(&[8, 10]);
+        options[options_len..][2..6].copy_from_slice(&value);
+        options[options_len..][6..10].copy_from_slice(&echo);
+        options_len += 10;
+    }
+
     if let Some(window_scale) = window_scale {
         // option-kind = 3, option-len = 3, option-data = window-scale
         options[options_len..][..3].copy_from_slice(&[3, 3, window_scale]);
@@ -585,6 +701,34 @@ fn display_udp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::i
     Ok(())
 }
 
+/// Helper for writing the icmp bytes of the packet.
+fn display_icmp_bytes(packet: *const c::Packet, mut writer: impl Write) -> std::io::Result<()> {
+    assert_eq!(
+        unsafe { c::packet_getProtocol(packet) },
+        c::_ProtocolType_PICMP
+    );
+
+    let icmp_header = unsafe { c::packet_getICMPHeader(packet) };
+    let icmp_header = unsafe { icmp_header.as_ref() }.unwrap();
+
+    // write the ICMP echo header
+
+    let icmp_type: u8 = icmp_header.type_.try_into().unwrap();
+    let checksum: u16 = 0x0;
+
+    // type: 1 byte
+    // code: 1 byte
+    writer.write_all(&[icmp_type, icmp_header.code])?;
+    // checksum: 2 bytes
+    writer.write_all(&checksum.to_be_bytes())?;
+    // identifier: 2 bytes
+    writer.write_all(&u16::from_be(icmp_header.identifier).to_be_bytes())?;
+    // sequence number: 2 bytes
+    writer.write_all(&u16::from_be(icmp_header.sequence).to_be_bytes())?;
+
+    Ok(())
+}
+
 pub fn to_legacy_tcp_flags(flags: tcp::TcpFlags) -> c::ProtocolTCPFlags {
     let mut new_flags = c::ProtocolTCPFlags_PTCP_NONE;
 
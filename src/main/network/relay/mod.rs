@@ -10,10 +10,12 @@ use crate::core::worker::Worker;
 use crate::cshadow as c;
 use crate::host::host::Host;
 use crate::network::packet::PacketStatus;
+use crate::network::relay::throttle::Throttle;
 use crate::network::relay::token_bucket::TokenBucket;
 use crate::network::PacketRc;
 use crate::utility::ObjectCounter;
 
+pub mod throttle;
 mod token_bucket;
 
 /// A `Relay` forwards `PacketRc`s between `PacketDevice`s, optionally enforcing a
@@ -56,9 +58,13 @@ pub struct Relay {
 struct RelayInternal {
     _counter: ObjectCounter,
     rate_limiter: Option<TokenBucket>,
+    /// Additional rate limits for the packets that match a throttling rule.
+    throttles: Vec<Throttle>,
     src_dev_address: Ipv4Addr,
     state: RelayState,
     next_packet: Option<PacketRc>,
+    /// Whether `next_packet` was already checked against the throttles.
+    next_packet_throttled: bool,
 }
 
 /// Track's the `Relay`s state, which typically moves from Idle to Pending to
@@ -86,8 +92,9 @@ impl Relay {
     /// `RateLimit` from the `PacketDevice` returned by the `Host` when passing
     /// the given `src_dev_address` to `Host::get_packet_device()`. The `Relay`
     /// internally schedules tasks as needed to ensure packets continue to be
-    /// forwarded over time without exceeding the configured `RateLimit`.
-    pub fn new(rate: RateLimit, src_dev_address: Ipv4Addr) -> Self {
+    /// forwarded over time without exceeding the configured `RateLimit`, or the
+    /// rate limits of the given `Throttle`s for the packets that match them.
+    pub fn new(rate: RateLimit, src_dev_address: Ipv4Addr, throttles: Vec<Throttle>) -> Self {
         let rate_limiter = match rate {
             RateLimit::BytesPerSecond(bytes) => Some(create_token_bucket(bytes)),
             RateLimit::Unlimited => None,
@@ -97,9 +104,11 @@ impl Relay {
             internal: AtomicRefCell::new(RelayInternal {
                 _counter: ObjectCounter::new("Relay"),
                 rate_limiter,
+                throttles,
                 src_dev_address,
                 state: RelayState::Idle,
                 next_packet: None,
+                next_packet_throttled: false,
             }),
         }
     }
@@ -228,6 +237,37 @@ impl Relay {
             // limits do not apply during bootstrapping, or if the source and
             // destination are the same device.
             if !is_bootstrapping && !is_local {
+                // The first active throttle that matches the packet limits it
+                // in addition to the rate limit. A packet cached by the rate
+                // limit was already charged to its throttle.
+                let throttled = std::mem::take(&mut internal.next_packet_throttled);
+                let now = Worker::current_time().unwrap();
+                let throttle = internal
+                    .throttles
+                    .iter_mut()
+                    .find(|x| !throttled && x.matches(&packet, now));
+                if let Some(throttle) = throttle {
+                    if let Err(blocking_dur) =
+                        throttle.conforming_remove(packet.total_size() as u64)
+                    {
+                        log::trace!(
+                            "Relay src={} dst={} exceeded throttle, blocking for {:?}",
+                            src.get_address(),
+                            packet.dst_address().ip(),
+                            blocking_dur
+                        );
+
+                        // Cache the packet until we can forward it later.
+                        packet.add_status(PacketStatus::RelayCached);
+                        assert!(internal.next_packet.is_none());
+                        internal.next_packet = Some(packet);
+                        internal.state = RelayState::Idle;
+
+                        // Call Relay::forward_later() after dropping the mutable borrow.
+                        return Some(blocking_dur);
+                    }
+                }
+
                 // Rate limit applies only if we have a token bucket.
                 if let Some(tb) = internal.rate_limiter.as_mut() {
                     // Try to remove tokens for this packet.
@@ -249,6 +289,7 @@ impl Relay {
                         packet.add_status(PacketStatus::RelayCached);
                         assert!(internal.next_packet.is_none());
                         internal.next_packet = Some(packet);
+                        internal.next_packet_throttled = true;
                         internal.state = RelayState::Idle;
 
                         // Call Relay::forward_later() after dropping the mutable borrow.
@@ -264,8 +305,18 @@ impl Relay {
                 // mutable borrow of the packet device.
                 src.push(packet);
             } else {
-                // The source and destination are different.
-                let dst = host.get_packet_device(*packet.dst_address().ip());
+                // The source and destination are different. Broadcast and
+                // multicast packets from the router were flooded to this host
+                // from its switch, so they go to the internet interface.
+                let dst_address = match *packet.dst_address().ip() {
+                    x if (x.is_broadcast() || x.is_multicast())
+                        && src.get_address().is_unspecified() =>
+                    {
+                        host.default_ip()
+                    }
+                    x => x,
+                };
+                let dst = host.get_packet_device(dst_address);
                 dst.push(packet);
             }
         }
@@ -0,0 +1,175 @@
+//! Per-host throttling rules, which limit the bandwidth of the packets that match a rule in
+//! addition to the host's bandwidth, optionally only during a period of the simulation. Each packet
+//! is limited by the first rule that matches it and is active when the packet is forwarded.
+
+use std::net::SocketAddrV4;
+use std::time::Duration;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::simulation_time::SimulationTime;
+
+use super::token_bucket::TokenBucket;
+use crate::core::support::configuration::{FirewallProtocol, ThrottleDirection, ThrottleOptions};
+use crate::core::support::units;
+use crate::cshadow as c;
+use crate::host::network::firewall::AddressPrefix;
+use crate::network::packet::PacketRc;
+
+/// A throttling rule from the host's configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThrottleRule {
+    bytes_per_second: u64,
+    direction: Option<ThrottleDirection>,
+    start: EmulatedTime,
+    end: Option<EmulatedTime>,
+    protocol: Option<FirewallProtocol>,
+    src: Option<AddressPrefix>,
+    dst: Option<AddressPrefix>,
+    src_port: Option<u16>,
+    dst_port: Option<u16>,
+}
+
+impl ThrottleRule {
+    pub fn from_options(options: &ThrottleOptions) -> anyhow::Result<Self> {
+        let bytes_per_second = options
+            .rate
+            .convert(units::SiPrefixUpper::Base)
+            .unwrap()
+            .value()
+            / 8;
+        if bytes_per_second == 0 {
+            return Err(anyhow::anyhow!(
+                "Throttle rate must be at least 1 byte per second"
+            ));
+        }
+
+        let time = |x: units::Time<units::TimePrefix>| {
+            let x: Duration = x.into();
+            EmulatedTime::SIMULATION_START + SimulationTime::try_from(x).unwrap()
+        };
+        let start = options
+            .start
+            .map(time)
+            .unwrap_or(EmulatedTime::SIMULATION_START);
+        let end = options.end.map(time);
+        if end.is_some_and(|end| end <= start) {
+            return Err(anyhow::anyhow!("Throttle must end after it starts"));
+        }
+
+        Ok(Self {
+            bytes_per_second,
+            direction: options.direction,
+            start,
+            end,
+            protocol: options.protocol,
+            src: options.src.as_deref().map(str::parse).transpose()?,
+            dst: options.dst.as_deref().map(str::parse).transpose()?,
+            src_port: options.src_port,
+            dst_port: options.dst_port,
+        })
+    }
+
+    /// Returns true if the rule limits packets in the direction `direction`.
+    pub fn applies_to(&self, direction: ThrottleDirection) -> bool {
+        self.direction.map_or(true, |x| x == direction)
+    }
+
+    fn matches(
+        &self,
+        protocol: FirewallProtocol,
+        src: SocketAddrV4,
+        dst: SocketAddrV4,
+        now: EmulatedTime,
+    ) -> bool {
+        self.start <= now
+            && self.end.map_or(true, |x| now < x)
+            && self.protocol.map_or(true, |x| x == protocol)
+            && self.src.map_or(true, |x| x.contains(*src.ip()))
+            && self.dst.map_or(true, |x| x.contains(*dst.ip()))
+            && self.src_port.map_or(true, |x| x == src.port())
+            && self.dst_port.map_or(true, |x| x == dst.port())
+    }
+}
+
+/// A throttling rule and the rate limiter of its matching packets.
+pub struct Throttle {
+    rule: ThrottleRule,
+    rate_limiter: TokenBucket,
+}
+
+impl Throttle {
+    pub fn new(rule: ThrottleRule) -> Self {
+        let rate_limiter = super::create_token_bucket(rule.bytes_per_second);
+        Self { rule, rate_limiter }
+    }
+
+    /// Returns true if the throttle limits `packet` at time `now`.
+    pub fn matches(&self, packet: &PacketRc, now: EmulatedTime) -> bool {
+        let protocol = match packet.protocol() {
+            c::_ProtocolType_PTCP => FirewallProtocol::Tcp,
+            c::_ProtocolType_PUDP => FirewallProtocol::Udp,
+            _ => return false,
+        };
+        self.rule
+            .matches(protocol, packet.src_address(), packet.dst_address(), now)
+    }
+
+    /// Remove `bytes` tokens from the throttle's rate limiter, or return the duration until it
+    /// will have enough tokens.
+    pub fn conforming_remove(&mut self, bytes: u64) -> Result<u64, SimulationTime> {
+        self.rate_limiter.comforming_remove(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddrV4 {
+        s.parse().unwrap()
+    }
+
+    fn time(secs: u64) -> EmulatedTime {
+        EmulatedTime::SIMULATION_START + SimulationTime::from_secs(secs)
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let options: ThrottleOptions = serde_yaml::from_str(
+            "
+            rate: 2 Mbit
+            start: 60 s
+            end: 120 s
+            protocol: tcp
+            dst: 11.0.0.0/8
+            dst_port: 443
+            ",
+        )
+        .unwrap();
+        let rule = ThrottleRule::from_options(&options).unwrap();
+        assert_eq!(rule.bytes_per_second, 250_000);
+
+        let tcp = FirewallProtocol::Tcp;
+        let (src, dst) = (addr("12.0.0.1:1000"), addr("11.0.0.1:443"));
+        assert!(!rule.matches(tcp, src, dst, time(59)));
+        assert!(rule.matches(tcp, src, dst, time(60)));
+        assert!(!rule.matches(tcp, src, dst, time(120)));
+        assert!(!rule.matches(FirewallProtocol::Udp, src, dst, time(60)));
+        assert!(!rule.matches(tcp, src, addr("11.0.0.1:80"), time(60)));
+
+        assert!(rule.applies_to(ThrottleDirection::Ingress));
+        assert!(rule.applies_to(ThrottleDirection::Egress));
+    }
+
+    #[test]
+    fn test_invalid_rule() {
+        let check = |yaml: &str| {
+            let options: ThrottleOptions = serde_yaml::from_str(yaml).unwrap();
+            ThrottleRule::from_options(&options).is_err()
+        };
+        assert!(check("{rate: 1 bit}"));
+        assert!(check("{rate: 1 Mbit, start: 10 s, end: 10 s}"));
+        assert!(check("{rate: 1 Mbit, dst: 11.0.0.0/40}"));
+        assert!(!check("{rate: 1 Mbit, start: 10 s}"));
+    }
+}
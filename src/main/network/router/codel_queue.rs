@@ -16,6 +16,7 @@ use shadow_shim_helper_rs::{emulated_time::EmulatedTime, simulation_time::Simula
 
 use crate::cshadow as c;
 use crate::network::packet::{PacketRc, PacketStatus};
+use crate::network::router::QueueDiscipline;
 
 /// The target minimum standing queue delay time, corresponding to the "TARGET"
 /// parameter in the RFC. This is recommended to be set to 5 milliseconds in
@@ -95,13 +96,11 @@ impl CoDelQueue {
     }
 
     /// Returns the total number of packets stored in the queue.
-    #[cfg(test)]
     pub fn len(&self) -> usize {
         self.elements.len()
     }
 
     /// Returns true if the queue is holding zero packets, false otherwise.
-    #[cfg(test)]
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -110,7 +109,6 @@ impl CoDelQueue {
     /// empty. Note that there is no gurantee that a subsequent `pop()`
     /// operation will return the same packet, since it could be dropped by the
     /// queue between the `peek()` and `pop()` operations.
-    #[cfg(test)]
     pub fn peek(&self) -> Option<&PacketRc> {
         self.elements.front().map(|x| &x.packet)
     }
@@ -323,6 +321,24 @@ impl CoDelQueue {
     }
 }
 
+impl QueueDiscipline for CoDelQueue {
+    fn push(&mut self, packet: PacketRc, now: EmulatedTime) {
+        CoDelQueue::push(self, packet, now)
+    }
+
+    fn pop(&mut self, now: EmulatedTime) -> Option<PacketRc> {
+        CoDelQueue::pop(self, now)
+    }
+
+    fn peek(&self) -> Option<&PacketRc> {
+        CoDelQueue::peek(self)
+    }
+
+    fn len(&self) -> usize {
+        CoDelQueue::len(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
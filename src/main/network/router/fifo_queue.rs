@@ -0,0 +1,85 @@
+//! A first-in-first-out packet queue that never drops packets.
+
+use std::collections::VecDeque;
+
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+
+use crate::network::packet::{PacketRc, PacketStatus};
+use crate::network::router::QueueDiscipline;
+
+/// A packet queue that forwards packets in the order they were received, and never drops them. This
+/// models a router with an unlimited buffer, so the queueing delay is only limited by the
+/// receiving host's downstream bandwidth.
+#[derive(Default)]
+pub struct FifoQueue {
+    packets: VecDeque<PacketRc>,
+}
+
+impl FifoQueue {
+    /// Creates a new empty packet queue.
+    pub fn new() -> FifoQueue {
+        FifoQueue {
+            packets: VecDeque::new(),
+        }
+    }
+}
+
+impl QueueDiscipline for FifoQueue {
+    fn push(&mut self, mut packet: PacketRc, _now: EmulatedTime) {
+        packet.add_status(PacketStatus::RouterEnqueued);
+        self.packets.push_back(packet);
+    }
+
+    fn pop(&mut self, _now: EmulatedTime) -> Option<PacketRc> {
+        let mut packet = self.packets.pop_front()?;
+        packet.add_status(PacketStatus::RouterDequeued);
+        Some(packet)
+    }
+
+    fn peek(&self) -> Option<&PacketRc> {
+        self.packets.front()
+    }
+
+    fn len(&self) -> usize {
+        self.packets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::tests::mock_time_millis;
+
+    #[test]
+    fn empty() {
+        let now = mock_time_millis(1000);
+        let mut queue = FifoQueue::new();
+        assert_eq!(queue.len(), 0);
+        assert!(queue.is_empty());
+        assert!(queue.peek().is_none());
+        assert!(queue.pop(now).is_none());
+    }
+
+    #[test]
+    // Ignore in miri for use of c::packet* functions.
+    #[cfg_attr(miri, ignore)]
+    fn no_drops() {
+        let start = mock_time_millis(1000);
+        let mut queue = FifoQueue::new();
+
+        const N: usize = 1000;
+
+        for _ in 0..N {
+            queue.push(PacketRc::mock_new(), start);
+        }
+        assert_eq!(queue.len(), N);
+
+        // packets that waited a long time are still forwarded
+        let end = mock_time_millis(1_000_000);
+        for i in 1..=N {
+            assert!(queue.pop(end).is_some());
+            assert_eq!(queue.len(), N - i);
+        }
+        assert!(queue.pop(end).is_none());
+    }
+}
@@ -2,15 +2,52 @@ use std::cell::RefCell;
 use std::net::Ipv4Addr;
 
 use self::codel_queue::CoDelQueue;
+use self::fifo_queue::FifoQueue;
+use crate::core::hooks::PacketInfo;
+use crate::core::support::configuration::RouterQDiscMode;
 use crate::core::worker::Worker;
 use crate::cshadow as c;
-use crate::network::packet::PacketRc;
+use crate::network::packet::{PacketRc, PacketStatus};
 use crate::network::PacketDevice;
 use crate::utility::{Magic, ObjectCounter};
 mod codel_queue;
+mod fifo_queue;
 
 use shadow_shim_helper_rs::emulated_time::EmulatedTime;
 
+/// A queueing discipline decides how the packets waiting in a router's queue are stored, and which
+/// of them are dropped rather than forwarded. Requires the current time as an argument to avoid
+/// calling into the worker module internally.
+pub trait QueueDiscipline {
+    /// Append a packet to the queue. The queue may drop the packet instead.
+    fn push(&mut self, packet: PacketRc, now: EmulatedTime);
+
+    /// Returns the next packet to forward, or None if the queue is empty. The queue may drop
+    /// packets during this operation.
+    fn pop(&mut self, now: EmulatedTime) -> Option<PacketRc>;
+
+    /// Returns the packet at the front of the queue without removing it. There is no guarantee
+    /// that a subsequent `pop()` will return the same packet, since the queue may drop it.
+    fn peek(&self) -> Option<&PacketRc>;
+
+    /// Returns the number of packets stored in the queue.
+    fn len(&self) -> usize;
+
+    /// Returns true if the queue is holding zero packets, false otherwise.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<RouterQDiscMode> for Box<dyn QueueDiscipline> {
+    fn from(mode: RouterQDiscMode) -> Self {
+        match mode {
+            RouterQDiscMode::Codel => Box::new(CoDelQueue::new()),
+            RouterQDiscMode::Fifo => Box::new(FifoQueue::new()),
+        }
+    }
+}
+
 /// A router assists with moving packets between hosts across the simulated
 /// network.
 pub struct Router {
@@ -18,19 +55,19 @@ pub struct Router {
     _counter: ObjectCounter,
     address: Ipv4Addr,
     /// Packets inbound to the host from the simulated network.
-    inbound_packets: RefCell<CoDelQueue>,
+    inbound_packets: RefCell<Box<dyn QueueDiscipline>>,
 }
 
 impl Router {
     /// Create a new router for a host that will help route packets between it
     /// and other hosts. The `address` must uniquely identify this router to the
-    /// host that owns it.
-    pub fn new(address: Ipv4Addr) -> Router {
+    /// host that owns it. Inbound packets are queued using the `qdisc` queueing discipline.
+    pub fn new(address: Ipv4Addr, qdisc: RouterQDiscMode) -> Router {
         Router {
             magic: Magic::new(),
             address,
             _counter: ObjectCounter::new("Router"),
-            inbound_packets: RefCell::new(CoDelQueue::new()),
+            inbound_packets: RefCell::new(qdisc.into()),
         }
     }
 
@@ -54,9 +91,34 @@ impl Router {
         unsafe { c::packet_unref(cpacket) };
     }
 
-    /// Routes the packet from the virtual internet into our CoDel queue, which
+    /// The number of packets in the inbound queue.
+    pub fn inbound_queue_len(&self) -> usize {
+        self.inbound_packets.borrow().len()
+    }
+
+    /// Routes the packet from the virtual internet into our inbound queue, which
     /// can then be received by the destiantion host by calling pop().
-    pub fn route_incoming_packet(&self, packet: PacketRc) {
+    pub fn route_incoming_packet(&self, mut packet: PacketRc) {
+        // multicast packets are flooded to every host on the switch, but like a router that snoops
+        // on IGMP reports, we only forward them to hosts that have joined the group
+        let dst_ip = *packet.dst_address().ip();
+        if dst_ip.is_multicast() {
+            let is_member = Worker::with_active_host(|host| {
+                host.network_namespace_borrow()
+                    .is_multicast_member(dst_ip, host.default_ip())
+            })
+            .unwrap();
+
+            if !is_member {
+                log::trace!("Dropping a packet for multicast group {dst_ip} with no members");
+                packet.add_status(PacketStatus::RouterDropped);
+                return;
+            }
+        }
+
+        Worker::call_hooks(|hooks, host, now| {
+            hooks.packet_enqueued(host, now, &PacketInfo::from(&packet))
+        });
         self.push_inner(packet, Worker::current_time().unwrap())
     }
 }
@@ -67,8 +129,12 @@ impl PacketDevice for Router {
     }
 
     fn pop(&self) -> Option<PacketRc> {
-        // When the host calls pop, we provide the next packet from the CoDel queue.
-        self.pop_inner(Worker::current_time().unwrap())
+        // When the host calls pop, we provide the next packet from the inbound queue.
+        let packet = self.pop_inner(Worker::current_time().unwrap())?;
+        Worker::call_hooks(|hooks, host, now| {
+            hooks.packet_dequeued(host, now, &PacketInfo::from(&packet))
+        });
+        Some(packet)
     }
 
     fn push(&self, packet: PacketRc) {
@@ -84,18 +150,26 @@ mod tests {
 
     #[test]
     fn empty() {
-        let now = mock_time_millis(1000);
-        let router = Router::new(Ipv4Addr::UNSPECIFIED);
-        assert!(router.inbound_packets.borrow().peek().is_none());
-        assert!(router.pop_inner(now).is_none());
+        for qdisc in [RouterQDiscMode::Codel, RouterQDiscMode::Fifo] {
+            let now = mock_time_millis(1000);
+            let router = Router::new(Ipv4Addr::UNSPECIFIED, qdisc);
+            assert!(router.inbound_packets.borrow().peek().is_none());
+            assert!(router.pop_inner(now).is_none());
+        }
     }
 
     #[test]
     // Ignore in miri for use of c::packet* functions.
     #[cfg_attr(miri, ignore)]
     fn push_pop_simple() {
+        for qdisc in [RouterQDiscMode::Codel, RouterQDiscMode::Fifo] {
+            push_pop_simple_inner(qdisc);
+        }
+    }
+
+    fn push_pop_simple_inner(qdisc: RouterQDiscMode) {
         let now = mock_time_millis(1000);
-        let router = Router::new(Ipv4Addr::UNSPECIFIED);
+        let router = Router::new(Ipv4Addr::UNSPECIFIED, qdisc);
 
         const N: usize = 10;
 
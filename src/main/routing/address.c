@@ -14,6 +14,7 @@
 #include <glib.h>
 #include <netinet/in.h>
 #include <stddef.h>
+#include <string.h>
 #include <sys/socket.h>
 
 #include "main/routing/address.h"
@@ -124,6 +125,16 @@ guint32 address_toNetworkIP(const Address* address) {
     return address->ip;
 }
 
+void address_toNetworkIPv6(const Address* address, struct in6_addr* ipv6) {
+    MAGIC_ASSERT(address);
+    if (address->ip == htonl(INADDR_LOOPBACK)) {
+        *ipv6 = in6addr_loopback;
+        return;
+    }
+    *ipv6 = (struct in6_addr){.s6_addr = {0xfd, 0x00}};
+    memcpy(&ipv6->s6_addr[12], &address->ip, sizeof(address->ip));
+}
+
 const gchar* address_toHostName(const Address* address) {
     MAGIC_ASSERT(address);
     return address->name;
@@ -78,6 +78,17 @@ const gchar* address_toHostIPString(const Address* address);
  */
 guint32 address_toNetworkIP(const Address* address);
 
+/**
+ * Retrieve the IPv6 address that corresponds to this address. Shadow's network only
+ * carries IPv4 packets, so a host's IPv6 address is the prefix fd00::/96 followed by
+ * its IPv4 address, and the loopback address is ::1. Keep in sync with
+ * `host::network::ipv6`.
+ * @param address a valid, non-NULL Address structure previously created
+ * with address_new()
+ * @param ipv6 the IPv6 address to write to
+ */
+void address_toNetworkIPv6(const Address* address, struct in6_addr* ipv6);
+
 /**
  * Retrieves the hostname of this address. The caller does not own and should
  * not modify or free the string.
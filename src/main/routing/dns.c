@@ -4,6 +4,7 @@
  * See LICENSE for licensing information
  */
 
+#include <arpa/inet.h>
 #include <errno.h>
 #include <glib.h>
 #include <netinet/in.h>
@@ -202,6 +203,14 @@ static void _dns_writeHostLine(gpointer key, gpointer value, gpointer data) {
     const Address* address = value;
     GString* buf = data;
     g_string_append_printf(buf, "%s %s\n", address_toHostIPString(address), name);
+
+    struct in6_addr ipv6;
+    address_toNetworkIPv6(address, &ipv6);
+
+    char ipv6String[INET6_ADDRSTRLEN];
+    if (inet_ntop(AF_INET6, &ipv6, ipv6String, sizeof(ipv6String)) != NULL) {
+        g_string_append_printf(buf, "%s %s\n", ipv6String, name);
+    }
 }
 
 static bool _dns_writeNewHostsFile(DNS* dns) {
@@ -215,7 +224,7 @@ static bool _dns_writeNewHostsFile(DNS* dns) {
         return false;
     }
 
-    GString* buf = g_string_new("127.0.0.1 localhost\n");
+    GString* buf = g_string_new("127.0.0.1 localhost\n::1 localhost\n");
     g_hash_table_foreach(dns->addressByName, _dns_writeHostLine, buf);
 
     trace("Hosts file string buffer is %zu bytes.", buf->len);
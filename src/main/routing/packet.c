@@ -8,6 +8,7 @@
 #include <netinet/in.h>
 #include <stddef.h>
 #include <stdint.h>
+#include <string.h>
 
 #include "lib/logger/log_level.h"
 #include "lib/logger/logger.h"
@@ -19,21 +20,6 @@
 #include "main/utility/utility.h"
 #include "shd-config.h"
 
-/* g_memdup() is deprecated due to a security issue and has been replaced
- * by g_memdup2(), but not all of our supported platforms support this yet.
- * https://gitlab.gnome.org/GNOME/glib/-/issues/2319
- */
-
-#if HAS_MEMDUP2
-#define compat_static_g_memdup g_memdup2
-#else
-#define compat_static_g_memdup(mem, byte_size)                                                     \
-    ({                                                                                             \
-        static_assert(byte_size < UINT_MAX, "g_memdup() overflow");                                \
-        g_memdup(mem, byte_size);                                                                  \
-    })
-#endif
-
 /* thread-safe structure representing a data/network packet */
 
 typedef struct _PacketLocalHeader PacketLocalHeader;
@@ -81,6 +67,9 @@ struct _Packet {
      */
     uint64_t priority;
 
+    /* if set, only the length of the payload is stored, and the payload reads as zero bytes */
+    gboolean zeroPayload;
+
     PacketDeliveryStatusFlags allStatus;
     GQueue* orderedStatus;
 
@@ -93,13 +82,14 @@ const gchar* protocol_toString(ProtocolType type) {
         case PUDP: return "UDP";
         case PTCP: return "TCP";
         case PMOCK: return "MOCK";
+        case PICMP: return "ICMP";
         default: return "UNKNOWN";
     }
 }
 
 // Exposed for unit testing only. Use `packet_new` outside of tests.
 Packet* packet_new_inner(guint hostID, guint64 packetID) {
-    Packet* packet = g_new0(Packet, 1);
+    Packet* packet = worker_new0_pooled(Packet);
     MAGIC_INIT(packet);
 
     packet->referenceCount = 1;
@@ -116,6 +106,7 @@ Packet* packet_new(const Host* host) {
     guint hostID = host_getID(host);
     guint64 packetID = host_getNewPacketID(host);
     Packet* packet = packet_new_inner(hostID, packetID);
+    packet->zeroPayload = host_zeroPacketPayloads(host);
     worker_count_allocation(Packet);
     return packet;
 }
@@ -130,7 +121,11 @@ void packet_setPayload(Packet* packet, const Thread* thread, UntypedForeignPtr p
     utility_debugAssert(!packet->payload);
 
     /* the payload starts with 1 ref, which we hold */
-    packet->payload = payload_new(thread, payload, payloadLength);
+    if (packet->zeroPayload) {
+        packet->payload = payload_newZeroed(payloadLength);
+    } else {
+        packet->payload = payload_new(thread, payload, payloadLength);
+    }
     utility_alwaysAssert(packet->payload != NULL);
     /* application data needs a priority ordering for FIFO onto the wire */
     packet->priority = packetPriority;
@@ -147,7 +142,11 @@ void packet_setPayloadWithMemoryManager(Packet* packet, UntypedForeignPtr payloa
     utility_debugAssert(!packet->payload);
 
     /* the payload starts with 1 ref, which we hold */
-    packet->payload = payload_newWithMemoryManager(payload, payloadLength, mem);
+    if (packet->zeroPayload) {
+        packet->payload = payload_newZeroed(payloadLength);
+    } else {
+        packet->payload = payload_newWithMemoryManager(payload, payloadLength, mem);
+    }
     utility_alwaysAssert(packet->payload != NULL);
     /* application data needs a priority ordering for FIFO onto the wire */
     packet->priority = packetPriority;
@@ -160,7 +159,11 @@ void packet_setPayloadFromShadow(Packet* packet, const void* payload, gsize payl
     utility_debugAssert(!packet->payload);
 
     /* the payload starts with 1 ref, which we hold */
-    packet->payload = payload_newFromShadow(payload, payloadLength);
+    if (packet->zeroPayload) {
+        packet->payload = payload_newZeroed(payloadLength);
+    } else {
+        packet->payload = payload_newFromShadow(payload, payloadLength);
+    }
     utility_alwaysAssert(packet->payload != NULL);
     /* application data needs a priority ordering for FIFO onto the wire */
     packet->priority = packetPriority;
@@ -172,13 +175,14 @@ void packet_setPayloadFromShadow(Packet* packet, const void* payload, gsize payl
 Packet* packet_copy(Packet* packet) {
     MAGIC_ASSERT(packet);
 
-    Packet* copy = g_new0(Packet, 1);
+    Packet* copy = worker_new0_pooled(Packet);
     MAGIC_INIT(copy);
 
     copy->referenceCount = 1;
 
     copy->hostID = packet->hostID;
     copy->packetID = packet->packetID;
+    copy->zeroPayload = packet->zeroPayload;
 
     if(packet->payload) {
         copy->payload = packet->payload;
@@ -197,17 +201,26 @@ Packet* packet_copy(Packet* packet) {
     if(packet->header) {
         switch (packet->protocol) {
             case PLOCAL: {
-                copy->header = compat_static_g_memdup(packet->header, sizeof(PacketLocalHeader));
+                copy->header = worker_new0_pooled(PacketLocalHeader);
+                memcpy(copy->header, packet->header, sizeof(PacketLocalHeader));
                 break;
             }
 
             case PUDP: {
-                copy->header = compat_static_g_memdup(packet->header, sizeof(PacketUDPHeader));
+                copy->header = worker_new0_pooled(PacketUDPHeader);
+                memcpy(copy->header, packet->header, sizeof(PacketUDPHeader));
+                break;
+            }
+
+            case PICMP: {
+                copy->header = worker_new0_pooled(PacketICMPHeader);
+                memcpy(copy->header, packet->header, sizeof(PacketICMPHeader));
                 break;
             }
 
             case PTCP: {
-                copy->header = compat_static_g_memdup(packet->header, sizeof(PacketTCPHeader));
+                copy->header = worker_new0_pooled(PacketTCPHeader);
+                memcpy(copy->header, packet->header, sizeof(PacketTCPHeader));
 
                 PacketTCPHeader* packetHeader = (PacketTCPHeader*)packet->header;
                 PacketTCPHeader* copyHeader = (PacketTCPHeader*)copy->header;
@@ -232,18 +245,44 @@ Packet* packet_copy(Packet* packet) {
     return copy;
 }
 
-static void _packet_free(Packet* packet) {
-    MAGIC_ASSERT(packet);
+static void _packet_freeHeader(Packet* packet) {
+    switch (packet->protocol) {
+        case PLOCAL: {
+            worker_free_pooled(PacketLocalHeader, packet->header);
+            break;
+        }
 
-    if(packet->protocol == PTCP) {
-        PacketTCPHeader* header = (PacketTCPHeader*)packet->header;
-        if(header->selectiveACKs) {
-            g_list_free(header->selectiveACKs);
+        case PUDP: {
+            worker_free_pooled(PacketUDPHeader, packet->header);
+            break;
+        }
+
+        case PICMP: {
+            worker_free_pooled(PacketICMPHeader, packet->header);
+            break;
+        }
+
+        case PTCP: {
+            PacketTCPHeader* header = (PacketTCPHeader*)packet->header;
+            if (header->selectiveACKs) {
+                g_list_free(header->selectiveACKs);
+            }
+            worker_free_pooled(PacketTCPHeader, packet->header);
+            break;
+        }
+
+        default: {
+            utility_panic("unrecognized protocol");
+            break;
         }
     }
+}
+
+static void _packet_free(Packet* packet) {
+    MAGIC_ASSERT(packet);
 
     if(packet->header) {
-        g_free(packet->header);
+        _packet_freeHeader(packet);
     }
     if(packet->payload) {
         payload_unref(packet->payload);
@@ -253,7 +292,7 @@ static void _packet_free(Packet* packet) {
     }
 
     MAGIC_CLEAR(packet);
-    g_free(packet);
+    worker_free_pooled(Packet, packet);
 
     worker_count_deallocation(Packet);
 }
@@ -306,7 +345,7 @@ void packet_setLocal(Packet* packet, enum ProtocolLocalFlags flags,
     utility_debugAssert(!(packet->header) && packet->protocol == PNONE);
     utility_debugAssert(port > 0);
 
-    PacketLocalHeader* header = g_new0(PacketLocalHeader, 1);
+    PacketLocalHeader* header = worker_new0_pooled(PacketLocalHeader);
 
     header->flags = flags;
     header->sourceDescriptorHandle = sourceDescriptorHandle;
@@ -325,7 +364,7 @@ void packet_setUDP(Packet* packet, enum ProtocolUDPFlags flags,
     utility_debugAssert(!(packet->header) && packet->protocol == PNONE);
     utility_debugAssert(sourceIP && sourcePort && destinationIP && destinationPort);
 
-    PacketUDPHeader* header = g_new0(PacketUDPHeader, 1);
+    PacketUDPHeader* header = worker_new0_pooled(PacketUDPHeader);
 
     header->flags = flags;
     header->sourceIP = sourceIP;
@@ -345,7 +384,7 @@ void packet_setTCP(Packet* packet, enum ProtocolTCPFlags flags,
     utility_debugAssert(!(packet->header) && packet->protocol == PNONE);
     utility_debugAssert(sourceIP && sourcePort && destinationIP && destinationPort);
 
-    PacketTCPHeader* header = g_new0(PacketTCPHeader, 1);
+    PacketTCPHeader* header = worker_new0_pooled(PacketTCPHeader);
 
     header->flags = flags;
     header->sourceIP = sourceIP;
@@ -358,8 +397,69 @@ void packet_setTCP(Packet* packet, enum ProtocolTCPFlags flags,
     packet->protocol = PTCP;
 }
 
+// The addresses, identifier, and sequence number must be in network byte order.
+void packet_setICMP(Packet* packet, enum ProtocolICMPType type, guint8 code, in_addr_t sourceIP,
+                    in_addr_t destinationIP, guint16 identifier, guint16 sequence) {
+    MAGIC_ASSERT(packet);
+    utility_debugAssert(!(packet->header) && packet->protocol == PNONE);
+    utility_debugAssert(sourceIP && destinationIP);
+
+    PacketICMPHeader* header = worker_new0_pooled(PacketICMPHeader);
+
+    header->type = type;
+    header->code = code;
+    header->sourceIP = sourceIP;
+    header->destinationIP = destinationIP;
+    header->identifier = identifier;
+    header->sequence = sequence;
+
+    packet->header = header;
+    packet->protocol = PICMP;
+}
+
+// Replace the addresses of a TCP, UDP, or ICMP packet. The addresses and ports must be in network
+// byte order.
+void packet_setAddresses(Packet* packet, in_addr_t sourceIP, in_port_t sourcePort,
+                         in_addr_t destinationIP, in_port_t destinationPort) {
+    MAGIC_ASSERT(packet);
+    utility_debugAssert(sourceIP && sourcePort && destinationIP && destinationPort);
+
+    switch (packet->protocol) {
+        case PUDP: {
+            PacketUDPHeader* header = packet->header;
+            header->sourceIP = sourceIP;
+            header->sourcePort = sourcePort;
+            header->destinationIP = destinationIP;
+            header->destinationPort = destinationPort;
+            break;
+        }
+
+        case PTCP: {
+            PacketTCPHeader* header = packet->header;
+            header->sourceIP = sourceIP;
+            header->sourcePort = sourcePort;
+            header->destinationIP = destinationIP;
+            header->destinationPort = destinationPort;
+            break;
+        }
+
+        case PICMP: {
+            // the ports of an echo message are its identifier, which isn't changed
+            PacketICMPHeader* header = packet->header;
+            header->sourceIP = sourceIP;
+            header->destinationIP = destinationIP;
+            break;
+        }
+
+        default: {
+            utility_panic("unrecognized protocol");
+            break;
+        }
+    }
+}
+
 void packet_updateTCP(Packet* packet, guint acknowledgement, GList* selectiveACKs, guint window,
-                      unsigned char windowScale, bool windowScaleSet,
+                      unsigned char windowScale, bool windowScaleSet, bool timestampSet,
                       CSimulationTime timestampValue, CSimulationTime timestampEcho) {
     MAGIC_ASSERT(packet);
     utility_debugAssert(packet->header && (packet->protocol == PTCP));
@@ -382,6 +482,7 @@ void packet_updateTCP(Packet* packet, guint acknowledgement, GList* selectiveACK
     header->window = window;
     header->windowScale = windowScale;
     header->windowScaleSet = windowScaleSet;
+    header->timestampSet = timestampSet;
     header->timestampValue = timestampValue;
     header->timestampEcho = timestampEcho;
 }
@@ -407,6 +508,8 @@ gsize packet_getHeaderSize(const Packet* packet) {
 
     if (packet->protocol == PUDP) {
         return CONFIG_HEADER_SIZE_UDPIP;
+    } else if (packet->protocol == PICMP) {
+        return CONFIG_HEADER_SIZE_ICMPIP;
     } else if (packet->protocol == PTCP) {
         gsize size = CONFIG_HEADER_SIZE_TCPIP;
 
@@ -417,6 +520,10 @@ gsize packet_getHeaderSize(const Packet* packet) {
             // window scale option is 3 bytes
             size += 3;
         }
+        if (header->timestampSet) {
+            // timestamps option is 10 bytes
+            size += 10;
+        }
 
         // add padding bytes if needed
         if ((size % 4) != 0) {
@@ -457,6 +564,12 @@ in_addr_t packet_getDestinationIP(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            PacketICMPHeader* header = packet->header;
+            ip = header->destinationIP;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -491,6 +604,13 @@ in_port_t packet_getDestinationPort(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            // echo messages are demultiplexed to sockets by their identifier
+            PacketICMPHeader* header = packet->header;
+            port = header->identifier;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -524,6 +644,12 @@ in_addr_t packet_getSourceIP(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            PacketICMPHeader* header = packet->header;
+            ip = header->sourceIP;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -558,6 +684,13 @@ in_port_t packet_getSourcePort(const Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            // echo messages are demultiplexed to sockets by their identifier
+            PacketICMPHeader* header = packet->header;
+            port = header->identifier;
+            break;
+        }
+
         default: {
             utility_panic("unrecognized protocol");
             break;
@@ -634,6 +767,12 @@ PacketTCPHeader* packet_getTCPHeader(const Packet* packet) {
     return (PacketTCPHeader*)packet->header;
 }
 
+PacketICMPHeader* packet_getICMPHeader(const Packet* packet) {
+    MAGIC_ASSERT(packet);
+    utility_alwaysAssert(packet->protocol == PICMP);
+    return (PacketICMPHeader*)packet->header;
+}
+
 static const gchar* _packet_deliveryStatusToAscii(PacketDeliveryStatusFlags status) {
     switch (status) {
         case PDS_NONE: return "NONE";
@@ -762,6 +901,21 @@ gchar* packet_toString(Packet* packet) {
             break;
         }
 
+        case PICMP: {
+            PacketICMPHeader* header = packet->header;
+            gchar* sourceIPString = address_ipToNewString(header->sourceIP);
+            gchar* destinationIPString = address_ipToNewString(header->destinationIP);
+
+            g_string_append_printf(packetString, "%s -> %s type=%u code=%u id=%u seq=%u bytes=%u",
+                                   sourceIPString, destinationIPString, header->type, header->code,
+                                   ntohs(header->identifier), ntohs(header->sequence),
+                                   payloadLength);
+
+            g_free(sourceIPString);
+            g_free(destinationIPString);
+            break;
+        }
+
         case PMOCK: {
             // TODO: We should panic here if this isn't a test.  We don't have a
             // good way to check whether this is being run inside a test in C.
@@ -812,7 +966,7 @@ void packet_addDeliveryStatus(Packet* packet, PacketDeliveryStatusFlags status)
     }
 }
 
-PacketDeliveryStatusFlags packet_getDeliveryStatus(Packet* packet) {
+PacketDeliveryStatusFlags packet_getDeliveryStatus(const Packet* packet) {
     MAGIC_ASSERT(packet);
     return packet->allStatus;
 }
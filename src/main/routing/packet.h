@@ -37,10 +37,29 @@ struct _PacketTCPHeader {
     guint window;
     unsigned char windowScale;
     bool windowScaleSet;
+    // internally the timestamps are always set, but they're only part of the header (and use
+    // header bytes) if the timestamps option is set
+    bool timestampSet;
     CSimulationTime timestampValue;
     CSimulationTime timestampEcho;
 };
 
+typedef struct _PacketICMPHeader PacketICMPHeader;
+struct _PacketICMPHeader {
+    enum ProtocolICMPType type;
+    guint8 code;
+
+    // address is in network byte order
+    in_addr_t sourceIP;
+    // address is in network byte order
+    in_addr_t destinationIP;
+
+    // identifier is in network byte order
+    guint16 identifier;
+    // sequence number is in network byte order
+    guint16 sequence;
+};
+
 const gchar* protocol_toString(ProtocolType type);
 
 Packet* packet_new(const Host* host);
@@ -62,6 +81,12 @@ void packet_ref(Packet* packet);
 void packet_unref(Packet* packet);
 static inline void packet_unrefTaskFreeFunc(gpointer packet) { packet_unref(packet); }
 
+// Packets with smaller priorities are sent first. Data packets get their priority from
+// `host_getNextPacketPriority`, which returns increasing values starting at 1. The priority
+// `PACKET_PRIORITY_CONTROL` is reserved for control packets, which are sent before data packets.
+// This must match `FifoPacketPriority` in "main/host/network/interface.rs".
+#define PACKET_PRIORITY_CONTROL 0
+
 void packet_setPriority(Packet *packet, uint64_t value);
 uint64_t packet_getPriority(const Packet* packet);
 
@@ -79,8 +104,18 @@ void packet_setTCP(Packet* packet, enum ProtocolTCPFlags flags,
         in_addr_t sourceIP, in_port_t sourcePort,
         in_addr_t destinationIP, in_port_t destinationPort, guint sequence);
 
+// Echo messages use their identifier as both the source and destination port of the packet. The
+// addresses, identifier, and sequence number must be in network byte order.
+void packet_setICMP(Packet* packet, enum ProtocolICMPType type, guint8 code, in_addr_t sourceIP,
+                    in_addr_t destinationIP, guint16 identifier, guint16 sequence);
+
+// Replace the addresses of a TCP, UDP, or ICMP packet. The ports of an ICMP packet are ignored. The
+// addresses and ports must be in network byte order.
+void packet_setAddresses(Packet* packet, in_addr_t sourceIP, in_port_t sourcePort,
+                         in_addr_t destinationIP, in_port_t destinationPort);
+
 void packet_updateTCP(Packet* packet, guint acknowledgement, GList* selectiveACKs, guint window,
-                      unsigned char windowScale, bool windowScaleSet,
+                      unsigned char windowScale, bool windowScaleSet, bool timestampSet,
                       CSimulationTime timestampValue, CSimulationTime timestampEcho);
 
 gsize packet_getTotalSize(const Packet* packet);
@@ -108,10 +143,11 @@ guint packet_copyPayloadShadow(const Packet* packet, gsize payloadOffset, void*
                                gsize bufferLength);
 GList* packet_copyTCPSelectiveACKs(Packet* packet);
 PacketTCPHeader* packet_getTCPHeader(const Packet* packet);
+PacketICMPHeader* packet_getICMPHeader(const Packet* packet);
 gint packet_compareTCPSequence(Packet* packet1, Packet* packet2, gpointer user_data);
 
 void packet_addDeliveryStatus(Packet* packet, PacketDeliveryStatusFlags status);
-PacketDeliveryStatusFlags packet_getDeliveryStatus(Packet* packet);
+PacketDeliveryStatusFlags packet_getDeliveryStatus(const Packet* packet);
 
 gchar* packet_toString(Packet* packet);
 
@@ -17,21 +17,32 @@
 struct _Payload {
     GMutex lock;
     guint referenceCount;
+    /* NULL for zeroed payloads */
     gpointer data;
     gsize length;
     MAGIC_DECLARE;
 };
 
+/* the contents of zeroed payloads, which can't be longer than an IP packet */
+static const guint8 _payloadZeroes[G_MAXUINT16 + 1];
+
+static gconstpointer _payload_getBytes(Payload* payload, gsize offset) {
+    if (payload->data) {
+        return payload->data + offset;
+    }
+    return _payloadZeroes;
+}
+
 /* If modifying this function, you should also modify `payload_newWithMemoryManager` below. */
 Payload* payload_new(const Thread* thread, UntypedForeignPtr data, gsize dataLength) {
-    Payload* payload = g_new0(Payload, 1);
+    Payload* payload = worker_new0_pooled(Payload);
     MAGIC_INIT(payload);
 
     if (data.val && dataLength > 0) {
         payload->data = g_malloc0(dataLength);
         if (process_readPtr(thread_getProcess(thread), payload->data, data, dataLength) != 0) {
             warning("Couldn't read data for packet");
-            g_free(payload);
+            worker_free_pooled(Payload, payload);
             return NULL;
         }
         utility_debugAssert(payload->data != NULL);
@@ -51,14 +62,14 @@ Payload* payload_new(const Thread* thread, UntypedForeignPtr data, gsize dataLen
  * `payload_new`. */
 Payload* payload_newWithMemoryManager(UntypedForeignPtr data, gsize dataLength,
                                       const MemoryManager* mem) {
-    Payload* payload = g_new0(Payload, 1);
+    Payload* payload = worker_new0_pooled(Payload);
     MAGIC_INIT(payload);
 
     if (data.val && dataLength > 0) {
         payload->data = g_malloc0(dataLength);
         if (memorymanager_readPtr(mem, payload->data, data, dataLength) != 0) {
             warning("Couldn't read data for packet");
-            g_free(payload);
+            worker_free_pooled(Payload, payload);
             return NULL;
         }
         utility_debugAssert(payload->data != NULL);
@@ -74,7 +85,7 @@ Payload* payload_newWithMemoryManager(UntypedForeignPtr data, gsize dataLength,
 }
 
 Payload* payload_newFromShadow(const void* data, gsize dataLength) {
-    Payload* payload = g_new0(Payload, 1);
+    Payload* payload = worker_new0_pooled(Payload);
     MAGIC_INIT(payload);
 
     if (data && dataLength > 0) {
@@ -92,6 +103,22 @@ Payload* payload_newFromShadow(const void* data, gsize dataLength) {
     return payload;
 }
 
+Payload* payload_newZeroed(gsize dataLength) {
+    utility_alwaysAssert(dataLength <= sizeof(_payloadZeroes));
+
+    Payload* payload = worker_new0_pooled(Payload);
+    MAGIC_INIT(payload);
+
+    payload->length = dataLength;
+
+    g_mutex_init(&(payload->lock));
+    payload->referenceCount = 1;
+
+    worker_count_allocation(Payload);
+
+    return payload;
+}
+
 static void _payload_free(Payload* payload) {
     MAGIC_ASSERT(payload);
 
@@ -102,7 +129,7 @@ static void _payload_free(Payload* payload) {
     }
 
     MAGIC_CLEAR(payload);
-    g_free(payload);
+    worker_free_pooled(Payload, payload);
 
     worker_count_deallocation(Payload);
 }
@@ -157,7 +184,7 @@ gssize payload_getData(Payload* payload, const Thread* thread, gsize offset,
 
     if (copyLength > 0) {
         int err = process_writePtr(
-            thread_getProcess(thread), destBuffer, payload->data + offset, copyLength);
+            thread_getProcess(thread), destBuffer, _payload_getBytes(payload, offset), copyLength);
         if (err) {
             _payload_unlock(payload);
             return err;
@@ -185,8 +212,8 @@ gssize payload_getDataWithMemoryManager(Payload* payload, gsize offset,
     gssize copyLength = MIN(targetLength, destBufferLength);
 
     if (copyLength > 0) {
-        int err =
-            memorymanager_writePtr(mem, destBuffer, payload->data + offset, copyLength);
+        int err = memorymanager_writePtr(
+            mem, destBuffer, _payload_getBytes(payload, offset), copyLength);
         if (err) {
             _payload_unlock(payload);
             return err;
@@ -210,7 +237,7 @@ gsize payload_getDataShadow(Payload* payload, gsize offset, void* destBuffer,
     gsize copyLength = MIN(targetLength, destBufferLength);
 
     if(copyLength > 0) {
-        memcpy(destBuffer, payload->data + offset, copyLength);
+        memcpy(destBuffer, _payload_getBytes(payload, offset), copyLength);
     }
 
     _payload_unlock(payload);
@@ -18,6 +18,8 @@ Payload* payload_new(const Thread* thread, UntypedForeignPtr data, gsize dataLen
 Payload* payload_newWithMemoryManager(UntypedForeignPtr data, gsize dataLength,
                                       const MemoryManager* mem);
 Payload* payload_newFromShadow(const void* data, gsize dataLength);
+/* A payload of `dataLength` zero bytes. Only the length is stored. */
+Payload* payload_newZeroed(gsize dataLength);
 
 void payload_ref(Payload* payload);
 void payload_unref(Payload* payload);
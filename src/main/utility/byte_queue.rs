@@ -306,6 +306,30 @@ impl ByteQueue {
         Some((bytes.into(), chunk_type))
     }
 
+    /// Pop a single chunk of data from the queue with at most `max_len` bytes. Unlike
+    /// [`pop_chunk()`](Self::pop_chunk), a packet with more than `max_len` bytes is split, and its
+    /// remaining bytes are left at the front of the queue as a new packet.
+    pub fn pop_chunk_split(&mut self, max_len: usize) -> Option<(Bytes, ChunkType)> {
+        let chunk = self.bytes.front_mut()?;
+        let chunk_type = chunk.chunk_type;
+
+        let bytes = if chunk.data.len() > max_len {
+            chunk.data.split_to(max_len)
+        } else {
+            self.bytes.pop_front().unwrap().data
+        };
+
+        self.length -= bytes.len();
+
+        Some((bytes.into(), chunk_type))
+    }
+
+    /// Iterate over the chunks in the queue without removing them. Consecutive chunks may have
+    /// the same type.
+    pub fn chunks(&self) -> impl Iterator<Item = (&[u8], ChunkType)> {
+        self.bytes.iter().map(|x| (x.data.as_ref(), x.chunk_type))
+    }
+
     /// Peek data from the queue. Only a single type of data will be peeked per invocation.
     /// Zero-length packets may be returned. If packet data is returned but `dst` did not have
     /// enough space, the packet written to `dst` will be truncated. Returns a tuple containing the
@@ -724,6 +748,43 @@ mod tests {
         assert_eq!(bq.num_bytes(), 3);
     }
 
+    #[test]
+    fn test_bytequeue_pop_chunk_split() {
+        let mut bq = ByteQueue::new(10);
+
+        bq.push_packet(&[1, 2, 3, 4, 5][..], 5).unwrap();
+        bq.push_stream(&[6, 7, 8][..]).unwrap();
+
+        let chunks: Vec<_> = bq.chunks().map(|(b, t)| (b.to_vec(), t)).collect();
+        assert_eq!(
+            chunks,
+            [
+                (vec![1, 2, 3, 4, 5], ChunkType::Packet),
+                (vec![6, 7, 8], ChunkType::Stream)
+            ]
+        );
+
+        // the packet is split, and its remainder stays at the front
+        let (bytes, chunk_type) = bq.pop_chunk_split(2).unwrap();
+        assert_eq!((&bytes[..], chunk_type), (&[1, 2][..], ChunkType::Packet));
+        assert_eq!(bq.num_bytes(), 6);
+
+        let (bytes, chunk_type) = bq.pop_chunk_split(10).unwrap();
+        assert_eq!(
+            (&bytes[..], chunk_type),
+            (&[3, 4, 5][..], ChunkType::Packet)
+        );
+
+        let (bytes, chunk_type) = bq.pop_chunk_split(10).unwrap();
+        assert_eq!(
+            (&bytes[..], chunk_type),
+            (&[6, 7, 8][..], ChunkType::Stream)
+        );
+
+        assert!(bq.pop_chunk_split(10).is_none());
+        assert_eq!(bq.num_bytes(), 0);
+    }
+
     /// Test that the peek output always matches the pop output.
     #[test]
     fn test_bytequeue_peek() {
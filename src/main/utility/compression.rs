@@ -0,0 +1,237 @@
+//! Streaming compression for output files such as pcap files and process output.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::core::support::configuration::OutputCompression;
+
+/// The path of a file compressed with `compression`, which has the compression format's extension
+/// added to `path`.
+pub fn compressed_path(path: &Path, compression: OutputCompression) -> PathBuf {
+    let extension = match compression {
+        OutputCompression::None => return path.to_owned(),
+        OutputCompression::Gzip => "gz",
+        OutputCompression::Zstd => "zst",
+    };
+
+    let mut path = path.to_owned().into_os_string();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// A writer that compresses the data written to it. The compressed stream is finished when the
+/// writer is dropped, but errors are ignored, so [`finish`](Self::finish) should be used if errors
+/// need to be handled.
+pub struct CompressedWriter<W: Write> {
+    // only `None` after the stream has been finished
+    encoder: Option<Encoder<W>>,
+}
+
+enum Encoder<W: Write> {
+    None(W),
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn new(inner: W, compression: OutputCompression) -> std::io::Result<Self> {
+        let encoder = match compression {
+            OutputCompression::None => Encoder::None(inner),
+            OutputCompression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                inner,
+                flate2::Compression::default(),
+            )),
+            OutputCompression::Zstd => Encoder::Zstd(zstd::stream::write::Encoder::new(
+                inner,
+                zstd::DEFAULT_COMPRESSION_LEVEL,
+            )?),
+        };
+
+        Ok(Self {
+            encoder: Some(encoder),
+        })
+    }
+
+    /// Write the end of the compressed stream and return the inner writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.finish_encoder()
+    }
+
+    fn finish_encoder(&mut self) -> std::io::Result<W> {
+        match self.encoder.take().unwrap() {
+            Encoder::None(inner) => Ok(inner),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+        }
+    }
+
+    fn encoder(&mut self) -> &mut dyn Write {
+        match self.encoder.as_mut().unwrap() {
+            Encoder::None(inner) => inner,
+            Encoder::Gzip(encoder) => encoder,
+            Encoder::Zstd(encoder) => encoder,
+        }
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.encoder().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.encoder().flush()
+    }
+}
+
+impl<W: Write> Drop for CompressedWriter<W> {
+    fn drop(&mut self) {
+        if self.encoder.is_some() {
+            let _ = self.finish_encoder();
+        }
+    }
+}
+
+/// A thread that compresses the data written to a pipe and writes it to a file. This allows a
+/// managed process's output to be compressed while it's being written, since the legacy file
+/// objects used for a process's stdout and stderr can only write to an OS file descriptor.
+pub struct PipeCompressor {
+    path: PathBuf,
+    thread: JoinHandle<std::io::Result<()>>,
+}
+
+impl PipeCompressor {
+    /// Create the file at `path` and start compressing to it. Returns the compressor and the write
+    /// end of the pipe. The file is finished once all copies of the write end have been closed.
+    pub fn spawn(
+        path: PathBuf,
+        compression: OutputCompression,
+    ) -> std::io::Result<(Self, OwnedFd)> {
+        let file = File::create(&path)?;
+
+        let (reader, writer) = nix::unistd::pipe2(nix::fcntl::OFlag::O_CLOEXEC)?;
+        let mut reader = unsafe { File::from_raw_fd(reader) };
+        let writer = unsafe { OwnedFd::from_raw_fd(writer) };
+
+        let mut output = CompressedWriter::new(BufWriter::new(file), compression)?;
+
+        let thread = std::thread::Builder::new()
+            .name("output-compressor".into())
+            .spawn(move || {
+                let rv =
+                    std::io::copy(&mut reader, &mut output).and_then(|_| output.finish()?.flush());
+
+                // if we couldn't write the file, keep reading from the pipe so that the process
+                // doesn't see an error (or block) when writing its output
+                if rv.is_err() {
+                    std::io::copy(&mut reader, &mut std::io::sink())?;
+                }
+
+                rv
+            })?;
+
+        Ok((Self { path, thread }, writer))
+    }
+
+    /// The path of the compressed file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait until all copies of the write end of the pipe have been closed and the compressed file
+    /// has been finished.
+    pub fn join(self) -> std::io::Result<()> {
+        self.thread.join().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn decompress(bytes: &[u8], compression: OutputCompression) -> Vec<u8> {
+        match compression {
+            OutputCompression::None => bytes.to_vec(),
+            OutputCompression::Gzip => {
+                let mut out = Vec::new();
+                flate2::read::GzDecoder::new(bytes)
+                    .read_to_end(&mut out)
+                    .unwrap();
+                out
+            }
+            OutputCompression::Zstd => zstd::decode_all(bytes).unwrap(),
+        }
+    }
+
+    const ALL: [OutputCompression; 3] = [
+        OutputCompression::None,
+        OutputCompression::Gzip,
+        OutputCompression::Zstd,
+    ];
+
+    #[test]
+    fn test_path() {
+        let path = Path::new("/a/b.pcap");
+        assert_eq!(compressed_path(path, OutputCompression::None), path);
+        assert_eq!(
+            compressed_path(path, OutputCompression::Gzip),
+            Path::new("/a/b.pcap.gz")
+        );
+        assert_eq!(
+            compressed_path(path, OutputCompression::Zstd),
+            Path::new("/a/b.pcap.zst")
+        );
+    }
+
+    #[test]
+    fn test_writer() {
+        let data = b"hello world\n".repeat(1000);
+
+        for compression in ALL {
+            let mut writer = CompressedWriter::new(Vec::new(), compression).unwrap();
+            writer.write_all(&data).unwrap();
+            let bytes = writer.finish().unwrap();
+            assert_eq!(decompress(&bytes, compression), data);
+        }
+    }
+
+    #[test]
+    fn test_writer_drop() {
+        let data = b"hello world\n".repeat(1000);
+
+        for compression in ALL {
+            let mut bytes = Vec::new();
+            let mut writer = CompressedWriter::new(&mut bytes, compression).unwrap();
+            writer.write_all(&data).unwrap();
+            drop(writer);
+            assert_eq!(decompress(&bytes, compression), data);
+        }
+    }
+
+    #[test]
+    // Uses FFI
+    #[cfg_attr(miri, ignore)]
+    fn test_pipe() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = b"hello world\n".repeat(100_000);
+
+        for compression in ALL {
+            let path = compressed_path(&dir.path().join("stdout"), compression);
+            let (compressor, writer) = PipeCompressor::spawn(path.clone(), compression).unwrap();
+            assert_eq!(compressor.path(), path);
+
+            // larger than the pipe's buffer
+            File::from(writer).write_all(&data).unwrap();
+            compressor.join().unwrap();
+
+            let bytes = std::fs::read(&path).unwrap();
+            assert_eq!(decompress(&bytes, compression), data);
+        }
+    }
+}
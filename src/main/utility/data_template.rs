@@ -0,0 +1,283 @@
+//! Populating a host's data directory from a template directory.
+
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::core::support::configuration::DataTemplateMode;
+
+// FICLONE from linux/fs.h
+nix::ioctl_write_int!(ficlone, 0x94, 9);
+
+/// Add the contents of the `src` template directory to the existing `dst` directory. In
+/// [`DataTemplateMode::Copy`] mode, files are copied with their permissions and any variables in
+/// UTF-8 text files are replaced with their values. In [`DataTemplateMode::Symlink`] mode, the
+/// template files are shared with the host instead, and no variable substitution is performed (see
+/// [`link_file`]).
+///
+/// Symlinks in the template are followed, so a symlinked directory is populated like any other
+/// directory.
+///
+/// Variables have the form `${NAME}`, and `vars` contains the `(NAME, value)` pairs. Unknown
+/// variables are left unchanged.
+pub fn apply_data_template(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    mode: DataTemplateMode,
+    vars: &[(&str, &str)],
+) -> std::io::Result<()> {
+    // a stack of (src, dst, ancestors) directories to populate, where the ancestors are the
+    // canonical paths of the template directories containing src (including src itself)
+    let src = src.as_ref().canonicalize()?;
+    let mut stack: Vec<(PathBuf, PathBuf, Vec<PathBuf>)> =
+        vec![(src.clone(), dst.as_ref().to_path_buf(), vec![src])];
+
+    while let Some((src, dst, ancestors)) = stack.pop() {
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            // follow symlinks
+            let meta = std::fs::metadata(entry.path())?;
+            let new_dst_path = dst.join(entry.file_name());
+
+            if meta.is_dir() {
+                // a symlink to one of its own parent directories would never finish
+                let canonical = entry.path().canonicalize()?;
+                if ancestors.contains(&canonical) {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("Symlink loop at '{}'", entry.path().display()),
+                    ));
+                }
+
+                // the directory may already exist if it was copied from the general template
+                // directory
+                if let Err(e) = std::fs::create_dir(&new_dst_path) {
+                    if e.kind() != std::io::ErrorKind::AlreadyExists {
+                        return Err(e);
+                    }
+                }
+
+                let mut ancestors = ancestors.clone();
+                ancestors.push(canonical);
+                stack.push((entry.path(), new_dst_path, ancestors));
+                continue;
+            }
+
+            // the file may already exist if it was copied from the general template directory, and
+            // we shouldn't write through an existing symlink
+            if let Err(e) = std::fs::remove_file(&new_dst_path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+
+            match mode {
+                DataTemplateMode::Copy => {
+                    let contents = std::fs::read(entry.path())?;
+                    match as_text(&contents) {
+                        Some(text) => {
+                            std::fs::write(&new_dst_path, substitute_vars(text, vars))?;
+                            std::fs::set_permissions(&new_dst_path, meta.permissions())?;
+                        }
+                        // copy() will also copy the permissions
+                        None => std::fs::copy(entry.path(), &new_dst_path).map(|_| ())?,
+                    }
+                }
+                DataTemplateMode::Symlink => link_file(&entry.path(), &new_dst_path, &meta)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Share the template file `src` with the host at `dst` without copying its contents. If the
+/// filesystem supports it, `dst` is a copy-on-write clone ("reflink") of `src`, so the host can
+/// modify its file without modifying the template. Otherwise `dst` is a symlink to `src`, and since
+/// the host's processes could then modify the template through the symlink, `src` must be
+/// read-only.
+fn link_file(src: &Path, dst: &Path, src_meta: &std::fs::Metadata) -> std::io::Result<()> {
+    let src_file = std::fs::File::open(src)?;
+    let dst_file = std::fs::File::create(dst)?;
+
+    match unsafe { ficlone(dst_file.as_raw_fd(), src_file.as_raw_fd() as _) } {
+        Ok(_) => return dst_file.set_permissions(src_meta.permissions()),
+        // the filesystem doesn't support reflinks, or the files are on different filesystems
+        Err(nix::errno::Errno::EOPNOTSUPP)
+        | Err(nix::errno::Errno::ENOTTY)
+        | Err(nix::errno::Errno::EINVAL)
+        | Err(nix::errno::Errno::EXDEV) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    drop(dst_file);
+    std::fs::remove_file(dst)?;
+
+    if src_meta.permissions().mode() & 0o222 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!(
+                "Template file '{}' is writable, but the filesystem doesn't support copy-on-write \
+                 clones, so it would be shared with the host through a symlink; make the template \
+                 files read-only or use the 'copy' mode",
+                src.display(),
+            ),
+        ));
+    }
+
+    std::os::unix::fs::symlink(src.canonicalize()?, dst)
+}
+
+/// Returns the file contents as a string if they look like text (valid UTF-8 with no nul bytes).
+fn as_text(contents: &[u8]) -> Option<&str> {
+    if contents.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(contents).ok()
+}
+
+/// Replace all occurrences of `${NAME}` with the value of the variable `NAME`.
+pub fn substitute_vars(text: &str, vars: &[(&str, &str)]) -> String {
+    let mut text = text.to_string();
+    for (name, value) in vars {
+        text = text.replace(&format!("${{{name}}}"), value);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_vars() {
+        let vars = [
+            ("SHADOW_HOSTNAME", "relay1"),
+            ("SHADOW_IP_ADDR", "11.0.0.1"),
+        ];
+
+        assert_eq!(
+            substitute_vars(
+                "Nickname ${SHADOW_HOSTNAME}\nAddress ${SHADOW_IP_ADDR}\n",
+                &vars
+            ),
+            "Nickname relay1\nAddress 11.0.0.1\n"
+        );
+        assert_eq!(
+            substitute_vars("$SHADOW_HOSTNAME ${OTHER} ${SHADOW_HOSTNAME", &vars),
+            "$SHADOW_HOSTNAME ${OTHER} ${SHADOW_HOSTNAME"
+        );
+    }
+
+    #[test]
+    fn test_as_text() {
+        assert_eq!(as_text(b"hello"), Some("hello"));
+        assert_eq!(as_text(b"hel\0lo"), None);
+        assert_eq!(as_text(&[0xff, 0xfe]), None);
+    }
+
+    #[test]
+    // Ignore in miri since it uses the filesystem.
+    #[cfg_attr(miri, ignore)]
+    fn test_apply_data_template() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+
+        std::fs::create_dir(src.path().join("keys")).unwrap();
+        std::fs::write(src.path().join("torrc"), "Nickname ${SHADOW_HOSTNAME}\n").unwrap();
+        std::fs::write(src.path().join("keys/secret"), [0u8, 1, 2]).unwrap();
+
+        let vars = [("SHADOW_HOSTNAME", "relay1")];
+        apply_data_template(src.path(), dst.path(), DataTemplateMode::Copy, &vars).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dst.path().join("torrc")).unwrap(),
+            "Nickname relay1\n"
+        );
+        assert_eq!(
+            std::fs::read(dst.path().join("keys/secret")).unwrap(),
+            [0u8, 1, 2]
+        );
+
+        // the template files are writable
+        let dst = tempfile::tempdir().unwrap();
+        match apply_data_template(src.path(), dst.path(), DataTemplateMode::Symlink, &vars) {
+            Ok(()) => {
+                // the filesystem supports reflinks, so the files aren't symlinks
+                let file = dst.path().join("torrc");
+                assert!(!file.symlink_metadata().unwrap().file_type().is_symlink());
+                assert_eq!(
+                    std::fs::read_to_string(&file).unwrap(),
+                    "Nickname ${SHADOW_HOSTNAME}\n"
+                );
+
+                // modifying the host's file doesn't modify the template
+                std::fs::write(&file, "modified").unwrap();
+                assert_eq!(
+                    std::fs::read_to_string(src.path().join("torrc")).unwrap(),
+                    "Nickname ${SHADOW_HOSTNAME}\n"
+                );
+            }
+            Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied),
+        }
+
+        // read-only template files can always be shared
+        for path in ["torrc", "keys/secret"] {
+            let path = src.path().join(path);
+            let mut perms = std::fs::metadata(&path).unwrap().permissions();
+            perms.set_readonly(true);
+            std::fs::set_permissions(&path, perms).unwrap();
+        }
+
+        let dst = tempfile::tempdir().unwrap();
+        apply_data_template(src.path(), dst.path(), DataTemplateMode::Symlink, &vars).unwrap();
+
+        let file = dst.path().join("torrc");
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "Nickname ${SHADOW_HOSTNAME}\n"
+        );
+        assert!(std::fs::metadata(&file).unwrap().permissions().readonly());
+        assert_eq!(
+            std::fs::read(dst.path().join("keys/secret")).unwrap(),
+            [0u8, 1, 2]
+        );
+    }
+
+    #[test]
+    // Ignore in miri since it uses the filesystem.
+    #[cfg_attr(miri, ignore)]
+    fn test_apply_data_template_symlinked_dir() {
+        let shared = tempfile::tempdir().unwrap();
+        std::fs::write(shared.path().join("key"), "${SHADOW_HOSTNAME}").unwrap();
+        let mut perms = std::fs::metadata(shared.path().join("key"))
+            .unwrap()
+            .permissions();
+        perms.set_readonly(true);
+        std::fs::set_permissions(shared.path().join("key"), perms).unwrap();
+
+        let src = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(shared.path(), src.path().join("keys")).unwrap();
+
+        let vars = [("SHADOW_HOSTNAME", "relay1")];
+
+        for (mode, expected) in [
+            (DataTemplateMode::Copy, "relay1"),
+            (DataTemplateMode::Symlink, "${SHADOW_HOSTNAME}"),
+        ] {
+            let dst = tempfile::tempdir().unwrap();
+            apply_data_template(src.path(), dst.path(), mode, &vars).unwrap();
+
+            // the symlinked directory is populated as a real directory
+            let dir = dst.path().join("keys");
+            assert!(dir.symlink_metadata().unwrap().is_dir());
+            assert_eq!(std::fs::read_to_string(dir.join("key")).unwrap(), expected);
+        }
+
+        // a symlink loop is an error
+        std::os::unix::fs::symlink(src.path(), shared.path().join("loop")).unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let err = apply_data_template(src.path(), dst.path(), DataTemplateMode::Copy, &vars);
+        assert_eq!(err.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
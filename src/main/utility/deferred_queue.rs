@@ -0,0 +1,132 @@
+//! A queue of work that is deferred until the code that is currently running has returned.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Closures that are run later with a reference to a `T` (usually the host). Unlike
+/// [`CallbackQueue`](super::callback_queue::CallbackQueue), closures are deferred through a shared
+/// reference, so the queue can be stored in the object that the closures are given.
+#[allow(clippy::type_complexity)]
+pub struct DeferredQueue<T>(RefCell<VecDeque<Box<dyn FnOnce(&T) + Send>>>);
+
+impl<T> DeferredQueue<T> {
+    pub fn new() -> Self {
+        Self(RefCell::new(VecDeque::new()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a closure to the queue.
+    pub fn defer(&self, f: impl FnOnce(&T) + Send + 'static) {
+        self.0.borrow_mut().push_back(Box::new(f));
+    }
+
+    /// Run all deferred closures in the order they were deferred, including any that are deferred
+    /// while running them.
+    pub fn run(&self, target: &T) {
+        loop {
+            // the closure may defer more work, so must not hold the borrow while running it
+            let Some(f) = self.0.borrow_mut().pop_front() else {
+                break;
+            };
+            f(target);
+        }
+    }
+}
+
+impl<T> Default for DeferredQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Target {
+        deferred: DeferredQueue<Target>,
+        log: RefCell<Vec<u32>>,
+        in_notify: Cell<bool>,
+    }
+
+    impl Target {
+        /// Logs `n` and recursively notifies `n + 1` up to 3. Like
+        /// `Host::notify_socket_has_packets`, this isn't reentrant, so recursive calls are
+        /// deferred.
+        fn notify(&self, n: u32) {
+            if self.in_notify.replace(true) {
+                self.deferred.defer(move |target| target.notify(n));
+                return;
+            }
+
+            self.log.borrow_mut().push(n);
+            if n < 3 {
+                self.notify(n + 1);
+            }
+
+            self.in_notify.set(false);
+        }
+
+        fn run_deferred(&self) {
+            self.deferred.run(self);
+        }
+    }
+
+    #[test]
+    fn order() {
+        let target = Target::default();
+        for n in 0..3 {
+            target.deferred.defer(move |t| t.log.borrow_mut().push(n));
+        }
+
+        // nothing runs until the queue is run
+        assert!(target.log.borrow().is_empty());
+        assert_eq!(target.deferred.len(), 3);
+
+        target.run_deferred();
+        assert_eq!(*target.log.borrow(), [0, 1, 2]);
+        assert!(target.deferred.is_empty());
+    }
+
+    #[test]
+    fn nested() {
+        let target = Target::default();
+        target.deferred.defer(|t| {
+            t.log.borrow_mut().push(0);
+            // runs after the closures that were already deferred
+            t.deferred.defer(|t| t.log.borrow_mut().push(2));
+        });
+        target.deferred.defer(|t| t.log.borrow_mut().push(1));
+
+        // the nested closure runs in the same call
+        target.run_deferred();
+        assert_eq!(*target.log.borrow(), [0, 1, 2]);
+        assert!(target.deferred.is_empty());
+    }
+
+    #[test]
+    fn recursive_notify() {
+        let target = Target::default();
+
+        // the recursive call is deferred
+        target.notify(0);
+        assert_eq!(*target.log.borrow(), [0]);
+        assert_eq!(target.deferred.len(), 1);
+
+        // and each deferred call defers its own recursive call, which still runs in the same call
+        target.run_deferred();
+        assert_eq!(*target.log.borrow(), [0, 1, 2, 3]);
+        assert!(target.deferred.is_empty());
+        assert!(!target.in_notify.get());
+    }
+}
@@ -63,6 +63,7 @@ mod export {
     use std::net::Ipv4Addr;
 
     use crate::core::worker;
+    use crate::host::descriptor::socket::inet::InetSocket;
     use crate::host::host::Host;
 
     /// Notify listeners using the global callback queue. If the queue hasn't been set using
@@ -92,15 +93,17 @@ mod export {
 
     /// Tell the host that the socket wants to send packets using the global callback queue. If the
     /// queue hasn't been set using [`with_global_cb_queue`], the host will be notified here before
-    /// returning.
+    /// returning. Takes ownership of the `socket` reference.
     #[no_mangle]
     pub unsafe extern "C-unwind" fn socket_wants_to_send_with_global_cb_queue(
         host: *const Host,
-        socket: c::CompatSocket,
+        socket: *const InetSocket,
         ip: libc::in_addr_t,
     ) {
         let host = unsafe { host.as_ref() }.unwrap();
         let ip = Ipv4Addr::from(u32::from_be(ip));
+        assert!(!socket.is_null());
+        let socket = *unsafe { Box::from_raw(socket.cast_mut()) };
 
         let host_id = host.id();
 
@@ -114,7 +117,6 @@ mod export {
                     worker::Worker::with_active_host(|host| {
                         assert_eq!(host.id(), host_id);
                         host.notify_socket_has_packets(ip, &socket);
-                        unsafe { c::compatsocket_unref(&socket) };
                     })
                     .unwrap();
                 });
@@ -7,21 +7,31 @@ pub mod macros;
 pub mod byte_queue;
 pub mod callback_queue;
 pub mod childpid_watcher;
+pub mod compression;
 pub mod counter;
+pub mod data_template;
+pub mod deferred_queue;
 pub mod give;
 pub mod interval_map;
 pub mod legacy_callback_queue;
+pub mod object_pool;
+pub mod pcap_reader;
 pub mod pcap_writer;
 pub mod perf_timer;
 pub mod proc_maps;
+pub mod rng_stream;
+pub mod seccomp_notify;
 pub mod shm_cleanup;
 pub mod sockaddr;
 pub mod status_bar;
 pub mod stream_len;
 pub mod synchronization;
 pub mod syscall;
+pub mod tls_certs;
+#[cfg(feature = "tor_helpers")]
+pub mod tor;
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::marker::PhantomData;
 use std::os::unix::fs::{DirBuilderExt, MetadataExt};
@@ -239,6 +249,12 @@ pub fn tilde_expansion(path: &str) -> std::path::PathBuf {
     std::path::PathBuf::from(path)
 }
 
+/// The nearest-rank `p`th percentile of the non-empty sorted `values`.
+pub fn percentile(values: &[u64], p: usize) -> u64 {
+    assert!(p <= 100);
+    values[(values.len() * p).div_ceil(100).max(1) - 1]
+}
+
 /// Copy the contents of the `src` directory to a new directory named `dst`. Permissions will be
 /// preserved.
 pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
@@ -314,6 +330,8 @@ pub enum VerifyPluginPathError {
     // File isn't a dynamically linked ELF.
     // TODO: split these errors, and/or support `#!` interpreters?
     NotDynamicallyLinkedElf,
+    // File is a 32-bit ELF.
+    Elf32,
     // Permission denied traversing the path.
     PathPermissionDenied,
     UnhandledIoError(std::io::Error),
@@ -329,6 +347,9 @@ impl std::fmt::Display for VerifyPluginPathError {
             VerifyPluginPathError::NotDynamicallyLinkedElf => {
                 f.write_str("not a dynamically linked ELF")
             }
+            VerifyPluginPathError::Elf32 => f.write_str(
+                "a 32-bit ELF, which Shadow doesn't support (see 'docs/limitations.md')",
+            ),
             VerifyPluginPathError::PathPermissionDenied => {
                 f.write_str("permission denied traversing path")
             }
@@ -337,8 +358,11 @@ impl std::fmt::Display for VerifyPluginPathError {
     }
 }
 
-/// Check that the plugin path is executable under Shadow.
-pub fn verify_plugin_path(path: impl AsRef<std::path::Path>) -> Result<(), VerifyPluginPathError> {
+/// Check that the path is an executable file, without checking what kind of executable it is.
+/// This is enough for an ELF interpreter, which is statically linked.
+pub fn verify_executable_path(
+    path: impl AsRef<std::path::Path>,
+) -> Result<(), VerifyPluginPathError> {
     let path = path.as_ref();
 
     let metadata = std::fs::metadata(path).map_err(|e| {
@@ -365,38 +389,118 @@ pub fn verify_plugin_path(path: impl AsRef<std::path::Path>) -> Result<(), Verif
         return Err(VerifyPluginPathError::NotExecutable);
     }
 
+    Ok(())
+}
+
+/// How an executable that Shadow can run is linked.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PluginLinkage {
+    /// Loaded by the dynamic linker, which preloads the shim.
+    Dynamic,
+    /// An ELF without an interpreter, so the shim can't be preloaded and Shadow intercepts its
+    /// syscalls with seccomp user notifications instead.
+    Static,
+}
+
+/// Check that the plugin path is executable under Shadow, and return how it's linked.
+pub fn verify_plugin_path(
+    path: impl AsRef<std::path::Path>,
+) -> Result<PluginLinkage, VerifyPluginPathError> {
+    let path = path.as_ref();
+
+    verify_executable_path(path)?;
+
     // a cache so we don't check the same path multiple times (assuming the user doesn't move any
     // binaries while shadow is running)
     // TODO: maybe move this into `sim_config.rs`? This seems slightly more
     // possible to go stale for paths exec'd by managed code.
-    static CHECKED_DYNAMIC_BINS: Lazy<RwLock<HashSet<PathBuf>>> =
-        Lazy::new(|| RwLock::new(HashSet::new()));
+    static CHECKED_BINS: Lazy<RwLock<HashMap<PathBuf, PluginLinkage>>> =
+        Lazy::new(|| RwLock::new(HashMap::new()));
 
-    let is_known_dynamic = CHECKED_DYNAMIC_BINS.read().unwrap().contains(path);
+    if let Some(linkage) = CHECKED_BINS.read().unwrap().get(path) {
+        return Ok(*linkage);
+    }
 
     // check if the binary is dynamically linked
-    if !is_known_dynamic {
-        let ld_path = "/lib64/ld-linux-x86-64.so.2";
-        let ld_output = std::process::Command::new(ld_path)
-            .arg("--verify")
-            .arg(path)
-            .output()
-            .expect("Unable to run '{ld_path}'");
-
-        if ld_output.status.success() {
-            CHECKED_DYNAMIC_BINS
-                .write()
-                .unwrap()
-                .insert(path.to_path_buf());
-        } else {
-            log::debug!("ld stderr: {:?}", ld_output.stderr);
+    let ld_path = "/lib64/ld-linux-x86-64.so.2";
+    let ld_output = std::process::Command::new(ld_path)
+        .arg("--verify")
+        .arg(path)
+        .output()
+        .expect("Unable to run '{ld_path}'");
+
+    let linkage = if ld_output.status.success() {
+        PluginLinkage::Dynamic
+    } else {
+        log::debug!("ld stderr: {:?}", ld_output.stderr);
+        // 32-bit ELFs are a common mistake, so we give them their own error
+        if elf_is_32_bit(path).unwrap_or(false) {
+            return Err(VerifyPluginPathError::Elf32);
+        }
+        if elf_has_interpreter(path).ok() != Some(Some(false)) {
             // technically ld-linux could return errors for other reasons, but this is the most
             // likely reason given that we already checked that the file exists
             return Err(VerifyPluginPathError::NotDynamicallyLinkedElf);
         }
+        PluginLinkage::Static
+    };
+
+    CHECKED_BINS
+        .write()
+        .unwrap()
+        .insert(path.to_path_buf(), linkage);
+
+    Ok(linkage)
+}
+
+/// Whether the 64-bit ELF file at `path` has a `PT_INTERP` program header, which names the dynamic
+/// loader that would load our preloaded libraries. Statically linked executables (including
+/// static-pie executables) don't have one. Returns `None` if the file isn't a 64-bit
+/// little-endian ELF.
+fn elf_has_interpreter(path: &Path) -> std::io::Result<Option<bool>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = std::fs::File::open(path)?;
+
+    let mut header = [0u8; 64];
+    if file.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+    // the magic number, ELFCLASS64, and ELFDATA2LSB
+    if header[..6] != [0x7f, b'E', b'L', b'F', 2, 1] {
+        return Ok(None);
     }
 
-    Ok(())
+    let phoff = u64::from_le_bytes(header[0x20..0x28].try_into().unwrap());
+    let phentsize = u16::from_le_bytes(header[0x36..0x38].try_into().unwrap());
+    let phnum = u16::from_le_bytes(header[0x38..0x3a].try_into().unwrap());
+    if phentsize < 4 {
+        return Ok(None);
+    }
+
+    let mut phdr = vec![0u8; phentsize.into()];
+    for i in 0..u64::from(phnum) {
+        file.seek(SeekFrom::Start(phoff + i * u64::from(phentsize)))?;
+        file.read_exact(&mut phdr)?;
+        let p_type = u32::from_le_bytes(phdr[..4].try_into().unwrap());
+        if p_type == libc::PT_INTERP {
+            return Ok(Some(true));
+        }
+    }
+
+    Ok(Some(false))
+}
+
+/// Whether the file at `path` is a 32-bit ELF.
+fn elf_is_32_bit(path: &Path) -> std::io::Result<bool> {
+    use std::io::Read;
+
+    let mut ident = [0u8; 5];
+    if std::fs::File::open(path)?.read_exact(&mut ident).is_err() {
+        return Ok(false);
+    }
+    // the magic number and ELFCLASS32
+    Ok(ident == [0x7f, b'E', b'L', b'F', 1])
 }
 
 /// Inject `injected_preloads` into the environment `envv`.
@@ -478,6 +582,53 @@ pub fn inject_preloads(mut envv: Vec<CString>, injected_preloads: &[PathBuf]) ->
     envv
 }
 
+/// The glibc tunables that enable Control-flow Enforcement Technology (CET) features.
+const CET_TUNABLES: [&[u8]; 2] = [b"glibc.cpu.x86_ibt", b"glibc.cpu.x86_shstk"];
+
+/// Set the glibc CET tunables in `envv`'s `GLIBC_TUNABLES` to "permissive", unless they're
+/// already set to something other than "on".
+///
+/// The shim isn't compatible with shadow stacks, so it isn't marked as supporting them. When the
+/// tunables are "on", the dynamic loader refuses to load unmarked objects such as the shim. When
+/// they're "permissive", it instead disables the feature for the process, which lets us run
+/// binaries built with CET enabled without rebuilding them.
+pub fn inject_cet_tunables(mut envv: Vec<CString>) -> Vec<CString> {
+    let key = b"GLIBC_TUNABLES=";
+
+    let kv = match envv.iter().position(|v| v.as_bytes().starts_with(key)) {
+        Some(i) => &mut envv[i],
+        None => {
+            envv.push(CString::new(&key[..]).unwrap());
+            envv.last_mut().unwrap()
+        }
+    };
+
+    // `GLIBC_TUNABLES` is a colon-separated list of "name=value" pairs
+    let mut tunables: Vec<Vec<u8>> = kv.as_bytes()[key.len()..]
+        .split(|c| *c == b':')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_vec())
+        .collect();
+
+    for name in CET_TUNABLES {
+        let permissive = [name, &b"=permissive"[..]].concat();
+        let on = [name, &b"=on"[..]].concat();
+
+        let existing = tunables
+            .iter_mut()
+            .find(|x| x.split(|c| *c == b'=').next() == Some(name));
+        match existing {
+            Some(x) if *x == on => *x = permissive,
+            Some(_) => {}
+            None => tunables.push(permissive),
+        }
+    }
+
+    *kv = CString::new([&key[..], &tunables.join(&b':')[..]].concat()).unwrap();
+
+    envv
+}
+
 /// If debug assertions are enabled, panics if `FD_CLOEXEC` is not set on `file`.
 ///
 /// In shadow we want `FD_CLOEXEC` set on most files that we create, to avoid them leaking
@@ -547,6 +698,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_elf_has_interpreter() {
+        // the test binary is dynamically linked
+        let exe = std::env::current_exe().unwrap();
+        assert_eq!(elf_has_interpreter(&exe).unwrap(), Some(true));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"#!/bin/sh\n").unwrap();
+        assert_eq!(elf_has_interpreter(file.path()).unwrap(), None);
+        assert!(!elf_is_32_bit(file.path()).unwrap());
+        assert!(!elf_is_32_bit(&exe).unwrap());
+    }
+
     #[test]
     fn test_inject_preloads() {
         // Base case
@@ -610,6 +774,36 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_inject_cet_tunables() {
+        assert_eq!(
+            inject_cet_tunables(vec![CString::new("foo=foo").unwrap()]),
+            vec![
+                CString::new("foo=foo").unwrap(),
+                CString::new(
+                    "GLIBC_TUNABLES=glibc.cpu.x86_ibt=permissive:glibc.cpu.x86_shstk=permissive"
+                )
+                .unwrap(),
+            ]
+        );
+
+        // Other tunables are preserved, "on" is relaxed, and other values are respected
+        assert_eq!(
+            inject_cet_tunables(vec![CString::new(
+                "GLIBC_TUNABLES=glibc.malloc.check=3:glibc.cpu.x86_shstk=on:glibc.cpu.x86_ibt=off"
+            )
+            .unwrap()]),
+            vec![CString::new(
+                "GLIBC_TUNABLES=glibc.malloc.check=3:glibc.cpu.x86_shstk=permissive:glibc.cpu.x86_ibt=off"
+            )
+            .unwrap()]
+        );
+
+        // Doesn't duplicate
+        let envv = inject_cet_tunables(vec![]);
+        assert_eq!(inject_cet_tunables(envv.clone()), envv);
+    }
 }
 
 mod export {
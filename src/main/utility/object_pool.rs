@@ -0,0 +1,150 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+use std::ptr::NonNull;
+
+/// A pool of freed memory blocks that are reused for new objects of the same size, to avoid calling
+/// the allocator for short-lived objects such as packets.
+///
+/// The blocks are allocated with `libc::calloc` and freed with `libc::free`, so a block can be
+/// returned to any pool (or freed directly) regardless of which pool it was taken from. This allows
+/// objects to be freed on a different worker thread than the one that created them. The pool holds
+/// at most `max_blocks_per_size` blocks of each size, and frees any blocks beyond that.
+#[derive(Debug)]
+pub struct ObjectPool {
+    // Freed blocks grouped by their size. There are only a few object types that are pooled, so a
+    // linear search is faster than a hash map.
+    size_classes: Vec<SizeClass>,
+    max_blocks_per_size: usize,
+}
+
+#[derive(Debug)]
+struct SizeClass {
+    size: usize,
+    blocks: Vec<NonNull<libc::c_void>>,
+}
+
+impl ObjectPool {
+    pub fn new(max_blocks_per_size: usize) -> Self {
+        Self {
+            size_classes: Vec::new(),
+            max_blocks_per_size,
+        }
+    }
+
+    /// Returns a zeroed block of `size` bytes, reusing a freed block if one is available. The block
+    /// must be returned with [`free`](Self::free) or `libc::free`.
+    pub fn alloc_zeroed(&mut self, size: usize) -> NonNull<libc::c_void> {
+        if let Some(block) = self.size_class(size).and_then(|x| x.blocks.pop()) {
+            unsafe { std::ptr::write_bytes(block.as_ptr() as *mut u8, 0, size) };
+            return block;
+        }
+
+        Self::calloc(size)
+    }
+
+    /// Returns a block of `size` bytes to the pool.
+    ///
+    /// # Safety
+    ///
+    /// The block must have been allocated with [`alloc_zeroed`](Self::alloc_zeroed) or
+    /// `libc::calloc`/`libc::malloc` with the same `size`, and must not be used after this call.
+    pub unsafe fn free(&mut self, block: NonNull<libc::c_void>, size: usize) {
+        let max_blocks = self.max_blocks_per_size;
+
+        let class = match self.size_classes.iter().position(|x| x.size == size) {
+            Some(idx) => &mut self.size_classes[idx],
+            None => {
+                self.size_classes.push(SizeClass {
+                    size,
+                    blocks: Vec::new(),
+                });
+                self.size_classes.last_mut().unwrap()
+            }
+        };
+
+        if class.blocks.len() < max_blocks {
+            class.blocks.push(block);
+        } else {
+            unsafe { libc::free(block.as_ptr()) };
+        }
+    }
+
+    /// The number of freed blocks of `size` bytes held by the pool.
+    pub fn num_blocks(&self, size: usize) -> usize {
+        self.size_classes
+            .iter()
+            .find(|x| x.size == size)
+            .map(|x| x.blocks.len())
+            .unwrap_or(0)
+    }
+
+    /// Allocate a zeroed block without a pool.
+    pub fn calloc(size: usize) -> NonNull<libc::c_void> {
+        // calloc may return NULL for a size of 0
+        let block = unsafe { libc::calloc(1, std::cmp::max(size, 1)) };
+        NonNull::new(block).expect("Out of memory")
+    }
+
+    fn size_class(&mut self, size: usize) -> Option<&mut SizeClass> {
+        self.size_classes.iter_mut().find(|x| x.size == size)
+    }
+}
+
+impl Drop for ObjectPool {
+    fn drop(&mut self) {
+        for class in &mut self.size_classes {
+            for block in class.blocks.drain(..) {
+                unsafe { libc::free(block.as_ptr()) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // Uses FFI
+    #[cfg_attr(miri, ignore)]
+    fn test_reuse() {
+        let mut pool = ObjectPool::new(10);
+
+        let a = pool.alloc_zeroed(16);
+        unsafe { std::ptr::write_bytes(a.as_ptr() as *mut u8, 0xff, 16) };
+        unsafe { pool.free(a, 16) };
+        assert_eq!(pool.num_blocks(16), 1);
+
+        // blocks are only reused for objects of the same size
+        let b = pool.alloc_zeroed(32);
+        assert_ne!(a, b);
+        assert_eq!(pool.num_blocks(16), 1);
+
+        // reused blocks are zeroed
+        let c = pool.alloc_zeroed(16);
+        assert_eq!(a, c);
+        assert_eq!(pool.num_blocks(16), 0);
+        let bytes = unsafe { std::slice::from_raw_parts(c.as_ptr() as *const u8, 16) };
+        assert!(bytes.iter().all(|x| *x == 0));
+
+        unsafe { pool.free(b, 32) };
+        unsafe { pool.free(c, 16) };
+    }
+
+    #[test]
+    // Uses FFI
+    #[cfg_attr(miri, ignore)]
+    fn test_max_blocks() {
+        let mut pool = ObjectPool::new(2);
+
+        let blocks: Vec<_> = (0..4).map(|_| pool.alloc_zeroed(8)).collect();
+        for block in blocks {
+            unsafe { pool.free(block, 8) };
+        }
+
+        assert_eq!(pool.num_blocks(8), 2);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::Read;
+use std::time::Duration;
+
+/// The link-layer header type of the packets in a capture file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LinkType {
+    /// BSD loopback encapsulation (LINKTYPE_NULL).
+    Null,
+    /// Ethernet (LINKTYPE_ETHERNET).
+    Ethernet,
+    /// Raw IPv4 or IPv6 (LINKTYPE_RAW), as written by Shadow.
+    Raw,
+    /// Linux "cooked" capture (LINKTYPE_LINUX_SLL).
+    LinuxSll,
+    Other(u32),
+}
+
+impl From<u32> for LinkType {
+    fn from(val: u32) -> Self {
+        match val {
+            0 => Self::Null,
+            1 => Self::Ethernet,
+            // some systems use the DLT_RAW values 12 or 14 instead of LINKTYPE_RAW
+            12 | 14 | 101 => Self::Raw,
+            113 => Self::LinuxSll,
+            x => Self::Other(x),
+        }
+    }
+}
+
+/// A packet read from a capture file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcapPacket {
+    /// The capture timestamp.
+    pub timestamp: Duration,
+    /// The captured bytes, which may be shorter than the original packet.
+    pub data: Vec<u8>,
+    /// The length of the original packet.
+    pub original_len: u32,
+}
+
+/// Reads packets from a file in the classic libpcap format (not pcapng).
+pub struct PcapReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    nanosecond_timestamps: bool,
+    link_type: LinkType,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the pcap file header.
+    pub fn new(mut reader: R) -> std::io::Result<Self> {
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+
+        const MAGIC_MICROS: u32 = 0xA1B2C3D4;
+        const MAGIC_NANOS: u32 = 0xA1B23C4D;
+
+        let magic: [u8; 4] = header[0..4].try_into().unwrap();
+        let (big_endian, nanosecond_timestamps) = match magic {
+            x if u32::from_le_bytes(x) == MAGIC_MICROS => (false, false),
+            x if u32::from_be_bytes(x) == MAGIC_MICROS => (true, false),
+            x if u32::from_le_bytes(x) == MAGIC_NANOS => (false, true),
+            x if u32::from_be_bytes(x) == MAGIC_NANOS => (true, true),
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Not a pcap file (pcapng files are not supported)",
+                ))
+            }
+        };
+
+        let mut rv = Self {
+            reader,
+            big_endian,
+            nanosecond_timestamps,
+            link_type: LinkType::Other(0),
+        };
+
+        rv.link_type = rv.to_u32(header[20..24].try_into().unwrap()).into();
+
+        Ok(rv)
+    }
+
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Read the next packet, or `None` if there are no more packets.
+    pub fn next_packet(&mut self) -> std::io::Result<Option<PcapPacket>> {
+        let mut header = [0u8; 16];
+
+        // check for a clean end of file before the record header
+        let mut first_byte = [0u8; 1];
+        if self.reader.read(&mut first_byte)? == 0 {
+            return Ok(None);
+        }
+        header[0] = first_byte[0];
+        self.reader.read_exact(&mut header[1..])?;
+
+        let ts_sec = self.to_u32(header[0..4].try_into().unwrap());
+        let ts_frac = self.to_u32(header[4..8].try_into().unwrap());
+        let captured_len = self.to_u32(header[8..12].try_into().unwrap());
+        let original_len = self.to_u32(header[12..16].try_into().unwrap());
+
+        let subsec_nanos = if self.nanosecond_timestamps {
+            ts_frac
+        } else {
+            ts_frac.saturating_mul(1000)
+        };
+
+        let mut data = vec![0u8; captured_len.try_into().unwrap()];
+        self.reader.read_exact(&mut data)?;
+
+        Ok(Some(PcapPacket {
+            timestamp: Duration::new(ts_sec.into(), subsec_nanos),
+            data,
+            original_len,
+        }))
+    }
+
+    fn to_u32(&self, bytes: [u8; 4]) -> u32 {
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utility::pcap_writer::{PcapWriter, LINKTYPE_RAW};
+
+    #[test]
+    fn test_read_written_packets() {
+        let mut buf = vec![];
+        let mut pcap = PcapWriter::new(&mut buf, 2, LINKTYPE_RAW).unwrap();
+        pcap.write_packet(32, 128, &[0x01]).unwrap();
+        pcap.write_packet(33, 0, &[0x02, 0x03, 0x04]).unwrap();
+
+        let mut pcap = PcapReader::new(&buf[..]).unwrap();
+        assert_eq!(pcap.link_type(), LinkType::Raw);
+
+        assert_eq!(
+            pcap.next_packet().unwrap(),
+            Some(PcapPacket {
+                timestamp: Duration::new(32, 128_000),
+                data: vec![0x01],
+                original_len: 1,
+            })
+        );
+        assert_eq!(
+            pcap.next_packet().unwrap(),
+            Some(PcapPacket {
+                timestamp: Duration::new(33, 0),
+                data: vec![0x02, 0x03],
+                original_len: 3,
+            })
+        );
+        assert_eq!(pcap.next_packet().unwrap(), None);
+    }
+
+    #[test]
+    fn test_big_endian_nanos() {
+        let mut buf = vec![];
+        buf.extend(0xA1B23C4Du32.to_be_bytes());
+        buf.extend(2u16.to_be_bytes());
+        buf.extend(4u16.to_be_bytes());
+        buf.extend([0u8; 12]);
+        buf.extend(1u32.to_be_bytes());
+
+        buf.extend(5u32.to_be_bytes());
+        buf.extend(7u32.to_be_bytes());
+        buf.extend(1u32.to_be_bytes());
+        buf.extend(1u32.to_be_bytes());
+        buf.push(0xFF);
+
+        let mut pcap = PcapReader::new(&buf[..]).unwrap();
+        assert_eq!(pcap.link_type(), LinkType::Ethernet);
+        assert_eq!(
+            pcap.next_packet().unwrap().unwrap().timestamp,
+            Duration::new(5, 7)
+        );
+        assert_eq!(pcap.next_packet().unwrap(), None);
+    }
+
+    #[test]
+    fn test_not_pcap() {
+        assert!(PcapReader::new(&[0u8; 24][..]).is_err());
+        // truncated header
+        assert!(PcapReader::new(&[0xD4, 0xC3, 0xB2, 0xA1][..]).is_err());
+    }
+}
@@ -1,20 +1,28 @@
 use std::io::{Seek, SeekFrom, Write};
 
-use crate::cshadow as c;
+use crate::core::support::configuration::PcapPayload;
 use crate::utility::give::Give;
 
+/// The link type of packets that start with their IP header.
+pub const LINKTYPE_RAW: u32 = 101;
+/// The link type of packets that start with an ethernet header.
+pub const LINKTYPE_ETHERNET: u32 = 1;
+
 pub struct PcapWriter<W: Write> {
     writer: W,
     capture_len: u32,
+    link_type: u32,
 }
 
 impl<W: Write> PcapWriter<W> {
     /// A new packet capture writer. Each packet (header and payload) captured will be truncated to
-    /// a length `capture_len`.
-    pub fn new(writer: W, capture_len: u32) -> std::io::Result<Self> {
+    /// a length `capture_len`. The packets must be of the given `link_type`, such as
+    /// [`LINKTYPE_RAW`].
+    pub fn new(writer: W, capture_len: u32, link_type: u32) -> std::io::Result<Self> {
         let mut rv = PcapWriter {
             writer,
             capture_len,
+            link_type,
         };
 
         rv.write_header()?;
@@ -22,6 +30,11 @@ impl<W: Write> PcapWriter<W> {
         Ok(rv)
     }
 
+    /// The underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
     fn write_header(&mut self) -> std::io::Result<()> {
         // magic number to show endianness
         const MAGIC_NUMBER: u32 = 0xA1B2C3D4;
@@ -31,8 +44,6 @@ impl<W: Write> PcapWriter<W> {
         const THIS_ZONE: i32 = 0;
         // accuracy of timestamps
         const SIG_FLAGS: u32 = 0;
-        // data link type (LINKTYPE_RAW)
-        const NETWORK: u32 = 101;
 
         // magic number: 4 bytes
         self.writer.write_all(&MAGIC_NUMBER.to_ne_bytes())?;
@@ -48,7 +59,7 @@ impl<W: Write> PcapWriter<W> {
         // snapshot length: 4 bytes
         self.writer.write_all(&self.capture_len.to_ne_bytes())?;
         // link type: 4 bytes
-        self.writer.write_all(&NETWORK.to_ne_bytes())?;
+        self.writer.write_all(&self.link_type.to_ne_bytes())?;
 
         Ok(())
     }
@@ -143,71 +154,34 @@ impl<W: Write + Seek> PcapWriter<W> {
 }
 
 pub trait PacketDisplay {
-    /// Write the packet bytes.
-    fn display_bytes(&self, writer: impl Write) -> std::io::Result<()>;
+    /// Write the packet bytes. The application payload is written, zeroed, or left out depending on
+    /// `payload`.
+    fn display_bytes(&self, writer: impl Write, payload: PcapPayload) -> std::io::Result<()>;
 }
 
-mod export {
-    use std::ffi::{CStr, OsStr};
-    use std::fs::File;
-    use std::io::BufWriter;
-    use std::os::unix::ffi::OsStrExt;
-
-    use super::*;
+/// The length of an ethernet header (without a VLAN tag).
+pub const ETHERNET_HEADER_LEN: u32 = 14;
 
-    /// A new packet capture writer. Each packet (header and payload) captured will be truncated to
-    /// a length `capture_len`.
-    #[no_mangle]
-    pub extern "C-unwind" fn pcapwriter_new(
-        path: *const libc::c_char,
-        capture_len: u32,
-    ) -> *mut PcapWriter<BufWriter<File>> {
-        assert!(!path.is_null());
-        let path = OsStr::from_bytes(unsafe { CStr::from_ptr(path) }.to_bytes());
-
-        let file = match File::create(path) {
-            Ok(f) => f,
-            Err(e) => {
-                log::warn!("Could not create pcap file: {}", e);
-                return std::ptr::null_mut();
-            }
-        };
-        let file = BufWriter::new(file);
-        Box::into_raw(Box::new(PcapWriter::new(file, capture_len).unwrap()))
-    }
-
-    #[no_mangle]
-    pub extern "C-unwind" fn pcapwriter_free(pcap: *mut PcapWriter<BufWriter<File>>) {
-        if pcap.is_null() {
-            return;
-        }
-        drop(unsafe { Box::from_raw(pcap) });
-    }
-
-    /// If there's an error, returns 1. Otherwise returns 0. If there's an error, the pcap file is
-    /// likely to be corrupt.
-    #[no_mangle]
-    pub extern "C-unwind" fn pcapwriter_writePacket(
-        pcap: *mut PcapWriter<BufWriter<File>>,
-        ts_sec: u32,
-        ts_usec: u32,
-        packet: *const c::Packet,
-    ) -> libc::c_int {
-        assert!(!pcap.is_null());
-        assert!(!packet.is_null());
+/// An IPv4 packet in an ethernet frame, for captures with the [`LINKTYPE_ETHERNET`] link type.
+pub struct EthernetFrame<'a, P> {
+    pub src: [u8; 6],
+    pub dst: [u8; 6],
+    pub packet: &'a P,
+}
 
-        let pcap = unsafe { pcap.as_mut() }.unwrap();
+impl<P: PacketDisplay> PacketDisplay for EthernetFrame<'_, P> {
+    fn display_bytes(&self, mut writer: impl Write, payload: PcapPayload) -> std::io::Result<()> {
+        // the IPv4 ethertype
+        const ETHERTYPE_IP: u16 = 0x0800;
 
-        let packet_len: u32 = u32::try_from(unsafe { c::packet_getTotalSize(packet) }).unwrap();
+        // destination: 6 bytes
+        writer.write_all(&self.dst)?;
+        // source: 6 bytes
+        writer.write_all(&self.src)?;
+        // ethertype: 2 bytes
+        writer.write_all(&ETHERTYPE_IP.to_be_bytes())?;
 
-        if let Err(e) = pcap.write_packet_fmt(ts_sec, ts_usec, packet_len, |writer| {
-            packet.display_bytes(writer)
-        }) {
-            log::warn!("Unable to write packet to pcap output: {}", e);
-            return 1;
-        }
-
-        0
+        self.packet.display_bytes(writer, payload)
     }
 }
 
@@ -220,7 +194,7 @@ mod tests {
     #[test]
     fn test_empty_pcap_writer() {
         let mut buf = vec![];
-        PcapWriter::new(&mut buf, 65535).unwrap();
+        PcapWriter::new(&mut buf, 65535, LINKTYPE_RAW).unwrap();
 
         let expected_header = [
             0xD4, 0xC3, 0xB2, 0xA1, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -233,7 +207,7 @@ mod tests {
     #[test]
     fn test_write_packet() {
         let mut buf = vec![];
-        let mut pcap = PcapWriter::new(&mut buf, 65535).unwrap();
+        let mut pcap = PcapWriter::new(&mut buf, 65535, LINKTYPE_RAW).unwrap();
         pcap.write_packet(32, 128, &[0x01, 0x02, 0x03]).unwrap();
 
         let expected_header = [
@@ -260,7 +234,7 @@ mod tests {
     #[test]
     fn test_write_packet_fmt() {
         let mut buf = Cursor::new(vec![]);
-        let mut pcap = PcapWriter::new(&mut buf, 65535).unwrap();
+        let mut pcap = PcapWriter::new(&mut buf, 65535, LINKTYPE_RAW).unwrap();
         pcap.write_packet_fmt(32, 128, 3, |writer| {
             writer.write_all(&[0x01])?;
             writer.write_all(&[0x02])?;
@@ -290,4 +264,37 @@ mod tests {
             .concat()
         );
     }
+
+    #[test]
+    fn test_ethernet_frame() {
+        struct MockPacket;
+
+        impl PacketDisplay for MockPacket {
+            fn display_bytes(
+                &self,
+                mut writer: impl Write,
+                _payload: PcapPayload,
+            ) -> std::io::Result<()> {
+                writer.write_all(&[0x45])
+            }
+        }
+
+        let frame = EthernetFrame {
+            src: [0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            dst: [0xFF; 6],
+            packet: &MockPacket,
+        };
+
+        let mut buf = vec![];
+        frame.display_bytes(&mut buf, PcapPayload::Keep).unwrap();
+
+        assert_eq!(buf.len(), usize::try_from(ETHERNET_HEADER_LEN).unwrap() + 1);
+        assert_eq!(
+            buf,
+            [
+                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00,
+                0x45
+            ]
+        );
+    }
 }
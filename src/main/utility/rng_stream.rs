@@ -0,0 +1,38 @@
+//! Named streams of pseudo-random numbers. Each stream is seeded from a base seed and the stream's
+//! name, so a feature that draws from its own stream doesn't change the numbers drawn by any other
+//! feature. This means that enabling a new source of randomness in a simulation doesn't change how
+//! the rest of the simulation behaves.
+
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// The seed for the stream `name`.
+pub fn seed(base_seed: u64, name: &str) -> u64 {
+    // FNV-1a, since the hashers in the standard library aren't guaranteed to be stable across rust
+    // versions
+    let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    base_seed ^ hash
+}
+
+/// A new random number generator for the stream `name`.
+pub fn new(base_seed: u64, name: &str) -> Xoshiro256PlusPlus {
+    Xoshiro256PlusPlus::seed_from_u64(seed(base_seed, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_streams() {
+        let a: u64 = new(1, "a").gen();
+        assert_eq!(a, new(1, "a").gen());
+        assert_ne!(a, new(1, "b").gen());
+        assert_ne!(a, new(2, "a").gen());
+        assert_ne!(a, Xoshiro256PlusPlus::seed_from_u64(1).gen());
+    }
+}
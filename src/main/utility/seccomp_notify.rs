@@ -0,0 +1,688 @@
+//! Interposition on a managed process's syscalls with seccomp user notifications.
+//!
+//! Statically linked executables don't run the dynamic loader, so Shadow can't preload its shim
+//! into them. Shadow instead starts them with a seccomp filter that turns every syscall into a
+//! notification on a listener file descriptor (see `seccomp_unotify(2)`). The syscall waits in
+//! the kernel until Shadow answers the notification, either with the result of the emulated
+//! syscall, or by letting the kernel run the syscall natively.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+use log::{debug, trace, warn};
+use nix::errno::Errno;
+use nix::unistd::Pid;
+
+use crate::utility::proc_maps::{self, MappingPath};
+
+// Definitions from `linux/seccomp.h`, `linux/filter.h`, and `linux/audit.h`, which the `libc`
+// crate doesn't have.
+
+const SECCOMP_SET_MODE_FILTER: libc::c_ulong = 1;
+const SECCOMP_FILTER_FLAG_NEW_LISTENER: libc::c_ulong = 1 << 3;
+const SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV: libc::c_ulong = 1 << 5;
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_USER_NOTIF_FLAG_CONTINUE: u32 = 1;
+const SECCOMP_ADDFD_FLAG_SETFD: u32 = 1;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JGE_K: u16 = 0x35;
+const BPF_RET_K: u16 = 0x06;
+
+const AUDIT_ARCH_X86_64: u32 = 0xc000_003e;
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+// A syscall interrupted by a signal is restarted if the signal's handler has `SA_RESTART`, and
+// otherwise fails with `EINTR`. Only visible to user space if no signal is delivered.
+const ERESTARTSYS: i64 = 512;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct seccomp_data {
+    nr: i32,
+    arch: u32,
+    instruction_pointer: u64,
+    args: [u64; 6],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct seccomp_notif {
+    id: u64,
+    pid: u32,
+    flags: u32,
+    data: seccomp_data,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct seccomp_notif_resp {
+    id: u64,
+    val: i64,
+    error: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct seccomp_notif_addfd {
+    id: u64,
+    flags: u32,
+    srcfd: u32,
+    newfd: u32,
+    newfd_flags: u32,
+}
+
+nix::ioctl_readwrite!(seccomp_notif_recv, b'!', 0, seccomp_notif);
+nix::ioctl_readwrite!(seccomp_notif_send, b'!', 1, seccomp_notif_resp);
+nix::ioctl_write_ptr!(seccomp_notif_addfd, b'!', 3, seccomp_notif_addfd);
+
+/// A syscall made by a thread of the managed process, which waits until it's answered.
+#[derive(Copy, Clone, Debug)]
+pub struct Notification {
+    pub id: u64,
+    /// The native thread that made the syscall.
+    pub tid: Pid,
+    pub number: i64,
+    pub args: [u64; 6],
+}
+
+/// The listener for the seccomp notifications of one managed process.
+#[derive(Debug)]
+pub struct SeccompNotifier {
+    pid: Pid,
+    listener: OwnedFd,
+    pidfd: OwnedFd,
+    // Whether a thread waiting for an answer ignores signals other than `SIGKILL`. Older kernels
+    // interrupt the syscall instead.
+    wait_killable: bool,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    // The native threads that Shadow knows about.
+    tids: HashSet<Pid>,
+    // Notifications that arrived while waiting for a different thread.
+    pending: HashMap<Pid, Notification>,
+}
+
+impl SeccompNotifier {
+    /// Run the executable at `path` in a new child process whose syscalls are all sent to the
+    /// returned notifier. The child's standard input is `/dev/null`, and its standard output and
+    /// error are `log_file`.
+    ///
+    /// Returns the notification of the program's first syscall, or the error from `execve`.
+    pub fn spawn(
+        path: &CStr,
+        argv: &[CString],
+        envv: &[CString],
+        working_dir: &CStr,
+        log_file: &File,
+    ) -> nix::Result<(Self, Notification)> {
+        let dev_null = File::open("/dev/null").unwrap();
+
+        let argv_ptrs: Vec<*const libc::c_char> = argv
+            .iter()
+            .map(|s| s.as_ptr())
+            .chain(std::iter::once(std::ptr::null()))
+            .collect();
+        let envv_ptrs: Vec<*const libc::c_char> = envv
+            .iter()
+            .map(|s| s.as_ptr())
+            .chain(std::iter::once(std::ptr::null()))
+            .collect();
+
+        let filter = notify_filter();
+        let prog = libc::sock_fprog {
+            len: filter.len().try_into().unwrap(),
+            filter: filter.as_ptr().cast_mut(),
+        };
+
+        // The child reports its listener and `execve` error through shared memory, since after
+        // the filter is installed it can't make syscalls that aren't answered by us.
+        let status = SharedStatus::new();
+
+        let child = ChildArgs {
+            path,
+            argv: &argv_ptrs,
+            envv: &envv_ptrs,
+            working_dir,
+            stdin: dev_null.as_raw_fd(),
+            stdout: log_file.as_raw_fd(),
+            prog: &prog,
+            status: status.get(),
+        };
+
+        let pid = match unsafe { libc::fork() } {
+            -1 => return Err(Errno::last()),
+            // SAFETY: `child` only uses memory that was prepared before the fork.
+            0 => unsafe { child.exec() },
+            pid => Pid::from_raw(pid),
+        };
+        debug!("forked child {pid} for {path:?}");
+
+        let pidfd = pidfd_open(pid).unwrap();
+
+        let listener = loop {
+            let fd = status.get().listener.load(Ordering::Acquire);
+            if fd >= 0 {
+                break pidfd_getfd(&pidfd, fd).unwrap();
+            }
+            if fd != UNSET {
+                // The child failed before it could run the executable.
+                reap(pid);
+                return Err(Errno::from_i32(-fd));
+            }
+            std::thread::yield_now();
+        };
+        let wait_killable = status.get().wait_killable.load(Ordering::Acquire) != 0;
+
+        let notifier = Self {
+            pid,
+            listener,
+            pidfd,
+            wait_killable,
+            inner: Mutex::new(Inner {
+                tids: HashSet::from([pid]),
+                pending: HashMap::new(),
+            }),
+        };
+
+        let exec = notifier.recv(pid).unwrap();
+        assert_eq!(exec.number, libc::SYS_execve);
+        notifier.respond_native(exec.id);
+
+        let Some(first) = notifier.recv(pid) else {
+            panic!("Child {pid} died during execve");
+        };
+        let exec_errno = status.get().exec_errno.load(Ordering::Acquire);
+        if first.number == libc::SYS_exit_group && exec_errno != 0 {
+            // The child is exiting after `execve` failed.
+            notifier.respond_native(first.id);
+            reap(pid);
+            return Err(Errno::from_i32(exec_errno));
+        }
+
+        if let Err(e) = patch_vdso(pid) {
+            warn!("Couldn't patch the vdso of process {pid}: {e}");
+        }
+
+        Ok((notifier, first))
+    }
+
+    /// Wait for the next syscall from the native thread `tid`. Returns `None` if the process has
+    /// exited.
+    pub fn recv(&self, tid: Pid) -> Option<Notification> {
+        if let Some(n) = self.inner.lock().unwrap().pending.remove(&tid) {
+            return Some(n);
+        }
+        loop {
+            let n = self.recv_any()?;
+            if n.tid == tid {
+                return Some(n);
+            }
+            let prev = self.inner.lock().unwrap().pending.insert(n.tid, n);
+            assert!(prev.is_none());
+        }
+    }
+
+    /// Wait for the first syscall of a thread created by a `clone` that was run natively by
+    /// `parent`. Returns `None` if no thread was created, for example because `clone` failed.
+    pub fn recv_new_thread(&self, parent: Pid) -> Option<Notification> {
+        loop {
+            {
+                let mut inner = self.inner.lock().unwrap();
+                let new = inner
+                    .pending
+                    .keys()
+                    .find(|t| !inner.tids.contains(t))
+                    .copied();
+                if let Some(tid) = new {
+                    inner.tids.insert(tid);
+                    return inner.pending.remove(&tid);
+                }
+
+                // The parent made its next syscall, so `clone` has returned. If it didn't create
+                // a thread we'd wait forever.
+                if inner.pending.contains_key(&parent) && !has_unknown_task(self.pid, &inner.tids) {
+                    return None;
+                }
+            }
+
+            let n = self.recv_any()?;
+            let prev = self.inner.lock().unwrap().pending.insert(n.tid, n);
+            assert!(prev.is_none());
+        }
+    }
+
+    /// Stop waiting for syscalls from `tid`, which has exited.
+    pub fn forget_thread(&self, tid: Pid) {
+        self.inner.lock().unwrap().tids.remove(&tid);
+    }
+
+    fn recv_any(&self) -> Option<Notification> {
+        loop {
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.listener.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: self.pidfd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            match Errno::result(unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) }) {
+                Ok(_) | Err(Errno::EINTR) => (),
+                Err(e) => panic!("poll: {e}"),
+            }
+
+            if fds[0].revents & libc::POLLIN != 0 {
+                let mut notif = seccomp_notif::default();
+                match unsafe { seccomp_notif_recv(self.listener.as_raw_fd(), &mut notif) } {
+                    Ok(_) => {
+                        let n = Notification {
+                            id: notif.id,
+                            tid: Pid::from_raw(notif.pid.try_into().unwrap()),
+                            number: notif.data.nr.into(),
+                            args: notif.data.args,
+                        };
+                        trace!("received {n:?}");
+                        return Some(n);
+                    }
+                    // The thread was killed or interrupted before we received the notification.
+                    Err(Errno::ENOENT) | Err(Errno::EINTR) => continue,
+                    Err(e) => panic!("SECCOMP_IOCTL_NOTIF_RECV: {e}"),
+                }
+            }
+
+            if fds[0].revents & libc::POLLHUP != 0 || fds[1].revents != 0 {
+                trace!("process {} has exited", self.pid);
+                return None;
+            }
+        }
+    }
+
+    /// Complete the syscall of notification `id` with `retval`, which is the negated error number
+    /// if the syscall failed.
+    pub fn respond(&self, id: u64, retval: i64) {
+        let resp = if (-4095..0).contains(&retval) {
+            seccomp_notif_resp {
+                id,
+                error: retval.try_into().unwrap(),
+                ..Default::default()
+            }
+        } else {
+            seccomp_notif_resp {
+                id,
+                val: retval,
+                ..Default::default()
+            }
+        };
+        self.send(resp);
+    }
+
+    /// Complete the syscall of notification `id` with a result that makes it fail with `EINTR`,
+    /// or be restarted if it's interrupted by a signal whose handler has `SA_RESTART`.
+    ///
+    /// A signal must have been sent to the thread, or the syscall would fail with the internal
+    /// error `ERESTARTSYS`.
+    pub fn respond_interrupted(&self, id: u64) {
+        self.respond(id, -ERESTARTSYS);
+    }
+
+    /// Let the kernel run the syscall of notification `id` natively.
+    pub fn respond_native(&self, id: u64) {
+        self.send(seccomp_notif_resp {
+            id,
+            flags: SECCOMP_USER_NOTIF_FLAG_CONTINUE,
+            ..Default::default()
+        });
+    }
+
+    fn send(&self, mut resp: seccomp_notif_resp) {
+        match unsafe { seccomp_notif_send(self.listener.as_raw_fd(), &mut resp) } {
+            Ok(_) => (),
+            // The thread was killed while it waited.
+            Err(Errno::ENOENT) => trace!("notification {} is no longer valid", resp.id),
+            Err(e) => panic!("SECCOMP_IOCTL_NOTIF_SEND: {e}"),
+        }
+    }
+
+    /// Copy `fd` into the process at descriptor number `target_fd`, which is closed first if it's
+    /// open. The thread that made the syscall of notification `id` must still be waiting.
+    pub fn install_fd(&self, id: u64, fd: RawFd, target_fd: RawFd) -> nix::Result<()> {
+        let addfd = seccomp_notif_addfd {
+            id,
+            flags: SECCOMP_ADDFD_FLAG_SETFD,
+            srcfd: fd.try_into().unwrap(),
+            newfd: target_fd.try_into().map_err(|_| Errno::EBADF)?,
+            newfd_flags: 0,
+        };
+        unsafe { seccomp_notif_addfd(self.listener.as_raw_fd(), &addfd) }?;
+        Ok(())
+    }
+
+    /// Whether a signal sent to a thread while Shadow handles its syscall stays pending until the
+    /// syscall is answered, rather than interrupting it.
+    pub fn signals_wait_for_response(&self) -> bool {
+        self.wait_killable
+    }
+
+    /// Whether all of the process's threads have exited.
+    pub fn has_exited(&self) -> bool {
+        let mut fds = [libc::pollfd {
+            fd: self.pidfd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        Errno::result(unsafe { libc::poll(fds.as_mut_ptr(), 1, 0) }).unwrap() > 0
+    }
+}
+
+const UNSET: i32 = i32::MIN;
+
+struct Status {
+    // The child's listener descriptor, or the negated error number if the child couldn't install
+    // the filter.
+    listener: AtomicI32,
+    // Whether the filter has `SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV`.
+    wait_killable: AtomicI32,
+    // The error from `execve`, or 0.
+    exec_errno: AtomicI32,
+}
+
+/// A `Status` in memory that's shared with the child.
+struct SharedStatus(*mut Status);
+
+impl SharedStatus {
+    fn new() -> Self {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                std::mem::size_of::<Status>(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        let ptr = ptr.cast::<Status>();
+        unsafe {
+            ptr.write(Status {
+                listener: AtomicI32::new(UNSET),
+                wait_killable: AtomicI32::new(0),
+                exec_errno: AtomicI32::new(0),
+            })
+        };
+        Self(ptr)
+    }
+
+    fn get(&self) -> &Status {
+        unsafe { &*self.0 }
+    }
+}
+
+impl Drop for SharedStatus {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.0.cast(), std::mem::size_of::<Status>()) };
+    }
+}
+
+/// What the forked child needs, prepared before the fork.
+struct ChildArgs<'a> {
+    path: &'a CStr,
+    argv: &'a [*const libc::c_char],
+    envv: &'a [*const libc::c_char],
+    working_dir: &'a CStr,
+    stdin: RawFd,
+    stdout: RawFd,
+    prog: &'a libc::sock_fprog,
+    status: &'a Status,
+}
+
+impl ChildArgs<'_> {
+    /// Set up the child's descriptors, working directory, and signals, install the filter, and
+    /// run the executable.
+    ///
+    /// The child of a multithreaded process may only call async-signal-safe functions, so this
+    /// doesn't allocate, and only makes raw syscalls through libc.
+    unsafe fn exec(&self) -> ! {
+        let fail = |errno: i32| -> ! {
+            self.status.listener.store(-errno, Ordering::Release);
+            unsafe { libc::_exit(127) }
+        };
+        let check = |rv: libc::c_long| {
+            if rv < 0 {
+                fail(unsafe { *libc::__errno_location() });
+            }
+        };
+
+        unsafe {
+            check(libc::dup2(self.stdin, libc::STDIN_FILENO).into());
+            check(libc::dup2(self.stdout, libc::STDOUT_FILENO).into());
+            check(libc::dup2(self.stdout, libc::STDERR_FILENO).into());
+            check(libc::chdir(self.working_dir.as_ptr()).into());
+
+            // Don't pass Shadow's signal dispositions and mask on to the program. Resetting
+            // `SIGKILL`, `SIGSTOP`, and the signals that libc reserves fails, which is fine.
+            for signal in 1..=64 {
+                libc::signal(signal, libc::SIG_DFL);
+            }
+            let mut mask: libc::sigset_t = std::mem::zeroed();
+            libc::sigemptyset(&mut mask);
+            check(libc::sigprocmask(libc::SIG_SETMASK, &mask, std::ptr::null_mut()).into());
+
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0).into());
+
+            let seccomp = |flags: libc::c_ulong| {
+                libc::syscall(
+                    libc::SYS_seccomp,
+                    SECCOMP_SET_MODE_FILTER,
+                    flags,
+                    self.prog as *const libc::sock_fprog,
+                )
+            };
+            let flags = SECCOMP_FILTER_FLAG_NEW_LISTENER | SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV;
+            let mut listener = seccomp(flags);
+            if listener < 0 && *libc::__errno_location() == libc::EINVAL {
+                // Kernels before 5.19 don't have `SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV`.
+                listener = seccomp(SECCOMP_FILTER_FLAG_NEW_LISTENER);
+            } else if listener >= 0 {
+                self.status.wait_killable.store(1, Ordering::Release);
+            }
+            check(listener);
+
+            // From here on every syscall waits for the parent, which waits for this.
+            self.status
+                .listener
+                .store(listener as i32, Ordering::Release);
+
+            libc::execve(self.path.as_ptr(), self.argv.as_ptr(), self.envv.as_ptr());
+            self.status
+                .exec_errno
+                .store(*libc::__errno_location(), Ordering::Release);
+            libc::_exit(127)
+        }
+    }
+}
+
+/// A filter that sends every 64-bit syscall to the listener, and fails other syscalls with
+/// `ENOSYS` as the shim does.
+fn notify_filter() -> [libc::sock_filter; 6] {
+    let stmt = |code, k| libc::sock_filter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    };
+    let jump = |code, k, jt, jf| libc::sock_filter { code, jt, jf, k };
+    [
+        // Offset of `seccomp_data::arch`.
+        stmt(BPF_LD_W_ABS, 4),
+        jump(BPF_JMP_JEQ_K, AUDIT_ARCH_X86_64, 0, 3),
+        // Offset of `seccomp_data::nr`.
+        stmt(BPF_LD_W_ABS, 0),
+        jump(BPF_JMP_JGE_K, X32_SYSCALL_BIT, 1, 0),
+        stmt(BPF_RET_K, SECCOMP_RET_USER_NOTIF),
+        stmt(BPF_RET_K, SECCOMP_RET_ERRNO | libc::ENOSYS as u32),
+    ]
+}
+
+fn pidfd_open(pid: Pid) -> nix::Result<OwnedFd> {
+    let fd = Errno::result(unsafe { libc::syscall(libc::SYS_pidfd_open, pid.as_raw(), 0) })?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd.try_into().unwrap()) })
+}
+
+fn pidfd_getfd(pidfd: &OwnedFd, fd: RawFd) -> nix::Result<OwnedFd> {
+    let fd =
+        Errno::result(unsafe { libc::syscall(libc::SYS_pidfd_getfd, pidfd.as_raw_fd(), fd, 0) })?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd.try_into().unwrap()) })
+}
+
+/// Whether process `pid` has a thread other than `tids`.
+fn has_unknown_task(pid: Pid, tids: &HashSet<Pid>) -> bool {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{pid}/task")) else {
+        return false;
+    };
+    tasks
+        .filter_map(|t| t.ok()?.file_name().to_str()?.parse().ok())
+        .any(|tid| !tids.contains(&Pid::from_raw(tid)))
+}
+
+fn reap(pid: Pid) {
+    let status = nix::sys::wait::waitpid(pid, None).unwrap();
+    trace!("reaped child {pid}: {status:?}");
+}
+
+/// The vDSO functions that read the time or CPU without a syscall, and the syscalls that replace
+/// them.
+const VDSO_FUNCTIONS: [(&str, i64); 4] = [
+    ("__vdso_clock_gettime", libc::SYS_clock_gettime),
+    ("__vdso_gettimeofday", libc::SYS_gettimeofday),
+    ("__vdso_time", libc::SYS_time),
+    ("__vdso_getcpu", libc::SYS_getcpu),
+];
+
+// The `endbr64` instruction, which marks the valid targets of indirect branches when Indirect
+// Branch Tracking (IBT) is enabled.
+const ENDBR64: [u8; 4] = [0xf3, 0x0f, 0x1e, 0xfa];
+
+/// Replace the vDSO functions of process `pid` with functions that make the corresponding syscall,
+/// which Shadow then emulates, as the shim does in processes that load it.
+fn patch_vdso(pid: Pid) -> Result<(), Box<dyn std::error::Error>> {
+    let mappings = proc_maps::mappings_for_pid(pid.as_raw())?;
+    let Some(vdso) = mappings.iter().find(|m| m.path == Some(MappingPath::Vdso)) else {
+        debug!("process {pid} has no vdso");
+        return Ok(());
+    };
+
+    // Writes through `/proc/<pid>/mem` ignore the protections of the mapping, so the vdso never
+    // has to be writable in the process itself.
+    let mem = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!("/proc/{pid}/mem"))?;
+    let mut image = vec![0u8; vdso.end - vdso.begin];
+    mem.read_exact_at(&mut image, vdso.begin.try_into()?)?;
+
+    for (name, number) in VDSO_FUNCTIONS {
+        let Some((mut offset, mut size)) = elf_dynamic_symbol(&image, name) else {
+            // This could happen e.g. if vdso is disabled at the system level.
+            warn!("Couldn't find symbol '{name}' to override");
+            continue;
+        };
+        // Callers reach the function through an indirect call, so keep the `endbr64`.
+        if image[offset..].starts_with(&ENDBR64) {
+            offset += ENDBR64.len();
+            size = size.saturating_sub(ENDBR64.len());
+        }
+        // Newer kernels export some functions as a `jmp` to the implementation, which is too
+        // small to patch, so patch the implementation instead. It isn't exported, so we don't
+        // know its size, but it's much larger than the stub.
+        if size == 5 && image[offset] == 0xe9 {
+            let rel = i32::from_le_bytes(image[offset + 1..offset + 5].try_into().unwrap());
+            offset = (offset + 5)
+                .checked_add_signed(rel.try_into()?)
+                .filter(|&o| o < image.len())
+                .ok_or_else(|| format!("'{name}' jumps outside of the vdso"))?;
+            size = image.len() - offset;
+        }
+
+        // mov $number, %eax; syscall; ret
+        let mut stub = vec![0xb8];
+        stub.extend(u32::try_from(number)?.to_le_bytes());
+        stub.extend([0x0f, 0x05, 0xc3]);
+        if stub.len() > size {
+            return Err(format!("'{name}' is too small to patch").into());
+        }
+
+        mem.write_all_at(&stub, (vdso.begin + offset).try_into()?)?;
+        trace!("patched '{name}' in process {pid}");
+    }
+
+    Ok(())
+}
+
+/// The offset and size of the dynamic symbol `name` in the 64-bit little-endian ELF `image`.
+fn elf_dynamic_symbol(image: &[u8], name: &str) -> Option<(usize, usize)> {
+    let u16_at = |off: usize| {
+        Some(u16::from_le_bytes(
+            image.get(off..off + 2)?.try_into().ok()?,
+        ))
+    };
+    let u32_at = |off: usize| {
+        Some(u32::from_le_bytes(
+            image.get(off..off + 4)?.try_into().ok()?,
+        ))
+    };
+    let u64_at = |off: usize| {
+        let v = u64::from_le_bytes(image.get(off..off + 8)?.try_into().ok()?);
+        usize::try_from(v).ok()
+    };
+
+    // the magic number, ELFCLASS64, and ELFDATA2LSB
+    if image.get(..6)? != [0x7f, b'E', b'L', b'F', 2, 1] {
+        return None;
+    }
+    let shoff = u64_at(0x28)?;
+    let shentsize = usize::from(u16_at(0x3a)?);
+    let shnum = usize::from(u16_at(0x3c)?);
+
+    const SHT_DYNSYM: u32 = 11;
+    let section = |i: usize| shoff.checked_add(i.checked_mul(shentsize)?);
+    let dynsym = (0..shnum)
+        .filter_map(section)
+        .find(|&sh| u32_at(sh + 4) == Some(SHT_DYNSYM))?;
+    let symtab = u64_at(dynsym + 0x18)?;
+    let symtab_size = u64_at(dynsym + 0x20)?;
+    let symsize = u64_at(dynsym + 0x38)?;
+    let strtab = u64_at(section(usize::try_from(u32_at(dynsym + 0x28)?).ok()?)? + 0x18)?;
+
+    if symsize == 0 {
+        return None;
+    }
+    (0..symtab_size / symsize)
+        .map(|i| symtab + i * symsize)
+        .find(|&sym| {
+            let Some(name_off) = u32_at(sym).and_then(|n| strtab.checked_add(n as usize)) else {
+                return false;
+            };
+            image
+                .get(name_off..)
+                .and_then(|s| CStr::from_bytes_until_nul(s).ok())
+                .is_some_and(|s| s.to_bytes() == name.as_bytes())
+        })
+        .and_then(|sym| Some((u64_at(sym + 8)?, u64_at(sym + 16)?)))
+}
@@ -22,6 +22,7 @@ union Addr {
     inet: libc::sockaddr_in,
     inet6: libc::sockaddr_in6,
     unix: libc::sockaddr_un,
+    netlink: libc::sockaddr_nl,
 }
 
 // verify there are no larger fields larger than `libc::sockaddr_storage`
@@ -162,6 +163,34 @@ impl SockaddrStorage {
         unsafe { Self::from_ptr(ptr as *const MaybeUninit<u8>, len) }.unwrap()
     }
 
+    /// If the socket address represents a valid netlink socket address (correct family and
+    /// length), returns the netlink socket address.
+    pub fn as_netlink(&self) -> Option<&nix::sys::socket::NetlinkAddr> {
+        if (self.len as usize) < std::mem::size_of::<libc::sockaddr_nl>() {
+            return None;
+        }
+        if self.family() != Some(AddressFamily::Netlink) {
+            return None;
+        }
+
+        // SAFETY: Assume that `nix::sys::socket::NetlinkAddr` is a transparent wrapper around a
+        // `libc::sockaddr_nl`. Verify (as best we can) that this is true.
+        assert_eq_size!(libc::sockaddr_nl, nix::sys::socket::NetlinkAddr);
+        assert_eq_align!(libc::sockaddr_nl, nix::sys::socket::NetlinkAddr);
+
+        Some(unsafe { &*(&self.addr.netlink as *const _ as *const nix::sys::socket::NetlinkAddr) })
+    }
+
+    /// Get a new `SockaddrStorage` with a copy of the netlink socket address.
+    pub fn from_netlink(addr: &nix::sys::socket::NetlinkAddr) -> Self {
+        // SAFETY: Assume that `nix::sys::socket::NetlinkAddr` is a transparent wrapper around a
+        // `libc::sockaddr_nl`. Verify (as best we can) that this is true.
+        assert_eq_size!(libc::sockaddr_nl, nix::sys::socket::NetlinkAddr);
+        assert_eq_align!(libc::sockaddr_nl, nix::sys::socket::NetlinkAddr);
+
+        unsafe { Self::from_ptr(addr.as_ptr() as *const MaybeUninit<u8>, addr.len()) }.unwrap()
+    }
+
     /// A pointer to the socket address. Some bytes may be uninitialized.
     pub fn as_ptr(&self) -> (*const MaybeUninit<u8>, libc::socklen_t) {
         (unsafe { &self.addr.slice }.as_ptr(), self.len)
@@ -178,13 +207,15 @@ impl std::fmt::Debug for SockaddrStorage {
         let as_inet = self.as_inet();
         let as_inet6 = self.as_inet6();
         let as_unix = self.as_unix();
+        let as_netlink = self.as_netlink();
 
         let as_inet = as_inet.map(|x| x as &dyn std::fmt::Debug);
         let as_inet6 = as_inet6.map(|x| x as &dyn std::fmt::Debug);
         let as_unix = as_unix.as_ref().map(|x| x as &dyn std::fmt::Debug);
+        let as_netlink = as_netlink.map(|x| x as &dyn std::fmt::Debug);
 
         // find a representation that is not None
-        let options = [as_inet, as_inet6, as_unix];
+        let options = [as_inet, as_inet6, as_unix, as_netlink];
         let addr = options.into_iter().find_map(std::convert::identity);
 
         if let Some(ref addr) = addr {
@@ -206,13 +237,15 @@ impl std::fmt::Display for SockaddrStorage {
         let as_inet = self.as_inet();
         let as_inet6 = self.as_inet6();
         let as_unix = self.as_unix();
+        let as_netlink = self.as_netlink();
 
         let as_inet = as_inet.map(|x| x as &dyn std::fmt::Display);
         let as_inet6 = as_inet6.map(|x| x as &dyn std::fmt::Display);
         let as_unix = as_unix.as_ref().map(|x| x as &dyn std::fmt::Display);
+        let as_netlink = as_netlink.map(|x| x as &dyn std::fmt::Display);
 
         // find a representation that is not None
-        let options = [as_inet, as_inet6, as_unix];
+        let options = [as_inet, as_inet6, as_unix, as_netlink];
         let addr = options.into_iter().find_map(std::convert::identity);
 
         if let Some(ref addr) = addr {
@@ -247,6 +280,12 @@ impl From<nix::sys::socket::SockaddrIn6> for SockaddrStorage {
     }
 }
 
+impl From<nix::sys::socket::NetlinkAddr> for SockaddrStorage {
+    fn from(addr: nix::sys::socket::NetlinkAddr) -> Self {
+        SockaddrStorage::from_netlink(&addr)
+    }
+}
+
 impl From<std::net::SocketAddrV4> for SockaddrStorage {
     fn from(addr: std::net::SocketAddrV4) -> Self {
         nix::sys::socket::SockaddrIn::from(addr).into()
@@ -551,6 +590,21 @@ mod tests {
         assert!(addr.as_inet6().is_none());
     }
 
+    /// Convert from a `NetlinkAddr` to a `SockaddrStorage` and back.
+    #[test]
+    fn netlink_addr_round_trip() {
+        let addr = nix::sys::socket::NetlinkAddr::new(1234, 0x5);
+        let storage = SockaddrStorage::from(addr);
+
+        assert_eq!(storage.family(), Some(AddressFamily::Netlink));
+        assert!(storage.as_inet().is_none());
+        assert!(storage.as_unix().is_none());
+
+        let addr = storage.as_netlink().unwrap();
+        assert_eq!(addr.pid(), 1234);
+        assert_eq!(addr.groups(), 0x5);
+    }
+
     /// Convert from a `sockaddr_in` to a `SockaddrStorage` to a `SockaddrIn`.
     #[test]
     fn inet_addr_from_libc() {
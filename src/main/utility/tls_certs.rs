@@ -0,0 +1,333 @@
+//! Generation of a certificate authority and per-host TLS certificates using the `openssl` command
+//! line tool. The private keys are derived from seeds and the certificates are signed with
+//! deterministic ECDSA, so the same seeds always give the same keys and certificates.
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use rand::RngCore;
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+
+/// The certificate validity period. Simulated time starts on January 1, 2000, so the certificates
+/// must be valid from before then rather than from the real current time.
+const START_DATE: &str = "19990101000000Z";
+const END_DATE: &str = "20991231235959Z";
+
+const CA_SUBJECT: &str = "/CN=Shadow Simulation CA";
+
+/// A minimal `openssl ca` configuration. The subject alternative names of a host certificate are
+/// passed in the `SHADOW_TLS_SAN` environment variable.
+const CA_CONFIG: &str = "\
+[ ca ]
+default_ca = shadow_ca
+
+[ shadow_ca ]
+dir = .
+database = $dir/index.txt
+new_certs_dir = $dir/issued
+serial = $dir/serial
+default_md = sha256
+policy = shadow_policy
+unique_subject = no
+email_in_dn = no
+
+[ shadow_policy ]
+commonName = supplied
+
+[ ca_ext ]
+basicConstraints = critical,CA:TRUE
+keyUsage = critical,keyCertSign,cRLSign
+subjectKeyIdentifier = hash
+
+[ host_ext ]
+basicConstraints = critical,CA:FALSE
+keyUsage = critical,digitalSignature,keyEncipherment
+extendedKeyUsage = serverAuth,clientAuth
+subjectKeyIdentifier = hash
+authorityKeyIdentifier = keyid
+subjectAltName = $ENV::SHADOW_TLS_SAN
+";
+
+/// The DER encoding of an EC private key on the prime256v1 curve (RFC 5915) is this prefix, the
+/// 32-byte private key, and [`EC_KEY_DER_SUFFIX`].
+const EC_KEY_DER_PREFIX: [u8; 7] = [0x30, 0x31, 0x02, 0x01, 0x01, 0x04, 0x20];
+/// The prime256v1 curve parameters.
+const EC_KEY_DER_SUFFIX: [u8; 12] = [
+    0xa0, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07,
+];
+
+/// A certificate authority used to issue a certificate for each host.
+#[derive(Debug)]
+pub struct CertAuthority {
+    dir: PathBuf,
+}
+
+impl CertAuthority {
+    /// Create a new certificate authority in the new directory `dir`, with a private key derived
+    /// from `seed`. The CA certificate (the trust bundle) is written to `ca.pem` in this directory.
+    pub fn generate(dir: impl AsRef<Path>, seed: u64) -> anyhow::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+
+        std::fs::create_dir(&dir)
+            .with_context(|| format!("Failed to create directory '{}'", dir.display()))?;
+        std::fs::create_dir(dir.join("issued"))?;
+        std::fs::write(dir.join("ca.cnf"), CA_CONFIG)?;
+        std::fs::write(dir.join("index.txt"), "")?;
+        std::fs::write(dir.join("serial"), "01\n")?;
+
+        let ca = Self { dir };
+
+        ca.new_key_and_csr(
+            &ca.dir.join("ca-key.pem"),
+            &ca.dir.join("ca.csr"),
+            CA_SUBJECT,
+            seed,
+        )?;
+        ca.sign(
+            &["-selfsign"],
+            &ca.dir.join("ca.csr"),
+            &ca.cert_path(),
+            "ca_ext",
+            "",
+        )?;
+
+        Ok(ca)
+    }
+
+    /// Path to the CA certificate.
+    pub fn cert_path(&self) -> PathBuf {
+        self.dir.join("ca.pem")
+    }
+
+    /// Issue a certificate for the host, valid for its hostname and IP address, with a private key
+    /// derived from `seed`. The host's private key (`key.pem`), certificate (`cert.pem`), and a
+    /// copy of the CA certificate (`ca.pem`) are written to `dst`, which is created if it doesn't
+    /// exist.
+    pub fn issue(
+        &self,
+        dst: impl AsRef<Path>,
+        hostname: &str,
+        ip: IpAddr,
+        seed: u64,
+    ) -> anyhow::Result<()> {
+        let dst = dst.as_ref();
+        std::fs::create_dir_all(dst)
+            .with_context(|| format!("Failed to create directory '{}'", dst.display()))?;
+
+        let csr_path = dst.join("req.csr");
+        self.new_key_and_csr(
+            &dst.join("key.pem"),
+            &csr_path,
+            &format!("/CN={hostname}"),
+            seed,
+        )?;
+        self.sign(
+            &["-cert", "ca.pem"],
+            &csr_path,
+            &dst.join("cert.pem"),
+            "host_ext",
+            &format!("DNS:{hostname},IP:{ip}"),
+        )?;
+        std::fs::remove_file(&csr_path)?;
+
+        std::fs::copy(self.cert_path(), dst.join("ca.pem"))?;
+
+        Ok(())
+    }
+
+    /// Write a new EC private key derived from `seed`, and a certificate signing request.
+    fn new_key_and_csr(
+        &self,
+        key_path: &Path,
+        csr_path: &Path,
+        subject: &str,
+        seed: u64,
+    ) -> anyhow::Result<()> {
+        // openssl can't derive a key from a seed, so write the DER encoding ourselves and have
+        // openssl convert it to PEM
+        let der_path = key_path.with_extension("der");
+        std::fs::write(&der_path, ec_key_der(seed))
+            .with_context(|| format!("Failed to write '{}'", der_path.display()))?;
+
+        let mut cmd = std::process::Command::new("openssl");
+        cmd.args(["pkey", "-inform", "DER"])
+            .arg("-in")
+            .arg(&der_path)
+            .arg("-out")
+            .arg(key_path);
+        self.run(cmd)?;
+        std::fs::remove_file(&der_path)?;
+
+        let mut cmd = std::process::Command::new("openssl");
+        cmd.args(["req", "-new"])
+            .arg("-key")
+            .arg(key_path)
+            .arg("-out")
+            .arg(csr_path)
+            .args(["-subj", subject]);
+        self.run(cmd)
+    }
+
+    /// Sign a certificate signing request using the CA key.
+    fn sign(
+        &self,
+        extra_args: &[&str],
+        csr_path: &Path,
+        cert_path: &Path,
+        extensions: &str,
+        san: &str,
+    ) -> anyhow::Result<()> {
+        let mut cmd = std::process::Command::new("openssl");
+        cmd.args([
+            "ca",
+            "-batch",
+            "-notext",
+            "-config",
+            "ca.cnf",
+            "-keyfile",
+            "ca-key.pem",
+        ])
+        .args(extra_args)
+        // deterministic ECDSA signatures (RFC 6979)
+        .args(["-sigopt", "nonce-type:1"])
+        .args(["-extensions", extensions])
+        .args(["-startdate", START_DATE, "-enddate", END_DATE])
+        .arg("-in")
+        .arg(csr_path)
+        .arg("-out")
+        .arg(cert_path)
+        .env("SHADOW_TLS_SAN", san);
+        self.run(cmd)
+    }
+
+    fn run(&self, mut cmd: std::process::Command) -> anyhow::Result<()> {
+        let output = cmd
+            .current_dir(&self.dir)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to run {cmd:?}"))?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Command {cmd:?} failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The DER encoding of a prime256v1 private key derived from `seed`.
+fn ec_key_der(seed: u64) -> Vec<u8> {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+    // any non-zero value below 2^255 is less than the curve order
+    let mut key = [0u8; 32];
+    while key == [0u8; 32] {
+        rng.fill_bytes(&mut key);
+        key[0] &= 0x7f;
+    }
+
+    [&EC_KEY_DER_PREFIX[..], &key, &EC_KEY_DER_SUFFIX].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(path: impl AsRef<Path>) -> Vec<u8> {
+        std::fs::read(path).unwrap()
+    }
+
+    fn openssl(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("openssl")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Generate a CA and a certificate for one host.
+    fn issue(dir: &Path, ca_seed: u64, host_seed: u64) -> CertAuthority {
+        let ca = CertAuthority::generate(dir.join("ca"), ca_seed).unwrap();
+        ca.issue(
+            dir.join("host"),
+            "server",
+            "11.0.0.1".parse().unwrap(),
+            host_seed,
+        )
+        .unwrap();
+        ca
+    }
+
+    // miri doesn't support running processes
+    #[cfg_attr(miri, ignore)]
+    #[test]
+    fn test_cert_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        issue(dir.path(), 1, 2);
+        let host = dir.path().join("host");
+
+        let out = openssl(&host, &["verify", "-CAfile", "ca.pem", "cert.pem"]);
+        assert_eq!(out.trim(), "cert.pem: OK");
+
+        let text = openssl(&host, &["x509", "-noout", "-text", "-in", "cert.pem"]);
+        assert!(text.contains("Subject: CN=server"));
+        assert!(text.contains("Issuer: CN=Shadow Simulation CA"));
+        assert!(text.contains("DNS:server, IP Address:11.0.0.1"));
+        assert!(text.contains("CA:FALSE"));
+
+        // the private key matches the certificate
+        let cert_pubkey = openssl(&host, &["x509", "-noout", "-pubkey", "-in", "cert.pem"]);
+        let key_pubkey = openssl(&host, &["pkey", "-pubout", "-in", "key.pem"]);
+        assert_eq!(cert_pubkey, key_pubkey);
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[test]
+    fn test_deterministic() {
+        let dir_1 = tempfile::tempdir().unwrap();
+        let dir_2 = tempfile::tempdir().unwrap();
+        let dir_3 = tempfile::tempdir().unwrap();
+        issue(dir_1.path(), 1, 2);
+        issue(dir_2.path(), 1, 2);
+        issue(dir_3.path(), 1, 3);
+
+        for file in [
+            "ca/ca.pem",
+            "ca/ca-key.pem",
+            "host/cert.pem",
+            "host/key.pem",
+        ] {
+            assert_eq!(
+                read(dir_1.path().join(file)),
+                read(dir_2.path().join(file)),
+                "{file} differs"
+            );
+        }
+
+        // a different host seed gives a different host key, but the same CA
+        assert_eq!(
+            read(dir_1.path().join("ca/ca.pem")),
+            read(dir_3.path().join("ca/ca.pem"))
+        );
+        assert_ne!(
+            read(dir_1.path().join("host/key.pem")),
+            read(dir_3.path().join("host/key.pem"))
+        );
+        assert_ne!(
+            read(dir_1.path().join("host/cert.pem")),
+            read(dir_3.path().join("host/cert.pem"))
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Helpers for simulating Tor networks: importing relay information from a network consensus
+//! document and generating a torrc file for each host. Only available when Shadow is built with the
+//! `tor_helpers` feature.
+
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::utility::data_template::substitute_vars;
+
+/// A relay listed in a consensus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsensusRelay {
+    pub nickname: String,
+    pub address: Ipv4Addr,
+    pub or_port: u16,
+    pub flags: Vec<String>,
+    /// The relay's bandwidth weight in bytes per second.
+    pub bandwidth: Option<u64>,
+}
+
+/// The parts of a Tor network status consensus document that are useful for configuring a
+/// simulation. Both full and microdescriptor consensuses are supported.
+#[derive(Debug, Clone)]
+pub struct Consensus {
+    /// The "valid-after" time as seconds since the unix epoch.
+    pub valid_after: u64,
+    /// The "valid-until" time as seconds since the unix epoch.
+    pub valid_until: u64,
+    pub relays: Vec<ConsensusRelay>,
+}
+
+impl Consensus {
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read consensus '{}'", path.display()))?;
+        Self::parse(&text)
+            .with_context(|| format!("Failed to parse consensus '{}'", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut valid_after = None;
+        let mut valid_until = None;
+        let mut relays = Vec::new();
+
+        for (line_num, line) in text.lines().enumerate() {
+            let line_num = line_num + 1;
+            let (keyword, args) = line.split_once(' ').unwrap_or((line, ""));
+
+            match keyword {
+                "valid-after" => {
+                    valid_after = Some(
+                        parse_time(args)
+                            .with_context(|| format!("Invalid time on line {line_num}"))?,
+                    )
+                }
+                "valid-until" => {
+                    valid_until = Some(
+                        parse_time(args)
+                            .with_context(|| format!("Invalid time on line {line_num}"))?,
+                    )
+                }
+                "r" => relays.push(
+                    parse_router_line(args)
+                        .with_context(|| format!("Invalid router entry on line {line_num}"))?,
+                ),
+                "s" | "w" => {
+                    let Some(relay) = relays.last_mut() else {
+                        return Err(anyhow::anyhow!(
+                            "Line {line_num} is not part of a router entry"
+                        ));
+                    };
+                    if keyword == "s" {
+                        relay.flags = args.split_whitespace().map(str::to_string).collect();
+                    } else {
+                        relay.bandwidth = parse_bandwidth_line(args)
+                            .with_context(|| format!("Invalid bandwidth on line {line_num}"))?;
+                    }
+                }
+                // the footer contains the bandwidth weights, which aren't per-relay
+                "directory-footer" => break,
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            valid_after: valid_after.context("Missing 'valid-after' line")?,
+            valid_until: valid_until.context("Missing 'valid-until' line")?,
+            relays,
+        })
+    }
+
+    /// The first relay with the given nickname. Nicknames aren't unique, so a nickname may refer
+    /// to more than one relay.
+    pub fn relay(&self, nickname: &str) -> Option<&ConsensusRelay> {
+        self.relays.iter().find(|x| x.nickname == nickname)
+    }
+
+    /// The number of seconds from `time` (seconds since the unix epoch) until the consensus
+    /// becomes valid. This is negative if the consensus became valid before `time`. Tor clients
+    /// reject consensuses that aren't valid at their current time, so a process using this
+    /// consensus must have its clock shifted by this amount.
+    pub fn time_skew(&self, time: u64) -> i64 {
+        i64::try_from(self.valid_after).unwrap() - i64::try_from(time).unwrap()
+    }
+}
+
+/// Parse the arguments of an "r" line: "nickname identity [digest] publication-date
+/// publication-time IP ORPort DirPort". The digest is not present in microdescriptor consensuses.
+fn parse_router_line(args: &str) -> anyhow::Result<ConsensusRelay> {
+    let fields: Vec<&str> = args.split_whitespace().collect();
+    if !(fields.len() == 7 || fields.len() == 8) {
+        return Err(anyhow::anyhow!(
+            "Expected 7 or 8 fields but found {}",
+            fields.len()
+        ));
+    }
+
+    let [address, or_port, _dir_port] = fields[fields.len() - 3..] else {
+        unreachable!();
+    };
+
+    Ok(ConsensusRelay {
+        nickname: fields[0].to_string(),
+        address: address.parse().context("Invalid IP address")?,
+        or_port: or_port.parse().context("Invalid ORPort")?,
+        flags: Vec::new(),
+        bandwidth: None,
+    })
+}
+
+/// Parse the arguments of a "w" line, returning the "Bandwidth" value in bytes per second.
+fn parse_bandwidth_line(args: &str) -> anyhow::Result<Option<u64>> {
+    for item in args.split_whitespace() {
+        if let Some(kilobytes) = item.strip_prefix("Bandwidth=") {
+            let kilobytes: u64 = kilobytes.parse()?;
+            return Ok(Some(kilobytes * 1000));
+        }
+    }
+    Ok(None)
+}
+
+/// Parse a UTC time in the form "YYYY-MM-DD HH:MM:SS", returning the seconds since the unix epoch.
+fn parse_time(s: &str) -> anyhow::Result<u64> {
+    let (date, time) = s
+        .trim()
+        .split_once(' ')
+        .context("Expected a date and a time")?;
+
+    let date: Vec<u64> = date
+        .split('-')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .context("Invalid date")?;
+    let time: Vec<u64> = time
+        .split(':')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .context("Invalid time")?;
+
+    let (&[year, month, day], &[hour, min, sec]) = (&date[..], &time[..]) else {
+        return Err(anyhow::anyhow!("Invalid date or time"));
+    };
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 {
+        return Err(anyhow::anyhow!("Invalid date or time"));
+    }
+
+    Ok(days_since_epoch(year, month, day) * 86400 + hour * 3600 + min * 60 + sec)
+}
+
+/// The number of days from 1970-01-01 to the given date in the proleptic Gregorian calendar.
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // count years starting in March so that the leap day is at the end of the year
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let days_in_prev_years = year * 365 + year / 4 - year / 100 + year / 400;
+    let days_in_prev_months = (153 * month + 2) / 5;
+    // 719468 is the number of days from 0000-03-01 to 1970-01-01
+    days_in_prev_years + days_in_prev_months + day - 1 - 719468
+}
+
+/// A default relay nickname for the host: its hostname with any characters that aren't allowed in
+/// nicknames removed, truncated to the maximum nickname length.
+pub fn default_nickname(hostname: &str) -> String {
+    const MAX_NICKNAME_LEN: usize = 19;
+    hostname
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(MAX_NICKNAME_LEN)
+        .collect()
+}
+
+/// Write the torrc template to `dst`, replacing any variables in the template with their values.
+/// See [`substitute_vars`] for the variable syntax.
+pub fn write_torrc(template: &Path, dst: &Path, vars: &[(&str, &str)]) -> anyhow::Result<()> {
+    let template = std::fs::read_to_string(template)
+        .with_context(|| format!("Failed to read torrc template '{}'", template.display()))?;
+    std::fs::write(dst, substitute_vars(&template, vars))
+        .with_context(|| format!("Failed to write torrc '{}'", dst.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONSENSUS: &str = "\
+network-status-version 3 microdesc
+vote-status consensus
+valid-after 2023-05-01 12:00:00
+fresh-until 2023-05-01 13:00:00
+valid-until 2023-05-01 15:00:00
+r relay1 AAoQ1DAR6kkoo19hBAX5K0QztNw 2023-05-01 02:15:27 192.0.2.1 9001 0
+m sha256=abc
+s Fast Guard Running Stable V2Dir Valid
+w Bandwidth=2500
+r relay2 AAoQ1DAR6kkoo19hBAX5K0QztNx xyz 2023-05-01 02:15:27 192.0.2.2 443 80
+s Exit Fast Running Valid
+w Bandwidth=40 Unmeasured=1
+directory-footer
+bandwidth-weights Wbd=0 Wbe=0
+";
+
+    #[test]
+    fn test_parse_consensus() {
+        let consensus = Consensus::parse(CONSENSUS).unwrap();
+
+        assert_eq!(consensus.valid_after, 1682942400);
+        assert_eq!(consensus.valid_until, 1682953200);
+        assert_eq!(consensus.relays.len(), 2);
+
+        assert_eq!(
+            consensus.relay("relay1"),
+            Some(&ConsensusRelay {
+                nickname: "relay1".to_string(),
+                address: Ipv4Addr::new(192, 0, 2, 1),
+                or_port: 9001,
+                flags: ["Fast", "Guard", "Running", "Stable", "V2Dir", "Valid"]
+                    .map(String::from)
+                    .to_vec(),
+                bandwidth: Some(2_500_000),
+            })
+        );
+
+        let relay2 = consensus.relay("relay2").unwrap();
+        assert_eq!(relay2.or_port, 443);
+        assert_eq!(relay2.bandwidth, Some(40_000));
+
+        assert_eq!(consensus.relay("relay3"), None);
+
+        // the simulation start time
+        assert_eq!(consensus.time_skew(946684800), 736257600);
+    }
+
+    #[test]
+    fn test_parse_invalid_consensus() {
+        assert!(Consensus::parse("valid-after 2023-05-01 12:00:00\n").is_err());
+        assert!(Consensus::parse("s Fast\n").is_err());
+        assert!(Consensus::parse("valid-after 2023-13-01 12:00:00\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("1970-01-01 00:00:00").unwrap(), 0);
+        assert_eq!(parse_time("2000-01-01 00:00:00").unwrap(), 946684800);
+        assert_eq!(parse_time("2000-02-29 23:59:59").unwrap(), 951868799);
+        assert_eq!(parse_time("2024-03-01 00:00:01").unwrap(), 1709251201);
+        assert!(parse_time("2024-03-01").is_err());
+        assert!(parse_time("2024-03-01 00:00").is_err());
+    }
+
+    #[test]
+    fn test_default_nickname() {
+        assert_eq!(default_nickname("relay1"), "relay1");
+        assert_eq!(default_nickname("exit-relay.example"), "exitrelayexample");
+        assert_eq!(
+            default_nickname("a-very-long-hostname-for-a-relay"),
+            "averylonghostnamefo"
+        );
+    }
+}
@@ -0,0 +1,66 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Measures the round trip times of datagrams echoed by a server. Run as `server <port>` to echo
+//! datagrams, or as `client <host:port> <extra ms>` to check that the first round trip takes
+//! `<extra ms>` longer than the later round trips, which is the time that the client waits for its
+//! ARP request to be answered.
+
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use test_utils::time::duration_abs_diff;
+
+fn server(port: u16) {
+    let socket = UdpSocket::bind(("0.0.0.0", port)).unwrap();
+    let mut buf = [0u8; 64];
+    loop {
+        let (len, addr) = socket.recv_from(&mut buf).unwrap();
+        socket.send_to(&buf[..len], addr).unwrap();
+    }
+}
+
+fn client(addr: &str, extra: Duration) {
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    socket.connect(addr).unwrap();
+
+    let rtts: Vec<Duration> = (0..3)
+        .map(|i: u8| {
+            let start = Instant::now();
+            socket.send(&[i]).unwrap();
+            let mut buf = [0u8; 64];
+            let len = socket.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..len], &[i]);
+            start.elapsed()
+        })
+        .collect();
+    println!("Round trip times: {rtts:?}");
+
+    // allow for the time spent in syscalls
+    let tolerance = Duration::from_millis(1);
+
+    // later datagrams are sent without waiting for an ARP reply
+    assert!(duration_abs_diff(rtts[1], rtts[2]) < tolerance);
+
+    let first_extra = rtts[0] - rtts[1];
+    assert!(
+        first_extra >= extra && first_extra < extra + tolerance,
+        "The first round trip took {first_extra:?} longer than the others, expected {extra:?}"
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match &args[1..] {
+        [mode, port] if mode == "server" => server(port.parse().unwrap()),
+        [mode, addr, extra] if mode == "client" => {
+            client(addr, Duration::from_millis(extra.parse().unwrap()))
+        }
+        _ => panic!(
+            "Usage: {} server <port> | client <host:port> <extra ms>",
+            args[0]
+        ),
+    }
+}
@@ -0,0 +1,19 @@
+#!/bin/sh
+
+# Runs shadow with a bridged host that represents a UDP echo server outside of the simulation. This
+# creates the TUN device, so it must run in its own network namespace, for example using
+# `unshare --user --map-root-user --net`.
+#
+# Usage: bridge.sh <shadow> <test_bridge> <config> <data directory>
+
+set -eu
+
+ip tuntap add dev shadow0 mode tun
+ip addr add 11.0.0.1/8 dev shadow0
+ip link set shadow0 up
+
+"$2" echo-server 11.0.0.1:5000 &
+trap 'kill $!' EXIT
+
+rm -rf "$4"
+"$1" --data-directory="$4" --log-level=info --parallelism 1 --use-cpu-pinning false "$3"
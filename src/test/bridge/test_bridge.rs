@@ -0,0 +1,47 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Exchanges datagrams between a simulated host and an echo server outside of the simulation. The
+//! arguments are the role and its address: "echo-server <address>" to run the echo server natively,
+//! or "client <address>" to send datagrams to the echo server from inside the simulation.
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn echo_server(addr: &str) {
+    let socket = UdpSocket::bind(addr).unwrap();
+    let mut buf = [0u8; 64];
+    loop {
+        let (len, src) = socket.recv_from(&mut buf).unwrap();
+        socket.send_to(&buf[..len], src).unwrap();
+    }
+}
+
+fn client(addr: &str) {
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    socket.connect(addr).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    for i in 0..3 {
+        let msg = format!("hello {i}");
+        socket.send(msg.as_bytes()).unwrap();
+
+        let mut buf = [0u8; 64];
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], msg.as_bytes());
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match &args[1..] {
+        ["echo-server", addr] => echo_server(addr),
+        ["client", addr] => client(addr),
+        _ => panic!("Unexpected arguments: {:?}", &args[1..]),
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Exchanges messages with an echo server through a keyword-blocking censor. The arguments are the
+//! host's role: "server", or "client <server> <residual>", where `residual` is "true" if the
+//! censor also blocks new connections to the server after a keyword is matched.
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+const PORT: u16 = 80;
+const KEYWORD: &[u8] = b"forbidden.example.com";
+
+fn server() {
+    let listener = TcpListener::bind(("0.0.0.0", PORT)).unwrap();
+    loop {
+        let (mut stream, _) = listener.accept().unwrap();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => stream.write_all(&buf[..len]).unwrap(),
+                }
+            }
+        });
+    }
+}
+
+/// Send `msg` to the echo server and check that it echoes it back.
+fn echo(stream: &mut TcpStream, msg: &[u8]) -> std::io::Result<()> {
+    stream.write_all(msg)?;
+    let mut buf = vec![0u8; msg.len()];
+    stream.read_exact(&mut buf)?;
+    assert_eq!(buf, msg);
+    Ok(())
+}
+
+fn connect(server: &str) -> TcpStream {
+    let stream = TcpStream::connect((server, PORT)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    stream
+}
+
+fn client(server: &str, residual: bool) {
+    let mut stream = connect(server);
+    echo(&mut stream, b"hello").unwrap();
+
+    // the censor blocks the message and resets the connection
+    let err = echo(&mut stream, KEYWORD).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+
+    // the censor blocks new connections to the server during the residual blocking period
+    let result = TcpStream::connect((server, PORT)).and_then(|mut stream| {
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        echo(&mut stream, b"hello")
+    });
+    assert_eq!(result.is_err(), residual, "{result:?}");
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match &args[1..] {
+        ["server"] => server(),
+        ["client", server, residual] => client(server, residual.parse().unwrap()),
+        _ => panic!("Unexpected arguments: {:?}", &args[1..]),
+    }
+}
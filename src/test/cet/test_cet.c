@@ -0,0 +1,65 @@
+/*
+ * Runs as a binary built with Control-flow Enforcement Technology (CET) enabled, and gets the time
+ * through the vdso functions that the shim patches.
+ *
+ * Pass "shadow" as the first argument to also check that the time is emulated, and the expected
+ * value of the GLIBC_TUNABLES environment variable as the second argument.
+ */
+
+#include <errno.h>
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/time.h>
+#include <time.h>
+
+#define check(cond)                                                                                \
+    do {                                                                                           \
+        if (!(cond)) {                                                                             \
+            fprintf(stderr, "%s:%d: check failed: %s (errno: %s)\n", __FILE__, __LINE__, #cond,    \
+                    strerror(errno));                                                              \
+            exit(EXIT_FAILURE);                                                                    \
+        }                                                                                          \
+    } while (0)
+
+// 2000-01-01 00:00:00 UTC, the simulation's start time.
+#define SIM_START_SECS 946684800
+
+// Check that a time in seconds is within the first day of the simulation.
+static void check_time(time_t secs, bool in_shadow) {
+    check(secs > 0);
+    if (in_shadow) {
+        check(secs >= SIM_START_SECS && secs < SIM_START_SECS + 24 * 60 * 60);
+    }
+}
+
+int main(int argc, char* argv[]) {
+    bool in_shadow = argc > 1 && strcmp(argv[1], "shadow") == 0;
+
+    // glibc calls each of these through an indirect call to the vdso, so with indirect branch
+    // tracking the patched vdso functions must still start with `endbr64`
+    struct timespec ts = {0};
+    check(clock_gettime(CLOCK_REALTIME, &ts) == 0);
+    check_time(ts.tv_sec, in_shadow);
+
+    check(clock_gettime(CLOCK_MONOTONIC, &ts) == 0);
+
+    struct timeval tv = {0};
+    check(gettimeofday(&tv, NULL) == 0);
+    check_time(tv.tv_sec, in_shadow);
+
+    check_time(time(NULL), in_shadow);
+
+    if (argc > 2) {
+        const char* tunables = getenv("GLIBC_TUNABLES");
+        check(tunables != NULL);
+        if (strcmp(tunables, argv[2]) != 0) {
+            fprintf(stderr, "GLIBC_TUNABLES is '%s' but expected '%s'\n", tunables, argv[2]);
+            return EXIT_FAILURE;
+        }
+    }
+
+    printf("Success\n");
+    return EXIT_SUCCESS;
+}
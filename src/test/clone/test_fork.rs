@@ -947,6 +947,278 @@ fn test_waitid_sets_signal_death_info(fatal_signal: Signal) -> anyhow::Result<()
     })
 }
 
+/// Fork a child that sleeps until it's killed.
+fn fork_sleeping_child() -> Pid {
+    match unsafe { linux_api::sched::fork() }.unwrap() {
+        CloneResult::CallerIsChild => loop {
+            match rustix::thread::nanosleep(&rustix::fs::Timespec {
+                tv_sec: i64::MAX,
+                tv_nsec: 0,
+            }) {
+                rustix::thread::NanosleepRelativeResult::Ok
+                | rustix::thread::NanosleepRelativeResult::Interrupted(_) => (),
+                other => panic!("Unexpected nanosleep result: {other:?}"),
+            }
+        },
+        CloneResult::CallerIsParent(child_pid) => child_pid,
+    }
+}
+
+/// Validate that `waitpid` reports a child being stopped with WUNTRACED, and
+/// being continued with WCONTINUED, and reports each only once.
+fn test_waitpid_reports_stop_and_continue() -> anyhow::Result<()> {
+    run_test_in_subprocess(|| {
+        let child_pid = fork_sleeping_child();
+        let raw_child_pid = child_pid.as_raw_nonzero().get();
+        let waitpid = |options| {
+            let mut wstatus = 0;
+            let rv = nix::errno::Errno::result(unsafe {
+                libc::waitpid(raw_child_pid, &mut wstatus, options)
+            })
+            .unwrap();
+            (rv, wstatus)
+        };
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGSTOP)),
+            Ok(())
+        );
+        let (rv, wstatus) = waitpid(libc::WUNTRACED);
+        assert_eq!(rv, raw_child_pid);
+        assert!(libc::WIFSTOPPED(wstatus));
+        assert_eq!(libc::WSTOPSIG(wstatus), libc::SIGSTOP);
+
+        // The stop was already reported.
+        assert_eq!(waitpid(libc::WUNTRACED | libc::WNOHANG).0, 0);
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGCONT)),
+            Ok(())
+        );
+        let (rv, wstatus) = waitpid(libc::WCONTINUED);
+        assert_eq!(rv, raw_child_pid);
+        assert!(libc::WIFCONTINUED(wstatus));
+
+        // The continue was already reported.
+        assert_eq!(waitpid(libc::WCONTINUED | libc::WNOHANG).0, 0);
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGKILL)),
+            Ok(())
+        );
+        let (rv, wstatus) = waitpid(0);
+        assert_eq!(rv, raw_child_pid);
+        assert!(libc::WIFSIGNALED(wstatus));
+        assert_eq!(libc::WTERMSIG(wstatus), libc::SIGKILL);
+    })
+}
+
+/// Validate that `waitid` correctly sets the `infop` parameter for a child that
+/// has been stopped and continued.
+fn test_waitid_sets_stop_and_continue_info() -> anyhow::Result<()> {
+    run_test_in_subprocess(|| {
+        let child_pid = fork_sleeping_child();
+        let raw_child_pid = child_pid.as_raw_nonzero().get();
+        let waitid = |options| {
+            let mut info: siginfo_t = unsafe { std::mem::zeroed() };
+            let rv = nix::errno::Errno::result(unsafe {
+                libc::waitid(
+                    libc::P_PID,
+                    raw_child_pid.try_into().unwrap(),
+                    &mut info,
+                    options,
+                )
+            });
+            assert_eq!(rv, Ok(0));
+            info
+        };
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGTSTP)),
+            Ok(())
+        );
+        let info = waitid(libc::WSTOPPED);
+        assert_eq!(info.si_signo, Signal::SIGCHLD.as_i32());
+        assert_eq!(
+            info.si_code,
+            i32::from(linux_api::signal::SigInfoCodeCld::CLD_STOPPED)
+        );
+        assert_eq!(unsafe { info.si_pid() }, raw_child_pid);
+        assert_eq!(unsafe { info.si_status() }, libc::SIGTSTP);
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGCONT)),
+            Ok(())
+        );
+        let info = waitid(libc::WCONTINUED);
+        assert_eq!(
+            info.si_code,
+            i32::from(linux_api::signal::SigInfoCodeCld::CLD_CONTINUED)
+        );
+        assert_eq!(unsafe { info.si_pid() }, raw_child_pid);
+        assert_eq!(unsafe { info.si_status() }, libc::SIGCONT);
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGKILL)),
+            Ok(())
+        );
+        let info = waitid(libc::WEXITED);
+        assert_eq!(
+            info.si_code,
+            i32::from(linux_api::signal::SigInfoCodeCld::CLD_KILLED)
+        );
+    })
+}
+
+/// Validate that a child that stops itself doesn't run again until it's
+/// continued.
+fn test_child_stops_itself() -> anyhow::Result<()> {
+    run_test_in_subprocess(|| {
+        const CHILD_EXIT_STATUS: i32 = 42;
+        let child_pid = match unsafe { linux_api::sched::fork() }.unwrap() {
+            CloneResult::CallerIsChild => {
+                unsafe { libc::raise(libc::SIGSTOP) };
+                unsafe { libc::exit(CHILD_EXIT_STATUS) };
+            }
+            CloneResult::CallerIsParent(child_pid) => child_pid,
+        };
+        let raw_child_pid = child_pid.as_raw_nonzero().get();
+
+        let mut wstatus = 0;
+        let rv = unsafe { libc::waitpid(raw_child_pid, &mut wstatus, libc::WUNTRACED) };
+        assert_eq!(rv, raw_child_pid);
+        assert!(libc::WIFSTOPPED(wstatus));
+
+        // The child doesn't exit while it's stopped.
+        match rustix::thread::nanosleep(&rustix::fs::Timespec {
+            tv_sec: 0,
+            tv_nsec: 100_000_000,
+        }) {
+            rustix::thread::NanosleepRelativeResult::Ok => (),
+            other => panic!("Unexpected nanosleep result: {other:?}"),
+        }
+        let rv = unsafe { libc::waitpid(raw_child_pid, &mut wstatus, libc::WNOHANG) };
+        assert_eq!(rv, 0);
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGCONT)),
+            Ok(())
+        );
+        let rv = unsafe { libc::waitpid(raw_child_pid, &mut wstatus, 0) };
+        assert_eq!(rv, raw_child_pid);
+        assert!(libc::WIFEXITED(wstatus));
+        assert_eq!(libc::WEXITSTATUS(wstatus), CHILD_EXIT_STATUS);
+    })
+}
+
+/// Validate that children don't become zombies if the parent sets
+/// SA_NOCLDWAIT for SIGCHLD.
+fn test_sa_nocldwait_autoreaps() -> anyhow::Result<()> {
+    run_test_in_subprocess(|| {
+        let action = SigAction::new(SigHandler::SigDfl, SaFlags::SA_NOCLDWAIT, SigSet::empty());
+        unsafe { nix::sys::signal::sigaction(nix::sys::signal::Signal::SIGCHLD, &action) }.unwrap();
+
+        let child_pid = match unsafe { linux_api::sched::fork() }.unwrap() {
+            CloneResult::CallerIsChild => {
+                unsafe { libc::exit(0) };
+            }
+            CloneResult::CallerIsParent(child_pid) => child_pid,
+        };
+
+        // `wait` blocks until the child has exited, and then fails since there
+        // are no children left.
+        let rv = nix::errno::Errno::result(unsafe { libc::wait(std::ptr::null_mut()) });
+        assert_eq!(rv, Err(nix::errno::Errno::ECHILD));
+
+        // The child doesn't exist as a zombie.
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, None),
+            Err(Errno::ESRCH)
+        );
+    })
+}
+
+/// Validate that `waitid` zeroes the `infop` parameter if WNOHANG is provided
+/// and no children have changed state.
+fn test_waitid_wnohang_zeroes_info() -> anyhow::Result<()> {
+    run_test_in_subprocess(|| {
+        let child_pid = fork_sleeping_child();
+
+        let mut info: siginfo_t = unsafe { std::mem::zeroed() };
+        info.si_signo = libc::SIGUSR1;
+        info.si_code = 1;
+        let rv = nix::errno::Errno::result(unsafe {
+            libc::waitid(libc::P_ALL, 0, &mut info, libc::WEXITED | libc::WNOHANG)
+        });
+        assert_eq!(rv, Ok(0));
+        assert_eq!(info.si_signo, 0);
+        assert_eq!(info.si_code, 0);
+        assert_eq!(unsafe { info.si_pid() }, 0);
+
+        // No events to wait for.
+        let rv = nix::errno::Errno::result(unsafe {
+            libc::waitid(libc::P_ALL, 0, &mut info, libc::WNOHANG)
+        });
+        assert_eq!(rv, Err(nix::errno::Errno::EINVAL));
+
+        assert_eq!(
+            linux_api::signal::kill_process(child_pid, Some(Signal::SIGKILL)),
+            Ok(())
+        );
+    })
+}
+
+/// Validate that `getrusage` and `wait4` report resource usage, and that a
+/// child's usage is added to its parent's children's usage once it's reaped.
+fn test_rusage() -> anyhow::Result<()> {
+    fn cpu_time(usage: &libc::rusage) -> std::time::Duration {
+        let timeval_duration = |tv: libc::timeval| {
+            std::time::Duration::from_secs(tv.tv_sec.try_into().unwrap())
+                + std::time::Duration::from_micros(tv.tv_usec.try_into().unwrap())
+        };
+        timeval_duration(usage.ru_utime) + timeval_duration(usage.ru_stime)
+    }
+    fn getrusage(who: i32) -> Result<libc::rusage, nix::errno::Errno> {
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        nix::errno::Errno::result(unsafe { libc::getrusage(who, &mut usage) })?;
+        Ok(usage)
+    }
+
+    run_test_in_subprocess(|| {
+        getrusage(libc::RUSAGE_SELF).unwrap();
+        getrusage(libc::RUSAGE_THREAD).unwrap();
+        assert_eq!(getrusage(42).map(|_| ()), Err(nix::errno::Errno::EINVAL));
+
+        // No children have been reaped yet.
+        let children_usage = getrusage(libc::RUSAGE_CHILDREN).unwrap();
+        assert_eq!(cpu_time(&children_usage), std::time::Duration::ZERO);
+
+        let child_pid = match unsafe { linux_api::sched::fork() }.unwrap() {
+            CloneResult::CallerIsChild => {
+                // Use some CPU.
+                let mut x = 0u64;
+                for i in 0..10_000_000 {
+                    x = std::hint::black_box(x.wrapping_add(i));
+                }
+                unsafe { libc::exit(0) };
+            }
+            CloneResult::CallerIsParent(child_pid) => child_pid,
+        };
+
+        let mut child_usage: libc::rusage = unsafe { std::mem::zeroed() };
+        let rv = unsafe { libc::wait4(-1, std::ptr::null_mut(), 0, &mut child_usage) };
+        assert_eq!(rv, child_pid.as_raw_nonzero().get());
+
+        let children_usage = getrusage(libc::RUSAGE_CHILDREN).unwrap();
+        if running_in_shadow() {
+            assert_eq!(cpu_time(&children_usage), cpu_time(&child_usage));
+        } else {
+            // Linux's accounting of the two may differ slightly.
+            assert!(cpu_time(&children_usage) > std::time::Duration::ZERO);
+        }
+    })
+}
+
 /// Core, minimal functionality for fork+exec
 fn test_fork_exec_and_reap(
     spawn_fn: impl FnOnce(&Path, &[&str]) -> Pid,
@@ -1648,6 +1920,37 @@ fn main() -> Result<(), Box<dyn Error>> {
         ));
     }
 
+    tests.push(ShadowTest::new(
+        "test_waitpid_reports_stop_and_continue",
+        test_waitpid_reports_stop_and_continue,
+        all_envs.clone(),
+    ));
+    tests.push(ShadowTest::new(
+        "test_waitid_sets_stop_and_continue_info",
+        test_waitid_sets_stop_and_continue_info,
+        all_envs.clone(),
+    ));
+    tests.push(ShadowTest::new(
+        "test_child_stops_itself",
+        test_child_stops_itself,
+        all_envs.clone(),
+    ));
+    tests.push(ShadowTest::new(
+        "test_sa_nocldwait_autoreaps",
+        test_sa_nocldwait_autoreaps,
+        all_envs.clone(),
+    ));
+    tests.push(ShadowTest::new(
+        "test_waitid_wnohang_zeroes_info",
+        test_waitid_wnohang_zeroes_info,
+        all_envs.clone(),
+    ));
+    tests.push(ShadowTest::new(
+        "test_rusage",
+        test_rusage,
+        all_envs.clone(),
+    ));
+
     #[allow(clippy::type_complexity)]
     let spawn_fns: [(&str, Arc<dyn Fn(&Path, &[&str]) -> Pid>); 4] = [
         (
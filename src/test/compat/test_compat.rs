@@ -0,0 +1,41 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Checks that 32-bit syscalls made with `int $0x80` fail with `ENOSYS` in Shadow, rather than
+//! being handled as the 64-bit syscalls with the same numbers.
+
+/// Make a 32-bit syscall without arguments.
+fn int80_syscall(n: i32) -> i32 {
+    let rv: i32;
+    unsafe {
+        // the kernel clobbers r8-r11 when a 64-bit process uses `int $0x80`
+        std::arch::asm!(
+            "int 0x80",
+            inlateout("eax") n => rv,
+            out("r8") _,
+            out("r9") _,
+            out("r10") _,
+            out("r11") _,
+            options(nostack),
+        );
+    }
+    rv
+}
+
+fn main() {
+    // the 32-bit `getpid` syscall, which is `writev` in the 64-bit ABI
+    const NR_GETPID_32: i32 = 20;
+
+    let rv = int80_syscall(NR_GETPID_32);
+
+    if test_utils::running_in_shadow() {
+        assert_eq!(rv, -libc::ENOSYS);
+    } else {
+        // the kernel may have been built without support for 32-bit syscalls
+        assert!(rv == -libc::ENOSYS || rv == unsafe { libc::getpid() });
+    }
+
+    println!("Success");
+}
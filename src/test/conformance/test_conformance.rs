@@ -0,0 +1,211 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Socket-level scenarios whose observable behavior is compared between Shadow and Linux by the
+//! `conformance` integration test. The server answers the client's scenarios, and the client runs
+//! each scenario and prints one line for it: the scenario's name, its result (`ok`, optionally
+//! followed by details such as the errno of an intermediate call, or the name of the error that
+//! the scenario failed with), and the time that the scenario took in microseconds.
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddrV4, TcpListener, TcpStream, UdpSocket};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags};
+use nix::sys::socket::{self, sockopt, AddressFamily, SockFlag, SockType, SockaddrIn};
+
+/// The port that the server answers TCP and UDP on.
+const PORT: u16 = 8000;
+/// A port that the server doesn't listen on.
+const CLOSED_PORT: u16 = 8001;
+/// The size of the data echoed by the `tcp_echo_large` scenario.
+const LARGE_LEN: usize = 1024 * 1024;
+/// How long the client waits for each response before the scenario fails.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+type Scenario = fn(Ipv4Addr) -> std::io::Result<String>;
+
+const SCENARIOS: &[(&str, Scenario)] = &[
+    ("tcp_connect", tcp_connect),
+    ("tcp_connect_refused", tcp_connect_refused),
+    ("tcp_nonblocking_connect", tcp_nonblocking_connect),
+    (
+        "tcp_nonblocking_connect_refused",
+        tcp_nonblocking_connect_refused,
+    ),
+    ("tcp_echo", tcp_echo),
+    ("tcp_echo_large", tcp_echo_large),
+    ("tcp_shutdown", tcp_shutdown),
+    ("udp_echo", udp_echo),
+];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match (args.get(1).map(String::as_str), args.get(2)) {
+        (Some("server"), None) => server(),
+        (Some("client"), Some(addr)) => client(addr.parse().expect("invalid server address")),
+        _ => {
+            eprintln!("Usage: {} server | client <server address>", args[0]);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn server() {
+    let udp = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT)).unwrap();
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, PORT)).unwrap();
+
+    // the test waits for this line before starting the client
+    println!("ready");
+    std::io::stdout().flush().unwrap();
+
+    std::thread::spawn(move || {
+        let mut buf = vec![0u8; 65536];
+        loop {
+            let (len, from) = udp.recv_from(&mut buf).unwrap();
+            udp.send_to(&buf[..len], from).unwrap();
+        }
+    });
+
+    for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        std::thread::spawn(move || {
+            let mut buf = vec![0u8; 65536];
+            loop {
+                // the client may reset the connection
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => {
+                        if stream.write_all(&buf[..len]).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn client(server: Ipv4Addr) {
+    for (name, scenario) in SCENARIOS {
+        let start = Instant::now();
+        let result = scenario(server);
+        let elapsed = start.elapsed();
+
+        let result = match result {
+            Ok(details) if details.is_empty() => "ok".to_string(),
+            Ok(details) => format!("ok:{details}"),
+            Err(e) => error_name(&e),
+        };
+        println!("{name} {result} {}", elapsed.as_micros());
+    }
+}
+
+/// The name of the errno of `e`, such as "ECONNREFUSED".
+fn error_name(e: &std::io::Error) -> String {
+    match e.raw_os_error() {
+        Some(errno) => errno_name(errno),
+        None => format!("{:?}", e.kind()),
+    }
+}
+
+/// The name of `errno`, or "0" if there's no error.
+fn errno_name(errno: i32) -> String {
+    match errno {
+        0 => "0".to_string(),
+        errno => format!("{:?}", Errno::from_i32(errno)),
+    }
+}
+
+fn connect(server: Ipv4Addr) -> std::io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&(server, PORT).into(), TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    Ok(stream)
+}
+
+fn tcp_connect(server: Ipv4Addr) -> std::io::Result<String> {
+    connect(server)?;
+    Ok(String::new())
+}
+
+fn tcp_connect_refused(server: Ipv4Addr) -> std::io::Result<String> {
+    TcpStream::connect_timeout(&(server, CLOSED_PORT).into(), TIMEOUT)?;
+    Ok(String::new())
+}
+
+/// Connect a non-blocking socket, returning the errno of `connect()` and the socket's `SO_ERROR`
+/// once it's writable.
+fn nonblocking_connect(addr: SocketAddrV4) -> std::io::Result<String> {
+    let fd = socket::socket(
+        AddressFamily::Inet,
+        SockType::Stream,
+        SockFlag::SOCK_NONBLOCK,
+        None,
+    )?;
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let rv = socket::connect(fd.as_raw_fd(), &SockaddrIn::from(addr));
+    let connect_errno = rv.err().map_or(0, |e| e as i32);
+
+    let mut fds = [PollFd::new(fd.as_raw_fd(), PollFlags::POLLOUT)];
+    let timeout = i32::try_from(TIMEOUT.as_millis()).unwrap();
+    if nix::poll::poll(&mut fds, timeout)? == 0 {
+        return Err(Errno::ETIMEDOUT.into());
+    }
+
+    let so_error = socket::getsockopt(fd.as_raw_fd(), sockopt::SocketError)?;
+    Ok(format!(
+        "{},{}",
+        errno_name(connect_errno),
+        errno_name(so_error)
+    ))
+}
+
+fn tcp_nonblocking_connect(server: Ipv4Addr) -> std::io::Result<String> {
+    nonblocking_connect(SocketAddrV4::new(server, PORT))
+}
+
+fn tcp_nonblocking_connect_refused(server: Ipv4Addr) -> std::io::Result<String> {
+    nonblocking_connect(SocketAddrV4::new(server, CLOSED_PORT))
+}
+
+fn tcp_echo(server: Ipv4Addr) -> std::io::Result<String> {
+    let mut stream = connect(server)?;
+    stream.write_all(&[1; 1000])?;
+    stream.read_exact(&mut [0; 1000])?;
+    Ok(String::new())
+}
+
+fn tcp_echo_large(server: Ipv4Addr) -> std::io::Result<String> {
+    let mut stream = connect(server)?;
+
+    // write from another thread so that the server's echoes don't fill the buffers
+    let mut writer = stream.try_clone()?;
+    let writer = std::thread::spawn(move || writer.write_all(&vec![1; LARGE_LEN]));
+
+    let mut buf = vec![0; LARGE_LEN];
+    stream.read_exact(&mut buf)?;
+    writer.join().unwrap()?;
+    Ok(String::new())
+}
+
+fn tcp_shutdown(server: Ipv4Addr) -> std::io::Result<String> {
+    let mut stream = connect(server)?;
+    stream.shutdown(Shutdown::Write)?;
+    // the server closes the connection once it reads the end of the stream
+    let len = stream.read(&mut [0; 16])?;
+    Ok(format!("{len}"))
+}
+
+fn udp_echo(server: Ipv4Addr) -> std::io::Result<String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.send_to(&[1; 1000], (server, PORT))?;
+    let mut buf = [0; 2000];
+    let len = socket.recv(&mut buf)?;
+    Ok(format!("{len}"))
+}
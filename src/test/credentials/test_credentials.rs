@@ -0,0 +1,114 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Starts as root and drops privileges the way that servers usually do at startup.
+
+use std::os::unix::fs::OpenOptionsExt;
+
+use test_utils::get_errno;
+
+const WORKER_ID: libc::uid_t = 33;
+
+fn getresuid() -> (libc::uid_t, libc::uid_t, libc::uid_t) {
+    let (mut ruid, mut euid, mut suid) = (0, 0, 0);
+    assert_eq!(
+        unsafe { libc::getresuid(&mut ruid, &mut euid, &mut suid) },
+        0
+    );
+    (ruid, euid, suid)
+}
+
+fn getgroups() -> Vec<libc::gid_t> {
+    let num = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    assert!(num >= 0);
+    let mut groups = vec![0; num as usize];
+    assert_eq!(unsafe { libc::getgroups(num, groups.as_mut_ptr()) }, num);
+    groups
+}
+
+fn main() {
+    // the ids from the config
+    assert_eq!(unsafe { libc::getuid() }, 0);
+    assert_eq!(unsafe { libc::geteuid() }, 0);
+    assert_eq!(unsafe { libc::getgid() }, 0);
+    assert_eq!(unsafe { libc::getegid() }, 0);
+    assert_eq!(getgroups(), [0, 4]);
+
+    // a privileged process can give its files to the user that it will run as
+    std::fs::write("test_credentials_file", "test").unwrap();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .mode(0o600)
+        .open("test_credentials_secret")
+        .unwrap();
+    let path = std::ffi::CString::new("test_credentials_file").unwrap();
+    assert_eq!(
+        unsafe { libc::chown(path.as_ptr(), WORKER_ID, WORKER_ID) },
+        0
+    );
+
+    // temporarily drop privileges
+    assert_eq!(unsafe { libc::seteuid(WORKER_ID) }, 0);
+    assert_eq!(getresuid(), (0, WORKER_ID, 0));
+    assert_eq!(unsafe { libc::setgroups(0, std::ptr::null()) }, -1);
+    assert_eq!(get_errno(), libc::EPERM);
+    assert_eq!(unsafe { libc::seteuid(0) }, 0);
+
+    // permanently drop privileges
+    assert_eq!(unsafe { libc::setgroups(1, &WORKER_ID) }, 0);
+    assert_eq!(unsafe { libc::setgid(WORKER_ID) }, 0);
+    assert_eq!(unsafe { libc::setuid(WORKER_ID) }, 0);
+    assert_eq!(getresuid(), (WORKER_ID, WORKER_ID, WORKER_ID));
+    assert_eq!(unsafe { libc::getgid() }, WORKER_ID);
+    assert_eq!(getgroups(), [WORKER_ID]);
+
+    // privileges can't be regained
+    assert_eq!(unsafe { libc::setuid(0) }, -1);
+    assert_eq!(get_errno(), libc::EPERM);
+    assert_eq!(unsafe { libc::setresgid(0, 0, 0) }, -1);
+    assert_eq!(get_errno(), libc::EPERM);
+
+    // the file permissions are checked against the worker's ids, not shadow's
+    let secret = std::ffi::CString::new("test_credentials_secret").unwrap();
+    assert_eq!(
+        std::fs::File::open("test_credentials_secret")
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    assert_eq!(unsafe { libc::access(secret.as_ptr(), libc::R_OK) }, -1);
+    assert_eq!(get_errno(), libc::EACCES);
+    assert_eq!(unsafe { libc::access(path.as_ptr(), libc::R_OK) }, 0);
+    // the worker can't write to the process's working directory
+    assert_eq!(unsafe { libc::unlink(secret.as_ptr()) }, -1);
+    assert_eq!(get_errno(), libc::EACCES);
+
+    // the worker is no longer in the root group, so it can't give its files to that group even if
+    // shadow could
+    assert_eq!(unsafe { libc::chown(path.as_ptr(), u32::MAX, 0) }, -1);
+    assert_eq!(get_errno(), libc::EPERM);
+    let file = std::fs::File::open("test_credentials_file").unwrap();
+    let fd = std::os::fd::AsRawFd::as_raw_fd(&file);
+    assert_eq!(unsafe { libc::fchown(fd, u32::MAX, 0) }, -1);
+    assert_eq!(get_errno(), libc::EPERM);
+
+    // the ids are inherited by child processes
+    let pid = unsafe { libc::fork() };
+    if pid == 0 {
+        let rv = if getresuid() == (WORKER_ID, WORKER_ID, WORKER_ID) {
+            0
+        } else {
+            1
+        };
+        unsafe { libc::_exit(rv) };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status));
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+
+    println!("Success.");
+}
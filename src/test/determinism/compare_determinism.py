@@ -0,0 +1,81 @@
+#!/usr/bin/env python3
+
+'''
+Check that runs of the same simulation were identical. Each run is given by
+the name of a test run by `add_determinism_tests`, whose data directory is
+`<name>.data` and whose log is `<name>.log`. The runs are compared with the
+first run, and must have the same hosts, the same packet fingerprint for each
+host, and the same heartbeat statistics for each host.
+'''
+
+import os
+import sys
+
+
+def fingerprints(name):
+    '''The packet fingerprint of each host.'''
+    hosts_dir = os.path.join(name + '.data', 'hosts')
+    result = {}
+    for host in sorted(os.listdir(hosts_dir)):
+        path = os.path.join(hosts_dir, host, 'packets.fingerprint')
+        with open(path) as f:
+            result[host] = f.read().strip()
+    return result
+
+
+def heartbeats(name):
+    '''The heartbeat lines of the hosts, without the real time and thread that logged them. The
+    hosts' lines are interleaved differently when there are several worker threads, so they're
+    sorted.'''
+    lines = []
+    with open(name + '.log') as f:
+        for line in f:
+            if '[shadow-heartbeat] [node]' in line:
+                lines.append(line.split(maxsplit=2)[2].rstrip('\n'))
+    return sorted(lines)
+
+
+def main():
+    if len(sys.argv) < 3:
+        print("USAGE: {0} run1 run2 [run3 ...]".format(sys.argv[0]), file=sys.stderr)
+        exit(1)
+
+    runs = sys.argv[1:]
+    expected_fingerprints = fingerprints(runs[0])
+    expected_heartbeats = heartbeats(runs[0])
+    if not expected_heartbeats:
+        print("No heartbeats were logged in '{}'".format(runs[0]), file=sys.stderr)
+        exit(1)
+
+    differences = 0
+    for run in runs[1:]:
+        actual = fingerprints(run)
+        if actual.keys() != expected_fingerprints.keys():
+            print("The hosts of '{}' and '{}' differ".format(runs[0], run))
+            differences += 1
+        for host in sorted(expected_fingerprints.keys() & actual.keys()):
+            if actual[host] != expected_fingerprints[host]:
+                print("The packet fingerprints of host '{}' differ: '{}' in '{}' but '{}' in '{}'"
+                      .format(host, expected_fingerprints[host], runs[0], actual[host], run))
+                differences += 1
+
+        actual = heartbeats(run)
+        if actual != expected_heartbeats:
+            missing = sorted(set(expected_heartbeats) - set(actual))
+            extra = sorted(set(actual) - set(expected_heartbeats))
+            print("The heartbeats of '{}' and '{}' differ".format(runs[0], run))
+            for line in missing[:10]:
+                print("  only in '{}': {}".format(runs[0], line))
+            for line in extra[:10]:
+                print("  only in '{}': {}".format(run, line))
+            differences += 1
+
+    if differences > 0:
+        print("Found {} differences; the simulation may be nondeterministic".format(differences))
+        exit(1)
+
+    print("The {} runs were identical".format(len(runs)))
+
+
+if __name__ == '__main__':
+    main()
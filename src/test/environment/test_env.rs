@@ -1,4 +1,9 @@
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("--loader") {
+        check_loader_options();
+        return;
+    }
+
     let var = std::env::var("TESTING_ENV_VAR_1")
         .expect("Environment variable 'TESTING_ENV_VAR_1' not set");
     assert_eq!(var, "HELLO WORLD");
@@ -24,3 +29,16 @@ fn main() {
     let ld_preload = ld_preload.split(':');
     assert!(ld_preload.last().unwrap() == "/my/custom/ld/preload/path.so");
 }
+
+/// Check the effects of the process' `interpreter`, `library_path`, and `preload` options.
+fn check_loader_options() {
+    // the interpreter passes the executable's path as argv[0]
+    let argv0 = std::env::args().next().unwrap();
+    assert!(argv0.starts_with('/'), "{argv0}");
+    assert!(argv0.ends_with("/test_env"), "{argv0}");
+
+    // the `library_path` option comes before the `environment` option's value
+    let library_path =
+        std::env::var("LD_LIBRARY_PATH").expect("Environment variable 'LD_LIBRARY_PATH' not set");
+    assert_eq!(library_path, "/usr/lib:/tmp:/my/custom/lib");
+}
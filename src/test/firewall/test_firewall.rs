@@ -0,0 +1,53 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Connects to a server whose firewall allows, rejects, and denies connections to different
+//! ports. The arguments are the host's role: "server", or "client <server>".
+
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const ALLOWED_PORT: u16 = 80;
+const REJECTED_PORT: u16 = 22;
+const DENIED_PORT: u16 = 81;
+
+fn server() {
+    let listeners: Vec<TcpListener> = [ALLOWED_PORT, REJECTED_PORT, DENIED_PORT]
+        .into_iter()
+        .map(|port| TcpListener::bind(("0.0.0.0", port)).unwrap())
+        .collect();
+    loop {
+        // only connections to the allowed port get through the firewall
+        let _ = listeners[0].accept().unwrap();
+    }
+}
+
+fn connect(server: &str, port: u16) -> std::io::Result<TcpStream> {
+    let addr = (server, port).to_socket_addrs().unwrap().next().unwrap();
+    TcpStream::connect_timeout(&addr, Duration::from_secs(2))
+}
+
+fn client(server: &str) {
+    connect(server, ALLOWED_PORT).unwrap();
+
+    // the server's firewall replies with a RST
+    let err = connect(server, REJECTED_PORT).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+
+    // the server's firewall silently drops the SYNs
+    let err = connect(server, DENIED_PORT).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match &args[1..] {
+        ["server"] => server(),
+        ["client", server] => client(server),
+        _ => panic!("Unexpected arguments: {:?}", &args[1..]),
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// A component that uses Shadow's stable C API from inside of a simulation, like an out-of-tree
+// component would. It's called from the hooks in hooks.rs, which run on Shadow's worker threads.
+
+#include <stdio.h>
+#include <string.h>
+
+#include "main/core/shadow_api.h"
+
+// 2000-01-01, when simulations start
+#define SIMULATION_START_NS (946684800ULL * 1000000000ULL)
+
+#define CHECK(cond)                                                                                \
+    do {                                                                                           \
+        if (!(cond)) {                                                                             \
+            fprintf(stderr, "C API check failed: %s\n", #cond);                                    \
+            return false;                                                                          \
+        }                                                                                          \
+    } while (0)
+
+// Check the API's results for the running host, whose ID is `expected_host_id`.
+bool apicheck_current_host(uint32_t expected_host_id) {
+    uint32_t version = shadow_api_version();
+    CHECK(version >> 16 == SHADOW_API_VERSION_MAJOR);
+    CHECK(version >= SHADOW_API_VERSION);
+    CHECK(SHADOW_API_VERSION_AT_LEAST(1, 0));
+
+    uint32_t host_id = 0;
+    CHECK(shadow_api_host_id(&host_id));
+    CHECK(host_id == expected_host_id);
+
+    char name[16];
+    size_t len = shadow_api_host_name(name, sizeof(name));
+    CHECK(len == strlen(name));
+    CHECK(strcmp(name, "server") == 0 || strcmp(name, "client") == 0);
+
+    // the name is truncated but still nul-terminated
+    char short_name[4];
+    CHECK(shadow_api_host_name(short_name, sizeof(short_name)) == len);
+    CHECK(strncmp(short_name, name, 3) == 0 && short_name[3] == '\0');
+
+    uint32_t addr = 0;
+    CHECK(shadow_api_host_ipv4(&addr));
+    CHECK(addr != 0);
+
+    uint64_t now = 0;
+    CHECK(shadow_api_now_ns(&now));
+    CHECK(now > SIMULATION_START_NS);
+
+    unsigned char bytes[32] = {0};
+    unsigned char zeros[32] = {0};
+    CHECK(shadow_api_random_bytes(bytes, sizeof(bytes)));
+    CHECK(memcmp(bytes, zeros, sizeof(bytes)) != 0);
+
+    return true;
+}
+
+// Check that the API fails when no host is running.
+bool apicheck_no_host(void) {
+    uint32_t host_id = 0;
+    CHECK(!shadow_api_host_id(&host_id));
+
+    char name[16];
+    CHECK(shadow_api_host_name(name, sizeof(name)) == 0);
+
+    uint64_t now = 0;
+    CHECK(!shadow_api_now_ns(&now));
+
+    return true;
+}
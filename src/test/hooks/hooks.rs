@@ -0,0 +1,163 @@
+//! Simulation hooks that count the events of a simulation, and check the counts once the
+//! simulation has finished. This is linked into a copy of the shadow executable, since the hooks can
+//! only be registered through Shadow's Rust library.
+//!
+//! When a process starts, the hooks also run the C component in `api_check.c`, which checks the
+//! results of Shadow's stable C API for the running host.
+
+use std::net::SocketAddrV4;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use shadow_rs::core::hooks::{self, PacketInfo, SimulationHooks, TcpStateKind};
+use shadow_rs::host::process::{ExitStatus, ProcessId};
+use shadow_rs::host::thread::ThreadId;
+use shadow_shim_helper_rs::emulated_time::EmulatedTime;
+use shadow_shim_helper_rs::HostId;
+
+static COUNTS: OnceLock<Arc<Counts>> = OnceLock::new();
+
+extern "C" {
+    fn apicheck_current_host(expected_host_id: u32) -> bool;
+    fn apicheck_no_host() -> bool;
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    packets_enqueued: AtomicU64,
+    packets_dequeued: AtomicU64,
+    tcp_established: AtomicU64,
+    processes_started: AtomicU64,
+    processes_exited_successfully: AtomicU64,
+    syscalls_entered: AtomicU64,
+    syscalls_exited: AtomicU64,
+    api_checks_passed: AtomicU64,
+}
+
+fn increment(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+impl SimulationHooks for Counts {
+    fn packet_enqueued(&self, _host: HostId, _time: EmulatedTime, _packet: &PacketInfo) {
+        increment(&self.packets_enqueued);
+    }
+
+    fn packet_dequeued(&self, _host: HostId, _time: EmulatedTime, _packet: &PacketInfo) {
+        increment(&self.packets_dequeued);
+    }
+
+    fn tcp_state_changed(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _local: SocketAddrV4,
+        _peer: SocketAddrV4,
+        _old: TcpStateKind,
+        new: TcpStateKind,
+    ) {
+        if new == TcpStateKind::Established {
+            increment(&self.tcp_established);
+        }
+    }
+
+    fn process_started(&self, host: HostId, _time: EmulatedTime, _pid: ProcessId, _name: &str) {
+        increment(&self.processes_started);
+        if unsafe { apicheck_current_host(host.into()) } {
+            increment(&self.api_checks_passed);
+        }
+    }
+
+    fn process_exited(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _pid: ProcessId,
+        status: &ExitStatus,
+    ) {
+        if matches!(status, ExitStatus::Normal(0)) {
+            increment(&self.processes_exited_successfully);
+        }
+    }
+
+    fn syscall_enter(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _pid: ProcessId,
+        _tid: ThreadId,
+        _number: i64,
+    ) {
+        increment(&self.syscalls_entered);
+    }
+
+    fn syscall_exit(
+        &self,
+        _host: HostId,
+        _time: EmulatedTime,
+        _pid: ProcessId,
+        _tid: ThreadId,
+        _number: i64,
+        _retval: Option<i64>,
+    ) {
+        increment(&self.syscalls_exited);
+    }
+}
+
+/// Register the hooks. Must be called before the simulation starts.
+#[no_mangle]
+pub extern "C" fn hookstest_register() {
+    let counts = Arc::clone(COUNTS.get_or_init(Default::default));
+    hooks::register(counts);
+}
+
+/// Check the events that the hooks counted during the simulation of `hooks.yaml`, where a client
+/// makes a TCP connection and exchanges UDP datagrams with a server. Returns 0 if the counts are
+/// correct, and 1 otherwise.
+#[no_mangle]
+pub extern "C" fn hookstest_check() -> libc::c_int {
+    let counts = COUNTS.get().unwrap();
+    let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    eprintln!("{counts:?}");
+
+    let checks = [
+        ("packets were enqueued", get(&counts.packets_enqueued) > 0),
+        (
+            "every enqueued packet was dequeued",
+            get(&counts.packets_dequeued) == get(&counts.packets_enqueued),
+        ),
+        (
+            "both ends of the connection were established",
+            get(&counts.tcp_established) == 2,
+        ),
+        (
+            "both processes started",
+            get(&counts.processes_started) == 2,
+        ),
+        (
+            "both processes exited successfully",
+            get(&counts.processes_exited_successfully) == 2,
+        ),
+        ("syscalls were made", get(&counts.syscalls_exited) > 0),
+        (
+            "syscalls were entered at least as often as they completed",
+            get(&counts.syscalls_entered) >= get(&counts.syscalls_exited),
+        ),
+        (
+            "the C API worked for both processes' hosts",
+            get(&counts.api_checks_passed) == 2,
+        ),
+        ("the C API failed outside of the simulation", unsafe {
+            apicheck_no_host()
+        }),
+    ];
+
+    let mut rv = 0;
+    for (description, passed) in checks {
+        if !passed {
+            eprintln!("Check failed: {description}");
+            rv = 1;
+        }
+    }
+    rv
+}
@@ -0,0 +1,32 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+#include "lib/log-c2rust/log-c2rust.h"
+#include "lib/logger/logger.h"
+#include "main/bindings/c/bindings.h"
+
+// defined in hooks.rs
+void hookstest_register(void);
+int hookstest_check(void);
+
+// the same as shadow's main function, but registers the hooks before running the simulation and
+// checks them afterwards
+int main(int argc, char* argv[]) {
+    ShadowBuildInfo info = {
+        .version = "hooks-test",
+        .build = "hooks-test",
+        .info = "hooks-test",
+    };
+    logger_setDefault(rustlogger_new());
+
+    hookstest_register();
+
+    int rv = main_runShadow(&info, argc, (const char**)argv);
+    if (rv != 0) {
+        return rv;
+    }
+
+    return hookstest_check();
+}
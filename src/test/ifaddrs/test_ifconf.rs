@@ -0,0 +1,154 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Lists the network interfaces with the network device ioctls (see netdevice(7)), and checks that
+//! they agree with each other and with `getifaddrs()`.
+//!
+//! Arguments are either IP addresses that must be listed, or `<interface>=<hardware address>`
+//! pairs that the interface's hardware address must match.
+
+use std::net::Ipv4Addr;
+
+use linux_api::netdevice::{ifconf, ifreq, InterfaceFlags, ARPHRD_LOOPBACK};
+
+fn ioctl<T>(fd: libc::c_int, request: libc::c_ulong, arg: &mut T) -> std::io::Result<()> {
+    let rv = unsafe { libc::ioctl(fd, request, arg as *mut T) };
+    if rv != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn new_ifreq(name: &[u8]) -> ifreq {
+    let mut req: ifreq = unsafe { std::mem::zeroed() };
+    req.set_name(name);
+    req
+}
+
+/// The IPv4 address in the `sockaddr_in` in the request's union.
+fn ifreq_addr(req: &ifreq) -> Ipv4Addr {
+    assert_eq!(
+        req.ifr_ifru[..2],
+        u16::try_from(libc::AF_INET).unwrap().to_ne_bytes()
+    );
+    Ipv4Addr::new(
+        req.ifr_ifru[4],
+        req.ifr_ifru[5],
+        req.ifr_ifru[6],
+        req.ifr_ifru[7],
+    )
+}
+
+fn format_hw_addr(addr: &[u8]) -> String {
+    let octets: Vec<_> = addr.iter().map(|x| format!("{x:02x}")).collect();
+    octets.join(":")
+}
+
+fn main() {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    assert!(fd >= 0);
+
+    // get the size of the list
+    let mut conf = ifconf {
+        ifc_len: 0,
+        pad: 0,
+        ifc_buf: std::ptr::null_mut(),
+    };
+    ioctl(fd, libc::SIOCGIFCONF, &mut conf).unwrap();
+    let len = usize::try_from(conf.ifc_len).unwrap();
+    assert!(len > 0);
+    assert_eq!(len % std::mem::size_of::<ifreq>(), 0);
+
+    // get the list
+    let mut reqs = vec![new_ifreq(b""); len / std::mem::size_of::<ifreq>()];
+    conf.ifc_buf = reqs.as_mut_ptr() as *mut u8;
+    ioctl(fd, libc::SIOCGIFCONF, &mut conf).unwrap();
+    assert_eq!(usize::try_from(conf.ifc_len).unwrap(), len);
+
+    let mut interfaces: Vec<(String, Ipv4Addr)> = reqs
+        .iter()
+        .map(|req| {
+            let name = String::from_utf8(req.name().to_vec()).unwrap();
+            (name, ifreq_addr(req))
+        })
+        .collect();
+    interfaces.sort();
+
+    // the ipv4 interfaces from getifaddrs
+    let mut expected: Vec<(String, Ipv4Addr)> = nix::ifaddrs::getifaddrs()
+        .unwrap()
+        .filter_map(|ifaddr| {
+            let addr = *ifaddr.address?.as_sockaddr_in()?;
+            Some((ifaddr.interface_name, Ipv4Addr::from(addr.ip())))
+        })
+        .collect();
+    expected.sort();
+    assert_eq!(interfaces, expected);
+
+    for (name, addr) in &interfaces {
+        let mut req = new_ifreq(name.as_bytes());
+        ioctl(fd, libc::SIOCGIFINDEX, &mut req).unwrap();
+        let index = req.ifru_int();
+        assert!(index > 0);
+
+        // the index maps back to the name
+        let mut req = new_ifreq(b"");
+        req.set_ifru_int(index);
+        ioctl(fd, libc::SIOCGIFNAME, &mut req).unwrap();
+        assert_eq!(req.name(), name.as_bytes());
+
+        let mut req = new_ifreq(name.as_bytes());
+        ioctl(fd, libc::SIOCGIFADDR, &mut req).unwrap();
+        assert_eq!(ifreq_addr(&req), *addr);
+
+        let mut req = new_ifreq(name.as_bytes());
+        ioctl(fd, libc::SIOCGIFFLAGS, &mut req).unwrap();
+        let flags = InterfaceFlags::from_bits_retain(i16::from_ne_bytes(
+            req.ifr_ifru[..2].try_into().unwrap(),
+        ));
+        assert!(flags.contains(InterfaceFlags::IFF_UP));
+        assert_eq!(
+            flags.contains(InterfaceFlags::IFF_LOOPBACK),
+            addr.is_loopback()
+        );
+
+        let mut req = new_ifreq(name.as_bytes());
+        ioctl(fd, libc::SIOCGIFMTU, &mut req).unwrap();
+        assert!(req.ifru_int() > 0);
+
+        let mut req = new_ifreq(name.as_bytes());
+        ioctl(fd, libc::SIOCGIFHWADDR, &mut req).unwrap();
+        let hw_type = u16::from_ne_bytes(req.ifr_ifru[..2].try_into().unwrap());
+        assert_eq!(hw_type == ARPHRD_LOOPBACK, addr.is_loopback());
+        let hw_addr = format_hw_addr(&req.ifr_ifru[2..8]);
+
+        println!(
+            "found interface {name} ({index}) address {addr} hwaddr {hw_addr} flags {flags:?}"
+        );
+
+        for argument in std::env::args().skip(1) {
+            if let Some((arg_name, arg_hw_addr)) = argument.split_once('=') {
+                if arg_name == name {
+                    assert_eq!(arg_hw_addr, hw_addr);
+                }
+            }
+        }
+    }
+
+    // an interface that doesn't exist
+    let mut req = new_ifreq(b"nonexistent0");
+    let err = ioctl(fd, libc::SIOCGIFINDEX, &mut req).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENODEV));
+
+    for argument in std::env::args().skip(1) {
+        if argument.contains('=') {
+            continue;
+        }
+        let addr: Ipv4Addr = argument.parse().unwrap();
+        assert!(interfaces.iter().any(|(_, x)| *x == addr));
+    }
+
+    unsafe { libc::close(fd) };
+}
@@ -0,0 +1,256 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Lists the network interfaces, addresses, and routes with a `NETLINK_ROUTE` socket (see
+//! rtnetlink(7)), and checks that they agree with `getifaddrs()` and the network device ioctls.
+//!
+//! Arguments are IP addresses that must be listed.
+
+use std::net::Ipv4Addr;
+
+use linux_api::netlink::{
+    IFA_LABEL, IFA_LOCAL, IFLA_IFNAME, IFLA_MTU, NETLINK_ROUTE, NLMSG_DONE, NLMSG_ERROR, NLM_F_ACK,
+    NLM_F_DUMP, NLM_F_MULTI, NLM_F_REQUEST, RTA_DST, RTA_OIF, RTM_GETADDR, RTM_GETLINK,
+    RTM_GETROUTE, RTM_NEWADDR, RTM_NEWLINK, RTM_NEWROUTE,
+};
+use nix::sys::socket::{getsockname, NetlinkAddr};
+
+const HDRLEN: usize = 16;
+
+/// A response message's type, flags, and payload.
+struct Message {
+    msg_type: u16,
+    flags: u16,
+    payload: Vec<u8>,
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Send a request with the sequence number `seq`.
+fn send_request(fd: libc::c_int, msg_type: u16, flags: u16, seq: u32, payload: &[u8]) {
+    let len = u32::try_from(HDRLEN + payload.len()).unwrap();
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&len.to_ne_bytes());
+    msg.extend_from_slice(&msg_type.to_ne_bytes());
+    msg.extend_from_slice(&(NLM_F_REQUEST | flags).to_ne_bytes());
+    msg.extend_from_slice(&seq.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes());
+    msg.extend_from_slice(payload);
+
+    let rv = unsafe { libc::send(fd, msg.as_ptr() as *const libc::c_void, msg.len(), 0) };
+    assert_eq!(rv, isize::try_from(msg.len()).unwrap());
+}
+
+/// Receive the messages of a response to the request with the sequence number `seq`. A dump
+/// response may span several datagrams, and is read until its `NLMSG_DONE` message.
+fn recv_response(fd: libc::c_int, seq: u32, portid: u32) -> Vec<Message> {
+    let mut messages = Vec::new();
+
+    loop {
+        // get the size of the next datagram
+        let len = unsafe {
+            libc::recv(
+                fd,
+                std::ptr::null_mut(),
+                0,
+                libc::MSG_PEEK | libc::MSG_TRUNC,
+            )
+        };
+        let len = usize::try_from(len).unwrap();
+
+        let mut buf = vec![0u8; len];
+        let rv = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        assert_eq!(rv, isize::try_from(len).unwrap());
+
+        let mut data = &buf[..];
+        let mut is_multi = false;
+        while data.len() >= HDRLEN {
+            let msg_len = u32::from_ne_bytes(data[0..4].try_into().unwrap());
+            let msg_len = usize::try_from(msg_len).unwrap();
+            let msg_type = u16::from_ne_bytes(data[4..6].try_into().unwrap());
+            let flags = u16::from_ne_bytes(data[6..8].try_into().unwrap());
+            let msg_seq = u32::from_ne_bytes(data[8..12].try_into().unwrap());
+            let msg_pid = u32::from_ne_bytes(data[12..16].try_into().unwrap());
+
+            // responses are addressed to our socket
+            assert_eq!(msg_seq, seq);
+            assert_eq!(msg_pid, portid);
+
+            is_multi = flags & NLM_F_MULTI != 0;
+            messages.push(Message {
+                msg_type,
+                flags,
+                payload: data[HDRLEN..msg_len].to_vec(),
+            });
+            data = &data[std::cmp::min(align(msg_len), data.len())..];
+        }
+
+        if !is_multi || messages.last().unwrap().msg_type == NLMSG_DONE {
+            return messages;
+        }
+    }
+}
+
+/// The data of each attribute in `attrs`.
+fn attrs(mut attrs: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut list = Vec::new();
+    while attrs.len() >= 4 {
+        let len = usize::from(u16::from_ne_bytes(attrs[0..2].try_into().unwrap()));
+        let attr_type = u16::from_ne_bytes(attrs[2..4].try_into().unwrap());
+        list.push((attr_type & 0x3fff, &attrs[4..len]));
+        attrs = &attrs[std::cmp::min(align(len), attrs.len())..];
+    }
+    list
+}
+
+fn find_attr(attrs: &[(u16, &[u8])], attr_type: u16) -> Option<Vec<u8>> {
+    attrs
+        .iter()
+        .find(|(x, _)| *x == attr_type)
+        .map(|(_, data)| data.to_vec())
+}
+
+fn nul_terminated_str(data: &[u8]) -> String {
+    let data = data.split(|x| *x == 0).next().unwrap();
+    String::from_utf8(data.to_vec()).unwrap()
+}
+
+fn interface_index(name: &str) -> i32 {
+    let name = std::ffi::CString::new(name).unwrap();
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    assert_ne!(index, 0);
+    index.try_into().unwrap()
+}
+
+/// The negated errno of an `NLMSG_ERROR` message, which is 0 for an acknowledgement.
+fn error_code(msg: &Message) -> i32 {
+    assert_eq!(msg.msg_type, NLMSG_ERROR);
+    i32::from_ne_bytes(msg.payload[..4].try_into().unwrap())
+}
+
+fn main() {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            NETLINK_ROUTE,
+        )
+    };
+    assert!(fd >= 0);
+
+    // bind to a port id chosen by the kernel
+    let addr = NetlinkAddr::new(0, 0);
+    nix::sys::socket::bind(fd, &addr).unwrap();
+    let portid = getsockname::<NetlinkAddr>(fd).unwrap().pid();
+    assert_ne!(portid, 0);
+
+    // the links
+    send_request(fd, RTM_GETLINK, NLM_F_DUMP, 1, &[libc::AF_UNSPEC as u8]);
+    let messages = recv_response(fd, 1, portid);
+    assert_eq!(messages.last().unwrap().msg_type, NLMSG_DONE);
+
+    let mut links = Vec::new();
+    for msg in &messages[..messages.len() - 1] {
+        assert_eq!(msg.msg_type, RTM_NEWLINK);
+        assert_ne!(msg.flags & NLM_F_MULTI, 0);
+
+        let index = i32::from_ne_bytes(msg.payload[4..8].try_into().unwrap());
+        let attrs = attrs(&msg.payload[16..]);
+        let name = nul_terminated_str(&find_attr(&attrs, IFLA_IFNAME).unwrap());
+        let mtu = u32::from_ne_bytes(find_attr(&attrs, IFLA_MTU).unwrap().try_into().unwrap());
+        assert!(mtu > 0);
+
+        // the index agrees with the network device ioctls
+        assert_eq!(interface_index(&name), index);
+        println!("found link {name} ({index}) with mtu {mtu}");
+        links.push((index, name));
+    }
+    assert!(links.iter().any(|(_, name)| name == "lo"));
+
+    // the ipv4 addresses
+    let ifaddrmsg = [libc::AF_INET as u8, 0, 0, 0, 0, 0, 0, 0];
+    send_request(fd, RTM_GETADDR, NLM_F_DUMP, 2, &ifaddrmsg);
+    let messages = recv_response(fd, 2, portid);
+    assert_eq!(messages.last().unwrap().msg_type, NLMSG_DONE);
+
+    let mut addrs = Vec::new();
+    for msg in &messages[..messages.len() - 1] {
+        assert_eq!(msg.msg_type, RTM_NEWADDR);
+        assert_eq!(msg.payload[0], libc::AF_INET as u8);
+
+        let index = i32::from_ne_bytes(msg.payload[4..8].try_into().unwrap());
+        let attrs = attrs(&msg.payload[8..]);
+        let local = find_attr(&attrs, IFA_LOCAL).unwrap();
+        let addr = Ipv4Addr::from(<[u8; 4]>::try_from(local).unwrap());
+        let label = nul_terminated_str(&find_attr(&attrs, IFA_LABEL).unwrap());
+
+        assert!(links.iter().any(|(x, _)| *x == index));
+        println!("found address {addr}/{} on {label}", msg.payload[1]);
+        addrs.push((label, addr));
+    }
+    addrs.sort();
+
+    // the addresses agree with getifaddrs()
+    let mut expected: Vec<(String, Ipv4Addr)> = nix::ifaddrs::getifaddrs()
+        .unwrap()
+        .filter_map(|ifaddr| {
+            let addr = *ifaddr.address?.as_sockaddr_in()?;
+            Some((ifaddr.interface_name, Ipv4Addr::from(addr.ip())))
+        })
+        .collect();
+    expected.sort();
+    assert_eq!(addrs, expected);
+
+    for argument in std::env::args().skip(1) {
+        let addr: Ipv4Addr = argument.parse().unwrap();
+        assert!(addrs.iter().any(|(_, x)| *x == addr));
+    }
+
+    // the route to localhost is through the loopback interface
+    let mut rtmsg = vec![libc::AF_INET as u8, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rtmsg.extend_from_slice(&8u16.to_ne_bytes());
+    rtmsg.extend_from_slice(&RTA_DST.to_ne_bytes());
+    rtmsg.extend_from_slice(&Ipv4Addr::LOCALHOST.octets());
+    send_request(fd, RTM_GETROUTE, 0, 3, &rtmsg);
+    let messages = recv_response(fd, 3, portid);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].msg_type, RTM_NEWROUTE);
+    let attrs = attrs(&messages[0].payload[12..]);
+    let oif = i32::from_ne_bytes(find_attr(&attrs, RTA_OIF).unwrap().try_into().unwrap());
+    assert_eq!(oif, interface_index("lo"));
+
+    // a link that doesn't exist, with an acknowledgement requested
+    let mut ifinfomsg = [0u8; 16];
+    ifinfomsg[4..8].copy_from_slice(&12345i32.to_ne_bytes());
+    send_request(fd, RTM_GETLINK, NLM_F_ACK, 4, &ifinfomsg);
+    let messages = recv_response(fd, 4, portid);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(error_code(&messages[0]), -libc::ENODEV);
+
+    // a successful request with an acknowledgement
+    let mut ifinfomsg = [0u8; 16];
+    ifinfomsg[4..8].copy_from_slice(&interface_index("lo").to_ne_bytes());
+    send_request(fd, RTM_GETLINK, NLM_F_ACK, 5, &ifinfomsg);
+    let mut messages = recv_response(fd, 5, portid);
+    if messages.len() == 1 {
+        // the ack may be in a separate datagram
+        messages.extend(recv_response(fd, 5, portid));
+    }
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].msg_type, RTM_NEWLINK);
+    assert_eq!(error_code(&messages[1]), 0);
+
+    // nothing else is waiting
+    let rv = unsafe { libc::recv(fd, std::ptr::null_mut(), 0, libc::MSG_DONTWAIT) };
+    assert_eq!(rv, -1);
+    assert_eq!(
+        std::io::Error::last_os_error().raw_os_error(),
+        Some(libc::EAGAIN)
+    );
+
+    unsafe { libc::close(fd) };
+}
@@ -0,0 +1,197 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Uses `AF_INET6` sockets on the loopback interface and between hosts.
+//!
+//! The first argument is the mode: "local", "server", or "client". A client also takes the name of
+//! the server's host.
+
+use std::io::{Read, Write};
+use std::net::{
+    IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6, TcpListener, TcpStream, ToSocketAddrs, UdpSocket,
+};
+use std::os::fd::AsRawFd;
+
+const TCP_PORT: u16 = 8080;
+const UDP_PORT: u16 = 8081;
+
+fn get_int_option(fd: libc::c_int, level: libc::c_int, optname: libc::c_int) -> libc::c_int {
+    let mut val: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&val) as libc::socklen_t;
+    let rv = unsafe {
+        libc::getsockopt(
+            fd,
+            level,
+            optname,
+            &mut val as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(rv, 0);
+    val
+}
+
+fn set_int_option(fd: libc::c_int, level: libc::c_int, optname: libc::c_int, val: libc::c_int) {
+    let rv = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            optname,
+            &val as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&val) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+}
+
+/// Bind a new socket of the given type to the IPv6 address, and return the errno.
+fn bind_errno(socket_type: libc::c_int, addr: SocketAddrV6, v6only: bool) -> libc::c_int {
+    let fd = unsafe { libc::socket(libc::AF_INET6, socket_type, 0) };
+    assert!(fd >= 0);
+
+    if v6only {
+        set_int_option(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, 1);
+    }
+
+    let sockaddr = libc::sockaddr_in6 {
+        sin6_family: libc::AF_INET6 as libc::sa_family_t,
+        sin6_port: addr.port().to_be(),
+        sin6_flowinfo: 0,
+        sin6_addr: libc::in6_addr {
+            s6_addr: addr.ip().octets(),
+        },
+        sin6_scope_id: 0,
+    };
+    let rv = unsafe {
+        libc::bind(
+            fd,
+            &sockaddr as *const _ as *const libc::sockaddr,
+            std::mem::size_of_val(&sockaddr) as libc::socklen_t,
+        )
+    };
+    let errno = if rv == 0 { 0 } else { test_utils::get_errno() };
+
+    unsafe { libc::close(fd) };
+    errno
+}
+
+fn local() {
+    // udp over the loopback interface
+    let socket = UdpSocket::bind("[::1]:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    assert_eq!(addr.ip(), IpAddr::V6(Ipv6Addr::LOCALHOST));
+    assert_ne!(addr.port(), 0);
+
+    let fd = socket.as_raw_fd();
+    assert_eq!(
+        get_int_option(fd, libc::SOL_SOCKET, libc::SO_DOMAIN),
+        libc::AF_INET6
+    );
+
+    socket.send_to(b"hello", addr).unwrap();
+    let mut buf = [0u8; 16];
+    let (len, src) = socket.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"hello");
+    assert_eq!(src, addr);
+
+    // tcp over the loopback interface
+    let listener = TcpListener::bind("[::1]:0").unwrap();
+    let listener_addr = listener.local_addr().unwrap();
+    let mut client = TcpStream::connect(listener_addr).unwrap();
+    let (mut server, peer_addr) = listener.accept().unwrap();
+    assert_eq!(peer_addr, client.local_addr().unwrap());
+    assert_eq!(client.peer_addr().unwrap(), listener_addr);
+    assert!(peer_addr.is_ipv6());
+
+    client.write_all(b"hello").unwrap();
+    let mut buf = [0u8; 5];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    // a dual-stack socket also accepts connections to its IPv4 address
+    let listener = TcpListener::bind("[::]:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let _client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let (_server, peer_addr) = listener.accept().unwrap();
+    assert!(peer_addr.is_ipv6());
+
+    // the IPV6_V6ONLY option
+    let fd = listener.as_raw_fd();
+    assert_eq!(get_int_option(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY), 0);
+    set_int_option(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, 1);
+    assert_eq!(get_int_option(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY), 1);
+
+    // an IPv4-mapped address can't be used with IPV6_V6ONLY
+    let mapped = SocketAddrV6::new("::ffff:127.0.0.1".parse().unwrap(), 0, 0, 0);
+    assert_eq!(bind_errno(libc::SOCK_DGRAM, mapped, false), 0);
+    assert_eq!(bind_errno(libc::SOCK_DGRAM, mapped, true), libc::EINVAL);
+
+    // an address that doesn't belong to the host
+    let other = SocketAddrV6::new("2001:db8::1".parse().unwrap(), 0, 0, 0);
+    assert_eq!(
+        bind_errno(libc::SOCK_STREAM, other, false),
+        libc::EADDRNOTAVAIL
+    );
+}
+
+fn server() {
+    let listener = TcpListener::bind(("::", TCP_PORT)).unwrap();
+    let udp = UdpSocket::bind(("::", UDP_PORT)).unwrap();
+
+    let (mut stream, peer_addr) = listener.accept().unwrap();
+    let SocketAddr::V6(peer_addr) = peer_addr else {
+        panic!("Expected an IPv6 peer address, got {peer_addr}");
+    };
+    assert!(!peer_addr.ip().is_loopback());
+    println!("accepted a connection from {peer_addr}");
+
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).unwrap();
+    stream.write_all(&buf).unwrap();
+
+    let mut buf = [0u8; 16];
+    let (len, src) = udp.recv_from(&mut buf).unwrap();
+    // the client's tcp and udp sockets have the same IPv6 address
+    assert_eq!(src.ip(), IpAddr::V6(*peer_addr.ip()));
+    udp.send_to(&buf[..len], src).unwrap();
+}
+
+fn client(server_name: &str) {
+    // name resolution returns the server's IPv6 address after its IPv4 address
+    let addrs: Vec<SocketAddr> = (server_name, TCP_PORT).to_socket_addrs().unwrap().collect();
+    println!("{server_name} resolved to {addrs:?}");
+    assert!(addrs.iter().any(SocketAddr::is_ipv4));
+    let Some(SocketAddr::V6(server_addr)) = addrs.into_iter().find(SocketAddr::is_ipv6) else {
+        panic!("No IPv6 address for {server_name}");
+    };
+
+    // the hosts' IPv6 addresses are in fd00::/96
+    assert_eq!(server_addr.ip().segments()[..6], [0xfd00, 0, 0, 0, 0, 0]);
+
+    let mut stream = TcpStream::connect(server_addr).unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), SocketAddr::V6(server_addr));
+    stream.write_all(b"hello").unwrap();
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    let udp = UdpSocket::bind("[::]:0").unwrap();
+    let udp_server_addr = SocketAddrV6::new(*server_addr.ip(), UDP_PORT, 0, 0);
+    udp.send_to(b"world", udp_server_addr).unwrap();
+    let mut buf = [0u8; 16];
+    let (len, src) = udp.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"world");
+    assert_eq!(src, SocketAddr::V6(udp_server_addr));
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("local") => local(),
+        Some("server") => server(),
+        Some("client") => client(&args[2]),
+        x => panic!("Unknown mode {x:?}"),
+    }
+}
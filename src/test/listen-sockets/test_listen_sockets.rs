@@ -0,0 +1,77 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Uses the sockets that Shadow passes to a process with the systemd socket activation protocol.
+//!
+//! The first argument is the mode: "server", "client", or "bind". A client also takes the name of
+//! the server's host, and "bind" takes the TCP port that it binds before sleeping.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::os::fd::FromRawFd;
+use std::time::Duration;
+
+const SD_LISTEN_FDS_START: i32 = 3;
+const TCP_PORT: u16 = 8080;
+const UDP_PORT: u16 = 5353;
+
+fn server() {
+    let var = |name| std::env::var(name).unwrap();
+    assert_eq!(var("LISTEN_FDS"), "2");
+    assert_eq!(var("LISTEN_PID"), std::process::id().to_string());
+    assert_eq!(var("LISTEN_FDNAMES"), "http:dns");
+
+    let listener = unsafe { TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    let udp = unsafe { UdpSocket::from_raw_fd(SD_LISTEN_FDS_START + 1) };
+
+    // the sockets are bound to all of the host's addresses
+    let addr = listener.local_addr().unwrap();
+    assert!(addr.ip().is_unspecified());
+    assert_eq!(addr.port(), TCP_PORT);
+    let addr = udp.local_addr().unwrap();
+    assert!(addr.ip().is_unspecified());
+    assert_eq!(addr.port(), UDP_PORT);
+
+    // the tcp socket is already listening
+    let (mut stream, peer_addr) = listener.accept().unwrap();
+    println!("accepted a connection from {peer_addr}");
+
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).unwrap();
+    stream.write_all(&buf).unwrap();
+
+    let mut buf = [0u8; 16];
+    let (len, src) = udp.recv_from(&mut buf).unwrap();
+    udp.send_to(&buf[..len], src).unwrap();
+}
+
+fn client(server_name: &str) {
+    let mut stream = TcpStream::connect((server_name, TCP_PORT)).unwrap();
+    stream.write_all(b"hello").unwrap();
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    let udp = UdpSocket::bind("0.0.0.0:0").unwrap();
+    udp.send_to(b"world", (server_name, UDP_PORT)).unwrap();
+    let mut buf = [0u8; 16];
+    let (len, _src) = udp.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"world");
+}
+
+fn bind(port: u16) {
+    let _listener = TcpListener::bind(("0.0.0.0", port)).unwrap();
+    std::thread::sleep(Duration::from_secs(5));
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("server") => server(),
+        Some("client") => client(&args[2]),
+        Some("bind") => bind(args[2].parse().unwrap()),
+        x => panic!("Unknown mode {x:?}"),
+    }
+}
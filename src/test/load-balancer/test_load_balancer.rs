@@ -0,0 +1,53 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Connects to backends through a load balancer. The arguments are the host's role and the role's
+//! arguments:
+//!
+//! - "backend <name> <port>": Accepts connections on the port and replies with the name.
+//! - "client <address> <name>...": Connects to the address once for each given name, and checks
+//!   that the connections reach the backends with those names in order.
+//! - "client-same <address> <count>": Connects to the address `count` times, and checks that the
+//!   connections all reach the same backend.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+fn backend(name: &str, port: u16) {
+    let listener = TcpListener::bind(("0.0.0.0", port)).unwrap();
+    loop {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(name.as_bytes()).unwrap();
+    }
+}
+
+/// Connect to the load balancer at `addr` and return the name of the backend that replied.
+fn connect(addr: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut name = String::new();
+    stream.read_to_string(&mut name).unwrap();
+    name
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match &args[1..] {
+        ["backend", name, port] => backend(name, port.parse().unwrap()),
+        ["client", addr, expected @ ..] => {
+            let names: Vec<String> = expected.iter().map(|_| connect(addr)).collect();
+            assert_eq!(names, expected);
+        }
+        ["client-same", addr, count] => {
+            let count: usize = count.parse().unwrap();
+            let names: Vec<String> = (0..count).map(|_| connect(addr)).collect();
+            assert!(
+                names.iter().all(|x| *x == names[0]),
+                "Connections reached different backends: {names:?}"
+            );
+        }
+        _ => panic!("Unexpected arguments: {:?}", &args[1..]),
+    }
+}
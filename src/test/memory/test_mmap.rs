@@ -384,6 +384,145 @@ fn test_mmap_mprotect_exe() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Memory that is given back with `MADV_DONTNEED` must read as zeros afterwards, which allocators
+/// such as jemalloc rely on.
+fn test_madvise_dontneed() -> Result<(), Box<dyn Error>> {
+    let size = 3 * page_size();
+    let buf_ptr = mmap_and_init_buf(size);
+    let buf = unsafe { std::slice::from_raw_parts_mut::<u8>(buf_ptr as *mut u8, size) };
+
+    let rv = unsafe { libc::madvise(buf_ptr.add(page_size()), page_size(), libc::MADV_DONTNEED) };
+    test_utils::assert_true_else_errno(rv == 0);
+
+    check_buf(&buf[..page_size()]);
+    assert!(buf[page_size()..2 * page_size()].iter().all(|x| *x == 0));
+    check_buf(&buf[2 * page_size()..]);
+    validate_shadow_access(buf)?;
+
+    // the memory is still usable
+    init_buf(buf);
+    check_buf(buf);
+    validate_shadow_access(buf)?;
+
+    // a length that overflows when rounded up to a multiple of the page size is invalid
+    let rv = unsafe { libc::madvise(buf_ptr, usize::MAX, libc::MADV_DONTNEED) };
+    assert_eq!(rv, -1);
+    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::EINVAL);
+    check_buf(buf);
+
+    let rv = unsafe { libc::munmap(buf_ptr, size) };
+    nix::errno::Errno::result(rv)?;
+
+    Ok(())
+}
+
+fn test_madvise_free() -> Result<(), Box<dyn Error>> {
+    let size = 2 * page_size();
+    let buf_ptr = mmap_and_init_buf(size);
+    let buf = unsafe { std::slice::from_raw_parts_mut::<u8>(buf_ptr as *mut u8, size) };
+
+    let rv = unsafe { libc::madvise(buf_ptr, size, libc::MADV_FREE) };
+    test_utils::assert_true_else_errno(rv == 0);
+
+    // the pages may or may not have been freed, but are usable again once written
+    init_buf(buf);
+    check_buf(buf);
+    validate_shadow_access(buf)?;
+
+    let rv = unsafe { libc::munmap(buf_ptr, size) };
+    nix::errno::Errno::result(rv)?;
+
+    Ok(())
+}
+
+fn test_madvise_hugepage() -> Result<(), Box<dyn Error>> {
+    // the kernel only accepts the advice if it supports transparent huge pages
+    if !std::path::Path::new("/sys/kernel/mm/transparent_hugepage").exists() {
+        return Ok(());
+    }
+
+    let size = 4 * (1 << 20);
+    let buf_ptr = mmap_and_init_buf(size);
+    let buf = unsafe { std::slice::from_raw_parts::<u8>(buf_ptr as *const u8, size) };
+
+    for advice in [libc::MADV_HUGEPAGE, libc::MADV_NOHUGEPAGE] {
+        let rv = unsafe { libc::madvise(buf_ptr, size, advice) };
+        test_utils::assert_true_else_errno(rv == 0);
+    }
+    check_buf(buf);
+
+    let rv = unsafe { libc::munmap(buf_ptr, size) };
+    nix::errno::Errno::result(rv)?;
+
+    Ok(())
+}
+
+/// Move and grow only the end of a mapping, as allocators do when resizing part of a larger
+/// reservation.
+fn test_mremap_partial() -> Result<(), Box<dyn Error>> {
+    let buf_ptr = mmap_and_init_buf(4 * page_size());
+
+    let old_ptr = unsafe { buf_ptr.add(2 * page_size()) };
+    let new_ptr = unsafe {
+        libc::mremap(
+            old_ptr,
+            2 * page_size(),
+            4 * page_size(),
+            libc::MREMAP_MAYMOVE,
+        )
+    };
+    test_utils::assert_true_else_errno(new_ptr != libc::MAP_FAILED);
+
+    // the start of the original mapping is untouched
+    let buf = unsafe { std::slice::from_raw_parts::<u8>(buf_ptr as *const u8, 2 * page_size()) };
+    check_buf(buf);
+    validate_shadow_access(buf)?;
+
+    // the moved pages kept their contents, and the added pages are zeroed
+    let moved = unsafe { std::slice::from_raw_parts::<u8>(new_ptr as *const u8, 4 * page_size()) };
+    assert!(moved[..page_size()].iter().all(|x| *x == 2));
+    assert!(moved[page_size()..2 * page_size()].iter().all(|x| *x == 3));
+    assert!(moved[2 * page_size()..].iter().all(|x| *x == 0));
+    validate_shadow_access(moved)?;
+
+    let rv = unsafe { libc::munmap(new_ptr, 4 * page_size()) };
+    nix::errno::Errno::result(rv)?;
+    let rv = unsafe { libc::munmap(buf_ptr, 2 * page_size()) };
+    nix::errno::Errno::result(rv)?;
+
+    Ok(())
+}
+
+/// The kernel rounds lengths up to a whole number of pages.
+fn test_mprotect_munmap_partial_page() -> Result<(), Box<dyn Error>> {
+    let size = 3 * page_size();
+    let buf_ptr = mmap_and_init_buf(size);
+
+    // make the whole middle page read-only, and then writable again
+    let middle = unsafe { buf_ptr.add(page_size()) };
+    let rv = unsafe { libc::mprotect(middle, 1, libc::PROT_READ) };
+    test_utils::assert_true_else_errno(rv == 0);
+    let buf = unsafe { std::slice::from_raw_parts::<u8>(buf_ptr as *const u8, size) };
+    check_buf(buf);
+    validate_shadow_access(buf)?;
+    let rv = unsafe { libc::mprotect(middle, page_size() - 1, libc::PROT_READ | libc::PROT_WRITE) };
+    test_utils::assert_true_else_errno(rv == 0);
+
+    // unmap the whole last page
+    let rv = unsafe { libc::munmap(buf_ptr.add(2 * page_size()), 1) };
+    nix::errno::Errno::result(rv)?;
+
+    let buf = unsafe { std::slice::from_raw_parts_mut::<u8>(buf_ptr as *mut u8, 2 * page_size()) };
+    init_buf(buf);
+    check_buf(buf);
+    validate_shadow_access(buf)?;
+
+    let rv = unsafe { libc::munmap(buf_ptr, 2 * page_size()) };
+    nix::errno::Errno::result(rv)?;
+
+    Ok(())
+}
+
 fn test_mmap_file_low(unlink_before_mmap: bool) -> Result<(), Box<dyn Error>> {
     test_mmap_file(0, unlink_before_mmap)
 }
@@ -439,12 +578,146 @@ fn test_mmap_nofollow_file() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Map the first `len` bytes of the file `fd` shared.
+fn mmap_shared(fd: libc::c_int, len: usize) -> *mut libc::c_void {
+    let mapbuf = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        )
+    };
+    test_utils::assert_true_else_errno(mapbuf != libc::MAP_FAILED);
+    mapbuf
+}
+
+/// Create an unlinked temporary file of `len` bytes, and map it shared.
+fn mmap_shared_temp_file(len: usize) -> Result<(libc::c_int, *mut libc::c_void), Box<dyn Error>> {
+    let template = b"test_mmapXXXXXX";
+    let (temp_fd, path) = nix::unistd::mkstemp(template.as_ref())?;
+    nix::unistd::unlink(&path)?;
+    nix::unistd::ftruncate(temp_fd, len as libc::off_t)?;
+
+    Ok((temp_fd, mmap_shared(temp_fd, len)))
+}
+
+/// Writes through a shared file mapping and through `write` must be visible to each other without
+/// unmapping the file, like a database that writes pages with `pwrite` and reads them through a
+/// mapping.
+fn test_mmap_file_coherence() -> Result<(), Box<dyn Error>> {
+    let (temp_fd, mapbuf) = mmap_shared_temp_file(page_size())?;
+    let map = unsafe { std::slice::from_raw_parts_mut::<u8>(mapbuf as *mut u8, page_size()) };
+
+    // a write to the file is visible through the mapping
+    let msg = b"Hello new world!";
+    nix::sys::uio::pwrite(temp_fd, msg, 100)?;
+    assert_eq!(&map[100..(100 + msg.len())], msg);
+
+    // a write through the mapping is visible to a read of the file
+    map[200..(200 + msg.len())].copy_from_slice(msg);
+    let mut rdbuf = [0_u8; MAPLEN];
+    nix::sys::uio::pread(temp_fd, &mut rdbuf, 200)?;
+    assert_eq!(&rdbuf, msg);
+
+    // a second mapping of the file sees the same data
+    let mapbuf2 = mmap_shared(temp_fd, page_size());
+    let map2 = unsafe { std::slice::from_raw_parts::<u8>(mapbuf2 as *const u8, page_size()) };
+    assert_eq!(&map[..], map2);
+
+    for flags in [
+        libc::MS_SYNC,
+        libc::MS_ASYNC,
+        libc::MS_SYNC | libc::MS_INVALIDATE,
+    ] {
+        let rv = unsafe { libc::msync(mapbuf, page_size(), flags) };
+        nix::errno::Errno::result(rv)?;
+    }
+
+    let rv = unsafe { libc::munmap(mapbuf2, page_size()) };
+    nix::errno::Errno::result(rv)?;
+    let rv = unsafe { libc::munmap(mapbuf, page_size()) };
+    nix::errno::Errno::result(rv)?;
+    nix::unistd::close(temp_fd)?;
+
+    Ok(())
+}
+
+/// Writes by another process, through its own mapping of the file or through `write`, must be
+/// visible through a shared file mapping.
+fn test_mmap_file_coherence_other_process() -> Result<(), Box<dyn Error>> {
+    let (temp_fd, mapbuf) = mmap_shared_temp_file(page_size())?;
+    let map = unsafe { std::slice::from_raw_parts::<u8>(mapbuf as *const u8, page_size()) };
+    let msg = b"Hello new world!";
+
+    let child = match unsafe { nix::unistd::fork() }? {
+        nix::unistd::ForkResult::Child => {
+            // replace the inherited mapping so that the child writes through its own mapping
+            let rv = unsafe { libc::munmap(mapbuf, page_size()) };
+            assert_eq!(rv, 0);
+            let child_mapbuf = mmap_shared(temp_fd, page_size());
+            let child_map = unsafe {
+                std::slice::from_raw_parts_mut::<u8>(child_mapbuf as *mut u8, page_size())
+            };
+            child_map[..msg.len()].copy_from_slice(msg);
+            let rv = unsafe { libc::msync(child_mapbuf, page_size(), libc::MS_SYNC) };
+            assert_eq!(rv, 0);
+            nix::sys::uio::pwrite(temp_fd, msg, 300).unwrap();
+            unsafe { libc::_exit(0) };
+        }
+        nix::unistd::ForkResult::Parent { child } => child,
+    };
+
+    let status = nix::sys::wait::waitpid(child, None)?;
+    assert_eq!(status, nix::sys::wait::WaitStatus::Exited(child, 0));
+
+    assert_eq!(&map[..msg.len()], msg);
+    assert_eq!(&map[300..(300 + msg.len())], msg);
+
+    let rv = unsafe { libc::munmap(mapbuf, page_size()) };
+    nix::errno::Errno::result(rv)?;
+    nix::unistd::close(temp_fd)?;
+
+    Ok(())
+}
+
+fn test_msync_errors() -> Result<(), Box<dyn Error>> {
+    let (temp_fd, mapbuf) = mmap_shared_temp_file(2 * page_size())?;
+
+    // MS_SYNC and MS_ASYNC are mutually exclusive
+    let rv = unsafe { libc::msync(mapbuf, page_size(), libc::MS_SYNC | libc::MS_ASYNC) };
+    assert_eq!(rv, -1);
+    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::EINVAL);
+
+    // the address must be page-aligned
+    let rv = unsafe { libc::msync(mapbuf.add(1), page_size(), libc::MS_SYNC) };
+    assert_eq!(rv, -1);
+    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::EINVAL);
+
+    // the range must be mapped
+    let rv = unsafe { libc::munmap(mapbuf.add(page_size()), page_size()) };
+    nix::errno::Errno::result(rv)?;
+    let rv = unsafe { libc::msync(mapbuf, 2 * page_size(), libc::MS_SYNC) };
+    assert_eq!(rv, -1);
+    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::ENOMEM);
+
+    let rv = unsafe { libc::munmap(mapbuf, page_size()) };
+    nix::errno::Errno::result(rv)?;
+    nix::unistd::close(temp_fd)?;
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     // should we restrict the tests we run?
     let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
     let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
     // should we summarize the results rather than exit on a failed test
     let summarize = std::env::args().any(|x| x == "--summarize");
+    // is shadow's memory manager remapping our memory?
+    let memory_manager = std::env::args().any(|x| x == "--memory-manager");
 
     let mut tests: Vec<test_utils::ShadowTest<_, _>> = vec![
         test_utils::ShadowTest::new(
@@ -487,11 +760,51 @@ fn main() -> Result<(), Box<dyn Error>> {
             test_mmap_mprotect_exe,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_madvise_dontneed",
+            test_madvise_dontneed,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_madvise_free",
+            test_madvise_free,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_madvise_hugepage",
+            test_madvise_hugepage,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_mremap_partial",
+            test_mremap_partial,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_mprotect_munmap_partial_page",
+            test_mprotect_munmap_partial_page,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
         test_utils::ShadowTest::new(
             "test_mmap_nofollow_file",
             test_mmap_nofollow_file,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_mmap_file_coherence",
+            test_mmap_file_coherence,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_mmap_file_coherence_other_process",
+            test_mmap_file_coherence_other_process,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_msync_errors",
+            test_msync_errors,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     for &unlink_before_mmap in [false, true].iter() {
@@ -527,6 +840,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     if filter_libc_passing {
         tests.retain(|x| x.passing(TestEnv::Libc));
     }
+    if memory_manager {
+        // the memory manager doesn't support fork
+        tests.retain(|x| x.name() != "test_mmap_file_coherence_other_process");
+    }
 
     test_utils::run_tests(&tests, summarize)?;
 
@@ -0,0 +1,131 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Sends and receives UDP multicast packets between hosts attached to the same switch.
+//!
+//! The only argument is the host's role: "sender", "member", "members", or "non-member".
+
+use std::net::{Ipv4Addr, UdpSocket};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::time::Duration;
+
+use nix::sys::socket::{self, sockopt, AddressFamily, SockFlag, SockType, SockaddrIn};
+
+const GROUP: Ipv4Addr = Ipv4Addr::new(239, 1, 2, 3);
+const OTHER_GROUP: Ipv4Addr = Ipv4Addr::new(239, 1, 2, 4);
+const ALL_HOSTS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 1);
+const PORT: u16 = 5000;
+
+fn errno<T: std::fmt::Debug>(result: std::io::Result<T>) -> i32 {
+    result.unwrap_err().raw_os_error().unwrap()
+}
+
+fn recv(socket: &UdpSocket) -> Vec<u8> {
+    let mut buf = [0u8; 64];
+    let (len, _src) = socket.recv_from(&mut buf).unwrap();
+    buf[..len].to_vec()
+}
+
+/// Bind a UDP socket with `SO_REUSEADDR` to the port on all interfaces.
+fn bind_reuse_addr(port: u16) -> UdpSocket {
+    let fd = socket::socket(
+        AddressFamily::Inet,
+        SockType::Datagram,
+        SockFlag::empty(),
+        None,
+    )
+    .unwrap();
+    socket::setsockopt(fd, sockopt::ReuseAddr, &true).unwrap();
+    socket::bind(fd, &SockaddrIn::new(0, 0, 0, 0, port)).unwrap();
+    unsafe { UdpSocket::from_raw_fd(fd) }
+}
+
+fn sender() {
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+
+    // sending to a multicast group doesn't require `SO_BROADCAST`
+    socket.send_to(b"first", (GROUP, PORT)).unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    socket.send_to(b"all", (ALL_HOSTS_GROUP, PORT)).unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    socket.send_to(b"second", (GROUP, PORT)).unwrap();
+}
+
+fn member() {
+    let socket = UdpSocket::bind(("0.0.0.0", PORT)).unwrap();
+    let any = Ipv4Addr::UNSPECIFIED;
+
+    socket.join_multicast_v4(&GROUP, &any).unwrap();
+
+    assert_eq!(
+        errno(socket.join_multicast_v4(&GROUP, &any)),
+        libc::EADDRINUSE
+    );
+    assert_eq!(
+        errno(socket.join_multicast_v4(&Ipv4Addr::new(10, 0, 0, 1), &any)),
+        libc::EINVAL
+    );
+    assert_eq!(
+        errno(socket.leave_multicast_v4(&OTHER_GROUP, &any)),
+        libc::EADDRNOTAVAIL
+    );
+    assert_eq!(
+        errno(socket.join_multicast_v4(&OTHER_GROUP, &Ipv4Addr::new(10, 9, 9, 9))),
+        libc::ENODEV
+    );
+
+    assert_eq!(socket.multicast_ttl_v4().unwrap(), 1);
+    assert!(socket.multicast_loop_v4().unwrap());
+    socket.set_multicast_ttl_v4(4).unwrap();
+    assert_eq!(socket.multicast_ttl_v4().unwrap(), 4);
+
+    assert_eq!(recv(&socket), b"first");
+    assert_eq!(recv(&socket), b"all");
+
+    // after leaving the group, the host no longer receives its packets
+    socket.leave_multicast_v4(&GROUP, &any).unwrap();
+    assert!(!test_utils::is_readable(socket.as_raw_fd(), 3000).unwrap());
+}
+
+fn members() {
+    let first = bind_reuse_addr(PORT);
+    // a socket can only share the port if it also sets `SO_REUSEADDR`
+    assert_eq!(errno(UdpSocket::bind(("0.0.0.0", PORT))), libc::EADDRINUSE);
+    let second = bind_reuse_addr(PORT);
+    let any = Ipv4Addr::UNSPECIFIED;
+
+    first.join_multicast_v4(&GROUP, &any).unwrap();
+    second.join_multicast_v4(&GROUP, &any).unwrap();
+
+    // each socket receives its own copy of the packets
+    assert_eq!(recv(&first), b"first");
+    assert_eq!(recv(&second), b"first");
+    assert_eq!(recv(&first), b"all");
+    assert_eq!(recv(&second), b"all");
+
+    // like linux with `IP_MULTICAST_ALL`, a socket that left the group still receives its packets
+    // while another socket on the host is a member
+    first.leave_multicast_v4(&GROUP, &any).unwrap();
+    assert_eq!(recv(&first), b"second");
+    assert_eq!(recv(&second), b"second");
+}
+
+fn non_member() {
+    let socket = UdpSocket::bind(("0.0.0.0", PORT)).unwrap();
+
+    // every host is a member of the all-hosts group, but not of the other group
+    assert_eq!(recv(&socket), b"all");
+    assert!(!test_utils::is_readable(socket.as_raw_fd(), 3000).unwrap());
+}
+
+fn main() {
+    match std::env::args().nth(1).unwrap().as_str() {
+        "sender" => sender(),
+        "member" => member(),
+        "members" => members(),
+        "non-member" => non_member(),
+        x => panic!("Unknown role '{x}'"),
+    }
+}
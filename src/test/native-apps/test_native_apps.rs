@@ -0,0 +1,34 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Exchanges datagrams with a native app. The arguments are the name of the host running the
+//! `udp-echo` native app and its port.
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, host, port] = &args[..] else {
+        panic!("Usage: {} <host> <port>", args[0]);
+    };
+    let port: u16 = port.parse().unwrap();
+
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    socket.connect((host.as_str(), port)).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    for i in 0..3 {
+        let msg = format!("hello {i}");
+        socket.send(msg.as_bytes()).unwrap();
+
+        // the app sends the datagram back to us unchanged
+        let mut buf = [0u8; 64];
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], msg.as_bytes());
+    }
+}
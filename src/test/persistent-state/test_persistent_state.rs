@@ -0,0 +1,18 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Reads and updates the host's persistent state, which is in the "state" directory of the
+//! process' working directory.
+
+fn main() {
+    // the value from the config
+    assert_eq!(std::fs::read_to_string("state/greeting").unwrap(), "hello");
+
+    // a key that isn't set yet
+    assert!(!std::path::Path::new("state/visits").exists());
+    std::fs::write("state/visits", "1").unwrap();
+
+    println!("Success.");
+}
@@ -0,0 +1,49 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Checks that the process is assigned the thread and process IDs given in its arguments.
+
+fn main() {
+    let expected_pid: libc::pid_t = std::env::args()
+        .nth(1)
+        .expect("Expected a process ID argument")
+        .parse()
+        .unwrap();
+
+    let pid = unsafe { libc::getpid() };
+    println!("Found pid {pid}, expected {expected_pid}.");
+    assert_eq!(pid, expected_pid);
+    assert_eq!(unsafe { libc::gettid() }, expected_pid);
+
+    // a new thread gets the next ID
+    let tid = std::thread::spawn(|| unsafe { libc::gettid() })
+        .join()
+        .unwrap();
+    println!("Found thread tid {tid}.");
+    assert_eq!(tid, expected_pid + 1);
+
+    // a child process gets the next ID
+    let child_pid = unsafe { libc::fork() };
+    if child_pid == 0 {
+        let rv = if unsafe { libc::getpid() } == expected_pid + 2 {
+            0
+        } else {
+            1
+        };
+        unsafe { libc::_exit(rv) };
+    }
+    println!("Found child pid {child_pid}.");
+    assert_eq!(child_pid, expected_pid + 2);
+
+    let mut status = 0;
+    assert_eq!(
+        unsafe { libc::waitpid(child_pid, &mut status, 0) },
+        child_pid
+    );
+    assert!(libc::WIFEXITED(status));
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+
+    println!("Success.");
+}
@@ -0,0 +1,361 @@
+use std::time::Duration;
+
+use linux_api::sched::clone_args;
+use nix::errno::Errno;
+use test_utils::{ensure_ord, is_readable, set, TestEnvironment};
+
+fn main() -> anyhow::Result<()> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
+    let all_envs = set![TestEnvironment::Libc, TestEnvironment::Shadow];
+    vec![
+        test_utils::ShadowTest::new("pidfd_open_errors", test_open_errors, all_envs.clone()),
+        test_utils::ShadowTest::new("pidfd_open_thread", test_open_thread, all_envs.clone()),
+        test_utils::ShadowTest::new("pidfd_open_cloexec", test_open_cloexec, all_envs.clone()),
+        test_utils::ShadowTest::new("pidfd_poll_exit", test_poll_exit, all_envs.clone()),
+        test_utils::ShadowTest::new("pidfd_send_signal", test_send_signal, all_envs.clone()),
+        test_utils::ShadowTest::new(
+            "pidfd_send_signal_errors",
+            test_send_signal_errors,
+            all_envs.clone(),
+        ),
+        test_utils::ShadowTest::new("pidfd_getfd", test_getfd, all_envs.clone()),
+        test_utils::ShadowTest::new("pidfd_waitid", test_waitid, all_envs.clone()),
+        test_utils::ShadowTest::new(
+            "pidfd_waitid_nonblock",
+            test_waitid_nonblock,
+            all_envs.clone(),
+        ),
+        test_utils::ShadowTest::new("clone3_pidfd", test_clone3_pidfd, all_envs.clone()),
+        test_utils::ShadowTest::new("clone3_args_size", test_clone3_args_size, all_envs.clone()),
+        test_utils::ShadowTest::new(
+            "clone_pidfd_parent_settid",
+            test_clone_pidfd_parent_settid,
+            all_envs.clone(),
+        ),
+    ]
+}
+
+fn pidfd_open(pid: libc::pid_t, flags: libc::c_uint) -> nix::Result<libc::c_int> {
+    let rv = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, flags) };
+    Errno::result(rv).map(|fd| fd.try_into().unwrap())
+}
+
+fn pidfd_send_signal(pidfd: libc::c_int, sig: libc::c_int) -> nix::Result<()> {
+    let info: *const libc::siginfo_t = std::ptr::null();
+    let rv = unsafe { libc::syscall(libc::SYS_pidfd_send_signal, pidfd, sig, info, 0) };
+    Errno::result(rv).map(drop)
+}
+
+fn pidfd_getfd(pidfd: libc::c_int, target_fd: libc::c_int) -> nix::Result<libc::c_int> {
+    let rv = unsafe { libc::syscall(libc::SYS_pidfd_getfd, pidfd, target_fd, 0) };
+    Errno::result(rv).map(|fd| fd.try_into().unwrap())
+}
+
+/// Calls waitid with `P_PIDFD` and returns the siginfo's `si_pid` and `si_status`.
+fn waitid_pidfd(pidfd: libc::c_int, options: libc::c_int) -> nix::Result<(libc::pid_t, i32)> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let idtype = i32::from(linux_api::wait::WaitId::P_PIDFD);
+    let usage: *mut libc::rusage = std::ptr::null_mut();
+    let rv = unsafe {
+        libc::syscall(
+            libc::SYS_waitid,
+            idtype,
+            pidfd,
+            &mut info as *mut libc::siginfo_t,
+            options,
+            usage,
+        )
+    };
+    Errno::result(rv)?;
+    Ok(unsafe { (info.si_pid(), info.si_status()) })
+}
+
+/// Forks a child that exits with status 0 once a byte is written to the returned pipe fd.
+fn fork_waiting_child() -> (libc::pid_t, libc::c_int) {
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+
+    let child_pid = unsafe { libc::fork() };
+    assert!(child_pid >= 0);
+    if child_pid == 0 {
+        let mut buf = [0u8];
+        let rv = nix::unistd::read(read_fd, &mut buf);
+        unsafe { libc::_exit(if rv == Ok(1) { 0 } else { 1 }) };
+    }
+
+    nix::unistd::close(read_fd).unwrap();
+    (child_pid, write_fd)
+}
+
+/// Lets a child created by [`fork_waiting_child`] exit.
+fn release_child(write_fd: libc::c_int) {
+    assert_eq!(nix::unistd::write(write_fd, &[0]), Ok(1));
+    nix::unistd::close(write_fd).unwrap();
+}
+
+fn waitpid(pid: libc::pid_t) -> libc::c_int {
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    status
+}
+
+fn test_open_errors() -> anyhow::Result<()> {
+    let pid = unsafe { libc::getpid() };
+
+    ensure_ord!(pidfd_open(pid, 1), ==, Err(Errno::EINVAL));
+    ensure_ord!(pidfd_open(0, 0), ==, Err(Errno::EINVAL));
+    ensure_ord!(pidfd_open(-1, 0), ==, Err(Errno::EINVAL));
+    ensure_ord!(pidfd_open(libc::pid_t::MAX, 0), ==, Err(Errno::ESRCH));
+
+    Ok(())
+}
+
+/// A pidfd can only refer to a thread group leader.
+fn test_open_thread() -> anyhow::Result<()> {
+    let (tid_sender, tid_receiver) = std::sync::mpsc::channel();
+    let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+
+    let handle = std::thread::spawn(move || {
+        tid_sender.send(unsafe { libc::gettid() }).unwrap();
+        done_receiver.recv().unwrap();
+    });
+
+    let tid = tid_receiver.recv().unwrap();
+    let rv = pidfd_open(tid, 0);
+    done_sender.send(()).unwrap();
+    handle.join().unwrap();
+
+    ensure_ord!(rv, ==, Err(Errno::EINVAL));
+    Ok(())
+}
+
+fn test_open_cloexec() -> anyhow::Result<()> {
+    let pidfd = pidfd_open(unsafe { libc::getpid() }, 0)?;
+
+    let fd_flags = unsafe { libc::fcntl(pidfd, libc::F_GETFD) };
+    ensure_ord!(fd_flags, ==, libc::FD_CLOEXEC);
+
+    let status_flags = unsafe { libc::fcntl(pidfd, libc::F_GETFL) };
+    ensure_ord!(status_flags & libc::O_NONBLOCK, ==, 0);
+    nix::unistd::close(pidfd)?;
+
+    let pidfd = pidfd_open(unsafe { libc::getpid() }, libc::O_NONBLOCK as libc::c_uint)?;
+    let status_flags = unsafe { libc::fcntl(pidfd, libc::F_GETFL) };
+    ensure_ord!(status_flags & libc::O_NONBLOCK, ==, libc::O_NONBLOCK);
+    nix::unistd::close(pidfd)?;
+
+    Ok(())
+}
+
+/// A pidfd becomes readable when its process exits, and remains readable after it's reaped.
+fn test_poll_exit() -> anyhow::Result<()> {
+    let (child_pid, write_fd) = fork_waiting_child();
+    let pidfd = pidfd_open(child_pid, 0)?;
+
+    ensure_ord!(is_readable(pidfd, 0)?, ==, false);
+
+    release_child(write_fd);
+    ensure_ord!(is_readable(pidfd, 1000)?, ==, true);
+
+    let status = waitpid(child_pid);
+    ensure_ord!(libc::WIFEXITED(status), ==, true);
+    ensure_ord!(libc::WEXITSTATUS(status), ==, 0);
+    ensure_ord!(is_readable(pidfd, 0)?, ==, true);
+
+    nix::unistd::close(pidfd)?;
+    Ok(())
+}
+
+fn test_send_signal() -> anyhow::Result<()> {
+    let (child_pid, write_fd) = fork_waiting_child();
+    let pidfd = pidfd_open(child_pid, 0)?;
+
+    // only checks that the process exists
+    ensure_ord!(pidfd_send_signal(pidfd, 0), ==, Ok(()));
+
+    ensure_ord!(pidfd_send_signal(pidfd, libc::SIGTERM), ==, Ok(()));
+    let status = waitpid(child_pid);
+    ensure_ord!(libc::WIFSIGNALED(status), ==, true);
+    ensure_ord!(libc::WTERMSIG(status), ==, libc::SIGTERM);
+
+    // the process has been reaped
+    ensure_ord!(pidfd_send_signal(pidfd, 0), ==, Err(Errno::ESRCH));
+    ensure_ord!(pidfd_send_signal(pidfd, libc::SIGTERM), ==, Err(Errno::ESRCH));
+
+    nix::unistd::close(write_fd)?;
+    nix::unistd::close(pidfd)?;
+    Ok(())
+}
+
+fn test_send_signal_errors() -> anyhow::Result<()> {
+    let pidfd = pidfd_open(unsafe { libc::getpid() }, 0)?;
+
+    // not a valid signal
+    ensure_ord!(pidfd_send_signal(pidfd, 65), ==, Err(Errno::EINVAL));
+
+    // flags must be 0
+    let info: *const libc::siginfo_t = std::ptr::null();
+    let rv = unsafe { libc::syscall(libc::SYS_pidfd_send_signal, pidfd, 0, info, 1) };
+    ensure_ord!(Errno::result(rv), ==, Err(Errno::EINVAL));
+
+    // not a pidfd
+    let (read_fd, write_fd) = nix::unistd::pipe()?;
+    ensure_ord!(pidfd_send_signal(read_fd, 0), ==, Err(Errno::EBADF));
+    ensure_ord!(pidfd_send_signal(-1, 0), ==, Err(Errno::EBADF));
+
+    for fd in [pidfd, read_fd, write_fd] {
+        nix::unistd::close(fd)?;
+    }
+    Ok(())
+}
+
+fn test_getfd() -> anyhow::Result<()> {
+    let pidfd = pidfd_open(unsafe { libc::getpid() }, 0)?;
+    let (read_fd, write_fd) = nix::unistd::pipe()?;
+
+    let new_fd = pidfd_getfd(pidfd, read_fd)?;
+    ensure_ord!(new_fd, !=, read_fd);
+    ensure_ord!(unsafe { libc::fcntl(new_fd, libc::F_GETFD) }, ==, libc::FD_CLOEXEC);
+
+    // the new fd refers to the same pipe
+    ensure_ord!(nix::unistd::write(write_fd, &[42])?, ==, 1);
+    let mut buf = [0u8];
+    ensure_ord!(nix::unistd::read(new_fd, &mut buf)?, ==, 1);
+    ensure_ord!(buf[0], ==, 42);
+
+    ensure_ord!(pidfd_getfd(pidfd, 1000), ==, Err(Errno::EBADF));
+    ensure_ord!(pidfd_getfd(read_fd, read_fd), ==, Err(Errno::EBADF));
+
+    for fd in [pidfd, read_fd, write_fd, new_fd] {
+        nix::unistd::close(fd)?;
+    }
+    Ok(())
+}
+
+fn test_waitid() -> anyhow::Result<()> {
+    let (child_pid, write_fd) = fork_waiting_child();
+    let pidfd = pidfd_open(child_pid, 0)?;
+
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED | libc::WNOHANG)?, ==, (0, 0));
+
+    release_child(write_fd);
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED)?, ==, (child_pid, 0));
+
+    // the process has been reaped
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED), ==, Err(Errno::ECHILD));
+
+    // not a pidfd
+    ensure_ord!(waitid_pidfd(1000, libc::WEXITED), ==, Err(Errno::EBADF));
+
+    nix::unistd::close(pidfd)?;
+    Ok(())
+}
+
+fn test_waitid_nonblock() -> anyhow::Result<()> {
+    let (child_pid, write_fd) = fork_waiting_child();
+    let pidfd = pidfd_open(child_pid, libc::O_NONBLOCK as libc::c_uint)?;
+
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED), ==, Err(Errno::EAGAIN));
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED | libc::WNOHANG)?, ==, (0, 0));
+
+    release_child(write_fd);
+    ensure_ord!(is_readable(pidfd, 1000)?, ==, true);
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED)?, ==, (child_pid, 0));
+
+    nix::unistd::close(pidfd)?;
+    Ok(())
+}
+
+fn clone3(args: *const libc::c_void, size: usize) -> nix::Result<libc::pid_t> {
+    let rv = unsafe { libc::syscall(libc::SYS_clone3, args, size) };
+    Errno::result(rv).map(|pid| pid.try_into().unwrap())
+}
+
+fn test_clone3_pidfd() -> anyhow::Result<()> {
+    let mut pidfd: libc::c_int = -1;
+
+    let mut args: clone_args = unsafe { std::mem::zeroed() };
+    args.flags = libc::CLONE_PIDFD as u64;
+    args.pidfd = &mut pidfd as *mut libc::c_int as u64;
+    args.exit_signal = libc::SIGCHLD as u64;
+
+    // use the smallest version of the args
+    let args_ptr = &args as *const clone_args as *const libc::c_void;
+    let child_pid = clone3(args_ptr, 64)?;
+    if child_pid == 0 {
+        std::thread::sleep(Duration::from_millis(10));
+        unsafe { libc::_exit(3) };
+    }
+
+    ensure_ord!(pidfd, >=, 0);
+    ensure_ord!(unsafe { libc::fcntl(pidfd, libc::F_GETFD) }, ==, libc::FD_CLOEXEC);
+
+    ensure_ord!(is_readable(pidfd, 1000)?, ==, true);
+    ensure_ord!(waitid_pidfd(pidfd, libc::WEXITED)?, ==, (child_pid, 3));
+
+    nix::unistd::close(pidfd)?;
+    Ok(())
+}
+
+fn test_clone3_args_size() -> anyhow::Result<()> {
+    #[repr(C)]
+    struct ExtendedArgs {
+        args: clone_args,
+        unknown: [u64; 4],
+    }
+
+    let mut args: ExtendedArgs = unsafe { std::mem::zeroed() };
+    args.args.exit_signal = libc::SIGCHLD as u64;
+    let args_ptr = &args as *const ExtendedArgs as *const libc::c_void;
+
+    // smaller than the first version of the args
+    ensure_ord!(clone3(args_ptr, 63), ==, Err(Errno::EINVAL));
+    ensure_ord!(clone3(args_ptr, 0), ==, Err(Errno::EINVAL));
+
+    // unknown fields that are set
+    args.unknown[3] = 1;
+    let size = std::mem::size_of::<ExtendedArgs>();
+    ensure_ord!(clone3(args_ptr, size), ==, Err(Errno::E2BIG));
+
+    Ok(())
+}
+
+fn test_clone_pidfd_parent_settid() -> anyhow::Result<()> {
+    let mut pidfd: libc::c_int = -1;
+    let flags = libc::CLONE_PIDFD | libc::CLONE_PARENT_SETTID | libc::SIGCHLD;
+    let null: *mut libc::c_void = std::ptr::null_mut();
+
+    let rv = unsafe {
+        libc::syscall(
+            libc::SYS_clone,
+            flags,
+            null,
+            &mut pidfd as *mut libc::c_int,
+            null,
+            null,
+        )
+    };
+    ensure_ord!(Errno::result(rv), ==, Err(Errno::EINVAL));
+    ensure_ord!(pidfd, ==, -1);
+
+    Ok(())
+}
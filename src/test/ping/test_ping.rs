@@ -0,0 +1,81 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Sends ICMP echo requests with a ping socket (see icmp(7)) and checks the replies.
+//!
+//! Arguments are `<address>=<minimum round-trip time in milliseconds>` pairs. Each address is
+//! pinged, and its replies must arrive no sooner than the minimum round-trip time.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::{Duration, Instant};
+
+use nix::sys::socket::{recvfrom, sendto, MsgFlags, SockaddrIn};
+
+const ICMP_ECHO: u8 = 8;
+const ICMP_ECHOREPLY: u8 = 0;
+
+fn echo_request(sequence: u16, data: &[u8]) -> Vec<u8> {
+    // the kernel fills in the checksum and identifier
+    let mut message = vec![ICMP_ECHO, 0, 0, 0, 0, 0];
+    message.extend_from_slice(&sequence.to_be_bytes());
+    message.extend_from_slice(data);
+    message
+}
+
+fn ping(fd: i32, addr: Ipv4Addr, sequence: u16) -> Duration {
+    let data = format!("ping {sequence}");
+    let dst = SockaddrIn::from(SocketAddrV4::new(addr, 0));
+
+    let start = Instant::now();
+    sendto(
+        fd,
+        &echo_request(sequence, data.as_bytes()),
+        &dst,
+        MsgFlags::empty(),
+    )
+    .unwrap();
+
+    let mut buf = [0u8; 1024];
+    let (len, src) = recvfrom::<SockaddrIn>(fd, &mut buf).unwrap();
+    let rtt = start.elapsed();
+
+    let reply = &buf[..len];
+    assert_eq!(Ipv4Addr::from(src.unwrap().ip()), addr);
+    assert_eq!(reply[0], ICMP_ECHOREPLY);
+    assert_eq!(reply[1], 0);
+    assert_eq!(u16::from_be_bytes([reply[6], reply[7]]), sequence);
+    assert_eq!(&reply[8..], data.as_bytes());
+
+    rtt
+}
+
+fn main() {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, libc::IPPROTO_ICMP) };
+    assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+
+    for argument in std::env::args().skip(1) {
+        let (addr, min_rtt) = argument.split_once('=').unwrap();
+        let addr: Ipv4Addr = addr.parse().unwrap();
+        let min_rtt = Duration::from_millis(min_rtt.parse().unwrap());
+
+        for sequence in 1..=3 {
+            let rtt = ping(fd, addr, sequence);
+            println!("reply from {addr}: seq={sequence} time={rtt:?}");
+            assert!(rtt >= min_rtt, "{rtt:?} < {min_rtt:?}");
+        }
+    }
+
+    // messages that aren't echo requests are rejected
+    let dst = SockaddrIn::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+    let err = sendto(
+        fd,
+        &[ICMP_ECHOREPLY, 0, 0, 0, 0, 0, 0, 1],
+        &dst,
+        MsgFlags::empty(),
+    );
+    assert_eq!(err, Err(nix::errno::Errno::EINVAL));
+
+    nix::unistd::close(fd).unwrap();
+}
@@ -149,6 +149,37 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             test_close_during_blocking_write,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_set_size",
+            test_set_size,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_set_size_busy",
+            test_set_size_busy,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_atomic_write",
+            test_atomic_write,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_splice",
+            test_splice,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_splice_errors",
+            test_splice_errors,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_splice_blocking",
+            test_splice_blocking,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new("test_tee", test_tee, set![TestEnv::Libc, TestEnv::Shadow]),
     ];
 
     tests
@@ -1224,3 +1255,282 @@ fn test_close_during_blocking_write() -> Result<(), String> {
 
     Ok(())
 }
+
+fn test_set_size() -> Result<(), String> {
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        // the size is rounded up to a power-of-two number of pages
+        let size = test_utils::check_system_call!(
+            || unsafe { libc::fcntl(write_fd, libc::F_SETPIPE_SZ, 4097) },
+            &[]
+        )?;
+        test_utils::result_assert_eq(size, 8192, "Unexpected pipe size")?;
+
+        // both ends of the pipe share the same buffer
+        let size = test_utils::check_system_call!(
+            || unsafe { libc::fcntl(read_fd, libc::F_GETPIPE_SZ) },
+            &[]
+        )?;
+        test_utils::result_assert_eq(size, 8192, "Unexpected pipe size")?;
+
+        // the size can't be smaller than a page
+        let size = test_utils::check_system_call!(
+            || unsafe { libc::fcntl(read_fd, libc::F_SETPIPE_SZ, 0) },
+            &[]
+        )?;
+        test_utils::result_assert_eq(size, 4096, "Unexpected pipe size")?;
+
+        // the pipe can be filled to its new capacity
+        nix::fcntl::fcntl(
+            write_fd,
+            nix::fcntl::FcntlArg::F_SETFL(nix::fcntl::OFlag::O_NONBLOCK),
+        )
+        .unwrap();
+        let buf = vec![0u8; 10_000];
+        test_utils::result_assert_eq(
+            nix::unistd::write(write_fd, &buf),
+            Ok(4096),
+            "Unexpected write size",
+        )?;
+
+        // only privileged processes can go beyond /proc/sys/fs/pipe-max-size
+        if unsafe { libc::geteuid() } != 0 {
+            test_utils::check_system_call!(
+                || unsafe { libc::fcntl(read_fd, libc::F_SETPIPE_SZ, 1 << 30) },
+                &[libc::EPERM]
+            )?;
+        }
+
+        // not a pipe
+        let fd = unsafe { libc::eventfd(0, 0) };
+        test_utils::result_assert(fd >= 0, "Could not create eventfd")?;
+        test_utils::run_and_close_fds(&[fd], || {
+            test_utils::check_system_call!(
+                || unsafe { libc::fcntl(fd, libc::F_SETPIPE_SZ, 4096) },
+                &[libc::EBADF]
+            )?;
+            test_utils::check_system_call!(
+                || unsafe { libc::fcntl(fd, libc::F_GETPIPE_SZ) },
+                &[libc::EBADF]
+            )
+        })?;
+
+        Ok(())
+    })
+}
+
+fn test_set_size_busy() -> Result<(), String> {
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        let buf = vec![0u8; 8192];
+        assert_eq!(nix::unistd::write(write_fd, &buf), Ok(buf.len()));
+
+        // the pipe can't shrink below the amount of data it holds
+        test_utils::check_system_call!(
+            || unsafe { libc::fcntl(write_fd, libc::F_SETPIPE_SZ, 4096) },
+            &[libc::EBUSY]
+        )?;
+
+        let mut buf = vec![0u8; 4096];
+        assert_eq!(nix::unistd::read(read_fd, &mut buf), Ok(buf.len()));
+
+        let size = test_utils::check_system_call!(
+            || unsafe { libc::fcntl(write_fd, libc::F_SETPIPE_SZ, 4096) },
+            &[]
+        )?;
+        test_utils::result_assert_eq(size, 4096, "Unexpected pipe size")?;
+
+        Ok(())
+    })
+}
+
+fn test_atomic_write() -> Result<(), String> {
+    let mut fds = [0 as libc::c_int; 2];
+    test_utils::check_system_call!(
+        || { unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK) } },
+        &[]
+    )?;
+
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    test_utils::run_and_close_fds(&[write_fd, read_fd], || {
+        let capacity = nix::fcntl::fcntl(read_fd, nix::fcntl::FcntlArg::F_GETPIPE_SZ).unwrap();
+        let capacity = capacity as usize;
+
+        // leave 100 bytes of space
+        let buffer = vec![0u8; capacity - 100];
+        assert_eq!(nix::unistd::write(write_fd, &buffer), Ok(buffer.len()));
+
+        // the pipe isn't writable until a full page is available
+        let mut poll_fds = [nix::poll::PollFd::new(write_fd, PollFlags::POLLOUT)];
+        assert_eq!(nix::poll::poll(&mut poll_fds, 0), Ok(0));
+
+        // writes of up to PIPE_BUF bytes are never split
+        assert_eq!(
+            nix::unistd::write(write_fd, &[0u8; 200]),
+            Err(nix::errno::Errno::EAGAIN)
+        );
+        assert_eq!(nix::unistd::write(write_fd, &[0u8; 50]), Ok(50));
+
+        Ok(())
+    })
+}
+
+fn splice(fd_in: libc::c_int, fd_out: libc::c_int, len: usize, flags: libc::c_uint) -> isize {
+    let null = std::ptr::null_mut();
+    unsafe { libc::splice(fd_in, null, fd_out, null, len, flags) }
+}
+
+fn test_splice() -> Result<(), String> {
+    let (read_fd_1, write_fd_1) = nix::unistd::pipe().unwrap();
+    let (read_fd_2, write_fd_2) = nix::unistd::pipe().unwrap();
+
+    // the write end of the first pipe is closed during the test
+    test_utils::run_and_close_fds(&[read_fd_1, read_fd_2, write_fd_2], || {
+        assert_eq!(nix::unistd::write(write_fd_1, b"hello world"), Ok(11));
+
+        let rv = test_utils::check_system_call!(|| splice(read_fd_1, write_fd_2, 5, 0), &[])?;
+        test_utils::result_assert_eq(rv, 5, "Unexpected splice size")?;
+
+        let rv = test_utils::check_system_call!(|| splice(read_fd_1, write_fd_2, 100, 0), &[])?;
+        test_utils::result_assert_eq(rv, 6, "Unexpected splice size")?;
+
+        let mut buf = [0u8; 100];
+        assert_eq!(nix::unistd::read(read_fd_2, &mut buf), Ok(11));
+        test_utils::result_assert_eq(&buf[..11], &b"hello world"[..], "Unexpected bytes")?;
+
+        // the source pipe is empty
+        test_utils::check_system_call!(
+            || splice(read_fd_1, write_fd_2, 100, libc::SPLICE_F_NONBLOCK),
+            &[libc::EAGAIN]
+        )?;
+
+        // the splice is limited by the space in the destination pipe
+        let capacity = nix::fcntl::fcntl(read_fd_2, nix::fcntl::FcntlArg::F_GETPIPE_SZ).unwrap();
+        let capacity = capacity as usize;
+        let buf = vec![0u8; 4096];
+        assert_eq!(
+            nix::unistd::write(write_fd_2, &vec![0u8; capacity - 4096]),
+            Ok(capacity - 4096)
+        );
+        assert_eq!(nix::unistd::write(write_fd_1, &buf), Ok(4096));
+        assert_eq!(nix::unistd::write(write_fd_1, &buf), Ok(4096));
+
+        let rv = test_utils::check_system_call!(|| splice(read_fd_1, write_fd_2, 10_000, 0), &[])?;
+        test_utils::result_assert_eq(rv, 4096, "Unexpected splice size")?;
+
+        test_utils::check_system_call!(
+            || splice(read_fd_1, write_fd_2, 10_000, libc::SPLICE_F_NONBLOCK),
+            &[libc::EAGAIN]
+        )?;
+
+        // splicing from a pipe with no data and no writers is EOF
+        nix::unistd::close(write_fd_1).unwrap();
+        let mut buf = vec![0u8; 4096];
+        assert_eq!(nix::unistd::read(read_fd_1, &mut buf), Ok(4096));
+        let rv = test_utils::check_system_call!(|| splice(read_fd_1, write_fd_2, 100, 0), &[])?;
+        test_utils::result_assert_eq(rv, 0, "Expected EOF")?;
+
+        Ok(())
+    })
+}
+
+fn test_splice_errors() -> Result<(), String> {
+    let (read_fd_1, write_fd_1) = nix::unistd::pipe().unwrap();
+    let (read_fd_2, write_fd_2) = nix::unistd::pipe().unwrap();
+
+    test_utils::run_and_close_fds(&[read_fd_1, write_fd_1, read_fd_2, write_fd_2], || {
+        assert_eq!(nix::unistd::write(write_fd_1, b"hello"), Ok(5));
+
+        // both ends of the same pipe
+        test_utils::check_system_call!(|| splice(read_fd_1, write_fd_1, 5, 0), &[libc::EINVAL])?;
+
+        // invalid flags
+        test_utils::check_system_call!(
+            || splice(read_fd_1, write_fd_2, 5, 0x100),
+            &[libc::EINVAL]
+        )?;
+
+        // pipes don't have offsets
+        let mut offset: libc::loff_t = 0;
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::splice(
+                    read_fd_1,
+                    &mut offset,
+                    write_fd_2,
+                    std::ptr::null_mut(),
+                    5,
+                    0,
+                )
+            },
+            &[libc::ESPIPE]
+        )?;
+
+        // wrong directions
+        test_utils::check_system_call!(|| splice(write_fd_1, read_fd_2, 5, 0), &[libc::EBADF])?;
+
+        Ok(())
+    })
+}
+
+fn test_splice_blocking() -> Result<(), String> {
+    let (read_fd_1, write_fd_1) = nix::unistd::pipe().unwrap();
+    let (read_fd_2, write_fd_2) = nix::unistd::pipe().unwrap();
+
+    let thread_handle = std::thread::spawn(move || {
+        // 2. wait for the splice to block
+        std::thread::sleep(Duration::from_millis(100));
+
+        // 3. wake the splice by writing
+        assert_eq!(nix::unistd::write(write_fd_1, &[1, 2, 3]), Ok(3));
+    });
+
+    // 1. the splice will block until there are bytes to move
+    let rv = test_utils::check_system_call!(|| splice(read_fd_1, write_fd_2, 100, 0), &[])?;
+    test_utils::result_assert_eq(rv, 3, "Unexpected splice size")?;
+
+    thread_handle.join().unwrap();
+
+    let mut buf = [0u8; 10];
+    assert_eq!(nix::unistd::read(read_fd_2, &mut buf), Ok(3));
+    test_utils::result_assert_eq(&buf[..3], &[1, 2, 3][..], "Unexpected bytes")?;
+
+    for fd in [read_fd_1, write_fd_1, read_fd_2, write_fd_2] {
+        nix::unistd::close(fd).unwrap();
+    }
+
+    Ok(())
+}
+
+fn test_tee() -> Result<(), String> {
+    let (read_fd_1, write_fd_1) = nix::unistd::pipe().unwrap();
+    let (read_fd_2, write_fd_2) = nix::unistd::pipe().unwrap();
+
+    test_utils::run_and_close_fds(&[read_fd_1, write_fd_1, read_fd_2, write_fd_2], || {
+        assert_eq!(nix::unistd::write(write_fd_1, b"hello"), Ok(5));
+
+        let rv = test_utils::check_system_call!(
+            || unsafe { libc::tee(read_fd_1, write_fd_2, 100, 0) },
+            &[]
+        )?;
+        test_utils::result_assert_eq(rv, 5, "Unexpected tee size")?;
+
+        // the data is in both pipes
+        let mut buf = [0u8; 10];
+        assert_eq!(nix::unistd::read(read_fd_2, &mut buf), Ok(5));
+        test_utils::result_assert_eq(&buf[..5], &b"hello"[..], "Unexpected bytes")?;
+        assert_eq!(nix::unistd::read(read_fd_1, &mut buf), Ok(5));
+        test_utils::result_assert_eq(&buf[..5], &b"hello"[..], "Unexpected bytes")?;
+
+        // tee only works between two pipes
+        test_utils::check_system_call!(
+            || unsafe { libc::tee(read_fd_1, write_fd_1, 100, 0) },
+            &[libc::EINVAL]
+        )?;
+
+        Ok(())
+    })
+}
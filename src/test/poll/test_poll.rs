@@ -5,6 +5,7 @@
 #![allow(clippy::too_many_arguments)]
 
 use std::cmp::Ordering;
+use std::sync::atomic::{self, AtomicUsize};
 use std::time::Duration;
 
 use nix::sys::signal;
@@ -20,6 +21,26 @@ enum PollFn {
 
 const TEST_STR: &[u8; 4] = b"test";
 
+static SIGUSR1_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn count_sigusr1(_signo: i32) {
+    SIGUSR1_COUNT.fetch_add(1, atomic::Ordering::SeqCst);
+}
+
+fn install_sigusr1_counter() {
+    unsafe {
+        signal::sigaction(
+            Signal::SIGUSR1,
+            &signal::SigAction::new(
+                signal::SigHandler::Handler(count_sigusr1),
+                signal::SaFlags::empty(),
+                signal::SigSet::empty(),
+            ),
+        )
+    }
+    .unwrap();
+}
+
 fn fd_write(fd: i32) -> Result<(), String> {
     let num = nix::unistd::write(fd, TEST_STR).map_err(|e| e.to_string())?;
     if num != TEST_STR.len() {
@@ -160,6 +181,122 @@ fn test_regular_file() -> Result<(), String> {
     })
 }
 
+/// A signal blocked by the thread's signal mask but not by the mask given to `ppoll` should
+/// interrupt `ppoll`, and the thread's mask should be restored afterwards.
+fn test_ppoll_sigmask_unblocks() -> Result<(), String> {
+    install_sigusr1_counter();
+
+    let mut sigusr1 = signal::SigSet::empty();
+    sigusr1.add(Signal::SIGUSR1);
+    signal::pthread_sigmask(signal::SigmaskHow::SIG_BLOCK, Some(&sigusr1), None).unwrap();
+
+    let count_before = SIGUSR1_COUNT.load(atomic::Ordering::SeqCst);
+
+    let interruptor = test_utils::Interruptor::new(Duration::from_millis(10), Signal::SIGUSR1);
+    let timeout = libc::timespec {
+        tv_sec: 1,
+        tv_nsec: 0,
+    };
+    let empty = signal::SigSet::empty();
+    let rv = test_utils::check_system_call!(
+        || unsafe { libc::ppoll(std::ptr::null_mut(), 0, &timeout, empty.as_ref()) },
+        &[libc::EINTR]
+    );
+    drop(interruptor);
+
+    let mask = signal::SigSet::thread_get_mask().unwrap();
+    signal::pthread_sigmask(signal::SigmaskHow::SIG_UNBLOCK, Some(&sigusr1), None).unwrap();
+    rv?;
+
+    test_utils::result_assert_eq(
+        SIGUSR1_COUNT.load(atomic::Ordering::SeqCst),
+        count_before + 1,
+        "The signal handler didn't run",
+    )?;
+    test_utils::result_assert(
+        mask.contains(Signal::SIGUSR1),
+        "The signal mask wasn't restored",
+    )?;
+
+    Ok(())
+}
+
+/// A signal blocked by the mask given to `ppoll` shouldn't interrupt `ppoll`, and should be
+/// handled once `ppoll` returns and the thread's mask is restored.
+fn test_ppoll_sigmask_blocks() -> Result<(), String> {
+    install_sigusr1_counter();
+
+    let count_before = SIGUSR1_COUNT.load(atomic::Ordering::SeqCst);
+
+    let interruptor = test_utils::Interruptor::new(Duration::from_millis(10), Signal::SIGUSR1);
+    let timeout = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 100_000_000,
+    };
+    let mut sigusr1 = signal::SigSet::empty();
+    sigusr1.add(Signal::SIGUSR1);
+    let rv = test_utils::check_system_call!(
+        || unsafe { libc::ppoll(std::ptr::null_mut(), 0, &timeout, sigusr1.as_ref()) },
+        &[]
+    )?;
+    drop(interruptor);
+
+    test_utils::result_assert_eq(rv, 0, "Expected ppoll to time out")?;
+    test_utils::result_assert_eq(
+        SIGUSR1_COUNT.load(atomic::Ordering::SeqCst),
+        count_before + 1,
+        "The signal wasn't handled after ppoll returned",
+    )?;
+
+    let mask = signal::SigSet::thread_get_mask().unwrap();
+    test_utils::result_assert(
+        !mask.contains(Signal::SIGUSR1),
+        "The signal mask wasn't restored",
+    )?;
+
+    Ok(())
+}
+
+/// The `ppoll` syscall (but not the libc wrapper) should update the timeout with the time that
+/// was left when it was interrupted.
+fn test_ppoll_remaining_time() -> Result<(), String> {
+    install_sigusr1_counter();
+
+    let interruptor = test_utils::Interruptor::new(Duration::from_millis(10), Signal::SIGUSR1);
+    let mut timeout = libc::timespec {
+        tv_sec: 1,
+        tv_nsec: 0,
+    };
+    let nfds: libc::nfds_t = 0;
+    let sigsetsize: libc::size_t = 8;
+    let rv = test_utils::check_system_call!(
+        || unsafe {
+            libc::syscall(
+                libc::SYS_ppoll,
+                std::ptr::null_mut::<libc::pollfd>(),
+                nfds,
+                &mut timeout as *mut libc::timespec,
+                std::ptr::null::<libc::sigset_t>(),
+                sigsetsize,
+            )
+        },
+        &[libc::EINTR]
+    );
+    drop(interruptor);
+    rv?;
+
+    let remaining = Duration::new(
+        timeout.tv_sec.try_into().unwrap(),
+        timeout.tv_nsec.try_into().unwrap(),
+    );
+    test_utils::result_assert(
+        remaining > Duration::ZERO && remaining < Duration::from_secs(1),
+        &format!("Unexpected remaining time {remaining:?}"),
+    )?;
+
+    Ok(())
+}
+
 fn get_pollable_fd() -> Result<libc::c_int, String> {
     // Get an fd we can poll
     let fd = test_utils::check_system_call!(
@@ -330,6 +467,21 @@ fn main() -> Result<(), String> {
             test_regular_file,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_ppoll_sigmask_unblocks",
+            test_ppoll_sigmask_unblocks,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_ppoll_sigmask_blocks",
+            test_ppoll_sigmask_blocks,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_ppoll_remaining_time",
+            test_ppoll_remaining_time,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     // For each combination of args, test both poll and ppoll
@@ -0,0 +1,245 @@
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use test_utils::{ensure_ord, set, TestEnvironment};
+
+fn main() -> anyhow::Result<()> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
+    let all_envs = set![TestEnvironment::Libc, TestEnvironment::Shadow];
+    vec![
+        test_utils::ShadowTest::new("prctl_name", test_name, all_envs.clone()),
+        test_utils::ShadowTest::new("prctl_pdeathsig", test_pdeathsig, all_envs.clone()),
+        test_utils::ShadowTest::new(
+            "prctl_pdeathsig_fork",
+            test_pdeathsig_fork,
+            all_envs.clone(),
+        ),
+        test_utils::ShadowTest::new(
+            "prctl_pdeathsig_delivered",
+            test_pdeathsig_delivered,
+            all_envs.clone(),
+        ),
+        test_utils::ShadowTest::new("prctl_dumpable", test_dumpable, all_envs.clone()),
+        test_utils::ShadowTest::new("prctl_capbset_read", test_capbset_read, all_envs.clone()),
+        test_utils::ShadowTest::new("arch_prctl_fs", test_arch_prctl_fs, all_envs.clone()),
+    ]
+}
+
+/// Calls `prctl` and returns the result and errno.
+fn prctl(option: libc::c_int, arg2: libc::c_ulong) -> (libc::c_int, libc::c_int) {
+    let rv = unsafe { libc::prctl(option, arg2, 0, 0, 0) };
+    (rv, unsafe { *libc::__errno_location() })
+}
+
+fn get_name() -> String {
+    let mut buf = [0u8; 16];
+    let rv = unsafe { libc::prctl(libc::PR_GET_NAME, buf.as_mut_ptr()) };
+    assert_eq!(rv, 0);
+    CStr::from_bytes_until_nul(&buf)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn set_name(name: &CStr) {
+    let rv = unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) };
+    assert_eq!(rv, 0);
+}
+
+fn get_pdeathsig() -> libc::c_int {
+    let mut signal: libc::c_int = -1;
+    let rv = unsafe { libc::prctl(libc::PR_GET_PDEATHSIG, &mut signal as *mut libc::c_int) };
+    assert_eq!(rv, 0);
+    signal
+}
+
+fn test_name() -> anyhow::Result<()> {
+    let orig = std::ffi::CString::new(get_name()).unwrap();
+
+    set_name(CStr::from_bytes_with_nul(b"prctl-name\0").unwrap());
+    ensure_ord!(get_name(), ==, "prctl-name");
+
+    // names are truncated to 15 bytes plus the nul terminator
+    set_name(CStr::from_bytes_with_nul(b"a-much-longer-thread-name\0").unwrap());
+    ensure_ord!(get_name(), ==, "a-much-longer-t");
+
+    set_name(&orig);
+    Ok(())
+}
+
+fn test_pdeathsig() -> anyhow::Result<()> {
+    ensure_ord!(get_pdeathsig(), ==, 0);
+
+    ensure_ord!(prctl(libc::PR_SET_PDEATHSIG, libc::SIGUSR1 as libc::c_ulong).0, ==, 0);
+    ensure_ord!(get_pdeathsig(), ==, libc::SIGUSR1);
+
+    // not a valid signal
+    let (rv, errno) = prctl(libc::PR_SET_PDEATHSIG, 65);
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(errno, ==, libc::EINVAL);
+    ensure_ord!(get_pdeathsig(), ==, libc::SIGUSR1);
+
+    ensure_ord!(prctl(libc::PR_SET_PDEATHSIG, 0).0, ==, 0);
+    ensure_ord!(get_pdeathsig(), ==, 0);
+
+    Ok(())
+}
+
+/// Runs `f` in a forked child process and returns its exit status.
+fn in_child(f: impl FnOnce() -> libc::c_int) -> libc::c_int {
+    let child_pid = unsafe { libc::fork() };
+    assert!(child_pid >= 0);
+    if child_pid == 0 {
+        let rv = f();
+        unsafe { libc::_exit(rv) };
+    }
+
+    let mut status = 0;
+    assert_eq!(
+        unsafe { libc::waitpid(child_pid, &mut status, 0) },
+        child_pid
+    );
+    assert!(libc::WIFEXITED(status));
+    libc::WEXITSTATUS(status)
+}
+
+/// The parent-death signal isn't inherited by a forked child.
+fn test_pdeathsig_fork() -> anyhow::Result<()> {
+    ensure_ord!(prctl(libc::PR_SET_PDEATHSIG, libc::SIGUSR1 as libc::c_ulong).0, ==, 0);
+    let child_signal = in_child(get_pdeathsig);
+    ensure_ord!(prctl(libc::PR_SET_PDEATHSIG, 0).0, ==, 0);
+
+    ensure_ord!(child_signal, ==, 0);
+    Ok(())
+}
+
+static SIGNAL_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn record_signal(_signal: libc::c_int) {
+    SIGNAL_RECEIVED.store(true, Ordering::SeqCst);
+}
+
+/// A grandchild gets its parent-death signal when the child exits.
+fn test_pdeathsig_delivered() -> anyhow::Result<()> {
+    let (ready_read, ready_write) = nix::unistd::pipe()?;
+    let (result_read, result_write) = nix::unistd::pipe()?;
+
+    let child_rv = in_child(|| {
+        let grandchild_pid = unsafe { libc::fork() };
+        assert!(grandchild_pid >= 0);
+        if grandchild_pid == 0 {
+            let handler = nix::sys::signal::SigHandler::Handler(record_signal);
+            unsafe { nix::sys::signal::signal(nix::sys::signal::Signal::SIGUSR1, handler) }
+                .unwrap();
+
+            let rv = unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGUSR1) };
+            assert_eq!(rv, 0);
+            nix::unistd::write(ready_write, &[0]).unwrap();
+
+            // wait for the signal
+            for _ in 0..500 {
+                if SIGNAL_RECEIVED.load(Ordering::SeqCst) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let received = u8::from(SIGNAL_RECEIVED.load(Ordering::SeqCst));
+            nix::unistd::write(result_write, &[received]).unwrap();
+            unsafe { libc::_exit(0) };
+        }
+
+        // exit once the grandchild has set its parent-death signal
+        let mut buf = [0u8];
+        nix::unistd::read(ready_read, &mut buf).unwrap();
+        0
+    });
+    ensure_ord!(child_rv, ==, 0);
+
+    let mut received = [0u8];
+    ensure_ord!(nix::unistd::read(result_read, &mut received)?, ==, 1);
+    ensure_ord!(received[0], ==, 1);
+
+    for fd in [ready_read, ready_write, result_read, result_write] {
+        nix::unistd::close(fd)?;
+    }
+
+    Ok(())
+}
+
+fn test_dumpable() -> anyhow::Result<()> {
+    let orig = prctl(libc::PR_GET_DUMPABLE, 0).0;
+
+    ensure_ord!(prctl(libc::PR_SET_DUMPABLE, 0).0, ==, 0);
+    ensure_ord!(prctl(libc::PR_GET_DUMPABLE, 0).0, ==, 0);
+
+    ensure_ord!(prctl(libc::PR_SET_DUMPABLE, 1).0, ==, 0);
+    ensure_ord!(prctl(libc::PR_GET_DUMPABLE, 0).0, ==, 1);
+
+    // SUID_DUMP_ROOT can't be set with prctl
+    let (rv, errno) = prctl(libc::PR_SET_DUMPABLE, 2);
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(errno, ==, libc::EINVAL);
+
+    ensure_ord!(prctl(libc::PR_SET_DUMPABLE, orig as libc::c_ulong).0, ==, 0);
+    Ok(())
+}
+
+fn test_capbset_read() -> anyhow::Result<()> {
+    // CAP_SETUID
+    let (rv, _errno) = prctl(libc::PR_CAPBSET_READ, 7);
+    ensure_ord!(rv, >=, 0);
+    ensure_ord!(rv, <=, 1);
+
+    // not a valid capability
+    let (rv, errno) = prctl(libc::PR_CAPBSET_READ, 1000);
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(errno, ==, libc::EINVAL);
+
+    Ok(())
+}
+
+fn get_fs() -> u64 {
+    let mut fs: u64 = 0;
+    let rv = unsafe { libc::syscall(libc::SYS_arch_prctl, libc::ARCH_GET_FS, &mut fs as *mut u64) };
+    assert_eq!(rv, 0);
+    fs
+}
+
+fn test_arch_prctl_fs() -> anyhow::Result<()> {
+    // With glibc, the thread pointer at `%fs:0` points to itself.
+    let thread_pointer: u64;
+    unsafe { std::arch::asm!("mov {tp}, fs:0", tp = out(reg) thread_pointer) };
+    let fs = get_fs();
+    ensure_ord!(fs, ==, thread_pointer);
+
+    // Setting the same thread pointer leaves it unchanged, and leaves us (and
+    // the shim under shadow) able to use thread-local storage.
+    let rv = unsafe { libc::syscall(libc::SYS_arch_prctl, libc::ARCH_SET_FS, fs) };
+    ensure_ord!(rv, ==, 0);
+    ensure_ord!(get_fs(), ==, fs);
+    std::thread::sleep(Duration::from_millis(1));
+
+    Ok(())
+}
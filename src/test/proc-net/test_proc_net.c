@@ -0,0 +1,195 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests that /proc/net/tcp, /proc/net/udp, and /proc/net/dev describe the
+// host's sockets and network interfaces.
+
+#include <glib.h>
+
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdbool.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "test/test_common.h"
+#include "test/test_glib_helpers.h"
+
+// Linux socket states from include/net/tcp_states.h.
+#define STATE_ESTABLISHED 0x01
+#define STATE_CLOSE 0x07
+#define STATE_LISTEN 0x0A
+
+// Returns true if the file has a socket with the given local and remote ports
+// and state. A port of 0 matches any port.
+static bool has_socket(const char* path, int local_port, int remote_port, int state) {
+    FILE* f = fopen(path, "r");
+    assert_nonnull_errno(f);
+
+    char line[1024] = {0};
+
+    // skip the header
+    g_assert_nonnull(fgets(line, sizeof(line), f));
+
+    bool found = false;
+    while (fgets(line, sizeof(line), f) != NULL) {
+        unsigned int local_ip = 0, local = 0, remote_ip = 0, remote = 0, st = 0;
+        int count =
+            sscanf(line, " %*d: %8X:%4X %8X:%4X %2X", &local_ip, &local, &remote_ip, &remote, &st);
+        g_assert_cmpint(count, ==, 5);
+
+        if (local == local_port && (remote_port == 0 || remote == remote_port) && st == state) {
+            // addresses are in network byte order
+            g_assert_cmpuint(local_ip, ==, htonl(INADDR_LOOPBACK));
+            found = true;
+            break;
+        }
+    }
+
+    fclose(f);
+    return found;
+}
+
+// Returns the number of packets received by the interface, or -1 if the
+// interface isn't listed.
+static long get_rx_packets(const char* interface) {
+    FILE* f = fopen("/proc/net/dev", "r");
+    assert_nonnull_errno(f);
+
+    char line[1024] = {0};
+    long rx_packets = -1;
+
+    while (fgets(line, sizeof(line), f) != NULL) {
+        char name[64] = {0};
+        unsigned long rx_bytes = 0, packets = 0;
+        if (sscanf(line, " %63[^:]: %lu %lu", name, &rx_bytes, &packets) != 3) {
+            // a header line
+            continue;
+        }
+        if (strcmp(name, interface) == 0) {
+            rx_packets = packets;
+            break;
+        }
+    }
+
+    fclose(f);
+    return rx_packets;
+}
+
+static int get_port(int fd) {
+    struct sockaddr_in addr = {0};
+    socklen_t len = sizeof(addr);
+    assert_nonneg_errno(getsockname(fd, (struct sockaddr*)&addr, &len));
+    return ntohs(addr.sin_port);
+}
+
+static void test_tcp() {
+    int listen_fd = -1;
+    assert_nonneg_errno(listen_fd = socket(AF_INET, SOCK_STREAM, 0));
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+        .sin_port = 0,
+    };
+    assert_nonneg_errno(bind(listen_fd, (struct sockaddr*)&addr, sizeof(addr)));
+    assert_nonneg_errno(listen(listen_fd, 10));
+
+    int port = get_port(listen_fd);
+    addr.sin_port = htons(port);
+
+    g_assert_true(has_socket("/proc/net/tcp", port, 0, STATE_LISTEN));
+
+    int client_fd = -1;
+    assert_nonneg_errno(client_fd = socket(AF_INET, SOCK_STREAM, 0));
+    assert_nonneg_errno(connect(client_fd, (struct sockaddr*)&addr, sizeof(addr)));
+
+    int server_fd = -1;
+    assert_nonneg_errno(server_fd = accept(listen_fd, NULL, NULL));
+
+    int client_port = get_port(client_fd);
+
+    // both ends of the connection are listed, along with the listening socket
+    g_assert_true(has_socket("/proc/net/tcp", port, 0, STATE_LISTEN));
+    g_assert_true(has_socket("/proc/net/tcp", port, client_port, STATE_ESTABLISHED));
+    g_assert_true(has_socket("/proc/net/tcp", client_port, port, STATE_ESTABLISHED));
+
+    assert_nonneg_errno(close(client_fd));
+    assert_nonneg_errno(close(server_fd));
+    assert_nonneg_errno(close(listen_fd));
+}
+
+static void test_udp() {
+    int fd = -1;
+    assert_nonneg_errno(fd = socket(AF_INET, SOCK_DGRAM, 0));
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+        .sin_port = 0,
+    };
+    assert_nonneg_errno(bind(fd, (struct sockaddr*)&addr, sizeof(addr)));
+
+    int port = get_port(fd);
+    g_assert_true(has_socket("/proc/net/udp", port, 0, STATE_CLOSE));
+
+    // a connected socket is listed as established
+    addr.sin_port = htons(port);
+    assert_nonneg_errno(connect(fd, (struct sockaddr*)&addr, sizeof(addr)));
+    g_assert_true(has_socket("/proc/net/udp", port, port, STATE_ESTABLISHED));
+
+    assert_nonneg_errno(close(fd));
+    g_assert_false(has_socket("/proc/net/udp", port, 0, STATE_ESTABLISHED));
+}
+
+static void test_dev() {
+    long before = get_rx_packets("lo");
+    g_assert_cmpint(before, >=, 0);
+
+    if (running_in_shadow()) {
+        g_assert_cmpint(get_rx_packets("eth0"), >=, 0);
+    }
+
+    int fd = -1;
+    assert_nonneg_errno(fd = socket(AF_INET, SOCK_DGRAM, 0));
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+        .sin_port = 0,
+    };
+    assert_nonneg_errno(bind(fd, (struct sockaddr*)&addr, sizeof(addr)));
+    addr.sin_port = htons(get_port(fd));
+
+    // send a datagram to ourselves over the loopback interface
+    char byte = 0;
+    assert_nonneg_errno(sendto(fd, &byte, 1, 0, (struct sockaddr*)&addr, sizeof(addr)));
+    g_assert_cmpint(recv(fd, &byte, 1, 0), ==, 1);
+
+    long after = get_rx_packets("lo");
+
+    // other sockets on the host may also use the interface when running outside
+    // of shadow
+    if (running_in_shadow()) {
+        g_assert_cmpint(after - before, ==, 1);
+    } else {
+        g_assert_cmpint(after - before, >=, 1);
+    }
+
+    assert_nonneg_errno(close(fd));
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+    // run first so that the packets of the other tests don't affect the
+    // interface counters
+    g_test_add_func("/proc_net/dev", &test_dev);
+    g_test_add_func("/proc_net/tcp", &test_tcp);
+    g_test_add_func("/proc_net/udp", &test_udp);
+    return g_test_run();
+}
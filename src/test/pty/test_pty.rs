@@ -0,0 +1,445 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use nix::errno::Errno;
+use test_utils::{ensure_ord, set, TestEnvironment};
+
+fn main() -> anyhow::Result<()> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
+    let all_envs = set![TestEnvironment::Libc, TestEnvironment::Shadow];
+    vec![
+        test_utils::ShadowTest::new("openpty", test_openpty, all_envs.clone()),
+        test_utils::ShadowTest::new("raw_passthrough", test_raw_passthrough, all_envs.clone()),
+        test_utils::ShadowTest::new("termios", test_termios, all_envs.clone()),
+        test_utils::ShadowTest::new("winsize", test_winsize, all_envs.clone()),
+        test_utils::ShadowTest::new("unlockpt", test_unlockpt, all_envs.clone()),
+        test_utils::ShadowTest::new("slave_hangup", test_slave_hangup, all_envs.clone()),
+        test_utils::ShadowTest::new(
+            "controlling_terminal",
+            test_controlling_terminal,
+            all_envs.clone(),
+        ),
+        test_utils::ShadowTest::new("sigwinch", test_sigwinch, all_envs.clone()),
+        test_utils::ShadowTest::new("kill_process_group", test_kill_process_group, all_envs),
+    ]
+}
+
+/// Opens a new master with `posix_openpt` and returns it with its pty number. The pair is left
+/// locked.
+fn open_master() -> anyhow::Result<(libc::c_int, libc::c_uint)> {
+    let master = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+    Errno::result(master)?;
+
+    let mut index: libc::c_uint = 0;
+    Errno::result(unsafe { libc::ioctl(master, libc::TIOCGPTN, &mut index) })?;
+
+    Ok((master, index))
+}
+
+fn slave_path(index: libc::c_uint) -> std::ffi::CString {
+    std::ffi::CString::new(format!("/dev/pts/{index}")).unwrap()
+}
+
+/// Opens a pty pair with `openpty` and puts the slave into raw mode, so that bytes are passed
+/// through unmodified.
+fn open_raw_pair() -> anyhow::Result<(libc::c_int, libc::c_int)> {
+    let mut master = -1;
+    let mut slave = -1;
+    let rv = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    Errno::result(rv)?;
+
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    Errno::result(unsafe { libc::tcgetattr(slave, &mut termios) })?;
+    unsafe { libc::cfmakeraw(&mut termios) };
+    Errno::result(unsafe { libc::tcsetattr(slave, libc::TCSANOW, &termios) })?;
+
+    Ok((master, slave))
+}
+
+/// Reads exactly `len` bytes. Linux may pass the bytes to the other end of the pty in several
+/// chunks.
+fn read_exact(fd: libc::c_int, len: usize) -> nix::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    let mut offset = 0;
+    while offset < len {
+        match nix::unistd::read(fd, &mut buf[offset..])? {
+            0 => return Err(Errno::EIO),
+            n => offset += n,
+        }
+    }
+    Ok(buf)
+}
+
+fn waitpid(pid: libc::pid_t) -> libc::c_int {
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    status
+}
+
+/// Runs `f` in a forked child and returns the child's exit status.
+fn run_in_child(f: impl FnOnce() -> bool) -> libc::c_int {
+    let child_pid = unsafe { libc::fork() };
+    assert!(child_pid >= 0);
+    if child_pid == 0 {
+        let success = f();
+        unsafe { libc::_exit(if success { 0 } else { 1 }) };
+    }
+    waitpid(child_pid)
+}
+
+fn test_openpty() -> anyhow::Result<()> {
+    let mut master = -1;
+    let mut slave = -1;
+    let rv = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    ensure_ord!(rv, ==, 0);
+
+    ensure_ord!(unsafe { libc::isatty(master) }, ==, 1);
+    ensure_ord!(unsafe { libc::isatty(slave) }, ==, 1);
+
+    // a pipe isn't a terminal
+    let (read_fd, write_fd) = nix::unistd::pipe()?;
+    ensure_ord!(unsafe { libc::isatty(read_fd) }, ==, 0);
+    ensure_ord!(Errno::last(), ==, Errno::ENOTTY);
+
+    for fd in [master, slave, read_fd, write_fd] {
+        nix::unistd::close(fd)?;
+    }
+
+    Ok(())
+}
+
+fn test_raw_passthrough() -> anyhow::Result<()> {
+    let (master, slave) = open_raw_pair()?;
+
+    // control characters and line endings aren't interpreted in raw mode
+    let msg = b"hello\r\n\x03world";
+    ensure_ord!(nix::unistd::write(master, msg), ==, Ok(msg.len()));
+    ensure_ord!(read_exact(slave, msg.len())?, ==, msg);
+
+    let msg = b"reply\n";
+    ensure_ord!(nix::unistd::write(slave, msg), ==, Ok(msg.len()));
+    ensure_ord!(read_exact(master, msg.len())?, ==, msg);
+
+    nix::unistd::close(master)?;
+    nix::unistd::close(slave)?;
+
+    Ok(())
+}
+
+fn test_termios() -> anyhow::Result<()> {
+    let (master, slave) = open_raw_pair()?;
+
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    ensure_ord!(unsafe { libc::tcgetattr(slave, &mut termios) }, ==, 0);
+    ensure_ord!(termios.c_lflag & libc::ECHO, ==, 0);
+    ensure_ord!(termios.c_lflag & libc::ICANON, ==, 0);
+
+    termios.c_lflag |= libc::ECHO;
+    termios.c_cc[libc::VMIN] = 4;
+    ensure_ord!(unsafe { libc::tcsetattr(slave, libc::TCSANOW, &termios) }, ==, 0);
+
+    // the attributes belong to the terminal, not to the descriptor
+    let flags = libc::O_RDWR | libc::O_NOCTTY;
+    let peer = unsafe { libc::ioctl(master, libc::TIOCGPTPEER, flags) };
+    ensure_ord!(peer, >=, 0);
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    ensure_ord!(unsafe { libc::tcgetattr(peer, &mut termios) }, ==, 0);
+    ensure_ord!(termios.c_lflag & libc::ECHO, ==, libc::ECHO);
+    ensure_ord!(termios.c_cc[libc::VMIN], ==, 4);
+
+    for fd in [master, slave, peer] {
+        nix::unistd::close(fd)?;
+    }
+
+    Ok(())
+}
+
+fn test_winsize() -> anyhow::Result<()> {
+    let (master, slave) = open_raw_pair()?;
+
+    let ws = libc::winsize {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 640,
+        ws_ypixel: 480,
+    };
+    ensure_ord!(unsafe { libc::ioctl(master, libc::TIOCSWINSZ, &ws) }, ==, 0);
+
+    let mut got: libc::winsize = unsafe { std::mem::zeroed() };
+    ensure_ord!(unsafe { libc::ioctl(slave, libc::TIOCGWINSZ, &mut got) }, ==, 0);
+    ensure_ord!(
+        (got.ws_row, got.ws_col, got.ws_xpixel, got.ws_ypixel),
+        ==,
+        (24, 80, 640, 480)
+    );
+
+    nix::unistd::close(master)?;
+    nix::unistd::close(slave)?;
+
+    Ok(())
+}
+
+fn test_unlockpt() -> anyhow::Result<()> {
+    let (master, index) = open_master()?;
+    let path = slave_path(index);
+
+    // the slave can't be opened until the pair is unlocked
+    let rv = unsafe { libc::open(path.as_ptr(), libc::O_RDWR | libc::O_NOCTTY) };
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(Errno::last(), ==, Errno::EIO);
+
+    let mut locked: libc::c_int = 0;
+    ensure_ord!(unsafe { libc::ioctl(master, libc::TIOCGPTLCK, &mut locked) }, ==, 0);
+    ensure_ord!(locked, ==, 1);
+
+    ensure_ord!(unsafe { libc::grantpt(master) }, ==, 0);
+    ensure_ord!(unsafe { libc::unlockpt(master) }, ==, 0);
+
+    ensure_ord!(unsafe { libc::ioctl(master, libc::TIOCGPTLCK, &mut locked) }, ==, 0);
+    ensure_ord!(locked, ==, 0);
+
+    let slave = unsafe { libc::open(path.as_ptr(), libc::O_RDWR | libc::O_NOCTTY) };
+    ensure_ord!(slave, >=, 0);
+
+    // the slave can also be opened through the master
+    let flags = libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC;
+    let peer = unsafe { libc::ioctl(master, libc::TIOCGPTPEER, flags) };
+    ensure_ord!(peer, >=, 0);
+    ensure_ord!(
+        unsafe { libc::fcntl(peer, libc::F_GETFD) } & libc::FD_CLOEXEC,
+        ==,
+        libc::FD_CLOEXEC
+    );
+
+    // TIOCGPTPEER isn't valid on the slave
+    let rv = unsafe { libc::ioctl(slave, libc::TIOCGPTPEER, flags) };
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(Errno::last(), ==, Errno::ENOTTY);
+
+    for fd in [master, slave, peer] {
+        nix::unistd::close(fd)?;
+    }
+
+    // the pty no longer exists once the master is closed
+    let rv = unsafe { libc::open(path.as_ptr(), libc::O_RDWR | libc::O_NOCTTY) };
+    ensure_ord!(rv, ==, -1);
+
+    Ok(())
+}
+
+fn test_slave_hangup() -> anyhow::Result<()> {
+    let (master, slave) = open_raw_pair()?;
+
+    nix::unistd::close(slave)?;
+
+    // reading from the master fails once all slave descriptors are closed
+    let mut buf = [0u8; 16];
+    ensure_ord!(nix::unistd::read(master, &mut buf), ==, Err(Errno::EIO));
+
+    nix::unistd::close(master)?;
+
+    Ok(())
+}
+
+fn test_controlling_terminal() -> anyhow::Result<()> {
+    let (master, index) = open_master()?;
+    ensure_ord!(unsafe { libc::unlockpt(master) }, ==, 0);
+    let path = slave_path(index);
+
+    let status = run_in_child(|| {
+        let pid = unsafe { libc::getpid() };
+        if unsafe { libc::setsid() } != pid {
+            return false;
+        }
+
+        // a new session has no controlling terminal
+        let tty_path = std::ffi::CString::new("/dev/tty").unwrap();
+        if unsafe { libc::open(tty_path.as_ptr(), libc::O_RDWR) } != -1 {
+            return false;
+        }
+
+        // the session leader acquires the terminal when opening it without O_NOCTTY
+        let slave = unsafe { libc::open(path.as_ptr(), libc::O_RDWR) };
+        if slave < 0 {
+            return false;
+        }
+
+        if unsafe { libc::tcgetsid(slave) } != pid || unsafe { libc::tcgetpgrp(slave) } != pid {
+            return false;
+        }
+
+        let tty = unsafe { libc::open(tty_path.as_ptr(), libc::O_RDWR) };
+        if tty < 0 || unsafe { libc::tcgetsid(tty) } != pid {
+            return false;
+        }
+
+        // acquiring our own controlling terminal again is a no-op
+        unsafe { libc::ioctl(slave, libc::TIOCSCTTY, 0) == 0 }
+    });
+    ensure_ord!(libc::WIFEXITED(status), ==, true);
+    ensure_ord!(libc::WEXITSTATUS(status), ==, 0);
+
+    // the test process isn't in the child's session
+    let flags = libc::O_RDWR | libc::O_NOCTTY;
+    let slave = unsafe { libc::ioctl(master, libc::TIOCGPTPEER, flags) };
+    ensure_ord!(slave, >=, 0);
+    let mut pgrp: libc::pid_t = 0;
+    ensure_ord!(unsafe { libc::ioctl(slave, libc::TIOCGPGRP, &mut pgrp) }, ==, -1);
+    ensure_ord!(Errno::last(), ==, Errno::ENOTTY);
+
+    nix::unistd::close(slave)?;
+    nix::unistd::close(master)?;
+
+    Ok(())
+}
+
+static SIGWINCH_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn sigwinch_handler(_sig: libc::c_int) {
+    SIGWINCH_RECEIVED.store(true, Ordering::SeqCst);
+}
+
+fn test_sigwinch() -> anyhow::Result<()> {
+    let (master, index) = open_master()?;
+    ensure_ord!(unsafe { libc::unlockpt(master) }, ==, 0);
+    let path = slave_path(index);
+
+    let (read_fd, write_fd) = nix::unistd::pipe()?;
+
+    let child_pid = unsafe { libc::fork() };
+    assert!(child_pid >= 0);
+    if child_pid == 0 {
+        let handler = sigwinch_handler as extern "C" fn(libc::c_int);
+        unsafe { libc::signal(libc::SIGWINCH, handler as libc::sighandler_t) };
+
+        // become the foreground process group of the terminal's session
+        let acquired = unsafe { libc::setsid() } >= 0
+            && unsafe { libc::open(path.as_ptr(), libc::O_RDWR) } >= 0;
+        if !acquired {
+            unsafe { libc::_exit(2) };
+        }
+        assert_eq!(nix::unistd::write(write_fd, &[0]), Ok(1));
+
+        for _ in 0..500 {
+            if SIGWINCH_RECEIVED.load(Ordering::SeqCst) {
+                unsafe { libc::_exit(0) };
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        unsafe { libc::_exit(1) };
+    }
+
+    nix::unistd::close(write_fd)?;
+    let mut buf = [0u8];
+    ensure_ord!(nix::unistd::read(read_fd, &mut buf), ==, Ok(1));
+
+    let ws = libc::winsize {
+        ws_row: 50,
+        ws_col: 132,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    ensure_ord!(unsafe { libc::ioctl(master, libc::TIOCSWINSZ, &ws) }, ==, 0);
+
+    let status = waitpid(child_pid);
+    ensure_ord!(libc::WIFEXITED(status), ==, true);
+    ensure_ord!(libc::WEXITSTATUS(status), ==, 0);
+
+    nix::unistd::close(read_fd)?;
+    nix::unistd::close(master)?;
+
+    Ok(())
+}
+
+/// Forks a child that moves itself into the process group `pgid` (or a new group if 0), and then
+/// waits to be killed. Returns once the child has joined the group.
+fn fork_group_member(pgid: libc::pid_t) -> libc::pid_t {
+    let (read_fd, write_fd) = nix::unistd::pipe().unwrap();
+
+    let child_pid = unsafe { libc::fork() };
+    assert!(child_pid >= 0);
+    if child_pid == 0 {
+        let pgid = if pgid == 0 {
+            unsafe { libc::getpid() }
+        } else {
+            pgid
+        };
+        if unsafe { libc::setpgid(0, pgid) } != 0 {
+            unsafe { libc::_exit(2) };
+        }
+        assert_eq!(nix::unistd::write(write_fd, &[0]), Ok(1));
+        loop {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    nix::unistd::close(write_fd).unwrap();
+    let mut buf = [0u8];
+    assert_eq!(nix::unistd::read(read_fd, &mut buf), Ok(1));
+    nix::unistd::close(read_fd).unwrap();
+
+    child_pid
+}
+
+fn test_kill_process_group() -> anyhow::Result<()> {
+    // signal 0 only checks that our own process group exists
+    ensure_ord!(unsafe { libc::kill(0, 0) }, ==, 0);
+    ensure_ord!(unsafe { libc::killpg(libc::getpgrp(), 0) }, ==, 0);
+
+    let leader = fork_group_member(0);
+    let member = fork_group_member(leader);
+
+    ensure_ord!(unsafe { libc::getpgid(leader) }, ==, leader);
+    ensure_ord!(unsafe { libc::getpgid(member) }, ==, leader);
+
+    ensure_ord!(unsafe { libc::kill(-leader, libc::SIGUSR1) }, ==, 0);
+
+    for pid in [leader, member] {
+        let status = waitpid(pid);
+        ensure_ord!(libc::WIFSIGNALED(status), ==, true);
+        ensure_ord!(libc::WTERMSIG(status), ==, libc::SIGUSR1);
+    }
+
+    // the group no longer has any members
+    ensure_ord!(unsafe { libc::kill(-leader, 0) }, ==, -1);
+    ensure_ord!(Errno::last(), ==, Errno::ESRCH);
+    ensure_ord!(unsafe { libc::killpg(leader, libc::SIGUSR1) }, ==, -1);
+    ensure_ord!(Errno::last(), ==, Errno::ESRCH);
+
+    Ok(())
+}
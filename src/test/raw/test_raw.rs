@@ -0,0 +1,137 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Sends and receives packets with raw sockets (see raw(7)). Must be run as root.
+//!
+//! The only argument is the address of another host to ping.
+
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+
+use nix::sys::socket::{recvfrom, sendto, MsgFlags, SockaddrIn};
+
+const ICMP_ECHO: u8 = 8;
+const ICMP_ECHOREPLY: u8 = 0;
+const ICMP_IDENTIFIER: u16 = 0x4242;
+
+/// The internet checksum (RFC 1071) of `data`.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|x| u32::from(u16::from_be_bytes([x[0], *x.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn raw_socket(protocol: libc::c_int) -> Result<i32, nix::errno::Errno> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_RAW, protocol) };
+    nix::errno::Errno::result(fd)
+}
+
+/// Ping `addr` from a raw ICMP socket, and check the IP header of the reply.
+fn test_ping(addr: Ipv4Addr) {
+    let fd = raw_socket(libc::IPPROTO_ICMP).unwrap();
+
+    // the application computes the checksum, and chooses the identifier
+    let mut request = vec![ICMP_ECHO, 0, 0, 0];
+    request.extend_from_slice(&ICMP_IDENTIFIER.to_be_bytes());
+    request.extend_from_slice(&1u16.to_be_bytes());
+    request.extend_from_slice(b"raw ping");
+    let sum = checksum(&request);
+    request[2..4].copy_from_slice(&sum.to_be_bytes());
+
+    let dst = SockaddrIn::from(SocketAddrV4::new(addr, 0));
+    sendto(fd, &request, &dst, MsgFlags::empty()).unwrap();
+
+    // the socket receives every ICMP packet, so skip any that aren't our reply
+    let mut buf = [0u8; 1024];
+    let packet = loop {
+        let (len, src) = recvfrom::<SockaddrIn>(fd, &mut buf).unwrap();
+        let packet = &buf[..len];
+        let header_len = usize::from(packet[0] & 0xf) * 4;
+        let message = &packet[header_len..];
+        if message[0] == ICMP_ECHOREPLY && message[4..6] == ICMP_IDENTIFIER.to_be_bytes() {
+            assert_eq!(Ipv4Addr::from(src.unwrap().ip()), addr);
+            break packet;
+        }
+    };
+
+    // the packet starts at its IP header, which has a valid checksum
+    assert_eq!(packet[0], 0x45);
+    assert_eq!(packet[9], libc::IPPROTO_ICMP as u8);
+    assert_eq!(packet[12..16], addr.octets());
+    assert_eq!(checksum(&packet[..20]), 0);
+
+    let reply = &packet[20..];
+    assert_eq!(checksum(reply), 0);
+    assert_eq!(u16::from_be_bytes([reply[6], reply[7]]), 1);
+    assert_eq!(&reply[8..], b"raw ping");
+
+    nix::unistd::close(fd).unwrap();
+}
+
+/// Send a UDP packet with a handwritten IP header to a UDP socket, and check that a raw UDP socket
+/// also receives it.
+fn test_header_included() {
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let udp_addr = match udp.local_addr().unwrap() {
+        std::net::SocketAddr::V4(x) => x,
+        x => panic!("Unexpected address {x}"),
+    };
+
+    let raw_udp = raw_socket(libc::IPPROTO_UDP).unwrap();
+    let send_only = raw_socket(libc::IPPROTO_RAW).unwrap();
+
+    let payload = b"raw udp";
+    let total_len = u16::try_from(20 + 8 + payload.len()).unwrap();
+    let udp_len = total_len - 20;
+
+    let mut packet = vec![0x45, 0];
+    packet.extend_from_slice(&total_len.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0x40, 0, 64, libc::IPPROTO_UDP as u8, 0, 0]);
+    packet.extend_from_slice(&Ipv4Addr::LOCALHOST.octets());
+    packet.extend_from_slice(&udp_addr.ip().octets());
+    packet.extend_from_slice(&9999u16.to_be_bytes());
+    packet.extend_from_slice(&udp_addr.port().to_be_bytes());
+    packet.extend_from_slice(&udp_len.to_be_bytes());
+    // no UDP checksum
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(payload);
+
+    let dst = SockaddrIn::from(udp_addr);
+    let len = sendto(send_only, &packet, &dst, MsgFlags::empty()).unwrap();
+    assert_eq!(len, packet.len());
+
+    let mut buf = [0u8; 1024];
+    let (len, src) = udp.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..len], payload);
+    assert_eq!(src, "127.0.0.1:9999".parse().unwrap());
+
+    let (len, _) = recvfrom::<SockaddrIn>(raw_udp, &mut buf).unwrap();
+    assert_eq!(len, packet.len());
+    assert_eq!(&buf[28..len], payload);
+
+    nix::unistd::close(raw_udp).unwrap();
+    nix::unistd::close(send_only).unwrap();
+}
+
+fn main() {
+    let addr: Ipv4Addr = std::env::args().nth(1).unwrap().parse().unwrap();
+
+    test_ping(addr);
+    test_header_included();
+
+    // unsupported protocols
+    assert_eq!(raw_socket(0), Err(nix::errno::Errno::EPROTONOSUPPORT));
+
+    // unprivileged processes can't create raw sockets
+    nix::unistd::seteuid(nix::unistd::Uid::from_raw(1000)).unwrap();
+    assert_eq!(
+        raw_socket(libc::IPPROTO_ICMP),
+        Err(nix::errno::Errno::EPERM)
+    );
+}
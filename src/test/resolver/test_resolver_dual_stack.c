@@ -0,0 +1,77 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests the IPv6 latency, address order, and per-family failures configured by
+// the host's `resolver` options in resolver-dual-stack.yaml. Only runs in
+// Shadow.
+
+#include <glib.h>
+
+#include <netdb.h>
+#include <stdint.h>
+#include <sys/socket.h>
+#include <time.h>
+
+static uint64_t now_ms() {
+    struct timespec ts;
+    g_assert_cmpint(clock_gettime(CLOCK_MONOTONIC, &ts), ==, 0);
+    return ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+// Look up `name` for the address `family`, and check how long the lookup took
+// and the families of the results in order. `expected_families` is terminated
+// by AF_UNSPEC, and is empty if the lookup should fail with EAI_NONAME.
+static void assert_lookup(const char* name, int family, uint64_t expected_ms,
+                          const int* expected_families) {
+    struct addrinfo hints = {.ai_family = family, .ai_socktype = SOCK_STREAM};
+    struct addrinfo* res = NULL;
+
+    uint64_t start = now_ms();
+    int rv = getaddrinfo(name, NULL, &hints, &res);
+    uint64_t elapsed = now_ms() - start;
+
+    g_assert_cmpuint(elapsed, ==, expected_ms);
+
+    if (expected_families[0] == AF_UNSPEC) {
+        g_assert_cmpint(rv, ==, EAI_NONAME);
+        return;
+    }
+    g_assert_cmpint(rv, ==, 0);
+
+    const struct addrinfo* ai = res;
+    for (const int* f = expected_families; *f != AF_UNSPEC; f++) {
+        g_assert_nonnull(ai);
+        g_assert_cmpint(ai->ai_family, ==, *f);
+        ai = ai->ai_next;
+    }
+    g_assert_null(ai);
+
+    freeaddrinfo(res);
+}
+
+static void test_resolver_dual_stack() {
+    const int ipv4[] = {AF_INET, AF_UNSPEC};
+    const int ipv6[] = {AF_INET6, AF_UNSPEC};
+    const int ipv6_first[] = {AF_INET6, AF_INET, AF_UNSPEC};
+    const int none[] = {AF_UNSPEC};
+
+    // each family has its own latency
+    assert_lookup("server", AF_INET, 100, ipv4);
+    assert_lookup("server", AF_INET6, 300, ipv6);
+
+    // the families are looked up one after the other, and the IPv6 address is
+    // listed first
+    assert_lookup("server", AF_UNSPEC, 400, ipv6_first);
+
+    // the failure only applies to the IPv6 lookup
+    assert_lookup("broken", AF_UNSPEC, 400, ipv4);
+    assert_lookup("broken", AF_INET6, 300, none);
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+    g_test_add_func("/resolver_dual_stack", &test_resolver_dual_stack);
+    return g_test_run();
+}
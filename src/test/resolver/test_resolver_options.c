@@ -0,0 +1,58 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests the latency and failures configured by the host's `resolver` options
+// in resolver-options.yaml. Only runs in Shadow.
+
+#include <glib.h>
+
+#include <netdb.h>
+#include <stdint.h>
+#include <time.h>
+#include <unistd.h>
+
+static uint64_t now_ms() {
+    struct timespec ts;
+    g_assert_cmpint(clock_gettime(CLOCK_MONOTONIC, &ts), ==, 0);
+    return ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+// Look up `name` and check the return value and how long the lookup took.
+static void assert_lookup(const char* name, int expected_rv, uint64_t expected_ms) {
+    struct addrinfo hints = {.ai_family = AF_INET, .ai_socktype = SOCK_STREAM};
+    struct addrinfo* res = NULL;
+
+    uint64_t start = now_ms();
+    int rv = getaddrinfo(name, NULL, &hints, &res);
+    uint64_t elapsed = now_ms() - start;
+
+    g_assert_cmpint(rv, ==, expected_rv);
+    g_assert_cmpuint(elapsed, ==, expected_ms);
+    if (rv == 0) {
+        freeaddrinfo(res);
+    }
+}
+
+static void test_resolver_options() {
+    // the process starts at 1 s, during the nxdomain window of "server"
+    assert_lookup("server", EAI_NONAME, 100);
+
+    // after the window ends
+    g_assert_cmpint(usleep(500 * 1000), ==, 0);
+    assert_lookup("server", 0, 100);
+
+    // timeouts take the resolver's timeout instead of its latency
+    assert_lookup("other", EAI_AGAIN, 2000);
+
+    // localhost and the host's own name don't use the resolver
+    assert_lookup("localhost", 0, 0);
+    assert_lookup("client", 0, 0);
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+    g_test_add_func("/resolver_options", &test_resolver_options);
+    return g_test_run();
+}
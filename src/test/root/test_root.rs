@@ -0,0 +1,62 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Uses absolute paths that are resolved in the root directory given in the config. The name of
+//! the root directory is passed as the only argument.
+
+use std::path::Path;
+
+fn main() {
+    let root_name = std::env::args()
+        .nth(1)
+        .expect("missing root directory name");
+
+    // relative paths are resolved in the host's data directory, which isn't in the root directory,
+    // so the root directory can still be reached through its real path
+    let real_root = Path::new("../../..").join(&root_name);
+
+    std::fs::write("/file", &root_name).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(real_root.join("file")).unwrap(),
+        root_name
+    );
+
+    std::fs::create_dir("/dir").unwrap();
+    assert!(std::fs::metadata("/dir").unwrap().is_dir());
+    assert!(real_root.join("dir").is_dir());
+
+    std::fs::rename("/file", "/dir/file").unwrap();
+    assert!(!Path::new("/file").exists());
+
+    // a relative symlink target stays in the root directory
+    std::os::unix::fs::symlink("file", "/dir/link").unwrap();
+    assert_eq!(std::fs::read_link("/dir/link").unwrap(), Path::new("file"));
+    assert_eq!(std::fs::read_to_string("/dir/link").unwrap(), root_name);
+
+    // the other process has written its own files by now, but to its own root directory
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    assert_eq!(std::fs::read_to_string("/dir/file").unwrap(), root_name);
+
+    // the root directory is inherited by child processes
+    let pid = unsafe { libc::fork() };
+    if pid == 0 {
+        let rv = match std::fs::read_to_string("/dir/file") {
+            Ok(x) if x == root_name => 0,
+            _ => 1,
+        };
+        unsafe { libc::_exit(rv) };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status));
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+
+    std::fs::remove_file("/dir/link").unwrap();
+    std::fs::remove_file("/dir/file").unwrap();
+    std::fs::remove_dir("/dir").unwrap();
+    assert!(!real_root.join("dir").exists());
+
+    println!("Success.");
+}
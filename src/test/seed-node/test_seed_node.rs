@@ -0,0 +1,37 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Requests peer addresses from a seed node. The arguments are the seed node's address, followed by
+//! the peer addresses that each response must contain.
+
+use std::collections::BTreeSet;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, seed_node, expected @ ..] = &args[..] else {
+        panic!("Usage: {} <seed node> <peer>...", args[0]);
+    };
+    let expected: BTreeSet<&str> = expected.iter().map(String::as_str).collect();
+
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    socket.connect(seed_node).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    for _ in 0..3 {
+        socket.send(b"peers?").unwrap();
+
+        // one address per line, never including our own
+        let mut buf = [0u8; 1024];
+        let len = socket.recv(&mut buf).unwrap();
+        let response = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(response.ends_with('\n'), "{response:?}");
+        let peers: BTreeSet<&str> = response.lines().collect();
+        assert_eq!(peers, expected);
+    }
+}
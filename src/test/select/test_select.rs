@@ -5,8 +5,11 @@
 #![allow(clippy::too_many_arguments)]
 
 use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
+use nix::sys::signal;
+use nix::sys::signal::Signal;
 use test_utils::set;
 use test_utils::TestEnvironment as TestEnv;
 
@@ -18,6 +21,12 @@ enum SelectFn {
 
 const TEST_STR: &[u8; 4] = b"test";
 
+static SIGUSR1_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn count_sigusr1(_signo: i32) {
+    SIGUSR1_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
 fn fd_write(fd: i32) -> Result<(), String> {
     let num = nix::unistd::write(fd, TEST_STR).map_err(|e| e.to_string())?;
     if num != TEST_STR.len() {
@@ -213,6 +222,92 @@ fn test_regular_file() -> Result<(), String> {
     })
 }
 
+/// Like Linux, `select` should update the timeout with the time that was left, which is zero if it
+/// timed out.
+fn test_select_remaining_time() -> Result<(), String> {
+    let mut timeout = libc::timeval {
+        tv_sec: 0,
+        tv_usec: 50_000,
+    };
+    let rv = test_utils::check_system_call!(
+        || unsafe {
+            libc::select(
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &mut timeout,
+            )
+        },
+        &[]
+    )?;
+
+    test_utils::result_assert_eq(rv, 0, "Expected select to time out")?;
+    test_utils::result_assert_eq(timeout.tv_sec, 0, "Unexpected remaining seconds")?;
+    test_utils::result_assert_eq(timeout.tv_usec, 0, "Unexpected remaining microseconds")?;
+
+    Ok(())
+}
+
+/// A signal blocked by the thread's signal mask but not by the mask given to `pselect` should
+/// interrupt `pselect`, and the thread's mask should be restored afterwards.
+fn test_pselect_sigmask() -> Result<(), String> {
+    unsafe {
+        signal::sigaction(
+            Signal::SIGUSR1,
+            &signal::SigAction::new(
+                signal::SigHandler::Handler(count_sigusr1),
+                signal::SaFlags::empty(),
+                signal::SigSet::empty(),
+            ),
+        )
+    }
+    .unwrap();
+
+    let mut sigusr1 = signal::SigSet::empty();
+    sigusr1.add(Signal::SIGUSR1);
+    signal::pthread_sigmask(signal::SigmaskHow::SIG_BLOCK, Some(&sigusr1), None).unwrap();
+
+    let count_before = SIGUSR1_COUNT.load(Ordering::SeqCst);
+
+    let interruptor = test_utils::Interruptor::new(Duration::from_millis(10), Signal::SIGUSR1);
+    let timeout = libc::timespec {
+        tv_sec: 1,
+        tv_nsec: 0,
+    };
+    let empty = signal::SigSet::empty();
+    let rv = test_utils::check_system_call!(
+        || unsafe {
+            libc::pselect(
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &timeout,
+                empty.as_ref(),
+            )
+        },
+        &[libc::EINTR]
+    );
+    drop(interruptor);
+
+    let mask = signal::SigSet::thread_get_mask().unwrap();
+    signal::pthread_sigmask(signal::SigmaskHow::SIG_UNBLOCK, Some(&sigusr1), None).unwrap();
+    rv?;
+
+    test_utils::result_assert_eq(
+        SIGUSR1_COUNT.load(Ordering::SeqCst),
+        count_before + 1,
+        "The signal handler didn't run",
+    )?;
+    test_utils::result_assert(
+        mask.contains(Signal::SIGUSR1),
+        "The signal mask wasn't restored",
+    )?;
+
+    Ok(())
+}
+
 fn get_selectable_fd() -> Result<libc::c_int, String> {
     // Get an fd we can select
     let fd = test_utils::check_system_call!(
@@ -431,6 +526,16 @@ fn main() -> Result<(), String> {
             test_regular_file,
             set![TestEnv::Libc, TestEnv::Shadow],
         ),
+        test_utils::ShadowTest::new(
+            "test_select_remaining_time",
+            test_select_remaining_time,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "test_pselect_sigmask",
+            test_pselect_sigmask,
+            set![TestEnv::Libc, TestEnv::Shadow],
+        ),
     ];
 
     // For each combination of args, test both select and pselect
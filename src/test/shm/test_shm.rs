@@ -0,0 +1,85 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Uses shared memory objects in the host's `/dev/shm` tmpfs, which is configured with a size of
+//! 1 MiB.
+
+use std::ffi::CStr;
+
+use nix::errno::Errno;
+
+const SHM_SIZE: u64 = 1024 * 1024;
+const TMPFS_MAGIC: i64 = 0x01021994;
+
+fn shm_open(name: &CStr) -> libc::c_int {
+    let fd = unsafe {
+        libc::shm_open(
+            name.as_ptr(),
+            libc::O_RDWR | libc::O_CREAT | libc::O_EXCL,
+            0o600,
+        )
+    };
+    Errno::result(fd).unwrap()
+}
+
+fn ftruncate(fd: libc::c_int, len: u64) -> Result<(), Errno> {
+    Errno::result(unsafe { libc::ftruncate(fd, len.try_into().unwrap()) }).map(|_| ())
+}
+
+fn shared_ram() -> u64 {
+    let mut info = unsafe { std::mem::zeroed::<libc::sysinfo>() };
+    assert_eq!(unsafe { libc::sysinfo(&mut info) }, 0);
+    info.sharedram * u64::from(info.mem_unit)
+}
+
+fn main() {
+    let shared_ram_before = shared_ram();
+
+    // the other host uses the same names, so this would fail if the hosts shared a directory
+    let a_name = CStr::from_bytes_with_nul(b"/test_shm_a\0").unwrap();
+    let b_name = CStr::from_bytes_with_nul(b"/test_shm_b\0").unwrap();
+    let a = shm_open(a_name);
+    let b = shm_open(b_name);
+
+    // the file system is a tmpfs with the configured size
+    let mut stat = unsafe { std::mem::zeroed::<libc::statfs>() };
+    assert_eq!(unsafe { libc::fstatfs(a, &mut stat) }, 0);
+    assert_eq!(stat.f_type, TMPFS_MAGIC);
+    assert_eq!(
+        stat.f_blocks * u64::try_from(stat.f_bsize).unwrap(),
+        SHM_SIZE
+    );
+
+    // the files share the tmpfs's size limit
+    ftruncate(a, SHM_SIZE / 2).unwrap();
+    assert_eq!(ftruncate(b, SHM_SIZE), Err(Errno::ENOSPC));
+    ftruncate(b, SHM_SIZE / 2).unwrap();
+    let rv = unsafe { libc::pwrite(b, [1u8].as_ptr().cast(), 1, (SHM_SIZE / 2) as i64) };
+    assert_eq!(Errno::result(rv), Err(Errno::ENOSPC));
+
+    // the files count against the host's memory
+    assert_eq!(shared_ram(), shared_ram_before + SHM_SIZE);
+
+    // the file's memory isn't freed until it's also closed
+    assert_eq!(unsafe { libc::shm_unlink(a_name.as_ptr()) }, 0);
+    assert_eq!(ftruncate(b, SHM_SIZE / 2 + 1), Err(Errno::ENOSPC));
+    nix::unistd::close(a).unwrap();
+    ftruncate(b, SHM_SIZE).unwrap();
+
+    // the data can be read back through a new descriptor
+    let rv = unsafe { libc::pwrite(b, b"shm".as_ptr().cast(), 3, 0) };
+    assert_eq!(rv, 3);
+    let c = unsafe { libc::shm_open(b_name.as_ptr(), libc::O_RDONLY, 0) };
+    let mut buf = [0u8; 3];
+    assert_eq!(unsafe { libc::pread(c, buf.as_mut_ptr().cast(), 3, 0) }, 3);
+    assert_eq!(&buf, b"shm");
+
+    nix::unistd::close(b).unwrap();
+    nix::unistd::close(c).unwrap();
+    assert_eq!(unsafe { libc::shm_unlink(b_name.as_ptr()) }, 0);
+    assert_eq!(shared_ram(), shared_ram_before);
+
+    println!("Success.");
+}
@@ -0,0 +1,73 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Checks that TCP sockets delay ACKs as configured in `socket-delayed-ack.yaml`. The ACKs aren't
+//! visible to the application, so this measures when nagle's algorithm lets the client send data
+//! that it was holding until its earlier data was acknowledged.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+const QUICK_TIMEOUT: Duration = Duration::from_millis(2);
+const TIMEOUT: Duration = Duration::from_millis(40);
+const MAX_UNACKED_BYTES: usize = 4000;
+
+fn main() {
+    test_timeouts();
+    test_max_unacked_bytes();
+
+    println!("Success.");
+}
+
+/// Returns a connected client and server.
+fn connect() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+/// Reads `len` bytes from `stream`, and returns the time at which the last byte was read.
+fn read_len(stream: &mut TcpStream, len: usize, start: Instant) -> Duration {
+    let mut buf = vec![0u8; len];
+    stream.read_exact(&mut buf).unwrap();
+    start.elapsed()
+}
+
+fn test_timeouts() {
+    let (mut client, mut server) = connect();
+
+    // the server's first delayed ACK is a quick ACK, which releases the held "b"
+    let start = Instant::now();
+    client.write_all(b"a").unwrap();
+    client.write_all(b"b").unwrap();
+    let elapsed = read_len(&mut server, 2, start);
+    println!("First write read at {elapsed:?}");
+    assert!(elapsed >= QUICK_TIMEOUT);
+    assert!(elapsed < QUICK_TIMEOUT + Duration::from_millis(1));
+
+    // "c" and "d" are held together until the ACK of "b", which uses the longer timeout
+    let start = Instant::now();
+    client.write_all(b"c").unwrap();
+    client.write_all(b"d").unwrap();
+    let elapsed = read_len(&mut server, 2, start);
+    println!("Second write read at {elapsed:?}");
+    assert!(elapsed <= TIMEOUT);
+    assert!(elapsed > TIMEOUT - Duration::from_millis(1));
+}
+
+fn test_max_unacked_bytes() {
+    let (mut client, mut server) = connect();
+
+    // the server acknowledges the first write without delay since it reaches the limit of
+    // unacknowledged bytes, so the held "e" is sent immediately
+    let start = Instant::now();
+    client.write_all(&[0; MAX_UNACKED_BYTES]).unwrap();
+    client.write_all(b"e").unwrap();
+    let elapsed = read_len(&mut server, MAX_UNACKED_BYTES + 1, start);
+    println!("Writes read at {elapsed:?}");
+    assert!(elapsed < QUICK_TIMEOUT);
+}
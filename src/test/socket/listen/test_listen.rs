@@ -567,12 +567,8 @@ fn test_backlog_size(domain: libc::c_int, sock_type: libc::c_int) -> Result<(),
         // > (SO_ERROR is zero) or unsuccessfully (SO_ERROR is one of the usual error codes
         // > listed here, explaining the reason for the failure)
 
-        // TODO: always run once shadow supports getsockopt() for unix sockets
-        if !test_utils::running_in_shadow() || domain != libc::AF_UNIX {
-            let error =
-                nix::sys::socket::getsockopt(client_fd_extra, sockopt::SocketError).unwrap();
-            assert_eq!(error, 0);
-        }
+        let error = nix::sys::socket::getsockopt(client_fd_extra, sockopt::SocketError).unwrap();
+        assert_eq!(error, 0);
 
         // close all of the sockets
         for client_fd in &client_fds {
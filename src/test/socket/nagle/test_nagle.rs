@@ -0,0 +1,127 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Checks when small writes are sent over a TCP connection with nagle's algorithm and the
+//! `TCP_NODELAY`, `TCP_CORK`, and `TCP_QUICKACK` socket options. Packets over the loopback
+//! interface arrive without any delay, and shadow delays ACKs by 1 ms.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+const DELAYED_ACK: Duration = Duration::from_millis(1);
+const CORK_MAX_DELAY: Duration = Duration::from_millis(200);
+
+fn main() {
+    test_nagle();
+    test_nodelay();
+    test_quickack();
+    test_cork();
+    test_cork_max_delay();
+
+    println!("Success.");
+}
+
+/// Returns a connected client and server.
+fn connect() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+fn set_tcp_option(stream: &TcpStream, optname: libc::c_int, value: libc::c_int) {
+    let rv = unsafe {
+        libc::setsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_TCP,
+            optname,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+}
+
+/// Writes "a" and then "b" to the client, and returns the time at which each byte is read from the
+/// server.
+fn write_two_bytes(client: &mut TcpStream, server: &mut TcpStream) -> Vec<Duration> {
+    let start = Instant::now();
+    client.write_all(b"a").unwrap();
+    client.write_all(b"b").unwrap();
+
+    let mut times = vec![];
+    let mut data = vec![];
+    while data.len() < 2 {
+        let mut buf = [0u8; 2];
+        let n = server.read(&mut buf).unwrap();
+        assert_ne!(n, 0);
+        data.extend_from_slice(&buf[..n]);
+        times.extend(std::iter::repeat(start.elapsed()).take(n));
+    }
+
+    assert_eq!(data, b"ab");
+    println!("Bytes read at {times:?}");
+    times
+}
+
+fn test_nagle() {
+    let (mut client, mut server) = connect();
+
+    // the second write is held until the first is acknowledged
+    let times = write_two_bytes(&mut client, &mut server);
+    assert!(times[0] < DELAYED_ACK);
+    assert!(times[1] >= DELAYED_ACK);
+    assert!(times[1] < DELAYED_ACK * 2);
+}
+
+fn test_nodelay() {
+    let (mut client, mut server) = connect();
+    set_tcp_option(&client, libc::TCP_NODELAY, 1);
+
+    let times = write_two_bytes(&mut client, &mut server);
+    assert!(times[1] < DELAYED_ACK);
+}
+
+fn test_quickack() {
+    let (mut client, mut server) = connect();
+    set_tcp_option(&server, libc::TCP_QUICKACK, 1);
+
+    // the first write is acknowledged immediately, so the second isn't held for long
+    let times = write_two_bytes(&mut client, &mut server);
+    assert!(times[1] < DELAYED_ACK);
+}
+
+fn test_cork() {
+    let (mut client, mut server) = connect();
+    set_tcp_option(&client, libc::TCP_CORK, 1);
+
+    client.write_all(b"a").unwrap();
+    client.write_all(b"b").unwrap();
+    assert!(!test_utils::is_readable(server.as_raw_fd(), 50).unwrap());
+
+    // both writes are sent in a single segment once the socket is uncorked
+    set_tcp_option(&client, libc::TCP_CORK, 0);
+    let mut buf = [0u8; 2];
+    assert_eq!(server.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf, b"ab");
+}
+
+fn test_cork_max_delay() {
+    let (mut client, mut server) = connect();
+    set_tcp_option(&client, libc::TCP_CORK, 1);
+
+    let start = Instant::now();
+    client.write_all(b"a").unwrap();
+
+    let mut buf = [0u8; 1];
+    assert_eq!(server.read(&mut buf).unwrap(), 1);
+    let elapsed = start.elapsed();
+    println!("Corked byte read at {elapsed:?}");
+
+    assert!(elapsed >= CORK_MAX_DELAY);
+    assert!(elapsed < CORK_MAX_DELAY + DELAYED_ACK);
+}
@@ -0,0 +1,58 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Sends data over TCP and UDP sockets on the loopback interface and checks the data that's
+//! received. With `--zero`, the host's `packet_payload` option is expected to be "zero", so the
+//! data should be received as zero bytes.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, UdpSocket};
+
+const MESSAGE: &[u8] = b"hello world";
+
+fn main() {
+    let zero = std::env::args().any(|x| x == "--zero");
+
+    let expected = if zero {
+        vec![0u8; MESSAGE.len()]
+    } else {
+        MESSAGE.to_vec()
+    };
+
+    test_tcp(&expected);
+    test_udp(&expected);
+
+    println!("Success.");
+}
+
+fn test_tcp(expected: &[u8]) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    client.write_all(MESSAGE).unwrap();
+
+    let mut buf = vec![0xffu8; MESSAGE.len()];
+    server.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, expected);
+
+    // the payload length is kept, so there's no more data to read
+    drop(client);
+    assert_eq!(server.read(&mut buf).unwrap(), 0);
+}
+
+fn test_udp(expected: &[u8]) {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    sender
+        .send_to(MESSAGE, receiver.local_addr().unwrap())
+        .unwrap();
+
+    let mut buf = vec![0xffu8; MESSAGE.len() + 1];
+    let (len, from) = receiver.recv_from(&mut buf).unwrap();
+    assert_eq!(from, sender.local_addr().unwrap());
+    assert_eq!(&buf[..len], expected);
+}
@@ -141,7 +141,7 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
         ),
     ];
 
-    let domains = [libc::AF_INET];
+    let domains = [libc::AF_INET, libc::AF_UNIX];
     let sock_types = [libc::SOCK_STREAM, libc::SOCK_DGRAM];
 
     for &domain in domains.iter() {
@@ -150,16 +150,6 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
             let append_args = |s| format!("{} <domain={},sock_type={}>", s, domain, sock_type);
 
             let more_tests: Vec<test_utils::ShadowTest<_, _>> = vec![
-                test_utils::ShadowTest::new(
-                    &append_args("test_so_sndbuf"),
-                    move || test_so_sndbuf(domain, sock_type),
-                    set![TestEnv::Libc, TestEnv::Shadow],
-                ),
-                test_utils::ShadowTest::new(
-                    &append_args("test_so_rcvbuf"),
-                    move || test_so_rcvbuf(domain, sock_type),
-                    set![TestEnv::Libc, TestEnv::Shadow],
-                ),
                 test_utils::ShadowTest::new(
                     &append_args("test_so_error"),
                     move || test_so_error(domain, sock_type),
@@ -186,26 +176,80 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), String>> {
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
                 test_utils::ShadowTest::new(
-                    &append_args("test_tcp_info"),
-                    move || test_tcp_info(domain, sock_type),
+                    &append_args("test_so_reuseaddr"),
+                    move || test_so_reuseaddr(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
                 test_utils::ShadowTest::new(
-                    &append_args("test_tcp_nodelay"),
-                    move || test_tcp_nodelay(domain, sock_type),
+                    &append_args("test_so_keepalive"),
+                    move || test_so_keepalive(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
                 test_utils::ShadowTest::new(
-                    &append_args("test_tcp_congestion"),
-                    move || test_tcp_congestion(domain, sock_type),
+                    &append_args("test_so_broadcast"),
+                    move || test_so_broadcast(domain, sock_type),
                     set![TestEnv::Libc, TestEnv::Shadow],
                 ),
             ];
 
             tests.extend(more_tests);
+
+            // options that only apply to inet sockets
+            if domain == libc::AF_INET {
+                let more_tests: Vec<test_utils::ShadowTest<_, _>> = vec![
+                    test_utils::ShadowTest::new(
+                        &append_args("test_so_reuseport"),
+                        move || test_so_reuseport(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_so_sndbuf"),
+                        move || test_so_sndbuf(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_so_rcvbuf"),
+                        move || test_so_rcvbuf(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_tcp_info"),
+                        move || test_tcp_info(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_tcp_nodelay"),
+                        move || test_tcp_nodelay(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_tcp_cork"),
+                        move || test_tcp_cork(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_tcp_quickack"),
+                        move || test_tcp_quickack(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                    test_utils::ShadowTest::new(
+                        &append_args("test_tcp_congestion"),
+                        move || test_tcp_congestion(domain, sock_type),
+                        set![TestEnv::Libc, TestEnv::Shadow],
+                    ),
+                ];
+
+                tests.extend(more_tests);
+            }
         }
     }
 
+    tests.push(test_utils::ShadowTest::new(
+        "test_so_error_refused",
+        test_so_error_refused,
+        set![TestEnv::Libc, TestEnv::Shadow],
+    ));
+
     tests
 }
 
@@ -640,9 +684,88 @@ fn test_so_error(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), Stri
 
         test_utils::result_assert_eq(returned_optval, 0, "Expected there to be no socket error")?;
 
-        // We could try to trigger a socket error here and check to see that the value changed.
-        // I tried to do this by making a non-blocking connection to localhost, but it didn't
-        // seem to update the error either within Shadow or outside Shadow.
+        Ok(())
+    })
+}
+
+/// Test that SO_ERROR returns the error from a refused non-blocking connect() once, and is then
+/// cleared.
+fn test_so_error_refused() -> Result<(), String> {
+    // find a port that nothing is listening on
+    let mut addr = libc::sockaddr_in {
+        sin_family: libc::AF_INET as u16,
+        sin_port: 0,
+        sin_addr: libc::in_addr {
+            s_addr: u32::from(std::net::Ipv4Addr::LOCALHOST).to_be(),
+        },
+        sin_zero: [0; 8],
+    };
+    let mut addr_len = std::mem::size_of_val(&addr) as libc::socklen_t;
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+    assert!(fd >= 0);
+    let rv = unsafe {
+        libc::bind(
+            fd,
+            &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    };
+    assert_eq!(rv, 0);
+    let rv = unsafe {
+        libc::getsockname(
+            fd,
+            &mut addr as *mut libc::sockaddr_in as *mut libc::sockaddr,
+            &mut addr_len,
+        )
+    };
+    assert_eq!(rv, 0);
+    assert_eq!(unsafe { libc::close(fd) }, 0);
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+    let optname = libc::SO_ERROR;
+    let optval = 0i32.to_ne_bytes();
+
+    let mut get_args_1 = GetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+    let mut get_args_2 = GetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+
+    test_utils::run_and_close_fds(&[fd], || {
+        test_utils::check_system_call!(
+            || unsafe {
+                libc::connect(
+                    fd,
+                    &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+                    std::mem::size_of_val(&addr) as libc::socklen_t,
+                )
+            },
+            &[libc::EINPROGRESS],
+        )?;
+
+        // wait for the connection to be refused
+        let mut poll_fd = libc::pollfd {
+            fd,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        let rv = unsafe { libc::poll(&mut poll_fd, 1, 1000) };
+        test_utils::result_assert_eq(rv, 1, "Expected the socket to be ready")?;
+
+        check_getsockopt_call(&mut get_args_1, &[])?;
+        check_getsockopt_call(&mut get_args_2, &[])?;
+
+        let value = |args: &GetsockoptArguments| {
+            i32::from_ne_bytes(args.optval.clone().unwrap().try_into().unwrap())
+        };
+
+        test_utils::result_assert_eq(
+            value(&get_args_1),
+            libc::ECONNREFUSED,
+            "Expected the connection to be refused",
+        )?;
+        test_utils::result_assert_eq(value(&get_args_2), 0, "Expected the error to be cleared")?;
 
         Ok(())
     })
@@ -776,6 +899,73 @@ fn test_so_acceptconn(domain: libc::c_int, sock_type: libc::c_int) -> Result<(),
     })
 }
 
+/// Test getsockopt() and setsockopt() using the SO_REUSEADDR option.
+fn test_so_reuseaddr(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    test_so_bool_option(domain, sock_type, libc::SO_REUSEADDR)
+}
+
+/// Test getsockopt() and setsockopt() using the SO_REUSEPORT option.
+fn test_so_reuseport(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    test_so_bool_option(domain, sock_type, libc::SO_REUSEPORT)
+}
+
+/// Test getsockopt() and setsockopt() using the SO_KEEPALIVE option.
+fn test_so_keepalive(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    test_so_bool_option(domain, sock_type, libc::SO_KEEPALIVE)
+}
+
+/// Test getsockopt() and setsockopt() using the SO_BROADCAST option.
+fn test_so_broadcast(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    test_so_bool_option(domain, sock_type, libc::SO_BROADCAST)
+}
+
+/// Test getsockopt() and setsockopt() using a `SOL_SOCKET` option that is initially disabled, and
+/// can be enabled and disabled.
+fn test_so_bool_option(
+    domain: libc::c_int,
+    sock_type: libc::c_int,
+    optname: libc::c_int,
+) -> Result<(), String> {
+    let fd = unsafe { libc::socket(domain, sock_type, 0) };
+    assert!(fd >= 0);
+
+    let level = libc::SOL_SOCKET;
+
+    let one = 1i32.to_ne_bytes();
+    let zero = 0i32.to_ne_bytes();
+
+    let mut get_args_1 = GetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    let mut get_args_2 = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+    let mut get_args_3 = GetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    let mut set_args_1 = SetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    let mut set_args_2 = SetsockoptArguments::new(fd, level, optname, Some(zero.into()));
+
+    // a short optlen is invalid
+    let mut set_args_short = SetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    set_args_short.optlen = 2;
+
+    test_utils::run_and_close_fds(&[fd], || {
+        let value = |args: &GetsockoptArguments| {
+            i32::from_ne_bytes(args.optval.clone().unwrap().try_into().unwrap())
+        };
+
+        check_getsockopt_call(&mut get_args_1, &[])?;
+        test_utils::result_assert_eq(value(&get_args_1), 0, "Unexpected initial value")?;
+
+        check_setsockopt_call(&mut set_args_1, &[])?;
+        check_getsockopt_call(&mut get_args_2, &[])?;
+        test_utils::result_assert_eq(value(&get_args_2), 1, "Unexpected value after enabling")?;
+
+        check_setsockopt_call(&mut set_args_2, &[])?;
+        check_getsockopt_call(&mut get_args_3, &[])?;
+        test_utils::result_assert_eq(value(&get_args_3), 0, "Unexpected value after disabling")?;
+
+        check_setsockopt_call(&mut set_args_short, &[libc::EINVAL])?;
+
+        Ok(())
+    })
+}
+
 /// Test getsockopt() and setsockopt() using the TCP_INFO option.
 fn test_tcp_info(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
@@ -807,19 +997,42 @@ fn test_tcp_info(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), Stri
 
 /// Test getsockopt() and setsockopt() using the TCP_NODELAY option.
 fn test_tcp_nodelay(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    // nagle's algorithm is enabled by default
+    test_tcp_bool_option(domain, sock_type, libc::TCP_NODELAY, &[0])
+}
+
+/// Test getsockopt() and setsockopt() using the TCP_CORK option.
+fn test_tcp_cork(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    test_tcp_bool_option(domain, sock_type, libc::TCP_CORK, &[0])
+}
+
+/// Test getsockopt() and setsockopt() using the TCP_QUICKACK option.
+fn test_tcp_quickack(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), String> {
+    // in shadow will return 0, but in linux will return 1
+    test_tcp_bool_option(domain, sock_type, libc::TCP_QUICKACK, &[0, 1])
+}
+
+/// Test getsockopt() and setsockopt() using a TCP option that can be enabled and disabled. The
+/// option's initial value must be one of `initial_values`.
+fn test_tcp_bool_option(
+    domain: libc::c_int,
+    sock_type: libc::c_int,
+    optname: libc::c_int,
+    initial_values: &[u32],
+) -> Result<(), String> {
     let fd = unsafe { libc::socket(domain, sock_type, 0) };
     assert!(fd >= 0);
 
     let level = libc::SOL_TCP;
-    let optname = libc::TCP_NODELAY;
 
-    // shadow doesn't support setting a value of 0
-    let optval = 1i32.to_ne_bytes();
+    let one = 1i32.to_ne_bytes();
     let zero = 0i32.to_ne_bytes();
 
     let mut get_args_1 = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
     let mut get_args_2 = GetsockoptArguments::new(fd, level, optname, Some(zero.into()));
-    let mut set_args = SetsockoptArguments::new(fd, level, optname, Some(optval.into()));
+    let mut get_args_3 = GetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    let mut set_args_1 = SetsockoptArguments::new(fd, level, optname, Some(one.into()));
+    let mut set_args_2 = SetsockoptArguments::new(fd, level, optname, Some(zero.into()));
 
     test_utils::run_and_close_fds(&[fd], || {
         let expected_errnos = if sock_type == libc::SOCK_STREAM {
@@ -827,20 +1040,36 @@ fn test_tcp_nodelay(domain: libc::c_int, sock_type: libc::c_int) -> Result<(), S
         } else {
             vec![libc::ENOPROTOOPT, libc::EOPNOTSUPP]
         };
+
+        let value = |args: &GetsockoptArguments| {
+            u32::from_ne_bytes(args.optval.clone().unwrap().try_into().unwrap())
+        };
+
         check_getsockopt_call(&mut get_args_1, &expected_errnos)?;
 
         if sock_type == libc::SOCK_STREAM {
-            // in shadow will return 1, but in linux will return 0
-            let value = u32::from_ne_bytes(get_args_1.optval.unwrap().try_into().unwrap());
-            test_utils::result_assert([0, 1].contains(&value), "Unexpected value for TCP_NODELAY")?;
+            test_utils::result_assert(
+                initial_values.contains(&value(&get_args_1)),
+                "Unexpected initial value",
+            )?;
         }
 
-        check_setsockopt_call(&mut set_args, &expected_errnos)?;
+        check_setsockopt_call(&mut set_args_1, &expected_errnos)?;
         check_getsockopt_call(&mut get_args_2, &expected_errnos)?;
 
         if sock_type == libc::SOCK_STREAM {
-            let value = u32::from_ne_bytes(get_args_2.optval.unwrap().try_into().unwrap());
-            test_utils::result_assert_eq(value, 1, "Unexpected value for TCP_NODELAY")?;
+            test_utils::result_assert_eq(value(&get_args_2), 1, "Unexpected value after enabling")?;
+        }
+
+        check_setsockopt_call(&mut set_args_2, &expected_errnos)?;
+        check_getsockopt_call(&mut get_args_3, &expected_errnos)?;
+
+        if sock_type == libc::SOCK_STREAM {
+            test_utils::result_assert_eq(
+                value(&get_args_3),
+                0,
+                "Unexpected value after disabling",
+            )?;
         }
 
         Ok(())
@@ -0,0 +1,33 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Checks that the syscall latencies configured in `socket-syscall-latency.yaml` are added to the
+//! simulated time.
+
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 10;
+const SOCKET_LATENCY: Duration = Duration::from_millis(1);
+const CLOSE_LATENCY: Duration = Duration::from_millis(2);
+
+fn main() {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+        assert!(fd >= 0);
+        assert_eq!(unsafe { libc::close(fd) }, 0);
+    }
+
+    let elapsed = start.elapsed();
+    let expected = (SOCKET_LATENCY + CLOSE_LATENCY) * ITERATIONS;
+    println!("Elapsed time {elapsed:?}, expected at least {expected:?}");
+
+    // no other syscalls add latency since `model_unblocked_syscall_latency` is disabled
+    assert!(elapsed >= expected);
+    assert!(elapsed < expected + SOCKET_LATENCY);
+
+    println!("Success.");
+}
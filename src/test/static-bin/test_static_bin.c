@@ -0,0 +1,205 @@
+/*
+ * Exercises a statically linked executable, which Shadow runs without the shim.
+ *
+ * Pass "shadow" as the first argument to also check that the time is emulated.
+ */
+
+#include <errno.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/time.h>
+#include <time.h>
+#include <unistd.h>
+
+#define check(cond)                                                                                \
+    do {                                                                                           \
+        if (!(cond)) {                                                                             \
+            fprintf(stderr, "%s:%d: check failed: %s (errno: %s)\n", __FILE__, __LINE__, #cond,    \
+                    strerror(errno));                                                              \
+            exit(EXIT_FAILURE);                                                                    \
+        }                                                                                          \
+    } while (0)
+
+// 2000-01-01 00:00:00 UTC, the simulation's start time.
+#define SIM_START_SECS 946684800
+
+#define NUM_THREADS 4
+
+static void _test_time(bool in_shadow) {
+    struct timespec before, after;
+    check(clock_gettime(CLOCK_MONOTONIC, &before) == 0);
+    struct timespec duration = {.tv_sec = 1};
+    check(nanosleep(&duration, NULL) == 0);
+    check(clock_gettime(CLOCK_MONOTONIC, &after) == 0);
+    long long elapsed_ns = (after.tv_sec - before.tv_sec) * 1000000000LL +
+                           (after.tv_nsec - before.tv_nsec);
+    check(elapsed_ns >= 1000000000LL);
+
+    if (!in_shadow) {
+        return;
+    }
+
+    // Each of these goes through the vDSO when made natively.
+    struct timespec ts;
+    check(clock_gettime(CLOCK_REALTIME, &ts) == 0);
+    check(ts.tv_sec >= SIM_START_SECS && ts.tv_sec < SIM_START_SECS + 3600);
+    struct timeval tv;
+    check(gettimeofday(&tv, NULL) == 0);
+    check(tv.tv_sec >= SIM_START_SECS && tv.tv_sec < SIM_START_SECS + 3600);
+    time_t t = time(NULL);
+    check(t >= SIM_START_SECS && t < SIM_START_SECS + 3600);
+
+    // The sleep takes exactly one second of simulated time, plus the small latency that Shadow
+    // charges for the syscalls.
+    check(elapsed_ns < 1001000000LL);
+}
+
+static int _thread_counter = 0;
+static pthread_mutex_t _thread_counter_lock = PTHREAD_MUTEX_INITIALIZER;
+
+static void* _thread_main(void* arg) {
+    struct timespec duration = {.tv_nsec = 1000000};
+    check(nanosleep(&duration, NULL) == 0);
+    check(pthread_mutex_lock(&_thread_counter_lock) == 0);
+    _thread_counter += (int)(long)arg;
+    check(pthread_mutex_unlock(&_thread_counter_lock) == 0);
+    return arg;
+}
+
+static void _test_threads() {
+    pthread_t threads[NUM_THREADS];
+    for (long i = 0; i < NUM_THREADS; i++) {
+        check(pthread_create(&threads[i], NULL, _thread_main, (void*)(i + 1)) == 0);
+    }
+    for (long i = 0; i < NUM_THREADS; i++) {
+        void* retval;
+        check(pthread_join(threads[i], &retval) == 0);
+        check(retval == (void*)(i + 1));
+    }
+    check(_thread_counter == NUM_THREADS * (NUM_THREADS + 1) / 2);
+}
+
+static volatile sig_atomic_t _signal_count = 0;
+
+static void _signal_handler(int signo) { _signal_count++; }
+
+struct _pipe_writer_args {
+    pthread_t reader;
+    int signo;
+    int write_fd;
+};
+
+// Interrupts the reader with a signal, and then gives it something to read.
+static void* _pipe_writer_main(void* arg) {
+    struct _pipe_writer_args* args = arg;
+    struct timespec duration = {.tv_nsec = 10000000};
+    check(nanosleep(&duration, NULL) == 0);
+    check(pthread_kill(args->reader, args->signo) == 0);
+    check(nanosleep(&duration, NULL) == 0);
+    check(write(args->write_fd, "x", 1) == 1);
+    return NULL;
+}
+
+// Reads from a pipe while another thread sends the signal `signo`, and returns the result of the
+// read.
+static ssize_t _read_interrupted_by(int signo) {
+    int fds[2];
+    check(pipe(fds) == 0);
+    struct _pipe_writer_args args = {
+        .reader = pthread_self(),
+        .signo = signo,
+        .write_fd = fds[1],
+    };
+    pthread_t writer;
+    check(pthread_create(&writer, NULL, _pipe_writer_main, &args) == 0);
+
+    char c;
+    ssize_t rv = read(fds[0], &c, 1);
+    int saved_errno = errno;
+    check(pthread_join(writer, NULL) == 0);
+    check(close(fds[0]) == 0);
+    check(close(fds[1]) == 0);
+
+    errno = saved_errno;
+    return rv;
+}
+
+static void _test_signals() {
+    struct sigaction action = {.sa_handler = _signal_handler};
+    check(sigaction(SIGUSR1, &action, NULL) == 0);
+    check(raise(SIGUSR1) == 0);
+    check(_signal_count == 1);
+
+    // A blocked signal is delivered once it's unblocked.
+    sigset_t set;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    check(sigprocmask(SIG_BLOCK, &set, NULL) == 0);
+    check(raise(SIGUSR1) == 0);
+    check(_signal_count == 1);
+    check(sigprocmask(SIG_UNBLOCK, &set, NULL) == 0);
+    check(_signal_count == 2);
+
+    // Without `SA_RESTART`, the handler interrupts a blocking syscall.
+    check(_read_interrupted_by(SIGUSR1) == -1 && errno == EINTR);
+    check(_signal_count == 3);
+
+    // With `SA_RESTART`, the syscall continues after the handler.
+    struct sigaction restart_action = {.sa_handler = _signal_handler, .sa_flags = SA_RESTART};
+    check(sigaction(SIGUSR2, &restart_action, NULL) == 0);
+    check(_read_interrupted_by(SIGUSR2) == 1);
+    check(_signal_count == 4);
+
+    // An ignored signal does nothing.
+    struct sigaction ignore_action = {.sa_handler = SIG_IGN};
+    check(sigaction(SIGUSR1, &ignore_action, NULL) == 0);
+    check(raise(SIGUSR1) == 0);
+    check(_signal_count == 4);
+}
+
+static void _test_mmap() {
+    char path[] = "static-bin-mmap-XXXXXX";
+    int fd = mkstemp(path);
+    check(fd >= 0);
+    const char contents[] = "mapped file contents";
+    check(write(fd, contents, sizeof(contents)) == sizeof(contents));
+
+    char* shared = mmap(NULL, sizeof(contents), PROT_READ, MAP_SHARED, fd, 0);
+    check(shared != MAP_FAILED);
+    check(memcmp(shared, contents, sizeof(contents)) == 0);
+
+    // A write through the file is visible in the mapping.
+    check(pwrite(fd, "M", 1, 0) == 1);
+    check(shared[0] == 'M');
+    check(munmap(shared, sizeof(contents)) == 0);
+
+    char* private = mmap(NULL, sizeof(contents), PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    check(private != MAP_FAILED);
+    check(private[0] == 'M');
+    check(munmap(private, sizeof(contents)) == 0);
+
+    check(close(fd) == 0);
+    check(unlink(path) == 0);
+
+    char* anonymous = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    check(anonymous != MAP_FAILED);
+    anonymous[4095] = 1;
+    check(munmap(anonymous, 4096) == 0);
+}
+
+int main(int argc, char* argv[]) {
+    bool in_shadow = argc > 1 && strcmp(argv[1], "shadow") == 0;
+
+    _test_time(in_shadow);
+    _test_threads();
+    _test_signals();
+    _test_mmap();
+
+    printf("Hello from a statically linked executable\n");
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,86 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Sends broadcast datagrams and bulk TCP data between hosts attached to a network graph node.
+//!
+//! The arguments are the host's role and the role's arguments: "broadcaster", "receiver",
+//! "non-receiver", "bulk-sender <host>", or "bulk-receiver <min ms>".
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+const BROADCAST_PORT: u16 = 5000;
+const BULK_PORT: u16 = 5001;
+const BULK_SIZE: usize = 1_250_000;
+
+fn broadcaster() {
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let dst = (Ipv4Addr::BROADCAST, BROADCAST_PORT);
+
+    // like linux, sending to the broadcast address requires SO_BROADCAST
+    let err = socket.send_to(b"hello", dst).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EACCES));
+
+    socket.set_broadcast(true).unwrap();
+    socket.send_to(b"hello", dst).unwrap();
+}
+
+fn bind_broadcast_receiver() -> UdpSocket {
+    let socket = UdpSocket::bind(("0.0.0.0", BROADCAST_PORT)).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    socket
+}
+
+fn receiver() {
+    let socket = bind_broadcast_receiver();
+    let mut buf = [0u8; 64];
+    let len = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"hello");
+}
+
+fn non_receiver() {
+    let socket = bind_broadcast_receiver();
+    let mut buf = [0u8; 64];
+    let err = socket.recv(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+}
+
+fn bulk_sender(host: &str) {
+    let mut stream = TcpStream::connect((host, BULK_PORT)).unwrap();
+    stream.write_all(&vec![0u8; BULK_SIZE]).unwrap();
+}
+
+fn bulk_receiver(min_duration: Duration) {
+    let listener = TcpListener::bind(("0.0.0.0", BULK_PORT)).unwrap();
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let start = Instant::now();
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    let elapsed = start.elapsed();
+    assert_eq!(buf.len(), BULK_SIZE);
+
+    println!("Received {BULK_SIZE} bytes in {elapsed:?}");
+    assert!(
+        elapsed >= min_duration,
+        "Received {BULK_SIZE} bytes in {elapsed:?}, expected at least {min_duration:?}"
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match &args[1..] {
+        ["broadcaster"] => broadcaster(),
+        ["receiver"] => receiver(),
+        ["non-receiver"] => non_receiver(),
+        ["bulk-sender", host] => bulk_sender(host),
+        ["bulk-receiver", min_ms] => bulk_receiver(Duration::from_millis(min_ms.parse().unwrap())),
+        _ => panic!("Unexpected arguments: {:?}", &args[1..]),
+    }
+}
@@ -0,0 +1,13 @@
+/*
+ * Sends messages to the host's syslog socket at /dev/log using the libc syslog functions.
+ */
+
+#include <syslog.h>
+
+int main(void) {
+    openlog("test-syslog", LOG_NDELAY, LOG_USER);
+    syslog(LOG_INFO, "hello from syslog");
+    syslog(LOG_WARNING, "goodbye from syslog");
+    closelog();
+    return 0;
+}
@@ -0,0 +1,124 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests that the window scale and timestamps options are negotiated in the TCP
+// handshake, and that the `tcp_window_scaling` and `tcp_timestamps` host
+// options in tcp-options.yaml disable them. Only runs in Shadow.
+
+#include <glib.h>
+
+#include <arpa/inet.h>
+#include <netdb.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define PORT 8080
+
+// The window scale that allows a 6 MiB receive window, as in linux.
+#define WINDOW_SCALE 7
+
+// The maximum segment size with and without the 12-byte timestamps option.
+#define MSS_TIMESTAMPS 1448
+#define MSS 1460
+
+static gboolean use_options;
+
+static void assert_options(int fd, gboolean expected) {
+    struct tcp_info info = {0};
+    socklen_t len = sizeof(info);
+    g_assert_cmpint(getsockopt(fd, SOL_TCP, TCP_INFO, &info, &len), ==, 0);
+
+    if (expected) {
+        g_assert_cmpuint(info.tcpi_options & TCPI_OPT_TIMESTAMPS, !=, 0);
+        g_assert_cmpuint(info.tcpi_options & TCPI_OPT_WSCALE, !=, 0);
+        g_assert_cmpuint(info.tcpi_snd_wscale, ==, WINDOW_SCALE);
+        g_assert_cmpuint(info.tcpi_rcv_wscale, ==, WINDOW_SCALE);
+        g_assert_cmpuint(info.tcpi_snd_mss, ==, MSS_TIMESTAMPS);
+    } else {
+        g_assert_cmpuint(info.tcpi_options & TCPI_OPT_TIMESTAMPS, ==, 0);
+        g_assert_cmpuint(info.tcpi_options & TCPI_OPT_WSCALE, ==, 0);
+        g_assert_cmpuint(info.tcpi_snd_mss, ==, MSS);
+    }
+}
+
+// Send a byte and wait for it to be echoed back.
+static void exchange_byte(int fd, gboolean is_client) {
+    char byte = 'a';
+    if (is_client) {
+        g_assert_cmpint(write(fd, &byte, 1), ==, 1);
+        g_assert_cmpint(read(fd, &byte, 1), ==, 1);
+    } else {
+        g_assert_cmpint(read(fd, &byte, 1), ==, 1);
+        g_assert_cmpint(write(fd, &byte, 1), ==, 1);
+    }
+    g_assert_cmpint(byte, ==, 'a');
+}
+
+// Accept a connection from the client with the options enabled, and then from
+// the client with the options disabled.
+static void test_server() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    g_assert_cmpint(listen_fd, >=, 0);
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_ANY),
+        .sin_port = htons(PORT),
+    };
+    g_assert_cmpint(bind(listen_fd, (struct sockaddr*)&addr, sizeof(addr)), ==, 0);
+    g_assert_cmpint(listen(listen_fd, 10), ==, 0);
+
+    for (int i = 0; i < 2; i++) {
+        int fd = accept(listen_fd, NULL, NULL);
+        g_assert_cmpint(fd, >=, 0);
+
+        // the server offers both options, but they're only used if the client
+        // also offers them
+        assert_options(fd, i == 0);
+        exchange_byte(fd, FALSE);
+        g_assert_cmpint(close(fd), ==, 0);
+    }
+
+    g_assert_cmpint(close(listen_fd), ==, 0);
+}
+
+static void test_client(const void* server) {
+    struct addrinfo hints = {.ai_family = AF_INET, .ai_socktype = SOCK_STREAM};
+    struct addrinfo* res = NULL;
+    g_assert_cmpint(getaddrinfo(server, NULL, &hints, &res), ==, 0);
+
+    struct sockaddr_in addr = *(struct sockaddr_in*)res->ai_addr;
+    addr.sin_port = htons(PORT);
+    freeaddrinfo(res);
+
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    g_assert_cmpint(fd, >=, 0);
+    g_assert_cmpint(connect(fd, (struct sockaddr*)&addr, sizeof(addr)), ==, 0);
+
+    assert_options(fd, use_options);
+    exchange_byte(fd, TRUE);
+    g_assert_cmpint(close(fd), ==, 0);
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+
+    // usage: test-tcp-options server
+    //        test-tcp-options client <server> <options-enabled>
+    if (argc == 2 && strcmp(argv[1], "server") == 0) {
+        g_test_add_func("/tcp_options/server", &test_server);
+    } else if (argc == 4 && strcmp(argv[1], "client") == 0) {
+        use_options = atoi(argv[3]) != 0;
+        g_test_add_data_func("/tcp_options/client", argv[2], &test_client);
+    } else {
+        g_error("Invalid arguments");
+    }
+
+    return g_test_run();
+}
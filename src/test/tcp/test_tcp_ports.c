@@ -0,0 +1,168 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests that ephemeral ports are chosen from the host's port range, and that
+// running out of them fails like on Linux. The client host in tcp-ports.yaml
+// only has NUM_PORTS ephemeral ports, and its TCP connections stay in the
+// TIME_WAIT state for TIME_WAIT_SECS seconds:
+//
+// - "timeout": once all of the ports are held by connections in TIME_WAIT,
+//   connecting fails until the connections time out.
+// - "reuse": with TIME_WAIT reuse enabled, a new connection takes the port of
+//   a connection in TIME_WAIT.
+//
+// Only runs in Shadow.
+
+#include <glib.h>
+
+#include <errno.h>
+#include <netdb.h>
+#include <netinet/in.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define PORT 8080
+#define MIN_PORT 20000
+#define NUM_PORTS 3
+#define TIME_WAIT_SECS 10
+
+static const char* mode;
+
+static void test_server() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    g_assert_cmpint(listen_fd, >=, 0);
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_ANY),
+        .sin_port = htons(PORT),
+    };
+    g_assert_cmpint(bind(listen_fd, (struct sockaddr*)&addr, sizeof(addr)), ==, 0);
+    g_assert_cmpint(listen(listen_fd, 10), ==, 0);
+
+    // the client closes each connection first, so that its side of the
+    // connection is the one that enters TIME_WAIT
+    for (int i = 0; i < NUM_PORTS + 1; i++) {
+        int fd = accept(listen_fd, NULL, NULL);
+        g_assert_cmpint(fd, >=, 0);
+
+        char byte = 0;
+        g_assert_cmpint(read(fd, &byte, 1), ==, 0);
+        g_assert_cmpint(close(fd), ==, 0);
+    }
+
+    g_assert_cmpint(close(listen_fd), ==, 0);
+}
+
+// Returns the local port of the new connection, or -1 with errno set if the
+// connection failed.
+static int connect_and_close(const struct sockaddr_in* addr) {
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    g_assert_cmpint(fd, >=, 0);
+
+    if (connect(fd, (struct sockaddr*)addr, sizeof(*addr)) != 0) {
+        int error = errno;
+        g_assert_cmpint(close(fd), ==, 0);
+        errno = error;
+        return -1;
+    }
+
+    struct sockaddr_in local = {0};
+    socklen_t len = sizeof(local);
+    g_assert_cmpint(getsockname(fd, (struct sockaddr*)&local, &len), ==, 0);
+    g_assert_cmpint(close(fd), ==, 0);
+
+    return ntohs(local.sin_port);
+}
+
+static void assert_udp_ports_exhausted(const struct sockaddr_in* server) {
+    int fds[NUM_PORTS] = {0};
+    for (int i = 0; i < NUM_PORTS; i++) {
+        fds[i] = socket(AF_INET, SOCK_DGRAM, 0);
+        g_assert_cmpint(fds[i], >=, 0);
+
+        struct sockaddr_in addr = {.sin_family = AF_INET, .sin_port = 0};
+        g_assert_cmpint(bind(fds[i], (struct sockaddr*)&addr, sizeof(addr)), ==, 0);
+    }
+
+    int fd = socket(AF_INET, SOCK_DGRAM, 0);
+    g_assert_cmpint(fd, >=, 0);
+
+    struct sockaddr_in addr = {.sin_family = AF_INET, .sin_port = 0};
+    g_assert_cmpint(bind(fd, (struct sockaddr*)&addr, sizeof(addr)), ==, -1);
+    g_assert_cmpint(errno, ==, EADDRINUSE);
+
+    char byte = 0;
+    g_assert_cmpint(
+        sendto(fd, &byte, 1, 0, (struct sockaddr*)server, sizeof(*server)), ==, -1);
+    g_assert_cmpint(errno, ==, EAGAIN);
+
+    g_assert_cmpint(connect(fd, (struct sockaddr*)server, sizeof(*server)), ==, -1);
+    g_assert_cmpint(errno, ==, EAGAIN);
+
+    g_assert_cmpint(close(fd), ==, 0);
+    for (int i = 0; i < NUM_PORTS; i++) {
+        g_assert_cmpint(close(fds[i]), ==, 0);
+    }
+}
+
+static void test_client(const void* server) {
+    struct addrinfo hints = {.ai_family = AF_INET, .ai_socktype = SOCK_STREAM};
+    struct addrinfo* res = NULL;
+    g_assert_cmpint(getaddrinfo(server, NULL, &hints, &res), ==, 0);
+
+    struct sockaddr_in addr = *(struct sockaddr_in*)res->ai_addr;
+    addr.sin_port = htons(PORT);
+    freeaddrinfo(res);
+
+    // each connection gets a different port from the range, since the earlier
+    // connections are still in TIME_WAIT
+    int ports[NUM_PORTS] = {0};
+    for (int i = 0; i < NUM_PORTS; i++) {
+        ports[i] = connect_and_close(&addr);
+        g_assert_cmpint(ports[i], >=, MIN_PORT);
+        g_assert_cmpint(ports[i], <, MIN_PORT + NUM_PORTS);
+        for (int j = 0; j < i; j++) {
+            g_assert_cmpint(ports[i], !=, ports[j]);
+        }
+    }
+
+    // wait for the connections to finish closing
+    sleep(2);
+
+    if (strcmp(mode, "timeout") == 0) {
+        g_assert_cmpint(connect_and_close(&addr), ==, -1);
+        g_assert_cmpint(errno, ==, EADDRNOTAVAIL);
+
+        // the UDP ports are separate from the TCP ports
+        assert_udp_ports_exhausted(&addr);
+
+        // wait for the connections to leave TIME_WAIT
+        sleep(TIME_WAIT_SECS);
+    }
+
+    int port = connect_and_close(&addr);
+    g_assert_cmpint(port, >=, MIN_PORT);
+    g_assert_cmpint(port, <, MIN_PORT + NUM_PORTS);
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+
+    // usage: test-tcp-ports server
+    //        test-tcp-ports client <mode> <server>
+    if (argc == 2 && strcmp(argv[1], "server") == 0) {
+        g_test_add_func("/tcp_ports/server", &test_server);
+    } else if (argc == 4 && strcmp(argv[1], "client") == 0) {
+        mode = argv[2];
+        g_test_add_data_func("/tcp_ports/client", argv[3], &test_client);
+    } else {
+        g_error("Invalid arguments");
+    }
+
+    return g_test_run();
+}
@@ -0,0 +1,248 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests the SYN and accept queues of listening sockets, using the host options
+// in tcp-queues.yaml:
+//
+// - "cookie": the SYN queue has no room, so connections are opened with SYN
+//   cookies, which don't negotiate any TCP options.
+// - "drop": the SYN queue has no room and SYN cookies are disabled, so the
+//   client can't connect.
+// - "overflow": the server is slow to accept, so one of the handshakes
+//   completes while the accept queue is full. The server ignores its final ACK,
+//   and the connection is accepted once the server accepts another.
+// - "abort": like "overflow", but the server resets the connection instead.
+//
+// Only runs in Shadow.
+
+#include <glib.h>
+
+#include <errno.h>
+#include <fcntl.h>
+#include <netdb.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <poll.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#define PORT 8080
+#define BACKLOG 1
+
+static const char* mode;
+
+static struct tcp_info get_info(int fd) {
+    struct tcp_info info = {0};
+    socklen_t len = sizeof(info);
+    g_assert_cmpint(getsockopt(fd, SOL_TCP, TCP_INFO, &info, &len), ==, 0);
+    return info;
+}
+
+// Connections opened with SYN cookies don't use the timestamps or window scale
+// options.
+static void assert_no_options(int fd) {
+    struct tcp_info info = get_info(fd);
+    g_assert_cmpuint(info.tcpi_options & TCPI_OPT_TIMESTAMPS, ==, 0);
+    g_assert_cmpuint(info.tcpi_options & TCPI_OPT_WSCALE, ==, 0);
+}
+
+// Read a byte and echo it back.
+static void echo_byte(int fd) {
+    char byte = 0;
+    g_assert_cmpint(read(fd, &byte, 1), ==, 1);
+    g_assert_cmpint(write(fd, &byte, 1), ==, 1);
+}
+
+static void set_nonblocking(int fd) {
+    int flags = fcntl(fd, F_GETFL);
+    g_assert_cmpint(flags, >=, 0);
+    g_assert_cmpint(fcntl(fd, F_SETFL, flags | O_NONBLOCK), ==, 0);
+}
+
+static void assert_nothing_to_accept(int listen_fd) {
+    set_nonblocking(listen_fd);
+    g_assert_cmpint(accept(listen_fd, NULL, NULL), ==, -1);
+    g_assert_cmpint(errno, ==, EAGAIN);
+}
+
+static void test_server() {
+    int listen_fd = socket(AF_INET, SOCK_STREAM, 0);
+    g_assert_cmpint(listen_fd, >=, 0);
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_ANY),
+        .sin_port = htons(PORT),
+    };
+    g_assert_cmpint(bind(listen_fd, (struct sockaddr*)&addr, sizeof(addr)), ==, 0);
+    g_assert_cmpint(listen(listen_fd, BACKLOG), ==, 0);
+
+    if (strcmp(mode, "cookie") == 0) {
+        int fd = accept(listen_fd, NULL, NULL);
+        g_assert_cmpint(fd, >=, 0);
+        assert_no_options(fd);
+        echo_byte(fd);
+        g_assert_cmpint(close(fd), ==, 0);
+    } else if (strcmp(mode, "drop") == 0) {
+        // the client gives up before then
+        sleep(4);
+        assert_nothing_to_accept(listen_fd);
+    } else {
+        // don't accept until the client has tried to open all of its connections
+        sleep(3);
+
+        // the listener reports the length and limit of its accept queue
+        struct tcp_info info = get_info(listen_fd);
+        g_assert_cmpuint(info.tcpi_unacked, ==, BACKLOG + 1);
+        g_assert_cmpuint(info.tcpi_sacked, ==, BACKLOG);
+
+        // the connection that overflowed the accept queue is only accepted if
+        // it wasn't reset
+        int num_connections = strcmp(mode, "overflow") == 0 ? 3 : 2;
+        for (int i = 0; i < num_connections; i++) {
+            int fd = accept(listen_fd, NULL, NULL);
+            g_assert_cmpint(fd, >=, 0);
+            echo_byte(fd);
+            g_assert_cmpint(close(fd), ==, 0);
+        }
+
+        if (strcmp(mode, "abort") == 0) {
+            assert_nothing_to_accept(listen_fd);
+        }
+    }
+
+    g_assert_cmpint(close(listen_fd), ==, 0);
+}
+
+static void wait_until_connected(int fd) {
+    struct pollfd poll_fd = {.fd = fd, .events = POLLOUT};
+    g_assert_cmpint(poll(&poll_fd, 1, 1000), ==, 1);
+    g_assert_cmpint(poll_fd.revents & POLLOUT, !=, 0);
+
+    int error = -1;
+    socklen_t len = sizeof(error);
+    g_assert_cmpint(getsockopt(fd, SOL_SOCKET, SO_ERROR, &error, &len), ==, 0);
+    g_assert_cmpint(error, ==, 0);
+}
+
+static void test_client(const void* server) {
+    struct addrinfo hints = {.ai_family = AF_INET, .ai_socktype = SOCK_STREAM};
+    struct addrinfo* res = NULL;
+    g_assert_cmpint(getaddrinfo(server, NULL, &hints, &res), ==, 0);
+
+    struct sockaddr_in addr = *(struct sockaddr_in*)res->ai_addr;
+    addr.sin_port = htons(PORT);
+    freeaddrinfo(res);
+
+    int fds[3] = {0};
+    for (int i = 0; i < 3; i++) {
+        fds[i] = socket(AF_INET, SOCK_STREAM | SOCK_NONBLOCK, 0);
+        g_assert_cmpint(fds[i], >=, 0);
+    }
+
+    g_assert_cmpint(connect(fds[0], (struct sockaddr*)&addr, sizeof(addr)), ==, -1);
+    g_assert_cmpint(errno, ==, EINPROGRESS);
+
+    if (strcmp(mode, "drop") == 0) {
+        // the server drops our SYN and its retransmissions
+        struct pollfd poll_fd = {.fd = fds[0], .events = POLLOUT};
+        g_assert_cmpint(poll(&poll_fd, 1, 3000), ==, 0);
+        goto done;
+    }
+
+    wait_until_connected(fds[0]);
+
+    if (strcmp(mode, "cookie") == 0) {
+        assert_no_options(fds[0]);
+
+        char byte = 'a';
+        g_assert_cmpint(write(fds[0], &byte, 1), ==, 1);
+        struct pollfd poll_fd = {.fd = fds[0], .events = POLLIN};
+        g_assert_cmpint(poll(&poll_fd, 1, 1000), ==, 1);
+        g_assert_cmpint(read(fds[0], &byte, 1), ==, 1);
+        g_assert_cmpint(byte, ==, 'a');
+        goto done;
+    }
+
+    // let the first connection finish its handshake, so that the next two are
+    // both in the SYN queue at the same time and then overflow the accept queue
+    usleep(100 * 1000);
+
+    for (int i = 1; i < 3; i++) {
+        g_assert_cmpint(connect(fds[i], (struct sockaddr*)&addr, sizeof(addr)), ==, -1);
+        g_assert_cmpint(errno, ==, EINPROGRESS);
+    }
+
+    // the server completes both handshakes from our point of view
+    for (int i = 1; i < 3; i++) {
+        wait_until_connected(fds[i]);
+    }
+
+    // wait for any reset to arrive
+    sleep(1);
+
+    int num_reset = 0;
+    for (int i = 0; i < 3; i++) {
+        char byte = 0;
+        ssize_t rv = read(fds[i], &byte, 1);
+        // shadow reports a reset connection as EOF rather than ECONNRESET
+        if (rv == 0 || (rv == -1 && errno == ECONNRESET)) {
+            num_reset++;
+            g_assert_cmpint(close(fds[i]), ==, 0);
+            fds[i] = -1;
+        } else {
+            g_assert_cmpint(rv, ==, -1);
+            g_assert_cmpint(errno, ==, EAGAIN);
+        }
+    }
+    g_assert_cmpint(num_reset, ==, strcmp(mode, "abort") == 0 ? 1 : 0);
+
+    // the server accepts the connections in any order, so send on all of them
+    // before waiting for any of the responses
+    for (int i = 0; i < 3; i++) {
+        char byte = 'a' + i;
+        if (fds[i] >= 0) {
+            g_assert_cmpint(write(fds[i], &byte, 1), ==, 1);
+        }
+    }
+
+    for (int i = 0; i < 3; i++) {
+        if (fds[i] >= 0) {
+            struct pollfd poll_fd = {.fd = fds[i], .events = POLLIN};
+            g_assert_cmpint(poll(&poll_fd, 1, 5000), ==, 1);
+
+            char byte = 0;
+            g_assert_cmpint(read(fds[i], &byte, 1), ==, 1);
+            g_assert_cmpint(byte, ==, 'a' + i);
+        }
+    }
+
+done:
+    for (int i = 0; i < 3; i++) {
+        if (fds[i] >= 0) {
+            g_assert_cmpint(close(fds[i]), ==, 0);
+        }
+    }
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+
+    // usage: test-tcp-queues server <mode>
+    //        test-tcp-queues client <mode> <server>
+    if (argc == 3 && strcmp(argv[1], "server") == 0) {
+        mode = argv[2];
+        g_test_add_func("/tcp_queues/server", &test_server);
+    } else if (argc == 4 && strcmp(argv[1], "client") == 0) {
+        mode = argv[2];
+        g_test_add_data_func("/tcp_queues/client", argv[3], &test_client);
+    } else {
+        g_error("Invalid arguments");
+    }
+
+    return g_test_run();
+}
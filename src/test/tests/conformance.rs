@@ -0,0 +1,354 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Checks that the observable behavior of Shadow's network stack conforms to Linux's. The
+//! socket-level scenarios of `test_conformance` are run in Shadow and in a pair of Linux network
+//! namespaces that are connected by a veth pair with the same latency and bandwidth (using netem),
+//! which act as the oracle. Each scenario must have the same result in both, and its duration in
+//! Shadow must be within a tolerance of its duration in Linux.
+//!
+//! The Linux oracle needs permission to create network namespaces (usually root) and the `ip` and
+//! `tc` commands, and the path of the `shadow` binary must be set in the `SHADOW_BIN` environment
+//! variable. The test is skipped if either is unavailable.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const SCENARIO_BIN: &str = env!("CARGO_BIN_EXE_test_conformance");
+
+const CLIENT_IP: &str = "11.0.0.1";
+const SERVER_IP: &str = "11.0.0.2";
+/// The one-way latency between the hosts.
+const LATENCY_MS: u32 = 10;
+/// The bandwidth of each host in each direction.
+const BANDWIDTH_MBIT: u32 = 100;
+
+/// The observed behavior of a scenario.
+#[derive(Debug)]
+struct Observation {
+    result: String,
+    elapsed: Duration,
+}
+
+/// Parse the client's output into the observations of each scenario.
+fn parse_observations(output: &str) -> BTreeMap<String, Observation> {
+    output
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, result, elapsed] = fields[..] else {
+                panic!("Invalid scenario output '{line}'");
+            };
+            let observation = Observation {
+                result: result.to_string(),
+                elapsed: Duration::from_micros(elapsed.parse().unwrap()),
+            };
+            (name.to_string(), observation)
+        })
+        .collect()
+}
+
+/// How much the duration of a scenario in Shadow may differ from its duration `linux` in Linux.
+/// Bulk transfers depend on the details of the TCP implementations, so they're allowed to differ
+/// more.
+fn tolerance(scenario: &str, linux: Duration) -> Duration {
+    let relative = match scenario {
+        "tcp_echo_large" => 0.5,
+        _ => 0.25,
+    };
+    Duration::from_millis(5).max(linux.mul_f64(relative))
+}
+
+/// Compare the observations in Shadow with the observations in Linux, returning a description of
+/// each difference.
+fn compare(
+    linux: &BTreeMap<String, Observation>,
+    shadow: &BTreeMap<String, Observation>,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (name, expected) in linux {
+        let Some(actual) = shadow.get(name) else {
+            differences.push(format!("{name}: missing in Shadow"));
+            continue;
+        };
+        if actual.result != expected.result {
+            differences.push(format!(
+                "{name}: result '{}' in Shadow but '{}' in Linux",
+                actual.result, expected.result
+            ));
+        }
+        let diff = if actual.elapsed > expected.elapsed {
+            actual.elapsed - expected.elapsed
+        } else {
+            expected.elapsed - actual.elapsed
+        };
+        if diff > tolerance(name, expected.elapsed) {
+            differences.push(format!(
+                "{name}: took {:?} in Shadow but {:?} in Linux",
+                actual.elapsed, expected.elapsed
+            ));
+        }
+    }
+    differences
+}
+
+/// Run `program` with `args`, returning an error if it fails.
+fn run(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "'{program} {}' failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// A pair of network namespaces connected by a veth pair, which are deleted when dropped.
+struct Namespaces {
+    client: String,
+    server: String,
+}
+
+impl Namespaces {
+    fn new() -> anyhow::Result<Self> {
+        let pid = std::process::id();
+        let namespaces = Self {
+            client: format!("shadow-conformance-client-{pid}"),
+            server: format!("shadow-conformance-server-{pid}"),
+        };
+        let (client, server) = (namespaces.client.as_str(), namespaces.server.as_str());
+
+        run("ip", &["netns", "add", client])?;
+        run("ip", &["netns", "add", server])?;
+        run(
+            "ip",
+            &[
+                "link",
+                "add",
+                "veth-client",
+                "netns",
+                client,
+                "type",
+                "veth",
+                "peer",
+                "name",
+                "veth-server",
+                "netns",
+                server,
+            ],
+        )?;
+
+        let delay = format!("{LATENCY_MS}ms");
+        let rate = format!("{BANDWIDTH_MBIT}mbit");
+        for (ns, dev, ip) in [
+            (client, "veth-client", CLIENT_IP),
+            (server, "veth-server", SERVER_IP),
+        ] {
+            run(
+                "ip",
+                &["-n", ns, "addr", "add", &format!("{ip}/24"), "dev", dev],
+            )?;
+            run("ip", &["-n", ns, "link", "set", dev, "up"])?;
+            run("ip", &["-n", ns, "link", "set", "lo", "up"])?;
+            run(
+                "tc",
+                &[
+                    "-n", ns, "qdisc", "add", "dev", dev, "root", "netem", "delay", &delay, "rate",
+                    &rate,
+                ],
+            )?;
+        }
+
+        Ok(namespaces)
+    }
+}
+
+impl Drop for Namespaces {
+    fn drop(&mut self) {
+        // deleting a namespace also deletes its end of the veth pair
+        for ns in [&self.client, &self.server] {
+            let _ = run("ip", &["netns", "del", ns]);
+        }
+    }
+}
+
+/// Run the scenarios in the network namespaces, returning the client's output.
+fn run_linux(namespaces: &Namespaces) -> anyhow::Result<String> {
+    let mut server = Command::new("ip")
+        .args(["netns", "exec", &namespaces.server, SCENARIO_BIN, "server"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // wait until the server is listening
+    let mut line = String::new();
+    BufReader::new(server.stdout.take().unwrap()).read_line(&mut line)?;
+
+    let client = Command::new("ip")
+        .args([
+            "netns",
+            "exec",
+            &namespaces.client,
+            SCENARIO_BIN,
+            "client",
+            SERVER_IP,
+        ])
+        .output();
+
+    server.kill()?;
+    server.wait()?;
+
+    let client = client?;
+    if !client.status.success() {
+        anyhow::bail!("The client failed in Linux: {}", client.status);
+    }
+    Ok(String::from_utf8(client.stdout)?)
+}
+
+/// Run the scenarios in Shadow, returning the client's output.
+fn run_shadow(shadow: &Path, dir: &Path) -> anyhow::Result<String> {
+    let config = format!(
+        r#"general:
+  stop_time: 60 s
+network:
+  graph:
+    type: gml
+    inline: |
+      graph [
+        node [
+          id 0
+          host_bandwidth_down "{BANDWIDTH_MBIT} Mbit"
+          host_bandwidth_up "{BANDWIDTH_MBIT} Mbit"
+        ]
+        edge [
+          source 0
+          target 0
+          latency "{LATENCY_MS} ms"
+          packet_loss 0.0
+        ]
+      ]
+hosts:
+  server:
+    network_node_id: 0
+    ip_addr: {SERVER_IP}
+    processes:
+    - path: {SCENARIO_BIN}
+      args: server
+      expected_final_state: running
+  client:
+    network_node_id: 0
+    ip_addr: {CLIENT_IP}
+    processes:
+    - path: {SCENARIO_BIN}
+      args: client {SERVER_IP}
+      start_time: 1 s
+"#
+    );
+    let config_path = dir.join("conformance.yaml");
+    std::fs::write(&config_path, config)?;
+
+    let data_dir = dir.join("shadow.data");
+    let status = Command::new(shadow)
+        .arg("--data-directory")
+        .arg(&data_dir)
+        .arg(&config_path)
+        .stdout(Stdio::null())
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("Shadow failed: {status}");
+    }
+
+    // the client's stdout file is named after the binary and the process' pid
+    let host_dir = data_dir.join("hosts").join("client");
+    for entry in std::fs::read_dir(&host_dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name.starts_with("test_conformance.") && name.ends_with(".stdout") {
+            return Ok(std::fs::read_to_string(&path)?);
+        }
+    }
+    anyhow::bail!("No client output in {}", host_dir.display());
+}
+
+#[test]
+fn test_conformance() {
+    let Some(shadow) = std::env::var_os("SHADOW_BIN").map(PathBuf::from) else {
+        eprintln!("Skipping the conformance test since SHADOW_BIN is not set");
+        return;
+    };
+    let namespaces = match Namespaces::new() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Skipping the conformance test since the network namespaces failed: {e}");
+            return;
+        }
+    };
+
+    let dir = std::env::temp_dir().join(format!("shadow-conformance-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let linux = parse_observations(&run_linux(&namespaces).unwrap());
+    drop(namespaces);
+    let shadow = parse_observations(&run_shadow(&shadow, &dir).unwrap());
+
+    for (name, expected) in &linux {
+        let actual = shadow.get(name);
+        println!(
+            "{name}: Linux {} in {:?}, Shadow {:?} in {:?}",
+            expected.result,
+            expected.elapsed,
+            actual.map(|x| &x.result),
+            actual.map(|x| x.elapsed),
+        );
+    }
+
+    let differences = compare(&linux, &shadow);
+    assert!(
+        differences.is_empty(),
+        "Shadow's behavior differs from Linux's:\n{}",
+        differences.join("\n")
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_compare() {
+    let observation = |result: &str, ms| Observation {
+        result: result.to_string(),
+        elapsed: Duration::from_millis(ms),
+    };
+    let linux = BTreeMap::from([
+        ("a".to_string(), observation("ok", 100)),
+        ("b".to_string(), observation("ECONNREFUSED", 20)),
+        ("c".to_string(), observation("ok", 20)),
+    ]);
+    let shadow = BTreeMap::from([
+        ("a".to_string(), observation("ok", 120)),
+        ("b".to_string(), observation("ok", 20)),
+    ]);
+
+    assert_eq!(
+        compare(&linux, &shadow),
+        [
+            "b: result 'ok' in Shadow but 'ECONNREFUSED' in Linux",
+            "c: missing in Shadow",
+        ]
+    );
+
+    let shadow = parse_observations("a ok 200000\nb ECONNREFUSED 20000\nc ok 26000\n");
+    assert_eq!(
+        compare(&linux, &shadow),
+        [
+            "a: took 200ms in Shadow but 100ms in Linux",
+            "c: took 26ms in Shadow but 20ms in Linux",
+        ]
+    );
+}
@@ -0,0 +1,59 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+//! Measures how long it takes to send data to a server. The arguments are the host's role:
+//! "server", or "client <server> <min ms> <max ms>", where the client checks that sending the data
+//! takes between `min ms` and `max ms`.
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+const PORT: u16 = 80;
+const SIZE: usize = 1_250_000;
+
+fn server() {
+    let listener = TcpListener::bind(("0.0.0.0", PORT)).unwrap();
+    loop {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf.len(), SIZE);
+
+        // tell the client that all of the data arrived
+        stream.write_all(&[1]).unwrap();
+    }
+}
+
+fn client(server: &str, min: Duration, max: Duration) {
+    let mut stream = TcpStream::connect((server, PORT)).unwrap();
+
+    let start = Instant::now();
+    stream.write_all(&vec![0u8; SIZE]).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut buf = [0u8; 1];
+    stream.read_exact(&mut buf).unwrap();
+    let elapsed = start.elapsed();
+
+    println!("Sent {SIZE} bytes in {elapsed:?}");
+    assert!(
+        min <= elapsed && elapsed <= max,
+        "Sent {SIZE} bytes in {elapsed:?}, expected between {min:?} and {max:?}"
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match &args[1..] {
+        ["server"] => server(),
+        ["client", server, min_ms, max_ms] => client(
+            server,
+            Duration::from_millis(min_ms.parse().unwrap()),
+            Duration::from_millis(max_ms.parse().unwrap()),
+        ),
+        _ => panic!("Unexpected arguments: {:?}", &args[1..]),
+    }
+}
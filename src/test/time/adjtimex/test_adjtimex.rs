@@ -0,0 +1,133 @@
+use std::time::{Duration, SystemTime};
+
+use test_utils::{ensure_ord, set, TestEnvironment};
+
+fn main() -> anyhow::Result<()> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
+    let mut tests: Vec<test_utils::ShadowTest<(), anyhow::Error>> = vec![];
+
+    for modes in [0, libc::ADJ_OFFSET_SS_READ] {
+        tests.extend(vec![
+            test_utils::ShadowTest::new(
+                &format!("adjtimex_read <modes={modes:#x}>"),
+                move || test_adjtimex_read(modes),
+                set![TestEnvironment::Libc, TestEnvironment::Shadow],
+            ),
+            test_utils::ShadowTest::new(
+                &format!("clock_adjtime_read <modes={modes:#x}>"),
+                move || test_clock_adjtime_read(modes),
+                set![TestEnvironment::Libc, TestEnvironment::Shadow],
+            ),
+        ]);
+    }
+
+    tests.extend(vec![
+        test_utils::ShadowTest::new(
+            "clock_adjtime_clock_ids",
+            test_clock_adjtime_clock_ids,
+            set![TestEnvironment::Libc, TestEnvironment::Shadow],
+        ),
+        // Linux would adjust the clock if the test has CAP_SYS_TIME.
+        test_utils::ShadowTest::new(
+            "adjtimex_adjust",
+            test_adjtimex_adjust,
+            set![TestEnvironment::Shadow],
+        ),
+    ]);
+
+    tests
+}
+
+fn new_timex(modes: libc::c_uint) -> libc::timex {
+    let mut buf: libc::timex = unsafe { std::mem::zeroed() };
+    buf.modes = modes;
+    buf
+}
+
+fn clock_adjtime(clockid: libc::clockid_t, buf: &mut libc::timex) -> (libc::c_long, libc::c_int) {
+    unsafe {
+        (
+            libc::syscall(libc::SYS_clock_adjtime, clockid, buf as *mut libc::timex),
+            *libc::__errno_location(),
+        )
+    }
+}
+
+/// Check that the clock state and the time in `buf` look like a successful read.
+fn check_read(rv: libc::c_int, buf: &libc::timex) -> anyhow::Result<()> {
+    // Linux returns one of the clock states, which are `TIME_OK` up to `TIME_ERROR`.
+    ensure_ord!(rv, >=, libc::TIME_OK);
+    ensure_ord!(rv, <=, libc::TIME_ERROR);
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    let time = Duration::new(
+        buf.time.tv_sec.try_into().unwrap(),
+        (buf.time.tv_usec * 1000).try_into().unwrap(),
+    );
+    ensure_ord!(time, <=, now);
+    ensure_ord!(now - time, <, Duration::from_secs(1));
+
+    Ok(())
+}
+
+fn test_adjtimex_read(modes: libc::c_uint) -> anyhow::Result<()> {
+    let mut buf = new_timex(modes);
+    let rv = unsafe { libc::adjtimex(&mut buf) };
+    check_read(rv, &buf)
+}
+
+fn test_clock_adjtime_read(modes: libc::c_uint) -> anyhow::Result<()> {
+    let mut buf = new_timex(modes);
+    let (rv, _errno) = clock_adjtime(libc::CLOCK_REALTIME, &mut buf);
+    check_read(rv.try_into().unwrap(), &buf)
+}
+
+/// Only the realtime clock can be adjusted.
+fn test_clock_adjtime_clock_ids() -> anyhow::Result<()> {
+    for clockid in [libc::CLOCK_MONOTONIC, libc::CLOCK_BOOTTIME] {
+        let mut buf = new_timex(0);
+        let (rv, errno) = clock_adjtime(clockid, &mut buf);
+        ensure_ord!(rv, ==, -1);
+        ensure_ord!(errno, ==, libc::EOPNOTSUPP);
+    }
+
+    let mut buf = new_timex(0);
+    let (rv, errno) = clock_adjtime(128, &mut buf);
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(errno, ==, libc::EINVAL);
+
+    Ok(())
+}
+
+/// The simulated clock can't be adjusted.
+fn test_adjtimex_adjust() -> anyhow::Result<()> {
+    let mut buf = new_timex(libc::ADJ_FREQUENCY);
+    let rv = unsafe { libc::adjtimex(&mut buf) };
+    let errno = unsafe { *libc::__errno_location() };
+    ensure_ord!(rv, ==, -1);
+    ensure_ord!(errno, ==, libc::EPERM);
+
+    Ok(())
+}
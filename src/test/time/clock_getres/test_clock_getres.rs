@@ -36,6 +36,15 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
         FuzzArg::new(libc::CLOCK_BOOTTIME_ALARM, Ok(())),
         FuzzArg::new(libc::CLOCK_PROCESS_CPUTIME_ID, Ok(())),
         FuzzArg::new(libc::CLOCK_THREAD_CPUTIME_ID, Ok(())),
+        // CLOCK_SGI_CYCLE, which Linux doesn't implement
+        FuzzArg::new(
+            10,
+            Err(FuzzError::new(
+                FuzzOrder::First,
+                Some(-1),
+                Some(libc::EINVAL),
+            )),
+        ),
         FuzzArg::new(
             128,
             Err(FuzzError::new(
@@ -170,6 +170,12 @@ fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
                     get_passing_test_envs(clockid.value, true),
                 )]);
             }
+
+            tests.extend(vec![test_utils::ShadowTest::new(
+                &append_args("endless_sleep"),
+                move || test_endless_sleep(clockid.value, flag.value),
+                get_passing_test_envs(clockid.value, true),
+            )]);
         }
     }
 
@@ -321,6 +327,26 @@ fn test_interrupted_sleep(clockid: libc::clockid_t, flags: libc::c_int) -> anyho
     })
 }
 
+/// A sleep request too large to represent as a time (like from `sleep infinity`) should sleep until
+/// interrupted.
+fn test_endless_sleep(clockid: libc::clockid_t, flags: libc::c_int) -> anyhow::Result<()> {
+    let intr_dur = Duration::from_millis(300);
+
+    test_utils::interrupt_fn_exec(intr_dur, || {
+        let request = libc::timespec {
+            tv_sec: libc::time_t::MAX,
+            tv_nsec: 999_999_999,
+        };
+
+        test_utils::check_fn_exec_duration(intr_dur, SLEEP_TOLERANCE, || {
+            let rv =
+                unsafe { libc::clock_nanosleep(clockid, flags, &request, std::ptr::null_mut()) };
+            ensure_ord!(libc::EINTR, ==, rv);
+            Ok(())
+        })
+    })
+}
+
 fn create_sleep_request(
     clockid: libc::clockid_t,
     flags: libc::c_int,
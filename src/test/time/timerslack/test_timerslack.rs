@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+use test_utils::{ensure_ord, set, TestEnvironment};
+
+fn main() -> anyhow::Result<()> {
+    // should we restrict the tests we run?
+    let filter_shadow_passing = std::env::args().any(|x| x == "--shadow-passing");
+    let filter_libc_passing = std::env::args().any(|x| x == "--libc-passing");
+    // should we summarize the results rather than exit on a failed test
+    let summarize = std::env::args().any(|x| x == "--summarize");
+
+    let mut tests = get_tests();
+
+    if filter_shadow_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Shadow));
+    }
+    if filter_libc_passing {
+        tests.retain(|x| x.passing(TestEnvironment::Libc));
+    }
+
+    test_utils::run_tests(&tests, summarize)?;
+
+    Ok(())
+}
+
+fn get_tests() -> Vec<test_utils::ShadowTest<(), anyhow::Error>> {
+    vec![
+        // The default depends on how Linux started the test.
+        test_utils::ShadowTest::new(
+            "timerslack_default",
+            test_timerslack_default,
+            set![TestEnvironment::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "timerslack_set",
+            test_timerslack_set,
+            set![TestEnvironment::Libc, TestEnvironment::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "timerslack_thread",
+            test_timerslack_thread,
+            set![TestEnvironment::Libc, TestEnvironment::Shadow],
+        ),
+        // Linux may expire the timers anywhere within the slack.
+        test_utils::ShadowTest::new(
+            "timerslack_nanosleep",
+            test_timerslack_nanosleep,
+            set![TestEnvironment::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "timerslack_poll",
+            test_timerslack_poll,
+            set![TestEnvironment::Shadow],
+        ),
+        test_utils::ShadowTest::new(
+            "timerslack_poll_long_timeout",
+            test_timerslack_poll_long_timeout,
+            set![TestEnvironment::Shadow],
+        ),
+    ]
+}
+
+fn get_timerslack() -> libc::c_int {
+    let rv = unsafe { libc::prctl(libc::PR_GET_TIMERSLACK) };
+    assert!(rv >= 0);
+    rv
+}
+
+fn set_timerslack(slack_ns: libc::c_ulong) {
+    let rv = unsafe { libc::prctl(libc::PR_SET_TIMERSLACK, slack_ns) };
+    assert_eq!(rv, 0);
+}
+
+/// Runs `f` with the given timer slack, and restores the default slack afterwards.
+fn with_timerslack<T>(slack_ns: libc::c_ulong, f: impl FnOnce() -> T) -> T {
+    set_timerslack(slack_ns);
+    let rv = f();
+    set_timerslack(0);
+    rv
+}
+
+/// Returns how long `f` took to run.
+fn time(f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    Instant::now() - start
+}
+
+fn test_timerslack_default() -> anyhow::Result<()> {
+    ensure_ord!(get_timerslack(), ==, 50_000);
+    Ok(())
+}
+
+fn test_timerslack_set() -> anyhow::Result<()> {
+    let default = get_timerslack();
+
+    set_timerslack(100_000);
+    ensure_ord!(get_timerslack(), ==, 100_000);
+
+    // setting a slack of 0 restores the default
+    set_timerslack(0);
+    ensure_ord!(get_timerslack(), ==, default);
+
+    Ok(())
+}
+
+fn test_timerslack_thread() -> anyhow::Result<()> {
+    let default = get_timerslack();
+
+    let (inherited, child_default) = with_timerslack(200_000, || {
+        std::thread::spawn(|| {
+            let inherited = get_timerslack();
+            // the child's default is the slack it inherited
+            set_timerslack(0);
+            (inherited, get_timerslack())
+        })
+        .join()
+        .unwrap()
+    });
+
+    ensure_ord!(inherited, ==, 200_000);
+    ensure_ord!(child_default, ==, 200_000);
+
+    // the child doesn't affect the parent
+    ensure_ord!(get_timerslack(), ==, default);
+
+    Ok(())
+}
+
+fn test_timerslack_nanosleep() -> anyhow::Result<()> {
+    // a sleep is delayed by the timer slack
+    let elapsed = with_timerslack(10_000_000, || {
+        time(|| std::thread::sleep(Duration::from_millis(1)))
+    });
+    ensure_ord!(elapsed, >=, Duration::from_millis(11));
+    ensure_ord!(elapsed, <, Duration::from_millis(12));
+
+    Ok(())
+}
+
+fn poll_timeout(timeout_ms: libc::c_int) {
+    let rv = unsafe { libc::poll(std::ptr::null_mut(), 0, timeout_ms) };
+    assert_eq!(rv, 0);
+}
+
+fn test_timerslack_poll() -> anyhow::Result<()> {
+    // a short timeout is delayed by the timer slack
+    let elapsed = with_timerslack(10_000_000, || time(|| poll_timeout(1)));
+    ensure_ord!(elapsed, >=, Duration::from_millis(11));
+    ensure_ord!(elapsed, <, Duration::from_millis(12));
+
+    Ok(())
+}
+
+fn test_timerslack_poll_long_timeout() -> anyhow::Result<()> {
+    // a long timeout is delayed by 0.1% of the timeout, which is more than the
+    // timer slack
+    let elapsed = with_timerslack(1, || time(|| poll_timeout(2000)));
+    ensure_ord!(elapsed, >=, Duration::from_millis(2002));
+    ensure_ord!(elapsed, <, Duration::from_millis(2003));
+
+    Ok(())
+}
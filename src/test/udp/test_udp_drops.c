@@ -0,0 +1,172 @@
+/*
+ * The Shadow Simulator
+ * See LICENSE for licensing information
+ */
+
+// Tests that datagrams which arrive while a UDP socket's receive buffer is full
+// are dropped, and that the drops are reported by the socket's SO_RXQ_OVFL
+// control messages and the host's /proc/net/snmp counters.
+
+#include <glib.h>
+
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+#include "test/test_common.h"
+#include "test/test_glib_helpers.h"
+
+#define NUM_DATAGRAMS 100
+#define DATAGRAM_SIZE 100
+
+struct udp_stats {
+    unsigned long in_datagrams;
+    unsigned long in_errors;
+    unsigned long rcvbuf_errors;
+};
+
+// Read the UDP counters from /proc/net/snmp.
+static struct udp_stats get_udp_stats() {
+    FILE* f = fopen("/proc/net/snmp", "r");
+    assert_nonnull_errno(f);
+
+    struct udp_stats stats = {0};
+    bool found = false;
+    char header[1024] = {0};
+    char values[1024] = {0};
+
+    // each section is a line of counter names followed by a line of values
+    while (fgets(header, sizeof(header), f) != NULL && fgets(values, sizeof(values), f) != NULL) {
+        if (strncmp(header, "Udp: ", 5) != 0) {
+            continue;
+        }
+        unsigned long no_ports = 0;
+        unsigned long out_datagrams = 0;
+        int count = sscanf(values, "Udp: %lu %lu %lu %lu %lu", &stats.in_datagrams, &no_ports,
+                           &stats.in_errors, &out_datagrams, &stats.rcvbuf_errors);
+        g_assert_cmpint(count, ==, 5);
+        found = true;
+        break;
+    }
+
+    fclose(f);
+    g_assert_true(found);
+    return stats;
+}
+
+// Receive a datagram, and return the drop count from its SO_RXQ_OVFL control
+// message, or 0 if it has none.
+static uint32_t recv_with_drop_count(int fd) {
+    char buf[DATAGRAM_SIZE] = {0};
+    struct iovec iov = {.iov_base = buf, .iov_len = sizeof(buf)};
+    char control[CMSG_SPACE(sizeof(uint32_t))] = {0};
+    struct msghdr msg = {
+        .msg_iov = &iov,
+        .msg_iovlen = 1,
+        .msg_control = control,
+        .msg_controllen = sizeof(control),
+    };
+
+    g_assert_cmpint(recvmsg(fd, &msg, MSG_DONTWAIT), ==, DATAGRAM_SIZE);
+    g_assert_cmpint(msg.msg_flags & MSG_CTRUNC, ==, 0);
+
+    struct cmsghdr* cmsg = CMSG_FIRSTHDR(&msg);
+    if (cmsg == NULL) {
+        return 0;
+    }
+
+    g_assert_cmpint(cmsg->cmsg_level, ==, SOL_SOCKET);
+    g_assert_cmpint(cmsg->cmsg_type, ==, SO_RXQ_OVFL);
+    g_assert_cmpint(cmsg->cmsg_len, ==, CMSG_LEN(sizeof(uint32_t)));
+
+    uint32_t drops = 0;
+    memcpy(&drops, CMSG_DATA(cmsg), sizeof(drops));
+    return drops;
+}
+
+static void test_rcvbuf_drops() {
+    int server_fd = -1;
+    assert_nonneg_errno(server_fd = socket(AF_INET, SOCK_DGRAM, 0));
+
+    int rcvbuf = 4096;
+    assert_nonneg_errno(setsockopt(server_fd, SOL_SOCKET, SO_RCVBUF, &rcvbuf, sizeof(rcvbuf)));
+
+    int enable = -1;
+    socklen_t optlen = sizeof(enable);
+    assert_nonneg_errno(getsockopt(server_fd, SOL_SOCKET, SO_RXQ_OVFL, &enable, &optlen));
+    g_assert_cmpint(enable, ==, 0);
+
+    enable = 1;
+    assert_nonneg_errno(setsockopt(server_fd, SOL_SOCKET, SO_RXQ_OVFL, &enable, sizeof(enable)));
+    assert_nonneg_errno(getsockopt(server_fd, SOL_SOCKET, SO_RXQ_OVFL, &enable, &optlen));
+    g_assert_cmpint(enable, ==, 1);
+
+    struct sockaddr_in addr = {
+        .sin_family = AF_INET,
+        .sin_addr.s_addr = htonl(INADDR_LOOPBACK),
+        .sin_port = 0,
+    };
+    socklen_t addr_len = sizeof(addr);
+    assert_nonneg_errno(bind(server_fd, (struct sockaddr*)&addr, sizeof(addr)));
+    assert_nonneg_errno(getsockname(server_fd, (struct sockaddr*)&addr, &addr_len));
+
+    int client_fd = -1;
+    assert_nonneg_errno(client_fd = socket(AF_INET, SOCK_DGRAM, 0));
+    assert_nonneg_errno(connect(client_fd, (struct sockaddr*)&addr, sizeof(addr)));
+
+    struct udp_stats before = get_udp_stats();
+
+    // send many more datagrams than fit in the server's receive buffer
+    char buf[DATAGRAM_SIZE] = {0};
+    for (int i = 0; i < NUM_DATAGRAMS; i++) {
+        g_assert_cmpint(send(client_fd, buf, sizeof(buf), 0), ==, sizeof(buf));
+    }
+
+    // wait for the datagrams to arrive
+    usleep(100 * 1000);
+
+    // the datagrams that fit in the buffer were received before any were dropped
+    int num_received = 0;
+    while (true) {
+        char byte = 0;
+        ssize_t rv = recv(server_fd, &byte, 1, MSG_DONTWAIT | MSG_PEEK);
+        if (rv == -1) {
+            g_assert_cmpint(errno, ==, EAGAIN);
+            break;
+        }
+        g_assert_cmpint(recv_with_drop_count(server_fd), ==, 0);
+        num_received++;
+    }
+    g_assert_cmpint(num_received, >, 0);
+    g_assert_cmpint(num_received, <, NUM_DATAGRAMS);
+
+    // the next datagram reports the drops since the socket was created
+    g_assert_cmpint(send(client_fd, buf, sizeof(buf), 0), ==, sizeof(buf));
+    usleep(100 * 1000);
+    int num_dropped = NUM_DATAGRAMS - num_received;
+    g_assert_cmpint(recv_with_drop_count(server_fd), ==, num_dropped);
+
+    struct udp_stats after = get_udp_stats();
+
+    // other sockets on the host may also be counted when running outside of shadow
+    if (running_in_shadow()) {
+        g_assert_cmpint(after.in_datagrams - before.in_datagrams, ==, num_received + 1);
+        g_assert_cmpint(after.in_errors - before.in_errors, ==, num_dropped);
+        g_assert_cmpint(after.rcvbuf_errors - before.rcvbuf_errors, ==, num_dropped);
+    } else {
+        g_assert_cmpint(after.rcvbuf_errors - before.rcvbuf_errors, >=, num_dropped);
+    }
+
+    assert_nonneg_errno(close(client_fd));
+    assert_nonneg_errno(close(server_fd));
+}
+
+int main(int argc, char* argv[]) {
+    g_test_init(&argc, &argv, NULL);
+    g_test_add_func("/udp_drops/rcvbuf_drops", &test_rcvbuf_drops);
+    return g_test_run();
+}
@@ -50,6 +50,7 @@ fn main() {
     test_getppid();
     test_gethostname(&expected_name.nodename);
     test_uname(&expected_name);
+    test_proc_sys_kernel();
     test_getpid_kill();
     test_getpgrp();
     test_getsid();
@@ -132,6 +133,28 @@ fn test_uname(expected_name: &ExpectedName) {
     assert_eq!(expected_name.version, to_cstr(&n.version).into());
 }
 
+/// Validates that the system identification files in `/proc/sys/kernel` agree with `uname`.
+fn test_proc_sys_kernel() {
+    let mut n = unsafe { std::mem::zeroed() };
+    let r = unsafe { libc::uname(&mut n) };
+    assert_eq!(r, 0);
+
+    for (path, field) in [
+        ("/proc/sys/kernel/ostype", &n.sysname),
+        ("/proc/sys/kernel/hostname", &n.nodename),
+        ("/proc/sys/kernel/osrelease", &n.release),
+        ("/proc/sys/kernel/version", &n.version),
+        ("/proc/sys/kernel/domainname", &n.domainname),
+    ] {
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert_eq!(
+            contents.strip_suffix('\n').unwrap().as_bytes(),
+            to_cstr(field).to_bytes(),
+            "Unexpected contents of {path}"
+        );
+    }
+}
+
 /// Validates that the returned pid is ours by using it to send a signal to ourselves.
 fn test_getpid_kill() {
     let pid = process::id();
@@ -0,0 +1,131 @@
+#!/usr/bin/env python3
+
+'''
+Compare the traffic between hosts in a Shadow simulation with the traffic in
+the ns-3 scenario exported from it (see the `network.topology_export.ns3`
+option). Reads `topology.gexf` and `ns3-hosts.csv` from Shadow's data
+directory and the `ns3-flows.xml` flow monitor file written by the ns-3
+scenario, and writes one CSV row for each pair of hosts that exchanged traffic
+in either simulator.
+
+Shadow's byte counts are payload bytes, while ns-3's flow monitor counts whole
+IP packets including their headers.
+'''
+
+import argparse
+import csv
+import os
+import re
+import sys
+import xml.etree.ElementTree as ET
+
+GEXF_NS = {'gexf': 'http://www.gexf.net/1.2draft'}
+
+# ns-3 times are written like "+1.5e+07ns"
+TIME_RE = re.compile(r'^([+-]?[0-9.eE+-]+?)(ns|us|ms|s|min|h|d|y|fs|ps)?$')
+TIME_UNITS = {
+    'fs': 1e-15, 'ps': 1e-12, 'ns': 1e-9, 'us': 1e-6, 'ms': 1e-3, 's': 1.0,
+    'min': 60.0, 'h': 3600.0, 'd': 86400.0, 'y': 31536000.0,
+}
+
+FIELDS = [
+    'source', 'destination',
+    'shadow_packets', 'shadow_bytes',
+    'ns3_tx_packets', 'ns3_rx_packets', 'ns3_rx_bytes', 'ns3_lost_packets',
+    'ns3_mean_delay_ms',
+]
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__,
+                                     formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument('shadow_data', help="Shadow's data directory")
+    parser.add_argument('ns3_flows', help="the 'ns3-flows.xml' file written by the ns-3 scenario")
+    parser.add_argument('-o', '--output', help='output CSV file (default: stdout)')
+    args = parser.parse_args()
+
+    hosts = read_hosts(os.path.join(args.shadow_data, 'ns3-hosts.csv'))
+    shadow = read_shadow_flows(os.path.join(args.shadow_data, 'topology.gexf'))
+    ns3 = read_ns3_flows(args.ns3_flows, hosts)
+
+    out = open(args.output, 'w', newline='') if args.output else sys.stdout
+    writer = csv.DictWriter(out, fieldnames=FIELDS)
+    writer.writeheader()
+    for key in sorted(set(shadow) | set(ns3)):
+        row = {'source': key[0], 'destination': key[1]}
+        row.update(shadow.get(key, {'shadow_packets': 0, 'shadow_bytes': 0}))
+        stats = ns3.get(key)
+        if stats is None:
+            row.update({'ns3_tx_packets': 0, 'ns3_rx_packets': 0, 'ns3_rx_bytes': 0,
+                        'ns3_lost_packets': 0, 'ns3_mean_delay_ms': ''})
+        else:
+            row.update({k: v for k, v in stats.items() if k != 'ns3_delay_sum'})
+            if stats['ns3_rx_packets'] > 0:
+                delay = stats['ns3_delay_sum'] / stats['ns3_rx_packets'] * 1e3
+                row['ns3_mean_delay_ms'] = '{:.3f}'.format(delay)
+            else:
+                row['ns3_mean_delay_ms'] = ''
+        writer.writerow(row)
+    if args.output:
+        out.close()
+
+
+def read_hosts(path):
+    '''Map each host's ns-3 address to its name.'''
+    with open(path, newline='') as f:
+        return {row['ns3_ip']: row['host'] for row in csv.DictReader(f)}
+
+
+def read_shadow_flows(path):
+    '''The packets and payload bytes sent between each pair of hosts in Shadow.'''
+    flows = {}
+    graph = ET.parse(path).getroot().find('gexf:graph', GEXF_NS)
+    for edge in graph.iterfind('gexf:edges/gexf:edge', GEXF_NS):
+        values = {v.get('for'): v.get('value')
+                  for v in edge.iterfind('gexf:attvalues/gexf:attvalue', GEXF_NS)}
+        if values.get('kind') != 'flow':
+            continue
+        # hosts' GEXF node ids are their names prefixed with "h:"
+        key = (edge.get('source')[2:], edge.get('target')[2:])
+        flows[key] = {'shadow_packets': int(values['packets']),
+                      'shadow_bytes': int(values['bytes'])}
+    return flows
+
+
+def read_ns3_flows(path, hosts):
+    '''The statistics of the traffic sent between each pair of hosts in ns-3, summed over all of
+    the flows between them.'''
+    root = ET.parse(path).getroot()
+    addresses = {}
+    for flow in root.iterfind('Ipv4FlowClassifier/Flow'):
+        src = hosts.get(flow.get('sourceAddress'))
+        dst = hosts.get(flow.get('destinationAddress'))
+        if src is not None and dst is not None:
+            addresses[flow.get('flowId')] = (src, dst)
+
+    flows = {}
+    for flow in root.iterfind('FlowStats/Flow'):
+        key = addresses.get(flow.get('flowId'))
+        if key is None:
+            continue
+        stats = flows.setdefault(key, {'ns3_tx_packets': 0, 'ns3_rx_packets': 0,
+                                       'ns3_rx_bytes': 0, 'ns3_lost_packets': 0,
+                                       'ns3_delay_sum': 0.0})
+        stats['ns3_tx_packets'] += int(flow.get('txPackets'))
+        stats['ns3_rx_packets'] += int(flow.get('rxPackets'))
+        stats['ns3_rx_bytes'] += int(flow.get('rxBytes'))
+        stats['ns3_lost_packets'] += int(flow.get('lostPackets'))
+        stats['ns3_delay_sum'] += parse_time(flow.get('delaySum'))
+    return flows
+
+
+def parse_time(value):
+    '''Parse an ns-3 time, returning seconds.'''
+    match = TIME_RE.match(value.strip())
+    if match is None:
+        raise ValueError("Invalid ns-3 time '{}'".format(value))
+    return float(match.group(1)) * TIME_UNITS[match.group(2) or 's']
+
+
+if __name__ == '__main__':
+    main()